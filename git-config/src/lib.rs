@@ -0,0 +1,177 @@
+//! Read git configuration files like `$GIT_DIR/config` or `~/.gitconfig`, with case-insensitive section and key
+//! names, case-sensitive subsections and typed accessors for booleans and integers.
+#![forbid(unsafe_code)]
+
+use bstr::{BStr, BString, ByteSlice};
+use quick_error::quick_error;
+use std::path::{Path, PathBuf};
+
+pub mod parse;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: std::io::Error, path: PathBuf) {
+            display("Could not read configuration file at '{}'", path.display())
+            source(err)
+        }
+        Parse(err: parse::Error, path: PathBuf) {
+            display("Could not parse configuration file at '{}'", path.display())
+            source(err)
+        }
+    }
+}
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum ValueError {
+        Boolean(value: BString) {
+            display("'{}' is not a boolean, expected one of true, yes, on, 1, false, no, off, 0 or nothing", value)
+        }
+        Integer(value: BString) {
+            display("'{}' is not an integer with an optional k, m or g suffix", value)
+        }
+    }
+}
+
+/// A single `key = value` pair. Keys without value are booleans set to true.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    /// The name of the key, lower-cased.
+    pub key: BString,
+    /// The value with quotes and escapes resolved, or `None` if there was no `=`.
+    pub value: Option<BString>,
+}
+
+impl Entry {
+    /// Interpret the value as boolean, where a missing value is true.
+    pub fn boolean(&self) -> Result<bool, ValueError> {
+        match &self.value {
+            None => Ok(true),
+            Some(value) => to_bool(value.as_ref()),
+        }
+    }
+}
+
+/// A section like `[remote "origin"]` along with all of its entries.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Section {
+    /// The name of the section, lower-cased.
+    pub name: BString,
+    /// The subsection, like `origin` in `[remote "origin"]`.
+    pub subsection: Option<BString>,
+    /// All entries in the order of their appearance.
+    pub entries: Vec<Entry>,
+}
+
+/// A parsed configuration file. Sections may appear multiple times and later values override earlier ones.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct File {
+    sections: Vec<Section>,
+}
+
+impl File {
+    /// Parse the configuration in `data`.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, parse::Error> {
+        Ok(File {
+            sections: parse::sections(data)?,
+        })
+    }
+
+    /// Read and parse the configuration file at `path`.
+    pub fn at(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|err| Error::Io(err, path.to_owned()))?;
+        Self::from_bytes(&data).map_err(|err| Error::Parse(err, path.to_owned()))
+    }
+
+    /// Return all sections in the order of their appearance.
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    /// Return all entries of `section` and `subsection` with `key`, in the order of their appearance.
+    pub fn entries(&self, section: &str, subsection: Option<&str>, key: &str) -> Vec<&Entry> {
+        self.sections
+            .iter()
+            .filter(|s| {
+                s.name.eq_ignore_ascii_case(section.as_bytes())
+                    && s.subsection.as_ref().map(|s| s.as_bytes()) == subsection.map(|s| s.as_bytes())
+            })
+            .flat_map(|s| s.entries.iter())
+            .filter(|e| e.key.eq_ignore_ascii_case(key.as_bytes()))
+            .collect()
+    }
+
+    /// Return the last value of `key` in `section` and `subsection`, if present and set to a value.
+    pub fn value(&self, section: &str, subsection: Option<&str>, key: &str) -> Option<&BStr> {
+        self.entries(section, subsection, key)
+            .last()
+            .and_then(|e| e.value.as_ref().map(|v| v.as_bstr()))
+    }
+
+    /// Return all values of the multi-valued `key` in `section` and `subsection`, skipping keys without value.
+    pub fn values(&self, section: &str, subsection: Option<&str>, key: &str) -> Vec<&BStr> {
+        self.entries(section, subsection, key)
+            .into_iter()
+            .filter_map(|e| e.value.as_ref().map(|v| v.as_bstr()))
+            .collect()
+    }
+
+    /// Interpret the last value of `key` as boolean, where keys without value are true.
+    pub fn boolean(&self, section: &str, subsection: Option<&str>, key: &str) -> Option<Result<bool, ValueError>> {
+        self.entries(section, subsection, key).last().map(|e| e.boolean())
+    }
+
+    /// Interpret the last value of `key` as integer, with `k`, `m` and `g` suffixes scaling it by powers of 1024.
+    pub fn integer(&self, section: &str, subsection: Option<&str>, key: &str) -> Option<Result<i64, ValueError>> {
+        self.value(section, subsection, key).map(to_integer)
+    }
+
+    /// Return all distinct subsections of `section` in the order of their first appearance, like the names of all
+    /// remotes for `remote`.
+    pub fn subsections<'a>(&'a self, section: &str) -> Vec<&'a BStr> {
+        let mut names = Vec::new();
+        for s in &self.sections {
+            if let Some(subsection) = s
+                .subsection
+                .as_ref()
+                .filter(|_| s.name.eq_ignore_ascii_case(section.as_bytes()))
+            {
+                let subsection = subsection.as_bstr();
+                if !names.contains(&subsection) {
+                    names.push(subsection);
+                }
+            }
+        }
+        names
+    }
+}
+
+fn to_bool(value: &BStr) -> Result<bool, ValueError> {
+    let lowercase = value.to_ascii_lowercase();
+    match lowercase.as_slice() {
+        b"true" | b"yes" | b"on" | b"1" => Ok(true),
+        b"false" | b"no" | b"off" | b"0" | b"" => Ok(false),
+        _ => Err(ValueError::Boolean(value.to_owned())),
+    }
+}
+
+fn to_integer(value: &BStr) -> Result<i64, ValueError> {
+    let err = || ValueError::Integer(value.to_owned());
+    let (number, scale) = match value.last() {
+        Some(b'k') | Some(b'K') => (&value[..value.len() - 1], 1024),
+        Some(b'm') | Some(b'M') => (&value[..value.len() - 1], 1024 * 1024),
+        Some(b'g') | Some(b'G') => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+    number
+        .to_str()
+        .ok()
+        .and_then(|n| n.parse::<i64>().ok())
+        .and_then(|n| n.checked_mul(scale))
+        .ok_or_else(err)
+}
@@ -0,0 +1,218 @@
+//! Parse the git configuration file format into sections and entries.
+use crate::{Entry, Section};
+use bstr::BString;
+use quick_error::quick_error;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        InvalidSectionHeader(line_number: usize) {
+            display("Line {}: invalid section header", line_number)
+        }
+        MissingSection(line_number: usize) {
+            display("Line {}: the key must be placed within a section", line_number)
+        }
+        InvalidKey(line_number: usize) {
+            display("Line {}: keys must start with a letter and contain only letters, digits and '-'", line_number)
+        }
+        UnterminatedQuote(line_number: usize) {
+            display("Line {}: the value ends within a quote", line_number)
+        }
+        InvalidEscape(line_number: usize) {
+            display("Line {}: unknown escape sequence in value", line_number)
+        }
+    }
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+    line_number: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let b = self.peek()?;
+        self.pos += 1;
+        if b == b'\n' {
+            self.line_number += 1;
+        }
+        Some(b)
+    }
+
+    fn skip_blanks(&mut self) {
+        while let Some(b' ') | Some(b'\t') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(b) = self.next() {
+            if b == b'\n' {
+                break;
+            }
+        }
+    }
+}
+
+/// Parse all sections in `data`.
+pub fn sections(data: &[u8]) -> Result<Vec<Section>, Error> {
+    let mut cursor = Cursor {
+        data,
+        pos: 0,
+        line_number: 1,
+    };
+    let mut sections: Vec<Section> = Vec::new();
+    while let Some(b) = cursor.peek() {
+        match b {
+            b' ' | b'\t' | b'\r' | b'\n' => {
+                cursor.next();
+            }
+            b'#' | b';' => cursor.skip_line(),
+            b'[' => {
+                cursor.next();
+                sections.push(section_header(&mut cursor)?);
+            }
+            b if b.is_ascii_alphabetic() => {
+                let entry = entry(&mut cursor)?;
+                sections
+                    .last_mut()
+                    .ok_or(Error::MissingSection(cursor.line_number))?
+                    .entries
+                    .push(entry);
+            }
+            _ => return Err(Error::InvalidKey(cursor.line_number)),
+        }
+    }
+    Ok(sections)
+}
+
+fn section_header(cursor: &mut Cursor<'_>) -> Result<Section, Error> {
+    let err = Error::InvalidSectionHeader(cursor.line_number);
+    let start = cursor.pos;
+    while let Some(b) = cursor.peek() {
+        if b.is_ascii_alphanumeric() || b == b'-' || b == b'.' {
+            cursor.pos += 1;
+        } else {
+            break;
+        }
+    }
+    let name = &cursor.data[start..cursor.pos];
+    if name.is_empty() {
+        return Err(err);
+    }
+    match cursor.next() {
+        Some(b']') => {
+            let mut parts = name.splitn(2, |b| *b == b'.');
+            let name = parts.next().expect("at least one part");
+            Ok(Section {
+                name: name.to_ascii_lowercase().into(),
+                subsection: parts.next().map(|s| s.to_ascii_lowercase().into()),
+                entries: Vec::new(),
+            })
+        }
+        Some(b' ') | Some(b'\t') if !name.contains(&b'.') => {
+            cursor.skip_blanks();
+            if cursor.next() != Some(b'"') {
+                return Err(err);
+            }
+            let mut subsection = Vec::new();
+            loop {
+                match cursor.next() {
+                    Some(b'"') => break,
+                    Some(b'\\') => match cursor.next() {
+                        Some(b'\n') | None => return Err(err),
+                        Some(b) => subsection.push(b),
+                    },
+                    Some(b'\n') | None => return Err(err),
+                    Some(b) => subsection.push(b),
+                }
+            }
+            if cursor.next() != Some(b']') {
+                return Err(err);
+            }
+            Ok(Section {
+                name: name.to_ascii_lowercase().into(),
+                subsection: Some(subsection.into()),
+                entries: Vec::new(),
+            })
+        }
+        _ => Err(err),
+    }
+}
+
+fn entry(cursor: &mut Cursor<'_>) -> Result<Entry, Error> {
+    let start = cursor.pos;
+    while let Some(b) = cursor.peek() {
+        if b.is_ascii_alphanumeric() || b == b'-' {
+            cursor.pos += 1;
+        } else {
+            break;
+        }
+    }
+    let key: BString = cursor.data[start..cursor.pos].to_ascii_lowercase().into();
+    cursor.skip_blanks();
+    let value = match cursor.peek() {
+        Some(b'=') => {
+            cursor.next();
+            Some(value(cursor)?)
+        }
+        None | Some(b'\n') | Some(b'\r') | Some(b'#') | Some(b';') => {
+            cursor.skip_line();
+            None
+        }
+        Some(_) => return Err(Error::InvalidKey(cursor.line_number)),
+    };
+    Ok(Entry { key, value })
+}
+
+fn value(cursor: &mut Cursor<'_>) -> Result<BString, Error> {
+    cursor.skip_blanks();
+    let mut value = Vec::new();
+    let mut pending_whitespace = Vec::new();
+    let mut in_quote = false;
+    loop {
+        let b = match cursor.next() {
+            Some(b) => b,
+            None if in_quote => return Err(Error::UnterminatedQuote(cursor.line_number)),
+            None => break,
+        };
+        let b = match b {
+            b'\n' if in_quote => return Err(Error::UnterminatedQuote(cursor.line_number - 1)),
+            b'\n' => break,
+            b'#' | b';' if !in_quote => {
+                cursor.skip_line();
+                break;
+            }
+            b' ' | b'\t' | b'\r' if !in_quote => {
+                pending_whitespace.push(b);
+                continue;
+            }
+            b'"' => {
+                in_quote = !in_quote;
+                continue;
+            }
+            b'\\' => match cursor.next() {
+                Some(b'\n') => continue,
+                Some(b'\r') if cursor.peek() == Some(b'\n') => {
+                    cursor.next();
+                    continue;
+                }
+                Some(b'n') => b'\n',
+                Some(b't') => b'\t',
+                Some(b'b') => b'\x08',
+                Some(b'"') => b'"',
+                Some(b'\\') => b'\\',
+                _ => return Err(Error::InvalidEscape(cursor.line_number)),
+            },
+            b => b,
+        };
+        value.append(&mut pending_whitespace);
+        value.push(b);
+    }
+    Ok(value.into())
+}
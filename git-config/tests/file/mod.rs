@@ -0,0 +1,111 @@
+use bstr::ByteSlice;
+use git_config::{parse, File};
+
+const CONFIG: &[u8] = br#"# a comment
+[core]
+	repositoryformatversion = 0
+	Bare = false ; trailing comment
+	logAllRefUpdates
+[remote "origin"]
+	url = https://example.com/repo.git
+	fetch = +refs/heads/*:refs/remotes/origin/*
+	fetch = +refs/tags/*:refs/tags/*
+[Remote "Upper"]
+	url = "quoted # not a comment"
+[branch.Main]
+	remote = origin
+[core]
+	bare = true
+[values]
+	spaces =   inner   spaced   
+	escapes = tab\there\nnewline \"quote\" back\\slash
+	continued = first \
+second
+	quoted = "  leading"
+	empty =
+	size = 2k
+	negative = -5
+"#;
+
+fn config() -> File {
+    File::from_bytes(CONFIG).unwrap()
+}
+
+#[test]
+fn sections_and_keys_are_case_insensitive_but_subsections_are_not() {
+    let config = config();
+    assert_eq!(
+        config.value("Core", None, "REPOSITORYFORMATVERSION"),
+        Some("0".as_bytes().as_bstr())
+    );
+    assert_eq!(
+        config.value("remote", Some("Upper"), "url"),
+        Some("quoted # not a comment".as_bytes().as_bstr())
+    );
+    assert_eq!(config.value("remote", Some("upper"), "url"), None);
+    assert_eq!(
+        config.value("branch", Some("main"), "remote"),
+        Some("origin".as_bytes().as_bstr()),
+        "legacy subsections are lower-cased"
+    );
+}
+
+#[test]
+fn later_values_override_earlier_ones_and_multi_values_are_kept() {
+    let config = config();
+    assert!(config.boolean("core", None, "bare").unwrap().unwrap());
+    assert_eq!(
+        config.values("remote", Some("origin"), "fetch"),
+        vec![
+            "+refs/heads/*:refs/remotes/origin/*".as_bytes().as_bstr(),
+            "+refs/tags/*:refs/tags/*".as_bytes().as_bstr()
+        ]
+    );
+    assert_eq!(config.subsections("remote"), vec!["origin", "Upper"]);
+}
+
+#[test]
+fn keys_without_value_are_true() {
+    let config = config();
+    assert_eq!(config.value("core", None, "logallrefupdates"), None);
+    assert!(config.boolean("core", None, "logallrefupdates").unwrap().unwrap());
+    assert!(!config.boolean("values", None, "empty").unwrap().unwrap());
+    assert!(config.boolean("values", None, "spaces").unwrap().is_err());
+    assert!(config.boolean("core", None, "missing").is_none());
+}
+
+#[test]
+fn values_resolve_quotes_escapes_and_continuations() {
+    let config = config();
+    let value = |key| config.value("values", None, key).unwrap().to_str().unwrap().to_owned();
+    assert_eq!(value("spaces"), "inner   spaced");
+    assert_eq!(value("escapes"), "tab\there\nnewline \"quote\" back\\slash");
+    assert_eq!(value("continued"), "first second");
+    assert_eq!(value("quoted"), "  leading");
+    assert_eq!(value("empty"), "");
+}
+
+#[test]
+fn integers_may_have_a_suffix() {
+    let config = config();
+    assert_eq!(config.integer("values", None, "size").unwrap().unwrap(), 2048);
+    assert_eq!(config.integer("values", None, "negative").unwrap().unwrap(), -5);
+    assert!(config.integer("values", None, "spaces").unwrap().is_err());
+}
+
+#[test]
+fn invalid_files() {
+    let err = |data: &str| File::from_bytes(data.as_bytes()).unwrap_err();
+    assert!(matches!(err("key = value"), parse::Error::MissingSection(1)));
+    assert!(matches!(err("[core\n"), parse::Error::InvalidSectionHeader(1)));
+    assert!(matches!(
+        err("[remote \"origin]\n"),
+        parse::Error::InvalidSectionHeader(1)
+    ));
+    assert!(matches!(err("[core]\n_key = 1"), parse::Error::InvalidKey(2)));
+    assert!(matches!(
+        err("[core]\nkey = \"open\n"),
+        parse::Error::UnterminatedQuote(2)
+    ));
+    assert!(matches!(err("[core]\nkey = \\x"), parse::Error::InvalidEscape(2)));
+}
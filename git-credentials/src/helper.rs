@@ -0,0 +1,263 @@
+//! Talk to credential helper programs using the protocol of `git credential`.
+//!
+//! Helpers receive `key=value` lines describing the remote on standard input, and answer `get` requests with
+//! the same kind of lines, completing what they know. `store` and `erase` are sent to tell them if the credentials
+//! worked.
+use bstr::{BString, ByteSlice};
+use quick_error::quick_error;
+use std::{
+    io::{self, Read, Write},
+    process::{Command, Stdio},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred while talking to the credential helper")
+            from()
+            source(err)
+        }
+        SpawnCommand(err: io::Error, command: String) {
+            display("Could not spawn the credential helper '{}'", command)
+            source(err)
+        }
+        ProgramFailed(command: String, status: std::process::ExitStatus) {
+            display("The credential helper '{}' failed with {}", command, status)
+        }
+        InvalidLine(line: BString) {
+            display("The line '{}' is not a 'key=value' pair", line)
+        }
+        InvalidValue(key: String) {
+            display("The value of '{}' is not valid UTF-8 or contains a null byte", key)
+        }
+        UnsafeValue(key: String) {
+            display("The value of '{}' contains a newline or null byte and can't be passed to credential helpers", key)
+        }
+    }
+}
+
+/// What to ask a credential helper for.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Action {
+    /// Complete the given context with a user name and password, if known.
+    Get,
+    /// Remember the credentials of the context, as they were accepted by the remote.
+    Store,
+    /// Forget the credentials of the context, as they were rejected by the remote.
+    Erase,
+}
+
+impl Action {
+    /// Return the name of the action as passed to helpers.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Get => "get",
+            Action::Store => "store",
+            Action::Erase => "erase",
+        }
+    }
+}
+
+/// The remote to obtain credentials for, and the credentials known so far.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Context {
+    /// The scheme of the remote URL, like `https`.
+    pub protocol: Option<String>,
+    /// The host of the remote, including the port if it isn't the default one, like `example.com:8080`.
+    pub host: Option<String>,
+    /// The path of the repository on the host, only set if credentials differ per repository.
+    pub path: Option<BString>,
+    /// The user name.
+    pub username: Option<String>,
+    /// The password.
+    pub password: Option<String>,
+    /// If true, the helper asked to not consult any further helpers.
+    pub quit: bool,
+}
+
+impl Context {
+    /// Create a context for `url`, including its path if `use_path` is true.
+    ///
+    /// The user and password contained in the URL are used as well.
+    pub fn from_url(url: &git_url::Url, use_path: bool) -> Self {
+        Context {
+            protocol: Some(url.scheme.as_str().into()),
+            host: url.host.as_ref().map(|host| match url.port {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.clone(),
+            }),
+            path: if use_path {
+                Some(url.path.trim_start_with(|c| c == '/').into())
+            } else {
+                None
+            },
+            username: url.user.clone(),
+            password: url.password.clone(),
+            quit: false,
+        }
+    }
+
+    /// Returns true if both the user name and the password are known.
+    pub fn is_complete(&self) -> bool {
+        self.username.is_some() && self.password.is_some()
+    }
+
+    /// Write all known fields as `key=value` lines to `out`, followed by an empty line.
+    ///
+    /// Like git, nothing is written if a value contains a newline or null byte, as helpers would take what follows for
+    /// another field, and [`Error::UnsafeValue`] is returned instead.
+    pub fn write_to(&self, mut out: impl io::Write) -> Result<(), Error> {
+        let fields = [
+            ("protocol", self.protocol.as_ref().map(|v| v.as_bytes())),
+            ("host", self.host.as_ref().map(|v| v.as_bytes())),
+            ("username", self.username.as_ref().map(|v| v.as_bytes())),
+            ("password", self.password.as_ref().map(|v| v.as_bytes())),
+            ("path", self.path.as_ref().map(|v| v.as_slice())),
+        ];
+        if let Some((key, _)) = fields
+            .iter()
+            .find(|(_, value)| value.is_some_and(|value| value.find_byteset(b"\n\0").is_some()))
+        {
+            return Err(Error::UnsafeValue((*key).into()));
+        }
+        for (key, value) in fields.iter() {
+            if let Some(value) = value {
+                out.write_all(key.as_bytes())?;
+                out.write_all(b"=")?;
+                out.write_all(value)?;
+                out.write_all(b"\n")?;
+            }
+        }
+        out.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Parse the `key=value` lines in `input` up to the first empty line, ignoring unknown keys.
+    pub fn from_bytes(input: &[u8]) -> Result<Self, Error> {
+        let mut ctx = Context::default();
+        for line in input.lines() {
+            if line.is_empty() {
+                break;
+            }
+            let pos = line
+                .find_byte(b'=')
+                .ok_or_else(|| Error::InvalidLine(line.as_bstr().to_owned()))?;
+            let (key, value) = (&line[..pos], &line[pos + 1..]);
+            let text = |key: &[u8]| -> Result<String, Error> {
+                match value.to_str() {
+                    Ok(value) if !value.contains('\0') => Ok(value.to_owned()),
+                    _ => Err(Error::InvalidValue(key.to_str_lossy().into_owned())),
+                }
+            };
+            match key {
+                b"protocol" => ctx.protocol = Some(text(key)?),
+                b"host" => ctx.host = Some(text(key)?),
+                b"path" => ctx.path = Some(value.into()),
+                b"username" => ctx.username = Some(text(key)?),
+                b"password" => ctx.password = Some(text(key)?),
+                b"quit" => ctx.quit = matches!(value, b"1" | b"true" | b"yes" | b"on"),
+                _ => {}
+            }
+        }
+        Ok(ctx)
+    }
+
+    /// Return the context as URL for display, like `https://user@example.com`.
+    pub fn to_url(&self) -> String {
+        let mut url = format!("{}://", self.protocol.as_deref().unwrap_or("unknown"));
+        if let Some(user) = &self.username {
+            url.push_str(user);
+            url.push('@');
+        }
+        if let Some(host) = &self.host {
+            url.push_str(host);
+        }
+        if let Some(path) = &self.path {
+            url.push('/');
+            url.push_str(&path.to_str_lossy());
+        }
+        url
+    }
+}
+
+/// A credential helper program, as configured with `credential.helper`.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Program {
+    /// A helper shipped with git or installed alongside it like `store` or `cache --timeout 60`, which is run as
+    /// `git credential-<name>`.
+    Builtin(String),
+    /// An absolute path to the helper program, possibly followed by arguments.
+    ExternalPath(String),
+    /// A shell command line run as is.
+    ExternalShellScript(String),
+}
+
+impl Program {
+    /// Interpret `value` like git interprets the value of `credential.helper`.
+    ///
+    /// Values starting with `!` are shell scripts, absolute paths are run as is and everything else names a
+    /// builtin helper.
+    pub fn from_config_value(value: &str) -> Self {
+        if let Some(script) = value.strip_prefix('!') {
+            Program::ExternalShellScript(script.into())
+        } else if std::path::Path::new(value.split_whitespace().next().unwrap_or_default()).is_absolute() {
+            Program::ExternalPath(value.into())
+        } else {
+            Program::Builtin(value.into())
+        }
+    }
+
+    /// Return the command line to run through the shell to perform `action`.
+    pub fn to_command_line(&self, action: Action) -> String {
+        match self {
+            Program::Builtin(name) => format!("git credential-{} {}", name, action.as_str()),
+            Program::ExternalPath(path) => format!("{} {}", path, action.as_str()),
+            Program::ExternalShellScript(script) => format!("{} {}", script, action.as_str()),
+        }
+    }
+
+    /// Run the program to perform `action` for `ctx`, returning the context it answered with for
+    /// [`Get`][Action::Get] and `None` otherwise.
+    pub fn invoke(&self, action: Action, ctx: &Context) -> Result<Option<Context>, Error> {
+        let command_line = self.to_command_line(action);
+        let mut buf = Vec::new();
+        ctx.write_to(&mut buf)?;
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&command_line)
+            .stdin(Stdio::piped())
+            .stdout(if action == Action::Get {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|err| Error::SpawnCommand(err, command_line.clone()))?;
+        {
+            let mut stdin = child.stdin.take().expect("stdin configured");
+            // Helpers are free to not read their input, for example if they don't handle the action.
+            match stdin.write_all(&buf) {
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {}
+                res => res?,
+            }
+        }
+        let mut output = Vec::new();
+        if let Some(mut stdout) = child.stdout.take() {
+            stdout.read_to_end(&mut output)?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(Error::ProgramFailed(command_line, status));
+        }
+        Ok(if action == Action::Get {
+            Some(Context::from_bytes(&output)?)
+        } else {
+            None
+        })
+    }
+}
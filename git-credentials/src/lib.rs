@@ -0,0 +1,134 @@
+//! Obtain credentials for a remote from credential helpers, falling back to asking the user, and let the helpers
+//! know whether they worked.
+//!
+//! Helpers are configured with `credential.helper` and consulted in order using the protocol of
+//! `git credential`, see [`helper`].
+#![forbid(unsafe_code)]
+
+use quick_error::quick_error;
+
+pub mod helper;
+pub mod prompt;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Url(err: git_url::parse::Error) {
+            display("The URL to obtain credentials for could not be parsed")
+            from()
+            source(err)
+        }
+        Helper(err: helper::Error) {
+            display("A credential helper failed")
+            from()
+            source(err)
+        }
+        Prompt(err: prompt::Error) {
+            display("Could not ask for credentials")
+            from()
+            source(err)
+        }
+        Incomplete(url: String) {
+            display("A credential helper stopped the lookup before the credentials for '{}' were complete", url)
+        }
+    }
+}
+
+/// The user name and password to authenticate with.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Identity {
+    /// The name of the user.
+    pub username: String,
+    /// The password or token of the user.
+    pub password: String,
+}
+
+/// The credentials obtained by [`Cascade::fill()`], along with the context to pass to
+/// [`Cascade::approve()`] or [`Cascade::reject()`] once it's known if they work.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Outcome {
+    /// The credentials.
+    pub identity: Identity,
+    /// The remote the credentials are for.
+    pub context: helper::Context,
+}
+
+/// The credential helpers to consult in order, and how to ask the user if none of them knows the credentials.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+pub struct Cascade {
+    /// The helpers to ask, in order.
+    pub programs: Vec<helper::Program>,
+    /// How to ask the user for missing credentials.
+    pub prompt: prompt::Options,
+    /// If true, the path of the repository is passed to helpers as well, like `credential.useHttpPath` does.
+    pub use_http_path: bool,
+}
+
+impl Cascade {
+    /// Consult no helpers and configure prompting from the environment, see [`prompt::Options::from_env()`].
+    pub fn from_env() -> Self {
+        Cascade {
+            programs: Vec::new(),
+            prompt: prompt::Options::from_env(),
+            use_http_path: false,
+        }
+    }
+
+    /// Obtain the credentials for `url`, which may contain the user name or password already.
+    ///
+    /// Each helper may add what it knows until the credentials are complete, then the user is asked for the rest.
+    pub fn fill(&self, url: &str) -> Result<Outcome, Error> {
+        let url = git_url::parse(url.as_bytes())?;
+        let mut ctx = helper::Context::from_url(&url, self.use_http_path);
+        for program in &self.programs {
+            if ctx.is_complete() {
+                break;
+            }
+            let answer = program.invoke(helper::Action::Get, &ctx)?.expect("an answer for 'get'");
+            ctx.username = ctx.username.or(answer.username);
+            ctx.password = ctx.password.or(answer.password);
+            if answer.quit && !ctx.is_complete() {
+                return Err(Error::Incomplete(ctx.to_url()));
+            }
+        }
+        if ctx.username.is_none() {
+            ctx.username = Some(prompt::ask(
+                &format!("Username for '{}': ", ctx.to_url()),
+                false,
+                &self.prompt,
+            )?);
+        }
+        if ctx.password.is_none() {
+            ctx.password = Some(prompt::ask(
+                &format!("Password for '{}': ", ctx.to_url()),
+                true,
+                &self.prompt,
+            )?);
+        }
+        Ok(Outcome {
+            identity: Identity {
+                username: ctx.username.clone().expect("set above"),
+                password: ctx.password.clone().expect("set above"),
+            },
+            context: ctx,
+        })
+    }
+
+    /// Tell all helpers to remember the credentials of `outcome` as the remote accepted them.
+    pub fn approve(&self, outcome: &Outcome) -> Result<(), Error> {
+        self.invoke_all(helper::Action::Store, &outcome.context)
+    }
+
+    /// Tell all helpers to forget the credentials of `outcome` as the remote rejected them.
+    pub fn reject(&self, outcome: &Outcome) -> Result<(), Error> {
+        self.invoke_all(helper::Action::Erase, &outcome.context)
+    }
+
+    fn invoke_all(&self, action: helper::Action, ctx: &helper::Context) -> Result<(), Error> {
+        for program in &self.programs {
+            program.invoke(action, ctx)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+//! Ask the user for credentials through an askpass program or the terminal, if no helper knew them.
+use quick_error::quick_error;
+use std::{
+    ffi::OsString,
+    fs,
+    io::{self, BufRead, Write},
+    process::{Command, Stdio},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred while prompting on the terminal")
+            from()
+            source(err)
+        }
+        SpawnCommand(err: io::Error, command: String) {
+            display("Could not spawn the askpass program '{}'", command)
+            source(err)
+        }
+        ProgramFailed(command: String, status: std::process::ExitStatus) {
+            display("The askpass program '{}' failed with {}", command, status)
+        }
+        Disabled(prompt: String) {
+            display("Cannot prompt for '{}' as there is no askpass program and terminal prompts are disabled", prompt)
+        }
+    }
+}
+
+/// Configure how to ask the user.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+pub struct Options {
+    /// The program to run with the prompt as only argument, printing the answer to its standard output.
+    pub askpass: Option<OsString>,
+    /// If true, the user is asked on the terminal if there is no askpass program.
+    pub terminal: bool,
+}
+
+impl Options {
+    /// Determine the options from the `GIT_ASKPASS`, `SSH_ASKPASS` and `GIT_TERMINAL_PROMPT` environment variables.
+    pub fn from_env() -> Self {
+        Options {
+            askpass: std::env::var_os("GIT_ASKPASS")
+                .or_else(|| std::env::var_os("SSH_ASKPASS"))
+                .filter(|program| !program.is_empty()),
+            terminal: !matches!(std::env::var_os("GIT_TERMINAL_PROMPT"), Some(value) if value == "0"),
+        }
+    }
+}
+
+/// Ask the user for an answer to `prompt`, without showing what is typed if `secret` is true.
+pub fn ask(prompt: &str, secret: bool, options: &Options) -> Result<String, Error> {
+    if let Some(program) = &options.askpass {
+        let output = Command::new(program)
+            .arg(prompt)
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()
+            .map_err(|err| Error::SpawnCommand(err, program.to_string_lossy().into_owned()))?;
+        if !output.status.success() {
+            return Err(Error::ProgramFailed(
+                program.to_string_lossy().into_owned(),
+                output.status,
+            ));
+        }
+        let answer = String::from_utf8_lossy(&output.stdout);
+        return Ok(answer.lines().next().unwrap_or_default().to_owned());
+    }
+    if !options.terminal {
+        return Err(Error::Disabled(prompt.into()));
+    }
+
+    let mut tty = fs::OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    tty.write_all(prompt.as_bytes())?;
+    tty.flush()?;
+    let set_echo = |enabled: bool| -> io::Result<()> {
+        Command::new("stty")
+            .arg(if enabled { "echo" } else { "-echo" })
+            .stdin(fs::File::open("/dev/tty")?)
+            .status()
+            .map(|_| ())
+    };
+    if secret {
+        set_echo(false)?;
+    }
+    let mut answer = String::new();
+    let res = io::BufReader::new(&tty).read_line(&mut answer);
+    if secret {
+        set_echo(true)?;
+        tty.write_all(b"\n")?;
+    }
+    res?;
+    Ok(answer.trim_end_matches(&['\n', '\r'][..]).to_owned())
+}
@@ -0,0 +1,11 @@
+mod cascade;
+mod helper;
+
+/// A helper script appending the action it was called with to `log`, which answers `get` with `answer`.
+pub fn logging_helper(log: &std::path::Path, answer: &str) -> git_credentials::helper::Program {
+    git_credentials::helper::Program::ExternalShellScript(format!(
+        "f() {{ cat > /dev/null; echo \"$1\" >> '{}'; test \"$1\" = get && printf '{}'; true; }}; f",
+        log.display(),
+        answer
+    ))
+}
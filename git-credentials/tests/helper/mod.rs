@@ -0,0 +1,121 @@
+use bstr::ByteSlice;
+use git_credentials::helper::{Action, Context, Program};
+
+fn context() -> Context {
+    Context {
+        protocol: Some("https".into()),
+        host: Some("example.com:8080".into()),
+        path: Some("org/repo.git".into()),
+        username: Some("alice".into()),
+        password: None,
+        quit: false,
+    }
+}
+
+#[test]
+fn context_from_url() -> Result<(), Box<dyn std::error::Error>> {
+    let url = git_url::parse(b"https://alice@example.com:8080/org/repo.git")?;
+    assert_eq!(Context::from_url(&url, true), context());
+    assert_eq!(
+        Context::from_url(&url, false),
+        Context {
+            path: None,
+            ..context()
+        },
+        "the path is only used if credentials differ per repository"
+    );
+    Ok(())
+}
+
+#[test]
+fn context_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let mut buf = Vec::new();
+    context().write_to(&mut buf)?;
+    assert_eq!(
+        buf.as_bstr(),
+        b"protocol=https\nhost=example.com:8080\nusername=alice\npath=org/repo.git\n\n".as_bstr()
+    );
+    assert_eq!(Context::from_bytes(&buf)?, context());
+    Ok(())
+}
+
+#[test]
+fn values_with_newlines_are_never_passed_to_helpers() -> Result<(), Box<dyn std::error::Error>> {
+    let url = git_url::parse(b"https://example.com%0Ahost=evil.com%0A/repo")?;
+    let ctx = Context::from_url(&url, true);
+    let mut buf = Vec::new();
+    assert!(matches!(
+        ctx.write_to(&mut buf),
+        Err(git_credentials::helper::Error::UnsafeValue(key)) if key == "host"
+    ));
+    assert!(buf.is_empty(), "nothing is written if any value is unsafe");
+
+    let dir = tempfile::tempdir()?;
+    let marker = dir.path().join("invoked");
+    let helper = Program::ExternalShellScript(format!("touch '{}'; cat >/dev/null", marker.display()));
+    assert!(helper.invoke(Action::Get, &ctx).is_err());
+    assert!(!marker.exists(), "the helper isn't even started");
+
+    let mut buf = Vec::new();
+    let with_null = Context {
+        password: Some("a\0b".into()),
+        ..context()
+    };
+    assert!(with_null.write_to(&mut buf).is_err());
+    assert!(buf.is_empty());
+    Ok(())
+}
+
+#[test]
+fn answers_of_helpers() -> Result<(), Box<dyn std::error::Error>> {
+    let ctx = Context::from_bytes(b"username=bob\npassword=a=b\nunknown=value\nquit=1\n\nhost=ignored\n")?;
+    assert_eq!(
+        ctx,
+        Context {
+            username: Some("bob".into()),
+            password: Some("a=b".into()),
+            quit: true,
+            ..Default::default()
+        }
+    );
+    assert!(ctx.is_complete());
+    assert!(Context::from_bytes(b"no separator\n").is_err());
+    Ok(())
+}
+
+#[test]
+fn programs_from_config_values() {
+    assert_eq!(
+        Program::from_config_value("cache --timeout 60"),
+        Program::Builtin("cache --timeout 60".into())
+    );
+    assert_eq!(
+        Program::from_config_value("/usr/bin/helper --flag"),
+        Program::ExternalPath("/usr/bin/helper --flag".into())
+    );
+    assert_eq!(
+        Program::from_config_value("!f() { echo hi; }; f"),
+        Program::ExternalShellScript("f() { echo hi; }; f".into())
+    );
+    assert_eq!(
+        Program::from_config_value("store").to_command_line(Action::Erase),
+        "git credential-store erase"
+    );
+}
+
+#[test]
+fn invoke_passes_the_context_and_parses_the_answer() -> Result<(), Box<dyn std::error::Error>> {
+    let echo_user = Program::ExternalShellScript("f() { sed -n 's/^username=/password=secret-of-/p'; }; f".into());
+    assert_eq!(
+        echo_user.invoke(Action::Get, &context())?,
+        Some(Context {
+            password: Some("secret-of-alice".into()),
+            ..Default::default()
+        })
+    );
+    assert_eq!(echo_user.invoke(Action::Store, &context())?, None);
+    assert!(Program::ExternalShellScript("false".into())
+        .invoke(Action::Get, &context())
+        .is_err());
+    Ok(())
+}
@@ -0,0 +1,48 @@
+//! Hashing of object data, packs and indices, with the backend chosen at compile time.
+//!
+//! The default backend detects the SHA-1 collision attacks known so far like the `sha1dc` implementation used by git.
+//! Where git refuses colliding data, it produces the safe hash of `sha1dc` instead, which differs from the SHA-1 of
+//! that data. The `fast-sha1` feature selects the faster, assembly-backed `sha-1` crate instead, which doesn't detect
+//! collisions.
+//!
+//! All backends offer streaming hashing with [`Sha1`] as well as hashing everything at once with [`sha1()`].
+#[cfg(not(feature = "fast-sha1"))]
+mod sha1dc;
+#[cfg(not(feature = "fast-sha1"))]
+mod _impl {
+    pub use super::sha1dc::Sha1;
+}
+
+pub type Sha1Digest = [u8; 20];
+
+#[cfg(feature = "fast-sha1")]
+mod _impl {
+    use super::Sha1Digest;
+    use fastsha1::Digest;
+
+    #[derive(Default, Clone)]
+    pub struct Sha1(fastsha1::Sha1);
+
+    impl Sha1 {
+        pub fn update(&mut self, d: &[u8]) {
+            self.0.update(d)
+        }
+        pub fn digest(self) -> Sha1Digest {
+            self.0.finalize().into()
+        }
+    }
+}
+
+pub use _impl::Sha1;
+
+/// Compute the SHA-1 digest of `data` in one go.
+pub fn sha1(data: &[u8]) -> Sha1Digest {
+    let mut hasher = Sha1::default();
+    hasher.update(data);
+    hasher.digest()
+}
+
+pub fn crc32_update(previous_value: u32, bytes: &[u8]) -> u32 {
+    crc::crc32::update(previous_value, &crc::crc32::IEEE_TABLE, bytes)
+}
+pub use crc::crc32::checksum_ieee as crc32;
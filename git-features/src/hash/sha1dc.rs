@@ -0,0 +1,912 @@
+//! SHA-1 with the collision detection of [sha1collisiondetection](https://github.com/cr-marcstevens/sha1collisiondetection),
+//! which git uses as well.
+//!
+//! Each block is compressed as usual, keeping its message expansion and the states before the steps at which the
+//! disturbance vectors of all known collision attacks are tested. For each disturbance vector, the block with the
+//! differences of the vector applied is compressed from the stored state backwards and forwards. If this results in the
+//! same intermediate hash value from another one, the block is part of a collision attack, and the block is compressed
+//! twice more to produce the safe hash of sha1collisiondetection, which differs from the SHA-1 of colliding data. git
+//! refuses such data instead.
+//!
+//! As in the original, the unavoidable bit conditions of each disturbance vector are checked on the message expansion
+//! first, and only the vectors whose conditions all hold are tested. This rules out all of them for almost all blocks.
+use super::Sha1Digest;
+use std::convert::TryInto;
+
+const INITIAL_STATE: [u32; 5] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476, 0xc3d2_e1f0];
+const BLOCK_LEN: usize = 64;
+
+#[derive(Clone)]
+pub struct Sha1 {
+    ihv: [u32; 5],
+    buffer: [u8; BLOCK_LEN],
+    len: u64,
+}
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Sha1 {
+            ihv: INITIAL_STATE,
+            buffer: [0; BLOCK_LEN],
+            len: 0,
+        }
+    }
+}
+
+impl Sha1 {
+    pub fn update(&mut self, mut data: &[u8]) {
+        let buffered = (self.len % BLOCK_LEN as u64) as usize;
+        self.len += data.len() as u64;
+        if buffered > 0 {
+            let len = (BLOCK_LEN - buffered).min(data.len());
+            self.buffer[buffered..buffered + len].copy_from_slice(&data[..len]);
+            data = &data[len..];
+            if buffered + len < BLOCK_LEN {
+                return;
+            }
+            let block = self.buffer;
+            self.process(&block);
+        }
+        let mut blocks = data.chunks_exact(BLOCK_LEN);
+        for block in &mut blocks {
+            self.process(block);
+        }
+        let rest = blocks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+    }
+
+    pub fn digest(mut self) -> Sha1Digest {
+        let bit_len = self.len.wrapping_mul(8);
+        let buffered = (self.len % BLOCK_LEN as u64) as usize;
+        let padding_len = if buffered < 56 { 56 - buffered } else { 120 - buffered };
+        let mut padding = [0u8; 72];
+        padding[0] = 0x80;
+        self.update(&padding[..padding_len]);
+        self.update(&bit_len.to_be_bytes());
+
+        let mut digest = [0u8; 20];
+        for (bytes, word) in digest.chunks_exact_mut(4).zip(self.ihv.iter()) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    fn process(&mut self, block: &[u8]) {
+        let mut w = [0u32; 80];
+        for (word, bytes) in w.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().expect("4 bytes"));
+        }
+        for t in 16..80 {
+            w[t] = (w[t - 3] ^ w[t - 8] ^ w[t - 14] ^ w[t - 16]).rotate_left(1);
+        }
+
+        // Only the states before the steps at which disturbance vectors are tested are needed later.
+        let mut states = [[0u32; 5]; 80];
+        let mut state = self.ihv;
+        for t in 0..58 {
+            state = step(state, &w, t);
+        }
+        states[58] = state;
+        for t in 58..65 {
+            state = step(state, &w, t);
+        }
+        states[65] = state;
+        for t in 65..80 {
+            state = step(state, &w, t);
+        }
+        let ihv = add(self.ihv, state);
+
+        let candidates = unavoidable_bit_conditions(&w);
+        if candidates == 0 {
+            self.ihv = ihv;
+            return;
+        }
+        for dv in DISTURBANCE_VECTORS
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| candidates & (1 << index) != 0)
+            .map(|(_, dv)| dv)
+        {
+            let mut w2 = w;
+            for (word, difference) in w2.iter_mut().zip(dv.dm.iter()) {
+                *word ^= difference;
+            }
+            let mut other_ihv = states[dv.testt];
+            for t in (0..dv.testt).rev() {
+                other_ihv = step_back(other_ihv, &w2, t);
+            }
+            let mut state = states[dv.testt];
+            for t in dv.testt..80 {
+                state = step(state, &w2, t);
+            }
+            if add(other_ihv, state) == ihv {
+                self.ihv = compress(compress(ihv, &w), &w);
+                return;
+            }
+        }
+        self.ihv = ihv;
+    }
+}
+
+fn add(a: [u32; 5], b: [u32; 5]) -> [u32; 5] {
+    let mut sum = a;
+    for (sum, b) in sum.iter_mut().zip(b.iter()) {
+        *sum = sum.wrapping_add(*b);
+    }
+    sum
+}
+
+fn compress(ihv: [u32; 5], w: &[u32; 80]) -> [u32; 5] {
+    let mut state = ihv;
+    for t in 0..80 {
+        state = step(state, w, t);
+    }
+    add(ihv, state)
+}
+
+/// The boolean function and constant of step `t`.
+fn round(t: usize, b: u32, c: u32, d: u32) -> (u32, u32) {
+    match t {
+        0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+        20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+        40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+        _ => (b ^ c ^ d, 0xca62_c1d6),
+    }
+}
+
+/// Compute the state after step `t` from the state `[a, b, c, d, e]` before it.
+fn step([a, b, c, d, e]: [u32; 5], w: &[u32; 80], t: usize) -> [u32; 5] {
+    let (f, k) = round(t, b, c, d);
+    let a_next = a
+        .rotate_left(5)
+        .wrapping_add(f)
+        .wrapping_add(e)
+        .wrapping_add(k)
+        .wrapping_add(w[t]);
+    [a_next, a, b.rotate_left(30), c, d]
+}
+
+/// Compute the state before step `t` from the state after it, undoing [`step()`].
+fn step_back([a_next, a, b, c, d]: [u32; 5], w: &[u32; 80], t: usize) -> [u32; 5] {
+    let b = b.rotate_right(30);
+    let (f, k) = round(t, b, c, d);
+    let e = a_next.wrapping_sub(a.rotate_left(5).wrapping_add(f).wrapping_add(k).wrapping_add(w[t]));
+    [a, b, c, d, e]
+}
+
+/// Clear the disturbance vectors `dvs` from `candidates` for each `(a, i, b, j, expected, dvs)` whose unavoidable bit
+/// condition, bit `i` of `w[a]` xor bit `j` of `w[b]` equals `expected`, is violated.
+macro_rules! clear_violated {
+    ($w:ident, $candidates:ident, [$(($a:literal, $i:literal, $b:literal, $j:literal, $expected:literal, $dvs:literal),)*]) => {
+        $(
+            $candidates &= !($dvs & ((($w[$a] >> $i) ^ ($w[$b] >> $j) ^ $expected) & 1u32).wrapping_neg());
+        )*
+    };
+}
+
+/// Return a mask with bit `n` set if none of the unavoidable bit conditions of the `n`th entry of
+/// [`DISTURBANCE_VECTORS`] are violated by the message expansion `w`, as computed by `ubc_check()` of
+/// sha1collisiondetection.
+///
+/// The conditions affecting the most disturbance vectors come first, so for most blocks all of them are ruled out early.
+#[rustfmt::skip]
+fn unavoidable_bit_conditions(w: &[u32; 80]) -> u32 {
+    let mut candidates = !0u32;
+    clear_violated!(w, candidates, [
+        (44, 29, 45, 29, 0, 0x0283a080),
+        (40, 29, 41, 29, 0, 0x800a00a2),
+        (41, 4, 44, 29, 0, 0x00812025),
+        (42, 4, 45, 29, 0, 0x0202808a),
+        (43, 4, 46, 29, 0, 0x08080225),
+        (43, 29, 44, 29, 0, 0x00a12820),
+        (44, 4, 47, 29, 0, 0x1010088a),
+        (45, 4, 48, 29, 0, 0x20202224),
+        (45, 29, 46, 29, 0, 0x0a0a8200),
+        (46, 4, 49, 29, 0, 0x40808888),
+        (46, 29, 47, 29, 0, 0x18180801),
+        (47, 4, 50, 29, 0, 0x82012220),
+        (47, 29, 48, 29, 0, 0x30302002),
+        (48, 29, 49, 29, 0, 0x60a08004),
+        (49, 29, 50, 29, 0, 0xc2810008),
+        (37, 4, 40, 29, 0, 0x50020021),
+        (38, 4, 41, 29, 0, 0xa0080082),
+        (39, 4, 42, 29, 0, 0x40100205),
+        (40, 4, 43, 29, 0, 0x8020080a),
+        (41, 29, 42, 29, 0, 0x00180284),
+    ]);
+    if candidates == 0 {
+        return candidates;
+    }
+    clear_violated!(w, candidates, [
+        (42, 29, 43, 29, 0, 0x00300a08),
+        (48, 4, 51, 29, 0, 0x08028880),
+        (49, 4, 52, 29, 0, 0x10092200),
+        (50, 4, 53, 29, 0, 0x20128800),
+        (50, 29, 51, 29, 0, 0x8a020020),
+        (52, 29, 53, 29, 0, 0x30110200),
+        (53, 29, 54, 29, 0, 0x60220800),
+        (54, 29, 55, 29, 0, 0xc0882000),
+        (36, 4, 40, 29, 0, 0x00110208),
+        (37, 4, 41, 29, 0, 0x00220820),
+        (38, 4, 42, 29, 0, 0x00882080),
+        (39, 4, 43, 29, 0, 0x02108200),
+        (41, 4, 45, 29, 0, 0x10812000),
+        (42, 4, 46, 29, 0, 0x22028000),
+        (43, 4, 47, 29, 0, 0x48080001),
+        (44, 4, 48, 29, 0, 0x90100002),
+        (51, 4, 54, 29, 0, 0x40282000),
+        (51, 29, 52, 29, 0, 0x18080080),
+        (52, 4, 55, 29, 0, 0x80908000),
+        (55, 29, 56, 29, 0, 0x82108000),
+    ]);
+    if candidates == 0 {
+        return candidates;
+    }
+    clear_violated!(w, candidates, [
+        (35, 4, 39, 29, 0, 0x00080084),
+        (36, 1, 37, 6, 1, 0x00041040),
+        (37, 4, 39, 4, 1, 0x50000001),
+        (38, 4, 40, 4, 1, 0xa0000002),
+        (39, 1, 40, 6, 1, 0x00401010),
+        (39, 4, 41, 4, 1, 0x40000005),
+        (40, 1, 41, 6, 1, 0x01004040),
+        (40, 4, 42, 4, 1, 0x8000000a),
+        (40, 4, 44, 29, 0, 0x08200800),
+        (41, 1, 42, 6, 1, 0x04040100),
+        (41, 4, 43, 4, 1, 0x00000025),
+        (42, 4, 44, 4, 1, 0x0000008a),
+        (43, 4, 45, 4, 1, 0x00000224),
+        (44, 4, 46, 4, 1, 0x00000888),
+        (44, 6, 46, 6, 0, 0x00001110),
+        (45, 4, 47, 4, 1, 0x00002220),
+        (45, 6, 47, 6, 0, 0x00004440),
+        (46, 4, 48, 4, 1, 0x00008880),
+        (47, 4, 49, 4, 1, 0x00012200),
+        (48, 4, 50, 4, 1, 0x00028800),
+    ]);
+    if candidates == 0 {
+        return candidates;
+    }
+    clear_violated!(w, candidates, [
+        (52, 29, 55, 29, 1, 0x00182000),
+        (53, 29, 56, 29, 1, 0x00308000),
+        (35, 1, 36, 6, 1, 0x00000410),
+        (35, 3, 39, 28, 0, 0x00082000),
+        (36, 4, 38, 4, 1, 0x28000000),
+        (37, 1, 38, 6, 1, 0x00004100),
+        (39, 1, 41, 1, 1, 0x00401000),
+        (40, 1, 42, 1, 1, 0x01004000),
+        (41, 1, 43, 1, 1, 0x04040000),
+        (42, 6, 44, 6, 0, 0x00000110),
+        (43, 6, 45, 6, 0, 0x00000440),
+        (44, 1, 45, 6, 1, 0x00404000),
+        (44, 6, 48, 6, 0, 0x00001100),
+        (45, 6, 49, 6, 0, 0x00004400),
+        (46, 6, 47, 1, 0, 0x01000010),
+        (47, 6, 48, 1, 0, 0x04000040),
+        (48, 6, 50, 6, 0, 0x00041000),
+        (48, 6, 51, 1, 0, 0x00041000),
+        (53, 4, 56, 29, 0, 0x02200000),
+        (54, 4, 57, 29, 0, 0x08800000),
+    ]);
+    if candidates == 0 {
+        return candidates;
+    }
+    clear_violated!(w, candidates, [
+        (55, 4, 58, 29, 0, 0x12000000),
+        (55, 29, 58, 29, 1, 0x02800000),
+        (56, 4, 59, 29, 0, 0x28000000),
+        (56, 29, 57, 29, 0, 0x08200000),
+        (56, 29, 59, 29, 1, 0x0a000000),
+        (57, 29, 58, 29, 0, 0x10800000),
+        (58, 29, 59, 29, 0, 0x22000000),
+        (60, 0, 61, 5, 1, 0x00010004),
+        (61, 0, 62, 5, 1, 0x00020008),
+        (61, 2, 62, 7, 1, 0x00040010),
+        (62, 0, 63, 5, 1, 0x00080020),
+        (63, 0, 64, 5, 1, 0x00100080),
+        (63, 1, 64, 6, 1, 0x00010004),
+        (35, 5, 39, 30, 0, 0x00004000),
+        (35, 30, 36, 3, 1, 0x00100000),
+        (35, 30, 40, 28, 1, 0x00100000),
+        (36, 0, 37, 5, 1, 0x00400000),
+        (36, 0, 41, 30, 1, 0x00400000),
+        (36, 4, 37, 4, 1, 0x00000800),
+        (36, 30, 37, 3, 1, 0x00200000),
+    ]);
+    if candidates == 0 {
+        return candidates;
+    }
+    clear_violated!(w, candidates, [
+        (36, 30, 41, 28, 1, 0x00200000),
+        (37, 0, 38, 5, 1, 0x01000000),
+        (37, 0, 42, 30, 1, 0x01000000),
+        (37, 1, 37, 6, 0, 0x00004000),
+        (37, 4, 38, 4, 1, 0x00002000),
+        (37, 30, 38, 3, 1, 0x00800000),
+        (37, 30, 42, 28, 1, 0x00800000),
+        (38, 0, 39, 5, 1, 0x04000000),
+        (38, 0, 43, 30, 1, 0x04000000),
+        (38, 1, 39, 6, 1, 0x00000400),
+        (38, 1, 40, 1, 1, 0x00000400),
+        (38, 4, 39, 4, 1, 0x00008000),
+        (38, 30, 39, 3, 1, 0x02000000),
+        (38, 30, 43, 28, 1, 0x02000000),
+        (39, 30, 40, 3, 1, 0x08000000),
+        (39, 30, 44, 28, 1, 0x08000000),
+        (40, 6, 42, 6, 0, 0x00000010),
+        (41, 3, 45, 28, 0, 0x10000000),
+        (41, 6, 43, 6, 0, 0x00000040),
+        (42, 1, 49, 6, 1, 0x00000400),
+    ]);
+    if candidates == 0 {
+        return candidates;
+    }
+    clear_violated!(w, candidates, [
+        (42, 1, 50, 1, 1, 0x00000400),
+        (42, 3, 46, 28, 0, 0x20000000),
+        (43, 1, 51, 1, 1, 0x00001000),
+        (43, 3, 47, 28, 0, 0x40000000),
+        (44, 3, 48, 28, 0, 0x80000000),
+        (45, 1, 47, 1, 1, 0x01000000),
+        (45, 6, 46, 1, 0, 0x00400000),
+        (46, 1, 48, 1, 1, 0x04000000),
+        (47, 1, 51, 1, 1, 0x00040000),
+        (48, 6, 49, 1, 0, 0x00000100),
+        (49, 6, 51, 6, 0, 0x00004000),
+        (49, 6, 52, 1, 0, 0x00004000),
+        (50, 1, 51, 6, 1, 0x00400000),
+        (50, 1, 53, 6, 1, 0x00400000),
+        (50, 1, 54, 1, 1, 0x00400000),
+        (51, 1, 52, 6, 1, 0x01000000),
+        (51, 1, 54, 6, 1, 0x01000000),
+        (51, 1, 55, 1, 1, 0x01000000),
+        (52, 1, 53, 6, 1, 0x04000000),
+        (52, 1, 55, 6, 1, 0x04000000),
+    ]);
+    if candidates == 0 {
+        return candidates;
+    }
+    clear_violated!(w, candidates, [
+        (52, 1, 56, 1, 1, 0x04000000),
+        (57, 4, 58, 29, 1, 0x10000000),
+        (57, 4, 59, 29, 0, 0x40000000),
+        (57, 4, 61, 29, 0, 0x10000000),
+        (58, 0, 59, 5, 1, 0x00000001),
+        (58, 0, 63, 30, 1, 0x00000001),
+        (58, 4, 62, 29, 0, 0x20000000),
+        (59, 0, 60, 5, 1, 0x00000002),
+        (59, 0, 64, 30, 1, 0x00000002),
+        (59, 4, 63, 29, 0, 0x40000000),
+        (59, 29, 60, 29, 0, 0x08000000),
+        (60, 4, 64, 29, 0, 0x80000000),
+        (61, 1, 62, 6, 1, 0x00000001),
+        (62, 1, 63, 6, 1, 0x00000002),
+        (62, 2, 63, 7, 1, 0x00000040),
+        (63, 2, 64, 7, 1, 0x00000100),
+    ]);
+    candidates
+}
+
+/// The expanded message differences of a disturbance vector, to be tested from the state before step `testt`.
+struct DisturbanceVector {
+    testt: usize,
+    dm: [u32; 80],
+}
+
+/// The disturbance vectors of sha1collisiondetection, named by their type, `K` and `b`.
+#[rustfmt::skip]
+const DISTURBANCE_VECTORS: &[DisturbanceVector] = &[
+    // DV_I_43_0
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0x08000000, 0x9800000c, 0xd8000010, 0x08000010, 0xb8000010, 0x98000000, 0x60000000, 0x00000008,
+            0xc0000000, 0x90000014, 0x10000010, 0xb8000014, 0x28000000, 0x20000010, 0x48000000, 0x08000018,
+            0x60000000, 0x90000010, 0xf0000010, 0x90000008, 0xc0000000, 0x90000010, 0xf0000010, 0xb0000008,
+            0x40000000, 0x90000000, 0xf0000010, 0x90000018, 0x60000000, 0x90000010, 0x90000010, 0x90000000,
+            0x80000000, 0x00000010, 0xa0000000, 0x20000000, 0xa0000000, 0x20000010, 0x00000000, 0x20000010,
+            0x20000000, 0x00000010, 0x20000000, 0x00000010, 0xa0000000, 0x00000000, 0x20000000, 0x20000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000001, 0x00000020, 0x00000001, 0x40000002, 0x40000040, 0x40000002,
+            0x80000004, 0x80000080, 0x80000006, 0x00000049, 0x00000103, 0x80000009, 0x80000012, 0x80000202,
+            0x00000018, 0x00000164, 0x00000408, 0x800000e6, 0x8000004c, 0x00000803, 0x80000161, 0x80000599,
+        ],
+    },
+    // DV_I_44_0
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0xb4000008, 0x08000000, 0x9800000c, 0xd8000010, 0x08000010, 0xb8000010, 0x98000000, 0x60000000,
+            0x00000008, 0xc0000000, 0x90000014, 0x10000010, 0xb8000014, 0x28000000, 0x20000010, 0x48000000,
+            0x08000018, 0x60000000, 0x90000010, 0xf0000010, 0x90000008, 0xc0000000, 0x90000010, 0xf0000010,
+            0xb0000008, 0x40000000, 0x90000000, 0xf0000010, 0x90000018, 0x60000000, 0x90000010, 0x90000010,
+            0x90000000, 0x80000000, 0x00000010, 0xa0000000, 0x20000000, 0xa0000000, 0x20000010, 0x00000000,
+            0x20000010, 0x20000000, 0x00000010, 0x20000000, 0x00000010, 0xa0000000, 0x00000000, 0x20000000,
+            0x20000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000001, 0x00000020, 0x00000001, 0x40000002, 0x40000040,
+            0x40000002, 0x80000004, 0x80000080, 0x80000006, 0x00000049, 0x00000103, 0x80000009, 0x80000012,
+            0x80000202, 0x00000018, 0x00000164, 0x00000408, 0x800000e6, 0x8000004c, 0x00000803, 0x80000161,
+        ],
+    },
+    // DV_I_45_0
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0xf4000014, 0xb4000008, 0x08000000, 0x9800000c, 0xd8000010, 0x08000010, 0xb8000010, 0x98000000,
+            0x60000000, 0x00000008, 0xc0000000, 0x90000014, 0x10000010, 0xb8000014, 0x28000000, 0x20000010,
+            0x48000000, 0x08000018, 0x60000000, 0x90000010, 0xf0000010, 0x90000008, 0xc0000000, 0x90000010,
+            0xf0000010, 0xb0000008, 0x40000000, 0x90000000, 0xf0000010, 0x90000018, 0x60000000, 0x90000010,
+            0x90000010, 0x90000000, 0x80000000, 0x00000010, 0xa0000000, 0x20000000, 0xa0000000, 0x20000010,
+            0x00000000, 0x20000010, 0x20000000, 0x00000010, 0x20000000, 0x00000010, 0xa0000000, 0x00000000,
+            0x20000000, 0x20000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000001, 0x00000020, 0x00000001, 0x40000002,
+            0x40000040, 0x40000002, 0x80000004, 0x80000080, 0x80000006, 0x00000049, 0x00000103, 0x80000009,
+            0x80000012, 0x80000202, 0x00000018, 0x00000164, 0x00000408, 0x800000e6, 0x8000004c, 0x00000803,
+        ],
+    },
+    // DV_I_46_0
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0x2c000010, 0xf4000014, 0xb4000008, 0x08000000, 0x9800000c, 0xd8000010, 0x08000010, 0xb8000010,
+            0x98000000, 0x60000000, 0x00000008, 0xc0000000, 0x90000014, 0x10000010, 0xb8000014, 0x28000000,
+            0x20000010, 0x48000000, 0x08000018, 0x60000000, 0x90000010, 0xf0000010, 0x90000008, 0xc0000000,
+            0x90000010, 0xf0000010, 0xb0000008, 0x40000000, 0x90000000, 0xf0000010, 0x90000018, 0x60000000,
+            0x90000010, 0x90000010, 0x90000000, 0x80000000, 0x00000010, 0xa0000000, 0x20000000, 0xa0000000,
+            0x20000010, 0x00000000, 0x20000010, 0x20000000, 0x00000010, 0x20000000, 0x00000010, 0xa0000000,
+            0x00000000, 0x20000000, 0x20000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000001, 0x00000020, 0x00000001,
+            0x40000002, 0x40000040, 0x40000002, 0x80000004, 0x80000080, 0x80000006, 0x00000049, 0x00000103,
+            0x80000009, 0x80000012, 0x80000202, 0x00000018, 0x00000164, 0x00000408, 0x800000e6, 0x8000004c,
+        ],
+    },
+    // DV_I_46_2
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0xb0000040, 0xd0000053, 0xd0000022, 0x20000000, 0x60000032, 0x60000043, 0x20000040, 0xe0000042,
+            0x60000002, 0x80000001, 0x00000020, 0x00000003, 0x40000052, 0x40000040, 0xe0000052, 0xa0000000,
+            0x80000040, 0x20000001, 0x20000060, 0x80000001, 0x40000042, 0xc0000043, 0x40000022, 0x00000003,
+            0x40000042, 0xc0000043, 0xc0000022, 0x00000001, 0x40000002, 0xc0000043, 0x40000062, 0x80000001,
+            0x40000042, 0x40000042, 0x40000002, 0x00000002, 0x00000040, 0x80000002, 0x80000000, 0x80000002,
+            0x80000040, 0x00000000, 0x80000040, 0x80000000, 0x00000040, 0x80000000, 0x00000040, 0x80000002,
+            0x00000000, 0x80000000, 0x80000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000004, 0x00000080, 0x00000004,
+            0x00000009, 0x00000101, 0x00000009, 0x00000012, 0x00000202, 0x0000001a, 0x00000124, 0x0000040c,
+            0x00000026, 0x0000004a, 0x0000080a, 0x00000060, 0x00000590, 0x00001020, 0x0000039a, 0x00000132,
+        ],
+    },
+    // DV_I_47_0
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0xc8000010, 0x2c000010, 0xf4000014, 0xb4000008, 0x08000000, 0x9800000c, 0xd8000010, 0x08000010,
+            0xb8000010, 0x98000000, 0x60000000, 0x00000008, 0xc0000000, 0x90000014, 0x10000010, 0xb8000014,
+            0x28000000, 0x20000010, 0x48000000, 0x08000018, 0x60000000, 0x90000010, 0xf0000010, 0x90000008,
+            0xc0000000, 0x90000010, 0xf0000010, 0xb0000008, 0x40000000, 0x90000000, 0xf0000010, 0x90000018,
+            0x60000000, 0x90000010, 0x90000010, 0x90000000, 0x80000000, 0x00000010, 0xa0000000, 0x20000000,
+            0xa0000000, 0x20000010, 0x00000000, 0x20000010, 0x20000000, 0x00000010, 0x20000000, 0x00000010,
+            0xa0000000, 0x00000000, 0x20000000, 0x20000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000001, 0x00000020,
+            0x00000001, 0x40000002, 0x40000040, 0x40000002, 0x80000004, 0x80000080, 0x80000006, 0x00000049,
+            0x00000103, 0x80000009, 0x80000012, 0x80000202, 0x00000018, 0x00000164, 0x00000408, 0x800000e6,
+        ],
+    },
+    // DV_I_47_2
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0x20000043, 0xb0000040, 0xd0000053, 0xd0000022, 0x20000000, 0x60000032, 0x60000043, 0x20000040,
+            0xe0000042, 0x60000002, 0x80000001, 0x00000020, 0x00000003, 0x40000052, 0x40000040, 0xe0000052,
+            0xa0000000, 0x80000040, 0x20000001, 0x20000060, 0x80000001, 0x40000042, 0xc0000043, 0x40000022,
+            0x00000003, 0x40000042, 0xc0000043, 0xc0000022, 0x00000001, 0x40000002, 0xc0000043, 0x40000062,
+            0x80000001, 0x40000042, 0x40000042, 0x40000002, 0x00000002, 0x00000040, 0x80000002, 0x80000000,
+            0x80000002, 0x80000040, 0x00000000, 0x80000040, 0x80000000, 0x00000040, 0x80000000, 0x00000040,
+            0x80000002, 0x00000000, 0x80000000, 0x80000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000004, 0x00000080,
+            0x00000004, 0x00000009, 0x00000101, 0x00000009, 0x00000012, 0x00000202, 0x0000001a, 0x00000124,
+            0x0000040c, 0x00000026, 0x0000004a, 0x0000080a, 0x00000060, 0x00000590, 0x00001020, 0x0000039a,
+        ],
+    },
+    // DV_I_48_0
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0xb800000a, 0xc8000010, 0x2c000010, 0xf4000014, 0xb4000008, 0x08000000, 0x9800000c, 0xd8000010,
+            0x08000010, 0xb8000010, 0x98000000, 0x60000000, 0x00000008, 0xc0000000, 0x90000014, 0x10000010,
+            0xb8000014, 0x28000000, 0x20000010, 0x48000000, 0x08000018, 0x60000000, 0x90000010, 0xf0000010,
+            0x90000008, 0xc0000000, 0x90000010, 0xf0000010, 0xb0000008, 0x40000000, 0x90000000, 0xf0000010,
+            0x90000018, 0x60000000, 0x90000010, 0x90000010, 0x90000000, 0x80000000, 0x00000010, 0xa0000000,
+            0x20000000, 0xa0000000, 0x20000010, 0x00000000, 0x20000010, 0x20000000, 0x00000010, 0x20000000,
+            0x00000010, 0xa0000000, 0x00000000, 0x20000000, 0x20000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000001,
+            0x00000020, 0x00000001, 0x40000002, 0x40000040, 0x40000002, 0x80000004, 0x80000080, 0x80000006,
+            0x00000049, 0x00000103, 0x80000009, 0x80000012, 0x80000202, 0x00000018, 0x00000164, 0x00000408,
+        ],
+    },
+    // DV_I_48_2
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0xe000002a, 0x20000043, 0xb0000040, 0xd0000053, 0xd0000022, 0x20000000, 0x60000032, 0x60000043,
+            0x20000040, 0xe0000042, 0x60000002, 0x80000001, 0x00000020, 0x00000003, 0x40000052, 0x40000040,
+            0xe0000052, 0xa0000000, 0x80000040, 0x20000001, 0x20000060, 0x80000001, 0x40000042, 0xc0000043,
+            0x40000022, 0x00000003, 0x40000042, 0xc0000043, 0xc0000022, 0x00000001, 0x40000002, 0xc0000043,
+            0x40000062, 0x80000001, 0x40000042, 0x40000042, 0x40000002, 0x00000002, 0x00000040, 0x80000002,
+            0x80000000, 0x80000002, 0x80000040, 0x00000000, 0x80000040, 0x80000000, 0x00000040, 0x80000000,
+            0x00000040, 0x80000002, 0x00000000, 0x80000000, 0x80000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000004,
+            0x00000080, 0x00000004, 0x00000009, 0x00000101, 0x00000009, 0x00000012, 0x00000202, 0x0000001a,
+            0x00000124, 0x0000040c, 0x00000026, 0x0000004a, 0x0000080a, 0x00000060, 0x00000590, 0x00001020,
+        ],
+    },
+    // DV_I_49_0
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0x18000000, 0xb800000a, 0xc8000010, 0x2c000010, 0xf4000014, 0xb4000008, 0x08000000, 0x9800000c,
+            0xd8000010, 0x08000010, 0xb8000010, 0x98000000, 0x60000000, 0x00000008, 0xc0000000, 0x90000014,
+            0x10000010, 0xb8000014, 0x28000000, 0x20000010, 0x48000000, 0x08000018, 0x60000000, 0x90000010,
+            0xf0000010, 0x90000008, 0xc0000000, 0x90000010, 0xf0000010, 0xb0000008, 0x40000000, 0x90000000,
+            0xf0000010, 0x90000018, 0x60000000, 0x90000010, 0x90000010, 0x90000000, 0x80000000, 0x00000010,
+            0xa0000000, 0x20000000, 0xa0000000, 0x20000010, 0x00000000, 0x20000010, 0x20000000, 0x00000010,
+            0x20000000, 0x00000010, 0xa0000000, 0x00000000, 0x20000000, 0x20000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000001, 0x00000020, 0x00000001, 0x40000002, 0x40000040, 0x40000002, 0x80000004, 0x80000080,
+            0x80000006, 0x00000049, 0x00000103, 0x80000009, 0x80000012, 0x80000202, 0x00000018, 0x00000164,
+        ],
+    },
+    // DV_I_49_2
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0x60000000, 0xe000002a, 0x20000043, 0xb0000040, 0xd0000053, 0xd0000022, 0x20000000, 0x60000032,
+            0x60000043, 0x20000040, 0xe0000042, 0x60000002, 0x80000001, 0x00000020, 0x00000003, 0x40000052,
+            0x40000040, 0xe0000052, 0xa0000000, 0x80000040, 0x20000001, 0x20000060, 0x80000001, 0x40000042,
+            0xc0000043, 0x40000022, 0x00000003, 0x40000042, 0xc0000043, 0xc0000022, 0x00000001, 0x40000002,
+            0xc0000043, 0x40000062, 0x80000001, 0x40000042, 0x40000042, 0x40000002, 0x00000002, 0x00000040,
+            0x80000002, 0x80000000, 0x80000002, 0x80000040, 0x00000000, 0x80000040, 0x80000000, 0x00000040,
+            0x80000000, 0x00000040, 0x80000002, 0x00000000, 0x80000000, 0x80000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000004, 0x00000080, 0x00000004, 0x00000009, 0x00000101, 0x00000009, 0x00000012, 0x00000202,
+            0x0000001a, 0x00000124, 0x0000040c, 0x00000026, 0x0000004a, 0x0000080a, 0x00000060, 0x00000590,
+        ],
+    },
+    // DV_I_50_0
+    DisturbanceVector {
+        testt: 65,
+        dm: [
+            0x0800000c, 0x18000000, 0xb800000a, 0xc8000010, 0x2c000010, 0xf4000014, 0xb4000008, 0x08000000,
+            0x9800000c, 0xd8000010, 0x08000010, 0xb8000010, 0x98000000, 0x60000000, 0x00000008, 0xc0000000,
+            0x90000014, 0x10000010, 0xb8000014, 0x28000000, 0x20000010, 0x48000000, 0x08000018, 0x60000000,
+            0x90000010, 0xf0000010, 0x90000008, 0xc0000000, 0x90000010, 0xf0000010, 0xb0000008, 0x40000000,
+            0x90000000, 0xf0000010, 0x90000018, 0x60000000, 0x90000010, 0x90000010, 0x90000000, 0x80000000,
+            0x00000010, 0xa0000000, 0x20000000, 0xa0000000, 0x20000010, 0x00000000, 0x20000010, 0x20000000,
+            0x00000010, 0x20000000, 0x00000010, 0xa0000000, 0x00000000, 0x20000000, 0x20000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000001, 0x00000020, 0x00000001, 0x40000002, 0x40000040, 0x40000002, 0x80000004,
+            0x80000080, 0x80000006, 0x00000049, 0x00000103, 0x80000009, 0x80000012, 0x80000202, 0x00000018,
+        ],
+    },
+    // DV_I_50_2
+    DisturbanceVector {
+        testt: 65,
+        dm: [
+            0x20000030, 0x60000000, 0xe000002a, 0x20000043, 0xb0000040, 0xd0000053, 0xd0000022, 0x20000000,
+            0x60000032, 0x60000043, 0x20000040, 0xe0000042, 0x60000002, 0x80000001, 0x00000020, 0x00000003,
+            0x40000052, 0x40000040, 0xe0000052, 0xa0000000, 0x80000040, 0x20000001, 0x20000060, 0x80000001,
+            0x40000042, 0xc0000043, 0x40000022, 0x00000003, 0x40000042, 0xc0000043, 0xc0000022, 0x00000001,
+            0x40000002, 0xc0000043, 0x40000062, 0x80000001, 0x40000042, 0x40000042, 0x40000002, 0x00000002,
+            0x00000040, 0x80000002, 0x80000000, 0x80000002, 0x80000040, 0x00000000, 0x80000040, 0x80000000,
+            0x00000040, 0x80000000, 0x00000040, 0x80000002, 0x00000000, 0x80000000, 0x80000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000004, 0x00000080, 0x00000004, 0x00000009, 0x00000101, 0x00000009, 0x00000012,
+            0x00000202, 0x0000001a, 0x00000124, 0x0000040c, 0x00000026, 0x0000004a, 0x0000080a, 0x00000060,
+        ],
+    },
+    // DV_I_51_0
+    DisturbanceVector {
+        testt: 65,
+        dm: [
+            0xe8000000, 0x0800000c, 0x18000000, 0xb800000a, 0xc8000010, 0x2c000010, 0xf4000014, 0xb4000008,
+            0x08000000, 0x9800000c, 0xd8000010, 0x08000010, 0xb8000010, 0x98000000, 0x60000000, 0x00000008,
+            0xc0000000, 0x90000014, 0x10000010, 0xb8000014, 0x28000000, 0x20000010, 0x48000000, 0x08000018,
+            0x60000000, 0x90000010, 0xf0000010, 0x90000008, 0xc0000000, 0x90000010, 0xf0000010, 0xb0000008,
+            0x40000000, 0x90000000, 0xf0000010, 0x90000018, 0x60000000, 0x90000010, 0x90000010, 0x90000000,
+            0x80000000, 0x00000010, 0xa0000000, 0x20000000, 0xa0000000, 0x20000010, 0x00000000, 0x20000010,
+            0x20000000, 0x00000010, 0x20000000, 0x00000010, 0xa0000000, 0x00000000, 0x20000000, 0x20000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000001, 0x00000020, 0x00000001, 0x40000002, 0x40000040, 0x40000002,
+            0x80000004, 0x80000080, 0x80000006, 0x00000049, 0x00000103, 0x80000009, 0x80000012, 0x80000202,
+        ],
+    },
+    // DV_I_51_2
+    DisturbanceVector {
+        testt: 65,
+        dm: [
+            0xa0000003, 0x20000030, 0x60000000, 0xe000002a, 0x20000043, 0xb0000040, 0xd0000053, 0xd0000022,
+            0x20000000, 0x60000032, 0x60000043, 0x20000040, 0xe0000042, 0x60000002, 0x80000001, 0x00000020,
+            0x00000003, 0x40000052, 0x40000040, 0xe0000052, 0xa0000000, 0x80000040, 0x20000001, 0x20000060,
+            0x80000001, 0x40000042, 0xc0000043, 0x40000022, 0x00000003, 0x40000042, 0xc0000043, 0xc0000022,
+            0x00000001, 0x40000002, 0xc0000043, 0x40000062, 0x80000001, 0x40000042, 0x40000042, 0x40000002,
+            0x00000002, 0x00000040, 0x80000002, 0x80000000, 0x80000002, 0x80000040, 0x00000000, 0x80000040,
+            0x80000000, 0x00000040, 0x80000000, 0x00000040, 0x80000002, 0x00000000, 0x80000000, 0x80000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000004, 0x00000080, 0x00000004, 0x00000009, 0x00000101, 0x00000009,
+            0x00000012, 0x00000202, 0x0000001a, 0x00000124, 0x0000040c, 0x00000026, 0x0000004a, 0x0000080a,
+        ],
+    },
+    // DV_I_52_0
+    DisturbanceVector {
+        testt: 65,
+        dm: [
+            0x04000010, 0xe8000000, 0x0800000c, 0x18000000, 0xb800000a, 0xc8000010, 0x2c000010, 0xf4000014,
+            0xb4000008, 0x08000000, 0x9800000c, 0xd8000010, 0x08000010, 0xb8000010, 0x98000000, 0x60000000,
+            0x00000008, 0xc0000000, 0x90000014, 0x10000010, 0xb8000014, 0x28000000, 0x20000010, 0x48000000,
+            0x08000018, 0x60000000, 0x90000010, 0xf0000010, 0x90000008, 0xc0000000, 0x90000010, 0xf0000010,
+            0xb0000008, 0x40000000, 0x90000000, 0xf0000010, 0x90000018, 0x60000000, 0x90000010, 0x90000010,
+            0x90000000, 0x80000000, 0x00000010, 0xa0000000, 0x20000000, 0xa0000000, 0x20000010, 0x00000000,
+            0x20000010, 0x20000000, 0x00000010, 0x20000000, 0x00000010, 0xa0000000, 0x00000000, 0x20000000,
+            0x20000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000001, 0x00000020, 0x00000001, 0x40000002, 0x40000040,
+            0x40000002, 0x80000004, 0x80000080, 0x80000006, 0x00000049, 0x00000103, 0x80000009, 0x80000012,
+        ],
+    },
+    // DV_II_45_0
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0xec000014, 0x0c000002, 0xc0000010, 0xb400001c, 0x2c000004, 0xbc000018, 0xb0000010, 0x0000000c,
+            0xb8000010, 0x08000018, 0x78000010, 0x08000014, 0x70000010, 0xb800001c, 0xe8000000, 0xb0000004,
+            0x58000010, 0xb000000c, 0x48000000, 0xb0000000, 0xb8000010, 0x98000010, 0xa0000000, 0x00000000,
+            0x00000000, 0x20000000, 0x80000000, 0x00000010, 0x00000000, 0x20000010, 0x20000000, 0x00000010,
+            0x60000000, 0x00000018, 0xe0000000, 0x90000000, 0x30000010, 0xb0000000, 0x20000000, 0x20000000,
+            0xa0000000, 0x00000010, 0x80000000, 0x20000000, 0x20000000, 0x20000000, 0x80000000, 0x00000010,
+            0x00000000, 0x20000010, 0xa0000000, 0x00000000, 0x20000000, 0x20000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000001, 0x00000020, 0x00000001, 0x40000002,
+            0x40000041, 0x40000022, 0x80000005, 0xc0000082, 0xc0000046, 0x4000004b, 0x80000107, 0x00000089,
+            0x00000014, 0x8000024b, 0x0000011b, 0x8000016d, 0x8000041a, 0x000002e4, 0x80000054, 0x00000967,
+        ],
+    },
+    // DV_II_46_0
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0x2400001c, 0xec000014, 0x0c000002, 0xc0000010, 0xb400001c, 0x2c000004, 0xbc000018, 0xb0000010,
+            0x0000000c, 0xb8000010, 0x08000018, 0x78000010, 0x08000014, 0x70000010, 0xb800001c, 0xe8000000,
+            0xb0000004, 0x58000010, 0xb000000c, 0x48000000, 0xb0000000, 0xb8000010, 0x98000010, 0xa0000000,
+            0x00000000, 0x00000000, 0x20000000, 0x80000000, 0x00000010, 0x00000000, 0x20000010, 0x20000000,
+            0x00000010, 0x60000000, 0x00000018, 0xe0000000, 0x90000000, 0x30000010, 0xb0000000, 0x20000000,
+            0x20000000, 0xa0000000, 0x00000010, 0x80000000, 0x20000000, 0x20000000, 0x20000000, 0x80000000,
+            0x00000010, 0x00000000, 0x20000010, 0xa0000000, 0x00000000, 0x20000000, 0x20000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000001, 0x00000020, 0x00000001,
+            0x40000002, 0x40000041, 0x40000022, 0x80000005, 0xc0000082, 0xc0000046, 0x4000004b, 0x80000107,
+            0x00000089, 0x00000014, 0x8000024b, 0x0000011b, 0x8000016d, 0x8000041a, 0x000002e4, 0x80000054,
+        ],
+    },
+    // DV_II_46_2
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0x90000070, 0xb0000053, 0x30000008, 0x00000043, 0xd0000072, 0xb0000010, 0xf0000062, 0xc0000042,
+            0x00000030, 0xe0000042, 0x20000060, 0xe0000041, 0x20000050, 0xc0000041, 0xe0000072, 0xa0000003,
+            0xc0000012, 0x60000041, 0xc0000032, 0x20000001, 0xc0000002, 0xe0000042, 0x60000042, 0x80000002,
+            0x00000000, 0x00000000, 0x80000000, 0x00000002, 0x00000040, 0x00000000, 0x80000040, 0x80000000,
+            0x00000040, 0x80000001, 0x00000060, 0x80000003, 0x40000002, 0xc0000040, 0xc0000002, 0x80000000,
+            0x80000000, 0x80000002, 0x00000040, 0x00000002, 0x80000000, 0x80000000, 0x80000000, 0x00000002,
+            0x00000040, 0x00000000, 0x80000040, 0x80000002, 0x00000000, 0x80000000, 0x80000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000004, 0x00000080, 0x00000004,
+            0x00000009, 0x00000105, 0x00000089, 0x00000016, 0x0000020b, 0x0000011b, 0x0000012d, 0x0000041e,
+            0x00000224, 0x00000050, 0x0000092e, 0x0000046c, 0x000005b6, 0x0000106a, 0x00000b90, 0x00000152,
+        ],
+    },
+    // DV_II_47_0
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0x20000010, 0x2400001c, 0xec000014, 0x0c000002, 0xc0000010, 0xb400001c, 0x2c000004, 0xbc000018,
+            0xb0000010, 0x0000000c, 0xb8000010, 0x08000018, 0x78000010, 0x08000014, 0x70000010, 0xb800001c,
+            0xe8000000, 0xb0000004, 0x58000010, 0xb000000c, 0x48000000, 0xb0000000, 0xb8000010, 0x98000010,
+            0xa0000000, 0x00000000, 0x00000000, 0x20000000, 0x80000000, 0x00000010, 0x00000000, 0x20000010,
+            0x20000000, 0x00000010, 0x60000000, 0x00000018, 0xe0000000, 0x90000000, 0x30000010, 0xb0000000,
+            0x20000000, 0x20000000, 0xa0000000, 0x00000010, 0x80000000, 0x20000000, 0x20000000, 0x20000000,
+            0x80000000, 0x00000010, 0x00000000, 0x20000010, 0xa0000000, 0x00000000, 0x20000000, 0x20000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000001, 0x00000020,
+            0x00000001, 0x40000002, 0x40000041, 0x40000022, 0x80000005, 0xc0000082, 0xc0000046, 0x4000004b,
+            0x80000107, 0x00000089, 0x00000014, 0x8000024b, 0x0000011b, 0x8000016d, 0x8000041a, 0x000002e4,
+        ],
+    },
+    // DV_II_48_0
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0xbc00001a, 0x20000010, 0x2400001c, 0xec000014, 0x0c000002, 0xc0000010, 0xb400001c, 0x2c000004,
+            0xbc000018, 0xb0000010, 0x0000000c, 0xb8000010, 0x08000018, 0x78000010, 0x08000014, 0x70000010,
+            0xb800001c, 0xe8000000, 0xb0000004, 0x58000010, 0xb000000c, 0x48000000, 0xb0000000, 0xb8000010,
+            0x98000010, 0xa0000000, 0x00000000, 0x00000000, 0x20000000, 0x80000000, 0x00000010, 0x00000000,
+            0x20000010, 0x20000000, 0x00000010, 0x60000000, 0x00000018, 0xe0000000, 0x90000000, 0x30000010,
+            0xb0000000, 0x20000000, 0x20000000, 0xa0000000, 0x00000010, 0x80000000, 0x20000000, 0x20000000,
+            0x20000000, 0x80000000, 0x00000010, 0x00000000, 0x20000010, 0xa0000000, 0x00000000, 0x20000000,
+            0x20000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000001,
+            0x00000020, 0x00000001, 0x40000002, 0x40000041, 0x40000022, 0x80000005, 0xc0000082, 0xc0000046,
+            0x4000004b, 0x80000107, 0x00000089, 0x00000014, 0x8000024b, 0x0000011b, 0x8000016d, 0x8000041a,
+        ],
+    },
+    // DV_II_49_0
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0x3c000004, 0xbc00001a, 0x20000010, 0x2400001c, 0xec000014, 0x0c000002, 0xc0000010, 0xb400001c,
+            0x2c000004, 0xbc000018, 0xb0000010, 0x0000000c, 0xb8000010, 0x08000018, 0x78000010, 0x08000014,
+            0x70000010, 0xb800001c, 0xe8000000, 0xb0000004, 0x58000010, 0xb000000c, 0x48000000, 0xb0000000,
+            0xb8000010, 0x98000010, 0xa0000000, 0x00000000, 0x00000000, 0x20000000, 0x80000000, 0x00000010,
+            0x00000000, 0x20000010, 0x20000000, 0x00000010, 0x60000000, 0x00000018, 0xe0000000, 0x90000000,
+            0x30000010, 0xb0000000, 0x20000000, 0x20000000, 0xa0000000, 0x00000010, 0x80000000, 0x20000000,
+            0x20000000, 0x20000000, 0x80000000, 0x00000010, 0x00000000, 0x20000010, 0xa0000000, 0x00000000,
+            0x20000000, 0x20000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000001, 0x00000020, 0x00000001, 0x40000002, 0x40000041, 0x40000022, 0x80000005, 0xc0000082,
+            0xc0000046, 0x4000004b, 0x80000107, 0x00000089, 0x00000014, 0x8000024b, 0x0000011b, 0x8000016d,
+        ],
+    },
+    // DV_II_49_2
+    DisturbanceVector {
+        testt: 58,
+        dm: [
+            0xf0000010, 0xf000006a, 0x80000040, 0x90000070, 0xb0000053, 0x30000008, 0x00000043, 0xd0000072,
+            0xb0000010, 0xf0000062, 0xc0000042, 0x00000030, 0xe0000042, 0x20000060, 0xe0000041, 0x20000050,
+            0xc0000041, 0xe0000072, 0xa0000003, 0xc0000012, 0x60000041, 0xc0000032, 0x20000001, 0xc0000002,
+            0xe0000042, 0x60000042, 0x80000002, 0x00000000, 0x00000000, 0x80000000, 0x00000002, 0x00000040,
+            0x00000000, 0x80000040, 0x80000000, 0x00000040, 0x80000001, 0x00000060, 0x80000003, 0x40000002,
+            0xc0000040, 0xc0000002, 0x80000000, 0x80000000, 0x80000002, 0x00000040, 0x00000002, 0x80000000,
+            0x80000000, 0x80000000, 0x00000002, 0x00000040, 0x00000000, 0x80000040, 0x80000002, 0x00000000,
+            0x80000000, 0x80000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000004, 0x00000080, 0x00000004, 0x00000009, 0x00000105, 0x00000089, 0x00000016, 0x0000020b,
+            0x0000011b, 0x0000012d, 0x0000041e, 0x00000224, 0x00000050, 0x0000092e, 0x0000046c, 0x000005b6,
+        ],
+    },
+    // DV_II_50_0
+    DisturbanceVector {
+        testt: 65,
+        dm: [
+            0xb400001c, 0x3c000004, 0xbc00001a, 0x20000010, 0x2400001c, 0xec000014, 0x0c000002, 0xc0000010,
+            0xb400001c, 0x2c000004, 0xbc000018, 0xb0000010, 0x0000000c, 0xb8000010, 0x08000018, 0x78000010,
+            0x08000014, 0x70000010, 0xb800001c, 0xe8000000, 0xb0000004, 0x58000010, 0xb000000c, 0x48000000,
+            0xb0000000, 0xb8000010, 0x98000010, 0xa0000000, 0x00000000, 0x00000000, 0x20000000, 0x80000000,
+            0x00000010, 0x00000000, 0x20000010, 0x20000000, 0x00000010, 0x60000000, 0x00000018, 0xe0000000,
+            0x90000000, 0x30000010, 0xb0000000, 0x20000000, 0x20000000, 0xa0000000, 0x00000010, 0x80000000,
+            0x20000000, 0x20000000, 0x20000000, 0x80000000, 0x00000010, 0x00000000, 0x20000010, 0xa0000000,
+            0x00000000, 0x20000000, 0x20000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000001, 0x00000020, 0x00000001, 0x40000002, 0x40000041, 0x40000022, 0x80000005,
+            0xc0000082, 0xc0000046, 0x4000004b, 0x80000107, 0x00000089, 0x00000014, 0x8000024b, 0x0000011b,
+        ],
+    },
+    // DV_II_50_2
+    DisturbanceVector {
+        testt: 65,
+        dm: [
+            0xd0000072, 0xf0000010, 0xf000006a, 0x80000040, 0x90000070, 0xb0000053, 0x30000008, 0x00000043,
+            0xd0000072, 0xb0000010, 0xf0000062, 0xc0000042, 0x00000030, 0xe0000042, 0x20000060, 0xe0000041,
+            0x20000050, 0xc0000041, 0xe0000072, 0xa0000003, 0xc0000012, 0x60000041, 0xc0000032, 0x20000001,
+            0xc0000002, 0xe0000042, 0x60000042, 0x80000002, 0x00000000, 0x00000000, 0x80000000, 0x00000002,
+            0x00000040, 0x00000000, 0x80000040, 0x80000000, 0x00000040, 0x80000001, 0x00000060, 0x80000003,
+            0x40000002, 0xc0000040, 0xc0000002, 0x80000000, 0x80000000, 0x80000002, 0x00000040, 0x00000002,
+            0x80000000, 0x80000000, 0x80000000, 0x00000002, 0x00000040, 0x00000000, 0x80000040, 0x80000002,
+            0x00000000, 0x80000000, 0x80000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000004, 0x00000080, 0x00000004, 0x00000009, 0x00000105, 0x00000089, 0x00000016,
+            0x0000020b, 0x0000011b, 0x0000012d, 0x0000041e, 0x00000224, 0x00000050, 0x0000092e, 0x0000046c,
+        ],
+    },
+    // DV_II_51_0
+    DisturbanceVector {
+        testt: 65,
+        dm: [
+            0xc0000010, 0xb400001c, 0x3c000004, 0xbc00001a, 0x20000010, 0x2400001c, 0xec000014, 0x0c000002,
+            0xc0000010, 0xb400001c, 0x2c000004, 0xbc000018, 0xb0000010, 0x0000000c, 0xb8000010, 0x08000018,
+            0x78000010, 0x08000014, 0x70000010, 0xb800001c, 0xe8000000, 0xb0000004, 0x58000010, 0xb000000c,
+            0x48000000, 0xb0000000, 0xb8000010, 0x98000010, 0xa0000000, 0x00000000, 0x00000000, 0x20000000,
+            0x80000000, 0x00000010, 0x00000000, 0x20000010, 0x20000000, 0x00000010, 0x60000000, 0x00000018,
+            0xe0000000, 0x90000000, 0x30000010, 0xb0000000, 0x20000000, 0x20000000, 0xa0000000, 0x00000010,
+            0x80000000, 0x20000000, 0x20000000, 0x20000000, 0x80000000, 0x00000010, 0x00000000, 0x20000010,
+            0xa0000000, 0x00000000, 0x20000000, 0x20000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000001, 0x00000020, 0x00000001, 0x40000002, 0x40000041, 0x40000022,
+            0x80000005, 0xc0000082, 0xc0000046, 0x4000004b, 0x80000107, 0x00000089, 0x00000014, 0x8000024b,
+        ],
+    },
+    // DV_II_51_2
+    DisturbanceVector {
+        testt: 65,
+        dm: [
+            0x00000043, 0xd0000072, 0xf0000010, 0xf000006a, 0x80000040, 0x90000070, 0xb0000053, 0x30000008,
+            0x00000043, 0xd0000072, 0xb0000010, 0xf0000062, 0xc0000042, 0x00000030, 0xe0000042, 0x20000060,
+            0xe0000041, 0x20000050, 0xc0000041, 0xe0000072, 0xa0000003, 0xc0000012, 0x60000041, 0xc0000032,
+            0x20000001, 0xc0000002, 0xe0000042, 0x60000042, 0x80000002, 0x00000000, 0x00000000, 0x80000000,
+            0x00000002, 0x00000040, 0x00000000, 0x80000040, 0x80000000, 0x00000040, 0x80000001, 0x00000060,
+            0x80000003, 0x40000002, 0xc0000040, 0xc0000002, 0x80000000, 0x80000000, 0x80000002, 0x00000040,
+            0x00000002, 0x80000000, 0x80000000, 0x80000000, 0x00000002, 0x00000040, 0x00000000, 0x80000040,
+            0x80000002, 0x00000000, 0x80000000, 0x80000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000004, 0x00000080, 0x00000004, 0x00000009, 0x00000105, 0x00000089,
+            0x00000016, 0x0000020b, 0x0000011b, 0x0000012d, 0x0000041e, 0x00000224, 0x00000050, 0x0000092e,
+        ],
+    },
+    // DV_II_52_0
+    DisturbanceVector {
+        testt: 65,
+        dm: [
+            0x0c000002, 0xc0000010, 0xb400001c, 0x3c000004, 0xbc00001a, 0x20000010, 0x2400001c, 0xec000014,
+            0x0c000002, 0xc0000010, 0xb400001c, 0x2c000004, 0xbc000018, 0xb0000010, 0x0000000c, 0xb8000010,
+            0x08000018, 0x78000010, 0x08000014, 0x70000010, 0xb800001c, 0xe8000000, 0xb0000004, 0x58000010,
+            0xb000000c, 0x48000000, 0xb0000000, 0xb8000010, 0x98000010, 0xa0000000, 0x00000000, 0x00000000,
+            0x20000000, 0x80000000, 0x00000010, 0x00000000, 0x20000010, 0x20000000, 0x00000010, 0x60000000,
+            0x00000018, 0xe0000000, 0x90000000, 0x30000010, 0xb0000000, 0x20000000, 0x20000000, 0xa0000000,
+            0x00000010, 0x80000000, 0x20000000, 0x20000000, 0x20000000, 0x80000000, 0x00000010, 0x00000000,
+            0x20000010, 0xa0000000, 0x00000000, 0x20000000, 0x20000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000001, 0x00000020, 0x00000001, 0x40000002, 0x40000041,
+            0x40000022, 0x80000005, 0xc0000082, 0xc0000046, 0x4000004b, 0x80000107, 0x00000089, 0x00000014,
+        ],
+    },
+    // DV_II_53_0
+    DisturbanceVector {
+        testt: 65,
+        dm: [
+            0xcc000014, 0x0c000002, 0xc0000010, 0xb400001c, 0x3c000004, 0xbc00001a, 0x20000010, 0x2400001c,
+            0xec000014, 0x0c000002, 0xc0000010, 0xb400001c, 0x2c000004, 0xbc000018, 0xb0000010, 0x0000000c,
+            0xb8000010, 0x08000018, 0x78000010, 0x08000014, 0x70000010, 0xb800001c, 0xe8000000, 0xb0000004,
+            0x58000010, 0xb000000c, 0x48000000, 0xb0000000, 0xb8000010, 0x98000010, 0xa0000000, 0x00000000,
+            0x00000000, 0x20000000, 0x80000000, 0x00000010, 0x00000000, 0x20000010, 0x20000000, 0x00000010,
+            0x60000000, 0x00000018, 0xe0000000, 0x90000000, 0x30000010, 0xb0000000, 0x20000000, 0x20000000,
+            0xa0000000, 0x00000010, 0x80000000, 0x20000000, 0x20000000, 0x20000000, 0x80000000, 0x00000010,
+            0x00000000, 0x20000010, 0xa0000000, 0x00000000, 0x20000000, 0x20000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000001, 0x00000020, 0x00000001, 0x40000002,
+            0x40000041, 0x40000022, 0x80000005, 0xc0000082, 0xc0000046, 0x4000004b, 0x80000107, 0x00000089,
+        ],
+    },
+    // DV_II_54_0
+    DisturbanceVector {
+        testt: 65,
+        dm: [
+            0x0400001c, 0xcc000014, 0x0c000002, 0xc0000010, 0xb400001c, 0x3c000004, 0xbc00001a, 0x20000010,
+            0x2400001c, 0xec000014, 0x0c000002, 0xc0000010, 0xb400001c, 0x2c000004, 0xbc000018, 0xb0000010,
+            0x0000000c, 0xb8000010, 0x08000018, 0x78000010, 0x08000014, 0x70000010, 0xb800001c, 0xe8000000,
+            0xb0000004, 0x58000010, 0xb000000c, 0x48000000, 0xb0000000, 0xb8000010, 0x98000010, 0xa0000000,
+            0x00000000, 0x00000000, 0x20000000, 0x80000000, 0x00000010, 0x00000000, 0x20000010, 0x20000000,
+            0x00000010, 0x60000000, 0x00000018, 0xe0000000, 0x90000000, 0x30000010, 0xb0000000, 0x20000000,
+            0x20000000, 0xa0000000, 0x00000010, 0x80000000, 0x20000000, 0x20000000, 0x20000000, 0x80000000,
+            0x00000010, 0x00000000, 0x20000010, 0xa0000000, 0x00000000, 0x20000000, 0x20000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000001, 0x00000020, 0x00000001,
+            0x40000002, 0x40000041, 0x40000022, 0x80000005, 0xc0000082, 0xc0000046, 0x4000004b, 0x80000107,
+        ],
+    },
+    // DV_II_55_0
+    DisturbanceVector {
+        testt: 65,
+        dm: [
+            0x00000010, 0x0400001c, 0xcc000014, 0x0c000002, 0xc0000010, 0xb400001c, 0x3c000004, 0xbc00001a,
+            0x20000010, 0x2400001c, 0xec000014, 0x0c000002, 0xc0000010, 0xb400001c, 0x2c000004, 0xbc000018,
+            0xb0000010, 0x0000000c, 0xb8000010, 0x08000018, 0x78000010, 0x08000014, 0x70000010, 0xb800001c,
+            0xe8000000, 0xb0000004, 0x58000010, 0xb000000c, 0x48000000, 0xb0000000, 0xb8000010, 0x98000010,
+            0xa0000000, 0x00000000, 0x00000000, 0x20000000, 0x80000000, 0x00000010, 0x00000000, 0x20000010,
+            0x20000000, 0x00000010, 0x60000000, 0x00000018, 0xe0000000, 0x90000000, 0x30000010, 0xb0000000,
+            0x20000000, 0x20000000, 0xa0000000, 0x00000010, 0x80000000, 0x20000000, 0x20000000, 0x20000000,
+            0x80000000, 0x00000010, 0x00000000, 0x20000010, 0xa0000000, 0x00000000, 0x20000000, 0x20000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000001, 0x00000020,
+            0x00000001, 0x40000002, 0x40000041, 0x40000022, 0x80000005, 0xc0000082, 0xc0000046, 0x4000004b,
+        ],
+    },
+    // DV_II_56_0
+    DisturbanceVector {
+        testt: 65,
+        dm: [
+            0x2600001a, 0x00000010, 0x0400001c, 0xcc000014, 0x0c000002, 0xc0000010, 0xb400001c, 0x3c000004,
+            0xbc00001a, 0x20000010, 0x2400001c, 0xec000014, 0x0c000002, 0xc0000010, 0xb400001c, 0x2c000004,
+            0xbc000018, 0xb0000010, 0x0000000c, 0xb8000010, 0x08000018, 0x78000010, 0x08000014, 0x70000010,
+            0xb800001c, 0xe8000000, 0xb0000004, 0x58000010, 0xb000000c, 0x48000000, 0xb0000000, 0xb8000010,
+            0x98000010, 0xa0000000, 0x00000000, 0x00000000, 0x20000000, 0x80000000, 0x00000010, 0x00000000,
+            0x20000010, 0x20000000, 0x00000010, 0x60000000, 0x00000018, 0xe0000000, 0x90000000, 0x30000010,
+            0xb0000000, 0x20000000, 0x20000000, 0xa0000000, 0x00000010, 0x80000000, 0x20000000, 0x20000000,
+            0x20000000, 0x80000000, 0x00000010, 0x00000000, 0x20000010, 0xa0000000, 0x00000000, 0x20000000,
+            0x20000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000001,
+            0x00000020, 0x00000001, 0x40000002, 0x40000041, 0x40000022, 0x80000005, 0xc0000082, 0xc0000046,
+        ],
+    },
+];
@@ -10,13 +10,13 @@ mod _impl {
         sync::atomic::{AtomicUsize, Ordering},
     };
 
+    /// Install a handler for SIGINT and SIGTERM which requests an interrupt, or, if an interrupt was requested
+    /// already, removes all [temporary files][crate::tempfile] and lock files and terminates the process.
     pub fn init_interrupt_handler(mut message_channel: impl io::Write + Send + 'static) {
         ctrlc::set_handler(move || {
             const MESSAGES: &[&str] = &[
                 "interrupt requested", 
-                "please wait…", 
-                "the program will respond soon…", 
-                "if the program doesn't respond quickly enough, please let us know here: https://github.com/Byron/gitoxide/issues"
+                "interrupt requested again, removing temporary files and aborting…", 
             ];
             static CURRENT_MESSAGE: AtomicUsize = AtomicUsize::new(0);
             if !super::is_interrupted() {
@@ -24,7 +24,11 @@ mod _impl {
             }
             let msg_idx =CURRENT_MESSAGE.fetch_add(1, Ordering::Relaxed);
             super::IS_INTERRUPTED.store(true, Ordering::Relaxed);
-            writeln!(message_channel, "{}", MESSAGES[msg_idx % MESSAGES.len()]).ok();
+            writeln!(message_channel, "{}", MESSAGES[msg_idx.min(MESSAGES.len() - 1)]).ok();
+            if msg_idx > 0 {
+                crate::tempfile::cleanup();
+                std::process::exit(130);
+            }
         })
         .expect("it is up to the application to ensure only one interrupt handler is installed, and this function is called only once.")
     }
@@ -2,5 +2,9 @@
 
 pub mod hash;
 pub mod interruptible;
+pub mod lock;
 pub mod parallel;
+pub mod path;
 pub mod progress;
+pub mod tempfile;
+pub mod trace;
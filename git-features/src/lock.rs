@@ -0,0 +1,286 @@
+//! Lock files like git uses them: `<path>.lock` is created exclusively and either renamed over `<path>` to change it
+//! atomically, or removed to leave it unchanged.
+//!
+//! Lock files are removed when dropped without being committed. All lock files held by the process are known, so they
+//! can be [removed][release_all()] before it is terminated by a signal.
+//!
+//! Locks left behind by crashed processes can be detected as stale if they are older than
+//! [`Options::stale_after`], or, for [markers][Marker], if the process on this machine which created them doesn't exist
+//! anymore. Stale locks are moved out of the way before they are removed, so a lock another process created in their
+//! place after removing them as well is never removed.
+use quick_error::quick_error;
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Locked(path: PathBuf) {
+            display("The lock at '{}' is held, another process might be changing the file", path.display())
+        }
+        Io(err: io::Error, path: PathBuf) {
+            display("The lock at '{}' could not be created or committed", path.display())
+            source(err)
+        }
+    }
+}
+
+/// What to do if the lock is held already.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy, Default)]
+pub enum Fail {
+    /// Fail right away.
+    #[default]
+    Immediately,
+    /// Try again after waiting longer each time, and fail once the given time passed.
+    AfterDurationWithBackoff(Duration),
+}
+
+/// Configure how to acquire a lock.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy, Default)]
+pub struct Options {
+    /// What to do if the lock is held already.
+    pub fail: Fail,
+    /// If set, locks last changed longer ago than this are considered left behind and removed.
+    pub stale_after: Option<Duration>,
+}
+
+static HELD: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+const MAX_BACKOFF: Duration = Duration::from_millis(250);
+const PID_PREFIX: &str = "pid ";
+
+/// A lock file which is written and then [committed][File::commit()] to replace the locked file.
+pub struct File {
+    file: Option<fs::File>,
+    lock_path: PathBuf,
+    resource_path: PathBuf,
+}
+
+impl File {
+    /// Lock `resource_path` by creating its lock file, along with all directories leading to it.
+    pub fn acquire(resource_path: impl Into<PathBuf>, options: Options) -> Result<File, Error> {
+        let resource_path = resource_path.into();
+        let lock_path = lock_path(&resource_path);
+        let file = create(&lock_path, options)?;
+        Ok(File {
+            file: Some(file),
+            lock_path,
+            resource_path,
+        })
+    }
+
+    /// The path of the lock file.
+    pub fn lock_path(&self) -> &Path {
+        &self.lock_path
+    }
+
+    /// The path of the locked file, which is replaced on commit.
+    pub fn resource_path(&self) -> &Path {
+        &self.resource_path
+    }
+
+    /// Move the lock file with everything written to it over the locked file and return its path, or remove the lock
+    /// file if that fails.
+    pub fn commit(mut self) -> Result<PathBuf, Error> {
+        drop(self.file.take());
+        let res = fs::rename(&self.lock_path, &self.resource_path);
+        if res.is_err() {
+            fs::remove_file(&self.lock_path).ok();
+        }
+        unregister(&self.lock_path);
+        res.map(|_| std::mem::take(&mut self.resource_path))
+            .map_err(|err| Error::Io(err, self.resource_path.clone()))
+    }
+}
+
+impl io::Write for File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.as_mut().expect("present until commit").write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.as_mut().expect("present until commit").flush()
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            fs::remove_file(&self.lock_path).ok();
+            unregister(&self.lock_path);
+        }
+    }
+}
+
+/// A lock which isn't meant to replace the locked file, but to keep other processes from changing it or doing the same
+/// work at the same time. Like `gc.pid`, it records the id of the process holding it along with the name of the machine
+/// it runs on, so it's stale once that process is gone.
+pub struct Marker {
+    lock_path: PathBuf,
+}
+
+impl Marker {
+    /// Lock `resource_path` by creating its lock file, along with all directories leading to it.
+    pub fn acquire(resource_path: impl AsRef<Path>, options: Options) -> Result<Marker, Error> {
+        let lock_path = lock_path(resource_path.as_ref());
+        let mut file = create(&lock_path, options)?;
+        let marker = Marker { lock_path };
+        match hostname() {
+            Some(hostname) => writeln!(file, "{}{} {}", PID_PREFIX, std::process::id(), hostname),
+            None => writeln!(file, "{}{}", PID_PREFIX, std::process::id()),
+        }
+        .map_err(|err| Error::Io(err, marker.lock_path.clone()))?;
+        Ok(marker)
+    }
+
+    /// The path of the lock file.
+    pub fn lock_path(&self) -> &Path {
+        &self.lock_path
+    }
+}
+
+impl Drop for Marker {
+    fn drop(&mut self) {
+        fs::remove_file(&self.lock_path).ok();
+        unregister(&self.lock_path);
+    }
+}
+
+/// Remove all lock files held by this process and return how many there were. It's meant to be called when the process
+/// is about to be terminated, as locks held afterwards can't be committed anymore.
+pub fn release_all() -> usize {
+    let mut held = HELD.lock().unwrap_or_else(|err| err.into_inner());
+    let num_held = held.len();
+    for path in std::mem::take(&mut *held) {
+        fs::remove_file(path).ok();
+    }
+    num_held
+}
+
+fn lock_path(resource_path: &Path) -> PathBuf {
+    let mut lock_path = resource_path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    PathBuf::from(lock_path)
+}
+
+fn create(lock_path: &Path, options: Options) -> Result<fs::File, Error> {
+    if let Some(directory) = lock_path.parent() {
+        fs::create_dir_all(directory).map_err(|err| Error::Io(err, directory.to_owned()))?;
+    }
+    let start = Instant::now();
+    let mut backoff = Duration::from_millis(1);
+    loop {
+        match fs::OpenOptions::new().write(true).create_new(true).open(lock_path) {
+            Ok(file) => {
+                HELD.lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .insert(lock_path.to_owned());
+                return Ok(file);
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                if is_stale(lock_path, options) && remove_stale(lock_path, options) {
+                    continue;
+                }
+                let remaining = match options.fail {
+                    Fail::Immediately => Duration::default(),
+                    Fail::AfterDurationWithBackoff(timeout) => timeout.checked_sub(start.elapsed()).unwrap_or_default(),
+                };
+                if remaining == Duration::default() {
+                    return Err(Error::Locked(lock_path.to_owned()));
+                }
+                std::thread::sleep(backoff.min(remaining));
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+            Err(err) => return Err(Error::Io(err, lock_path.to_owned())),
+        }
+    }
+}
+
+fn is_stale(lock_path: &Path, options: Options) -> bool {
+    let is_old = match (options.stale_after, fs::metadata(lock_path).and_then(|m| m.modified())) {
+        (Some(stale_after), Ok(modified)) => SystemTime::now()
+            .duration_since(modified)
+            .map(|age| age > stale_after)
+            .unwrap_or(false),
+        _ => false,
+    };
+    is_old
+        || fs::read_to_string(lock_path)
+            .ok()
+            .and_then(|content| {
+                let mut tokens = content.strip_prefix(PID_PREFIX)?.split_whitespace();
+                let pid = tokens.next()?.parse::<u32>().ok()?;
+                // Processes of other machines can't be seen, and markers without hostname were made on this one.
+                let is_local = match tokens.next() {
+                    Some(name) => hostname().is_some_and(|hostname| hostname == name),
+                    None => true,
+                };
+                Some(is_local && !process_exists(pid))
+            })
+            .unwrap_or(false)
+}
+
+/// Remove the stale lock at `lock_path` and return true if it's worth trying to acquire it again.
+///
+/// Another process may have found it stale as well, removed it and created its own lock since we checked, so it's
+/// moved to a name unique to this process first and only removed if it's still stale there. Otherwise it's moved back
+/// unless there is another lock already.
+fn remove_stale(lock_path: &Path, options: Options) -> bool {
+    static MOVED: AtomicUsize = AtomicUsize::new(0);
+    let mut moved_path = lock_path.as_os_str().to_owned();
+    moved_path.push(format!(
+        ".stale-{}-{}",
+        std::process::id(),
+        MOVED.fetch_add(1, Ordering::Relaxed)
+    ));
+    let moved_path = PathBuf::from(moved_path);
+    if fs::rename(lock_path, &moved_path).is_err() {
+        return false;
+    }
+    if !is_stale(&moved_path, options) {
+        fs::hard_link(&moved_path, lock_path).ok();
+    }
+    fs::remove_file(&moved_path).ok();
+    true
+}
+
+/// The name of this machine, to tell markers of its processes from the ones of other machines sharing the repository.
+#[cfg(target_os = "linux")]
+fn hostname() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .map(|name| name.trim_end().to_owned())
+        .filter(|name| !name.is_empty())
+}
+
+/// The name of this machine, to tell markers of its processes from the ones of other machines sharing the repository.
+#[cfg(not(target_os = "linux"))]
+fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+}
+
+#[cfg(target_os = "linux")]
+fn process_exists(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+/// Without a way of knowing, processes are assumed to exist to avoid removing locks which are still held.
+#[cfg(not(target_os = "linux"))]
+fn process_exists(_pid: u32) -> bool {
+    true
+}
+
+fn unregister(lock_path: &Path) {
+    HELD.lock().unwrap_or_else(|err| err.into_inner()).remove(lock_path);
+}
@@ -0,0 +1,61 @@
+/// The amount of chunks each thread should receive at least, which allows idle threads to pick up remaining work.
+const CHUNKS_PER_THREAD: usize = 4;
+
+/// An iterator adapter yielding the items of an iterator in chunks, which get smaller the fewer items remain.
+///
+/// It's made to produce the input of [`in_parallel()`][super::in_parallel()], which hands out chunks to threads as they
+/// become idle. Large chunks at the beginning keep the overhead of passing them around low, while small chunks at the
+/// end balance the load if items are very uneven in cost, so no thread is left working on a long tail while all
+/// others are idle.
+///
+/// The amount of remaining items is taken from the upper bound of the iterator's `size_hint()`, or its lower bound
+/// if there is none, and all chunks have the maximum size if both are unknown.
+pub struct DynamicChunks<I> {
+    iter: I,
+    num_threads: usize,
+    max_chunk_size: usize,
+}
+
+impl<I> DynamicChunks<I>
+where
+    I: Iterator,
+{
+    /// Create a new instance to produce chunks of items from `iter` for use by at most `thread_limit` threads,
+    /// with each chunk containing no more than `max_chunk_size` items.
+    pub fn new(iter: I, thread_limit: Option<usize>, max_chunk_size: usize) -> Self {
+        assert!(max_chunk_size > 0, "non-zero chunk size is needed");
+        DynamicChunks {
+            iter,
+            num_threads: super::num_threads(thread_limit),
+            max_chunk_size,
+        }
+    }
+
+    fn chunk_size(&self) -> usize {
+        let remaining = match self.iter.size_hint() {
+            (_, Some(upper)) => upper,
+            (0, None) => return self.max_chunk_size,
+            (lower, None) => lower,
+        };
+        (remaining / (self.num_threads * CHUNKS_PER_THREAD))
+            .max(1)
+            .min(self.max_chunk_size)
+    }
+}
+
+impl<I> Iterator for DynamicChunks<I>
+where
+    I: Iterator,
+{
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk_size = self.chunk_size();
+        let chunk: Vec<_> = self.iter.by_ref().take(chunk_size).collect();
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(chunk)
+        }
+    }
+}
@@ -74,8 +74,10 @@ where
     I: Iterator + Send + 'static,
     <I as Iterator>::Item: Send,
 {
+    /// Produce items of `iter` on another thread if `condition` is true, or on demand otherwise, which is always the
+    /// case without the `parallel` feature to not require threads.
     pub fn new(condition: impl FnOnce() -> bool, iter: I, chunk_size: usize, chunks_in_flight: usize) -> Self {
-        if condition() {
+        if cfg!(feature = "parallel") && condition() {
             EagerIterIf::Eager(EagerIter::new(iter, chunk_size, chunks_in_flight))
         } else {
             EagerIterIf::OnDemand(iter)
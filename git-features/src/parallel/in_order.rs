@@ -0,0 +1,83 @@
+use crate::parallel::{in_parallel_if, Reducer};
+use std::collections::BTreeMap;
+
+/// A reducer adapter to feed the results of [`in_parallel_in_order_if()`] to the contained reducer in the order of
+/// their input, each as soon as all results of the inputs before it were fed.
+///
+/// Results arriving early are kept until it's their turn, which is why the amount of memory used depends on how
+/// uneven the cost of processing inputs is.
+pub struct InOrder<R: Reducer> {
+    inner: R,
+    next_index: usize,
+    pending: BTreeMap<usize, R::Input>,
+}
+
+impl<R> InOrder<R>
+where
+    R: Reducer,
+{
+    /// Create a new instance to feed `inner` in order.
+    pub fn new(inner: R) -> Self {
+        InOrder {
+            inner,
+            next_index: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+}
+
+impl<R> Reducer for InOrder<R>
+where
+    R: Reducer,
+{
+    type Input = (usize, R::Input);
+    type Output = R::Output;
+    type Error = R::Error;
+
+    fn feed(&mut self, (index, input): Self::Input) -> Result<(), Self::Error> {
+        if index != self.next_index {
+            self.pending.insert(index, input);
+            return Ok(());
+        }
+        self.inner.feed(input)?;
+        self.next_index += 1;
+        while let Some(input) = self.pending.remove(&self.next_index) {
+            self.inner.feed(input)?;
+            self.next_index += 1;
+        }
+        Ok(())
+    }
+
+    fn finalize(self) -> Result<Self::Output, Self::Error> {
+        assert!(
+            self.pending.is_empty(),
+            "BUG: all results must have been fed in order before finalizing"
+        );
+        self.inner.finalize()
+    }
+}
+
+/// Like [`in_parallel_if()`], but `reducer` receives the results of `consume` in the order of their `input`, streamed
+/// to it as they become available instead of after all processing is done.
+pub fn in_parallel_in_order_if<I, S, O, R>(
+    condition: impl FnOnce() -> bool,
+    input: impl Iterator<Item = I> + Send,
+    thread_limit: Option<usize>,
+    new_thread_state: impl Fn(usize) -> S + Send + Sync,
+    consume: impl Fn(I, &mut S) -> O + Send + Sync,
+    reducer: R,
+) -> Result<<R as Reducer>::Output, <R as Reducer>::Error>
+where
+    R: Reducer<Input = O>,
+    I: Send,
+    O: Send,
+{
+    in_parallel_if(
+        condition,
+        input.enumerate(),
+        thread_limit,
+        new_thread_state,
+        |(index, item), state| (index, consume(item, state)),
+        InOrder::new(reducer),
+    )
+}
@@ -11,6 +11,12 @@ pub use in_parallel::*;
 mod eager;
 pub use eager::{EagerIter, EagerIterIf};
 
+mod dynamic_chunks;
+pub use dynamic_chunks::DynamicChunks;
+
+mod in_order;
+pub use in_order::{in_parallel_in_order_if, InOrder};
+
 #[cfg(not(feature = "parallel"))]
 pub fn optimize_chunk_size_and_thread_limit(
     desired_chunk_size: usize,
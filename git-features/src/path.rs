@@ -0,0 +1,236 @@
+//! Turning paths of tree entries and names of refs into paths on disk, without letting them escape the directory
+//! they are meant for or alias files git cares about.
+//!
+//! Like git with `core.protectNTFS`, names which NTFS treats as `.git`, like `git~1` or `.git.`, are rejected on all
+//! platforms by default so repositories remain safe to check out on Windows. On Windows, backslashes, reserved device
+//! names like `CON` or `aux.txt` and characters the filesystem doesn't allow are rejected as well.
+//!
+//! Paths on disk which exceed `MAX_PATH` on Windows can be turned into extended-length paths starting with `\\?\`,
+//! which Windows accepts up to about 32767 characters.
+use quick_error::quick_error;
+use std::path::{Path, PathBuf};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Empty {
+            display("A path must not have empty components")
+        }
+        Relative(component: String) {
+            display("The path component '{}' refers to the current or the parent directory", component)
+        }
+        Separator(component: String) {
+            display("The path component '{}' contains a directory separator", component)
+        }
+        DotGit(component: String) {
+            display("The path component '{}' would be treated as '.git' directory", component)
+        }
+        Reserved(component: String) {
+            display("The path component '{}' is a reserved device name on Windows", component)
+        }
+        InvalidByte(component: String) {
+            display("The path component '{}' contains characters which aren't allowed on Windows", component)
+        }
+        TrailingDotOrSpace(component: String) {
+            display("The path component '{}' would lose its trailing dots or spaces on Windows", component)
+        }
+        IllformedUtf8(component: String) {
+            display("The path component '{}' is not valid UTF-8", component)
+        }
+    }
+}
+
+/// The checks to perform in addition to the ones made on all platforms.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub struct Protections {
+    /// Reject names which NTFS treats like `.git`, similar to `core.protectNTFS`.
+    pub ntfs: bool,
+    /// Reject names which can't be represented on Windows or refer to devices there.
+    pub windows: bool,
+}
+
+impl Default for Protections {
+    fn default() -> Self {
+        Protections {
+            ntfs: true,
+            windows: cfg!(windows),
+        }
+    }
+}
+
+const RESERVED_NAMES: &[&[u8]] = &[b"con", b"prn", b"aux", b"nul"];
+const RESERVED_NUMBERED_NAMES: &[&[u8]] = &[b"com", b"lpt"];
+
+/// Return an error if `component`, a single file or directory name, isn't safe to use on disk.
+pub fn verify_component(component: &[u8], protections: Protections) -> Result<(), Error> {
+    let err = || String::from_utf8_lossy(component).into_owned();
+    if component.is_empty() {
+        return Err(Error::Empty);
+    }
+    if component == b"." || component == b".." {
+        return Err(Error::Relative(err()));
+    }
+    if component.contains(&b'/') || (protections.windows && component.contains(&b'\\')) {
+        return Err(Error::Separator(err()));
+    }
+    if component.eq_ignore_ascii_case(b".git") || (protections.ntfs && is_ntfs_dot_git(component)) {
+        return Err(Error::DotGit(err()));
+    }
+    if protections.windows {
+        if component
+            .iter()
+            .any(|b| matches!(b, b'<' | b'>' | b':' | b'"' | b'|' | b'?' | b'*' | b'\0'..=b'\x1F'))
+        {
+            return Err(Error::InvalidByte(err()));
+        }
+        if matches!(component.last(), Some(b' ') | Some(b'.')) {
+            return Err(Error::TrailingDotOrSpace(err()));
+        }
+        if is_reserved_name(component) {
+            return Err(Error::Reserved(err()));
+        }
+    }
+    Ok(())
+}
+
+/// Turn the relative, slash-separated `path` into a path on disk after [verifying][verify_component()] each of its
+/// components.
+pub fn to_native(path: &[u8], protections: Protections) -> Result<PathBuf, Error> {
+    let mut native = PathBuf::new();
+    for component in path.split(|b| *b == b'/') {
+        verify_component(component, protections)?;
+        native.push(component_to_os_str(component)?);
+    }
+    Ok(native)
+}
+
+#[cfg(unix)]
+fn component_to_os_str(component: &[u8]) -> Result<&std::ffi::OsStr, Error> {
+    use std::os::unix::ffi::OsStrExt;
+    Ok(std::ffi::OsStr::from_bytes(component))
+}
+
+#[cfg(not(unix))]
+fn component_to_os_str(component: &[u8]) -> Result<&std::ffi::OsStr, Error> {
+    std::str::from_utf8(component)
+        .map(AsRef::as_ref)
+        .map_err(|_| Error::IllformedUtf8(String::from_utf8_lossy(component).into_owned()))
+}
+
+/// NTFS ignores trailing dots and spaces as well as alternate data streams, and may use `git~1` as short name of
+/// `.git`.
+fn is_ntfs_dot_git(component: &[u8]) -> bool {
+    let name = match component.iter().position(|b| *b == b':') {
+        Some(pos) => &component[..pos],
+        None => component,
+    };
+    let name = trim_dots_and_spaces(name);
+    name.eq_ignore_ascii_case(b".git") || name.eq_ignore_ascii_case(b"git~1")
+}
+
+/// Device names are reserved no matter the extension, and with trailing spaces before it.
+fn is_reserved_name(component: &[u8]) -> bool {
+    let stem = match component.iter().position(|b| *b == b'.') {
+        Some(pos) => &component[..pos],
+        None => component,
+    };
+    let stem = trim_dots_and_spaces(stem);
+    RESERVED_NAMES.iter().any(|name| stem.eq_ignore_ascii_case(name))
+        || (stem.len() == 4
+            && matches!(stem[3], b'1'..=b'9')
+            && RESERVED_NUMBERED_NAMES
+                .iter()
+                .any(|name| stem[..3].eq_ignore_ascii_case(name)))
+}
+
+fn trim_dots_and_spaces(mut name: &[u8]) -> &[u8] {
+    while let Some((last, rest)) = name.split_last() {
+        if *last != b' ' && *last != b'.' {
+            break;
+        }
+        name = rest;
+    }
+    name
+}
+
+/// The longest path, including the terminating null character, which Windows accepts unless it is an extended-length
+/// path.
+pub const MAX_PATH: usize = 260;
+
+/// Return `path` as extended-length path on Windows if it is too long to be used otherwise, or unchanged on all other
+/// platforms.
+pub fn long(path: PathBuf) -> PathBuf {
+    if cfg!(windows) && path.as_os_str().len() >= MAX_PATH {
+        extended_length(&path)
+    } else {
+        path
+    }
+}
+
+/// Return `path` as absolute extended-length path on Windows, so paths below it may exceed [`MAX_PATH`], or
+/// unchanged on all other platforms.
+///
+/// As Windows doesn't resolve `.` and `..` in extended-length paths, they are resolved here.
+#[cfg(windows)]
+pub fn extended_length(path: &Path) -> PathBuf {
+    use std::{
+        ffi::OsString,
+        path::{Component, Prefix},
+    };
+    let absolute;
+    let path = if path.is_absolute() {
+        path
+    } else {
+        match std::env::current_dir() {
+            Ok(current_dir) => {
+                absolute = current_dir.join(path);
+                &absolute
+            }
+            Err(_) => return path.to_owned(),
+        }
+    };
+    let mut components = path.components();
+    let mut extended = match components.next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(_) => {
+                let mut extended = OsString::from(r"\\?\");
+                extended.push(prefix.as_os_str());
+                extended
+            }
+            Prefix::UNC(server, share) => {
+                let mut extended = OsString::from(r"\\?\UNC\");
+                extended.push(server);
+                extended.push(r"\");
+                extended.push(share);
+                extended
+            }
+            _ => return path.to_owned(),
+        },
+        _ => return path.to_owned(),
+    };
+    let mut names = Vec::new();
+    for component in components {
+        match component {
+            Component::Normal(name) => names.push(name),
+            Component::ParentDir => {
+                names.pop();
+            }
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+        }
+    }
+    if names.is_empty() {
+        extended.push(r"\");
+    }
+    for name in names {
+        extended.push(r"\");
+        extended.push(name);
+    }
+    PathBuf::from(extended)
+}
+
+/// Return `path` as absolute extended-length path on Windows, so paths below it may exceed [`MAX_PATH`], or
+/// unchanged on all other platforms.
+#[cfg(not(windows))]
+pub fn extended_length(path: &Path) -> PathBuf {
+    path.to_owned()
+}
@@ -3,6 +3,9 @@ use std::io;
 pub use prodash::progress::{Discard, DoOrDiscard, Either, ThroughputOnDrop};
 pub use prodash::{unit, Progress, Unit};
 
+mod rate;
+pub use rate::Rate;
+
 pub fn bytes() -> Option<Unit> {
     Some(unit::dynamic_and_mode(
         unit::Bytes,
@@ -0,0 +1,195 @@
+use prodash::{messages::MessageLevel, progress::Step, Progress, Unit};
+use std::time::{Duration, Instant};
+
+/// The minimal amount of time between two measurements of the rate, to avoid them being dominated by noise.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+/// The weight of the latest measurement when updating the smoothed rate, with older measurements fading accordingly.
+const SMOOTHING_FACTOR: f64 = 0.3;
+
+/// A [`Progress`] implementation passing all calls to the contained instance while tracking the rate at which progress
+/// is made, which is the base for reporting the elapsed time, the throughput and the estimated time until completion.
+///
+/// Steps are counted independently of the contained instance, which works even if it is [`Discard`][super::Discard].
+/// All children created with [`add_child()`][Progress::add_child()] track their own rate.
+pub struct Rate<P> {
+    inner: P,
+    start: Instant,
+    max: Option<Step>,
+    step: Step,
+    last_sample: (Instant, Step),
+    steps_per_second: Option<f64>,
+    /// The interval to report at and the amount of child levels which report as well, if reporting is enabled.
+    reporting: Option<(Duration, usize)>,
+    last_report: Instant,
+}
+
+impl<P> Rate<P>
+where
+    P: Progress,
+{
+    /// Start tracking the rate of progress made with `inner` now.
+    pub fn new(inner: P) -> Self {
+        let now = Instant::now();
+        Rate {
+            inner,
+            start: now,
+            max: None,
+            step: 0,
+            last_sample: (now, 0),
+            steps_per_second: None,
+            reporting: None,
+            last_report: now,
+        }
+    }
+
+    /// [Report][Rate::report()] the throughput and the estimated time until completion at most once per `interval` while
+    /// progress is made, in this instance and its children up to `max_level` levels down.
+    ///
+    /// This is useful with progress implementations which don't compute these themselves, like logs.
+    pub fn reporting_every(mut self, interval: Duration, max_level: usize) -> Self {
+        self.reporting = Some((interval, max_level));
+        self
+    }
+
+    /// The time passed since this instance was created.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// The smoothed amount of steps made per second, or `None` if there was no time to measure it yet.
+    ///
+    /// It falls back to the average over the entire lifetime of this instance until enough time passed to measure it.
+    pub fn steps_per_second(&self) -> Option<f64> {
+        self.steps_per_second.or_else(|| {
+            let elapsed = self.elapsed().as_secs_f64();
+            if elapsed > 0.0 {
+                Some(self.step as f64 / elapsed)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The estimated time until the maximum step as configured with [`init()`][Progress::init()] is reached, or `None`
+    /// if the progress is unbounded or no progress was made yet.
+    pub fn eta(&self) -> Option<Duration> {
+        let remaining = self.max?.saturating_sub(self.step);
+        if remaining == 0 {
+            return Some(Duration::default());
+        }
+        match self.steps_per_second() {
+            Some(rate) if rate > 0.0 => Some(Duration::from_secs_f64(remaining as f64 / rate)),
+            _ => None,
+        }
+    }
+
+    /// Report the amount of steps made along with the elapsed time and average throughput as info message.
+    pub fn show_throughput(&mut self) {
+        let (start, step) = (self.start, self.step);
+        match self.inner.unit() {
+            Some(unit) => self.inner.show_throughput_with(start, step, unit),
+            None => self.inner.show_throughput(start),
+        }
+    }
+
+    /// Report the throughput and, if known, the estimated time until completion as info message.
+    pub fn report(&mut self) {
+        let mut message = match (self.steps_per_second(), self.inner.unit()) {
+            (Some(rate), Some(unit)) => format!("{}/s", unit.display(rate.round() as Step, None, None)),
+            (Some(rate), None) => format!("{:.1}/s", rate),
+            (None, _) => return,
+        };
+        if let Some(eta) = self.eta() {
+            message.push_str(&format!(", ETA {}", format_duration(eta)));
+        }
+        self.last_report = Instant::now();
+        self.inner.info(message)
+    }
+
+    /// Return the contained progress instance.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    fn sample(&mut self) {
+        let now = Instant::now();
+        let (last_time, last_step) = self.last_sample;
+        let elapsed = now.duration_since(last_time);
+        if elapsed < SAMPLE_INTERVAL {
+            return;
+        }
+        let rate = self.step.saturating_sub(last_step) as f64 / elapsed.as_secs_f64();
+        self.steps_per_second = Some(match self.steps_per_second {
+            Some(previous) => SMOOTHING_FACTOR * rate + (1.0 - SMOOTHING_FACTOR) * previous,
+            None => rate,
+        });
+        self.last_sample = (now, self.step);
+        if let Some((interval, _)) = self.reporting {
+            if now.duration_since(self.last_report) >= interval {
+                self.report();
+            }
+        }
+    }
+}
+
+/// Format `duration` in whole seconds like `1h02m03s`, leaving out leading zero components.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h{:02}m{:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m{:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+impl<P> Progress for Rate<P>
+where
+    P: Progress,
+{
+    type SubProgress = Rate<P::SubProgress>;
+
+    fn add_child(&mut self, name: impl Into<String>) -> Self::SubProgress {
+        let mut child = Rate::new(self.inner.add_child(name));
+        child.reporting = match self.reporting {
+            Some((interval, max_level)) if max_level > 0 => Some((interval, max_level - 1)),
+            _ => None,
+        };
+        child
+    }
+
+    fn init(&mut self, max: Option<Step>, unit: Option<Unit>) {
+        self.max = max;
+        self.inner.init(max, unit)
+    }
+
+    fn set(&mut self, step: Step) {
+        self.step = step;
+        self.sample();
+        self.inner.set(step)
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        self.inner.unit()
+    }
+
+    fn max(&self) -> Option<Step> {
+        self.max
+    }
+
+    fn step(&self) -> Step {
+        self.step
+    }
+
+    fn inc_by(&mut self, step: Step) {
+        self.step += step;
+        self.sample();
+        self.inner.inc_by(step)
+    }
+
+    fn message(&mut self, level: MessageLevel, message: impl Into<String>) {
+        self.inner.message(level, message)
+    }
+}
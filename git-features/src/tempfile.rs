@@ -0,0 +1,75 @@
+//! A registry of temporary files and directories, like packs being received or indices being written, so they can be
+//! removed if the process is interrupted before their owners get to clean them up.
+//!
+//! The owner of a temporary file remains responsible for removing it, and [registers][register()] its path only for
+//! as long as it exists. With the `interrupt-handler` feature, the [interrupt handler][crate::interruptible::init_interrupt_handler()]
+//! calls [`cleanup()`] once an interrupt is requested again while the first one is still being handled, and then
+//! terminates the process. As the handler runs on its own thread, it's not constrained to what is safe to do within a
+//! signal handler.
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+/// What kind of temporary path is registered, to know how to remove it.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum Kind {
+    /// A file, removed by itself.
+    File,
+    /// A directory, removed along with everything in it.
+    Directory,
+}
+
+static REGISTRY: Mutex<BTreeMap<usize, (PathBuf, Kind)>> = Mutex::new(BTreeMap::new());
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Keeps a temporary path registered until it's dropped, which doesn't remove the path.
+#[must_use = "the path is unregistered right away unless the registration is kept"]
+pub struct Registration {
+    id: usize,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        registry().remove(&self.id);
+    }
+}
+
+/// Register `path` of `kind` for removal by [`cleanup()`] until the returned registration is dropped.
+///
+/// It should be dropped once the owner persisted or removed the path.
+pub fn register(path: impl Into<PathBuf>, kind: Kind) -> Registration {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    registry().insert(id, (path.into(), kind));
+    Registration { id }
+}
+
+/// Return true if `path` is currently registered.
+pub fn is_registered(path: impl AsRef<Path>) -> bool {
+    let path = path.as_ref();
+    registry().values().any(|(registered, _)| registered == path)
+}
+
+/// Remove all registered temporary paths as well as all [lock files][crate::lock] held by this process, and return how
+/// many there were.
+///
+/// It's meant to be called when the process is about to be terminated, as registered paths are not used afterwards.
+pub fn cleanup() -> usize {
+    let registered = std::mem::take(&mut *registry());
+    for (path, kind) in registered.values() {
+        match kind {
+            Kind::File => fs::remove_file(path).ok(),
+            Kind::Directory => fs::remove_dir_all(path).ok(),
+        };
+    }
+    registered.len() + crate::lock::release_all()
+}
+
+fn registry() -> std::sync::MutexGuard<'static, BTreeMap<usize, (PathBuf, Kind)>> {
+    REGISTRY.lock().unwrap_or_else(|err| err.into_inner())
+}
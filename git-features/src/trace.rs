@@ -0,0 +1,133 @@
+//! Spans measuring how long operations take, to diagnose performance problems without a debugger.
+//!
+//! Like `GIT_TRACE_PERFORMANCE` does for git, `GITOXIDE_TRACE` decides where finished spans are written to: `1`, `2`
+//! or `true` write them to standard error and an absolute path appends them to that file, while `0`, `false` or
+//! nothing turns tracing off. `GIT_TRACE_PERFORMANCE` is used if `GITOXIDE_TRACE` isn't set.
+//!
+//! With the `tracing` feature, each span is also entered as [`tracing`] span named `span` with the fields `name` and
+//! `details`, so any subscriber can see them.
+use std::{
+    cell::Cell,
+    fmt, fs,
+    io::{self, Write},
+    path::PathBuf,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+thread_local! {
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Where finished spans are written.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Destination {
+    /// Write to standard error.
+    Stderr,
+    /// Append to the file at the given absolute path.
+    File(PathBuf),
+}
+
+impl Destination {
+    /// Read the destination from `GITOXIDE_TRACE`, falling back to `GIT_TRACE_PERFORMANCE`, or return `None` if
+    /// tracing is turned off.
+    pub fn from_env() -> Option<Self> {
+        let value = std::env::var_os("GITOXIDE_TRACE").or_else(|| std::env::var_os("GIT_TRACE_PERFORMANCE"))?;
+        match value.to_str() {
+            Some("1") | Some("2") => Some(Destination::Stderr),
+            Some(value) if value.eq_ignore_ascii_case("true") => Some(Destination::Stderr),
+            _ => {
+                let path = PathBuf::from(value);
+                if path.is_absolute() {
+                    Some(Destination::File(path))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn write(&self, line: &str) -> io::Result<()> {
+        match self {
+            Destination::Stderr => io::stderr().write_all(line.as_bytes()),
+            Destination::File(path) => fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?
+                .write_all(line.as_bytes()),
+        }
+    }
+}
+
+/// A running span, which is written to its [`Destination`] when dropped along with the time it took.
+///
+/// Spans created while another one runs on the same thread are indented below it.
+pub struct Span {
+    name: &'static str,
+    details: String,
+    start: Instant,
+    destination: Option<Destination>,
+    depth: usize,
+    #[cfg(feature = "tracing")]
+    span: tracing::span::EnteredSpan,
+}
+
+/// Start a span called `name`, like `pack::index`, which ends when the returned value is dropped.
+///
+/// Spans should cover coarse operations as the environment is read each time one is created.
+pub fn span(name: &'static str) -> Span {
+    let depth = DEPTH.with(|depth| depth.replace(depth.get() + 1));
+    Span {
+        name,
+        details: String::new(),
+        start: Instant::now(),
+        destination: Destination::from_env(),
+        depth,
+        #[cfg(feature = "tracing")]
+        span: tracing::info_span!("span", name, details = tracing::field::Empty).entered(),
+    }
+}
+
+impl Span {
+    /// Add `detail`, like `objects=42`, to the details written along with the span.
+    pub fn record(&mut self, detail: impl fmt::Display) {
+        if !self.is_enabled() {
+            return;
+        }
+        if !self.details.is_empty() {
+            self.details.push(' ');
+        }
+        self.details.push_str(&detail.to_string());
+        #[cfg(feature = "tracing")]
+        self.span.record("details", self.details.as_str());
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.destination.is_some() || cfg!(feature = "tracing")
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        DEPTH.with(|depth| depth.set(self.depth));
+        if let Some(destination) = &self.destination {
+            let elapsed = self.start.elapsed();
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+            let seconds_of_day = now.as_secs() % (24 * 60 * 60);
+            let line = format!(
+                "{:02}:{:02}:{:02}.{:06} performance: {}.{:09} s: {:indent$}{}{}{}\n",
+                seconds_of_day / 3600,
+                seconds_of_day / 60 % 60,
+                seconds_of_day % 60,
+                now.subsec_micros(),
+                elapsed.as_secs(),
+                elapsed.subsec_nanos(),
+                "",
+                self.name,
+                if self.details.is_empty() { "" } else { " " },
+                self.details,
+                indent = self.depth * 2
+            );
+            destination.write(&line).ok();
+        }
+    }
+}
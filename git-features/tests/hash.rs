@@ -11,3 +11,119 @@ fn size_of_sha1() {
 fn size_of_sha1() {
     assert_eq!(std::mem::size_of::<Sha1>(), 104)
 }
+
+#[test]
+fn one_shot_and_streaming_hashing_produce_the_same_digest() {
+    let expected = [
+        0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e, 0x25, 0x71, 0x78, 0x50, 0xc2, 0x6c, 0x9c, 0xd0,
+        0xd8, 0x9d,
+    ];
+    assert_eq!(git_features::hash::sha1(b"abc"), expected);
+
+    let mut hasher = Sha1::default();
+    hasher.update(b"a");
+    hasher.update(b"bc");
+    assert_eq!(hasher.digest(), expected);
+}
+
+#[test]
+fn digests_match_known_vectors_regardless_of_how_data_is_streamed() {
+    fn hex(digest: [u8; 20]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+    assert_eq!(
+        hex(git_features::hash::sha1(b"")),
+        "da39a3ee5e6b4b0d3255bfef95601890afd80709"
+    );
+    assert_eq!(
+        hex(git_features::hash::sha1(
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+        )),
+        "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+    );
+
+    let data = vec![b'a'; 1_000_000];
+    assert_eq!(
+        hex(git_features::hash::sha1(&data)),
+        "34aa973cd4c4daa4f61eeb2bdbad27316534016f"
+    );
+    for chunk_size in &[1, 55, 63, 64, 65, 1000] {
+        let mut hasher = Sha1::default();
+        for chunk in data.chunks(*chunk_size) {
+            hasher.update(chunk);
+        }
+        assert_eq!(
+            hex(hasher.digest()),
+            "34aa973cd4c4daa4f61eeb2bdbad27316534016f",
+            "chunks of {} bytes",
+            chunk_size
+        );
+    }
+}
+
+mod collisions {
+    fn hex(digest: [u8; 20]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("valid hex"))
+            .collect()
+    }
+
+    /// The first 320 bytes of the two PDF files of the SHAttered attack, which have the same SHA-1 as the two near
+    /// collision blocks at their end only differ by a disturbance vector.
+    fn shattered_prefixes() -> (Vec<u8>, Vec<u8>) {
+        let prefix: &[u8] = b"%PDF-1.3\n%\xe2\xe3\xcf\xd3\n\n\n1 0 obj\n<</Width 2 0 R/Height 3 0 R/Type 4 0 R/Subtype 5 0 R/Filter 6 0 R/ColorSpace 7 0 R/Length 8 0 R/BitsPerComponent 8>>\nstream\n\xff\xd8\xff\xfe\x00\x24SHA-1 is dead!!!!!\x85/\xec\x09\x239u\x9c9\xb1\xa1\xc6<L\x97\xe1\xff\xfe\x01";
+        let first = [
+            prefix,
+            &from_hex(concat!(
+                "7346dc9166b67e118f029ab621b2560ff9ca67cca8c7f85ba84c79030c2b3de218f86db3a90901d5df45c14f26fedfb3",
+                "dc38e96ac22fe7bd728f0e45bce046d23c570feb141398bb552ef5a0a82be331fea48037b8b5d71f0e332edf93ac3500",
+                "eb4ddc0decc1a864790c782c76215660dd309791d06bd0af3f98cda4bc4629b1"
+            )),
+        ]
+        .concat();
+        let second = [
+            prefix,
+            &from_hex(concat!(
+                "7f46dc93a6b67e013b029aaa1db2560b45ca67d688c7f84b8c4c791fe02b3df614f86db1690901c56b45c1530afedfb7",
+                "6038e972722fe7ad728f0e4904e046c230570fe9d41398abe12ef5bc942be33542a4802d98b5d70f2a332ec37fac3514",
+                "e74ddc0f2cc1a874cd0c78305a21566461309789606bd0bf3f98cda8044629a1"
+            )),
+        ]
+        .concat();
+        (first, second)
+    }
+
+    #[cfg(not(feature = "fast-sha1"))]
+    #[test]
+    fn colliding_data_produces_the_safe_hash_of_sha1collisiondetection() {
+        let (first, second) = shattered_prefixes();
+        assert_eq!(
+            hex(git_features::hash::sha1(&first)),
+            "7117b3cb9225aaf0d8ef1a40e493957b0bf8693d",
+            "it differs from the SHA-1 f92d74e3874587aaf443d1db961d4e26dde13e9c of both"
+        );
+        assert_eq!(
+            hex(git_features::hash::sha1(&second)),
+            "29f38ae9fd98e2931120fa0bf213e024250d3f6a"
+        );
+    }
+
+    #[cfg(feature = "fast-sha1")]
+    #[test]
+    fn colliding_data_produces_the_same_digest() {
+        let (first, second) = shattered_prefixes();
+        assert_eq!(
+            hex(git_features::hash::sha1(&first)),
+            "f92d74e3874587aaf443d1db961d4e26dde13e9c"
+        );
+        assert_eq!(
+            hex(git_features::hash::sha1(&second)),
+            "f92d74e3874587aaf443d1db961d4e26dde13e9c"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use git_features::lock::{self, Error, Fail, Marker, Options};
+use std::{
+    fs,
+    io::Write,
+    time::{Duration, Instant},
+};
+
+type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+
+#[test]
+fn commit_replaces_the_locked_file_and_removes_the_lock() -> Result {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("a").join("file");
+    let mut file = lock::File::acquire(&path, Options::default())?;
+    assert!(
+        file.lock_path().is_file(),
+        "directories leading to the lock are created"
+    );
+    file.write_all(b"content")?;
+    assert!(!path.exists(), "nothing changes before the commit");
+    assert_eq!(file.commit()?, path);
+    assert_eq!(fs::read(&path)?, b"content");
+    assert!(!dir.path().join("a").join("file.lock").exists());
+    Ok(())
+}
+
+#[test]
+fn dropping_an_uncommitted_lock_leaves_the_file_unchanged() -> Result {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("file");
+    fs::write(&path, b"original")?;
+    let lock_path = {
+        let mut file = lock::File::acquire(&path, Options::default())?;
+        file.write_all(b"changed")?;
+        file.lock_path().to_owned()
+    };
+    assert!(!lock_path.exists());
+    assert_eq!(fs::read(&path)?, b"original");
+    Ok(())
+}
+
+#[test]
+fn held_locks_fail_immediately_by_default() -> Result {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("file");
+    let _held = lock::File::acquire(&path, Options::default())?;
+    assert!(matches!(
+        lock::File::acquire(&path, Options::default()),
+        Err(Error::Locked(lock_path)) if lock_path == dir.path().join("file.lock")
+    ));
+    Ok(())
+}
+
+#[test]
+fn held_locks_are_retried_until_the_timeout() -> Result {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("file");
+    let _held = lock::File::acquire(&path, Options::default())?;
+    let start = Instant::now();
+    let options = Options {
+        fail: Fail::AfterDurationWithBackoff(Duration::from_millis(50)),
+        ..Default::default()
+    };
+    assert!(matches!(lock::File::acquire(&path, options), Err(Error::Locked(_))));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    Ok(())
+}
+
+#[test]
+fn locks_released_while_waiting_are_acquired() -> Result {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("file");
+    let held = lock::File::acquire(&path, Options::default())?;
+    let waiter = std::thread::spawn({
+        let path = path.clone();
+        move || {
+            lock::File::acquire(
+                path,
+                Options {
+                    fail: Fail::AfterDurationWithBackoff(Duration::from_secs(5)),
+                    ..Default::default()
+                },
+            )
+            .map(drop)
+        }
+    });
+    std::thread::sleep(Duration::from_millis(20));
+    drop(held);
+    assert!(waiter.join().expect("no panic").is_ok());
+    Ok(())
+}
+
+#[test]
+fn locks_older_than_stale_after_are_removed() -> Result {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("file");
+    fs::write(dir.path().join("file.lock"), b"")?;
+    std::thread::sleep(Duration::from_millis(20));
+    let fresh = Options {
+        stale_after: Some(Duration::from_secs(3600)),
+        ..Default::default()
+    };
+    assert!(matches!(lock::File::acquire(&path, fresh), Err(Error::Locked(_))));
+    let stale = Options {
+        stale_after: Some(Duration::from_millis(10)),
+        ..Default::default()
+    };
+    let lock = lock::File::acquire(&path, stale)?;
+    assert_eq!(
+        fs::read_dir(dir.path())?.count(),
+        1,
+        "the stale lock is moved out of the way and removed"
+    );
+    drop(lock);
+    Ok(())
+}
+
+#[test]
+fn markers_record_the_process_and_are_removed_on_drop() -> Result {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("gc.pid");
+    let marker = Marker::acquire(&path, Options::default())?;
+    let content = fs::read_to_string(marker.lock_path())?;
+    assert!(content.starts_with(&format!("pid {}", std::process::id())));
+    #[cfg(target_os = "linux")]
+    assert_eq!(
+        content,
+        format!(
+            "pid {} {}\n",
+            std::process::id(),
+            fs::read_to_string("/proc/sys/kernel/hostname")?.trim_end()
+        ),
+        "the machine is recorded as well"
+    );
+    assert!(matches!(
+        Marker::acquire(&path, Options::default()),
+        Err(Error::Locked(_))
+    ));
+    let lock_path = marker.lock_path().to_owned();
+    drop(marker);
+    assert!(!lock_path.exists());
+    Ok(())
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn markers_of_processes_which_are_gone_are_stale() -> Result {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("gc.pid");
+    let mut child = std::process::Command::new("true").spawn()?;
+    let pid = child.id();
+    child.wait()?;
+    fs::write(dir.path().join("gc.pid.lock"), format!("pid {}\n", pid))?;
+    drop(Marker::acquire(&path, Options::default())?);
+
+    let hostname = fs::read_to_string("/proc/sys/kernel/hostname")?;
+    fs::write(
+        dir.path().join("gc.pid.lock"),
+        format!("pid {} {}\n", pid, hostname.trim_end()),
+    )?;
+    drop(Marker::acquire(&path, Options::default())?);
+
+    fs::write(
+        dir.path().join("gc.pid.lock"),
+        format!("pid {} elsewhere.example.com\n", pid),
+    )?;
+    assert!(
+        matches!(Marker::acquire(&path, Options::default()), Err(Error::Locked(_))),
+        "processes of other machines can't be checked"
+    );
+    Ok(())
+}
@@ -120,3 +120,67 @@ mod optimize_chunk_size_and_thread_limit {
         }
     }
 }
+
+mod dynamic_chunks {
+    use git_features::parallel::DynamicChunks;
+
+    #[test]
+    fn chunks_get_smaller_towards_the_end() {
+        let chunks: Vec<_> = DynamicChunks::new(0..100, Some(2), 10).collect();
+        let sizes: Vec<_> = chunks.iter().map(Vec::len).collect();
+        assert_eq!(sizes.first(), Some(&10), "the maximum chunk size is respected");
+        assert!(sizes.windows(2).all(|w| w[0] >= w[1]), "sizes never grow: {:?}", sizes);
+        assert_eq!(sizes.last(), Some(&1), "the last chunks are as small as possible");
+        assert_eq!(
+            chunks.into_iter().flatten().collect::<Vec<_>>(),
+            (0..100).collect::<Vec<_>>(),
+            "all items are produced in order"
+        );
+    }
+
+    #[test]
+    fn items_of_unknown_amount_use_the_maximum_chunk_size() {
+        let sizes: Vec<_> = DynamicChunks::new((0..).take_while(|n| *n < 25), Some(2), 10)
+            .map(|c| c.len())
+            .collect();
+        assert_eq!(sizes, vec![10, 10, 5]);
+    }
+}
+
+mod in_parallel_in_order_if {
+    use git_features::parallel::{self, in_parallel_in_order_if};
+
+    struct Collect(Vec<usize>);
+
+    impl parallel::Reducer for Collect {
+        type Input = usize;
+        type Output = Vec<usize>;
+        type Error = ();
+
+        fn feed(&mut self, input: Self::Input) -> Result<(), Self::Error> {
+            self.0.push(input);
+            Ok(())
+        }
+
+        fn finalize(self) -> Result<Self::Output, Self::Error> {
+            Ok(self.0)
+        }
+    }
+
+    #[test]
+    fn results_are_fed_in_input_order_even_if_processing_cost_is_uneven() {
+        let out = in_parallel_in_order_if(
+            || true,
+            0..50usize,
+            Some(4),
+            |_| (),
+            |n, _| {
+                std::thread::sleep(std::time::Duration::from_micros(((50 - n) * 20) as u64));
+                n * 2
+            },
+            Collect(Vec::new()),
+        )
+        .unwrap();
+        assert_eq!(out, (0..50).map(|n| n * 2).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,156 @@
+use git_features::path::{self, Error, Protections};
+use std::path::PathBuf;
+
+const NONE: Protections = Protections {
+    ntfs: false,
+    windows: false,
+};
+const ALL: Protections = Protections {
+    ntfs: true,
+    windows: true,
+};
+
+#[test]
+fn components_escaping_the_directory_or_naming_git_are_always_rejected() {
+    for component in &[&b""[..], b".", b"..", b"a/b", b".git", b".GIT"] {
+        assert!(
+            path::verify_component(component, NONE).is_err(),
+            "{:?}",
+            std::str::from_utf8(component)
+        );
+    }
+    for component in &[&b"a\\b"[..], b"git~1", b".git.", b"con", b"a:b"] {
+        assert!(path::verify_component(component, NONE).is_ok());
+    }
+}
+
+#[test]
+fn ntfs_aliases_of_dot_git_are_rejected() {
+    let protections = Protections {
+        ntfs: true,
+        windows: false,
+    };
+    for component in &[
+        &b"git~1"[..],
+        b"GIT~1",
+        b".git. . ",
+        b".git::$INDEX_ALLOCATION",
+        b".Git .",
+    ] {
+        assert!(matches!(
+            path::verify_component(component, protections),
+            Err(Error::DotGit(_))
+        ));
+    }
+    for component in &[&b"git~2"[..], b".gitignore", b".git-foo", b"x.git"] {
+        assert!(path::verify_component(component, protections).is_ok());
+    }
+}
+
+#[test]
+fn names_windows_cannot_store_are_rejected() {
+    assert!(matches!(
+        path::verify_component(b"a\\..\\b", ALL),
+        Err(Error::Separator(_))
+    ));
+    for component in &[&b"con"[..], b"AUX", b"nul.txt", b"com1", b"Lpt9.tar.gz", b"prn .c"] {
+        assert!(
+            matches!(path::verify_component(component, ALL), Err(Error::Reserved(_))),
+            "{:?}",
+            std::str::from_utf8(component)
+        );
+    }
+    for component in &[&b"a:b"[..], b"what?", b"a*", b"tab\t", b"<>", b"\"", b"pipe|"] {
+        assert!(matches!(
+            path::verify_component(component, ALL),
+            Err(Error::InvalidByte(_))
+        ));
+    }
+    for component in &[&b"file."[..], b"file "] {
+        assert!(matches!(
+            path::verify_component(component, ALL),
+            Err(Error::TrailingDotOrSpace(_))
+        ));
+    }
+    for component in &[
+        &b"console"[..],
+        b"com0",
+        b"com10",
+        b"auxiliary.txt",
+        b"lpt",
+        b"file.txt",
+    ] {
+        assert!(path::verify_component(component, ALL).is_ok());
+    }
+}
+
+#[test]
+fn slash_separated_paths_are_verified_per_component() {
+    assert_eq!(
+        path::to_native(b"refs/heads/main", ALL).unwrap(),
+        PathBuf::from("refs").join("heads").join("main")
+    );
+    assert!(path::to_native(b"refs/heads/con", ALL).is_err());
+    assert!(path::to_native(b"refs/heads/con", NONE).is_ok());
+    assert!(matches!(path::to_native(b"refs//main", NONE), Err(Error::Empty)));
+    assert!(matches!(path::to_native(b"a/../b", NONE), Err(Error::Relative(_))));
+    assert!(matches!(
+        path::to_native(b"dir/git~1/hooks", ALL),
+        Err(Error::DotGit(_))
+    ));
+}
+
+#[test]
+fn windows_is_only_protected_by_default_on_windows() {
+    assert_eq!(
+        Protections::default(),
+        Protections {
+            ntfs: true,
+            windows: cfg!(windows)
+        }
+    );
+}
+
+mod long {
+    use git_features::path;
+    use std::path::PathBuf;
+
+    #[test]
+    #[cfg(not(windows))]
+    fn paths_are_unchanged_on_other_platforms() {
+        let long = PathBuf::from("/").join("a".repeat(path::MAX_PATH * 2));
+        assert_eq!(path::long(long.clone()), long);
+        assert_eq!(path::extended_length("relative".as_ref()), PathBuf::from("relative"));
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn only_paths_exceeding_max_path_are_extended() {
+        let short = PathBuf::from(r"C:\repo\.git\objects\ab\cdef");
+        assert_eq!(path::long(short.clone()), short);
+        let name = "a".repeat(path::MAX_PATH);
+        assert_eq!(
+            path::long(PathBuf::from(r"C:\repo").join(&name)),
+            PathBuf::from(format!(r"\\?\C:\repo\{}", name))
+        );
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn extended_length_paths_are_absolute_and_normalized() {
+        assert_eq!(
+            path::extended_length(r"C:\repo\.\dir\..\file".as_ref()),
+            PathBuf::from(r"\\?\C:\repo\file")
+        );
+        assert_eq!(path::extended_length(r"C:\".as_ref()), PathBuf::from(r"\\?\C:\"));
+        assert_eq!(
+            path::extended_length(r"\\server\share\repo".as_ref()),
+            PathBuf::from(r"\\?\UNC\server\share\repo")
+        );
+        assert_eq!(
+            path::extended_length(r"\\?\C:\already".as_ref()),
+            PathBuf::from(r"\\?\C:\already")
+        );
+        assert!(path::extended_length("relative".as_ref()).starts_with(r"\\?\"));
+    }
+}
@@ -0,0 +1,108 @@
+mod rate {
+    use git_features::progress::{self, Progress, Rate, Unit};
+    use prodash::messages::MessageLevel;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    /// A progress keeping the messages sent to it and all of its children.
+    #[derive(Default, Clone)]
+    struct Messages(Arc<Mutex<Vec<String>>>);
+
+    impl Progress for Messages {
+        type SubProgress = Messages;
+
+        fn add_child(&mut self, _name: impl Into<String>) -> Self::SubProgress {
+            self.clone()
+        }
+
+        fn init(&mut self, _max: Option<usize>, _unit: Option<Unit>) {}
+
+        fn set(&mut self, _step: usize) {}
+
+        fn unit(&self) -> Option<Unit> {
+            None
+        }
+
+        fn max(&self) -> Option<usize> {
+            None
+        }
+
+        fn step(&self) -> usize {
+            0
+        }
+
+        fn inc_by(&mut self, _step: usize) {}
+
+        fn message(&mut self, _level: MessageLevel, message: impl Into<String>) {
+            self.0.lock().unwrap().push(message.into());
+        }
+    }
+
+    #[test]
+    fn throughput_and_eta_are_reported_as_messages_while_progress_is_made() {
+        let messages = Messages::default();
+        let mut p = Rate::new(messages.clone()).reporting_every(Duration::from_millis(10), 1);
+        p.init(Some(100), None);
+        p.set(10);
+        assert!(messages.0.lock().unwrap().is_empty(), "the interval didn't pass yet");
+        std::thread::sleep(Duration::from_millis(300));
+        p.set(50);
+        {
+            let messages = messages.0.lock().unwrap();
+            assert_eq!(messages.len(), 1);
+            assert!(messages[0].contains("/s, ETA "), "{}", messages[0]);
+        }
+
+        let mut child = p.add_child("child");
+        let mut grandchild = child.add_child("grandchild");
+        std::thread::sleep(Duration::from_millis(300));
+        child.set(1);
+        grandchild.set(1);
+        assert_eq!(
+            messages.0.lock().unwrap().len(),
+            2,
+            "only children up to the configured level report, and without maximum there is no ETA"
+        );
+        assert!(messages.0.lock().unwrap()[1].ends_with("/s"));
+    }
+
+    #[test]
+    fn counts_steps_even_if_the_inner_progress_is_discarded() {
+        let mut p = Rate::new(progress::Discard);
+        p.init(Some(10), None);
+        p.inc_by(3);
+        p.inc();
+        assert_eq!(p.step(), 4);
+        assert_eq!(p.max(), Some(10));
+        p.set(7);
+        assert_eq!(p.step(), 7);
+    }
+
+    #[test]
+    fn eta_is_unknown_without_maximum_or_progress() {
+        let mut p = Rate::new(progress::Discard);
+        assert_eq!(p.eta(), None, "unbounded progress has no end");
+        p.init(Some(10), None);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(p.eta(), None, "no progress was made yet");
+        p.set(10);
+        assert_eq!(p.eta(), Some(Duration::default()), "it's done");
+    }
+
+    #[test]
+    fn throughput_and_eta_follow_the_progress_made() {
+        let mut p = Rate::new(progress::Discard);
+        p.init(Some(100), None);
+        std::thread::sleep(Duration::from_millis(20));
+        p.set(50);
+        let rate = p.steps_per_second().expect("time passed");
+        assert!(rate > 0.0 && rate <= 50.0 / 0.02, "rate {} is plausible", rate);
+        let eta = p.eta().expect("progress was made");
+        assert!(
+            eta <= p.elapsed() + Duration::from_millis(1),
+            "half the work remains, which takes about as long as it took so far"
+        );
+    }
+}
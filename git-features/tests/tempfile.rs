@@ -0,0 +1,32 @@
+use git_features::tempfile::{self, Kind};
+use std::fs;
+
+#[test]
+fn cleanup_removes_registered_files_and_directories_but_not_unregistered_ones() -> Result<(), Box<dyn std::error::Error>>
+{
+    let dir = ::tempfile::tempdir()?;
+    let file = dir.path().join("pack");
+    let directory = dir.path().join("indices");
+    let unregistered = dir.path().join("persisted");
+    fs::write(&file, b"")?;
+    fs::create_dir_all(directory.join("sub"))?;
+    fs::write(&unregistered, b"")?;
+
+    let _file = tempfile::register(&file, Kind::File);
+    let _directory = tempfile::register(&directory, Kind::Directory);
+    drop(tempfile::register(&unregistered, Kind::File));
+    assert!(tempfile::is_registered(&file));
+    assert!(
+        !tempfile::is_registered(&unregistered),
+        "dropping a registration unregisters the path"
+    );
+
+    let lock = git_features::lock::File::acquire(dir.path().join("config"), Default::default())?;
+    assert!(tempfile::cleanup() >= 3, "locks are removed as well");
+    assert!(!file.exists());
+    assert!(!directory.exists());
+    assert!(!lock.lock_path().exists());
+    assert!(unregistered.is_file());
+    assert!(!tempfile::is_registered(&file));
+    Ok(())
+}
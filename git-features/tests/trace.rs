@@ -0,0 +1,43 @@
+use git_features::trace::{self, Destination};
+
+#[test]
+fn spans_are_written_to_the_configured_file_once_they_end() {
+    let path = std::env::temp_dir().join(format!("git-features-trace-{}", std::process::id()));
+    std::env::set_var("GITOXIDE_TRACE", &path);
+    assert_eq!(Destination::from_env(), Some(Destination::File(path.clone())));
+    {
+        let mut outer = trace::span("outer");
+        outer.record("objects=3");
+        {
+            let _inner = trace::span("inner");
+        }
+        outer.record("bytes=42");
+    }
+    drop(trace::span("after"));
+
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let spans: Vec<_> = written
+        .lines()
+        .map(|line| {
+            assert!(line.contains(" performance: "), "{}", line);
+            line.split_once(" s: ").unwrap().1
+        })
+        .collect();
+    assert_eq!(
+        spans,
+        vec!["  inner", "outer objects=3 bytes=42", "after"],
+        "nested spans are indented and written first"
+    );
+
+    for (value, expected) in &[
+        ("1", Some(Destination::Stderr)),
+        ("true", Some(Destination::Stderr)),
+        ("0", None),
+        ("relative/path", None),
+    ] {
+        std::env::set_var("GITOXIDE_TRACE", value);
+        assert_eq!(&Destination::from_env(), expected, "{}", value);
+    }
+    std::env::remove_var("GITOXIDE_TRACE");
+}
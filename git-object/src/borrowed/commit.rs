@@ -3,6 +3,7 @@ use crate::{
     borrowed::{parse, parse::NL, Signature},
     commit, owned, BStr, ByteSlice,
 };
+use alloc::{borrow::Cow, vec::Vec};
 use nom::{
     branch::alt,
     bytes::{complete::is_not, complete::tag},
@@ -11,7 +12,6 @@ use nom::{
     IResult,
 };
 use smallvec::SmallVec;
-use std::borrow::Cow;
 
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
@@ -27,9 +27,16 @@ pub struct Commit<'a> {
     pub encoding: Option<&'a BStr>,
     pub message: &'a BStr,
     /// Extra header fields, either single line or multi-line.
-    pub extra_headers: Vec<(&'a BStr, Cow<'a, BStr>)>,
+    pub extra_headers: Vec<(&'a BStr, HeaderValue<'a>)>,
 }
 
+/// The value of an extra header, which is owned if it spans multiple lines as their leading spaces are removed.
+#[cfg(feature = "std")]
+pub type HeaderValue<'a> = Cow<'a, BStr>;
+/// The value of an extra header, which is owned if it spans multiple lines as their leading spaces are removed.
+#[cfg(not(feature = "std"))]
+pub type HeaderValue<'a> = Cow<'a, [u8]>;
+
 pub fn parse_message(i: &[u8]) -> IResult<&[u8], &BStr, Error> {
     if i.is_empty() {
         // newline + [message]
@@ -53,7 +60,7 @@ pub fn parse(i: &[u8]) -> IResult<&[u8], Commit, Error> {
         opt(|i| parse::header_field(i, b"encoding", is_not(NL)))(i).map_err(Error::context("encoding <encoding>"))?;
     let (i, extra_headers) = many0(alt((
         |i| parse::any_header_field_multi_line(i).map(|(i, (k, o))| (i, (k.as_bstr(), Cow::Owned(o)))),
-        |i| parse::any_header_field(i, is_not(NL)).map(|(i, (k, o))| (i, (k.as_bstr(), Cow::Borrowed(o.as_bstr())))),
+        |i| parse::any_header_field(i, is_not(NL)).map(|(i, (k, o))| (i, (k.as_bstr(), Cow::Borrowed(o.into())))),
     )))(i)
     .map_err(Error::context("<field> <single-line|multi-line>"))?;
     let (i, message) = all_consuming(parse_message)(i)?;
@@ -76,10 +83,26 @@ impl<'a> Commit<'a> {
     pub fn tree(&self) -> owned::Id {
         owned::Id::from_40_bytes_in_hex(self.tree).expect("prior validation")
     }
+    pub fn parents(&self) -> impl Iterator<Item = owned::Id> + '_ {
+        self.parents
+            .iter()
+            .map(|hex| owned::Id::from_40_bytes_in_hex(hex).expect("prior validation"))
+    }
     pub fn from_bytes(d: &'a [u8]) -> Result<Commit<'a>, Error> {
         parse(d).map(|(_, t)| t).map_err(Error::from)
     }
     pub fn extra_headers(&self) -> commit::ExtraHeaders<impl Iterator<Item = (&BStr, &BStr)>> {
-        commit::ExtraHeaders::new(self.extra_headers.iter().map(|(k, v)| (*k, v.as_ref())))
+        commit::ExtraHeaders::new(self.extra_headers.iter().map(|(k, v)| (*k, v.as_bstr())))
+    }
+    /// Decode `data` of this commit, like the name of its author, using its [encoding][Commit::encoding], as done by
+    /// [`commit::decode()`].
+    #[cfg(feature = "encoding")]
+    pub fn decode<'b>(&self, data: &'b [u8]) -> Option<Cow<'b, str>> {
+        commit::decode(self.encoding, data)
+    }
+    /// The message decoded using the [encoding][Commit::encoding] of this commit, or `None` if it's unknown.
+    #[cfg(feature = "encoding")]
+    pub fn decoded_message(&self) -> Option<Cow<'a, str>> {
+        commit::decode(self.encoding, self.message)
     }
 }
@@ -1,7 +1,7 @@
 use crate::SHA1_SIZE;
 use bstr::ByteSlice;
-use std::convert::{TryFrom, TryInto};
-use std::fmt;
+use core::convert::{TryFrom, TryInto};
+use core::fmt;
 
 /// A reference to a SHA1 identifying objects
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
@@ -27,6 +27,26 @@ impl<'a> Id<'a> {
     pub fn sha1(&self) -> &[u8; SHA1_SIZE] {
         self.0
     }
+    /// Returns true if the hexadecimal representation of this id starts with `prefix`, like `b"877c3a"`.
+    ///
+    /// The prefix may have an odd amount of hex digits, upper and lower case are treated the same, and invalid or
+    /// overly long prefixes never match.
+    pub fn starts_with_hex(&self, prefix: &[u8]) -> bool {
+        if prefix.len() > SHA1_SIZE * 2 {
+            return false;
+        }
+        prefix.iter().enumerate().all(|(index, digit)| {
+            let byte = self.0[index / 2];
+            let nibble = if index % 2 == 0 { byte >> 4 } else { byte & 0xf };
+            (*digit as char).to_digit(16) == Some(u32::from(nibble))
+        })
+    }
+    /// Compare this id with `other` in constant time, which doesn't reveal the position of the first differing byte.
+    ///
+    /// Use it when comparing against ids provided by untrusted parties, like when checking signatures or tokens.
+    pub fn ct_eq(&self, other: Id<'_>) -> bool {
+        self.0.iter().zip(other.0.iter()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+    }
 }
 
 impl<'a> From<&'a [u8; SHA1_SIZE]> for Id<'a> {
@@ -36,7 +56,7 @@ impl<'a> From<&'a [u8; SHA1_SIZE]> for Id<'a> {
 }
 
 impl<'a> TryFrom<&'a [u8]> for Id<'a> {
-    type Error = std::array::TryFromSliceError;
+    type Error = core::array::TryFromSliceError;
 
     fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
         Ok(Id(value.try_into()?))
@@ -2,7 +2,7 @@
 //! can not be serialized directly. Instead, one will convert them into their `owned` counterparts,
 //! which support serialization.
 mod commit;
-pub use commit::Commit;
+pub use commit::{Commit, HeaderValue};
 
 mod id;
 pub use id::*;
@@ -15,7 +15,7 @@ pub mod tree;
 pub use tree::Tree;
 
 mod blob {
-    use std::convert::Infallible;
+    use core::convert::Infallible;
 
     #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
     #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
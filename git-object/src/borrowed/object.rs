@@ -83,7 +83,7 @@ impl<'a> Object<'a> {
 
 mod convert {
     use crate::borrowed::{Blob, Commit, Object, Tag, Tree};
-    use std::convert::TryFrom;
+    use core::convert::TryFrom;
 
     impl<'a> From<Tag<'a>> for Object<'a> {
         fn from(v: Tag<'a>) -> Self {
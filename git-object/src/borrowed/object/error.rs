@@ -1,29 +1,49 @@
+use alloc::string::{String, ToString};
+use bstr::ByteSlice;
+use core::fmt;
 use nom::error::ParseError;
-use quick_error::quick_error;
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        ParseIntegerError(msg: &'static str, number: crate::BString, err: btoi::ParseIntegerError) {
-            display("{}: {:?}", msg, number)
-            source(err)
-        }
-        Nom(err_msg: String) {
-            display("{}", err_msg)
-        }
-        NomDetail(input: crate::BString, msg: &'static str) {
-            display("{}: '{}' could not be parsed", msg, input)
-        }
-        ParseKindError(err: crate::types::Error) {
-            display("{}", err)
-            source(err)
+
+/// The error returned when parsing borrowed objects.
+///
+/// It's implemented by hand as it also has to work without `std`.
+#[derive(Debug)]
+pub enum Error {
+    ParseIntegerError(&'static str, crate::BString, btoi::ParseIntegerError),
+    Nom(String),
+    NomDetail(crate::BString, &'static str),
+    ParseKindError(crate::types::Error),
+    ObjectKind(crate::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ParseIntegerError(msg, number, _) => write!(f, "{}: {:?}", msg, number.as_bstr()),
+            Error::Nom(err_msg) => f.write_str(err_msg),
+            Error::NomDetail(input, msg) => write!(f, "{}: '{}' could not be parsed", msg, input.as_bstr()),
+            Error::ParseKindError(err) => write!(f, "{}", err),
+            Error::ObjectKind(_) => write!(f, "{:?}", self),
         }
-        ObjectKind(err: crate::Error) {
-            from()
-            source(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::ParseIntegerError(_, _, err) => Some(err),
+            Error::ParseKindError(err) | Error::ObjectKind(err) => Some(err),
+            Error::Nom(_) | Error::NomDetail(..) => None,
         }
     }
 }
 
+impl From<crate::Error> for Error {
+    fn from(err: crate::Error) -> Self {
+        Error::ObjectKind(err)
+    }
+}
+
 impl Error {
     fn set_parse_context(mut self, ctx: &'static str) -> Self {
         if let Error::NomDetail(_, ref mut message) = self {
@@ -1,8 +1,10 @@
+use crate::BString;
 use crate::{
     borrowed::{Error, Signature},
     ByteSlice, Sign, Time,
 };
-use bstr::{BStr, BString, ByteVec};
+use alloc::vec::Vec;
+use bstr::BStr;
 use btoi::btoi;
 use nom::{
     branch::alt,
@@ -34,10 +36,10 @@ pub(crate) fn any_header_field_multi_line<'a>(i: &'a [u8]) -> IResult<&'a [u8],
     let bytes = o[..o.len() - 1].as_bstr();
     let mut out = BString::from(Vec::with_capacity(bytes.len()));
     let mut lines = bytes.lines();
-    out.push_str(lines.next().expect("first line"));
+    out.extend_from_slice(lines.next().expect("first line"));
     for line in lines {
         out.push(b'\n');
-        out.push_str(&line[1..]); // cut leading space
+        out.extend_from_slice(&line[1..]); // cut leading space
     }
     Ok((&i[end - start_input + 1..], (k, out)))
 }
@@ -1,14 +1,15 @@
 use crate::{borrowed, borrowed::parse::SPACE, borrowed::Error, TreeMode};
+use alloc::vec::Vec;
 use bstr::{BStr, ByteSlice};
+use core::convert::TryFrom;
 use nom::{
     bytes::complete::{tag, take, take_while1, take_while_m_n},
     character::is_digit,
     combinator::all_consuming,
-    multi::many1,
+    multi::many0,
     sequence::terminated,
     IResult,
 };
-use std::convert::TryFrom;
 
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
@@ -61,7 +62,7 @@ fn parse_entry(i: &[u8]) -> IResult<&[u8], Entry, Error> {
 }
 
 fn parse(i: &[u8]) -> IResult<&[u8], Tree, Error> {
-    let (i, entries) = all_consuming(many1(parse_entry))(i)?;
+    let (i, entries) = all_consuming(many0(parse_entry))(i)?;
     Ok((i, Tree { entries }))
 }
 
@@ -1,5 +1,7 @@
 use crate::borrowed;
 use bstr::{BStr, ByteSlice};
+#[cfg(feature = "encoding")]
+use std::borrow::Cow;
 
 pub struct ExtraHeaders<I> {
     inner: I,
@@ -27,3 +29,17 @@ where
         self.find("gpgsig")
     }
 }
+
+/// Decode `data` of a commit, like its message or the name of its author, from the `encoding` named by its header into
+/// UTF-8, or return `None` if the encoding is unknown.
+///
+/// Without `encoding`, UTF-8 is assumed like git does. Invalid sequences are replaced with `U+FFFD`, and data which is
+/// valid already is borrowed.
+#[cfg(feature = "encoding")]
+pub fn decode<'a>(encoding: Option<&BStr>, data: &'a [u8]) -> Option<Cow<'a, str>> {
+    let encoding = match encoding {
+        Some(label) => encoding_rs::Encoding::for_label_no_replacement(label)?,
+        None => encoding_rs::UTF_8,
+    };
+    Some(encoding.decode_without_bom_handling(data).0)
+}
@@ -1,6 +1,21 @@
+//! Without the default `std` feature, the crate is `no_std` and only needs `alloc` to parse [borrowed] objects and
+//! [ids][owned::Id], while all other [owned] objects are unavailable as they are meant for serialization.
+//!
+//! As `bstr` doesn't provide owned byte strings then, errors hold the bytes they refer to as `Vec<u8>` instead of
+//! `BString`, and multi-line [commit headers][borrowed::Commit::extra_headers] are `Cow<[u8]>`.
+//!
+//! With the `encoding` feature, commits in encodings other than UTF-8 can be [decoded][commit::decode()] for display,
+//! while their raw bytes stay available as parsed.
 #![forbid(unsafe_code)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
-use bstr::{BStr, BString, ByteSlice};
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use bstr::BString;
+use bstr::{BStr, ByteSlice};
+#[cfg(not(feature = "std"))]
+type BString = alloc::vec::Vec<u8>;
 
 /// For convenience to allow using `bstr` without adding it to own cargo manifest
 pub use bstr;
@@ -8,6 +23,11 @@ pub use bstr;
 pub mod borrowed;
 pub mod owned;
 
+/// The borrowed id of an object, as [`ObjectId`] is to owned ids.
+pub use borrowed::Id as oid;
+/// The owned id of an object, for use where the difference to borrowed ids matters more than the one to other owned types.
+pub use owned::Id as ObjectId;
+
 mod types;
 pub use types::*;
 
@@ -25,6 +25,11 @@ impl Commit {
     pub fn extra_headers(&self) -> commit::ExtraHeaders<impl Iterator<Item = (&BStr, &BStr)>> {
         commit::ExtraHeaders::new(self.extra_headers.iter().map(|(k, v)| (k.as_bstr(), v.as_bstr())))
     }
+    /// The message decoded using the [encoding][Commit::encoding] of this commit, or `None` if it's unknown.
+    #[cfg(feature = "encoding")]
+    pub fn decoded_message(&self) -> Option<std::borrow::Cow<'_, str>> {
+        commit::decode(self.encoding.as_ref().map(|e| e.as_bstr()), &self.message)
+    }
     pub fn write_to(&self, mut out: impl io::Write) -> io::Result<()> {
         ser::trusted_header_id(b"tree", &self.tree, &mut out)?;
         for parent in &self.parents {
@@ -1,6 +1,9 @@
 use crate::{borrowed, SHA1_SIZE};
+use alloc::string::{String, ToString};
 use bstr::ByteSlice;
-use std::{fmt, io, ops::Deref};
+use core::{fmt, ops::Deref};
+#[cfg(feature = "std")]
+use std::io;
 
 /// An owned SHA1 identifying objects
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
@@ -20,6 +23,7 @@ impl Id {
     pub fn as_mut_slice(&mut self) -> &mut [u8] {
         self.0.as_mut()
     }
+    #[cfg(feature = "std")]
     pub fn write_to(&self, mut out: impl io::Write) -> io::Result<()> {
         out.write_all(&self.to_sha1_hex())
     }
@@ -44,7 +48,7 @@ impl Id {
     }
     pub fn to_sha1_hex_string(&self) -> String {
         let buf = self.to_sha1_hex();
-        std::str::from_utf8(&buf).expect("hex is valid UTF-8").to_string()
+        core::str::from_utf8(&buf).expect("hex is valid UTF-8").to_string()
     }
     pub fn new_sha1(id: [u8; SHA1_SIZE]) -> Self {
         Id(id)
@@ -64,6 +68,28 @@ impl Id {
     }
 }
 
+/// Comparisons
+impl Id {
+    /// Returns true if the hexadecimal representation of this id starts with `prefix`, see
+    /// [`borrowed::Id::starts_with_hex()`].
+    pub fn starts_with_hex(&self, prefix: &[u8]) -> bool {
+        self.to_borrowed().starts_with_hex(prefix)
+    }
+    /// Compare this id with `other` in constant time, see [`borrowed::Id::ct_eq()`].
+    pub fn ct_eq(&self, other: borrowed::Id<'_>) -> bool {
+        self.to_borrowed().ct_eq(other)
+    }
+}
+
+impl core::str::FromStr for Id {
+    type Err = hex::FromHexError;
+
+    /// Parse the 40 hexadecimal digits of a SHA1 id.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Id::from_40_bytes_in_hex(s.as_bytes())
+    }
+}
+
 impl From<[u8; SHA1_SIZE]> for Id {
     fn from(v: [u8; 20]) -> Self {
         Self::new_sha1(v)
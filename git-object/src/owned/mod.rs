@@ -1,24 +1,35 @@
-//! Owned objects for use with serialization.
+//! Owned objects for use with serialization, of which only [ids][Id] are available without the `std` feature.
 
+#[cfg(feature = "std")]
 pub(crate) const NL: &[u8; 1] = b"\n";
+#[cfg(feature = "std")]
 pub(crate) const SPACE: &[u8; 1] = b" ";
 
+#[cfg(feature = "std")]
 mod convert;
+#[cfg(feature = "std")]
 mod ser;
 
 mod id;
 pub use id::*;
 
+#[cfg(feature = "std")]
 mod tag;
+#[cfg(feature = "std")]
 pub use tag::Tag;
 
+#[cfg(feature = "std")]
 pub mod tree;
+#[cfg(feature = "std")]
 #[doc(inline)]
 pub use tree::Tree;
 
+#[cfg(feature = "std")]
 mod commit;
+#[cfg(feature = "std")]
 pub use commit::Commit;
 
+#[cfg(feature = "std")]
 mod blob {
     use std::io;
 
@@ -34,11 +45,16 @@ mod blob {
         }
     }
 }
+#[cfg(feature = "std")]
 pub use blob::*;
 
+#[cfg(feature = "std")]
 pub mod signature;
+#[cfg(feature = "std")]
 #[doc(inline)]
 pub use signature::Signature;
 
+#[cfg(feature = "std")]
 mod object;
+#[cfg(feature = "std")]
 pub use object::*;
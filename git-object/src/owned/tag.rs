@@ -9,7 +9,7 @@ quick_error! {
         StartsWithDash {
             display("Tags must not start with a dash: '-'")
         }
-        InvalidRefName(err: git_ref::validated::NameError) {
+        InvalidRefName(err: git_ref::name::Error) {
             display("The tag name was no valid reference name")
             from()
             source(err)
@@ -58,7 +58,7 @@ impl Tag {
 }
 
 fn validated_name(name: &BStr) -> Result<&BStr, Error> {
-    git_ref::validated::name(name)?;
+    git_ref::name::validate(name)?;
     if name[0] == b'-' {
         return Err(Error::StartsWithDash);
     }
@@ -1,6 +1,6 @@
-use crate::owned::SPACE;
-use quick_error::quick_error;
-use std::{fmt, io};
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
 
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
@@ -21,9 +21,10 @@ pub struct Time {
 }
 
 impl Time {
+    #[cfg(feature = "std")]
     pub fn write_to(&self, mut out: impl io::Write) -> io::Result<()> {
         itoa::write(&mut out, self.time)?;
-        out.write_all(SPACE)?;
+        out.write_all(crate::owned::SPACE)?;
         out.write_all(&[match self.sign {
             Sign::Plus => b'+',
             Sign::Minus => b'-',
@@ -59,15 +60,23 @@ pub enum Kind {
     Commit,
     Tag,
 }
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        InvalidObjectKind(kind: crate::BString) {
-            display("Unknown object kind: {:?}", std::str::from_utf8(&kind))
+/// The error returned by [`Kind::from_bytes()`], implemented by hand as it also has to work without `std`.
+#[derive(Debug)]
+pub enum Error {
+    InvalidObjectKind(crate::BString),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidObjectKind(kind) => write!(f, "Unknown object kind: {:?}", core::str::from_utf8(kind)),
         }
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
 impl Kind {
     pub fn from_bytes(s: &[u8]) -> Result<Kind, Error> {
         Ok(match s {
@@ -91,7 +100,7 @@ impl Kind {
 
 impl fmt::Display for Kind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(std::str::from_utf8(self.to_bytes()).expect("valid utf8 in kind name"))
+        f.write_str(core::str::from_utf8(self.to_bytes()).expect("valid utf8 in kind name"))
     }
 }
 
@@ -316,3 +316,49 @@ iyBBl69jASy41Ug/BlFJbw4+ItkShpXwkJKuBBV/JExChmvbxYWaS7QnyYC9UO0=
         Ok(())
     }
 }
+
+#[cfg(feature = "encoding")]
+mod decode {
+    use git_object::borrowed::Commit;
+    use std::borrow::Cow;
+
+    fn commit_bytes(encoding: Option<&str>, author: &[u8], message: &[u8]) -> Vec<u8> {
+        let mut data = b"tree 4a1c03029e7407c0afe9fc0320b3258e188b115e\nauthor ".to_vec();
+        data.extend_from_slice(author);
+        data.extend_from_slice(b" <a@example.com> 1592438199 +0800\ncommitter c <c@example.com> 1592438199 +0800\n");
+        if let Some(encoding) = encoding {
+            data.extend_from_slice(format!("encoding {}\n", encoding).as_bytes());
+        }
+        data.push(b'\n');
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn messages_and_names_are_decoded_with_the_encoding_of_the_commit() -> Result<(), Box<dyn std::error::Error>> {
+        let data = commit_bytes(Some("ISO-8859-1"), b"Ren\xe9", b"caf\xe9\n");
+        let commit = Commit::from_bytes(&data)?;
+        assert_eq!(commit.message, &b"caf\xe9\n"[..], "the raw bytes are kept");
+        assert_eq!(commit.decoded_message(), Some("café\n".into()));
+        assert_eq!(commit.decode(commit.author.name), Some("René".into()));
+
+        let data = commit_bytes(Some("Shift_JIS"), b"a", b"\x93\xfa\x96\x7b\x8c\xea");
+        assert_eq!(Commit::from_bytes(&data)?.decoded_message(), Some("日本語".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn without_encoding_utf8_is_borrowed_and_unknown_encodings_are_not_decoded(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let data = commit_bytes(None, b"a", "café\n".as_bytes());
+        let message = Commit::from_bytes(&data)?.decoded_message();
+        assert!(matches!(message, Some(Cow::Borrowed("café\n"))));
+
+        let data = commit_bytes(None, b"a", b"caf\xe9");
+        assert_eq!(Commit::from_bytes(&data)?.decoded_message(), Some("caf\u{fffd}".into()));
+
+        let data = commit_bytes(Some("no-such-encoding"), b"a", b"caf\xe9");
+        assert_eq!(Commit::from_bytes(&data)?.decoded_message(), None);
+        Ok(())
+    }
+}
@@ -60,3 +60,22 @@ mod object {
         )
     }
 }
+
+mod error {
+    use git_object::{borrowed, Kind};
+    use std::error::Error;
+
+    #[test]
+    fn display() {
+        let err = Kind::from_bytes(b"trees").unwrap_err();
+        assert_eq!(err.to_string(), "Unknown object kind: Ok(\"trees\")");
+
+        let err =
+            borrowed::Tag::from_bytes(b"object 0000000000000000000000000000000000000000\ntype foo\n").unwrap_err();
+        assert_eq!(err.to_string(), "Unknown object kind: Ok(\"foo\")");
+
+        let err = borrowed::Commit::from_bytes(b"tree invalid\n").unwrap_err();
+        assert!(err.to_string().contains("could not be parsed"), "{}", err);
+        assert!(err.source().is_none());
+    }
+}
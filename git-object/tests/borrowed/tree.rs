@@ -16,6 +16,12 @@ mod from_bytes {
         id.into()
     }
 
+    #[test]
+    fn empty() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(Tree::from_bytes(&[])?, Tree { entries: vec![] });
+        Ok(())
+    }
+
     #[test]
     fn everything() -> Result<(), Box<dyn std::error::Error>> {
         assert_eq!(
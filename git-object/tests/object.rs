@@ -27,3 +27,36 @@ pub fn fixture(path: &str) -> PathBuf {
 fn fixture_bytes(path: &str) -> Vec<u8> {
     fixup(std::fs::read(fixture(path)).unwrap())
 }
+
+mod id {
+    use crate::hex_to_id;
+    use git_object::{oid, ObjectId};
+
+    #[test]
+    fn starts_with_hex_matches_prefixes_of_any_length_and_case() {
+        let id = hex_to_id("877c3ad4e85fa4408811d3fcc38aef2500f04ade");
+        for prefix in &[
+            "",
+            "8",
+            "87",
+            "877",
+            "877C3aD4",
+            "877c3ad4e85fa4408811d3fcc38aef2500f04ade",
+        ] {
+            assert!(id.starts_with_hex(prefix.as_bytes()), "{} is a prefix", prefix);
+        }
+        for prefix in &["9", "878", "877g", "877c3ad4e85fa4408811d3fcc38aef2500f04ade0"] {
+            assert!(!id.starts_with_hex(prefix.as_bytes()), "{} is no prefix", prefix);
+        }
+    }
+
+    #[test]
+    fn ct_eq_compares_like_eq() {
+        let id: ObjectId = "877c3ad4e85fa4408811d3fcc38aef2500f04ade".parse().unwrap();
+        let other = hex_to_id("cecbafcde689e1bc457bf13334c3d934200802a1");
+        let borrowed: oid = id.to_borrowed();
+        assert!(id.ct_eq(borrowed));
+        assert!(!id.ct_eq(other.to_borrowed()));
+        assert!(!other.to_borrowed().ct_eq(borrowed));
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{compound, loose, open, pack};
+use std::path::{Path, PathBuf};
+
+/// The error returned when opening a [`Store`][compound::Store].
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("Could not read the pack directory at '{}'", .1.display())]
+    Io(#[source] std::io::Error, PathBuf),
+    #[error("Could not open a pack of the object database")]
+    Pack(#[from] pack::bundle::Error),
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::Io(..) => crate::Category::Environment,
+            Error::Pack(err) => err.category(),
+        }
+    }
+}
+
+/// Instantiation
+impl compound::Db {
+    /// Open the `objects` directory at `objects_directory` with all packs in its `pack` sub-directory.
+    pub fn at(objects_directory: impl Into<PathBuf>) -> Result<compound::Db, Error> {
+        compound::Db::at_with_options(objects_directory, open::Options::default())
+    }
+
+    /// Like [`at()`][compound::Db::at()], but open packs with `options`, also when [refreshing][compound::Db::refresh()].
+    pub fn at_with_options(
+        objects_directory: impl Into<PathBuf>,
+        options: open::Options,
+    ) -> Result<compound::Db, Error> {
+        let loose = loose::Db::at(objects_directory);
+        let packs = packs_in(&loose.path.join("pack"), options)?;
+        Ok(compound::Db {
+            loose,
+            packs,
+            missing_object_handler: None,
+            options,
+        })
+    }
+
+    /// Open packs again to pick up ones which were added since the database was instantiated.
+    pub fn refresh(&mut self) -> Result<(), Error> {
+        self.packs = packs_in(&self.loose.path.join("pack"), self.options)?;
+        Ok(())
+    }
+}
+
+pub(crate) fn packs_in(directory: &Path, options: open::Options) -> Result<Vec<pack::Bundle>, Error> {
+    if !directory.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut index_paths = Vec::new();
+    for entry in directory
+        .read_dir()
+        .map_err(|err| Error::Io(err, directory.to_owned()))?
+    {
+        let path = entry.map_err(|err| Error::Io(err, directory.to_owned()))?.path();
+        if path.extension() == Some("idx".as_ref()) && path.with_extension("pack").is_file() {
+            index_paths.push(path);
+        }
+    }
+    index_paths.sort();
+    index_paths
+        .into_iter()
+        .map(|path| pack::Bundle::at_with_options(path, options).map_err(Into::into))
+        .collect()
+}
@@ -0,0 +1,173 @@
+use crate::{compound, loose, pack, prefix};
+use git_object::{borrowed, owned};
+
+/// The error returned by [`Store::locate()`][compound::Store::locate()] and the handles to it.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("An error occurred while looking up a loose object")]
+    Loose(#[from] loose::db::locate::Error),
+    #[error("A loose object could not be decompressed")]
+    LooseDecode(#[from] loose::object::decode::Error),
+    #[error("An error occurred while looking up a packed object")]
+    Pack(#[from] pack::bundle::locate::Error),
+    #[error("The missing object could not be fetched")]
+    MissingObjectHandler(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("The packs could not be opened again after fetching a missing object")]
+    Refresh(#[from] compound::init::Error),
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::Loose(err) => err.category(),
+            Error::LooseDecode(err) => err.category(),
+            Error::Pack(err) => err.category(),
+            Error::MissingObjectHandler(_) => crate::Category::Environment,
+            Error::Refresh(err) => err.category(),
+        }
+    }
+}
+
+/// Object lookup
+impl compound::Db {
+    /// Find the object with `id` in any pack or among the loose objects, and place its data into `out`.
+    pub fn locate<'a>(
+        &self,
+        id: borrowed::Id<'_>,
+        out: &'a mut Vec<u8>,
+        cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<pack::Object<'a>, Error>> {
+        if let Some(index) = self.packs.iter().position(|p| p.index.lookup(id).is_some()) {
+            return self.packs[index]
+                .locate(id, out, cache)
+                .map(|res| res.map_err(Into::into));
+        }
+        match self.loose.locate(id)? {
+            Ok(mut object) => Some(object.decompress_all().map_err(Into::into).map(move |_| {
+                out.clear();
+                out.extend_from_slice(&object.decompressed_data[object.header_size..]);
+                pack::Object {
+                    kind: object.kind,
+                    data: out.as_slice(),
+                }
+            })),
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+
+    /// Like [`locate()`][compound::Db::locate()], but call the missing object handler if `id` is missing and the
+    /// database has promisor packs, and look again if the handler fetched it.
+    pub fn locate_or_fetch<'a>(
+        &mut self,
+        id: borrowed::Id<'_>,
+        out: &'a mut Vec<u8>,
+        cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<pack::Object<'a>, Error>> {
+        if self.contains(id) || !self.has_promisor_packs() {
+            return self.locate(id, out, cache);
+        }
+        let handler = self.missing_object_handler.as_mut()?;
+        match handler(id) {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(err) => return Some(Err(Error::MissingObjectHandler(err))),
+        }
+        if let Err(err) = self.refresh() {
+            return Some(Err(err.into()));
+        }
+        self.locate(id, out, cache)
+    }
+
+    /// Returns true if the object with `id` is contained in any pack or among the loose objects.
+    pub fn contains(&self, id: borrowed::Id<'_>) -> bool {
+        self.packs.iter().any(|p| p.index.lookup(id).is_some())
+            || loose::db::sha1_path(id, self.loose.path.clone()).is_file()
+    }
+
+    /// Return whether each of `ids` is contained in any pack or among the loose objects, in the order of `ids`.
+    ///
+    /// The ids are sorted once to probe each pack index in a single pass, and only the ones found in none of them are
+    /// looked up among the loose objects.
+    pub fn contains_many(&self, ids: &[owned::Id]) -> Vec<bool> {
+        let mut found = vec![false; ids.len()];
+        let pending = in_packs(&self.packs, ids, crate::traits::ascending(ids), &mut found);
+        for position in pending {
+            found[position] = loose::db::sha1_path(ids[position].to_borrowed(), self.loose.path.clone()).is_file();
+        }
+        found
+    }
+
+    /// Find the only object whose id starts with the hexadecimal `prefix` in any pack or among the loose objects, or
+    /// `None` if there is none.
+    ///
+    /// It's an error if more than one object matches.
+    pub fn lookup_prefix(&self, prefix: impl AsRef<[u8]>) -> Result<Option<owned::Id>, prefix::Error> {
+        let prefix = prefix.as_ref();
+        let mut candidates = self.loose.ids_with_prefix(prefix)?;
+        for pack in &self.packs {
+            candidates.extend(pack.index.ids_with_prefix(prefix).map(owned::Id::from));
+        }
+        prefix::unique(prefix, candidates)
+    }
+}
+
+/// Mark the ids at the ascending `pending` positions of `ids` which are in any of `packs` as `found`, and return the
+/// positions of the ones which aren't, still in ascending order.
+pub(crate) fn in_packs(
+    packs: &[pack::Bundle],
+    ids: &[owned::Id],
+    pending: Vec<usize>,
+    found: &mut [bool],
+) -> Vec<usize> {
+    let mut pending = pending;
+    for pack in packs {
+        if pending.is_empty() {
+            break;
+        }
+        let indices = pack
+            .index
+            .lookup_sorted(pending.iter().map(|&position| ids[position].to_borrowed()));
+        pending = pending
+            .into_iter()
+            .zip(indices)
+            .filter_map(|(position, index)| match index {
+                Some(_) => {
+                    found[position] = true;
+                    None
+                }
+                None => Some(position),
+            })
+            .collect();
+    }
+    pending
+}
+
+impl crate::Find for compound::Db {
+    type Error = Error;
+
+    fn locate<'a>(
+        &self,
+        id: borrowed::Id<'_>,
+        buf: &'a mut Vec<u8>,
+        cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<pack::Object<'a>, Self::Error>> {
+        compound::Db::locate(self, id, buf, cache)
+    }
+
+    fn contains(&self, id: borrowed::Id<'_>) -> bool {
+        compound::Db::contains(self, id)
+    }
+
+    fn contains_many(&self, ids: &[owned::Id]) -> Vec<bool> {
+        compound::Db::contains_many(self, ids)
+    }
+
+    fn header(&self, id: borrowed::Id<'_>) -> Option<Result<(git_object::Kind, u64), Self::Error>> {
+        match self.packs.iter().find(|p| p.index.lookup(id).is_some()) {
+            Some(bundle) => crate::Find::header(bundle, id).map(|res| res.map_err(Into::into)),
+            None => crate::Find::header(&self.loose, id).map(|res| res.map_err(Into::into)),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! An object database combining the loose objects and packs of an `objects` directory, as used by repositories.
+//!
+//! In partial clones, packs received from the promisor remote are marked with a `.promisor` file. Objects missing
+//! from such a database may be fetched on demand through a [handler][Db::set_missing_object_handler()].
+use crate::{loose, open, pack};
+use git_object::{borrowed, owned, HashKind};
+use std::io;
+
+pub mod init;
+pub mod locate;
+pub mod store;
+pub use store::{Handle, Store};
+
+/// A function called with the id of an object which is missing from a database with promisor packs.
+///
+/// It should fetch the object into a new pack of the database, and return true if it did so.
+pub type MissingObjectHandler =
+    Box<dyn FnMut(borrowed::Id<'_>) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> + Send>;
+
+/// Loose objects and packs of a single `objects` directory.
+pub struct Db {
+    pub loose: loose::Db,
+    pub packs: Vec<pack::Bundle>,
+    missing_object_handler: Option<MissingObjectHandler>,
+    options: open::Options,
+}
+
+impl Db {
+    /// Returns true if any of the packs was received from a promisor remote, making this the database of a partial clone.
+    pub fn has_promisor_packs(&self) -> bool {
+        self.packs.iter().any(pack::Bundle::is_promisor)
+    }
+
+    /// Call `handler` whenever [`locate_or_fetch()`][Db::locate_or_fetch()] is asked for an object that is missing,
+    /// if the database has promisor packs.
+    pub fn set_missing_object_handler(&mut self, handler: MissingObjectHandler) {
+        self.missing_object_handler = Some(handler);
+    }
+}
+
+/// New objects are written as loose objects.
+impl crate::Write for Db {
+    type Error = loose::db::write::Error;
+
+    fn write_stream(
+        &self,
+        kind: git_object::Kind,
+        size: u64,
+        from: impl io::Read,
+        hash: HashKind,
+    ) -> Result<owned::Id, Self::Error> {
+        self.loose.write_stream(kind, size, from, hash)
+    }
+}
@@ -0,0 +1,196 @@
+//! An object database shared among threads, each of which accesses it through a [`Handle`] of its own.
+//!
+//! Packs are opened only once and kept in a list which handles hold on to, so looking up objects never waits for a
+//! lock. Each handle keeps its own cache for resolving deltas, which is why handles are meant to stay on one thread.
+use crate::{
+    compound::{
+        init,
+        locate::{self, Error},
+    },
+    loose, open, pack,
+};
+use git_object::{borrowed, owned, HashKind};
+use std::{cell::RefCell, io, path::PathBuf, sync::Arc};
+
+/// Counters describing the packs of a [`Store`].
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metrics {
+    /// The amount of packs the store has opened.
+    pub packs: usize,
+    /// The bytes of all pack and index files mapped into memory.
+    pub mapped_bytes: usize,
+}
+
+/// The loose objects and packs of a single `objects` directory, shared by all of its [handles][Handle].
+pub struct Store {
+    loose: loose::Db,
+    packs: parking_lot::RwLock<Arc<Vec<pack::Bundle>>>,
+    options: open::Options,
+}
+
+impl Store {
+    /// Open the `objects` directory at `objects_directory` with all packs in its `pack` sub-directory.
+    pub fn at(objects_directory: impl Into<PathBuf>) -> Result<Arc<Store>, init::Error> {
+        Store::at_with_options(objects_directory, open::Options::default())
+    }
+
+    /// Like [`at()`][Store::at()], but open packs with `options`, also when [refreshing][Store::refresh()].
+    pub fn at_with_options(
+        objects_directory: impl Into<PathBuf>,
+        options: open::Options,
+    ) -> Result<Arc<Store>, init::Error> {
+        let loose = loose::Db::at(objects_directory);
+        let packs = init::packs_in(&loose.path.join("pack"), options)?;
+        Ok(Arc::new(Store {
+            loose,
+            packs: parking_lot::RwLock::new(Arc::new(packs)),
+            options,
+        }))
+    }
+
+    /// Open packs again to pick up ones which were added since the store was opened.
+    ///
+    /// Handles see the new packs once they fail to find an object in the ones they know.
+    pub fn refresh(&self) -> Result<(), init::Error> {
+        let packs = init::packs_in(&self.loose.path.join("pack"), self.options)?;
+        *self.packs.write() = Arc::new(packs);
+        Ok(())
+    }
+
+    /// Return the amount of packs and how much memory they map.
+    pub fn metrics(&self) -> Metrics {
+        let packs = self.packs.read();
+        Metrics {
+            packs: packs.len(),
+            mapped_bytes: packs.iter().map(|p| p.pack.data_len() + p.index.data_len()).sum(),
+        }
+    }
+
+    /// Return a new handle to access the objects in this store with a cache of its own.
+    pub fn to_handle(self: &Arc<Self>) -> Handle {
+        Handle {
+            packs: self.packs.read().clone(),
+            store: Arc::clone(self),
+            cache: RefCell::new(pack::cache::DecodeEntryLRU::default()),
+        }
+    }
+}
+
+/// Access to the objects of a [`Store`] from a single thread, which is cheap to create and to clone.
+pub struct Handle {
+    store: Arc<Store>,
+    packs: Arc<Vec<pack::Bundle>>,
+    cache: RefCell<pack::cache::DecodeEntryLRU>,
+}
+
+impl Clone for Handle {
+    fn clone(&self) -> Self {
+        Handle {
+            store: Arc::clone(&self.store),
+            packs: Arc::clone(&self.packs),
+            cache: RefCell::new(pack::cache::DecodeEntryLRU::default()),
+        }
+    }
+}
+
+impl Handle {
+    /// Find the object with `id` in any pack or among the loose objects using the cache of this handle, and place its
+    /// data into `out`.
+    pub fn locate<'a>(&self, id: borrowed::Id<'_>, out: &'a mut Vec<u8>) -> Option<Result<pack::Object<'a>, Error>> {
+        let mut cache = self.cache.borrow_mut();
+        crate::Find::locate(self, id, out, &mut *cache)
+    }
+
+    /// Return the counters of the cache of this handle, which is used by [`locate()`][Handle::locate()].
+    pub fn cache_metrics(&self) -> pack::cache::Metrics {
+        pack::cache::DecodeEntry::metrics(&*self.cache.borrow())
+    }
+
+    /// Search the packs the store has now first. Without it, they are only searched for objects which can't be found
+    /// in the packs this handle obtained earlier.
+    pub fn refresh(&mut self) {
+        self.packs = self.store.packs.read().clone();
+    }
+
+    fn bundle(&self, id: borrowed::Id<'_>) -> Option<&pack::Bundle> {
+        self.packs.iter().find(|p| p.index.lookup(id).is_some())
+    }
+
+    /// Return the packs of the store if they changed since this handle obtained its own, or `None` otherwise.
+    fn newer_packs(&self) -> Option<Arc<Vec<pack::Bundle>>> {
+        let packs = self.store.packs.read();
+        if Arc::ptr_eq(&packs, &self.packs) {
+            None
+        } else {
+            Some(packs.clone())
+        }
+    }
+}
+
+impl crate::Find for Handle {
+    type Error = Error;
+
+    fn locate<'a>(
+        &self,
+        id: borrowed::Id<'_>,
+        buf: &'a mut Vec<u8>,
+        cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<pack::Object<'a>, Self::Error>> {
+        if let Some(bundle) = self.bundle(id) {
+            return bundle.locate(id, buf, cache).map(|res| res.map_err(Into::into));
+        }
+        if self.store.loose.contains(id) {
+            return crate::Find::locate(&self.store.loose, id, buf, cache).map(|res| res.map_err(Into::into));
+        }
+        let packs = self.newer_packs()?;
+        let bundle = packs.iter().find(|p| p.index.lookup(id).is_some())?;
+        bundle.locate(id, buf, cache).map(|res| res.map_err(Into::into))
+    }
+
+    fn contains(&self, id: borrowed::Id<'_>) -> bool {
+        self.bundle(id).is_some()
+            || self.store.loose.contains(id)
+            || matches!(self.newer_packs(), Some(packs) if packs.iter().any(|p| p.index.lookup(id).is_some()))
+    }
+
+    fn contains_many(&self, ids: &[owned::Id]) -> Vec<bool> {
+        let mut found = vec![false; ids.len()];
+        let mut pending = locate::in_packs(&self.packs, ids, crate::traits::ascending(ids), &mut found);
+        pending.retain(|&position| {
+            found[position] = self.store.loose.contains(ids[position].to_borrowed());
+            !found[position]
+        });
+        if let Some(packs) = self.newer_packs().filter(|_| !pending.is_empty()) {
+            locate::in_packs(&packs, ids, pending, &mut found);
+        }
+        found
+    }
+
+    fn header(&self, id: borrowed::Id<'_>) -> Option<Result<(git_object::Kind, u64), Self::Error>> {
+        if let Some(bundle) = self.bundle(id) {
+            return crate::Find::header(bundle, id).map(|res| res.map_err(Into::into));
+        }
+        if self.store.loose.contains(id) {
+            return crate::Find::header(&self.store.loose, id).map(|res| res.map_err(Into::into));
+        }
+        let packs = self.newer_packs()?;
+        let bundle = packs.iter().find(|p| p.index.lookup(id).is_some())?;
+        crate::Find::header(bundle, id).map(|res| res.map_err(Into::into))
+    }
+}
+
+/// New objects are written as loose objects.
+impl crate::Write for Handle {
+    type Error = loose::db::write::Error;
+
+    fn write_stream(
+        &self,
+        kind: git_object::Kind,
+        size: u64,
+        from: impl io::Read,
+        hash: HashKind,
+    ) -> Result<owned::Id, Self::Error> {
+        self.store.loose.write_stream(kind, size, from, hash)
+    }
+}
@@ -0,0 +1,16 @@
+/// The broad kind of problem an error describes, to decide how to react to it without matching all variants of the
+/// error types of this crate, which may grow over time.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[non_exhaustive]
+pub enum Category {
+    /// The data read is damaged or isn't what it claims to be, like a pack with a checksum mismatch or a truncated index,
+    /// which won't change by trying again.
+    Corruption,
+    /// Accessing the data failed, like reading a file or writing a temporary one, which might succeed another time.
+    Environment,
+    /// The data or arguments aren't supported by the operation, like ref deltas when writing an index or paths without
+    /// the expected extension.
+    Usage,
+    /// The operation was aborted as an [interrupt][git_features::interruptible] was requested.
+    Interrupted,
+}
@@ -2,8 +2,16 @@
 
 mod zlib;
 
+mod error;
+pub use error::Category;
+
+pub mod compound;
 pub mod loose;
+pub mod memory;
+pub mod open;
 pub mod pack;
+pub mod prefix;
+pub mod traverse;
 
 mod sink;
 pub use sink::{sink, Sink};
@@ -1,15 +1,13 @@
 use crate::loose::Db;
 use git_object::owned;
-use quick_error::quick_error;
 use walkdir::WalkDir;
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        WalkDir(err: walkdir::Error) {
-            source(err)
-        }
-    }
+/// The error returned when iterating loose objects.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("Could not traverse the directory of loose objects")]
+    WalkDir(#[source] walkdir::Error),
 }
 
 /// Iteration and traversal
@@ -4,25 +4,30 @@ use crate::{
 };
 use git_object as object;
 use object::borrowed;
-use quick_error::quick_error;
 use smallvec::SmallVec;
 use std::{convert::TryInto, fs, io::Read, path::PathBuf};
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        DecompressFile(err: zlib::Error, path: PathBuf) {
-            display("decompression of loose object at '{}' failed", path.display())
-            source(err)
-        }
-        Decode(err: header::Error) {
-            display("Could not decode header")
-            from()
-            source(err)
-        }
-        Io(err: std::io::Error, action: &'static str, path: PathBuf) {
-            display("Could not {} data at '{}'", action, path.display())
-            source(err)
+/// The error returned by [`Db::locate()`].
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("decompression of loose object at '{}' failed", .1.display())]
+    DecompressFile(#[source] zlib::Error, PathBuf),
+    #[error("Could not decode header")]
+    Decode(#[from] header::Error),
+    #[error("Could not {1} data at '{}'", .2.display())]
+    Io(#[source] std::io::Error, &'static str, PathBuf),
+    #[error("Could not decompress all data of the object")]
+    Decompress(#[from] crate::loose::object::decode::Error),
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::DecompressFile(..) | Error::Decode(_) => crate::Category::Corruption,
+            Error::Io(..) => crate::Category::Environment,
+            Error::Decompress(err) => err.category(),
         }
     }
 }
@@ -116,3 +121,36 @@ impl Db {
         })
     }
 }
+
+impl crate::Find for Db {
+    type Error = Error;
+
+    fn locate<'a>(
+        &self,
+        id: borrowed::Id<'_>,
+        buf: &'a mut Vec<u8>,
+        _cache: &mut impl crate::pack::cache::DecodeEntry,
+    ) -> Option<Result<crate::pack::Object<'a>, Self::Error>> {
+        let mut object = match Db::locate(self, id)? {
+            Ok(object) => object,
+            Err(err) => return Some(Err(err)),
+        };
+        if let Err(err) = object.decompress_all() {
+            return Some(Err(err.into()));
+        }
+        buf.clear();
+        buf.extend_from_slice(&object.decompressed_data[object.header_size..]);
+        Some(Ok(crate::pack::Object {
+            kind: object.kind,
+            data: buf.as_slice(),
+        }))
+    }
+
+    fn contains(&self, id: borrowed::Id<'_>) -> bool {
+        sha1_path(id, self.path.clone()).is_file()
+    }
+
+    fn header(&self, id: borrowed::Id<'_>) -> Option<Result<(object::Kind, u64), Self::Error>> {
+        Db::locate(self, id).map(|res| res.map(|object| (object.kind, object.size as u64)))
+    }
+}
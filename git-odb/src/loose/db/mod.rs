@@ -1,4 +1,5 @@
-use git_object::{borrowed, HashKind};
+use crate::prefix;
+use git_object::{borrowed, owned, HashKind, SHA1_SIZE};
 use std::path::PathBuf;
 
 pub struct Db {
@@ -12,6 +13,49 @@ impl Db {
     }
 }
 
+/// Lookup by prefix
+impl Db {
+    /// Returns the ids of all loose objects starting with the hexadecimal `prefix`, in no particular order.
+    pub fn ids_with_prefix(&self, prefix: &[u8]) -> Result<Vec<owned::Id>, prefix::Error> {
+        let lower_bound = prefix::lower_bound(prefix)?;
+        let hex = lower_bound.to_sha1_hex();
+        let directory = self
+            .path
+            .join(std::str::from_utf8(&hex[..2]).expect("ascii only in hex"));
+        let entries = match directory.read_dir() {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => {
+                return Err(prefix::Error::Io {
+                    source,
+                    path: directory,
+                })
+            }
+        };
+        let mut ids = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|source| prefix::Error::Io {
+                source,
+                path: directory.clone(),
+            })?;
+            let name = entry.file_name();
+            let name = match name.to_str() {
+                Some(name) if name.len() == SHA1_SIZE * 2 - 2 => name,
+                _ => continue,
+            };
+            let mut buf = [0u8; SHA1_SIZE * 2];
+            buf[..2].copy_from_slice(&hex[..2]);
+            buf[2..].copy_from_slice(name.as_bytes());
+            if let Ok(id) = owned::Id::from_40_bytes_in_hex(&buf) {
+                if id.starts_with_hex(prefix) {
+                    ids.push(id);
+                }
+            }
+        }
+        Ok(ids)
+    }
+}
+
 pub(crate) fn sha1_path(id: borrowed::Id, mut root: PathBuf) -> PathBuf {
     match id.kind() {
         HashKind::Sha1 => {
@@ -19,7 +63,7 @@ pub(crate) fn sha1_path(id: borrowed::Id, mut root: PathBuf) -> PathBuf {
             let buf = std::str::from_utf8(&hex).expect("ascii only in hex");
             root.push(&buf[..2]);
             root.push(&buf[2..]);
-            root
+            git_features::path::long(root)
         }
     }
 }
@@ -1,27 +1,19 @@
 use super::Db;
 use crate::{hash, loose, zlib::stream::DeflateWriter};
 use git_object::{owned, HashKind};
-use quick_error::quick_error;
 use std::{fs, io, io::Write, path::PathBuf};
 use tempfile::NamedTempFile;
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        Io(err: io::Error, msg: &'static str, path: PathBuf) {
-            display("Could not {} '{}'", msg, path.display())
-            source(err)
-        }
-        IoRaw(err: io::Error) {
-            display("An IO error occurred while writing an object")
-            source(err)
-            from()
-        }
-        Persist(err: tempfile::PersistError, target: PathBuf) {
-            display("Could not turn temporary file into persisted file at '{}'", target.display())
-            source(err)
-       }
-    }
+/// The error returned when writing loose objects.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("Could not {1} '{}'", .2.display())]
+    Io(#[source] io::Error, &'static str, PathBuf),
+    #[error("An IO error occurred while writing an object")]
+    IoRaw(#[from] io::Error),
+    #[error("Could not turn temporary file into persisted file at '{}'", .1.display())]
+    Persist(#[source] tempfile::PersistError, PathBuf),
 }
 
 impl crate::Write for Db {
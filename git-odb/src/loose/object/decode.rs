@@ -3,26 +3,27 @@ use crate::{loose, zlib};
 use git_object as object;
 use miniz_oxide::inflate::decompress_to_vec_zlib;
 use object::borrowed;
-use quick_error::quick_error;
 use smallvec::SmallVec;
 use std::{io::Read, path::PathBuf};
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        Decompress(err: zlib::Error) {
-            display("decompression of object data failed")
-            from()
-            source(err)
-        }
-        Parse(err: borrowed::Error) {
-            display("Could not parse object object")
-            from()
-            source(err)
-        }
-        Io(err: std::io::Error, action: &'static str, path: PathBuf) {
-            display("Could not {} data at '{}'", action, path.display())
-            source(err)
+/// The error returned when decompressing or parsing loose objects.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("decompression of object data failed")]
+    Decompress(#[from] zlib::Error),
+    #[error("Could not parse object object")]
+    Parse(#[from] borrowed::Error),
+    #[error("Could not {1} data at '{}'", .2.display())]
+    Io(#[source] std::io::Error, &'static str, PathBuf),
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::Decompress(_) | Error::Parse(_) => crate::Category::Corruption,
+            Error::Io(..) => crate::Category::Environment,
         }
     }
 }
@@ -1,23 +1,16 @@
 use byteorder::WriteBytesExt;
 use git_object as object;
-use quick_error::quick_error;
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        ParseIntegerError(msg: &'static str, number: Vec<u8>, err: btoi::ParseIntegerError) {
-            display("{}: {:?}", msg, std::str::from_utf8(number))
-            source(err)
-        }
-        InvalidHeader(msg: &'static str) {
-            display("{}", msg)
-        }
-        ObjectHeader(err: object::Error) {
-            display("Could not parse object kind")
-            from()
-            source(err)
-        }
-    }
+/// The error returned when decoding the header of a loose object.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("{0}: {:?}", std::str::from_utf8(.1))]
+    ParseIntegerError(&'static str, Vec<u8>, #[source] btoi::ParseIntegerError),
+    #[error("{0}")]
+    InvalidHeader(&'static str),
+    #[error("Could not parse object kind")]
+    ObjectHeader(#[from] object::Error),
 }
 
 pub fn decode(input: &[u8]) -> Result<(object::Kind, u64, usize), Error> {
@@ -1,23 +1,26 @@
 use crate::{hash::Write as HashWrite, loose};
 use git_object::{borrowed, owned};
-use quick_error::quick_error;
 use std::io;
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        Io(err: io::Error) {
-            display("reading of object failed")
-            from()
-            source(err)
-        }
-        Decode(err: super::decode::Error) {
-            display("Decoding of object failed")
-            from()
-            source(err)
-        }
-        ChecksumMismatch(desired: owned::Id, actual: owned::Id) {
-            display("Object expected to have id {}, but actual id was {}", desired, actual)
+/// The error returned by [`Object::verify_checksum()`][loose::Object::verify_checksum()].
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("reading of object failed")]
+    Io(#[from] io::Error),
+    #[error("Decoding of object failed")]
+    Decode(#[from] super::decode::Error),
+    #[error("Object expected to have id {desired}, but actual id was {actual}")]
+    ChecksumMismatch { desired: owned::Id, actual: owned::Id },
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::Io(_) => crate::Category::Environment,
+            Error::Decode(err) => err.category(),
+            Error::ChecksumMismatch { .. } => crate::Category::Corruption,
         }
     }
 }
@@ -33,7 +36,10 @@ impl loose::Object {
 
         let actual_id = owned::Id::from(sink.hash.digest());
         if desired != actual_id.to_borrowed() {
-            return Err(Error::ChecksumMismatch(desired.into(), actual_id));
+            return Err(Error::ChecksumMismatch {
+                desired: desired.into(),
+                actual: actual_id,
+            });
         }
         Ok(())
     }
@@ -0,0 +1,103 @@
+//! An object database keeping all objects in memory, for tests, servers creating objects on the fly and anything else
+//! which shouldn't touch the disk.
+//!
+//! Objects are kept sorted by id, so iterating them is deterministic no matter in which order they were written.
+use crate::pack;
+use git_object::{borrowed, owned, HashKind};
+use std::{
+    collections::BTreeMap,
+    convert::{Infallible, TryInto},
+    io::{self, Read},
+};
+
+/// Objects stored in memory, which can be shared among threads.
+#[derive(Default)]
+pub struct Store {
+    objects: parking_lot::RwLock<BTreeMap<owned::Id, (git_object::Kind, Vec<u8>)>>,
+}
+
+impl Store {
+    /// Create a store without any object.
+    pub fn new() -> Self {
+        Store::default()
+    }
+
+    /// Find the object with `id` and place its data into `out`.
+    pub fn locate<'a>(&self, id: borrowed::Id<'_>, out: &'a mut Vec<u8>) -> Option<pack::Object<'a>> {
+        let objects = self.objects.read();
+        let (kind, data) = objects.get(&id.into())?;
+        out.clear();
+        out.extend_from_slice(data);
+        Some(pack::Object {
+            kind: *kind,
+            data: out.as_slice(),
+        })
+    }
+
+    /// Returns true if the object with `id` was written to this store.
+    pub fn contains(&self, id: borrowed::Id<'_>) -> bool {
+        self.objects.read().contains_key(&id.into())
+    }
+
+    /// Return the ids of all objects in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = owned::Id> {
+        self.objects.read().keys().copied().collect::<Vec<_>>().into_iter()
+    }
+
+    /// Return the amount of objects in the store.
+    pub fn len(&self) -> usize {
+        self.objects.read().len()
+    }
+
+    /// Returns true if no object was written yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl crate::Write for Store {
+    type Error = io::Error;
+
+    fn write_stream(
+        &self,
+        kind: git_object::Kind,
+        size: u64,
+        from: impl io::Read,
+        hash: HashKind,
+    ) -> Result<owned::Id, Self::Error> {
+        let mut data = Vec::with_capacity(size.try_into().expect("object size to fit into usize"));
+        from.take(size).read_to_end(&mut data)?;
+        if data.len() as u64 != size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("expected {} bytes of object data, got {}", size, data.len()),
+            ));
+        }
+        let id = crate::sink().write_buf(kind, &data, hash)?;
+        self.objects.write().entry(id).or_insert((kind, data));
+        Ok(id)
+    }
+}
+
+impl crate::Find for Store {
+    type Error = Infallible;
+
+    fn locate<'a>(
+        &self,
+        id: borrowed::Id<'_>,
+        buf: &'a mut Vec<u8>,
+        _cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<pack::Object<'a>, Self::Error>> {
+        Store::locate(self, id, buf).map(Ok)
+    }
+
+    fn contains(&self, id: borrowed::Id<'_>) -> bool {
+        Store::contains(self, id)
+    }
+
+    fn header(&self, id: borrowed::Id<'_>) -> Option<Result<(git_object::Kind, u64), Self::Error>> {
+        let objects = self.objects.read();
+        let (kind, data) = objects.get(&id.into())?;
+        Some(Ok((*kind, data.len() as u64)))
+    }
+}
@@ -0,0 +1,26 @@
+//! Options for opening packs, indices and other files of the object database, to trade the time it takes for how
+//! much of their integrity is assured before they are used.
+//!
+//! Opening files does the least amount of work by default, which suits short-lived command-line programs. Long-running
+//! servers reading files they didn't write themselves may prefer to find corruption early by verifying checksums.
+
+/// How much of a file to verify when opening it.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Verify {
+    /// Only check what's needed to use the file, like its signature and version.
+    #[default]
+    None,
+    /// Additionally check that the header agrees with the size of the file, like the amount of objects in it.
+    Header,
+    /// Additionally hash the whole file and compare it to the checksum in its trailer.
+    Checksum,
+}
+
+/// Configure how files are opened.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Options {
+    /// How much of each file to verify.
+    pub verify: Verify,
+}
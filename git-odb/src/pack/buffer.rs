@@ -0,0 +1,31 @@
+use std::{io, ops::Deref, path::Path};
+
+/// The bytes of a pack or index file, which are memory mapped with the `mmap` feature if they were opened from disk, or
+/// owned otherwise.
+pub(crate) enum Buffer {
+    #[cfg(feature = "mmap")]
+    Mapped(filebuffer::FileBuffer),
+    Owned(Vec<u8>),
+}
+
+impl Buffer {
+    /// Map the file at `path`, or read it entirely without the `mmap` feature.
+    pub fn open(path: &Path) -> io::Result<Buffer> {
+        #[cfg(feature = "mmap")]
+        return filebuffer::FileBuffer::open(path).map(Buffer::Mapped);
+        #[cfg(not(feature = "mmap"))]
+        return std::fs::read(path).map(Buffer::Owned);
+    }
+}
+
+impl Deref for Buffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            #[cfg(feature = "mmap")]
+            Buffer::Mapped(data) => data,
+            Buffer::Owned(data) => data,
+        }
+    }
+}
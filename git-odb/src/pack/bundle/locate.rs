@@ -1,17 +1,110 @@
 use crate::pack;
-use git_object::borrowed;
-use quick_error::quick_error;
-
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        Decode(err: pack::data::decode::Error) {
-            display("Could not decode object")
+use git_object::{borrowed, owned, SHA1_SIZE};
+
+/// The error returned by [`Bundle::locate()`][pack::Bundle::locate()].
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("Could not decode object {id} at pack offset {offset}")]
+    Decode {
+        source: pack::data::decode::Error,
+        id: owned::Id,
+        offset: u64,
+    },
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::Decode { source, .. } => source.category(),
         }
     }
 }
 
+/// How an object is stored in a pack, as returned by [`Bundle::entry_size_info()`][pack::Bundle::entry_size_info()].
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct SizeInfo {
+    pub kind: git_object::Kind,
+    /// The size of the object once decoded.
+    pub size: u64,
+    /// The amount of bytes the entry takes in the pack including its header, like `%(objectsize:disk)` in `git`.
+    pub disk_size: u64,
+    /// The amount of deltas to apply to the base object to obtain this one, or 0 if it isn't a delta.
+    pub delta_depth: u32,
+    /// The object the delta of this entry applies to, or `None` if it isn't a delta, like `%(deltabase)` in `git`.
+    pub base: Option<owned::Id>,
+}
+
 impl pack::Bundle {
+    /// Return how the object with `id` is stored in the pack, or `None` if it isn't in it.
+    ///
+    /// Only the headers of the entry and its bases are read, along with the start of the first delta to learn the
+    /// size of the object it produces. The disk size and the id of an offset delta base are found by scanning the
+    /// offsets in the index once.
+    pub fn entry_size_info(&self, id: borrowed::Id<'_>) -> Option<Result<SizeInfo, Error>> {
+        let idx = self.index.lookup(id)?;
+        let ofs = self.index.pack_offset_at_index(idx);
+        let entry = self.pack.entry(ofs);
+        let error = |source| Error::Decode {
+            source,
+            id: id.into(),
+            offset: ofs,
+        };
+        let lookup = |id: borrowed::Id<'_>| {
+            self.index
+                .lookup(id)
+                .map(|idx| self.pack.entry(self.index.pack_offset_at_index(idx)))
+        };
+        let (kind, size) = match self.pack.decode_header(entry.clone(), lookup) {
+            Ok(header) => header,
+            Err(err) => return Some(Err(error(err))),
+        };
+
+        let mut delta_depth = 0;
+        let mut cursor = entry.clone();
+        loop {
+            cursor = match cursor.header {
+                pack::data::Header::OfsDelta { base_distance } => {
+                    self.pack.entry(cursor.base_pack_offset(base_distance))
+                }
+                pack::data::Header::RefDelta { base_id } => match lookup(base_id.to_borrowed()) {
+                    Some(base) => base,
+                    None => return Some(Err(error(pack::data::decode::Error::DeltaBaseUnresolved(base_id)))),
+                },
+                _ => break,
+            };
+            delta_depth += 1;
+        }
+
+        let base_ofs = match entry.header {
+            pack::data::Header::OfsDelta { base_distance } => Some(entry.base_pack_offset(base_distance)),
+            _ => None,
+        };
+        let mut next_ofs = self.pack.data_len() as u64 - SHA1_SIZE as u64;
+        let mut base = match entry.header {
+            pack::data::Header::RefDelta { base_id } => Some(base_id),
+            _ => None,
+        };
+        for other_idx in 0..self.index.num_objects() {
+            let other_ofs = self.index.pack_offset_at_index(other_idx);
+            if other_ofs > ofs && other_ofs < next_ofs {
+                next_ofs = other_ofs;
+            }
+            if Some(other_ofs) == base_ofs {
+                base = Some(self.index.oid_at_index(other_idx).into());
+            }
+        }
+        Some(Ok(SizeInfo {
+            kind,
+            size,
+            disk_size: next_ofs - ofs,
+            delta_depth,
+            base,
+        }))
+    }
+
     /// `id` is a 20 byte SHA1 of the object to locate in the pack
     ///
     /// Note that ref deltas are automatically resolved within this pack only, which makes this implementation unusable
@@ -37,7 +130,11 @@ impl pack::Bundle {
                 },
                 cache,
             )
-            .map_err(Error::Decode)
+            .map_err(|source| Error::Decode {
+                source,
+                id: id.into(),
+                offset: ofs,
+            })
             .map(move |r| pack::Object {
                 kind: r.kind,
                 data: out.as_slice(),
@@ -45,3 +142,50 @@ impl pack::Bundle {
             .into()
     }
 }
+
+impl crate::Find for pack::Bundle {
+    type Error = Error;
+
+    fn locate<'a>(
+        &self,
+        id: borrowed::Id<'_>,
+        buf: &'a mut Vec<u8>,
+        cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<pack::Object<'a>, Self::Error>> {
+        pack::Bundle::locate(self, id, buf, cache)
+    }
+
+    fn contains(&self, id: borrowed::Id<'_>) -> bool {
+        self.index.lookup(id).is_some()
+    }
+
+    fn contains_many(&self, ids: &[owned::Id]) -> Vec<bool> {
+        let positions = crate::traits::ascending(ids);
+        let indices = self
+            .index
+            .lookup_sorted(positions.iter().map(|&position| ids[position].to_borrowed()));
+        let mut found = vec![false; ids.len()];
+        for (position, index) in positions.into_iter().zip(indices) {
+            found[position] = index.is_some();
+        }
+        found
+    }
+
+    fn header(&self, id: borrowed::Id<'_>) -> Option<Result<(git_object::Kind, u64), Self::Error>> {
+        let idx = self.index.lookup(id)?;
+        let ofs = self.index.pack_offset_at_index(idx);
+        let entry = self.pack.entry(ofs);
+        self.pack
+            .decode_header(entry, |id| {
+                self.index
+                    .lookup(id)
+                    .map(|idx| self.pack.entry(self.index.pack_offset_at_index(idx)))
+            })
+            .map_err(|source| Error::Decode {
+                source,
+                id: id.into(),
+                offset: ofs,
+            })
+            .into()
+    }
+}
@@ -1,5 +1,4 @@
-use crate::pack;
-use quick_error::quick_error;
+use crate::{open, pack};
 use std::{
     convert::TryFrom,
     path::{Path, PathBuf},
@@ -8,21 +7,25 @@ use std::{
 pub mod locate;
 pub mod write;
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        InvalidPath(path: PathBuf) {
-            display("An 'idx' extension is expected of an index file: '{}'", path.display())
-        }
-        Pack(err: pack::data::parse::Error) {
-            display("Could not instantiate pack")
-            from()
-            source(err)
-        }
-        Index(err: pack::index::init::Error) {
-            display("Could not instantiate pack index")
-            from()
-            source(err)
+/// The error returned when opening a [`Bundle`].
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("An 'idx' extension is expected of an index file: '{}'", .0.display())]
+    InvalidPath(PathBuf),
+    #[error("Could not instantiate pack")]
+    Pack(#[from] pack::data::parse::Error),
+    #[error("Could not instantiate pack index")]
+    Index(#[from] pack::index::init::Error),
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::InvalidPath(_) => crate::Category::Usage,
+            Error::Pack(err) => err.category(),
+            Error::Index(err) => err.category(),
         }
     }
 }
@@ -38,26 +41,40 @@ impl Bundle {
     pub fn at(path: impl AsRef<Path>) -> Result<Self, Error> {
         Self::try_from(path.as_ref())
     }
-}
 
-impl TryFrom<&Path> for Bundle {
-    type Error = Error;
-
-    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+    /// Like [`at()`][Bundle::at()], but verify as much of the pack and its index as configured in `options` before
+    /// returning them.
+    pub fn at_with_options(path: impl AsRef<Path>, options: open::Options) -> Result<Self, Error> {
+        let path = path.as_ref();
         let ext = path
             .extension()
             .and_then(|e| e.to_str())
             .ok_or_else(|| Error::InvalidPath(path.to_owned()))?;
         Ok(match ext {
             "idx" => Self {
-                index: pack::index::File::at(path)?,
-                pack: pack::data::File::at(path.with_extension("pack"))?,
+                index: pack::index::File::at_with_options(path, options)?,
+                pack: pack::data::File::at_with_options(path.with_extension("pack"), options)?,
             },
             "pack" => Self {
-                pack: pack::data::File::at(path)?,
-                index: pack::index::File::at(path.with_extension("idx"))?,
+                pack: pack::data::File::at_with_options(path, options)?,
+                index: pack::index::File::at_with_options(path.with_extension("idx"), options)?,
             },
             _ => return Err(Error::InvalidPath(path.to_owned())),
         })
     }
+
+    /// Returns true if the pack was received from a promisor remote, as indicated by a `.promisor` file next to it.
+    ///
+    /// Objects referenced by such packs may be missing on purpose as they can be fetched from the remote on demand.
+    pub fn is_promisor(&self) -> bool {
+        self.pack.path().with_extension("promisor").is_file()
+    }
+}
+
+impl TryFrom<&Path> for Bundle {
+    type Error = Error;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        Self::at_with_options(path, open::Options::default())
+    }
 }
@@ -1,29 +1,27 @@
 use crate::pack;
-use quick_error::quick_error;
 use std::io;
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        Io(err: io::Error) {
-            display("An IO error occurred when reading the pack or creating a temporary file")
-            from()
-            source(err)
-        }
-        PackIter(err: pack::data::iter::Error) {
-            display("Pack iteration failed")
-            from()
-            source(err)
-        }
-        PeristError(err: tempfile::PersistError) {
-            display("Could not move a temporary file into its desired place")
-            from()
-            source(err)
-        }
-        IndexWrite(err: pack::index::write::Error) {
-            display("The index file could not be written")
-            from()
-            source(err)
+/// The error returned when writing a pack along with its index.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("An IO error occurred when reading the pack or creating a temporary file")]
+    Io(#[from] io::Error),
+    #[error("Pack iteration failed")]
+    PackIter(#[from] pack::data::iter::Error),
+    #[error("Could not move a temporary file into its desired place")]
+    PeristError(#[from] tempfile::PersistError),
+    #[error("The index file could not be written")]
+    IndexWrite(#[from] pack::index::write::Error),
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::Io(_) | Error::PeristError(_) => crate::Category::Environment,
+            Error::PackIter(err) => err.category(),
+            Error::IndexWrite(err) => err.category(),
         }
     }
 }
@@ -1,5 +1,3 @@
-use filebuffer::FileBuffer;
-
 use crate::pack;
 use git_features::{interruptible, progress, progress::Progress};
 use std::{
@@ -10,7 +8,7 @@ use std::{
 use tempfile::NamedTempFile;
 
 mod error;
-use error::Error;
+pub use error::Error;
 
 mod types;
 pub use types::Outcome;
@@ -55,6 +53,7 @@ impl pack::Bundle {
             None => NamedTempFile::new()?,
         }));
         let data_path: PathBuf = data_file.lock().path().into();
+        let _data_registration = git_features::tempfile::register(&data_path, git_features::tempfile::Kind::File);
         let pack = PassThrough {
             reader: interruptible::Read { inner: pack },
             writer: Some(data_file.clone()),
@@ -75,6 +74,8 @@ impl pack::Bundle {
             Some(directory) => {
                 let directory = directory.as_ref();
                 let mut index_file = NamedTempFile::new_in(directory)?;
+                let _index_registration =
+                    git_features::tempfile::register(index_file.path(), git_features::tempfile::Kind::File);
 
                 let outcome = pack::index::File::write_data_iter_to_stream(
                     index_kind,
@@ -129,7 +130,7 @@ impl pack::Bundle {
 fn new_pack_file_resolver(
     data_path: PathBuf,
 ) -> io::Result<impl Fn(pack::data::EntrySlice, &mut Vec<u8>) -> Option<()> + Send + Sync> {
-    let mapped_file = FileBuffer::open(data_path)?;
+    let mapped_file = pack::Buffer::open(&data_path)?;
     let pack_data_lookup = move |range: std::ops::Range<u64>, out: &mut Vec<u8>| -> Option<()> {
         mapped_file
             .get(range.start as usize..range.end as usize)
@@ -1,6 +1,27 @@
 pub trait DecodeEntry {
     fn put(&mut self, offset: u64, data: &[u8], kind: git_object::Kind, compressed_size: usize);
     fn get(&mut self, offset: u64, out: &mut Vec<u8>) -> Option<(git_object::Kind, usize)>;
+
+    /// Return how well the cache performed so far, which is nothing for caches not keeping track of it.
+    fn metrics(&self) -> Metrics {
+        Metrics::default()
+    }
+}
+
+/// Counters of a cache, to help choosing its size.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metrics {
+    /// The amount of entries which were found in the cache.
+    pub hits: usize,
+    /// The amount of entries which weren't in the cache.
+    pub misses: usize,
+    /// The amount of entries which were removed to make room for new ones.
+    pub evictions: usize,
+    /// The amount of entries in the cache right now.
+    pub entries: usize,
+    /// The decoded bytes of all entries in the cache right now.
+    pub cached_bytes: usize,
 }
 
 pub struct DecodeEntryNoop;
@@ -19,21 +40,37 @@ struct LRUEntry {
     compressed_size: usize,
 }
 
+const LRU_CAPACITY: usize = 64;
+
 #[derive(Default)]
-pub struct DecodeEntryLRU(uluru::LRUCache<[uluru::Entry<LRUEntry>; 64]>);
+pub struct DecodeEntryLRU {
+    entries: uluru::LRUCache<[uluru::Entry<LRUEntry>; LRU_CAPACITY]>,
+    metrics: Metrics,
+}
 
 impl DecodeEntry for DecodeEntryLRU {
     fn put(&mut self, offset: u64, data: &[u8], kind: git_object::Kind, compressed_size: usize) {
-        self.0.insert(LRUEntry {
+        if self.entries.num_entries() == LRU_CAPACITY {
+            let mut least_recently_used = 0;
+            self.entries.lookup(|e: &mut LRUEntry| -> Option<()> {
+                least_recently_used = e.data.len();
+                None
+            });
+            self.metrics.evictions += 1;
+            self.metrics.cached_bytes -= least_recently_used;
+        }
+        self.metrics.cached_bytes += data.len();
+        self.entries.insert(LRUEntry {
             offset,
             data: Vec::from(data),
             kind,
             compressed_size,
-        })
+        });
+        self.metrics.entries = self.entries.num_entries();
     }
 
     fn get(&mut self, offset: u64, out: &mut Vec<u8>) -> Option<(git_object::Kind, usize)> {
-        self.0.lookup(|e: &mut LRUEntry| {
+        let res = self.entries.lookup(|e: &mut LRUEntry| {
             if e.offset == offset {
                 out.resize(e.data.len(), 0);
                 out.copy_from_slice(&e.data);
@@ -41,6 +78,16 @@ impl DecodeEntry for DecodeEntryLRU {
             } else {
                 None
             }
-        })
+        });
+        if res.is_some() {
+            self.metrics.hits += 1;
+        } else {
+            self.metrics.misses += 1;
+        }
+        res
+    }
+
+    fn metrics(&self) -> Metrics {
+        self.metrics
     }
 }
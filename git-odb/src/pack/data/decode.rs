@@ -3,19 +3,29 @@ use crate::{
     zlib,
 };
 use git_object::{self as object, borrowed, owned};
-use quick_error::quick_error;
 use smallvec::SmallVec;
 use std::{convert::TryInto, io, ops::Range};
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        ZlibInflate(err: crate::zlib::Error, msg: &'static str) {
-            display("{}", msg)
-            source(err)
-        }
-        DeltaBaseUnresolved(id: owned::Id) {
-            display("A delta chain could not be applied as the ref base with id {} could not be found", id)
+/// The error returned when decoding pack entries.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("{message} at data offset {data_offset}")]
+    ZlibInflate {
+        source: crate::zlib::Error,
+        message: &'static str,
+        data_offset: u64,
+    },
+    #[error("A delta chain could not be applied as the ref base with id {0} could not be found")]
+    DeltaBaseUnresolved(owned::Id),
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::ZlibInflate { .. } => crate::Category::Corruption,
+            Error::DeltaBaseUnresolved(_) => crate::Category::Usage,
         }
     }
 }
@@ -114,7 +124,11 @@ impl File {
 
         zlib::Inflate::default()
             .once(&self.data[offset..], out, true)
-            .map_err(|e| Error::ZlibInflate(e, "Failed to decompress pack entry"))
+            .map_err(|source| Error::ZlibInflate {
+                source,
+                message: "Failed to decompress pack entry",
+                data_offset,
+            })
             .map(|(_, consumed_in, _)| consumed_in)
     }
 
@@ -151,6 +165,47 @@ impl File {
         }
     }
 
+    /// Obtain the kind and size of the object at `entry` without decoding it, following its deltas to the base for the
+    /// kind and reading the size from the instructions of the first delta. `resolve` looks up the bases of ref deltas.
+    pub fn decode_header(
+        &self,
+        mut entry: pack::data::Entry,
+        resolve: impl Fn(borrowed::Id) -> Option<pack::data::Entry>,
+    ) -> Result<(object::Kind, u64), Error> {
+        use crate::pack::data::header::Header::*;
+        let mut size = None;
+        loop {
+            if entry.header.is_delta() && size.is_none() {
+                size = Some(self.delta_result_size(&entry)?);
+            }
+            entry = match entry.header {
+                Tree | Blob | Commit | Tag => {
+                    let kind = entry.header.to_kind().expect("a non-delta entry");
+                    return Ok((kind, size.unwrap_or(entry.decompressed_size)));
+                }
+                OfsDelta { base_distance } => self.entry(entry.base_pack_offset(base_distance)),
+                RefDelta { base_id } => resolve(base_id.to_borrowed()).ok_or(Error::DeltaBaseUnresolved(base_id))?,
+            };
+        }
+    }
+
+    /// Read the size of the object the delta at `entry` produces from the start of its instructions.
+    fn delta_result_size(&self, entry: &pack::data::Entry) -> Result<u64, Error> {
+        let offset: usize = entry.data_offset.try_into().expect("offset representable by machine");
+        assert!(offset < self.data.len(), "entry offset out of bounds");
+        let mut instructions = [0u8; 20];
+        let len = entry.decompressed_size.min(instructions.len() as u64) as usize;
+        zlib::Inflate::default()
+            .once(&self.data[offset..], &mut instructions[..len], true)
+            .map_err(|source| Error::ZlibInflate {
+                source,
+                message: "Failed to decompress delta instructions",
+                data_offset: entry.data_offset,
+            })?;
+        let (_base_size, consumed) = delta_header_size_ofs(&instructions[..len]);
+        Ok(delta_header_size_ofs(&instructions[consumed..len]).0)
+    }
+
     /// resolve: technically, this shoudln't ever be required as stored local packs don't refer to objects by id
     /// that are outside of the pack. Unless, of course, the ref refers to an object within this pack, which means
     /// it's very, very large as 20bytes are smaller than the corresponding MSB encoded number
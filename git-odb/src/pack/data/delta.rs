@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+/// The size of the blocks of the base to find in the target, which is also the smallest copy produced.
+const BLOCK_SIZE: usize = 16;
+/// The most bytes a single insert instruction can carry.
+const MAX_INSERT: usize = 0x7f;
+/// The most bytes a single copy instruction can copy, as its size is encoded in 3 bytes.
+const MAX_COPY: usize = 0xff_ffff;
+
+/// Produce the instructions to recreate `target` from `base`, in the format of deltas in packs.
+///
+/// Blocks of the base are looked up at each position of the target and extended as far as possible, all other bytes
+/// of the target are inserted.
+pub(crate) fn encode(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut blocks = HashMap::new();
+    for (index, block) in base.chunks_exact(BLOCK_SIZE).enumerate() {
+        blocks.entry(block).or_insert(index * BLOCK_SIZE);
+    }
+
+    let mut out = Vec::new();
+    write_size(base.len(), &mut out);
+    write_size(target.len(), &mut out);
+    let mut insert_start = 0;
+    let mut pos = 0;
+    while pos + BLOCK_SIZE <= target.len() {
+        let base_offset = match blocks.get(&target[pos..pos + BLOCK_SIZE]) {
+            Some(offset) => *offset,
+            None => {
+                pos += 1;
+                continue;
+            }
+        };
+        let len = base[base_offset..]
+            .iter()
+            .zip(&target[pos..])
+            .take_while(|(a, b)| a == b)
+            .count();
+        write_insert(&target[insert_start..pos], &mut out);
+        write_copy(base_offset, len, &mut out);
+        pos += len;
+        insert_start = pos;
+    }
+    write_insert(&target[insert_start..], &mut out);
+    out
+}
+
+fn write_size(mut size: usize, out: &mut Vec<u8>) {
+    while size >= 0x80 {
+        out.push(size as u8 | 0x80);
+        size >>= 7;
+    }
+    out.push(size as u8);
+}
+
+fn write_insert(data: &[u8], out: &mut Vec<u8>) {
+    for chunk in data.chunks(MAX_INSERT) {
+        out.push(chunk.len() as u8);
+        out.extend_from_slice(chunk);
+    }
+}
+
+fn write_copy(mut offset: usize, mut len: usize, out: &mut Vec<u8>) {
+    while len > 0 {
+        let size = len.min(MAX_COPY);
+        let cmd_pos = out.len();
+        let mut cmd = 0b1000_0000;
+        for byte in 0..4 {
+            let value = (offset >> (byte * 8)) as u8;
+            if value != 0 {
+                cmd |= 1 << byte;
+                out.push(value);
+            }
+        }
+        for byte in 0..3 {
+            let value = (size >> (byte * 8)) as u8;
+            if value != 0 {
+                cmd |= 1 << (4 + byte);
+                out.push(value);
+            }
+        }
+        out.insert(cmd_pos, cmd);
+        offset += size;
+        len -= size;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod encode {
+        use crate::pack::data::{
+            decode::{apply_delta, delta_header_size_ofs},
+            delta,
+        };
+
+        fn round_trip(base: &[u8], target: &[u8]) -> Vec<u8> {
+            let delta = delta::encode(base, target);
+            let (base_size, consumed) = delta_header_size_ofs(&delta);
+            assert_eq!(base_size, base.len() as u64);
+            let (target_size, consumed_target) = delta_header_size_ofs(&delta[consumed..]);
+            assert_eq!(target_size, target.len() as u64);
+            let mut actual = vec![0; target.len()];
+            apply_delta(base, &mut actual, &delta[consumed + consumed_target..]);
+            assert_eq!(actual, target);
+            delta
+        }
+
+        #[test]
+        fn similar_objects_produce_small_deltas() {
+            let base: Vec<u8> = (0..2000u32)
+                .flat_map(|n| format!("line {}\n", n).into_bytes())
+                .collect();
+            let mut target = base.clone();
+            target.splice(5000..5010, b"changed in the middle".iter().copied());
+            target.extend_from_slice(b"and at the end");
+            assert!(round_trip(&base, &target).len() < 100);
+        }
+
+        #[test]
+        fn unrelated_and_empty_objects_are_inserted() {
+            round_trip(b"", b"");
+            round_trip(b"base", b"");
+            round_trip(b"", &[1; 300]);
+            round_trip(&[2; 15], &[3; 200]);
+            round_trip(&[4; 100_000], &[4; 100_000]);
+        }
+    }
+}
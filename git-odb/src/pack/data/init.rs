@@ -1,36 +1,73 @@
-use crate::pack::data;
-use filebuffer::FileBuffer;
+use crate::{
+    open,
+    pack::{data, Buffer},
+};
+use git_features::progress;
 use git_object::SHA1_SIZE;
-use std::{convert::TryFrom, convert::TryInto, path::Path};
+use std::{
+    convert::TryFrom,
+    convert::TryInto,
+    path::{Path, PathBuf},
+};
+
+/// The smallest possible entry, with a single byte of header and an empty zlib stream.
+const MIN_ENTRY_SIZE: usize = 1 + 8;
 
 /// Instantiation
 impl data::File {
     pub fn at(path: impl AsRef<Path>) -> Result<data::File, data::parse::Error> {
         data::File::try_from(path.as_ref())
     }
-}
 
-impl TryFrom<&Path> for data::File {
-    type Error = data::parse::Error;
+    /// Like [`at()`][data::File::at()], but verify as much of the pack as configured in `options` before returning it.
+    pub fn at_with_options(path: impl AsRef<Path>, options: open::Options) -> Result<data::File, data::parse::Error> {
+        let pack = data::File::at(path)?;
+        if options.verify >= open::Verify::Header {
+            let size = pack.data.len();
+            let entries_size = size - 12 - SHA1_SIZE;
+            if (pack.num_objects as usize).saturating_mul(MIN_ENTRY_SIZE) > entries_size {
+                return Err(data::parse::Error::InconsistentHeader {
+                    num_objects: pack.num_objects,
+                    size,
+                });
+            }
+        }
+        if options.verify >= open::Verify::Checksum {
+            pack.verify_checksum(progress::Discard)?;
+        }
+        Ok(pack)
+    }
 
-    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+    /// Parse the pack in `data`, which is useful where there is no file system, like in browsers.
+    ///
+    /// The [path][data::File::path()] of the returned pack is empty.
+    pub fn from_data(data: Vec<u8>) -> Result<data::File, data::parse::Error> {
+        data::File::from_buffer(Buffer::Owned(data), PathBuf::new())
+    }
+
+    fn from_buffer(data: Buffer, path: PathBuf) -> Result<data::File, data::parse::Error> {
         use data::parse::N32_SIZE;
 
-        let data = FileBuffer::open(path).map_err(|e| data::parse::Error::Io(e, path.to_owned()))?;
         let pack_len = data.len();
         if pack_len < N32_SIZE * 3 + SHA1_SIZE {
-            return Err(data::parse::Error::Corrupt(format!(
-                "Pack data of size {} is too small for even an empty pack",
-                pack_len
-            )));
+            return Err(data::parse::Error::TooSmall { size: pack_len });
         }
         let (kind, num_objects) =
             data::parse::header(&data[..12].try_into().expect("enough data after previous check"))?;
         Ok(data::File {
             data,
-            path: path.to_owned(),
+            path,
             kind,
             num_objects,
         })
     }
 }
+
+impl TryFrom<&Path> for data::File {
+    type Error = data::parse::Error;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let data = Buffer::open(path).map_err(|e| data::parse::Error::Io(e, path.to_owned()))?;
+        data::File::from_buffer(data, path.to_owned())
+    }
+}
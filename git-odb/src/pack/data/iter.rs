@@ -2,24 +2,32 @@ use crate::zlib::stream::inflate::InflateReaderBoxed;
 use crate::{hash, pack, zlib::stream::inflate::Inflate};
 use git_features::hash::Sha1;
 use git_object::owned;
-use quick_error::quick_error;
 use std::{fs, io};
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        Io(err: io::Error) {
-            display("An IO operation failed while streaming an entry")
-            from()
-            source(err)
-        }
-        PackParse(err: pack::data::parse::Error) {
-            display("The pack header could not be parsed")
-            from()
-            source(err)
-        }
-        ChecksumMismatch { expected: owned::Id, actual: owned::Id } {
-            display("pack checksum in trailer was {}, but actual checksum was {}", expected, actual)
+/// The error returned when streaming pack entries.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("An IO operation failed while streaming an entry")]
+    Io(#[from] io::Error),
+    #[error("The pack header could not be parsed")]
+    PackParse(#[from] pack::data::parse::Error),
+    #[error("pack checksum in trailer was {expected}, but actual checksum was {actual}")]
+    ChecksumMismatch { expected: owned::Id, actual: owned::Id },
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::Io(err)
+                if err.kind() == io::ErrorKind::InvalidData || err.kind() == io::ErrorKind::UnexpectedEof =>
+            {
+                crate::Category::Corruption
+            }
+            Error::Io(_) => crate::Category::Environment,
+            Error::PackParse(err) => err.category(),
+            Error::ChecksumMismatch { .. } => crate::Category::Corruption,
         }
     }
 }
@@ -58,7 +66,6 @@ pub struct Iter<R> {
     hash: Option<Sha1>,
     mode: Mode,
     compressed: CompressedBytesMode,
-    compressed_buf: Option<Vec<u8>>,
 }
 
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
@@ -78,6 +85,11 @@ pub enum Mode {
     Restore,
 }
 
+/// Determine what to do with the compressed bytes of each entry.
+///
+/// Unless they are kept, they are digested while streaming through and never buffered, so memory usage doesn't grow
+/// with the size of objects. Use the `pack_offset`, `header_size` and `compressed_size` of entries to read them again
+/// from a pack on disk if needed.
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompressedBytesMode {
@@ -147,7 +159,6 @@ where
                 None
             },
             mode: trailer,
-            compressed_buf: None,
         })
     }
 
@@ -172,17 +183,28 @@ where
         }
         .map_err(Error::from)?;
 
-        // Decompress object to learn it's compressed bytes
+        // Decompress object to learn it's compressed bytes, which are digested as they pass by and only kept if desired
+        let crc32 = if self.compressed.crc32() {
+            let mut header_buf = [0u8; 32];
+            let header_len = entry.header.to_write(entry.decompressed_size, header_buf.as_mut())?;
+            Some(git_features::hash::crc32_update(0, &header_buf[..header_len]))
+        } else {
+            None
+        };
         let mut decompressor = self.decompressor.take().unwrap_or_default();
-        let compressed_buf = self.compressed_buf.take().unwrap_or_else(|| Vec::with_capacity(4096));
         decompressor.reset();
         let mut decompressed_reader = InflateReaderBoxed {
             inner: read_and_pass_to(
                 &mut self.read,
-                if self.compressed.keep() {
-                    Vec::with_capacity(entry.decompressed_size as usize)
-                } else {
-                    compressed_buf
+                CompressedDigest {
+                    hash: self.hash.take(),
+                    crc32,
+                    kept: if self.compressed.keep() {
+                        Some(Vec::with_capacity(entry.decompressed_size as usize))
+                    } else {
+                        None
+                    },
+                    len: 0,
                 },
             ),
             decompressor,
@@ -200,32 +222,17 @@ where
         self.offset += entry.header_size() as u64 + compressed_size;
         self.decompressor = Some(decompressed_reader.decompressor);
 
-        let mut compressed = decompressed_reader.inner.write;
+        let CompressedDigest {
+            hash,
+            crc32,
+            kept: compressed,
+            len,
+        } = decompressed_reader.inner.write;
         debug_assert_eq!(
-            compressed_size,
-            compressed.len() as u64,
+            compressed_size, len,
             "we must track exactly the same amount of bytes as read by the decompressor"
         );
-        if let Some(hash) = self.hash.as_mut() {
-            hash.update(&compressed);
-        }
-
-        let crc32 = if self.compressed.crc32() {
-            let mut header_buf = [0u8; 32];
-            let header_len = entry.header.to_write(bytes_copied, header_buf.as_mut())?;
-            let state = git_features::hash::crc32_update(0, &header_buf[..header_len]);
-            Some(git_features::hash::crc32_update(state, &compressed))
-        } else {
-            None
-        };
-
-        let compressed = if self.compressed.keep() {
-            Some(compressed)
-        } else {
-            compressed.clear();
-            self.compressed_buf = Some(compressed);
-            None
-        };
+        self.hash = hash;
 
         // Last objects gets trailer (which is potentially verified)
         let trailer = if self.objects_left == 0 {
@@ -301,6 +308,34 @@ where
 }
 impl<R> std::iter::ExactSizeIterator for Iter<R> where R: io::BufRead {}
 
+/// Receives the compressed bytes of an entry to update the pack hash and the entry's CRC32 with, and keeps them if set.
+struct CompressedDigest {
+    hash: Option<Sha1>,
+    crc32: Option<u32>,
+    kept: Option<Vec<u8>>,
+    len: u64,
+}
+
+impl io::Write for CompressedDigest {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(hash) = self.hash.as_mut() {
+            hash.update(buf);
+        }
+        if let Some(crc32) = self.crc32.as_mut() {
+            *crc32 = git_features::hash::crc32_update(*crc32, buf);
+        }
+        if let Some(kept) = self.kept.as_mut() {
+            kept.extend_from_slice(buf);
+        }
+        self.len += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 struct PassThrough<R, W> {
     read: R,
     write: W,
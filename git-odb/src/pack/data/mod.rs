@@ -1,14 +1,15 @@
 //! data within a pack file
-use filebuffer::FileBuffer;
 use std::{convert::TryInto, path::Path};
 
 pub mod decode;
+mod delta;
 mod header;
 pub use header::*;
 
 pub mod init;
 pub mod parse;
 pub mod verify;
+pub mod write;
 
 pub mod iter;
 use git_object::SHA1_SIZE;
@@ -24,7 +25,7 @@ pub enum Kind {
 }
 
 pub struct File {
-    data: FileBuffer,
+    data: crate::pack::Buffer,
     path: std::path::PathBuf,
     kind: Kind,
     num_objects: u32,
@@ -52,16 +53,31 @@ impl File {
         &self.path
     }
 
+    /// All bytes of all entries, without the pack header and the trailing hash, for use when copying pack entries
+    /// without decoding them. The first byte is the one at pack offset [`HEADER_LEN`][File::HEADER_LEN].
+    pub fn entries_data(&self) -> &[u8] {
+        &self.data[Self::HEADER_LEN..self.pack_end()]
+    }
+
+    /// The bytes of the entry at the pack offsets in `slice`, with the entry header being the first bytes, directly
+    /// referencing the mapped pack.
+    ///
+    /// Returns `None` if `slice` doesn't lie between the pack header and the trailing hash.
     pub fn entry_slice(&self, slice: EntrySlice) -> Option<&[u8]> {
-        let entry_end: usize = slice.end.try_into().expect("end of pack fits into usize");
-        let entry_start = slice.start as usize;
+        let entry_start: usize = slice.start.try_into().ok()?;
+        let entry_end: usize = slice.end.try_into().ok()?;
+        if entry_start < Self::HEADER_LEN || entry_end > self.pack_end() {
+            return None;
+        }
         self.data.get(entry_start..entry_end)
     }
 
     /// Currently only done during pack verification - finding the right size is only possible by decompressing
     /// the pack entry beforehand, or by using the (to be sorted) offsets stored in an index file.
     pub fn entry_crc32(&self, pack_offset: u64, size: usize) -> u32 {
-        let pack_offset: usize = pack_offset.try_into().expect("pack_size fits into usize");
-        git_features::hash::crc32(&self.data[pack_offset..pack_offset + size])
+        git_features::hash::crc32(
+            self.entry_slice(pack_offset..pack_offset + size as u64)
+                .expect("entry to lie within the pack"),
+        )
     }
 }
@@ -1,21 +1,36 @@
 use crate::pack::data;
 use byteorder::{BigEndian, ByteOrder};
-use quick_error::quick_error;
 
 pub(crate) const N32_SIZE: usize = std::mem::size_of::<u32>();
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        Io(err: std::io::Error, path: std::path::PathBuf) {
-            display("Could not open pack file at '{}'", path.display())
-            source(err)
-        }
-        Corrupt(msg: String) {
-            display("{}", msg)
-        }
-        UnsupportedVersion(version: u32) {
-            display("Unsupported pack version: {}", version)
+/// The error returned when opening packs or parsing their header.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("Could not open pack file at '{}'", .1.display())]
+    Io(#[source] std::io::Error, std::path::PathBuf),
+    #[error("Pack data of size {size} is too small for even an empty pack")]
+    TooSmall { size: usize },
+    #[error("Pack data type not recognized")]
+    UnknownSignature,
+    #[error("Unsupported pack version: {0}")]
+    UnsupportedVersion(u32),
+    #[error("Pack data of size {size} is too small for the {num_objects} objects it claims to contain")]
+    InconsistentHeader { num_objects: u32, size: usize },
+    #[error("The pack checksum could not be verified")]
+    Checksum(#[from] data::verify::Error),
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::Io(..) => crate::Category::Environment,
+            Error::TooSmall { .. } | Error::UnknownSignature | Error::InconsistentHeader { .. } => {
+                crate::Category::Corruption
+            }
+            Error::UnsupportedVersion(_) => crate::Category::Usage,
+            Error::Checksum(err) => err.category(),
         }
     }
 }
@@ -24,7 +39,7 @@ quick_error! {
 pub fn header(data: &[u8; 12]) -> Result<(data::Kind, u32), Error> {
     let mut ofs = 0;
     if &data[ofs..ofs + b"PACK".len()] != b"PACK" {
-        return Err(Error::Corrupt("Pack data type not recognized".into()));
+        return Err(Error::UnknownSignature);
     }
     ofs += N32_SIZE;
     let kind = match BigEndian::read_u32(&data[ofs..ofs + N32_SIZE]) {
@@ -0,0 +1,319 @@
+use crate::{
+    hash, loose,
+    pack::{
+        self, cache,
+        data::{decode::ResolvedBase, delta, Header},
+    },
+    zlib::stream::DeflateWriter,
+};
+use git_features::progress::{self, Progress};
+use git_object::{borrowed, owned, HashKind};
+use std::{cell::RefCell, collections::HashMap, convert::TryInto, io, io::Write};
+
+/// The error returned when writing packs.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("An IO error occurred when writing the pack")]
+    Io(#[from] io::Error),
+    #[error("Object {0} to be written into the pack could not be found")]
+    NotFound(owned::Id),
+    #[error("Only u32::MAX objects can be stored in a pack, found {0}")]
+    TooManyObjects(usize),
+    #[error("The entries of the pack could not be iterated")]
+    Iteration(#[from] pack::data::iter::Error),
+    #[error("An entry of the pack could not be decoded")]
+    Decode(#[from] pack::data::decode::Error),
+}
+
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The hash of all written bytes, which is also the trailer of the pack
+    pub data_hash: owned::Id,
+    pub num_objects: u32,
+    /// The amount of bytes written, including the header and the trailing hash
+    pub bytes_written: u64,
+}
+
+/// Writing pack data files
+impl pack::data::File {
+    /// Write a V2 pack containing the objects with the given `ids` to `out`, looking up their data with `find`.
+    ///
+    /// All objects are stored as base objects, deltas are not produced yet. `find` places the object data into
+    /// the provided buffer and returns it along with its kind.
+    pub fn write_objects_to_stream<F>(
+        ids: &[owned::Id],
+        mut find: F,
+        mut progress: impl Progress,
+        out: impl io::Write,
+    ) -> Result<Outcome, Error>
+    where
+        F: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<pack::Object<'a>>,
+    {
+        let num_objects: u32 = ids.len().try_into().map_err(|_| Error::TooManyObjects(ids.len()))?;
+        progress.init(Some(ids.len()), progress::count("objects"));
+        let mut out = write_header(out, num_objects)?;
+
+        let mut buf = Vec::new();
+        for id in ids {
+            let object = find(id.to_borrowed(), &mut buf).ok_or(Error::NotFound(*id))?;
+            write_base_object(object.kind, object.data, &mut out)?;
+            progress.inc();
+        }
+        write_trailer(out, num_objects)
+    }
+
+    /// Like [`write_objects_to_stream()`][pack::data::File::write_objects_to_stream()], but store each of the `objects`
+    /// as delta against the base it is paired with if possible, creating a thin pack as the bases aren't part of it.
+    ///
+    /// Deltas are stored as ref deltas if they take less than half the size of the object, like git does, otherwise the
+    /// object is stored as is. Bases which `find` can't find or which are of another kind are ignored.
+    pub fn write_thin_objects_to_stream<F>(
+        objects: &[(owned::Id, Option<owned::Id>)],
+        mut find: F,
+        mut progress: impl Progress,
+        out: impl io::Write,
+    ) -> Result<Outcome, Error>
+    where
+        F: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<pack::Object<'a>>,
+    {
+        let num_objects: u32 = objects
+            .len()
+            .try_into()
+            .map_err(|_| Error::TooManyObjects(objects.len()))?;
+        progress.init(Some(objects.len()), progress::count("objects"));
+        let mut out = write_header(out, num_objects)?;
+
+        let mut buf = Vec::new();
+        let mut base_buf = Vec::new();
+        for (id, base_id) in objects {
+            let object = find(id.to_borrowed(), &mut buf).ok_or(Error::NotFound(*id))?;
+            let delta = base_id.and_then(|base_id| {
+                let base = find(base_id.to_borrowed(), &mut base_buf).filter(|base| base.kind == object.kind)?;
+                let delta = delta::encode(base.data, object.data);
+                (delta.len() < (object.data.len() / 2).saturating_sub(git_object::SHA1_SIZE))
+                    .then_some((base_id, delta))
+            });
+            match delta {
+                Some((base_id, delta)) => {
+                    Header::RefDelta { base_id }.to_write(delta.len() as u64, &mut out)?;
+                    let mut compressed = DeflateWriter::new(&mut out);
+                    compressed.write_all(&delta)?;
+                    compressed.flush()?;
+                }
+                None => write_base_object(object.kind, object.data, &mut out)?,
+            }
+            progress.inc();
+        }
+        write_trailer(out, num_objects)
+    }
+
+    /// Write this pack to `out` along with all objects outside of it its ref deltas refer to, looking them up
+    /// with `find`, to turn a thin pack into one that can be indexed.
+    ///
+    /// The missing objects are stored as base objects in front of all entries of this pack, as bases have to come
+    /// before their deltas when indexing. Ref deltas become offset deltas to their base, or base objects if their base
+    /// comes after them, while all other entries are copied as is. Thus packs without ref deltas are written unchanged.
+    pub fn write_completed_to_stream<F>(
+        &self,
+        find: F,
+        mut progress: impl Progress,
+        out: impl io::Write,
+    ) -> Result<Outcome, Error>
+    where
+        F: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<pack::Object<'a>>,
+    {
+        let entries = pack::data::Iter::new_from_header(
+            &self.data[..],
+            pack::data::iter::Mode::Verify,
+            pack::data::iter::CompressedBytesMode::Ignore,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let find = RefCell::new(find);
+        let mut buf = Vec::new();
+        let mut ids = HashMap::new();
+        let mut pending: Vec<_> = entries.iter().collect();
+        while !pending.is_empty() {
+            let num_pending = pending.len();
+            let mut unresolved = None;
+            let mut retry = Vec::new();
+            for entry in pending {
+                match self.decode_entry(
+                    self.entry(entry.pack_offset),
+                    &mut buf,
+                    |id, out| resolve_base(self, &ids, &find, id, out),
+                    &mut cache::DecodeEntryNoop,
+                ) {
+                    Ok(outcome) => {
+                        ids.insert(object_id(outcome.kind, &buf), entry.pack_offset);
+                    }
+                    // The base may be an entry of this pack which comes later.
+                    Err(pack::data::decode::Error::DeltaBaseUnresolved(id)) => {
+                        unresolved = Some(id);
+                        retry.push(entry);
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            if retry.len() == num_pending {
+                return Err(Error::NotFound(
+                    unresolved.expect("an unresolved base for each retried entry"),
+                ));
+            }
+            pending = retry;
+        }
+
+        let mut missing: Vec<_> = entries
+            .iter()
+            .filter_map(|entry| match entry.header {
+                Header::RefDelta { base_id } if !ids.contains_key(&base_id) => Some(base_id),
+                _ => None,
+            })
+            .collect();
+        missing.sort();
+        missing.dedup();
+
+        let num_entries = entries.len() + missing.len();
+        let num_objects: u32 = num_entries.try_into().map_err(|_| Error::TooManyObjects(num_entries))?;
+        progress.init(Some(num_entries), progress::count("objects"));
+        let mut out = write_header(out, num_objects)?;
+
+        let mut base_offsets = HashMap::new();
+        for id in missing {
+            let offset = out.count;
+            let mut find = find.borrow_mut();
+            let object = find(id.to_borrowed(), &mut buf).ok_or(Error::NotFound(id))?;
+            write_base_object(object.kind, object.data, &mut out)?;
+            base_offsets.insert(id, offset);
+            progress.inc();
+        }
+
+        let mut offsets = HashMap::new();
+        for entry in &entries {
+            let offset = out.count;
+            offsets.insert(entry.pack_offset, offset);
+            let header = match entry.header {
+                Header::OfsDelta { base_distance } => Header::OfsDelta {
+                    base_distance: offset - offsets[&(entry.pack_offset - base_distance)],
+                },
+                Header::RefDelta { base_id } => {
+                    let base_offset = base_offsets
+                        .get(&base_id)
+                        .or_else(|| offsets.get(&ids[&base_id]))
+                        .copied();
+                    match base_offset {
+                        Some(base_offset) => Header::OfsDelta {
+                            base_distance: offset - base_offset,
+                        },
+                        None => {
+                            let outcome = self.decode_entry(
+                                self.entry(entry.pack_offset),
+                                &mut buf,
+                                |id, out| resolve_base(self, &ids, &find, id, out),
+                                &mut cache::DecodeEntryNoop,
+                            )?;
+                            write_base_object(outcome.kind, &buf, &mut out)?;
+                            progress.inc();
+                            continue;
+                        }
+                    }
+                }
+                header => header,
+            };
+            header.to_write(entry.decompressed_size, &mut out)?;
+            let data_offset = entry.pack_offset + entry.header_size as u64;
+            out.write_all(
+                self.entry_slice(data_offset..data_offset + entry.compressed_size)
+                    .expect("entries to lie within the pack"),
+            )?;
+            progress.inc();
+        }
+        write_trailer(out, num_objects)
+    }
+}
+
+/// Resolve the base with `id` with the entries of `pack` in `ids`, or copy it into `out` if `find` knows it.
+fn resolve_base<F>(
+    pack: &pack::data::File,
+    ids: &HashMap<owned::Id, u64>,
+    find: &RefCell<F>,
+    id: borrowed::Id<'_>,
+    out: &mut Vec<u8>,
+) -> Option<ResolvedBase>
+where
+    F: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<pack::Object<'a>>,
+{
+    if let Some(pack_offset) = ids.get(&owned::Id::from_borrowed_sha1(id.sha1())) {
+        return Some(ResolvedBase::InPack(pack.entry(*pack_offset)));
+    }
+    let mut buf = Vec::new();
+    let object = (*find.borrow_mut())(id, &mut buf)?;
+    out.clear();
+    out.extend_from_slice(object.data);
+    Some(ResolvedBase::OutOfPack {
+        kind: object.kind,
+        end: out.len(),
+    })
+}
+
+fn object_id(kind: git_object::Kind, data: &[u8]) -> owned::Id {
+    let mut hash = hash::Write::new(io::sink(), HashKind::Sha1);
+    loose::object::header::encode(kind, data.len() as u64, &mut hash).expect("hashing to never fail");
+    hash.hash.update(data);
+    owned::Id::new_sha1(hash.hash.digest())
+}
+
+fn write_base_object(kind: git_object::Kind, data: &[u8], mut out: impl io::Write) -> io::Result<()> {
+    let header = match kind {
+        git_object::Kind::Commit => Header::Commit,
+        git_object::Kind::Tree => Header::Tree,
+        git_object::Kind::Blob => Header::Blob,
+        git_object::Kind::Tag => Header::Tag,
+    };
+    header.to_write(data.len() as u64, &mut out)?;
+    let mut compressed = DeflateWriter::new(&mut out);
+    compressed.write_all(data)?;
+    compressed.flush()
+}
+
+fn write_header<W: io::Write>(out: W, num_objects: u32) -> io::Result<Counted<hash::Write<W>>> {
+    let mut out = Counted {
+        inner: hash::Write::new(out, HashKind::Sha1),
+        count: 0,
+    };
+    out.write_all(b"PACK")?;
+    out.write_all(&2u32.to_be_bytes())?;
+    out.write_all(&num_objects.to_be_bytes())?;
+    Ok(out)
+}
+
+fn write_trailer<W: io::Write>(mut out: Counted<hash::Write<W>>, num_objects: u32) -> Result<Outcome, Error> {
+    let data_hash = owned::Id::new_sha1(out.inner.hash.clone().digest());
+    out.write_all(data_hash.as_slice())?;
+    out.flush()?;
+    Ok(Outcome {
+        data_hash,
+        num_objects,
+        bytes_written: out.count,
+    })
+}
+
+/// Count the bytes written to the pack, which is unknown until all entries are compressed.
+struct Counted<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: io::Write> io::Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -126,6 +126,68 @@ impl index::File {
         None
     }
 
+    /// Like [`lookup()`][index::File::lookup()] for many `ids` in ascending order, returning their indices in the same
+    /// order.
+    ///
+    /// Each search starts where the previous one ended and gallops forward from there, so the denser the ids are, the
+    /// fewer ids of the index are compared with each of them.
+    pub fn lookup_sorted<'a>(&self, ids: impl IntoIterator<Item = borrowed::Id<'a>>) -> Vec<Option<u32>> {
+        let mut cursor = 0;
+        ids.into_iter()
+            .map(|id| {
+                let first_byte = id.first_byte() as usize;
+                let end = self.fan[first_byte];
+                let mut lower = cursor.max(if first_byte != 0 { self.fan[first_byte - 1] } else { 0 });
+                let (mut upper, mut step) = (lower, 1u32);
+                while upper < end && self.oid_at_index(upper) < id {
+                    lower = upper + 1;
+                    upper = lower.saturating_add(step).min(end);
+                    step = step.saturating_mul(2);
+                }
+                while lower < upper {
+                    let mid = (lower + upper) / 2;
+                    if self.oid_at_index(mid) < id {
+                        lower = mid + 1;
+                    } else {
+                        upper = mid;
+                    }
+                }
+                cursor = lower;
+                if lower < end && self.oid_at_index(lower) == id {
+                    Some(lower)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the ids starting with the hexadecimal `prefix` in ascending order, or none if it isn't a valid
+    /// [prefix][crate::prefix].
+    pub fn ids_with_prefix<'a>(&'a self, prefix: &'a [u8]) -> impl Iterator<Item = borrowed::Id<'a>> + 'a {
+        let (lower_bound, upper_bound) = match crate::prefix::lower_bound(prefix) {
+            Ok(id) => {
+                let first_byte = id.to_borrowed().first_byte() as usize;
+                let start = if first_byte != 0 { self.fan[first_byte - 1] } else { 0 };
+                let end = self.fan[first_byte];
+                let (mut lower, mut upper) = (start, end);
+                while lower < upper {
+                    let mid = (lower + upper) / 2;
+                    if self.oid_at_index(mid) < id.to_borrowed() {
+                        lower = mid + 1;
+                    } else {
+                        upper = mid;
+                    }
+                }
+                (lower, end)
+            }
+            Err(_) => (0, 0),
+        };
+        (lower_bound..upper_bound)
+            .map(move |index| self.oid_at_index(index))
+            .take_while(move |id| id.starts_with_hex(prefix))
+    }
+
     pub fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = Entry> + 'a> {
         match self.kind {
             index::Kind::V2 => Box::new(self.iter_v2()),
@@ -1,22 +1,43 @@
-use crate::pack::index::{self, Kind, FAN_LEN, V2_SIGNATURE};
+use crate::{
+    open,
+    pack::{
+        index::{self, Kind, FAN_LEN, V2_SIGNATURE},
+        Buffer,
+    },
+};
 use byteorder::{BigEndian, ByteOrder};
-use filebuffer::FileBuffer;
+use git_features::progress;
 use git_object::SHA1_SIZE;
-use quick_error::quick_error;
-use std::{convert::TryFrom, mem::size_of, path::Path};
+use std::{
+    convert::TryFrom,
+    mem::size_of,
+    path::{Path, PathBuf},
+};
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        Io(err: std::io::Error, path: std::path::PathBuf) {
-            display("Could not open pack index file at '{}'", path.display())
-            source(err)
-        }
-        Corrupt(msg: String) {
-            display("{}", msg)
-        }
-        UnsupportedVersion(version: u32) {
-            display("Unsupported index version: {}", version)
+/// The error returned when opening indices.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("Could not open pack index file at '{}'", .1.display())]
+    Io(#[source] std::io::Error, std::path::PathBuf),
+    #[error("Pack index of size {size} is too small for even an empty index")]
+    TooSmall { size: usize },
+    #[error("Unsupported index version: {0}")]
+    UnsupportedVersion(u32),
+    #[error("Pack index of size {size} doesn't match the {num_objects} objects its fan-out table claims it contains")]
+    InconsistentHeader { num_objects: u32, size: usize },
+    #[error("The pack index checksum could not be verified")]
+    Checksum(#[from] index::verify::Error),
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::Io(..) => crate::Category::Environment,
+            Error::TooSmall { .. } | Error::InconsistentHeader { .. } => crate::Category::Corruption,
+            Error::UnsupportedVersion(_) => crate::Category::Usage,
+            Error::Checksum(err) => err.category(),
         }
     }
 }
@@ -29,19 +50,33 @@ impl index::File {
     pub fn at(path: impl AsRef<Path>) -> Result<index::File, Error> {
         Self::try_from(path.as_ref())
     }
-}
 
-impl TryFrom<&Path> for index::File {
-    type Error = Error;
+    /// Like [`at()`][index::File::at()], but verify as much of the index as configured in `options` before returning it.
+    pub fn at_with_options(path: impl AsRef<Path>, options: open::Options) -> Result<index::File, Error> {
+        let index = index::File::at(path)?;
+        if options.verify >= open::Verify::Header && !index.header_matches_size() {
+            return Err(Error::InconsistentHeader {
+                num_objects: index.num_objects,
+                size: index.data.len(),
+            });
+        }
+        if options.verify >= open::Verify::Checksum {
+            index.verify_checksum(progress::Discard)?;
+        }
+        Ok(index)
+    }
 
-    fn try_from(path: &Path) -> Result<Self, Self::Error> {
-        let data = FileBuffer::open(&path).map_err(|e| Error::Io(e, path.to_owned()))?;
+    /// Parse the index in `data`, which is useful where there is no file system, like in browsers.
+    ///
+    /// The [path][index::File::path()] of the returned index is empty.
+    pub fn from_data(data: Vec<u8>) -> Result<index::File, Error> {
+        index::File::from_buffer(Buffer::Owned(data), PathBuf::new())
+    }
+
+    fn from_buffer(data: Buffer, path: PathBuf) -> Result<index::File, Error> {
         let idx_len = data.len();
         if idx_len < FAN_LEN * N32_SIZE + FOOTER_SIZE {
-            return Err(Error::Corrupt(format!(
-                "Pack index of size {} is too small for even an empty index",
-                idx_len
-            )));
+            return Err(Error::TooSmall { size: idx_len });
         }
         let (kind, version, fan, num_objects) = {
             let (kind, d) = {
@@ -72,7 +107,7 @@ impl TryFrom<&Path> for index::File {
         };
         Ok(index::File {
             data,
-            path: path.to_owned(),
+            path,
             kind,
             num_objects,
             version,
@@ -81,6 +116,38 @@ impl TryFrom<&Path> for index::File {
     }
 }
 
+impl index::File {
+    /// Return true if the fan-out table is ascending and the size of the tables it implies matches the size of the file.
+    fn header_matches_size(&self) -> bool {
+        if self.fan.windows(2).any(|w| w[0] > w[1]) {
+            return false;
+        }
+        let num_objects = self.num_objects as usize;
+        let size = self.data.len();
+        match self.kind {
+            Kind::V1 => size == FAN_LEN * N32_SIZE + num_objects * (N32_SIZE + SHA1_SIZE) + FOOTER_SIZE,
+            Kind::V2 => {
+                let tables_size = V2_SIGNATURE.len()
+                    + N32_SIZE
+                    + FAN_LEN * N32_SIZE
+                    + num_objects * (SHA1_SIZE + N32_SIZE * 2)
+                    + FOOTER_SIZE;
+                let large_offsets_size = size.checked_sub(tables_size);
+                matches!(large_offsets_size, Some(s) if s % 8 == 0 && s / 8 <= num_objects)
+            }
+        }
+    }
+}
+
+impl TryFrom<&Path> for index::File {
+    type Error = Error;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let data = Buffer::open(path).map_err(|e| Error::Io(e, path.to_owned()))?;
+        index::File::from_buffer(data, path.to_owned())
+    }
+}
+
 fn read_fan(d: &[u8]) -> ([u32; FAN_LEN], usize) {
     let mut fan = [0; FAN_LEN];
     for (c, f) in d.chunks(N32_SIZE).zip(fan.iter_mut()) {
@@ -73,8 +73,6 @@ macro_rules! izip {
     };
 }
 
-use filebuffer::FileBuffer;
-
 #[derive(PartialEq, Eq, Ord, PartialOrd, Debug, Hash, Clone, Copy)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub enum Kind {
@@ -100,7 +98,7 @@ impl Kind {
 const FAN_LEN: usize = 256;
 
 pub struct File {
-    pub(crate) data: FileBuffer,
+    pub(crate) data: crate::pack::Buffer,
     path: std::path::PathBuf,
     kind: Kind,
     version: u32,
@@ -121,6 +119,10 @@ impl File {
     pub fn version(&self) -> u32 {
         self.version
     }
+    /// The length of all mapped data of the index file.
+    pub fn data_len(&self) -> usize {
+        self.data.len()
+    }
 }
 
 const V2_SIGNATURE: &[u8] = b"\xfftOc";
@@ -93,7 +93,7 @@ impl index::File {
                             processor,
                         );
                         match result {
-                            Err(err @ Error::PackDecode(_, _, _)) if !check.fatal_decode_error() => {
+                            Err(err @ Error::PackDecode { .. }) if !check.fatal_decode_error() => {
                                 progress.info(format!("Ignoring decode error: {}", err));
                                 Ok(())
                             }
@@ -96,7 +96,7 @@ impl index::File {
                             );
                             progress.inc();
                             let stat = match result {
-                                Err(err @ Error::PackDecode(_, _, _)) if !check.fatal_decode_error() => {
+                                Err(err @ Error::PackDecode { .. }) if !check.fatal_decode_error() => {
                                     progress.info(format!("Ignoring decode error: {}", err));
                                     continue;
                                 }
@@ -111,7 +111,11 @@ impl index::File {
                 },
                 cache,
             )
-            .map_err(|e| Error::PackDecode(e, index_entry.oid, index_entry.pack_offset))?;
+            .map_err(|source| Error::PackDecode {
+                source,
+                id: index_entry.oid,
+                offset: index_entry.pack_offset,
+            })?;
         let object_kind = entry_stats.kind;
         let header_size = (pack_entry_data_offset - index_entry.pack_offset) as usize;
         let entry_len = header_size + entry_stats.compressed_size;
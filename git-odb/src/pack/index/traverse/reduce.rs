@@ -57,7 +57,7 @@ where
 
     fn feed(&mut self, input: Self::Input) -> Result<(), Self::Error> {
         let chunk_stats: Vec<_> = match input {
-            Err(err @ traverse::Error::PackDecode(_, _, _)) if !self.check.fatal_decode_error() => {
+            Err(err @ traverse::Error::PackDecode { .. }) if !self.check.fatal_decode_error() => {
                 self.progress.lock().info(format!("Ignoring decode error: {}", err));
                 return Ok(());
             }
@@ -5,29 +5,45 @@ use git_object::{
     bstr::{BString, ByteSlice},
     owned, SHA1_SIZE,
 };
-use quick_error::quick_error;
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        Mismatch { expected: owned::Id, actual: owned::Id } {
-            display("index checksum mismatch: expected {}, got {}", expected, actual)
-        }
-        ObjectDecode(err: borrowed::Error, kind: git_object::Kind, oid: owned::Id) {
-            display("{} object {} could not be decoded", kind, oid)
-            source(err)
-        }
-        ObjectEncodeMismatch(kind: git_object::Kind, oid: owned::Id, expected: BString, actual: BString) {
-            display("{} object {} wasn't re-encoded without change, wanted\n{}\n\nGOT\n\n{}", kind, oid, expected, actual)
-        }
-        ObjectEncode(err: std::io::Error) {
-            from()
+/// The error returned when verifying indices and the objects they refer to.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("index checksum mismatch: expected {expected}, got {actual}")]
+    Mismatch { expected: owned::Id, actual: owned::Id },
+    #[error("{kind} object {id} could not be decoded")]
+    ObjectDecode {
+        source: borrowed::Error,
+        kind: git_object::Kind,
+        id: owned::Id,
+    },
+    #[error("{kind} object {id} wasn't re-encoded without change, wanted\n{expected}\n\nGOT\n\n{actual}")]
+    ObjectEncodeMismatch {
+        kind: git_object::Kind,
+        id: owned::Id,
+        expected: BString,
+        actual: BString,
+    },
+    #[error("An object could not be encoded to compare it to its original")]
+    ObjectEncode(#[from] std::io::Error),
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::Mismatch { .. } | Error::ObjectDecode { .. } | Error::ObjectEncodeMismatch { .. } => {
+                crate::Category::Corruption
+            }
+            Error::ObjectEncode(_) => crate::Category::Environment,
         }
     }
 }
 
 /// Various ways in which a pack and index can be verified
 #[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub enum Mode {
     /// Validate SHA1 and CRC32
     Sha1CRC32,
@@ -130,8 +146,12 @@ impl index::File {
             use git_object::Kind::*;
             match object_kind {
                 Tree | Commit | Tag => {
-                    let borrowed_object = borrowed::Object::from_bytes(object_kind, buf)
-                        .map_err(|err| Error::ObjectDecode(err, object_kind, index_entry.oid))?;
+                    let borrowed_object =
+                        borrowed::Object::from_bytes(object_kind, buf).map_err(|source| Error::ObjectDecode {
+                            source,
+                            kind: object_kind,
+                            id: index_entry.oid,
+                        })?;
                     if let Mode::Sha1CRC32DecodeEncode = mode {
                         let object = owned::Object::from(borrowed_object);
                         encode_buf.clear();
@@ -145,12 +165,12 @@ impl index::File {
                                 }
                             }
                             if should_return_error {
-                                return Err(Error::ObjectEncodeMismatch(
-                                    object_kind,
-                                    index_entry.oid,
-                                    buf.into(),
-                                    encode_buf.clone().into(),
-                                ));
+                                return Err(Error::ObjectEncodeMismatch {
+                                    kind: object_kind,
+                                    id: index_entry.oid,
+                                    expected: buf.into(),
+                                    actual: encode_buf.clone().into(),
+                                });
                             }
                         }
                     }
@@ -40,6 +40,10 @@ impl pack::index::File {
     /// Note that neither in-pack nor out-of-pack Ref Deltas are supported here, these must have been resolved beforehand.
     /// `make_resolver()`:  It will only be called after the iterator stopped returning elements and produces a function that
     /// provides all bytes belonging to an entry.
+    ///
+    /// Deltas are resolved by [traversing][Tree::traverse()] a tree of all entries, handing each thread whole trees
+    /// of bases along with their deltas. Thus resolved bases are only ever seen by the thread which resolves their
+    /// children, and there is no cache shared among threads which could become a point of contention.
     pub fn write_data_iter_to_stream<F, F2, P>(
         kind: pack::index::Kind,
         make_resolver: F,
@@ -57,6 +61,7 @@ impl pack::index::File {
         if kind != pack::index::Kind::default() {
             return Err(Error::Unsupported(kind));
         }
+        let mut span = git_features::trace::span("pack::index");
         let mut num_objects: usize = 0;
         let mut bytes_to_process = 0u64;
         let mut last_seen_trailer = None;
@@ -107,7 +112,10 @@ impl pack::index::File {
                 RefDelta { .. } => return Err(Error::IteratorInvariantNoRefDelta),
                 OfsDelta { base_distance } => {
                     let base_pack_offset = pack::data::Header::verified_base_pack_offset(pack_offset, base_distance)
-                        .ok_or_else(|| Error::IteratorInvariantBaseOffset(pack_offset, base_distance))?;
+                        .ok_or(Error::IteratorInvariantBaseOffset {
+                            pack_offset,
+                            distance: base_distance,
+                        })?;
                     tree.add_child(
                         base_pack_offset,
                         pack_offset,
@@ -139,6 +147,7 @@ impl pack::index::File {
         drop(decompressed_progress);
 
         root_progress.inc();
+        span.record(format_args!("objects={} bytes={}", num_objects, bytes_to_process));
 
         let resolver = make_resolver()?;
         let sorted_pack_offsets_by_oid = {
@@ -4,6 +4,9 @@ pub mod data;
 pub mod index;
 pub mod tree;
 
+mod buffer;
+pub(crate) use buffer::Buffer;
+
 mod object;
 pub use object::Object;
 
@@ -3,34 +3,37 @@ use git_features::{
     interruptible::is_interrupted,
     progress::{self, Progress},
 };
-use quick_error::quick_error;
 use std::{
     fs, io,
     io::{BufRead, Read},
     time::Instant,
 };
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        Io(err: io::Error, msg: &'static str) {
-            display("{}", msg)
-            source(err)
-        }
-        Header(err: pack::data::parse::Error) {
-            source(err)
-            from()
-        }
-        UnresolvedRefDelta(id: git_object::owned::Id) {
-            display("Could find object with id {} in this pack. Thin packs are not supported", id)
-        }
-        Tree(err: pack::tree::Error) {
-            display("An error occurred when handling the delta tree")
-            source(err)
-            from()
-        }
-        Interrupted {
-            display("Interrupted")
+/// The error returned by [`Tree::from_offsets_in_pack()`].
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("{1}")]
+    Io(#[source] io::Error, &'static str),
+    #[error("The header of a pack entry could not be parsed")]
+    Header(#[from] pack::data::parse::Error),
+    #[error("Could find object with id {0} in this pack. Thin packs are not supported")]
+    UnresolvedRefDelta(git_object::owned::Id),
+    #[error("An error occurred when handling the delta tree")]
+    Tree(#[from] pack::tree::Error),
+    #[error("Interrupted")]
+    Interrupted,
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::Io(..) => crate::Category::Environment,
+            Error::Header(err) => err.category(),
+            Error::UnresolvedRefDelta(_) => crate::Category::Usage,
+            Error::Tree(_) => crate::Category::Corruption,
+            Error::Interrupted => crate::Category::Interrupted,
         }
     }
 }
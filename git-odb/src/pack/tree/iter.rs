@@ -37,7 +37,7 @@ impl<T> Tree<T> {
 
     #[allow(unsafe_code)]
     /// SAFETY: A tree is a data structure without cycles, and we assure of that by verifying all input.
-    /// A node as identified by index can only be traversed once using the Chunks iterator.
+    /// A node as identified by index can only be traversed once using the Roots iterator.
     /// When the iterator is created, this instance cannot be mutated anymore nor can it be read.
     /// That iterator is only handed out once.
     /// `Node` instances produced by it consume themselves when iterating their children, allowing them to be
@@ -76,13 +76,9 @@ impl<T> Tree<T> {
 
 /// Iteration
 impl<T> Tree<T> {
-    /// Return an iterator over chunks of roots. Roots are not children themselves, they have no parents.
-    pub fn iter_root_chunks(&mut self, size: usize) -> Chunks<T> {
-        Chunks {
-            tree: self,
-            size,
-            cursor: 0,
-        }
+    /// Return an iterator over all roots, which are not children themselves as they have no parents.
+    pub fn iter_roots(&mut self) -> Roots<T> {
+        Roots { tree: self, cursor: 0 }
     }
 }
 
@@ -134,46 +130,39 @@ where
     }
 }
 
-pub struct Chunks<'a, T> {
+pub struct Roots<'a, T> {
     tree: &'a Tree<T>,
-    size: usize,
     cursor: usize,
 }
 
-impl<'a, T> Iterator for Chunks<'a, T>
+impl<'a, T> Iterator for Roots<'a, T>
 where
     T: Default,
 {
-    type Item = Vec<Node<'a, T>>;
+    type Item = Node<'a, T>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.cursor == self.tree.one_past_last_seen_root {
-            return None;
-        }
-        let mut items_remaining = self.size;
-        let mut res = Vec::with_capacity(self.size);
-
-        while items_remaining > 0 && self.cursor < self.tree.one_past_last_seen_root {
+        while self.cursor < self.tree.one_past_last_seen_root {
+            let index = self.cursor;
+            self.cursor += 1;
             // SAFETY: The index is valid as the cursor cannot surpass the amount of items. `one_past_last_seen_root`
             // is guaranteed to be self.tree.items.len() at most, or smaller.
             // Then see `take_entry_if_root(…)`
             #[allow(unsafe_code)]
-            if let Some((data, children)) = unsafe { self.tree.from_iter_take_entry_if_root(self.cursor) } {
-                res.push(Node {
+            if let Some((data, children)) = unsafe { self.tree.from_iter_take_entry_if_root(index) } {
+                return Some(Node {
                     tree: self.tree,
-                    index: self.cursor,
+                    index,
                     children,
                     data,
                 });
-                items_remaining -= 1;
             }
-            self.cursor += 1;
         }
+        None
+    }
 
-        if res.is_empty() {
-            None
-        } else {
-            Some(res)
-        }
+    /// The upper bound is the amount of items left to look at, which may or may not be roots.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.tree.one_past_last_seen_root - self.cursor))
     }
 }
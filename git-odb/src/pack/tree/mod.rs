@@ -1,23 +1,24 @@
-use quick_error::quick_error;
 use std::cell::UnsafeCell;
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        InvariantIncreasingPackOffset(last_pack_offset: u64, pack_offset: u64) {
-            display("Pack offsets must only increment. The previous pack offset was {}, the current one is {}", last_pack_offset, pack_offset)
-        }
-        InvariantNonEmpty {
-            display("Is there ever a need to create empty indices? If so, please post a PR.")
-        }
-        InvariantBasesBeforeDeltasNeedThem(delta_pack_offset: u64, base_pack_offset: u64) {
-            display("The delta at pack offset {} could not find its base at {} - it should have been seen already", delta_pack_offset, base_pack_offset)
-        }
-    }
+/// The error returned when the invariants of a delta [`Tree`] don't hold.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error(
+        "Pack offsets must only increment. The previous pack offset was {last_pack_offset}, the current one is {pack_offset}"
+    )]
+    InvariantIncreasingPackOffset { last_pack_offset: u64, pack_offset: u64 },
+    #[error("Is there ever a need to create empty indices? If so, please post a PR.")]
+    InvariantNonEmpty,
+    #[error("The delta at pack offset {delta_pack_offset} could not find its base at {base_pack_offset} - it should have been seen already")]
+    InvariantBasesBeforeDeltasNeedThem {
+        delta_pack_offset: u64,
+        base_pack_offset: u64,
+    },
 }
 
 mod iter;
-pub use iter::{Chunks, Node};
+pub use iter::{Node, Roots};
 pub mod traverse;
 
 pub mod from_offsets;
@@ -36,8 +37,12 @@ pub struct Tree<T> {
     last_added_offset: u64,
     one_past_last_seen_root: usize,
     pack_entries_end: Option<u64>,
+    cache_limit: u64,
 }
 
+/// The amount of bytes of resolved bases each thread keeps in memory by default while traversing a tree.
+pub const DEFAULT_CACHE_LIMIT: u64 = 256 * 1024 * 1024;
+
 /// SAFETY: We solemnly swear…that this is sync because without the unsafe cell, it is also sync.
 /// But that's really the only reason why I would dare to know.
 #[allow(unsafe_code)]
@@ -53,15 +58,27 @@ impl<T> Tree<T> {
             last_added_offset: 0,
             one_past_last_seen_root: 0,
             pack_entries_end: None,
+            cache_limit: DEFAULT_CACHE_LIMIT,
         })
     }
 
+    /// Set the amount of bytes of resolved bases each thread keeps in memory while [traversing][Tree::traverse()]
+    /// to `bytes`, with bases beyond that being written to temporary files until all their children are resolved.
+    ///
+    /// Defaults to [`DEFAULT_CACHE_LIMIT`].
+    pub fn set_cache_limit(&mut self, bytes: u64) {
+        self.cache_limit = bytes;
+    }
+
     fn assert_is_incrementing(&mut self, offset: u64) -> Result<u64, Error> {
         if offset > self.last_added_offset {
             self.last_added_offset = offset;
             Ok(offset)
         } else {
-            Err(Error::InvariantIncreasingPackOffset(self.last_added_offset, offset))
+            Err(Error::InvariantIncreasingPackOffset {
+                last_pack_offset: self.last_added_offset,
+                pack_offset: offset,
+            })
         }
     }
 
@@ -87,9 +104,12 @@ impl<T> Tree<T> {
         #[allow(unsafe_code)]
         let items = unsafe { &mut *(self.items.get()) };
         let offset = self.assert_is_incrementing(offset)?;
-        let base_index = items
-            .binary_search_by_key(&base_offset, |e| e.offset)
-            .map_err(|_| Error::InvariantBasesBeforeDeltasNeedThem(offset, base_offset))?;
+        let base_index = items.binary_search_by_key(&base_offset, |e| e.offset).map_err(|_| {
+            Error::InvariantBasesBeforeDeltasNeedThem {
+                delta_pack_offset: offset,
+                base_pack_offset: base_offset,
+            }
+        })?;
         let child_index = items.len();
         items[base_index].children.push(child_index);
         items.push(Item {
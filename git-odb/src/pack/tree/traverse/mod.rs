@@ -9,27 +9,32 @@ use git_features::{
     parallel::in_parallel_if,
     progress::{self, Progress},
 };
-use quick_error::quick_error;
 
 mod resolve;
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum Error {
-        ZlibInflate(err: crate::zlib::Error, msg: &'static str) {
-            display("{}", msg)
-            source(err)
-        }
-        ResolveFailed(pack_offset: u64) {
-            display("The resolver failed to obtain the pack entry bytes for the entry at {}", pack_offset)
-        }
-        Inspect(err: Box<dyn std::error::Error + Send + Sync>) {
-            display("One of the object inspectors failed")
-            source(&**err)
-            from()
-        }
-        Interrupted {
-            display("Interrupted")
+/// The error returned when traversing a delta [`Tree`].
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("{1}")]
+    ZlibInflate(#[source] crate::zlib::Error, &'static str),
+    #[error("The resolver failed to obtain the pack entry bytes for the entry at {0}")]
+    ResolveFailed(u64),
+    #[error("One of the object inspectors failed")]
+    Inspect(#[from] Box<dyn std::error::Error + Send + Sync>),
+    #[error("A resolved base could not be moved to or read from a temporary file")]
+    SpillToDisk(#[source] std::io::Error),
+    #[error("Interrupted")]
+    Interrupted,
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::ZlibInflate(..) => crate::Category::Corruption,
+            Error::ResolveFailed(_) | Error::SpillToDisk(_) | Error::Inspect(_) => crate::Category::Environment,
+            Error::Interrupted => crate::Category::Interrupted,
         }
     }
 }
@@ -65,15 +70,21 @@ where
         E: std::error::Error + Send + Sync + 'static,
     {
         self.pack_entries_end = Some(pack_entries_end);
-        let (chunk_size, thread_limit, _) = parallel::optimize_chunk_size_and_thread_limit(1, None, thread_limit, None);
+        let cache_limit = self.cache_limit;
+        let (max_chunk_size, thread_limit, _) =
+            parallel::optimize_chunk_size_and_thread_limit(1, None, thread_limit, None);
         let object_progress = parking_lot::Mutex::new(object_progress);
 
         // SAFETY: We are owning 'self', and it's the UnsafeCell which we are supposed to use requiring unsafe on every access now.
         #[allow(unsafe_code)]
         let num_objects = unsafe { (*self.items.get()).len() } as u32;
+        let mut span = git_features::trace::span("pack::resolve_deltas");
+        span.record(format_args!("objects={}", num_objects));
         in_parallel_if(
             should_run_in_parallel,
-            self.iter_root_chunks(chunk_size),
+            // The cost of resolving the delta chains of roots varies a lot, so let chunks shrink towards the end
+            // to keep all threads busy.
+            parallel::DynamicChunks::new(self.iter_roots(), thread_limit, max_chunk_size),
             thread_limit,
             |thread_index| {
                 (
@@ -82,7 +93,7 @@ where
                     new_thread_state(),
                 )
             },
-            |root_nodes, state| resolve::deltas(root_nodes, state, &resolve, &inspect_object),
+            |root_nodes, state| resolve::deltas(root_nodes, state, &resolve, &inspect_object, cache_limit),
             Reducer::new(num_objects, &object_progress, size_progress),
         )?;
         Ok(self.into_items())
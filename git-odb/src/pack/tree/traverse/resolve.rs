@@ -3,13 +3,79 @@ use crate::{
     zlib,
 };
 use git_features::progress::{unit, Progress};
-use std::{cell::RefCell, collections::BTreeMap};
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    rc::Rc,
+};
+
+/// A resolved base shared by all of its children, which is dropped once the last of them is resolved.
+struct Base<'a> {
+    entry: pack::data::Entry,
+    bytes: Cached,
+    cached_bytes: &'a Cell<u64>,
+}
+
+enum Cached {
+    Memory(Vec<u8>),
+    /// The bytes didn't fit into memory anymore and were written to a temporary file.
+    Disk(RefCell<fs::File>),
+}
+
+impl<'a> Base<'a> {
+    /// Keep `bytes` in memory, or write them to a temporary file if that would exceed `cache_limit` cached bytes.
+    fn new(
+        entry: pack::data::Entry,
+        bytes: Vec<u8>,
+        cached_bytes: &'a Cell<u64>,
+        cache_limit: u64,
+    ) -> io::Result<Self> {
+        let len = bytes.len() as u64;
+        let bytes = if cached_bytes.get() + len > cache_limit {
+            let mut file = tempfile::tempfile()?;
+            file.write_all(&bytes)?;
+            Cached::Disk(RefCell::new(file))
+        } else {
+            cached_bytes.set(cached_bytes.get() + len);
+            Cached::Memory(bytes)
+        };
+        Ok(Base {
+            entry,
+            bytes,
+            cached_bytes,
+        })
+    }
+
+    fn bytes(&self) -> io::Result<Cow<'_, [u8]>> {
+        Ok(match &self.bytes {
+            Cached::Memory(bytes) => Cow::Borrowed(bytes),
+            Cached::Disk(file) => {
+                let mut file = file.borrow_mut();
+                let mut bytes = Vec::with_capacity(self.entry.decompressed_size as usize);
+                file.seek(SeekFrom::Start(0))?;
+                file.read_to_end(&mut bytes)?;
+                Cow::Owned(bytes)
+            }
+        })
+    }
+}
+
+impl Drop for Base<'_> {
+    fn drop(&mut self) {
+        if let Cached::Memory(bytes) = &self.bytes {
+            self.cached_bytes.set(self.cached_bytes.get() - bytes.len() as u64);
+        }
+    }
+}
 
 pub(crate) fn deltas<T, F, P, MBFN, S, E>(
     nodes: Vec<pack::tree::Node<T>>,
     (bytes_buf, ref mut progress, state): &mut (Vec<u8>, P, S),
     resolve: F,
     modify_base: MBFN,
+    cache_limit: u64,
 ) -> Result<(usize, u64), Error>
 where
     F: for<'r> Fn(EntrySlice, &'r mut Vec<u8>) -> Option<()> + Send + Sync,
@@ -18,7 +84,7 @@ where
     T: Default,
     E: std::error::Error + Send + Sync + 'static,
 {
-    let mut decompressed_bytes_by_pack_offset = BTreeMap::new();
+    let cached_bytes = Cell::new(0);
     let bytes_buf = RefCell::new(bytes_buf);
     let mut num_objects = 0;
     let mut decompressed_bytes: u64 = 0;
@@ -32,7 +98,8 @@ where
         Ok((entry, slice.end, decompress_all_at_once(compressed, decompressed_len)?))
     };
 
-    // Traverse the tree breadth first and loose the data produced for the base as it won't be needed anymore.
+    // Traverse the tree depth first, resolving each delta only when it's its turn. Resolved bases are shared by their
+    // children and dropped with the last of them, and moved to disk when there are too many bytes cached already.
     progress.init(
         None,
         Some(unit::dynamic(unit::Human::new(
@@ -41,58 +108,58 @@ where
         ))),
     );
 
-    // each node is a base, and its children always start out as deltas which become a base after applying them.
-    // These will be pushed onto our stack until all are processed
     let root_level = 0;
-    let mut nodes: Vec<_> = nodes.into_iter().map(|n| (root_level, n)).collect();
-    while let Some((level, mut base)) = nodes.pop() {
-        let (base_entry, entry_end, base_bytes) = if level == root_level {
-            decompress_from_resolver(base.entry_slice())?
-        } else {
-            decompressed_bytes_by_pack_offset
-                .remove(&base.offset())
-                .expect("we store the resolved delta buffer when done")
+    let mut nodes: Vec<(u16, _, Option<Rc<Base<'_>>>)> = nodes.into_iter().map(|n| (root_level, n, None)).collect();
+    while let Some((level, mut node, base)) = nodes.pop() {
+        let (entry, entry_end, bytes) = match base {
+            None => decompress_from_resolver(node.entry_slice())?,
+            Some(base) => {
+                let (mut entry, entry_end, delta_bytes) = decompress_from_resolver(node.entry_slice())?;
+                let base_bytes = base.bytes().map_err(Error::SpillToDisk)?;
+                let (base_size, consumed) = pack::data::decode::delta_header_size_ofs(&delta_bytes);
+                let mut header_ofs = consumed;
+                assert_eq!(
+                    base_bytes.len(),
+                    base_size as usize,
+                    "recorded base size in delta does not match"
+                );
+                let (result_size, consumed) = pack::data::decode::delta_header_size_ofs(&delta_bytes[consumed..]);
+                header_ofs += consumed;
+
+                let mut fully_resolved_delta_bytes = vec![0; result_size as usize];
+                pack::data::decode::apply_delta(
+                    &base_bytes,
+                    &mut fully_resolved_delta_bytes,
+                    &delta_bytes[header_ofs..],
+                );
+
+                // FIXME: this actually invalidates the "pack_offset()" computation, which is not obvious to consumers
+                // at all
+                entry.header = base.entry.header;
+                (entry, entry_end, fully_resolved_delta_bytes)
+            }
         };
 
         modify_base(
-            &mut base.data,
+            &mut node.data,
             progress,
             Context {
-                entry: &base_entry,
+                entry: &entry,
                 entry_end,
-                decompressed: &base_bytes,
+                decompressed: &bytes,
                 state,
                 level,
             },
         )
         .map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send + Sync>)?;
         num_objects += 1;
-        decompressed_bytes += base_bytes.len() as u64;
+        decompressed_bytes += bytes.len() as u64;
         progress.inc();
-        for child in base.store_changes_then_into_child_iter() {
-            let (mut child_entry, entry_end, delta_bytes) = decompress_from_resolver(child.entry_slice())?;
-            let (base_size, consumed) = pack::data::decode::delta_header_size_ofs(&delta_bytes);
-            let mut header_ofs = consumed;
-            assert_eq!(
-                base_bytes.len(),
-                base_size as usize,
-                "recorded base size in delta does not match"
-            );
-            let (result_size, consumed) = pack::data::decode::delta_header_size_ofs(&delta_bytes[consumed..]);
-            header_ofs += consumed;
-
-            let mut fully_resolved_delta_bytes = bytes_buf.borrow_mut();
-            fully_resolved_delta_bytes.resize(result_size as usize, 0);
-            pack::data::decode::apply_delta(&base_bytes, &mut fully_resolved_delta_bytes, &delta_bytes[header_ofs..]);
 
-            // FIXME: this actually invalidates the "pack_offset()" computation, which is not obvious to consumers
-            // at all
-            child_entry.header = base_entry.header;
-            decompressed_bytes_by_pack_offset.insert(
-                child.offset(),
-                (child_entry, entry_end, fully_resolved_delta_bytes.to_owned()),
-            );
-            nodes.push((level + 1, child));
+        let mut children = node.store_changes_then_into_child_iter().peekable();
+        if children.peek().is_some() {
+            let base = Rc::new(Base::new(entry, bytes, &cached_bytes, cache_limit).map_err(Error::SpillToDisk)?);
+            nodes.extend(children.map(|child| (level + 1, child, Some(Rc::clone(&base)))));
         }
     }
 
@@ -0,0 +1,57 @@
+//! Find objects by an abbreviated id, like the ones `git` shows and accepts on the command-line.
+//!
+//! Prefixes are hexadecimal digits of any case, and need at least [`MIN_HEX_LEN`] of them to be looked up.
+use git_object::{bstr::BString, owned, SHA1_SIZE};
+use std::path::PathBuf;
+
+/// The least amount of hex digits a prefix must have, like `git` requires.
+pub const MIN_HEX_LEN: usize = 4;
+
+/// The error returned when looking up objects by prefix.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("'{prefix}' is no prefix of an object id, which needs {MIN_HEX_LEN} to 40 hex digits")]
+    Invalid { prefix: BString },
+    #[error("The prefix '{prefix}' is ambiguous as it matches {} objects", candidates.len())]
+    Ambiguous {
+        prefix: BString,
+        candidates: Vec<owned::Id>,
+    },
+    #[error("Could not read the loose objects in '{}'", path.display())]
+    Io { source: std::io::Error, path: PathBuf },
+}
+
+impl Error {
+    /// The kind of problem this error describes.
+    pub fn category(&self) -> crate::Category {
+        match self {
+            Error::Invalid { .. } | Error::Ambiguous { .. } => crate::Category::Usage,
+            Error::Io { .. } => crate::Category::Environment,
+        }
+    }
+}
+
+/// Return the smallest id starting with `hex`, or an error if it's not a valid prefix.
+pub(crate) fn lower_bound(hex: &[u8]) -> Result<owned::Id, Error> {
+    if hex.len() < MIN_HEX_LEN || hex.len() > SHA1_SIZE * 2 {
+        return Err(Error::Invalid { prefix: hex.into() });
+    }
+    let mut padded = [b'0'; SHA1_SIZE * 2];
+    padded[..hex.len()].copy_from_slice(hex);
+    owned::Id::from_40_bytes_in_hex(&padded).map_err(|_| Error::Invalid { prefix: hex.into() })
+}
+
+/// Turn the ids matching `hex` into the only one there is, if any.
+pub(crate) fn unique(hex: &[u8], mut candidates: Vec<owned::Id>) -> Result<Option<owned::Id>, Error> {
+    candidates.sort();
+    candidates.dedup();
+    match candidates.len() {
+        0 => Ok(None),
+        1 => Ok(candidates.pop()),
+        _ => Err(Error::Ambiguous {
+            prefix: hex.into(),
+            candidates,
+        }),
+    }
+}
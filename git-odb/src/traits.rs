@@ -1,6 +1,43 @@
-use git_object::{owned, HashKind};
+use crate::pack;
+use git_object::{borrowed, owned, HashKind};
 use std::io;
 
+/// Find objects by id, implemented by all object databases.
+pub trait Find {
+    type Error: std::error::Error + 'static;
+
+    /// Find the object with `id` and place its data into `buf`, or return `None` if it doesn't exist.
+    ///
+    /// `cache` speeds up resolving the deltas of packed objects and is ignored by databases without packs.
+    fn locate<'a>(
+        &self,
+        id: borrowed::Id<'_>,
+        buf: &'a mut Vec<u8>,
+        cache: &mut impl pack::cache::DecodeEntry,
+    ) -> Option<Result<pack::Object<'a>, Self::Error>>;
+
+    /// Returns true if the object with `id` exists.
+    fn contains(&self, id: borrowed::Id<'_>) -> bool;
+
+    /// Return whether each of `ids` exists, in the order of `ids`.
+    ///
+    /// Databases with packs sort the ids to probe their indices in one pass, which is much faster than calling
+    /// [`contains()`][Find::contains()] for each of many ids.
+    fn contains_many(&self, ids: &[owned::Id]) -> Vec<bool> {
+        ids.iter().map(|id| self.contains(id.to_borrowed())).collect()
+    }
+
+    /// Return the kind and size of the object with `id` without decoding all of its data, or `None` if it doesn't exist.
+    fn header(&self, id: borrowed::Id<'_>) -> Option<Result<(git_object::Kind, u64), Self::Error>>;
+}
+
+/// Return the positions of `ids` ordered by the ids they point to.
+pub(crate) fn ascending(ids: &[owned::Id]) -> Vec<usize> {
+    let mut positions: Vec<_> = (0..ids.len()).collect();
+    positions.sort_by_key(|&position| ids[position]);
+    positions
+}
+
 pub trait Write {
     type Error: std::error::Error + From<io::Error>;
 
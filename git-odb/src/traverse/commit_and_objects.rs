@@ -0,0 +1,283 @@
+use git_object::{borrowed, owned, Kind, TreeMode};
+use std::collections::{HashSet, VecDeque};
+
+/// The error returned when traversing objects reachable from commits.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("Object {0} could not be found")]
+    NotFound(owned::Id),
+    #[error("Object {id} was expected to be a {expected} but was a {actual}")]
+    UnexpectedKind {
+        id: owned::Id,
+        expected: Kind,
+        actual: Kind,
+    },
+}
+
+/// An object reachable from one of the tips.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    pub id: owned::Id,
+    pub kind: Kind,
+}
+
+/// An iterator over all commits, trees, blobs and tags reachable from a set of tips, returning each object exactly once.
+///
+/// Blobs are never looked up as their kind is known from the tree referencing them, and submodule entries are skipped
+/// as these commits are not part of this repository.
+pub struct Iter<Find> {
+    find: Find,
+    queue: VecDeque<(owned::Id, Option<Kind>)>,
+    seen: HashSet<owned::Id>,
+    shallow: HashSet<owned::Id>,
+    buf: Vec<u8>,
+}
+
+/// Traverse all objects reachable from `tips`, which may be commits, annotated tags, trees or blobs, looking up
+/// objects using `find`.
+pub fn commit_and_objects<Find>(tips: impl IntoIterator<Item = owned::Id>, find: Find) -> Iter<Find>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>>,
+{
+    let mut seen = HashSet::new();
+    let queue = tips
+        .into_iter()
+        .filter(|id| seen.insert(*id))
+        .map(|id| (id, None))
+        .collect();
+    Iter {
+        find,
+        queue,
+        seen,
+        shallow: HashSet::new(),
+        buf: Vec::new(),
+    }
+}
+
+impl<Find> Iter<Find> {
+    /// Mark the given objects as seen, preventing them and everything only reachable through them from being returned.
+    ///
+    /// This must be called before the first iteration to be effective.
+    pub fn with_seen(mut self, ids: impl IntoIterator<Item = owned::Id>) -> Self {
+        let ids: HashSet<_> = ids.into_iter().collect();
+        self.queue.retain(|(id, _)| !ids.contains(id));
+        self.seen.extend(ids);
+        self
+    }
+
+    /// Treat the given commits as if they had no parents, as is the case for the boundary commits of shallow repositories.
+    pub fn with_shallow(mut self, commits: impl IntoIterator<Item = owned::Id>) -> Self {
+        self.shallow.extend(commits);
+        self
+    }
+}
+
+impl<Find> Iter<Find>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>>,
+{
+    fn queue(&mut self, id: owned::Id, kind: Kind) {
+        if self.seen.insert(id) {
+            self.queue.push_back((id, Some(kind)));
+        }
+    }
+
+    fn next_entry(&mut self, id: owned::Id, expected_kind: Option<Kind>) -> Result<Entry, Error> {
+        if let Some(Kind::Blob) = expected_kind {
+            return Ok(Entry { id, kind: Kind::Blob });
+        }
+        let mut children = Vec::new();
+        let kind = {
+            let object = (self.find)(id.to_borrowed(), &mut self.buf).ok_or(Error::NotFound(id))?;
+            let kind = object.kind();
+            if let Some(expected) = expected_kind {
+                if expected != kind {
+                    return Err(Error::UnexpectedKind {
+                        id,
+                        expected,
+                        actual: kind,
+                    });
+                }
+            }
+            match object {
+                borrowed::Object::Commit(commit) => {
+                    children.push((commit.tree(), Kind::Tree));
+                    if !self.shallow.contains(&id) {
+                        children.extend(commit.parents().map(|id| (id, Kind::Commit)));
+                    }
+                }
+                borrowed::Object::Tree(tree) => {
+                    children.extend(tree.entries.iter().filter_map(tree_entry_with_kind));
+                }
+                borrowed::Object::Tag(tag) => children.push((tag.target(), tag.target_kind)),
+                borrowed::Object::Blob(_) => {}
+            }
+            kind
+        };
+        for (id, kind) in children {
+            self.queue(id, kind);
+        }
+        Ok(Entry { id, kind })
+    }
+}
+
+impl<Find> Iterator for Iter<Find>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>>,
+{
+    type Item = Result<Entry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, kind) = self.queue.pop_front()?;
+        let res = self.next_entry(id, kind);
+        if res.is_err() {
+            self.queue.clear();
+        }
+        Some(res)
+    }
+}
+
+pub(crate) fn tree_entry_with_kind(entry: &borrowed::tree::Entry<'_>) -> Option<(owned::Id, Kind)> {
+    let kind = match entry.mode {
+        TreeMode::Tree => Kind::Tree,
+        TreeMode::Blob | TreeMode::BlobExecutable | TreeMode::Link => Kind::Blob,
+        TreeMode::Commit => return None,
+    };
+    Some((owned::Id::from_borrowed_sha1(entry.oid.sha1()), kind))
+}
+
+mod reduce {
+    use super::{Entry, Error};
+    use git_features::parallel;
+
+    pub struct Collect {
+        pub entries: Vec<Entry>,
+    }
+
+    impl parallel::Reducer for Collect {
+        type Input = Result<Vec<Entry>, Error>;
+        type Output = Vec<Entry>;
+        type Error = Error;
+
+        fn feed(&mut self, input: Self::Input) -> Result<(), Self::Error> {
+            self.entries.extend(input?);
+            Ok(())
+        }
+
+        fn finalize(self) -> Result<Self::Output, Self::Error> {
+            Ok(self.entries)
+        }
+    }
+}
+
+/// Like [`commit_and_objects()`][commit_and_objects()], but traverses the trees of all reachable commits using up to
+/// `thread_limit` threads, each using a `find` function created by `new_find`.
+///
+/// Commits and the objects pointed to by tags are traversed first on the current thread, and the returned entries are
+/// in no particular order. All threads share the set of seen objects, so each object is returned only once even if
+/// it is reachable in different ways. Parents of `shallow` commits are not traversed.
+pub fn in_parallel<Find, NewFind>(
+    tips: impl IntoIterator<Item = owned::Id>,
+    shallow: &HashSet<owned::Id>,
+    new_find: NewFind,
+    thread_limit: Option<usize>,
+) -> Result<Vec<Entry>, Error>
+where
+    NewFind: Fn() -> Find + Send + Sync,
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>>,
+{
+    let mut entries = Vec::new();
+    let mut trees = Vec::new();
+    let mut seen = HashSet::new();
+    {
+        let mut buf = Vec::new();
+        let mut find = new_find();
+        let mut queue: VecDeque<_> = tips.into_iter().filter(|id| seen.insert(*id)).collect();
+        while let Some(id) = queue.pop_front() {
+            let object = find(id.to_borrowed(), &mut buf).ok_or(Error::NotFound(id))?;
+            let kind = object.kind();
+            match object {
+                borrowed::Object::Commit(commit) => {
+                    trees.push(commit.tree());
+                    if !shallow.contains(&id) {
+                        queue.extend(commit.parents().filter(|id| seen.insert(*id)));
+                    }
+                }
+                borrowed::Object::Tag(tag) => {
+                    let target = tag.target();
+                    match tag.target_kind {
+                        Kind::Tree => trees.push(target),
+                        Kind::Blob => {
+                            if seen.insert(target) {
+                                entries.push(Entry {
+                                    id: target,
+                                    kind: Kind::Blob,
+                                })
+                            }
+                        }
+                        Kind::Commit | Kind::Tag => {
+                            if seen.insert(target) {
+                                queue.push_back(target)
+                            }
+                        }
+                    }
+                }
+                borrowed::Object::Tree(_) => {
+                    // Trees are marked as seen when they are traversed.
+                    seen.remove(&id);
+                    trees.push(id);
+                    continue;
+                }
+                borrowed::Object::Blob(_) => {}
+            }
+            entries.push(Entry { id, kind });
+        }
+    }
+
+    let seen = parking_lot::Mutex::new(seen);
+    let (chunk_size, thread_limit, _) =
+        git_features::parallel::optimize_chunk_size_and_thread_limit(1, Some(trees.len()), thread_limit, None);
+    let chunks = trees.chunks(chunk_size).map(|c| c.to_vec()).collect::<Vec<_>>();
+    let seen = &seen;
+    let num_chunks = chunks.len();
+    git_features::parallel::in_parallel_if(
+        || num_chunks > 1,
+        chunks.into_iter(),
+        thread_limit,
+        |_| (new_find(), Vec::new()),
+        |trees: Vec<owned::Id>, (find, buf)| {
+            let mut out = Vec::new();
+            let mut queue: Vec<_> = {
+                let mut seen = seen.lock();
+                trees.into_iter().filter(|id| seen.insert(*id)).collect()
+            };
+            let mut children = Vec::new();
+            while let Some(id) = queue.pop() {
+                match find(id.to_borrowed(), buf).ok_or(Error::NotFound(id))? {
+                    borrowed::Object::Tree(tree) => {
+                        children.extend(tree.entries.iter().filter_map(tree_entry_with_kind));
+                    }
+                    object => {
+                        return Err(Error::UnexpectedKind {
+                            id,
+                            expected: Kind::Tree,
+                            actual: object.kind(),
+                        })
+                    }
+                }
+                out.push(Entry { id, kind: Kind::Tree });
+                let mut seen = seen.lock();
+                for (id, kind) in children.drain(..).filter(|(id, _)| seen.insert(*id)) {
+                    match kind {
+                        Kind::Tree => queue.push(id),
+                        _ => out.push(Entry { id, kind }),
+                    }
+                }
+            }
+            Ok(out)
+        },
+        reduce::Collect { entries },
+    )
+}
@@ -0,0 +1,102 @@
+//! Check that all objects reachable from a set of tips exist, like `git` does before accepting the pack of a push.
+use crate::{traverse::commit_and_objects::tree_entry_with_kind, Find};
+use git_object::{borrowed, owned, Kind};
+use std::collections::{HashSet, VecDeque};
+
+/// The error returned by [`check()`].
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("Object {id} was expected to be a {expected} but was a {actual}")]
+    UnexpectedKind {
+        id: owned::Id,
+        expected: Kind,
+        actual: Kind,
+    },
+}
+
+/// The result of [`check()`].
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The amount of reachable objects which exist, not counting the known ones.
+    pub objects: usize,
+    /// The ids of all reachable objects which don't exist, in ascending order.
+    pub missing: Vec<owned::Id>,
+}
+
+impl Outcome {
+    /// Returns true if no reachable object is missing.
+    pub fn is_connected(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Traverse all objects reachable from `tips` in `db` and return the ones which don't exist, instead of stopping at
+/// the first one.
+///
+/// Objects reachable from `known` ones are expected to exist and are not traversed, which typically are the objects
+/// the refs pointed to before the pack was received. Commits, trees and tags are looked up to find the objects they
+/// point to, treating those which can't be read or decoded as missing, while blobs are only checked for existence
+/// all at once with [`contains_many()`][Find::contains_many()]. Submodule entries are skipped as these commits are not
+/// part of this repository.
+pub fn check(
+    db: &impl Find,
+    tips: impl IntoIterator<Item = owned::Id>,
+    known: impl IntoIterator<Item = owned::Id>,
+) -> Result<Outcome, Error> {
+    let mut find = super::find_in(db);
+    let mut seen: HashSet<_> = known.into_iter().collect();
+    let mut queue: VecDeque<_> = tips
+        .into_iter()
+        .filter(|id| seen.insert(*id))
+        .map(|id| (id, None))
+        .collect();
+    let mut outcome = Outcome::default();
+    let mut blobs = Vec::new();
+    let mut buf = Vec::new();
+    while let Some((id, expected)) = queue.pop_front() {
+        if let Some(Kind::Blob) = expected {
+            blobs.push(id);
+            continue;
+        }
+        let object = match find(id.to_borrowed(), &mut buf) {
+            Some(object) => object,
+            None => {
+                outcome.missing.push(id);
+                continue;
+            }
+        };
+        if let Some(expected) = expected.filter(|kind| *kind != object.kind()) {
+            return Err(Error::UnexpectedKind {
+                id,
+                expected,
+                actual: object.kind(),
+            });
+        }
+        outcome.objects += 1;
+        let children: Vec<_> = match object {
+            borrowed::Object::Commit(commit) => Some((commit.tree(), Kind::Tree))
+                .into_iter()
+                .chain(commit.parents().map(|id| (id, Kind::Commit)))
+                .collect(),
+            borrowed::Object::Tree(tree) => tree.entries.iter().filter_map(tree_entry_with_kind).collect(),
+            borrowed::Object::Tag(tag) => vec![(tag.target(), tag.target_kind)],
+            borrowed::Object::Blob(_) => Vec::new(),
+        };
+        for (id, kind) in children {
+            if seen.insert(id) {
+                queue.push_back((id, Some(kind)));
+            }
+        }
+    }
+    for (id, exists) in blobs.iter().zip(db.contains_many(&blobs)) {
+        if exists {
+            outcome.objects += 1;
+        } else {
+            outcome.missing.push(*id);
+        }
+    }
+    outcome.missing.sort();
+    Ok(outcome)
+}
@@ -0,0 +1,28 @@
+//! Traversal of the object graph, independent of the kind of object database.
+//!
+//! All traversals obtain objects using a `find` function taking an object id and a buffer to place the object data in,
+//! returning the decoded object or `None` if it does not exist. [`find_in()`] provides one for any object database.
+use crate::{pack, Find};
+use git_object::borrowed;
+
+pub mod commit_and_objects;
+#[doc(inline)]
+pub use commit_and_objects::commit_and_objects;
+
+pub mod connectivity;
+
+pub mod tree;
+#[doc(inline)]
+pub use tree::tree;
+
+/// Return a `find` function looking up objects in `db` for use with traversals, treating objects which can't be read or
+/// decoded as missing.
+pub fn find_in<D: Find>(
+    db: &D,
+) -> impl for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>> + '_ {
+    let mut cache = pack::cache::DecodeEntryLRU::default();
+    move |id, buf| {
+        let object = db.locate(id, buf, &mut cache)?.ok()?;
+        borrowed::Object::from_bytes(object.kind, object.data).ok()
+    }
+}
@@ -0,0 +1,183 @@
+use git_object::{
+    borrowed,
+    bstr::{BString, ByteSlice},
+    owned, Kind, TreeMode,
+};
+
+/// The error returned when traversing trees.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("Tree {0} could not be found")]
+    NotFound(owned::Id),
+    #[error("Object {id} was expected to be a tree but was a {actual}")]
+    NotATree { id: owned::Id, actual: Kind },
+}
+
+/// An entry of a tree or one of its subtrees.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    /// The path relative to the traversed tree, with components separated by slashes.
+    pub path: BString,
+    pub mode: TreeMode,
+    pub id: owned::Id,
+}
+
+/// Configure which entries to return.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+pub struct Options {
+    /// If set, don't enter trees deeper than this, with 0 only returning the entries of the traversed tree itself.
+    pub max_depth: Option<usize>,
+    /// If not empty, only return entries with one of these paths or below them, like pathspecs without wildcards.
+    ///
+    /// Trees leading to these paths are entered but only returned if `trees` is true.
+    pub paths: Vec<BString>,
+    /// If true, return trees before their entries as well, like `git ls-tree -r -t`. Otherwise only the entries in
+    /// trees which aren't entered are returned.
+    pub trees: bool,
+}
+
+/// An iterator over the entries of a tree and its subtrees, depth first in the order `git ls-tree -r` lists them.
+///
+/// Submodules are returned but not entered, as their commits are not part of this repository.
+pub struct Iter<Find> {
+    find: Find,
+    options: Options,
+    root: Option<owned::Id>,
+    /// The entries of each entered tree yet to be returned, in reverse order, along with the depth of the tree.
+    stack: Vec<(Vec<Entry>, usize)>,
+    buf: Vec<u8>,
+}
+
+/// Traverse `tree` and all trees in it, looking up trees using `find`.
+pub fn tree<Find>(tree: owned::Id, find: Find, options: Options) -> Iter<Find>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>>,
+{
+    let mut options = options;
+    for path in &mut options.paths {
+        let len = path.trim_end_with(|c| c == '/').len();
+        path.truncate(len);
+    }
+    Iter {
+        find,
+        options,
+        root: Some(tree),
+        stack: Vec::new(),
+        buf: Vec::new(),
+    }
+}
+
+/// How an entry relates to the paths to return.
+enum Selection {
+    Selected,
+    Leading,
+    Excluded,
+}
+
+impl<Find> Iter<Find>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>>,
+{
+    fn enter(&mut self, id: owned::Id, path: &[u8], depth: usize) -> Result<(), Error> {
+        let mut entries: Vec<_> = match (self.find)(id.to_borrowed(), &mut self.buf).ok_or(Error::NotFound(id))? {
+            borrowed::Object::Tree(tree) => tree
+                .entries
+                .iter()
+                .map(|entry| {
+                    let mut entry_path = BString::from(path);
+                    if !entry_path.is_empty() {
+                        entry_path.push(b'/');
+                    }
+                    entry_path.extend_from_slice(entry.filename);
+                    Entry {
+                        path: entry_path,
+                        mode: entry.mode,
+                        id: owned::Id::from_borrowed_sha1(entry.oid.sha1()),
+                    }
+                })
+                .collect(),
+            object => {
+                return Err(Error::NotATree {
+                    id,
+                    actual: object.kind(),
+                })
+            }
+        };
+        entries.reverse();
+        self.stack.push((entries, depth));
+        Ok(())
+    }
+
+    fn select(&self, path: &[u8]) -> Selection {
+        if self.options.paths.is_empty() {
+            return Selection::Selected;
+        }
+        let is_below = |path: &[u8], directory: &[u8]| {
+            path.len() > directory.len() && path.starts_with(directory) && path[directory.len()] == b'/'
+        };
+        if self
+            .options
+            .paths
+            .iter()
+            .any(|selected| path == selected.as_slice() || is_below(path, selected))
+        {
+            Selection::Selected
+        } else if self.options.paths.iter().any(|selected| is_below(selected, path)) {
+            Selection::Leading
+        } else {
+            Selection::Excluded
+        }
+    }
+
+    fn next_entry(&mut self) -> Option<Result<Entry, Error>> {
+        if let Some(root) = self.root.take() {
+            if let Err(err) = self.enter(root, &[], 0) {
+                return Some(Err(err));
+            }
+        }
+        loop {
+            let (entry, depth) = {
+                let (entries, depth) = self.stack.last_mut()?;
+                match entries.pop() {
+                    Some(entry) => (entry, *depth),
+                    None => {
+                        self.stack.pop();
+                        continue;
+                    }
+                }
+            };
+            let selection = self.select(&entry.path);
+            if let Selection::Excluded = selection {
+                continue;
+            }
+            let descend = entry.mode == TreeMode::Tree && self.options.max_depth.is_none_or(|max| depth < max);
+            if descend {
+                if let Err(err) = self.enter(entry.id, &entry.path, depth + 1) {
+                    return Some(Err(err));
+                }
+            }
+            match selection {
+                Selection::Selected if !descend || self.options.trees => return Some(Ok(entry)),
+                Selection::Leading if self.options.trees => return Some(Ok(entry)),
+                _ => continue,
+            }
+        }
+    }
+}
+
+impl<Find> Iterator for Iter<Find>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>>,
+{
+    type Item = Result<Entry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.next_entry();
+        if let Some(Err(_)) = res {
+            self.stack.clear();
+        }
+        res
+    }
+}
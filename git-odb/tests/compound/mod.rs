@@ -0,0 +1,243 @@
+use crate::{fixture_path, hex_to_id};
+use git_odb::{compound, pack, prefix};
+use std::{
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+const SMALL_PACK: &str = "pack-a2bf8e71d8c18879e499335762dd95119d93d9f1";
+const OTHER_PACK: &str = "pack-11fdfa9e156ab73caae3b6da867192221f2089c2";
+const LOOSE_OBJECT: &str = "722fe60ad4f0276d5a8121970b5bb9dccdad4ef9";
+
+fn copy_pack(name: &str, objects_dir: &Path) {
+    let pack_dir = objects_dir.join("pack");
+    fs::create_dir_all(&pack_dir).unwrap();
+    for ext in &["idx", "pack"] {
+        let file = format!("{}.{}", name, ext);
+        fs::copy(fixture_path("packs").join(&file), pack_dir.join(file)).unwrap();
+    }
+}
+
+fn object_in(pack_name: &str) -> git_object::owned::Id {
+    let index = pack::index::File::at(fixture_path("packs").join(format!("{}.idx", pack_name))).unwrap();
+    let id = index.iter().next().expect("at least one object").oid;
+    id
+}
+
+fn objects_dir_with_small_pack() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    copy_pack(SMALL_PACK, dir.path());
+    let (prefix, rest) = LOOSE_OBJECT.split_at(2);
+    fs::create_dir_all(dir.path().join(prefix)).unwrap();
+    fs::copy(
+        fixture_path("objects").join(prefix).join(rest),
+        dir.path().join(prefix).join(rest),
+    )
+    .unwrap();
+    dir
+}
+
+#[test]
+fn packed_and_loose_objects_are_found() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = objects_dir_with_small_pack();
+    let db = compound::Db::at(dir.path())?;
+    assert_eq!(db.packs.len(), 1);
+    assert!(!db.has_promisor_packs());
+
+    let mut buf = Vec::new();
+    let id = object_in(SMALL_PACK);
+    let object = db
+        .locate(id.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+        .expect("present")?;
+    object.verify_checksum(id.to_borrowed())?;
+
+    let id = hex_to_id(LOOSE_OBJECT);
+    let object = db
+        .locate(id.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+        .expect("present")?;
+    object.verify_checksum(id.to_borrowed())?;
+
+    assert!(db
+        .locate(
+            object_in(OTHER_PACK).to_borrowed(),
+            &mut buf,
+            &mut pack::cache::DecodeEntryNoop
+        )
+        .is_none());
+    crate::find::assert_headers_match_objects(&db, vec![object_in(SMALL_PACK), hex_to_id(LOOSE_OBJECT)]);
+    Ok(())
+}
+
+#[test]
+fn objects_are_found_by_unique_prefix() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = objects_dir_with_small_pack();
+    let db = compound::Db::at(dir.path())?;
+    let packed = object_in(SMALL_PACK);
+    assert_eq!(db.lookup_prefix(&packed.to_sha1_hex()[..7])?, Some(packed));
+    assert_eq!(
+        db.lookup_prefix(LOOSE_OBJECT[..6].to_uppercase())?,
+        Some(hex_to_id(LOOSE_OBJECT))
+    );
+    assert_eq!(db.lookup_prefix(LOOSE_OBJECT)?, Some(hex_to_id(LOOSE_OBJECT)));
+    assert_eq!(db.lookup_prefix(&object_in(OTHER_PACK).to_sha1_hex()[..8])?, None);
+    assert!(matches!(db.lookup_prefix("722"), Err(prefix::Error::Invalid { .. })));
+    assert!(matches!(db.lookup_prefix("722g"), Err(prefix::Error::Invalid { .. })));
+
+    let (directory, rest) = LOOSE_OBJECT.split_at(2);
+    let similar = format!("{}{}", &rest[..2], "0".repeat(36));
+    fs::copy(
+        dir.path().join(directory).join(rest),
+        dir.path().join(directory).join(similar),
+    )?;
+    match db.lookup_prefix(&LOOSE_OBJECT[..4]) {
+        Err(err @ prefix::Error::Ambiguous { .. }) => {
+            assert_eq!(err.category(), git_odb::Category::Usage);
+            assert_eq!(
+                err.to_string(),
+                "The prefix '722f' is ambiguous as it matches 2 objects"
+            );
+        }
+        res => panic!("expected ambiguity, got {:?}", res),
+    }
+    assert_eq!(db.lookup_prefix(&LOOSE_OBJECT[..5])?, Some(hex_to_id(LOOSE_OBJECT)));
+    Ok(())
+}
+
+#[test]
+fn missing_objects_are_fetched_on_demand_only_with_promisor_packs() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = objects_dir_with_small_pack();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let handler = |objects_dir: std::path::PathBuf, calls: Arc<AtomicUsize>| -> compound::MissingObjectHandler {
+        Box::new(move |_id| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            copy_pack(OTHER_PACK, &objects_dir);
+            Ok(true)
+        })
+    };
+    let missing = object_in(OTHER_PACK);
+    let mut buf = Vec::new();
+
+    let mut db = compound::Db::at(dir.path())?;
+    db.set_missing_object_handler(handler(dir.path().to_owned(), calls.clone()));
+    assert!(db
+        .locate_or_fetch(missing.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+        .is_none());
+    assert_eq!(calls.load(Ordering::SeqCst), 0, "not a partial clone");
+
+    fs::write(dir.path().join("pack").join(format!("{}.promisor", SMALL_PACK)), b"")?;
+    let mut db = compound::Db::at(dir.path())?;
+    assert!(db.has_promisor_packs());
+    db.set_missing_object_handler(handler(dir.path().to_owned(), calls.clone()));
+    let object = db
+        .locate_or_fetch(missing.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+        .expect("fetched")?;
+    object.verify_checksum(missing.to_borrowed())?;
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert_eq!(db.packs.len(), 2, "the packs were refreshed");
+
+    db.locate_or_fetch(missing.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+        .expect("present")?;
+    assert_eq!(calls.load(Ordering::SeqCst), 1, "present objects are not fetched again");
+    Ok(())
+}
+
+#[test]
+fn errors_of_the_missing_object_handler_are_returned() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = objects_dir_with_small_pack();
+    fs::write(dir.path().join("pack").join(format!("{}.promisor", SMALL_PACK)), b"")?;
+    let mut db = compound::Db::at(dir.path())?;
+    db.set_missing_object_handler(Box::new(|_id| Err("remote unavailable".into())));
+    let mut buf = Vec::new();
+    assert!(matches!(
+        db.locate_or_fetch(
+            object_in(OTHER_PACK).to_borrowed(),
+            &mut buf,
+            &mut pack::cache::DecodeEntryNoop
+        ),
+        Some(Err(compound::locate::Error::MissingObjectHandler(_)))
+    ));
+    Ok(())
+}
+
+#[test]
+fn handles_of_a_shared_store_find_objects_on_their_own_threads() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = objects_dir_with_small_pack();
+    let store = compound::Store::at(dir.path())?;
+    let ids = vec![object_in(SMALL_PACK), hex_to_id(LOOSE_OBJECT)];
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let handle = store.to_handle();
+            let ids = ids.clone();
+            std::thread::spawn(move || {
+                let mut buf = Vec::new();
+                for id in ids {
+                    let object = handle.locate(id.to_borrowed(), &mut buf).expect("present").unwrap();
+                    object.verify_checksum(id.to_borrowed()).unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().expect("no panic");
+    }
+    crate::find::assert_headers_match_objects(&store.to_handle(), ids);
+    Ok(())
+}
+
+#[test]
+fn handles_see_packs_added_after_the_store_was_refreshed() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = objects_dir_with_small_pack();
+    let store = compound::Store::at(dir.path())?;
+    let handle = store.to_handle();
+    let clone = handle.clone();
+
+    let id = object_in(OTHER_PACK);
+    let mut buf = Vec::new();
+    copy_pack(OTHER_PACK, dir.path());
+    assert!(handle.locate(id.to_borrowed(), &mut buf).is_none());
+
+    store.refresh()?;
+    for handle in &[handle, clone] {
+        let object = handle.locate(id.to_borrowed(), &mut buf).expect("present")?;
+        object.verify_checksum(id.to_borrowed())?;
+    }
+    Ok(())
+}
+
+#[test]
+fn store_and_handles_report_metrics() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = objects_dir_with_small_pack();
+    let store = compound::Store::at(dir.path())?;
+    let pack_len = |ext: &str| {
+        fs::metadata(fixture_path("packs").join(format!("{}.{}", SMALL_PACK, ext)))
+            .unwrap()
+            .len() as usize
+    };
+    assert_eq!(
+        store.metrics(),
+        compound::store::Metrics {
+            packs: 1,
+            mapped_bytes: pack_len("pack") + pack_len("idx"),
+        }
+    );
+
+    let handle = store.to_handle();
+    let bundle = pack::Bundle::at(fixture_path("packs").join(format!("{}.idx", SMALL_PACK)))?;
+    let mut buf = Vec::new();
+    for entry in bundle.index.iter() {
+        handle.locate(entry.oid.to_borrowed(), &mut buf).expect("present")?;
+    }
+    let metrics = handle.cache_metrics();
+    assert!(
+        metrics.hits + metrics.misses > 0,
+        "delta bases are looked up in the cache"
+    );
+    assert!(metrics.entries > 0 && metrics.cached_bytes > 0);
+    assert_eq!(handle.clone().cache_metrics(), pack::cache::Metrics::default());
+    Ok(())
+}
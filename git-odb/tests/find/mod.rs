@@ -0,0 +1,81 @@
+use crate::{fixture_path, loose::db::object_ids};
+use git_object::{owned, HashKind};
+use git_odb::{loose, memory, pack, Find, Write};
+
+/// Assert that the header of each object has the kind and size of its data, no matter how it is stored, and that
+/// looking up many ids at once finds the same objects as looking them up one by one.
+pub fn assert_headers_match_objects(db: &impl Find, ids: impl IntoIterator<Item = owned::Id>) {
+    let ids: Vec<_> = ids.into_iter().collect();
+    let mut buf = Vec::new();
+    for &id in &ids {
+        assert!(db.contains(id.to_borrowed()));
+        let (kind, size) = db.header(id.to_borrowed()).expect("present").expect("readable");
+        let object = db
+            .locate(id.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+            .expect("present")
+            .expect("readable");
+        object.verify_checksum(id.to_borrowed()).expect("valid object");
+        assert_eq!((kind, size), (object.kind, object.data.len() as u64), "{}", id);
+    }
+    let missing = owned::Id::null();
+    assert!(!db.contains(missing.to_borrowed()));
+    assert!(db.header(missing.to_borrowed()).is_none());
+
+    let mut queries: Vec<_> = ids.iter().rev().copied().collect();
+    queries.insert(queries.len() / 2, missing);
+    queries.extend(ids.first().copied());
+    let mut expected = vec![true; queries.len()];
+    expected[ids.len() / 2] = false;
+    assert_eq!(
+        db.contains_many(&queries),
+        expected,
+        "unordered and duplicate ids are found in any order"
+    );
+}
+
+fn copy(from: &impl Find, ids: impl IntoIterator<Item = owned::Id>, to: &impl Write) {
+    let mut buf = Vec::new();
+    for id in ids {
+        let object = from
+            .locate(id.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+            .expect("present")
+            .expect("readable");
+        let written = to.write_buf(object.kind, object.data, HashKind::Sha1).expect("written");
+        assert_eq!(written, id);
+    }
+}
+
+#[test]
+fn headers_of_packed_objects_are_read_without_resolving_deltas() -> Result<(), Box<dyn std::error::Error>> {
+    let mut num_deltas = 0;
+    for entry in std::fs::read_dir(fixture_path("packs"))? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("idx") {
+            continue;
+        }
+        let bundle = pack::Bundle::at(&path)?;
+        num_deltas += bundle
+            .index
+            .iter()
+            .filter(|entry| bundle.pack.entry(entry.pack_offset).header.is_delta())
+            .count();
+        assert_headers_match_objects(&bundle, bundle.index.iter().map(|entry| entry.oid));
+    }
+    assert!(num_deltas > 0, "there are deltified objects to resolve");
+    Ok(())
+}
+
+#[test]
+fn loose_objects_and_memory_stores_are_interchangeable() {
+    let loose = loose::Db::at(fixture_path("objects"));
+    assert_headers_match_objects(&loose, object_ids());
+
+    let store = memory::Store::new();
+    copy(&loose, object_ids(), &store);
+    assert_headers_match_objects(&store, object_ids());
+
+    let dir = tempfile::tempdir().unwrap();
+    let copied = loose::Db::at(dir.path());
+    copy(&store, object_ids(), &copied);
+    assert_headers_match_objects(&copied, object_ids());
+}
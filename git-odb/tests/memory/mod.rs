@@ -0,0 +1,44 @@
+use crate::loose::db::{locate_oid, object_ids};
+use git_object::HashKind;
+use git_odb::{memory, Write};
+
+#[test]
+fn objects_written_in_any_order_are_located_and_iterated_in_order() -> Result<(), Box<dyn std::error::Error>> {
+    let store = memory::Store::new();
+    assert!(store.is_empty());
+    for oid in object_ids().into_iter().rev() {
+        let mut obj = locate_oid(oid);
+        assert_eq!(store.write(&obj.decode()?.into(), HashKind::Sha1)?, oid);
+    }
+    let oid = object_ids()[0];
+    let mut obj = locate_oid(oid);
+    assert_eq!(
+        store.write(&obj.decode()?.into(), HashKind::Sha1)?,
+        oid,
+        "writing an object twice is fine"
+    );
+    assert_eq!(store.len(), object_ids().len());
+    assert_eq!(store.iter().collect::<Vec<_>>(), object_ids());
+
+    let mut buf = Vec::new();
+    for oid in object_ids() {
+        assert!(store.contains(oid.to_borrowed()));
+        let object = store.locate(oid.to_borrowed(), &mut buf).expect("present");
+        let mut expected = locate_oid(oid);
+        assert_eq!(object.kind, expected.kind);
+        assert_eq!(object.decode()?, expected.decode()?);
+    }
+    let missing = git_object::owned::Id::null();
+    assert!(!store.contains(missing.to_borrowed()));
+    assert!(store.locate(missing.to_borrowed(), &mut buf).is_none());
+    Ok(())
+}
+
+#[test]
+fn streams_shorter_than_announced_are_rejected() {
+    let store = memory::Store::new();
+    assert!(store
+        .write_stream(git_object::Kind::Blob, 10, &b"short"[..], HashKind::Sha1)
+        .is_err());
+    assert!(store.is_empty());
+}
@@ -22,6 +22,10 @@ pub fn fixture_path(path: &str) -> PathBuf {
     PathBuf::from("tests").join("fixtures").join(path)
 }
 
+mod compound;
+mod find;
 mod loose;
+mod memory;
 mod pack;
 mod sink;
+mod traverse;
@@ -33,6 +33,28 @@ mod locate {
             }
             Ok(())
         }
+
+        #[test]
+        fn in_memory() -> Result<(), Box<dyn std::error::Error>> {
+            for (index_path, data_path) in PACKS_AND_INDICES {
+                let bundle = pack::Bundle {
+                    pack: pack::data::File::from_data(std::fs::read(fixture_path(data_path))?)?,
+                    index: pack::index::File::from_data(std::fs::read(fixture_path(index_path))?)?,
+                };
+                assert_eq!(bundle.pack.path(), std::path::Path::new(""));
+
+                let mut buf = Vec::new();
+                for entry in bundle.index.iter() {
+                    let obj = bundle
+                        .locate(entry.oid.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+                        .expect("id present")?;
+                    obj.verify_checksum(entry.oid.to_borrowed())?;
+                }
+            }
+            assert!(pack::data::File::from_data(b"PACK".to_vec()).is_err());
+            assert!(pack::index::File::from_data(Vec::new()).is_err());
+            Ok(())
+        }
     }
 
     #[test]
@@ -153,3 +175,113 @@ mod write_to_directory {
         .map_err(Into::into)
     }
 }
+
+mod at_with_options {
+    use crate::{
+        fixture_path,
+        pack::{SMALL_PACK, SMALL_PACK_INDEX},
+    };
+    use git_odb::{open, pack, Category};
+    use std::{fs, path::PathBuf};
+
+    fn options(verify: open::Verify) -> open::Options {
+        open::Options { verify }
+    }
+
+    fn copy_with(change: impl FnOnce(&mut Vec<u8>, &mut Vec<u8>)) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut pack = fs::read(fixture_path(SMALL_PACK)).unwrap();
+        let mut index = fs::read(fixture_path(SMALL_PACK_INDEX)).unwrap();
+        change(&mut pack, &mut index);
+        let index_path = dir.path().join("pack-test.idx");
+        fs::write(index_path.with_extension("pack"), pack).unwrap();
+        fs::write(&index_path, index).unwrap();
+        (dir, index_path)
+    }
+
+    #[test]
+    fn intact_files_pass_all_levels() -> Result<(), Box<dyn std::error::Error>> {
+        for verify in &[open::Verify::None, open::Verify::Header, open::Verify::Checksum] {
+            pack::Bundle::at_with_options(fixture_path(SMALL_PACK_INDEX), options(*verify))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn corrupt_pack_data_is_only_found_by_checksum() {
+        let (_dir, path) = copy_with(|pack, _| {
+            let middle = pack.len() / 2;
+            pack[middle] ^= 0xff;
+        });
+        assert!(pack::Bundle::at_with_options(&path, options(open::Verify::Header)).is_ok());
+        let err = pack::Bundle::at_with_options(&path, options(open::Verify::Checksum))
+            .err()
+            .expect("checksum mismatch");
+        assert!(matches!(
+            err,
+            pack::bundle::Error::Pack(pack::data::parse::Error::Checksum(
+                pack::data::verify::Error::Mismatch { .. }
+            ))
+        ));
+        assert_eq!(err.category(), Category::Corruption);
+    }
+
+    #[test]
+    fn inconsistent_index_header_is_found_by_header_verification() {
+        let (_dir, path) = copy_with(|_, index| index[8..12].copy_from_slice(&[0xff; 4]));
+        assert!(pack::Bundle::at(&path).is_ok());
+        let err = pack::Bundle::at_with_options(&path, options(open::Verify::Header))
+            .err()
+            .expect("fan-out isn't ascending");
+        assert!(matches!(
+            err,
+            pack::bundle::Error::Index(pack::index::init::Error::InconsistentHeader { .. })
+        ));
+        assert_eq!(err.category(), Category::Corruption);
+    }
+}
+
+mod entry_size_info {
+    use crate::{fixture_path, hex_to_id, pack::SMALL_PACK_INDEX};
+    use git_object::Kind;
+    use git_odb::pack::{self, bundle::locate::SizeInfo};
+
+    fn size_info(hex_id: &str) -> Option<SizeInfo> {
+        let bundle = pack::Bundle::at(fixture_path(SMALL_PACK_INDEX)).expect("pack and idx");
+        bundle
+            .entry_size_info(hex_to_id(hex_id).to_borrowed())
+            .map(|res| res.expect("readable headers"))
+    }
+
+    #[test]
+    fn matches_what_git_reports() {
+        assert_eq!(
+            size_info("bf027b93fe40c8b0b5993918fadf4678749959f4"),
+            Some(SizeInfo {
+                kind: Kind::Tree,
+                size: 34,
+                disk_size: 45,
+                delta_depth: 0,
+                base: None
+            }),
+            "undeltified objects"
+        );
+        assert_eq!(
+            size_info("b8aa61be84b78d7fcff788e8d844406cc97132bf"),
+            Some(SizeInfo {
+                kind: Kind::Blob,
+                size: 173,
+                disk_size: 16,
+                delta_depth: 2,
+                base: Some(hex_to_id("e0dc2c78d3bd3d14d20906ad7905a32f0f0918a8"))
+            }),
+            "the base is the one the delta applies to, not the one at the end of the chain"
+        );
+        assert_eq!(
+            size_info("531ea8f97a99eee41a7678d94f14d0dba6587c66").map(|info| (info.size, info.disk_size)),
+            Some((1180, 50)),
+            "the last entry ends where the trailer begins"
+        );
+        assert_eq!(size_info("ffffffffffffffffffffffffffffffffffffffff"), None);
+    }
+}
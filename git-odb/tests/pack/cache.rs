@@ -0,0 +1,39 @@
+use git_object::Kind;
+use git_odb::pack::cache::{DecodeEntry, DecodeEntryLRU, DecodeEntryNoop, Metrics};
+
+#[test]
+fn lru_counts_hits_misses_and_evictions() {
+    let mut cache = DecodeEntryLRU::default();
+    let mut out = Vec::new();
+    assert_eq!(cache.get(0, &mut out), None);
+
+    for offset in 0..65 {
+        cache.put(offset, &[1, 2, 3][..(offset % 3 + 1) as usize], Kind::Blob, 10);
+    }
+    assert_eq!(
+        cache.get(0, &mut out),
+        None,
+        "the least recently used entry was evicted"
+    );
+    assert_eq!(cache.get(64, &mut out), Some((Kind::Blob, 10)));
+    assert_eq!(out, &[1, 2]);
+
+    assert_eq!(
+        cache.metrics(),
+        Metrics {
+            hits: 1,
+            misses: 2,
+            evictions: 1,
+            entries: 64,
+            cached_bytes: (1..65).map(|offset| offset % 3 + 1).sum(),
+        }
+    );
+}
+
+#[test]
+fn noop_has_no_metrics() {
+    let mut cache = DecodeEntryNoop;
+    cache.put(0, b"data", Kind::Blob, 4);
+    assert_eq!(cache.get(0, &mut Vec::new()), None);
+    assert_eq!(cache.metrics(), Metrics::default());
+}
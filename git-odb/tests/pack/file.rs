@@ -9,6 +9,7 @@ fn pack_at(at: &str) -> pack::data::File {
 mod method {
     use crate::{pack::file::pack_at, pack::SMALL_PACK};
     use git_features::progress;
+    use git_odb::pack;
 
     #[test]
     fn checksum() {
@@ -26,6 +27,26 @@ mod method {
         Ok(())
     }
 
+    #[test]
+    fn entry_slice() {
+        let pack = pack_at(SMALL_PACK);
+        let entries_end = pack.pack_end() as u64;
+        let first_entry = pack::data::File::HEADER_LEN as u64;
+        assert_eq!(
+            pack.entry_slice(first_entry..entries_end),
+            Some(pack.entries_data()),
+            "all entries can be referenced at once"
+        );
+        assert_eq!(pack.entry_slice(first_entry..first_entry + 2).map(|s| s.len()), Some(2));
+        assert_eq!(pack.entry_slice(0..first_entry), None, "the pack header isn't an entry");
+        assert_eq!(
+            pack.entry_slice(first_entry..entries_end + 1),
+            None,
+            "the trailing hash isn't part of the entries"
+        );
+        assert_eq!(pack.entry_slice(entries_end..u64::MAX), None);
+    }
+
     #[test]
     fn iter() -> Result<(), Box<dyn std::error::Error>> {
         let pack = pack_at(SMALL_PACK);
@@ -147,3 +168,243 @@ mod decompress_entry {
         buf
     }
 }
+
+mod write_objects_to_stream {
+    use crate::{fixture_path, pack::SMALL_PACK_INDEX};
+    use git_features::progress;
+    use git_odb::pack::{self, bundle};
+    use std::{io::Cursor, path::PathBuf};
+
+    #[test]
+    fn all_objects_of_a_pack_make_an_equivalent_pack() -> Result<(), Box<dyn std::error::Error>> {
+        let bundle = pack::Bundle::at(fixture_path(SMALL_PACK_INDEX))?;
+        let ids: Vec<_> = bundle.index.iter().map(|e| e.oid).collect();
+        let mut pack = Vec::new();
+        let outcome = pack::data::File::write_objects_to_stream(
+            &ids,
+            |id, buf| bundle.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok(),
+            progress::Discard,
+            &mut pack,
+        )?;
+        assert_eq!(outcome.num_objects, 42);
+        assert_eq!(outcome.bytes_written, pack.len() as u64);
+
+        let written = pack::Bundle::write_to_directory(
+            Cursor::new(pack),
+            None,
+            None::<PathBuf>,
+            progress::Discard,
+            bundle::write::Options {
+                thread_limit: None,
+                iteration_mode: pack::data::iter::Mode::Verify,
+                index_kind: pack::index::Kind::V2,
+            },
+        )?;
+        assert_eq!(written.index.num_objects, 42, "the pack can be read back");
+        assert_eq!(
+            written.index.data_hash, outcome.data_hash,
+            "the trailer is the hash of the pack"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn missing_objects_are_an_error() {
+        let ids = vec![crate::hex_to_id("0000000000000000000000000000000000000001")];
+        assert!(matches!(
+            pack::data::File::write_objects_to_stream(&ids, |_, _| None, progress::Discard, Vec::new()),
+            Err(pack::data::write::Error::NotFound(_))
+        ));
+    }
+}
+
+mod write_thin_objects_to_stream {
+    use git_features::progress;
+    use git_object::{borrowed, owned, HashKind, Kind};
+    use git_odb::{pack, Write};
+    use std::collections::HashMap;
+
+    fn find_blob<'a>(
+        blobs: &HashMap<owned::Id, Vec<u8>>,
+        id: borrowed::Id<'_>,
+        buf: &'a mut Vec<u8>,
+    ) -> Option<pack::Object<'a>> {
+        let data = blobs.get(&owned::Id::from_borrowed_sha1(id.sha1()))?;
+        buf.clear();
+        buf.extend_from_slice(data);
+        Some(pack::Object {
+            kind: Kind::Blob,
+            data: buf.as_slice(),
+        })
+    }
+
+    #[test]
+    fn similar_objects_become_ref_deltas_to_the_bases_outside_of_the_pack() -> Result<(), Box<dyn std::error::Error>> {
+        let base_data: Vec<u8> = (0..500u32).flat_map(|n| format!("line {}\n", n).into_bytes()).collect();
+        let mut changed = base_data.clone();
+        changed.extend_from_slice(b"one more line\n");
+        let mut objects = HashMap::new();
+        let mut add = |data: Vec<u8>| {
+            let id = git_odb::sink().write_buf(Kind::Blob, &data, HashKind::Sha1).unwrap();
+            objects.insert(id, data);
+            id
+        };
+        let base = add(base_data);
+        let changed = add(changed);
+        let unrelated = add(b"nothing in common".to_vec());
+
+        let mut thin = Vec::new();
+        let outcome = pack::data::File::write_thin_objects_to_stream(
+            &[(changed, Some(base)), (unrelated, Some(base))],
+            |id, buf| find_blob(&objects, id, buf),
+            progress::Discard,
+            &mut thin,
+        )?;
+        assert_eq!(outcome.num_objects, 2);
+        assert_eq!(outcome.bytes_written, thin.len() as u64);
+        let thin = pack::data::File::from_data(thin)?;
+        assert_eq!(
+            thin.entry(pack::data::File::HEADER_LEN as u64).header,
+            pack::data::Header::RefDelta { base_id: base }
+        );
+
+        let mut completed = Vec::new();
+        thin.write_completed_to_stream(
+            |id, buf| find_blob(&objects, id, buf),
+            progress::Discard,
+            &mut completed,
+        )?;
+        let entries = pack::data::Iter::new_from_header(
+            completed.as_slice(),
+            pack::data::iter::Mode::Verify,
+            pack::data::iter::CompressedBytesMode::Ignore,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+        let completed = pack::data::File::from_data(completed)?;
+        assert_eq!(completed.num_objects(), 3, "the base was added");
+        let mut buf = Vec::new();
+        let mut decoded = Vec::new();
+        for entry in entries {
+            completed.decode_entry(
+                completed.entry(entry.pack_offset),
+                &mut buf,
+                |_, _| None,
+                &mut pack::cache::DecodeEntryNoop,
+            )?;
+            decoded.push(buf.clone());
+        }
+        assert_eq!(
+            decoded,
+            vec![
+                objects[&base].clone(),
+                objects[&changed].clone(),
+                objects[&unrelated].clone()
+            ],
+            "the base comes first, and the delta decodes to the changed object"
+        );
+        Ok(())
+    }
+}
+
+mod write_completed_to_stream {
+    use crate::{fixture_path, hex_to_id, pack::file::pack_at, pack::SMALL_PACK};
+    use git_features::progress;
+    use git_odb::{compound, pack};
+    use std::io::Cursor;
+
+    const THIN_PACK: &str = "packs/thin.pack";
+
+    #[test]
+    fn thin_packs_get_the_missing_bases_and_can_be_indexed() -> Result<(), Box<dyn std::error::Error>> {
+        let thin = pack_at(THIN_PACK);
+        assert_eq!(thin.num_objects(), 5);
+        let bases = compound::Db::at(fixture_path("thin-pack-bases"))?;
+        let mut pack = Vec::new();
+        let outcome = thin.write_completed_to_stream(
+            |id, buf| bases.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok(),
+            progress::Discard,
+            &mut pack,
+        )?;
+        assert_eq!(outcome.num_objects, 6, "the only missing base is added");
+        assert_eq!(outcome.bytes_written, pack.len() as u64);
+
+        let dir = tempfile::tempdir()?;
+        let written = pack::Bundle::write_to_directory(
+            Cursor::new(pack),
+            None,
+            Some(dir.path()),
+            progress::Discard,
+            pack::bundle::write::Options {
+                thread_limit: None,
+                iteration_mode: pack::data::iter::Mode::Verify,
+                index_kind: pack::index::Kind::V2,
+            },
+        )?;
+        assert_eq!(written.index.num_objects, 6);
+        assert_eq!(written.index.data_hash, outcome.data_hash);
+
+        let bundle = written.to_bundle().expect("written to a directory")?;
+        let mut buf = Vec::new();
+        for (hex, kind) in &[
+            ("6218a7763d80fddbb991ec40d351be7b84c1d120", git_object::Kind::Blob),
+            ("8a2895371554ebf62ad9cea33715d12b55e17167", git_object::Kind::Blob),
+            ("1bfc6395a779e639115bb039727431c3ab86b432", git_object::Kind::Blob),
+            ("22572d397f4c5698addb137bd58ed1048e7cb2ba", git_object::Kind::Blob),
+        ] {
+            let object = bundle
+                .locate(
+                    hex_to_id(hex).to_borrowed(),
+                    &mut buf,
+                    &mut pack::cache::DecodeEntryNoop,
+                )
+                .expect("object to be in the pack")?;
+            assert_eq!(object.kind, *kind);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn packs_without_ref_deltas_are_written_unchanged() -> Result<(), Box<dyn std::error::Error>> {
+        let mut pack = Vec::new();
+        pack_at(SMALL_PACK).write_completed_to_stream(|_, _| None, progress::Discard, &mut pack)?;
+        assert_eq!(pack, std::fs::read(fixture_path(SMALL_PACK))?);
+        Ok(())
+    }
+
+    #[test]
+    fn missing_bases_are_an_error() {
+        let err = pack_at(THIN_PACK)
+            .write_completed_to_stream(|_, _| None, progress::Discard, Vec::new())
+            .expect_err("the base is missing");
+        assert!(matches!(
+            err,
+            pack::data::write::Error::NotFound(id) if id == hex_to_id("6218a7763d80fddbb991ec40d351be7b84c1d120")
+        ));
+    }
+}
+
+mod error {
+    use crate::{fixture_path, pack::SMALL_PACK};
+    use git_odb::{pack, Category};
+    use std::error::Error;
+
+    #[test]
+    fn truncated_pack_is_corruption() {
+        let err = pack::data::File::from_data(b"PACK".to_vec()).err().expect("too small");
+        assert!(matches!(err, pack::data::parse::Error::TooSmall { size: 4 }));
+        assert_eq!(err.category(), Category::Corruption);
+    }
+
+    #[test]
+    fn missing_pack_is_environment_with_io_source() {
+        let path = fixture_path(SMALL_PACK).with_extension("missing");
+        let err = pack::data::File::at(&path).err().expect("missing");
+        assert_eq!(err.category(), Category::Environment);
+        let source = err
+            .source()
+            .and_then(|source| source.downcast_ref::<std::io::Error>())
+            .expect("io error source");
+        assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+        assert!(err.to_string().contains(&path.display().to_string()));
+    }
+}
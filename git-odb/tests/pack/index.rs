@@ -33,6 +33,17 @@ mod method {
                 assert_eq!(entry.pack_offset, idx.pack_offset_at_index(index));
                 assert_eq!(entry.crc32, idx.crc32_at_index(index));
             }
+            let mut ids: Vec<_> = idx.iter().map(|entry| entry.oid).collect();
+            let middle = ids.len() / 2;
+            ids.insert(middle, ids[middle]);
+            ids.push(owned::Id::from_40_bytes_in_hex(
+                b"ffffffffffffffffffffffffffffffffffffffff",
+            )?);
+            assert_eq!(
+                idx.lookup_sorted(ids.iter().map(|id| id.to_borrowed())),
+                ids.iter().map(|id| idx.lookup(id.to_borrowed())).collect::<Vec<_>>(),
+                "sorted lookups find each id, even if it's given twice"
+            );
             Ok(())
         }
     }
@@ -63,6 +74,17 @@ mod method {
                 assert_eq!(entry.pack_offset, idx.pack_offset_at_index(index));
                 assert_eq!(entry.crc32, idx.crc32_at_index(index), "{} {:?}", index, entry);
             }
+            let mut ids: Vec<_> = idx.iter().map(|entry| entry.oid).collect();
+            let middle = ids.len() / 2;
+            ids.insert(middle, ids[middle]);
+            ids.push(owned::Id::from_40_bytes_in_hex(
+                b"ffffffffffffffffffffffffffffffffffffffff",
+            )?);
+            assert_eq!(
+                idx.lookup_sorted(ids.iter().map(|id| id.to_borrowed())),
+                ids.iter().map(|id| idx.lookup(id.to_borrowed())).collect::<Vec<_>>(),
+                "sorted lookups find each id, even if it's given twice"
+            );
             Ok(())
         }
     }
@@ -399,3 +421,12 @@ fn iter() -> Result<(), Box<dyn std::error::Error>> {
     }
     Ok(())
 }
+
+#[test]
+fn truncated_index_is_corruption() {
+    let err = index::File::from_data(vec![0xff, b't', b'O', b'c'])
+        .err()
+        .expect("too small");
+    assert!(matches!(err, index::init::Error::TooSmall { size: 4 }));
+    assert_eq!(err.category(), git_odb::Category::Corruption);
+}
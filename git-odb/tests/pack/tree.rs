@@ -28,6 +28,59 @@ mod method {
             Ok(())
         }
     }
+
+    mod traverse {
+        use crate::{
+            fixture_path,
+            pack::{SMALL_PACK, SMALL_PACK_INDEX},
+        };
+        use git_odb::pack;
+
+        /// The pack offset and id of each resolved object.
+        type Resolved = Vec<(u64, [u8; 20])>;
+
+        fn resolved_objects(cache_limit: Option<u64>) -> Result<Resolved, Box<dyn std::error::Error>> {
+            let idx = pack::index::File::at(fixture_path(SMALL_PACK_INDEX))?;
+            let data = pack::data::File::at(fixture_path(SMALL_PACK))?;
+            let mut tree = pack::tree::Tree::from_offsets_in_pack(
+                idx.sorted_offsets().into_iter().map(|ofs| (ofs, [0u8; 20])),
+                |(ofs, _)| *ofs,
+                fixture_path(SMALL_PACK),
+                git_features::progress::Discard,
+                |id| idx.lookup(id).map(|index| idx.pack_offset_at_index(index)),
+            )?;
+            if let Some(limit) = cache_limit {
+                tree.set_cache_limit(limit);
+            }
+            let mut items = tree.traverse(
+                || false,
+                |slice, out| data.entry_slice(slice).map(|entry| out.copy_from_slice(entry)),
+                git_features::progress::Discard,
+                git_features::progress::Discard,
+                None,
+                data.pack_end() as u64,
+                || (),
+                |(_, digest), _progress, ctx| -> Result<(), std::convert::Infallible> {
+                    *digest = git_features::hash::sha1(ctx.decompressed);
+                    Ok(())
+                },
+            )?;
+            items.sort_by_key(|item| item.offset);
+            Ok(items.into_iter().map(|item| item.data).collect())
+        }
+
+        #[test]
+        fn bases_written_to_disk_resolve_to_the_same_objects() -> Result<(), Box<dyn std::error::Error>> {
+            let in_memory = resolved_objects(None)?;
+            assert_eq!(in_memory.len(), 42, "all objects are resolved");
+            assert_eq!(
+                resolved_objects(Some(0))?,
+                in_memory,
+                "the cache limit only moves bases to disk, without affecting the result"
+            );
+            Ok(())
+        }
+    }
 }
 
 struct TreeItem<D> {
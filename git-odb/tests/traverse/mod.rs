@@ -0,0 +1,384 @@
+mod commit_and_objects {
+    use crate::{fixture_path, hex_to_id, pack::SMALL_PACK_INDEX};
+    use git_object::{borrowed, owned, HashKind, Kind, TreeMode};
+    use git_odb::{memory, pack, traverse, Write};
+    use std::collections::HashSet;
+
+    const TIP: &str = "779c5451ba9fe210ffd1f55db202e55f51acecac";
+
+    fn bundle() -> pack::Bundle {
+        pack::Bundle::at(fixture_path(SMALL_PACK_INDEX)).expect("pack and idx")
+    }
+
+    fn find(
+        bundle: &pack::Bundle,
+    ) -> impl for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>> + '_ {
+        move |id, buf| {
+            let object = bundle.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+            borrowed::Object::from_bytes(object.kind, object.data).ok()
+        }
+    }
+
+    fn find_in_store(
+        store: &memory::Store,
+    ) -> impl for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>> + '_ {
+        move |id, buf| {
+            let object = store.locate(id, buf)?;
+            borrowed::Object::from_bytes(object.kind, object.data).ok()
+        }
+    }
+
+    fn count_kinds(entries: &[traverse::commit_and_objects::Entry]) -> (usize, usize, usize) {
+        let count = |kind| entries.iter().filter(|e| e.kind == kind).count();
+        (count(Kind::Commit), count(Kind::Tree), count(Kind::Blob))
+    }
+
+    #[test]
+    fn all_reachable_objects_exactly_once() -> Result<(), Box<dyn std::error::Error>> {
+        let bundle = bundle();
+        let entries =
+            traverse::commit_and_objects(Some(hex_to_id(TIP)), find(&bundle)).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            entries.len(),
+            42,
+            "the pack contains only objects reachable from the tip"
+        );
+        assert_eq!(count_kinds(&entries), (14, 14, 14));
+
+        let unique: HashSet<_> = entries.iter().map(|e| e.id).collect();
+        let in_pack: HashSet<_> = bundle.index.iter().map(|e| e.oid).collect();
+        assert_eq!(unique, in_pack);
+        Ok(())
+    }
+
+    #[test]
+    fn in_parallel_yields_the_same_objects() -> Result<(), Box<dyn std::error::Error>> {
+        let bundle = bundle();
+        let serial: HashSet<_> =
+            traverse::commit_and_objects(Some(hex_to_id(TIP)), find(&bundle)).collect::<Result<_, _>>()?;
+        for thread_limit in &[Some(1), None] {
+            let parallel = traverse::commit_and_objects::in_parallel(
+                Some(hex_to_id(TIP)),
+                &Default::default(),
+                || find(&bundle),
+                *thread_limit,
+            )?;
+            assert_eq!(parallel.len(), serial.len(), "every object is returned only once");
+            assert_eq!(parallel.into_iter().collect::<HashSet<_>>(), serial);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn in_parallel_yields_objects_reachable_in_different_ways_once() -> Result<(), Box<dyn std::error::Error>> {
+        let store = memory::Store::new();
+        let blob = store.write_buf(Kind::Blob, b"content", HashKind::Sha1)?;
+        let tree = store.write(
+            &owned::Object::Tree(owned::Tree {
+                entries: vec![owned::tree::Entry {
+                    mode: TreeMode::Blob,
+                    filename: "file".into(),
+                    oid: blob,
+                }],
+            }),
+            HashKind::Sha1,
+        )?;
+        let tag = |target, target_kind, name: &str| {
+            store.write(
+                &owned::Object::Tag(owned::Tag {
+                    target,
+                    target_kind,
+                    name: name.into(),
+                    signature: None,
+                    message: Default::default(),
+                    pgp_signature: None,
+                }),
+                HashKind::Sha1,
+            )
+        };
+        let tips = vec![
+            tag(blob, Kind::Blob, "blob")?,
+            tag(tree, Kind::Tree, "tree")?,
+            blob,
+            tree,
+        ];
+        let find = || find_in_store(&store);
+
+        let serial = traverse::commit_and_objects(tips.clone(), find()).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(serial.len(), 4);
+        for thread_limit in &[Some(1), None] {
+            let mut parallel =
+                traverse::commit_and_objects::in_parallel(tips.clone(), &Default::default(), find, *thread_limit)?;
+            parallel.sort();
+            let mut expected = serial.clone();
+            expected.sort();
+            assert_eq!(parallel, expected, "the blob and the tree are returned only once");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn seen_objects_stop_the_traversal() -> Result<(), Box<dyn std::error::Error>> {
+        let bundle = bundle();
+        let entries = traverse::commit_and_objects(Some(hex_to_id(TIP)), find(&bundle))
+            .with_seen(Some(hex_to_id("94227580f13b8e1fd02ab7fe3db79cd99b294ef9")))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            count_kinds(&entries).0,
+            2,
+            "only the commits in front of the seen one are returned"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn shallow_commits_have_no_parents() -> Result<(), Box<dyn std::error::Error>> {
+        let bundle = bundle();
+        let shallow: HashSet<_> = Some(hex_to_id("60e868acbaebff8a3150956f72639c1ecf095b00"))
+            .into_iter()
+            .collect();
+        let entries = traverse::commit_and_objects(Some(hex_to_id(TIP)), find(&bundle))
+            .with_shallow(shallow.iter().cloned())
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(count_kinds(&entries).0, 2, "the tip and the shallow commit");
+
+        let parallel =
+            traverse::commit_and_objects::in_parallel(Some(hex_to_id(TIP)), &shallow, || find(&bundle), None)?;
+        assert_eq!(
+            parallel.into_iter().collect::<HashSet<_>>(),
+            entries.into_iter().collect::<HashSet<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn missing_objects_end_the_iteration_with_an_error() {
+        let bundle = bundle();
+        let mut iter = traverse::commit_and_objects(
+            Some(hex_to_id("0000000000000000000000000000000000000001")),
+            find(&bundle),
+        );
+        assert!(matches!(
+            iter.next(),
+            Some(Err(traverse::commit_and_objects::Error::NotFound(_)))
+        ));
+        assert!(iter.next().is_none());
+    }
+}
+
+mod tree {
+    use git_object::{
+        borrowed,
+        bstr::{BString, ByteSlice},
+        owned, HashKind, TreeMode,
+    };
+    use git_odb::{memory, traverse, Write};
+
+    /// A tree with `a`, `dir/b`, `dir/sub/c`, `link` and the submodule `sm`, returned with its id.
+    fn store() -> Result<(memory::Store, owned::Id), Box<dyn std::error::Error>> {
+        let store = memory::Store::new();
+        let blob = |store: &memory::Store, data: &[u8]| {
+            store.write(
+                &owned::Object::Blob(owned::Blob { data: data.to_vec() }),
+                HashKind::Sha1,
+            )
+        };
+        let tree = |store: &memory::Store, entries: Vec<(TreeMode, &str, owned::Id)>| {
+            let entries = entries
+                .into_iter()
+                .map(|(mode, filename, oid)| owned::tree::Entry {
+                    mode,
+                    filename: filename.into(),
+                    oid,
+                })
+                .collect();
+            store.write(&owned::Object::Tree(owned::Tree { entries }), HashKind::Sha1)
+        };
+        let sub = tree(&store, vec![(TreeMode::Blob, "c", blob(&store, b"c")?)])?;
+        let dir = tree(
+            &store,
+            vec![(TreeMode::Blob, "b", blob(&store, b"b")?), (TreeMode::Tree, "sub", sub)],
+        )?;
+        let root = tree(
+            &store,
+            vec![
+                (TreeMode::BlobExecutable, "a", blob(&store, b"a")?),
+                (TreeMode::Tree, "dir", dir),
+                (TreeMode::Link, "link", blob(&store, b"a")?),
+                (TreeMode::Commit, "sm", owned::Id::null()),
+            ],
+        )?;
+        Ok((store, root))
+    }
+
+    fn find(
+        store: &memory::Store,
+    ) -> impl for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>> + '_ {
+        move |id, buf| {
+            let object = store.locate(id, buf)?;
+            borrowed::Object::from_bytes(object.kind, object.data).ok()
+        }
+    }
+
+    fn paths(options: traverse::tree::Options) -> Result<Vec<BString>, Box<dyn std::error::Error>> {
+        let (store, root) = store()?;
+        Ok(traverse::tree(root, find(&store), options)
+            .map(|entry| entry.map(|e| e.path))
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn strings(paths: &[&str]) -> Vec<BString> {
+        paths.iter().map(|p| p.as_bytes().as_bstr().to_owned()).collect()
+    }
+
+    #[test]
+    fn all_non_tree_entries_in_ls_tree_order() -> Result<(), Box<dyn std::error::Error>> {
+        let (store, root) = store()?;
+        let entries = traverse::tree(root, find(&store), Default::default()).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            entries.iter().map(|e| e.path.clone()).collect::<Vec<_>>(),
+            strings(&["a", "dir/b", "dir/sub/c", "link", "sm"])
+        );
+        assert_eq!(
+            entries.iter().map(|e| e.mode).collect::<Vec<_>>(),
+            vec![
+                TreeMode::BlobExecutable,
+                TreeMode::Blob,
+                TreeMode::Blob,
+                TreeMode::Link,
+                TreeMode::Commit
+            ]
+        );
+        assert_eq!(entries[4].id, owned::Id::null(), "submodules are not entered");
+        Ok(())
+    }
+
+    #[test]
+    fn trees_are_returned_before_their_entries() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(
+            paths(traverse::tree::Options {
+                trees: true,
+                ..Default::default()
+            })?,
+            strings(&["a", "dir", "dir/b", "dir/sub", "dir/sub/c", "link", "sm"])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn trees_at_max_depth_are_returned_but_not_entered() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(
+            paths(traverse::tree::Options {
+                max_depth: Some(0),
+                ..Default::default()
+            })?,
+            strings(&["a", "dir", "link", "sm"])
+        );
+        assert_eq!(
+            paths(traverse::tree::Options {
+                max_depth: Some(1),
+                ..Default::default()
+            })?,
+            strings(&["a", "dir/b", "dir/sub", "link", "sm"])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn paths_select_entries_at_and_below_them() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(
+            paths(traverse::tree::Options {
+                paths: strings(&["dir/sub/", "link", "di"]),
+                ..Default::default()
+            })?,
+            strings(&["dir/sub/c", "link"]),
+            "paths match whole components only"
+        );
+        assert_eq!(
+            paths(traverse::tree::Options {
+                paths: strings(&["dir/sub/c"]),
+                trees: true,
+                ..Default::default()
+            })?,
+            strings(&["dir", "dir/sub", "dir/sub/c"]),
+            "trees leading to selected paths are returned as well if trees are"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn missing_trees_end_the_traversal_with_an_error() -> Result<(), Box<dyn std::error::Error>> {
+        let (store, _) = store()?;
+        let mut iter = traverse::tree(owned::Id::null(), find(&store), Default::default());
+        assert!(matches!(iter.next(), Some(Err(traverse::tree::Error::NotFound(_)))));
+        assert!(iter.next().is_none());
+        Ok(())
+    }
+}
+
+mod connectivity {
+    use crate::{fixture_path, hex_to_id, pack::SMALL_PACK_INDEX};
+    use git_object::{owned, HashKind, Kind, TreeMode};
+    use git_odb::{memory, pack, traverse::connectivity, Write};
+
+    #[test]
+    fn all_objects_of_a_complete_pack_are_found() -> Result<(), Box<dyn std::error::Error>> {
+        let bundle = pack::Bundle::at(fixture_path(SMALL_PACK_INDEX))?;
+        let outcome = connectivity::check(
+            &bundle,
+            Some(hex_to_id("779c5451ba9fe210ffd1f55db202e55f51acecac")),
+            None,
+        )?;
+        assert!(outcome.is_connected());
+        assert_eq!(outcome.objects, 42);
+        Ok(())
+    }
+
+    #[test]
+    fn all_missing_objects_are_returned_and_known_ones_are_skipped() -> Result<(), Box<dyn std::error::Error>> {
+        let store = memory::Store::new();
+        let missing_blob = hex_to_id("ffffffffffffffffffffffffffffffffffffffff");
+        let missing_tree = hex_to_id("0000000000000000000000000000000000000001");
+        let known_tree = hex_to_id("0000000000000000000000000000000000000002");
+        let entry = |mode, filename: &str, oid| owned::tree::Entry {
+            mode,
+            filename: filename.into(),
+            oid,
+        };
+        let tree = store.write(
+            &owned::Object::Tree(owned::Tree {
+                entries: vec![
+                    entry(TreeMode::Blob, "blob", missing_blob),
+                    entry(TreeMode::Tree, "known", known_tree),
+                    entry(TreeMode::Commit, "submodule", missing_blob),
+                    entry(TreeMode::Tree, "tree", missing_tree),
+                ],
+            }),
+            HashKind::Sha1,
+        )?;
+        let outcome = connectivity::check(&store, Some(tree), Some(known_tree))?;
+        assert_eq!(outcome.objects, 1);
+        assert_eq!(
+            outcome.missing,
+            vec![missing_tree, missing_blob],
+            "missing objects are sorted and submodules are ignored"
+        );
+
+        let blob = store.write_buf(Kind::Blob, b"content", HashKind::Sha1)?;
+        let tree = store.write(
+            &owned::Object::Tree(owned::Tree {
+                entries: vec![entry(TreeMode::Tree, "not-a-tree", blob)],
+            }),
+            HashKind::Sha1,
+        )?;
+        assert!(matches!(
+            connectivity::check(&store, Some(tree), None),
+            Err(connectivity::Error::UnexpectedKind {
+                expected: Kind::Tree,
+                actual: Kind::Blob,
+                ..
+            })
+        ));
+        Ok(())
+    }
+}
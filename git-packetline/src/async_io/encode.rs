@@ -0,0 +1,66 @@
+use crate::{
+    encode::Error, Channel, DELIMITER_LINE, ERR_PREFIX, FLUSH_LINE, MAX_DATA_LEN, RESPONSE_END_LINE, U16_HEX_BYTES,
+};
+use futures_io::AsyncWrite;
+use futures_lite::AsyncWriteExt;
+use std::io;
+
+/// Write a side-band line with `data` in the given `channel` to `out`, returning the amount of bytes written.
+pub async fn band_to_write(channel: Channel, data: &[u8], out: impl AsyncWrite + Unpin) -> io::Result<usize> {
+    prefixed_and_suffixed_data_to_write(&[channel as u8], data, &[], out).await
+}
+
+/// Write `data` as data line, returning the amount of bytes written.
+pub async fn data_to_write(data: &[u8], out: impl AsyncWrite + Unpin) -> io::Result<usize> {
+    prefixed_and_suffixed_data_to_write(&[], data, &[], out).await
+}
+
+/// Write `text` as data line followed by a newline, returning the amount of bytes written.
+pub async fn text_to_write(text: &[u8], out: impl AsyncWrite + Unpin) -> io::Result<usize> {
+    prefixed_and_suffixed_data_to_write(&[], text, b"\n", out).await
+}
+
+/// Write `message` as error line, returning the amount of bytes written.
+pub async fn error_to_write(message: &[u8], out: impl AsyncWrite + Unpin) -> io::Result<usize> {
+    prefixed_and_suffixed_data_to_write(ERR_PREFIX, message, &[], out).await
+}
+
+/// Write a flush line, returning the amount of bytes written.
+pub async fn flush_to_write(mut out: impl AsyncWrite + Unpin) -> io::Result<usize> {
+    out.write_all(FLUSH_LINE).await.map(|_| FLUSH_LINE.len())
+}
+
+/// Write a delimiter line, returning the amount of bytes written.
+pub async fn delim_to_write(mut out: impl AsyncWrite + Unpin) -> io::Result<usize> {
+    out.write_all(DELIMITER_LINE).await.map(|_| DELIMITER_LINE.len())
+}
+
+/// Write a response-end line, returning the amount of bytes written.
+pub async fn response_end_to_write(mut out: impl AsyncWrite + Unpin) -> io::Result<usize> {
+    out.write_all(RESPONSE_END_LINE).await.map(|_| RESPONSE_END_LINE.len())
+}
+
+async fn prefixed_and_suffixed_data_to_write(
+    prefix: &[u8],
+    data: &[u8],
+    suffix: &[u8],
+    mut out: impl AsyncWrite + Unpin,
+) -> io::Result<usize> {
+    let data_len = prefix.len() + data.len() + suffix.len();
+    if data_len > MAX_DATA_LEN {
+        return Err(Error::DataLengthLimitExceeded(data_len).into());
+    }
+    if data.is_empty() {
+        return Err(Error::DataIsEmpty.into());
+    }
+
+    // Lines are written at once as each write may end up as separate packet on the network.
+    let data_len = data_len + U16_HEX_BYTES;
+    let mut line = Vec::with_capacity(data_len);
+    line.extend_from_slice(&crate::encode::u16_to_hex(data_len as u16));
+    line.extend_from_slice(prefix);
+    line.extend_from_slice(data);
+    line.extend_from_slice(suffix);
+    out.write_all(&line).await?;
+    Ok(data_len)
+}
@@ -0,0 +1,13 @@
+//! Asynchronous variants of the [encoding functions][crate::encode], the [`Provider`][crate::Provider] and the
+//! [`Writer`][crate::Writer], built on the `AsyncRead` and `AsyncWrite` traits of `futures-io`.
+//!
+//! They behave exactly like their blocking counterparts, which allows executors like `tokio` or `async-std` to serve
+//! or fetch from remotes without dedicating a thread to each connection.
+
+pub mod encode;
+
+mod read;
+pub use read::{Provider, WithSidebands};
+
+mod write;
+pub use write::Writer;
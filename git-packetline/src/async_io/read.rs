@@ -0,0 +1,348 @@
+use crate::{decode, Band, PacketLine, MAX_LINE_LEN, U16_HEX_BYTES};
+use futures_io::{AsyncBufRead, AsyncRead};
+use futures_lite::future;
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Read pkt-lines from an `AsyncRead` implementation one at a time, stopping at any of the configured delimiters.
+pub struct Provider<T> {
+    inner: T,
+    buf: Vec<u8>,
+    /// The amount of bytes of the current line read into `buf` so far.
+    filled: usize,
+    /// The length of the current line including its prefix, once the prefix was read.
+    line_len: Option<usize>,
+    /// The length of the line in `buf` if it was peeked.
+    peeked_len: Option<usize>,
+    fail_on_err_lines: bool,
+    delimiters: &'static [PacketLine<'static>],
+    is_done: bool,
+    stopped_at: Option<PacketLine<'static>>,
+}
+
+impl<T> Provider<T>
+where
+    T: AsyncRead + Unpin,
+{
+    /// Create a new instance reading from `inner`, stopping at and consuming the first of the given `delimiters`.
+    pub fn new(inner: T, delimiters: &'static [PacketLine<'static>]) -> Self {
+        Provider {
+            inner,
+            buf: vec![0; MAX_LINE_LEN],
+            filled: 0,
+            line_len: None,
+            peeked_len: None,
+            fail_on_err_lines: false,
+            delimiters,
+            is_done: false,
+            stopped_at: None,
+        }
+    }
+
+    /// Returns the delimiter line we stopped at, if any.
+    pub fn stopped_at(&self) -> Option<PacketLine<'static>> {
+        self.stopped_at
+    }
+
+    /// Allow reading lines again after a delimiter was encountered.
+    pub fn reset(&mut self) {
+        self.is_done = false;
+        self.stopped_at = None;
+    }
+
+    /// Like [`reset()`][Provider::reset()], but configures new `delimiters` as well.
+    pub fn reset_with(&mut self, delimiters: &'static [PacketLine<'static>]) {
+        self.delimiters = delimiters;
+        self.reset();
+    }
+
+    /// If enabled, lines starting with `ERR ` will be turned into an `io::Error` carrying the remote message.
+    pub fn fail_on_err_lines(&mut self, value: bool) {
+        self.fail_on_err_lines = value;
+    }
+
+    /// Replace the reader with `read`, returning the previous one.
+    pub fn replace(&mut self, read: T) -> T {
+        let prev = std::mem::replace(&mut self.inner, read);
+        self.reset();
+        self.filled = 0;
+        self.line_len = None;
+        self.peeked_len = None;
+        prev
+    }
+
+    /// Access the underlying reader.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Read the next complete line into `buf`, returning its length including the prefix.
+    ///
+    /// Partially read lines are kept across calls, which makes this safe to use with futures that are cancelled.
+    fn poll_line_len(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Result<usize, decode::Error>>> {
+        if let Some(line_len) = self.peeked_len.take() {
+            return Poll::Ready(Ok(Ok(line_len)));
+        }
+        loop {
+            let wanted = self.line_len.unwrap_or(U16_HEX_BYTES);
+            while self.filled < wanted {
+                let read = match Pin::new(&mut self.inner).poll_read(cx, &mut self.buf[self.filled..wanted]) {
+                    Poll::Ready(Ok(read)) => read,
+                    Poll::Ready(Err(err)) => {
+                        self.filled = 0;
+                        self.line_len = None;
+                        return Poll::Ready(Err(err));
+                    }
+                    Poll::Pending => return Poll::Pending,
+                };
+                if read == 0 {
+                    self.filled = 0;
+                    self.line_len = None;
+                    return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+                }
+                self.filled += read;
+            }
+            match self.line_len {
+                Some(line_len) => {
+                    self.filled = 0;
+                    self.line_len = None;
+                    return Poll::Ready(Ok(
+                        decode::to_data_line(&self.buf[U16_HEX_BYTES..line_len]).map(|_| line_len)
+                    ));
+                }
+                None => match decode::hex_prefix(&self.buf[..U16_HEX_BYTES]) {
+                    Ok(decode::PacketLineOrWantedSize::Line(_)) => {
+                        self.filled = 0;
+                        return Poll::Ready(Ok(Ok(U16_HEX_BYTES)));
+                    }
+                    Ok(decode::PacketLineOrWantedSize::Wanted(additional_bytes)) => {
+                        self.line_len = Some(U16_HEX_BYTES + additional_bytes as usize)
+                    }
+                    Err(err) => {
+                        self.filled = 0;
+                        return Poll::Ready(Ok(Err(err)));
+                    }
+                },
+            }
+        }
+    }
+
+    /// Returns true if `line` is a delimiter, and marks us done if so.
+    fn is_delimiter(&mut self, line: PacketLine<'static>) -> bool {
+        if self.delimiters.contains(&line) {
+            self.is_done = true;
+            self.stopped_at = Some(line);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn handle_line(
+        &mut self,
+        res: io::Result<Result<usize, decode::Error>>,
+    ) -> Option<io::Result<Result<PacketLine<'_>, decode::Error>>> {
+        let line_len = match res {
+            Ok(Ok(line_len)) => line_len,
+            Ok(Err(err)) => return Some(Ok(Err(err))),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                self.is_done = true;
+                return None;
+            }
+            Err(err) => return Some(Err(err)),
+        };
+        let line = decode::all_at_once(&self.buf[..line_len]).expect("valid line as it was decoded before");
+        if let Some(err) = line.to_error().filter(|_| self.fail_on_err_lines) {
+            let msg = err.to_message();
+            self.is_done = true;
+            return Some(Err(io::Error::other(msg)));
+        }
+        let special_line = match line {
+            PacketLine::Data(_) => None,
+            PacketLine::Flush => Some(PacketLine::Flush),
+            PacketLine::Delimiter => Some(PacketLine::Delimiter),
+            PacketLine::ResponseEnd => Some(PacketLine::ResponseEnd),
+        };
+        if let Some(line) = special_line {
+            if self.is_delimiter(line) {
+                return None;
+            }
+        }
+        Some(Ok(Ok(
+            decode::all_at_once(&self.buf[..line_len]).expect("valid line as it was decoded before")
+        )))
+    }
+
+    /// Read the next line, or `None` if a delimiter was encountered or the stream ended.
+    pub async fn next_line(&mut self) -> Option<io::Result<Result<PacketLine<'_>, decode::Error>>> {
+        if self.is_done {
+            return None;
+        }
+        let res = future::poll_fn(|cx| self.poll_line_len(cx)).await;
+        self.handle_line(res)
+    }
+
+    /// Peek the next line without consuming it, or `None` if a delimiter was encountered or the stream ended.
+    pub async fn peek_line(&mut self) -> Option<io::Result<Result<PacketLine<'_>, decode::Error>>> {
+        if self.is_done {
+            return None;
+        }
+        let res = future::poll_fn(|cx| self.poll_line_len(cx)).await;
+        if let Ok(Ok(line_len)) = res {
+            let is_delimiter = matches!(
+                decode::all_at_once(&self.buf[..line_len]),
+                Ok(line) if line.as_slice().is_none() && self.delimiters.contains(&line)
+            );
+            if !is_delimiter {
+                self.peeked_len = Some(line_len);
+            }
+        }
+        self.handle_line(res)
+    }
+
+    /// Obtain a reader over all data lines until a delimiter is encountered, decoding side-bands and passing
+    /// all progress and error messages to `handle_progress(is_error, text)`.
+    pub fn as_read_with_sidebands<F: FnMut(bool, &[u8]) + Unpin>(
+        &mut self,
+        handle_progress: F,
+    ) -> WithSidebands<'_, T, F> {
+        WithSidebands::with_progress_handler(self, handle_progress)
+    }
+
+    /// Obtain a reader over all data lines until a delimiter is encountered.
+    pub fn as_read(&mut self) -> WithSidebands<'_, T, fn(bool, &[u8])> {
+        WithSidebands::new(self)
+    }
+}
+
+/// An `AsyncRead` and `AsyncBufRead` implementation over the data lines of a [`Provider`][Provider], optionally
+/// demultiplexing side-bands.
+pub struct WithSidebands<'a, T, F>
+where
+    T: AsyncRead,
+{
+    parent: &'a mut Provider<T>,
+    handle_progress: Option<F>,
+    pos: usize,
+    cap: usize,
+}
+
+impl<'a, T> WithSidebands<'a, T, fn(bool, &[u8])>
+where
+    T: AsyncRead + Unpin,
+{
+    /// Create a reader over the data lines of `parent` without side-band support.
+    pub fn new(parent: &'a mut Provider<T>) -> Self {
+        WithSidebands {
+            parent,
+            handle_progress: None,
+            pos: 0,
+            cap: 0,
+        }
+    }
+}
+
+impl<'a, T, F> WithSidebands<'a, T, F>
+where
+    T: AsyncRead + Unpin,
+    F: FnMut(bool, &[u8]) + Unpin,
+{
+    /// Create a reader which decodes side-bands and passes progress and error messages to `handle_progress`.
+    pub fn with_progress_handler(parent: &'a mut Provider<T>, handle_progress: F) -> Self {
+        WithSidebands {
+            parent,
+            handle_progress: Some(handle_progress),
+            pos: 0,
+            cap: 0,
+        }
+    }
+
+    /// Provide access to the underlying provider, for example to reset it.
+    pub fn provider_mut(&mut self) -> &mut Provider<T> {
+        self.parent
+    }
+
+    /// Returns the delimiter the underlying provider stopped at, if any.
+    pub fn stopped_at(&self) -> Option<PacketLine<'static>> {
+        self.parent.stopped_at
+    }
+}
+
+fn other(err: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::other(err)
+}
+
+impl<'a, T, F> AsyncBufRead for WithSidebands<'a, T, F>
+where
+    T: AsyncRead + Unpin,
+    F: FnMut(bool, &[u8]) + Unpin,
+{
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        if this.pos >= this.cap {
+            let (ofs, cap) = loop {
+                if this.parent.is_done {
+                    break (0, 0);
+                }
+                let res = match this.parent.poll_line_len(cx) {
+                    Poll::Ready(res) => res,
+                    Poll::Pending => return Poll::Pending,
+                };
+                let line = match this.parent.handle_line(res) {
+                    Some(line) => line?.map_err(other)?,
+                    None => break (0, 0),
+                };
+                match this.handle_progress.as_mut() {
+                    Some(handle_progress) => {
+                        const ENCODED_BAND: usize = 1;
+                        match line.decode_band().map_err(other)? {
+                            Band::Data(d) => break (U16_HEX_BYTES + ENCODED_BAND, d.len()),
+                            Band::Progress(d) => handle_progress(false, d),
+                            Band::Error(d) => handle_progress(true, d),
+                        };
+                    }
+                    None => match line.as_slice() {
+                        Some(d) => break (U16_HEX_BYTES, d.len()),
+                        None => {
+                            return Poll::Ready(Err(io::Error::new(
+                                io::ErrorKind::UnexpectedEof,
+                                "encountered a non-data line in a data-line only context",
+                            )))
+                        }
+                    },
+                }
+            };
+            this.cap = cap + ofs;
+            this.pos = ofs;
+        }
+        Poll::Ready(Ok(&this.parent.buf[this.pos..this.cap]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        this.pos = std::cmp::min(this.pos + amt, this.cap);
+    }
+}
+
+impl<'a, T, F> AsyncRead for WithSidebands<'a, T, F>
+where
+    T: AsyncRead + Unpin,
+    F: FnMut(bool, &[u8]) + Unpin,
+{
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let nread = {
+            let rem = match self.as_mut().poll_fill_buf(cx) {
+                Poll::Ready(res) => res?,
+                Poll::Pending => return Poll::Pending,
+            };
+            let nread = rem.len().min(buf.len());
+            buf[..nread].copy_from_slice(&rem[..nread]);
+            nread
+        };
+        self.consume(nread);
+        Poll::Ready(Ok(nread))
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{encode, MAX_DATA_LEN};
+use futures_io::AsyncWrite;
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// An `AsyncWrite` implementation turning each call to `write()` into one or more data lines.
+///
+/// Encoded lines are buffered until they are written completely, so a call to `write()` never produces partial lines.
+pub struct Writer<T> {
+    inner: T,
+    binary: bool,
+    pending: Vec<u8>,
+    pending_pos: usize,
+}
+
+impl<T: AsyncWrite + Unpin> Writer<T> {
+    /// Create a new writer in binary mode.
+    pub fn new(inner: T) -> Self {
+        Writer {
+            inner,
+            binary: true,
+            pending: Vec::new(),
+            pending_pos: 0,
+        }
+    }
+
+    /// In text mode, each line is terminated with a newline.
+    pub fn text_mode(mut self) -> Self {
+        self.binary = false;
+        self
+    }
+
+    /// In binary mode, data is written as is.
+    pub fn binary_mode(mut self) -> Self {
+        self.binary = true;
+        self
+    }
+
+    /// Switch between binary and text mode.
+    pub fn enable_text_mode(&mut self, text: bool) {
+        self.binary = !text;
+    }
+
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.pending_pos < self.pending.len() {
+            let written = match Pin::new(&mut self.inner).poll_write(cx, &self.pending[self.pending_pos..]) {
+                Poll::Ready(res) => res?,
+                Poll::Pending => return Poll::Pending,
+            };
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pending_pos += written;
+        }
+        self.pending.clear();
+        self.pending_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Writer<T> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, mut buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if buf.is_empty() {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Other,
+                "empty packet lines are not permitted as '0004' is invalid",
+            )));
+        }
+        if this.poll_write_pending(cx)?.is_pending() {
+            return Poll::Pending;
+        }
+
+        let written = buf.len();
+        while !buf.is_empty() {
+            let (data, rest) = buf.split_at(buf.len().min(MAX_DATA_LEN - if this.binary { 0 } else { 1 }));
+            if this.binary {
+                encode::data_to_write(data, &mut this.pending)
+            } else {
+                encode::text_to_write(data, &mut this.pending)
+            }?;
+            buf = rest;
+        }
+        // The data is accepted as it is buffered, writing it is continued with the next call.
+        if let Poll::Ready(Err(err)) = this.poll_write_pending(cx) {
+            return Poll::Ready(Err(err));
+        }
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.poll_write_pending(cx)?.is_pending() {
+            return Poll::Pending;
+        }
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.poll_write_pending(cx)?.is_pending() {
+            return Poll::Pending;
+        }
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
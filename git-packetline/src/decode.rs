@@ -0,0 +1,152 @@
+use crate::{PacketLine, DELIMITER_LINE, FLUSH_LINE, MAX_DATA_LEN, MAX_LINE_LEN, RESPONSE_END_LINE, U16_HEX_BYTES};
+use bstr::BString;
+use quick_error::quick_error;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        HexDecode(err: String) {
+            display("Failed to decode the line length prefix: {}", err)
+        }
+        DataLengthLimitExceeded(length_in_bytes: usize) {
+            display("The data received claims to be larger than the maximum allowed size: got {}, exceeds {}", length_in_bytes, MAX_DATA_LEN)
+        }
+        DataIsEmpty {
+            display("Received an invalid empty line")
+        }
+        InvalidLineLength {
+            display("Received an invalid line of length 3")
+        }
+        Line(data: BString, bytes_consumed: usize) {
+            display("{:?} - consumed {} bytes", data, bytes_consumed)
+        }
+        NotEnoughData(bytes_needed: usize) {
+            display("Needing {} additional bytes to decode the line successfully", bytes_needed)
+        }
+    }
+}
+
+pub mod band {
+    use quick_error::quick_error;
+    quick_error! {
+        #[derive(Debug)]
+        pub enum Error {
+            InvalidSideBand(band: Option<u8>) {
+                display("Side-band id {:?} is not one of 1, 2 or 3", band)
+            }
+            NonDataLine {
+                display("Flush, delimiter and response-end lines don't have a band")
+            }
+        }
+    }
+}
+
+/// The result of decoding the four-byte hexadecimal length prefix of a line.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum PacketLineOrWantedSize<'a> {
+    /// The special lines which don't carry data.
+    Line(PacketLine<'a>),
+    /// The amount of bytes of data following the prefix.
+    Wanted(u16),
+}
+
+/// Decode the hexadecimal length prefix taken from the first four bytes of `four_bytes`.
+pub fn hex_prefix(four_bytes: &[u8]) -> Result<PacketLineOrWantedSize<'_>, Error> {
+    debug_assert_eq!(four_bytes.len(), U16_HEX_BYTES, "need four hex bytes");
+    for (line_bytes, line_type) in &[
+        (FLUSH_LINE, PacketLine::Flush),
+        (DELIMITER_LINE, PacketLine::Delimiter),
+        (RESPONSE_END_LINE, PacketLine::ResponseEnd),
+    ] {
+        if four_bytes == *line_bytes {
+            return Ok(PacketLineOrWantedSize::Line(*line_type));
+        }
+    }
+
+    let mut buf = [0u8; U16_HEX_BYTES / 2];
+    hex::decode_to_slice(four_bytes, &mut buf).map_err(|err| Error::HexDecode(err.to_string()))?;
+    let wanted_bytes = u16::from_be_bytes(buf);
+
+    if wanted_bytes == 3 {
+        return Err(Error::InvalidLineLength);
+    }
+    if wanted_bytes == 4 {
+        return Err(Error::DataIsEmpty);
+    }
+    if wanted_bytes as usize > MAX_LINE_LEN {
+        return Err(Error::DataLengthLimitExceeded(wanted_bytes as usize));
+    }
+    debug_assert!(
+        wanted_bytes as usize > U16_HEX_BYTES,
+        "by now there should be more wanted bytes than prefix bytes"
+    );
+    Ok(PacketLineOrWantedSize::Wanted(wanted_bytes - U16_HEX_BYTES as u16))
+}
+
+/// Obtain a line of `data` which must be exactly as long as the length prefix of the line indicates.
+pub fn to_data_line(data: &[u8]) -> Result<PacketLine<'_>, Error> {
+    if data.len() > MAX_DATA_LEN {
+        return Err(Error::DataLengthLimitExceeded(data.len()));
+    }
+    Ok(PacketLine::Data(data))
+}
+
+/// The result of [`streaming()`][streaming()].
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum Stream<'a> {
+    /// A complete line could be decoded.
+    Complete {
+        line: PacketLine<'a>,
+        /// The amount of bytes the line used in the input buffer.
+        bytes_consumed: usize,
+    },
+    /// More data is needed to decode a line.
+    Incomplete {
+        /// The amount of additional bytes needed.
+        bytes_needed: usize,
+    },
+}
+
+/// Decode the first line in `data`, which may contain more bytes than needed, or too few.
+pub fn streaming(data: &[u8]) -> Result<Stream<'_>, Error> {
+    let data_len = data.len();
+    if data_len < U16_HEX_BYTES {
+        return Ok(Stream::Incomplete {
+            bytes_needed: U16_HEX_BYTES - data_len,
+        });
+    }
+    let wanted_bytes = match hex_prefix(&data[..U16_HEX_BYTES])? {
+        PacketLineOrWantedSize::Wanted(s) => s as usize,
+        PacketLineOrWantedSize::Line(line) => {
+            return Ok(Stream::Complete {
+                line,
+                bytes_consumed: U16_HEX_BYTES,
+            })
+        }
+    } + U16_HEX_BYTES;
+    if wanted_bytes > MAX_LINE_LEN {
+        return Err(Error::DataLengthLimitExceeded(wanted_bytes));
+    }
+    if data_len < wanted_bytes {
+        return Ok(Stream::Incomplete {
+            bytes_needed: wanted_bytes - data_len,
+        });
+    }
+
+    Ok(Stream::Complete {
+        line: to_data_line(&data[U16_HEX_BYTES..wanted_bytes])?,
+        bytes_consumed: wanted_bytes,
+    })
+}
+
+/// Decode an entire line from `data`, which must contain exactly one line and nothing else.
+pub fn all_at_once(data: &[u8]) -> Result<PacketLine<'_>, Error> {
+    match streaming(data)? {
+        Stream::Complete { line, bytes_consumed } if bytes_consumed == data.len() => Ok(line),
+        Stream::Complete { line, bytes_consumed } => Err(Error::Line(
+            line.as_slice().unwrap_or(&data[..bytes_consumed]).into(),
+            bytes_consumed,
+        )),
+        Stream::Incomplete { bytes_needed } => Err(Error::NotEnoughData(bytes_needed)),
+    }
+}
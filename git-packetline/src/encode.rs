@@ -0,0 +1,101 @@
+use crate::{Channel, DELIMITER_LINE, ERR_PREFIX, FLUSH_LINE, MAX_DATA_LEN, RESPONSE_END_LINE, U16_HEX_BYTES};
+use quick_error::quick_error;
+use std::io;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An error occurred while writing")
+            from()
+            source(err)
+        }
+        DataLengthLimitExceeded(length_in_bytes: usize) {
+            display("Cannot encode more than {} bytes, got {}", MAX_DATA_LEN, length_in_bytes)
+        }
+        DataIsEmpty {
+            display("Empty lines are invalid")
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            other => io::Error::other(other),
+        }
+    }
+}
+
+/// Write a side-band line with `data` in the given `channel` to `out`, returning the amount of bytes written.
+pub fn band_to_write(channel: Channel, data: &[u8], out: impl io::Write) -> io::Result<usize> {
+    prefixed_data_to_write(&[channel as u8], data, out)
+}
+
+/// Write `data` as data line, returning the amount of bytes written.
+pub fn data_to_write(data: &[u8], out: impl io::Write) -> io::Result<usize> {
+    prefixed_data_to_write(&[], data, out)
+}
+
+/// Write `text` as data line followed by a newline, returning the amount of bytes written.
+pub fn text_to_write(text: &[u8], out: impl io::Write) -> io::Result<usize> {
+    prefixed_and_suffixed_data_to_write(&[], text, b"\n", out)
+}
+
+/// Write `message` as error line, returning the amount of bytes written.
+pub fn error_to_write(message: &[u8], out: impl io::Write) -> io::Result<usize> {
+    prefixed_data_to_write(ERR_PREFIX, message, out)
+}
+
+/// Write a flush line, returning the amount of bytes written.
+pub fn flush_to_write(mut out: impl io::Write) -> io::Result<usize> {
+    out.write_all(FLUSH_LINE).map(|_| FLUSH_LINE.len())
+}
+
+/// Write a delimiter line, returning the amount of bytes written.
+pub fn delim_to_write(mut out: impl io::Write) -> io::Result<usize> {
+    out.write_all(DELIMITER_LINE).map(|_| DELIMITER_LINE.len())
+}
+
+/// Write a response-end line, returning the amount of bytes written.
+pub fn response_end_to_write(mut out: impl io::Write) -> io::Result<usize> {
+    out.write_all(RESPONSE_END_LINE).map(|_| RESPONSE_END_LINE.len())
+}
+
+/// Encode `value` into the four hexadecimal bytes of a line length prefix.
+pub fn u16_to_hex(value: u16) -> [u8; U16_HEX_BYTES] {
+    let mut buf = [0u8; U16_HEX_BYTES];
+    hex::encode_to_slice(value.to_be_bytes(), &mut buf).expect("two bytes to 4 hex chars never fails");
+    buf
+}
+
+fn prefixed_data_to_write(prefix: &[u8], data: &[u8], out: impl io::Write) -> io::Result<usize> {
+    prefixed_and_suffixed_data_to_write(prefix, data, &[], out)
+}
+
+fn prefixed_and_suffixed_data_to_write(
+    prefix: &[u8],
+    data: &[u8],
+    suffix: &[u8],
+    mut out: impl io::Write,
+) -> io::Result<usize> {
+    let data_len = prefix.len() + data.len() + suffix.len();
+    if data_len > MAX_DATA_LEN {
+        return Err(Error::DataLengthLimitExceeded(data_len).into());
+    }
+    if data.is_empty() {
+        return Err(Error::DataIsEmpty.into());
+    }
+
+    let data_len = data_len + U16_HEX_BYTES;
+    out.write_all(&u16_to_hex(data_len as u16))?;
+    if !prefix.is_empty() {
+        out.write_all(prefix)?;
+    }
+    out.write_all(data)?;
+    if !suffix.is_empty() {
+        out.write_all(suffix)?;
+    }
+    Ok(data_len)
+}
@@ -0,0 +1,61 @@
+//! Read and write the [pkt-line](https://github.com/git/git/blob/master/Documentation/technical/protocol-common.txt#L52:L52)
+//! format used by git to frame data in its protocols.
+#![forbid(unsafe_code)]
+
+pub(crate) const U16_HEX_BYTES: usize = 4;
+/// The maximum amount of data bytes a single line can carry.
+pub const MAX_DATA_LEN: usize = 65516;
+/// The maximum length of a line, including its hexadecimal length prefix.
+pub const MAX_LINE_LEN: usize = MAX_DATA_LEN + U16_HEX_BYTES;
+pub(crate) const FLUSH_LINE: &[u8] = b"0000";
+pub(crate) const DELIMITER_LINE: &[u8] = b"0001";
+pub(crate) const RESPONSE_END_LINE: &[u8] = b"0002";
+pub(crate) const ERR_PREFIX: &[u8] = b"ERR ";
+
+/// A borrowed packet line as it would be read from a stream.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum PacketLine<'a> {
+    /// A line containing data, without its hexadecimal length prefix.
+    Data(&'a [u8]),
+    /// The end of a section or message, `0000`.
+    Flush,
+    /// A separator between sections in protocol V2, `0001`.
+    Delimiter,
+    /// The end of a response in stateless protocol V2 connections, `0002`.
+    ResponseEnd,
+}
+
+/// The channel of a multiplexed stream as used by the `side-band` and `side-band-64k` capabilities.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum Channel {
+    Data = 1,
+    Progress = 2,
+    Error = 3,
+}
+
+/// The data of a line on one of the multiplexed channels.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum Band<'a> {
+    Data(&'a [u8]),
+    Progress(&'a [u8]),
+    Error(&'a [u8]),
+}
+
+/// The error message the remote sent in a line starting with `ERR `.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub struct RemoteError<'a>(pub &'a [u8]);
+
+mod line;
+
+pub mod decode;
+pub mod encode;
+
+pub mod read;
+#[doc(inline)]
+pub use read::Provider;
+
+mod write;
+pub use write::Writer;
+
+#[cfg(feature = "async-io")]
+pub mod async_io;
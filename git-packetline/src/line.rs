@@ -0,0 +1,76 @@
+use crate::{decode, encode, Band, Channel, PacketLine, RemoteError, ERR_PREFIX};
+use bstr::BStr;
+use std::io;
+
+impl<'a> PacketLine<'a> {
+    /// Return this line as slice if it carries data.
+    pub fn as_slice(&self) -> Option<&'a [u8]> {
+        match self {
+            PacketLine::Data(d) => Some(d),
+            PacketLine::Flush | PacketLine::Delimiter | PacketLine::ResponseEnd => None,
+        }
+    }
+
+    /// Return this line as `BStr` if it carries data.
+    pub fn as_bstr(&self) -> Option<&'a BStr> {
+        self.as_slice().map(Into::into)
+    }
+
+    /// Return the data of this line with a single trailing newline removed, as is customary for text lines.
+    pub fn to_text(&self) -> Option<&'a BStr> {
+        self.as_slice()
+            .map(|d| if d.last() == Some(&b'\n') { &d[..d.len() - 1] } else { d }.into())
+    }
+
+    /// Interpret the data of this line as error sent by the remote, if it starts with `ERR `.
+    pub fn to_error(&self) -> Option<RemoteError<'a>> {
+        self.as_slice()
+            .filter(|d| d.starts_with(ERR_PREFIX))
+            .map(|d| RemoteError(&d[ERR_PREFIX.len()..]))
+    }
+
+    /// Decode the band of this line as used in multiplexed streams.
+    pub fn decode_band(&self) -> Result<Band<'a>, decode::band::Error> {
+        let d = self.as_slice().ok_or(decode::band::Error::NonDataLine)?;
+        Ok(match d.first() {
+            Some(1) => Band::Data(&d[1..]),
+            Some(2) => Band::Progress(&d[1..]),
+            Some(3) => Band::Error(&d[1..]),
+            _ => return Err(decode::band::Error::InvalidSideBand(d.first().copied())),
+        })
+    }
+
+    /// Serialize this line to `out`, returning the amount of bytes written.
+    pub fn write_to(&self, out: impl io::Write) -> io::Result<usize> {
+        match self {
+            PacketLine::Data(d) => encode::data_to_write(d, out),
+            PacketLine::Flush => encode::flush_to_write(out),
+            PacketLine::Delimiter => encode::delim_to_write(out),
+            PacketLine::ResponseEnd => encode::response_end_to_write(out),
+        }
+    }
+}
+
+impl<'a> RemoteError<'a> {
+    /// The error message, lossily converted to a string.
+    pub fn to_message(&self) -> String {
+        let msg = self.0;
+        String::from_utf8_lossy(if msg.last() == Some(&b'\n') {
+            &msg[..msg.len() - 1]
+        } else {
+            msg
+        })
+        .into_owned()
+    }
+}
+
+impl<'a> Band<'a> {
+    /// The channel this band belongs to.
+    pub fn channel(&self) -> Channel {
+        match self {
+            Band::Data(_) => Channel::Data,
+            Band::Progress(_) => Channel::Progress,
+            Band::Error(_) => Channel::Error,
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use crate::{decode, Band, PacketLine, MAX_LINE_LEN, U16_HEX_BYTES};
+use std::io;
+
+/// Read pkt-lines from an `io::Read` implementation one at a time, stopping at any of the configured delimiters.
+pub struct Provider<T> {
+    inner: T,
+    buf: Vec<u8>,
+    peek_buf: Vec<u8>,
+    fail_on_err_lines: bool,
+    delimiters: &'static [PacketLine<'static>],
+    is_done: bool,
+    stopped_at: Option<PacketLine<'static>>,
+}
+
+impl<T> Provider<T>
+where
+    T: io::Read,
+{
+    /// Create a new instance reading from `inner`, stopping at and consuming the first of the given `delimiters`.
+    pub fn new(inner: T, delimiters: &'static [PacketLine<'static>]) -> Self {
+        Provider {
+            inner,
+            buf: vec![0; MAX_LINE_LEN],
+            peek_buf: Vec::new(),
+            fail_on_err_lines: false,
+            delimiters,
+            is_done: false,
+            stopped_at: None,
+        }
+    }
+
+    /// Returns the delimiter line we stopped at, if any.
+    pub fn stopped_at(&self) -> Option<PacketLine<'static>> {
+        self.stopped_at
+    }
+
+    /// Allow reading lines again after a delimiter was encountered.
+    pub fn reset(&mut self) {
+        self.is_done = false;
+        self.stopped_at = None;
+    }
+
+    /// Like [`reset()`][Provider::reset()], but configures new `delimiters` as well.
+    pub fn reset_with(&mut self, delimiters: &'static [PacketLine<'static>]) {
+        self.delimiters = delimiters;
+        self.reset();
+    }
+
+    /// If enabled, lines starting with `ERR ` will be turned into an `io::Error` carrying the remote message.
+    pub fn fail_on_err_lines(&mut self, value: bool) {
+        self.fail_on_err_lines = value;
+    }
+
+    /// Replace the reader with `read`, returning the previous one.
+    pub fn replace(&mut self, read: T) -> T {
+        let prev = std::mem::replace(&mut self.inner, read);
+        self.reset();
+        self.peek_buf.clear();
+        prev
+    }
+
+    /// Access the underlying reader.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    fn read_line_inner<'a>(reader: &mut T, buf: &'a mut Vec<u8>) -> io::Result<Result<PacketLine<'a>, decode::Error>> {
+        buf.resize(MAX_LINE_LEN, 0);
+        let (hex_bytes, data_bytes) = buf.split_at_mut(U16_HEX_BYTES);
+        reader.read_exact(hex_bytes)?;
+        let num_data_bytes = match decode::hex_prefix(hex_bytes) {
+            Ok(decode::PacketLineOrWantedSize::Line(line)) => return Ok(Ok(line)),
+            Ok(decode::PacketLineOrWantedSize::Wanted(additional_bytes)) => additional_bytes as usize,
+            Err(err) => return Ok(Err(err)),
+        };
+        let data_bytes = &mut data_bytes[..num_data_bytes];
+        reader.read_exact(data_bytes)?;
+        Ok(decode::to_data_line(data_bytes))
+    }
+
+    /// Returns true if `line` is a delimiter, and marks us done if so.
+    fn is_delimiter(&mut self, line: PacketLine<'static>) -> bool {
+        if self.delimiters.contains(&line) {
+            self.is_done = true;
+            self.stopped_at = Some(line);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn handle_line(
+        &mut self,
+        res: io::Result<Result<usize, decode::Error>>,
+        from_peek_buf: bool,
+    ) -> Option<io::Result<Result<PacketLine<'_>, decode::Error>>> {
+        let line_len = match res {
+            Ok(Ok(line_len)) => line_len,
+            Ok(Err(err)) => return Some(Ok(Err(err))),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                self.is_done = true;
+                return None;
+            }
+            Err(err) => return Some(Err(err)),
+        };
+        let buf = if from_peek_buf { &self.peek_buf } else { &self.buf };
+        let line = decode::all_at_once(&buf[..line_len]).expect("valid line as it was decoded before");
+        if let Some(err) = line.to_error().filter(|_| self.fail_on_err_lines) {
+            let msg = err.to_message();
+            self.is_done = true;
+            return Some(Err(io::Error::other(msg)));
+        }
+        let special_line = match line {
+            PacketLine::Data(_) => None,
+            PacketLine::Flush => Some(PacketLine::Flush),
+            PacketLine::Delimiter => Some(PacketLine::Delimiter),
+            PacketLine::ResponseEnd => Some(PacketLine::ResponseEnd),
+        };
+        if let Some(line) = special_line {
+            if self.is_delimiter(line) {
+                if from_peek_buf {
+                    self.peek_buf.clear();
+                }
+                return None;
+            }
+        }
+        let buf = if from_peek_buf { &self.peek_buf } else { &self.buf };
+        Some(Ok(Ok(
+            decode::all_at_once(&buf[..line_len]).expect("valid line as it was decoded before")
+        )))
+    }
+
+    fn read_line_len(reader: &mut T, buf: &mut Vec<u8>) -> io::Result<Result<usize, decode::Error>> {
+        Self::read_line_inner(reader, buf)
+            .map(|res| res.map(|line| line.as_slice().map(|d| d.len()).unwrap_or(0) + U16_HEX_BYTES))
+    }
+
+    /// Read the next line, or `None` if a delimiter was encountered or the stream ended.
+    ///
+    /// Data lines are always located at offset 4 of the internal buffer, which is used by readers built on top.
+    pub fn next_line(&mut self) -> Option<io::Result<Result<PacketLine<'_>, decode::Error>>> {
+        if self.is_done {
+            return None;
+        }
+        if !self.peek_buf.is_empty() {
+            std::mem::swap(&mut self.peek_buf, &mut self.buf);
+            self.peek_buf.clear();
+            let line_len = decode::streaming(&self.buf)
+                .map(|s| match s {
+                    decode::Stream::Complete { bytes_consumed, .. } => bytes_consumed,
+                    decode::Stream::Incomplete { .. } => unreachable!("peeked lines are complete"),
+                })
+                .expect("peeked lines are valid");
+            return self.handle_line(Ok(Ok(line_len)), false);
+        }
+        let res = Self::read_line_len(&mut self.inner, &mut self.buf);
+        self.handle_line(res, false)
+    }
+
+    /// Peek the next line without consuming it, or `None` if a delimiter was encountered or the stream ended.
+    pub fn peek_line(&mut self) -> Option<io::Result<Result<PacketLine<'_>, decode::Error>>> {
+        if self.is_done {
+            return None;
+        }
+        let res = if self.peek_buf.is_empty() {
+            let res = Self::read_line_len(&mut self.inner, &mut self.peek_buf);
+            match res {
+                Ok(Ok(line_len)) => {
+                    self.peek_buf.truncate(line_len);
+                    Ok(Ok(line_len))
+                }
+                other => {
+                    self.peek_buf.clear();
+                    other
+                }
+            }
+        } else {
+            Ok(Ok(self.peek_buf.len()))
+        };
+        self.handle_line(res, true)
+    }
+
+    /// Obtain a reader over all data lines until a delimiter is encountered, decoding side-bands and passing
+    /// all progress and error messages to `handle_progress(is_error, text)`.
+    pub fn as_read_with_sidebands<F: FnMut(bool, &[u8])>(&mut self, handle_progress: F) -> WithSidebands<'_, T, F> {
+        WithSidebands::with_progress_handler(self, handle_progress)
+    }
+
+    /// Obtain a reader over all data lines until a delimiter is encountered.
+    pub fn as_read(&mut self) -> WithSidebands<'_, T, fn(bool, &[u8])> {
+        WithSidebands::new(self)
+    }
+}
+
+/// An `io::Read` and `io::BufRead` implementation over the data lines of a [`Provider`][Provider], optionally
+/// demultiplexing side-bands.
+pub struct WithSidebands<'a, T, F>
+where
+    T: io::Read,
+{
+    parent: &'a mut Provider<T>,
+    handle_progress: Option<F>,
+    pos: usize,
+    cap: usize,
+}
+
+impl<'a, T> WithSidebands<'a, T, fn(bool, &[u8])>
+where
+    T: io::Read,
+{
+    /// Create a reader over the data lines of `parent` without side-band support.
+    pub fn new(parent: &'a mut Provider<T>) -> Self {
+        WithSidebands {
+            parent,
+            handle_progress: None,
+            pos: 0,
+            cap: 0,
+        }
+    }
+}
+
+impl<'a, T, F> WithSidebands<'a, T, F>
+where
+    T: io::Read,
+    F: FnMut(bool, &[u8]),
+{
+    /// Create a reader which decodes side-bands and passes progress and error messages to `handle_progress`.
+    pub fn with_progress_handler(parent: &'a mut Provider<T>, handle_progress: F) -> Self {
+        WithSidebands {
+            parent,
+            handle_progress: Some(handle_progress),
+            pos: 0,
+            cap: 0,
+        }
+    }
+
+    /// Provide access to the underlying provider, for example to reset it.
+    pub fn provider_mut(&mut self) -> &mut Provider<T> {
+        self.parent
+    }
+
+    /// Returns the delimiter the underlying provider stopped at, if any.
+    pub fn stopped_at(&self) -> Option<PacketLine<'static>> {
+        self.parent.stopped_at
+    }
+}
+
+fn other(err: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::other(err)
+}
+
+impl<'a, T, F> io::BufRead for WithSidebands<'a, T, F>
+where
+    T: io::Read,
+    F: FnMut(bool, &[u8]),
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.cap {
+            let (ofs, cap) = loop {
+                let line = match self.parent.next_line() {
+                    Some(line) => line?.map_err(other)?,
+                    None => break (0, 0),
+                };
+                match self.handle_progress.as_mut() {
+                    Some(handle_progress) => {
+                        const ENCODED_BAND: usize = 1;
+                        match line.decode_band().map_err(other)? {
+                            Band::Data(d) => break (U16_HEX_BYTES + ENCODED_BAND, d.len()),
+                            Band::Progress(d) => handle_progress(false, d),
+                            Band::Error(d) => handle_progress(true, d),
+                        };
+                    }
+                    None => match line.as_slice() {
+                        Some(d) => break (U16_HEX_BYTES, d.len()),
+                        None => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::UnexpectedEof,
+                                "encountered a non-data line in a data-line only context",
+                            ))
+                        }
+                    },
+                }
+            };
+            self.cap = cap + ofs;
+            self.pos = ofs;
+        }
+        Ok(&self.parent.buf[self.pos..self.cap])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = std::cmp::min(self.pos + amt, self.cap);
+    }
+}
+
+impl<'a, T, F> io::Read for WithSidebands<'a, T, F>
+where
+    T: io::Read,
+    F: FnMut(bool, &[u8]),
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        use io::BufRead;
+        let nread = {
+            let mut rem = self.fill_buf()?;
+            rem.read(buf)?
+        };
+        self.consume(nread);
+        Ok(nread)
+    }
+}
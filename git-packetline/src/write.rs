@@ -0,0 +1,68 @@
+use crate::{encode, MAX_DATA_LEN, U16_HEX_BYTES};
+use std::io;
+
+/// An `io::Write` implementation turning each call to `write()` into one or more data lines.
+pub struct Writer<T> {
+    inner: T,
+    binary: bool,
+}
+
+impl<T: io::Write> Writer<T> {
+    /// Create a new writer in binary mode.
+    pub fn new(inner: T) -> Self {
+        Writer { inner, binary: true }
+    }
+
+    /// In text mode, each line is terminated with a newline.
+    pub fn text_mode(mut self) -> Self {
+        self.binary = false;
+        self
+    }
+
+    /// In binary mode, data is written as is.
+    pub fn binary_mode(mut self) -> Self {
+        self.binary = true;
+        self
+    }
+
+    /// Switch between binary and text mode.
+    pub fn enable_text_mode(&mut self, text: bool) {
+        self.binary = !text;
+    }
+
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: io::Write> io::Write for Writer<T> {
+    fn write(&mut self, mut buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Err(io::Error::other(
+                "empty packet lines are not permitted as '0004' is invalid",
+            ));
+        }
+
+        let mut written = 0;
+        while !buf.is_empty() {
+            let (data, rest) = buf.split_at(buf.len().min(MAX_DATA_LEN - if self.binary { 0 } else { 1 }));
+            written += if self.binary {
+                encode::data_to_write(data, &mut self.inner)
+            } else {
+                encode::text_to_write(data, &mut self.inner)
+            }?;
+            // subtract the header bytes and the newline as these aren't part of the data
+            written -= U16_HEX_BYTES + if self.binary { 0 } else { 1 };
+            buf = rest;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,98 @@
+use bstr::ByteSlice;
+use futures_io::AsyncRead;
+use futures_lite::{future::block_on, AsyncReadExt, AsyncWriteExt};
+use git_packetline::{async_io, PacketLine};
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Returns one byte at a time, and `Pending` before each of them, to exercise resuming reads.
+struct Trickle<'a> {
+    data: &'a [u8],
+    ready: bool,
+}
+
+impl<'a> AsyncRead for Trickle<'a> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        if !self.ready {
+            self.ready = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        self.ready = false;
+        let n = self.data.len().min(buf.len()).min(1);
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Poll::Ready(Ok(n))
+    }
+}
+
+fn lines(data: &[u8]) -> async_io::Provider<Trickle<'_>> {
+    async_io::Provider::new(Trickle { data, ready: false }, &[PacketLine::Flush])
+}
+
+#[test]
+fn read_lines_until_flush_then_reset() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut rd = lines(b"0008abcd0006ef0000000ahello\n0000");
+        assert_eq!(rd.next_line().await.expect("line")??, PacketLine::Data(b"abcd"));
+        assert_eq!(rd.peek_line().await.expect("line")??, PacketLine::Data(b"ef"));
+        assert_eq!(
+            rd.peek_line().await.expect("line")??,
+            PacketLine::Data(b"ef"),
+            "peeking is idempotent"
+        );
+        assert_eq!(rd.next_line().await.expect("line")??, PacketLine::Data(b"ef"));
+        assert!(rd.next_line().await.is_none());
+        assert_eq!(rd.stopped_at(), Some(PacketLine::Flush));
+
+        rd.reset();
+        assert_eq!(rd.next_line().await.expect("line")??.to_text().expect("text"), "hello");
+        assert!(rd.next_line().await.is_none());
+        rd.reset();
+        assert!(rd.next_line().await.is_none(), "end of stream");
+        Ok(())
+    })
+}
+
+#[test]
+fn oversized_lines_are_an_error() {
+    block_on(async {
+        let mut rd = lines(b"ffff");
+        assert!(matches!(
+            rd.next_line().await.expect("line").expect("no io error"),
+            Err(git_packetline::decode::Error::DataLengthLimitExceeded(0xffff))
+        ));
+    })
+}
+
+#[test]
+fn read_lines_with_sidebands() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut rd = lines(b"0009\x01abcd0007\x02p10007\x03e10007\x01ef0000");
+        let mut messages = Vec::new();
+        let mut out = String::new();
+        rd.as_read_with_sidebands(|is_err, text| messages.push((is_err, text.as_bstr().to_string())))
+            .read_to_string(&mut out)
+            .await?;
+        assert_eq!(out, "abcdef");
+        assert_eq!(messages, vec![(false, "p1".to_string()), (true, "e1".to_string())]);
+        Ok(())
+    })
+}
+
+#[test]
+fn writes_are_encoded_as_lines() -> Result<(), Box<dyn std::error::Error>> {
+    block_on(async {
+        let mut w = async_io::Writer::new(Vec::new());
+        w.write_all(b"hello").await?;
+        w.enable_text_mode(true);
+        w.write_all(b"world").await?;
+        async_io::encode::flush_to_write(w.inner_mut()).await?;
+        w.flush().await?;
+        assert_eq!(w.into_inner().as_bstr(), "0009hello000aworld\n0000");
+        Ok(())
+    })
+}
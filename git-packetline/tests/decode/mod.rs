@@ -0,0 +1,71 @@
+use git_packetline::{decode, PacketLine};
+
+#[test]
+fn special_lines() {
+    assert_eq!(decode::all_at_once(b"0000").unwrap(), PacketLine::Flush);
+    assert_eq!(decode::all_at_once(b"0001").unwrap(), PacketLine::Delimiter);
+    assert_eq!(decode::all_at_once(b"0002").unwrap(), PacketLine::ResponseEnd);
+}
+
+#[test]
+fn data_lines() {
+    assert_eq!(decode::all_at_once(b"0008abcd").unwrap(), PacketLine::Data(b"abcd"));
+    assert_eq!(decode::all_at_once(b"0006a\n").unwrap().to_text().unwrap(), "a");
+}
+
+#[test]
+fn invalid_lines() {
+    assert!(matches!(
+        decode::all_at_once(b"0003"),
+        Err(decode::Error::InvalidLineLength)
+    ));
+    assert!(matches!(decode::all_at_once(b"0004"), Err(decode::Error::DataIsEmpty)));
+    assert!(matches!(decode::all_at_once(b"zzzz"), Err(decode::Error::HexDecode(_))));
+    for prefix in &[b"fff1", b"ffff"] {
+        assert!(matches!(
+            decode::hex_prefix(*prefix),
+            Err(decode::Error::DataLengthLimitExceeded(_))
+        ));
+        assert!(matches!(
+            decode::all_at_once(*prefix),
+            Err(decode::Error::DataLengthLimitExceeded(_))
+        ));
+    }
+    assert!(
+        matches!(decode::all_at_once(b"0005ab"), Err(decode::Error::Line(_, 5)),),
+        "more data than the line contains"
+    );
+}
+
+#[test]
+fn streaming_reports_missing_bytes() {
+    assert_eq!(
+        decode::streaming(b"00").unwrap(),
+        decode::Stream::Incomplete { bytes_needed: 2 }
+    );
+    assert_eq!(
+        decode::streaming(b"0009ab").unwrap(),
+        decode::Stream::Incomplete { bytes_needed: 3 }
+    );
+    assert_eq!(
+        decode::streaming(b"0006ab0000").unwrap(),
+        decode::Stream::Complete {
+            line: PacketLine::Data(b"ab"),
+            bytes_consumed: 6
+        }
+    );
+}
+
+#[test]
+fn error_lines_and_bands() {
+    let line = decode::all_at_once(b"000cERR oops").unwrap();
+    assert_eq!(line.to_error().expect("error line").to_message(), "oops");
+    assert_eq!(decode::all_at_once(b"0008data").unwrap().to_error(), None);
+
+    assert_eq!(
+        decode::all_at_once(b"0007\x02hi").unwrap().decode_band().unwrap(),
+        git_packetline::Band::Progress(b"hi")
+    );
+    assert!(decode::all_at_once(b"0007\x04hi").unwrap().decode_band().is_err());
+    assert!(PacketLine::Flush.decode_band().is_err());
+}
@@ -0,0 +1,41 @@
+use git_packetline::{encode, Channel, PacketLine};
+
+fn written(f: impl FnOnce(&mut Vec<u8>) -> std::io::Result<usize>) -> (usize, Vec<u8>) {
+    let mut out = Vec::new();
+    let n = f(&mut out).expect("write to succeed");
+    (n, out)
+}
+
+#[test]
+fn lines() {
+    assert_eq!(
+        written(|out| encode::data_to_write(b"hello", out)),
+        (9, b"0009hello".to_vec())
+    );
+    assert_eq!(
+        written(|out| encode::text_to_write(b"hello", out)),
+        (10, b"000ahello\n".to_vec())
+    );
+    assert_eq!(
+        written(|out| encode::error_to_write(b"oops", out)),
+        (12, b"000cERR oops".to_vec())
+    );
+    assert_eq!(
+        written(|out| encode::band_to_write(Channel::Progress, b"50%", out)),
+        (8, b"0008\x0250%".to_vec())
+    );
+    assert_eq!(written(|out| encode::flush_to_write(out)), (4, b"0000".to_vec()));
+    assert_eq!(written(|out| encode::delim_to_write(out)), (4, b"0001".to_vec()));
+    assert_eq!(written(|out| encode::response_end_to_write(out)), (4, b"0002".to_vec()));
+    assert_eq!(
+        written(|out| PacketLine::Data(b"a").write_to(out)),
+        (5, b"0005a".to_vec())
+    );
+}
+
+#[test]
+fn invalid_input() {
+    assert!(encode::data_to_write(b"", Vec::new()).is_err());
+    assert!(encode::data_to_write(&vec![0; git_packetline::MAX_DATA_LEN + 1], Vec::new()).is_err());
+    assert!(encode::data_to_write(&vec![0; git_packetline::MAX_DATA_LEN], Vec::new()).is_ok());
+}
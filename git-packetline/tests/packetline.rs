@@ -0,0 +1,6 @@
+#[cfg(feature = "async-io")]
+mod async_io;
+mod decode;
+mod encode;
+mod read;
+mod write;
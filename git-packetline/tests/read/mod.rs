@@ -0,0 +1,81 @@
+use bstr::ByteSlice;
+use git_packetline::{PacketLine, Provider};
+use std::io::{BufRead, Read};
+
+fn lines(data: &[u8]) -> Provider<&[u8]> {
+    Provider::new(data, &[PacketLine::Flush])
+}
+
+#[test]
+fn read_lines_until_flush_then_reset() -> Result<(), Box<dyn std::error::Error>> {
+    let mut rd = lines(b"0008abcd0006ef0000000ahello\n0000");
+    assert_eq!(rd.next_line().expect("line")??, PacketLine::Data(b"abcd"));
+    assert_eq!(rd.peek_line().expect("line")??, PacketLine::Data(b"ef"));
+    assert_eq!(
+        rd.peek_line().expect("line")??,
+        PacketLine::Data(b"ef"),
+        "peeking is idempotent"
+    );
+    assert_eq!(rd.next_line().expect("line")??, PacketLine::Data(b"ef"));
+    assert!(rd.next_line().is_none());
+    assert_eq!(rd.stopped_at(), Some(PacketLine::Flush));
+    assert!(rd.next_line().is_none(), "stays done until reset");
+
+    rd.reset();
+    assert_eq!(rd.next_line().expect("line")??.to_text().expect("text"), "hello");
+    assert!(rd.peek_line().is_none());
+    assert!(rd.next_line().is_none());
+    rd.reset();
+    assert!(rd.next_line().is_none(), "end of stream");
+    Ok(())
+}
+
+#[test]
+fn error_lines_can_fail() -> Result<(), Box<dyn std::error::Error>> {
+    let mut rd = lines(b"000cERR oops0000");
+    rd.fail_on_err_lines(true);
+    let err = rd.next_line().expect("line").expect_err("error line");
+    assert_eq!(err.to_string(), "oops");
+
+    let mut rd = lines(b"000cERR oops0000");
+    assert!(rd.next_line().expect("line")??.to_error().is_some());
+    Ok(())
+}
+
+#[test]
+fn oversized_lines_are_an_error() {
+    let mut rd = lines(b"ffff");
+    assert!(matches!(
+        rd.next_line().expect("line").expect("no io error"),
+        Err(git_packetline::decode::Error::DataLengthLimitExceeded(0xffff))
+    ));
+}
+
+#[test]
+fn read_data_lines_as_stream() -> Result<(), Box<dyn std::error::Error>> {
+    let mut rd = lines(b"0008abcd0006ef0000");
+    let mut out = Vec::new();
+    rd.as_read().read_to_end(&mut out)?;
+    assert_eq!(out.as_bstr(), "abcdef");
+    Ok(())
+}
+
+#[test]
+fn read_lines_with_sidebands() -> Result<(), Box<dyn std::error::Error>> {
+    let mut rd = lines(b"0009\x01abcd0007\x02p10007\x03e10007\x01ef0000");
+    let mut messages = Vec::new();
+    let mut out = String::new();
+    rd.as_read_with_sidebands(|is_err, text| messages.push((is_err, text.as_bstr().to_string())))
+        .read_to_string(&mut out)?;
+    assert_eq!(out, "abcdef");
+    assert_eq!(messages, vec![(false, "p1".to_string()), (true, "e1".to_string())]);
+    Ok(())
+}
+
+#[test]
+fn buf_read_lines_across_packets() -> Result<(), Box<dyn std::error::Error>> {
+    let mut rd = lines(b"0007a\nb0006c\n0000");
+    let lines: Vec<_> = rd.as_read().lines().collect::<Result<_, _>>()?;
+    assert_eq!(lines, vec!["a", "bc"]);
+    Ok(())
+}
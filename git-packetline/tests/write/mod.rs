@@ -0,0 +1,31 @@
+use bstr::ByteSlice;
+use git_packetline::{Provider, Writer};
+use std::io::{Read, Write};
+
+#[test]
+fn each_write_is_a_line() -> Result<(), Box<dyn std::error::Error>> {
+    let mut w = Writer::new(Vec::new());
+    assert_eq!(w.write(b"hello")?, 5);
+    w.enable_text_mode(true);
+    assert_eq!(w.write(b"world")?, 5);
+    assert_eq!(w.into_inner().as_bstr(), "0009hello000aworld\n");
+    assert!(Writer::new(Vec::new()).write(b"").is_err());
+    Ok(())
+}
+
+#[test]
+fn large_writes_are_split_into_multiple_lines() -> Result<(), Box<dyn std::error::Error>> {
+    let data: Vec<u8> = (0..git_packetline::MAX_DATA_LEN * 2 + 10).map(|n| n as u8).collect();
+    let mut w = Writer::new(Vec::new());
+    w.write_all(&data)?;
+    git_packetline::encode::flush_to_write(w.inner_mut())?;
+    let encoded = w.into_inner();
+    assert_eq!(encoded.len(), data.len() + 3 * 4 + 4, "three lines and a flush");
+
+    let mut out = Vec::new();
+    Provider::new(encoded.as_slice(), &[git_packetline::PacketLine::Flush])
+        .as_read()
+        .read_to_end(&mut out)?;
+    assert_eq!(out, data);
+    Ok(())
+}
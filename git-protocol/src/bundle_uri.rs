@@ -0,0 +1,351 @@
+//! Learn where to download bundles to seed a clone from, using the V2 `bundle-uri` command.
+//!
+//! Servers advertise a bundle list as `key=value` lines, with the same keys a bundle list file uses in its `bundle`
+//! section: `bundle.version` and `bundle.mode` apply to the whole list, while `bundle.<id>.uri` and
+//! `bundle.<id>.creationToken` describe a single bundle.
+use crate::Capabilities;
+use bstr::{BStr, BString, ByteSlice};
+use git_transport::{
+    client::{self, MessageKind, WriteMode},
+    packetline,
+};
+use quick_error::quick_error;
+use std::io::{self, Write};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred while talking to the server")
+            from()
+            source(err)
+        }
+        Transport(err: client::Error) {
+            display("The request could not be sent")
+            from()
+            source(err)
+        }
+        PacketLine(err: packetline::decode::Error) {
+            display("A packet line could not be decoded")
+            from()
+            source(err)
+        }
+        Unsupported {
+            display("The server does not support the 'bundle-uri' command")
+        }
+        MalformedLine(line: BString) {
+            display("Could not parse bundle list line {:?}", line)
+        }
+        UnsupportedVersion(version: BString) {
+            display("The bundle list version '{}' is not supported", version)
+        }
+        InvalidValue(key: BString, value: BString) {
+            display("The value '{}' of '{}' is invalid", value, key)
+        }
+        UriRefused(uri: String, allowed: String) {
+            display("Refusing to download the bundle at '{}' as its list only allows {} uris", uri, allowed)
+        }
+    }
+}
+
+/// How the bundles of a [`List`] relate to each other.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mode {
+    /// All bundles are needed, as each one may build on the objects of the ones before it.
+    All,
+    /// Each bundle contains the same objects, so any one of them is enough.
+    Any,
+}
+
+/// A bundle of a [`List`].
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bundle {
+    /// The name of the bundle in the list, like `daily` in `bundle.daily.uri`.
+    pub id: BString,
+    /// Where to download the bundle from, which may be relative to the location of the list as handled by
+    /// [`resolve_uri()`].
+    pub uri: BString,
+    /// A number which increases with the age of bundles, such that bundles are applied in this order as later
+    /// ones may require the objects of earlier ones.
+    pub creation_token: Option<u64>,
+}
+
+/// The bundles to download along with how they can be combined.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct List {
+    /// How the bundles relate to each other.
+    pub mode: Mode,
+    /// The heuristic to use when fetching later, like `creationToken`, if set.
+    pub heuristic: Option<BString>,
+    /// The bundles in the order of their first appearance.
+    pub bundles: Vec<Bundle>,
+}
+
+impl List {
+    /// Build a list from `key` and `value` pairs like `bundle.version` and `1`, with keys compared without regard
+    /// to case as in configuration files. Unknown keys are ignored, as well as bundles without uri.
+    pub fn from_pairs<'a>(pairs: impl IntoIterator<Item = (&'a BStr, &'a BStr)>) -> Result<Self, Error> {
+        let mut list = List {
+            mode: Mode::All,
+            heuristic: None,
+            bundles: Vec::new(),
+        };
+        let mut version = None;
+        for (key, value) in pairs {
+            let invalid = || Error::InvalidValue(key.to_owned(), value.to_owned());
+            let name = match key.get(..7) {
+                Some(prefix) if prefix.eq_ignore_ascii_case(b"bundle.") => &key[7..],
+                _ => continue,
+            };
+            let (id, name) = match name.rfind_byte(b'.') {
+                Some(pos) => (Some(&name[..pos]), &name[pos + 1..]),
+                None => (None, name),
+            };
+            match id {
+                None if name.eq_ignore_ascii_case(b"version") => version = Some(value),
+                None if name.eq_ignore_ascii_case(b"mode") => {
+                    list.mode = match value.as_bytes() {
+                        b"all" => Mode::All,
+                        b"any" => Mode::Any,
+                        _ => return Err(invalid()),
+                    }
+                }
+                None if name.eq_ignore_ascii_case(b"heuristic") => list.heuristic = Some(value.to_owned()),
+                None => {}
+                Some(id) => {
+                    let bundle = match list.bundles.iter().position(|b| b.id == id) {
+                        Some(pos) => &mut list.bundles[pos],
+                        None => {
+                            list.bundles.push(Bundle {
+                                id: id.into(),
+                                uri: BString::default(),
+                                creation_token: None,
+                            });
+                            list.bundles.last_mut().expect("just pushed")
+                        }
+                    };
+                    if name.eq_ignore_ascii_case(b"uri") {
+                        bundle.uri = value.to_owned();
+                    } else if name.eq_ignore_ascii_case(b"creationtoken") {
+                        bundle.creation_token =
+                            Some(value.to_str().ok().and_then(|v| v.parse().ok()).ok_or_else(invalid)?);
+                    }
+                }
+            }
+        }
+        match version {
+            Some(version) if version == "1" => {}
+            Some(version) => return Err(Error::UnsupportedVersion(version.to_owned())),
+            None => return Err(Error::UnsupportedVersion(BString::default())),
+        }
+        list.bundles.retain(|b| !b.uri.is_empty());
+        Ok(list)
+    }
+
+    /// Return the bundles in the order to apply them in, which is the order of their creation tokens if all of them
+    /// have one, or the order of the list otherwise.
+    pub fn sorted_bundles(&self) -> Vec<&Bundle> {
+        let mut bundles: Vec<_> = self.bundles.iter().collect();
+        if bundles.iter().all(|b| b.creation_token.is_some()) {
+            bundles.sort_by_key(|b| b.creation_token);
+        }
+        bundles
+    }
+}
+
+/// Send the `bundle-uri` command to the V2 server behind `transport`, which advertised `capabilities` during the
+/// handshake, and return the bundle list it advertises.
+pub fn bundle_uri<T: client::Transport + ?Sized>(
+    transport: &mut T,
+    capabilities: &Capabilities,
+) -> Result<List, Error> {
+    if !capabilities.contains("bundle-uri") {
+        return Err(Error::Unsupported);
+    }
+    let mut writer = transport.request(WriteMode::OneLfTerminatedLinePerWriteCall, MessageKind::Flush)?;
+    writer.write_all(b"command=bundle-uri")?;
+    if capabilities.contains("agent") {
+        writer.write_all(format!("agent=git/oxide-{}", env!("CARGO_PKG_VERSION")).as_bytes())?;
+    }
+    if let Some(format) = capabilities.capability("object-format").and_then(|c| c.value()) {
+        let mut cap = BString::from("object-format=");
+        cap.extend_from_slice(format);
+        writer.write_all(&cap)?;
+    }
+    writer.write_message(MessageKind::Delimiter)?;
+    let lines = writer.into_read()?;
+
+    let mut pairs = Vec::new();
+    while let Some(line) = lines.next_line() {
+        let line = line??;
+        let line = match line.as_slice() {
+            Some(line) => line.trim_end_with(|c| c == '\n'),
+            None => continue,
+        };
+        let pos = line.find_byte(b'=').ok_or_else(|| Error::MalformedLine(line.into()))?;
+        pairs.push((BString::from(&line[..pos]), BString::from(&line[pos + 1..])));
+    }
+    List::from_pairs(pairs.iter().map(|(k, v)| (k.as_bstr(), v.as_bstr())))
+}
+
+/// Resolve the `uri` of a bundle in a [`List`] as described in RFC 3986 relative to the `base` uri the list was
+/// downloaded from, or return it as is if the list was advertised by the server.
+///
+/// Similar to git, which only allows `file` uris given by the user, a downloaded list may only refer to bundles using
+/// its own scheme, with paths counting as `file`, and advertised lists may only refer to `http` and `https` uris.
+pub fn resolve_uri(base: Option<&str>, uri: &str) -> Result<String, Error> {
+    let reference = UriParts::from(uri);
+    let base = match base {
+        Some(base) => UriParts::from(base),
+        None => {
+            return match reference.scheme().as_str() {
+                "http" | "https" => Ok(uri.to_owned()),
+                _ => Err(Error::UriRefused(uri.into(), "http and https".into())),
+            }
+        }
+    };
+    let resolved = if reference.scheme.is_some() {
+        UriParts {
+            path: remove_dot_segments(&reference.path),
+            ..reference
+        }
+    } else if reference.authority.is_some() {
+        UriParts {
+            scheme: base.scheme,
+            path: remove_dot_segments(&reference.path),
+            ..reference
+        }
+    } else if reference.path.is_empty() {
+        UriParts {
+            scheme: base.scheme,
+            authority: base.authority,
+            path: base.path.clone(),
+            query: reference.query.or(base.query),
+            fragment: reference.fragment,
+        }
+    } else {
+        let path = if reference.path.starts_with('/') {
+            reference.path
+        } else if base.authority.is_some() && base.path.is_empty() {
+            format!("/{}", reference.path)
+        } else {
+            let directory = base.path.rfind('/').map_or("", |pos| &base.path[..=pos]);
+            format!("{}{}", directory, reference.path)
+        };
+        UriParts {
+            scheme: base.scheme,
+            authority: base.authority,
+            path: remove_dot_segments(&path),
+            ..reference
+        }
+    };
+    if resolved.scheme() != base.scheme() {
+        return Err(Error::UriRefused(resolved.to_string(), base.scheme()));
+    }
+    Ok(resolved.to_string())
+}
+
+/// The components `scheme:[//authority]path[?query][#fragment]` of a uri.
+struct UriParts<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: String,
+    query: Option<&'a str>,
+    fragment: Option<&'a str>,
+}
+
+impl<'a> UriParts<'a> {
+    /// The scheme in lower case, with uris without scheme being paths.
+    fn scheme(&self) -> String {
+        self.scheme.unwrap_or("file").to_ascii_lowercase()
+    }
+}
+
+impl<'a> From<&'a str> for UriParts<'a> {
+    fn from(uri: &'a str) -> Self {
+        fn split_off(uri: &str, delimiter: char) -> (&str, Option<&str>) {
+            match uri.find(delimiter) {
+                Some(pos) => (&uri[..pos], Some(&uri[pos + 1..])),
+                None => (uri, None),
+            }
+        }
+        let (rest, fragment) = split_off(uri, '#');
+        let (rest, query) = split_off(rest, '?');
+        let (scheme, rest) = match rest.find(':') {
+            Some(pos)
+                if rest[..pos].starts_with(|c: char| c.is_ascii_alphabetic())
+                    && rest[..pos]
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.') =>
+            {
+                (Some(&rest[..pos]), &rest[pos + 1..])
+            }
+            _ => (None, rest),
+        };
+        let (authority, path) = match rest.strip_prefix("//") {
+            Some(rest) => {
+                let end = rest.find('/').unwrap_or(rest.len());
+                (Some(&rest[..end]), &rest[end..])
+            }
+            None => (None, rest),
+        };
+        UriParts {
+            scheme,
+            authority,
+            path: path.into(),
+            query,
+            fragment,
+        }
+    }
+}
+
+impl<'a> std::fmt::Display for UriParts<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(scheme) = self.scheme {
+            write!(f, "{}:", scheme)?;
+        }
+        if let Some(authority) = self.authority {
+            write!(f, "//{}", authority)?;
+        }
+        f.write_str(&self.path)?;
+        if let Some(query) = self.query {
+            write!(f, "?{}", query)?;
+        }
+        if let Some(fragment) = self.fragment {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+/// Remove the `.` and `..` segments of `path` as described in section 5.2.4 of RFC 3986.
+fn remove_dot_segments(path: &str) -> String {
+    let mut input = path;
+    let mut output = String::with_capacity(path.len());
+    while !input.is_empty() {
+        if let Some(rest) = input.strip_prefix("../").or_else(|| input.strip_prefix("./")) {
+            input = rest;
+        } else if input.starts_with("/./") || input == "/." {
+            input = &input[2..];
+            if input.is_empty() {
+                input = "/";
+            }
+        } else if input.starts_with("/../") || input == "/.." {
+            input = &input[3..];
+            if input.is_empty() {
+                input = "/";
+            }
+            output.truncate(output.rfind('/').unwrap_or(0));
+        } else if input == "." || input == ".." {
+            input = "";
+        } else {
+            let end = input[1..].find('/').map_or(input.len(), |pos| pos + 1);
+            output.push_str(&input[..end]);
+            input = &input[end..];
+        }
+    }
+    output
+}
@@ -0,0 +1,137 @@
+//! Parse the capabilities advertised by the server, either in the first line of a V1 ref advertisement or as
+//! individual lines in V2.
+use bstr::{BStr, BString, ByteSlice};
+use quick_error::quick_error;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        MissingDelimitingNullByte {
+            display("Capabilities were missing entirely as there was no 0 byte")
+        }
+        NoCapabilities {
+            display("there was not a single capability behind the delimiter")
+        }
+        MissingVersionLine {
+            display("a version line was expected, but none was retrieved")
+        }
+        MalformattedVersionLine(actual: BString) {
+            display("expected 'version X', got '{}'", actual)
+        }
+        UnsupportedVersion(wanted: u8, got: BString) {
+            display("Got unsupported version '{}', expected '{}'", got, wanted)
+        }
+    }
+}
+
+/// A list of capabilities, as sent by the server.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Capabilities {
+    data: BString,
+    value_sep: u8,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities {
+            data: BString::default(),
+            value_sep: b' ',
+        }
+    }
+}
+
+/// A single capability, possibly with a value, like `agent=git/2.28.0`.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub struct Capability<'a>(&'a BStr);
+
+impl<'a> Capability<'a> {
+    /// The name of the capability, which is everything before the first `=`.
+    pub fn name(&self) -> &'a BStr {
+        self.0
+            .splitn(2, |b| *b == b'=')
+            .next()
+            .expect("at least one field")
+            .as_bstr()
+    }
+    /// The value of the capability after the first `=`, if present.
+    pub fn value(&self) -> Option<&'a BStr> {
+        self.0.splitn(2, |b| *b == b'=').nth(1).map(|name| name.as_bstr())
+    }
+    /// The value split by spaces, as used by V2 capabilities like `fetch=shallow filter`.
+    pub fn values(&self) -> Option<impl Iterator<Item = &'a BStr>> {
+        self.value().map(|v| v.split(|b| *b == b' ').map(|s| s.as_bstr()))
+    }
+    /// Returns `Some(true)` if `feature` is one of the `values()`, or `None` if there are no values.
+    pub fn supports(&self, feature: &str) -> Option<bool> {
+        self.values().map(|mut values| values.any(|v| v == feature.as_bytes()))
+    }
+}
+
+impl Capabilities {
+    /// Parse V1 capabilities from `bytes`, the first line of a ref advertisement, returning them along with the
+    /// position of the null byte which separates them from the ref.
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Capabilities, usize), Error> {
+        let delimiter_pos = bytes.find_byte(0).ok_or(Error::MissingDelimitingNullByte)?;
+        if delimiter_pos + 1 == bytes.len() {
+            return Err(Error::NoCapabilities);
+        }
+        let capabilities = &bytes[delimiter_pos + 1..];
+        Ok((
+            Capabilities {
+                data: capabilities.trim_end_with(|c| c == '\n').as_bstr().to_owned(),
+                value_sep: b' ',
+            },
+            delimiter_pos,
+        ))
+    }
+
+    /// Parse V2 capabilities from the given newline-free `lines`, the first of which must be `version 2`.
+    pub fn from_lines<'a>(lines: impl IntoIterator<Item = &'a BStr>) -> Result<Capabilities, Error> {
+        let mut lines = lines.into_iter();
+        let version_line = lines.next().ok_or(Error::MissingVersionLine)?;
+        let mut fields = version_line.splitn(2, |b| *b == b' ');
+        let (name, value) = (fields.next(), fields.next());
+        if name != Some(b"version") {
+            return Err(Error::MalformattedVersionLine(version_line.to_owned()));
+        }
+        if value != Some(b"2") {
+            return Err(Error::UnsupportedVersion(
+                2,
+                value.unwrap_or_default().as_bstr().to_owned(),
+            ));
+        }
+        let mut data = BString::default();
+        for line in lines.filter(|l| !l.is_empty()) {
+            if !data.is_empty() {
+                data.push(b'\n');
+            }
+            data.extend_from_slice(line);
+        }
+        Ok(Capabilities { data, value_sep: b'\n' })
+    }
+
+    /// Returns true if this is the capability list of a `version 2` server.
+    pub fn is_v2(&self) -> bool {
+        self.value_sep == b'\n'
+    }
+
+    /// Returns true if a capability named `feature` is present.
+    pub fn contains(&self, feature: &str) -> bool {
+        self.capability(feature).is_some()
+    }
+
+    /// Returns the capability named `name`, if present.
+    pub fn capability(&self, name: &str) -> Option<Capability<'_>> {
+        self.iter().find(|c| c.name() == name.as_bytes())
+    }
+
+    /// Iterate all capabilities in the order they were advertised.
+    pub fn iter(&self) -> impl Iterator<Item = Capability<'_>> {
+        let sep = self.value_sep;
+        self.data
+            .split(move |b| *b == sep)
+            .filter(|c| !c.is_empty())
+            .map(|c| Capability(c.as_bstr()))
+    }
+}
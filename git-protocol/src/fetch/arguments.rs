@@ -0,0 +1,324 @@
+use crate::{fetch::Filter, handshake};
+use bstr::{BStr, BString, ByteVec};
+use git_object::owned;
+use git_transport::{
+    client::{self, MessageKind, WriteMode},
+    Protocol,
+};
+use std::io::{self, Write};
+
+/// The arguments of a fetch request, which are sent with every round of the negotiation.
+///
+/// Features are only enabled if the server supports them, as learned from the capabilities of the handshake.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Arguments {
+    version: Protocol,
+    /// V1 capabilities to append to the first `want`, or V2 arguments.
+    features: Vec<BString>,
+    /// V2 capabilities to send before the arguments.
+    capabilities: Vec<BString>,
+    wants: Vec<owned::Id>,
+    want_refs: Vec<BString>,
+    shallow_args: Vec<BString>,
+    filter: Option<BString>,
+    supports_shallow: bool,
+    supports_deepen_since: bool,
+    supports_deepen_not: bool,
+    supports_deepen_relative: bool,
+    supports_filter: bool,
+    supports_ref_in_want: bool,
+    sideband: bool,
+    multi_ack: bool,
+}
+
+impl Arguments {
+    /// Create new arguments for a server that responded to the handshake with `server`.
+    pub fn new(server: &handshake::Outcome) -> Self {
+        let caps = &server.capabilities;
+        let agent = format!("agent=git/oxide-{}", env!("CARGO_PKG_VERSION"));
+        let mut features = Vec::new();
+        let mut capabilities = Vec::new();
+        match server.server_protocol_version {
+            Protocol::V1 => {
+                let mut use_if_supported = |name: &str| {
+                    if caps.contains(name) {
+                        features.push(BString::from(name));
+                        true
+                    } else {
+                        false
+                    }
+                };
+                let multi_ack = use_if_supported("multi_ack_detailed") || use_if_supported("multi_ack");
+                let sideband = use_if_supported("side-band-64k") || use_if_supported("side-band");
+                use_if_supported("thin-pack");
+                use_if_supported("ofs-delta");
+                if caps.contains("agent") {
+                    features.push(agent.into());
+                }
+                Arguments {
+                    version: Protocol::V1,
+                    features,
+                    capabilities,
+                    wants: Vec::new(),
+                    want_refs: Vec::new(),
+                    shallow_args: Vec::new(),
+                    filter: None,
+                    supports_shallow: caps.contains("shallow"),
+                    supports_deepen_since: caps.contains("deepen-since"),
+                    supports_deepen_not: caps.contains("deepen-not"),
+                    supports_deepen_relative: caps.contains("deepen-relative"),
+                    supports_filter: caps.contains("filter"),
+                    supports_ref_in_want: false,
+                    sideband,
+                    multi_ack,
+                }
+            }
+            Protocol::V2 => {
+                let fetch = caps.capability("fetch");
+                let fetch_supports = |name: &str| fetch.as_ref().and_then(|c| c.supports(name)).unwrap_or(false);
+                if caps.contains("agent") {
+                    capabilities.push(agent.into());
+                }
+                if let Some(format) = caps.capability("object-format").and_then(|c| c.value()) {
+                    let mut cap = BString::from("object-format=");
+                    cap.push_str(format);
+                    capabilities.push(cap);
+                }
+                features.extend(vec!["thin-pack".into(), "ofs-delta".into()]);
+                let supports_shallow = fetch_supports("shallow");
+                Arguments {
+                    version: Protocol::V2,
+                    features,
+                    capabilities,
+                    wants: Vec::new(),
+                    want_refs: Vec::new(),
+                    shallow_args: Vec::new(),
+                    filter: None,
+                    supports_shallow,
+                    supports_deepen_since: supports_shallow,
+                    supports_deepen_not: supports_shallow,
+                    supports_deepen_relative: supports_shallow,
+                    supports_filter: fetch_supports("filter"),
+                    supports_ref_in_want: fetch_supports("ref-in-want"),
+                    sideband: true,
+                    multi_ack: true,
+                }
+            }
+        }
+    }
+
+    /// Return the protocol version the arguments are serialized for.
+    pub fn version(&self) -> Protocol {
+        self.version
+    }
+
+    /// Ask the server to send `id` and all objects reachable from it that we don't have.
+    pub fn want(&mut self, id: owned::Id) {
+        self.wants.push(id);
+    }
+
+    /// Ask the server to send the object the ref at `ref_path` points to, like `refs/heads/main`, and all objects
+    /// reachable from it that we don't have.
+    ///
+    /// The server tells which object that is in the [`wanted_refs`][super::Response::wanted_refs] of the response,
+    /// which saves listing the refs beforehand. Only call this if
+    /// [`can_use_ref_in_want()`][Arguments::can_use_ref_in_want()] returns true.
+    pub fn want_ref(&mut self, ref_path: &BStr) {
+        self.want_refs.push(ref_path.to_owned());
+    }
+
+    /// Return the ids we want.
+    pub fn wants(&self) -> &[owned::Id] {
+        &self.wants
+    }
+
+    /// Return the paths of the refs we want.
+    pub fn want_refs(&self) -> &[BString] {
+        &self.want_refs
+    }
+
+    /// Returns true if there is nothing we want.
+    pub fn is_empty(&self) -> bool {
+        self.wants.is_empty() && self.want_refs.is_empty()
+    }
+
+    /// Returns true if the server supports `shallow` and `deepen` arguments.
+    pub fn can_use_shallow(&self) -> bool {
+        self.supports_shallow
+    }
+
+    /// Returns true if the server supports `deepen-since`.
+    pub fn can_use_deepen_since(&self) -> bool {
+        self.supports_deepen_since
+    }
+
+    /// Returns true if the server supports `deepen-not`.
+    pub fn can_use_deepen_not(&self) -> bool {
+        self.supports_deepen_not
+    }
+
+    /// Returns true if the server supports `deepen-relative`.
+    pub fn can_use_deepen_relative(&self) -> bool {
+        self.supports_deepen_relative
+    }
+
+    /// Returns true if the server supports `filter` for partial clones.
+    pub fn can_use_filter(&self) -> bool {
+        self.supports_filter
+    }
+
+    /// Returns true if the server supports `want-ref` to ask for refs by name, which is only possible with V2.
+    pub fn can_use_ref_in_want(&self) -> bool {
+        self.supports_ref_in_want
+    }
+
+    /// Returns true if the pack and progress messages are multiplexed using side-bands.
+    pub fn uses_sideband(&self) -> bool {
+        self.sideband
+    }
+
+    pub(crate) fn uses_multi_ack(&self) -> bool {
+        self.multi_ack
+    }
+
+    pub(crate) fn has_shallow_args(&self) -> bool {
+        !self.shallow_args.is_empty()
+    }
+
+    /// Tell the server that the parents of commit `id` are not present locally, as our repository is shallow.
+    pub fn shallow(&mut self, id: owned::Id) {
+        let mut arg = BString::from("shallow ");
+        arg.push_str(id.to_sha1_hex());
+        self.shallow_args.push(arg);
+    }
+
+    /// Limit the history to the given `depth` of commits, counted from the wanted commits.
+    ///
+    /// If `deepen-relative` is used as well, the depth is counted from the current shallow boundary instead.
+    pub fn deepen(&mut self, depth: usize) {
+        self.shallow_args.push(format!("deepen {}", depth).into());
+    }
+
+    /// Only fetch commits more recent than `seconds_since_epoch`.
+    pub fn deepen_since(&mut self, seconds_since_epoch: u32) {
+        self.shallow_args
+            .push(format!("deepen-since {}", seconds_since_epoch).into());
+    }
+
+    /// Don't fetch commits reachable from the remote ref at `ref_path`.
+    pub fn deepen_not(&mut self, ref_path: &BStr) {
+        let mut arg = BString::from("deepen-not ");
+        arg.push_str(ref_path);
+        self.shallow_args.push(arg);
+    }
+
+    /// Interpret the `deepen` depth relative to the current shallow boundary.
+    pub fn deepen_relative(&mut self) {
+        self.shallow_args.push("deepen-relative".into());
+    }
+
+    /// Ask the server to omit the objects matching `filter` from the pack, as done by partial clones.
+    ///
+    /// Only call this if [`can_use_filter()`][Arguments::can_use_filter()] returns true.
+    pub fn filter(&mut self, filter: Filter) {
+        if self.version == Protocol::V1 && self.filter.is_none() {
+            self.features.push("filter".into());
+        }
+        self.filter = Some(format!("filter {}", filter).into());
+    }
+
+    /// Ask the server to not send progress messages.
+    pub fn no_progress(&mut self) {
+        self.features.push("no-progress".into());
+    }
+
+    /// Ask the server to send annotated tags pointing to objects that are part of the pack.
+    pub fn include_tag(&mut self) {
+        self.features.push("include-tag".into());
+    }
+
+    /// Add the argument `feature` as is, like `filter blob:none` with V2 or a capability with V1.
+    pub fn add_feature(&mut self, feature: impl Into<BString>) {
+        self.features.push(feature.into());
+    }
+
+    /// Send one round of the negotiation through `transport` using the `haves`, which is the last round if `done`.
+    ///
+    /// `wants` are included if `with_wants` is true, which is always the case for the first round and for stateless
+    /// transports.
+    pub(crate) fn send<'a, T: client::Transport + ?Sized>(
+        &self,
+        transport: &'a mut T,
+        haves: &[owned::Id],
+        with_wants: bool,
+        done: bool,
+    ) -> Result<&'a mut client::Lines, client::Error> {
+        match self.version {
+            Protocol::V1 => {
+                let on_into_read = if done {
+                    MessageKind::Text(b"done")
+                } else {
+                    MessageKind::Flush
+                };
+                let mut writer = transport.request(WriteMode::OneLfTerminatedLinePerWriteCall, on_into_read)?;
+                if with_wants {
+                    for (index, want) in self.wants.iter().enumerate() {
+                        let mut line = BString::from("want ");
+                        line.push_str(want.to_sha1_hex());
+                        if index == 0 {
+                            for feature in &self.features {
+                                line.push(b' ');
+                                line.push_str(feature);
+                            }
+                        }
+                        writer.write_all(&line)?;
+                    }
+                    for arg in self.shallow_args.iter().chain(self.filter.as_ref()) {
+                        writer.write_all(arg)?;
+                    }
+                    writer.write_message(MessageKind::Flush)?;
+                }
+                write_haves(&mut writer, haves)?;
+                Ok(writer.into_read()?)
+            }
+            Protocol::V2 => {
+                let mut writer = transport.request(WriteMode::OneLfTerminatedLinePerWriteCall, MessageKind::Flush)?;
+                writer.write_all(b"command=fetch")?;
+                for capability in &self.capabilities {
+                    writer.write_all(capability)?;
+                }
+                writer.write_message(MessageKind::Delimiter)?;
+                for feature in &self.features {
+                    writer.write_all(feature)?;
+                }
+                for want in &self.wants {
+                    let mut line = BString::from("want ");
+                    line.push_str(want.to_sha1_hex());
+                    writer.write_all(&line)?;
+                }
+                for want_ref in &self.want_refs {
+                    let mut line = BString::from("want-ref ");
+                    line.push_str(want_ref);
+                    writer.write_all(&line)?;
+                }
+                for arg in self.shallow_args.iter().chain(self.filter.as_ref()) {
+                    writer.write_all(arg)?;
+                }
+                write_haves(&mut writer, haves)?;
+                if done {
+                    writer.write_all(b"done")?;
+                }
+                Ok(writer.into_read()?)
+            }
+        }
+    }
+}
+
+fn write_haves(mut out: impl io::Write, haves: &[owned::Id]) -> io::Result<()> {
+    for have in haves {
+        let mut line = BString::from("have ");
+        line.push_str(have.to_sha1_hex());
+        out.write_all(&line)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use bstr::{BString, ByteSlice};
+use quick_error::quick_error;
+use std::{fmt, str::FromStr};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Unsupported(spec: BString) {
+            display("The filter specification '{}' is not supported", spec)
+        }
+    }
+}
+
+/// The objects the server may omit from the pack of a partial clone, as passed to `git clone --filter`.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Filter {
+    /// Omit all blobs, specified as `blob:none`.
+    BlobNone,
+    /// Omit blobs of at least the given size in bytes, specified as `blob:limit=<n>[kmg]`.
+    BlobLimit(u64),
+    /// Omit trees and blobs deeper than the given depth, counted from the root tree, specified as `tree:<depth>`.
+    ///
+    /// A depth of 0 omits all trees and blobs.
+    TreeDepth(usize),
+}
+
+impl Filter {
+    /// Parse the filter specification `spec`, like `blob:none`.
+    pub fn from_bytes(spec: &[u8]) -> Result<Self, Error> {
+        let unsupported = || Error::Unsupported(spec.as_bstr().to_owned());
+        let parse_number = |digits: &[u8]| digits.to_str().ok().and_then(|d| d.parse::<u64>().ok());
+        Ok(match spec {
+            b"blob:none" => Filter::BlobNone,
+            _ => {
+                if let Some(limit) = spec.strip_prefix(b"blob:limit=") {
+                    let (digits, factor) = match limit.last() {
+                        Some(b'k') | Some(b'K') => (&limit[..limit.len() - 1], 1024),
+                        Some(b'm') | Some(b'M') => (&limit[..limit.len() - 1], 1024 * 1024),
+                        Some(b'g') | Some(b'G') => (&limit[..limit.len() - 1], 1024 * 1024 * 1024),
+                        _ => (limit, 1),
+                    };
+                    Filter::BlobLimit(
+                        parse_number(digits)
+                            .and_then(|n| n.checked_mul(factor))
+                            .ok_or_else(unsupported)?,
+                    )
+                } else if let Some(depth) = spec.strip_prefix(b"tree:") {
+                    Filter::TreeDepth(parse_number(depth).ok_or_else(unsupported)? as usize)
+                } else {
+                    return Err(unsupported());
+                }
+            }
+        })
+    }
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Filter::from_bytes(s.as_bytes())
+    }
+}
+
+/// Display the filter as specification sent to the server, like `blob:none`.
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::BlobNone => f.write_str("blob:none"),
+            Filter::BlobLimit(limit) => write!(f, "blob:limit={}", limit),
+            Filter::TreeDepth(depth) => write!(f, "tree:{}", depth),
+        }
+    }
+}
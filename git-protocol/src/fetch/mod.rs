@@ -0,0 +1,281 @@
+//! Negotiate the objects to fetch with the server and receive them as pack.
+//!
+//! The negotiation sends the objects we want along with batches of `have` lines chosen by a
+//! [negotiation algorithm][negotiate::Negotiate], until enough common objects were found or there is nothing more to
+//! send. Stateless transports and V2 resend all wants and common objects with each round.
+use crate::remote_progress;
+use bstr::BString;
+use git_transport::{client, packetline, Protocol};
+use quick_error::quick_error;
+use std::io;
+
+mod arguments;
+pub use arguments::Arguments;
+
+pub mod filter;
+#[doc(inline)]
+pub use filter::Filter;
+
+mod response;
+pub use response::{Acknowledgement, Response, ShallowUpdate, WantedRef};
+
+pub mod negotiate;
+#[doc(inline)]
+pub use negotiate::Negotiate;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred while talking to the server")
+            from()
+            source(err)
+        }
+        Transport(err: client::Error) {
+            display("The request could not be sent")
+            from()
+            source(err)
+        }
+        PacketLine(err: packetline::decode::Error) {
+            display("A packet line could not be decoded")
+            from()
+            source(err)
+        }
+        Id(hex: BString) {
+            display("'{}' could not be parsed as an object id", hex)
+        }
+        UnknownLine(line: BString) {
+            display("The line '{}' sent by the server could not be understood", line)
+        }
+        UnknownSectionHeader(header: BString) {
+            display("The response section '{}' is unknown", header)
+        }
+        UnexpectedEndOfResponse {
+            display("The server response ended unexpectedly")
+        }
+        Remote(message: BString) {
+            display("The server aborted with: {}", message)
+        }
+    }
+}
+
+/// The amount of `have` lines to send in the first round.
+pub const INITIAL_FLUSH: usize = 16;
+/// The amount of `have` lines by which to grow the batches of stateful transports once they reached this size.
+pub const PIPESAFE_FLUSH: usize = 32;
+/// The batch size for stateless transports at which they stop doubling.
+pub const LARGE_FLUSH: usize = 16384;
+/// Give up negotiating after sending this many `have` lines without finding a new common commit.
+pub const MAX_IN_VAIN: usize = 256;
+
+/// Compute the size of the batch of `have` lines to send after one of `count` lines.
+pub fn next_flush(is_stateless: bool, count: usize) -> usize {
+    if is_stateless {
+        if count < LARGE_FLUSH {
+            count * 2
+        } else {
+            count * 11 / 10
+        }
+    } else if count < PIPESAFE_FLUSH {
+        count * 2
+    } else {
+        count + PIPESAFE_FLUSH
+    }
+}
+
+/// Negotiate with the server behind `transport` which objects to send, then pass the received pack to `receive_pack`.
+///
+/// The `arguments` must be created from the handshake with the server, and `negotiator` must know about our tips
+/// already. Progress messages of the server are passed to `progress(is_error, text)`, and if the server sent an error
+/// message it is returned as [`Error::Remote`]. `receive_pack` is called with the pack, already separated from the
+/// progress side-band, and the response so far, before it is returned. Nothing is sent if there is nothing we want.
+pub fn fetch<T, P, F>(
+    transport: &mut T,
+    arguments: &Arguments,
+    negotiator: &mut dyn Negotiate,
+    mut progress: P,
+    receive_pack: F,
+) -> Result<Response, Error>
+where
+    T: client::Transport + ?Sized,
+    P: FnMut(bool, &[u8]),
+    F: FnOnce(&mut dyn io::BufRead, &Response) -> io::Result<()>,
+{
+    let mut response = Response::default();
+    if arguments.is_empty() {
+        return Ok(response);
+    }
+    let version = arguments.version();
+    let is_stateless = version == Protocol::V2 || !transport.is_stateful();
+    let mut common = Vec::new();
+    let mut batch_size = INITIAL_FLUSH;
+    let mut in_vain = 0;
+    let mut ready = false;
+    let mut got_final_ack = false;
+
+    loop {
+        let mut haves = if is_stateless { common.clone() } else { Vec::new() };
+        let mut num_new_haves = 0;
+        if !ready && in_vain < MAX_IN_VAIN {
+            while num_new_haves < batch_size {
+                match negotiator.next_have() {
+                    Some(id) => {
+                        haves.push(id);
+                        num_new_haves += 1;
+                    }
+                    None => break,
+                }
+            }
+        }
+        let done = num_new_haves == 0;
+        let with_wants = is_stateless || response.rounds == 0;
+        response.rounds += 1;
+        let mut span = git_features::trace::span("fetch::negotiation_round");
+        span.record(format_args!("round={} haves={}", response.rounds, haves.len()));
+
+        let lines = arguments.send(transport, &haves, with_wants, done)?;
+        lines.fail_on_err_lines(true);
+        let mut found_common = false;
+        let mut on_common = |id, found_common: &mut bool| {
+            if !negotiator.in_common_with_remote(id) {
+                *found_common = true;
+                if is_stateless {
+                    common.push(id);
+                }
+            }
+        };
+        let pack_follows = match version {
+            Protocol::V1 => {
+                if with_wants && arguments.has_shallow_args() {
+                    while let Some(line) = next_text_line(lines)? {
+                        response.shallow_updates.push(ShallowUpdate::from_line(line.as_ref())?);
+                    }
+                    lines.reset();
+                }
+                if !(done && !arguments.uses_multi_ack() && got_final_ack) {
+                    loop {
+                        let line = next_text_line(lines)?.ok_or(Error::UnexpectedEndOfResponse)?;
+                        let ack = Acknowledgement::from_v1_line(line.as_ref())?;
+                        response.acknowledgements.push(ack);
+                        match ack {
+                            Acknowledgement::Common(id) => on_common(id, &mut found_common),
+                            Acknowledgement::Ready(id) => {
+                                if let Some(id) = id {
+                                    on_common(id, &mut found_common);
+                                }
+                                ready = true;
+                            }
+                            Acknowledgement::Final(id) => {
+                                on_common(id, &mut found_common);
+                                got_final_ack = true;
+                                if !arguments.uses_multi_ack() {
+                                    ready = true;
+                                }
+                                break;
+                            }
+                            Acknowledgement::Nak => break,
+                        }
+                    }
+                }
+                done
+            }
+            Protocol::V2 => {
+                lines.reset_with(&[packetline::PacketLine::Flush, packetline::PacketLine::Delimiter]);
+                if done {
+                    true
+                } else {
+                    expect_section(lines, "acknowledgments")?;
+                    while let Some(line) = next_text_line(lines)? {
+                        let ack = Acknowledgement::from_v2_line(line.as_ref())?;
+                        response.acknowledgements.push(ack);
+                        match ack {
+                            Acknowledgement::Common(id) | Acknowledgement::Final(id) => {
+                                on_common(id, &mut found_common)
+                            }
+                            Acknowledgement::Ready(_) => ready = true,
+                            Acknowledgement::Nak => {}
+                        }
+                    }
+                    match lines.stopped_at() {
+                        Some(packetline::PacketLine::Delimiter) => true,
+                        Some(packetline::PacketLine::Flush) => false,
+                        _ => return Err(Error::UnexpectedEndOfResponse),
+                    }
+                }
+            }
+        };
+        drop(span);
+
+        if pack_follows {
+            if version == Protocol::V2 {
+                loop {
+                    lines.reset();
+                    let header = next_text_line(lines)?.ok_or(Error::UnexpectedEndOfResponse)?;
+                    match header.as_slice() {
+                        b"shallow-info" => {
+                            while let Some(line) = next_text_line(lines)? {
+                                response.shallow_updates.push(ShallowUpdate::from_line(line.as_ref())?);
+                            }
+                        }
+                        b"wanted-refs" => {
+                            while let Some(line) = next_text_line(lines)? {
+                                response.wanted_refs.push(WantedRef::from_line(line.as_ref())?);
+                            }
+                        }
+                        b"packfile" => break,
+                        _ => return Err(Error::UnknownSectionHeader(header)),
+                    }
+                }
+                lines.reset_with(&[packetline::PacketLine::Flush]);
+            }
+            response.has_pack = true;
+            if arguments.uses_sideband() {
+                let mut remote_error = None;
+                let res = receive_pack(
+                    &mut lines.as_read_with_sidebands(|is_err, text| {
+                        if is_err {
+                            remote_progress::collect_error(&mut remote_error, text);
+                        }
+                        progress(is_err, text)
+                    }),
+                    &response,
+                );
+                if let Some(message) = remote_error {
+                    return Err(Error::Remote(message));
+                }
+                res?;
+            } else {
+                receive_pack(&mut io::BufReader::new(lines.inner_mut()), &response)?;
+            }
+            return Ok(response);
+        }
+
+        if found_common {
+            in_vain = 0;
+        } else {
+            in_vain += num_new_haves;
+        }
+        batch_size = next_flush(is_stateless, batch_size);
+    }
+}
+
+fn next_text_line(lines: &mut client::Lines) -> Result<Option<BString>, Error> {
+    match lines.next_line() {
+        Some(line) => {
+            let line = line??;
+            let text = line
+                .to_text()
+                .ok_or_else(|| Error::UnknownLine(line.as_slice().unwrap_or_default().into()))?;
+            Ok(Some(text.to_owned()))
+        }
+        None => Ok(None),
+    }
+}
+
+fn expect_section(lines: &mut client::Lines, name: &str) -> Result<(), Error> {
+    match next_text_line(lines)? {
+        Some(header) if header == name => Ok(()),
+        Some(header) => Err(Error::UnknownSectionHeader(header)),
+        None => Err(Error::UnexpectedEndOfResponse),
+    }
+}
@@ -0,0 +1,350 @@
+//! Algorithms to choose the `have` lines to send to the server, to find out which objects we have in common.
+//!
+//! All implementations walk the commit graph from the tips added with [`add_tip()`][Negotiate::add_tip()], with the
+//! newest commits first, and don't send the ancestors of commits the server has in common with us.
+use git_object::{borrowed, owned};
+use std::collections::{BinaryHeap, HashMap};
+
+/// A negotiation algorithm, choosing the commits to send as `have` lines.
+pub trait Negotiate {
+    /// Add `id` as a commit to start the traversal from, usually the tip of a local ref.
+    fn add_tip(&mut self, id: owned::Id);
+    /// Mark `id` as common, as we know the server has it, usually because it advertised a ref pointing to it.
+    ///
+    /// It is not sent as `have` line, and neither are its ancestors.
+    fn known_common(&mut self, id: owned::Id);
+    /// Return the next commit to send as `have` line, or `None` if there is nothing more to send.
+    fn next_have(&mut self) -> Option<owned::Id>;
+    /// Called when the server acknowledged `id` as common. Returns true if it was already known to be common.
+    fn in_common_with_remote(&mut self, id: owned::Id) -> bool;
+}
+
+/// The negotiation algorithms known to us, as selected by `fetch.negotiationAlgorithm`.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Algorithm {
+    /// Don't send any `have` lines, which means the server will send all objects reachable from the wanted ones.
+    Noop,
+    /// Send all commits, walking back from the tips, as implemented by [`Consecutive`].
+    #[default]
+    Consecutive,
+    /// Skip more and more commits while walking back from the tips, as implemented by [`Skipping`].
+    Skipping,
+}
+
+impl Algorithm {
+    /// Parse the value of `fetch.negotiationAlgorithm`, where `default` is the same as `consecutive`.
+    pub fn from_config_value(value: &str) -> Option<Self> {
+        Some(match value {
+            "noop" => Algorithm::Noop,
+            "consecutive" | "default" => Algorithm::Consecutive,
+            "skipping" => Algorithm::Skipping,
+            _ => return None,
+        })
+    }
+
+    /// Create the negotiator implementing this algorithm, using `find` to lookup commits.
+    pub fn into_negotiator<'a, Find>(self, find: Find) -> Box<dyn Negotiate + 'a>
+    where
+        Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>> + 'a,
+    {
+        match self {
+            Algorithm::Noop => Box::new(Noop),
+            Algorithm::Consecutive => Box::new(Consecutive::new(find)),
+            Algorithm::Skipping => Box::new(Skipping::new(find)),
+        }
+    }
+}
+
+/// A negotiator which never sends `have` lines.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy, Default)]
+pub struct Noop;
+
+impl Negotiate for Noop {
+    fn add_tip(&mut self, _id: owned::Id) {}
+
+    fn known_common(&mut self, _id: owned::Id) {}
+
+    fn next_have(&mut self) -> Option<owned::Id> {
+        None
+    }
+
+    fn in_common_with_remote(&mut self, _id: owned::Id) -> bool {
+        false
+    }
+}
+
+const SEEN: u8 = 1;
+const COMMON: u8 = 1 << 1;
+const COMMON_REF: u8 = 1 << 2;
+const POPPED: u8 = 1 << 3;
+
+/// The commits seen so far along with their flags, and a queue of commits to visit, newest first.
+struct Graph<Find> {
+    find: Find,
+    buf: Vec<u8>,
+    flags: HashMap<owned::Id, u8>,
+    parents: HashMap<owned::Id, Vec<owned::Id>>,
+    queue: BinaryHeap<(u32, owned::Id)>,
+    non_common_revs: usize,
+}
+
+impl<Find> Graph<Find>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    fn new(find: Find) -> Self {
+        Graph {
+            find,
+            buf: Vec::new(),
+            flags: HashMap::new(),
+            parents: HashMap::new(),
+            queue: BinaryHeap::new(),
+            non_common_revs: 0,
+        }
+    }
+
+    fn flags(&self, id: &owned::Id) -> u8 {
+        self.flags.get(id).copied().unwrap_or(0)
+    }
+
+    fn set(&mut self, id: owned::Id, flags: u8) {
+        *self.flags.entry(id).or_insert(0) |= flags;
+    }
+
+    /// Mark `id` with `flags` and queue it, if it is a commit we have.
+    fn push(&mut self, id: owned::Id, flags: u8) -> bool {
+        self.set(id, flags | SEEN);
+        let (time, parents) = match (self.find)(id.to_borrowed(), &mut self.buf).and_then(|o| match o {
+            borrowed::Object::Commit(c) => Some((c.committer.time.time, c.parents().collect::<Vec<_>>())),
+            _ => None,
+        }) {
+            Some(commit) => commit,
+            None => return false,
+        };
+        self.parents.insert(id, parents);
+        self.queue.push((time, id));
+        if self.flags(&id) & COMMON == 0 {
+            self.non_common_revs += 1;
+        }
+        true
+    }
+
+    /// Pop the newest commit and return it along with its flags, unless there is nothing left that isn't common.
+    fn pop(&mut self) -> Option<(owned::Id, u8)> {
+        if self.non_common_revs == 0 {
+            return None;
+        }
+        let (_, id) = self.queue.pop()?;
+        self.set(id, POPPED);
+        let flags = self.flags(&id);
+        if flags & COMMON == 0 {
+            self.non_common_revs = self.non_common_revs.saturating_sub(1);
+        }
+        Some((id, flags))
+    }
+
+    fn parents_of(&self, id: &owned::Id) -> Vec<owned::Id> {
+        self.parents.get(id).cloned().unwrap_or_default()
+    }
+}
+
+/// The default negotiator, sending all commits as `have` lines, newest first, until the ones in common are found.
+pub struct Consecutive<Find> {
+    graph: Graph<Find>,
+}
+
+impl<Find> Consecutive<Find>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    /// Create a new instance using `find` to lookup commits.
+    pub fn new(find: Find) -> Self {
+        Consecutive {
+            graph: Graph::new(find),
+        }
+    }
+
+    fn mark_common(&mut self, id: owned::Id, ancestors_only: bool) {
+        let mut stack = vec![(id, ancestors_only)];
+        while let Some((id, ancestors_only)) = stack.pop() {
+            let flags = self.graph.flags(&id);
+            if flags & COMMON != 0 {
+                continue;
+            }
+            if !ancestors_only {
+                self.graph.set(id, COMMON);
+            }
+            if flags & SEEN == 0 {
+                self.graph.push(id, SEEN);
+            } else {
+                if !ancestors_only && flags & POPPED == 0 {
+                    self.graph.non_common_revs = self.graph.non_common_revs.saturating_sub(1);
+                }
+                stack.extend(self.graph.parents_of(&id).into_iter().map(|id| (id, false)));
+            }
+        }
+    }
+}
+
+impl<Find> Negotiate for Consecutive<Find>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    fn add_tip(&mut self, id: owned::Id) {
+        if self.graph.flags(&id) & SEEN == 0 {
+            self.graph.push(id, SEEN);
+        }
+    }
+
+    fn known_common(&mut self, id: owned::Id) {
+        if self.graph.flags(&id) & SEEN == 0 {
+            self.graph.push(id, COMMON_REF | SEEN);
+            self.mark_common(id, true);
+        }
+    }
+
+    fn next_have(&mut self) -> Option<owned::Id> {
+        loop {
+            let (id, flags) = self.graph.pop()?;
+            let (mark, send) = if flags & COMMON != 0 {
+                (COMMON | SEEN, false)
+            } else if flags & COMMON_REF != 0 {
+                (COMMON | SEEN, true)
+            } else {
+                (SEEN, true)
+            };
+            for parent in self.graph.parents_of(&id) {
+                if self.graph.flags(&parent) & SEEN == 0 {
+                    self.graph.push(parent, mark);
+                }
+                if mark & COMMON != 0 {
+                    self.mark_common(parent, true);
+                }
+            }
+            if send {
+                return Some(id);
+            }
+        }
+    }
+
+    fn in_common_with_remote(&mut self, id: owned::Id) -> bool {
+        let known_to_be_common = self.graph.flags(&id) & COMMON != 0;
+        self.mark_common(id, false);
+        known_to_be_common
+    }
+}
+
+/// A negotiator which skips an increasing amount of commits between the ones it sends as `have` lines.
+///
+/// This needs less rounds for long histories, at the cost of the server possibly sending objects we already have.
+pub struct Skipping<Find> {
+    graph: Graph<Find>,
+    /// The `(original_ttl, ttl)` of each queued commit, where `ttl` is the amount of commits to skip before sending one.
+    ttl: HashMap<owned::Id, (u16, u16)>,
+}
+
+impl<Find> Skipping<Find>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    /// Create a new instance using `find` to lookup commits.
+    pub fn new(find: Find) -> Self {
+        Skipping {
+            graph: Graph::new(find),
+            ttl: HashMap::new(),
+        }
+    }
+
+    fn mark_common(&mut self, id: owned::Id) {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let flags = self.graph.flags(&id);
+            if flags & COMMON != 0 {
+                continue;
+            }
+            self.graph.set(id, COMMON);
+            if flags & POPPED == 0 {
+                self.graph.non_common_revs = self.graph.non_common_revs.saturating_sub(1);
+            }
+            for parent in self.graph.parents_of(&id) {
+                if self.graph.flags(&parent) & (SEEN | COMMON) != 0 {
+                    stack.push(parent);
+                }
+            }
+        }
+    }
+
+    /// Queue `parent` of the commit `id`, returning true if it wasn't popped already.
+    fn push_parent(&mut self, id: owned::Id, flags: u8, parent: owned::Id) -> bool {
+        let parent_flags = self.graph.flags(&parent);
+        if parent_flags & SEEN != 0 {
+            if parent_flags & POPPED != 0 {
+                return false;
+            }
+        } else if !self.graph.push(parent, 0) {
+            return false;
+        }
+        if flags & (COMMON | COMMON_REF) != 0 {
+            self.mark_common(parent);
+        } else {
+            let (original_ttl, ttl) = self.ttl.get(&id).copied().unwrap_or((0, 0));
+            let new_original_ttl = if ttl > 0 {
+                original_ttl
+            } else {
+                original_ttl.saturating_mul(3) / 2 + 1
+            };
+            let new_ttl = if ttl > 0 { ttl - 1 } else { new_original_ttl };
+            let parent_ttl = self.ttl.entry(parent).or_insert((0, 0));
+            if parent_ttl.0 < new_original_ttl {
+                *parent_ttl = (new_original_ttl, new_ttl);
+            }
+        }
+        true
+    }
+}
+
+impl<Find> Negotiate for Skipping<Find>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    fn add_tip(&mut self, id: owned::Id) {
+        if self.graph.flags(&id) & SEEN == 0 {
+            self.graph.push(id, 0);
+        }
+    }
+
+    fn known_common(&mut self, id: owned::Id) {
+        if self.graph.flags(&id) & SEEN == 0 {
+            self.graph.push(id, COMMON_REF);
+        }
+    }
+
+    fn next_have(&mut self) -> Option<owned::Id> {
+        loop {
+            let (id, flags) = self.graph.pop()?;
+            let ttl = self.ttl.get(&id).map(|t| t.1).unwrap_or(0);
+            let mut to_send = flags & COMMON == 0 && ttl == 0;
+            let mut parent_pushed = false;
+            for parent in self.graph.parents_of(&id) {
+                parent_pushed |= self.push_parent(id, flags, parent);
+            }
+            self.ttl.remove(&id);
+            if flags & COMMON == 0 && !parent_pushed {
+                to_send = true;
+            }
+            if to_send {
+                return Some(id);
+            }
+        }
+    }
+
+    fn in_common_with_remote(&mut self, id: owned::Id) -> bool {
+        let flags = self.graph.flags(&id);
+        if flags & SEEN == 0 {
+            return false;
+        }
+        self.mark_common(id);
+        flags & COMMON != 0
+    }
+}
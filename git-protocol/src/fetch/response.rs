@@ -0,0 +1,133 @@
+use super::Error;
+use bstr::{BStr, BString, ByteSlice};
+use git_object::owned;
+
+/// The response of the server to a single `have` or to the end of a negotiation round.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Acknowledgement {
+    /// The server has the object as well, as sent by `ACK <id> common|continue` in V1 and `ACK <id>` in V2.
+    Common(owned::Id),
+    /// The server found enough common objects to send a pack, as sent by `ACK <id> ready` in V1 and `ready` in V2.
+    Ready(Option<owned::Id>),
+    /// The last common object in V1, sent as `ACK <id>` at the end of the negotiation or when multi-ack is not used.
+    Final(owned::Id),
+    /// No common object was found in this round, or none at all if the negotiation is done.
+    Nak,
+}
+
+impl Acknowledgement {
+    /// Return the id of the object the server has in common with us, if any.
+    pub fn id(&self) -> Option<&owned::Id> {
+        match self {
+            Acknowledgement::Common(id) | Acknowledgement::Final(id) | Acknowledgement::Ready(Some(id)) => Some(id),
+            Acknowledgement::Ready(None) | Acknowledgement::Nak => None,
+        }
+    }
+
+    /// Parse a V1 acknowledgement `line`, which is expected to be without trailing newline.
+    pub fn from_v1_line(line: &BStr) -> Result<Self, Error> {
+        if line == "NAK" {
+            return Ok(Acknowledgement::Nak);
+        }
+        let mut tokens = line.splitn_str(3, " ");
+        match (tokens.next(), tokens.next(), tokens.next()) {
+            (Some(b"ACK"), Some(id), status) => {
+                let id = parse_id(id)?;
+                Ok(match status {
+                    None => Acknowledgement::Final(id),
+                    Some(b"common") | Some(b"continue") => Acknowledgement::Common(id),
+                    Some(b"ready") => Acknowledgement::Ready(Some(id)),
+                    Some(_) => return Err(Error::UnknownLine(line.to_owned())),
+                })
+            }
+            _ => Err(Error::UnknownLine(line.to_owned())),
+        }
+    }
+
+    /// Parse a V2 line of the `acknowledgments` section, which is expected to be without trailing newline.
+    pub fn from_v2_line(line: &BStr) -> Result<Self, Error> {
+        if line == "NAK" {
+            return Ok(Acknowledgement::Nak);
+        }
+        if line == "ready" {
+            return Ok(Acknowledgement::Ready(None));
+        }
+        match line.strip_prefix(b"ACK ") {
+            Some(id) => Ok(Acknowledgement::Common(parse_id(id)?)),
+            None => Err(Error::UnknownLine(line.to_owned())),
+        }
+    }
+}
+
+/// A change to the shallow boundary of the local repository, sent by the server if `deepen` arguments were used.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShallowUpdate {
+    /// The commit becomes shallow, its parents will not be sent.
+    Shallow(owned::Id),
+    /// The commit is not shallow anymore and its parents will be sent.
+    Unshallow(owned::Id),
+}
+
+impl ShallowUpdate {
+    /// Parse a `shallow <id>` or `unshallow <id>` `line`, which is expected to be without trailing newline.
+    pub fn from_line(line: &BStr) -> Result<Self, Error> {
+        let mut tokens = line.splitn_str(2, " ");
+        match (tokens.next(), tokens.next()) {
+            (Some(b"shallow"), Some(id)) => Ok(ShallowUpdate::Shallow(parse_id(id)?)),
+            (Some(b"unshallow"), Some(id)) => Ok(ShallowUpdate::Unshallow(parse_id(id)?)),
+            _ => Err(Error::UnknownLine(line.to_owned())),
+        }
+    }
+}
+
+/// A ref asked for with [`want_ref()`][super::Arguments::want_ref()] and the object it points to on the server, as
+/// sent in the V2 `wanted-refs` section.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct WantedRef {
+    pub id: owned::Id,
+    pub path: BString,
+}
+
+impl WantedRef {
+    /// Parse a `<id> <path>` `line` of the `wanted-refs` section, which is expected to be without trailing newline.
+    pub fn from_line(line: &BStr) -> Result<Self, Error> {
+        let mut tokens = line.splitn_str(2, " ");
+        match (tokens.next(), tokens.next()) {
+            (Some(id), Some(path)) if !path.is_empty() => Ok(WantedRef {
+                id: parse_id(id)?,
+                path: path.into(),
+            }),
+            _ => Err(Error::UnknownLine(line.to_owned())),
+        }
+    }
+}
+
+/// Information about the negotiation, accumulated over all rounds.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Response {
+    /// All acknowledgements in the order the server sent them.
+    pub acknowledgements: Vec<Acknowledgement>,
+    /// Changes to the shallow boundary, only sent if `deepen` arguments were used.
+    pub shallow_updates: Vec<ShallowUpdate>,
+    /// The objects the refs asked for by name point to, only sent if `want-ref` arguments were used.
+    pub wanted_refs: Vec<WantedRef>,
+    /// The amount of negotiation rounds, including the one ending with `done`.
+    pub rounds: usize,
+    /// True if a pack was received. It is always sent unless nothing is wanted.
+    pub has_pack: bool,
+}
+
+impl Response {
+    /// Return all objects the server has in common with us.
+    pub fn common(&self) -> impl Iterator<Item = &owned::Id> {
+        self.acknowledgements.iter().filter_map(|a| a.id())
+    }
+}
+
+fn parse_id(hex: &[u8]) -> Result<owned::Id, Error> {
+    owned::Id::from_40_bytes_in_hex(hex).map_err(|_| Error::Id(hex.as_bstr().to_owned()))
+}
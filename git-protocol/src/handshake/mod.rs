@@ -0,0 +1,197 @@
+//! Parse the initial response of the server to learn about its protocol version, capabilities and refs.
+use crate::Capabilities;
+use bstr::{BStr, BString};
+use git_object::{owned, HashKind};
+use git_transport::{packetline, Protocol};
+use quick_error::quick_error;
+use std::io;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred while reading the server response")
+            from()
+            source(err)
+        }
+        PacketLine(err: packetline::decode::Error) {
+            display("A packet line could not be decoded")
+            from()
+            source(err)
+        }
+        Capabilities(err: crate::capabilities::Error) {
+            display("The capabilities could not be parsed")
+            from()
+            source(err)
+        }
+        Id(hex: BString) {
+            display("'{}' could not be parsed as an object id", hex)
+        }
+        MalformedSymref(symref: BString) {
+            display("'{}' could not be parsed. A symref is expected to look like <NAME>:<target>.", symref)
+        }
+        MalformedV1RefLine(line: String) {
+            display("'{}' could not be parsed. A V1 ref line should be '<hex-hash> <path>'.", line)
+        }
+        MalformedV2RefLine(line: String) {
+            display("'{}' could not be parsed. A V2 ref line should be '<hex-hash> <path>[ (peeled|symref-target):<value>'.", line)
+        }
+        UnknownAttribute(attribute: String, line: String) {
+            display("The ref attribute '{}' is unknown. Found in line '{}'", attribute, line)
+        }
+        InvariantViolation(message: &'static str) {
+            display("{}", message)
+        }
+        UnsupportedObjectFormat(name: BString) {
+            display("The object format '{}' is not supported", name)
+        }
+        EmptyResponse {
+            display("The server did not send any data")
+        }
+    }
+}
+
+/// A git reference, as advertised by the server.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Ref {
+    /// A ref pointing to a `tag` object, which in turns points to an `object`, usually a commit.
+    Peeled {
+        path: BString,
+        tag: owned::Id,
+        object: owned::Id,
+    },
+    /// A ref pointing to a commit object.
+    Direct { path: BString, object: owned::Id },
+    /// A symbolic ref pointing to `target` ref, which in turn points to an `object`.
+    Symbolic {
+        path: BString,
+        target: BString,
+        object: owned::Id,
+    },
+    /// A symbolic ref pointing to `target` which doesn't exist yet, as in a repository without commits.
+    ///
+    /// Only sent by V2 servers when asked for unborn refs.
+    Unborn { path: BString, target: BString },
+}
+
+impl Ref {
+    /// Return the path of this ref and the object it ultimately points to, if it's not unborn.
+    pub fn unpack(&self) -> (&BStr, Option<&owned::Id>) {
+        match self {
+            Ref::Direct { path, object } | Ref::Peeled { path, object, .. } | Ref::Symbolic { path, object, .. } => {
+                (path.as_ref(), Some(object))
+            }
+            Ref::Unborn { path, .. } => (path.as_ref(), None),
+        }
+    }
+}
+
+/// The result of parsing the initial response of the server.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The protocol version the server responded with, which might differ from the one we asked for.
+    pub server_protocol_version: Protocol,
+    /// The refs advertised by V1 servers, or `None` for V2 servers which send refs only when asked with `ls-refs`.
+    pub refs: Option<Vec<Ref>>,
+    /// The capabilities of the server.
+    pub capabilities: Capabilities,
+    /// The kind of hash the server uses, as indicated by the `object-format` capability.
+    pub object_hash: HashKind,
+    /// Commits whose parents are not available on a shallow V1 server.
+    pub shallow_commits: Vec<owned::Id>,
+}
+
+impl Outcome {
+    /// The response of a V1 server for a repository without any refs, which also advertises no capabilities.
+    fn empty_v1() -> Self {
+        Outcome {
+            server_protocol_version: Protocol::V1,
+            refs: Some(Vec::new()),
+            capabilities: Capabilities::default(),
+            object_hash: HashKind::Sha1,
+            shallow_commits: Vec::new(),
+        }
+    }
+}
+
+pub mod refs;
+
+/// Parse the server response from `lines`, which are expected to stop at the first flush packet.
+///
+/// The response may be a V1 ref advertisement, optionally preceded by a `version 1` line, or a V2 capability
+/// advertisement. The `lines` can be reset and reused afterwards to continue the conversation.
+pub fn parse<R: io::Read>(lines: &mut packetline::Provider<R>) -> Result<Outcome, Error> {
+    let first_line = match lines.peek_line() {
+        Some(line) => Some(line??.to_text().map(|t| t.to_owned())),
+        None => None,
+    };
+    let first_line = match first_line {
+        Some(line) => line,
+        None if lines.stopped_at().is_some() => return Ok(Outcome::empty_v1()),
+        None => return Err(Error::EmptyResponse),
+    };
+    let first_line = first_line.ok_or(Error::InvariantViolation("the first line must carry data"))?;
+
+    let outcome = if first_line == "version 2" {
+        let mut v2_lines = Vec::new();
+        while let Some(line) = lines.next_line() {
+            if let Some(text) = line??.to_text() {
+                v2_lines.push(text.to_owned());
+            }
+        }
+        let capabilities = Capabilities::from_lines(v2_lines.iter().map(|l| l.as_ref()))?;
+        Outcome {
+            server_protocol_version: Protocol::V2,
+            refs: None,
+            object_hash: object_hash(&capabilities)?,
+            capabilities,
+            shallow_commits: Vec::new(),
+        }
+    } else {
+        if first_line == "version 1" {
+            lines.next_line().expect("peeked line")??;
+            if lines.peek_line().is_none() && lines.stopped_at().is_some() {
+                return Ok(Outcome::empty_v1());
+            }
+        }
+        let (refs, capabilities, shallow_commits) = refs::from_v1_refs_received_as_part_of_handshake(lines)?;
+        Outcome {
+            server_protocol_version: Protocol::V1,
+            refs: Some(refs),
+            object_hash: object_hash(&capabilities)?,
+            capabilities,
+            shallow_commits,
+        }
+    };
+    Ok(outcome)
+}
+
+/// Like [`parse()`], but reads the server response from asynchronous `lines`.
+///
+/// The response is buffered in memory before parsing it, which is fine as it ends at the first flush packet.
+#[cfg(feature = "async-client")]
+pub async fn parse_async<R: futures_io::AsyncRead + Unpin>(
+    lines: &mut packetline::async_io::Provider<R>,
+) -> Result<Outcome, Error> {
+    let mut buf = Vec::new();
+    while let Some(line) = lines.next_line().await {
+        line??.write_to(&mut buf)?;
+    }
+    if let Some(stopped_at) = lines.stopped_at() {
+        stopped_at.write_to(&mut buf)?;
+    }
+    parse(&mut packetline::Provider::new(
+        buf.as_slice(),
+        &[packetline::PacketLine::Flush],
+    ))
+}
+
+fn object_hash(capabilities: &Capabilities) -> Result<HashKind, Error> {
+    match capabilities.capability("object-format").and_then(|c| c.value()) {
+        None => Ok(HashKind::Sha1),
+        Some(name) if name == "sha1" => Ok(HashKind::Sha1),
+        Some(name) => Err(Error::UnsupportedObjectFormat(name.to_owned())),
+    }
+}
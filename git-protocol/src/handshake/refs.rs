@@ -0,0 +1,161 @@
+//! Parse refs as advertised by V1 servers or returned by the V2 `ls-refs` command.
+use super::{Error, Ref};
+use crate::Capabilities;
+use bstr::{BString, ByteSlice};
+use git_object::owned;
+use git_transport::packetline;
+use std::io;
+
+fn parse_id(hex: &[u8]) -> Result<owned::Id, Error> {
+    owned::Id::from_40_bytes_in_hex(hex).map_err(|_| Error::Id(hex.as_bstr().to_owned()))
+}
+
+/// Parse a `symref=<name>:<target>` capability value into its name and target.
+fn parse_symref(value: &[u8]) -> Result<(BString, BString), Error> {
+    let mut fields = value.splitn(2, |b| *b == b':');
+    match (fields.next(), fields.next()) {
+        (Some(name), Some(target)) if !name.is_empty() && !target.is_empty() => {
+            Ok((name.as_bstr().to_owned(), target.as_bstr().to_owned()))
+        }
+        _ => Err(Error::MalformedSymref(value.as_bstr().to_owned())),
+    }
+}
+
+/// Parse a single ref line `<hex-hash> <path>` of the V1 ref advertisement, adding it to `out`.
+///
+/// Lines for peeled tags, ending in `^{}`, turn the previous ref into a `Ref::Peeled`. Lines starting with `shallow `
+/// are added to `shallow_commits`.
+fn parse_v1_line(line: &[u8], out: &mut Vec<Ref>, shallow_commits: &mut Vec<owned::Id>) -> Result<(), Error> {
+    let trimmed = line.trim_end_with(|c| c == '\n');
+    if let Some(hex) = trimmed.strip_prefix(b"shallow ") {
+        shallow_commits.push(parse_id(hex)?);
+        return Ok(());
+    }
+    let mut fields = trimmed.splitn(2, |b| *b == b' ');
+    let (hex, path) = match (fields.next(), fields.next()) {
+        (Some(hex), Some(path)) if !path.is_empty() => (hex, path),
+        _ => return Err(Error::MalformedV1RefLine(trimmed.to_str_lossy().into_owned())),
+    };
+    let id = parse_id(hex)?;
+    match path.strip_suffix(b"^{}") {
+        Some(stripped) => match out.pop() {
+            Some(Ref::Direct { path, object }) if path == stripped => out.push(Ref::Peeled {
+                path,
+                tag: object,
+                object: id,
+            }),
+            _ => {
+                return Err(Error::InvariantViolation(
+                    "a peeled ref must follow the ref it peels and may not be peeled twice",
+                ))
+            }
+        },
+        None => out.push(Ref::Direct {
+            path: path.as_bstr().to_owned(),
+            object: id,
+        }),
+    }
+    Ok(())
+}
+
+/// Parse the V1 ref advertisement from `lines`, including the capabilities in the first line.
+///
+/// Returns the refs, the capabilities and the shallow commits advertised by the server.
+pub fn from_v1_refs_received_as_part_of_handshake<R: io::Read>(
+    lines: &mut packetline::Provider<R>,
+) -> Result<(Vec<Ref>, Capabilities, Vec<owned::Id>), Error> {
+    let mut out = Vec::new();
+    let mut shallow_commits = Vec::new();
+    let capabilities = {
+        let first_line = lines.next_line().ok_or(Error::EmptyResponse)???;
+        let first_line = first_line
+            .as_slice()
+            .ok_or(Error::InvariantViolation("the first line must carry data"))?;
+        let (capabilities, delimiter_position) = Capabilities::from_bytes(first_line)?;
+        let ref_line = &first_line[..delimiter_position];
+        if !ref_line.ends_with(b" capabilities^{}") {
+            parse_v1_line(ref_line, &mut out, &mut shallow_commits)?;
+        }
+        capabilities
+    };
+
+    while let Some(line) = lines.next_line() {
+        let line = line??;
+        let line = line
+            .as_slice()
+            .ok_or(Error::InvariantViolation("ref lines must carry data"))?;
+        parse_v1_line(line, &mut out, &mut shallow_commits)?;
+    }
+
+    for symref in capabilities.iter().filter(|c| c.name() == "symref") {
+        let (name, target) = parse_symref(symref.value().unwrap_or_default())?;
+        if let Some(r) = out
+            .iter_mut()
+            .find(|r| matches!(r, Ref::Direct { path, .. } if *path == name))
+        {
+            if let Ref::Direct { path, object } = r {
+                *r = Ref::Symbolic {
+                    path: std::mem::take(path),
+                    target,
+                    object: *object,
+                };
+            }
+        }
+    }
+    Ok((out, capabilities, shallow_commits))
+}
+
+/// Parse a single line of the V2 `ls-refs` output.
+pub fn parse_v2_line(line: &[u8]) -> Result<Ref, Error> {
+    let trimmed = line.trim_end_with(|c| c == '\n');
+    let malformed = || Error::MalformedV2RefLine(trimmed.to_str_lossy().into_owned());
+    let mut fields = trimmed.split(|b| *b == b' ');
+    let (hex, path) = match (fields.next(), fields.next()) {
+        (Some(hex), Some(path)) if !path.is_empty() => (hex, path.as_bstr().to_owned()),
+        _ => return Err(malformed()),
+    };
+    let mut target = None;
+    let mut peeled = None;
+    for attribute in fields {
+        let mut tokens = attribute.splitn(2, |b| *b == b':');
+        match (tokens.next(), tokens.next()) {
+            (Some(b"symref-target"), Some(value)) => target = Some(value.as_bstr().to_owned()),
+            (Some(b"peeled"), Some(value)) => peeled = Some(parse_id(value)?),
+            _ => {
+                return Err(Error::UnknownAttribute(
+                    attribute.to_str_lossy().into_owned(),
+                    trimmed.to_str_lossy().into_owned(),
+                ))
+            }
+        }
+    }
+    if hex == b"unborn" {
+        return Ok(Ref::Unborn {
+            path,
+            target: target.ok_or_else(malformed)?,
+        });
+    }
+    let id = parse_id(hex)?;
+    Ok(match (target, peeled) {
+        (Some(target), _) => Ref::Symbolic {
+            path,
+            target,
+            object: id,
+        },
+        (None, Some(object)) => Ref::Peeled { path, tag: id, object },
+        (None, None) => Ref::Direct { path, object: id },
+    })
+}
+
+/// Parse all refs as returned by the V2 `ls-refs` command, until a flush packet is encountered in `lines`.
+pub fn from_v2_refs<R: io::Read>(lines: &mut packetline::Provider<R>) -> Result<Vec<Ref>, Error> {
+    let mut out = Vec::new();
+    while let Some(line) = lines.next_line() {
+        let line = line??;
+        let line = line
+            .as_slice()
+            .ok_or(Error::InvariantViolation("ref lines must carry data"))?;
+        out.push(parse_v2_line(line)?);
+    }
+    Ok(out)
+}
@@ -1 +1,30 @@
+//! An abstraction over the git protocols used to fetch and push packs, built on top of a transport.
 #![forbid(unsafe_code)]
+
+pub use git_transport as transport;
+
+pub mod bundle_uri;
+#[doc(inline)]
+pub use bundle_uri::bundle_uri;
+
+pub mod capabilities;
+#[doc(inline)]
+pub use capabilities::Capabilities;
+
+pub mod handshake;
+
+pub mod fetch;
+
+pub mod ls_refs;
+#[doc(inline)]
+pub use ls_refs::{ls_refs, ls_remote};
+
+pub mod object_info;
+#[doc(inline)]
+pub use object_info::object_info;
+
+pub mod push;
+
+pub mod remote_progress;
+#[doc(inline)]
+pub use remote_progress::RemoteProgress;
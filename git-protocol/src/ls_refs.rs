@@ -0,0 +1,121 @@
+//! List the refs of a remote, using the V2 `ls-refs` command to only receive the refs of interest.
+//!
+//! With V1 the server advertises all of its refs during the handshake, which are filtered afterwards.
+use crate::{handshake, handshake::Ref, Capabilities};
+use bstr::{BString, ByteSlice};
+use git_transport::{
+    client::{self, MessageKind, WriteMode},
+    Protocol, Service,
+};
+use quick_error::quick_error;
+use std::io::{self, Write};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred while talking to the server")
+            from()
+            source(err)
+        }
+        Transport(err: client::Error) {
+            display("The request could not be sent")
+            from()
+            source(err)
+        }
+        Handshake(err: handshake::Error) {
+            display("The server response could not be understood")
+            from()
+            source(err)
+        }
+        Unsupported {
+            display("The server does not support the 'ls-refs' command")
+        }
+    }
+}
+
+/// Configure which refs to list and what to know about them.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Options {
+    /// Only list refs whose name starts with one of these prefixes, like `refs/heads/`, or all refs if empty.
+    pub prefixes: Vec<BString>,
+    /// If true, the targets of symbolic refs like `HEAD` are listed as well.
+    pub symrefs: bool,
+    /// If true, annotated tags are listed along with the object they point to.
+    pub peel: bool,
+    /// If true, symbolic refs pointing to refs that don't exist yet are listed as well, if the server supports it.
+    pub unborn: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            prefixes: Vec::new(),
+            symrefs: true,
+            peel: true,
+            unborn: false,
+        }
+    }
+}
+
+/// Send the `ls-refs` command to the V2 server behind `transport`, which advertised `capabilities` during the
+/// handshake, and return the listed refs.
+pub fn ls_refs<T: client::Transport + ?Sized>(
+    transport: &mut T,
+    capabilities: &Capabilities,
+    options: &Options,
+) -> Result<Vec<Ref>, Error> {
+    let ls_refs = capabilities.capability("ls-refs").ok_or(Error::Unsupported)?;
+    let mut writer = transport.request(WriteMode::OneLfTerminatedLinePerWriteCall, MessageKind::Flush)?;
+    writer.write_all(b"command=ls-refs")?;
+    if capabilities.contains("agent") {
+        writer.write_all(format!("agent=git/oxide-{}", env!("CARGO_PKG_VERSION")).as_bytes())?;
+    }
+    if let Some(format) = capabilities.capability("object-format").and_then(|c| c.value()) {
+        let mut cap = BString::from("object-format=");
+        cap.extend_from_slice(format);
+        writer.write_all(&cap)?;
+    }
+    writer.write_message(MessageKind::Delimiter)?;
+    if options.symrefs {
+        writer.write_all(b"symrefs")?;
+    }
+    if options.peel {
+        writer.write_all(b"peel")?;
+    }
+    if options.unborn && ls_refs.supports("unborn").unwrap_or(false) {
+        writer.write_all(b"unborn")?;
+    }
+    for prefix in &options.prefixes {
+        let mut arg = BString::from("ref-prefix ");
+        arg.extend_from_slice(prefix);
+        writer.write_all(&arg)?;
+    }
+    let lines = writer.into_read()?;
+    Ok(handshake::refs::from_v2_refs(lines)?)
+}
+
+/// Perform a handshake with the `upload-pack` service behind `transport` and list its refs according to `options`.
+///
+/// V2 servers are asked for the refs with `ls-refs`, while the refs advertised by V1 servers are filtered to look
+/// the same. The connection is closed afterwards.
+pub fn ls_remote<T: client::Transport + ?Sized>(transport: &mut T, options: &Options) -> Result<Vec<Ref>, Error> {
+    let outcome = handshake::parse(transport.handshake(Service::UploadPack)?)?;
+    let refs = match (outcome.server_protocol_version, outcome.refs) {
+        (Protocol::V1, Some(refs)) => refs
+            .into_iter()
+            .filter(|r| {
+                let (path, _) = r.unpack();
+                options.prefixes.is_empty() || options.prefixes.iter().any(|p| path.starts_with(p.as_bytes()))
+            })
+            .map(|r| match r {
+                Ref::Peeled { path, tag, .. } if !options.peel => Ref::Direct { path, object: tag },
+                Ref::Symbolic { path, object, .. } if !options.symrefs => Ref::Direct { path, object },
+                r => r,
+            })
+            .collect(),
+        _ => ls_refs(transport, &outcome.capabilities, options)?,
+    };
+    transport.close()?;
+    Ok(refs)
+}
@@ -0,0 +1,105 @@
+//! Learn about objects of a remote without fetching them, using the V2 `object-info` command.
+use crate::Capabilities;
+use bstr::{BString, ByteSlice};
+use git_object::owned;
+use git_transport::{
+    client::{self, MessageKind, WriteMode},
+    packetline,
+};
+use quick_error::quick_error;
+use std::io::{self, Write};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred while talking to the server")
+            from()
+            source(err)
+        }
+        Transport(err: client::Error) {
+            display("The request could not be sent")
+            from()
+            source(err)
+        }
+        PacketLine(err: packetline::decode::Error) {
+            display("A packet line could not be decoded")
+            from()
+            source(err)
+        }
+        Unsupported {
+            display("The server does not support the 'object-info' command or the 'size' attribute")
+        }
+        MalformedLine(line: BString) {
+            display("Could not parse object info line {:?}", line)
+        }
+    }
+}
+
+/// What the server told us about one of the requested objects.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Info {
+    pub id: owned::Id,
+    /// The size of the object's data in bytes, or `None` if the server doesn't have the object.
+    pub size: Option<u64>,
+}
+
+/// Send the `object-info` command to the V2 server behind `transport`, which advertised `capabilities` during the
+/// handshake, and return the sizes of the objects with `ids` in the order the server lists them.
+pub fn object_info<T: client::Transport + ?Sized>(
+    transport: &mut T,
+    capabilities: &Capabilities,
+    ids: &[owned::Id],
+) -> Result<Vec<Info>, Error> {
+    let object_info = capabilities.capability("object-info").ok_or(Error::Unsupported)?;
+    if object_info.supports("size") == Some(false) {
+        return Err(Error::Unsupported);
+    }
+    let mut writer = transport.request(WriteMode::OneLfTerminatedLinePerWriteCall, MessageKind::Flush)?;
+    writer.write_all(b"command=object-info")?;
+    if capabilities.contains("agent") {
+        writer.write_all(format!("agent=git/oxide-{}", env!("CARGO_PKG_VERSION")).as_bytes())?;
+    }
+    if let Some(format) = capabilities.capability("object-format").and_then(|c| c.value()) {
+        let mut cap = BString::from("object-format=");
+        cap.extend_from_slice(format);
+        writer.write_all(&cap)?;
+    }
+    writer.write_message(MessageKind::Delimiter)?;
+    writer.write_all(b"size")?;
+    for id in ids {
+        writer.write_all(format!("oid {}", id).as_bytes())?;
+    }
+    let lines = writer.into_read()?;
+
+    let mut out = Vec::with_capacity(ids.len());
+    let mut attributes_seen = false;
+    while let Some(line) = lines.next_line() {
+        let line = line??;
+        let line = match line.as_slice() {
+            Some(line) => line.trim_end_with(|c| c == '\n'),
+            None => continue,
+        };
+        if !attributes_seen {
+            if line != b"size" {
+                return Err(Error::MalformedLine(line.into()));
+            }
+            attributes_seen = true;
+            continue;
+        }
+        out.push(parse_line(line).ok_or_else(|| Error::MalformedLine(line.into()))?);
+    }
+    Ok(out)
+}
+
+/// Parse `<id> <size>`, where the size is empty for objects the server doesn't have.
+fn parse_line(line: &[u8]) -> Option<Info> {
+    let mut tokens = line.splitn(2, |b| *b == b' ');
+    let id = owned::Id::from_40_bytes_in_hex(tokens.next()?).ok()?;
+    let size = match tokens.next().unwrap_or_default() {
+        b"" => None,
+        size => Some(size.to_str().ok()?.parse().ok()?),
+    };
+    Some(Info { id, size })
+}
@@ -0,0 +1,128 @@
+use crate::handshake;
+use bstr::BString;
+use git_transport::Protocol;
+
+/// The capabilities to enable for a push, which are sent along with the first command.
+///
+/// Features are only enabled if the server supports them, as learned from the capabilities of the handshake.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Arguments {
+    version: Protocol,
+    capabilities: Vec<BString>,
+    push_options: Vec<BString>,
+    report_status: bool,
+    sideband: bool,
+    supports_delete: bool,
+    supports_quiet: bool,
+    supports_atomic: bool,
+    supports_push_options: bool,
+    supports_thin_pack: bool,
+}
+
+impl Arguments {
+    /// Create new arguments for a `receive-pack` server that responded to the handshake with `server`.
+    pub fn new(server: &handshake::Outcome) -> Self {
+        let caps = &server.capabilities;
+        let mut capabilities = Vec::new();
+        let mut use_if_supported = |name: &str| {
+            if caps.contains(name) {
+                capabilities.push(BString::from(name));
+                true
+            } else {
+                false
+            }
+        };
+        let report_status = use_if_supported("report-status-v2") || use_if_supported("report-status");
+        let sideband = use_if_supported("side-band-64k") || use_if_supported("side-band");
+        if caps.contains("agent") {
+            capabilities.push(format!("agent=git/oxide-{}", env!("CARGO_PKG_VERSION")).into());
+        }
+        if let Some(format) = caps.capability("object-format").and_then(|c| c.value()) {
+            let mut cap = BString::from("object-format=");
+            cap.extend_from_slice(format);
+            capabilities.push(cap);
+        }
+        Arguments {
+            version: server.server_protocol_version,
+            capabilities,
+            push_options: Vec::new(),
+            report_status,
+            sideband,
+            supports_delete: caps.contains("delete-refs"),
+            supports_quiet: caps.contains("quiet"),
+            supports_atomic: caps.contains("atomic"),
+            supports_push_options: caps.contains("push-options"),
+            supports_thin_pack: !caps.contains("no-thin"),
+        }
+    }
+
+    /// Return the protocol version the server responded with.
+    pub fn version(&self) -> Protocol {
+        self.version
+    }
+
+    /// Return the capabilities to send to the server.
+    pub fn capabilities(&self) -> &[BString] {
+        &self.capabilities
+    }
+
+    /// Returns true if the server reports the outcome of unpacking and of each ref update.
+    pub fn uses_report_status(&self) -> bool {
+        self.report_status
+    }
+
+    /// Returns true if the status report and progress messages are multiplexed using side-bands.
+    pub fn uses_sideband(&self) -> bool {
+        self.sideband
+    }
+
+    /// Returns true if the server allows deleting refs.
+    pub fn can_delete(&self) -> bool {
+        self.supports_delete
+    }
+
+    /// Returns true if the pack may contain deltas against objects the server has, which all servers accept unless
+    /// they advertise `no-thin`.
+    pub fn can_send_thin_pack(&self) -> bool {
+        self.supports_thin_pack
+    }
+
+    /// Ask the server to not send progress messages, returning false if it doesn't support that.
+    pub fn quiet(&mut self) -> bool {
+        if self.supports_quiet {
+            self.capabilities.push("quiet".into());
+        }
+        self.supports_quiet
+    }
+
+    /// Ask the server to apply all ref updates or none of them, returning false if it doesn't support that.
+    pub fn atomic(&mut self) -> bool {
+        if self.supports_atomic {
+            self.capabilities.push("atomic".into());
+        }
+        self.supports_atomic
+    }
+
+    /// Send `option` to the server for its hooks to interpret, like `ci.skip`, returning false if it doesn't
+    /// support push options.
+    pub fn push_option(&mut self, option: impl Into<BString>) -> bool {
+        if !self.supports_push_options {
+            return false;
+        }
+        if self.push_options.is_empty() {
+            self.capabilities.push("push-options".into());
+        }
+        self.push_options.push(option.into());
+        true
+    }
+
+    /// Return the push options to send after the commands.
+    pub fn push_options(&self) -> &[BString] {
+        &self.push_options
+    }
+
+    /// Add the capability `feature` as is.
+    pub fn add_feature(&mut self, feature: impl Into<BString>) {
+        self.capabilities.push(feature.into());
+    }
+}
@@ -0,0 +1,184 @@
+//! Send ref updates to `git-receive-pack` along with the pack containing the objects the server lacks.
+//!
+//! The server reports whether it could unpack the objects and the outcome of each ref update if `report-status` or
+//! `report-status-v2` is supported. Only V1 is used for pushing, as there is no V2 `receive-pack` command.
+use crate::remote_progress;
+use bstr::BString;
+use git_object::owned;
+use git_transport::{
+    client::{self, MessageKind, WriteMode},
+    packetline, Protocol,
+};
+use quick_error::quick_error;
+use std::io::{self, Write};
+
+mod arguments;
+pub use arguments::Arguments;
+
+mod response;
+pub use response::{RefOptions, RefStatus, Report, Status};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred while talking to the server")
+            from()
+            source(err)
+        }
+        Transport(err: client::Error) {
+            display("The request could not be sent")
+            from()
+            source(err)
+        }
+        PacketLine(err: packetline::decode::Error) {
+            display("A packet line could not be decoded")
+            from()
+            source(err)
+        }
+        UnsupportedProtocolVersion(version: Protocol) {
+            display("Pushing is not possible with protocol {:?}", version)
+        }
+        DeleteUnsupported(name: BString) {
+            display("The ref '{}' cannot be deleted as the server doesn't support deleting refs", name)
+        }
+        Id(hex: BString) {
+            display("'{}' could not be parsed as an object id", hex)
+        }
+        UnknownLine(line: BString) {
+            display("The line '{}' of the status report could not be understood", line)
+        }
+        UnexpectedEndOfResponse {
+            display("The status report ended unexpectedly")
+        }
+        Remote(message: BString) {
+            display("The server aborted with: {}", message)
+        }
+    }
+}
+
+/// An instruction for the server to change the ref `name` from `old` to `new`.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Command {
+    /// The object the ref is expected to point to on the server, or the null id if it should be created.
+    pub old: owned::Id,
+    /// The object the ref should point to, or the null id if it should be deleted.
+    pub new: owned::Id,
+    /// The full name of the ref, like `refs/heads/main`.
+    pub name: BString,
+}
+
+impl Command {
+    /// Returns true if the ref is to be created.
+    pub fn is_create(&self) -> bool {
+        self.old == owned::Id::null_sha1()
+    }
+
+    /// Returns true if the ref is to be deleted.
+    pub fn is_delete(&self) -> bool {
+        self.new == owned::Id::null_sha1()
+    }
+
+    fn to_line(&self) -> BString {
+        let mut line = BString::from(self.old.to_sha1_hex().to_vec());
+        line.push(b' ');
+        line.extend_from_slice(&self.new.to_sha1_hex());
+        line.push(b' ');
+        line.extend_from_slice(&self.name);
+        line
+    }
+}
+
+/// Send `commands` to the server behind `transport`, followed by the pack written by `write_pack` unless all
+/// commands are deletions, and return the status report.
+///
+/// [Push options][Arguments::push_option()] are sent between the commands and the pack.
+///
+/// The `arguments` must be created from the handshake with the `receive-pack` service. Progress messages of the
+/// server are passed to `progress(is_error, text)`, and if the server sent an error message it is returned as
+/// [`Error::Remote`]. Nothing is sent if there are no commands, and the report is empty if the server doesn't support
+/// reporting the status.
+pub fn push<T, P, W>(
+    transport: &mut T,
+    arguments: &Arguments,
+    commands: &[Command],
+    mut progress: P,
+    write_pack: W,
+) -> Result<Report, Error>
+where
+    T: client::Transport + ?Sized,
+    P: FnMut(bool, &[u8]),
+    W: FnOnce(&mut dyn io::Write) -> io::Result<()>,
+{
+    if arguments.version() != Protocol::V1 {
+        return Err(Error::UnsupportedProtocolVersion(arguments.version()));
+    }
+    if commands.is_empty() {
+        return Ok(Report::default());
+    }
+    if let Some(command) = commands.iter().find(|c| c.is_delete() && !arguments.can_delete()) {
+        return Err(Error::DeleteUnsupported(command.name.clone()));
+    }
+
+    let mut writer = transport.request(WriteMode::OneLfTerminatedLinePerWriteCall, MessageKind::Flush)?;
+    for (index, command) in commands.iter().enumerate() {
+        let mut line = command.to_line();
+        if index == 0 {
+            line.push(0);
+            for (index, capability) in arguments.capabilities().iter().enumerate() {
+                if index > 0 {
+                    line.push(b' ');
+                }
+                line.extend_from_slice(capability);
+            }
+        }
+        writer.write_all(&line)?;
+    }
+    if !arguments.push_options().is_empty() {
+        writer.write_message(MessageKind::Flush)?;
+        for option in arguments.push_options() {
+            writer.write_all(option)?;
+        }
+    }
+    let lines = if commands.iter().all(Command::is_delete) {
+        writer.into_read()?
+    } else {
+        writer.into_read_with_raw_data(write_pack)?
+    };
+
+    let mut remote_error = None;
+    let mut progress = |is_err: bool, text: &[u8]| {
+        if is_err {
+            remote_progress::collect_error(&mut remote_error, text);
+        }
+        progress(is_err, text)
+    };
+    let report = if !arguments.uses_report_status() {
+        let drained = if arguments.uses_sideband() {
+            io::copy(&mut lines.as_read_with_sidebands(&mut progress), &mut io::sink()).map(|_| ())
+        } else {
+            Ok(())
+        };
+        drained.map(|()| Report::default()).map_err(Error::from)
+    } else {
+        lines.fail_on_err_lines(true);
+        if arguments.uses_sideband() {
+            let mut report = packetline::Provider::new(
+                lines.as_read_with_sidebands(&mut progress),
+                &[packetline::PacketLine::Flush],
+            );
+            Report::from_lines(&mut report).and_then(|outcome| {
+                // Consume the remaining side-band packets to see all messages and the final flush.
+                io::copy(report.inner_mut(), &mut io::sink())?;
+                Ok(outcome)
+            })
+        } else {
+            Report::from_lines(lines)
+        }
+    };
+    match remote_error {
+        Some(message) => Err(Error::Remote(message)),
+        None => report,
+    }
+}
@@ -0,0 +1,119 @@
+use super::Error;
+use bstr::{BStr, BString, ByteSlice};
+use git_object::owned;
+use git_transport::packetline;
+use std::io;
+
+/// The outcome of a single ref update as reported by the server.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Status {
+    /// The ref was updated, as reported by `ok <ref>`.
+    Ok,
+    /// The ref was not updated for the given reason, as reported by `ng <ref> <reason>`.
+    Rejected(BString),
+}
+
+/// Details about an update reported with `report-status-v2`, if the server changed it on the way, for example when
+/// the update was handled by a `proc-receive` hook.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct RefOptions {
+    /// The name of the ref that was actually updated.
+    pub refname: Option<BString>,
+    /// The object the ref pointed to before the update.
+    pub old: Option<owned::Id>,
+    /// The object the ref points to after the update.
+    pub new: Option<owned::Id>,
+    /// If true, the update was not a fast-forward.
+    pub forced_update: bool,
+}
+
+/// The reported outcome of updating one ref.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct RefStatus {
+    /// The full name of the ref as sent in the command.
+    pub name: BString,
+    /// Whether the ref was updated.
+    pub status: Status,
+    /// Additional details, only sent with `report-status-v2`.
+    pub options: RefOptions,
+}
+
+/// The status report of a push, which is empty if the server doesn't support `report-status`.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Report {
+    /// The reason for the pack not being unpacked, or `None` if it was unpacked successfully.
+    pub unpack_error: Option<BString>,
+    /// The outcome of each ref update, in the order sent by the server.
+    pub refs: Vec<RefStatus>,
+}
+
+impl Report {
+    /// Returns true if the pack was unpacked and all refs were updated.
+    pub fn is_success(&self) -> bool {
+        self.unpack_error.is_none() && self.refs.iter().all(|r| r.status == Status::Ok)
+    }
+
+    /// Return the outcome of the update of the ref called `name`, if it was reported.
+    pub fn status_of(&self, name: &BStr) -> Option<&RefStatus> {
+        self.refs.iter().find(|r| r.name == name)
+    }
+
+    /// Parse a `report-status` or `report-status-v2` report from `lines`, up to the next flush.
+    pub fn from_lines<R: io::Read>(lines: &mut packetline::Provider<R>) -> Result<Self, Error> {
+        let line = next_text_line(lines)?.ok_or(Error::UnexpectedEndOfResponse)?;
+        let unpack_error = match line.strip_prefix(b"unpack ") {
+            Some(b"ok") => None,
+            Some(reason) => Some(reason.as_bstr().to_owned()),
+            None => return Err(Error::UnknownLine(line)),
+        };
+        let mut refs = Vec::<RefStatus>::new();
+        while let Some(line) = next_text_line(lines)? {
+            let mut tokens = line.splitn_str(3, " ");
+            match (tokens.next(), tokens.next(), tokens.next()) {
+                (Some(b"ok"), Some(name), None) => refs.push(RefStatus {
+                    name: name.as_bstr().to_owned(),
+                    status: Status::Ok,
+                    options: RefOptions::default(),
+                }),
+                (Some(b"ng"), Some(name), reason) => refs.push(RefStatus {
+                    name: name.as_bstr().to_owned(),
+                    status: Status::Rejected(reason.unwrap_or_default().as_bstr().to_owned()),
+                    options: RefOptions::default(),
+                }),
+                (Some(b"option"), Some(key), value) => {
+                    let options = &mut refs.last_mut().ok_or_else(|| Error::UnknownLine(line.clone()))?.options;
+                    match (key, value) {
+                        (b"refname", Some(name)) => options.refname = Some(name.as_bstr().to_owned()),
+                        (b"old-oid", Some(hex)) => options.old = Some(parse_id(hex)?),
+                        (b"new-oid", Some(hex)) => options.new = Some(parse_id(hex)?),
+                        (b"forced-update", None) => options.forced_update = true,
+                        _ => return Err(Error::UnknownLine(line)),
+                    }
+                }
+                _ => return Err(Error::UnknownLine(line)),
+            }
+        }
+        Ok(Report { unpack_error, refs })
+    }
+}
+
+fn parse_id(hex: &[u8]) -> Result<owned::Id, Error> {
+    owned::Id::from_40_bytes_in_hex(hex).map_err(|_| Error::Id(hex.as_bstr().to_owned()))
+}
+
+fn next_text_line<R: io::Read>(lines: &mut packetline::Provider<R>) -> Result<Option<BString>, Error> {
+    match lines.next_line() {
+        Some(line) => {
+            let line = line??;
+            let text = line
+                .to_text()
+                .ok_or_else(|| Error::UnknownLine(line.as_slice().unwrap_or_default().into()))?;
+            Ok(Some(text.to_owned()))
+        }
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,107 @@
+//! Understand the progress messages sent by the server on side-band channel 2, and forward them to a [`Progress`].
+//!
+//! `git` formats them like `Counting objects:  33% (1/3)\r`, finishing each action with `, done.\n`. Multiple
+//! messages may be sent at once, so each line is handled separately.
+use bstr::{BStr, BString, ByteSlice};
+use git_features::progress::{unit, Progress};
+use std::fmt;
+
+/// A single progress message of the server.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub struct RemoteProgress<'a> {
+    /// The name of the action, like `Counting objects`.
+    pub action: &'a BStr,
+    /// The completion in percent, if the amount of work is known.
+    pub percent: Option<u32>,
+    /// The amount of work done so far.
+    pub step: Option<usize>,
+    /// The total amount of work, if known.
+    pub max: Option<usize>,
+}
+
+impl<'a> RemoteProgress<'a> {
+    /// Parse a single message `line` without line separators, or return `None` if it isn't formatted like progress.
+    pub fn from_bytes(line: &'a [u8]) -> Option<RemoteProgress<'a>> {
+        let colon = line.find_byte(b':')?;
+        let action = line[..colon].trim_with(char::is_whitespace);
+        if action.is_empty() {
+            return None;
+        }
+        let rest = line[colon + 1..].trim_start_with(char::is_whitespace);
+        let mut progress = RemoteProgress {
+            action: action.as_bstr(),
+            percent: None,
+            step: None,
+            max: None,
+        };
+        let (number, remainder) = parse_number(rest)?;
+        if let Some(remainder) = remainder.strip_prefix(b"%") {
+            progress.percent = Some(number as u32);
+            let counts = remainder.trim_start_with(char::is_whitespace).strip_prefix(b"(")?;
+            let (step, counts) = parse_number(counts)?;
+            let (max, _) = parse_number(counts.strip_prefix(b"/")?)?;
+            progress.step = Some(step);
+            progress.max = Some(max);
+        } else {
+            progress.step = Some(number);
+        }
+        Some(progress)
+    }
+
+    /// Forward the side-band message `text` to `progress`, with `is_error` indicating it was sent on the error
+    /// channel.
+    ///
+    /// Progress is set according to each message, and messages which finish an action or aren't formatted like
+    /// progress are logged as info. Errors are logged as failure.
+    pub fn translate_to_progress(is_error: bool, text: &[u8], progress: &mut impl Progress) {
+        for line in text
+            .split(|b| *b == b'\r' || *b == b'\n')
+            .map(|l| l.trim_with(char::is_whitespace))
+            .filter(|l| !l.is_empty())
+        {
+            if is_error {
+                progress.fail(line.to_str_lossy());
+                continue;
+            }
+            match RemoteProgress::from_bytes(line) {
+                Some(RemoteProgress {
+                    action,
+                    step: Some(step),
+                    max,
+                    ..
+                }) => {
+                    progress.init(max, Some(unit::dynamic(Action(action.to_str_lossy().into_owned()))));
+                    progress.set(step);
+                    if line.ends_with(b"done.") {
+                        progress.info(line.to_str_lossy());
+                    }
+                }
+                _ => progress.info(line.to_str_lossy()),
+            }
+        }
+    }
+}
+
+/// Append `text` received on the error side-band to the `message` so far.
+pub(crate) fn collect_error(message: &mut Option<BString>, text: &[u8]) {
+    let message = message.get_or_insert_with(BString::default);
+    if !message.is_empty() {
+        message.push(b'\n');
+    }
+    message.extend_from_slice(text.trim_end_with(char::is_whitespace));
+}
+
+fn parse_number(input: &[u8]) -> Option<(usize, &[u8])> {
+    let digits = input.iter().take_while(|b| b.is_ascii_digit()).count();
+    let number = input[..digits].to_str().ok()?.parse().ok()?;
+    Some((number, &input[digits..]))
+}
+
+/// Display the action of the server as unit, like `1/3 Counting objects`.
+struct Action(String);
+
+impl unit::DisplayValue for Action {
+    fn display_unit(&self, w: &mut dyn fmt::Write, _value: usize) -> fmt::Result {
+        w.write_str(&self.0)
+    }
+}
@@ -0,0 +1,195 @@
+use bstr::{BStr, ByteSlice};
+use git_protocol::{bundle_uri, handshake};
+use git_transport::{
+    client::{file, Transport},
+    Protocol, Service,
+};
+
+fn fixture_repo_path() -> String {
+    std::env::current_dir()
+        .unwrap()
+        .join("tests")
+        .join("fixtures")
+        .join("repos")
+        .join("linear.git")
+        .display()
+        .to_string()
+}
+
+fn list(pairs: &[(&str, &str)]) -> Result<bundle_uri::List, bundle_uri::Error> {
+    bundle_uri::List::from_pairs(
+        pairs
+            .iter()
+            .map(|(k, v)| (k.as_bytes().as_bstr(), v.as_bytes().as_bstr()))
+            .collect::<Vec<(&BStr, &BStr)>>(),
+    )
+}
+
+#[test]
+fn lists_are_parsed_from_key_value_pairs() -> Result<(), Box<dyn std::error::Error>> {
+    let list = list(&[
+        ("bundle.version", "1"),
+        ("bundle.mode", "any"),
+        ("bundle.heuristic", "creationToken"),
+        ("bundle.daily.uri", "https://cdn.example.com/daily.bundle"),
+        ("bundle.daily.creationToken", "2"),
+        ("bundle.base.uri", "base.bundle"),
+        ("bundle.base.creationtoken", "1"),
+        ("bundle.without-uri.creationToken", "3"),
+        ("unrelated.key", "value"),
+    ])?;
+    assert_eq!(list.mode, bundle_uri::Mode::Any);
+    assert_eq!(list.heuristic, Some("creationToken".into()));
+    assert_eq!(
+        list.bundles,
+        vec![
+            bundle_uri::Bundle {
+                id: "daily".into(),
+                uri: "https://cdn.example.com/daily.bundle".into(),
+                creation_token: Some(2),
+            },
+            bundle_uri::Bundle {
+                id: "base".into(),
+                uri: "base.bundle".into(),
+                creation_token: Some(1),
+            },
+        ]
+    );
+    assert_eq!(
+        list.sorted_bundles().iter().map(|b| b.id.as_bstr()).collect::<Vec<_>>(),
+        vec!["base", "daily"],
+        "bundles are applied in the order of their creation"
+    );
+    Ok(())
+}
+
+#[test]
+fn lists_without_supported_version_or_with_invalid_values_are_rejected() {
+    assert!(matches!(
+        list(&[("bundle.mode", "all")]),
+        Err(bundle_uri::Error::UnsupportedVersion(_))
+    ));
+    assert!(matches!(
+        list(&[("bundle.version", "2")]),
+        Err(bundle_uri::Error::UnsupportedVersion(_))
+    ));
+    assert!(matches!(
+        list(&[("bundle.version", "1"), ("bundle.mode", "some")]),
+        Err(bundle_uri::Error::InvalidValue(..))
+    ));
+    assert!(matches!(
+        list(&[("bundle.version", "1"), ("bundle.a.creationToken", "soon")]),
+        Err(bundle_uri::Error::InvalidValue(..))
+    ));
+}
+
+#[test]
+fn servers_not_advertising_it_do_not_support_it() -> Result<(), Box<dyn std::error::Error>> {
+    for version in &[Protocol::V1, Protocol::V2] {
+        let mut transport = file::connect(fixture_repo_path(), *version);
+        let outcome = handshake::parse(transport.handshake(Service::UploadPack)?)?;
+        assert!(matches!(
+            git_protocol::bundle_uri(&mut transport, &outcome.capabilities),
+            Err(bundle_uri::Error::Unsupported)
+        ));
+        transport.close()?;
+    }
+    Ok(())
+}
+
+fn resolve(base: &str, uri: &str) -> String {
+    bundle_uri::resolve_uri(Some(base), uri).expect("allowed uri")
+}
+
+#[test]
+fn uris_are_resolved_like_the_examples_of_rfc_3986() {
+    let base = "http://a/b/c/d;p?q";
+    for (uri, expected) in &[
+        ("g", "http://a/b/c/g"),
+        ("./g", "http://a/b/c/g"),
+        ("g/", "http://a/b/c/g/"),
+        ("/g", "http://a/g"),
+        ("//g", "http://g"),
+        ("?y", "http://a/b/c/d;p?y"),
+        ("g?y", "http://a/b/c/g?y"),
+        ("#s", "http://a/b/c/d;p?q#s"),
+        ("g#s", "http://a/b/c/g#s"),
+        (";x", "http://a/b/c/;x"),
+        ("", "http://a/b/c/d;p?q"),
+        (".", "http://a/b/c/"),
+        ("./", "http://a/b/c/"),
+        ("..", "http://a/b/"),
+        ("../g", "http://a/b/g"),
+        ("../..", "http://a/"),
+        ("../../g", "http://a/g"),
+        ("../../../g", "http://a/g"),
+        ("/./g", "http://a/g"),
+        ("/../g", "http://a/g"),
+        ("g.", "http://a/b/c/g."),
+        ("..g", "http://a/b/c/..g"),
+        ("./../g", "http://a/b/g"),
+        ("g/./h", "http://a/b/c/g/h"),
+        ("g/../h", "http://a/b/c/h"),
+        ("http:g", "http:g"),
+        ("http://other/list/../bundle", "http://other/bundle"),
+    ] {
+        assert_eq!(resolve(base, uri), *expected, "{}", uri);
+    }
+}
+
+#[test]
+fn paths_are_resolved_relative_to_the_directory_of_the_list() {
+    assert_eq!(resolve("/srv/bundles/list", "base.bundle"), "/srv/bundles/base.bundle");
+    assert_eq!(resolve("/srv/bundles/list", "../other.bundle"), "/srv/other.bundle");
+    assert_eq!(resolve("lists/list", "/srv/base.bundle"), "/srv/base.bundle");
+    assert_eq!(
+        resolve("file:///srv/bundles/list", "base.bundle"),
+        "file:///srv/bundles/base.bundle"
+    );
+    assert_eq!(resolve("HTTPS://a/list", "https://b/bundle"), "https://b/bundle");
+}
+
+#[test]
+fn schemes_other_than_the_one_of_the_list_are_refused() {
+    for uri in &[
+        "file:///etc/passwd",
+        "FILE:///etc/passwd",
+        "ssh://host/bundle",
+        "http://a/bundle",
+    ] {
+        assert!(
+            matches!(
+                bundle_uri::resolve_uri(Some("https://a/list"), uri),
+                Err(bundle_uri::Error::UriRefused(..))
+            ),
+            "{}",
+            uri
+        );
+    }
+    assert!(matches!(
+        bundle_uri::resolve_uri(Some("/srv/list"), "https://a/bundle"),
+        Err(bundle_uri::Error::UriRefused(..))
+    ));
+}
+
+#[test]
+fn advertised_lists_may_only_refer_to_http_uris() {
+    assert_eq!(
+        bundle_uri::resolve_uri(None, "https://cdn/bundle").expect("allowed"),
+        "https://cdn/bundle"
+    );
+    assert_eq!(
+        bundle_uri::resolve_uri(None, "http://cdn/bundle").expect("allowed"),
+        "http://cdn/bundle"
+    );
+    for uri in &["file:///etc/passwd", "/etc/passwd", "bundle", "ssh://host/bundle"] {
+        assert!(
+            matches!(
+                bundle_uri::resolve_uri(None, uri),
+                Err(bundle_uri::Error::UriRefused(..))
+            ),
+            "{}",
+            uri
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use bstr::ByteSlice;
+use git_protocol::{capabilities, Capabilities};
+
+#[test]
+fn v1_capabilities_follow_the_null_byte() -> Result<(), Box<dyn std::error::Error>> {
+    let (caps, pos) = Capabilities::from_bytes(b"7814e8a05a59c0cf5fb186661d1551c75d1299b5 HEAD\0multi_ack thin-pack symref=HEAD:refs/heads/master agent=git/2.28.0\n")?;
+    assert_eq!(pos, 45);
+    assert!(!caps.is_v2());
+    assert!(caps.contains("thin-pack"));
+    assert!(!caps.contains("thin"));
+    assert_eq!(
+        caps.iter()
+            .map(|c| c.name().to_str_lossy().into_owned())
+            .collect::<Vec<_>>(),
+        vec!["multi_ack", "thin-pack", "symref", "agent"]
+    );
+    let agent = caps.capability("agent").expect("present");
+    assert_eq!(agent.value().expect("value"), "git/2.28.0");
+    assert_eq!(caps.capability("multi_ack").expect("present").value(), None);
+    Ok(())
+}
+
+#[test]
+fn v1_capabilities_must_be_present() {
+    assert!(matches!(
+        Capabilities::from_bytes(b"hello"),
+        Err(capabilities::Error::MissingDelimitingNullByte)
+    ));
+    assert!(matches!(
+        Capabilities::from_bytes(b"hello\0"),
+        Err(capabilities::Error::NoCapabilities)
+    ));
+}
+
+#[test]
+fn v2_capabilities_are_lines_after_the_version() -> Result<(), Box<dyn std::error::Error>> {
+    let lines = [
+        "version 2",
+        "agent=git/2.39.5",
+        "ls-refs=unborn",
+        "fetch=shallow wait-for-done",
+        "server-option",
+    ];
+    let caps = Capabilities::from_lines(lines.iter().map(|l| l.as_bytes().as_bstr()))?;
+    assert!(caps.is_v2());
+    let fetch = caps.capability("fetch").expect("present");
+    assert_eq!(fetch.supports("shallow"), Some(true));
+    assert_eq!(fetch.supports("filter"), Some(false));
+    assert_eq!(caps.capability("server-option").expect("present").supports("x"), None);
+    assert_eq!(
+        fetch.values().expect("values").collect::<Vec<_>>(),
+        vec!["shallow".as_bytes().as_bstr(), "wait-for-done".as_bytes().as_bstr()]
+    );
+
+    assert!(matches!(
+        Capabilities::from_lines(vec!["version 1".as_bytes().as_bstr()]),
+        Err(capabilities::Error::UnsupportedVersion(2, _))
+    ));
+    assert!(matches!(
+        Capabilities::from_lines(vec!["agent=git".as_bytes().as_bstr()]),
+        Err(capabilities::Error::MalformattedVersionLine(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,321 @@
+use crate::fetch::linear;
+use bstr::ByteSlice;
+use git_object::owned;
+use git_protocol::{
+    fetch::{self, negotiate::Algorithm, Acknowledgement},
+    handshake, RemoteProgress,
+};
+use git_transport::{
+    client::{file, Transport},
+    Protocol, Service,
+};
+use std::{io, path::Path};
+
+fn fixture_repo_path() -> String {
+    std::env::current_dir()
+        .unwrap()
+        .join("tests")
+        .join("fixtures")
+        .join("repos")
+        .join("linear.git")
+        .display()
+        .to_string()
+}
+
+/// Fetch the tip of the `linear.git` fixture with a local history of `local_commits` out of its 5 commits.
+fn fetch_tip(
+    version: Protocol,
+    algorithm: Algorithm,
+    local_commits: u32,
+) -> Result<(fetch::Response, u32), Box<dyn std::error::Error>> {
+    let (graph, ids) = linear(5);
+    let mut transport = file::connect(fixture_repo_path(), version);
+    let outcome = handshake::parse(transport.handshake(Service::UploadPack)?)?;
+    let mut arguments = fetch::Arguments::new(&outcome);
+    arguments.no_progress();
+    arguments.want(ids[4]);
+
+    let mut negotiator = algorithm.into_negotiator(|id, buf| graph.find(id, buf));
+    if local_commits > 0 {
+        negotiator.add_tip(ids[local_commits as usize - 1]);
+    }
+    let mut pack = Vec::new();
+    let response = fetch::fetch(
+        &mut transport,
+        &arguments,
+        &mut *negotiator,
+        |_is_err, _text| {},
+        |read, _response| read.read_to_end(&mut pack).map(|_| ()),
+    )?;
+    transport.close()?;
+
+    assert_eq!(&pack[..4], b"PACK");
+    let mut num_objects = [0u8; 4];
+    num_objects.copy_from_slice(&pack[8..12]);
+    Ok((response, u32::from_be_bytes(num_objects)))
+}
+
+fn id(hex: &[u8]) -> owned::Id {
+    owned::Id::from_40_bytes_in_hex(hex).unwrap()
+}
+
+#[test]
+fn fixture_matches_in_memory_graph() {
+    let (_, ids) = linear(5);
+    assert_eq!(ids[4], id(b"b3870b6c68db48aa999089b522e15651eb83a7b5"));
+}
+
+#[test]
+fn v1_without_local_commits_receives_everything() -> Result<(), Box<dyn std::error::Error>> {
+    let (response, num_objects) = fetch_tip(Protocol::V1, Algorithm::Consecutive, 0)?;
+    assert_eq!(num_objects, 6, "5 commits and the empty tree");
+    assert_eq!(response.acknowledgements, vec![Acknowledgement::Nak]);
+    assert_eq!(response.rounds, 1, "there is nothing to negotiate");
+    assert!(response.has_pack);
+    Ok(())
+}
+
+#[test]
+fn v1_with_common_commits_receives_only_new_ones() -> Result<(), Box<dyn std::error::Error>> {
+    for algorithm in &[Algorithm::Consecutive, Algorithm::Skipping] {
+        let (response, num_objects) = fetch_tip(Protocol::V1, *algorithm, 3)?;
+        assert_eq!(num_objects, 2, "only c4 and c5");
+        assert_eq!(response.rounds, 2, "one round of haves, one to finish");
+        assert!(
+            response
+                .acknowledgements
+                .iter()
+                .any(|ack| matches!(ack, Acknowledgement::Ready(_))),
+            "the server was ready after the first round"
+        );
+        assert!(response.common().any(|id| *id == *linear(3).1.last().unwrap()));
+    }
+    Ok(())
+}
+
+#[test]
+fn v1_noop_receives_everything_despite_common_commits() -> Result<(), Box<dyn std::error::Error>> {
+    let (_response, num_objects) = fetch_tip(Protocol::V1, Algorithm::Noop, 3)?;
+    assert_eq!(num_objects, 6);
+    Ok(())
+}
+
+#[test]
+fn v2_without_local_commits_receives_everything() -> Result<(), Box<dyn std::error::Error>> {
+    let (response, num_objects) = fetch_tip(Protocol::V2, Algorithm::Consecutive, 0)?;
+    assert_eq!(num_objects, 6);
+    assert_eq!(response.rounds, 1);
+    assert!(
+        response.acknowledgements.is_empty(),
+        "no acknowledgments are sent with 'done'"
+    );
+    Ok(())
+}
+
+#[test]
+fn v2_with_common_commits_receives_only_new_ones() -> Result<(), Box<dyn std::error::Error>> {
+    let (response, num_objects) = fetch_tip(Protocol::V2, Algorithm::Consecutive, 3)?;
+    assert_eq!(num_objects, 2);
+    assert_eq!(
+        response.rounds, 1,
+        "the server is ready after the first round and sends the pack right away"
+    );
+    assert!(response.acknowledgements.contains(&Acknowledgement::Ready(None)));
+    Ok(())
+}
+
+#[test]
+fn v1_shallow_fetch_receives_shallow_updates() -> Result<(), Box<dyn std::error::Error>> {
+    let (_, ids) = linear(5);
+    let mut transport = file::connect(fixture_repo_path(), Protocol::V1);
+    let outcome = handshake::parse(transport.handshake(Service::UploadPack)?)?;
+    let mut arguments = fetch::Arguments::new(&outcome);
+    assert!(arguments.can_use_shallow());
+    arguments.want(ids[4]);
+    arguments.deepen(2);
+
+    let mut pack = Vec::new();
+    let response = fetch::fetch(
+        &mut transport,
+        &arguments,
+        &mut fetch::negotiate::Noop,
+        |_is_err, _text| {},
+        |read, _response| read.read_to_end(&mut pack).map(|_| ()),
+    )?;
+    transport.close()?;
+    assert_eq!(response.shallow_updates, vec![fetch::ShallowUpdate::Shallow(ids[3])]);
+    assert_eq!(&pack[8..12], &[0, 0, 0, 3], "c4, c5 and the empty tree");
+    Ok(())
+}
+
+fn fetch_everything(
+    repo: &str,
+    version: Protocol,
+    progress: impl FnMut(bool, &[u8]),
+) -> Result<fetch::Response, fetch::Error> {
+    let (_, ids) = linear(5);
+    let mut transport = file::connect(repo, version);
+    let outcome = handshake::parse(transport.handshake(Service::UploadPack)?).expect("valid handshake");
+    let mut arguments = fetch::Arguments::new(&outcome);
+    arguments.want(ids[4]);
+    let response = fetch::fetch(
+        &mut transport,
+        &arguments,
+        &mut fetch::negotiate::Noop,
+        progress,
+        |read, _response| io::copy(read, &mut io::sink()).map(|_| ()),
+    )?;
+    transport.close()?;
+    Ok(response)
+}
+
+#[test]
+fn progress_messages_of_the_server_are_forwarded() -> Result<(), Box<dyn std::error::Error>> {
+    for version in &[Protocol::V1, Protocol::V2] {
+        let mut messages = Vec::new();
+        fetch_everything(&fixture_repo_path(), *version, |is_err, text| {
+            messages.push((is_err, text.as_bstr().to_owned()))
+        })?;
+        assert!(messages.iter().all(|(is_err, _)| !is_err));
+        assert!(
+            messages
+                .iter()
+                .flat_map(|(_, text)| text.lines().map(ToOwned::to_owned).collect::<Vec<_>>())
+                .filter_map(|line| RemoteProgress::from_bytes(&line).map(|p| p.action.to_owned()))
+                .any(|action| action == "Counting objects"),
+            "{:?}: {:?}",
+            version,
+            messages
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn errors_of_the_server_abort_the_fetch() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let repo = dir.path().join("broken.git");
+    copy_dir(fixture_repo_path().as_ref(), &repo)?;
+    std::fs::remove_dir_all(repo.join("objects").join("49"))?;
+
+    for version in &[Protocol::V1, Protocol::V2] {
+        match fetch_everything(&repo.display().to_string(), *version, |_is_err, _text| {}) {
+            Err(fetch::Error::Remote(message)) => assert!(
+                message.contains_str("repository corruption"),
+                "{:?}: {}",
+                version,
+                message
+            ),
+            res => panic!("{:?}: expected a remote error, got {:?}", version, res),
+        }
+    }
+    Ok(())
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let dst = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dst)?;
+        } else {
+            std::fs::copy(entry.path(), dst)?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn filter_omits_objects_if_the_server_allows_it() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let repo = dir.path().join("filtered.git");
+    copy_dir(fixture_repo_path().as_ref(), &repo)?;
+    let status = std::process::Command::new("git")
+        .args(["config", "uploadpack.allowFilter", "true"])
+        .current_dir(&repo)
+        .status()?;
+    assert!(status.success());
+
+    let (_, ids) = linear(5);
+    for version in &[Protocol::V1, Protocol::V2] {
+        let mut transport = file::connect(fixture_repo_path(), *version);
+        let outcome = handshake::parse(transport.handshake(Service::UploadPack)?)?;
+        assert!(
+            !fetch::Arguments::new(&outcome).can_use_filter(),
+            "filters are disabled by default"
+        );
+        transport.close()?;
+
+        let mut transport = file::connect(repo.display().to_string(), *version);
+        let outcome = handshake::parse(transport.handshake(Service::UploadPack)?)?;
+        let mut arguments = fetch::Arguments::new(&outcome);
+        assert!(arguments.can_use_filter());
+        arguments.want(ids[4]);
+        arguments.filter(fetch::Filter::TreeDepth(0));
+
+        let mut pack = Vec::new();
+        fetch::fetch(
+            &mut transport,
+            &arguments,
+            &mut fetch::negotiate::Noop,
+            |_is_err, _text| {},
+            |read, _response| read.read_to_end(&mut pack).map(|_| ()),
+        )?;
+        transport.close()?;
+        assert_eq!(
+            &pack[8..12],
+            &[0, 0, 0, 5],
+            "{:?}: only the commits, without the tree",
+            version
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn refs_can_be_wanted_by_name_if_the_server_allows_it() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let repo = dir.path().join("ref-in-want.git");
+    copy_dir(fixture_repo_path().as_ref(), &repo)?;
+    let status = std::process::Command::new("git")
+        .args(["config", "uploadpack.allowRefInWant", "true"])
+        .current_dir(&repo)
+        .status()?;
+    assert!(status.success());
+
+    let mut transport = file::connect(fixture_repo_path(), Protocol::V2);
+    let outcome = handshake::parse(transport.handshake(Service::UploadPack)?)?;
+    assert!(
+        !fetch::Arguments::new(&outcome).can_use_ref_in_want(),
+        "ref-in-want is disabled by default"
+    );
+    transport.close()?;
+
+    let (_, ids) = linear(5);
+    let mut transport = file::connect(repo.display().to_string(), Protocol::V2);
+    let outcome = handshake::parse(transport.handshake(Service::UploadPack)?)?;
+    let mut arguments = fetch::Arguments::new(&outcome);
+    assert!(arguments.can_use_ref_in_want());
+    arguments.want_ref("refs/heads/main".into());
+    assert!(!arguments.is_empty());
+
+    let mut pack = Vec::new();
+    let response = fetch::fetch(
+        &mut transport,
+        &arguments,
+        &mut fetch::negotiate::Noop,
+        |_is_err, _text| {},
+        |read, _response| read.read_to_end(&mut pack).map(|_| ()),
+    )?;
+    transport.close()?;
+    assert_eq!(
+        response.wanted_refs,
+        vec![fetch::WantedRef {
+            id: ids[4],
+            path: "refs/heads/main".into()
+        }]
+    );
+    assert!(pack.starts_with(b"PACK"));
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use git_protocol::fetch::Filter;
+
+#[test]
+fn specifications_round_trip() {
+    for (spec, filter) in &[
+        ("blob:none", Filter::BlobNone),
+        ("blob:limit=512", Filter::BlobLimit(512)),
+        ("tree:0", Filter::TreeDepth(0)),
+        ("tree:3", Filter::TreeDepth(3)),
+    ] {
+        assert_eq!(spec.parse::<Filter>().unwrap(), *filter);
+        assert_eq!(filter.to_string(), *spec);
+    }
+}
+
+#[test]
+fn blob_limits_may_have_units() {
+    assert_eq!("blob:limit=1k".parse::<Filter>().unwrap(), Filter::BlobLimit(1024));
+    assert_eq!(
+        "blob:limit=2M".parse::<Filter>().unwrap(),
+        Filter::BlobLimit(2 * 1024 * 1024)
+    );
+    assert_eq!(
+        "blob:limit=1g".parse::<Filter>().unwrap(),
+        Filter::BlobLimit(1024 * 1024 * 1024)
+    );
+}
+
+#[test]
+fn unsupported_specifications_are_rejected() {
+    for spec in &[
+        "blob:all",
+        "blob:limit=",
+        "blob:limit=1x",
+        "tree:",
+        "tree:-1",
+        "sparse:oid=main:.sparse",
+    ] {
+        assert!(spec.parse::<Filter>().is_err(), "{}", spec);
+    }
+}
@@ -0,0 +1,60 @@
+use git_object::{borrowed, owned, HashKind, Kind, Sign, Time};
+use git_odb::Write;
+use std::collections::HashMap;
+
+/// An in-memory commit graph mirroring what `git` produces for the same empty commits.
+#[derive(Default)]
+pub struct Graph {
+    objects: HashMap<owned::Id, Vec<u8>>,
+}
+
+impl Graph {
+    pub fn commit(&mut self, time: u32, message: &str, parents: &[owned::Id]) -> owned::Id {
+        let signature = owned::Signature {
+            name: "a".into(),
+            email: "a@b".into(),
+            time: Time {
+                time,
+                offset: 0,
+                sign: Sign::Plus,
+            },
+        };
+        let commit = owned::Commit {
+            tree: owned::Id::from_40_bytes_in_hex(b"4b825dc642cb6eb9a060e54bf8d69288fbee4904").unwrap(),
+            parents: parents.iter().cloned().collect(),
+            author: signature.clone(),
+            committer: signature,
+            encoding: None,
+            message: format!("{}\n", message).into(),
+            extra_headers: Vec::new(),
+        };
+        let mut buf = Vec::new();
+        commit.write_to(&mut buf).unwrap();
+        let id = git_odb::sink().write_buf(Kind::Commit, &buf, HashKind::Sha1).unwrap();
+        self.objects.insert(id, buf);
+        id
+    }
+
+    pub fn find<'a>(&self, id: borrowed::Id<'_>, buf: &'a mut Vec<u8>) -> Option<borrowed::Object<'a>> {
+        let data = self.objects.get(&owned::Id::from_borrowed_sha1(id.sha1()))?;
+        buf.clear();
+        buf.extend_from_slice(data);
+        borrowed::Object::from_bytes(Kind::Commit, buf).ok()
+    }
+}
+
+/// Build a linear history of `count` commits named `c1` to `c<count>`, as found in the `linear.git` fixture.
+pub fn linear(count: u32) -> (Graph, Vec<owned::Id>) {
+    let mut graph = Graph::default();
+    let mut ids = Vec::new();
+    for n in 1..=count {
+        let parents: Vec<_> = ids.last().cloned().into_iter().collect();
+        ids.push(graph.commit(1_000_000_000 + n, &format!("c{}", n), &parents));
+    }
+    (graph, ids)
+}
+
+mod file;
+mod filter;
+mod negotiate;
+mod response;
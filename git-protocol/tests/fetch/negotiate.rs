@@ -0,0 +1,82 @@
+use crate::fetch::linear;
+use git_protocol::fetch::negotiate::{Algorithm, Negotiate};
+
+fn all_haves(negotiator: &mut dyn Negotiate) -> Vec<git_object::owned::Id> {
+    std::iter::from_fn(|| negotiator.next_have()).collect()
+}
+
+#[test]
+fn algorithm_from_config_value() {
+    assert_eq!(Algorithm::from_config_value("default"), Some(Algorithm::Consecutive));
+    assert_eq!(Algorithm::from_config_value("skipping"), Some(Algorithm::Skipping));
+    assert_eq!(Algorithm::from_config_value("noop"), Some(Algorithm::Noop));
+    assert_eq!(Algorithm::from_config_value("fancy"), None);
+}
+
+#[test]
+fn noop_sends_nothing() {
+    let (graph, ids) = linear(3);
+    let mut negotiator = Algorithm::Noop.into_negotiator(|id, buf| graph.find(id, buf));
+    negotiator.add_tip(ids[2]);
+    assert!(negotiator.next_have().is_none());
+}
+
+#[test]
+fn consecutive_sends_all_commits_newest_first() {
+    let (graph, ids) = linear(5);
+    let mut negotiator = Algorithm::Consecutive.into_negotiator(|id, buf| graph.find(id, buf));
+    negotiator.add_tip(ids[4]);
+    assert_eq!(
+        all_haves(&mut *negotiator),
+        ids.iter().rev().cloned().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn consecutive_stops_at_common_commits() {
+    let (graph, ids) = linear(5);
+    let mut negotiator = Algorithm::Consecutive.into_negotiator(|id, buf| graph.find(id, buf));
+    negotiator.add_tip(ids[4]);
+    assert_eq!(negotiator.next_have(), Some(ids[4]));
+    assert_eq!(negotiator.next_have(), Some(ids[3]));
+    assert!(
+        !negotiator.in_common_with_remote(ids[3]),
+        "it wasn't known to be common"
+    );
+    assert!(negotiator.in_common_with_remote(ids[3]), "now it is");
+    assert_eq!(negotiator.next_have(), None, "ancestors of common commits are not sent");
+}
+
+#[test]
+fn consecutive_does_not_send_known_common_commits() {
+    let (graph, ids) = linear(5);
+    let mut negotiator = Algorithm::Consecutive.into_negotiator(|id, buf| graph.find(id, buf));
+    negotiator.known_common(ids[1]);
+    negotiator.add_tip(ids[4]);
+    assert_eq!(all_haves(&mut *negotiator), vec![ids[4], ids[3], ids[2], ids[1]]);
+}
+
+#[test]
+fn skipping_skips_more_commits_the_further_it_walks() {
+    let (graph, ids) = linear(20);
+    let mut negotiator = Algorithm::Skipping.into_negotiator(|id, buf| graph.find(id, buf));
+    negotiator.add_tip(ids[19]);
+    let haves = all_haves(&mut *negotiator);
+    let position = |id| ids.iter().position(|i| *i == id).map(|p| p + 1).unwrap();
+    assert_eq!(
+        haves.into_iter().map(position).collect::<Vec<_>>(),
+        vec![20, 18, 15, 10, 2, 1],
+        "the distance grows with each commit sent, but the root is always sent"
+    );
+}
+
+#[test]
+fn skipping_stops_at_common_commits() {
+    let (graph, ids) = linear(20);
+    let mut negotiator = Algorithm::Skipping.into_negotiator(|id, buf| graph.find(id, buf));
+    negotiator.add_tip(ids[19]);
+    assert_eq!(negotiator.next_have(), Some(ids[19]));
+    assert_eq!(negotiator.next_have(), Some(ids[17]));
+    negotiator.in_common_with_remote(ids[17]);
+    assert_eq!(negotiator.next_have(), None);
+}
@@ -0,0 +1,75 @@
+use bstr::ByteSlice;
+use git_object::owned;
+use git_protocol::fetch::{Acknowledgement, ShallowUpdate, WantedRef};
+
+fn id(hex: &str) -> owned::Id {
+    owned::Id::from_40_bytes_in_hex(hex.as_bytes()).unwrap()
+}
+
+const HEX: &str = "49ef5cb06ece2cb5d816cb659526781d156eb05b";
+
+#[test]
+fn v1_acknowledgements() {
+    let parse = |line: String| Acknowledgement::from_v1_line(line.as_bytes().as_bstr()).unwrap();
+    assert_eq!(parse("NAK".into()), Acknowledgement::Nak);
+    assert_eq!(parse(format!("ACK {}", HEX)), Acknowledgement::Final(id(HEX)));
+    assert_eq!(parse(format!("ACK {} common", HEX)), Acknowledgement::Common(id(HEX)));
+    assert_eq!(parse(format!("ACK {} continue", HEX)), Acknowledgement::Common(id(HEX)));
+    assert_eq!(
+        parse(format!("ACK {} ready", HEX)),
+        Acknowledgement::Ready(Some(id(HEX)))
+    );
+}
+
+#[test]
+fn v2_acknowledgements() {
+    let parse = |line: String| Acknowledgement::from_v2_line(line.as_bytes().as_bstr()).unwrap();
+    assert_eq!(parse("NAK".into()), Acknowledgement::Nak);
+    assert_eq!(parse("ready".into()), Acknowledgement::Ready(None));
+    assert_eq!(parse(format!("ACK {}", HEX)), Acknowledgement::Common(id(HEX)));
+}
+
+#[test]
+fn invalid_acknowledgements() {
+    for line in &[
+        "ACK",
+        "ACK 1234",
+        "ACK 49ef5cb06ece2cb5d816cb659526781d156eb05b maybe",
+        "ACKNOWLEDGED",
+    ] {
+        assert!(
+            Acknowledgement::from_v1_line(line.as_bytes().as_bstr()).is_err(),
+            "{}",
+            line
+        );
+    }
+    assert!(Acknowledgement::from_v2_line(format!("ACK {} common", HEX).as_bytes().as_bstr()).is_err());
+}
+
+#[test]
+fn shallow_updates() {
+    let parse = |line: String| ShallowUpdate::from_line(line.as_bytes().as_bstr());
+    assert_eq!(
+        parse(format!("shallow {}", HEX)).unwrap(),
+        ShallowUpdate::Shallow(id(HEX))
+    );
+    assert_eq!(
+        parse(format!("unshallow {}", HEX)).unwrap(),
+        ShallowUpdate::Unshallow(id(HEX))
+    );
+    assert!(parse(format!("deepen {}", HEX)).is_err());
+}
+
+#[test]
+fn wanted_refs() {
+    let parse = |line: String| WantedRef::from_line(line.as_bytes().as_bstr());
+    assert_eq!(
+        parse(format!("{} refs/heads/main", HEX)).unwrap(),
+        WantedRef {
+            id: id(HEX),
+            path: "refs/heads/main".into()
+        }
+    );
+    assert!(parse(HEX.into()).is_err(), "the path is required");
+    assert!(parse("1234 refs/heads/main".into()).is_err());
+}
@@ -0,0 +1,184 @@
+use crate::fixture_bytes;
+use bstr::ByteSlice;
+use git_object::{owned, HashKind};
+use git_protocol::handshake::{self, refs, Ref};
+use git_transport::{packetline, Protocol};
+
+fn id(hex: &str) -> owned::Id {
+    owned::Id::from_40_bytes_in_hex(hex.as_bytes()).expect("valid hex")
+}
+
+fn parse(path: &str) -> Result<handshake::Outcome, handshake::Error> {
+    let data = fixture_bytes(path);
+    let mut lines = packetline::Provider::new(data.as_slice(), &[packetline::PacketLine::Flush]);
+    handshake::parse(&mut lines)
+}
+
+fn encode(lines: &[&[u8]]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for line in lines {
+        packetline::encode::data_to_write(line, &mut out).expect("write to memory");
+    }
+    packetline::encode::flush_to_write(&mut out).expect("write to memory");
+    out
+}
+
+fn expected_v1_refs() -> Vec<Ref> {
+    let main = id("529d504d0deadca6d9079225c631eea5757a47f4");
+    vec![
+        Ref::Symbolic {
+            path: "HEAD".into(),
+            target: "refs/heads/main".into(),
+            object: main,
+        },
+        Ref::Direct {
+            path: "refs/heads/dev".into(),
+            object: main,
+        },
+        Ref::Direct {
+            path: "refs/heads/main".into(),
+            object: main,
+        },
+        Ref::Direct {
+            path: "refs/tags/lightweight".into(),
+            object: main,
+        },
+        Ref::Peeled {
+            path: "refs/tags/v1.0".into(),
+            tag: id("6b2732db15a7d13b259c3ac67eee076fdef12b9a"),
+            object: id("08f5e5bda116ca087fbccf51e77208fb0b0d348d"),
+        },
+    ]
+}
+
+#[test]
+fn v1_ref_advertisement() -> Result<(), Box<dyn std::error::Error>> {
+    for fixture in &["v1/clone.response", "v1/clone-with-version-line.response"] {
+        let outcome = parse(fixture)?;
+        assert_eq!(outcome.server_protocol_version, Protocol::V1);
+        assert_eq!(outcome.object_hash, HashKind::Sha1);
+        assert!(outcome.capabilities.contains("multi_ack_detailed"));
+        assert_eq!(outcome.refs.expect("v1 refs"), expected_v1_refs());
+        assert!(outcome.shallow_commits.is_empty());
+    }
+    Ok(())
+}
+
+#[test]
+fn v1_ref_advertisement_with_many_refs() -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = parse("v1/fetch/01-many-refs.response")?;
+    let refs = outcome.refs.expect("v1 refs");
+    assert_eq!(refs.len(), 785);
+    assert_eq!(
+        refs[0],
+        Ref::Symbolic {
+            path: "HEAD".into(),
+            target: "refs/heads/master".into(),
+            object: id("7814e8a05a59c0cf5fb186661d1551c75d1299b5")
+        }
+    );
+    assert!(refs
+        .iter()
+        .any(|r| matches!(r, Ref::Peeled { path, .. } if path == "refs/tags/v2.9.5")));
+    Ok(())
+}
+
+#[test]
+fn v1_empty_repository() -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = parse("v1/empty.response")?;
+    assert_eq!(outcome.server_protocol_version, Protocol::V1);
+    assert_eq!(outcome.refs, Some(Vec::new()));
+
+    let data = encode(&[
+        b"0000000000000000000000000000000000000000 capabilities^{}\0multi_ack object-format=sha1 agent=git/2.28.0\n",
+    ]);
+    let mut lines = packetline::Provider::new(data.as_slice(), &[packetline::PacketLine::Flush]);
+    let outcome = handshake::parse(&mut lines)?;
+    assert_eq!(outcome.refs, Some(Vec::new()));
+    assert!(outcome.capabilities.contains("multi_ack"));
+    Ok(())
+}
+
+#[test]
+fn v1_shallow_lines_and_unsupported_object_formats() -> Result<(), Box<dyn std::error::Error>> {
+    let data = encode(&[
+        b"529d504d0deadca6d9079225c631eea5757a47f4 HEAD\0object-format=sha1 agent=git\n",
+        b"shallow 08f5e5bda116ca087fbccf51e77208fb0b0d348d\n",
+    ]);
+    let mut lines = packetline::Provider::new(data.as_slice(), &[packetline::PacketLine::Flush]);
+    let outcome = handshake::parse(&mut lines)?;
+    assert_eq!(
+        outcome.shallow_commits,
+        vec![id("08f5e5bda116ca087fbccf51e77208fb0b0d348d")]
+    );
+
+    let data = encode(&[b"529d504d0deadca6d9079225c631eea5757a47f4 HEAD\0object-format=sha256\n"]);
+    let mut lines = packetline::Provider::new(data.as_slice(), &[packetline::PacketLine::Flush]);
+    assert!(matches!(
+        handshake::parse(&mut lines),
+        Err(handshake::Error::UnsupportedObjectFormat(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn v2_capability_advertisement() -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = parse("v2/clone.response")?;
+    assert_eq!(outcome.server_protocol_version, Protocol::V2);
+    assert_eq!(outcome.refs, None, "refs are obtained with ls-refs");
+    assert!(outcome.capabilities.is_v2());
+    assert_eq!(
+        outcome
+            .capabilities
+            .capability("ls-refs")
+            .expect("present")
+            .supports("unborn"),
+        Some(true)
+    );
+    assert_eq!(outcome.object_hash, HashKind::Sha1);
+    Ok(())
+}
+
+#[test]
+fn v2_ls_refs_output() -> Result<(), Box<dyn std::error::Error>> {
+    let data = fixture_bytes("v2/ls-refs.response");
+    let mut lines = packetline::Provider::new(data.as_slice(), &[packetline::PacketLine::Flush]);
+    assert_eq!(refs::from_v2_refs(&mut lines)?, expected_v1_refs());
+
+    let data = fixture_bytes("v2/ls-refs-unborn.response");
+    let mut lines = packetline::Provider::new(data.as_slice(), &[packetline::PacketLine::Flush]);
+    assert_eq!(
+        refs::from_v2_refs(&mut lines)?,
+        vec![Ref::Unborn {
+            path: "HEAD".into(),
+            target: "refs/heads/master".into()
+        }]
+    );
+    assert_eq!(
+        refs::parse_v2_line(b"unborn HEAD symref-target:refs/heads/main")?
+            .unpack()
+            .1,
+        None
+    );
+    assert!(matches!(
+        refs::parse_v2_line(b"529d504d0deadca6d9079225c631eea5757a47f4 HEAD unknown:value"),
+        Err(handshake::Error::UnknownAttribute(..))
+    ));
+    assert!(refs::parse_v2_line(b"529d504d0deadca6d9079225c631eea5757a47f4".as_bstr()).is_err());
+    Ok(())
+}
+
+#[cfg(feature = "async-client")]
+#[test]
+fn async_lines_are_parsed_like_blocking_ones() -> Result<(), Box<dyn std::error::Error>> {
+    for fixture in &["v1/clone.response", "v2/clone.response"] {
+        let data = fixture_bytes(fixture);
+        let mut lines = packetline::async_io::Provider::new(
+            futures_lite::io::Cursor::new(data.as_slice()),
+            &[packetline::PacketLine::Flush],
+        );
+        let outcome = futures_lite::future::block_on(handshake::parse_async(&mut lines))?;
+        assert_eq!(outcome, parse(fixture)?);
+    }
+    Ok(())
+}
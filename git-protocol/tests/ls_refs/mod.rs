@@ -0,0 +1,112 @@
+use git_object::owned;
+use git_protocol::{handshake::Ref, ls_refs};
+use git_transport::{client::file, Protocol};
+
+fn fixture_repo_path() -> String {
+    std::env::current_dir()
+        .unwrap()
+        .join("tests")
+        .join("fixtures")
+        .join("repos")
+        .join("linear.git")
+        .display()
+        .to_string()
+}
+
+fn id(hex: &str) -> owned::Id {
+    owned::Id::from_40_bytes_in_hex(hex.as_bytes()).unwrap()
+}
+
+const MAIN: &str = "b3870b6c68db48aa999089b522e15651eb83a7b5";
+const FEATURE: &str = "134a0f80e73cdd97a5c865119e82d2de936ba71e";
+const TAG: &str = "ee3b56c84b3eb21cff73d889c55b900fe8f1e2c2";
+const TAGGED: &str = "49ef5cb06ece2cb5d816cb659526781d156eb05b";
+
+fn ls_remote(version: Protocol, options: ls_refs::Options) -> Vec<Ref> {
+    let mut transport = file::connect(fixture_repo_path(), version);
+    git_protocol::ls_remote(&mut transport, &options).unwrap()
+}
+
+#[test]
+fn all_refs_with_symrefs_and_peeled_tags() {
+    for version in &[Protocol::V1, Protocol::V2] {
+        assert_eq!(
+            ls_remote(*version, ls_refs::Options::default()),
+            vec![
+                Ref::Symbolic {
+                    path: "HEAD".into(),
+                    target: "refs/heads/main".into(),
+                    object: id(MAIN),
+                },
+                Ref::Direct {
+                    path: "refs/heads/feature".into(),
+                    object: id(FEATURE),
+                },
+                Ref::Direct {
+                    path: "refs/heads/main".into(),
+                    object: id(MAIN),
+                },
+                Ref::Peeled {
+                    path: "refs/tags/v1.0".into(),
+                    tag: id(TAG),
+                    object: id(TAGGED),
+                },
+            ],
+            "{:?}",
+            version
+        );
+    }
+}
+
+#[test]
+fn prefixes_limit_the_listed_refs() {
+    for version in &[Protocol::V1, Protocol::V2] {
+        let refs = ls_remote(
+            *version,
+            ls_refs::Options {
+                prefixes: vec!["refs/tags/".into(), "refs/heads/fea".into()],
+                symrefs: false,
+                peel: false,
+                unborn: false,
+            },
+        );
+        assert_eq!(
+            refs,
+            vec![
+                Ref::Direct {
+                    path: "refs/heads/feature".into(),
+                    object: id(FEATURE),
+                },
+                Ref::Direct {
+                    path: "refs/tags/v1.0".into(),
+                    object: id(TAG),
+                },
+            ],
+            "{:?}",
+            version
+        );
+    }
+}
+
+#[test]
+fn without_symrefs_head_is_a_plain_ref() {
+    for version in &[Protocol::V1, Protocol::V2] {
+        let refs = ls_remote(
+            *version,
+            ls_refs::Options {
+                prefixes: vec!["HEAD".into()],
+                symrefs: false,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            refs,
+            vec![Ref::Direct {
+                path: "HEAD".into(),
+                object: id(MAIN),
+            }],
+            "{:?}",
+            version
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use git_object::owned;
+use git_protocol::{handshake, object_info};
+use git_transport::{
+    client::{file, Transport},
+    Protocol, Service,
+};
+
+fn fixture_repo_path() -> String {
+    std::env::current_dir()
+        .unwrap()
+        .join("tests")
+        .join("fixtures")
+        .join("repos")
+        .join("linear.git")
+        .display()
+        .to_string()
+}
+
+fn id(hex: &str) -> owned::Id {
+    owned::Id::from_40_bytes_in_hex(hex.as_bytes()).unwrap()
+}
+
+const MAIN: &str = "b3870b6c68db48aa999089b522e15651eb83a7b5";
+const TAG: &str = "ee3b56c84b3eb21cff73d889c55b900fe8f1e2c2";
+const MISSING: &str = "ffffffffffffffffffffffffffffffffffffffff";
+
+#[test]
+fn sizes_of_present_and_missing_objects() -> Result<(), Box<dyn std::error::Error>> {
+    let mut transport = file::connect(fixture_repo_path(), Protocol::V2);
+    let outcome = handshake::parse(transport.handshake(Service::UploadPack)?)?;
+    let ids = vec![id(MAIN), id(MISSING), id(TAG)];
+    let infos = git_protocol::object_info(&mut transport, &outcome.capabilities, &ids)?;
+    transport.close()?;
+    assert_eq!(
+        infos,
+        vec![
+            object_info::Info {
+                id: id(MAIN),
+                size: Some(165)
+            },
+            object_info::Info {
+                id: id(MISSING),
+                size: None
+            },
+            object_info::Info {
+                id: id(TAG),
+                size: Some(107)
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn v1_servers_do_not_support_it() -> Result<(), Box<dyn std::error::Error>> {
+    let mut transport = file::connect(fixture_repo_path(), Protocol::V1);
+    let outcome = handshake::parse(transport.handshake(Service::UploadPack)?)?;
+    assert!(matches!(
+        git_protocol::object_info(&mut transport, &outcome.capabilities, &[id(MAIN)]),
+        Err(object_info::Error::Unsupported)
+    ));
+    transport.close()?;
+    Ok(())
+}
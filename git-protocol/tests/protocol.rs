@@ -0,0 +1,14 @@
+use std::path::PathBuf;
+
+pub fn fixture_bytes(path: &str) -> Vec<u8> {
+    std::fs::read(PathBuf::from("tests").join("fixtures").join(path)).expect("fixture to be present")
+}
+
+mod bundle_uri;
+mod capabilities;
+mod fetch;
+mod handshake;
+mod ls_refs;
+mod object_info;
+mod push;
+mod remote_progress;
@@ -0,0 +1,100 @@
+use git_object::owned;
+use git_protocol::push::{Error, RefOptions, RefStatus, Report, Status};
+use git_transport::packetline::{PacketLine, Provider};
+
+fn lines(lines: &[&str]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for line in lines {
+        out.extend_from_slice(format!("{:04x}{}\n", line.len() + 5, line).as_bytes());
+    }
+    out.extend_from_slice(b"0000");
+    out
+}
+
+fn parse(input: &[&str]) -> Result<Report, Error> {
+    let data = lines(input);
+    Report::from_lines(&mut Provider::new(data.as_slice(), &[PacketLine::Flush]))
+}
+
+fn id(hex: &str) -> owned::Id {
+    owned::Id::from_40_bytes_in_hex(hex.as_bytes()).unwrap()
+}
+
+#[test]
+fn v1_report() {
+    let report = parse(&[
+        "unpack ok",
+        "ok refs/heads/main",
+        "ng refs/heads/other non-fast-forward",
+    ])
+    .unwrap();
+    assert_eq!(report.unpack_error, None);
+    assert_eq!(
+        report.refs,
+        vec![
+            RefStatus {
+                name: "refs/heads/main".into(),
+                status: Status::Ok,
+                options: RefOptions::default(),
+            },
+            RefStatus {
+                name: "refs/heads/other".into(),
+                status: Status::Rejected("non-fast-forward".into()),
+                options: RefOptions::default(),
+            }
+        ]
+    );
+    assert!(!report.is_success());
+    assert_eq!(
+        report.status_of("refs/heads/main".into()).map(|r| &r.status),
+        Some(&Status::Ok)
+    );
+}
+
+#[test]
+fn v2_report_with_options() {
+    const OLD: &str = "49ef5cb06ece2cb5d816cb659526781d156eb05b";
+    const NEW: &str = "877c3ad4e85fa4408811d3fcc38aef2500f04ade";
+    let old = format!("option old-oid {}", OLD);
+    let new = format!("option new-oid {}", NEW);
+    let report = parse(&[
+        "unpack ok",
+        "ok refs/for/main",
+        "option refname refs/changes/1/1",
+        &old,
+        &new,
+        "option forced-update",
+    ])
+    .unwrap();
+    assert!(report.is_success());
+    assert_eq!(
+        report.refs[0].options,
+        RefOptions {
+            refname: Some("refs/changes/1/1".into()),
+            old: Some(id(OLD)),
+            new: Some(id(NEW)),
+            forced_update: true,
+        }
+    );
+}
+
+#[test]
+fn unpack_failures_are_reported() {
+    let report = parse(&["unpack index-pack abnormal exit", "ng refs/heads/main unpacker error"]).unwrap();
+    assert_eq!(report.unpack_error, Some("index-pack abnormal exit".into()));
+    assert!(!report.is_success());
+}
+
+#[test]
+fn malformed_reports() {
+    assert!(matches!(parse(&[]), Err(Error::UnexpectedEndOfResponse)));
+    assert!(matches!(parse(&["ok refs/heads/main"]), Err(Error::UnknownLine(_))));
+    assert!(matches!(
+        parse(&["unpack ok", "option forced-update"]),
+        Err(Error::UnknownLine(_))
+    ));
+    assert!(matches!(
+        parse(&["unpack ok", "ok refs/heads/main", "option old-oid 1234"]),
+        Err(Error::Id(_))
+    ));
+}
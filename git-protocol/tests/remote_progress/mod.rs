@@ -0,0 +1,50 @@
+use bstr::ByteSlice;
+use git_protocol::RemoteProgress;
+
+#[test]
+fn counts_with_percentage() {
+    assert_eq!(
+        RemoteProgress::from_bytes(b"Counting objects:  33% (1/3)"),
+        Some(RemoteProgress {
+            action: b"Counting objects".as_bstr(),
+            percent: Some(33),
+            step: Some(1),
+            max: Some(3),
+        })
+    );
+    assert_eq!(
+        RemoteProgress::from_bytes(b"Compressing objects: 100% (2/2), done."),
+        Some(RemoteProgress {
+            action: b"Compressing objects".as_bstr(),
+            percent: Some(100),
+            step: Some(2),
+            max: Some(2),
+        })
+    );
+}
+
+#[test]
+fn counts_without_maximum() {
+    assert_eq!(
+        RemoteProgress::from_bytes(b"Enumerating objects: 6, done."),
+        Some(RemoteProgress {
+            action: b"Enumerating objects".as_bstr(),
+            percent: None,
+            step: Some(6),
+            max: None,
+        })
+    );
+}
+
+#[test]
+fn other_messages_are_not_progress() {
+    for line in &[
+        &b"Total 6 (delta 0), reused 0 (delta 0), pack-reused 0"[..],
+        b"error: the hook declined",
+        b": 50% (1/2)",
+        b"Counting objects: 50% 1/2",
+        b"",
+    ] {
+        assert_eq!(RemoteProgress::from_bytes(line), None, "{}", line.as_bstr());
+    }
+}
@@ -1,3 +1,5 @@
 #![forbid(unsafe_code)]
 
+pub mod name;
+
 pub mod validated;
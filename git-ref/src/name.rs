@@ -0,0 +1,155 @@
+//! Check ref names with the rules of `git check-ref-format`, and turn arbitrary text into valid names.
+//!
+//! Names consisting of a single component, like `HEAD` or `main`, are valid as if `--allow-onelevel` was given, as
+//! callers decide whether they need a full name like `refs/heads/main`.
+use bstr::{BStr, BString, ByteSlice};
+use quick_error::quick_error;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        InvalidByte(name: BString) {
+            display("A ref must not contain invalid bytes or ascii control characters: '{}'", name)
+        }
+        DoubleDot {
+            display("A ref must not contain '..' as it may be mistaken for a range")
+        }
+        LockFileSuffix {
+            display("A ref component must not end with '.lock'")
+        }
+        ReflogPortion {
+            display("A ref must not contain '@{{' which is a part of a ref-log")
+        }
+        Asterisk {
+            display("A ref must not contain '*' character")
+        }
+        StartsWithDot {
+            display("A ref component must not start with a '.'")
+        }
+        StartsWithSlash {
+            display("A ref must not start with a '/'")
+        }
+        EndsWithSlash {
+            display("A ref must not end with a '/'")
+        }
+        DoubleSlash {
+            display("A ref must not contain '//' as its components must not be empty")
+        }
+        EndsWithDot {
+            display("A ref must not end with a '.'")
+        }
+        SingleAt {
+            display("A ref must not be '@' which is a shortcut for 'HEAD'")
+        }
+        Empty {
+            display("A ref must not be empty")
+        }
+    }
+}
+
+/// Returns true if `byte` may never appear in a ref name.
+fn is_invalid_byte(byte: u8) -> bool {
+    matches!(
+        byte,
+        b'\\' | b'^' | b':' | b'[' | b'?' | b' ' | b'~' | b'*' | b'\0'..=b'\x1F' | b'\x7F'
+    )
+}
+
+/// Return `name` if it's a valid ref name, like `refs/heads/main` or `HEAD`.
+///
+/// Like `git check-ref-format`, no component may start with `.` or end with `.lock`, and the name may not contain
+/// `..`, `//`, `@{`, ascii control characters, spaces or any of `~^:?*[\`, nor be `@`, start with `/` or end with `/`
+/// or `.`.
+pub fn validate(name: &BStr) -> Result<&BStr, Error> {
+    if name.is_empty() {
+        return Err(Error::Empty);
+    }
+    if name == "@" {
+        return Err(Error::SingleAt);
+    }
+
+    let mut last = 0;
+    for byte in name.iter() {
+        match byte {
+            b'*' => return Err(Error::Asterisk),
+            b if is_invalid_byte(*b) => return Err(Error::InvalidByte(name.into())),
+            b'.' if last == b'.' => return Err(Error::DoubleDot),
+            b'{' if last == b'@' => return Err(Error::ReflogPortion),
+            b'/' if last == b'/' => return Err(Error::DoubleSlash),
+            _ => {}
+        }
+        last = *byte;
+    }
+    if name[0] == b'/' {
+        return Err(Error::StartsWithSlash);
+    }
+    for component in name.split_str("/") {
+        if component.starts_with(b".") {
+            return Err(Error::StartsWithDot);
+        }
+        if component.ends_with(b".lock") {
+            return Err(Error::LockFileSuffix);
+        }
+    }
+    match name.last().expect("non-empty") {
+        b'/' => Err(Error::EndsWithSlash),
+        b'.' => Err(Error::EndsWithDot),
+        _ => Ok(name),
+    }
+}
+
+/// Turn `name`, like the title of an issue, into a valid ref name by replacing runs of invalid bytes with `-`,
+/// collapsing repeated dots and slashes, and dropping what isn't allowed at the start or end of components, like
+/// leading dots and `.lock` suffixes.
+///
+/// Returns [`Error::Empty`] if nothing is left, and otherwise a name which passes [`validate()`]. Valid names are
+/// returned unchanged.
+pub fn sanitize(name: &BStr) -> Result<BString, Error> {
+    let mut out = BString::default();
+    for component in name.split_str("/") {
+        let mut sanitized = Vec::with_capacity(component.len());
+        let mut last_was_replaced = false;
+        for &byte in component {
+            let replace = is_invalid_byte(byte) || (byte == b'{' && sanitized.last() == Some(&b'@'));
+            match (sanitized.last(), byte) {
+                _ if replace && last_was_replaced => {}
+                _ if replace => sanitized.push(b'-'),
+                (Some(b'.'), b'.') => {}
+                _ => sanitized.push(byte),
+            }
+            last_was_replaced = replace;
+        }
+        let mut component = sanitized.trim_start_with(|c| c == '.');
+        while let Some(stripped) = component.strip_suffix(b".lock") {
+            component = stripped;
+        }
+        if component.is_empty() {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push(b'/');
+        }
+        out.extend_from_slice(component);
+    }
+    loop {
+        let len = out.len();
+        while out.ends_with(b".") || out.ends_with(b"/") {
+            out.pop();
+        }
+        if out.ends_with(b".lock") {
+            let without_suffix = out.len() - b".lock".len();
+            out.truncate(without_suffix);
+        }
+        if out.len() == len {
+            break;
+        }
+    }
+    if out.is_empty() {
+        return Err(Error::Empty);
+    }
+    if out == "@" {
+        out = "-".into();
+    }
+    debug_assert!(validate(out.as_ref()).is_ok(), "sanitized names are valid");
+    Ok(out)
+}
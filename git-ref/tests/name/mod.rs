@@ -0,0 +1,78 @@
+use bstr::ByteSlice;
+use git_ref::name;
+
+#[test]
+fn components_are_checked_individually() {
+    for (input, expected) in &[
+        (&b"refs/heads/.hidden"[..], "StartsWithDot"),
+        (b"refs/heads.lock/main", "LockFileSuffix"),
+        (b"refs//heads", "DoubleSlash"),
+        (b"/refs/heads/main", "StartsWithSlash"),
+        (b"refs/heads/main.", "EndsWithDot"),
+    ] {
+        let err = name::validate(input.as_bstr()).expect_err("invalid");
+        assert_eq!(format!("{:?}", err), *expected, "{:?}", input.as_bstr());
+    }
+    assert!(name::validate(b"refs/heads/v1.0./.x".as_bstr()).is_err());
+    assert!(name::validate(b"refs/heads/v1.0./x".as_bstr()).is_ok());
+}
+
+#[test]
+fn validation_agrees_with_git() {
+    let names = [
+        "HEAD",
+        "refs/heads/main",
+        "refs/heads/a.lock.b",
+        "refs/heads/main.lock",
+        "refs/heads/.main",
+        "refs/heads/ma..in",
+        "refs//heads",
+        "refs/heads/",
+        "refs/heads/main.",
+        "refs/heads/@",
+        "@",
+        "refs/heads/a@{b",
+        "refs/heads/a b",
+        "refs/heads/a*b",
+        "refs/heads/\u{4f60}\u{597d}",
+    ];
+    for input in &names {
+        let status = std::process::Command::new("git")
+            .args(["check-ref-format", "--allow-onelevel", input])
+            .status()
+            .unwrap();
+        assert_eq!(
+            name::validate(input.as_bytes().as_bstr()).is_ok(),
+            status.success(),
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn sanitize_turns_text_into_valid_names() {
+    for (input, expected) in &[
+        ("refs/heads/main", "refs/heads/main"),
+        ("Fix: the  crash [urgent]?", "Fix-the-crash-urgent]-"),
+        ("feature//.hidden/..dots...here", "feature/hidden/dots.here"),
+        ("/leading/and/trailing/", "leading/and/trailing"),
+        ("topic.lock/name.lock", "topic/name"),
+        ("ends with dot..", "ends-with-dot"),
+        ("reflog@{1}", "reflog@-1}"),
+        ("@", "-"),
+        (".lock", "lock"),
+        ("tab\tand\nnewline", "tab-and-newline"),
+    ] {
+        let sanitized = name::sanitize(input.as_bytes().as_bstr()).unwrap();
+        assert_eq!(sanitized, *expected, "{:?}", input);
+        assert!(name::validate(sanitized.as_ref()).is_ok());
+    }
+    for input in &["", "/", "...", "//.//"] {
+        assert!(
+            matches!(name::sanitize(input.as_bytes().as_bstr()), Err(name::Error::Empty)),
+            "{:?}",
+            input
+        );
+    }
+}
@@ -12,7 +12,7 @@ mod name {
             };
         }
 
-        mktest!(an_at_sign, b"@");
+        mktest!(an_at_sign_in_a_component, b"refs/@");
         mktest!(chinese_unicode, "你好吗".as_bytes());
         mktest!(non_text, "😅🙌".as_bytes());
         mktest!(contains_an_at, b"hello@foo");
@@ -20,7 +20,7 @@ mod name {
         mktest!(contains_brackets, b"this_{is-fine}_too");
         mktest!(contains_brackets_and_at, b"this_{@is-fine@}_too");
         mktest!(dot_in_the_middle, b"token.other");
-        mktest!(dot_at_the_end, b"hello.");
+        mktest!(dot_at_the_end_of_a_component, b"hello./world");
         mktest!(slash_inbetween, b"hello/world");
     }
 
@@ -78,6 +78,8 @@ mod name {
         mktestb!(contains_newline, b"prefix\nsuffix");
         mktestb!(contains_carriage_return, b"prefix\rsuffix");
         mktest!(starts_with_dot, b".with-dot", StartsWithDot);
+        mktest!(an_at_sign, b"@", SingleAt);
+        mktest!(dot_at_the_end, b"hello.", EndsWithDot);
         mktest!(empty, b"", Empty);
     }
 }
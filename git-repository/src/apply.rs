@@ -0,0 +1,1111 @@
+//! Parse patches in the unified diff format as written by `git diff` and `git format-patch`, and apply them to the
+//! content of files, to trees and to worktrees like `git apply` does.
+//!
+//! Like git, each hunk is searched for starting at the line it was written for and moving away from it in both
+//! directions, and it only applies where all of its context lines match, unless [`Options::min_context`] allows to
+//! ignore some of them. Hunks which don't apply are rejected, and patches with rejected hunks change nothing unless
+//! [`Options::reject`] is set, in which case the hunks which do apply are applied like with `--reject`. Binary patches
+//! aren't supported.
+//!
+//! Emails written by `git format-patch` are split into the author, message and patches of the commits they contain
+//! with [`mails()`], to apply them on top of each other like `git am` does.
+use crate::{clone, date, fast_import, merge, worktree};
+use git_object::{
+    borrowed,
+    bstr::{BStr, BString, ByteSlice},
+    owned, HashKind, Kind, Sign, Time, TreeMode,
+};
+use git_odb::{loose, Write};
+use quick_error::quick_error;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Parse(line_number: usize, message: &'static str) {
+            display("Line {} of the patch is invalid: {}", line_number, message)
+        }
+        Date(err: date::Error) {
+            display("The date of an email could not be parsed")
+            from()
+            source(err)
+        }
+        Binary(path: BString) {
+            display("The binary patch of '{}' can't be applied", path)
+        }
+        Missing(path: BString) {
+            display("The file '{}' to patch doesn't exist", path)
+        }
+        Exists(path: BString) {
+            display("The file '{}' to create already exists", path)
+        }
+        NotEmpty(path: BString) {
+            display("The file '{}' to delete isn't empty after applying the patch", path)
+        }
+        UnsafePath(path: BString) {
+            display("The path '{}' leaves the worktree, enters the git directory or is beyond a symbolic link", path)
+        }
+        UnknownPaths {
+            display("A patch must have an old or a new path")
+        }
+        Find(id: owned::Id) {
+            display("Object {} could not be found", id)
+        }
+        NotATree(id: owned::Id) {
+            display("Object {} was expected to be a tree", id)
+        }
+        NotABlob(id: owned::Id) {
+            display("Object {} was expected to be a blob", id)
+        }
+        WriteObject(err: loose::db::write::Error) {
+            display("A patched object could not be written")
+            from()
+            source(err)
+        }
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not read or write '{}'", path.display())
+            source(err)
+        }
+    }
+}
+
+/// A line of a hunk, along with its newline unless it's the last line of a file without one.
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+pub enum Line {
+    /// A line which is kept.
+    Context(BString),
+    /// A line which is removed.
+    Removed(BString),
+    /// A line which is added.
+    Added(BString),
+}
+
+/// A change to consecutive lines of a file.
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+pub struct Hunk {
+    /// The line number of the first line before the change, or the one before it if no lines were there.
+    pub old_start: usize,
+    /// The amount of lines before the change.
+    pub old_lines: usize,
+    /// The line number of the first line after the change, or the one before it if no lines are left.
+    pub new_start: usize,
+    /// The amount of lines after the change.
+    pub new_lines: usize,
+    /// The context, removed and added lines in the order they were written in.
+    pub lines: Vec<Line>,
+}
+
+/// The changes to a single file.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default)]
+pub struct FilePatch {
+    /// The path of the file to patch, or `None` if the file is created.
+    pub old_path: Option<BString>,
+    /// The path of the patched file, or `None` if the file is deleted. It differs from `old_path` for renames and copies.
+    pub new_path: Option<BString>,
+    /// The mode of the file to patch, if the patch mentions it.
+    pub old_mode: Option<TreeMode>,
+    /// The mode of the patched file, if the patch mentions it.
+    pub new_mode: Option<TreeMode>,
+    /// If true, the file at `old_path` is copied to `new_path` instead of being renamed.
+    pub copy: bool,
+    /// If true, the file is binary and its changes can't be applied.
+    pub binary: bool,
+    /// The changes to the content of the file.
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    /// The path of the patched file, or the one of the deleted file.
+    pub fn path(&self) -> &BStr {
+        self.new_path
+            .as_ref()
+            .or(self.old_path.as_ref())
+            .map(|path| path.as_bstr())
+            .unwrap_or_default()
+    }
+}
+
+/// A commit sent as email by `git format-patch`.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Mail {
+    /// The author of the commit, taken from the `From` and `Date` headers.
+    pub author: owned::Signature,
+    /// The subject without the prefixes in brackets like `[PATCH 1/2]`.
+    pub subject: BString,
+    /// The commit message made of the subject and the text before the patch, separated by an empty line.
+    pub message: BString,
+    /// The changes of the commit.
+    pub patches: Vec<FilePatch>,
+}
+
+/// Configure how to apply patches.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy, Default)]
+pub struct Options {
+    /// If set, context lines at the start and end of hunks which don't apply are ignored one by one until the given
+    /// amount is left, like `-C<n>` does. Hunks starting at the first line or ending at the last one then don't have
+    /// to stay there.
+    pub min_context: Option<usize>,
+    /// If true, changes in the amount of whitespace don't matter when matching lines, like `--ignore-whitespace`.
+    /// Context lines keep the whitespace of the patched file.
+    pub ignore_whitespace: bool,
+    /// If true, the hunks which apply are applied even if others were rejected, like `--reject` does.
+    pub reject: bool,
+}
+
+/// The result of [`patch()`].
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+pub struct Patched {
+    /// The content with all hunks which applied.
+    pub data: Vec<u8>,
+    /// The hunks which didn't apply.
+    pub rejected: Vec<Hunk>,
+}
+
+/// The hunks of a file which didn't apply.
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+pub struct Rejected {
+    /// The path of the patched file.
+    pub path: BString,
+    /// The hunks which didn't apply.
+    pub hunks: Vec<Hunk>,
+}
+
+/// The result of [`to_tree()`].
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+pub struct Outcome {
+    /// The patched tree, which is the original one if hunks were rejected and [`Options::reject`] isn't set.
+    pub tree: owned::Id,
+    /// The files with hunks which didn't apply, in the order of the patches.
+    pub rejected: Vec<Rejected>,
+}
+
+/// Parse the patches of all files in `input`, which may contain other text around them like commit messages.
+///
+/// Paths have their first component removed, like the default `-p1` of git does.
+pub fn parse(input: &[u8]) -> Result<Vec<FilePatch>, Error> {
+    Parser {
+        lines: input.lines_with_terminator().collect(),
+        pos: 0,
+        first_line_number: 1,
+    }
+    .patches()
+}
+
+/// Parse the emails in `input` in the mbox format, like it's written by `git format-patch --stdout`.
+///
+/// Headers encoded as described in RFC 2047 are decoded, but their charset is expected to be UTF-8.
+pub fn mails(input: &[u8]) -> Result<Vec<Mail>, Error> {
+    let lines: Vec<_> = input.lines_with_terminator().collect();
+    let is_header = |line: &[u8]| matches!(line.find_byte(b':'), Some(pos) if pos > 0 && !line[..pos].contains(&b' '));
+    let mut starts: Vec<_> = (0..lines.len())
+        .filter(|&n| lines[n].starts_with(b"From ") && matches!(lines.get(n + 1), Some(line) if is_header(line)))
+        .collect();
+    if starts.first() != Some(&0) && matches!(lines.first(), Some(line) if is_header(line)) {
+        starts.insert(0, 0);
+    }
+    starts
+        .iter()
+        .zip(starts.iter().skip(1).chain(std::iter::once(&lines.len())))
+        .map(|(&start, &end)| mail(&lines[start..end], start + 1))
+        .collect()
+}
+
+fn mail(lines: &[&[u8]], first_line_number: usize) -> Result<Mail, Error> {
+    let mut pos = usize::from(matches!(lines.first(), Some(line) if line.starts_with(b"From ")));
+    let mut headers = Vec::<(BString, Vec<u8>)>::new();
+    while let Some(line) = lines.get(pos) {
+        let line = line.trim_end_with(|c| c == '\n' || c == '\r');
+        pos += 1;
+        if line.is_empty() {
+            break;
+        }
+        match (line[0], headers.last_mut()) {
+            (b' ', Some((_, value))) | (b'\t', Some((_, value))) => value.extend_from_slice(line),
+            _ => {
+                let colon = line
+                    .find_byte(b':')
+                    .ok_or(Error::Parse(first_line_number + pos - 1, "invalid email header"))?;
+                headers.push((
+                    line[..colon].to_ascii_lowercase().into(),
+                    line[colon + 1..].trim_with(|c| c == ' ' || c == '\t').to_vec(),
+                ));
+            }
+        }
+    }
+    let header = |name: &str, line_number: usize| {
+        headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| decode_header(value))
+            .ok_or(Error::Parse(
+                line_number,
+                "the email lacks a 'From', 'Date' or 'Subject' header",
+            ))
+    };
+    let from = header("from", first_line_number)?;
+    let date = header("date", first_line_number)?;
+    let subject = clean_subject(header("subject", first_line_number)?.as_ref());
+    let (name, email) = match (from.find_byte(b'<'), from.rfind_byte(b'>')) {
+        (Some(start), Some(end)) if start < end => (
+            from[..start].trim_with(|c| c == ' ' || c == '"'),
+            from[start + 1..end].as_bstr(),
+        ),
+        _ => (from.as_bytes(), from.as_bstr()),
+    };
+    let epoch = Time {
+        time: 0,
+        offset: 0,
+        sign: Sign::Plus,
+    };
+    let author = owned::Signature {
+        name: name.as_bstr().to_owned(),
+        email: email.to_owned(),
+        time: date::parse(date.as_ref(), epoch)?,
+    };
+
+    let body_start = pos;
+    while let Some(line) = lines.get(pos) {
+        if line.trim_end_with(|c| c == '\n' || c == '\r') == b"---" {
+            break;
+        }
+        if line.starts_with(b"diff --git ") || line.starts_with(b"Index: ") {
+            pos -= 1;
+            break;
+        }
+        pos += 1;
+    }
+    let is_blank = |line: &&&[u8]| line.trim_with(char::is_whitespace).is_empty();
+    let body = &lines[body_start..pos.min(lines.len())];
+    let leading = body.iter().take_while(is_blank).count();
+    let trailing = body[leading..].iter().rev().take_while(is_blank).count();
+    let body = &body[leading..body.len() - trailing];
+    let mut message = subject.clone();
+    message.push(b'\n');
+    if !body.is_empty() {
+        message.push(b'\n');
+        for line in body {
+            message.extend_from_slice(line.trim_end_with(|c| c == '\n' || c == '\r'));
+            message.push(b'\n');
+        }
+    }
+
+    let patches = Parser {
+        lines: lines[(pos + 1).min(lines.len())..].to_vec(),
+        pos: 0,
+        first_line_number: first_line_number + pos + 1,
+    }
+    .patches()?;
+    Ok(Mail {
+        author,
+        subject,
+        message,
+        patches,
+    })
+}
+
+/// Remove prefixes like `Re:` and `[PATCH 1/2]` from `subject`, too, and collapse whitespace like `git mailinfo` does.
+fn clean_subject(mut subject: &[u8]) -> BString {
+    loop {
+        subject = subject.trim_start_with(|c| c.is_whitespace() || c == ':');
+        if subject.len() > 3 && subject[..3].eq_ignore_ascii_case(b"re:") {
+            subject = &subject[3..];
+        } else if let (Some(b'['), Some(end)) = (subject.first(), subject.find_byte(b']')) {
+            subject = &subject[end + 1..];
+        } else {
+            break;
+        }
+    }
+    let mut cleaned = BString::default();
+    for word in subject.fields() {
+        if !cleaned.is_empty() {
+            cleaned.push(b' ');
+        }
+        cleaned.extend_from_slice(word);
+    }
+    cleaned
+}
+
+/// Decode the encoded words of a header value like `=?UTF-8?q?J=C3=B6rg?=`, dropping the whitespace between them.
+fn decode_header(mut value: &[u8]) -> BString {
+    let mut decoded = BString::default();
+    let mut whitespace: &[u8] = b"";
+    let mut after_encoded_word = false;
+    while let Some(&byte) = value.first() {
+        if let Some((word, len)) = encoded_word(value) {
+            if !after_encoded_word {
+                decoded.extend_from_slice(whitespace);
+            }
+            decoded.extend_from_slice(&word);
+            whitespace = b"";
+            after_encoded_word = true;
+            value = &value[len..];
+        } else if byte == b' ' || byte == b'\t' {
+            let len = value.iter().take_while(|b| **b == b' ' || **b == b'\t').count();
+            whitespace = &value[..len];
+            value = &value[len..];
+        } else {
+            decoded.extend_from_slice(whitespace);
+            decoded.push(byte);
+            whitespace = b"";
+            after_encoded_word = false;
+            value = &value[1..];
+        }
+    }
+    decoded.extend_from_slice(whitespace);
+    decoded
+}
+
+/// Decode the encoded word at the start of `input` and return it along with its length.
+fn encoded_word(input: &[u8]) -> Option<(Vec<u8>, usize)> {
+    let rest = input.strip_prefix(b"=?")?;
+    let charset_end = rest.find_byte(b'?')?;
+    let rest = &rest[charset_end + 1..];
+    let (encoding, rest) = (rest.first()?.to_ascii_lowercase(), rest.get(1..)?.strip_prefix(b"?")?);
+    let text_end = rest.find(b"?=")?;
+    let text = &rest[..text_end];
+    let len = 2 + charset_end + 1 + 2 + text_end + 2;
+    match encoding {
+        b'q' => {
+            let mut decoded = Vec::with_capacity(text.len());
+            let mut bytes = text.iter();
+            while let Some(&byte) = bytes.next() {
+                match byte {
+                    b'_' => decoded.push(b' '),
+                    b'=' => {
+                        let hex = [*bytes.next()?, *bytes.next()?];
+                        decoded.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+                    }
+                    byte => decoded.push(byte),
+                }
+            }
+            Some((decoded, len))
+        }
+        b'b' => {
+            let mut decoded = Vec::with_capacity(text.len() * 3 / 4);
+            let (mut bits, mut count) = (0u32, 0);
+            for &byte in text.iter().take_while(|b| **b != b'=') {
+                let value = match byte {
+                    b'A'..=b'Z' => byte - b'A',
+                    b'a'..=b'z' => byte - b'a' + 26,
+                    b'0'..=b'9' => byte - b'0' + 52,
+                    b'+' => 62,
+                    b'/' => 63,
+                    _ => return None,
+                };
+                bits = (bits << 6) | u32::from(value);
+                count += 6;
+                if count >= 8 {
+                    count -= 8;
+                    decoded.push((bits >> count) as u8);
+                    bits &= (1 << count) - 1;
+                }
+            }
+            Some((decoded, len))
+        }
+        _ => None,
+    }
+}
+
+struct Parser<'a> {
+    lines: Vec<&'a [u8]>,
+    pos: usize,
+    first_line_number: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &'static str) -> Error {
+        Error::Parse(self.first_line_number + self.pos, message)
+    }
+
+    fn patches(mut self) -> Result<Vec<FilePatch>, Error> {
+        let mut patches = Vec::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if let Some(names) = line.strip_prefix(b"diff --git ") {
+                self.pos += 1;
+                patches.push(self.git_patch(names)?);
+            } else if line.starts_with(b"--- ")
+                && matches!(self.lines.get(self.pos + 1), Some(line) if line.starts_with(b"+++ "))
+            {
+                let mut patch = FilePatch::default();
+                self.paths(&mut patch)?;
+                if patch.old_path.is_none() && patch.new_path.is_none() {
+                    return Err(self.error("the paths of the patch are unknown"));
+                }
+                self.hunks(&mut patch)?;
+                patches.push(patch);
+            } else {
+                self.pos += 1;
+            }
+        }
+        Ok(patches)
+    }
+
+    /// Parse the extended header lines and hunks following a `diff --git` line with the given `names`.
+    fn git_patch(&mut self, names: &[u8]) -> Result<FilePatch, Error> {
+        let names = trim_newline(names);
+        let mut patch = FilePatch::default();
+        if let Some((old_path, new_path)) = git_header_paths(names) {
+            patch.old_path = Some(old_path);
+            patch.new_path = Some(new_path);
+        }
+        let (mut created, mut deleted) = (false, false);
+        while let Some(&line) = self.lines.get(self.pos) {
+            let line = trim_newline(line);
+            let value = |prefix: &str| line.strip_prefix(prefix.as_bytes());
+            let mode = |value: &[u8]| parse_mode(value).ok_or_else(|| self.error("invalid file mode"));
+            let path = |value: &[u8]| {
+                fast_import::parse_path(value, false)
+                    .map(|(path, _)| path)
+                    .ok_or_else(|| self.error("invalid path"))
+            };
+            if let Some(value) = value("old mode ") {
+                patch.old_mode = Some(mode(value)?);
+            } else if let Some(value) = value("new mode ") {
+                patch.new_mode = Some(mode(value)?);
+            } else if let Some(value) = value("deleted file mode ") {
+                patch.old_mode = Some(mode(value)?);
+                deleted = true;
+            } else if let Some(value) = value("new file mode ") {
+                patch.new_mode = Some(mode(value)?);
+                created = true;
+            } else if let Some(value) = value("rename from ").or_else(|| value("copy from ")) {
+                patch.old_path = Some(path(value)?);
+            } else if let Some(value) = value("rename to ") {
+                patch.new_path = Some(path(value)?);
+            } else if let Some(value) = value("copy to ") {
+                patch.new_path = Some(path(value)?);
+                patch.copy = true;
+            } else if let Some(value) = value("index ") {
+                if let Some(pos) = value.find_byte(b' ') {
+                    let mode = mode(&value[pos + 1..])?;
+                    patch.old_mode = patch.old_mode.or(Some(mode));
+                    patch.new_mode = patch.new_mode.or(Some(mode));
+                }
+            } else if line.starts_with(b"similarity index ") || line.starts_with(b"dissimilarity index ") {
+            } else if line.starts_with(b"GIT binary patch") || line.starts_with(b"Binary files ") {
+                patch.binary = true;
+            } else if line.starts_with(b"--- ") {
+                self.paths(&mut patch)?;
+                break;
+            } else {
+                break;
+            }
+            self.pos += 1;
+        }
+        if created {
+            patch.old_path = None;
+        }
+        if deleted {
+            patch.new_path = None;
+        }
+        if patch.old_path.is_none() && patch.new_path.is_none() {
+            return Err(self.error("the paths of the patch are unknown"));
+        }
+        self.hunks(&mut patch)?;
+        Ok(patch)
+    }
+
+    /// Parse the `---` and `+++` lines at the current position into the paths of `patch`.
+    fn paths(&mut self, patch: &mut FilePatch) -> Result<(), Error> {
+        let mut path = |prefix: &str| -> Result<Option<BString>, Error> {
+            let line = self
+                .lines
+                .get(self.pos)
+                .and_then(|line| trim_newline(line).strip_prefix(prefix.as_bytes()))
+                .ok_or_else(|| self.error("expected '---' and '+++' lines"))?;
+            self.pos += 1;
+            let path = if line.starts_with(b"\"") {
+                fast_import::parse_path(line, false).map(|(path, _)| path)
+            } else {
+                Some(line[..line.find_byte(b'\t').unwrap_or(line.len())].as_bstr().to_owned())
+            }
+            .ok_or_else(|| self.error("invalid path"))?;
+            Ok(if path == "/dev/null" {
+                None
+            } else {
+                Some(strip_component(&path).as_bstr().to_owned())
+            })
+        };
+        patch.old_path = path("--- ")?;
+        patch.new_path = path("+++ ")?;
+        Ok(())
+    }
+
+    fn hunks(&mut self, patch: &mut FilePatch) -> Result<(), Error> {
+        while let Some(header) = self.lines.get(self.pos).and_then(|line| line.strip_prefix(b"@@ -")) {
+            let (old, new) = hunk_header(header).ok_or_else(|| self.error("invalid hunk header"))?;
+            self.pos += 1;
+            let mut hunk = Hunk {
+                old_start: old.0,
+                old_lines: old.1,
+                new_start: new.0,
+                new_lines: new.1,
+                lines: Vec::new(),
+            };
+            let (mut old_left, mut new_left) = (old.1, new.1);
+            while old_left > 0 || new_left > 0 || self.at_incomplete_line() {
+                let line = *self
+                    .lines
+                    .get(self.pos)
+                    .ok_or_else(|| self.error("the hunk is truncated"))?;
+                let content = || line.get(1..).unwrap_or_default().as_bstr().to_owned();
+                let (old_lines, new_lines) = match line.first() {
+                    Some(b' ') => {
+                        hunk.lines.push(Line::Context(content()));
+                        (1, 1)
+                    }
+                    // Some mail clients remove the trailing space of empty context lines.
+                    Some(b'\n') | Some(b'\r') => {
+                        hunk.lines.push(Line::Context(line.as_bstr().to_owned()));
+                        (1, 1)
+                    }
+                    Some(b'-') => {
+                        hunk.lines.push(Line::Removed(content()));
+                        (1, 0)
+                    }
+                    Some(b'+') => {
+                        hunk.lines.push(Line::Added(content()));
+                        (0, 1)
+                    }
+                    Some(b'\\') => {
+                        let previous = match hunk.lines.last_mut() {
+                            Some(Line::Context(line)) | Some(Line::Removed(line)) | Some(Line::Added(line)) => line,
+                            None => return Err(self.error("no line lacks the newline")),
+                        };
+                        if previous.ends_with(b"\n") {
+                            previous.pop();
+                        }
+                        (0, 0)
+                    }
+                    _ => return Err(self.error("invalid line in hunk")),
+                };
+                old_left = old_left
+                    .checked_sub(old_lines)
+                    .ok_or_else(|| self.error("the hunk has too many old lines"))?;
+                new_left = new_left
+                    .checked_sub(new_lines)
+                    .ok_or_else(|| self.error("the hunk has too many new lines"))?;
+                self.pos += 1;
+            }
+            patch.hunks.push(hunk);
+        }
+        Ok(())
+    }
+
+    fn at_incomplete_line(&self) -> bool {
+        matches!(self.lines.get(self.pos), Some(line) if line.starts_with(b"\\"))
+    }
+}
+
+fn trim_newline(line: &[u8]) -> &[u8] {
+    line.strip_suffix(b"\n").unwrap_or(line)
+}
+
+fn strip_component(path: &[u8]) -> &[u8] {
+    path.find_byte(b'/').map_or(path, |pos| &path[pos + 1..])
+}
+
+fn parse_mode(mode: &[u8]) -> Option<TreeMode> {
+    Some(match mode {
+        b"100644" => TreeMode::Blob,
+        b"100755" => TreeMode::BlobExecutable,
+        b"120000" => TreeMode::Link,
+        b"160000" => TreeMode::Commit,
+        b"040000" => TreeMode::Tree,
+        _ => return None,
+    })
+}
+
+/// Return the paths of a `diff --git` line, which are only known if both are quoted or the same.
+fn git_header_paths(names: &[u8]) -> Option<(BString, BString)> {
+    if names.starts_with(b"\"") {
+        let (old, rest) = fast_import::parse_path(names, true)?;
+        let (new, _) = fast_import::parse_path(rest, false)?;
+        return Some((
+            strip_component(&old).as_bstr().to_owned(),
+            strip_component(&new).as_bstr().to_owned(),
+        ));
+    }
+    let middle = names.len() / 2;
+    let (old, new) = (&names[..middle], names.get(middle + 1..)?);
+    (names.len() % 2 == 1 && names[middle] == b' ' && strip_component(old) == strip_component(new))
+        .then(|| strip_component(old).as_bstr().to_owned())
+        .map(|path| (path.clone(), path))
+}
+
+/// Parse the ranges of a hunk header like `1,2 +1,3 @@` into the start and amount of old and new lines.
+fn hunk_header(header: &[u8]) -> Option<((usize, usize), (usize, usize))> {
+    let range = |range: &[u8]| -> Option<(usize, usize)> {
+        let mut numbers = range.splitn(2, |b| *b == b',');
+        let start = numbers.next()?.to_str().ok()?.parse().ok()?;
+        let len = match numbers.next() {
+            Some(len) => len.to_str().ok()?.parse().ok()?,
+            None => 1,
+        };
+        Some((start, len))
+    };
+    let mut ranges = header.splitn(3, |b| *b == b' ');
+    let old = range(ranges.next()?)?;
+    let new = range(ranges.next()?.strip_prefix(b"+")?)?;
+    ranges.next()?.starts_with(b"@@").then_some((old, new))
+}
+
+/// Apply `hunks` to `data` one after another, and return the patched data along with the hunks which didn't apply.
+pub fn patch(data: &[u8], hunks: &[Hunk], options: &Options) -> Patched {
+    let mut image: Vec<Vec<u8>> = data.lines_with_terminator().map(<[u8]>::to_vec).collect();
+    let rejected = hunks
+        .iter()
+        .filter(|hunk| !apply_hunk(&mut image, hunk, options))
+        .cloned()
+        .collect();
+    Patched {
+        data: image.concat(),
+        rejected,
+    }
+}
+
+/// Apply `hunk` to the lines of `image` and return true, or return false if it doesn't apply.
+fn apply_hunk(image: &mut Vec<Vec<u8>>, hunk: &Hunk, options: &Options) -> bool {
+    let is_context = |line: &&Line| matches!(line, Line::Context(_));
+    let mut lines = &hunk.lines[..];
+    let mut leading = lines.iter().take_while(is_context).count();
+    let mut trailing = lines.iter().rev().take_while(is_context).count();
+    let mut match_beginning = hunk.old_start <= 1;
+    let mut match_end = trailing == 0;
+    let mut hint = hunk.new_start.saturating_sub(1);
+    let min_context = options.min_context.unwrap_or(usize::MAX);
+    loop {
+        let preimage: Vec<&[u8]> = lines
+            .iter()
+            .filter_map(|line| match line {
+                Line::Context(line) | Line::Removed(line) => Some(line.as_slice()),
+                Line::Added(_) => None,
+            })
+            .collect();
+        if let Some(pos) = find_position(
+            image,
+            &preimage,
+            hint,
+            (match_beginning, match_end),
+            options.ignore_whitespace,
+        ) {
+            let mut matched = image[pos..pos + preimage.len()].iter();
+            let mut replacement = Vec::new();
+            for line in lines {
+                match line {
+                    Line::Context(_) => replacement.push(matched.next().expect("matched lines").clone()),
+                    Line::Removed(_) => {
+                        matched.next();
+                    }
+                    Line::Added(line) => replacement.push(line.to_vec()),
+                }
+            }
+            image.splice(pos..pos + preimage.len(), replacement);
+            return true;
+        }
+
+        // Like git, first give up the position at the start or end of the file, then the context lines, starting with
+        // the larger amount of them.
+        if leading <= min_context && trailing <= min_context {
+            return false;
+        }
+        if match_beginning || match_end {
+            match_beginning = false;
+            match_end = false;
+            continue;
+        }
+        if leading >= trailing {
+            lines = lines.get(1..).unwrap_or_default();
+            hint = hint.saturating_sub(1);
+            leading -= 1;
+        }
+        if trailing > leading {
+            lines = lines.split_last().map_or(lines, |(_, rest)| rest);
+            trailing -= 1;
+        }
+    }
+}
+
+/// Find the position of `preimage` in `image`, trying `hint` first and then the positions around it alternately, with
+/// those after it first, like git does.
+fn find_position(
+    image: &[Vec<u8>],
+    preimage: &[&[u8]],
+    hint: usize,
+    (match_beginning, match_end): (bool, bool),
+    ignore_whitespace: bool,
+) -> Option<usize> {
+    let last = image.len().checked_sub(preimage.len())?;
+    let matches_at = |pos: usize| {
+        pos <= last
+            && (!match_beginning || pos == 0)
+            && (!match_end || pos == last)
+            && image[pos..].iter().zip(preimage).all(|(line, expected)| {
+                line == expected || (ignore_whitespace && equal_ignoring_whitespace(line, expected))
+            })
+    };
+    let start = if match_beginning {
+        0
+    } else if match_end {
+        last
+    } else {
+        hint.min(image.len())
+    };
+    if matches_at(start) {
+        return Some(start);
+    }
+    for distance in 1.. {
+        let forwards = start + distance;
+        let backwards = start.checked_sub(distance);
+        if forwards > image.len() && backwards.is_none() {
+            break;
+        }
+        if forwards <= image.len() && matches_at(forwards) {
+            return Some(forwards);
+        }
+        if let Some(backwards) = backwards.filter(|pos| matches_at(*pos)) {
+            return Some(backwards);
+        }
+    }
+    None
+}
+
+/// Compare lines like `git apply --ignore-whitespace`, which ignores line endings and requires whitespace on both sides
+/// wherever one side has it, but not the same amount.
+fn equal_ignoring_whitespace(a: &[u8], b: &[u8]) -> bool {
+    let trim = |line: &[u8]| line.len() - line.iter().rev().take_while(|b| **b == b'\r' || **b == b'\n').count();
+    let (a, b) = (&a[..trim(a)], &b[..trim(b)]);
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i].is_ascii_whitespace() {
+            if !b[j].is_ascii_whitespace() {
+                return false;
+            }
+            i += a[i..].iter().take_while(|b| b.is_ascii_whitespace()).count();
+            j += b[j..].iter().take_while(|b| b.is_ascii_whitespace()).count();
+        } else if a[i] != b[j] {
+            return false;
+        } else {
+            i += 1;
+            j += 1;
+        }
+    }
+    i == a.len() && j == b.len()
+}
+
+/// A file with its mode and content, or `None` if it doesn't exist.
+type File = Option<(TreeMode, Vec<u8>)>;
+
+/// Apply `patches` to the files in `files`, which returns the file at a path, and collect the files to write by path.
+fn apply_all(
+    patches: &[FilePatch],
+    mut files: impl FnMut(&BStr) -> Result<File, Error>,
+    options: &Options,
+) -> Result<(BTreeMap<BString, File>, Vec<Rejected>), Error> {
+    let mut changes = BTreeMap::<BString, File>::new();
+    let mut rejected = Vec::new();
+    for patch in patches {
+        let mut file = |path: &BString| match changes.get(path) {
+            Some(file) => Ok(file.clone()),
+            None => files(path.as_ref()),
+        };
+        if patch.binary {
+            return Err(Error::Binary(patch.path().to_owned()));
+        }
+        let (old_mode, data) = match &patch.old_path {
+            Some(path) => file(path)?.ok_or_else(|| Error::Missing(path.clone()))?,
+            None => (TreeMode::Blob, Vec::new()),
+        };
+        if let Some(path) = patch
+            .new_path
+            .as_ref()
+            .filter(|path| Some(*path) != patch.old_path.as_ref())
+        {
+            if file(path)?.is_some() {
+                return Err(Error::Exists(path.clone()));
+            }
+        }
+
+        let patched = self::patch(&data, &patch.hunks, options);
+        let has_rejected_hunks = !patched.rejected.is_empty();
+        if has_rejected_hunks {
+            rejected.push(Rejected {
+                path: patch.path().to_owned(),
+                hunks: patched.rejected,
+            });
+            if !options.reject {
+                continue;
+            }
+        }
+        match (&patch.old_path, &patch.new_path) {
+            (Some(old_path), None) => {
+                if patched.data.is_empty() {
+                    changes.insert(old_path.clone(), None);
+                } else if !has_rejected_hunks {
+                    return Err(Error::NotEmpty(old_path.clone()));
+                }
+            }
+            (old_path, Some(new_path)) => {
+                if let Some(old_path) = old_path.as_ref().filter(|path| *path != new_path && !patch.copy) {
+                    changes.insert(old_path.clone(), None);
+                }
+                let mode = patch.new_mode.unwrap_or(old_mode);
+                changes.insert(new_path.clone(), Some((mode, patched.data)));
+            }
+            (None, None) => return Err(Error::UnknownPaths),
+        }
+    }
+    Ok((changes, rejected))
+}
+
+/// Apply `patches` to the files of `tree` and write the patched files and trees into `db`.
+///
+/// Objects are looked up using `find`, which places their data into the provided buffer and returns the decoded object.
+pub fn to_tree<Find>(
+    tree: owned::Id,
+    patches: &[FilePatch],
+    mut find: Find,
+    db: &loose::Db,
+    options: &Options,
+) -> Result<Outcome, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let mut buf = Vec::new();
+    let (changes, rejected) = apply_all(
+        patches,
+        |path| {
+            let (mode, id) = match entry_at(&mut find, &mut buf, tree, path)? {
+                Some(entry) => entry,
+                None => return Ok(None),
+            };
+            match find(id.to_borrowed(), &mut buf).ok_or(Error::Find(id))? {
+                borrowed::Object::Blob(blob) => Ok(Some((mode, blob.data.to_vec()))),
+                _ => Err(Error::NotABlob(id)),
+            }
+        },
+        options,
+    )?;
+    if !rejected.is_empty() && !options.reject {
+        return Ok(Outcome { tree, rejected });
+    }
+
+    let mut edits = Vec::with_capacity(changes.len());
+    for (path, file) in &changes {
+        let entry = match file {
+            Some((mode, data)) => Some((*mode, db.write_buf(Kind::Blob, data, HashKind::Sha1)?)),
+            None => None,
+        };
+        edits.push((path.as_slice(), entry));
+    }
+    let tree = match edit_tree(&mut find, &mut buf, db, Some(tree), &edits)? {
+        Some(tree) => tree,
+        None => db.write(
+            &owned::Object::Tree(owned::Tree { entries: Vec::new() }),
+            HashKind::Sha1,
+        )?,
+    };
+    Ok(Outcome { tree, rejected })
+}
+
+/// Return the entry at `path` in `tree`.
+fn entry_at<Find>(
+    find: &mut Find,
+    buf: &mut Vec<u8>,
+    mut tree: owned::Id,
+    path: &BStr,
+) -> Result<Option<merge::Entry>, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let mut components = path.split_str("/").peekable();
+    while let Some(component) = components.next() {
+        let entry = match find(tree.to_borrowed(), buf).ok_or(Error::Find(tree))? {
+            borrowed::Object::Tree(entries) => entries
+                .entries
+                .iter()
+                .find(|entry| entry.filename == component)
+                .map(|entry| (entry.mode, owned::Id::from_borrowed_sha1(entry.oid.sha1()))),
+            _ => return Err(Error::NotATree(tree)),
+        };
+        match entry {
+            Some(entry) if components.peek().is_none() => return Ok(Some(entry)),
+            Some((TreeMode::Tree, id)) => tree = id,
+            _ => return Ok(None),
+        }
+    }
+    Ok(None)
+}
+
+/// Apply `edits`, which are sorted by their path relative to `tree`, and write the changed trees unless they are empty.
+fn edit_tree<Find>(
+    find: &mut Find,
+    buf: &mut Vec<u8>,
+    db: &loose::Db,
+    tree: Option<owned::Id>,
+    mut edits: &[(&[u8], Option<merge::Entry>)],
+) -> Result<Option<owned::Id>, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let mut entries = BTreeMap::new();
+    if let Some(tree) = tree {
+        match find(tree.to_borrowed(), buf).ok_or(Error::Find(tree))? {
+            borrowed::Object::Tree(tree) => {
+                for entry in tree.entries {
+                    entries.insert(
+                        entry.filename.to_owned(),
+                        (entry.mode, owned::Id::from_borrowed_sha1(entry.oid.sha1())),
+                    );
+                }
+            }
+            _ => return Err(Error::NotATree(tree)),
+        }
+    }
+    let mut directories = Vec::new();
+    while let Some((path, entry)) = edits.first() {
+        match path.find_byte(b'/') {
+            None => {
+                match entry {
+                    Some(entry) => entries.insert(path.as_bstr().to_owned(), *entry),
+                    None => entries.remove(path.as_bstr()),
+                };
+                edits = &edits[1..];
+            }
+            Some(pos) => {
+                let directory = &path[..=pos];
+                let len = edits.iter().take_while(|(path, _)| path.starts_with(directory)).count();
+                let children: Vec<_> = edits[..len]
+                    .iter()
+                    .map(|(path, entry)| (&path[directory.len()..], *entry))
+                    .collect();
+                directories.push((directory[..pos].as_bstr(), children));
+                edits = &edits[len..];
+            }
+        }
+    }
+    // Directories are edited last so files replacing them are kept, and they only replace files they contain files.
+    for (name, children) in directories {
+        let subtree = entries
+            .get(name)
+            .and_then(|(mode, id)| (*mode == TreeMode::Tree).then_some(*id));
+        match edit_tree(find, buf, db, subtree, &children)? {
+            Some(id) => {
+                entries.insert(name.to_owned(), (TreeMode::Tree, id));
+            }
+            None if subtree.is_some() => {
+                entries.remove(name);
+            }
+            None => {}
+        }
+    }
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    let mut entries: Vec<_> = entries
+        .into_iter()
+        .map(|(filename, (mode, oid))| owned::tree::Entry { mode, filename, oid })
+        .collect();
+    entries.sort_by_key(merge::sort_key);
+    Ok(Some(db.write(
+        &owned::Object::Tree(owned::Tree { entries }),
+        HashKind::Sha1,
+    )?))
+}
+
+/// Apply `patches` to the files in the worktree at `root` and return the files with hunks which didn't apply.
+///
+/// Files are only changed if no hunk was rejected or [`Options::reject`] is set. Directories left empty by deleted files
+/// are removed.
+pub fn to_worktree(root: impl AsRef<Path>, patches: &[FilePatch], options: &Options) -> Result<Vec<Rejected>, Error> {
+    let root = root.as_ref();
+    let (changes, rejected) = apply_all(
+        patches,
+        |path| {
+            let path = worktree_path(root, path)?;
+            read_file(&path).map_err(|err| Error::Io(err, path))
+        },
+        options,
+    )?;
+    if !rejected.is_empty() && !options.reject {
+        return Ok(rejected);
+    }
+
+    let capabilities = worktree::Capabilities::from_git_dir(root.join(".git"));
+    let (deletions, writes): (Vec<_>, Vec<_>) = changes.into_iter().partition(|(_, file)| file.is_none());
+    for (path, _) in deletions {
+        let path = worktree_path(root, path.as_ref())?;
+        fs::remove_file(&path).map_err(|err| Error::Io(err, path.clone()))?;
+        for directory in path.ancestors().skip(1).take_while(|directory| *directory != root) {
+            if fs::remove_dir(directory).is_err() {
+                break;
+            }
+        }
+    }
+    for (path, file) in writes {
+        let path = worktree_path(root, path.as_ref())?;
+        let (mode, data) = file.expect("only writes are left");
+        let io = |err| Error::Io(err, path.clone());
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(io)?;
+        }
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(io(err)),
+            _ => {}
+        }
+        clone::write_blob(&path, &data, mode, &capabilities).map_err(io)?;
+    }
+    Ok(rejected)
+}
+
+/// Turn `path` into a path below `root`, rejecting paths which leave it, enter the git directory or lead through a
+/// symbolic link, which may point anywhere.
+fn worktree_path(root: &Path, path: &BStr) -> Result<PathBuf, Error> {
+    let unsafe_path = || Error::UnsafePath(path.to_owned());
+    let relative = git_features::path::to_native(path, Default::default()).map_err(|_| unsafe_path())?;
+    let mut leading = root.to_owned();
+    for component in relative.parent().into_iter().flat_map(Path::components) {
+        leading.push(component);
+        match fs::symlink_metadata(&leading) {
+            Ok(metadata) if metadata.file_type().is_symlink() => return Err(unsafe_path()),
+            _ => {}
+        }
+    }
+    Ok(root.join(relative))
+}
+
+#[cfg(unix)]
+fn read_file(path: &Path) -> io::Result<File> {
+    use std::os::unix::{ffi::OsStrExt, fs::PermissionsExt};
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    Ok(if metadata.file_type().is_symlink() {
+        Some((TreeMode::Link, fs::read_link(path)?.as_os_str().as_bytes().to_vec()))
+    } else if metadata.is_file() {
+        let mode = if metadata.permissions().mode() & 0o111 != 0 {
+            TreeMode::BlobExecutable
+        } else {
+            TreeMode::Blob
+        };
+        Some((mode, fs::read(path)?))
+    } else {
+        None
+    })
+}
+
+#[cfg(not(unix))]
+fn read_file(path: &Path) -> io::Result<File> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => Ok(Some((TreeMode::Blob, fs::read(path)?))),
+        Ok(_) => Ok(None),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
@@ -0,0 +1,201 @@
+//! Parse `.gitattributes` files and look up the state of attributes for paths.
+//!
+//! Like git, patterns without a slash match the file name at any depth below the directory containing the
+//! `.gitattributes` file, while all other patterns match the path relative to that directory. Files in deeper
+//! directories take precedence over those closer to the root, and later lines over earlier ones.
+//!
+//! Macros like `[attr]binary` are ignored, as are negative patterns, which git doesn't allow either.
+use git_object::bstr::{BStr, BString, ByteSlice};
+
+/// The state of an attribute for a path.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub enum State {
+    /// The attribute is set, as in `attr`.
+    Set,
+    /// The attribute is unset, as in `-attr`.
+    Unset,
+    /// The attribute is set to a value, as in `attr=value`.
+    Value(BString),
+    /// Nothing is known about the attribute, or it was reset with `!attr`.
+    Unspecified,
+}
+
+/// The state given to an attribute on a line.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Assignment {
+    /// The name of the attribute.
+    pub name: BString,
+    /// Its state for matching paths.
+    pub state: State,
+}
+
+/// A line of a `.gitattributes` file.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Line {
+    /// The pattern to match paths against.
+    pub pattern: BString,
+    /// The attributes to assign to matching paths.
+    pub assignments: Vec<Assignment>,
+}
+
+/// Parse the lines of a `.gitattributes` file, skipping empty lines, comments, macros and negative patterns.
+pub fn parse(data: &[u8]) -> impl Iterator<Item = Line> + '_ {
+    data.lines().filter_map(|line| {
+        let mut tokens = line
+            .split(|b: &u8| b.is_ascii_whitespace())
+            .filter(|token| !token.is_empty());
+        let pattern = tokens.next()?;
+        if pattern.starts_with(b"#") || pattern.starts_with(b"!") || pattern.starts_with(b"[attr]") {
+            return None;
+        }
+        let assignments = tokens
+            .map(|token| {
+                let (name, state) = match token.first() {
+                    Some(b'-') => (&token[1..], State::Unset),
+                    Some(b'!') => (&token[1..], State::Unspecified),
+                    _ => match token.find_byte(b'=') {
+                        Some(pos) => (&token[..pos], State::Value(token[pos + 1..].into())),
+                        None => (token, State::Set),
+                    },
+                };
+                Assignment {
+                    name: name.into(),
+                    state,
+                }
+            })
+            .collect();
+        Some(Line {
+            pattern: pattern.into(),
+            assignments,
+        })
+    })
+}
+
+/// The lines of all `.gitattributes` files known so far, to look up the attributes of paths.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Stack {
+    /// The directory of each file, like `dir/sub`, along with its lines, ordered by the length of the directory.
+    files: Vec<(BString, Vec<Line>)>,
+}
+
+impl Stack {
+    /// Add the contents of the `.gitattributes` file in `directory`, which is relative to the root of the tree and empty
+    /// for the root itself.
+    pub fn add(&mut self, directory: &BStr, data: &[u8]) -> &mut Self {
+        let directory = directory.trim_end_with(|c| c == '/');
+        let pos = self
+            .files
+            .iter()
+            .position(|(existing, _)| existing.len() > directory.len())
+            .unwrap_or(self.files.len());
+        self.files.insert(pos, (directory.into(), parse(data).collect()));
+        self
+    }
+
+    /// Return the state of the attribute `name` for the file at `path`, which is relative to the root of the tree.
+    pub fn state(&self, path: &BStr, name: &str) -> State {
+        let mut state = State::Unspecified;
+        for (directory, lines) in &self.files {
+            let relative = if directory.is_empty() {
+                path
+            } else if path.starts_with(directory) && path.get(directory.len()) == Some(&b'/') {
+                path[directory.len() + 1..].as_bstr()
+            } else {
+                continue;
+            };
+            for line in lines
+                .iter()
+                .filter(|line| pattern_matches(line.pattern.as_ref(), relative))
+            {
+                for assignment in line.assignments.iter().filter(|a| a.name == name) {
+                    state = assignment.state.clone();
+                }
+            }
+        }
+        state
+    }
+
+    /// Return true if the attribute `name` is set for the file at `path`.
+    pub fn is_set(&self, path: &BStr, name: &str) -> bool {
+        self.state(path, name) == State::Set
+    }
+}
+
+/// Match `path` against `pattern` like git does for attributes, with patterns lacking a slash matching the file name.
+pub(crate) fn pattern_matches(pattern: &BStr, path: &BStr) -> bool {
+    match pattern.strip_prefix(b"/") {
+        Some(anchored) => wildmatch(anchored, path),
+        None if pattern.find_byte(b'/').is_none() => {
+            let name = path.rfind_byte(b'/').map(|pos| &path[pos + 1..]).unwrap_or(path);
+            wildmatch(pattern, name)
+        }
+        None => wildmatch(pattern, path),
+    }
+}
+
+/// Match `text` against a glob `pattern` where `*`, `?` and `[…]` don't match slashes, but `**` between slashes matches
+/// any amount of directories.
+pub(crate) fn wildmatch(pattern: &[u8], text: &[u8]) -> bool {
+    fn class_matches(class: &[u8], c: u8) -> Option<(bool, usize)> {
+        let mut i = 0;
+        let negated = matches!(class.first(), Some(b'!') | Some(b'^'));
+        if negated {
+            i += 1;
+        }
+        let mut matched = false;
+        let mut first = true;
+        while i < class.len() {
+            let start = class[i];
+            if start == b']' && !first {
+                return Some((matched != negated, i + 1));
+            }
+            first = false;
+            if class.get(i + 1) == Some(&b'-') && matches!(class.get(i + 2), Some(b) if *b != b']') {
+                let end = class[i + 2];
+                matched |= start <= c && c <= end;
+                i += 3;
+            } else {
+                matched |= start == c;
+                i += 1;
+            }
+        }
+        None
+    }
+
+    fn matches(p: &[u8], t: &[u8], at_component_start: bool) -> bool {
+        match p.first() {
+            None => t.is_empty(),
+            Some(b'*') if p.starts_with(b"**") && at_component_start && (p.len() == 2 || p[2] == b'/') => {
+                if p.len() == 2 {
+                    return true;
+                }
+                let rest = &p[3..];
+                matches(rest, t, true)
+                    || t.iter()
+                        .enumerate()
+                        .any(|(i, c)| *c == b'/' && matches(rest, &t[i + 1..], true))
+            }
+            Some(b'*') => {
+                let p = &p[1..];
+                for skip in 0..=t.len() {
+                    if matches(p, &t[skip..], false) {
+                        return true;
+                    }
+                    if t.get(skip) == Some(&b'/') {
+                        break;
+                    }
+                }
+                false
+            }
+            Some(b'?') => matches!(t.first(), Some(c) if *c != b'/') && matches(&p[1..], &t[1..], false),
+            Some(b'[') => match (t.first(), class_matches(&p[1..], t.first().copied().unwrap_or(0))) {
+                (Some(c), Some((true, consumed))) if *c != b'/' => matches(&p[1 + consumed..], &t[1..], false),
+                (Some(c), None) => *c == b'[' && matches(&p[1..], &t[1..], false),
+                _ => false,
+            },
+            Some(b'\\') if p.len() > 1 => t.first() == Some(&p[1]) && matches(&p[2..], &t[1..], false),
+            Some(c) => t.first() == Some(c) && matches(&p[1..], &t[1..], *c == b'/'),
+        }
+    }
+    matches(pattern, text, true)
+}
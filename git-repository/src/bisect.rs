@@ -0,0 +1,478 @@
+//! Find the commit which introduced a change by bisecting the commits between good and bad ones, like `git bisect`.
+//!
+//! The state of a bisection is kept where git keeps it, in `refs/bisect/bad`, `refs/bisect/good-<id>` and
+//! `refs/bisect/skip-<id>` for the marked commits, and in the `BISECT_*` files of the git directory with all marks
+//! recorded in `BISECT_LOG`. This allows to continue or inspect a bisection with git.
+//!
+//! The commit to test next is chosen like git does, as the one reaching closest to half of the commits which are
+//! reachable from the bad commit but not from any good one. Unlike git, it isn't checked out, and if the best commit
+//! was skipped, the next best one is chosen instead of a pseudo-random one further away from it. Good commits are
+//! expected to be ancestors of the bad one, as merge bases aren't tested first like git does.
+use crate::remote::update;
+use git_features::lock;
+use git_object::{
+    borrowed,
+    bstr::{BString, ByteSlice},
+    owned,
+};
+use quick_error::quick_error;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Find(id: owned::Id) {
+            display("Object {} could not be found", id)
+        }
+        NotACommit(id: owned::Id) {
+            display("Object {} was expected to be a commit", id)
+        }
+        Unborn {
+            display("HEAD doesn't point to a commit yet")
+        }
+        InProgress(path: PathBuf) {
+            display("A bisection is in progress already, as '{}' exists", path.display())
+        }
+        NotInProgress(path: PathBuf) {
+            display("No bisection is in progress, as '{}' doesn't exist", path.display())
+        }
+        Corrupt(path: PathBuf) {
+            display("The bisect ref at '{}' could not be parsed", path.display())
+        }
+        BothGoodAndBad(id: owned::Id) {
+            display("The bad commit {} is reachable from a good commit", id)
+        }
+        Ref(err: update::Error) {
+            display("HEAD could not be read")
+            from()
+            source(err)
+        }
+        Lock(err: lock::Error) {
+            display("Could not lock a file to change it")
+            from()
+            source(err)
+        }
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not access '{}'", path.display())
+            source(err)
+        }
+    }
+}
+
+/// The directory within the git directory holding the refs of the marked commits.
+pub const REF_DIR: &str = "refs/bisect";
+const START: &str = "BISECT_START";
+const LOG: &str = "BISECT_LOG";
+const EXPECTED_REV: &str = "BISECT_EXPECTED_REV";
+const STATE_FILES: &[&str] = &[
+    START,
+    LOG,
+    EXPECTED_REV,
+    "BISECT_TERMS",
+    "BISECT_NAMES",
+    "BISECT_ANCESTORS_OK",
+];
+
+/// How a tested commit is marked.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum Mark {
+    /// The commit has the change, so it or one of its ancestors introduced it.
+    Bad,
+    /// The commit doesn't have the change, and neither have its ancestors.
+    Good,
+    /// The commit can't be tested and should be avoided.
+    Skip,
+}
+
+impl Mark {
+    /// The name of the mark, as used by `git bisect` and in `BISECT_LOG`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mark::Bad => "bad",
+            Mark::Good => "good",
+            Mark::Skip => "skip",
+        }
+    }
+}
+
+/// The commits marked so far, as stored in `refs/bisect`.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default)]
+pub struct State {
+    /// The latest commit marked bad.
+    pub bad: Option<owned::Id>,
+    /// All commits marked good, ordered by id.
+    pub good: Vec<owned::Id>,
+    /// All commits which were skipped, ordered by id.
+    pub skipped: Vec<owned::Id>,
+}
+
+/// What to do next, as returned by [`next()`] and [`mark()`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Next {
+    /// A bad commit and at least one good one need to be marked to start bisecting.
+    Waiting,
+    /// Test `commit`, and [mark][mark()] it.
+    Test {
+        /// The commit to test.
+        commit: owned::Id,
+        /// The amount of commits left to test after this one if it's marked bad, which is roughly half of the
+        /// commits left in either case.
+        remaining: usize,
+        /// The estimated amount of steps needed after this one, as shown by git.
+        steps: usize,
+    },
+    /// The bisection is done and found the commit which introduced the change.
+    FirstBad(owned::Id),
+    /// Only skipped commits are left, so the first bad commit could be any of these, starting with the bad commit.
+    OnlySkipped(Vec<owned::Id>),
+}
+
+/// Start a bisection in the repository at `git_dir`, remembering the branch `HEAD` points to for [`reset()`].
+pub fn start(git_dir: impl AsRef<Path>) -> Result<(), Error> {
+    let git_dir = git_dir.as_ref();
+    let path = git_dir.join(START);
+    if path.exists() {
+        return Err(Error::InProgress(path));
+    }
+    let mut head = match update::resolve(git_dir, "HEAD".into())? {
+        (_, None) => return Err(Error::Unborn),
+        (name, Some(_)) if name.starts_with(b"refs/heads/") => BString::from(&name[b"refs/heads/".len()..]),
+        (_, Some(id)) => id.to_sha1_hex().as_bstr().to_owned(),
+    };
+    head.push(b'\n');
+    write(&git_dir.join("BISECT_TERMS"), b"bad\ngood\n")?;
+    write(&git_dir.join("BISECT_NAMES"), b"\n")?;
+    write(&git_dir.join(LOG), b"git bisect start\n")?;
+    write(&path, &head)?;
+    append_status(git_dir, &State::default())
+}
+
+/// Mark `commit` in the bisection of the repository at `git_dir` and return what to do next.
+///
+/// Like git, marking a commit bad replaces the previous bad commit, and the mark along with what's next is recorded in
+/// `BISECT_LOG`. `find` is used to lookup commits, and places the object data into the provided buffer and returns the
+/// decoded object.
+pub fn mark<Find>(git_dir: impl AsRef<Path>, mark: Mark, commit: owned::Id, mut find: Find) -> Result<Next, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let git_dir = git_dir.as_ref();
+    let start = git_dir.join(START);
+    if !start.is_file() {
+        return Err(Error::NotInProgress(start));
+    }
+    let name = match mark {
+        Mark::Bad => "bad".to_owned(),
+        Mark::Good | Mark::Skip => format!("{}-{}", mark.as_str(), commit),
+    };
+    let mut ref_content = commit.to_sha1_hex().to_vec();
+    ref_content.push(b'\n');
+    write(&git_dir.join(REF_DIR).join(name), &ref_content)?;
+
+    let mut buf = Vec::new();
+    let mut log = format!("# {}: ", mark.as_str()).into_bytes();
+    describe(&mut find, &mut buf, commit, &mut log)?;
+    log.extend_from_slice(format!("git bisect {} {}\n", mark.as_str(), commit).as_bytes());
+    append(git_dir, &log)?;
+
+    let state = state(git_dir)?;
+    let next = next_in(&state, &mut find)?;
+    let mut log = Vec::new();
+    match &next {
+        Next::Waiting => return append_status(git_dir, &state).map(|_| next),
+        Next::Test { commit, .. } => write(&git_dir.join(EXPECTED_REV), format!("{}\n", commit).as_bytes())?,
+        Next::FirstBad(commit) => {
+            log.extend_from_slice(b"# first bad commit: ");
+            describe(&mut find, &mut buf, *commit, &mut log)?;
+        }
+        Next::OnlySkipped(commits) => {
+            log.extend_from_slice(b"# only skipped commits left to test\n");
+            for commit in commits {
+                log.extend_from_slice(b"# possible first bad commit: ");
+                describe(&mut find, &mut buf, *commit, &mut log)?;
+            }
+        }
+    }
+    append(git_dir, &log)?;
+    Ok(next)
+}
+
+/// Return the commits marked in the bisection of the repository at `git_dir`.
+pub fn state(git_dir: impl AsRef<Path>) -> Result<State, Error> {
+    let dir = git_dir.as_ref().join(REF_DIR);
+    let mut state = State::default();
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(state),
+        Err(err) => return Err(Error::Io(err, dir)),
+    };
+    for entry in entries {
+        let path = entry.map_err(|err| Error::Io(err, dir.clone()))?.path();
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned());
+        let list = match name.as_deref() {
+            Some("bad") => None,
+            Some(name) if name.starts_with("good-") => Some(&mut state.good),
+            Some(name) if name.starts_with("skip-") => Some(&mut state.skipped),
+            _ => continue,
+        };
+        let content = fs::read(&path).map_err(|err| Error::Io(err, path.clone()))?;
+        let id = owned::Id::from_40_bytes_in_hex(content.trim_end_with(char::is_whitespace))
+            .map_err(|_| Error::Corrupt(path.clone()))?;
+        match list {
+            Some(list) => list.push(id),
+            None => state.bad = Some(id),
+        }
+    }
+    state.good.sort();
+    state.skipped.sort();
+    Ok(state)
+}
+
+/// Return what to do next in the bisection of the repository at `git_dir`, without changing it.
+///
+/// `find` is used to lookup commits, and places the object data into the provided buffer and returns the decoded
+/// object.
+pub fn next<Find>(git_dir: impl AsRef<Path>, mut find: Find) -> Result<Next, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    next_in(&state(git_dir)?, &mut find)
+}
+
+/// End the bisection in the repository at `git_dir` by removing all of its refs and files, and return the branch name
+/// or commit `HEAD` pointed to when it was started, to check it out again.
+pub fn reset(git_dir: impl AsRef<Path>) -> Result<BString, Error> {
+    let git_dir = git_dir.as_ref();
+    let start = git_dir.join(START);
+    let head = match fs::read(&start) {
+        Ok(head) => head.trim_end_with(char::is_whitespace).as_bstr().to_owned(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Err(Error::NotInProgress(start)),
+        Err(err) => return Err(Error::Io(err, start)),
+    };
+    let dir = git_dir.join(REF_DIR);
+    match fs::remove_dir_all(&dir) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(Error::Io(err, dir)),
+        _ => {}
+    }
+    for name in STATE_FILES {
+        let path = git_dir.join(name);
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(Error::Io(err, path)),
+            _ => {}
+        }
+    }
+    Ok(head)
+}
+
+fn next_in<Find>(state: &State, find: &mut Find) -> Result<Next, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let bad = match state.bad {
+        Some(bad) if !state.good.is_empty() => bad,
+        _ => return Ok(Next::Waiting),
+    };
+    let mut buf = Vec::new();
+    let mut good_ancestors = HashSet::new();
+    let mut stack = state.good.clone();
+    while let Some(id) = stack.pop() {
+        if good_ancestors.insert(id) {
+            stack.extend(lookup(find, &mut buf, id)?.1);
+        }
+    }
+    if good_ancestors.contains(&bad) {
+        return Err(Error::BothGoodAndBad(bad));
+    }
+
+    // Like `git rev-list <bad> ^<good>…`, newest first, and in the order they were found in if their time is equal.
+    let mut commits = Vec::new();
+    let mut queue = BinaryHeap::new();
+    let mut seen = HashSet::new();
+    queue.push((lookup(find, &mut buf, bad)?.0, Reverse(0), bad));
+    seen.insert(bad);
+    while let Some((_, _, id)) = queue.pop() {
+        let parents: Vec<_> = lookup(find, &mut buf, id)?
+            .1
+            .into_iter()
+            .filter(|parent| !good_ancestors.contains(parent))
+            .collect();
+        for parent in &parents {
+            if seen.insert(*parent) {
+                queue.push((lookup(find, &mut buf, *parent)?.0, Reverse(seen.len()), *parent));
+            }
+        }
+        commits.push((id, parents));
+    }
+    // Git looks at them oldest first.
+    commits.reverse();
+    let index: HashMap<_, _> = commits
+        .iter()
+        .enumerate()
+        .map(|(index, (id, _))| (*id, index))
+        .collect();
+    let parents: Vec<Vec<usize>> = commits
+        .iter()
+        .map(|(_, parents)| parents.iter().map(|parent| index[parent]).collect())
+        .collect();
+
+    let skipped: HashSet<_> = state.skipped.iter().copied().collect();
+    let skipping = commits.iter().any(|(id, _)| skipped.contains(id));
+    let nr = commits.len();
+    let (weights, halfway) = weights(&parents, !skipping);
+    let chosen = halfway.or_else(|| {
+        let distance = |index: &usize| {
+            let weight = weights[*index].expect("all weights are known without a halfway commit");
+            weight.min(nr - weight)
+        };
+        let mut by_distance: Vec<_> = (0..nr).collect();
+        by_distance.sort_by_key(|index| Reverse(distance(index)));
+        by_distance
+            .into_iter()
+            .find(|index| !skipped.contains(&commits[*index].0))
+    });
+
+    Ok(match chosen {
+        Some(index) if commits[index].0 != bad => Next::Test {
+            commit: commits[index].0,
+            remaining: nr - weights[index].expect("the weight of the chosen commit is known") - 1,
+            steps: estimate_steps(nr),
+        },
+        _ => {
+            let possible: Vec<_> = commits
+                .iter()
+                .rev()
+                .map(|(id, _)| *id)
+                .filter(|id| *id == bad || skipped.contains(id))
+                .collect();
+            if chosen.is_some() && possible.len() == 1 {
+                Next::FirstBad(bad)
+            } else {
+                Next::OnlySkipped(possible)
+            }
+        }
+    })
+}
+
+/// Compute how many of the commits each commit reaches, including itself, in the order git does, and return the first
+/// commit reaching about half of them if `find_halfway` is set, which makes the weights of commits after it unknown.
+fn weights(parents: &[Vec<usize>], find_halfway: bool) -> (Vec<Option<usize>>, Option<usize>) {
+    let nr = parents.len();
+    let is_halfway = |weight: usize| {
+        let diff = (2 * weight as isize - nr as isize).abs();
+        find_halfway && (diff <= 1 || diff < (nr / 1024) as isize)
+    };
+    let mut weights: Vec<_> = parents.iter().map(|parents| parents.is_empty().then_some(1)).collect();
+    let mut counted = weights.iter().filter(|weight| weight.is_some()).count();
+
+    // Merges reach their parents' commits more than once, so they are counted one by one.
+    for (index, commit_parents) in parents.iter().enumerate() {
+        if commit_parents.len() < 2 {
+            continue;
+        }
+        let mut reached = HashSet::new();
+        let mut stack = vec![index];
+        while let Some(index) = stack.pop() {
+            if reached.insert(index) {
+                stack.extend(&parents[index]);
+            }
+        }
+        weights[index] = Some(reached.len());
+        if is_halfway(reached.len()) {
+            return (weights, Some(index));
+        }
+        counted += 1;
+    }
+
+    // Commits with a single parent reach one commit more than their parent.
+    while counted < nr {
+        for index in 0..nr {
+            if weights[index].is_some() {
+                continue;
+            }
+            if let Some(weight) = parents[index].iter().find_map(|parent| weights[*parent]) {
+                weights[index] = Some(weight + 1);
+                counted += 1;
+                if is_halfway(weight + 1) {
+                    return (weights, Some(index));
+                }
+            }
+        }
+    }
+    (weights, None)
+}
+
+/// Estimate the amount of steps to bisect `all` commits like git does.
+fn estimate_steps(all: usize) -> usize {
+    if all < 3 {
+        return 0;
+    }
+    let n = (usize::BITS - 1 - all.leading_zeros()) as usize;
+    let e = 1 << n;
+    if e < 3 * (all - e) {
+        n
+    } else {
+        n - 1
+    }
+}
+
+/// Return the commit time and the parents of the commit `id`.
+fn lookup<Find>(find: &mut Find, buf: &mut Vec<u8>, id: owned::Id) -> Result<(u32, Vec<owned::Id>), Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    match find(id.to_borrowed(), buf) {
+        Some(borrowed::Object::Commit(commit)) => Ok((commit.committer.time.time, commit.parents().collect())),
+        Some(_) => Err(Error::NotACommit(id)),
+        None => Err(Error::Find(id)),
+    }
+}
+
+/// Append `[<id>] <subject>` of the commit `id` and a newline to `out`, as used in `BISECT_LOG`.
+fn describe<Find>(find: &mut Find, buf: &mut Vec<u8>, id: owned::Id, out: &mut Vec<u8>) -> Result<(), Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let subject = match find(id.to_borrowed(), buf) {
+        Some(borrowed::Object::Commit(commit)) => commit.message.lines().next().unwrap_or_default(),
+        Some(_) => return Err(Error::NotACommit(id)),
+        None => return Err(Error::Find(id)),
+    };
+    out.extend_from_slice(format!("[{}] ", id).as_bytes());
+    out.extend_from_slice(subject);
+    out.push(b'\n');
+    Ok(())
+}
+
+/// Record that marks are missing in `BISECT_LOG`, like git does.
+fn append_status(git_dir: &Path, state: &State) -> Result<(), Error> {
+    let status = match (state.bad, state.good.len()) {
+        (None, 0) => "waiting for both good and bad commits".to_owned(),
+        (Some(_), 0) => "waiting for good commit(s), bad commit known".to_owned(),
+        (_, 1) => "waiting for bad commit, 1 good commit known".to_owned(),
+        (_, good) => format!("waiting for bad commit, {} good commits known", good),
+    };
+    append(git_dir, format!("# status: {}\n", status).as_bytes())
+}
+
+fn append(git_dir: &Path, content: &[u8]) -> Result<(), Error> {
+    let path = git_dir.join(LOG);
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(content))
+        .map_err(|err| Error::Io(err, path))
+}
+
+/// Replace the file at `path` with `content` by renaming a lock file over it.
+fn write(path: &Path, content: &[u8]) -> Result<(), Error> {
+    let mut lock = lock::File::acquire(path, Default::default())?;
+    lock.write_all(content).map_err(|err| Error::Io(err, path.to_owned()))?;
+    lock.commit()?;
+    Ok(())
+}
@@ -0,0 +1,421 @@
+//! Find the commit which introduced each line of a file, similar to `git blame`.
+//!
+//! Starting at a commit, the lines of the file are passed on to the parents which contain them unchanged, as determined
+//! by a line diff against the same path in each parent. Lines which aren't found in any parent were introduced by the
+//! commit. Commits are visited newest first, like git does, and renames aren't followed.
+use git_object::{
+    borrowed,
+    bstr::{BStr, BString, ByteSlice},
+    owned, TreeMode,
+};
+use quick_error::quick_error;
+use std::{
+    collections::{hash_map, BinaryHeap, HashMap},
+    ops::Range,
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Find(id: owned::Id) {
+            display("Object {} could not be found", id)
+        }
+        NotACommit(id: owned::Id) {
+            display("Object {} was expected to be a commit", id)
+        }
+        NotATree(id: owned::Id) {
+            display("Object {} was expected to be a tree", id)
+        }
+        NotABlob(id: owned::Id) {
+            display("Object {} was expected to be a blob", id)
+        }
+        NotFound(path: BString, commit: owned::Id) {
+            display("The file '{}' does not exist in commit {}", path, commit)
+        }
+        InvalidRange(range: Range<u32>, lines: u32) {
+            display("The lines {}..{} are out of bounds or empty, as the file has {} lines", range.start, range.end, lines)
+        }
+    }
+}
+
+/// The options for [`file()`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Options {
+    /// Only follow the first parent of merge commits, blaming all lines brought in by a merge on the merge commit itself.
+    pub first_parent: bool,
+    /// The zero-based lines of the file to blame, or `None` to blame all of them.
+    pub range: Option<Range<u32>>,
+}
+
+/// A run of consecutive lines of the blamed file, introduced by the same commit.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hunk {
+    /// The zero-based lines in the blamed file.
+    pub lines: Range<u32>,
+    /// The commit which introduced the lines.
+    pub commit: owned::Id,
+    /// The zero-based lines in the file as it was in `commit`.
+    pub source_lines: Range<u32>,
+}
+
+/// The result of a successful [`file()`] call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// The hunks covering all blamed lines, ordered by their position in the file.
+    pub hunks: Vec<Hunk>,
+    /// The amount of commits we traversed.
+    pub commits_seen: u32,
+}
+
+/// Lines of the blamed file, still looking for the commit which introduced them.
+#[derive(Debug, Clone, Copy)]
+struct Chunk {
+    /// The first line in the blamed file.
+    start: u32,
+    /// The first line in the file of the commit currently suspected to introduce them.
+    source_start: u32,
+    len: u32,
+}
+
+/// A commit which might have introduced lines, along with the version of the file it contains.
+struct Suspect {
+    blob: owned::Id,
+    chunks: Vec<Chunk>,
+}
+
+/// Find the commits which introduced the lines of the file at `path`, like `dir/file`, as it is in `commit`.
+///
+/// `find` is used to lookup commits, trees and blobs.
+pub fn file<Find>(path: &BStr, commit: borrowed::Id<'_>, mut find: Find, options: Options) -> Result<Outcome, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let commit = owned::Id::from_borrowed_sha1(commit.sha1());
+    let mut buf = Vec::new();
+    let tree = find_commit(&mut find, &mut buf, commit)?.tree();
+    let blob = blob_at(&mut find, &mut buf, tree, path)?.ok_or_else(|| Error::NotFound(path.to_owned(), commit))?;
+    let num_lines = find_blob(&mut find, &mut buf, blob)?.lines_with_terminator().count() as u32;
+    let range = match options.range {
+        Some(range) if range.start >= range.end || range.end > num_lines => {
+            return Err(Error::InvalidRange(range, num_lines))
+        }
+        Some(range) => range,
+        None => 0..num_lines,
+    };
+
+    let mut hunks = Vec::new();
+    let mut commits_seen = 0;
+    let mut queue = BinaryHeap::new();
+    let mut suspects = HashMap::new();
+    if !range.is_empty() {
+        queue.push((commit_time(&mut find, &mut buf, commit)?, commit));
+        suspects.insert(
+            commit,
+            Suspect {
+                blob,
+                chunks: vec![Chunk {
+                    start: range.start,
+                    source_start: range.start,
+                    len: range.end - range.start,
+                }],
+            },
+        );
+    }
+
+    while let Some((_, id)) = queue.pop() {
+        commits_seen += 1;
+        let Suspect { blob, mut chunks } = suspects.remove(&id).expect("queued commits are suspects");
+        let parents: Vec<_> = {
+            let commit = find_commit(&mut find, &mut buf, id)?;
+            let parents = commit.parents();
+            if options.first_parent {
+                parents.take(1).collect()
+            } else {
+                parents.collect()
+            }
+        };
+        let mut parent_blobs = Vec::with_capacity(parents.len());
+        for parent in parents {
+            let tree = find_commit(&mut find, &mut buf, parent)?.tree();
+            if let Some(parent_blob) = blob_at(&mut find, &mut buf, tree, path)? {
+                parent_blobs.push((parent, parent_blob));
+            }
+        }
+
+        // Like git, pass everything to the first parent with the same file, as nothing was changed compared to it.
+        if let Some(&(parent, parent_blob)) = parent_blobs.iter().find(|(_, parent_blob)| *parent_blob == blob) {
+            let chunks = std::mem::take(&mut chunks);
+            blame_on(
+                &mut find,
+                &mut buf,
+                parent,
+                parent_blob,
+                chunks,
+                &mut suspects,
+                &mut queue,
+            )?;
+        }
+        if !chunks.is_empty() && !parent_blobs.is_empty() {
+            let data = find_blob(&mut find, &mut buf, blob)?.to_owned();
+            let lines: Vec<_> = data.lines_with_terminator().collect();
+            for (parent, parent_blob) in parent_blobs {
+                if chunks.is_empty() {
+                    break;
+                }
+                let blocks = {
+                    let parent_data = find_blob(&mut find, &mut buf, parent_blob)?;
+                    let parent_lines: Vec<_> = parent_data.lines_with_terminator().collect();
+                    matching_blocks(&parent_lines, &lines)
+                };
+                let (passed, kept) = split(&chunks, &blocks);
+                chunks = kept;
+                blame_on(
+                    &mut find,
+                    &mut buf,
+                    parent,
+                    parent_blob,
+                    passed,
+                    &mut suspects,
+                    &mut queue,
+                )?;
+            }
+        }
+        hunks.extend(chunks.into_iter().map(|chunk| Hunk {
+            lines: chunk.start..chunk.start + chunk.len,
+            commit: id,
+            source_lines: chunk.source_start..chunk.source_start + chunk.len,
+        }));
+    }
+
+    hunks.sort_by_key(|hunk| hunk.lines.start);
+    let mut merged: Vec<Hunk> = Vec::with_capacity(hunks.len());
+    for hunk in hunks {
+        match merged.last_mut() {
+            Some(last)
+                if last.commit == hunk.commit
+                    && last.lines.end == hunk.lines.start
+                    && last.source_lines.end == hunk.source_lines.start =>
+            {
+                last.lines.end = hunk.lines.end;
+                last.source_lines.end = hunk.source_lines.end;
+            }
+            _ => merged.push(hunk),
+        }
+    }
+    Ok(Outcome {
+        hunks: merged,
+        commits_seen,
+    })
+}
+
+/// Make `commit` the suspect of `chunks`, queueing it if needed.
+fn blame_on<Find>(
+    find: &mut Find,
+    buf: &mut Vec<u8>,
+    commit: owned::Id,
+    blob: owned::Id,
+    chunks: Vec<Chunk>,
+    suspects: &mut HashMap<owned::Id, Suspect>,
+    queue: &mut BinaryHeap<(u32, owned::Id)>,
+) -> Result<(), Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    if chunks.is_empty() {
+        return Ok(());
+    }
+    match suspects.entry(commit) {
+        hash_map::Entry::Occupied(mut entry) => entry.get_mut().chunks.extend(chunks),
+        hash_map::Entry::Vacant(entry) => {
+            entry.insert(Suspect { blob, chunks });
+            queue.push((commit_time(find, buf, commit)?, commit));
+        }
+    }
+    Ok(())
+}
+
+/// Split `chunks` into the parts found in the parent according to `blocks`, translated to the lines of the parent, and
+/// the parts which aren't.
+fn split(chunks: &[Chunk], blocks: &[(u32, u32, u32)]) -> (Vec<Chunk>, Vec<Chunk>) {
+    let mut passed = Vec::new();
+    let mut kept = Vec::new();
+    for chunk in chunks {
+        let mut pos = chunk.source_start;
+        let end = chunk.source_start + chunk.len;
+        for &(parent_start, start, len) in blocks {
+            let overlap_start = pos.max(start);
+            let overlap_end = end.min(start + len);
+            if overlap_start >= overlap_end {
+                continue;
+            }
+            if pos < overlap_start {
+                kept.push(Chunk {
+                    start: chunk.start + (pos - chunk.source_start),
+                    source_start: pos,
+                    len: overlap_start - pos,
+                });
+            }
+            passed.push(Chunk {
+                start: chunk.start + (overlap_start - chunk.source_start),
+                source_start: parent_start + (overlap_start - start),
+                len: overlap_end - overlap_start,
+            });
+            pos = overlap_end;
+        }
+        if pos < end {
+            kept.push(Chunk {
+                start: chunk.start + (pos - chunk.source_start),
+                source_start: pos,
+                len: end - pos,
+            });
+        }
+    }
+    (passed, kept)
+}
+
+/// Return the runs of equal lines in `old` and `new` as `(old_start, new_start, len)`, ordered by their position.
+pub(crate) fn matching_blocks(old: &[&[u8]], new: &[&[u8]]) -> Vec<(u32, u32, u32)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut pairs = Vec::new();
+    shortest_edit(
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+        &mut pairs,
+    );
+
+    let mut blocks: Vec<(u32, u32, u32)> = Vec::new();
+    let mut push = |old_start: usize, new_start: usize, len: usize| {
+        let (old_start, new_start, len) = (old_start as u32, new_start as u32, len as u32);
+        match blocks.last_mut() {
+            Some(last) if last.0 + last.2 == old_start && last.1 + last.2 == new_start => last.2 += len,
+            _ => blocks.push((old_start, new_start, len)),
+        }
+    };
+    if prefix != 0 {
+        push(0, 0, prefix);
+    }
+    for (x, y) in pairs.into_iter().rev() {
+        push(prefix + x, prefix + y, 1);
+    }
+    if suffix != 0 {
+        push(old.len() - suffix, new.len() - suffix, suffix);
+    }
+    blocks
+}
+
+/// Find the longest common subsequence of `a` and `b` with the Myers algorithm, pushing positions of matching lines
+/// to `pairs` in reverse order.
+fn shortest_edit(a: &[&[u8]], b: &[&[u8]], pairs: &mut Vec<(usize, usize)>) {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    // The furthest reaching paths of the previous step, for each step, limited to the diagonals it could reach.
+    let mut trace = Vec::new();
+    'steps: for d in 0..=max {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let at = |k: isize| v[(k + offset) as usize];
+            let mut x = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+                at(k + 1)
+            } else {
+                at(k - 1) + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(k + offset) as usize] = x;
+            if x >= n && y >= m {
+                break 'steps;
+            }
+        }
+    }
+
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let (prev_x, prev_y) = if d == 0 {
+            (0, 0)
+        } else {
+            let at = |k: isize| v[(k + d) as usize];
+            let k = x - y;
+            let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+                k + 1
+            } else {
+                k - 1
+            };
+            (at(prev_k), at(prev_k) - prev_k)
+        };
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            pairs.push((x as usize, y as usize));
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+}
+
+/// Return the blob at `path` in `tree`, or `None` if there is no such file.
+fn blob_at<Find>(find: &mut Find, buf: &mut Vec<u8>, tree: owned::Id, path: &BStr) -> Result<Option<owned::Id>, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let mut id = tree;
+    let mut mode = TreeMode::Tree;
+    for component in path.split_str("/").filter(|c| !c.is_empty()) {
+        if mode != TreeMode::Tree {
+            return Ok(None);
+        }
+        let tree = match find(id.to_borrowed(), buf).ok_or(Error::Find(id))? {
+            borrowed::Object::Tree(tree) => tree,
+            _ => return Err(Error::NotATree(id)),
+        };
+        match tree.entries.iter().find(|entry| entry.filename == component) {
+            Some(entry) => {
+                id = owned::Id::from_borrowed_sha1(entry.oid.sha1());
+                mode = entry.mode;
+            }
+            None => return Ok(None),
+        }
+    }
+    Ok(match mode {
+        TreeMode::Blob | TreeMode::BlobExecutable | TreeMode::Link => Some(id),
+        TreeMode::Tree | TreeMode::Commit => None,
+    })
+}
+
+fn find_blob<'b, Find>(find: &mut Find, buf: &'b mut Vec<u8>, id: owned::Id) -> Result<&'b [u8], Error>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>>,
+{
+    match find(id.to_borrowed(), buf).ok_or(Error::Find(id))? {
+        borrowed::Object::Blob(blob) => Ok(blob.data),
+        _ => Err(Error::NotABlob(id)),
+    }
+}
+
+fn find_commit<'b, Find>(find: &mut Find, buf: &'b mut Vec<u8>, id: owned::Id) -> Result<borrowed::Commit<'b>, Error>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>>,
+{
+    match find(id.to_borrowed(), buf).ok_or(Error::Find(id))? {
+        borrowed::Object::Commit(commit) => Ok(commit),
+        _ => Err(Error::NotACommit(id)),
+    }
+}
+
+fn commit_time<Find>(find: &mut Find, buf: &mut Vec<u8>, id: owned::Id) -> Result<u32, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    find_commit(find, buf, id).map(|c| c.committer.time.time)
+}
@@ -0,0 +1,306 @@
+//! Read and write bundle files as created by `git bundle`, which transport refs along with a pack without a connection
+//! to a server.
+//!
+//! A bundle starts with a header listing the commits the receiver must have already, called prerequisites, and the refs
+//! it contains, followed by a pack with all objects reachable from the refs but not from the prerequisites.
+//! Version 3 bundles may list capabilities like the `object-format` as well.
+use crate::push::decoded;
+use git_features::progress::Progress;
+use git_object::{
+    borrowed,
+    bstr::{BString, ByteSlice, ByteVec},
+    owned,
+};
+use git_odb::{pack, traverse};
+use quick_error::quick_error;
+use std::{
+    fs,
+    io::{self, Seek},
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred while reading or writing the bundle")
+            from()
+            source(err)
+        }
+        Open(err: io::Error, path: PathBuf) {
+            display("Could not open the bundle at '{}'", path.display())
+            source(err)
+        }
+        InvalidSignature(line: BString) {
+            display("'{}' is not the signature of a version 2 or 3 bundle", line)
+        }
+        InvalidLine(line: BString) {
+            display("The bundle header line '{}' could not be parsed", line)
+        }
+        UnexpectedEndOfHeader {
+            display("The bundle ended before its header was complete")
+        }
+        UnsupportedObjectFormat(format: BString) {
+            display("The object format '{}' of the bundle is not supported", format)
+        }
+        Traverse(err: traverse::commit_and_objects::Error) {
+            display("The objects to add to the bundle could not be determined")
+            from()
+            source(err)
+        }
+        WritePack(err: pack::data::write::Error) {
+            display("The pack of the bundle could not be written")
+            from()
+            source(err)
+        }
+        IndexPack(err: pack::bundle::write::Error) {
+            display("The pack of the bundle could not be indexed")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// The signature of version 2 bundles.
+pub const V2_SIGNATURE: &[u8] = b"# v2 git bundle";
+/// The signature of version 3 bundles.
+pub const V3_SIGNATURE: &[u8] = b"# v3 git bundle";
+
+/// The version of the bundle format.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum Version {
+    /// The original format, containing prerequisites and refs.
+    V2,
+    /// Like V2, but with capabilities like the `object-format` before the prerequisites.
+    V3,
+}
+
+/// A commit that must be present in the repository before the bundle can be unpacked.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Prerequisite {
+    /// The id of the commit.
+    pub id: owned::Id,
+    /// A description of the commit for the user, which is the first line of its message when written by git.
+    pub comment: BString,
+}
+
+/// A ref contained in the bundle.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Ref {
+    /// The object the ref points to.
+    pub id: owned::Id,
+    /// The full name of the ref, like `refs/heads/main`, or `HEAD`.
+    pub name: BString,
+}
+
+/// Everything in a bundle file before the pack.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Header {
+    /// The version of the bundle.
+    pub version: Version,
+    /// The capabilities of version 3 bundles without the leading `@`, like `object-format=sha1`.
+    pub capabilities: Vec<BString>,
+    /// The commits the receiving repository must have.
+    pub prerequisites: Vec<Prerequisite>,
+    /// The refs contained in the bundle.
+    pub refs: Vec<Ref>,
+}
+
+impl Header {
+    /// Read the header from `input`, which is positioned at the start of the pack afterwards.
+    pub fn from_read(mut input: impl io::BufRead) -> Result<Self, Error> {
+        let mut line = Vec::new();
+        let mut read_line = |line: &mut Vec<u8>| -> Result<(), Error> {
+            line.clear();
+            if input.read_until(b'\n', line)? == 0 {
+                return Err(Error::UnexpectedEndOfHeader);
+            }
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            Ok(())
+        };
+        read_line(&mut line)?;
+        let version = match line.as_slice() {
+            V2_SIGNATURE => Version::V2,
+            V3_SIGNATURE => Version::V3,
+            _ => return Err(Error::InvalidSignature(line.into())),
+        };
+        let mut header = Header {
+            version,
+            capabilities: Vec::new(),
+            prerequisites: Vec::new(),
+            refs: Vec::new(),
+        };
+        loop {
+            read_line(&mut line)?;
+            if line.is_empty() {
+                break;
+            }
+            let invalid = || Error::InvalidLine(line.as_bstr().to_owned());
+            if let Some(capability) = line.strip_prefix(b"@") {
+                if version != Version::V3 || !header.prerequisites.is_empty() || !header.refs.is_empty() {
+                    return Err(invalid());
+                }
+                if let Some(format) = capability.strip_prefix(b"object-format=") {
+                    if format != b"sha1" {
+                        return Err(Error::UnsupportedObjectFormat(format.into()));
+                    }
+                }
+                header.capabilities.push(capability.into());
+                continue;
+            }
+            let (is_prerequisite, rest) = match line.strip_prefix(b"-") {
+                Some(rest) => (true, rest),
+                None => (false, line.as_slice()),
+            };
+            let (hex, remainder) = match rest.find_byte(b' ') {
+                Some(pos) => (&rest[..pos], &rest[pos + 1..]),
+                None => (rest, &[][..]),
+            };
+            let id = owned::Id::from_40_bytes_in_hex(hex).map_err(|_| invalid())?;
+            if is_prerequisite {
+                header.prerequisites.push(Prerequisite {
+                    id,
+                    comment: remainder.into(),
+                });
+            } else {
+                if remainder.is_empty() {
+                    return Err(invalid());
+                }
+                header.refs.push(Ref {
+                    id,
+                    name: remainder.into(),
+                });
+            }
+        }
+        Ok(header)
+    }
+
+    /// Write the header to `out`, including the empty line separating it from the pack.
+    pub fn write_to(&self, mut out: impl io::Write) -> io::Result<()> {
+        let mut buf = BString::from(match self.version {
+            Version::V2 => V2_SIGNATURE,
+            Version::V3 => V3_SIGNATURE,
+        });
+        buf.push(b'\n');
+        if self.version == Version::V3 {
+            for capability in &self.capabilities {
+                buf.push(b'@');
+                buf.push_str(capability);
+                buf.push(b'\n');
+            }
+        }
+        for prerequisite in &self.prerequisites {
+            buf.push(b'-');
+            buf.push_str(prerequisite.id.to_sha1_hex());
+            if !prerequisite.comment.is_empty() {
+                buf.push(b' ');
+                buf.push_str(&prerequisite.comment);
+            }
+            buf.push(b'\n');
+        }
+        for r in &self.refs {
+            buf.push_str(r.id.to_sha1_hex());
+            buf.push(b' ');
+            buf.push_str(&r.name);
+            buf.push(b'\n');
+        }
+        buf.push(b'\n');
+        out.write_all(&buf)
+    }
+}
+
+/// Open the bundle at `path` and read its header, returning it along with a reader positioned at the start of the pack.
+pub fn open(path: impl AsRef<Path>) -> Result<(Header, io::BufReader<fs::File>), Error> {
+    let path = path.as_ref();
+    let mut reader = io::BufReader::new(fs::File::open(path).map_err(|err| Error::Open(err, path.to_owned()))?);
+    let header = Header::from_read(&mut reader)?;
+    Ok((header, reader))
+}
+
+/// Read the header of the bundle at `path` and write its pack along with an index into `directory`, like
+/// [`pack::Bundle::write_to_directory()`] does.
+///
+/// Note that the prerequisites are not checked, use the returned header to verify they are present.
+pub fn index_pack<P>(
+    path: impl AsRef<Path>,
+    directory: Option<impl AsRef<Path>>,
+    progress: P,
+    options: pack::bundle::write::Options,
+) -> Result<(Header, pack::bundle::write::Outcome), Error>
+where
+    P: Progress,
+    <P as Progress>::SubProgress: Send + 'static,
+    <<P as Progress>::SubProgress as Progress>::SubProgress: Send,
+{
+    let (header, mut reader) = open(path)?;
+    let header_len = reader.stream_position()?;
+    let pack_len = reader.get_ref().metadata()?.len().checked_sub(header_len);
+    let outcome = pack::Bundle::write_to_directory(reader, pack_len, directory, progress, options)?;
+    Ok((header, outcome))
+}
+
+/// The result of [`write()`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Outcome {
+    /// The header written before the pack.
+    pub header: Header,
+    /// Information about the written pack.
+    pub pack: pack::data::write::Outcome,
+}
+
+/// Write a bundle of the given `version` with `refs` to `out`, containing all objects reachable from them that aren't
+/// reachable from the `prerequisites` commits.
+///
+/// `find` is used to lookup objects, placing their data into the provided buffer and returning it along with its kind.
+/// Progress of writing the pack is reported to `progress`.
+pub fn write<Find>(
+    version: Version,
+    refs: Vec<Ref>,
+    prerequisites: &[owned::Id],
+    mut find: Find,
+    progress: impl Progress,
+    mut out: impl io::Write,
+) -> Result<Outcome, Error>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<pack::Object<'a>>,
+{
+    let mut buf = Vec::new();
+    let prerequisites = prerequisites
+        .iter()
+        .map(|id| Prerequisite {
+            id: *id,
+            comment: match decoded(&mut find, id.to_borrowed(), &mut buf) {
+                Some(borrowed::Object::Commit(commit)) => ByteSlice::lines(commit.message.as_bytes())
+                    .next()
+                    .unwrap_or_default()
+                    .into(),
+                _ => BString::default(),
+            },
+        })
+        .collect::<Vec<_>>();
+    let header = Header {
+        version,
+        capabilities: match version {
+            Version::V2 => Vec::new(),
+            Version::V3 => vec!["object-format=sha1".into()],
+        },
+        prerequisites,
+        refs,
+    };
+
+    let seen = traverse::commit_and_objects(header.prerequisites.iter().map(|p| p.id), |id, buf| {
+        decoded(&mut find, id, buf)
+    })
+    .map(|entry| entry.map(|e| e.id))
+    .collect::<Result<Vec<_>, _>>()?;
+    let ids = traverse::commit_and_objects(header.refs.iter().map(|r| r.id), |id, buf| decoded(&mut find, id, buf))
+        .with_seen(seen)
+        .map(|entry| entry.map(|e| e.id))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    header.write_to(&mut out)?;
+    let pack = pack::data::File::write_objects_to_stream(&ids, &mut find, progress, out)?;
+    Ok(Outcome { header, pack })
+}
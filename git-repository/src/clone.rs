@@ -0,0 +1,995 @@
+//! Create a new repository from a remote one: fetch all of its branches and tags, set up remote-tracking refs and
+//! check out the branch its `HEAD` points to.
+//!
+//! The pack sent by the server is streamed through the index writer into the object directory, which makes it
+//! available as bundle right away. The checked out files are recorded in the index along with their metadata, so they
+//! show as unchanged right away.
+//!
+//! With a [filter][Options::filter] a partial clone is made, which records the remote as promisor in the configuration
+//! and marks the received pack with a `.promisor` file, just like `git clone --filter` does.
+//! A [depth][Options::depth] makes a shallow clone with the boundary commits recorded in the `shallow` file, and
+//! [bare][Options::bare] clones mirror the branches of the remote without worktree.
+//!
+//! With an [LFS client][Options::lfs], the pointers of files with the `filter=lfs` attribute are replaced with the
+//! content they point to during checkout.
+//! [Sparse][Options::sparse] clones only check out the files matching their patterns, like `git clone --sparse`
+//! followed by `git sparse-checkout set` does.
+//!
+//! With a [bundle uri][Options::bundle_uri] or the [bundle list advertised][Options::advertised_bundle_uris] by the
+//! server, bundles are downloaded and unpacked first, which is much faster if they are served by a CDN, and only the
+//! objects not contained in them are fetched afterwards. Like with git, bundles which can't be downloaded or unpacked
+//! are reported as progress messages and the server sends their objects instead.
+use crate::{attributes, bundle, index, init, lfs, shallow, sparse, worktree};
+use git_features::{
+    lock,
+    progress::{self, Progress},
+};
+use git_object::{
+    borrowed,
+    bstr::{BStr, BString, ByteSlice, ByteVec},
+    owned, TreeMode,
+};
+use git_odb::{compound, pack, Find};
+use git_protocol::{bundle_uri, fetch, handshake, handshake::Ref, ls_refs, RemoteProgress};
+use git_transport::{
+    client::{self, http::pipe, Transport},
+    Protocol, Service,
+};
+use quick_error::quick_error;
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        DirectoryNotEmpty(path: PathBuf) {
+            display("Refusing to clone into the non-empty directory '{}'", path.display())
+        }
+        Init(err: init::Error) {
+            display("The repository could not be initialized")
+            from()
+            source(err)
+        }
+        Transport(err: client::Error) {
+            display("Could not connect to the remote")
+            from()
+            source(err)
+        }
+        Handshake(err: handshake::Error) {
+            display("The server response could not be understood")
+            from()
+            source(err)
+        }
+        ListRefs(err: ls_refs::Error) {
+            display("The refs of the remote could not be listed")
+            from()
+            source(err)
+        }
+        Fetch(err: fetch::Error) {
+            display("The pack could not be fetched")
+            from()
+            source(err)
+        }
+        WritePack(err: pack::bundle::write::Error) {
+            display("The received pack could not be written")
+            from()
+            source(err)
+        }
+        OpenDb(err: compound::init::Error) {
+            display("The objects of the new repository could not be opened")
+            from()
+            source(err)
+        }
+        FilterUnsupported(filter: fetch::Filter) {
+            display("The remote does not allow partial clones with the filter '{}'", filter)
+        }
+        ShallowUnsupported {
+            display("The remote does not support shallow clones")
+        }
+        BundleUriWithDepth {
+            display("Bundle URIs can't be used with shallow clones")
+        }
+        BundleList(err: bundle_uri::Error) {
+            display("The bundle list is invalid or could not be obtained from the remote")
+            from()
+            source(err)
+        }
+        BundleListFile(err: git_config::parse::Error, uri: String) {
+            display("'{}' is neither a bundle nor a bundle list", uri)
+            source(err)
+        }
+        BundleDownload(err: client::Error, uri: String) {
+            display("Could not download '{}'", uri)
+            source(err)
+        }
+        Bundle(err: bundle::Error, uri: String) {
+            display("The bundle at '{}' could not be unpacked", uri)
+            source(err)
+        }
+        BundlePrerequisiteMissing(id: owned::Id, uri: String) {
+            display("The bundle at '{}' requires the commit {}, which isn't present", uri, id)
+        }
+        Shallow(err: shallow::Error) {
+            display("The shallow boundary could not be written")
+            from()
+            source(err)
+        }
+        InvalidRefName(name: BString, err: git_ref::name::Error) {
+            display("The remote ref '{}' has an invalid name", name)
+            source(err)
+        }
+        UnsafePath(name: BString) {
+            display("Refusing to check out '{}' as it could write outside of the worktree", name)
+        }
+        ObjectMissing(id: owned::Id) {
+            display("The object {} needed for checkout is not contained in the received pack", id)
+        }
+        PromisedObjectMissing(id: owned::Id) {
+            display("The object {} needed for checkout was omitted by the filter and must be fetched from the promisor remote", id)
+        }
+        Lfs(err: lfs::Error, path: BString) {
+            display("The LFS pointer at '{}' could not be smudged", path)
+            source(err)
+        }
+        Lock(err: lock::Error) {
+            display("Could not lock a file of the new repository")
+            from()
+            source(err)
+        }
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not write '{}'", path.display())
+            source(err)
+        }
+    }
+}
+
+/// Configure how to clone.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Options {
+    /// The name of the remote to create remote-tracking refs for.
+    pub remote_name: String,
+    /// The protocol version to ask the server for.
+    pub protocol: Protocol,
+    /// If true, the files of the branch `HEAD` points to are written into the worktree.
+    pub checkout: bool,
+    /// The amount of threads to use when indexing the pack, or `None` to use all cores.
+    pub thread_limit: Option<usize>,
+    /// The credential helpers to ask if the remote requires authentication.
+    pub credentials: git_credentials::Cascade,
+    /// The proxy, TLS settings and extra headers to use with HTTP remotes, like the ones read by
+    /// [`remote::http_options()`][crate::remote::http_options()].
+    pub http: client::http::Options,
+    /// If set, the server is asked to omit the objects matching the filter, making this a partial clone.
+    pub filter: Option<fetch::Filter>,
+    /// If set, only this many commits of the history of each ref are fetched, making this a shallow clone.
+    pub depth: Option<usize>,
+    /// If true, the repository has no worktree and the branches of the remote are written as local branches.
+    ///
+    /// Nothing is checked out in this case, and the given directory becomes the git directory.
+    pub bare: bool,
+    /// If set, LFS pointers are passed to this client during checkout to write the content they point to instead.
+    pub lfs: Option<lfs::Client>,
+    /// If set, only the files included by these patterns are checked out, and they are written to the sparse checkout
+    /// file along with the configuration enabling it.
+    pub sparse: Option<sparse::Patterns>,
+    /// If set, the bundle or bundle list at this uri is downloaded and unpacked before fetching the objects not
+    /// contained in it, like `git clone --bundle-uri` does.
+    pub bundle_uri: Option<String>,
+    /// If true and no [bundle uri][Options::bundle_uri] is set, the bundles advertised by V2 servers are used in the
+    /// same way, like `transfer.bundleURI` does, but only the ones with `http` or `https` uris. Shallow clones don't use
+    /// them.
+    pub advertised_bundle_uris: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            remote_name: "origin".into(),
+            protocol: Protocol::V2,
+            checkout: true,
+            thread_limit: None,
+            credentials: git_credentials::Cascade::from_env(),
+            http: Default::default(),
+            filter: None,
+            depth: None,
+            bare: false,
+            lfs: None,
+            sparse: None,
+            bundle_uri: None,
+            advertised_bundle_uris: false,
+        }
+    }
+}
+
+/// The result of [`clone()`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The git directory of the new repository.
+    pub git_dir: PathBuf,
+    /// The refs advertised by the remote of which the branches and tags were fetched.
+    pub refs: Vec<Ref>,
+    /// The branch `HEAD` points to, like `refs/heads/main`, or `None` if it's detached or the remote is empty.
+    pub head: Option<BString>,
+    /// Information about the written pack, or `None` if the remote is empty or all objects were contained in bundles.
+    pub pack: Option<pack::bundle::write::Outcome>,
+    /// Information about the packs of the bundles which were unpacked before fetching, in the order they were applied.
+    pub bundles: Vec<pack::bundle::write::Outcome>,
+    /// The amount of files written into the worktree.
+    pub checked_out_files: usize,
+    /// Paths which weren't checked out as they refer to the same file as another path on a case-insensitive or
+    /// unicode normalizing filesystem, like `README` and `readme`.
+    pub collided_paths: Vec<BString>,
+    /// The commits at the boundary of a shallow clone, which is empty unless a [depth][Options::depth] was set.
+    pub shallow_commits: Vec<owned::Id>,
+}
+
+/// Clone the repository at `url` into the new or empty `directory`, reporting progress to `progress`.
+pub fn clone<P>(url: &str, directory: impl AsRef<Path>, mut progress: P, options: Options) -> Result<Outcome, Error>
+where
+    P: Progress,
+    <P as Progress>::SubProgress: Send + 'static,
+    <<P as Progress>::SubProgress as Progress>::SubProgress: Send + 'static,
+    <<<P as Progress>::SubProgress as Progress>::SubProgress as Progress>::SubProgress: Send,
+{
+    let directory = directory.as_ref();
+    if directory.is_dir()
+        && fs::read_dir(directory)
+            .map_err(|err| Error::Io(err, directory.into()))?
+            .next()
+            .is_some()
+    {
+        return Err(Error::DirectoryNotEmpty(directory.into()));
+    }
+    progress.init(Some(4), progress::steps());
+
+    progress.info("connecting");
+    progress.inc();
+    let mut transport = client::connect(url.as_bytes(), options.protocol)?;
+    transport.set_http_options(options.http.clone());
+    transport.set_credentials(options.credentials.clone());
+    let outcome = handshake::parse(transport.handshake(Service::UploadPack)?)?;
+    let refs = match outcome.refs.clone() {
+        Some(refs) => refs,
+        None => git_protocol::ls_refs(
+            &mut transport,
+            &outcome.capabilities,
+            &ls_refs::Options {
+                prefixes: vec!["HEAD".into(), "refs/heads/".into(), "refs/tags/".into()],
+                unborn: true,
+                ..Default::default()
+            },
+        )?,
+    };
+    let refs: Vec<_> = refs
+        .into_iter()
+        .filter(|r| {
+            let (path, _) = r.unpack();
+            path == "HEAD" || path.starts_with(b"refs/heads/") || path.starts_with(b"refs/tags/")
+        })
+        .collect();
+
+    let mut arguments = fetch::Arguments::new(&outcome);
+    if let Some(filter) = options.filter {
+        if !arguments.can_use_filter() {
+            return Err(Error::FilterUnsupported(filter));
+        }
+    }
+    if options.depth.is_some() {
+        if !arguments.can_use_shallow() {
+            return Err(Error::ShallowUnsupported);
+        }
+        if options.bundle_uri.is_some() {
+            return Err(Error::BundleUriWithDepth);
+        }
+    }
+
+    let git_dir = init::repository_with_options(
+        directory,
+        init::Options {
+            bare: options.bare,
+            ..Default::default()
+        },
+    )?;
+
+    let list = match &options.bundle_uri {
+        Some(uri) => Some(Ok(Bundles::Uri(uri.clone()))),
+        None if options.advertised_bundle_uris
+            && options.depth.is_none()
+            && outcome.capabilities.contains("bundle-uri") =>
+        {
+            Some(
+                git_protocol::bundle_uri(&mut transport, &outcome.capabilities)
+                    .map(Bundles::List)
+                    .map_err(Error::from),
+            )
+        }
+        None => None,
+    };
+    let (bundles, tips) = match list {
+        Some(Ok(list)) => {
+            progress.info("unpacking bundles");
+            unbundle(&git_dir, list, &options, &mut progress)
+        }
+        Some(Err(err)) => {
+            progress.fail(err.to_string());
+            Default::default()
+        }
+        None => Default::default(),
+    };
+    let db = if bundles.is_empty() {
+        None
+    } else {
+        Some(compound::Db::at(git_dir.join("objects"))?)
+    };
+    for r in &refs {
+        let id = match r {
+            Ref::Peeled { tag, .. } => *tag,
+            Ref::Direct { object, .. } | Ref::Symbolic { object, .. } => *object,
+            Ref::Unborn { .. } => continue,
+        };
+        let is_present = db.as_ref().is_some_and(|db| db.contains(id.to_borrowed()));
+        if !is_present && !arguments.wants().contains(&id) {
+            arguments.want(id);
+        }
+    }
+    if let Some(filter) = options.filter {
+        arguments.filter(filter);
+    }
+    if let Some(depth) = options.depth {
+        arguments.deepen(depth);
+    }
+    let mut negotiator = match &db {
+        Some(db) => {
+            let mut cache = pack::cache::DecodeEntryLRU::default();
+            let mut negotiator = fetch::negotiate::Algorithm::Consecutive
+                .into_negotiator(move |id, buf| find(db, owned::Id::from_borrowed_sha1(id.sha1()), buf, &mut cache));
+            for tip in &tips {
+                negotiator.add_tip(*tip);
+            }
+            negotiator
+        }
+        None => fetch::negotiate::Algorithm::Noop.into_negotiator(|_, _| None),
+    };
+
+    let mut boundary = shallow::Boundary::default();
+    progress.info("receiving pack");
+    progress.inc();
+    let pack = if arguments.is_empty() {
+        None
+    } else {
+        let pack_dir = git_dir.join("objects").join("pack");
+        let write_progress = progress.add_child("pack");
+        let mut remote_progress = progress.add_child("remote");
+        let thread_limit = options.thread_limit;
+        let mut written = None;
+        fetch::fetch(
+            &mut transport,
+            &arguments,
+            negotiator.as_mut(),
+            |is_err, text| RemoteProgress::translate_to_progress(is_err, text, &mut remote_progress),
+            |pack, response| {
+                boundary.apply(response.shallow_updates.iter().map(|update| match update {
+                    fetch::ShallowUpdate::Shallow(id) => shallow::Update::Shallow(*id),
+                    fetch::ShallowUpdate::Unshallow(id) => shallow::Update::Unshallow(*id),
+                }));
+                let (mut writer, reader) = pipe::unidirectional(64);
+                let indexer = std::thread::spawn(move || {
+                    pack::Bundle::write_to_directory(
+                        reader,
+                        None,
+                        Some(pack_dir),
+                        write_progress,
+                        pack::bundle::write::Options {
+                            thread_limit,
+                            iteration_mode: pack::data::iter::Mode::Verify,
+                            index_kind: pack::index::Kind::default(),
+                        },
+                    )
+                });
+                if let Err(err) = io::copy(pack, &mut writer) {
+                    writer.send_error(io::Error::new(err.kind(), err.to_string()));
+                }
+                drop(writer);
+                written = Some(indexer.join().expect("no panic in indexer thread"));
+                Ok(())
+            },
+        )?;
+        let written = written.expect("pack to be received")?;
+        if let (Some(data_path), Some(_)) = (&written.data_path, options.filter) {
+            write_promisor_file(data_path, &refs)?;
+        }
+        Some(written)
+    };
+    transport.close()?;
+    boundary.write_to(&git_dir)?;
+
+    progress.info("updating refs");
+    progress.inc();
+    let remote_name = if options.bare {
+        None
+    } else {
+        Some(options.remote_name.as_str())
+    };
+    let head = write_refs(&git_dir, &refs, remote_name)?;
+    write_config(
+        &git_dir,
+        url,
+        &options.remote_name,
+        head.as_ref().map(|h| h.as_ref()).filter(|_| !options.bare),
+        !options.bare,
+        options.filter,
+    )?;
+    if let (Some(patterns), false) = (&options.sparse, options.bare) {
+        write_sparse_checkout(&git_dir, patterns)?;
+    }
+
+    progress.info("checking out");
+    progress.inc();
+    let head_commit = refs.iter().find_map(|r| match r {
+        Ref::Direct { path, object } | Ref::Symbolic { path, object, .. } if path == "HEAD" => Some(*object),
+        _ => None,
+    });
+    let should_checkout = options.checkout && !options.bare;
+    let (checked_out_files, collided_paths) = match (should_checkout, head_commit) {
+        (true, Some(commit)) if pack.is_some() || !bundles.is_empty() => {
+            let db = compound::Db::at(git_dir.join("objects"))?;
+            let (num_files, collided_paths, entries) = checkout(
+                &db,
+                db.has_promisor_packs(),
+                &worktree::Capabilities::from_git_dir(&git_dir),
+                commit,
+                directory,
+                options.lfs.as_ref(),
+                options.sparse.as_ref(),
+            )?;
+            write_index(&git_dir, entries)?;
+            (num_files, collided_paths)
+        }
+        _ => (0, Vec::new()),
+    };
+
+    Ok(Outcome {
+        git_dir,
+        refs,
+        head,
+        pack,
+        bundles,
+        checked_out_files,
+        collided_paths,
+        shallow_commits: boundary.commits().collect(),
+    })
+}
+
+/// Where to find the bundles to unpack before fetching.
+enum Bundles {
+    /// A bundle or a bundle list file.
+    Uri(String),
+    /// The list advertised by the server.
+    List(bundle_uri::List),
+}
+
+/// Download and unpack the bundles into the git directory of the new repository at `git_dir`, reporting failures
+/// to `progress` instead of failing. Return the written packs along with the objects of the refs of all bundles.
+///
+/// Like git does, branches of the bundles are written as `refs/bundles/*` to keep track of what they contained.
+fn unbundle<P>(
+    git_dir: &Path,
+    bundles: Bundles,
+    options: &Options,
+    progress: &mut P,
+) -> (Vec<pack::bundle::write::Outcome>, Vec<owned::Id>)
+where
+    P: Progress,
+    <P as Progress>::SubProgress: Send + 'static,
+    <<P as Progress>::SubProgress as Progress>::SubProgress: Send + 'static,
+    <<<P as Progress>::SubProgress as Progress>::SubProgress as Progress>::SubProgress: Send,
+{
+    let pack_dir = git_dir.join("objects").join("pack");
+    let mut outcomes = Vec::new();
+    let mut tips = Vec::new();
+    let mut apply = |uri: &str, file: Option<tempfile::NamedTempFile>, progress: &mut P| -> Result<(), Error> {
+        let file = match file {
+            Some(file) => file,
+            None => download_into(&pack_dir, uri, &options.http)?,
+        };
+        let (header, _) = bundle::open(file.path()).map_err(|err| Error::Bundle(err, uri.into()))?;
+        if !header.prerequisites.is_empty() {
+            let db = compound::Db::at(git_dir.join("objects"))?;
+            if let Some(missing) = header.prerequisites.iter().find(|p| !db.contains(p.id.to_borrowed())) {
+                return Err(Error::BundlePrerequisiteMissing(missing.id, uri.into()));
+            }
+        }
+        let (header, outcome) = bundle::index_pack(
+            file.path(),
+            Some(&pack_dir),
+            progress.add_child(uri),
+            pack::bundle::write::Options {
+                thread_limit: options.thread_limit,
+                iteration_mode: pack::data::iter::Mode::Verify,
+                index_kind: pack::index::Kind::default(),
+            },
+        )
+        .map_err(|err| Error::Bundle(err, uri.into()))?;
+        for r in &header.refs {
+            if let Some(branch) = r.name.strip_prefix(b"refs/heads/") {
+                let mut name = BString::from("refs/bundles/");
+                name.push_str(branch);
+                write_ref(git_dir, name.as_ref(), &r.id)?;
+            }
+            tips.push(r.id);
+        }
+        outcomes.push(outcome);
+        Ok(())
+    };
+
+    let (list, base) = match bundles {
+        Bundles::List(list) => (list, None),
+        Bundles::Uri(uri) => {
+            let list = download_into(&pack_dir, &uri, &options.http).and_then(|file| {
+                if bundle::open(file.path()).is_ok() {
+                    return Ok(Err(file));
+                }
+                let data = fs::read(file.path()).map_err(|err| Error::Io(err, file.path().into()))?;
+                let config =
+                    git_config::File::from_bytes(&data).map_err(|err| Error::BundleListFile(err, uri.clone()))?;
+                let pairs: Vec<_> = config
+                    .sections()
+                    .iter()
+                    .filter(|section| section.name == "bundle")
+                    .flat_map(|section| {
+                        section.entries.iter().filter_map(move |entry| {
+                            let mut key = BString::from("bundle.");
+                            if let Some(subsection) = &section.subsection {
+                                key.push_str(subsection);
+                                key.push(b'.');
+                            }
+                            key.push_str(&entry.key);
+                            entry.value.clone().map(|value| (key, value))
+                        })
+                    })
+                    .collect();
+                Ok(Ok(bundle_uri::List::from_pairs(
+                    pairs.iter().map(|(key, value)| (key.as_ref(), value.as_ref())),
+                )?))
+            });
+            match list {
+                Ok(Ok(list)) => (list, Some(uri)),
+                Ok(Err(file)) => {
+                    if let Err(err) = apply(&uri, Some(file), progress) {
+                        progress.fail(err.to_string());
+                    }
+                    return (outcomes, tips);
+                }
+                Err(err) => {
+                    progress.fail(err.to_string());
+                    return (outcomes, tips);
+                }
+            }
+        }
+    };
+    for b in list.sorted_bundles() {
+        let uri = match b.uri.to_str() {
+            Ok(uri) => bundle_uri::resolve_uri(base.as_deref(), uri),
+            Err(_) => {
+                progress.fail(format!("Ignoring bundle '{}' with invalid uri '{}'", b.id, b.uri));
+                continue;
+            }
+        };
+        let uri = match uri {
+            Ok(uri) => uri,
+            Err(err) => {
+                progress.fail(err.to_string());
+                continue;
+            }
+        };
+        match apply(&uri, None, progress) {
+            Ok(()) if list.mode == bundle_uri::Mode::Any => break,
+            Ok(()) => {}
+            Err(err) => progress.fail(err.to_string()),
+        }
+    }
+    (outcomes, tips)
+}
+
+/// Download the file at `uri` into a temporary file in `directory`, which is removed when dropped.
+fn download_into(
+    directory: &Path,
+    uri: &str,
+    options: &client::http::Options,
+) -> Result<tempfile::NamedTempFile, Error> {
+    let mut file = tempfile::NamedTempFile::new_in(directory).map_err(|err| Error::Io(err, directory.into()))?;
+    client::download(uri.as_bytes(), options, file.as_file_mut())
+        .map_err(|err| Error::BundleDownload(err, uri.into()))?;
+    Ok(file)
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), Error> {
+    let io = |err| Error::Io(err, path.to_owned());
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io)?;
+    }
+    fs::write(path, data).map_err(io)
+}
+
+fn ref_path(git_dir: &Path, name: &BStr) -> Result<PathBuf, Error> {
+    git_ref::name::validate(name).map_err(|err| Error::InvalidRefName(name.to_owned(), err))?;
+    let relative =
+        git_features::path::to_native(name, Default::default()).map_err(|_| Error::UnsafePath(name.to_owned()))?;
+    Ok(git_features::path::long(git_dir.join(relative)))
+}
+
+fn write_ref(git_dir: &Path, name: &BStr, id: &owned::Id) -> Result<(), Error> {
+    let mut content = id.to_sha1_hex().to_vec();
+    content.push(b'\n');
+    write_file(&ref_path(git_dir, name)?, &content)
+}
+
+fn write_symbolic_ref(git_dir: &Path, name: &BStr, target: &BStr) -> Result<(), Error> {
+    let mut content = BString::from("ref: ");
+    content.push_str(target);
+    content.push(b'\n');
+    write_file(&ref_path(git_dir, name)?, &content)
+}
+
+/// Write remote-tracking branches of `remote_name`, tags and `HEAD` along with the local branch it points to, which
+/// is returned.
+///
+/// Without `remote_name` all branches are written as local branches, as done for bare clones.
+fn write_refs(git_dir: &Path, refs: &[Ref], remote_name: Option<&str>) -> Result<Option<BString>, Error> {
+    let tracking_name = |remote_name: &str, branch: &BStr| {
+        let mut name = BString::from(format!("refs/remotes/{}/", remote_name));
+        name.push_str(branch);
+        name
+    };
+    let mut head = None;
+    for r in refs {
+        match r {
+            Ref::Symbolic { path, target, object } if path == "HEAD" => {
+                if let Some(branch) = target.strip_prefix(b"refs/heads/") {
+                    write_ref(git_dir, target.as_ref(), object)?;
+                    if let Some(remote_name) = remote_name {
+                        write_symbolic_ref(
+                            git_dir,
+                            tracking_name(remote_name, b"HEAD".as_bstr()).as_ref(),
+                            tracking_name(remote_name, branch.as_bstr()).as_ref(),
+                        )?;
+                    }
+                    head = Some(target.clone());
+                }
+                write_symbolic_ref(git_dir, path.as_ref(), target.as_ref())?;
+            }
+            Ref::Unborn { path, target } if path == "HEAD" => {
+                write_symbolic_ref(git_dir, path.as_ref(), target.as_ref())?;
+            }
+            Ref::Direct { path, object } if path == "HEAD" => write_ref(git_dir, path.as_ref(), object)?,
+            _ => {
+                let (path, id) = match r {
+                    Ref::Peeled { path, tag, .. } => (path, tag),
+                    Ref::Direct { path, object } | Ref::Symbolic { path, object, .. } => (path, object),
+                    Ref::Unborn { .. } => continue,
+                };
+                match (path.strip_prefix(b"refs/heads/"), remote_name) {
+                    (Some(branch), Some(remote_name)) => {
+                        write_ref(git_dir, tracking_name(remote_name, branch.as_bstr()).as_ref(), id)?
+                    }
+                    _ => write_ref(git_dir, path.as_ref(), id)?,
+                }
+            }
+        }
+    }
+    Ok(head)
+}
+
+/// Write the `.promisor` file next to the pack at `data_path`, listing the `refs` it was fetched for like git does.
+fn write_promisor_file(data_path: &Path, refs: &[Ref]) -> Result<(), Error> {
+    let mut content = Vec::new();
+    for r in refs {
+        if let Ref::Direct { path, object }
+        | Ref::Peeled { path, tag: object, .. }
+        | Ref::Symbolic { path, object, .. } = r
+        {
+            writeln!(content, "{} {}", object, path).expect("write to memory");
+        }
+    }
+    write_file(&data_path.with_extension("promisor"), &content)
+}
+
+/// Change the configuration file in `git_dir` with `edit` while holding its lock.
+fn edit_config(git_dir: &Path, edit: impl FnOnce(&mut Vec<u8>)) -> Result<(), Error> {
+    let path = git_dir.join("config");
+    let mut lock = lock::File::acquire(&path, Default::default())?;
+    let mut config = fs::read(&path).map_err(|err| Error::Io(err, path.clone()))?;
+    edit(&mut config);
+    lock.write_all(&config).map_err(|err| Error::Io(err, path))?;
+    lock.commit()?;
+    Ok(())
+}
+
+/// Add the remote and the upstream of the `head` branch to the configuration file, along with the default
+/// fetch refspec if `with_fetch_refspec` is true.
+///
+/// If a `filter` was used, the remote is configured as promisor, which requires repository format version 1.
+fn write_config(
+    git_dir: &Path,
+    url: &str,
+    remote_name: &str,
+    head: Option<&BStr>,
+    with_fetch_refspec: bool,
+    filter: Option<fetch::Filter>,
+) -> Result<(), Error> {
+    edit_config(git_dir, |config| {
+        writeln!(config, "[remote \"{}\"]\n\turl = {}", remote_name, url).expect("write to memory");
+        if with_fetch_refspec {
+            writeln!(config, "\tfetch = +refs/heads/*:refs/remotes/{}/*", remote_name).expect("write to memory");
+        }
+        if let Some(filter) = filter {
+            writeln!(
+                config,
+                "\tpromisor = true\n\tpartialclonefilter = {}\n[extensions]\n\tpartialclone = {}",
+                filter, remote_name
+            )
+            .expect("write to memory");
+            *config = config.replace("repositoryformatversion = 0", "repositoryformatversion = 1");
+        }
+        if let Some(branch) = head.and_then(|h| h.strip_prefix(b"refs/heads/")) {
+            writeln!(
+                config,
+                "[branch \"{}\"]\n\tremote = {}\n\tmerge = {}",
+                branch.as_bstr(),
+                remote_name,
+                head.expect("present")
+            )
+            .expect("write to memory");
+        }
+    })
+}
+
+/// Enable the sparse checkout with `patterns` in the configuration and write them to the sparse checkout file.
+fn write_sparse_checkout(git_dir: &Path, patterns: &sparse::Patterns) -> Result<(), Error> {
+    edit_config(git_dir, |config| {
+        writeln!(
+            config,
+            "[core]\n\tsparseCheckout = true\n\tsparseCheckoutCone = {}",
+            matches!(patterns, sparse::Patterns::Cone { .. })
+        )
+        .expect("write to memory");
+    })?;
+    write_file(&git_dir.join(sparse::FILE_NAME), &patterns.to_bstring())
+}
+
+/// Write all files of the tree of `commit` in `db` into `worktree`, returning the amount of files written along with
+/// the paths which weren't written as they refer to the same file as an earlier path on a filesystem with
+/// `capabilities`, and the index entries of all files. Directories referring to the same directory are merged.
+///
+/// Objects missing from a database marked as `is_promisor` are reported as [`Error::PromisedObjectMissing`].
+/// With an `lfs` client, pointers of files with the `filter=lfs` attribute are turned into their content.
+/// With `sparse` patterns, only the files they include are written, along with the directories containing them, while
+/// the entries of all other files are marked to skip the worktree.
+fn checkout(
+    db: &impl Find,
+    is_promisor: bool,
+    capabilities: &worktree::Capabilities,
+    commit: owned::Id,
+    worktree: &Path,
+    lfs: Option<&lfs::Client>,
+    sparse: Option<&sparse::Patterns>,
+) -> Result<(usize, Vec<BString>, Vec<index::Entry>), Error> {
+    let mut span = git_features::trace::span("clone::checkout");
+    let missing = |id| {
+        if is_promisor {
+            Error::PromisedObjectMissing(id)
+        } else {
+            Error::ObjectMissing(id)
+        }
+    };
+    let mut buf = Vec::new();
+    let mut cache = pack::cache::DecodeEntryLRU::default();
+    let tree = match find(db, commit, &mut buf, &mut cache).ok_or_else(|| missing(commit))? {
+        borrowed::Object::Commit(commit) => commit.tree(),
+        _ => return Err(missing(commit)),
+    };
+    let mut num_files = 0;
+    let mut paths = worktree::icase::Paths::new(capabilities);
+    let mut collided_paths = Vec::new();
+    let mut attributes = attributes::Stack::default();
+    let mut entries = Vec::new();
+    let mut trees = vec![(
+        tree,
+        git_features::path::extended_length(worktree),
+        BString::default(),
+        true,
+    )];
+    while let Some((tree_id, directory, relative_directory, is_included)) = trees.pop() {
+        let tree = match find(db, tree_id, &mut buf, &mut cache).ok_or_else(|| missing(tree_id))? {
+            borrowed::Object::Tree(tree) => tree,
+            _ => return Err(missing(tree_id)),
+        };
+        let tree_entries: Vec<_> = tree
+            .entries
+            .iter()
+            .map(|e| {
+                (
+                    e.mode,
+                    e.filename.to_owned(),
+                    owned::Id::from_borrowed_sha1(e.oid.sha1()),
+                )
+            })
+            .collect();
+        if lfs.is_some() && is_included {
+            let gitattributes = tree_entries
+                .iter()
+                .find(|(mode, filename, _)| *mode == TreeMode::Blob && filename == ".gitattributes");
+            if let Some((_, _, id)) = gitattributes {
+                if let borrowed::Object::Blob(blob) = find(db, *id, &mut buf, &mut cache).ok_or_else(|| missing(*id))? {
+                    attributes.add(relative_directory.as_ref(), blob.data);
+                }
+            }
+        }
+        for (mode, filename, id) in tree_entries {
+            if git_features::path::verify_component(&filename, Default::default()).is_err() {
+                return Err(Error::UnsafePath(filename));
+            }
+            let path = directory.join(filename.to_path().map_err(|_| Error::UnsafePath(filename.clone()))?);
+            let mut relative_path = relative_directory.clone();
+            if !relative_path.is_empty() {
+                relative_path.push(b'/');
+            }
+            relative_path.push_str(&filename);
+            let entry = |path: BString, stat, skip_worktree| index::Entry {
+                stat,
+                mode,
+                id,
+                stage: 0,
+                assume_valid: false,
+                skip_worktree,
+                intent_to_add: false,
+                path,
+            };
+            let is_included = is_included
+                && match (mode, sparse) {
+                    (TreeMode::Tree, Some(patterns)) => patterns.may_include_directory(relative_path.as_ref()),
+                    (_, Some(patterns)) => patterns.is_included(relative_path.as_ref()),
+                    (_, None) => true,
+                };
+            if !is_included {
+                match mode {
+                    TreeMode::Tree => trees.push((id, path, relative_path, false)),
+                    _ => entries.push(entry(relative_path, index::Stat::default(), true)),
+                }
+                continue;
+            }
+            let collides = paths.insert(relative_path.as_ref()).is_some();
+            if let Ok(metadata) = fs::symlink_metadata(&path) {
+                match collides {
+                    true if mode == TreeMode::Tree && metadata.is_dir() => {}
+                    true => {
+                        if mode != TreeMode::Tree {
+                            entries.push(entry(relative_path.clone(), index::Stat::default(), false));
+                        }
+                        collided_paths.push(relative_path);
+                        continue;
+                    }
+                    false => return Err(Error::UnsafePath(filename)),
+                }
+            }
+            let io = |err| Error::Io(err, path.clone());
+            match mode {
+                TreeMode::Tree => {
+                    if sparse.is_none() {
+                        fs::create_dir_all(&path).map_err(io)?;
+                    }
+                    trees.push((id, path, relative_path, true));
+                    continue;
+                }
+                TreeMode::Commit => fs::create_dir_all(&path).map_err(io)?,
+                TreeMode::Blob | TreeMode::BlobExecutable | TreeMode::Link => {
+                    if sparse.is_some() {
+                        fs::create_dir_all(&directory).map_err(io)?;
+                    }
+                    let data = match find(db, id, &mut buf, &mut cache).ok_or_else(|| missing(id))? {
+                        borrowed::Object::Blob(blob) => blob.data,
+                        _ => return Err(missing(id)),
+                    };
+                    match lfs {
+                        Some(client)
+                            if mode != TreeMode::Link
+                                && lfs::is_pointer(data)
+                                && attributes.state(relative_path.as_ref(), "filter")
+                                    == attributes::State::Value("lfs".into()) =>
+                        {
+                            let content = client
+                                .smudge(data, relative_path.as_ref())
+                                .map_err(|err| Error::Lfs(err, relative_path.clone()))?;
+                            write_blob(&path, &content, mode, capabilities).map_err(io)?
+                        }
+                        _ => write_blob(&path, data, mode, capabilities).map_err(io)?,
+                    }
+                    num_files += 1;
+                }
+            }
+            let stat = match mode {
+                TreeMode::Commit => index::Stat::default(),
+                _ => index::Stat::from_metadata(&fs::symlink_metadata(&path).map_err(io)?),
+            };
+            entries.push(entry(relative_path, stat, false));
+        }
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    span.record(format_args!("files={}", num_files));
+    Ok((num_files, collided_paths, entries))
+}
+
+/// Write `entries` as the index of the repository at `git_dir`.
+fn write_index(git_dir: &Path, entries: Vec<index::Entry>) -> Result<(), Error> {
+    let path = git_dir.join("index");
+    let mut file = lock::File::acquire(&path, Default::default())?;
+    index::File {
+        version: 2,
+        entries,
+        mtime: None,
+    }
+    .write_to(&mut file)
+    .map_err(|err| Error::Io(err, path.clone()))?;
+    file.commit()?;
+    Ok(())
+}
+
+fn find<'a>(
+    db: &impl Find,
+    id: owned::Id,
+    buf: &'a mut Vec<u8>,
+    cache: &mut pack::cache::DecodeEntryLRU,
+) -> Option<borrowed::Object<'a>> {
+    db.locate(id.to_borrowed(), buf, cache)
+        .and_then(Result::ok)
+        .and_then(|object| borrowed::Object::from_bytes(object.kind, object.data).ok())
+}
+
+/// Write `data` of a blob with `mode` to `path`, as symlink or executable file only if `capabilities` allow it.
+#[cfg(unix)]
+pub(crate) fn write_blob(
+    path: &Path,
+    data: &[u8],
+    mode: TreeMode,
+    capabilities: &worktree::Capabilities,
+) -> io::Result<()> {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    match mode {
+        TreeMode::Link if capabilities.symlinks => symlink(data.to_path().map_err(io::Error::other)?, path),
+        TreeMode::BlobExecutable if capabilities.file_mode => {
+            fs::write(path, data)?;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        }
+        _ => fs::write(path, data),
+    }
+}
+
+/// Write `data` of a blob with `mode` to `path`, as symlink only if `capabilities` allow it.
+#[cfg(windows)]
+pub(crate) fn write_blob(
+    path: &Path,
+    data: &[u8],
+    mode: TreeMode,
+    capabilities: &worktree::Capabilities,
+) -> io::Result<()> {
+    match mode {
+        TreeMode::Link if capabilities.symlinks => {
+            std::os::windows::fs::symlink_file(data.to_path().map_err(io::Error::other)?, path)
+        }
+        _ => fs::write(path, data),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn write_blob(
+    path: &Path,
+    data: &[u8],
+    _mode: TreeMode,
+    _capabilities: &worktree::Capabilities,
+) -> io::Result<()> {
+    fs::write(path, data)
+}
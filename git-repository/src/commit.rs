@@ -0,0 +1,338 @@
+//! Create commits and move the branch `HEAD` points to, or any other ref, to them, like `git commit` does.
+//!
+//! The ref is only moved if it still points to the first parent of the new commit, so concurrent updates aren't lost.
+//! Like git, an entry is appended to the reflog of the updated ref, as well as to the one of `HEAD` if it was followed.
+//!
+//! Hooks may reject the commit before it is written and change its message, and learn about it once the ref was
+//! updated. The executable hooks of a repository can be used with [`Hooks::from_git_dir()`].
+//!
+//! Commits are signed by a [`Sign`] function, whose signature is embedded in the `gpgsig` header. For verification, the
+//! signed payload and the signature are obtained from the commit object with [`extract_signature()`].
+use crate::{hooks, remote::update, worktree::linked};
+use git_object::{
+    bstr::{BStr, BString, ByteSlice},
+    owned, HashKind,
+};
+use git_odb::{loose, Write};
+use quick_error::quick_error;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Rejected(hook: &'static str, messages: BString) {
+            display("The {} hook rejected the commit", hook)
+        }
+        RefChanged(name: BString, expected: Option<owned::Id>, actual: Option<owned::Id>) {
+            display("The ref '{}' was expected to point to {}, but it points to {}",
+                name,
+                expected.map(|id| id.to_string()).unwrap_or_else(|| "nothing".into()),
+                actual.map(|id| id.to_string()).unwrap_or_else(|| "nothing".into()))
+        }
+        Sign(err: Box<dyn std::error::Error + Send + Sync>) {
+            display("The commit could not be signed")
+            source(&**err)
+        }
+        Ref(err: update::Error) {
+            display("The ref could not be updated")
+            from()
+            source(err)
+        }
+        WriteObject(err: loose::db::write::Error) {
+            display("The commit object could not be written")
+            from()
+            source(err)
+        }
+        Worktree(err: linked::Error) {
+            display("The common git directory of the worktree could not be determined")
+            from()
+            source(err)
+        }
+        Io(err: std::io::Error, path: PathBuf) {
+            display("Could not access '{}'", path.display())
+            source(err)
+        }
+    }
+}
+
+/// A function called with the commit about to be written, which may write messages for the user into the buffer and
+/// returns false to reject it.
+pub type PreCommit<'a> = Box<dyn FnMut(&owned::Commit, &mut Vec<u8>) -> bool + 'a>;
+/// A function called with the message of the commit after [`PreCommit`] accepted it, which may change it, write
+/// messages for the user into the buffer and returns false to reject the commit.
+pub type CommitMsg<'a> = Box<dyn FnMut(&mut BString, &mut Vec<u8>) -> bool + 'a>;
+/// A function called with the id of the new commit once the ref was updated, which may write messages for the user into
+/// the buffer.
+pub type PostCommit<'a> = Box<dyn FnMut(owned::Id, &mut Vec<u8>) + 'a>;
+
+/// A function called with the payload to sign, the serialized object without a signature, which returns the ASCII-armored
+/// signature to embed, like the one produced by `gpg --armor --detach-sign`.
+///
+/// It's used to sign commits as well as [tags][crate::tag::Options::sign].
+pub type Sign<'a> = Box<dyn FnMut(&[u8]) -> Result<BString, Box<dyn std::error::Error + Send + Sync>> + 'a>;
+
+/// The hooks to call while committing, all of which are optional.
+#[derive(Default)]
+pub struct Hooks<'a> {
+    /// Called first, with the commit as it would be written.
+    pub pre_commit: Option<PreCommit<'a>>,
+    /// Called after `pre_commit` accepted the commit, with its message.
+    pub commit_msg: Option<CommitMsg<'a>>,
+    /// Called once the commit was written and the ref was updated.
+    pub post_commit: Option<PostCommit<'a>>,
+}
+
+impl Hooks<'static> {
+    /// Use the executable `pre-commit`, `commit-msg` and `post-commit` programs of the repository at `git_dir`, if they
+    /// exist, as found by [`hooks::Runner::from_git_dir()`].
+    pub fn from_git_dir(git_dir: impl Into<PathBuf>) -> Self {
+        Self::from_runner(hooks::Runner::from_git_dir(git_dir))
+    }
+
+    /// Use the executable `pre-commit`, `commit-msg` and `post-commit` programs found by `runner`, if they exist.
+    ///
+    /// They are invoked like git does it: only `commit-msg` receives an argument, the path to the `COMMIT_EDITMSG`
+    /// file containing the message, which it may change.
+    pub fn from_runner(runner: hooks::Runner) -> Self {
+        let runner = std::rc::Rc::new(runner);
+        let mut hooks = Hooks::default();
+        if runner.find("pre-commit").is_some() {
+            let runner = runner.clone();
+            hooks.pre_commit = Some(Box::new(move |_commit, messages| {
+                runner.run("pre-commit", &[], None, messages).unwrap_or(true)
+            }));
+        }
+        if runner.find("commit-msg").is_some() {
+            let runner = runner.clone();
+            hooks.commit_msg = Some(Box::new(move |message, messages| {
+                let path = runner.git_dir.join("COMMIT_EDITMSG");
+                if let Err(err) = fs::write(&path, message.as_slice()) {
+                    messages.extend_from_slice(
+                        format!("error: could not write '{}': {}\n", path.display(), err).as_bytes(),
+                    );
+                    return false;
+                }
+                let args = [runner.git_path("COMMIT_EDITMSG").to_string_lossy().into_owned()];
+                let accepted = runner.run("commit-msg", &args, None, messages).unwrap_or(true);
+                match fs::read(&path) {
+                    Ok(edited) => *message = edited.into(),
+                    Err(err) => {
+                        messages.extend_from_slice(
+                            format!("error: could not read '{}': {}\n", path.display(), err).as_bytes(),
+                        );
+                        return false;
+                    }
+                }
+                accepted
+            }));
+        }
+        if runner.find("post-commit").is_some() {
+            hooks.post_commit = Some(Box::new(move |_id, messages| {
+                runner.run("post-commit", &[], None, messages);
+            }));
+        }
+        hooks
+    }
+}
+
+/// Configure how to commit.
+#[derive(Default)]
+pub struct Options<'a> {
+    /// The ref to point to the new commit, or `None` to use `HEAD`, which usually means the branch it points to.
+    pub reference: Option<BString>,
+    /// The hooks to call.
+    pub hooks: Hooks<'a>,
+    /// If set, the commit is signed after the hooks accepted it.
+    pub sign: Option<Sign<'a>>,
+    /// The encoding of the message and the names of the signatures, like `ISO-8859-1`, as obtained with
+    /// [`encoding_from_config()`]. It's recorded in the `encoding` header unless it's UTF-8, which is the default.
+    pub encoding: Option<BString>,
+}
+
+/// Return the encoding of new commits as configured with `i18n.commitEncoding`, or `None` if it's unset or UTF-8.
+pub fn encoding_from_config(config: &git_config::File) -> Option<BString> {
+    config
+        .value("i18n", None, "commitEncoding")
+        .filter(|encoding| !is_utf8(encoding))
+        .map(ToOwned::to_owned)
+}
+
+/// Returns true if `encoding` names UTF-8 like git understands it, which doesn't need to be recorded.
+fn is_utf8(encoding: &BStr) -> bool {
+    encoding.eq_ignore_ascii_case(b"utf-8") || encoding.eq_ignore_ascii_case(b"utf8")
+}
+
+/// The result of [`create()`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Outcome {
+    /// The id of the new commit.
+    pub id: owned::Id,
+    /// The full name of the updated ref, after following symbolic refs.
+    pub reference: BString,
+    /// The object the ref pointed to before, or `None` if this is the first commit of a branch.
+    pub previous: Option<owned::Id>,
+    /// Everything the hooks wrote for the user.
+    pub messages: BString,
+}
+
+/// Create a commit of `tree` with `parents`, `author`, `committer` and `message` in the repository at `git_dir`, and
+/// point the [ref][Options::reference] to it, which must point to the first parent, or not exist if there is none.
+pub fn create(
+    git_dir: impl AsRef<Path>,
+    tree: owned::Id,
+    parents: impl IntoIterator<Item = owned::Id>,
+    author: owned::Signature,
+    committer: owned::Signature,
+    message: impl Into<BString>,
+    mut options: Options<'_>,
+) -> Result<Outcome, Error> {
+    let git_dir = git_dir.as_ref();
+    let mut commit = owned::Commit {
+        tree,
+        parents: parents.into_iter().collect(),
+        author,
+        committer,
+        encoding: options.encoding.take().filter(|encoding| !is_utf8(encoding.as_ref())),
+        message: message.into(),
+        extra_headers: Vec::new(),
+    };
+    let requested = options.reference.take().unwrap_or_else(|| "HEAD".into());
+    let (reference, previous) = update::resolve(git_dir, requested.as_ref())?;
+    let expected = commit.parents.first().copied();
+    if previous != expected {
+        return Err(Error::RefChanged(reference, expected, previous));
+    }
+
+    let mut messages = Vec::new();
+    if let Some(pre_commit) = options.hooks.pre_commit.as_mut() {
+        if !pre_commit(&commit, &mut messages) {
+            return Err(Error::Rejected("pre-commit", messages.into()));
+        }
+    }
+    if let Some(commit_msg) = options.hooks.commit_msg.as_mut() {
+        if !commit_msg(&mut commit.message, &mut messages) {
+            return Err(Error::Rejected("commit-msg", messages.into()));
+        }
+    }
+    if let Some(sign) = options.sign.as_mut() {
+        let mut payload = Vec::new();
+        commit
+            .write_to(&mut payload)
+            .expect("writing to a vector does not fail");
+        let mut signature = sign(&payload).map_err(Error::Sign)?;
+        if signature.ends_with(b"\n") {
+            signature.pop();
+        }
+        commit.extra_headers.push(("gpgsig".into(), signature));
+    }
+
+    let id = write(git_dir, &commit)?;
+    move_ref(
+        git_dir,
+        requested.as_ref(),
+        reference.as_ref(),
+        previous,
+        id,
+        &commit.committer,
+        reflog_message(&commit).as_ref(),
+    )?;
+
+    if let Some(post_commit) = options.hooks.post_commit.as_mut() {
+        post_commit(id, &mut messages);
+    }
+    Ok(Outcome {
+        id,
+        reference,
+        previous,
+        messages: messages.into(),
+    })
+}
+
+/// Write `commit` into the object database of the repository at `git_dir`.
+pub(crate) fn write(git_dir: &Path, commit: &owned::Commit) -> Result<owned::Id, Error> {
+    Ok(loose::Db::at(linked::common_dir(git_dir)?.join("objects"))
+        .write(&owned::Object::Commit(commit.clone()), HashKind::Sha1)?)
+}
+
+/// Point `reference`, which `requested` resolved to, from `previous` to `id` and add an entry with `message` to its
+/// reflog, as well as to the one of `requested` if it's a different, symbolic ref.
+pub(crate) fn move_ref(
+    git_dir: &Path,
+    requested: &BStr,
+    reference: &BStr,
+    previous: Option<owned::Id>,
+    id: owned::Id,
+    committer: &owned::Signature,
+    message: &BStr,
+) -> Result<(), Error> {
+    let reflog_options = update::Options {
+        committer: committer.clone(),
+        message: message.to_owned(),
+        dry_run: false,
+        hooks: None,
+    };
+    update::write_ref(git_dir, reference, previous, id, message, &reflog_options)?;
+    if reference != requested {
+        update::append_reflog(git_dir, requested, previous, id, message, &reflog_options)
+            .map_err(|err| Error::Io(err, git_dir.join("logs").join(requested.to_path_lossy())))?;
+    }
+    Ok(())
+}
+
+/// The reflog message git uses, like `commit (initial): subject`.
+fn reflog_message(commit: &owned::Commit) -> BString {
+    let kind = match commit.parents.len() {
+        0 => "commit (initial)",
+        1 => "commit",
+        _ => "commit (merge)",
+    };
+    let subject: &BStr = commit.message.lines().next().unwrap_or_default().as_bstr();
+    let mut message = BString::from(kind);
+    message.extend_from_slice(b": ");
+    message.extend_from_slice(subject);
+    message
+}
+
+/// Split the data of a signed commit object into the payload which was signed and the signature, or return `None` if it
+/// isn't signed.
+///
+/// Like git, the payload is the commit without its `gpgsig` header, and the signature is the value of the header with
+/// each of its lines terminated by a newline.
+pub fn extract_signature(data: &[u8]) -> Option<(BString, BString)> {
+    let mut payload = Vec::with_capacity(data.len());
+    let mut signature: Option<Vec<u8>> = None;
+    let mut in_signature = false;
+    let mut lines = data.lines_with_terminator();
+    for line in &mut lines {
+        if line == b"\n" {
+            payload.extend_from_slice(line);
+            break;
+        }
+        if in_signature && line.starts_with(b" ") {
+            let signature = signature.as_mut().expect("set when starting the signature");
+            signature.extend_from_slice(&line[1..]);
+            if !line.ends_with(b"\n") {
+                signature.push(b'\n');
+            }
+            continue;
+        }
+        in_signature = false;
+        if signature.is_none() && line.starts_with(b"gpgsig ") {
+            let mut value = line[b"gpgsig ".len()..].to_vec();
+            if !value.ends_with(b"\n") {
+                value.push(b'\n');
+            }
+            signature = Some(value);
+            in_signature = true;
+            continue;
+        }
+        payload.extend_from_slice(line);
+    }
+    for line in lines {
+        payload.extend_from_slice(line);
+    }
+    signature.map(|signature| (payload.into(), signature.into()))
+}
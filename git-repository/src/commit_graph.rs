@@ -0,0 +1,283 @@
+//! Read the commit-graph file at `objects/info/commit-graph` and the Bloom filters of changed paths it may contain.
+//!
+//! Only the lookup of commits and their changed-path Bloom filters, as written by `git commit-graph write
+//! --changed-paths`, is supported. Split commit-graphs in `objects/info/commit-graphs` are not read yet.
+use git_object::{borrowed, owned};
+use git_odb::open;
+use quick_error::quick_error;
+use std::{
+    convert::{TryFrom, TryInto},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not read the commit-graph at '{}'", path.display())
+            source(err)
+        }
+        Corrupt(message: &'static str) {
+            display("The commit-graph is corrupt: {}", message)
+        }
+        Unsupported(message: String) {
+            display("The commit-graph is not supported: {}", message)
+        }
+        ChecksumMismatch { expected: owned::Id, actual: owned::Id } {
+            display("The commit-graph checksum didn't match: expected {}, got {}", expected, actual)
+        }
+    }
+}
+
+/// The path of the commit-graph file, relative to the git directory.
+pub const FILE_NAME: &str = "objects/info/commit-graph";
+const SIGNATURE: &[u8] = b"CGPH";
+const HEADER_LEN: usize = 8;
+const CHUNK_ENTRY_LEN: usize = 12;
+const SHA1_LEN: usize = 20;
+const FANOUT_LEN: usize = 256 * 4;
+const BLOOM_HEADER_LEN: usize = 12;
+const BLOOM_SEEDS: [u32; 2] = [0x293a_e76f, 0x7e64_6e2c];
+
+/// A commit-graph file.
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+pub struct File {
+    data: Vec<u8>,
+    num_commits: u32,
+    fanout: usize,
+    lookup: usize,
+    bloom: Option<BloomChunks>,
+}
+
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy)]
+struct BloomChunks {
+    index: usize,
+    data: usize,
+    data_end: usize,
+    hash_version: u32,
+    num_hashes: u32,
+}
+
+/// The Bloom filter of the paths a commit changed compared to its first parent, including all of their directories.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy)]
+pub struct BloomFilter<'a> {
+    data: &'a [u8],
+    hash_version: u32,
+    num_hashes: u32,
+}
+
+impl File {
+    /// Parse the commit-graph in `data`.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, Error> {
+        if data.len() < HEADER_LEN || &data[..4] != SIGNATURE {
+            return Err(Error::Corrupt("the signature is missing"));
+        }
+        match (data[4], data[5], data[7]) {
+            (1, 1, 0) => {}
+            (1, 1, _) => return Err(Error::Unsupported("split commit-graphs can't be read".into())),
+            (version, hash, _) => {
+                return Err(Error::Unsupported(format!(
+                    "version {} with hash version {} is unknown",
+                    version, hash
+                )))
+            }
+        }
+        let num_chunks = usize::from(data[6]);
+        let table = data
+            .get(HEADER_LEN..HEADER_LEN + (num_chunks + 1) * CHUNK_ENTRY_LEN)
+            .ok_or(Error::Corrupt("the chunk table is truncated"))?;
+        let mut chunks = Vec::with_capacity(num_chunks);
+        for entry in table.chunks(CHUNK_ENTRY_LEN).take(num_chunks) {
+            let start = be_u64(&entry[4..]) as usize;
+            let end = be_u64(&table[chunks.len() * CHUNK_ENTRY_LEN + CHUNK_ENTRY_LEN + 4..]) as usize;
+            if start > end || end > data.len() {
+                return Err(Error::Corrupt("a chunk is out of bounds"));
+            }
+            chunks.push((&entry[..4], start, end));
+        }
+        let chunk = |id: &[u8]| {
+            chunks
+                .iter()
+                .find(|(chunk_id, ..)| *chunk_id == id)
+                .map(|(_, s, e)| (*s, *e))
+        };
+
+        let (fanout, fanout_end) = chunk(b"OIDF").ok_or(Error::Corrupt("the fanout chunk is missing"))?;
+        if fanout_end - fanout != FANOUT_LEN {
+            return Err(Error::Corrupt("the fanout chunk has the wrong size"));
+        }
+        let fanout_entries = &data[fanout..fanout_end];
+        if fanout_entries
+            .chunks(4)
+            .zip(fanout_entries.chunks(4).skip(1))
+            .any(|(a, b)| be_u32(a) > be_u32(b))
+        {
+            return Err(Error::Corrupt("the fanout chunk isn't ascending"));
+        }
+        // The last entry of the ascending fanout is the amount of commits, so no lookup can exceed it.
+        let num_commits = be_u32(&data[fanout + FANOUT_LEN - 4..]);
+        let (lookup, lookup_end) = chunk(b"OIDL").ok_or(Error::Corrupt("the lookup chunk is missing"))?;
+        if lookup_end - lookup != num_commits as usize * SHA1_LEN {
+            return Err(Error::Corrupt("the lookup chunk has the wrong size"));
+        }
+        let bloom = match (chunk(b"BIDX"), chunk(b"BDAT")) {
+            (Some((index, index_end)), Some((bloom_data, data_end))) => {
+                if index_end - index != num_commits as usize * 4 || data_end - bloom_data < BLOOM_HEADER_LEN {
+                    return Err(Error::Corrupt("the bloom filter chunks have the wrong size"));
+                }
+                Some(BloomChunks {
+                    index,
+                    data: bloom_data + BLOOM_HEADER_LEN,
+                    data_end,
+                    hash_version: be_u32(&data[bloom_data..]),
+                    num_hashes: be_u32(&data[bloom_data + 4..]),
+                })
+            }
+            _ => None,
+        };
+        Ok(File {
+            data,
+            num_commits,
+            fanout,
+            lookup,
+            bloom,
+        })
+    }
+
+    /// Read the commit-graph of the repository at `git_dir`, or `None` if there is none.
+    pub fn at(git_dir: impl AsRef<Path>) -> Result<Option<Self>, Error> {
+        Self::at_with_options(git_dir, open::Options::default())
+    }
+
+    /// Like [`at()`][File::at()], but verify as much of the commit-graph as configured in `options` before returning it.
+    pub fn at_with_options(git_dir: impl AsRef<Path>, options: open::Options) -> Result<Option<Self>, Error> {
+        let path = git_dir.as_ref().join(FILE_NAME);
+        let graph = match fs::read(&path) {
+            Ok(data) => Self::from_bytes(data)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::Io(err, path)),
+        };
+        if options.verify >= open::Verify::Checksum {
+            let trailer = graph
+                .data
+                .len()
+                .checked_sub(SHA1_LEN)
+                .filter(|trailer| *trailer >= HEADER_LEN)
+                .ok_or(Error::Corrupt("the checksum is missing"))?;
+            let expected = owned::Id::from_20_bytes(&graph.data[trailer..]);
+            let actual = owned::Id::new_sha1(git_features::hash::sha1(&graph.data[..trailer]));
+            if actual != expected {
+                return Err(Error::ChecksumMismatch { expected, actual });
+            }
+        }
+        Ok(Some(graph))
+    }
+
+    /// The amount of commits in the graph.
+    pub fn num_commits(&self) -> u32 {
+        self.num_commits
+    }
+
+    /// The id of the commit at `position`, which must be smaller than [`num_commits()`][File::num_commits()].
+    pub fn id_at(&self, position: u32) -> borrowed::Id<'_> {
+        let start = self.lookup + position as usize * SHA1_LEN;
+        borrowed::Id::try_from(&self.data[start..start + SHA1_LEN]).expect("20 bytes are an id")
+    }
+
+    /// The position of the commit `id` in the graph, or `None` if it isn't contained.
+    pub fn lookup(&self, id: borrowed::Id<'_>) -> Option<u32> {
+        let id = id.sha1();
+        let first_byte = usize::from(id[0]);
+        let mut lower = match first_byte {
+            0 => 0,
+            _ => be_u32(&self.data[self.fanout + (first_byte - 1) * 4..]),
+        };
+        let mut upper = be_u32(&self.data[self.fanout + first_byte * 4..]);
+        while lower < upper {
+            let middle = lower + (upper - lower) / 2;
+            match self.id_at(middle).sha1().cmp(id) {
+                std::cmp::Ordering::Less => lower = middle + 1,
+                std::cmp::Ordering::Greater => upper = middle,
+                std::cmp::Ordering::Equal => return Some(middle),
+            }
+        }
+        None
+    }
+
+    /// The changed-path Bloom filter of the commit `id`, or `None` if the commit isn't in the graph or has no filter,
+    /// or if the filters were written with an unknown hash version.
+    pub fn bloom_filter(&self, id: &owned::Id) -> Option<BloomFilter<'_>> {
+        let bloom = self.bloom.filter(|bloom| matches!(bloom.hash_version, 1 | 2))?;
+        let position = self.lookup(id.to_borrowed())? as usize;
+        let end = be_u32(&self.data[bloom.index + position * 4..]) as usize;
+        let start = match position {
+            0 => 0,
+            _ => be_u32(&self.data[bloom.index + (position - 1) * 4..]) as usize,
+        };
+        let data = self.data.get(bloom.data + start..bloom.data + end)?;
+        if data.is_empty() || bloom.data + end > bloom.data_end {
+            return None;
+        }
+        Some(BloomFilter {
+            data,
+            hash_version: bloom.hash_version,
+            num_hashes: bloom.num_hashes,
+        })
+    }
+}
+
+impl<'a> BloomFilter<'a> {
+    /// Return false if the file or directory `path` was definitely not changed, or true if it may have been.
+    pub fn may_contain(&self, path: &[u8]) -> bool {
+        let hashes = [
+            murmur3(BLOOM_SEEDS[0], path, self.hash_version),
+            murmur3(BLOOM_SEEDS[1], path, self.hash_version),
+        ];
+        let bits = self.data.len() as u64 * 8;
+        (0..self.num_hashes).all(|i| {
+            let bit = u64::from(hashes[0].wrapping_add(i.wrapping_mul(hashes[1]))) % bits;
+            self.data[(bit / 8) as usize] & (1 << (bit % 8)) != 0
+        })
+    }
+}
+
+/// The 32 bit murmur3 hash of `data`, where hash version 1 treats bytes as signed like a bug in git did.
+fn murmur3(seed: u32, data: &[u8], hash_version: u32) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+    let byte = |b: u8| match hash_version {
+        1 => b as i8 as u32,
+        _ => u32::from(b),
+    };
+    let mut hash = seed;
+    let mut blocks = data.chunks_exact(4);
+    for block in &mut blocks {
+        let mut k = byte(block[0]) | byte(block[1]) << 8 | byte(block[2]) << 16 | byte(block[3]) << 24;
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        hash ^= k;
+        hash = hash.rotate_left(13).wrapping_mul(5).wrapping_add(0xe654_6b64);
+    }
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        let mut k = 0;
+        for (index, b) in tail.iter().enumerate().rev() {
+            k ^= byte(*b) << (8 * index);
+        }
+        hash ^= k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+    }
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
+
+fn be_u32(data: &[u8]) -> u32 {
+    u32::from_be_bytes(data[..4].try_into().expect("4 bytes"))
+}
+
+fn be_u64(data: &[u8]) -> u64 {
+    u64::from_be_bytes(data[..8].try_into().expect("8 bytes"))
+}
@@ -0,0 +1,444 @@
+//! Parse dates the way git does for `--since`, `--until` and `<ref>@{<date>}`, from exact timestamps to approximate
+//! dates like `2 weeks ago` or `yesterday noon`.
+//!
+//! The formats understood are:
+//!
+//! * unix timestamps like `@1234567890` or `1234567890`, and git's internal format `1234567890 +0100`
+//! * RFC2822 dates like `Thu, 07 Apr 2005 22:13:13 +0200`
+//! * ISO8601 dates like `2005-04-07T22:13:13+02:00` or `2005-04-07 22:13:13`, and also `2005.04.07` or `04/07/2005`
+//! * approximate dates made of numbers, month names, times like `10:00` and words like `yesterday`, `noon`,
+//!   `midnight`, `now`, `last`, `am` and `pm`, as well as units from `seconds` to `years` followed by an optional `ago`
+//!
+//! Like with git, fields not mentioned are taken from the current time, so `2005-04-07` is at the current time of day,
+//! and dates without a timezone are in the local one, which is the one of the current time passed by the caller.
+//! Unlike git, which uses the current time instead, input it doesn't understand is an error.
+use git_object::{
+    bstr::{BStr, BString, ByteSlice},
+    Sign, Time,
+};
+use quick_error::quick_error;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Invalid(input: BString) {
+            display("The date '{}' could not be parsed", input)
+        }
+        UnknownWord(word: BString, input: BString) {
+            display("The word '{}' is not understood in the date '{}'", word, input)
+        }
+        OutOfRange(input: BString) {
+            display("The date '{}' is before 1970 or too far in the future", input)
+        }
+    }
+}
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+const MONTHS: &[&str] = &[
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+const WEEKDAYS: &[&str] = &[
+    "sunday",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+];
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum Token<'a> {
+    Number { value: i64, digits: usize },
+    Word(&'a str),
+    Punct(char),
+}
+
+/// Parse `input` relative to `now`, whose offset is used as the local timezone, and return the time it denotes along
+/// with the timezone given in `input`, or the one of `now` if there was none.
+pub fn parse(input: &BStr, now: Time) -> Result<Time, Error> {
+    let text = input.to_str().map_err(|_| Error::Invalid(input.to_owned()))?;
+    let text = text.trim().to_ascii_lowercase();
+    if let Some(time) = parse_timestamp(&text) {
+        return time.ok_or_else(|| Error::OutOfRange(input.to_owned()));
+    }
+
+    let tokens = tokenize(&text);
+    let local = i64::from(now.time) + i64::from(now.offset);
+    let (mut year, mut month, mut day) = civil_from_days(local.div_euclid(SECONDS_PER_DAY));
+    let seconds_of_day = local.rem_euclid(SECONDS_PER_DAY);
+    let (mut hour, mut minute, mut second) = (seconds_of_day / 3600, seconds_of_day / 60 % 60, seconds_of_day % 60);
+    let mut offset = None;
+    let mut seconds_ago = 0;
+    let mut months_ago = 0;
+    let mut pending = None;
+    let mut understood = false;
+
+    let invalid = || Error::Invalid(input.to_owned());
+    let out_of_range = || Error::OutOfRange(input.to_owned());
+    // Add `count` times `unit` to `total`, failing instead of overflowing for large counts.
+    let add = |total: i64, count: i64, unit: i64| {
+        count
+            .checked_mul(unit)
+            .and_then(|value| total.checked_add(value))
+            .ok_or_else(out_of_range)
+    };
+    let number_at = |pos: usize| match tokens.get(pos) {
+        Some(Token::Number { value, digits }) => Some((*value, *digits)),
+        _ => None,
+    };
+    let mut pos = 0;
+    while pos < tokens.len() {
+        match tokens[pos] {
+            Token::Number { value, digits } => {
+                if tokens.get(pos + 1) == Some(&Token::Punct(':')) {
+                    let (minutes, _) = number_at(pos + 2).ok_or_else(invalid)?;
+                    let (seconds, consumed) = match (tokens.get(pos + 3), number_at(pos + 4)) {
+                        (Some(Token::Punct(':')), Some((seconds, _))) => (seconds, 5),
+                        _ => (0, 3),
+                    };
+                    if value > 23 || minutes > 59 || seconds > 60 {
+                        return Err(invalid());
+                    }
+                    hour = value;
+                    minute = minutes;
+                    second = seconds;
+                    pos += consumed;
+                    understood = true;
+                    continue;
+                }
+                if let (
+                    Some(Token::Punct(separator)),
+                    Some((b, _)),
+                    Some(Token::Punct(separator2)),
+                    Some((c, c_digits)),
+                ) = (
+                    tokens.get(pos + 1),
+                    number_at(pos + 2),
+                    tokens.get(pos + 3),
+                    number_at(pos + 4),
+                ) {
+                    if separator == separator2 && matches!(separator, '-' | '.' | '/') {
+                        let (y, m, d) = if digits == 4 {
+                            (value, b, c)
+                        } else if c_digits == 4 && *separator == '/' {
+                            (c, value, b)
+                        } else if c_digits == 4 {
+                            (c, b, value)
+                        } else {
+                            return Err(invalid());
+                        };
+                        if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+                            return Err(invalid());
+                        }
+                        year = y;
+                        month = m;
+                        day = d;
+                        pos += 5;
+                        understood = true;
+                        continue;
+                    }
+                }
+                if digits == 4 && (1970..2100).contains(&value) {
+                    year = value;
+                    understood = true;
+                } else if pending.replace(value).is_some() {
+                    return Err(invalid());
+                }
+            }
+            Token::Word(word) => {
+                if let Some(index) = find_name(MONTHS, word) {
+                    month = index as i64 + 1;
+                    match pending.take() {
+                        Some(value) => day = value,
+                        None => {
+                            if let Some((value, digits)) = number_at(pos + 1) {
+                                if digits <= 2 && tokens.get(pos + 2) != Some(&Token::Punct(':')) {
+                                    day = value;
+                                    pos += 1;
+                                }
+                            }
+                        }
+                    }
+                    if !(1..=31).contains(&day) {
+                        return Err(invalid());
+                    }
+                } else if let Some(seconds) = unit_seconds(word) {
+                    let count = pending.take().unwrap_or(1);
+                    match seconds {
+                        Some(seconds) => seconds_ago = add(seconds_ago, count, seconds)?,
+                        None if word.starts_with("month") => months_ago = add(months_ago, count, 1)?,
+                        None => months_ago = add(months_ago, count, 12)?,
+                    }
+                } else {
+                    match word {
+                        "ago" | "now" | "today" | "t" => {}
+                        "yesterday" => seconds_ago = add(seconds_ago, 1, SECONDS_PER_DAY)?,
+                        "last" => pending = Some(1),
+                        "noon" | "midnight" => {
+                            if word == "noon" && hour * 3600 + minute * 60 + second < 12 * 3600 {
+                                seconds_ago = add(seconds_ago, 1, SECONDS_PER_DAY)?;
+                            }
+                            hour = if word == "noon" { 12 } else { 0 };
+                            minute = 0;
+                            second = 0;
+                        }
+                        "am" if hour == 12 => hour = 0,
+                        "pm" if hour < 12 => hour += 12,
+                        "am" | "pm" => {}
+                        "utc" | "gmt" | "z" => offset = Some(0),
+                        _ if find_name(WEEKDAYS, word).is_some() => {}
+                        _ => return Err(Error::UnknownWord(word.into(), input.to_owned())),
+                    }
+                }
+                understood = true;
+            }
+            Token::Punct(sign @ '+') | Token::Punct(sign @ '-') => {
+                let (hours, minutes, consumed) = match (number_at(pos + 1), tokens.get(pos + 2), number_at(pos + 3)) {
+                    (Some((hhmm, 4)), _, _) => (hhmm / 100, hhmm % 100, 2),
+                    (Some((hours, 2)), Some(Token::Punct(':')), Some((minutes, 2))) => (hours, minutes, 4),
+                    _ => return Err(invalid()),
+                };
+                let seconds = (hours * 3600 + minutes * 60) as i32;
+                offset = Some(if sign == '-' { -seconds } else { seconds });
+                pos += consumed;
+                understood = true;
+                continue;
+            }
+            Token::Punct(_) => {}
+        }
+        pos += 1;
+    }
+    if !understood || pending.is_some() {
+        return Err(invalid());
+    }
+
+    // Years have at most four digits, so only going back by a huge amount of months could overflow.
+    let months = (year * 12 + (month - 1))
+        .checked_sub(months_ago)
+        .filter(|months| *months >= 0)
+        .ok_or_else(out_of_range)?;
+    let days = days_from_civil(months.div_euclid(12), months.rem_euclid(12) + 1, day);
+    let offset = offset.unwrap_or(now.offset);
+    let time = (days * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second - i64::from(offset))
+        .checked_sub(seconds_ago)
+        .ok_or_else(out_of_range)?;
+    to_time(time, offset).ok_or_else(out_of_range)
+}
+
+/// Parse `@1234567890`, `1234567890 +0100` or `1234567890`, whose value must have more than 8 digits to not be
+/// confused with approximate dates. The outer `Option` is `None` if `text` isn't a timestamp, the inner one if it's
+/// out of range.
+fn parse_timestamp(text: &str) -> Option<Option<Time>> {
+    let (forced, text) = match text.strip_prefix('@') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let (seconds, offset) = match text.find(' ') {
+        Some(pos) => (&text[..pos], Some(text[pos + 1..].trim_start())),
+        None => (text, None),
+    };
+    if seconds.is_empty() || !seconds.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let offset = match offset {
+        Some(offset) => {
+            let sign = match offset.as_bytes().first() {
+                Some(b'+') => 1,
+                Some(b'-') => -1,
+                _ => return None,
+            };
+            let hhmm = &offset[1..];
+            if hhmm.len() != 4 || !hhmm.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let hhmm: i32 = hhmm.parse().ok()?;
+            sign * (hhmm / 100 * 3600 + hhmm % 100 * 60)
+        }
+        None if forced || seconds.len() > 8 => 0,
+        None => return None,
+    };
+    Some(seconds.parse().ok().and_then(|seconds| to_time(seconds, offset)))
+}
+
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let start = pos;
+        let b = bytes[pos];
+        if b.is_ascii_digit() {
+            while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                pos += 1;
+            }
+            let digits = &text[start..pos];
+            tokens.push(Token::Number {
+                value: digits.parse().unwrap_or(i64::MAX),
+                digits: digits.len(),
+            });
+        } else if b.is_ascii_alphabetic() {
+            while pos < bytes.len() && bytes[pos].is_ascii_alphabetic() {
+                pos += 1;
+            }
+            tokens.push(Token::Word(&text[start..pos]));
+        } else {
+            pos += text[pos..].chars().next().map_or(1, char::len_utf8);
+            if !b.is_ascii_whitespace() {
+                tokens.push(Token::Punct(
+                    text[start..pos].chars().next().expect("at least one char"),
+                ));
+            }
+        }
+    }
+    tokens
+}
+
+/// Find the index of `word` in `names`, where it may be abbreviated to at least three characters.
+fn find_name(names: &[&str], word: &str) -> Option<usize> {
+    if word.len() < 3 {
+        return None;
+    }
+    names.iter().position(|name| name.starts_with(word))
+}
+
+/// Return the seconds of the unit `word`, like `weeks`, or `Some(None)` for months and years, which don't have a fixed
+/// amount of seconds.
+fn unit_seconds(word: &str) -> Option<Option<i64>> {
+    let unit = word.strip_suffix('s').unwrap_or(word);
+    Some(match unit {
+        "sec" | "second" => Some(1),
+        "min" | "minute" => Some(60),
+        "hour" => Some(3600),
+        "day" => Some(SECONDS_PER_DAY),
+        "week" => Some(7 * SECONDS_PER_DAY),
+        "month" | "year" => None,
+        _ => return None,
+    })
+}
+
+fn to_time(seconds: i64, offset: i32) -> Option<Time> {
+    if seconds < 0 || seconds > i64::from(u32::MAX) {
+        return None;
+    }
+    Some(Time {
+        time: seconds as u32,
+        offset,
+        sign: if offset < 0 { Sign::Minus } else { Sign::Plus },
+    })
+}
+
+/// How to show dates, like `git log --date=<format>` does.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Format {
+    /// Like `Thu Apr 7 22:13:13 2005 +0200`, the default of `git log`.
+    #[default]
+    Default,
+    /// Like `Thu, 7 Apr 2005 22:13:13 +0200`, as used in emails.
+    Rfc2822,
+    /// Like `2005-04-07 22:13:13 +0200`, which is similar to ISO 8601.
+    Iso8601,
+    /// Like `2005-04-07T22:13:13+02:00`, which is strictly ISO 8601.
+    Iso8601Strict,
+    /// The seconds since the unix epoch, like `1112904793`.
+    Unix,
+}
+
+impl Format {
+    /// Return the format with `name`, as given to `--date`, like `rfc` or `iso-strict`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "default" => Format::Default,
+            "rfc" | "rfc2822" => Format::Rfc2822,
+            "iso" | "iso8601" => Format::Iso8601,
+            "iso-strict" | "iso8601-strict" => Format::Iso8601Strict,
+            "unix" => Format::Unix,
+            _ => return None,
+        })
+    }
+}
+
+/// Write `time` in its own timezone in the given `format`.
+pub fn format(time: Time, format: Format) -> String {
+    if format == Format::Unix {
+        return time.time.to_string();
+    }
+    let seconds = i64::from(time.time) + i64::from(time.offset);
+    let days = seconds.div_euclid(SECONDS_PER_DAY);
+    let seconds_of_day = seconds.rem_euclid(SECONDS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    let (hour, minute, second) = (seconds_of_day / 3600, seconds_of_day / 60 % 60, seconds_of_day % 60);
+    let abbreviation = |name: &str| format!("{}{}", name[..1].to_uppercase(), &name[1..3]);
+    let weekday = abbreviation(WEEKDAYS[(days + 4).rem_euclid(7) as usize]);
+    let month_name = abbreviation(MONTHS[month as usize - 1]);
+    let offset_minutes = time.offset.abs() / 60;
+    // Like git, the sign of offsets of zero isn't kept.
+    let sign = if time.offset < 0 { '-' } else { '+' };
+    let zone = format!("{}{:02}{:02}", sign, offset_minutes / 60, offset_minutes % 60);
+    match format {
+        Format::Default => format!(
+            "{} {} {} {:02}:{:02}:{:02} {} {}",
+            weekday, month_name, day, hour, minute, second, year, zone
+        ),
+        Format::Rfc2822 => format!(
+            "{}, {} {} {} {:02}:{:02}:{:02} {}",
+            weekday, day, month_name, year, hour, minute, second, zone
+        ),
+        Format::Iso8601 => format!(
+            "{}-{:02}-{:02} {:02}:{:02}:{:02} {}",
+            year, month, day, hour, minute, second, zone
+        ),
+        Format::Iso8601Strict => format!(
+            "{}-{:02}-{:02}T{:02}:{:02}:{:02}{}{:02}:{:02}",
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            sign,
+            offset_minutes / 60,
+            offset_minutes % 60
+        ),
+        Format::Unix => unreachable!("handled above"),
+    }
+}
+
+/// The days since 1970-01-01 of the given date, where days beyond the end of `month` continue into the next one.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The year, month and day of the given days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    (year_of_era + era * 400 + if month <= 2 { 1 } else { 0 }, month, day)
+}
@@ -0,0 +1,418 @@
+//! Name a commit relative to the closest reachable tag, similar to `git describe`.
+use git_object::{
+    borrowed,
+    bstr::{BStr, ByteSlice},
+    owned,
+};
+use quick_error::quick_error;
+use std::{
+    borrow::Cow,
+    collections::{hash_map, BinaryHeap, HashMap, HashSet},
+    fmt,
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Find(id: owned::Id) {
+            display("Commit {} could not be found", id)
+        }
+        NotACommit(id: owned::Id) {
+            display("Object {} was expected to be a commit", id)
+        }
+    }
+}
+
+/// The default amount of candidates to consider, equivalent to `git describe --candidates=10`.
+pub const DEFAULT_MAX_CANDIDATES: usize = 10;
+/// The most candidates we can track at once.
+pub const MAX_CANDIDATES: usize = 32;
+
+/// The options for [`describe()`][describe()].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options<'name> {
+    /// The names to use for describing commits, usually the names of tags pointing (after peeling) to the commit used as key.
+    pub name_by_oid: HashMap<owned::Id, Cow<'name, BStr>>,
+    /// The amount of names we will keep track of, with at most [`MAX_CANDIDATES`][MAX_CANDIDATES].
+    ///
+    /// If set to 0, only exact matches are allowed, i.e. the commit to describe must have a name.
+    pub max_candidates: usize,
+    /// If not empty, only names matching at least one of these glob patterns will be considered.
+    ///
+    /// Patterns support `*`, `?` and `[…]` character classes, like `git describe --match`.
+    pub patterns: Vec<Cow<'name, BStr>>,
+    /// Only follow the first parent of merge commits.
+    pub first_parent: bool,
+    /// If no name can be found, return the commit id itself.
+    pub fallback_to_oid: bool,
+    /// Commits at the boundary of a shallow repository, which are treated as if they had no parents.
+    pub shallow: HashSet<owned::Id>,
+}
+
+impl<'name> Default for Options<'name> {
+    fn default() -> Self {
+        Options {
+            name_by_oid: Default::default(),
+            max_candidates: DEFAULT_MAX_CANDIDATES,
+            patterns: Vec::new(),
+            first_parent: false,
+            fallback_to_oid: false,
+            shallow: Default::default(),
+        }
+    }
+}
+
+/// The result of a successful [`describe()`][describe()] call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome<'name> {
+    /// The name of the tag or reference closest to `id`, or `None` if no name could be found and the options allowed
+    /// to fall back to the commit id.
+    pub name: Option<Cow<'name, BStr>>,
+    /// The commit that was described.
+    pub id: owned::Id,
+    /// The number of commits reachable from `id` which are not reachable from the commit carrying `name`.
+    pub depth: u32,
+    /// The amount of commits we traversed.
+    pub commits_seen: u32,
+}
+
+impl<'name> Outcome<'name> {
+    /// Turn this outcome into a structure that displays like `git describe`, using `hex_len` characters of the commit id.
+    pub fn into_format(self, hex_len: usize) -> Format<'name> {
+        Format {
+            name: self.name,
+            id: self.id,
+            hex_len,
+            depth: self.depth,
+            long: false,
+            dirty_suffix: None,
+        }
+    }
+}
+
+/// A structure implementing `Display` to produce `v1.2.0-14-gdeadbee` style output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Format<'name> {
+    /// The name of the closest tag, or `None` to display only the commit id.
+    pub name: Option<Cow<'name, BStr>>,
+    /// The commit being described.
+    pub id: owned::Id,
+    /// The amount of hex characters of `id` to display. If 0, only `name` is displayed, similar to `git describe --abbrev=0`.
+    pub hex_len: usize,
+    /// The amount of commits between `name` and `id`.
+    pub depth: u32,
+    /// If true, always display depth and commit id even if `id` is tagged with `name`.
+    pub long: bool,
+    /// If set, this suffix will be appended after a `-`, like `dirty`.
+    pub dirty_suffix: Option<String>,
+}
+
+impl<'name> Format<'name> {
+    /// Returns true if the commit is tagged with `name` exactly.
+    pub fn is_exact_match(&self) -> bool {
+        self.depth == 0
+    }
+}
+
+impl<'name> fmt::Display for Format<'name> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = self.id.to_sha1_hex();
+        let hex = hex[..self.hex_len.min(hex.len())].as_bstr();
+        match &self.name {
+            Some(name) => {
+                name.fmt(f)?;
+                if self.hex_len != 0 && (self.long || !self.is_exact_match()) {
+                    write!(f, "-{}-g{}", self.depth, hex)?;
+                }
+            }
+            None => hex.fmt(f)?,
+        }
+        if let Some(suffix) = &self.dirty_suffix {
+            write!(f, "-{}", suffix)?;
+        }
+        Ok(())
+    }
+}
+
+type Flags = u32;
+
+/// Information on how to obtain the parents of a commit.
+struct Parents {
+    first_parent: bool,
+    shallow: HashSet<owned::Id>,
+}
+
+struct Candidate<'name> {
+    name: Cow<'name, BStr>,
+    commits_in_its_future: u32,
+    identity_bit: Flags,
+    order: usize,
+}
+
+/// Describe the given `commit` by finding the closest name in `opts.name_by_oid`, traversing the commit graph in order of
+/// commit time.
+///
+/// `find` is used to lookup objects by id, placing their data into the provided buffer and returning the decoded object.
+///
+/// Returns `None` if no name could be found and `opts.fallback_to_oid` is false.
+pub fn describe<'name, Find>(
+    commit: borrowed::Id<'_>,
+    mut find: Find,
+    opts: Options<'name>,
+) -> Result<Option<Outcome<'name>>, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let Options {
+        mut name_by_oid,
+        max_candidates,
+        patterns,
+        first_parent,
+        fallback_to_oid,
+        shallow,
+    } = opts;
+    if !patterns.is_empty() {
+        name_by_oid.retain(|_, name| patterns.iter().any(|p| glob_matches(p.as_ref(), name.as_ref())));
+    }
+    let max_candidates = max_candidates.min(MAX_CANDIDATES);
+    let commit = owned::Id::from_borrowed_sha1(commit.sha1());
+
+    if let Some(name) = name_by_oid.remove(&commit) {
+        return Ok(Some(Outcome {
+            name: Some(name),
+            id: commit,
+            depth: 0,
+            commits_seen: 0,
+        }));
+    }
+    if max_candidates == 0 || name_by_oid.is_empty() {
+        return if fallback_to_oid {
+            Ok(Some(Outcome {
+                name: None,
+                id: commit,
+                depth: 0,
+                commits_seen: 0,
+            }))
+        } else {
+            Ok(None)
+        };
+    }
+
+    let mut buf = Vec::new();
+    let mut queue = BinaryHeap::new();
+    let parents = Parents { first_parent, shallow };
+    let mut seen = HashMap::<owned::Id, Flags>::new();
+    let mut candidates = Vec::<Candidate<'_>>::with_capacity(max_candidates);
+    let mut commits_seen = 0;
+    let mut gave_up_on_commit = None;
+
+    queue.push((commit_time(&mut find, &mut buf, commit)?, commit));
+    seen.insert(commit, 0);
+
+    while let Some((_, id)) = queue.pop() {
+        commits_seen += 1;
+        if let Some(name) = name_by_oid.remove(&id) {
+            if candidates.len() < max_candidates {
+                let identity_bit = 1 << candidates.len();
+                candidates.push(Candidate {
+                    name,
+                    commits_in_its_future: commits_seen - 1,
+                    identity_bit,
+                    order: candidates.len(),
+                });
+                *seen.get_mut(&id).expect("inserted when queued") |= identity_bit;
+            } else {
+                gave_up_on_commit = Some(id);
+                break;
+            }
+        }
+
+        let flags = seen[&id];
+        for candidate in candidates.iter_mut().filter(|c| flags & c.identity_bit == 0) {
+            candidate.commits_in_its_future += 1;
+        }
+
+        if queue.is_empty() && !candidates.is_empty() {
+            // Stop if the last remaining path is already covered by all of the best candidates.
+            let best_depth = candidates
+                .iter()
+                .map(|c| c.commits_in_its_future)
+                .min()
+                .expect("not empty");
+            let best_within = candidates
+                .iter()
+                .filter(|c| c.commits_in_its_future == best_depth)
+                .fold(0, |within, c| within | c.identity_bit);
+            if flags & best_within == best_within {
+                break;
+            }
+        }
+
+        queue_parents(&mut find, &mut buf, id, flags, &parents, &mut queue, &mut seen)?;
+    }
+
+    if candidates.is_empty() {
+        return if fallback_to_oid {
+            Ok(Some(Outcome {
+                name: None,
+                id: commit,
+                depth: 0,
+                commits_seen,
+            }))
+        } else {
+            Ok(None)
+        };
+    }
+
+    candidates.sort_by(|a, b| {
+        a.commits_in_its_future
+            .cmp(&b.commits_in_its_future)
+            .then_with(|| a.order.cmp(&b.order))
+    });
+
+    if let Some(id) = gave_up_on_commit {
+        queue.push((commit_time(&mut find, &mut buf, id)?, id));
+        commits_seen -= 1;
+    }
+
+    let best = &mut candidates[0];
+    commits_seen += finish_depth_computation(&mut find, &mut buf, queue, &mut seen, &parents, best)?;
+
+    let best = candidates.swap_remove(0);
+    Ok(Some(Outcome {
+        name: Some(best.name),
+        id: commit,
+        depth: best.commits_in_its_future,
+        commits_seen,
+    }))
+}
+
+fn finish_depth_computation<Find>(
+    find: &mut Find,
+    buf: &mut Vec<u8>,
+    mut queue: BinaryHeap<(u32, owned::Id)>,
+    seen: &mut HashMap<owned::Id, Flags>,
+    parents: &Parents,
+    best: &mut Candidate<'_>,
+) -> Result<u32, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let mut commits_seen = 0;
+    while let Some((_, id)) = queue.pop() {
+        commits_seen += 1;
+        let flags = seen[&id];
+        if flags & best.identity_bit == best.identity_bit {
+            if queue
+                .iter()
+                .all(|(_, id)| seen[id] & best.identity_bit == best.identity_bit)
+            {
+                break;
+            }
+        } else {
+            best.commits_in_its_future += 1;
+        }
+        queue_parents(find, buf, id, flags, parents, &mut queue, seen)?;
+    }
+    Ok(commits_seen)
+}
+
+fn queue_parents<Find>(
+    find: &mut Find,
+    buf: &mut Vec<u8>,
+    id: owned::Id,
+    flags: Flags,
+    parents: &Parents,
+    queue: &mut BinaryHeap<(u32, owned::Id)>,
+    seen: &mut HashMap<owned::Id, Flags>,
+) -> Result<(), Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    if parents.shallow.contains(&id) {
+        return Ok(());
+    }
+    let parent_ids: Vec<_> = {
+        let commit = find_commit(find, buf, id)?;
+        let ids = commit.parents();
+        if parents.first_parent {
+            ids.take(1).collect()
+        } else {
+            ids.collect()
+        }
+    };
+    for parent in parent_ids {
+        match seen.entry(parent) {
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(flags);
+                queue.push((commit_time(find, buf, parent)?, parent));
+            }
+            hash_map::Entry::Occupied(mut entry) => {
+                *entry.get_mut() |= flags;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn find_commit<'b, Find>(find: &mut Find, buf: &'b mut Vec<u8>, id: owned::Id) -> Result<borrowed::Commit<'b>, Error>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>>,
+{
+    match find(id.to_borrowed(), buf).ok_or(Error::Find(id))? {
+        borrowed::Object::Commit(commit) => Ok(commit),
+        _ => Err(Error::NotACommit(id)),
+    }
+}
+
+fn commit_time<Find>(find: &mut Find, buf: &mut Vec<u8>, id: owned::Id) -> Result<u32, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    find_commit(find, buf, id).map(|c| c.committer.time.time)
+}
+
+/// Match `text` against a glob `pattern` supporting `*`, `?`, `[…]` and backslash escapes.
+fn glob_matches(pattern: &BStr, text: &BStr) -> bool {
+    fn class_matches(class: &[u8], c: u8) -> Option<(bool, usize)> {
+        let mut i = 0;
+        let negated = matches!(class.first(), Some(b'!') | Some(b'^'));
+        if negated {
+            i += 1;
+        }
+        let mut matched = false;
+        let mut first = true;
+        while i < class.len() {
+            let start = class[i];
+            if start == b']' && !first {
+                return Some((matched != negated, i + 1));
+            }
+            first = false;
+            if class.get(i + 1) == Some(&b'-') && matches!(class.get(i + 2), Some(b) if *b != b']') {
+                let end = class[i + 2];
+                matched |= start <= c && c <= end;
+                i += 3;
+            } else {
+                matched |= start == c;
+                i += 1;
+            }
+        }
+        None
+    }
+
+    fn matches(p: &[u8], t: &[u8]) -> bool {
+        match p.first() {
+            None => t.is_empty(),
+            Some(b'*') => (0..=t.len()).any(|skip| matches(&p[1..], &t[skip..])),
+            Some(b'?') => !t.is_empty() && matches(&p[1..], &t[1..]),
+            Some(b'[') => match (t.first(), class_matches(&p[1..], t.first().copied().unwrap_or(0))) {
+                (Some(_), Some((true, consumed))) => matches(&p[1 + consumed..], &t[1..]),
+                (Some(c), None) => *c == b'[' && matches(&p[1..], &t[1..]),
+                _ => false,
+            },
+            Some(b'\\') if p.len() > 1 => t.first() == Some(&p[1]) && matches(&p[2..], &t[1..]),
+            Some(c) => t.first() == Some(c) && matches(&p[1..], &t[1..]),
+        }
+    }
+    matches(pattern.as_bytes(), text.as_bytes())
+}
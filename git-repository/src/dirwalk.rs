@@ -0,0 +1,415 @@
+//! Walk the files of a worktree like git does to find untracked and ignored files, reading directories in parallel.
+//!
+//! Directories are read level by level on multiple threads, and the `.gitignore` files found along the way are added
+//! to the patterns the walk starts with for everything below them. Each entry is classified as tracked if it's in the
+//! index, as ignored if the patterns apply to it, or as untracked otherwise. The `.git` directories and nested
+//! repositories are never entered and reported as a whole unless they are tracked as submodules. Untracked directories
+//! are optionally reported as a whole as well, like `git status` does by default, and so are ignored directories unless
+//! all files are reported.
+//!
+//! This is the shared basis for showing untracked files, adding them and cleaning them up.
+use crate::{ignore, index, worktree};
+use git_features::parallel;
+use git_object::bstr::{BStr, BString, ByteSlice, ByteVec};
+use quick_error::quick_error;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not read '{}'", path.display())
+            source(err)
+        }
+        IllformedName(path: PathBuf) {
+            display("The name of '{}' is not valid UTF-8", path.display())
+        }
+        Path(err: git_features::path::Error, path: BString) {
+            display("The path '{}' can't be used in the worktree", path)
+            source(err)
+        }
+    }
+}
+
+/// Configure what the walk reports.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub struct Options {
+    /// If true, tracked files are reported as well.
+    pub tracked: bool,
+    /// If true, ignored files and directories are reported, like with `git status --ignored`.
+    pub ignored: bool,
+    /// If true, untracked directories are reported as a single entry instead of their files, like `git status` does
+    /// unless `--untracked-files=all` is given. Directories without untracked files aren't reported as untracked, but
+    /// the ignored files inside them are if `ignored` is set, or the entire directory if all of its files are ignored.
+    ///
+    /// If false and `ignored` is set, ignored directories are entered to report each of their files as ignored like
+    /// `git status --ignored --untracked-files=all` does.
+    pub collapse_untracked_directories: bool,
+    /// The amount of threads to read directories with, or `None` to use all of them.
+    pub thread_limit: Option<usize>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            tracked: false,
+            ignored: false,
+            collapse_untracked_directories: true,
+            thread_limit: None,
+        }
+    }
+}
+
+/// How an entry relates to the index and the ignore patterns.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Status {
+    /// The entry is in the index, no matter if it's ignored or not.
+    Tracked,
+    /// The entry is ignored.
+    Ignored,
+    /// The entry is neither tracked nor ignored.
+    Untracked,
+}
+
+/// The kind of an entry in the worktree.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Kind {
+    /// A file.
+    File,
+    /// A symbolic link, which is never followed.
+    Link,
+    /// A directory, which is only reported if it wasn't entered.
+    Directory,
+    /// A directory with a `.git` file or directory, which is a submodule if it's tracked.
+    Repository,
+    /// Anything else, like a named pipe or a socket, which git can't track and which is only reported if it replaced a
+    /// tracked file.
+    Special,
+}
+
+/// A file or directory of the worktree.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    /// The path relative to the root of the worktree, without a trailing slash for directories.
+    pub path: BString,
+    /// The kind of entry.
+    pub kind: Kind,
+    /// How the entry relates to the index and the ignore patterns.
+    pub status: Status,
+}
+
+impl Entry {
+    /// Return true if the entry is a directory, which includes nested repositories.
+    pub fn is_directory(&self) -> bool {
+        matches!(self.kind, Kind::Directory | Kind::Repository)
+    }
+}
+
+/// The outcome of a walk.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The reported entries, ordered like git orders them, as if directories had a trailing slash.
+    pub entries: Vec<Entry>,
+}
+
+/// A directory to read, along with the patterns applying to its entries.
+struct Directory {
+    path: BString,
+    ignore: Arc<ignore::Stack>,
+    /// The untracked directory this one is part of, if untracked directories are collapsed.
+    collapsed_into: Option<Arc<BString>>,
+    /// If true, the directory is ignored along with all untracked entries in it.
+    ignored: bool,
+}
+
+/// What was found within one directory.
+#[derive(Default)]
+struct Listing {
+    entries: Vec<(Entry, Option<Arc<BString>>)>,
+    directories: Vec<Directory>,
+}
+
+/// The contents of an untracked directory which is reported as a whole.
+#[derive(Default)]
+struct Collapsed {
+    has_untracked: bool,
+    ignored: Vec<Entry>,
+}
+
+/// Walk the files of `worktree` on a filesystem with `capabilities`, classifying them using the paths of `index` and
+/// `excludes`, the patterns applying before those of the `.gitignore` files.
+pub fn walk(
+    worktree: impl AsRef<Path>,
+    index: &index::File,
+    excludes: &ignore::Stack,
+    capabilities: &worktree::Capabilities,
+    options: &Options,
+) -> Result<Outcome, Error> {
+    let mut span = git_features::trace::span("dirwalk");
+    let worktree = worktree.as_ref();
+    let tracked_files: HashSet<&BStr> = index.entries.iter().map(|e| e.path.as_bstr()).collect();
+    let mut tracked_directories = HashSet::new();
+    for path in &tracked_files {
+        let mut path = path.as_bytes();
+        while let Some(pos) = path.rfind_byte(b'/') {
+            path = &path[..pos];
+            if !tracked_directories.insert(path.as_bstr()) {
+                break;
+            }
+        }
+    }
+    let context = Context {
+        worktree,
+        tracked_files,
+        tracked_directories,
+        capabilities,
+        options,
+    };
+
+    let mut entries = Vec::new();
+    let mut collapsed = BTreeMap::<Arc<BString>, Collapsed>::new();
+    let mut level = vec![Directory {
+        path: BString::default(),
+        ignore: Arc::new(excludes.clone()),
+        collapsed_into: None,
+        ignored: false,
+    }];
+    let mut total_directories = 0;
+    while !level.is_empty() {
+        total_directories += level.len();
+        let num_directories = level.len();
+        let listings = parallel::in_parallel_if(
+            || num_directories > 1,
+            level.into_iter(),
+            options.thread_limit,
+            |_| (),
+            |directory, _| context.read(directory),
+            reduce::Collect::default(),
+        )?;
+        level = Vec::new();
+        for listing in listings {
+            for (entry, collapsed_into) in listing.entries {
+                match collapsed_into {
+                    Some(directory) => {
+                        let collapsed = collapsed.entry(directory).or_default();
+                        match entry.status {
+                            Status::Ignored => collapsed.ignored.push(entry),
+                            _ => collapsed.has_untracked = true,
+                        }
+                    }
+                    None => entries.push(entry),
+                }
+            }
+            level.extend(listing.directories);
+        }
+    }
+
+    for (directory, contents) in collapsed {
+        let directory = Arc::try_unwrap(directory).unwrap_or_else(|directory| (*directory).clone());
+        if contents.has_untracked {
+            entries.push(Entry {
+                path: directory,
+                kind: Kind::Directory,
+                status: Status::Untracked,
+            });
+            if options.ignored {
+                entries.extend(contents.ignored);
+            }
+        } else if options.ignored && !contents.ignored.is_empty() {
+            entries.push(Entry {
+                path: directory,
+                kind: Kind::Directory,
+                status: Status::Ignored,
+            });
+        }
+    }
+    entries.sort_by_cached_key(|entry| {
+        let mut key = entry.path.clone();
+        if entry.is_directory() {
+            key.push(b'/');
+        }
+        key
+    });
+    span.record(format_args!(
+        "directories={} entries={}",
+        total_directories,
+        entries.len()
+    ));
+    Ok(Outcome { entries })
+}
+
+/// What is shared by all threads reading directories.
+struct Context<'a> {
+    worktree: &'a Path,
+    tracked_files: HashSet<&'a BStr>,
+    tracked_directories: HashSet<&'a BStr>,
+    capabilities: &'a worktree::Capabilities,
+    options: &'a Options,
+}
+
+impl<'a> Context<'a> {
+    fn read(&self, directory: Directory) -> Result<Listing, Error> {
+        let native = if directory.path.is_empty() {
+            self.worktree.to_owned()
+        } else {
+            self.worktree.join(
+                git_features::path::to_native(&directory.path, Default::default())
+                    .map_err(|err| Error::Path(err, directory.path.clone()))?,
+            )
+        };
+        let ignore = match fs::read(native.join(ignore::FILE_NAME)) {
+            Ok(data) => {
+                let mut stack = (*directory.ignore).clone();
+                stack.add(directory.path.as_bstr(), &data);
+                Arc::new(stack)
+            }
+            Err(_) => directory.ignore,
+        };
+        let dir_entries = match fs::read_dir(&native) {
+            Ok(entries) => entries,
+            // The directory may have been removed or replaced by a file since its parent was read.
+            Err(err) if matches!(err.kind(), io::ErrorKind::NotFound | io::ErrorKind::NotADirectory) => {
+                return Ok(Listing::default())
+            }
+            Err(err) => return Err(Error::Io(err, native)),
+        };
+
+        let mut listing = Listing::default();
+        for dir_entry in dir_entries {
+            let dir_entry = dir_entry.map_err(|err| Error::Io(err, native.clone()))?;
+            let name = self.name(&dir_entry)?;
+            if name == ".git" {
+                continue;
+            }
+            let mut path = directory.path.clone();
+            if !path.is_empty() {
+                path.push(b'/');
+            }
+            path.push_str(&name);
+
+            let file_type = dir_entry.file_type().map_err(|err| Error::Io(err, dir_entry.path()))?;
+            let kind = if file_type.is_dir() {
+                match fs::symlink_metadata(dir_entry.path().join(".git")) {
+                    Ok(_) => Kind::Repository,
+                    Err(_) => Kind::Directory,
+                }
+            } else if file_type.is_symlink() {
+                Kind::Link
+            } else if file_type.is_file() {
+                Kind::File
+            } else {
+                Kind::Special
+            };
+            let is_directory = matches!(kind, Kind::Directory | Kind::Repository);
+
+            let status = if self.tracked_files.contains(path.as_bstr()) {
+                Status::Tracked
+            } else if is_directory && self.tracked_directories.contains(path.as_bstr()) {
+                listing.directories.push(Directory {
+                    path,
+                    ignore: ignore.clone(),
+                    collapsed_into: None,
+                    ignored: directory.ignored,
+                });
+                continue;
+            } else if directory.ignored || ignore.matches(path.as_bstr(), is_directory) {
+                Status::Ignored
+            } else {
+                Status::Untracked
+            };
+
+            match (status, kind) {
+                (Status::Tracked, _) if !self.options.tracked => continue,
+                (Status::Ignored, _) if !self.options.ignored => continue,
+                // Like git, such files are skipped as they can't be added.
+                (Status::Untracked, Kind::Special) | (Status::Ignored, Kind::Special) => continue,
+                (Status::Untracked, Kind::Directory) => {
+                    let collapsed_into = match &directory.collapsed_into {
+                        Some(collapsed_into) => Some(collapsed_into.clone()),
+                        None if self.options.collapse_untracked_directories => Some(Arc::new(path.clone())),
+                        None => None,
+                    };
+                    listing.directories.push(Directory {
+                        path,
+                        ignore: ignore.clone(),
+                        collapsed_into,
+                        ignored: false,
+                    });
+                    continue;
+                }
+                (Status::Ignored, Kind::Directory) if !self.options.collapse_untracked_directories => {
+                    listing.directories.push(Directory {
+                        path,
+                        ignore: ignore.clone(),
+                        collapsed_into: None,
+                        ignored: true,
+                    });
+                    continue;
+                }
+                _ => {}
+            }
+            listing
+                .entries
+                .push((Entry { path, kind, status }, directory.collapsed_into.clone()));
+        }
+        Ok(listing)
+    }
+
+    /// The name of `entry` as git would record it.
+    fn name(&self, entry: &fs::DirEntry) -> Result<BString, Error> {
+        let name = entry.file_name();
+        #[cfg(unix)]
+        let name = {
+            use std::os::unix::ffi::OsStrExt;
+            BString::from(name.as_bytes())
+        };
+        #[cfg(not(unix))]
+        let name = BString::from(name.to_str().ok_or_else(|| Error::IllformedName(entry.path()))?);
+        Ok(match name.to_str() {
+            Ok(utf8) if self.capabilities.precompose_unicode && !utf8.is_ascii() => {
+                use unicode_normalization::UnicodeNormalization;
+                utf8.nfc().collect::<String>().into()
+            }
+            _ => name,
+        })
+    }
+}
+
+mod reduce {
+    use super::Error;
+    use git_features::parallel;
+
+    pub struct Collect<T> {
+        pub items: Vec<T>,
+    }
+
+    impl<T> Default for Collect<T> {
+        fn default() -> Self {
+            Collect { items: Vec::new() }
+        }
+    }
+
+    impl<T> parallel::Reducer for Collect<T> {
+        type Input = Result<T, Error>;
+        type Output = Vec<T>;
+        type Error = Error;
+
+        fn feed(&mut self, input: Self::Input) -> Result<(), Self::Error> {
+            self.items.push(input?);
+            Ok(())
+        }
+
+        fn finalize(self) -> Result<Self::Output, Self::Error> {
+            Ok(self.items)
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Find the repository to work on from a directory inside of it, or open it at a known git directory, honoring the
+//! environment variables git uses to override its location and layout.
+//!
+//! These are `GIT_DIR`, `GIT_WORK_TREE`, `GIT_OBJECT_DIRECTORY`, `GIT_ALTERNATE_OBJECT_DIRECTORIES`, `GIT_INDEX_FILE`
+//! and `GIT_CEILING_DIRECTORIES`, which hooks and wrappers in CI set to point git at a repository other than the one
+//! the current directory is in. Relative paths in them are relative to the current directory.
+use crate::worktree::linked;
+use git_object::bstr::ByteSlice;
+use quick_error::quick_error;
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not access '{}'", path.display())
+            source(err)
+        }
+        NotFound(directory: PathBuf) {
+            display("Neither '{}' nor any of its parents up to a ceiling directory are in a git repository", directory.display())
+        }
+        NotAGitDir(path: PathBuf) {
+            display("'{}' is not a git directory", path.display())
+        }
+        Linked(err: linked::Error) {
+            display("Could not find the git directory a '.git' file points to")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// The environment variables overriding where the parts of a repository are, with relative paths not yet resolved.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+pub struct Environment {
+    /// The git directory to use instead of discovering one, from `GIT_DIR`.
+    pub git_dir: Option<PathBuf>,
+    /// The top-level directory of the worktree, from `GIT_WORK_TREE`.
+    pub work_tree: Option<PathBuf>,
+    /// The directory to store objects in instead of `objects` in the common git directory, from `GIT_OBJECT_DIRECTORY`.
+    pub object_dir: Option<PathBuf>,
+    /// Object directories to read objects from in addition to the object directory, from
+    /// `GIT_ALTERNATE_OBJECT_DIRECTORIES`.
+    pub alternate_object_dirs: Vec<PathBuf>,
+    /// The index file to use instead of `index` in the git directory, from `GIT_INDEX_FILE`.
+    pub index_file: Option<PathBuf>,
+    /// Directories whose parents are not searched for a repository, from `GIT_CEILING_DIRECTORIES`.
+    pub ceiling_dirs: Vec<PathBuf>,
+}
+
+impl Environment {
+    /// Read the overrides from the environment of the current process.
+    pub fn from_env() -> Self {
+        Environment::from_vars(|name| std::env::var_os(name))
+    }
+
+    /// Read the overrides from `var`, which returns the value of the environment variable of the given name.
+    ///
+    /// Like git, empty values are ignored, and lists are separated like `PATH`. Relative ceiling directories are
+    /// ignored as well.
+    pub fn from_vars(var: impl Fn(&str) -> Option<OsString>) -> Self {
+        let path = |name| var(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+        let paths = |name| -> Vec<PathBuf> {
+            var(name)
+                .map(|value| {
+                    std::env::split_paths(&value)
+                        .filter(|path| !path.as_os_str().is_empty())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        Environment {
+            git_dir: path("GIT_DIR"),
+            work_tree: path("GIT_WORK_TREE"),
+            object_dir: path("GIT_OBJECT_DIRECTORY"),
+            alternate_object_dirs: paths("GIT_ALTERNATE_OBJECT_DIRECTORIES"),
+            index_file: path("GIT_INDEX_FILE"),
+            ceiling_dirs: paths("GIT_CEILING_DIRECTORIES")
+                .into_iter()
+                .filter(|path| path.is_absolute())
+                .collect(),
+        }
+    }
+}
+
+/// Where the parts of a repository are.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Paths {
+    /// The git directory, which is the one of a linked worktree if it's opened through its `.git` file.
+    pub git_dir: PathBuf,
+    /// The git directory shared by all worktrees.
+    pub common_dir: PathBuf,
+    /// The top-level directory of the worktree, or `None` if the repository is bare.
+    pub work_tree: Option<PathBuf>,
+    /// The directory objects are written to.
+    pub object_dir: PathBuf,
+    /// Additional directories objects are read from.
+    pub alternate_object_dirs: Vec<PathBuf>,
+    /// The index file, which may not exist.
+    pub index_file: PathBuf,
+}
+
+impl Paths {
+    /// All directories objects are read from, starting with the one they are written to.
+    pub fn object_dirs(&self) -> impl Iterator<Item = &Path> {
+        std::iter::once(self.object_dir.as_path()).chain(self.alternate_object_dirs.iter().map(PathBuf::as_path))
+    }
+}
+
+/// Find the repository `directory` is in, which is assumed to be the current directory, by checking it and its
+/// parents for a `.git` directory or file, or for being a bare repository.
+///
+/// If `GIT_DIR` is set, no search is performed and the git directory is [opened][open()] instead. Parents of
+/// ceiling directories aren't searched.
+pub fn discover(directory: impl AsRef<Path>, env: &Environment) -> Result<Paths, Error> {
+    let directory = directory.as_ref();
+    if let Some(git_dir) = &env.git_dir {
+        return open(directory.join(git_dir), directory, env);
+    }
+    let ceiling_dirs: Vec<_> = env
+        .ceiling_dirs
+        .iter()
+        .map(|dir| dir.canonicalize().unwrap_or_else(|_| dir.to_owned()))
+        .collect();
+    let start = directory
+        .canonicalize()
+        .map_err(|err| Error::Io(err, directory.to_owned()))?;
+    let mut cursor = start.as_path();
+    loop {
+        let dot_git = cursor.join(".git");
+        if dot_git.exists() {
+            let git_dir = linked::git_dir(&dot_git)?;
+            if is_git_dir(&git_dir) {
+                return paths(git_dir, Some(cursor.to_owned()), directory, env);
+            }
+        }
+        if is_git_dir(cursor) {
+            return paths(cursor.to_owned(), None, directory, env);
+        }
+        cursor = match cursor.parent() {
+            Some(parent) if !ceiling_dirs.iter().any(|ceiling| ceiling == parent) => parent,
+            _ => return Err(Error::NotFound(start)),
+        };
+    }
+}
+
+/// Open the repository at `git_dir`, which may also be a `.git` file pointing to it, from the current directory `cwd`.
+///
+/// Unless set by `GIT_WORK_TREE` or `core.worktree`, the worktree is `cwd` like git assumes when `GIT_DIR` is set, or
+/// none if `core.bare` is true.
+pub fn open(git_dir: impl AsRef<Path>, cwd: impl AsRef<Path>, env: &Environment) -> Result<Paths, Error> {
+    let git_dir = linked::git_dir(git_dir)?;
+    if !is_git_dir(&git_dir) {
+        return Err(Error::NotAGitDir(git_dir));
+    }
+    let cwd = cwd.as_ref();
+    paths(git_dir, Some(cwd.to_owned()), cwd, env)
+}
+
+/// Return true if `path` looks like a git directory, with a `HEAD` file as well as `objects` and `refs` directories,
+/// the latter two of which may also be in its common directory.
+pub fn is_git_dir(path: impl AsRef<Path>) -> bool {
+    let path = path.as_ref();
+    if !path.join("HEAD").is_file() {
+        return false;
+    }
+    let common_dir = match linked::common_dir(path) {
+        Ok(common_dir) => common_dir,
+        Err(_) => return false,
+    };
+    common_dir.join("objects").is_dir() && common_dir.join("refs").is_dir()
+}
+
+/// Combine the default layout of the repository at `git_dir` with the overrides in `env` and its configuration.
+fn paths(git_dir: PathBuf, work_tree: Option<PathBuf>, cwd: &Path, env: &Environment) -> Result<Paths, Error> {
+    let common_dir = linked::common_dir(&git_dir)?;
+    let config = git_config::File::at(common_dir.join("config")).ok();
+    let work_tree = match &env.work_tree {
+        Some(work_tree) => Some(cwd.join(work_tree)),
+        None => match config
+            .as_ref()
+            .and_then(|config| config.value("core", None, "worktree"))
+        {
+            Some(configured) => configured.to_path().ok().map(|path| git_dir.join(path)),
+            None if matches!(
+                config.as_ref().and_then(|config| config.boolean("core", None, "bare")),
+                Some(Ok(true))
+            ) =>
+            {
+                None
+            }
+            None => work_tree,
+        },
+    };
+    Ok(Paths {
+        object_dir: match &env.object_dir {
+            Some(object_dir) => cwd.join(object_dir),
+            None => common_dir.join("objects"),
+        },
+        alternate_object_dirs: env.alternate_object_dirs.iter().map(|dir| cwd.join(dir)).collect(),
+        index_file: match &env.index_file {
+            Some(index_file) => cwd.join(index_file),
+            None => git_dir.join("index"),
+        },
+        work_tree,
+        git_dir,
+        common_dir,
+    })
+}
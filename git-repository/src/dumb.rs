@@ -0,0 +1,405 @@
+//! Serve repositories from any web server and fetch from them without git running remotely, like the dumb HTTP
+//! protocol does.
+//!
+//! Servers only need the files written by [`update_server_info()`], `info/refs` and `objects/info/packs`, which
+//! list the refs and packs of the repository as directories can't be listed. It should run whenever refs or packs
+//! change, like `git update-server-info` does in a `post-update` hook.
+//!
+//! The client is meant as fallback for servers which don't speak the smart protocol, which is apparent by the missing
+//! service announcement in their response to the handshake. [`refs()`] lists the refs of the remote, and [`fetch()`]
+//! walks the history starting at the given objects until it reaches objects we have, downloading objects one by one
+//! if they are available loose and entire packs otherwise.
+use git_features::{
+    lock,
+    progress::{self, Progress},
+};
+use git_object::{
+    borrowed,
+    bstr::{BString, ByteSlice},
+    owned, TreeMode,
+};
+use git_odb::{compound, loose, pack};
+use git_protocol::handshake::Ref;
+use git_transport::client::http;
+use quick_error::quick_error;
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not access '{}'", path.display())
+            source(err)
+        }
+        Lock(err: lock::Error) {
+            display("Could not lock a file to change it")
+            from()
+            source(err)
+        }
+        Download(err: io::Error, path: String) {
+            display("Could not download '{}' from the remote", path)
+            source(err)
+        }
+        MissingRefs {
+            display("The remote has no 'info/refs' file, it might not be a git repository or needs 'git update-server-info' to be run")
+        }
+        InvalidRefLine(line: BString) {
+            display("The line '{}' of the remote 'info/refs' file could not be parsed", line)
+        }
+        ObjectMissing(id: owned::Id) {
+            display("The object {} is neither available loose nor in any of the packs of the remote", id)
+        }
+        CorruptObject(id: owned::Id) {
+            display("The object {} received from the remote is corrupt", id)
+        }
+        CorruptPack(name: String) {
+            display("The pack '{}' received from the remote is corrupt", name)
+        }
+        OpenDb(err: compound::init::Error) {
+            display("The object database could not be opened")
+            from()
+            source(err)
+        }
+        OpenIndex(err: pack::index::init::Error) {
+            display("The index of a remote pack could not be opened")
+            from()
+            source(err)
+        }
+        Locate(err: compound::locate::Error) {
+            display("A received object could not be read")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// Access to the files of a remote repository.
+pub trait Remote {
+    /// Return the content of the file at `path`, relative to the git directory of the remote, or `None` if there is no such file.
+    fn get(&mut self, path: &str) -> io::Result<Option<Vec<u8>>>;
+}
+
+impl<F> Remote for F
+where
+    F: FnMut(&str) -> io::Result<Option<Vec<u8>>>,
+{
+    fn get(&mut self, path: &str) -> io::Result<Option<Vec<u8>>> {
+        self(path)
+    }
+}
+
+/// A remote served by a web server at `url`, which is reached using the given HTTP backend.
+pub struct Http<H> {
+    /// The url of the git directory of the remote, like `https://example.com/repo.git`.
+    pub url: String,
+    /// The backend performing the requests.
+    pub http: H,
+    /// The configuration applied to all requests.
+    pub options: http::Options,
+}
+
+impl<H: http::Http> Remote for Http<H> {
+    fn get(&mut self, path: &str) -> io::Result<Option<Vec<u8>>> {
+        let url = format!("{}/{}", self.url.trim_end_matches('/'), path);
+        let mut buf = Vec::new();
+        match http::download(
+            &mut self.http,
+            &url,
+            self.options.extra_headers.clone(),
+            &self.options,
+            &mut buf,
+        ) {
+            Ok(_) => Ok(Some(buf)),
+            Err(http::Error::Status(404, _)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Write `info/refs` with all `refs` but `HEAD`, and `objects/info/packs` with all packs in the `objects/pack` directory
+/// of the repository at `git_dir`.
+///
+/// Like `git update-server-info`, the refs are listed along with the objects annotated tags point to.
+pub fn update_server_info(git_dir: impl AsRef<Path>, refs: &[Ref]) -> Result<(), Error> {
+    let git_dir = git_dir.as_ref();
+    let mut refs: Vec<_> = refs.iter().filter(|r| r.unpack().0 != "HEAD").collect();
+    refs.sort_by(|a, b| a.unpack().0.cmp(b.unpack().0));
+    let mut info_refs = Vec::new();
+    for r in refs {
+        match r {
+            Ref::Direct { path, object } | Ref::Symbolic { path, object, .. } => {
+                info_refs.extend_from_slice(format!("{}\t{}\n", object, path).as_bytes())
+            }
+            Ref::Peeled { path, tag, object } => {
+                info_refs.extend_from_slice(format!("{}\t{}\n{}\t{}^{{}}\n", tag, path, object, path).as_bytes())
+            }
+            Ref::Unborn { .. } => {}
+        }
+    }
+    write_atomically(&git_dir.join("info").join("refs"), &info_refs)?;
+
+    let pack_dir = git_dir.join("objects").join("pack");
+    let mut packs = Vec::new();
+    if pack_dir.is_dir() {
+        for entry in fs::read_dir(&pack_dir).map_err(|err| Error::Io(err, pack_dir.clone()))? {
+            let path = entry.map_err(|err| Error::Io(err, pack_dir.clone()))?.path();
+            if path.extension() == Some("pack".as_ref()) && path.with_extension("idx").is_file() {
+                if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                    packs.push(name.to_owned());
+                }
+            }
+        }
+    }
+    packs.sort();
+    let mut info_packs = String::new();
+    for name in packs {
+        info_packs.push_str(&format!("P {}\n", name));
+    }
+    info_packs.push('\n');
+    write_atomically(
+        &git_dir.join("objects").join("info").join("packs"),
+        info_packs.as_bytes(),
+    )
+}
+
+/// Obtain the refs of `remote` from its `info/refs` file, with `HEAD` as first ref if it exists.
+pub fn refs(remote: &mut impl Remote) -> Result<Vec<Ref>, Error> {
+    let info_refs = download(remote, "info/refs")?.ok_or(Error::MissingRefs)?;
+    let mut refs = Vec::new();
+    for line in info_refs.lines() {
+        let invalid = || Error::InvalidRefLine(line.into());
+        let tab = line.find_byte(b'\t').ok_or_else(invalid)?;
+        let (hex, path) = (&line[..tab], &line[tab + 1..]);
+        let object = owned::Id::from_40_bytes_in_hex(hex).map_err(|_| invalid())?;
+        match path.strip_suffix(b"^{}") {
+            Some(peeled) => match refs.pop() {
+                Some(Ref::Direct { path, object: tag }) if path == peeled => {
+                    refs.push(Ref::Peeled { path, tag, object })
+                }
+                _ => return Err(invalid()),
+            },
+            None => refs.push(Ref::Direct {
+                path: path.into(),
+                object,
+            }),
+        }
+    }
+
+    let head = download(remote, "HEAD")?.map(|head| head.lines().next().unwrap_or_default().to_vec());
+    let head = match head.as_ref().map(|h| (h.strip_prefix(b"ref: "), h)) {
+        Some((Some(target), _)) => refs.iter().find_map(|r| match r {
+            Ref::Direct { path, object } | Ref::Peeled { path, object, .. } if path == target => Some(Ref::Symbolic {
+                path: "HEAD".into(),
+                target: target.into(),
+                object: *object,
+            }),
+            _ => None,
+        }),
+        Some((None, hex)) => owned::Id::from_40_bytes_in_hex(hex).ok().map(|object| Ref::Direct {
+            path: "HEAD".into(),
+            object,
+        }),
+        None => None,
+    };
+    refs.splice(0..0, head);
+    Ok(refs)
+}
+
+/// The result of [`fetch()`].
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Outcome {
+    /// The amount of loose objects which were received.
+    pub loose_objects: usize,
+    /// The paths to the index files of the packs which were received.
+    pub packs: Vec<PathBuf>,
+}
+
+/// Receive all objects reachable from `wants` from `remote` which aren't in the `objects` directory at `objects_dir`
+/// yet, and place them there.
+///
+/// Objects we have are assumed to be complete, all objects reachable from them are never received.
+pub fn fetch(
+    remote: &mut impl Remote,
+    wants: impl IntoIterator<Item = owned::Id>,
+    objects_dir: impl AsRef<Path>,
+    mut progress: impl Progress,
+) -> Result<Outcome, Error> {
+    let objects_dir = objects_dir.as_ref();
+    let pack_dir = objects_dir.join("pack");
+    let mut db = compound::Db::at(objects_dir)?;
+    let mut outcome = Outcome::default();
+    let mut remote_packs: Option<RemotePacks> = None;
+    let mut buf = Vec::new();
+
+    progress.init(None, progress::count("objects"));
+    let mut seen = HashSet::new();
+    let mut ids: Vec<_> = wants.into_iter().collect();
+    while let Some(id) = ids.pop() {
+        if !seen.insert(id) {
+            continue;
+        }
+        let in_received_pack = matches!(&remote_packs, Some(packs) if packs.received(id));
+        if !in_received_pack {
+            if db.contains(id.to_borrowed()) {
+                continue;
+            }
+            if receive_loose(remote, &db.loose, id)? {
+                outcome.loose_objects += 1;
+            } else {
+                if remote_packs.is_none() {
+                    progress.info("downloading the indices of all remote packs");
+                    remote_packs = Some(RemotePacks::download(remote, &pack_dir)?);
+                }
+                let packs = remote_packs.as_mut().expect("just set");
+                let name = packs.find(id).ok_or(Error::ObjectMissing(id))?;
+                progress.info(format!("downloading pack '{}'", name));
+                outcome.packs.push(packs.receive(remote, name, &pack_dir)?);
+                db.refresh()?;
+            }
+        }
+        progress.inc();
+
+        let object = db
+            .locate(id.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+            .ok_or(Error::ObjectMissing(id))??;
+        match borrowed::Object::from_bytes(object.kind, object.data).map_err(|_| Error::CorruptObject(id))? {
+            borrowed::Object::Commit(commit) => {
+                ids.push(commit.tree());
+                ids.extend(commit.parents());
+            }
+            borrowed::Object::Tree(tree) => ids.extend(
+                tree.entries
+                    .iter()
+                    .filter(|entry| entry.mode != TreeMode::Commit)
+                    .map(|entry| owned::Id::from_borrowed_sha1(entry.oid.sha1())),
+            ),
+            borrowed::Object::Tag(tag) => ids.push(tag.target()),
+            borrowed::Object::Blob(_) => {}
+        }
+    }
+    Ok(outcome)
+}
+
+/// The packs of a remote, whose indices are downloaded into a temporary directory to learn which objects they contain.
+struct RemotePacks {
+    indices: Vec<(String, pack::index::File)>,
+    received: Vec<usize>,
+    directory: tempfile::TempDir,
+    _registration: git_features::tempfile::Registration,
+}
+
+impl RemotePacks {
+    fn download(remote: &mut impl Remote, pack_dir: &Path) -> Result<Self, Error> {
+        fs::create_dir_all(pack_dir).map_err(|err| Error::Io(err, pack_dir.to_owned()))?;
+        let directory = tempfile::TempDir::new_in(pack_dir).map_err(|err| Error::Io(err, pack_dir.to_owned()))?;
+        let registration = git_features::tempfile::register(directory.path(), git_features::tempfile::Kind::Directory);
+        let info_packs = download(remote, "objects/info/packs")?.unwrap_or_default();
+        let mut indices = Vec::new();
+        for name in info_packs.lines().filter_map(|line| line.strip_prefix(b"P ")) {
+            let name = match name.to_str() {
+                Ok(name) if name.ends_with(".pack") && !name.contains('/') => name,
+                _ => continue,
+            };
+            let index_name = index_name(name);
+            let data = match download(remote, &format!("objects/pack/{}", index_name))? {
+                Some(data) => data,
+                None => continue,
+            };
+            let path = directory.path().join(&index_name);
+            fs::write(&path, data).map_err(|err| Error::Io(err, path.clone()))?;
+            let index = pack::index::File::at(&path)?;
+            if index.verify_checksum(progress::Discard).is_err() {
+                return Err(Error::CorruptPack(index_name));
+            }
+            indices.push((name.to_owned(), index));
+        }
+        Ok(RemotePacks {
+            indices,
+            received: Vec::new(),
+            directory,
+            _registration: registration,
+        })
+    }
+
+    fn received(&self, id: owned::Id) -> bool {
+        self.received
+            .iter()
+            .any(|&pack| self.indices[pack].1.lookup(id.to_borrowed()).is_some())
+    }
+
+    /// The name of the first pack we didn't receive yet containing `id`.
+    fn find(&self, id: owned::Id) -> Option<String> {
+        self.indices
+            .iter()
+            .enumerate()
+            .find(|(pack, (_, index))| !self.received.contains(pack) && index.lookup(id.to_borrowed()).is_some())
+            .map(|(_, (name, _))| name.clone())
+    }
+
+    /// Download the pack called `name` into `pack_dir` along with its index, returning the path to the index.
+    fn receive(&mut self, remote: &mut impl Remote, name: String, pack_dir: &Path) -> Result<PathBuf, Error> {
+        let pack = self
+            .indices
+            .iter()
+            .position(|(candidate, _)| *candidate == name)
+            .expect("pack names are obtained from the indices");
+        let data =
+            download(remote, &format!("objects/pack/{}", name))?.ok_or_else(|| Error::CorruptPack(name.clone()))?;
+        let index = &self.indices[pack].1;
+        if data.len() < git_object::SHA1_SIZE
+            || git_features::hash::sha1(&data[..data.len() - git_object::SHA1_SIZE]) != *index.pack_checksum().sha1()
+        {
+            return Err(Error::CorruptPack(name));
+        }
+
+        let pack_path = pack_dir.join(&name);
+        write_atomically(&pack_path, &data)?;
+        let index_path = pack_dir.join(index_name(&name));
+        let index_data = fs::read(self.directory.path().join(index_name(&name)))
+            .map_err(|err| Error::Io(err, index_path.clone()))?;
+        write_atomically(&index_path, &index_data)?;
+        self.received.push(pack);
+        Ok(index_path)
+    }
+}
+
+fn index_name(pack_name: &str) -> String {
+    format!("{}.idx", pack_name.trim_end_matches(".pack"))
+}
+
+/// Download the loose object `id` into `db`, returning false if the remote doesn't have it as loose object.
+fn receive_loose(remote: &mut impl Remote, db: &loose::Db, id: owned::Id) -> Result<bool, Error> {
+    let hex = id.to_string();
+    let data = match download(remote, &format!("objects/{}/{}", &hex[..2], &hex[2..]))? {
+        Some(data) => data,
+        None => return Ok(false),
+    };
+    let path = db.path.join(&hex[..2]).join(&hex[2..]);
+    write_atomically(&path, &data)?;
+    let verified = match db.locate(id.to_borrowed()) {
+        Some(Ok(mut object)) => object.verify_checksum(id.to_borrowed()).is_ok(),
+        _ => false,
+    };
+    if !verified {
+        fs::remove_file(&path).ok();
+        return Err(Error::CorruptObject(id));
+    }
+    Ok(true)
+}
+
+fn download(remote: &mut impl Remote, path: &str) -> Result<Option<Vec<u8>>, Error> {
+    remote.get(path).map_err(|err| Error::Download(err, path.into()))
+}
+
+/// Write `data` into a lock file next to `path`, and move it into place once it's complete.
+fn write_atomically(path: &Path, data: &[u8]) -> Result<(), Error> {
+    let mut lock = lock::File::acquire(path, Default::default())?;
+    lock.write_all(data).map_err(|err| Error::Io(err, path.to_owned()))?;
+    lock.commit()?;
+    Ok(())
+}
@@ -0,0 +1,420 @@
+//! Write commits along with their blobs and tags as a stream for `git fast-import`, like `git fast-export` does.
+//!
+//! The stream contains all commits reachable from the given refs but not from any of the excluded commits, oldest first.
+//! Each commit is written with the file changes relative to its first parent, or with all of its files if the first
+//! parent isn't part of the stream, and the blobs it introduces are written right before it. Objects are referred to
+//! by marks, numbered in the order they are written in.
+//!
+//! The output is the same as the one of `git fast-export <refs> ^<excluded>…` with `--reencode=no` and
+//! `--signed-tags=verbatim`, so commits keep their `encoding` header and tags keep their signatures. Commit signatures
+//! are dropped, like git does.
+use git_object::{
+    borrowed,
+    bstr::{BStr, BString, ByteSlice},
+    owned, TreeMode,
+};
+use quick_error::quick_error;
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet},
+    io,
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Find(id: owned::Id) {
+            display("Object {} could not be found", id)
+        }
+        NotACommit(id: owned::Id) {
+            display("Object {} was expected to be a commit", id)
+        }
+        NotATree(id: owned::Id) {
+            display("Object {} was expected to be a tree", id)
+        }
+        UnsupportedRef(name: BString) {
+            display("The ref '{}' doesn't point to a commit or a tag of a commit", name)
+        }
+        TagOfUnexportedObject(name: BString) {
+            display("The tag '{}' points to a commit which isn't exported", name)
+        }
+        Io(err: io::Error) {
+            display("The stream could not be written")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// A change of a file between the tree of a commit and the one of its first parent.
+struct Change {
+    path: BString,
+    new: Option<(TreeMode, owned::Id)>,
+}
+
+/// Write the commits reachable from `refs`, pairs of full ref names and the commits or annotated tags they point to,
+/// but not from `excluded`, as fast-import stream to `out`, and return the marks of all written objects.
+///
+/// Commits are written for the ref they were first found from, in the order of `refs`. Refs pointing to commits which
+/// were written for another ref are set with `reset` commands at the end, followed by the annotated tags, which must
+/// point to written commits. `find` is used to lookup objects, and places the object data into the provided buffer and
+/// returns the decoded object.
+pub fn write<Find>(
+    refs: impl IntoIterator<Item = (BString, owned::Id)>,
+    excluded: impl IntoIterator<Item = owned::Id>,
+    find: Find,
+    out: impl io::Write,
+) -> Result<BTreeMap<u32, owned::Id>, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let mut export = Export {
+        find,
+        out,
+        buf: Vec::new(),
+        marks: HashMap::new(),
+    };
+    let mut tags = Vec::new();
+    let mut branches = Vec::new();
+    let mut sources = HashMap::new();
+    let mut tips = Vec::new();
+    for (name, id) in refs {
+        let commit = match (export.find)(id.to_borrowed(), &mut export.buf).ok_or(Error::Find(id))? {
+            borrowed::Object::Commit(_) => {
+                branches.push((name.clone(), id));
+                id
+            }
+            borrowed::Object::Tag(tag) if tag.target_kind == git_object::Kind::Commit => {
+                let target = owned::Id::from_40_bytes_in_hex(tag.target).map_err(|_| Error::Find(id))?;
+                tags.push((name.clone(), id));
+                target
+            }
+            _ => return Err(Error::UnsupportedRef(name)),
+        };
+        sources.entry(commit).or_insert(name);
+        tips.push(commit);
+    }
+    branches.sort_by(|a, b| a.0.cmp(&b.0));
+    branches.dedup_by(|a, b| a.0 == b.0);
+
+    let mut excluded_ancestors = HashSet::new();
+    let mut stack: Vec<_> = excluded.into_iter().collect();
+    while let Some(id) = stack.pop() {
+        if excluded_ancestors.insert(id) {
+            stack.extend(export.commit(id)?.1);
+        }
+    }
+
+    // Like git, commits are found newest first, in the order they were found in if their time is equal.
+    let mut found = Vec::new();
+    let mut parents_of = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    let mut seen = HashSet::new();
+    for tip in tips {
+        if !excluded_ancestors.contains(&tip) && seen.insert(tip) {
+            queue.push((export.commit(tip)?.0, Reverse(seen.len()), tip));
+        }
+    }
+    while let Some((_, _, id)) = queue.pop() {
+        let parents = export.commit(id)?.1;
+        let source = sources[&id].clone();
+        for parent in &parents {
+            sources.entry(*parent).or_insert_with(|| source.clone());
+            if !excluded_ancestors.contains(parent) && seen.insert(*parent) {
+                queue.push((export.commit(*parent)?.0, Reverse(seen.len()), *parent));
+            }
+        }
+        found.push(id);
+        parents_of.insert(id, parents);
+    }
+
+    for id in topological_order(&found, &parents_of).into_iter().rev() {
+        let name = sources[&id].clone();
+        export.write_commit(id, &parents_of[&id], name.as_ref())?;
+        branches.retain(|(branch, _)| *branch != name);
+    }
+    for (name, id) in branches.iter().rev() {
+        match export.marks.get(id) {
+            Some(mark) => writeln!(export.out, "reset {}\nfrom :{}\n", name, mark)?,
+            None => writeln!(export.out, "reset {}\nfrom {}\n", name, owned::Id::null_sha1())?,
+        }
+    }
+    for (name, id) in tags.iter().rev() {
+        export.write_tag(*id, name.as_ref())?;
+    }
+    Ok(export.marks.into_iter().map(|(id, mark)| (mark, id)).collect())
+}
+
+/// Sort `commits`, which are ordered newest first, newest first once more, but so that all children of a commit come
+/// before it and commits of a line of history aren't intermixed with others, like git's `--topo-order` does.
+fn topological_order(commits: &[owned::Id], parents_of: &HashMap<owned::Id, Vec<owned::Id>>) -> Vec<owned::Id> {
+    let mut indegree: HashMap<_, usize> = commits.iter().map(|id| (*id, 1)).collect();
+    for parent in commits.iter().flat_map(|id| &parents_of[id]) {
+        if let Some(indegree) = indegree.get_mut(parent) {
+            *indegree += 1;
+        }
+    }
+    let mut stack: Vec<_> = commits.iter().rev().filter(|id| indegree[*id] == 1).copied().collect();
+    let mut sorted = Vec::with_capacity(commits.len());
+    while let Some(id) = stack.pop() {
+        for parent in &parents_of[&id] {
+            if let Some(indegree) = indegree.get_mut(parent) {
+                *indegree -= 1;
+                if *indegree == 1 {
+                    stack.push(*parent);
+                }
+            }
+        }
+        sorted.push(id);
+    }
+    sorted
+}
+
+struct Export<Find, W> {
+    find: Find,
+    out: W,
+    buf: Vec<u8>,
+    marks: HashMap<owned::Id, u32>,
+}
+
+impl<Find, W> Export<Find, W>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+    W: io::Write,
+{
+    fn mark(&mut self, id: owned::Id) -> u32 {
+        let mark = self.marks.len() as u32 + 1;
+        self.marks.insert(id, mark);
+        mark
+    }
+
+    /// Return the commit time, the parents and the tree of the commit `id`.
+    fn commit(&mut self, id: owned::Id) -> Result<(u32, Vec<owned::Id>, owned::Id), Error> {
+        match (self.find)(id.to_borrowed(), &mut self.buf) {
+            Some(borrowed::Object::Commit(commit)) => {
+                Ok((commit.committer.time.time, commit.parents().collect(), commit.tree()))
+            }
+            Some(_) => Err(Error::NotACommit(id)),
+            None => Err(Error::Find(id)),
+        }
+    }
+
+    fn write_commit(&mut self, id: owned::Id, parents: &[owned::Id], name: &BStr) -> Result<(), Error> {
+        let tree = self.commit(id)?.2;
+        let base = match parents.first() {
+            Some(parent) if self.marks.contains_key(parent) => Some(self.commit(*parent)?.2),
+            _ => None,
+        };
+        let mut changes = Vec::new();
+        self.diff(base, Some(tree), b"".as_bstr(), &mut changes)?;
+        for change in &changes {
+            match change.new {
+                Some((mode, id)) if mode != TreeMode::Commit && !self.marks.contains_key(&id) => self.write_blob(id)?,
+                _ => {}
+            }
+        }
+
+        self.commit(id)?;
+        let raw = std::mem::take(&mut self.buf);
+        let header_end = raw.find(b"\n\n").unwrap_or(raw.len());
+        let header_line = |field: &[u8]| {
+            let mut prefix = b"\n".to_vec();
+            prefix.extend_from_slice(field);
+            prefix.push(b' ');
+            raw[..header_end].find(&prefix).map(|pos| {
+                let line = &raw[pos + 1..];
+                &line[..line.find_byte(b'\n').unwrap_or(line.len())]
+            })
+        };
+        if parents.is_empty() {
+            writeln!(self.out, "reset {}", name)?;
+        }
+        let mark = self.mark(id);
+        writeln!(self.out, "commit {}\nmark :{}", name, mark)?;
+        for field in &[&b"author"[..], b"committer", b"encoding"] {
+            if let Some(line) = header_line(field) {
+                self.out.write_all(line)?;
+                self.out.write_all(b"\n")?;
+            }
+        }
+        let message = raw.get(header_end + 2..).unwrap_or_default();
+        writeln!(self.out, "data {}", message.len())?;
+        self.out.write_all(message)?;
+        self.buf = raw;
+
+        let parent_marks: Vec<_> = parents
+            .iter()
+            .filter_map(|parent| self.marks.get(parent).copied())
+            .collect();
+        for (index, mark) in parent_marks.into_iter().enumerate() {
+            writeln!(self.out, "{} :{}", if index == 0 { "from" } else { "merge" }, mark)?;
+        }
+        // Git writes the changes of a directory before the directory itself.
+        changes.sort_by(|a, b| {
+            let len = a.path.len().min(b.path.len());
+            a.path[..len]
+                .cmp(&b.path[..len])
+                .then_with(|| b.path.len().cmp(&a.path.len()))
+        });
+        for change in &changes {
+            match change.new {
+                None => self.out.write_all(b"D ")?,
+                Some((TreeMode::Commit, id)) => write!(self.out, "M {:06o} {} ", TreeMode::Commit as u16, id)?,
+                Some((mode, id)) => write!(self.out, "M {:06o} :{} ", mode as u16, self.marks[&id])?,
+            }
+            write_path(&mut self.out, change.path.as_ref())?;
+            self.out.write_all(b"\n")?;
+        }
+        self.out.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn write_blob(&mut self, id: owned::Id) -> Result<(), Error> {
+        let data = match (self.find)(id.to_borrowed(), &mut self.buf).ok_or(Error::Find(id))? {
+            borrowed::Object::Blob(blob) => blob.data.to_vec(),
+            _ => return Err(Error::Find(id)),
+        };
+        let mark = self.mark(id);
+        write!(self.out, "blob\nmark :{}\ndata {}\n", mark, data.len())?;
+        self.out.write_all(&data)?;
+        self.out.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn write_tag(&mut self, id: owned::Id, name: &BStr) -> Result<(), Error> {
+        let target = match (self.find)(id.to_borrowed(), &mut self.buf).ok_or(Error::Find(id))? {
+            borrowed::Object::Tag(tag) => owned::Id::from_40_bytes_in_hex(tag.target).map_err(|_| Error::Find(id))?,
+            _ => return Err(Error::Find(id)),
+        };
+        let mark = *self
+            .marks
+            .get(&target)
+            .ok_or_else(|| Error::TagOfUnexportedObject(name.to_owned()))?;
+        let raw = &self.buf;
+        let message_start = raw.find(b"\n\n").map(|pos| pos + 2);
+        let tagger = raw[..message_start.unwrap_or(raw.len())].find(b"\ntagger ").map(|pos| {
+            let line = &raw[pos + 1..];
+            &line[..line.find_byte(b'\n').unwrap_or(line.len())]
+        });
+        let message = message_start.map(|start| &raw[start..]).unwrap_or_default();
+        let name = name.strip_prefix(b"refs/tags/").unwrap_or(name);
+        self.out.write_all(b"tag ")?;
+        self.out.write_all(name)?;
+        writeln!(self.out, "\nfrom :{}", mark)?;
+        if let Some(tagger) = tagger {
+            self.out.write_all(tagger)?;
+            self.out.write_all(b"\n")?;
+        }
+        writeln!(self.out, "data {}", message.len())?;
+        self.out.write_all(message)?;
+        self.out.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Collect the changes of files between the trees `old` and `new` at `prefix`, which may not exist, in the order of
+    /// their entries.
+    fn diff(
+        &mut self,
+        old: Option<owned::Id>,
+        new: Option<owned::Id>,
+        prefix: &BStr,
+        changes: &mut Vec<Change>,
+    ) -> Result<(), Error> {
+        let old = self.entries(old)?;
+        let new = self.entries(new)?;
+        let (mut old, mut new) = (old.into_iter().peekable(), new.into_iter().peekable());
+        loop {
+            let (old_entry, new_entry) = match (old.peek(), new.peek()) {
+                (None, None) => break,
+                (Some(_), None) => (old.next(), None),
+                (None, Some(_)) => (None, new.next()),
+                (Some(a), Some(b)) => match sort_key(a).cmp(&sort_key(b)) {
+                    Ordering::Less => (old.next(), None),
+                    Ordering::Greater => (None, new.next()),
+                    Ordering::Equal => (old.next(), new.next()),
+                },
+            };
+            let entry = |entry: &Option<owned::tree::Entry>| entry.as_ref().map(|entry| (entry.mode, entry.oid));
+            if entry(&old_entry) == entry(&new_entry) {
+                continue;
+            }
+            let name = old_entry.as_ref().or(new_entry.as_ref()).expect("either exists");
+            let mut path = BString::from(prefix.as_bytes());
+            if !path.is_empty() {
+                path.push(b'/');
+            }
+            path.extend_from_slice(&name.filename);
+            if name.mode == TreeMode::Tree {
+                let tree = |entry: Option<owned::tree::Entry>| entry.map(|entry| entry.oid);
+                self.diff(tree(old_entry), tree(new_entry), path.as_ref(), changes)?;
+            } else {
+                changes.push(Change {
+                    path,
+                    new: entry(&new_entry),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn entries(&mut self, tree: Option<owned::Id>) -> Result<Vec<owned::tree::Entry>, Error> {
+        let tree = match tree {
+            Some(tree) => tree,
+            None => return Ok(Vec::new()),
+        };
+        match (self.find)(tree.to_borrowed(), &mut self.buf).ok_or(Error::Find(tree))? {
+            borrowed::Object::Tree(tree) => Ok(tree
+                .entries
+                .into_iter()
+                .map(|entry| owned::tree::Entry {
+                    mode: entry.mode,
+                    filename: entry.filename.to_owned(),
+                    oid: owned::Id::from_borrowed_sha1(entry.oid.sha1()),
+                })
+                .collect()),
+            _ => Err(Error::NotATree(tree)),
+        }
+    }
+}
+
+/// Git sorts trees as if their names ended with a slash.
+fn sort_key(entry: &owned::tree::Entry) -> Vec<u8> {
+    let mut key = entry.filename.to_vec();
+    if entry.mode == TreeMode::Tree {
+        key.push(b'/');
+    }
+    key
+}
+
+/// Write `path`, quoted like git does if it contains special characters or spaces.
+fn write_path(out: &mut impl io::Write, path: &BStr) -> io::Result<()> {
+    let needs_quotes = path
+        .iter()
+        .any(|b| *b < 0x20 || *b >= 0x7f || *b == b'"' || *b == b'\\');
+    if !needs_quotes {
+        return if path.contains(&b' ') {
+            out.write_all(b"\"")?;
+            out.write_all(path)?;
+            out.write_all(b"\"")
+        } else {
+            out.write_all(path)
+        };
+    }
+    out.write_all(b"\"")?;
+    for b in path.iter() {
+        match b {
+            b'\x07' => out.write_all(b"\\a")?,
+            b'\x08' => out.write_all(b"\\b")?,
+            b'\t' => out.write_all(b"\\t")?,
+            b'\n' => out.write_all(b"\\n")?,
+            b'\x0b' => out.write_all(b"\\v")?,
+            b'\x0c' => out.write_all(b"\\f")?,
+            b'\r' => out.write_all(b"\\r")?,
+            b'"' => out.write_all(b"\\\"")?,
+            b'\\' => out.write_all(b"\\\\")?,
+            b if *b < 0x20 || *b >= 0x7f => write!(out, "\\{:03o}", b)?,
+            b => out.write_all(&[*b])?,
+        }
+    }
+    out.write_all(b"\"")
+}
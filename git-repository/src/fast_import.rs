@@ -0,0 +1,701 @@
+//! Read a stream of commands as written by `git fast-export` and tools converting from other version control systems,
+//! and write the objects it describes into a repository, like `git fast-import` does.
+//!
+//! The `blob`, `commit`, `tag` and `reset` commands are supported along with all of their file changes except for notes,
+//! and `feature`, `option`, `progress`, `checkpoint` and `done` are accepted. Commands which answer back to the stream
+//! like `cat-blob`, `ls` and `get-mark` aren't supported, and dates are expected in the `raw` format.
+//!
+//! Unlike git, objects are written as loose objects instead of a pack, and all refs are updated once the stream ended.
+//! Branches are only moved forward unless forced, while tags are always overwritten, like git does. A `reset` without
+//! `from` makes the next commit of its branch a root commit, but the ref isn't deleted if no commit follows.
+use crate::{
+    remote::update::{self, Mode},
+    worktree::linked,
+};
+use git_object::{
+    borrowed,
+    bstr::{BStr, BString, ByteSlice},
+    owned, HashKind, Kind, TreeMode,
+};
+use git_odb::{loose, traverse, Write};
+use quick_error::quick_error;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    path::Path,
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("The stream could not be read")
+            from()
+            source(err)
+        }
+        Parse(line_number: usize, message: &'static str) {
+            display("Line {} of the stream is invalid: {}", line_number, message)
+        }
+        Unsupported(line_number: usize, command: BString) {
+            display("Line {} of the stream uses '{}', which isn't supported", line_number, command)
+        }
+        UnknownMark(mark: u32) {
+            display("The mark :{} wasn't set", mark)
+        }
+        UnknownRef(name: BString) {
+            display("The commit-ish '{}' doesn't name a mark, an object or a ref", name)
+        }
+        Find(id: owned::Id) {
+            display("Object {} could not be found", id)
+        }
+        NotACommit(id: owned::Id) {
+            display("Object {} was expected to be a commit", id)
+        }
+        NotATree(id: owned::Id) {
+            display("Object {} was expected to be a tree", id)
+        }
+        WriteObject(err: loose::db::write::Error) {
+            display("An object could not be written")
+            from()
+            source(err)
+        }
+        Ref(err: update::Error) {
+            display("A ref could not be read or written")
+            from()
+            source(err)
+        }
+        Worktree(err: linked::Error) {
+            display("The common git directory of the worktree could not be determined")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// Configure how to import.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Options {
+    /// The identity to record in the reflogs of the updated refs.
+    pub committer: owned::Signature,
+    /// If true, branches are updated even if the update isn't a fast-forward, like `--force` does.
+    pub force: bool,
+}
+
+/// How a ref was set by the import.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Update {
+    /// The full name of the ref.
+    pub name: BString,
+    /// The object the ref pointed to before, or `None` if it didn't exist.
+    pub previous: Option<owned::Id>,
+    /// The commit or tag the stream set the ref to.
+    pub new: owned::Id,
+    /// How the ref was changed.
+    pub mode: Mode,
+}
+
+/// The result of [`import()`].
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Outcome {
+    /// The objects of all marks set by the stream.
+    pub marks: BTreeMap<u32, owned::Id>,
+    /// The refs set by the stream, branches ordered by name first, followed by tags ordered by name.
+    pub updates: Vec<Update>,
+}
+
+/// The files of a tree by their path.
+type Files = BTreeMap<BString, (TreeMode, owned::Id)>;
+
+#[derive(Default)]
+struct Branch {
+    tip: Option<owned::Id>,
+    files: Files,
+}
+
+/// Import the stream read from `input` into the repository at `git_dir`, and update its refs once the stream ended.
+///
+/// `find` is used to lookup the objects the stream refers to as well as the ones it wrote, and places the object data
+/// into the provided buffer and returns the decoded object.
+pub fn import<Find>(
+    git_dir: impl AsRef<Path>,
+    input: impl io::BufRead,
+    find: Find,
+    options: Options,
+) -> Result<Outcome, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let git_dir = git_dir.as_ref();
+    let mut import = Import {
+        stream: Stream {
+            input,
+            line_number: 0,
+            peeked: None,
+        },
+        git_dir,
+        find,
+        buf: Vec::new(),
+        db: loose::Db::at(linked::common_dir(git_dir)?.join("objects")),
+        marks: HashMap::new(),
+        branches: BTreeMap::new(),
+        tags: BTreeMap::new(),
+        force: options.force,
+    };
+    import.run()?;
+
+    let reflog_options = update::Options {
+        committer: options.committer,
+        message: "fast-import".into(),
+        dry_run: false,
+        hooks: None,
+    };
+    let mut updates = Vec::new();
+    let branches = import
+        .branches
+        .iter()
+        .filter_map(|(name, branch)| branch.tip.map(|tip| (name.clone(), tip)));
+    let tags = import
+        .tags
+        .iter()
+        .map(|(name, id)| (format!("refs/tags/{}", name).into(), *id));
+    for (name, new) in branches.collect::<Vec<_>>().into_iter().chain(tags) {
+        let (_, previous) = update::resolve(git_dir, name.as_ref())?;
+        let mode = match previous {
+            None => Mode::New,
+            Some(previous) if previous == new => Mode::NoChange,
+            Some(_) if import.force || name.starts_with(b"refs/tags/") => Mode::Forced,
+            Some(previous) => {
+                if update::is_ancestor(&mut import.find, &mut import.buf, previous, new).map_err(Error::Find)? {
+                    Mode::FastForward
+                } else {
+                    Mode::Rejected
+                }
+            }
+        };
+        if mode.is_update() {
+            update::write_ref(
+                git_dir,
+                name.as_ref(),
+                previous,
+                new,
+                b"fast-import".as_bstr(),
+                &reflog_options,
+            )?;
+        }
+        updates.push(Update {
+            name,
+            previous,
+            new,
+            mode,
+        });
+    }
+    Ok(Outcome {
+        marks: import.marks.into_iter().collect(),
+        updates,
+    })
+}
+
+struct Stream<R> {
+    input: R,
+    line_number: usize,
+    peeked: Option<BString>,
+}
+
+impl<R: io::BufRead> Stream<R> {
+    /// Return the next line without its newline, skipping empty lines and comments, or `None` at the end of the stream.
+    fn line(&mut self) -> Result<Option<BString>, Error> {
+        if let Some(line) = self.peeked.take() {
+            return Ok(Some(line));
+        }
+        loop {
+            match self.raw_line()? {
+                Some(line) if line.is_empty() || line.starts_with(b"#") => continue,
+                line => return Ok(line),
+            }
+        }
+    }
+
+    fn raw_line(&mut self) -> Result<Option<BString>, Error> {
+        let mut line = Vec::new();
+        if self.input.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+        self.line_number += 1;
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+        Ok(Some(line.into()))
+    }
+
+    /// Return the remainder of the next line if it starts with `command` followed by a space, or leave it alone.
+    fn optional(&mut self, command: &str) -> Result<Option<BString>, Error> {
+        let line = match self.line()? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+        match line
+            .strip_prefix(command.as_bytes())
+            .and_then(|rest| rest.strip_prefix(b" "))
+        {
+            Some(rest) => Ok(Some(rest.as_bstr().to_owned())),
+            None => {
+                self.peeked = Some(line);
+                Ok(None)
+            }
+        }
+    }
+
+    fn error(&self, message: &'static str) -> Error {
+        Error::Parse(self.line_number, message)
+    }
+
+    /// Read a `data` command in either the exact byte count or the delimited format and return its content.
+    fn data(&mut self) -> Result<Vec<u8>, Error> {
+        let header = self.optional("data")?.ok_or_else(|| self.error("expected 'data'"))?;
+        if let Some(delimiter) = header.strip_prefix(b"<<") {
+            let mut data = Vec::new();
+            loop {
+                let line = self
+                    .raw_line()?
+                    .ok_or_else(|| self.error("unterminated delimited data"))?;
+                if line == delimiter {
+                    return Ok(data);
+                }
+                data.extend_from_slice(&line);
+                data.push(b'\n');
+            }
+        }
+        let len: usize = header
+            .to_str()
+            .ok()
+            .and_then(|len| len.parse().ok())
+            .ok_or_else(|| self.error("invalid data length"))?;
+        let mut data = vec![0; len];
+        self.input.read_exact(&mut data)?;
+        self.line_number += data.iter().filter(|b| **b == b'\n').count();
+        Ok(data)
+    }
+}
+
+struct Import<'a, R, Find> {
+    stream: Stream<R>,
+    git_dir: &'a Path,
+    find: Find,
+    buf: Vec<u8>,
+    db: loose::Db,
+    marks: HashMap<u32, owned::Id>,
+    branches: BTreeMap<BString, Branch>,
+    tags: BTreeMap<BString, owned::Id>,
+    force: bool,
+}
+
+impl<'a, R, Find> Import<'a, R, Find>
+where
+    R: io::BufRead,
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    fn run(&mut self) -> Result<(), Error> {
+        while let Some(line) = self.stream.line()? {
+            let (command, argument) = match line.find_byte(b' ') {
+                Some(pos) => (&line[..pos], line[pos + 1..].as_bstr()),
+                None => (&line[..], b"".as_bstr()),
+            };
+            match command {
+                b"blob" => self.blob()?,
+                b"commit" => self.commit(argument)?,
+                b"tag" => self.tag(argument)?,
+                b"reset" => self.reset(argument)?,
+                b"feature" => match argument.as_bytes() {
+                    b"done" | b"date-format=raw" => {}
+                    b"force" => self.force = true,
+                    _ => return Err(Error::Unsupported(self.stream.line_number, line)),
+                },
+                b"progress" | b"option" | b"checkpoint" => {}
+                b"done" => break,
+                _ => return Err(Error::Unsupported(self.stream.line_number, line)),
+            }
+        }
+        Ok(())
+    }
+
+    fn mark(&mut self) -> Result<Option<u32>, Error> {
+        let mark = match self.stream.optional("mark")? {
+            Some(mark) => mark,
+            None => return Ok(None),
+        };
+        let mark = parse_mark(mark.as_ref()).ok_or_else(|| self.stream.error("invalid mark"))?;
+        self.stream.optional("original-oid")?;
+        Ok(Some(mark))
+    }
+
+    fn set_mark(&mut self, mark: Option<u32>, id: owned::Id) {
+        if let Some(mark) = mark {
+            self.marks.insert(mark, id);
+        }
+    }
+
+    fn blob(&mut self) -> Result<(), Error> {
+        let mark = self.mark()?;
+        let data = self.stream.data()?;
+        let id = self.db.write_buf(Kind::Blob, &data, HashKind::Sha1)?;
+        self.set_mark(mark, id);
+        Ok(())
+    }
+
+    fn commit(&mut self, name: &BStr) -> Result<(), Error> {
+        let mark = self.mark()?;
+        let author = self.stream.optional("author")?;
+        let committer = self
+            .stream
+            .optional("committer")?
+            .ok_or_else(|| self.stream.error("expected 'committer'"))?;
+        let encoding = self.stream.optional("encoding")?;
+        let message = self.stream.data()?;
+
+        let mut parents = Vec::new();
+        let mut branch = self.branches.remove(name).unwrap_or_default();
+        if let Some(from) = self.stream.optional("from")? {
+            let tip = self.commit_ish(from.as_ref())?;
+            branch = Branch {
+                files: match tip {
+                    Some(tip) => self.files_of_commit(tip)?,
+                    None => Files::new(),
+                },
+                tip,
+            };
+        }
+        parents.extend(branch.tip);
+        while let Some(merge) = self.stream.optional("merge")? {
+            parents.extend(self.commit_ish(merge.as_ref())?);
+        }
+        while let Some(line) = self.stream.line()? {
+            if !self.file_change(&mut branch.files, line.as_ref())? {
+                self.stream.peeked = Some(line);
+                break;
+            }
+        }
+
+        let tree = self.write_tree(&branch.files)?;
+        let mut commit = format!("tree {}\n", tree).into_bytes();
+        for parent in &parents {
+            commit.extend_from_slice(format!("parent {}\n", parent).as_bytes());
+        }
+        for (field, value) in &[
+            ("author", author.as_ref().unwrap_or(&committer)),
+            ("committer", &committer),
+        ] {
+            commit.extend_from_slice(field.as_bytes());
+            commit.push(b' ');
+            commit.extend_from_slice(value);
+            commit.push(b'\n');
+        }
+        if let Some(encoding) = encoding {
+            commit.extend_from_slice(b"encoding ");
+            commit.extend_from_slice(&encoding);
+            commit.push(b'\n');
+        }
+        commit.push(b'\n');
+        commit.extend_from_slice(&message);
+        let id = self.db.write_buf(Kind::Commit, &commit, HashKind::Sha1)?;
+        self.set_mark(mark, id);
+        branch.tip = Some(id);
+        self.branches.insert(name.to_owned(), branch);
+        Ok(())
+    }
+
+    /// Apply the file change in `line` to `files`, or return false if `line` isn't a file change.
+    fn file_change(&mut self, files: &mut Files, line: &BStr) -> Result<bool, Error> {
+        let error = |message| Error::Parse(self.stream.line_number, message);
+        if line == "deleteall" {
+            files.clear();
+            return Ok(true);
+        }
+        let (command, rest) = match (line.get(..2), line.get(2..)) {
+            (Some(command), Some(rest)) if command.ends_with(b" ") => (command[0], rest),
+            _ => return Ok(false),
+        };
+        match command {
+            b'M' => {
+                let mut tokens = rest.splitn(3, |b| *b == b' ');
+                let (mode, data_ref, path) = match (tokens.next(), tokens.next(), tokens.next()) {
+                    (Some(mode), Some(data_ref), Some(path)) => (mode, data_ref.to_owned(), path),
+                    _ => return Err(error("expected 'M <mode> <dataref> <path>'")),
+                };
+                let mode = match mode {
+                    b"100644" | b"644" => TreeMode::Blob,
+                    b"100755" | b"755" => TreeMode::BlobExecutable,
+                    b"120000" => TreeMode::Link,
+                    b"160000" => TreeMode::Commit,
+                    b"040000" => TreeMode::Tree,
+                    _ => return Err(error("invalid file mode")),
+                };
+                let (path, _) = parse_path(path, false).ok_or_else(|| error("invalid path"))?;
+                let id = if data_ref == b"inline" {
+                    let data = self.stream.data()?;
+                    self.db.write_buf(Kind::Blob, &data, HashKind::Sha1)?
+                } else {
+                    self.object(data_ref.as_bstr())?
+                };
+                remove(files, path.as_ref());
+                for ancestor in path.find_iter(b"/").map(|pos| &path[..pos]) {
+                    files.remove(ancestor.as_bstr());
+                }
+                if mode == TreeMode::Tree {
+                    let mut prefix = path;
+                    prefix.push(b'/');
+                    self.flatten(id, prefix, files)?;
+                } else {
+                    files.insert(path, (mode, id));
+                }
+            }
+            b'D' => {
+                let (path, _) = parse_path(rest, false).ok_or_else(|| error("invalid path"))?;
+                remove(files, path.as_ref());
+            }
+            b'C' | b'R' => {
+                let (source, rest) = parse_path(rest, true).ok_or_else(|| error("invalid source path"))?;
+                let (destination, _) = parse_path(rest, false).ok_or_else(|| error("invalid destination path"))?;
+                let copied: Vec<_> = files
+                    .iter()
+                    .filter_map(|(path, entry)| {
+                        let rest = path.strip_prefix(source.as_bytes())?;
+                        (rest.is_empty() || rest.starts_with(b"/")).then(|| {
+                            let mut path = destination.clone();
+                            path.extend_from_slice(rest);
+                            (path, *entry)
+                        })
+                    })
+                    .collect();
+                if copied.is_empty() {
+                    return Err(error("the source path doesn't exist"));
+                }
+                if command == b'R' {
+                    remove(files, source.as_ref());
+                }
+                remove(files, destination.as_ref());
+                files.extend(copied);
+            }
+            b'N' => return Err(Error::Unsupported(self.stream.line_number, "N".into())),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn tag(&mut self, name: &BStr) -> Result<(), Error> {
+        let mark = self.mark()?;
+        let from = self
+            .stream
+            .optional("from")?
+            .ok_or_else(|| self.stream.error("expected 'from'"))?;
+        let target = self
+            .commit_ish(from.as_ref())?
+            .ok_or_else(|| self.stream.error("tags can't point to the null id"))?;
+        self.stream.optional("original-oid")?;
+        let tagger = self.stream.optional("tagger")?;
+        let message = self.stream.data()?;
+        let kind = match (self.find)(target.to_borrowed(), &mut self.buf).ok_or(Error::Find(target))? {
+            borrowed::Object::Commit(_) => "commit",
+            borrowed::Object::Tag(_) => "tag",
+            borrowed::Object::Tree(_) => "tree",
+            borrowed::Object::Blob(_) => "blob",
+        };
+        let mut tag = format!("object {}\ntype {}\ntag ", target, kind).into_bytes();
+        tag.extend_from_slice(name);
+        tag.push(b'\n');
+        if let Some(tagger) = tagger {
+            tag.extend_from_slice(b"tagger ");
+            tag.extend_from_slice(&tagger);
+            tag.push(b'\n');
+        }
+        tag.push(b'\n');
+        tag.extend_from_slice(&message);
+        let id = self.db.write_buf(Kind::Tag, &tag, HashKind::Sha1)?;
+        self.set_mark(mark, id);
+        self.tags.insert(name.to_owned(), id);
+        Ok(())
+    }
+
+    fn reset(&mut self, name: &BStr) -> Result<(), Error> {
+        let branch = match self.stream.optional("from")? {
+            Some(from) => {
+                let tip = self.commit_ish(from.as_ref())?;
+                Branch {
+                    files: match tip {
+                        Some(tip) => self.files_of_commit(tip)?,
+                        None => Files::new(),
+                    },
+                    tip,
+                }
+            }
+            None => Branch::default(),
+        };
+        self.branches.insert(name.to_owned(), branch);
+        Ok(())
+    }
+
+    /// Resolve a mark or an id.
+    fn object(&mut self, spec: &BStr) -> Result<owned::Id, Error> {
+        match spec.strip_prefix(b":") {
+            Some(mark) => {
+                let mark = parse_mark(mark.as_bstr()).ok_or_else(|| self.stream.error("invalid mark"))?;
+                self.marks.get(&mark).copied().ok_or(Error::UnknownMark(mark))
+            }
+            None => owned::Id::from_40_bytes_in_hex(spec).map_err(|_| self.stream.error("invalid object id")),
+        }
+    }
+
+    /// Resolve a mark, an id, a branch of the stream or a ref of the repository, or return `None` for the null id.
+    fn commit_ish(&mut self, spec: &BStr) -> Result<Option<owned::Id>, Error> {
+        if spec.starts_with(b":") || (spec.len() == 40 && spec.iter().all(u8::is_ascii_hexdigit)) {
+            let id = self.object(spec)?;
+            return Ok(Some(id).filter(|id| *id != owned::Id::null_sha1()));
+        }
+        if let Some(branch) = self.branches.get(spec) {
+            return Ok(branch.tip);
+        }
+        match update::resolve(self.git_dir, spec)? {
+            (_, Some(id)) => Ok(Some(id)),
+            (_, None) => Err(Error::UnknownRef(spec.to_owned())),
+        }
+    }
+
+    fn files_of_commit(&mut self, commit: owned::Id) -> Result<Files, Error> {
+        let tree = match (self.find)(commit.to_borrowed(), &mut self.buf).ok_or(Error::Find(commit))? {
+            borrowed::Object::Commit(commit) => commit.tree(),
+            _ => return Err(Error::NotACommit(commit)),
+        };
+        let mut files = Files::new();
+        self.flatten(tree, BString::default(), &mut files)?;
+        Ok(files)
+    }
+
+    /// Add all files of `tree` to `files`, with `prefix` prepended to their paths.
+    fn flatten(&mut self, tree: owned::Id, prefix: BString, files: &mut Files) -> Result<(), Error> {
+        for entry in traverse::tree(tree, &mut self.find, Default::default()) {
+            let entry = entry.map_err(|err| match err {
+                traverse::tree::Error::NotFound(id) => Error::Find(id),
+                traverse::tree::Error::NotATree { id, .. } => Error::NotATree(id),
+                _ => Error::NotATree(tree),
+            })?;
+            let mut path = prefix.clone();
+            path.extend_from_slice(&entry.path);
+            files.insert(path, (entry.mode, entry.id));
+        }
+        Ok(())
+    }
+
+    /// Write the trees of `files` and return the id of the root tree.
+    fn write_tree(&self, files: &Files) -> Result<owned::Id, Error> {
+        let files: Vec<_> = files.iter().map(|(path, entry)| (path.as_slice(), *entry)).collect();
+        self.write_subtree(&files)
+    }
+
+    /// Write the tree of `files`, whose paths are relative to it and sorted.
+    fn write_subtree(&self, mut files: &[(&[u8], (TreeMode, owned::Id))]) -> Result<owned::Id, Error> {
+        let mut entries = Vec::new();
+        while let Some((path, (mode, oid))) = files.first() {
+            match path.find_byte(b'/') {
+                None => {
+                    entries.push(owned::tree::Entry {
+                        mode: *mode,
+                        filename: path.as_bstr().to_owned(),
+                        oid: *oid,
+                    });
+                    files = &files[1..];
+                }
+                Some(pos) => {
+                    let directory = &path[..=pos];
+                    let len = files.iter().take_while(|(path, _)| path.starts_with(directory)).count();
+                    let children: Vec<_> = files[..len]
+                        .iter()
+                        .map(|(path, entry)| (&path[directory.len()..], *entry))
+                        .collect();
+                    entries.push(owned::tree::Entry {
+                        mode: TreeMode::Tree,
+                        filename: directory[..pos].as_bstr().to_owned(),
+                        oid: self.write_subtree(&children)?,
+                    });
+                    files = &files[len..];
+                }
+            }
+        }
+        // Git sorts trees as if their names ended with a slash.
+        entries.sort_by_key(|entry| {
+            let mut key = entry.filename.to_vec();
+            if entry.mode == TreeMode::Tree {
+                key.push(b'/');
+            }
+            key
+        });
+        Ok(self
+            .db
+            .write(&owned::Object::Tree(owned::Tree { entries }), HashKind::Sha1)?)
+    }
+}
+
+/// Remove the file at `path` and all files below it.
+fn remove(files: &mut Files, path: &BStr) {
+    files.retain(|file, _| {
+        !matches!(file.strip_prefix(path.as_bytes()), Some(rest) if rest.is_empty() || rest.starts_with(b"/"))
+    });
+}
+
+fn parse_mark(mark: &BStr) -> Option<u32> {
+    mark.strip_prefix(b":")
+        .unwrap_or(mark)
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+        .filter(|mark| *mark != 0)
+}
+
+/// Parse a path which may be quoted like git quotes paths, and return it with the remaining input after it.
+///
+/// Unquoted paths end at the first space if `until_space` is set, or at the end of the input otherwise.
+pub(crate) fn parse_path(input: &[u8], until_space: bool) -> Option<(BString, &[u8])> {
+    let mut quoted = match input.strip_prefix(b"\"") {
+        Some(quoted) => quoted,
+        None => {
+            let end = if until_space {
+                input.find_byte(b' ')?
+            } else {
+                input.len()
+            };
+            let rest = input.get(end + 1..).unwrap_or_default();
+            return Some((input[..end].as_bstr().to_owned(), rest)).filter(|(path, _)| !path.is_empty());
+        }
+    };
+    let mut path = BString::default();
+    loop {
+        match *quoted.first()? {
+            b'"' => {
+                let rest = &quoted[1..];
+                return Some((path, rest.strip_prefix(b" ").unwrap_or(rest)));
+            }
+            b'\\' => {
+                let escaped = *quoted.get(1)?;
+                quoted = &quoted[2..];
+                path.push(match escaped {
+                    b'a' => b'\x07',
+                    b'b' => b'\x08',
+                    b't' => b'\t',
+                    b'n' => b'\n',
+                    b'v' => b'\x0b',
+                    b'f' => b'\x0c',
+                    b'r' => b'\r',
+                    b'0'..=b'3' => {
+                        let digits = [escaped, *quoted.first()?, *quoted.get(1)?];
+                        quoted = &quoted[2..];
+                        u8::from_str_radix(std::str::from_utf8(&digits).ok()?, 8).ok()?
+                    }
+                    other => other,
+                });
+            }
+            b => {
+                path.push(b);
+                quoted = &quoted[1..];
+            }
+        }
+    }
+}
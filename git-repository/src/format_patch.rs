@@ -0,0 +1,960 @@
+//! Write commits as emails with their patches, like `git format-patch --stdout` does, to send them to mailing lists and
+//! apply them with [`apply::mails()`][crate::apply::mails()] or `git am` elsewhere.
+//!
+//! Each commit becomes an email from its author, with its subject prefixed like `[PATCH 1/2]` and its message as
+//! body, followed by a `---` line, the diffstat and the changes to its first parent, or to the empty tree for root
+//! commits. Binary files are written as `GIT binary patch` with a deflated copy or delta of their content encoded in
+//! base85, so git can apply them. Like with git, merge commits are skipped, and so are commits without changes,
+//! which are still counted when numbering the emails.
+//!
+//! The output is the same as the one of `git format-patch --stdout --no-renames`, except that object names are always
+//! abbreviated to 7 characters, that changes which could be shown in several places, like a line added next to an
+//! identical one, may be shown in another one, and that binary patches are compressed differently. Messages aren't
+//! re-encoded and are declared as UTF-8 if they aren't ASCII.
+use crate::{blame, date, merge};
+use git_object::{
+    borrowed,
+    bstr::{self, BStr, BString, ByteSlice},
+    owned, TreeMode,
+};
+use miniz_oxide::deflate;
+use quick_error::quick_error;
+use std::{cmp::Ordering, collections::HashMap, io, ops::Range};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Find(id: owned::Id) {
+            display("Object {} could not be found", id)
+        }
+        NotACommit(id: owned::Id) {
+            display("Object {} was expected to be a commit", id)
+        }
+        NotATree(id: owned::Id) {
+            display("Object {} was expected to be a tree", id)
+        }
+        NotABlob(id: owned::Id) {
+            display("Object {} was expected to be a blob", id)
+        }
+        Io(err: io::Error) {
+            display("The emails could not be written")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// Configure how commits are written as emails.
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+pub struct Options {
+    /// The text in brackets in front of subjects, like `--subject-prefix`.
+    pub subject_prefix: BString,
+    /// If `Some(true)`, subjects are numbered like `[PATCH 1/2]`, and if `Some(false)` they never are. By default they
+    /// are numbered if there is more than one commit which isn't a merge.
+    pub numbered: Option<bool>,
+    /// The amount of unchanged lines shown around changes.
+    pub context_lines: usize,
+    /// The text below the `-- ` line at the end of each email, or `None` to end emails with their patch.
+    pub signature: Option<BString>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            subject_prefix: "PATCH".into(),
+            numbered: None,
+            context_lines: 3,
+            signature: None,
+        }
+    }
+}
+
+/// Write `commits`, oldest first, as emails to `out` and return the amount of emails written.
+///
+/// `find` is used to lookup objects, and places the object data into the provided buffer and returns the decoded object.
+pub fn write<Find>(
+    commits: &[owned::Id],
+    mut find: Find,
+    options: &Options,
+    mut out: impl io::Write,
+) -> Result<usize, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let mut buf = Vec::new();
+    let mut to_write = Vec::new();
+    for id in commits {
+        let commit = read_commit(&mut find, &mut buf, *id)?;
+        if commit.parents.len() <= 1 {
+            to_write.push((*id, commit));
+        }
+    }
+    let total = to_write.len();
+    let numbered = options.numbered.unwrap_or(total > 1);
+    let mut written = 0;
+    for (index, (id, commit)) in to_write.iter().enumerate() {
+        let base = match commit.parents.first() {
+            Some(parent) => Some(read_commit(&mut find, &mut buf, *parent)?.tree),
+            None => None,
+        };
+        let mut changes = Vec::new();
+        diff(
+            &mut find,
+            &mut buf,
+            base,
+            Some(commit.tree),
+            b"".as_bstr(),
+            &mut changes,
+        )?;
+        if changes.is_empty() {
+            continue;
+        }
+        let files = changes
+            .into_iter()
+            .map(|(path, old, new)| {
+                Ok(File {
+                    path,
+                    old: old.map(|(mode, id)| side(&mut find, &mut buf, mode, id)).transpose()?,
+                    new: new.map(|(mode, id)| side(&mut find, &mut buf, mode, id)).transpose()?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        if written != 0 {
+            out.write_all(b"\n")?;
+        }
+        let mut subject_prefix = BString::from("Subject: ");
+        if numbered {
+            let digits = total.to_string().len();
+            subject_prefix.push(b'[');
+            subject_prefix.extend_from_slice(&options.subject_prefix);
+            if !options.subject_prefix.is_empty() {
+                subject_prefix.push(b' ');
+            }
+            subject_prefix.extend_from_slice(format!("{:0digits$}/{}] ", index + 1, total, digits = digits).as_bytes());
+        } else if !options.subject_prefix.is_empty() {
+            subject_prefix.push(b'[');
+            subject_prefix.extend_from_slice(&options.subject_prefix);
+            subject_prefix.extend_from_slice(b"] ");
+        }
+        write_message(&mut out, *id, commit, &subject_prefix)?;
+        out.write_all(b"---\n")?;
+        write_stat(&mut out, &files)?;
+        out.write_all(b"\n")?;
+        for file in &files {
+            match (&file.old, &file.new) {
+                (Some(old), Some(new)) if kind(old.mode) != kind(new.mode) => {
+                    write_patch(&mut out, file.path.as_ref(), Some(old), None, options.context_lines)?;
+                    write_patch(&mut out, file.path.as_ref(), None, Some(new), options.context_lines)?;
+                }
+                (old, new) => write_patch(
+                    &mut out,
+                    file.path.as_ref(),
+                    old.as_ref(),
+                    new.as_ref(),
+                    options.context_lines,
+                )?,
+            }
+        }
+        if let Some(signature) = options.signature.as_ref().filter(|signature| !signature.is_empty()) {
+            out.write_all(b"-- \n")?;
+            out.write_all(signature)?;
+            if !signature.ends_with(b"\n") {
+                out.write_all(b"\n")?;
+            }
+            out.write_all(b"\n")?;
+        }
+        written += 1;
+    }
+    Ok(written)
+}
+
+struct Commit {
+    tree: owned::Id,
+    parents: Vec<owned::Id>,
+    author: owned::Signature,
+    message: BString,
+}
+
+/// One side of a changed file.
+struct Side {
+    mode: TreeMode,
+    id: owned::Id,
+    data: Vec<u8>,
+}
+
+/// A file which differs between the tree of a commit and the one of its first parent.
+struct File {
+    path: BString,
+    old: Option<Side>,
+    new: Option<Side>,
+}
+
+fn read_commit<Find>(find: &mut Find, buf: &mut Vec<u8>, id: owned::Id) -> Result<Commit, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    match find(id.to_borrowed(), buf) {
+        Some(borrowed::Object::Commit(commit)) => Ok(Commit {
+            tree: commit.tree(),
+            parents: commit.parents().collect(),
+            author: owned::Signature {
+                name: commit.author.name.to_owned(),
+                email: commit.author.email.to_owned(),
+                time: commit.author.time,
+            },
+            message: commit.message.to_owned(),
+        }),
+        Some(_) => Err(Error::NotACommit(id)),
+        None => Err(Error::Find(id)),
+    }
+}
+
+fn side<Find>(find: &mut Find, buf: &mut Vec<u8>, mode: TreeMode, id: owned::Id) -> Result<Side, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let data = if mode == TreeMode::Commit {
+        format!("Subproject commit {}\n", id).into_bytes()
+    } else {
+        match find(id.to_borrowed(), buf).ok_or(Error::Find(id))? {
+            borrowed::Object::Blob(blob) => blob.data.to_vec(),
+            _ => return Err(Error::NotABlob(id)),
+        }
+    };
+    Ok(Side { mode, id, data })
+}
+
+/// Collect the files which differ between the trees `old` and `new` at `prefix`, which may not exist, in the order
+/// git shows them in.
+fn diff<Find>(
+    find: &mut Find,
+    buf: &mut Vec<u8>,
+    old: Option<owned::Id>,
+    new: Option<owned::Id>,
+    prefix: &BStr,
+    changes: &mut Vec<(BString, Option<merge::Entry>, Option<merge::Entry>)>,
+) -> Result<(), Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let old = entries(find, buf, old)?;
+    let new = entries(find, buf, new)?;
+    let (mut old, mut new) = (old.into_iter().peekable(), new.into_iter().peekable());
+    loop {
+        let (old_entry, new_entry) = match (old.peek(), new.peek()) {
+            (None, None) => break,
+            (Some(_), None) => (old.next(), None),
+            (None, Some(_)) => (None, new.next()),
+            (Some(a), Some(b)) => match merge::sort_key(a).cmp(&merge::sort_key(b)) {
+                Ordering::Less => (old.next(), None),
+                Ordering::Greater => (None, new.next()),
+                Ordering::Equal => (old.next(), new.next()),
+            },
+        };
+        let entry = |entry: &Option<owned::tree::Entry>| entry.as_ref().map(|entry| (entry.mode, entry.oid));
+        if entry(&old_entry) == entry(&new_entry) {
+            continue;
+        }
+        let name = old_entry.as_ref().or(new_entry.as_ref()).expect("either exists");
+        let mut path = BString::from(prefix.as_bytes());
+        if !path.is_empty() {
+            path.push(b'/');
+        }
+        path.extend_from_slice(&name.filename);
+        if name.mode == TreeMode::Tree {
+            let tree = |entry: Option<owned::tree::Entry>| entry.map(|entry| entry.oid);
+            diff(find, buf, tree(old_entry), tree(new_entry), path.as_ref(), changes)?;
+        } else {
+            changes.push((path, entry(&old_entry), entry(&new_entry)));
+        }
+    }
+    Ok(())
+}
+
+fn entries<Find>(find: &mut Find, buf: &mut Vec<u8>, tree: Option<owned::Id>) -> Result<Vec<owned::tree::Entry>, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let tree = match tree {
+        Some(tree) => tree,
+        None => return Ok(Vec::new()),
+    };
+    match find(tree.to_borrowed(), buf).ok_or(Error::Find(tree))? {
+        borrowed::Object::Tree(tree) => Ok(tree
+            .entries
+            .into_iter()
+            .map(|entry| owned::tree::Entry {
+                mode: entry.mode,
+                filename: entry.filename.to_owned(),
+                oid: owned::Id::from_borrowed_sha1(entry.oid.sha1()),
+            })
+            .collect()),
+        _ => Err(Error::NotATree(tree)),
+    }
+}
+
+/// Files, links and submodules are different kinds of entries, whose changes are shown as deletion and creation.
+fn kind(mode: TreeMode) -> u8 {
+    match mode {
+        TreeMode::Link => 1,
+        TreeMode::Commit => 2,
+        _ => 0,
+    }
+}
+
+/// Git considers data binary if it contains a NUL byte within its first 8000 bytes.
+fn is_binary(data: &[u8]) -> bool {
+    data[..data.len().min(8000)].contains(&0)
+}
+
+fn is_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+/// Write the `From`, `Date` and `Subject` headers of `commit`, the latter starting with `subject_prefix`, followed by
+/// its message.
+fn write_message(out: &mut impl io::Write, id: owned::Id, commit: &Commit, subject_prefix: &[u8]) -> io::Result<()> {
+    writeln!(out, "From {} Mon Sep 17 00:00:00 2001", id)?;
+    let (name, email) = (commit.author.name.as_slice(), commit.author.email.as_slice());
+    let mut headers = b"From: ".to_vec();
+    let mut max_length = 78;
+    if needs_rfc2047(name) {
+        add_rfc2047(&mut headers, name, true);
+        max_length = 76;
+    } else if name.iter().any(|b| b"()<>[]:;@,.\"\\".contains(b)) {
+        let mut quoted = vec![b'"'];
+        for b in name {
+            if *b == b'"' || *b == b'\\' {
+                quoted.push(b'\\');
+            }
+            quoted.push(*b);
+        }
+        quoted.push(b'"');
+        add_wrapped(&mut headers, &quoted, -6, 1, 78);
+    } else {
+        add_wrapped(&mut headers, name, -6, 1, 78);
+    }
+    if max_length < last_line_length(&headers) + email.len() + 3 {
+        headers.push(b'\n');
+    }
+    headers.extend_from_slice(b" <");
+    headers.extend_from_slice(email);
+    headers.extend_from_slice(b">\n");
+    out.write_all(&headers)?;
+    writeln!(out, "Date: {}", date::format(commit.author.time, date::Format::Rfc2822))?;
+
+    let mut lines = commit
+        .message
+        .lines()
+        .map(|line| line.trim_end_with(|c| c.is_ascii() && is_space(c as u8)))
+        .skip_while(|line| line.is_empty())
+        .peekable();
+    let mut title = Vec::new();
+    while let Some(line) = lines.next_if(|line| !line.is_empty()) {
+        if !title.is_empty() {
+            title.push(b' ');
+        }
+        title.extend_from_slice(line);
+    }
+    let mut subject = subject_prefix.to_vec();
+    if needs_rfc2047(&title) {
+        add_rfc2047(&mut subject, &title, false);
+    } else {
+        let indent = last_line_length(&subject) as isize;
+        add_wrapped(&mut subject, &title, -indent, 1, 78);
+    }
+    subject.push(b'\n');
+    out.write_all(&subject)?;
+    if !commit.message.is_ascii() {
+        out.write_all(
+            b"MIME-Version: 1.0\nContent-Type: text/plain; charset=UTF-8\nContent-Transfer-Encoding: 8bit\n",
+        )?;
+    }
+    out.write_all(b"\n")?;
+
+    let body: Vec<_> = lines.skip_while(|line| line.is_empty()).collect();
+    let body_len = body.iter().rposition(|line| !line.is_empty()).map_or(0, |pos| pos + 1);
+    for line in &body[..body_len] {
+        out.write_all(line)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+fn last_line_length(text: &[u8]) -> usize {
+    text.len() - text.rfind_byte(b'\n').map_or(0, |pos| pos + 1)
+}
+
+/// Header values with non-ASCII characters, newlines or what looks like an encoded word need to be encoded.
+fn needs_rfc2047(value: &[u8]) -> bool {
+    !value.is_ascii() || value.contains(&b'\n') || value.find(b"=?").is_some()
+}
+
+/// Append `value` as RFC2047 encoded words to the header in `out`, folded to keep lines below 76 characters, with the
+/// stricter rules of names in addresses if `address` is true.
+fn add_rfc2047(out: &mut Vec<u8>, mut value: &[u8], address: bool) {
+    const MAX_ENCODED_LENGTH: usize = 76;
+    let mut line_len = last_line_length(out) + "UTF-8".len() + 5;
+    out.extend_from_slice(b"=?UTF-8?q?");
+    while !value.is_empty() {
+        let (_, char_len) = bstr::decode_utf8(value);
+        let (character, rest) = value.split_at(char_len.max(1));
+        value = rest;
+        let c = character[0];
+        let is_special = character.len() > 1
+            || !(c.is_ascii_graphic() || c == b' ')
+            || matches!(c, b' ' | b'=' | b'?' | b'_')
+            || (address && !(c.is_ascii_alphanumeric() || b"!*+-/".contains(&c)));
+        let encoded_len = if is_special { 3 * character.len() } else { 1 };
+        if line_len + encoded_len + 2 > MAX_ENCODED_LENGTH {
+            out.extend_from_slice(b"?=\n =?UTF-8?q?");
+            line_len = "UTF-8".len() + 5 + 1;
+        }
+        if is_special {
+            for b in character {
+                out.extend_from_slice(format!("={:02X}", b).as_bytes());
+            }
+        } else {
+            out.push(c);
+        }
+        line_len += encoded_len;
+    }
+    out.extend_from_slice(b"?=");
+}
+
+/// Append `text` to `out` wrapped at `width` columns like git's `strbuf_add_wrapped_text()` does, where the first line
+/// is indented by `indent1`, or already has `-indent1` columns if it's negative, and the others are indented by
+/// `indent2`.
+fn add_wrapped(out: &mut Vec<u8>, text: &[u8], indent1: isize, indent2: isize, width: isize) {
+    let (mut pos, mut bol) = (0, 0);
+    let (mut w, mut indent) = (indent1, indent1);
+    let mut space = None;
+    if indent < 0 {
+        w = -indent;
+        space = Some(0);
+    }
+    loop {
+        let c = text.get(pos).copied();
+        if !matches!(c, None | Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            w += 1;
+            pos += 1;
+            continue;
+        }
+        let mut new_line = w > width && space.is_some();
+        if !new_line {
+            if c.is_none() && pos == bol {
+                return;
+            }
+            let start = match space {
+                Some(space) => space,
+                None => {
+                    out.resize(out.len() + indent.max(0) as usize, b' ');
+                    bol
+                }
+            };
+            out.extend_from_slice(&text[start..pos]);
+            let c = match c {
+                Some(c) => c,
+                None => return,
+            };
+            space = Some(pos);
+            if c == b'\t' {
+                w |= 0x07;
+            } else if c == b'\n' {
+                space = Some(pos + 1);
+                match text.get(pos + 1) {
+                    Some(b'\n') => {
+                        out.push(b'\n');
+                        new_line = true;
+                    }
+                    Some(next) if next.is_ascii_alphanumeric() => out.push(b' '),
+                    _ => new_line = true,
+                }
+            }
+            if !new_line {
+                w += 1;
+                pos += 1;
+            }
+        }
+        if new_line {
+            out.push(b'\n');
+            let space = space.take().expect("set when breaking lines");
+            pos = space + text.get(space).map_or(0, |c| is_space(*c) as usize);
+            bol = pos;
+            w = indent2;
+            indent = indent2;
+        }
+    }
+}
+
+/// Return `path` with `prefix`, quoted like git does if it contains special characters.
+fn quoted(prefix: &str, path: &[u8]) -> Vec<u8> {
+    let mut out = prefix.as_bytes().to_vec();
+    if !path
+        .iter()
+        .any(|b| *b < 0x20 || *b >= 0x7f || *b == b'"' || *b == b'\\')
+    {
+        out.extend_from_slice(path);
+        return out;
+    }
+    out.insert(0, b'"');
+    for b in path {
+        match b {
+            b'\x07' => out.extend_from_slice(b"\\a"),
+            b'\x08' => out.extend_from_slice(b"\\b"),
+            b'\t' => out.extend_from_slice(b"\\t"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            b'\x0b' => out.extend_from_slice(b"\\v"),
+            b'\x0c' => out.extend_from_slice(b"\\f"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            b'"' => out.extend_from_slice(b"\\\""),
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            b if *b < 0x20 || *b >= 0x7f => out.extend_from_slice(format!("\\{:03o}", b).as_bytes()),
+            b => out.push(*b),
+        }
+    }
+    out.push(b'"');
+    out
+}
+
+/// The added and removed lines of a text file, or bytes of a binary one.
+struct Stat {
+    added: usize,
+    deleted: usize,
+    binary: bool,
+}
+
+impl Stat {
+    fn of(file: &File) -> Stat {
+        let empty = Vec::new();
+        let old = file.old.as_ref().map_or(&empty, |side| &side.data);
+        let new = file.new.as_ref().map_or(&empty, |side| &side.data);
+        if is_binary(old) || is_binary(new) {
+            let unchanged = file.old.as_ref().map(|side| side.id) == file.new.as_ref().map(|side| side.id);
+            return Stat {
+                added: if unchanged { 0 } else { new.len() },
+                deleted: if unchanged { 0 } else { old.len() },
+                binary: true,
+            };
+        }
+        let (old, new) = (lines(old), lines(new));
+        let changes = changes(&old, &new);
+        Stat {
+            added: changes.iter().map(|(_, new)| new.len()).sum(),
+            deleted: changes.iter().map(|(old, _)| old.len()).sum(),
+            binary: false,
+        }
+    }
+}
+
+fn decimal_width(n: usize) -> isize {
+    n.to_string().len() as isize
+}
+
+/// Write the diffstat of `files` along with the summary of created, deleted and changed modes like git does for emails.
+fn write_stat(out: &mut impl io::Write, files: &[File]) -> io::Result<()> {
+    let stats: Vec<_> = files.iter().map(Stat::of).collect();
+    let names: Vec<_> = files.iter().map(|file| quoted("", &file.path)).collect();
+    let (mut max_change, mut max_len, mut bin_width, mut number_width) = (0, 0, 0, 0);
+    for (name, stat) in names.iter().zip(&stats) {
+        max_len = max_len.max(name.len() as isize);
+        if stat.binary {
+            bin_width = bin_width.max(14 + decimal_width(stat.added) + decimal_width(stat.deleted));
+            number_width = 3;
+        } else {
+            max_change = max_change.max(stat.added + stat.deleted);
+        }
+    }
+    number_width = number_width.max(decimal_width(max_change));
+    let width = 72.max(16 + 6 + number_width);
+    let max_change = max_change as isize;
+    let mut graph_width = if max_change + 4 > bin_width {
+        max_change
+    } else {
+        bin_width - 4
+    };
+    let mut name_width = max_len;
+    if name_width + number_width + 6 + graph_width > width {
+        if graph_width > width * 3 / 8 - number_width - 6 {
+            graph_width = (width * 3 / 8 - number_width - 6).max(6);
+        }
+        if name_width > width - number_width - 6 - graph_width {
+            name_width = width - number_width - 6 - graph_width;
+        } else {
+            graph_width = width - number_width - 6 - name_width;
+        }
+    }
+
+    let (mut adds, mut dels) = (0, 0);
+    for (name, stat) in names.iter().zip(&stats) {
+        let (mut name, mut prefix, mut len) = (&name[..], "", name_width);
+        if name_width < name.len() as isize {
+            prefix = "...";
+            len = (len - 3).max(0);
+            name = &name[name.len() - len as usize..];
+            if let Some(slash) = name.find_byte(b'/') {
+                name = &name[slash..];
+            }
+        }
+        let padding = (len - name.len() as isize).max(0) as usize;
+        out.write_all(b" ")?;
+        out.write_all(prefix.as_bytes())?;
+        out.write_all(name)?;
+        let number_width = number_width as usize;
+        if stat.binary {
+            write!(
+                out,
+                "{:padding$} | {:>number_width$}",
+                "",
+                "Bin",
+                padding = padding,
+                number_width = number_width
+            )?;
+            if stat.added != 0 || stat.deleted != 0 {
+                write!(out, " {} -> {} bytes", stat.deleted, stat.added)?;
+            }
+            out.write_all(b"\n")?;
+            continue;
+        }
+        adds += stat.added;
+        dels += stat.deleted;
+        let (mut add, mut del) = (stat.added as isize, stat.deleted as isize);
+        if graph_width <= max_change {
+            let scale = |it: isize| {
+                if it == 0 {
+                    0
+                } else {
+                    1 + it * (graph_width - 1) / max_change
+                }
+            };
+            let mut total = scale(add + del);
+            if total < 2 && add != 0 && del != 0 {
+                total = 2;
+            }
+            if add < del {
+                add = scale(add);
+                del = total - add;
+            } else {
+                del = scale(del);
+                add = total - del;
+            }
+        }
+        write!(
+            out,
+            "{:padding$} | {:>number_width$}{}",
+            "",
+            stat.added + stat.deleted,
+            if stat.added + stat.deleted != 0 { " " } else { "" },
+            padding = padding,
+            number_width = number_width
+        )?;
+        let graph = |c: &str, n: isize| c.repeat(n.max(0) as usize);
+        writeln!(out, "{}{}", graph("+", add), graph("-", del))?;
+    }
+
+    write!(
+        out,
+        " {} file{} changed",
+        files.len(),
+        if files.len() == 1 { "" } else { "s" }
+    )?;
+    if adds != 0 || dels == 0 {
+        write!(out, ", {} insertion{}(+)", adds, if adds == 1 { "" } else { "s" })?;
+    }
+    if dels != 0 || adds == 0 {
+        write!(out, ", {} deletion{}(-)", dels, if dels == 1 { "" } else { "s" })?;
+    }
+    out.write_all(b"\n")?;
+
+    for (file, name) in files.iter().zip(&names) {
+        match (&file.old, &file.new) {
+            (None, Some(new)) => write!(out, " create mode {:06o} ", new.mode as u16)?,
+            (Some(old), None) => write!(out, " delete mode {:06o} ", old.mode as u16)?,
+            (Some(old), Some(new)) if old.mode != new.mode => {
+                write!(out, " mode change {:06o} => {:06o} ", old.mode as u16, new.mode as u16)?
+            }
+            _ => continue,
+        }
+        out.write_all(name)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+fn lines(data: &[u8]) -> Vec<&[u8]> {
+    data.lines_with_terminator().collect()
+}
+
+/// The ranges of lines in `old` replaced by the ones of `new`, in order.
+fn changes(old: &[&[u8]], new: &[&[u8]]) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut changes = Vec::new();
+    let (mut old_pos, mut new_pos) = (0, 0);
+    let blocks = blame::matching_blocks(old, new);
+    let end = (old.len() as u32, new.len() as u32, 0);
+    for (old_start, new_start, len) in blocks.into_iter().chain(Some(end)) {
+        let (old_start, new_start, len) = (old_start as usize, new_start as usize, len as usize);
+        if old_pos < old_start || new_pos < new_start {
+            changes.push((old_pos..old_start, new_pos..new_start));
+        }
+        old_pos = old_start + len;
+        new_pos = new_start + len;
+    }
+    changes
+}
+
+/// Write the patch turning `old` into `new` at `path`, where `None` is a file which doesn't exist.
+fn write_patch(
+    out: &mut impl io::Write,
+    path: &BStr,
+    old: Option<&Side>,
+    new: Option<&Side>,
+    context_lines: usize,
+) -> io::Result<()> {
+    out.write_all(b"diff --git ")?;
+    out.write_all(&quoted("a/", path))?;
+    out.write_all(b" ")?;
+    out.write_all(&quoted("b/", path))?;
+    out.write_all(b"\n")?;
+    match (old, new) {
+        (None, Some(new)) => writeln!(out, "new file mode {:06o}", new.mode as u16)?,
+        (Some(old), None) => writeln!(out, "deleted file mode {:06o}", old.mode as u16)?,
+        (Some(old), Some(new)) if old.mode != new.mode => writeln!(
+            out,
+            "old mode {:06o}\nnew mode {:06o}",
+            old.mode as u16, new.mode as u16
+        )?,
+        _ => {}
+    }
+    let (old_id, new_id) = (old.map(|side| side.id), new.map(|side| side.id));
+    if old_id == new_id {
+        return Ok(());
+    }
+    let empty = Vec::new();
+    let old_data = old.map_or(&empty, |side| &side.data);
+    let new_data = new.map_or(&empty, |side| &side.data);
+    let binary = is_binary(old_data) || is_binary(new_data);
+    let hex_len = if binary { 40 } else { 7 };
+    let hex =
+        |id: Option<owned::Id>| id.unwrap_or_else(owned::Id::null_sha1).to_sha1_hex_string()[..hex_len].to_owned();
+    write!(out, "index {}..{}", hex(old_id), hex(new_id))?;
+    match (old, new) {
+        (Some(old), Some(new)) if old.mode == new.mode => writeln!(out, " {:06o}", old.mode as u16)?,
+        _ => out.write_all(b"\n")?,
+    }
+    if binary {
+        out.write_all(b"GIT binary patch\n")?;
+        write_binary(out, old_data, new_data)?;
+        return write_binary(out, new_data, old_data);
+    }
+
+    let (old_lines, new_lines) = (lines(old_data), lines(new_data));
+    let changes = changes(&old_lines, &new_lines);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    // Like git, end paths containing spaces with a tab, so patch tools know where they end.
+    let tab = if path.contains(&b' ') { "\t" } else { "" };
+    match old {
+        Some(_) => writeln!(out, "--- {}{}", quoted("a/", path).as_bstr(), tab)?,
+        None => out.write_all(b"--- /dev/null\n")?,
+    }
+    match new {
+        Some(_) => writeln!(out, "+++ {}{}", quoted("b/", path).as_bstr(), tab)?,
+        None => out.write_all(b"+++ /dev/null\n")?,
+    }
+    write_hunks(out, &old_lines, &new_lines, &changes, context_lines)
+}
+
+/// Write the hunks showing `changes` with `context_lines` around them, and with the last line before each hunk which
+/// looks like the start of a function, like git does without configured function name patterns.
+fn write_hunks(
+    out: &mut impl io::Write,
+    old: &[&[u8]],
+    new: &[&[u8]],
+    changes: &[(Range<usize>, Range<usize>)],
+    context_lines: usize,
+) -> io::Result<()> {
+    let write_line = |out: &mut dyn io::Write, marker: u8, line: &[u8]| {
+        out.write_all(&[marker])?;
+        out.write_all(line)?;
+        if !line.ends_with(b"\n") {
+            out.write_all(b"\n\\ No newline at end of file\n")?;
+        }
+        Ok::<_, io::Error>(())
+    };
+    let range = |start: usize, len: usize| match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    };
+    let mut function: &[u8] = b"";
+    let mut searched_until = -1;
+    let mut index = 0;
+    while index < changes.len() {
+        let mut last = index;
+        while last + 1 < changes.len() && changes[last + 1].0.start - changes[last].0.end <= 2 * context_lines {
+            last += 1;
+        }
+        let (first_change, last_change) = (&changes[index], &changes[last]);
+        let old_start = first_change.0.start.saturating_sub(context_lines);
+        let new_start = first_change.1.start - (first_change.0.start - old_start);
+        let old_end = (last_change.0.end + context_lines).min(old.len());
+        let new_end = last_change.1.end + (old_end - last_change.0.end);
+
+        let mut line = old_start as isize - 1;
+        while line > searched_until {
+            let text = old[line as usize];
+            if matches!(text.first(), Some(c) if c.is_ascii_alphabetic() || *c == b'_' || *c == b'$') {
+                function = text[..text.len().min(80)].trim_end_with(|c| c.is_ascii() && is_space(c as u8));
+                break;
+            }
+            line -= 1;
+        }
+        searched_until = old_start as isize - 1;
+
+        write!(
+            out,
+            "@@ -{} +{} @@",
+            range(old_start, old_end - old_start),
+            range(new_start, new_end - new_start)
+        )?;
+        if !function.is_empty() {
+            out.write_all(b" ")?;
+            out.write_all(function)?;
+        }
+        out.write_all(b"\n")?;
+        let mut pos = old_start;
+        for (old_lines, new_lines) in &changes[index..=last] {
+            for line in &old[pos..old_lines.start] {
+                write_line(out, b' ', line)?;
+            }
+            for line in &old[old_lines.clone()] {
+                write_line(out, b'-', line)?;
+            }
+            for line in &new[new_lines.clone()] {
+                write_line(out, b'+', line)?;
+            }
+            pos = old_lines.end;
+        }
+        for line in &old[pos..old_end] {
+            write_line(out, b' ', line)?;
+        }
+        index = last + 1;
+    }
+    Ok(())
+}
+
+const BASE85: &[u8; 85] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!#$%&()*+-;<=>?@^_`{|}~";
+
+/// Write the part of a binary patch turning `old` into `new`, which is a delta if it's smaller than the deflated `new`.
+fn write_binary(out: &mut impl io::Write, old: &[u8], new: &[u8]) -> io::Result<()> {
+    let literal = deflate::compress_to_vec_zlib(new, 6);
+    let delta = if old.is_empty() || new.is_empty() {
+        None
+    } else {
+        delta(old, new, literal.len()).map(|delta| (delta.len(), deflate::compress_to_vec_zlib(&delta, 6)))
+    };
+    let data = match delta {
+        Some((size, delta)) if delta.len() < literal.len() => {
+            writeln!(out, "delta {}", size)?;
+            delta
+        }
+        _ => {
+            writeln!(out, "literal {}", new.len())?;
+            literal
+        }
+    };
+    for chunk in data.chunks(52) {
+        let len = chunk.len() as u8;
+        let mut line = vec![if len <= 26 { b'A' + len - 1 } else { b'a' + len - 27 }];
+        for group in chunk.chunks(4) {
+            let mut value = group
+                .iter()
+                .enumerate()
+                .fold(0u32, |value, (index, b)| value | u32::from(*b) << (24 - 8 * index));
+            let mut encoded = [0; 5];
+            for c in encoded.iter_mut().rev() {
+                *c = BASE85[(value % 85) as usize];
+                value /= 85;
+            }
+            line.extend_from_slice(&encoded);
+        }
+        line.push(b'\n');
+        out.write_all(&line)?;
+    }
+    out.write_all(b"\n")
+}
+
+/// Encode `target` as git delta against `source` by copying the blocks of 16 bytes it has in common, or return `None` if
+/// the delta is larger than `max_size`.
+fn delta(source: &[u8], target: &[u8], max_size: usize) -> Option<Vec<u8>> {
+    const BLOCK: usize = 16;
+    let mut blocks = HashMap::new();
+    for offset in (0..source.len().saturating_sub(BLOCK - 1)).step_by(BLOCK) {
+        blocks.entry(&source[offset..offset + BLOCK]).or_insert(offset);
+    }
+    let mut out = Vec::new();
+    for mut size in [source.len(), target.len()] {
+        while size >= 0x80 {
+            out.push(size as u8 | 0x80);
+            size >>= 7;
+        }
+        out.push(size as u8);
+    }
+    let insert = |out: &mut Vec<u8>, data: &[u8]| {
+        for chunk in data.chunks(0x7f) {
+            out.push(chunk.len() as u8);
+            out.extend_from_slice(chunk);
+        }
+    };
+    let (mut pos, mut inserted_until) = (0, 0);
+    while pos + BLOCK <= target.len() {
+        let mut offset = match blocks.get(&target[pos..pos + BLOCK]) {
+            Some(offset) => *offset,
+            None => {
+                pos += 1;
+                continue;
+            }
+        };
+        while offset > 0 && pos > inserted_until && source[offset - 1] == target[pos - 1] {
+            offset -= 1;
+            pos -= 1;
+        }
+        let len = source[offset..]
+            .iter()
+            .zip(&target[pos..])
+            .take_while(|(a, b)| a == b)
+            .count();
+        insert(&mut out, &target[inserted_until..pos]);
+        for start in (0..len).step_by(0x10000) {
+            let (copy_offset, copy_len) = (offset + start, (len - start).min(0x10000));
+            let mut command = 0x80;
+            let mut arguments = Vec::new();
+            for (index, b) in (copy_offset as u32).to_le_bytes().iter().enumerate() {
+                if *b != 0 {
+                    command |= 1 << index;
+                    arguments.push(*b);
+                }
+            }
+            for (index, b) in (copy_len as u32).to_le_bytes()[..3].iter().enumerate() {
+                if *b != 0 {
+                    command |= 0x10 << index;
+                    arguments.push(*b);
+                }
+            }
+            out.push(command);
+            out.extend_from_slice(&arguments);
+        }
+        pos += len;
+        inserted_until = pos;
+        if out.len() > max_size {
+            return None;
+        }
+    }
+    insert(&mut out, &target[inserted_until..]);
+    if out.len() > max_size {
+        return None;
+    }
+    Some(out)
+}
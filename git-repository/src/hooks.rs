@@ -0,0 +1,174 @@
+//! Find and run the executable hooks of a repository like git does.
+//!
+//! Hooks are looked up in the `hooks` directory of the common git directory, which is shared by all linked worktrees,
+//! or in the directory configured with `core.hooksPath`. They are run in the root of the worktree, or in the git
+//! directory of bare repositories and while receiving pushes, with `GIT_DIR` set, and everything they print is
+//! collected for the user.
+//!
+//! The arguments and input depend on the hook, and are the ones documented in `githooks(5)`:
+//!
+//! * `pre-commit` and `post-commit` receive nothing, `commit-msg` the path to the file with the message.
+//! * `pre-receive` and `post-receive` receive `<old> <new> <ref>` lines on standard input.
+//! * `update` receives the name of the ref as well as the old and new object as arguments.
+//! * `reference-transaction` receives its state, `prepared` or `committed`, as argument, and `<old> <new> <ref>` lines
+//!   on standard input.
+use crate::worktree::linked;
+use git_object::{
+    bstr::{BStr, ByteSlice},
+    owned,
+};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{self, Stdio},
+};
+
+/// Where to find hooks and how to run them.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+pub struct Runner {
+    /// The directory containing the hooks, or `None` if no hook is run.
+    pub directory: Option<PathBuf>,
+    /// The directory hooks are run in.
+    pub working_dir: PathBuf,
+    /// The git directory of the repository, passed to hooks as `GIT_DIR`.
+    pub git_dir: PathBuf,
+}
+
+impl Runner {
+    /// Run the hooks of the repository at `git_dir`, honoring `core.hooksPath` and `core.bare` of its configuration.
+    ///
+    /// Like for git, a relative `core.hooksPath` is relative to the directory hooks are run in. Configuration files
+    /// which can't be read or parsed are ignored.
+    pub fn from_git_dir(git_dir: impl Into<PathBuf>) -> Self {
+        let git_dir = git_dir.into();
+        let common_dir = linked::common_dir(&git_dir).unwrap_or_else(|_| git_dir.clone());
+        let config = git_config::File::at(common_dir.join("config")).unwrap_or_default();
+        let working_dir = working_dir(&git_dir, &config);
+        let directory = match config
+            .value("core", None, "hooksPath")
+            .and_then(|path| path.to_path().ok())
+        {
+            Some(path) => match path.strip_prefix("~") {
+                Ok(relative) => std::env::var_os("HOME")
+                    .map(|home| Path::new(&home).join(relative))
+                    .unwrap_or_else(|| path.to_owned()),
+                Err(_) => working_dir.join(path),
+            },
+            None => common_dir.join("hooks"),
+        };
+        Runner {
+            directory: Some(directory),
+            working_dir,
+            git_dir,
+        }
+    }
+
+    /// A runner which never runs any hook, as if none existed.
+    pub fn disabled() -> Self {
+        Runner::default()
+    }
+
+    /// The path to the hook `name`, if it exists and hooks aren't disabled.
+    pub fn find(&self, name: &str) -> Option<PathBuf> {
+        self.directory
+            .as_ref()
+            .map(|directory| directory.join(name))
+            .filter(|path| path.is_file())
+    }
+
+    /// The path of `path` within the git directory as seen by hooks, which is relative to the directory they run in.
+    pub fn git_path(&self, path: impl AsRef<Path>) -> PathBuf {
+        match self.git_dir.strip_prefix(&self.working_dir) {
+            Ok(relative) => relative.join(path),
+            Err(_) => self.git_dir.join(path),
+        }
+    }
+
+    /// Run the hook `name` with `args`, passing `input` on standard input if set, and return true if it succeeded, or
+    /// `None` if there is no such hook. Its output is appended to `messages`.
+    pub fn run(&self, name: &str, args: &[String], input: Option<&[u8]>, messages: &mut Vec<u8>) -> Option<bool> {
+        let program = self.find(name)?;
+        let git_dir = match self.git_dir.strip_prefix(&self.working_dir) {
+            Ok(relative) if relative.as_os_str().is_empty() => Path::new("."),
+            Ok(relative) => relative,
+            Err(_) => &self.git_dir,
+        };
+        let child = process::Command::new(&program)
+            .args(args)
+            .current_dir(&self.working_dir)
+            .env("GIT_DIR", git_dir)
+            .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(err) => {
+                messages.extend_from_slice(format!("error: cannot run {}: {}\n", name, err).as_bytes());
+                return Some(false);
+            }
+        };
+        if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+            // Hooks are free to ignore their input.
+            stdin.write_all(input).ok();
+        }
+        Some(match child.wait_with_output() {
+            Ok(output) => {
+                messages.extend_from_slice(&output.stdout);
+                messages.extend_from_slice(&output.stderr);
+                output.status.success()
+            }
+            Err(err) => {
+                messages.extend_from_slice(format!("error: {} failed: {}\n", name, err).as_bytes());
+                false
+            }
+        })
+    }
+}
+
+/// The `<old> <new> <ref>` lines hooks receive on standard input for ref `updates`, where missing objects are
+/// written as null ids.
+pub fn ref_updates_input<'a>(
+    updates: impl IntoIterator<Item = (Option<owned::Id>, Option<owned::Id>, &'a BStr)>,
+) -> Vec<u8> {
+    let mut input = Vec::new();
+    for (old, new, name) in updates {
+        let null = owned::Id::null_sha1();
+        input.extend_from_slice(format!("{} {} ", old.unwrap_or(null), new.unwrap_or(null)).as_bytes());
+        input.extend_from_slice(name);
+        input.push(b'\n');
+    }
+    input
+}
+
+/// The directory hooks are run in, which is the root of the worktree of `git_dir`, or `git_dir` itself if it's bare.
+fn working_dir(git_dir: &Path, config: &git_config::File) -> PathBuf {
+    if let Some(Ok(true)) = config.boolean("core", None, "bare") {
+        return git_dir.to_owned();
+    }
+    if let Some(worktree) = config
+        .value("core", None, "worktree")
+        .and_then(|path| path.to_path().ok())
+    {
+        return git_dir.join(worktree);
+    }
+    if git_dir.file_name() == Some(".git".as_ref()) {
+        if let Some(parent) = git_dir.parent() {
+            return if parent.as_os_str().is_empty() {
+                ".".into()
+            } else {
+                parent.to_owned()
+            };
+        }
+    }
+    // The `gitdir` file of linked worktrees points to the `.git` file in the worktree.
+    match std::fs::read(git_dir.join("gitdir")) {
+        Ok(dot_git) => dot_git
+            .trim_end_with(|c| c == '\n')
+            .to_path()
+            .ok()
+            .and_then(|dot_git| git_dir.join(dot_git).parent().map(ToOwned::to_owned))
+            .unwrap_or_else(|| git_dir.to_owned()),
+        Err(_) => git_dir.to_owned(),
+    }
+}
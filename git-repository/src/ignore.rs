@@ -0,0 +1,158 @@
+//! Parse `.gitignore` files and tell which paths of a worktree are ignored.
+//!
+//! Like git, patterns without a slash, other than a trailing one, match the name of files and directories at any
+//! depth below the directory containing the `.gitignore` file, while all other patterns match the path relative to
+//! that directory. Files in deeper directories take precedence over those closer to the root, and later patterns over
+//! earlier ones, with patterns starting with `!` including paths again. Paths within ignored directories can't be
+//! included again, as git doesn't look into these directories.
+//!
+//! Before any `.gitignore` file, the patterns of `core.excludesFile` and `$GIT_DIR/info/exclude` apply, with the latter
+//! taking precedence.
+use crate::{attributes, worktree::linked};
+use git_object::bstr::{BStr, BString, ByteSlice};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The name of the files with patterns in the worktree.
+pub const FILE_NAME: &str = ".gitignore";
+
+/// A pattern of a `.gitignore` file.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Pattern {
+    /// The pattern without the leading `!` and trailing `/`.
+    pub text: BString,
+    /// If true, matching paths are included again.
+    pub negative: bool,
+    /// If true, the pattern ends with `/` to only match directories.
+    pub directory_only: bool,
+}
+
+/// Parse the patterns of a `.gitignore` file, skipping empty lines and comments.
+///
+/// Like git, trailing spaces are removed unless they are escaped with a backslash.
+pub fn parse(data: &[u8]) -> impl Iterator<Item = Pattern> + '_ {
+    data.lines().filter_map(|line| {
+        let mut end = line.len();
+        while end > 0 && line[end - 1] == b' ' && !(end > 1 && line[end - 2] == b'\\') {
+            end -= 1;
+        }
+        let line = &line[..end];
+        if line.is_empty() || line.starts_with(b"#") {
+            return None;
+        }
+        let (negative, line) = match line.strip_prefix(b"!") {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let (directory_only, text) = match line.strip_suffix(b"/") {
+            Some(text) => (true, text),
+            None => (false, line),
+        };
+        Some(Pattern {
+            text: text.into(),
+            negative,
+            directory_only,
+        })
+    })
+}
+
+/// The patterns of all ignore files known so far, to tell if paths are ignored.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Stack {
+    /// The directory of each file, like `dir/sub`, along with its patterns, ordered by precedence.
+    files: Vec<(BString, Vec<Pattern>)>,
+}
+
+impl Stack {
+    /// Create a stack with the patterns of `core.excludesFile` and `info/exclude` of the repository at `git_dir`.
+    ///
+    /// If `core.excludesFile` isn't set, `$XDG_CONFIG_HOME/git/ignore` or `~/.config/git/ignore` is used like git does.
+    /// Files which can't be read are ignored.
+    pub fn from_git_dir(git_dir: impl AsRef<Path>) -> Self {
+        let git_dir = git_dir.as_ref();
+        let common_dir = linked::common_dir(git_dir).unwrap_or_else(|_| git_dir.to_owned());
+        let config = git_config::File::at(common_dir.join("config")).unwrap_or_default();
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let excludes_file = match config
+            .value("core", None, "excludesFile")
+            .and_then(|path| path.to_path().ok())
+        {
+            Some(path) => match path.strip_prefix("~") {
+                Ok(relative) => home.map(|home| home.join(relative)),
+                Err(_) => Some(path.to_owned()),
+            },
+            None => std::env::var_os("XDG_CONFIG_HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| home.map(|home| home.join(".config")))
+                .map(|dir| dir.join("git").join("ignore")),
+        };
+
+        let mut stack = Stack::default();
+        for path in excludes_file
+            .into_iter()
+            .chain(Some(common_dir.join("info").join("exclude")))
+        {
+            if let Ok(data) = fs::read(path) {
+                stack.add("".into(), &data);
+            }
+        }
+        stack
+    }
+
+    /// Add the contents of the `.gitignore` file in `directory`, which is relative to the root of the worktree and
+    /// empty for the root itself. Of files in the same directory, the one added last takes precedence.
+    pub fn add(&mut self, directory: &BStr, data: &[u8]) -> &mut Self {
+        let directory = directory.trim_end_with(|c| c == '/');
+        let pos = self
+            .files
+            .iter()
+            .position(|(existing, _)| existing.len() > directory.len())
+            .unwrap_or(self.files.len());
+        self.files.insert(pos, (directory.into(), parse(data).collect()));
+        self
+    }
+
+    /// Return true if there are no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.files.iter().all(|(_, patterns)| patterns.is_empty())
+    }
+
+    /// Return true if the file or directory at `path`, which is relative to the root of the worktree, is ignored by
+    /// its own patterns or those of one of its parent directories.
+    pub fn is_ignored(&self, path: &BStr, is_directory: bool) -> bool {
+        let mut end = 0;
+        while let Some(pos) = path[end..].find_byte(b'/') {
+            end += pos;
+            if self.matches(path[..end].as_bstr(), true) {
+                return true;
+            }
+            end += 1;
+        }
+        self.matches(path, is_directory)
+    }
+
+    /// Return true if the patterns ignore `path` itself, assuming its parent directories aren't ignored.
+    pub(crate) fn matches(&self, path: &BStr, is_directory: bool) -> bool {
+        let mut ignored = false;
+        for (directory, patterns) in &self.files {
+            let relative = if directory.is_empty() {
+                path
+            } else if path.starts_with(directory) && path.get(directory.len()) == Some(&b'/') {
+                path[directory.len() + 1..].as_bstr()
+            } else {
+                continue;
+            };
+            if let Some(pattern) = patterns
+                .iter()
+                .rev()
+                .find(|p| (is_directory || !p.directory_only) && attributes::pattern_matches(p.text.as_ref(), relative))
+            {
+                ignored = !pattern.negative;
+            }
+        }
+        ignored
+    }
+}
@@ -0,0 +1,420 @@
+//! Read the index, the file listing the contents of the next commit along with the metadata the files had in the
+//! worktree when they were last added, as written by git.
+//!
+//! Versions 2 to 4 of the format are supported, including the path compression of version 4. Optional extensions, like
+//! the cached trees or the untracked cache, are skipped, while split indices and sparse indices whose entries may be
+//! directories aren't supported as their entries alone don't describe the next commit. Indices are
+//! written without extensions in the version they were read in, or in version 3 if entries need extended flags.
+use git_object::{bstr::BString, owned, TreeMode, SHA1_SIZE};
+use quick_error::quick_error;
+use std::{
+    convert::TryInto,
+    fs, io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not read the index at '{}'", path.display())
+            source(err)
+        }
+        Corrupt(message: &'static str) {
+            display("The index is corrupt: {}", message)
+        }
+        UnsupportedVersion(version: u32) {
+            display("The index version {} is not supported", version)
+        }
+        Checksum {
+            display("The checksum of the index doesn't match its content")
+        }
+        Sparse {
+            display("Sparse indices with directory entries aren't supported")
+        }
+        Split {
+            display("Split indices, as written by 'git update-index --split-index', aren't supported")
+        }
+        UnknownExtension(signature: BString) {
+            display("The index requires the extension '{}', which isn't supported", signature)
+        }
+    }
+}
+
+const SIGNATURE: &[u8] = b"DIRC";
+
+/// A point in time with nanosecond precision, as stored for entries.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timestamp {
+    /// The seconds since the unix epoch.
+    pub secs: u32,
+    /// The nanoseconds within the second.
+    pub nsecs: u32,
+}
+
+/// The metadata of a file in the worktree, truncated to 32 bits like git does, to quickly tell if it changed.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stat {
+    /// The time the metadata of the file changed.
+    pub ctime: Timestamp,
+    /// The time the content of the file changed.
+    pub mtime: Timestamp,
+    /// The device the file is on.
+    pub dev: u32,
+    /// The inode of the file.
+    pub ino: u32,
+    /// The id of the user owning the file.
+    pub uid: u32,
+    /// The id of the group owning the file.
+    pub gid: u32,
+    /// The size of the file in bytes.
+    pub size: u32,
+}
+
+impl Stat {
+    /// Obtain the metadata git stores from `metadata`, leaving what the platform doesn't provide at zero.
+    pub fn from_metadata(metadata: &fs::Metadata) -> Self {
+        let timestamp = |time: io::Result<std::time::SystemTime>| {
+            time.ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| Timestamp {
+                    secs: duration.as_secs() as u32,
+                    nsecs: duration.subsec_nanos(),
+                })
+                .unwrap_or_default()
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            Stat {
+                ctime: Timestamp {
+                    secs: metadata.ctime() as u32,
+                    nsecs: metadata.ctime_nsec() as u32,
+                },
+                mtime: timestamp(metadata.modified()),
+                dev: metadata.dev() as u32,
+                ino: metadata.ino() as u32,
+                uid: metadata.uid(),
+                gid: metadata.gid(),
+                size: metadata.len() as u32,
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            Stat {
+                ctime: timestamp(metadata.created()),
+                mtime: timestamp(metadata.modified()),
+                size: metadata.len() as u32,
+                ..Default::default()
+            }
+        }
+    }
+
+    /// Return true if the file with this metadata is unchanged compared to when `other` was recorded, judging by the
+    /// same fields git compares by default, which are all but the device.
+    pub fn matches(&self, other: &Stat) -> bool {
+        self.mtime == other.mtime
+            && self.ctime == other.ctime
+            && self.ino == other.ino
+            && self.uid == other.uid
+            && self.gid == other.gid
+            && self.size == other.size
+    }
+}
+
+/// A file of the index.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    /// The metadata of the file when it was added.
+    pub stat: Stat,
+    /// The kind of file.
+    pub mode: TreeMode,
+    /// The blob with the content of the file, or the commit of a submodule.
+    pub id: owned::Id,
+    /// `0` for files without conflicts, or the stage of one side of a conflict: `1` for the merge base, `2` for our
+    /// side and `3` for theirs.
+    pub stage: u8,
+    /// If true, the file is assumed to be unchanged in the worktree, as set by `git update-index --assume-unchanged`.
+    pub assume_valid: bool,
+    /// If true, the file isn't checked out as it's outside of the sparse checkout, and is assumed to be unchanged.
+    pub skip_worktree: bool,
+    /// If true, the file was added with `git add -N` and is considered new in the worktree, but not part of the next
+    /// commit yet.
+    pub intent_to_add: bool,
+    /// The path of the file relative to the root of the worktree, with slashes as separators.
+    pub path: BString,
+}
+
+/// The contents of an index file.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct File {
+    /// The version of the format the file was written in.
+    pub version: u32,
+    /// The entries ordered by path and stage.
+    pub entries: Vec<Entry>,
+    /// The time the index file was last changed, if known, as entries changed at or after that time might have been
+    /// changed again without their metadata changing.
+    pub mtime: Option<Timestamp>,
+}
+
+impl File {
+    /// Read the index file at `path`, or return `None` if it doesn't exist as nothing was added yet.
+    pub fn at(path: impl AsRef<Path>) -> Result<Option<Self>, Error> {
+        let path = path.as_ref();
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::Io(err, path.to_owned())),
+        };
+        let mut file = Self::from_bytes(&data)?;
+        file.mtime = fs::metadata(path)
+            .map(|metadata| Stat::from_metadata(&metadata).mtime)
+            .ok();
+        Ok(Some(file))
+    }
+
+    /// Parse the index file in `data`, whose modification time is unknown.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < 12 + SHA1_SIZE || &data[..4] != SIGNATURE {
+            return Err(Error::Corrupt("the header is missing"));
+        }
+        let (content, checksum) = data.split_at(data.len() - SHA1_SIZE);
+        if checksum != [0; SHA1_SIZE] && git_features::hash::sha1(content) != checksum {
+            return Err(Error::Checksum);
+        }
+        let version = be_u32(&content[4..]);
+        if !(2..=4).contains(&version) {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        let num_entries = be_u32(&content[8..]) as usize;
+
+        // The amount of entries isn't trusted until they were read.
+        let mut entries = Vec::with_capacity(num_entries.min(content.len() / ENTRY_HEADER_SIZE));
+        let mut data = &content[12..];
+        let mut previous_path = BString::default();
+        for _ in 0..num_entries {
+            let (entry, rest) = parse_entry(data, version, &previous_path)?;
+            previous_path = entry.path.clone();
+            entries.push(entry);
+            data = rest;
+        }
+        check_extensions(data)?;
+        Ok(File {
+            version,
+            entries,
+            mtime: None,
+        })
+    }
+
+    /// Write the entries, which must be ordered by path and stage, as index file to `out`.
+    pub fn write_to(&self, mut out: impl io::Write) -> io::Result<()> {
+        let needs_extended_flags = self.entries.iter().any(|e| e.skip_worktree || e.intent_to_add);
+        let version: u32 = match self.version {
+            4 => 4,
+            _ if needs_extended_flags => 3,
+            3 => 3,
+            _ => 2,
+        };
+        let mut data = Vec::with_capacity(12 + self.entries.len() * (ENTRY_HEADER_SIZE + 16) + SHA1_SIZE);
+        data.extend_from_slice(SIGNATURE);
+        data.extend_from_slice(&version.to_be_bytes());
+        data.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+        let mut previous_path: &[u8] = &[];
+        for entry in &self.entries {
+            write_entry(&mut data, entry, version, previous_path);
+            previous_path = &entry.path;
+        }
+        let checksum = git_features::hash::sha1(&data);
+        data.extend_from_slice(&checksum);
+        out.write_all(&data)
+    }
+}
+
+/// The size of the fixed part of an entry, from its timestamps to its flags.
+const ENTRY_HEADER_SIZE: usize = 40 + SHA1_SIZE + 2;
+
+fn parse_entry<'a>(data: &'a [u8], version: u32, previous_path: &[u8]) -> Result<(Entry, &'a [u8]), Error> {
+    let truncated = || Error::Corrupt("an entry is truncated");
+    if data.len() < ENTRY_HEADER_SIZE {
+        return Err(truncated());
+    }
+    let word = |index: usize| be_u32(&data[index * 4..]);
+    let timestamp = |index: usize| Timestamp {
+        secs: word(index),
+        nsecs: word(index + 1),
+    };
+    let mode = match word(6) {
+        0o100644 | 0o100664 => TreeMode::Blob,
+        0o100755 => TreeMode::BlobExecutable,
+        0o120000 => TreeMode::Link,
+        0o160000 => TreeMode::Commit,
+        0o040000 => return Err(Error::Sparse),
+        _ => return Err(Error::Corrupt("an entry has an invalid mode")),
+    };
+    let stat = Stat {
+        ctime: timestamp(0),
+        mtime: timestamp(2),
+        dev: word(4),
+        ino: word(5),
+        uid: word(7),
+        gid: word(8),
+        size: word(9),
+    };
+    let id = owned::Id::from_20_bytes(&data[40..40 + SHA1_SIZE]);
+    let flags = be_u16(&data[40 + SHA1_SIZE..]);
+    let mut offset = ENTRY_HEADER_SIZE;
+    let extended_flags = if flags & 0x4000 != 0 {
+        if version < 3 {
+            return Err(Error::Corrupt("an entry has extended flags in a version 2 index"));
+        }
+        let extended = be_u16(data.get(offset..offset + 2).ok_or_else(truncated)?);
+        offset += 2;
+        extended
+    } else {
+        0
+    };
+
+    let (path, rest) = if version < 4 {
+        let len = data[offset..].iter().position(|b| *b == 0).ok_or_else(truncated)?;
+        let path = BString::from(&data[offset..offset + len]);
+        // Entries are padded with one to eight NUL bytes to a multiple of eight bytes.
+        let end = (offset + len + 8) & !7;
+        (path, data.get(end..).ok_or_else(truncated)?)
+    } else {
+        let (strip, consumed) = varint(&data[offset..]).ok_or_else(truncated)?;
+        offset += consumed;
+        let len = data[offset..].iter().position(|b| *b == 0).ok_or_else(truncated)?;
+        let kept = previous_path
+            .len()
+            .checked_sub(strip)
+            .ok_or(Error::Corrupt("a path removes more than the previous path has"))?;
+        let mut path = BString::from(&previous_path[..kept]);
+        path.extend_from_slice(&data[offset..offset + len]);
+        (path, &data[offset + len + 1..])
+    };
+    Ok((
+        Entry {
+            stat,
+            mode,
+            id,
+            stage: ((flags >> 12) & 0b11) as u8,
+            assume_valid: flags & 0x8000 != 0,
+            skip_worktree: extended_flags & 0x4000 != 0,
+            intent_to_add: extended_flags & 0x2000 != 0,
+            path,
+        },
+        rest,
+    ))
+}
+
+/// Return an error if one of the extensions in `data`, which follow the entries, changes their meaning.
+///
+/// Like git, extensions whose signature starts with an uppercase letter are optional and skipped.
+fn check_extensions(mut data: &[u8]) -> Result<(), Error> {
+    while !data.is_empty() {
+        if data.len() < 8 {
+            return Err(Error::Corrupt("an extension is truncated"));
+        }
+        let signature = &data[..4];
+        let size = be_u32(&data[4..]) as usize;
+        data = data
+            .get(8..)
+            .and_then(|data| data.get(size..))
+            .ok_or(Error::Corrupt("an extension is truncated"))?;
+        match signature {
+            b"link" => return Err(Error::Split),
+            b"sdir" => return Err(Error::Sparse),
+            _ if signature[0].is_ascii_uppercase() => {}
+            _ => return Err(Error::UnknownExtension(signature.into())),
+        }
+    }
+    Ok(())
+}
+
+fn write_entry(out: &mut Vec<u8>, entry: &Entry, version: u32, previous_path: &[u8]) {
+    let start = out.len();
+    let stat = &entry.stat;
+    for word in &[
+        stat.ctime.secs,
+        stat.ctime.nsecs,
+        stat.mtime.secs,
+        stat.mtime.nsecs,
+        stat.dev,
+        stat.ino,
+        entry.mode as u32,
+        stat.uid,
+        stat.gid,
+        stat.size,
+    ] {
+        out.extend_from_slice(&word.to_be_bytes());
+    }
+    out.extend_from_slice(entry.id.as_slice());
+    let extended_flags: u16 =
+        if entry.skip_worktree { 0x4000 } else { 0 } | if entry.intent_to_add { 0x2000 } else { 0 };
+    let flags: u16 = if entry.assume_valid { 0x8000 } else { 0 }
+        | if extended_flags != 0 { 0x4000 } else { 0 }
+        | (u16::from(entry.stage) & 0b11) << 12
+        | entry.path.len().min(0xfff) as u16;
+    out.extend_from_slice(&flags.to_be_bytes());
+    if extended_flags != 0 {
+        out.extend_from_slice(&extended_flags.to_be_bytes());
+    }
+
+    if version < 4 {
+        out.extend_from_slice(&entry.path);
+        let end = start + ((out.len() - start + 8) & !7);
+        out.resize(end, 0);
+    } else {
+        let common = previous_path
+            .iter()
+            .zip(entry.path.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        write_varint(out, previous_path.len() - common);
+        out.extend_from_slice(&entry.path[common..]);
+        out.push(0);
+    }
+}
+
+/// Encode `value` as variable length integer the way [`varint()`] decodes it.
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    let mut buf = [0u8; 16];
+    let mut pos = buf.len() - 1;
+    buf[pos] = (value & 0x7f) as u8;
+    value >>= 7;
+    while value != 0 {
+        value -= 1;
+        pos -= 1;
+        buf[pos] = 0x80 | (value & 0x7f) as u8;
+        value >>= 7;
+    }
+    out.extend_from_slice(&buf[pos..]);
+}
+
+/// Decode the variable length integer at the start of `data`, in the encoding git uses for offsets in packs, and
+/// return it along with the amount of bytes it occupied.
+fn varint(data: &[u8]) -> Option<(usize, usize)> {
+    let mut bytes = data.iter();
+    let mut byte = *bytes.next()?;
+    let mut value = (byte & 0x7f) as usize;
+    let mut consumed = 1;
+    while byte & 0x80 != 0 {
+        byte = *bytes.next()?;
+        value = ((value + 1) << 7) | (byte & 0x7f) as usize;
+        consumed += 1;
+    }
+    Some((value, consumed))
+}
+
+fn be_u32(data: &[u8]) -> u32 {
+    u32::from_be_bytes(data[..4].try_into().expect("four bytes"))
+}
+
+fn be_u16(data: &[u8]) -> u16 {
+    u16::from_be_bytes(data[..2].try_into().expect("two bytes"))
+}
@@ -24,9 +24,31 @@ quick_error! {
             display("Cold not create directory at '{}'", path.display())
             source(err)
         }
+        NotADirectory(path: PathBuf) {
+            display("Cannot initialize a repository at '{}' as it is not a directory", path.display())
+        }
+        DirectoryNotEmpty(path: PathBuf) {
+            display("Refusing to initialize a bare repository in the non-empty directory '{}'", path.display())
+        }
+        InvalidBranchName(name: String, err: git_ref::name::Error) {
+            display("'{}' is not a valid name for the initial branch", name)
+            source(err)
+        }
     }
 }
 
+/// Configure how to initialize a repository.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+pub struct Options {
+    /// If true, the repository has no worktree and the given directory is the git directory itself, like
+    /// `git init --bare` does it.
+    pub bare: bool,
+    /// The name of the branch `HEAD` points to, like `main`, or `None` to use `main`.
+    pub initial_branch: Option<String>,
+    /// The kind of hash used to name objects.
+    pub object_hash: git_object::HashKind,
+}
+
 const GIT_DIR_NAME: &str = ".git";
 
 const TPL_INFO_EXCLUDE: &[u8] = include_bytes!("./assets/baseline-init/info/exclude");
@@ -95,11 +117,49 @@ fn create_dir(p: &Path) -> Result<(), Error> {
 }
 
 pub fn repository() -> Result<(), Error> {
-    let mut cursor = PathBuf::from(GIT_DIR_NAME);
-    if cursor.is_dir() {
-        return Err(Error::DirectoryExists(cursor));
+    repository_at("").map(|_| ())
+}
+
+/// Create a new repository in `directory`, which is created if it doesn't exist, and return the path to its git directory.
+pub fn repository_at(directory: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    repository_with_options(directory, Options::default())
+}
+
+/// Create a new repository in `directory` configured by `options` and return the path to its git directory.
+///
+/// The `directory` is created if it doesn't exist. Bare repositories are placed into `directory` itself, which
+/// must be empty, while the git directory of other repositories is placed into it and must not exist yet.
+pub fn repository_with_options(directory: impl AsRef<Path>, options: Options) -> Result<PathBuf, Error> {
+    let directory = directory.as_ref();
+    let initial_branch = options.initial_branch.as_deref().unwrap_or("main");
+    let head_ref = format!("refs/heads/{}", initial_branch);
+    git_ref::name::validate(head_ref.as_bytes().into())
+        .map_err(|err| Error::InvalidBranchName(initial_branch.to_owned(), err))?;
+
+    if directory.exists() && !directory.is_dir() {
+        return Err(Error::NotADirectory(directory.to_owned()));
     }
-    create_dir(&cursor)?;
+    if !directory.as_os_str().is_empty() && !directory.is_dir() {
+        fs::create_dir_all(directory).map_err(|e| Error::CreateDirectory(e, directory.to_owned()))?;
+    }
+    let mut cursor = if options.bare {
+        let is_empty = directory
+            .read_dir()
+            .map_err(|e| Error::IoOpen(e, directory.to_owned()))?
+            .next()
+            .is_none();
+        if !is_empty {
+            return Err(Error::DirectoryNotEmpty(directory.to_owned()));
+        }
+        directory.to_owned()
+    } else {
+        let git_dir = directory.join(GIT_DIR_NAME);
+        if git_dir.is_dir() {
+            return Err(Error::DirectoryExists(git_dir));
+        }
+        create_dir(&git_dir)?;
+        git_dir
+    };
 
     {
         let mut cursor = NewDir(&mut cursor).at("info")?;
@@ -138,13 +198,28 @@ pub fn repository() -> Result<(), Error> {
         create_dir(PathCursor(cursor.as_mut()).at("tags"))?;
     }
 
+    let head = match options.initial_branch {
+        Some(_) => format!("ref: {}\n", head_ref).into_bytes(),
+        None => TPL_HEAD.to_vec(),
+    };
+    let capabilities =
+        crate::worktree::Capabilities::probe(&cursor).map_err(|e| Error::IoWrite(e, cursor.to_owned()))?;
+    let mut config = TPL_CONFIG.to_vec();
+    config.extend_from_slice(capabilities.to_config().as_bytes());
+    if options.bare {
+        config.extend_from_slice(b"\tbare = true\n");
+    }
+    match options.object_hash {
+        // SHA1 is the default and needs no `extensions.objectFormat`.
+        git_object::HashKind::Sha1 => {}
+    }
     for (tpl, filename) in &[
-        (TPL_HEAD, "HEAD"),
+        (head.as_slice(), "HEAD"),
         (TPL_DESCRIPTION, "description"),
-        (TPL_CONFIG, "config"),
+        (config.as_slice(), "config"),
     ] {
         write_file(tpl, PathCursor(&mut cursor).at(filename))?;
     }
 
-    Ok(())
+    Ok(cursor)
 }
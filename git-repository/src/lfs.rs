@@ -0,0 +1,155 @@
+//! Recognize [git-lfs](https://git-lfs.github.com) pointer files and turn them into the content they point to.
+//!
+//! Files with the `filter=lfs` attribute are stored as small pointer blobs naming the object by its SHA-256 hash and
+//! size. During checkout these are passed to an LFS [client][Client], which prints the actual content, just like
+//! git does with the `filter.lfs.smudge` program.
+use git_object::bstr::{BStr, BString, ByteSlice, ByteVec};
+use quick_error::quick_error;
+use std::{
+    ffi::OsString,
+    io::{self, Write},
+    process::{self, ExitStatus, Stdio},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("The LFS client could not be run")
+            from()
+            source(err)
+        }
+        Failed(status: ExitStatus, stderr: BString) {
+            display("The LFS client failed with {}: {}", status, stderr)
+        }
+    }
+}
+
+/// The version line every pointer starts with.
+pub const VERSION: &str = "https://git-lfs.github.com/spec/v1";
+
+/// Blobs of this size or larger are never pointers.
+pub const MAX_POINTER_SIZE: usize = 1024;
+
+/// A parsed pointer file.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Pointer {
+    /// The hash of the content, like `sha256:4d7a…`.
+    pub oid: BString,
+    /// The size of the content in bytes.
+    pub size: u64,
+}
+
+impl Pointer {
+    /// Parse `data` as pointer, returning `None` if it isn't one.
+    ///
+    /// Like git-lfs, the version has to come first, all other keys have to be sorted and each line has to end with a
+    /// newline. Unknown keys, like those of extensions, are skipped.
+    pub fn from_bytes(data: &[u8]) -> Option<Pointer> {
+        if data.len() >= MAX_POINTER_SIZE || !data.ends_with(b"\n") {
+            return None;
+        }
+        let mut lines = data[..data.len() - 1].split(|b| *b == b'\n').map(|line| {
+            let pos = line.find_byte(b' ')?;
+            let key = &line[..pos];
+            let valid_key = !key.is_empty()
+                && key
+                    .iter()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || *b == b'.' || *b == b'-');
+            if valid_key {
+                Some((key, &line[pos + 1..]))
+            } else {
+                None
+            }
+        });
+        match lines.next()?? {
+            (b"version", version) if version == VERSION.as_bytes() => {}
+            _ => return None,
+        }
+        let (mut oid, mut size) = (None, None);
+        let mut previous_key: &[u8] = b"";
+        for line in lines {
+            let (key, value) = line?;
+            if key <= previous_key {
+                return None;
+            }
+            previous_key = key;
+            match key {
+                b"oid" => {
+                    let hash = value.strip_prefix(b"sha256:")?;
+                    if hash.len() != 64 || !hash.iter().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(b)) {
+                        return None;
+                    }
+                    oid = Some(value.into());
+                }
+                b"size" => {
+                    if value.is_empty() || !value.iter().all(u8::is_ascii_digit) {
+                        return None;
+                    }
+                    size = Some(value.to_str().ok()?.parse().ok()?);
+                }
+                _ => {}
+            }
+        }
+        Some(Pointer { oid: oid?, size: size? })
+    }
+
+    /// Serialize this pointer the way git-lfs writes it.
+    pub fn to_bstring(&self) -> BString {
+        let mut out = BString::from(format!("version {}\noid ", VERSION));
+        out.push_str(&self.oid);
+        out.push_str(format!("\nsize {}\n", self.size));
+        out
+    }
+}
+
+/// Return true if `data` is an LFS pointer.
+pub fn is_pointer(data: &[u8]) -> bool {
+    Pointer::from_bytes(data).is_some()
+}
+
+/// The program turning pointers into the content they point to.
+///
+/// It receives the pointer on standard input and the path of the file as last argument, and prints the content.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Client {
+    /// The program to run.
+    pub program: OsString,
+    /// The arguments to pass before the path of the file.
+    pub args: Vec<OsString>,
+}
+
+impl Default for Client {
+    /// Use `git-lfs smudge`, which is what `git lfs install` configures.
+    fn default() -> Self {
+        Client {
+            program: "git-lfs".into(),
+            args: vec!["smudge".into(), "--".into()],
+        }
+    }
+}
+
+impl Client {
+    /// Return the content the `pointer` of the file at `path` points to.
+    pub fn smudge(&self, pointer: &[u8], path: &BStr) -> Result<Vec<u8>, Error> {
+        let mut child = process::Command::new(&self.program)
+            .args(&self.args)
+            .arg(path.to_os_str_lossy())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // Pointers are small enough to never fill the pipe.
+            stdin.write_all(pointer)?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(Error::Failed(
+                output.status,
+                output.stderr.trim_end_with(|c| c == '\n').into(),
+            ));
+        }
+        Ok(output.stdout)
+    }
+}
@@ -1,3 +1,94 @@
 #![forbid(unsafe_code)]
 
 pub mod init;
+
+pub mod apply;
+
+pub mod attributes;
+
+pub mod bisect;
+
+pub mod blame;
+
+pub mod bundle;
+
+pub mod clone;
+#[doc(inline)]
+pub use clone::clone;
+
+pub mod commit;
+
+pub mod commit_graph;
+
+pub mod date;
+
+pub mod dirwalk;
+
+pub mod discover;
+
+pub mod dumb;
+
+pub mod fast_export;
+pub mod fast_import;
+
+pub mod format_patch;
+
+pub mod hooks;
+
+pub mod ignore;
+
+pub mod index;
+
+pub mod lfs;
+
+pub mod mailmap;
+
+pub mod merge;
+
+pub mod describe;
+#[doc(inline)]
+pub use describe::describe;
+
+pub mod pretty;
+
+pub mod push;
+#[doc(inline)]
+pub use push::push;
+
+pub mod rebase;
+
+pub mod reflog;
+
+pub mod remote;
+
+pub mod revision;
+
+pub mod revwalk;
+
+pub mod sequencer;
+
+pub mod shallow;
+
+pub mod sparse;
+
+pub mod stash;
+
+pub mod status;
+#[doc(inline)]
+pub use status::status;
+
+pub mod submodule;
+
+pub mod tag;
+
+pub mod transfer;
+
+pub mod upload_pack;
+#[doc(inline)]
+pub use upload_pack::upload_pack;
+
+pub mod receive_pack;
+#[doc(inline)]
+pub use receive_pack::receive_pack;
+
+pub mod worktree;
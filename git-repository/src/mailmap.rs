@@ -0,0 +1,186 @@
+//! Parse `.mailmap` files and use them to map the names and emails in commits to the canonical identity of their
+//! author, like `git log --use-mailmap` and `git blame` do.
+//!
+//! Each line maps an identity in one of four forms:
+//!
+//! * `Proper Name <commit@email>` changes the name of everyone using `commit@email`.
+//! * `<proper@email> <commit@email>` changes the email `commit@email`.
+//! * `Proper Name <proper@email> <commit@email>` changes both the name and email of everyone using `commit@email`.
+//! * `Proper Name <proper@email> Commit Name <commit@email>` does the same, but only if the name is `Commit Name`, too.
+//!
+//! Like git, names and emails are matched case-insensitively, and text after the last email, like comments, is ignored.
+use git_object::{
+    bstr::{BStr, BString, ByteSlice},
+    owned,
+};
+use quick_error::quick_error;
+use std::collections::BTreeMap;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Malformed(line_number: usize, line: BString) {
+            display("Line {} is not a mailmap entry as it lacks an email in angle brackets: '{}'", line_number, line)
+        }
+    }
+}
+
+/// A single line of a `.mailmap` file.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Entry {
+    /// The name to use instead, or `None` to keep the name.
+    pub new_name: Option<BString>,
+    /// The email to use instead, or `None` to keep the email.
+    pub new_email: Option<BString>,
+    /// The name to match in addition to the email, or `None` to map everyone using `old_email`.
+    pub old_name: Option<BString>,
+    /// The email to match.
+    pub old_email: BString,
+}
+
+/// Parse the lines of a `.mailmap` file into entries, skipping empty lines and comments.
+pub fn parse(data: &[u8]) -> impl Iterator<Item = Result<Entry, Error>> + '_ {
+    data.lines().enumerate().filter_map(|(index, line)| {
+        let trimmed = trim(line);
+        if trimmed.is_empty() || trimmed.starts_with(b"#") {
+            return None;
+        }
+        Some(parse_line(line).ok_or_else(|| Error::Malformed(index + 1, line.into())))
+    })
+}
+
+fn parse_line(line: &[u8]) -> Option<Entry> {
+    let (name, email, rest) = name_and_email(line)?;
+    Some(match name_and_email(rest) {
+        Some((old_name, old_email, _)) => Entry {
+            new_name: name,
+            new_email: Some(email),
+            old_name,
+            old_email,
+        },
+        None => Entry {
+            new_name: name,
+            new_email: None,
+            old_name: None,
+            old_email: email,
+        },
+    })
+}
+
+/// Parse `Name <email>` with an optional name, returning what follows the email as well.
+fn name_and_email(data: &[u8]) -> Option<(Option<BString>, BString, &[u8])> {
+    let start = data.find_byte(b'<')?;
+    let end = start + 1 + data[start + 1..].find_byte(b'>')?;
+    let name = trim(&data[..start]);
+    Some((
+        if name.is_empty() { None } else { Some(name.into()) },
+        data[start + 1..end].into(),
+        &data[end + 1..],
+    ))
+}
+
+/// What to change in an identity.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+struct Mapping {
+    name: Option<BString>,
+    email: Option<BString>,
+}
+
+/// The mappings of one email.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+struct EmailEntry {
+    mapping: Mapping,
+    /// Mappings which only apply to particular names, keyed by the lower-cased name.
+    by_name: BTreeMap<BString, Mapping>,
+}
+
+/// All entries of one or more `.mailmap` files, ready to map identities.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Snapshot {
+    /// Keyed by the lower-cased email.
+    by_email: BTreeMap<BString, EmailEntry>,
+}
+
+impl Snapshot {
+    /// Create a snapshot from the contents of a `.mailmap` file, ignoring malformed lines like git does.
+    pub fn from_bytes(data: &[u8]) -> Self {
+        let mut snapshot = Snapshot::default();
+        snapshot.merge(parse(data).filter_map(Result::ok));
+        snapshot
+    }
+
+    /// Add `entries`, which take precedence over existing ones mapping the same identity, like the entries of later lines
+    /// or files do in git.
+    pub fn merge(&mut self, entries: impl IntoIterator<Item = Entry>) -> &mut Self {
+        for entry in entries {
+            let email_entry = self.by_email.entry(lowercase(entry.old_email.as_ref())).or_default();
+            let mapping = match entry.old_name {
+                Some(old_name) => email_entry.by_name.entry(lowercase(old_name.as_ref())).or_default(),
+                None => &mut email_entry.mapping,
+            };
+            if entry.new_name.is_some() {
+                mapping.name = entry.new_name;
+            }
+            if entry.new_email.is_some() {
+                mapping.email = entry.new_email;
+            }
+        }
+        self
+    }
+
+    /// Return the canonical identity of `signature`, or `None` if it isn't changed by any entry.
+    pub fn try_resolve(&self, signature: &owned::Signature) -> Option<owned::Signature> {
+        let (name, email) = self.try_resolve_identity(signature.name.as_ref(), signature.email.as_ref())?;
+        Some(owned::Signature {
+            name,
+            email,
+            time: signature.time,
+        })
+    }
+
+    /// Return the canonical identity of `signature`, which is a copy of it if it isn't changed by any entry.
+    pub fn resolve(&self, signature: &owned::Signature) -> owned::Signature {
+        self.try_resolve(signature).unwrap_or_else(|| signature.clone())
+    }
+
+    /// Return the canonical `(name, email)` of the identity with `name` and `email`, or `None` if it isn't changed.
+    pub fn try_resolve_identity(&self, name: &BStr, email: &BStr) -> Option<(BString, BString)> {
+        let email_entry = self.by_email.get(&lowercase(email))?;
+        let mapping = email_entry
+            .by_name
+            .get(&lowercase(name))
+            .unwrap_or(&email_entry.mapping);
+        if mapping.name.is_none() && mapping.email.is_none() {
+            return None;
+        }
+        Some((
+            mapping.name.clone().unwrap_or_else(|| name.to_owned()),
+            mapping.email.clone().unwrap_or_else(|| email.to_owned()),
+        ))
+    }
+
+    /// Return true if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.by_email.is_empty()
+    }
+}
+
+fn trim(mut data: &[u8]) -> &[u8] {
+    while let Some((first, rest)) = data.split_first() {
+        if !first.is_ascii_whitespace() {
+            break;
+        }
+        data = rest;
+    }
+    while let Some((last, rest)) = data.split_last() {
+        if !last.is_ascii_whitespace() {
+            break;
+        }
+        data = rest;
+    }
+    data
+}
+
+fn lowercase(data: &BStr) -> BString {
+    data.to_ascii_lowercase().into()
+}
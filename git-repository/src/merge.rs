@@ -0,0 +1,364 @@
+//! Merge trees and the files in them three-way, like the default strategy of `git merge` does, but without detecting
+//! renames.
+//!
+//! Changes made on only one side are taken as they are. Files changed on both sides are merged line by line, and
+//! overlapping or adjacent changes to the same lines conflict, in which case both versions are written into the file
+//! between conflict markers. Changes which can't be merged by content, like a file which was modified on one side and
+//! deleted on the other, also conflict and keep the version of the side which didn't delete it, or of our side.
+use crate::blame;
+use git_object::{
+    borrowed,
+    bstr::{BStr, BString, ByteSlice},
+    owned, HashKind, Kind, TreeMode,
+};
+use git_odb::{loose, Write};
+use quick_error::quick_error;
+use std::collections::BTreeMap;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Find(id: owned::Id) {
+            display("Object {} could not be found", id)
+        }
+        NotATree(id: owned::Id) {
+            display("Object {} was expected to be a tree", id)
+        }
+        NotABlob(id: owned::Id) {
+            display("Object {} was expected to be a blob", id)
+        }
+        WriteObject(err: loose::db::write::Error) {
+            display("A merged object could not be written")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// The names of both sides, as shown after the conflict markers.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy)]
+pub struct Labels<'a> {
+    /// The name of our side, like `HEAD`.
+    pub ours: &'a BStr,
+    /// The name of their side, like the name of the branch to merge.
+    pub theirs: &'a BStr,
+}
+
+/// The mode and object of an entry in a tree.
+pub type Entry = (TreeMode, owned::Id);
+
+/// How the changes of both sides conflict.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum ConflictKind {
+    /// A file modified on both sides has conflict markers.
+    Content,
+    /// A file added on both sides has conflict markers.
+    AddAdd,
+    /// A file was modified on one side and deleted on the other.
+    ModifyDelete,
+    /// The entries of both sides can't be merged, like a file and a directory, or two different symlinks.
+    Unmergeable,
+}
+
+/// A path whose changes conflict.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Conflict {
+    /// The path relative to the root of the tree.
+    pub path: BString,
+    /// How the changes conflict.
+    pub kind: ConflictKind,
+    /// The entry in the merge base, if there was one.
+    pub base: Option<Entry>,
+    /// The entry on our side, if there is one.
+    pub ours: Option<Entry>,
+    /// The entry on their side, if there is one.
+    pub theirs: Option<Entry>,
+}
+
+/// The result of [`trees()`].
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+pub struct Outcome {
+    /// The merged tree, which contains the conflict markers of conflicting files.
+    pub tree: owned::Id,
+    /// The conflicting paths, ordered by path.
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Merge the lines of `ours` and `theirs`, both of which were changed from `base`, and return the merged data along
+/// with the amount of conflicts in it.
+///
+/// Like git, the lines both sides have in common at the start or end of a conflict are moved out of it.
+pub fn lines(base: &[u8], ours: &[u8], theirs: &[u8], labels: Labels<'_>) -> (Vec<u8>, usize) {
+    let (base, ours, theirs) = (split_lines(base), split_lines(ours), split_lines(theirs));
+    let to_usize = |blocks: Vec<(u32, u32, u32)>| {
+        blocks
+            .into_iter()
+            .map(|(a, b, len)| (a as usize, b as usize, len as usize))
+            .collect::<Vec<_>>()
+    };
+    let with_ours = to_usize(blame::matching_blocks(&base, &ours));
+    let with_theirs = to_usize(blame::matching_blocks(&base, &theirs));
+
+    // The runs of base lines which are unchanged on both sides, as `(base_start, ours_start, theirs_start, len)`.
+    let mut unchanged = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < with_ours.len() && j < with_theirs.len() {
+        let (base_a, ours_start, len_a) = with_ours[i];
+        let (base_b, theirs_start, len_b) = with_theirs[j];
+        let start = base_a.max(base_b);
+        let end = (base_a + len_a).min(base_b + len_b);
+        if start < end {
+            unchanged.push((
+                start,
+                ours_start + start - base_a,
+                theirs_start + start - base_b,
+                end - start,
+            ));
+        }
+        if base_a + len_a < base_b + len_b {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    unchanged.push((base.len(), ours.len(), theirs.len(), 0));
+
+    let mut out = Vec::new();
+    let mut conflicts = 0;
+    let (mut base_pos, mut ours_pos, mut theirs_pos) = (0, 0, 0);
+    for (base_start, ours_start, theirs_start, len) in unchanged {
+        let changed_base = &base[base_pos..base_start];
+        let changed_ours = &ours[ours_pos..ours_start];
+        let changed_theirs = &theirs[theirs_pos..theirs_start];
+        if changed_ours == changed_theirs || changed_base == changed_theirs {
+            out.extend(changed_ours.iter().copied().flatten());
+        } else if changed_base == changed_ours {
+            out.extend(changed_theirs.iter().copied().flatten());
+        } else {
+            conflicts += 1;
+            let prefix = changed_ours
+                .iter()
+                .zip(changed_theirs)
+                .take_while(|(a, b)| a == b)
+                .count();
+            let suffix = changed_ours[prefix..]
+                .iter()
+                .rev()
+                .zip(changed_theirs[prefix..].iter().rev())
+                .take_while(|(a, b)| a == b)
+                .count();
+            out.extend(changed_ours[..prefix].iter().copied().flatten());
+            write_marker(&mut out, b"<<<<<<<", labels.ours);
+            write_lines(&mut out, &changed_ours[prefix..changed_ours.len() - suffix]);
+            write_marker(&mut out, b"=======", b"".as_bstr());
+            write_lines(&mut out, &changed_theirs[prefix..changed_theirs.len() - suffix]);
+            write_marker(&mut out, b">>>>>>>", labels.theirs);
+            out.extend(changed_ours[changed_ours.len() - suffix..].iter().copied().flatten());
+        }
+        out.extend(ours[ours_start..ours_start + len].iter().copied().flatten());
+        base_pos = base_start + len;
+        ours_pos = ours_start + len;
+        theirs_pos = theirs_start + len;
+    }
+    (out, conflicts)
+}
+
+fn split_lines(data: &[u8]) -> Vec<&[u8]> {
+    data.lines_with_terminator().collect()
+}
+
+/// Write `lines` so that a conflict marker can follow, adding a newline after the last one if it's missing.
+fn write_lines(out: &mut Vec<u8>, lines: &[&[u8]]) {
+    out.extend(lines.iter().copied().flatten());
+    if !lines.is_empty() && !out.ends_with(b"\n") {
+        out.push(b'\n');
+    }
+}
+
+fn write_marker(out: &mut Vec<u8>, marker: &[u8], label: &BStr) {
+    out.extend_from_slice(marker);
+    if !label.is_empty() {
+        out.push(b' ');
+        out.extend_from_slice(label);
+    }
+    out.push(b'\n');
+}
+
+/// Merge the trees `ours` and `theirs` with `base` as their merge base, or as if they had nothing in common if `None`,
+/// and write the merged tree and files into `db`.
+///
+/// Objects are looked up using `find`, which places their data into the provided buffer and returns the decoded object.
+/// Conflicts aren't errors but are written into the merged tree as well, and returned along with it.
+pub fn trees<Find>(
+    base: Option<owned::Id>,
+    ours: owned::Id,
+    theirs: owned::Id,
+    find: Find,
+    db: &loose::Db,
+    labels: Labels<'_>,
+) -> Result<Outcome, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let mut merge = Merge {
+        find,
+        db,
+        labels,
+        buf: Vec::new(),
+        conflicts: Vec::new(),
+    };
+    let tree = match merge.tree([base, Some(ours), Some(theirs)], b"".as_bstr())? {
+        Some(tree) => tree,
+        None => db.write(
+            &owned::Object::Tree(owned::Tree { entries: Vec::new() }),
+            HashKind::Sha1,
+        )?,
+    };
+    Ok(Outcome {
+        tree,
+        conflicts: merge.conflicts,
+    })
+}
+
+struct Merge<'a, Find> {
+    find: Find,
+    db: &'a loose::Db,
+    labels: Labels<'a>,
+    buf: Vec<u8>,
+    conflicts: Vec<Conflict>,
+}
+
+impl<'a, Find> Merge<'a, Find>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    /// Merge the `[base, ours, theirs]` trees at `prefix`, which may not exist, and write the result unless it's empty.
+    fn tree(&mut self, trees: [Option<owned::Id>; 3], prefix: &BStr) -> Result<Option<owned::Id>, Error> {
+        let mut entries = BTreeMap::<BString, [Option<Entry>; 3]>::new();
+        for (side, tree) in trees.iter().enumerate() {
+            let tree = match tree {
+                Some(tree) => *tree,
+                None => continue,
+            };
+            match (self.find)(tree.to_borrowed(), &mut self.buf).ok_or(Error::Find(tree))? {
+                borrowed::Object::Tree(tree) => {
+                    for entry in tree.entries {
+                        entries.entry(entry.filename.to_owned()).or_default()[side] =
+                            Some((entry.mode, owned::Id::from_borrowed_sha1(entry.oid.sha1())));
+                    }
+                }
+                _ => return Err(Error::NotATree(tree)),
+            }
+        }
+
+        let mut merged = Vec::with_capacity(entries.len());
+        for (name, [base, ours, theirs]) in entries {
+            let entry = if ours == theirs || base == theirs {
+                ours
+            } else if base == ours {
+                theirs
+            } else {
+                let mut path = BString::from(prefix.as_bytes());
+                if !path.is_empty() {
+                    path.push(b'/');
+                }
+                path.extend_from_slice(&name);
+                self.entry(path, base, ours, theirs)?
+            };
+            if let Some((mode, oid)) = entry {
+                merged.push(owned::tree::Entry {
+                    mode,
+                    filename: name,
+                    oid,
+                });
+            }
+        }
+        if merged.is_empty() {
+            return Ok(None);
+        }
+        // Git sorts trees as if their names ended with a slash.
+        merged.sort_by_key(sort_key);
+        Ok(Some(self.db.write(
+            &owned::Object::Tree(owned::Tree { entries: merged }),
+            HashKind::Sha1,
+        )?))
+    }
+
+    /// Merge the entries at `path`, which were changed on both sides in different ways.
+    fn entry(
+        &mut self,
+        path: BString,
+        base: Option<Entry>,
+        ours: Option<Entry>,
+        theirs: Option<Entry>,
+    ) -> Result<Option<Entry>, Error> {
+        let is_tree = |entry: Option<Entry>| matches!(entry, None | Some((TreeMode::Tree, _)));
+        if is_tree(base) && is_tree(ours) && is_tree(theirs) {
+            let tree = |entry: Option<Entry>| entry.map(|(_, id)| id);
+            return Ok(self
+                .tree([tree(base), tree(ours), tree(theirs)], path.as_bstr())?
+                .map(|id| (TreeMode::Tree, id)));
+        }
+
+        let mut conflict = Conflict {
+            path,
+            kind: ConflictKind::Unmergeable,
+            base,
+            ours,
+            theirs,
+        };
+        let is_file = |mode: TreeMode| matches!(mode, TreeMode::Blob | TreeMode::BlobExecutable);
+        let entry = match (ours, theirs) {
+            (Some((ours_mode, ours_id)), Some((theirs_mode, theirs_id)))
+                if is_file(ours_mode) && is_file(theirs_mode) =>
+            {
+                let base = base.filter(|(mode, _)| is_file(*mode));
+                let mode = match base {
+                    Some((base_mode, _)) if base_mode == ours_mode => theirs_mode,
+                    _ => ours_mode,
+                };
+                let base_data = match base {
+                    Some((_, id)) => self.blob(id)?,
+                    None => Vec::new(),
+                };
+                let ours_data = self.blob(ours_id)?;
+                let theirs_data = self.blob(theirs_id)?;
+                let (data, conflicts) = lines(&base_data, &ours_data, &theirs_data, self.labels);
+                let id = self.db.write_buf(Kind::Blob, &data, HashKind::Sha1)?;
+                let modes_conflict = base.is_none() && ours_mode != theirs_mode;
+                if conflicts == 0 && !modes_conflict {
+                    return Ok(Some((mode, id)));
+                }
+                conflict.kind = if base.is_some() {
+                    ConflictKind::Content
+                } else {
+                    ConflictKind::AddAdd
+                };
+                (mode, id)
+            }
+            (Some(ours), Some(_)) => ours,
+            (Some(remaining), None) | (None, Some(remaining)) => {
+                conflict.kind = ConflictKind::ModifyDelete;
+                remaining
+            }
+            (None, None) => unreachable!("entries deleted on both sides are equal"),
+        };
+        self.conflicts.push(conflict);
+        Ok(Some(entry))
+    }
+
+    fn blob(&mut self, id: owned::Id) -> Result<Vec<u8>, Error> {
+        match (self.find)(id.to_borrowed(), &mut self.buf).ok_or(Error::Find(id))? {
+            borrowed::Object::Blob(blob) => Ok(blob.data.to_owned()),
+            _ => Err(Error::NotABlob(id)),
+        }
+    }
+}
+
+pub(crate) fn sort_key(entry: &owned::tree::Entry) -> Vec<u8> {
+    let mut key = entry.filename.to_vec();
+    if entry.mode == TreeMode::Tree {
+        key.push(b'/');
+    }
+    key
+}
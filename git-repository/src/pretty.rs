@@ -0,0 +1,210 @@
+//! Show commits like `git log` does, in the builtin `oneline` and `medium` formats or in custom formats with
+//! placeholders, like `--format=%h %s`.
+//!
+//! The supported placeholders are `%H`, `%h`, `%T`, `%t`, `%P` and `%p` for the ids of the commit, its tree and its
+//! parents, `%an`, `%ae`, `%aN`, `%aE`, `%ad`, `%aD`, `%ai`, `%aI` and `%at` for its author and the same with `c` for
+//! its committer, `%s`, `%b` and `%B` for its message, as well as `%n` and `%%`. Unknown placeholders are kept as they
+//! are. Like with git, the uppercase names and emails are mapped with a mailmap, and `%ad` and `%cd` use the date
+//! format chosen by the caller. Unlike git, abbreviated ids always have seven hex digits, even if they aren't unique.
+use crate::{date, mailmap};
+use git_object::{
+    borrowed,
+    bstr::{BString, ByteSlice},
+    owned,
+};
+
+/// The information about a commit which can be shown.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Commit {
+    /// The id of the commit.
+    pub id: owned::Id,
+    /// The id of its tree.
+    pub tree: owned::Id,
+    /// The ids of its parents.
+    pub parents: Vec<owned::Id>,
+    /// Its author.
+    pub author: owned::Signature,
+    /// Its committer.
+    pub committer: owned::Signature,
+    /// Its message.
+    pub message: BString,
+}
+
+impl Commit {
+    /// Copy the information of `commit` with `id`.
+    pub fn from_borrowed(id: owned::Id, commit: &borrowed::Commit<'_>) -> Self {
+        let signature = |s: &borrowed::Signature<'_>| owned::Signature {
+            name: s.name.to_owned(),
+            email: s.email.to_owned(),
+            time: s.time,
+        };
+        Commit {
+            id,
+            tree: commit.tree(),
+            parents: commit.parents().collect(),
+            author: signature(&commit.author),
+            committer: signature(&commit.committer),
+            message: commit.message.to_owned(),
+        }
+    }
+
+    /// The first paragraph of the message with its lines joined by spaces, as shown by `%s`.
+    pub fn subject(&self) -> BString {
+        let lines: Vec<&[u8]> = self
+            .paragraphs()
+            .0
+            .lines()
+            .map(|l| {
+                l.trim_start_with(char::is_whitespace)
+                    .trim_end_with(char::is_whitespace)
+            })
+            .collect();
+        lines.join(&b' ').into()
+    }
+
+    /// The message without its first paragraph, as shown by `%b`.
+    pub fn body(&self) -> &[u8] {
+        self.paragraphs().1
+    }
+
+    /// Split the message into its first paragraph and the rest, skipping the blank lines before each like git does.
+    fn paragraphs(&self) -> (&[u8], &[u8]) {
+        let message = skip_blank_lines(self.message.as_slice());
+        let mut end = 0;
+        for line in message.lines_with_terminator() {
+            if is_blank(line) {
+                return (&message[..end], skip_blank_lines(&message[end..]));
+            }
+            end += line.len();
+        }
+        (message, b"")
+    }
+}
+
+/// Write `commit` to `out` like `git log --oneline`, without the trailing newline.
+pub fn oneline(commit: &Commit, out: &mut Vec<u8>) {
+    out.extend_from_slice(&short(commit.id));
+    out.push(b' ');
+    out.extend_from_slice(&commit.subject());
+}
+
+/// Write `commit` to `out` like `git log` does by default, with the identity of its author mapped by `mailmap` and
+/// their date in the `date` format.
+///
+/// Like with git, the message is indented by four spaces with tabs expanded, and the output ends with a single
+/// newline. Separate commits with an empty line.
+pub fn medium(commit: &Commit, mailmap: &mailmap::Snapshot, date: date::Format, out: &mut Vec<u8>) {
+    let start = out.len();
+    out.extend_from_slice(b"commit ");
+    out.extend_from_slice(&commit.id.to_sha1_hex());
+    out.push(b'\n');
+    if commit.parents.len() > 1 {
+        out.extend_from_slice(b"Merge:");
+        for parent in &commit.parents {
+            out.push(b' ');
+            out.extend_from_slice(&short(*parent));
+        }
+        out.push(b'\n');
+    }
+    let author = mailmap.resolve(&commit.author);
+    out.extend_from_slice(b"Author: ");
+    out.extend_from_slice(&author.name);
+    out.extend_from_slice(b" <");
+    out.extend_from_slice(&author.email);
+    out.extend_from_slice(b">\nDate:   ");
+    out.extend_from_slice(date::format(author.time, date).as_bytes());
+    out.extend_from_slice(b"\n\n");
+    for line in commit.message.lines().skip_while(|line| is_blank(line)) {
+        out.extend_from_slice(b"    ");
+        expand_tabs(line, out);
+        out.push(b'\n');
+    }
+    let trimmed = start + out[start..].trim_end_with(|c| c.is_whitespace()).len();
+    out.truncate(trimmed);
+    out.push(b'\n');
+}
+
+/// Expand the placeholders of `format` for `commit` like `git log --format` does and write the result to `out`,
+/// keeping unknown placeholders as they are.
+pub fn expand(format: &[u8], commit: &Commit, mailmap: &mailmap::Snapshot, date: date::Format, out: &mut Vec<u8>) {
+    let mut format = format;
+    let subject = commit.subject();
+    let (author, committer) = (mailmap.resolve(&commit.author), mailmap.resolve(&commit.committer));
+    while let Some(pos) = format.find_byte(b'%') {
+        out.extend_from_slice(&format[..pos]);
+        format = &format[pos + 1..];
+        let (expansion, consumed): (Vec<u8>, usize) = match format {
+            [b'H', ..] => (commit.id.to_sha1_hex().to_vec(), 1),
+            [b'h', ..] => (short(commit.id), 1),
+            [b'T', ..] => (commit.tree.to_sha1_hex().to_vec(), 1),
+            [b't', ..] => (short(commit.tree), 1),
+            [b'P', ..] => (join(commit.parents.iter().map(|id| id.to_sha1_hex().to_vec())), 1),
+            [b'p', ..] => (join(commit.parents.iter().copied().map(short)), 1),
+            [who @ b'a', placeholder, ..] | [who @ b'c', placeholder, ..] => {
+                let (signature, mapped) = match who {
+                    b'a' => (&commit.author, &author),
+                    _ => (&commit.committer, &committer),
+                };
+                let time = |format| date::format(signature.time, format).into_bytes();
+                match placeholder {
+                    b'n' => (signature.name.to_vec(), 2),
+                    b'e' => (signature.email.to_vec(), 2),
+                    b'N' => (mapped.name.to_vec(), 2),
+                    b'E' => (mapped.email.to_vec(), 2),
+                    b'd' => (time(date), 2),
+                    b'D' => (time(date::Format::Rfc2822), 2),
+                    b'i' => (time(date::Format::Iso8601), 2),
+                    b'I' => (time(date::Format::Iso8601Strict), 2),
+                    b't' => (time(date::Format::Unix), 2),
+                    _ => (b"%".to_vec(), 0),
+                }
+            }
+            [b's', ..] => (subject.to_vec(), 1),
+            [b'b', ..] => (commit.body().to_vec(), 1),
+            [b'B', ..] => (commit.message.to_vec(), 1),
+            [b'n', ..] => (b"\n".to_vec(), 1),
+            [b'%', ..] => (b"%".to_vec(), 1),
+            _ => (b"%".to_vec(), 0),
+        };
+        out.extend_from_slice(&expansion);
+        format = &format[consumed..];
+    }
+    out.extend_from_slice(format);
+}
+
+fn short(id: owned::Id) -> Vec<u8> {
+    id.to_sha1_hex()[..7].to_vec()
+}
+
+fn join(items: impl Iterator<Item = Vec<u8>>) -> Vec<u8> {
+    items.collect::<Vec<_>>().join(&b' ')
+}
+
+fn is_blank(line: &[u8]) -> bool {
+    line.iter().all(u8::is_ascii_whitespace)
+}
+
+fn skip_blank_lines(mut text: &[u8]) -> &[u8] {
+    while let Some(line) = text.lines_with_terminator().next().filter(|line| is_blank(line)) {
+        text = &text[line.len()..];
+    }
+    text
+}
+
+/// Write `line` to `out` with its tabs replaced by spaces up to the next multiple of eight characters.
+fn expand_tabs(line: &[u8], out: &mut Vec<u8>) {
+    let mut column = 0;
+    for chunk in line.split_inclusive(|b| *b == b'\t') {
+        let (text, tab) = match chunk.split_last() {
+            Some((b'\t', text)) => (text, true),
+            _ => (chunk, false),
+        };
+        out.extend_from_slice(text);
+        column += text.chars().count();
+        if tab {
+            let width = 8 - column % 8;
+            out.resize(out.len() + width, b' ');
+            column += width;
+        }
+    }
+}
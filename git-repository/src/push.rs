@@ -0,0 +1,624 @@
+//! Update refs of a remote repository, sending it all objects reachable from the new ref values that it doesn't have.
+//!
+//! Updates which are not fast-forwards are refused before contacting the server unless they are forced or the remote
+//! ref has the value expected by their [lease][Lease], like `git push` does. [Atomic][Options::atomic] pushes send no
+//! update at all if one of them is refused.
+//!
+//! Like `git rev-list <new values> --not <advertised refs>`, the commits to send are found by walking the history of the
+//! new values newest first, marking the advertised commits we have and all of their ancestors as uninteresting, until
+//! only uninteresting commits are left to walk. Thus only the history which differs is walked, assuming commit times
+//! increase along the history. All objects reachable from the commits to send are sent except for those in the trees
+//! of the boundary, the uninteresting parents of the commits to send. These trees are known to the server, so unless it
+//! advertises `no-thin`, trees and blobs are sent as deltas against the objects at the same path in the boundary trees
+//! where that is smaller.
+use crate::remote::{
+    update::{self as remote_update, is_ancestor},
+    Remote,
+};
+use git_features::progress::{self, Progress};
+use git_object::{
+    borrowed,
+    bstr::{BStr, BString, ByteVec},
+    owned, Kind, TreeMode,
+};
+use git_odb::{pack, traverse};
+use git_protocol::{handshake, handshake::Ref, push, RemoteProgress};
+use git_transport::{client, Service};
+use quick_error::quick_error;
+use std::{
+    collections::{BinaryHeap, HashMap, HashSet},
+    io,
+    path::Path,
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Transport(err: client::Error) {
+            display("Could not connect to the remote")
+            from()
+            source(err)
+        }
+        Handshake(err: handshake::Error) {
+            display("The server response could not be understood")
+            from()
+            source(err)
+        }
+        Push(err: push::Error) {
+            display("The ref updates could not be sent")
+            from()
+            source(err)
+        }
+        Traverse(err: traverse::commit_and_objects::Error) {
+            display("The objects to send could not be determined")
+            from()
+            source(err)
+        }
+        TraverseTree(err: traverse::tree::Error) {
+            display("The trees of the boundary could not be traversed")
+            from()
+            source(err)
+        }
+        WritePack(err: pack::data::write::Error) {
+            display("The pack could not be written")
+            from()
+            source(err)
+        }
+        ObjectMissing(id: owned::Id) {
+            display("The object {} needed to check for a fast-forward could not be found", id)
+        }
+        CommitMissing(id: owned::Id) {
+            display("The commit {} to send could not be found", id)
+        }
+        AtomicUnsupported {
+            display("The remote does not support atomic pushes")
+        }
+        PushOptionsUnsupported {
+            display("The remote does not support push options")
+        }
+    }
+}
+
+/// A ref of the remote to set to a local object.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Update {
+    /// The object to set the remote ref to, or `None` to delete it.
+    pub source: Option<owned::Id>,
+    /// The full name of the ref on the remote, like `refs/heads/main`.
+    pub destination: BString,
+    /// If true, the remote ref may be set even if it's not a fast-forward.
+    pub force: bool,
+    /// If set, the remote ref is only set if it has the expected value, but then even if it's not a fast-forward.
+    pub lease: Option<Lease>,
+}
+
+/// The value a remote ref must have to be updated, like `git push --force-with-lease` demands.
+///
+/// The expected value is sent as the old value of the ref, so the server refuses the update as well if the ref
+/// changed in the meantime.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub struct Lease {
+    /// The object the remote ref must point to, or `None` if it must not exist.
+    pub expected: Option<owned::Id>,
+}
+
+impl Lease {
+    /// Expect the remote ref `destination` to point to the object its remote-tracking ref in the repository at
+    /// `git_dir` points to, as mapped by the fetch refspecs of `remote`.
+    ///
+    /// The remote ref must not exist if there is no remote-tracking ref, like `git push --force-with-lease` without
+    /// an explicit value expects.
+    pub fn from_tracking_ref(
+        git_dir: impl AsRef<Path>,
+        remote: &Remote,
+        destination: &BStr,
+    ) -> Result<Self, remote_update::Error> {
+        let tracking_ref = remote
+            .fetch_mappings(Some(destination))
+            .into_iter()
+            .find_map(|mapping| mapping.local);
+        let expected = match tracking_ref {
+            Some(name) => remote_update::resolve(git_dir.as_ref(), name.as_ref())?.1,
+            None => None,
+        };
+        Ok(Lease { expected })
+    }
+}
+
+/// Configure how to push.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+pub struct Options {
+    /// If true, the server must apply all updates or none of them, like `git push --atomic`, and no update is sent if
+    /// one of them is rejected locally.
+    pub atomic: bool,
+    /// Strings to pass to the hooks of the server, like `git push --push-option`.
+    pub push_options: Vec<BString>,
+}
+
+/// The reason for not sending an [`Update`] to the server.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum Rejection {
+    /// The remote ref points to an object we don't have, so it must be fetched first to know if the update is a
+    /// fast-forward.
+    FetchFirst,
+    /// The local object doesn't contain the object the remote ref points to in its history.
+    NonFastForward,
+    /// The remote tag exists already and may only be changed by force.
+    AlreadyExists,
+    /// The ref to delete doesn't exist on the remote.
+    MissingRemoteRef,
+    /// The remote ref doesn't have the value expected by the [`Lease`] of the update, so our information about it
+    /// is stale.
+    StaleInfo,
+    /// The push is atomic and another update was rejected.
+    AtomicPushFailed,
+}
+
+/// The result of [`push()`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Outcome {
+    /// The commands sent to the server.
+    pub commands: Vec<push::Command>,
+    /// The names of the remote refs which already point to the desired object.
+    pub up_to_date: Vec<BString>,
+    /// The remote refs which were not sent to the server as the update was refused locally.
+    pub rejected: Vec<(BString, Rejection)>,
+    /// Information about the sent pack, or `None` if no pack was sent.
+    pub pack: Option<pack::data::write::Outcome>,
+    /// The status report of the server.
+    pub report: push::Report,
+}
+
+/// Apply `updates` to the refs of the remote behind `transport` as configured with `options`, reporting progress to
+/// `progress`.
+///
+/// `find` is used to lookup objects, placing their data into the provided buffer and returning it along with its kind.
+pub fn push<T, Find, P>(
+    transport: &mut T,
+    updates: &[Update],
+    mut find: Find,
+    mut progress: P,
+    options: Options,
+) -> Result<Outcome, Error>
+where
+    T: client::Transport + ?Sized,
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<pack::Object<'b>>,
+    P: Progress,
+{
+    progress.init(Some(3), progress::steps());
+    progress.info("connecting");
+    progress.inc();
+    let outcome = handshake::parse(transport.handshake(Service::ReceivePack)?)?;
+    let remote_refs = outcome.refs.clone().unwrap_or_default();
+    let mut arguments = push::Arguments::new(&outcome);
+    let unsupported = if options.atomic && !arguments.atomic() {
+        Some(Error::AtomicUnsupported)
+    } else if !options
+        .push_options
+        .into_iter()
+        .all(|option| arguments.push_option(option))
+    {
+        Some(Error::PushOptionsUnsupported)
+    } else {
+        None
+    };
+    if let Some(err) = unsupported {
+        transport.close()?;
+        return Err(err);
+    }
+
+    let mut buf = Vec::new();
+    let mut commands = Vec::new();
+    let mut up_to_date = Vec::new();
+    let mut rejected = Vec::new();
+    for update in updates {
+        let old = remote_refs
+            .iter()
+            .find_map(|r| match r {
+                Ref::Peeled { path, tag, .. } if *path == update.destination => Some(*tag),
+                Ref::Direct { path, object } | Ref::Symbolic { path, object, .. } if *path == update.destination => {
+                    Some(*object)
+                }
+                _ => None,
+            })
+            .unwrap_or_else(owned::Id::null_sha1);
+        let new = update.source.unwrap_or_else(owned::Id::null_sha1);
+        if old == new {
+            if new == owned::Id::null_sha1() {
+                rejected.push((update.destination.clone(), Rejection::MissingRemoteRef));
+            } else {
+                up_to_date.push(update.destination.clone());
+            }
+            continue;
+        }
+        if let Some(lease) = update.lease {
+            if lease.expected.unwrap_or_else(owned::Id::null_sha1) != old {
+                rejected.push((update.destination.clone(), Rejection::StaleInfo));
+                continue;
+            }
+        } else if old != owned::Id::null_sha1() && update.source.is_some() && !update.force {
+            let rejection = if update.destination.starts_with(b"refs/tags/") {
+                Some(Rejection::AlreadyExists)
+            } else if find(old.to_borrowed(), &mut buf).is_none() {
+                Some(Rejection::FetchFirst)
+            } else if is_ancestor(&mut |id, buf| decoded(&mut find, id, buf), &mut buf, old, new)
+                .map_err(Error::ObjectMissing)?
+            {
+                None
+            } else {
+                Some(Rejection::NonFastForward)
+            };
+            if let Some(rejection) = rejection {
+                rejected.push((update.destination.clone(), rejection));
+                continue;
+            }
+        }
+        commands.push(push::Command {
+            old,
+            new,
+            name: update.destination.clone(),
+        });
+    }
+    if options.atomic && !rejected.is_empty() {
+        rejected.extend(
+            commands
+                .drain(..)
+                .map(|command| (command.name, Rejection::AtomicPushFailed)),
+        );
+    }
+
+    progress.info("counting objects");
+    progress.inc();
+    let tips: Vec<_> = commands.iter().filter(|c| !c.is_delete()).map(|c| c.new).collect();
+    let objects = if tips.is_empty() {
+        Vec::new()
+    } else {
+        let known_to_remote: Vec<_> = remote_refs.iter().filter_map(|r| r.unpack().1.copied()).collect();
+        objects_to_send(&mut find, tips, known_to_remote, arguments.can_send_thin_pack())?
+    };
+
+    progress.info("sending pack");
+    progress.inc();
+    let mut pack = None;
+    let mut pack_error = None;
+    let pack_progress = progress.add_child("pack");
+    let mut remote_progress = progress.add_child("remote");
+    let report = push::push(
+        transport,
+        &arguments,
+        &commands,
+        |is_err, text| RemoteProgress::translate_to_progress(is_err, text, &mut remote_progress),
+        |out| match pack::data::File::write_thin_objects_to_stream(&objects, &mut find, pack_progress, out) {
+            Ok(outcome) => {
+                pack = Some(outcome);
+                Ok(())
+            }
+            Err(err) => {
+                pack_error = Some(err);
+                Err(io::Error::other("the pack could not be written"))
+            }
+        },
+    );
+    if let Some(err) = pack_error {
+        return Err(err.into());
+    }
+    let report = report?;
+    transport.close()?;
+
+    Ok(Outcome {
+        commands,
+        up_to_date,
+        rejected,
+        pack,
+        report,
+    })
+}
+
+/// Return the objects reachable from `tips` which aren't reachable from the objects `known` to the remote, along with the
+/// object to try to store them as delta against if `thin` is true, as described in the [module documentation][self].
+fn objects_to_send<Find>(
+    find: &mut Find,
+    tips: Vec<owned::Id>,
+    known: Vec<owned::Id>,
+    thin: bool,
+) -> Result<Vec<(owned::Id, Option<owned::Id>)>, Error>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<pack::Object<'a>>,
+{
+    let Known {
+        seen,
+        walk,
+        boundary_paths,
+    } = known_objects(find, &tips, known)?;
+    let ids = traverse::commit_and_objects(tips, |id, buf| decoded(find, id, buf))
+        .with_seen(seen)
+        .map(|entry| entry.map(|e| e.id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let bases = if thin && !boundary_paths.is_empty() {
+        let to_send: HashSet<_> = ids.iter().copied().collect();
+        delta_bases(find, &walk.interesting, &to_send, &boundary_paths)
+    } else {
+        HashMap::new()
+    };
+    Ok(ids.into_iter().map(|id| (id, bases.get(&id).copied())).collect())
+}
+
+/// The result of [`known_objects()`].
+pub(crate) struct Known {
+    /// The objects reachable from the `known` objects, as far as they were looked at: the known objects themselves and the
+    /// tags leading to commits, all uninteresting commits and all objects in the trees of the boundary commits.
+    pub seen: HashSet<owned::Id>,
+    /// The commits reachable from `tips` and not from `known`.
+    pub walk: Difference,
+    /// The objects in the trees of the boundary commits by path, starting with the empty path of the root trees.
+    pub boundary_paths: HashMap<BString, (owned::Id, Kind)>,
+}
+
+/// Walk the commits reachable from `tips` but not from the objects `known` to be present, and return the objects these
+/// are known to be built upon.
+pub(crate) fn known_objects<Find>(find: &mut Find, tips: &[owned::Id], known: Vec<owned::Id>) -> Result<Known, Error>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<pack::Object<'a>>,
+{
+    let mut buf = Vec::new();
+    let mut seen = HashSet::new();
+    let mut known_commits = Vec::new();
+    for id in known {
+        let mut id = Some(id);
+        while let Some(current) = id.take() {
+            if !seen.insert(current) {
+                break;
+            }
+            match decoded(find, current.to_borrowed(), &mut buf) {
+                Some(borrowed::Object::Tag(tag)) => id = Some(tag.target()),
+                Some(borrowed::Object::Commit(_)) => known_commits.push(current),
+                _ => {}
+            }
+        }
+    }
+    let mut tip_commits = Vec::new();
+    for mut id in tips.iter().copied() {
+        loop {
+            match decoded(find, id.to_borrowed(), &mut buf) {
+                Some(borrowed::Object::Tag(tag)) => id = tag.target(),
+                Some(borrowed::Object::Commit(_)) => {
+                    tip_commits.push(id);
+                    break;
+                }
+                _ => break,
+            }
+        }
+    }
+
+    let walk = walk_difference(find, tip_commits, known_commits)?;
+    seen.extend(walk.uninteresting.iter().copied());
+    let mut boundary_paths = HashMap::new();
+    for commit in &walk.boundary {
+        let tree = match decoded(find, commit.to_borrowed(), &mut buf) {
+            Some(borrowed::Object::Commit(commit)) => commit.tree(),
+            _ => continue,
+        };
+        seen.insert(tree);
+        boundary_paths.entry(BString::default()).or_insert((tree, Kind::Tree));
+        let options = traverse::tree::Options {
+            trees: true,
+            ..Default::default()
+        };
+        for entry in traverse::tree(tree, |id, buf| decoded(find, id, buf), options) {
+            let entry = entry?;
+            seen.insert(entry.id);
+            if let Some(kind) = kind_of(entry.mode) {
+                boundary_paths.entry(entry.path).or_insert((entry.id, kind));
+            }
+        }
+    }
+    Ok(Known {
+        seen,
+        walk,
+        boundary_paths,
+    })
+}
+
+/// The result of [`walk_difference()`].
+pub(crate) struct Difference {
+    /// The commits to send, in the order they were walked.
+    interesting: Vec<owned::Id>,
+    /// All commits found to be reachable from the known commits.
+    uninteresting: HashSet<owned::Id>,
+    /// The uninteresting parents of the commits to send.
+    boundary: Vec<owned::Id>,
+}
+
+/// Walk the commits reachable from `tips` by commit time, newest first, until all commits left to walk are reachable
+/// from the `known` commits, like `git rev-list` does.
+///
+/// Commits found to be reachable from `known` are marked uninteresting along with all of their ancestors walked so far.
+/// Uninteresting commits which can't be found are skipped, as the remote has them anyway.
+fn walk_difference<Find>(find: &mut Find, tips: Vec<owned::Id>, known: Vec<owned::Id>) -> Result<Difference, Error>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<pack::Object<'a>>,
+{
+    let mut walk = DifferenceWalk {
+        find,
+        buf: Vec::new(),
+        parents: HashMap::new(),
+        walked: HashSet::new(),
+        uninteresting: HashSet::new(),
+        queue: BinaryHeap::new(),
+        interesting_in_queue: 0,
+    };
+    for id in known {
+        walk.uninteresting.insert(id);
+        walk.queue(id)?;
+    }
+    for id in tips {
+        walk.queue(id)?;
+    }
+
+    let mut walked_interesting = Vec::new();
+    while walk.interesting_in_queue > 0 {
+        let (_, id) = walk.queue.pop().expect("interesting commits in the queue");
+        walk.walked.insert(id);
+        let parents = walk.parents[&id].clone();
+        if walk.uninteresting.contains(&id) {
+            for parent in &parents {
+                walk.mark_uninteresting(*parent);
+            }
+        } else {
+            walk.interesting_in_queue -= 1;
+            walked_interesting.push(id);
+        }
+        for parent in parents {
+            walk.queue(parent)?;
+        }
+    }
+
+    let interesting: Vec<_> = walked_interesting
+        .into_iter()
+        .filter(|id| !walk.uninteresting.contains(id))
+        .collect();
+    let mut boundary = Vec::new();
+    for id in &interesting {
+        for parent in &walk.parents[id] {
+            if walk.uninteresting.contains(parent) && !boundary.contains(parent) {
+                boundary.push(*parent);
+            }
+        }
+    }
+    Ok(Difference {
+        interesting,
+        uninteresting: walk.uninteresting,
+        boundary,
+    })
+}
+
+/// The state of [`walk_difference()`].
+struct DifferenceWalk<'a, Find> {
+    find: &'a mut Find,
+    buf: Vec<u8>,
+    /// The parents of all queued commits.
+    parents: HashMap<owned::Id, Vec<owned::Id>>,
+    walked: HashSet<owned::Id>,
+    uninteresting: HashSet<owned::Id>,
+    queue: BinaryHeap<(u32, owned::Id)>,
+    /// The amount of commits in the queue which aren't uninteresting, as the walk ends once there are none.
+    interesting_in_queue: usize,
+}
+
+impl<'a, Find> DifferenceWalk<'a, Find>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<pack::Object<'b>>,
+{
+    fn queue(&mut self, id: owned::Id) -> Result<(), Error> {
+        if self.parents.contains_key(&id) {
+            return Ok(());
+        }
+        let (time, parents) = match decoded(self.find, id.to_borrowed(), &mut self.buf) {
+            Some(borrowed::Object::Commit(commit)) => (commit.committer.time.time, commit.parents().collect()),
+            _ if self.uninteresting.contains(&id) => return Ok(()),
+            _ => return Err(Error::CommitMissing(id)),
+        };
+        self.parents.insert(id, parents);
+        if !self.uninteresting.contains(&id) {
+            self.interesting_in_queue += 1;
+        }
+        self.queue.push((time, id));
+        Ok(())
+    }
+
+    /// Mark `id` and all of its ancestors walked so far as uninteresting.
+    fn mark_uninteresting(&mut self, id: owned::Id) {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if !self.uninteresting.insert(id) {
+                continue;
+            }
+            if self.walked.contains(&id) {
+                stack.extend(self.parents[&id].iter().copied());
+            } else if self.parents.contains_key(&id) {
+                self.interesting_in_queue -= 1;
+            }
+        }
+    }
+}
+
+/// Pair the trees and blobs of the `commits` to send with the object of the same kind at the same path in the
+/// `boundary` trees, entering only the trees to send.
+fn delta_bases<Find>(
+    find: &mut Find,
+    commits: &[owned::Id],
+    to_send: &HashSet<owned::Id>,
+    boundary: &HashMap<BString, (owned::Id, Kind)>,
+) -> HashMap<owned::Id, owned::Id>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<pack::Object<'a>>,
+{
+    let mut buf = Vec::new();
+    let mut bases = HashMap::new();
+    let mut stack = Vec::new();
+    for commit in commits {
+        if let Some(borrowed::Object::Commit(commit)) = decoded(find, commit.to_borrowed(), &mut buf) {
+            stack.push((commit.tree(), BString::default()));
+        }
+    }
+    let mut entered = HashSet::new();
+    while let Some((tree, path)) = stack.pop() {
+        if !to_send.contains(&tree) || !entered.insert(tree) {
+            continue;
+        }
+        if let Some((base, Kind::Tree)) = boundary.get(&path) {
+            bases.entry(tree).or_insert(*base);
+        }
+        let entries = match decoded(find, tree.to_borrowed(), &mut buf) {
+            Some(borrowed::Object::Tree(tree)) => tree
+                .entries
+                .iter()
+                .map(|entry| {
+                    (
+                        entry.mode,
+                        owned::Id::from_borrowed_sha1(entry.oid.sha1()),
+                        entry.filename.to_owned(),
+                    )
+                })
+                .collect::<Vec<_>>(),
+            _ => continue,
+        };
+        for (mode, id, name) in entries {
+            let mut entry_path = path.clone();
+            if !entry_path.is_empty() {
+                entry_path.push_byte(b'/');
+            }
+            entry_path.push_str(name);
+            match kind_of(mode) {
+                Some(Kind::Tree) => stack.push((id, entry_path)),
+                Some(Kind::Blob) if to_send.contains(&id) => {
+                    if let Some((base, Kind::Blob)) = boundary.get(&entry_path) {
+                        bases.entry(id).or_insert(*base);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    bases
+}
+
+fn kind_of(mode: TreeMode) -> Option<Kind> {
+    match mode {
+        TreeMode::Tree => Some(Kind::Tree),
+        TreeMode::Blob | TreeMode::BlobExecutable | TreeMode::Link => Some(Kind::Blob),
+        TreeMode::Commit => None,
+    }
+}
+
+pub(crate) fn decoded<'b, Find>(
+    find: &mut Find,
+    id: borrowed::Id<'_>,
+    buf: &'b mut Vec<u8>,
+) -> Option<borrowed::Object<'b>>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<pack::Object<'a>>,
+{
+    let object = find(id, buf)?;
+    borrowed::Object::from_bytes(object.kind, object.data).ok()
+}
@@ -0,0 +1,421 @@
+//! Transplant the commits of a branch onto another commit, like a non-interactive `git rebase` does.
+//!
+//! The commits reachable from the branch but not from the upstream commit are [picked][crate::sequencer] one after
+//! another, oldest first, onto the upstream commit or the one given as [`onto`][Options::onto]. Like git, merge commits
+//! are skipped and commits whose changes are already contained are dropped. The branch is moved once all commits were
+//! picked, so it's left untouched while the rebase is stopped and if it's aborted.
+//!
+//! If picking a commit conflicts, the rebase stops and its state is written into the `rebase-merge` directory of the
+//! git directory like git does, from where it's [resumed][resume()] with the resolved tree of the conflicting commit.
+//! Unlike git, `HEAD` isn't detached while rebasing, and neither the worktree nor the index are changed.
+use crate::{commit, merge, remote::update, revwalk, sequencer};
+use git_object::{
+    borrowed,
+    bstr::{BStr, BString, ByteSlice},
+    owned,
+};
+use quick_error::quick_error;
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Find(id: owned::Id) {
+            display("Object {} could not be found", id)
+        }
+        NotACommit(id: owned::Id) {
+            display("Object {} was expected to be a commit", id)
+        }
+        Unborn(name: BString) {
+            display("The ref '{}' doesn't point to a commit yet", name)
+        }
+        InProgress(path: PathBuf) {
+            display("A rebase is in progress already, as '{}' exists", path.display())
+        }
+        NotInProgress(path: PathBuf) {
+            display("No rebase is in progress, as '{}' doesn't exist", path.display())
+        }
+        Corrupt(path: PathBuf) {
+            display("The rebase state in '{}' could not be parsed", path.display())
+        }
+        RefChanged(name: BString, expected: owned::Id, actual: Option<owned::Id>) {
+            display("The ref '{}' was expected to point to {}, but it points to {}",
+                name,
+                expected,
+                actual.map(|id| id.to_string()).unwrap_or_else(|| "nothing".into()))
+        }
+        Walk(err: revwalk::Error) {
+            display("The commits to rebase could not be found")
+            from()
+            source(err)
+        }
+        Sequencer(err: sequencer::Error) {
+            display("A commit could not be picked")
+            from()
+            source(err)
+        }
+        Commit(err: commit::Error) {
+            display("The commit with the resolved conflicts could not be created")
+            from()
+            source(err)
+        }
+        Ref(err: update::Error) {
+            display("The branch could not be read or updated")
+            from()
+            source(err)
+        }
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not access '{}'", path.display())
+            source(err)
+        }
+    }
+}
+
+/// The directory within the git directory holding the state of a stopped rebase.
+pub const STATE_DIR: &str = "rebase-merge";
+const DETACHED_HEAD: &str = "detached HEAD";
+
+/// Configure how to rebase.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Options {
+    /// The committer of the picked commits.
+    pub committer: owned::Signature,
+    /// The commit to pick the commits onto, or `None` to use the upstream commit, like `git rebase --onto` does.
+    pub onto: Option<owned::Id>,
+}
+
+/// The result of [`start()`] and [`resume()`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Outcome {
+    /// All commits were picked, and the branch was moved from `previous` to `new`.
+    Done {
+        /// The commit the branch pointed to before the rebase.
+        previous: owned::Id,
+        /// The commit the branch points to now.
+        new: owned::Id,
+    },
+    /// Picking `commit` conflicted, and the rebase stopped to be resumed once the conflicts are resolved.
+    Stopped {
+        /// The commit whose changes conflicted.
+        commit: owned::Id,
+        /// The merged tree containing the conflicts.
+        merge: merge::Outcome,
+    },
+}
+
+/// The state of a rebase, as stored in the rebase-merge directory.
+struct State {
+    head_name: BString,
+    onto: owned::Id,
+    orig_head: owned::Id,
+    todo: Vec<(owned::Id, BString)>,
+    done: Vec<(owned::Id, BString)>,
+    rewritten: Vec<(owned::Id, owned::Id)>,
+}
+
+impl State {
+    /// The commit to pick the next commit onto.
+    fn tip(&self) -> owned::Id {
+        self.rewritten.last().map(|(_, new)| *new).unwrap_or(self.onto)
+    }
+
+    fn read(git_dir: &Path) -> Result<Self, Error> {
+        let dir = git_dir.join(STATE_DIR);
+        if !dir.is_dir() {
+            return Err(Error::NotInProgress(dir));
+        }
+        let read = |name: &str| {
+            let path = dir.join(name);
+            match fs::read(&path) {
+                Ok(data) => Ok(data),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+                Err(err) => Err(Error::Io(err, path)),
+            }
+        };
+        let corrupt = |name: &str| Error::Corrupt(dir.join(name));
+        let id = |name: &str| -> Result<owned::Id, Error> {
+            owned::Id::from_40_bytes_in_hex(read(name)?.trim_end_with(char::is_whitespace)).map_err(|_| corrupt(name))
+        };
+        let picks = |name: &str| -> Result<Vec<(owned::Id, BString)>, Error> {
+            read(name)?
+                .lines()
+                .filter(|line| !line.is_empty() && !line.starts_with(b"#"))
+                .map(|line| {
+                    let line = line.strip_prefix(b"pick ").ok_or_else(|| corrupt(name))?;
+                    let id = owned::Id::from_40_bytes_in_hex(line.get(..40).ok_or_else(|| corrupt(name))?)
+                        .map_err(|_| corrupt(name))?;
+                    Ok((id, line.get(41..).unwrap_or_default().into()))
+                })
+                .collect()
+        };
+        let rewritten = read("rewritten-list")?
+            .lines()
+            .map(|line| {
+                let parse = |hex: Option<&[u8]>| hex.and_then(|hex| owned::Id::from_40_bytes_in_hex(hex).ok());
+                match (parse(line.get(..40)), parse(line.get(41..81))) {
+                    (Some(old), Some(new)) => Ok((old, new)),
+                    _ => Err(corrupt("rewritten-list")),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(State {
+            head_name: read("head-name")?.trim_end_with(char::is_whitespace).into(),
+            onto: id("onto")?,
+            orig_head: id("orig-head")?,
+            todo: picks("git-rebase-todo")?,
+            done: picks("done")?,
+            rewritten,
+        })
+    }
+
+    fn write(&self, git_dir: &Path, stopped: owned::Id) -> Result<(), Error> {
+        let dir = git_dir.join(STATE_DIR);
+        fs::create_dir_all(&dir).map_err(|err| Error::Io(err, dir.clone()))?;
+        let hex_line = |id: owned::Id| format!("{}\n", id).into_bytes();
+        let picks = |picks: &[(owned::Id, BString)]| {
+            let mut out = Vec::new();
+            for (id, subject) in picks {
+                out.extend_from_slice(format!("pick {} ", id).as_bytes());
+                out.extend_from_slice(subject);
+                out.push(b'\n');
+            }
+            out
+        };
+        let mut head_name = self.head_name.to_vec();
+        head_name.push(b'\n');
+        for (name, content) in &[
+            ("head-name", head_name),
+            ("onto", hex_line(self.onto)),
+            ("orig-head", hex_line(self.orig_head)),
+            ("git-rebase-todo", picks(&self.todo)),
+            ("done", picks(&self.done)),
+            ("msgnum", format!("{}\n", self.done.len()).into_bytes()),
+            ("end", format!("{}\n", self.done.len() + self.todo.len()).into_bytes()),
+            ("stopped-sha", hex_line(stopped)),
+            (
+                "rewritten-list",
+                self.rewritten
+                    .iter()
+                    .map(|(old, new)| format!("{} {}\n", old, new))
+                    .collect::<String>()
+                    .into_bytes(),
+            ),
+        ] {
+            let path = dir.join(name);
+            fs::write(&path, content).map_err(|err| Error::Io(err, path))?;
+        }
+        Ok(())
+    }
+}
+
+/// Rebase the commits of `branch`, like `HEAD` or `refs/heads/main`, which aren't reachable from `upstream` onto it, or
+/// onto [`onto`][Options::onto], in the repository at `git_dir`, looking up objects using `find`, which places their
+/// data into the provided buffer and returns the decoded object.
+///
+/// Like git, `ORIG_HEAD` is set to the commit the branch pointed to before.
+pub fn start<Find>(
+    git_dir: impl AsRef<Path>,
+    branch: &BStr,
+    upstream: owned::Id,
+    mut find: Find,
+    options: Options,
+) -> Result<Outcome, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let git_dir = git_dir.as_ref();
+    let state_dir = git_dir.join(STATE_DIR);
+    if state_dir.exists() {
+        return Err(Error::InProgress(state_dir));
+    }
+    let (name, orig_head) = match update::resolve(git_dir, branch)? {
+        (name, Some(id)) => (name, id),
+        (name, None) => return Err(Error::Unborn(name)),
+    };
+
+    let upstream_commits = revwalk::walk(Some(upstream), &mut find, revwalk::Options::default())
+        .map(|commit| commit.map(|commit| commit.id))
+        .collect::<Result<HashSet<_>, _>>()?;
+    let mut todo = Vec::new();
+    for commit in revwalk::walk(Some(orig_head), &mut find, revwalk::Options::default()) {
+        let commit = commit?;
+        if commit.parents.len() <= 1 && !upstream_commits.contains(&commit.id) {
+            todo.push(commit.id);
+        }
+    }
+    let mut buf = Vec::new();
+    let todo = todo
+        .into_iter()
+        .rev()
+        .map(|id| Ok((id, subject(&mut find, &mut buf, id)?)))
+        .collect::<Result<_, Error>>()?;
+
+    let path = git_dir.join("ORIG_HEAD");
+    fs::write(&path, format!("{}\n", orig_head)).map_err(|err| Error::Io(err, path))?;
+    let state = State {
+        head_name: if name == "HEAD" { DETACHED_HEAD.into() } else { name },
+        onto: options.onto.unwrap_or(upstream),
+        orig_head,
+        todo,
+        done: Vec::new(),
+        rewritten: Vec::new(),
+    };
+    run(git_dir, state, find, options.committer)
+}
+
+/// Resume the stopped rebase in the repository at `git_dir` by committing `tree`, the tree of the conflicting commit
+/// with its conflicts resolved, with `committer`, and continue picking the remaining commits.
+///
+/// If `tree` is the tree of the commit the conflicting commit was picked onto, the conflicting commit is dropped, like
+/// `git rebase --skip` does.
+pub fn resume<Find>(
+    git_dir: impl AsRef<Path>,
+    tree: owned::Id,
+    mut find: Find,
+    committer: owned::Signature,
+) -> Result<Outcome, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let git_dir = git_dir.as_ref();
+    let mut state = State::read(git_dir)?;
+    let stopped = state
+        .done
+        .last()
+        .map(|(id, _)| *id)
+        .ok_or_else(|| Error::Corrupt(git_dir.join(STATE_DIR).join("done")))?;
+    let mut buf = Vec::new();
+    let tip = state.tip();
+    if tree != find_commit(&mut find, &mut buf, tip)?.tree {
+        let picked = find_commit(&mut find, &mut buf, stopped)?;
+        let new = commit::write(
+            git_dir,
+            &owned::Commit {
+                tree,
+                parents: std::iter::once(tip).collect(),
+                author: picked.author,
+                committer: committer.clone(),
+                encoding: None,
+                message: picked.message,
+                extra_headers: Vec::new(),
+            },
+        )?;
+        state.rewritten.push((stopped, new));
+    }
+    remove_stop_files(git_dir)?;
+    run(git_dir, state, find, committer)
+}
+
+/// Abort the stopped rebase in the repository at `git_dir` by removing its state, leaving the branch untouched.
+pub fn abort(git_dir: impl AsRef<Path>) -> Result<(), Error> {
+    let git_dir = git_dir.as_ref();
+    let dir = git_dir.join(STATE_DIR);
+    if !dir.is_dir() {
+        return Err(Error::NotInProgress(dir));
+    }
+    remove_stop_files(git_dir)?;
+    fs::remove_dir_all(&dir).map_err(|err| Error::Io(err, dir))
+}
+
+/// Pick the commits left to do, and move the branch once all of them are done.
+fn run<Find>(git_dir: &Path, mut state: State, mut find: Find, committer: owned::Signature) -> Result<Outcome, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    while !state.todo.is_empty() {
+        let pick = state.todo.remove(0);
+        let id = pick.0;
+        state.done.push(pick);
+        let options = sequencer::Options {
+            committer: committer.clone(),
+            reference: None,
+        };
+        match sequencer::cherry_pick(git_dir, id, state.tip(), &mut find, options)? {
+            sequencer::Outcome::Commit(new) => state.rewritten.push((id, new)),
+            sequencer::Outcome::Empty => {}
+            sequencer::Outcome::Conflict(merge) => {
+                let (from, to) = (git_dir.join("CHERRY_PICK_HEAD"), git_dir.join("REBASE_HEAD"));
+                fs::rename(&from, &to).map_err(|err| Error::Io(err, to))?;
+                state.write(git_dir, id)?;
+                return Ok(Outcome::Stopped { commit: id, merge });
+            }
+        }
+    }
+
+    let branch: BString = if state.head_name == DETACHED_HEAD {
+        "HEAD".into()
+    } else {
+        state.head_name.clone()
+    };
+    let (reference, previous) = update::resolve(git_dir, branch.as_ref())?;
+    if previous != Some(state.orig_head) {
+        return Err(Error::RefChanged(reference, state.orig_head, previous));
+    }
+    let new = state.tip();
+    let mut message = BString::from("rebase (finish): ");
+    message.extend_from_slice(&state.head_name);
+    message.extend_from_slice(format!(" onto {}", state.onto).as_bytes());
+    let reflog_options = update::Options {
+        committer,
+        message: message.clone(),
+        dry_run: false,
+        hooks: None,
+    };
+    update::write_ref(
+        git_dir,
+        reference.as_ref(),
+        previous,
+        new,
+        message.as_ref(),
+        &reflog_options,
+    )?;
+    if reference != "HEAD" && update::resolve(git_dir, "HEAD".into())?.0 == reference {
+        let mut message = BString::from("rebase (finish): returning to ");
+        message.extend_from_slice(&reference);
+        update::append_reflog(git_dir, "HEAD".into(), previous, new, message.as_ref(), &reflog_options)
+            .map_err(|err| Error::Io(err, git_dir.join("logs/HEAD")))?;
+    }
+    let dir = git_dir.join(STATE_DIR);
+    match fs::remove_dir_all(&dir) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(Error::Io(err, dir)),
+        _ => {}
+    }
+    Ok(Outcome::Done {
+        previous: state.orig_head,
+        new,
+    })
+}
+
+/// Remove the files the sequencer left in the git directory when the rebase stopped.
+fn remove_stop_files(git_dir: &Path) -> Result<(), Error> {
+    for name in &["REBASE_HEAD", "MERGE_MSG", "AUTO_MERGE"] {
+        let path = git_dir.join(name);
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(Error::Io(err, path)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn subject<Find>(find: &mut Find, buf: &mut Vec<u8>, id: owned::Id) -> Result<BString, Error>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>>,
+{
+    let commit = find_commit(find, buf, id)?;
+    Ok(commit.message.lines().next().unwrap_or_default().into())
+}
+
+fn find_commit<Find>(find: &mut Find, buf: &mut Vec<u8>, id: owned::Id) -> Result<owned::Commit, Error>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>>,
+{
+    match find(id.to_borrowed(), buf).ok_or(Error::Find(id))? {
+        borrowed::Object::Commit(commit) => Ok(commit.into()),
+        _ => Err(Error::NotACommit(id)),
+    }
+}
@@ -0,0 +1,637 @@
+//! Receive pushes into a repository, answering requests like `git receive-pack` does.
+//!
+//! The pack sent by the client is indexed into a quarantine directory within `objects` while it is read, and refs are
+//! only updated if all objects reachable from their new values are present, with the missing ones being reported to the
+//! client. Only the objects which aren't reachable from the existing refs are checked. The pack is moved into
+//! `objects/pack` once the `pre-receive` hook accepted the updates, and removed otherwise.
+//! Hooks are called like git calls them: `pre-receive` may reject all updates, `update` may reject the update of a
+//! single ref, and `post-receive` learns about all applied updates. The executable hooks of a repository can be used
+//! with [`Hooks::from_git_dir()`].
+//!
+//! All refs to update are locked before any of them is changed, and if the client asked for an `atomic` push, none of
+//! them is changed unless all updates can be applied. Thin packs are not supported, which is advertised to clients.
+use crate::{
+    hooks, push,
+    remote::update::{self, resolve},
+    transfer,
+};
+use git_features::{lock, progress::Progress};
+use git_object::{
+    bstr::{BStr, BString, ByteSlice},
+    owned,
+};
+use git_odb::{compound, pack, traverse};
+use git_protocol::{
+    handshake::Ref,
+    push::{Command, RefOptions, RefStatus, Report, Status},
+};
+use git_transport::{packetline, packetline::PacketLine};
+use quick_error::quick_error;
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred while talking to the client")
+            from()
+            source(err)
+        }
+        PacketLine(err: packetline::decode::Error) {
+            display("A packet line sent by the client could not be decoded")
+            from()
+            source(err)
+        }
+        UnexpectedLine(line: BString) {
+            display("The client sent the unexpected line '{}'", line)
+        }
+        Unpack(err: pack::bundle::write::Error) {
+            display("The pack sent by the client could not be received")
+            source(err)
+        }
+        Odb(err: compound::init::Error) {
+            display("The object database could not be opened")
+            from()
+            source(err)
+        }
+        Refs(err: update::Error) {
+            display("The refs to update could not be read")
+            from()
+            source(err)
+        }
+        Quarantine(err: io::Error, path: PathBuf) {
+            display("Could not create the directory to receive the pack in at '{}'", path.display())
+            source(err)
+        }
+        MovePack(err: io::Error, path: PathBuf) {
+            display("Could not move the received pack to '{}'", path.display())
+            source(err)
+        }
+        WriteRef(err: io::Error, path: PathBuf) {
+            display("Could not write the ref at '{}'", path.display())
+            source(err)
+        }
+    }
+}
+
+/// The capabilities advertised to clients in addition to `agent`.
+pub const CAPABILITIES: &[&str] = &[
+    "report-status",
+    "delete-refs",
+    "side-band-64k",
+    "quiet",
+    "atomic",
+    "ofs-delta",
+    "no-thin",
+];
+
+/// A function called with all commands before refs are updated, which may write messages for the user into the
+/// buffer and returns false to reject all of them.
+pub type PreReceive<'a> = Box<dyn FnMut(&[Command], &mut Vec<u8>) -> bool + 'a>;
+/// A function called with each command right before its ref is updated, which may write messages for the user into
+/// the buffer and returns false to reject it.
+pub type Update<'a> = Box<dyn FnMut(&Command, &mut Vec<u8>) -> bool + 'a>;
+/// A function called with all applied commands after the refs were updated, which may write messages for the user
+/// into the buffer.
+pub type PostReceive<'a> = Box<dyn FnMut(&[Command], &mut Vec<u8>) + 'a>;
+
+/// The hooks to call while receiving a push, all of which are optional.
+///
+/// Messages written by hooks are sent to the client as progress if it supports side-bands.
+#[derive(Default)]
+pub struct Hooks<'a> {
+    /// Called once with all commands that passed the connectivity check.
+    pub pre_receive: Option<PreReceive<'a>>,
+    /// Called for each command after `pre_receive` accepted them.
+    pub update: Option<Update<'a>>,
+    /// Called once with all commands whose refs were updated.
+    pub post_receive: Option<PostReceive<'a>>,
+}
+
+impl Hooks<'static> {
+    /// Use the executable `pre-receive`, `update` and `post-receive` programs of the repository at `git_dir`, if they
+    /// exist, as found by [`hooks::Runner::from_git_dir()`].
+    pub fn from_git_dir(git_dir: impl Into<PathBuf>) -> Self {
+        Self::from_runner(hooks::Runner::from_git_dir(git_dir))
+    }
+
+    /// Use the executable `pre-receive`, `update` and `post-receive` programs found by `runner`, if they exist.
+    ///
+    /// They are invoked like git does it: `pre-receive` and `post-receive` receive `<old> <new> <ref>` lines on
+    /// standard input, and `update` receives the name of the ref as well as the old and new object as arguments.
+    /// Everything they print is passed on to the client. Unlike other hooks, they always run in the git directory.
+    pub fn from_runner(runner: hooks::Runner) -> Self {
+        let runner = std::rc::Rc::new(hooks::Runner {
+            working_dir: runner.git_dir.clone(),
+            ..runner
+        });
+        let mut hooks = Hooks::default();
+        if runner.find("pre-receive").is_some() {
+            let runner = runner.clone();
+            hooks.pre_receive = Some(Box::new(move |commands, messages| {
+                runner
+                    .run("pre-receive", &[], Some(&hook_input(commands)), messages)
+                    .unwrap_or(true)
+            }));
+        }
+        if runner.find("update").is_some() {
+            let runner = runner.clone();
+            hooks.update = Some(Box::new(move |command, messages| {
+                let name = command.name.to_str_lossy().into_owned();
+                let args = [name, command.old.to_string(), command.new.to_string()];
+                runner.run("update", &args, None, messages).unwrap_or(true)
+            }));
+        }
+        if runner.find("post-receive").is_some() {
+            hooks.post_receive = Some(Box::new(move |commands, messages| {
+                runner.run("post-receive", &[], Some(&hook_input(commands)), messages);
+            }));
+        }
+        hooks
+    }
+}
+
+/// The `<old> <new> <ref>` lines hooks receive on standard input.
+fn hook_input(commands: &[Command]) -> Vec<u8> {
+    hooks::ref_updates_input(
+        commands
+            .iter()
+            .map(|command| (Some(command.old), Some(command.new), command.name.as_ref())),
+    )
+}
+
+/// Configure how to talk to the client.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+pub struct Options {
+    /// If true, refs are not advertised before reading the request, like the smart http protocol demands.
+    ///
+    /// Use [`advertise_refs()`] to answer the separate request for refs in that case.
+    pub stateless: bool,
+    /// The agent to advertise and the refs to hide, which can't be updated either.
+    pub advertisement: transfer::Advertisement,
+}
+
+/// The result of [`receive_pack()`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The commands sent by the client.
+    pub commands: Vec<Command>,
+    /// The capabilities the client asked for with its first command.
+    pub capabilities: Vec<BString>,
+    /// Information about the received pack, or `None` if the client only deleted refs. It only has the paths of its
+    /// files if the pack was kept, as it's removed if all updates were rejected before getting to the `update` hook.
+    pub pack: Option<pack::bundle::write::Outcome>,
+    /// The outcome of each command, as reported to the client if it asked for it.
+    pub report: Report,
+    /// The objects reachable from the new values of refs which don't exist, causing their commands to be rejected.
+    pub missing: Vec<owned::Id>,
+}
+
+/// Write the V1 advertisement of the `refs` which aren't hidden along with our capabilities as configured in `options`
+/// to `out`, followed by a flush packet.
+///
+/// Symbolic refs, like `HEAD`, are not advertised as they are never updated directly.
+pub fn advertise_refs(refs: &[Ref], options: &Options, mut out: impl io::Write) -> io::Result<()> {
+    let mut capabilities: Vec<String> = CAPABILITIES.iter().map(|c| (*c).to_owned()).collect();
+    capabilities.push(format!("agent={}", options.advertisement.agent));
+    let capabilities = capabilities.join(" ");
+
+    let mut lines: Vec<String> = options
+        .advertisement
+        .visible(refs)
+        .filter_map(|r| match r {
+            Ref::Direct { path, object } => Some(format!("{} {}", object, path)),
+            Ref::Peeled { path, tag, .. } => Some(format!("{} {}", tag, path)),
+            Ref::Symbolic { .. } | Ref::Unborn { .. } => None,
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push(format!("{} capabilities^{{}}", owned::Id::null_sha1()));
+    }
+    for (index, line) in lines.iter().enumerate() {
+        if index == 0 {
+            packetline::encode::text_to_write(format!("{}\0{}", line, capabilities).as_bytes(), &mut out)?;
+        } else {
+            packetline::encode::text_to_write(line.as_bytes(), &mut out)?;
+        }
+    }
+    packetline::encode::flush_to_write(&mut out)?;
+    out.flush()
+}
+
+/// Receive the push read from `input` into the repository at `git_dir`, advertising `refs` on `out` first unless
+/// [stateless][Options::stateless], and report the outcome to the client if it asked for it.
+///
+/// The refs must be the current refs of the repository, whose objects are considered available on the client.
+/// Progress of receiving the pack is reported to `progress`.
+pub fn receive_pack<R, P>(
+    mut input: R,
+    mut out: impl io::Write,
+    git_dir: &Path,
+    refs: &[Ref],
+    mut hooks: Hooks<'_>,
+    progress: P,
+    options: Options,
+) -> Result<Outcome, Error>
+where
+    R: io::Read + Send + 'static,
+    P: Progress,
+    <P as Progress>::SubProgress: Send + 'static,
+    <<P as Progress>::SubProgress as Progress>::SubProgress: Send,
+{
+    if !options.stateless {
+        advertise_refs(refs, &options, &mut out)?;
+    }
+    let mut outcome = Outcome {
+        commands: Vec::new(),
+        capabilities: Vec::new(),
+        pack: None,
+        report: Report::default(),
+        missing: Vec::new(),
+    };
+    {
+        let mut lines = packetline::Provider::new(&mut input, &[PacketLine::Flush]);
+        while let Some(line) = lines.next_line() {
+            let line = line??;
+            let line: &[u8] = line.to_text().unwrap_or_default();
+            let (command, capabilities) = match line.find_byte(0) {
+                Some(pos) => (&line[..pos], &line[pos + 1..]),
+                None => (line, &[][..]),
+            };
+            let command = parse_command(command).ok_or_else(|| Error::UnexpectedLine(line.into()))?;
+            if outcome.commands.is_empty() {
+                outcome.capabilities = capabilities.fields().map(|c| c.into()).collect();
+            }
+            outcome.commands.push(command);
+        }
+    }
+    if outcome.commands.is_empty() {
+        return Ok(outcome);
+    }
+    let has = |name: &str| outcome.capabilities.iter().any(|c| c == name);
+    let (report_status, sideband, atomic) = (has("report-status"), has("side-band-64k"), has("atomic"));
+    let mut messages = Vec::new();
+
+    let objects_dir = git_dir.join("objects");
+    let mut quarantine = None;
+    if !outcome.commands.iter().all(Command::is_delete) {
+        let mut header = [0u8; 12];
+        input.read_exact(&mut header)?;
+        let pack = if header[8..] == [0, 0, 0, 0] {
+            // Clients send packs without objects if the remote has all of them, which leaves nothing to index.
+            let mut trailer = [0u8; 20];
+            input.read_exact(&mut trailer).map(|_| None).map_err(Into::into)
+        } else {
+            let dir = tempfile::Builder::new()
+                .prefix("incoming-")
+                .tempdir_in(&objects_dir)
+                .map_err(|err| Error::Quarantine(err, objects_dir.clone()))?;
+            let directory = dir.path().to_owned();
+            quarantine = Some(dir);
+            pack::Bundle::write_to_directory(
+                io::Cursor::new(header).chain(input),
+                None,
+                Some(directory),
+                progress,
+                pack::bundle::write::Options {
+                    thread_limit: None,
+                    iteration_mode: pack::data::iter::Mode::Verify,
+                    index_kind: pack::index::Kind::default(),
+                },
+            )
+            .map(Some)
+        };
+        match pack {
+            Ok(pack) => outcome.pack = pack,
+            Err(err) => {
+                outcome.report.unpack_error = Some(format!("index-pack failed: {}", err).into());
+                outcome.report.refs = outcome.commands.iter().map(|c| rejected(c, "unpacker error")).collect();
+                send_report(&outcome.report, report_status, sideband, &messages, &mut out)?;
+                return Err(Error::Unpack(err));
+            }
+        }
+    }
+
+    let mut statuses: Vec<Option<BString>> = vec![None; outcome.commands.len()];
+    let mut db = compound::Db::at(&objects_dir)?;
+    if let Some(bundle) = outcome.pack.as_ref().and_then(pack::bundle::write::Outcome::to_bundle) {
+        db.packs.push(bundle.map_err(compound::init::Error::from)?);
+    }
+    let available: Vec<_> = refs
+        .iter()
+        .filter_map(|r| match r {
+            Ref::Peeled { tag, .. } => Some(*tag),
+            Ref::Direct { object, .. } => Some(*object),
+            Ref::Symbolic { .. } | Ref::Unborn { .. } => None,
+        })
+        .filter(|id| db.contains(id.to_borrowed()))
+        .collect();
+    let mut cache = pack::cache::DecodeEntryLRU::default();
+    for (command, status) in outcome.commands.iter().zip(statuses.iter_mut()) {
+        if git_ref::name::validate(command.name.as_ref()).is_err() || !command.name.starts_with(b"refs/") {
+            *status = Some("funny refname".into());
+        } else if options.advertisement.is_hidden(command.name.as_ref()) {
+            *status = Some("deny updating a hidden ref".into());
+        } else if !command.is_delete() {
+            // Only the objects which aren't reachable from the refs are checked, and if a new commit is missing the
+            // walk can't tell where these end, leaving it to the check to find its way to the refs.
+            let known = push::known_objects(
+                &mut |id, buf| db.locate(id, buf, &mut cache)?.ok(),
+                &[command.new],
+                available.clone(),
+            )
+            .map(|known| known.seen)
+            .unwrap_or_else(|_| available.iter().copied().collect());
+            match traverse::connectivity::check(&db, Some(command.new), known) {
+                Ok(connectivity) if connectivity.is_connected() => {}
+                Ok(connectivity) => {
+                    for id in &connectivity.missing {
+                        writeln!(messages, "error: object {} needed by {} is missing", id, command.name)?;
+                    }
+                    outcome.missing.extend(connectivity.missing);
+                    *status = Some("missing necessary objects".into());
+                }
+                Err(err) => {
+                    writeln!(messages, "error: {}", err)?;
+                    *status = Some("missing necessary objects".into());
+                }
+            }
+        }
+    }
+
+    if let Some(pre_receive) = hooks.pre_receive.as_mut() {
+        let accepted: Vec<_> = accepted(&outcome.commands, &statuses).cloned().collect();
+        if !accepted.is_empty() && !pre_receive(&accepted, &mut messages) {
+            reject_all(&mut statuses, "pre-receive hook declined");
+        }
+    }
+    if let Some(pack) = outcome.pack.as_mut() {
+        if accepted(&outcome.commands, &statuses).next().is_some() {
+            move_pack(pack, &objects_dir.join("pack"))?;
+        } else {
+            pack.index_path = None;
+            pack.data_path = None;
+        }
+    }
+    drop(quarantine);
+
+    let mut locks = Vec::new();
+    for (command, status) in outcome.commands.iter().zip(statuses.iter_mut()) {
+        if status.is_some() {
+            continue;
+        }
+        match lock(git_dir, command)? {
+            Ok(lock) => locks.push(lock),
+            Err(reason) => *status = Some(reason.into()),
+        }
+    }
+    if let Some(update) = hooks.update.as_mut() {
+        for (command, status) in outcome.commands.iter().zip(statuses.iter_mut()) {
+            if status.is_none() && !update(command, &mut messages) {
+                *status = Some("hook declined".into());
+            }
+        }
+    }
+    if atomic && statuses.iter().any(Option::is_some) {
+        reject_all(&mut statuses, "atomic transaction failed");
+    }
+
+    for lock in locks {
+        let index = outcome
+            .commands
+            .iter()
+            .position(|c| c.name == lock.name)
+            .expect("locks are taken for commands");
+        if statuses[index].is_some() {
+            drop(lock);
+        } else if let Err(reason) = lock.commit(git_dir)? {
+            statuses[index] = Some(reason.into());
+        }
+    }
+
+    if let Some(post_receive) = hooks.post_receive.as_mut() {
+        let applied: Vec<_> = accepted(&outcome.commands, &statuses).cloned().collect();
+        if !applied.is_empty() {
+            post_receive(&applied, &mut messages);
+        }
+    }
+
+    outcome.report.refs = outcome
+        .commands
+        .iter()
+        .zip(statuses)
+        .map(|(command, status)| match status {
+            None => RefStatus {
+                name: command.name.clone(),
+                status: Status::Ok,
+                options: RefOptions::default(),
+            },
+            Some(reason) => rejected(command, reason),
+        })
+        .collect();
+    send_report(&outcome.report, report_status, sideband, &messages, &mut out)?;
+    Ok(outcome)
+}
+
+/// Move the files of the received `pack` from the quarantine to the `pack_dir`, and update their paths.
+fn move_pack(pack: &mut pack::bundle::write::Outcome, pack_dir: &Path) -> Result<(), Error> {
+    fs::create_dir_all(pack_dir).map_err(|err| Error::MovePack(err, pack_dir.to_owned()))?;
+    // The data goes first, as packs are only picked up once their index exists.
+    for path in pack.data_path.iter_mut().chain(pack.index_path.iter_mut()) {
+        let destination = pack_dir.join(path.file_name().expect("pack files have names"));
+        fs::rename(&*path, &destination).map_err(|err| Error::MovePack(err, destination.clone()))?;
+        *path = destination;
+    }
+    Ok(())
+}
+
+fn parse_command(line: &[u8]) -> Option<Command> {
+    let mut tokens = line.splitn(3, |b| *b == b' ');
+    let mut id = || owned::Id::from_40_bytes_in_hex(tokens.next()?).ok();
+    let (old, new) = (id()?, id()?);
+    let name = tokens.next().filter(|name| !name.is_empty())?;
+    Some(Command {
+        old,
+        new,
+        name: name.into(),
+    })
+}
+
+fn rejected(command: &Command, reason: impl AsRef<[u8]>) -> RefStatus {
+    RefStatus {
+        name: command.name.clone(),
+        status: Status::Rejected(reason.as_ref().into()),
+        options: RefOptions::default(),
+    }
+}
+
+fn accepted<'a>(commands: &'a [Command], statuses: &'a [Option<BString>]) -> impl Iterator<Item = &'a Command> {
+    commands
+        .iter()
+        .zip(statuses)
+        .filter(|(_, s)| s.is_none())
+        .map(|(c, _)| c)
+}
+
+fn reject_all(statuses: &mut [Option<BString>], reason: &str) {
+    for status in statuses.iter_mut().filter(|s| s.is_none()) {
+        *status = Some(reason.into());
+    }
+}
+
+/// Send the hook `messages` and the `report` to the client, the latter only if it asked for it.
+fn send_report(
+    report: &Report,
+    report_status: bool,
+    sideband: bool,
+    messages: &[u8],
+    mut out: impl io::Write,
+) -> io::Result<()> {
+    if sideband {
+        for chunk in messages.chunks(packetline::MAX_DATA_LEN - 1) {
+            packetline::encode::band_to_write(packetline::Channel::Progress, chunk, &mut out)?;
+        }
+    }
+    if report_status {
+        let mut lines = Vec::new();
+        let unpack = match &report.unpack_error {
+            Some(reason) => format!("unpack {}", reason),
+            None => "unpack ok".into(),
+        };
+        packetline::encode::text_to_write(unpack.as_bytes(), &mut lines)?;
+        for r in &report.refs {
+            let mut line = BString::from(match r.status {
+                Status::Ok => "ok ",
+                Status::Rejected(_) => "ng ",
+            });
+            line.extend_from_slice(&r.name);
+            if let Status::Rejected(reason) = &r.status {
+                line.push(b' ');
+                line.extend_from_slice(reason);
+            }
+            packetline::encode::text_to_write(&line, &mut lines)?;
+        }
+        packetline::encode::flush_to_write(&mut lines)?;
+        if sideband {
+            for chunk in lines.chunks(packetline::MAX_DATA_LEN - 1) {
+                packetline::encode::band_to_write(packetline::Channel::Data, chunk, &mut out)?;
+            }
+        } else {
+            out.write_all(&lines)?;
+        }
+    }
+    if sideband {
+        packetline::encode::flush_to_write(&mut out)?;
+    }
+    out.flush()
+}
+
+/// The lock file of a ref about to be updated.
+struct Lock {
+    name: BString,
+    file: lock::File,
+    new: Option<owned::Id>,
+}
+
+/// Lock the ref of `command` and verify it still points to the old object while locked, or return the reason for
+/// rejecting it.
+fn lock(git_dir: &Path, command: &Command) -> Result<Result<Lock, &'static str>, Error> {
+    let (name, _) = resolve(git_dir, command.name.as_ref())?;
+    let path = match ref_path(git_dir, name.as_ref()) {
+        Some(path) => path,
+        None => return Ok(Err("unsafe ref name")),
+    };
+    let file = match lock::File::acquire(path, Default::default()) {
+        Ok(file) => file,
+        Err(lock::Error::Locked(_)) => return Ok(Err("failed to lock")),
+        Err(lock::Error::Io(err, path)) => return Err(Error::WriteRef(err, path)),
+    };
+    // Only once the lock is held the ref can't change anymore.
+    let (_, current) = resolve(git_dir, name.as_ref())?;
+    let expected = Some(command.old).filter(|id| *id != owned::Id::null_sha1());
+    if current != expected {
+        return Ok(Err("stale info"));
+    }
+    Ok(Ok(Lock {
+        name: command.name.clone(),
+        file,
+        new: Some(command.new).filter(|id| *id != owned::Id::null_sha1()),
+    }))
+}
+
+impl Lock {
+    /// Move the new value into place, or delete the ref from both its loose file and the `packed-refs` file.
+    fn commit(mut self, git_dir: &Path) -> Result<Result<(), &'static str>, Error> {
+        let path = self.file.resource_path().to_owned();
+        match self.new {
+            Some(new) => {
+                writeln!(self.file, "{}", new).map_err(|err| Error::WriteRef(err, path))?;
+                self.file.commit().map_err(|err| match err {
+                    lock::Error::Io(err, path) => Error::WriteRef(err, path),
+                    lock::Error::Locked(path) => {
+                        Error::WriteRef(io::Error::new(io::ErrorKind::AlreadyExists, "locked"), path)
+                    }
+                })?;
+                Ok(Ok(()))
+            }
+            None => match fs::remove_file(&path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(Error::WriteRef(err, path)),
+                _ => match remove_packed(git_dir, self.name.as_ref()) {
+                    Ok(true) => Ok(Ok(())),
+                    Ok(false) => Ok(Err("failed to lock")),
+                    Err(err) => Err(Error::WriteRef(err, path)),
+                },
+            },
+        }
+    }
+}
+
+/// Remove `name` from the `packed-refs` file, if present, returning false if the file was locked.
+fn remove_packed(git_dir: &Path, name: &BStr) -> io::Result<bool> {
+    let path = git_dir.join("packed-refs");
+    let content = match fs::read(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(true),
+        Err(err) => return Err(err),
+    };
+    let mut kept = Vec::with_capacity(content.len());
+    let mut is_removed_ref = false;
+    for line in content.lines_with_terminator() {
+        if line.starts_with(b"^") {
+            if is_removed_ref {
+                continue;
+            }
+        } else {
+            is_removed_ref =
+                line.trim_end_with(char::is_whitespace).splitn(2, |b| *b == b' ').nth(1) == Some(name.as_bytes());
+            if is_removed_ref {
+                continue;
+            }
+        }
+        kept.extend_from_slice(line);
+    }
+    if kept.len() == content.len() {
+        return Ok(true);
+    }
+    let mut file = match lock::File::acquire(path, Default::default()) {
+        Ok(file) => file,
+        Err(lock::Error::Locked(_)) => return Ok(false),
+        Err(lock::Error::Io(err, _)) => return Err(err),
+    };
+    file.write_all(&kept)?;
+    file.commit().map(|_| true).map_err(|err| match err {
+        lock::Error::Io(err, _) => err,
+        lock::Error::Locked(_) => io::Error::new(io::ErrorKind::AlreadyExists, "locked"),
+    })
+}
+
+fn ref_path(git_dir: &Path, name: &BStr) -> Option<PathBuf> {
+    git_features::path::to_native(name, Default::default())
+        .ok()
+        .map(|relative| git_features::path::long(git_dir.join(relative)))
+}
@@ -0,0 +1,203 @@
+//! Read the reflogs in `$GIT_DIR/logs` and find the object a ref pointed to at a given time, or a given amount of
+//! changes ago, like `<ref>@{<date>}` and `<ref>@{<n>}` do.
+use crate::{date, worktree::linked};
+use git_object::{
+    bstr::{BStr, BString, ByteSlice},
+    owned, Sign, Time,
+};
+use quick_error::quick_error;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not read the reflog at '{}'", path.display())
+            source(err)
+        }
+        Corrupt(line_number: usize, path: PathBuf) {
+            display("Line {} of the reflog at '{}' could not be parsed", line_number, path.display())
+        }
+        InvalidSpec(spec: BString) {
+            display("'{}' is not of the form '<ref>@{{<date>}}' or '<ref>@{{<n>}}'", spec)
+        }
+        Date(err: date::Error) {
+            display("The date of the reflog lookup could not be parsed")
+            from()
+            source(err)
+        }
+        Worktree(err: linked::Error) {
+            display("The common git directory of the worktree could not be determined")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// A single change to a ref as recorded in its reflog.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    /// The object the ref pointed to before, or `None` if it was created.
+    pub previous: Option<owned::Id>,
+    /// The object the ref points to since.
+    pub new: owned::Id,
+    /// The identity and time of whoever changed the ref.
+    pub committer: owned::Signature,
+    /// Why the ref changed, like `commit: <subject>`.
+    pub message: BString,
+}
+
+impl Entry {
+    /// Write the entry as a line of a reflog, the way [`from_bytes()`] reads it.
+    pub fn write_to(&self, mut out: impl io::Write) -> io::Result<()> {
+        out.write_all(&self.previous.unwrap_or_else(owned::Id::null_sha1).to_sha1_hex())?;
+        out.write_all(b" ")?;
+        out.write_all(&self.new.to_sha1_hex())?;
+        out.write_all(b" ")?;
+        self.committer.write_to(&mut out)?;
+        out.write_all(b"\t")?;
+        out.write_all(&self.message)?;
+        out.write_all(b"\n")
+    }
+}
+
+/// Parse the lines of a reflog, oldest first, into entries, whose time is the one of their committer.
+///
+/// `path` is only used for error messages.
+pub fn from_bytes(data: &[u8], path: &Path) -> Result<Vec<Entry>, Error> {
+    data.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(index, line)| parse_line(line).ok_or_else(|| Error::Corrupt(index + 1, path.to_owned())))
+        .collect()
+}
+
+/// Read the reflog of the ref `name` in the repository at `git_dir`, oldest entry first, which is empty if there is
+/// none.
+pub fn read(git_dir: impl AsRef<Path>, name: &BStr) -> Result<Vec<Entry>, Error> {
+    let path = path(git_dir.as_ref(), name)?;
+    match fs::read(&path) {
+        Ok(data) => from_bytes(&data, &path),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(Error::Io(err, path)),
+    }
+}
+
+/// Return the object the ref with reflog `entries` pointed to at `time` in seconds since epoch, or `None` if it
+/// didn't exist yet.
+///
+/// Like git, times before the first entry yield the object the ref pointed to before it, if it existed, or the one
+/// it was first set to.
+pub fn at_time(entries: &[Entry], time: u32) -> Option<owned::Id> {
+    match entries.iter().rev().find(|entry| entry.committer.time.time <= time) {
+        Some(entry) => Some(entry.new),
+        None => entries.first().map(|first| first.previous.unwrap_or(first.new)),
+    }
+}
+
+/// Return the object the ref with reflog `entries` pointed to `n` changes ago, where 0 is its current value, or
+/// `None` if the reflog isn't long enough or the ref didn't exist at that point.
+pub fn nth(entries: &[Entry], n: usize) -> Option<owned::Id> {
+    match n {
+        0 => entries.last().map(|entry| entry.new),
+        n => entries.len().checked_sub(n).and_then(|index| entries[index].previous),
+    }
+}
+
+/// Lookup `spec` like `main@{yesterday}`, `@{2 weeks ago}` or `HEAD@{1}` in the repository at `git_dir`, using `now`
+/// for dates relative to the current time, and return the object its ref pointed to at that point.
+///
+/// Refs without a reflog as well as points before the ref existed yield `None`. Like git, short names are looked up in
+/// `refs/`, `refs/tags/`, `refs/heads/` and `refs/remotes/` if they aren't found as they are, and an empty name
+/// refers to `HEAD`.
+pub fn lookup(git_dir: impl AsRef<Path>, spec: &BStr, now: Time) -> Result<Option<owned::Id>, Error> {
+    let git_dir = git_dir.as_ref();
+    let invalid = || Error::InvalidSpec(spec.to_owned());
+    let pos = spec.find(b"@{").ok_or_else(invalid)?;
+    let (name, selector) = (&spec[..pos], spec[pos + 2..].strip_suffix(b"}").ok_or_else(invalid)?);
+    let name: BString = if name.is_empty() { "HEAD".into() } else { name.into() };
+
+    let mut entries = Vec::new();
+    for candidate in candidates(name.as_ref()) {
+        entries = read(git_dir, candidate.as_ref())?;
+        if !entries.is_empty() {
+            break;
+        }
+    }
+    Ok(match selector.to_str().ok().and_then(|n| n.parse().ok()) {
+        Some(n) if !selector.starts_with(b"+") => nth(&entries, n),
+        _ => at_time(&entries, date::parse(selector.as_bstr(), now)?.time),
+    })
+}
+
+fn candidates(name: &BStr) -> Vec<BString> {
+    let mut candidates = vec![name.to_owned()];
+    if !name.starts_with(b"refs/") {
+        for prefix in &["refs/", "refs/tags/", "refs/heads/", "refs/remotes/"] {
+            let mut candidate = BString::from(*prefix);
+            candidate.extend_from_slice(name);
+            candidates.push(candidate);
+        }
+        let mut remote_head = BString::from("refs/remotes/");
+        remote_head.extend_from_slice(name);
+        remote_head.extend_from_slice(b"/HEAD");
+        candidates.push(remote_head);
+    }
+    candidates
+}
+
+/// The path of the reflog of the ref `name`.
+pub(crate) fn path(git_dir: &Path, name: &BStr) -> Result<PathBuf, Error> {
+    let logs = linked::ref_dir(git_dir, name)?.join("logs");
+    let relative = name
+        .to_path()
+        .map_err(|_| Error::Io(io::ErrorKind::InvalidInput.into(), logs.clone()))?;
+    Ok(logs.join(relative))
+}
+
+/// Parse `<old> <new> <name> <<email>> <time> <offset>\t<message>`.
+fn parse_line(line: &[u8]) -> Option<Entry> {
+    let (header, message) = match line.find_byte(b'\t') {
+        Some(pos) => (&line[..pos], &line[pos + 1..]),
+        None => (line, &b""[..]),
+    };
+    let previous = owned::Id::from_40_bytes_in_hex(header.get(..40)?).ok()?;
+    let new = owned::Id::from_40_bytes_in_hex(header.get(41..81)?).ok()?;
+    let identity = header.get(82..)?;
+    let email_start = identity.find_byte(b'<')?;
+    let email_end = email_start + identity[email_start..].find_byte(b'>')?;
+    let mut time = identity[email_end + 1..]
+        .split_str(" ")
+        .filter(|token| !token.is_empty());
+    let seconds = time.next()?.to_str().ok()?.parse().ok()?;
+    let offset = time.next()?;
+    let sign = match offset.first()? {
+        b'+' => Sign::Plus,
+        b'-' => Sign::Minus,
+        _ => return None,
+    };
+    let hhmm: i32 = offset.get(1..)?.to_str().ok()?.parse().ok()?;
+    let offset = (hhmm / 100 * 3600 + hhmm % 100 * 60) * if sign == Sign::Minus { -1 } else { 1 };
+    Some(Entry {
+        previous: if previous == owned::Id::null_sha1() {
+            None
+        } else {
+            Some(previous)
+        },
+        new,
+        committer: owned::Signature {
+            name: identity[..email_start].trim_end_with(|c| c == ' ').into(),
+            email: identity[email_start + 1..email_end].into(),
+            time: Time {
+                time: seconds,
+                offset,
+                sign,
+            },
+        },
+        message: message.into(),
+    })
+}
@@ -0,0 +1,288 @@
+//! Remotes as configured with `remote.<name>.*` keys, along with the refspecs to map their refs to local ones.
+use git_object::bstr::{BStr, BString, ByteSlice};
+use quick_error::quick_error;
+
+pub mod refspec;
+#[doc(inline)]
+pub use refspec::RefSpec;
+
+pub mod update;
+
+mod url_match;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Url(value: BString, err: git_url::parse::Error) {
+            display("The url '{}' could not be parsed", value)
+            source(err)
+        }
+        RefSpec(value: BString, err: refspec::Error) {
+            display("The refspec '{}' could not be parsed", value)
+            source(err)
+        }
+        MissingUrl(name: BString) {
+            display("The remote '{}' has no url", name)
+        }
+    }
+}
+
+/// A remote repository to fetch from and push to.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Remote {
+    /// The name of the remote, like `origin`.
+    pub name: BString,
+    /// The urls to fetch from, as configured with `url` and rewritten with `url.<base>.insteadOf`.
+    pub urls: Vec<git_url::Url>,
+    /// The urls to push to if they differ from the ones to fetch from, as configured with `pushurl` and rewritten
+    /// with `url.<base>.insteadOf`, or the ones configured with `url` which are rewritten with
+    /// `url.<base>.pushInsteadOf`.
+    pub push_urls: Vec<git_url::Url>,
+    /// The refspecs to use when fetching, as configured with `fetch`.
+    pub fetch_specs: Vec<RefSpec>,
+    /// The refspecs to use when pushing, as configured with `push`.
+    pub push_specs: Vec<RefSpec>,
+}
+
+/// A remote ref which is to be written into a local ref, according to a fetch refspec.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mapping {
+    /// The full name of the ref on the remote.
+    pub remote: BString,
+    /// The full name of the local ref to update, or `None` if the ref is only fetched.
+    pub local: Option<BString>,
+    /// If true, the local ref may be updated even if it's not a fast-forward.
+    pub force: bool,
+}
+
+impl Remote {
+    /// Read the remote called `name` from `config`, or return `None` if it is not configured.
+    pub fn from_config(config: &git_config::File, name: &str) -> Result<Option<Self>, Error> {
+        if !config.subsections("remote").contains(&name.as_bytes().as_bstr()) {
+            return Ok(None);
+        }
+        let parse = |urls: Vec<BString>| {
+            urls.into_iter()
+                .map(|url| git_url::parse(&url).map_err(|err| Error::Url(url.clone(), err)))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let rewrite_all = |urls: &[&BStr]| -> Vec<BString> {
+            urls.iter()
+                .map(|url| rewritten(config, url, "insteadof").unwrap_or_else(|| (*url).to_owned()))
+                .collect()
+        };
+        let fetch_urls = config.values("remote", Some(name), "url");
+        let push_urls = config.values("remote", Some(name), "pushurl");
+        // Like git, `pushInsteadOf` only applies to the urls to fetch from, and only those it rewrites are pushed to.
+        let push_urls = if push_urls.is_empty() {
+            fetch_urls
+                .iter()
+                .filter_map(|url| rewritten(config, url, "pushinsteadof"))
+                .collect()
+        } else {
+            rewrite_all(&push_urls)
+        };
+        let specs = |key, operation| {
+            config
+                .values("remote", Some(name), key)
+                .into_iter()
+                .map(|spec| RefSpec::parse(spec, operation).map_err(|err| Error::RefSpec(spec.to_owned(), err)))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let remote = Remote {
+            name: name.into(),
+            urls: parse(rewrite_all(&fetch_urls))?,
+            push_urls: parse(push_urls)?,
+            fetch_specs: specs("fetch", refspec::Operation::Fetch)?,
+            push_specs: specs("push", refspec::Operation::Push)?,
+        };
+        if remote.urls.is_empty() && remote.push_urls.is_empty() {
+            return Err(Error::MissingUrl(remote.name));
+        }
+        Ok(Some(remote))
+    }
+
+    /// Return the urls to push to, which are the ones to fetch from unless `pushurl` is set.
+    pub fn push_urls(&self) -> &[git_url::Url] {
+        if self.push_urls.is_empty() {
+            &self.urls
+        } else {
+            &self.push_urls
+        }
+    }
+
+    /// Map all `remote_refs` with the fetch refspecs, skipping those excluded by negative refspecs or not matched
+    /// at all.
+    pub fn fetch_mappings<'a>(&self, remote_refs: impl IntoIterator<Item = &'a BStr>) -> Vec<Mapping> {
+        map(&self.fetch_specs, remote_refs)
+    }
+}
+
+/// Map all `refs` with `specs`, skipping those excluded by negative refspecs or not matched at all.
+pub fn map<'a>(specs: &[RefSpec], refs: impl IntoIterator<Item = &'a BStr>) -> Vec<Mapping> {
+    let mut mappings = Vec::new();
+    for name in refs {
+        if specs.iter().any(|spec| spec.negative && spec.matches(name)) {
+            continue;
+        }
+        for spec in specs.iter().filter(|spec| !spec.negative && spec.matches(name)) {
+            let mapping = Mapping {
+                remote: name.to_owned(),
+                local: spec.expand(name),
+                force: spec.force,
+            };
+            if !mappings.contains(&mapping) {
+                mappings.push(mapping);
+            }
+        }
+    }
+    mappings
+}
+
+/// Rewrite `url` with the `url.<base>.insteadOf` value in `config` which is its longest prefix, trying the
+/// `url.<base>.pushInsteadOf` values first if the url is used to push, and return it unchanged if none matches.
+///
+/// This is how git rewrites urls which are given instead of the names of remotes.
+pub fn rewrite_url(config: &git_config::File, url: &BStr, operation: refspec::Operation) -> BString {
+    let push = match operation {
+        refspec::Operation::Push => rewritten(config, url, "pushinsteadof"),
+        refspec::Operation::Fetch => None,
+    };
+    push.or_else(|| rewritten(config, url, "insteadof"))
+        .unwrap_or_else(|| url.to_owned())
+}
+
+/// Replace the longest prefix of `url` among the values of `key` in all `url.<base>` sections with its base, where
+/// the first one wins among prefixes of the same length.
+fn rewritten(config: &git_config::File, url: &BStr, key: &str) -> Option<BString> {
+    let mut longest: Option<(&BStr, &BStr)> = None;
+    for section in config.sections().iter().filter(|section| section.name == "url") {
+        let base = match &section.subsection {
+            Some(base) => base.as_bstr(),
+            None => continue,
+        };
+        for prefix in section
+            .entries
+            .iter()
+            .filter(|entry| entry.key == key)
+            .filter_map(|entry| entry.value.as_ref())
+        {
+            if url.starts_with(prefix) && !matches!(longest, Some((longest, _)) if longest.len() >= prefix.len()) {
+                longest = Some((prefix.as_bstr(), base));
+            }
+        }
+    }
+    longest.map(|(prefix, base)| {
+        let mut new_url = base.to_owned();
+        new_url.extend_from_slice(&url[prefix.len()..]);
+        new_url
+    })
+}
+
+/// Return the names of all remotes in `config`, in the order of their appearance.
+pub fn names(config: &git_config::File) -> Vec<&BStr> {
+    config.subsections("remote")
+}
+
+/// Configure the credential helpers with the `credential.helper` values in `config`, where an empty value removes
+/// all helpers configured before it, along with `credential.useHttpPath`.
+///
+/// The askpass program is taken from `core.askPass` unless `GIT_ASKPASS` is set, see
+/// [`git_credentials::prompt::Options::from_env()`] for all other settings.
+pub fn credentials(config: &git_config::File) -> git_credentials::Cascade {
+    let mut cascade = git_credentials::Cascade::from_env();
+    for value in config.values("credential", None, "helper") {
+        if value.is_empty() {
+            cascade.programs.clear();
+        } else {
+            cascade
+                .programs
+                .push(git_credentials::helper::Program::from_config_value(
+                    &value.to_str_lossy(),
+                ));
+        }
+    }
+    cascade.use_http_path = matches!(config.boolean("credential", None, "useHttpPath"), Some(Ok(true)));
+    if std::env::var_os("GIT_ASKPASS").is_none() {
+        if let Some(askpass) = config.value("core", None, "askPass").filter(|v| !v.is_empty()) {
+            cascade.prompt.askpass = Some(askpass.to_os_str_lossy().into_owned());
+        }
+    }
+    cascade
+}
+
+/// Configure HTTP requests to `url` with the `http.proxy`, `http.sslVerify`, `http.sslCAInfo`, `http.extraHeader`,
+/// `http.followRedirects` and `http.maxRetries` values in `config`, where an empty `extraHeader` removes all headers
+/// configured before it.
+///
+/// Like in git, values in `http.<url>.*` sections override the plain ones if `<url>` matches, like
+/// `[http "https://*.example.com"]`, with the most specific match winning. `GIT_SSL_NO_VERIFY` and `GIT_SSL_CAINFO`
+/// override any configuration.
+pub fn http_options(config: &git_config::File, url: &BStr) -> git_transport::client::http::Options {
+    let mut options = git_transport::client::http::Options::default();
+    let mut best = Vec::<(&BStr, url_match::Specificity)>::new();
+    for section in config.sections().iter().filter(|section| section.name == "http") {
+        let specificity = match &section.subsection {
+            Some(pattern) => match url_match::specificity(pattern.as_bstr(), url) {
+                Some(specificity) => specificity,
+                None => continue,
+            },
+            None => url_match::Specificity::default(),
+        };
+        for entry in &section.entries {
+            match best.iter_mut().find(|(key, _)| *key == entry.key) {
+                Some((_, best)) if *best > specificity => continue,
+                Some((_, best)) => *best = specificity,
+                None => best.push((entry.key.as_bstr(), specificity)),
+            }
+            let value = entry.value.as_ref().map(|value| value.as_bstr());
+            match entry.key.as_slice() {
+                b"proxy" => {
+                    options.proxy = value
+                        .filter(|value| !value.is_empty())
+                        .map(|value| value.to_str_lossy().into_owned())
+                }
+                b"sslverify" => {
+                    if let Ok(verify) = entry.boolean() {
+                        options.ssl_no_verify = !verify;
+                    }
+                }
+                b"sslcainfo" => {
+                    options.ssl_ca_info = value
+                        .filter(|value| !value.is_empty())
+                        .map(|value| expand_home(&value.to_path_lossy()))
+                }
+                b"followredirects" => options.follow_redirects = entry.boolean().unwrap_or(true),
+                b"maxretries" => {
+                    if let Some(retries) = value.and_then(|value| value.to_str().ok()?.parse().ok()) {
+                        options.max_retries = retries;
+                    }
+                }
+                b"extraheader" => match value {
+                    Some(value) if value.is_empty() => options.extra_headers.clear(),
+                    Some(value) => options.extra_headers.push(value.to_str_lossy().into_owned()),
+                    None => {}
+                },
+                _ => {}
+            }
+        }
+    }
+    if std::env::var_os("GIT_SSL_NO_VERIFY").is_some() {
+        options.ssl_no_verify = true;
+    }
+    if let Some(path) = std::env::var_os("GIT_SSL_CAINFO") {
+        options.ssl_ca_info = Some(path.into());
+    }
+    options
+}
+
+fn expand_home(path: &std::path::Path) -> std::path::PathBuf {
+    match path.strip_prefix("~") {
+        Ok(relative) => std::env::var_os("HOME")
+            .map(|home| std::path::Path::new(&home).join(relative))
+            .unwrap_or_else(|| path.to_owned()),
+        Err(_) => path.to_owned(),
+    }
+}
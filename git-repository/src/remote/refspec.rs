@@ -0,0 +1,213 @@
+//! Parse refspecs like `+refs/heads/*:refs/remotes/origin/*` and map ref names from their source to their
+//! destination.
+use git_object::bstr::{BStr, BString, ByteSlice, ByteVec};
+use quick_error::quick_error;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Empty {
+            display("A refspec must not be empty")
+        }
+        NegativeWithDestination {
+            display("Negative refspecs must not have a destination")
+        }
+        NegativeForced {
+            display("Negative refspecs can't be forced")
+        }
+        NegativeObjectId {
+            display("Negative refspecs must be ref names, not object ids")
+        }
+        PatternMismatch {
+            display("Either both sides of a refspec contain a '*' or neither does")
+        }
+        MultipleWildcards(side: BString) {
+            display("'{}' contains more than one '*'", side)
+        }
+        InvalidName(name: BString, err: git_ref::name::Error) {
+            display("'{}' is not a valid ref name", name)
+            source(err)
+        }
+    }
+}
+
+/// The direction in which a refspec is used, which affects how the source and destination are interpreted.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Operation {
+    /// The source is a ref of the remote and the destination a local ref.
+    Fetch,
+    /// The source is a local ref and the destination a ref of the remote.
+    Push,
+}
+
+/// A parsed refspec.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct RefSpec {
+    /// The operation the refspec was parsed for.
+    pub operation: Operation,
+    /// If true, the destination is updated even if it's not a fast-forward, written as leading `+`.
+    pub force: bool,
+    /// If true, refs matching the source are excluded, written as leading `^`.
+    pub negative: bool,
+    /// The ref to read from, which is `HEAD` if it was left empty when fetching, or `None` to delete the destination
+    /// when pushing.
+    pub source: Option<BString>,
+    /// The ref to write to. It's `None` when fetching without storing the ref.
+    pub destination: Option<BString>,
+}
+
+impl RefSpec {
+    /// Parse `spec` to be used for `operation`.
+    pub fn parse(spec: &BStr, operation: Operation) -> Result<Self, Error> {
+        let spec = spec.as_bytes();
+        if spec.is_empty() {
+            return Err(Error::Empty);
+        }
+        let (negative, force, spec) = match spec[0] {
+            b'^' => (true, false, &spec[1..]),
+            b'+' => (false, true, &spec[1..]),
+            _ => (false, false, spec),
+        };
+        if negative && spec.starts_with(b"+") {
+            return Err(Error::NegativeForced);
+        }
+        let (source, destination) = match spec.find_byte(b':') {
+            Some(pos) => (&spec[..pos], Some(&spec[pos + 1..])),
+            None => (spec, None),
+        };
+        if negative && destination.is_some() {
+            return Err(Error::NegativeWithDestination);
+        }
+
+        let source: Option<BString> = match (operation, source) {
+            (Operation::Fetch, b"") => Some("HEAD".into()),
+            (Operation::Push, b"") if destination.is_some() => None,
+            (Operation::Push, b"@") => Some("HEAD".into()),
+            (_, b"") => return Err(Error::Empty),
+            (_, source) => Some(source.into()),
+        };
+        let destination: Option<BString> = match destination {
+            Some(b"") if operation == Operation::Fetch => None,
+            Some(b"") => return Err(Error::Empty),
+            Some(destination) => Some(destination.into()),
+            None if operation == Operation::Push && !negative => source.clone(),
+            None => None,
+        };
+
+        let source_is_pattern = matches!(&source, Some(s) if is_pattern(s.as_ref()));
+        let destination_is_pattern = matches!(&destination, Some(d) if is_pattern(d.as_ref()));
+        if destination.is_some() && source.is_some() && source_is_pattern != destination_is_pattern {
+            return Err(Error::PatternMismatch);
+        }
+        if let Some(source) = &source {
+            if is_object_id(source.as_ref()) {
+                if negative {
+                    return Err(Error::NegativeObjectId);
+                }
+            } else {
+                validate(source.as_ref())?;
+            }
+        }
+        if let Some(destination) = &destination {
+            validate(destination.as_ref())?;
+        }
+        Ok(RefSpec {
+            operation,
+            force,
+            negative,
+            source,
+            destination,
+        })
+    }
+
+    /// Returns true if the source contains a `*` to match many refs.
+    pub fn is_pattern(&self) -> bool {
+        matches!(&self.source, Some(s) if is_pattern(s.as_ref()))
+    }
+
+    /// Returns true if the full ref `name` matches the source of this refspec.
+    ///
+    /// Sources without `*` also match the ref they would be expanded to, like `main` matches `refs/heads/main`.
+    pub fn matches(&self, name: &BStr) -> bool {
+        match &self.source {
+            None => false,
+            Some(source) if is_pattern(source.as_ref()) => match_pattern(source.as_ref(), name).is_some(),
+            Some(source) => {
+                name == source
+                    || ["refs/", "refs/tags/", "refs/heads/", "refs/remotes/"]
+                        .iter()
+                        .any(|prefix| name.strip_prefix(prefix.as_bytes()) == Some(source.as_bytes()))
+                    || name
+                        .strip_prefix(b"refs/remotes/")
+                        .and_then(|rest| rest.strip_suffix(b"/HEAD"))
+                        == Some(source.as_bytes())
+            }
+        }
+    }
+
+    /// Return the destination for the ref `name` if it matches, substituting the `*` of patterns.
+    pub fn expand(&self, name: &BStr) -> Option<BString> {
+        if self.negative || !self.matches(name) {
+            return None;
+        }
+        let destination = self.destination.as_ref()?;
+        match self.source.as_ref().and_then(|s| match_pattern(s.as_ref(), name)) {
+            Some(matched) => {
+                let star = destination.find_byte(b'*').expect("patterns on both sides");
+                let mut expanded = BString::from(&destination[..star]);
+                expanded.push_str(matched);
+                expanded.push_str(&destination[star + 1..]);
+                Some(expanded)
+            }
+            None => Some(destination.clone()),
+        }
+    }
+
+    /// Serialize this instance into the form it was parsed from.
+    pub fn to_bstring(&self) -> BString {
+        let mut out = BString::from(if self.negative {
+            "^"
+        } else if self.force {
+            "+"
+        } else {
+            ""
+        });
+        if let Some(source) = &self.source {
+            out.push_str(source);
+        }
+        if let Some(destination) = &self.destination {
+            out.push(b':');
+            out.push_str(destination);
+        }
+        out
+    }
+}
+
+fn is_pattern(name: &BStr) -> bool {
+    name.contains(&b'*')
+}
+
+fn is_object_id(name: &BStr) -> bool {
+    name.len() == 40 && name.iter().all(|b| b.is_ascii_hexdigit())
+}
+
+fn validate(name: &BStr) -> Result<(), Error> {
+    if name.iter().filter(|b| **b == b'*').count() > 1 {
+        return Err(Error::MultipleWildcards(name.to_owned()));
+    }
+    let without_wildcard = name.replace("*", "a");
+    git_ref::name::validate(without_wildcard.as_bstr()).map_err(|err| Error::InvalidName(name.to_owned(), err))?;
+    Ok(())
+}
+
+/// Return the part of `name` matched by the `*` in `pattern`.
+fn match_pattern<'a>(pattern: &BStr, name: &'a BStr) -> Option<&'a BStr> {
+    let star = pattern.find_byte(b'*')?;
+    let (prefix, suffix) = (&pattern[..star], &pattern[star + 1..]);
+    if name.len() < prefix.len() + suffix.len() || !name.starts_with(prefix) || !name.ends_with(suffix) {
+        return None;
+    }
+    Some(name[prefix.len()..name.len() - suffix.len()].as_bstr())
+}
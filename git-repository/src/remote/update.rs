@@ -0,0 +1,387 @@
+//! Apply the refs of a remote to local refs after fetching, as mapped by the fetch refspecs.
+//!
+//! Local refs are only moved forward unless the mapping is forced, and existing tags are never changed without force,
+//! like `git fetch` does. Each update writes the ref through a lock file and appends an entry to its reflog. Refs which
+//! were changed by another process since they were read are not written.
+//!
+//! If hooks are configured, the `reference-transaction` hook is run with the `prepared` state before any ref is
+//! written, which may reject all updates, and with the `committed` state once they were written.
+use crate::{hooks, remote::Mapping, worktree::linked};
+use git_features::lock;
+use git_object::{
+    borrowed,
+    bstr::{BStr, BString, ByteSlice},
+    owned,
+};
+use git_protocol::handshake::Ref;
+use quick_error::quick_error;
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        UnknownRemoteRef(name: BString) {
+            display("The remote ref '{}' of a mapping was not advertised by the remote", name)
+        }
+        InvalidRefName(name: BString, err: git_ref::name::Error) {
+            display("The local ref '{}' has an invalid name", name)
+            source(err)
+        }
+        CorruptRef(path: PathBuf) {
+            display("The ref at '{}' could not be parsed", path.display())
+        }
+        SymbolicRefLoop(name: BString) {
+            display("The symbolic ref '{}' could not be resolved as it points to too many other symbolic refs", name)
+        }
+        Locked(path: PathBuf) {
+            display("The lock at '{}' exists, another process might be updating the ref", path.display())
+        }
+        RefChanged(name: BString, expected: Option<owned::Id>, actual: Option<owned::Id>) {
+            display("The ref '{}' was changed while updating it: it points to {}, but {} was expected",
+                name,
+                actual.map(|id| id.to_string()).unwrap_or_else(|| "nothing".into()),
+                expected.map(|id| id.to_string()).unwrap_or_else(|| "nothing".into()))
+        }
+        Rejected(messages: BString) {
+            display("The reference-transaction hook rejected the updates:\n{}", messages)
+        }
+        ObjectMissing(id: owned::Id) {
+            display("The object {} needed to check for a fast-forward could not be found", id)
+        }
+        Worktree(err: linked::Error) {
+            display("The common git directory of the worktree could not be determined")
+            from()
+            source(err)
+        }
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not access '{}'", path.display())
+            source(err)
+        }
+    }
+}
+
+/// The maximum amount of symbolic refs to follow before giving up, like `git` does.
+const MAX_SYMBOLIC_REF_DEPTH: usize = 5;
+
+/// Configure how to update local refs.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Options {
+    /// The identity to record in the reflog as the one performing the update.
+    pub committer: owned::Signature,
+    /// The first part of the message of each reflog entry, like `fetch origin`, followed by the kind of update.
+    pub message: BString,
+    /// If true, only compute the outcome of each update but don't change any ref.
+    pub dry_run: bool,
+    /// The hooks to run when refs are written, or `None` to not run any.
+    pub hooks: Option<hooks::Runner>,
+}
+
+/// How a local ref was changed.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mode {
+    /// The local ref already pointed to the remote object.
+    NoChange,
+    /// The local ref did not exist and was created.
+    New,
+    /// The local ref was an ancestor of the remote object and was moved forward.
+    FastForward,
+    /// The local ref was overwritten even though the update wasn't a fast-forward, as the mapping was forced.
+    Forced,
+    /// The local ref was left alone as the update isn't a fast-forward, or would change an existing tag, and wasn't
+    /// forced.
+    Rejected,
+}
+
+impl Mode {
+    /// Returns true if the local ref was written.
+    pub fn is_update(&self) -> bool {
+        matches!(self, Mode::New | Mode::FastForward | Mode::Forced)
+    }
+}
+
+/// The outcome of applying a single [`Mapping`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Update {
+    /// The full name of the ref on the remote.
+    pub remote: BString,
+    /// The full name of the local ref, after following symbolic refs.
+    pub local: BString,
+    /// The object the local ref pointed to before, or `None` if it didn't exist.
+    pub previous: Option<owned::Id>,
+    /// The object the remote ref points to.
+    pub new: owned::Id,
+    /// How the local ref was changed.
+    pub mode: Mode,
+}
+
+/// Update the refs in `git_dir` according to `mappings`, setting them to the objects `remote_refs` point to.
+///
+/// `find` is used to lookup commits to determine if an update is a fast-forward, placing their data into the provided
+/// buffer and returning the decoded object. All objects of `remote_refs` must have been received at this point.
+///
+/// Mappings without a local ref, as well as remote refs which are unborn, are ignored.
+///
+/// The output of the `reference-transaction` hook is only kept if it rejects the updates, in which case no ref is
+/// written and it's part of the error.
+pub fn apply<Find>(
+    git_dir: &Path,
+    mappings: &[Mapping],
+    remote_refs: &[Ref],
+    mut find: Find,
+    options: &Options,
+) -> Result<Vec<Update>, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let mut buf = Vec::new();
+    let mut updates = Vec::with_capacity(mappings.len());
+    for mapping in mappings {
+        let local = match &mapping.local {
+            Some(local) => local,
+            None => continue,
+        };
+        let new = match remote_refs.iter().find(|r| r.unpack().0 == mapping.remote) {
+            Some(Ref::Peeled { tag, .. }) => *tag,
+            Some(Ref::Direct { object, .. }) | Some(Ref::Symbolic { object, .. }) => *object,
+            Some(Ref::Unborn { .. }) => continue,
+            None => return Err(Error::UnknownRemoteRef(mapping.remote.clone())),
+        };
+        git_ref::name::validate(local.as_ref()).map_err(|err| Error::InvalidRefName(local.clone(), err))?;
+        let (local, previous) = resolve(git_dir, local.as_ref())?;
+
+        let mode = match previous {
+            None => Mode::New,
+            Some(previous) if previous == new => Mode::NoChange,
+            Some(_) if mapping.force => Mode::Forced,
+            Some(_) if local.starts_with(b"refs/tags/") => Mode::Rejected,
+            Some(previous) => {
+                if is_ancestor(&mut find, &mut buf, previous, new).map_err(Error::ObjectMissing)? {
+                    Mode::FastForward
+                } else {
+                    Mode::Rejected
+                }
+            }
+        };
+        updates.push(Update {
+            remote: mapping.remote.clone(),
+            local,
+            previous,
+            new,
+            mode,
+        });
+    }
+    if options.dry_run || !updates.iter().any(|update| update.mode.is_update()) {
+        return Ok(updates);
+    }
+
+    let input = hooks::ref_updates_input(
+        updates
+            .iter()
+            .filter(|update| update.mode.is_update())
+            .map(|update| (update.previous, Some(update.new), update.local.as_ref())),
+    );
+    let run_hook = |state: &str, messages: &mut Vec<u8>| {
+        options
+            .hooks
+            .as_ref()
+            .and_then(|hooks| hooks.run("reference-transaction", &[state.into()], Some(&input), messages))
+            .unwrap_or(true)
+    };
+    let mut messages = Vec::new();
+    if !run_hook("prepared", &mut messages) {
+        run_hook("aborted", &mut Vec::new());
+        return Err(Error::Rejected(messages.into()));
+    }
+    for update in updates.iter().filter(|update| update.mode.is_update()) {
+        let message = reflog_message(options, update.local.as_ref(), update.mode);
+        write_ref(
+            git_dir,
+            update.local.as_ref(),
+            update.previous,
+            update.new,
+            message.as_ref(),
+            options,
+        )?;
+    }
+    run_hook("committed", &mut Vec::new());
+    Ok(updates)
+}
+
+fn reflog_message(options: &Options, local: &BStr, mode: Mode) -> BString {
+    let kind = match mode {
+        Mode::New if local.starts_with(b"refs/tags/") => "storing tag",
+        Mode::New if local.starts_with(b"refs/heads/") || local.starts_with(b"refs/remotes/") => "storing head",
+        Mode::New => "storing ref",
+        Mode::FastForward => "fast-forward",
+        Mode::Forced => "forced-update",
+        Mode::NoChange | Mode::Rejected => unreachable!("only called for refs that are written"),
+    };
+    let mut message = options.message.clone();
+    message.extend_from_slice(b": ");
+    message.extend_from_slice(kind.as_bytes());
+    message
+}
+
+/// Follow `name` through symbolic refs and return the name of the direct ref along with the object it points to, if
+/// it exists.
+pub(crate) fn resolve(git_dir: &Path, name: &BStr) -> Result<(BString, Option<owned::Id>), Error> {
+    let mut name = name.to_owned();
+    for _ in 0..MAX_SYMBOLIC_REF_DEPTH {
+        let path = ref_path(git_dir, name.as_ref())?;
+        let content = match fs::read(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                let id = find_packed(git_dir, name.as_ref())?;
+                return Ok((name, id));
+            }
+            Err(err) => return Err(Error::Io(err, path)),
+        };
+        let content = content.trim_end_with(char::is_whitespace);
+        match content.strip_prefix(b"ref: ") {
+            Some(target) => {
+                name = target.trim_start_with(char::is_whitespace).as_bstr().to_owned();
+                git_ref::name::validate(name.as_ref()).map_err(|err| Error::InvalidRefName(name.clone(), err))?;
+            }
+            None => {
+                let id = owned::Id::from_40_bytes_in_hex(content).map_err(|_| Error::CorruptRef(path))?;
+                return Ok((name, Some(id)));
+            }
+        }
+    }
+    Err(Error::SymbolicRefLoop(name))
+}
+
+/// Lookup `name` in the `packed-refs` file, if there is one.
+fn find_packed(git_dir: &Path, name: &BStr) -> Result<Option<owned::Id>, Error> {
+    let path = linked::common_dir(git_dir)?.join("packed-refs");
+    let content = match fs::read(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(Error::Io(err, path)),
+    };
+    for line in content.lines() {
+        if line.starts_with(b"#") || line.starts_with(b"^") {
+            continue;
+        }
+        let mut tokens = line.splitn(2, |b| *b == b' ');
+        if let (Some(hex), Some(ref_name)) = (tokens.next(), tokens.next()) {
+            if ref_name == name.as_bytes() {
+                return owned::Id::from_40_bytes_in_hex(hex)
+                    .map(Some)
+                    .map_err(|_| Error::CorruptRef(path));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn ref_path(git_dir: &Path, name: &BStr) -> Result<PathBuf, Error> {
+    let relative = git_features::path::to_native(name, Default::default())
+        .map_err(|_| Error::CorruptRef(git_dir.join(name.to_str_lossy().as_ref())))?;
+    Ok(git_features::path::long(linked::ref_dir(git_dir, name)?.join(relative)))
+}
+
+/// Returns true if `ancestor` can be reached by following the parents of `commit`, or the id of the first object that
+/// could not be found.
+pub(crate) fn is_ancestor<Find>(
+    find: &mut Find,
+    buf: &mut Vec<u8>,
+    ancestor: owned::Id,
+    commit: owned::Id,
+) -> Result<bool, owned::Id>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    match find(ancestor.to_borrowed(), buf) {
+        Some(borrowed::Object::Commit(_)) => {}
+        Some(_) => return Ok(false),
+        None => return Err(ancestor),
+    }
+    let mut seen = HashSet::new();
+    let mut queue = vec![commit];
+    while let Some(id) = queue.pop() {
+        if id == ancestor {
+            return Ok(true);
+        }
+        if !seen.insert(id) {
+            continue;
+        }
+        match find(id.to_borrowed(), buf) {
+            Some(borrowed::Object::Commit(commit)) => queue.extend(commit.parents()),
+            Some(_) => return Ok(false),
+            None => return Err(id),
+        }
+    }
+    Ok(false)
+}
+
+/// Point `name` from `previous` to `new` by writing a lock file and moving it into place, and record the change in its
+/// reflog once it is in place.
+///
+/// Once the lock is held, `name` is read again and [`Error::RefChanged`] is returned if it doesn't point to `previous`
+/// anymore, so concurrent updates can't be lost.
+pub(crate) fn write_ref(
+    git_dir: &Path,
+    name: &BStr,
+    previous: Option<owned::Id>,
+    new: owned::Id,
+    message: &BStr,
+    options: &Options,
+) -> Result<(), Error> {
+    let path = ref_path(git_dir, name)?;
+    let mut lock = lock::File::acquire(&path, Default::default()).map_err(lock_error)?;
+    let (_, current) = resolve(git_dir, name)?;
+    if current != previous {
+        return Err(Error::RefChanged(name.to_owned(), previous, current));
+    }
+    lock.write_all(&new.to_sha1_hex())
+        .and_then(|_| lock.write_all(b"\n"))
+        .map_err(|err| Error::Io(err, path.clone()))?;
+    lock.commit().map_err(lock_error)?;
+    append_reflog(git_dir, name, previous, new, message, options).map_err(|err| Error::Io(err, path))
+}
+
+fn lock_error(err: lock::Error) -> Error {
+    match err {
+        lock::Error::Locked(path) => Error::Locked(path),
+        lock::Error::Io(err, path) => Error::Io(err, path),
+    }
+}
+
+pub(crate) fn append_reflog(
+    git_dir: &Path,
+    name: &BStr,
+    previous: Option<owned::Id>,
+    new: owned::Id,
+    message: &BStr,
+    options: &Options,
+) -> io::Result<()> {
+    let path = linked::ref_dir(git_dir, name)
+        .map_err(io::Error::other)?
+        .join("logs")
+        .join(git_features::path::to_native(name, Default::default()).map_err(io::Error::other)?);
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    let mut line = Vec::new();
+    line.extend_from_slice(&previous.unwrap_or_else(owned::Id::null_sha1).to_sha1_hex());
+    line.push(b' ');
+    line.extend_from_slice(&new.to_sha1_hex());
+    line.push(b' ');
+    options.committer.write_to(&mut line)?;
+    line.push(b'\t');
+    line.extend(message.iter().map(|b| if *b == b'\n' { b' ' } else { *b }));
+    line.push(b'\n');
+    fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)?
+        .write_all(&line)
+}
@@ -0,0 +1,104 @@
+//! Matching urls against the patterns of `http.<url>.*` sections like git does.
+use git_object::bstr::{BStr, ByteSlice};
+
+/// How well a pattern matches a url, where patterns matching more of the url are more specific.
+///
+/// Like in git, the length of the host pattern counts the most, followed by the length of the matched path and
+/// whether the pattern names the user.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Default)]
+pub(crate) struct Specificity {
+    host: usize,
+    path: usize,
+    user: bool,
+}
+
+struct Parts<'a> {
+    scheme: String,
+    user: Option<&'a [u8]>,
+    host: String,
+    port: Option<&'a [u8]>,
+    path: &'a [u8],
+}
+
+/// Return how specific `pattern` is if it matches `url`. Hosts are compared case-insensitively and `*` matches any
+/// single label of a host, while paths match if the pattern is a prefix ending at a `/`.
+pub(crate) fn specificity(pattern: &BStr, url: &BStr) -> Option<Specificity> {
+    let pattern = parse(pattern)?;
+    let url = parse(url)?;
+    if pattern.scheme != url.scheme || pattern.port != url.port || !hosts_match(&pattern.host, &url.host) {
+        return None;
+    }
+    if pattern.user.is_some() && pattern.user != url.user {
+        return None;
+    }
+    Some(Specificity {
+        host: pattern.host.len(),
+        path: path_match(pattern.path, url.path)?,
+        user: pattern.user.is_some(),
+    })
+}
+
+fn parse(url: &[u8]) -> Option<Parts<'_>> {
+    let scheme_end = url.find("://")?;
+    let scheme = url[..scheme_end].to_str().ok()?.to_ascii_lowercase();
+    let rest = &url[scheme_end + 3..];
+    let (authority, path) = match rest.find_byte(b'/') {
+        Some(pos) => rest.split_at(pos),
+        None => (rest, &b""[..]),
+    };
+    let (user, host_and_port) = match authority.rfind_byte(b'@') {
+        Some(pos) => {
+            let user = &authority[..pos];
+            (
+                Some(user.find_byte(b':').map_or(user, |colon| &user[..colon])),
+                &authority[pos + 1..],
+            )
+        }
+        None => (None, authority),
+    };
+    let (host, port) = match host_and_port.rfind_byte(b':') {
+        Some(pos) if !host_and_port[pos..].contains(&b']') => (&host_and_port[..pos], Some(&host_and_port[pos + 1..])),
+        _ => (host_and_port, None),
+    };
+    let default_port: &[u8] = match scheme.as_str() {
+        "http" => b"80",
+        "https" => b"443",
+        _ => b"",
+    };
+    Some(Parts {
+        port: port.filter(|port| !port.is_empty() && *port != default_port),
+        scheme,
+        user,
+        host: host.to_str().ok()?.to_ascii_lowercase(),
+        path,
+    })
+}
+
+fn hosts_match(pattern: &str, host: &str) -> bool {
+    let mut pattern = pattern.split('.');
+    let mut host = host.split('.');
+    loop {
+        match (pattern.next(), host.next()) {
+            (Some(pattern), Some(label)) if pattern == "*" || pattern == label => continue,
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Return one more than the length of the matched path, or 1 if the pattern has no path.
+fn path_match(pattern: &[u8], path: &[u8]) -> Option<usize> {
+    if pattern.is_empty() || pattern == b"/" {
+        return if path.is_empty() || path[0] == b'/' {
+            Some(1)
+        } else {
+            None
+        };
+    }
+    let pattern = pattern.strip_suffix(b"/").unwrap_or(pattern);
+    if path.starts_with(pattern) && (path.len() == pattern.len() || path[pattern.len()] == b'/') {
+        Some(pattern.len() + 1)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,76 @@
+//! Resolve revisions as given on the command-line to the objects they name.
+//!
+//! A revision is a full object id, the name of a ref, or a unique prefix of an object id with at least
+//! [four][git_odb::prefix::MIN_HEX_LEN] hex digits. Like `git`, names are looked up as given, then in `refs/`,
+//! `refs/tags/`, `refs/heads/` and `refs/remotes/`, and as `refs/remotes/<name>/HEAD`, with refs taking precedence over
+//! prefixes. Suffixes like `~` and `^` to navigate from there are not supported yet.
+use crate::remote::update;
+use git_object::{
+    bstr::{BStr, BString, ByteSlice},
+    owned, SHA1_SIZE,
+};
+use git_odb::{compound, prefix};
+use quick_error::quick_error;
+use std::path::Path;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        NotFound(spec: BString) {
+            display("'{}' is neither a ref nor an object id prefix of an existing object", spec)
+        }
+        Ref(err: update::Error) {
+            display("A ref could not be resolved")
+            from()
+            source(err)
+        }
+        Prefix(err: prefix::Error) {
+            display("The object id prefix could not be resolved")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// The patterns names are looked up with, in order, where `%s` is replaced by the name.
+const REF_RULES: &[&str] = &[
+    "%s",
+    "refs/%s",
+    "refs/tags/%s",
+    "refs/heads/%s",
+    "refs/remotes/%s",
+    "refs/remotes/%s/HEAD",
+];
+
+/// Resolve `spec` to the id of the object it names in the repository at `git_dir` whose objects are in `db`.
+///
+/// The object of a full id doesn't have to exist, but the one of a ref or prefix does.
+pub fn resolve(git_dir: impl AsRef<Path>, db: &compound::Db, spec: &BStr) -> Result<owned::Id, Error> {
+    if spec.len() == SHA1_SIZE * 2 {
+        if let Ok(id) = owned::Id::from_40_bytes_in_hex(spec) {
+            return Ok(id);
+        }
+    }
+    if let Some(id) = find_ref(git_dir.as_ref(), spec)? {
+        return Ok(id);
+    }
+    match db.lookup_prefix(spec) {
+        Ok(Some(id)) => Ok(id),
+        Ok(None) | Err(prefix::Error::Invalid { .. }) => Err(Error::NotFound(spec.to_owned())),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Return the id the first ref matching `name` points to, or `None` if there is none.
+fn find_ref(git_dir: &Path, name: &BStr) -> Result<Option<owned::Id>, Error> {
+    for rule in REF_RULES {
+        let full_name = BString::from(rule.replace("%s", &name.to_str_lossy()));
+        if git_ref::name::validate(full_name.as_ref()).is_err() {
+            continue;
+        }
+        if let (_, Some(id)) = update::resolve(git_dir, full_name.as_ref())? {
+            return Ok(Some(id));
+        }
+    }
+    Ok(None)
+}
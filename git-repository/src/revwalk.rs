@@ -0,0 +1,323 @@
+//! Walk the commits reachable from a set of tips newest first, like `git rev-list` does, optionally limited to the
+//! commits changing a set of paths.
+//!
+//! If paths are given, history is simplified like git does by default. A commit with the same content at these paths
+//! as one of its parents, which git calls TREESAME, isn't returned and only the first such parent is followed. All
+//! other commits are returned and all of their parents are followed. The parents of returned commits are rewritten
+//! to their closest returned ancestors, as shown by `git rev-list --parents <tips> -- <paths>`.
+//!
+//! Comparing a commit to its first parent uses the changed-path Bloom filter of the commit if a commit-graph with
+//! such filters is provided, which avoids the tree diff for most commits not changing the paths.
+use crate::commit_graph;
+use git_object::{
+    borrowed,
+    bstr::{BStr, BString, ByteSlice},
+    owned, TreeMode,
+};
+use quick_error::quick_error;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Find(id: owned::Id) {
+            display("Object {} could not be found", id)
+        }
+        NotACommit(id: owned::Id) {
+            display("Object {} was expected to be a commit", id)
+        }
+        NotATree(id: owned::Id) {
+            display("Object {} was expected to be a tree", id)
+        }
+    }
+}
+
+/// Configure which commits to return.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Options {
+    /// The files or directories, relative to the root of the repository, whose changes a commit must contain to be
+    /// returned, or all commits if empty.
+    ///
+    /// Paths are literal and match all files below them, like pathspecs without wildcards.
+    pub paths: Vec<BString>,
+    /// The commit-graph whose changed-path Bloom filters to use for comparing commits to their first parent.
+    pub commit_graph: Option<commit_graph::File>,
+}
+
+/// A commit returned by the walk.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Commit {
+    /// The id of the commit.
+    pub id: owned::Id,
+    /// The parents of the commit, which are rewritten to their closest returned ancestors if paths are given.
+    pub parents: Vec<owned::Id>,
+}
+
+/// How much work the walk did.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy, Default)]
+pub struct Statistics {
+    /// The amount of comparisons of a commit with one of its parents which needed a tree diff.
+    pub tree_diffs: usize,
+    /// The amount of comparisons which could be answered by a Bloom filter, as it didn't contain the paths.
+    pub bloom_filter_hits: usize,
+}
+
+/// What to do with a commit.
+#[derive(Debug, Clone)]
+enum Decision {
+    /// Return the commit and follow all of its parents.
+    Show(Vec<owned::Id>),
+    /// Skip the commit as it's TREESAME to the given parent, which is the only one followed.
+    SameAs(owned::Id),
+    /// Skip the commit as it's a root commit without any of the paths.
+    Empty,
+}
+
+/// An iterator over the commits of a walk, see [`walk()`].
+pub struct Walk<Find> {
+    find: Find,
+    options: Options,
+    queue: BinaryHeap<(u32, Reverse<usize>, owned::Id)>,
+    queued: usize,
+    seen: HashSet<owned::Id>,
+    decisions: HashMap<owned::Id, Decision>,
+    tips: Vec<owned::Id>,
+    buf: Vec<u8>,
+    statistics: Statistics,
+}
+
+/// Walk the commits reachable from `tips` according to `options`, looking up objects using `find`, which places their
+/// data into the provided buffer and returns the decoded object.
+///
+/// Commits are returned in order of their commit time, newest first, and those with the same time in the order they
+/// were found in.
+pub fn walk<Find>(tips: impl IntoIterator<Item = owned::Id>, find: Find, mut options: Options) -> Walk<Find>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    for path in &mut options.paths {
+        let trimmed = path.trim_end_with(|c| c == '/').len();
+        path.truncate(trimmed);
+    }
+    Walk {
+        find,
+        options,
+        queue: BinaryHeap::new(),
+        queued: 0,
+        seen: HashSet::new(),
+        decisions: HashMap::new(),
+        tips: tips.into_iter().collect(),
+        buf: Vec::new(),
+        statistics: Statistics::default(),
+    }
+}
+
+impl<Find> Walk<Find> {
+    /// How much work the walk did so far.
+    pub fn statistics(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+impl<Find> Walk<Find>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    fn queue(&mut self, id: owned::Id) -> Result<(), Error> {
+        if self.seen.insert(id) {
+            let time = find_commit(&mut self.find, &mut self.buf, id)?.committer.time.time;
+            self.queue.push((time, Reverse(self.queued), id));
+            self.queued += 1;
+        }
+        Ok(())
+    }
+
+    fn decide(&mut self, id: owned::Id) -> Result<Decision, Error> {
+        if let Some(decision) = self.decisions.get(&id) {
+            return Ok(decision.clone());
+        }
+        let (tree, parents) = {
+            let commit = find_commit(&mut self.find, &mut self.buf, id)?;
+            (commit.tree(), commit.parents().collect::<Vec<_>>())
+        };
+        let decision = if self.options.paths.is_empty() {
+            Decision::Show(parents)
+        } else if parents.is_empty() {
+            self.statistics.tree_diffs += 1;
+            if self.differs(None, Some(tree), b"".as_bstr())? {
+                Decision::Show(parents)
+            } else {
+                Decision::Empty
+            }
+        } else {
+            let mut same_as = None;
+            for (index, parent) in parents.iter().enumerate() {
+                if index == 0 && !self.may_differ_by_bloom_filter(&id) {
+                    self.statistics.bloom_filter_hits += 1;
+                    same_as = Some(*parent);
+                    break;
+                }
+                let parent_tree = find_commit(&mut self.find, &mut self.buf, *parent)?.tree();
+                self.statistics.tree_diffs += 1;
+                if !self.differs(Some(parent_tree), Some(tree), b"".as_bstr())? {
+                    same_as = Some(*parent);
+                    break;
+                }
+            }
+            match same_as {
+                Some(parent) => Decision::SameAs(parent),
+                None => Decision::Show(parents),
+            }
+        };
+        self.decisions.insert(id, decision.clone());
+        Ok(decision)
+    }
+
+    /// Return false if the Bloom filter of `id` shows that none of the paths changed compared to its first parent.
+    fn may_differ_by_bloom_filter(&self, id: &owned::Id) -> bool {
+        let filter = match self
+            .options
+            .commit_graph
+            .as_ref()
+            .and_then(|graph| graph.bloom_filter(id))
+        {
+            Some(filter) => filter,
+            None => return true,
+        };
+        // The filter contains all changed paths along with their directories.
+        self.options.paths.iter().any(|path| {
+            let mut prefix = path.as_slice();
+            loop {
+                if !filter.may_contain(prefix) {
+                    return false;
+                }
+                match prefix.rfind_byte(b'/') {
+                    Some(pos) => prefix = &prefix[..pos],
+                    None => return true,
+                }
+            }
+        })
+    }
+
+    /// Return true if the trees `a` and `b`, which are at `prefix` and may not exist, differ at any of the paths.
+    fn differs(&mut self, a: Option<owned::Id>, b: Option<owned::Id>, prefix: &BStr) -> Result<bool, Error> {
+        if a == b {
+            return Ok(false);
+        }
+        let mut entries = BTreeMap::<BString, [Option<(TreeMode, owned::Id)>; 2]>::new();
+        for (side, tree) in [a, b].iter().enumerate() {
+            let tree = match tree {
+                Some(tree) => *tree,
+                None => continue,
+            };
+            match (self.find)(tree.to_borrowed(), &mut self.buf).ok_or(Error::Find(tree))? {
+                borrowed::Object::Tree(tree) => {
+                    for entry in tree.entries {
+                        entries.entry(entry.filename.to_owned()).or_default()[side] =
+                            Some((entry.mode, owned::Id::from_borrowed_sha1(entry.oid.sha1())));
+                    }
+                }
+                _ => return Err(Error::NotATree(tree)),
+            }
+        }
+
+        for (name, [a, b]) in entries {
+            if a == b {
+                continue;
+            }
+            let mut path = BString::from(prefix.as_bytes());
+            if !path.is_empty() {
+                path.push(b'/');
+            }
+            path.extend_from_slice(&name);
+            let is_within_paths = self.options.paths.iter().any(|p| is_within(path.as_slice(), p));
+            if is_within_paths {
+                return Ok(true);
+            }
+            let leads_to_paths = self.options.paths.iter().any(|p| is_within(p, path.as_slice()));
+            if leads_to_paths {
+                let tree = |entry: Option<(TreeMode, owned::Id)>| match entry {
+                    Some((TreeMode::Tree, id)) => Some(id),
+                    _ => None,
+                };
+                if self.differs(tree(a), tree(b), path.as_bstr())? {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Follow `id` through skipped commits to the closest one that is returned, if there is any.
+    fn rewrite(&mut self, mut id: owned::Id) -> Result<Option<owned::Id>, Error> {
+        loop {
+            match self.decide(id)? {
+                Decision::Show(_) => return Ok(Some(id)),
+                Decision::SameAs(parent) => id = parent,
+                Decision::Empty => return Ok(None),
+            }
+        }
+    }
+
+    fn next_commit(&mut self) -> Result<Option<Commit>, Error> {
+        for tip in std::mem::take(&mut self.tips) {
+            self.queue(tip)?;
+        }
+        while let Some((_, _, id)) = self.queue.pop() {
+            match self.decide(id)? {
+                Decision::Show(parents) => {
+                    for parent in &parents {
+                        self.queue(*parent)?;
+                    }
+                    let mut rewritten = Vec::with_capacity(parents.len());
+                    for parent in parents {
+                        if let Some(parent) = self.rewrite(parent)? {
+                            if !rewritten.contains(&parent) {
+                                rewritten.push(parent);
+                            }
+                        }
+                    }
+                    return Ok(Some(Commit { id, parents: rewritten }));
+                }
+                Decision::SameAs(parent) => self.queue(parent)?,
+                Decision::Empty => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<Find> Iterator for Walk<Find>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    type Item = Result<Commit, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_commit() {
+            Ok(commit) => commit.map(Ok),
+            Err(err) => {
+                self.queue.clear();
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Return true if `path` is `directory` or inside of it.
+fn is_within(path: &[u8], directory: &[u8]) -> bool {
+    path.starts_with(directory) && (path.len() == directory.len() || path[directory.len()] == b'/')
+}
+
+fn find_commit<'b, Find>(find: &mut Find, buf: &'b mut Vec<u8>, id: owned::Id) -> Result<borrowed::Commit<'b>, Error>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>>,
+{
+    match find(id.to_borrowed(), buf).ok_or(Error::Find(id))? {
+        borrowed::Object::Commit(commit) => Ok(commit),
+        _ => Err(Error::NotACommit(id)),
+    }
+}
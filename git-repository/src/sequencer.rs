@@ -0,0 +1,291 @@
+//! Apply the changes of a commit on top of another commit, or revert them, like `git cherry-pick` and `git revert` do.
+//!
+//! The changes are applied with a [three-way merge][crate::merge]. Picking a commit merges it into the commit it's
+//! picked onto with its parent as merge base, while reverting a commit merges its parent with the commit itself as
+//! merge base. If there are no conflicts, a commit of the merged tree is created, and a ref pointing to the commit it's
+//! based on may be moved to it.
+//!
+//! Otherwise, like git, the state of the operation is written into the git directory: `CHERRY_PICK_HEAD` or
+//! `REVERT_HEAD` contains the commit which was picked or reverted, `MERGE_MSG` the message of the commit to create with
+//! a list of the conflicting paths, and `AUTO_MERGE` the merged tree with conflict markers. Neither the worktree nor the
+//! index are changed.
+use crate::{commit, merge, remote::update, worktree::linked};
+use git_object::{
+    borrowed,
+    bstr::{BString, ByteSlice},
+    owned, HashKind,
+};
+use git_odb::{loose, Write};
+use quick_error::quick_error;
+use std::{fs, path::Path};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Find(id: owned::Id) {
+            display("Object {} could not be found", id)
+        }
+        NotACommit(id: owned::Id) {
+            display("Object {} was expected to be a commit", id)
+        }
+        MergeCommit(id: owned::Id) {
+            display("Commit {} is a merge, which can't be picked or reverted", id)
+        }
+        RefChanged(name: BString, expected: owned::Id, actual: Option<owned::Id>) {
+            display("The ref '{}' was expected to point to {}, but it points to {}",
+                name,
+                expected,
+                actual.map(|id| id.to_string()).unwrap_or_else(|| "nothing".into()))
+        }
+        Unborn(name: BString) {
+            display("The ref '{}' doesn't point to a commit yet", name)
+        }
+        Merge(err: merge::Error) {
+            display("The changes could not be merged")
+            from()
+            source(err)
+        }
+        Commit(err: commit::Error) {
+            display("The commit could not be created")
+            from()
+            source(err)
+        }
+        Ref(err: update::Error) {
+            display("The ref could not be read")
+            from()
+            source(err)
+        }
+        WriteObject(err: loose::db::write::Error) {
+            display("An object could not be written")
+            from()
+            source(err)
+        }
+        Worktree(err: linked::Error) {
+            display("The common git directory of the worktree could not be determined")
+            from()
+            source(err)
+        }
+        Io(err: std::io::Error, path: std::path::PathBuf) {
+            display("Could not write '{}'", path.display())
+            source(err)
+        }
+    }
+}
+
+/// Configure how to create the commit.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Options {
+    /// The committer of the new commit, who is also its author when reverting.
+    pub committer: owned::Signature,
+    /// The ref to point to the new commit, like `HEAD`, which must point to the commit the changes are applied to, or
+    /// `None` to only create the commit.
+    pub reference: Option<BString>,
+}
+
+/// The result of [`cherry_pick()`] and [`revert()`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Outcome {
+    /// The commit with the merged tree was created.
+    Commit(owned::Id),
+    /// Nothing changed as the commit the changes were applied to already contains them, and no commit was created.
+    Empty,
+    /// The changes conflict, and the state of the operation was written into the git directory.
+    Conflict(merge::Outcome),
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum Operation {
+    CherryPick,
+    Revert,
+}
+
+/// Apply the changes `commit` made compared to its parent onto the commit `onto` in the repository at `git_dir`,
+/// looking up objects using `find`, which places their data into the provided buffer and returns the decoded object.
+///
+/// The new commit keeps the author and message of `commit`.
+pub fn cherry_pick<Find>(
+    git_dir: impl AsRef<Path>,
+    commit: owned::Id,
+    onto: owned::Id,
+    find: Find,
+    options: Options,
+) -> Result<Outcome, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    apply(git_dir.as_ref(), Operation::CherryPick, commit, onto, find, options)
+}
+
+/// Undo the changes `commit` made compared to its parent on top of the commit [`reference`][Options::reference], or
+/// `HEAD` if it's `None`, points to in the repository at `git_dir`, looking up objects using `find`.
+///
+/// Like git, the new commit has the message `Revert "<subject>"` and explains which commit it reverts.
+pub fn revert<Find>(
+    git_dir: impl AsRef<Path>,
+    commit: owned::Id,
+    find: Find,
+    options: Options,
+) -> Result<Outcome, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let git_dir = git_dir.as_ref();
+    let name = options.reference.clone().unwrap_or_else(|| "HEAD".into());
+    let onto = match update::resolve(git_dir, name.as_ref())? {
+        (_, Some(onto)) => onto,
+        (name, None) => return Err(Error::Unborn(name)),
+    };
+    apply(git_dir, Operation::Revert, commit, onto, find, options)
+}
+
+fn apply<Find>(
+    git_dir: &Path,
+    operation: Operation,
+    id: owned::Id,
+    onto: owned::Id,
+    mut find: Find,
+    options: Options,
+) -> Result<Outcome, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let mut buf = Vec::new();
+    let picked = find_commit(&mut find, &mut buf, id)?;
+    let parent = match picked.parents.as_slice() {
+        [] => None,
+        [parent] => Some(find_commit(&mut find, &mut buf, *parent)?.tree),
+        _ => return Err(Error::MergeCommit(id)),
+    };
+    let ours = find_commit(&mut find, &mut buf, onto)?.tree;
+    let reference = match &options.reference {
+        Some(requested) => {
+            let (reference, previous) = update::resolve(git_dir, requested.as_ref())?;
+            if previous != Some(onto) {
+                return Err(Error::RefChanged(reference, onto, previous));
+            }
+            Some((requested, reference))
+        }
+        None => None,
+    };
+
+    let db = loose::Db::at(linked::common_dir(git_dir)?.join("objects"));
+    let subject = picked.message.lines().next().unwrap_or_default().as_bstr();
+    let mut label = BString::from(match operation {
+        Operation::CherryPick => "",
+        Operation::Revert => "parent of ",
+    });
+    label.extend_from_slice(&id.to_sha1_hex()[..7]);
+    label.extend_from_slice(b" (");
+    label.extend_from_slice(subject);
+    label.push(b')');
+    let (base, theirs, message, author, head_file) = match operation {
+        Operation::CherryPick => (
+            parent,
+            picked.tree,
+            picked.message.clone(),
+            picked.author.clone(),
+            "CHERRY_PICK_HEAD",
+        ),
+        Operation::Revert => {
+            let theirs = match parent {
+                Some(tree) => tree,
+                None => db.write(
+                    &owned::Object::Tree(owned::Tree { entries: Vec::new() }),
+                    HashKind::Sha1,
+                )?,
+            };
+            let mut message = BString::from("Revert \"");
+            message.extend_from_slice(subject);
+            message.extend_from_slice(b"\"\n\nThis reverts commit ");
+            message.extend_from_slice(&id.to_sha1_hex());
+            message.extend_from_slice(b".\n");
+            (
+                Some(picked.tree),
+                theirs,
+                message,
+                options.committer.clone(),
+                "REVERT_HEAD",
+            )
+        }
+    };
+    let merged = merge::trees(
+        base,
+        ours,
+        theirs,
+        &mut find,
+        &db,
+        merge::Labels {
+            ours: "HEAD".into(),
+            theirs: label.as_ref(),
+        },
+    )?;
+
+    if !merged.conflicts.is_empty() {
+        let mut merge_message = message.clone();
+        if !merge_message.ends_with(b"\n") {
+            merge_message.push(b'\n');
+        }
+        merge_message.extend_from_slice(b"\n# Conflicts:\n");
+        for conflict in &merged.conflicts {
+            merge_message.extend_from_slice(b"#\t");
+            merge_message.extend_from_slice(&conflict.path);
+            merge_message.push(b'\n');
+        }
+        for (file, content) in &[
+            (head_file, hex_line(id)),
+            ("MERGE_MSG", merge_message.into()),
+            ("AUTO_MERGE", hex_line(merged.tree)),
+        ] {
+            let path = git_dir.join(file);
+            fs::write(&path, content).map_err(|err| Error::Io(err, path))?;
+        }
+        return Ok(Outcome::Conflict(merged));
+    }
+    if merged.tree == ours {
+        return Ok(Outcome::Empty);
+    }
+
+    let commit = owned::Commit {
+        tree: merged.tree,
+        parents: std::iter::once(onto).collect(),
+        author,
+        committer: options.committer.clone(),
+        encoding: None,
+        message,
+        extra_headers: Vec::new(),
+    };
+    let new = commit::write(git_dir, &commit)?;
+    if let Some((requested, reference)) = reference {
+        let mut reflog_message = BString::from(match operation {
+            Operation::CherryPick => "cherry-pick: ",
+            Operation::Revert => "revert: ",
+        });
+        reflog_message.extend_from_slice(commit.message.lines().next().unwrap_or_default());
+        commit::move_ref(
+            git_dir,
+            requested.as_ref(),
+            reference.as_ref(),
+            Some(onto),
+            new,
+            &commit.committer,
+            reflog_message.as_ref(),
+        )?;
+    }
+    Ok(Outcome::Commit(new))
+}
+
+fn hex_line(id: owned::Id) -> Vec<u8> {
+    let mut line = id.to_sha1_hex().to_vec();
+    line.push(b'\n');
+    line
+}
+
+fn find_commit<Find>(find: &mut Find, buf: &mut Vec<u8>, id: owned::Id) -> Result<owned::Commit, Error>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<borrowed::Object<'a>>,
+{
+    match find(id.to_borrowed(), buf).ok_or(Error::Find(id))? {
+        borrowed::Object::Commit(commit) => Ok(commit.into()),
+        _ => Err(Error::NotACommit(id)),
+    }
+}
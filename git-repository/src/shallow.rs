@@ -0,0 +1,123 @@
+//! Read and write the `shallow` file listing the commits at the boundary of a shallow repository.
+//!
+//! Commits listed in the shallow file are treated as if they had no parents, as their parents are not present in the
+//! object database.
+use git_features::lock;
+use git_object::{bstr::ByteSlice, owned};
+use quick_error::quick_error;
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not access shallow file at '{}'", path.display())
+            source(err)
+        }
+        Lock(err: lock::Error) {
+            display("Could not lock the shallow file")
+            from()
+            source(err)
+        }
+        Parse(line_number: usize) {
+            display("Line {} of the shallow file is not a hexadecimal object id", line_number)
+        }
+    }
+}
+
+/// The name of the file listing the shallow commits, relative to the git directory.
+pub const FILE_NAME: &str = "shallow";
+
+/// A change to the shallow boundary as communicated by the server when fetching with `deepen` arguments.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Update {
+    /// The commit becomes a shallow commit, its parents are not available.
+    Shallow(owned::Id),
+    /// The parents of the commit are available now and it's not a shallow commit anymore.
+    Unshallow(owned::Id),
+}
+
+/// The set of commits whose parents are not available, as stored in `$GIT_DIR/shallow`.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Boundary {
+    commits: BTreeSet<owned::Id>,
+}
+
+impl Boundary {
+    /// Parse the contents of a shallow file, one hexadecimal commit id per line.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let commits = data
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(line_number, line)| owned::Id::from_40_bytes_in_hex(line).map_err(|_| Error::Parse(line_number + 1)))
+            .collect::<Result<_, _>>()?;
+        Ok(Boundary { commits })
+    }
+
+    /// Read the shallow file from the given `git_dir`. A missing file means the repository is not shallow.
+    pub fn at(git_dir: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = git_dir.as_ref().join(FILE_NAME);
+        match fs::read(&path) {
+            Ok(data) => Self::from_bytes(&data),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Boundary::default()),
+            Err(err) => Err(Error::Io(err, path)),
+        }
+    }
+
+    /// Write all commits, sorted, into the shallow file in `git_dir` or remove it if there are no shallow commits.
+    ///
+    /// The file is written to `shallow.lock` first and moved into place afterwards.
+    pub fn write_to(&self, git_dir: impl AsRef<Path>) -> Result<(), Error> {
+        let path = git_dir.as_ref().join(FILE_NAME);
+        if self.commits.is_empty() {
+            return match fs::remove_file(&path) {
+                Ok(()) => Ok(()),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+                Err(err) => Err(Error::Io(err, path)),
+            };
+        }
+        let mut lock = lock::File::acquire(path, Default::default())?;
+        let mut buf = Vec::with_capacity(self.commits.len() * 41);
+        for id in &self.commits {
+            buf.extend_from_slice(&id.to_sha1_hex());
+            buf.push(b'\n');
+        }
+        lock.write_all(&buf)
+            .map_err(|err| Error::Io(err, lock.lock_path().to_owned()))?;
+        lock.commit()?;
+        Ok(())
+    }
+
+    /// Returns true if there is at least one shallow commit.
+    pub fn is_shallow(&self) -> bool {
+        !self.commits.is_empty()
+    }
+
+    /// Returns true if `id` is a shallow commit whose parents must be ignored.
+    pub fn contains(&self, id: &owned::Id) -> bool {
+        self.commits.contains(id)
+    }
+
+    /// Return all shallow commits in sorted order.
+    pub fn commits(&self) -> impl Iterator<Item = owned::Id> + '_ {
+        self.commits.iter().copied()
+    }
+
+    /// Apply all `updates` as received from the server in response to `deepen`.
+    pub fn apply(&mut self, updates: impl IntoIterator<Item = Update>) {
+        for update in updates {
+            match update {
+                Update::Shallow(id) => self.commits.insert(id),
+                Update::Unshallow(id) => self.commits.remove(&id),
+            };
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! Read and write the patterns of sparse checkouts, which limit the files written into the worktree.
+//!
+//! The patterns are stored in `$GIT_DIR/info/sparse-checkout` and used if `core.sparseCheckout` is set. In cone mode,
+//! enabled with `core.sparseCheckoutCone`, they name directories whose files are all included, along with the files
+//! directly inside their parent directories and the root. Otherwise they are matched like the patterns of
+//! `.gitignore` files, where the last matching pattern decides and those starting with `!` exclude paths.
+//!
+//! Files outside of the sparse checkout have the skip-worktree bit set in the index, which isn't written yet.
+use crate::attributes;
+use git_object::bstr::{BStr, BString, ByteSlice, ByteVec};
+use quick_error::quick_error;
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        NotCone(line: BString) {
+            display("The pattern '{}' is not a cone mode pattern", line)
+        }
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not access sparse checkout file at '{}'", path.display())
+            source(err)
+        }
+    }
+}
+
+/// The path of the file with the sparse checkout patterns, relative to the git directory.
+pub const FILE_NAME: &str = "info/sparse-checkout";
+
+/// A pattern of a sparse checkout file which isn't in cone mode.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Pattern {
+    /// The pattern without the leading `!` and trailing `/`.
+    pub text: BString,
+    /// If true, matching paths are excluded.
+    pub negative: bool,
+    /// If true, the pattern ends with `/` to only match directories.
+    pub directory_only: bool,
+}
+
+/// The patterns of a sparse checkout.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub enum Patterns {
+    /// Directories whose contents are included, like `git sparse-checkout set` writes them.
+    Cone {
+        /// Directories like `a/b` whose files are included at any depth.
+        recursive: BTreeSet<BString>,
+        /// The parents of recursive directories, like `a`, of which only the files directly inside are included.
+        parents: BTreeSet<BString>,
+    },
+    /// Patterns like the ones of `.gitignore` files, where the last matching one decides if a path is included.
+    NonCone(Vec<Pattern>),
+}
+
+impl Patterns {
+    /// Create cone mode patterns including all files in `directories` and the root, like `git sparse-checkout set`.
+    pub fn cone<'a>(directories: impl IntoIterator<Item = &'a BStr>) -> Self {
+        let mut recursive = BTreeSet::new();
+        for directory in directories {
+            let directory = directory.trim_with(|c| c == '/');
+            if !directory.is_empty() {
+                recursive.insert(directory.as_bstr().to_owned());
+            }
+        }
+        // Directories within other included directories are included already.
+        let recursive: BTreeSet<BString> = recursive
+            .iter()
+            .filter(|directory| {
+                !ancestors(directory)
+                    .skip(1)
+                    .any(|dir| recursive.contains(dir.as_bstr()))
+            })
+            .cloned()
+            .collect();
+        let mut parents = BTreeSet::new();
+        for directory in &recursive {
+            let mut parent = directory.as_slice();
+            while let Some(pos) = parent.rfind_byte(b'/') {
+                parent = &parent[..pos];
+                parents.insert(parent.as_bstr().to_owned());
+            }
+        }
+        Patterns::Cone { recursive, parents }
+    }
+
+    /// Parse the contents of a sparse checkout file, in cone mode if `cone` is true.
+    ///
+    /// Unlike git, which falls back to non-cone mode in this case, patterns not written in cone mode are an error.
+    pub fn from_bytes(data: &[u8], cone: bool) -> Result<Self, Error> {
+        let lines = data
+            .lines()
+            .map(|line| line.trim_end_with(|c| c == ' ' || c == '\r'))
+            .filter(|line| !line.is_empty() && !line.starts_with(b"#"));
+        if !cone {
+            return Ok(Patterns::NonCone(
+                lines
+                    .map(|line| {
+                        let (negative, line) = match line.strip_prefix(b"!") {
+                            Some(line) => (true, line),
+                            None => (false, line.strip_prefix(b"\\").unwrap_or(line)),
+                        };
+                        let (directory_only, text) = match line.strip_suffix(b"/") {
+                            Some(text) => (true, text),
+                            None => (false, line),
+                        };
+                        Pattern {
+                            text: text.into(),
+                            negative,
+                            directory_only,
+                        }
+                    })
+                    .collect(),
+            ));
+        }
+
+        let mut recursive = BTreeSet::new();
+        let mut parents = BTreeSet::new();
+        for line in lines {
+            let not_cone = || Error::NotCone(line.into());
+            match line {
+                b"/*" | b"!/*/" => {}
+                _ => match line.strip_prefix(b"!/") {
+                    Some(negated) => {
+                        let directory = unescape(negated.strip_suffix(b"/*/").ok_or_else(not_cone)?);
+                        if !recursive.remove(&directory) {
+                            return Err(not_cone());
+                        }
+                        parents.insert(directory);
+                    }
+                    None => {
+                        let directory = line
+                            .strip_prefix(b"/")
+                            .and_then(|line| line.strip_suffix(b"/"))
+                            .filter(|directory| !directory.is_empty())
+                            .ok_or_else(not_cone)?;
+                        recursive.insert(unescape(directory));
+                    }
+                },
+            }
+        }
+        Ok(Patterns::Cone { recursive, parents })
+    }
+
+    /// Read the patterns of the repository at `git_dir`, in cone mode if `cone` is true, or `None` if there are none.
+    pub fn at(git_dir: impl AsRef<Path>, cone: bool) -> Result<Option<Self>, Error> {
+        let path = git_dir.as_ref().join(FILE_NAME);
+        match fs::read(&path) {
+            Ok(data) => Self::from_bytes(&data, cone).map(Some),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(Error::Io(err, path)),
+        }
+    }
+
+    /// Read the patterns of the repository at `git_dir` if `core.sparseCheckout` is set in its `config`, in cone
+    /// mode if `core.sparseCheckoutCone` is set as well.
+    pub fn from_config(git_dir: impl AsRef<Path>, config: &git_config::File) -> Result<Option<Self>, Error> {
+        let enabled = |key| matches!(config.boolean("core", None, key), Some(Ok(true)));
+        if !enabled("sparseCheckout") {
+            return Ok(None);
+        }
+        Self::at(git_dir, enabled("sparseCheckoutCone"))
+    }
+
+    /// Serialize the patterns the way git writes them into the sparse checkout file.
+    pub fn to_bstring(&self) -> BString {
+        let mut out = BString::default();
+        match self {
+            Patterns::Cone { recursive, parents } => {
+                out.push_str("/*\n!/*/\n");
+                for directory in recursive.iter().chain(parents.iter()).collect::<BTreeSet<_>>() {
+                    out.push(b'/');
+                    out.push_str(escape(directory.as_ref()));
+                    out.push_str("/\n");
+                    if parents.contains(directory) {
+                        out.push_str("!/");
+                        out.push_str(escape(directory.as_ref()));
+                        out.push_str("/*/\n");
+                    }
+                }
+            }
+            Patterns::NonCone(patterns) => {
+                for pattern in patterns {
+                    if pattern.negative {
+                        out.push(b'!');
+                    }
+                    out.push_str(&pattern.text);
+                    if pattern.directory_only {
+                        out.push(b'/');
+                    }
+                    out.push(b'\n');
+                }
+            }
+        }
+        out
+    }
+
+    /// Return true if the file at `path`, which is relative to the root of the worktree, is part of the sparse
+    /// checkout.
+    pub fn is_included(&self, path: &BStr) -> bool {
+        match self {
+            Patterns::Cone { recursive, parents } => {
+                let directory = match path.rfind_byte(b'/') {
+                    Some(pos) => &path[..pos],
+                    None => return true,
+                };
+                parents.contains(directory.as_bstr())
+                    || ancestors(directory).any(|dir| recursive.contains(dir.as_bstr()))
+            }
+            Patterns::NonCone(patterns) => {
+                let mut is_directory = false;
+                let mut path = path.as_bytes();
+                loop {
+                    let decision = patterns
+                        .iter()
+                        .rev()
+                        .find(|p| {
+                            (is_directory || !p.directory_only)
+                                && attributes::pattern_matches(p.text.as_ref(), path.as_bstr())
+                        })
+                        .map(|p| !p.negative);
+                    if let Some(decision) = decision {
+                        return decision;
+                    }
+                    match path.rfind_byte(b'/') {
+                        Some(pos) => path = &path[..pos],
+                        None => return false,
+                    }
+                    is_directory = true;
+                }
+            }
+        }
+    }
+
+    /// Return true if the directory at `path` may contain files of the sparse checkout, or false if it can be skipped
+    /// entirely.
+    pub fn may_include_directory(&self, path: &BStr) -> bool {
+        match self {
+            Patterns::Cone { recursive, parents } => {
+                parents.contains(path)
+                    || recursive.iter().any(|dir| {
+                        dir.as_slice() == path.as_bytes()
+                            || ancestors(path).any(|ancestor| ancestor == dir.as_slice())
+                            || (dir.starts_with(path) && dir.get(path.len()) == Some(&b'/'))
+                    })
+            }
+            // Negative patterns can include files of directories which are excluded.
+            Patterns::NonCone(_) => true,
+        }
+    }
+}
+
+/// Return `path` followed by all of its parent directories.
+fn ancestors(path: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut next = Some(path);
+    std::iter::from_fn(move || {
+        let current = next?;
+        next = current.rfind_byte(b'/').map(|pos| &current[..pos]);
+        Some(current)
+    })
+}
+
+fn escape(directory: &BStr) -> BString {
+    let mut out = BString::default();
+    for b in directory.iter() {
+        if matches!(b, b'*' | b'?' | b'[' | b'\\') {
+            out.push(b'\\');
+        }
+        out.push(*b);
+    }
+    out
+}
+
+fn unescape(directory: &[u8]) -> BString {
+    let mut out = BString::default();
+    let mut bytes = directory.iter();
+    while let Some(b) = bytes.next() {
+        match b {
+            b'\\' => out.extend(bytes.next()),
+            _ => out.push(*b),
+        }
+    }
+    out
+}
@@ -0,0 +1,306 @@
+//! Save changes of the index and the worktree as stash commits in `refs/stash`, and apply them again, like `git stash`.
+//!
+//! Like git, a stash consists of a commit of the index, whose parent is the commit `HEAD` pointed to, and a commit of
+//! the worktree with both of them as parents. Only the latter is stored in `refs/stash`, whose reflog holds all stashes
+//! with the newest one first, so that the `n`th stash is `stash@{n}`.
+//!
+//! As the index isn't read or written by this crate yet, the trees of the index and the worktree to stash are passed
+//! in, and applying a stash produces the merged trees for them instead of changing files.
+use crate::{commit, merge, reflog, remote::update, worktree::linked};
+use git_features::lock;
+use git_object::{
+    borrowed,
+    bstr::{BStr, BString, ByteSlice},
+    owned,
+};
+use quick_error::quick_error;
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Find(id: owned::Id) {
+            display("Object {} could not be found", id)
+        }
+        NotAStash(id: owned::Id) {
+            display("Commit {} is not a stash, as it doesn't have the commit of the index as its second parent", id)
+        }
+        Missing(n: usize) {
+            display("The stash 'stash@{{{}}}' doesn't exist", n)
+        }
+        Unborn {
+            display("HEAD doesn't point to a commit yet")
+        }
+        Merge(err: merge::Error) {
+            display("The stash could not be merged")
+            from()
+            source(err)
+        }
+        Commit(err: commit::Error) {
+            display("A stash commit could not be written")
+            from()
+            source(err)
+        }
+        Ref(err: update::Error) {
+            display("A ref could not be read or written")
+            from()
+            source(err)
+        }
+        Reflog(err: reflog::Error) {
+            display("The reflog of 'refs/stash' could not be read")
+            from()
+            source(err)
+        }
+        Worktree(err: linked::Error) {
+            display("The common git directory of the worktree could not be determined")
+            from()
+            source(err)
+        }
+        Lock(err: lock::Error) {
+            display("Could not lock a file to change it")
+            from()
+            source(err)
+        }
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not access '{}'", path.display())
+            source(err)
+        }
+    }
+}
+
+/// The name of the ref holding the latest stash.
+pub const REF_NAME: &str = "refs/stash";
+
+/// Configure how to stash.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Options {
+    /// The author and committer of the stash commits.
+    pub committer: owned::Signature,
+    /// The message describing the stash, or `None` to use the subject of the commit `HEAD` points to, like git does.
+    pub message: Option<BString>,
+}
+
+/// The commits of a stash.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub struct Stash {
+    /// The commit of the worktree, which is stored in `refs/stash`.
+    pub worktree: owned::Id,
+    /// The commit of the index.
+    pub index: owned::Id,
+    /// The commit `HEAD` pointed to when stashing.
+    pub base: owned::Id,
+}
+
+/// The result of [`apply()`] and [`pop()`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Applied {
+    /// The changes of the worktree merged into the tree they were applied to.
+    pub worktree: merge::Outcome,
+    /// The changes of the index merged into the tree they were applied to, for restoring the index like `--index` does.
+    pub index: merge::Outcome,
+}
+
+/// Stash the tree `index` of the index and `worktree` of the worktree in the repository at `git_dir` on top of the commit
+/// `HEAD` points to, and return the new stash, after updating `refs/stash` and adding it to its reflog.
+///
+/// `find` is used to lookup the subject of the commit `HEAD` points to, and places the object data into the provided
+/// buffer and returns the decoded object.
+pub fn push<Find>(
+    git_dir: impl AsRef<Path>,
+    index: owned::Id,
+    worktree: owned::Id,
+    mut find: Find,
+    options: Options,
+) -> Result<Stash, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let git_dir = git_dir.as_ref();
+    let (head_ref, base) = match update::resolve(git_dir, "HEAD".into())? {
+        (name, Some(id)) => (name, id),
+        (_, None) => return Err(Error::Unborn),
+    };
+    let branch = match head_ref.strip_prefix(b"refs/heads/") {
+        Some(branch) => branch.as_bstr(),
+        None => "(no branch)".into(),
+    };
+    let mut buf = Vec::new();
+    let subject: BString = match find(base.to_borrowed(), &mut buf).ok_or(Error::Find(base))? {
+        borrowed::Object::Commit(commit) => commit.message.lines().next().unwrap_or_default().into(),
+        _ => return Err(Error::Find(base)),
+    };
+    let describe = |prefix: &str| {
+        let mut message = BString::from(prefix);
+        message.extend_from_slice(branch);
+        message.extend_from_slice(b": ");
+        message.extend_from_slice(&base.to_sha1_hex()[..7]);
+        message.push(b' ');
+        message.extend_from_slice(&subject);
+        message
+    };
+    let mut index_message = describe("index on ");
+    index_message.push(b'\n');
+    let message = match &options.message {
+        Some(message) => {
+            let mut on = BString::from("On ");
+            on.extend_from_slice(branch);
+            on.extend_from_slice(b": ");
+            on.extend_from_slice(message);
+            on
+        }
+        None => describe("WIP on "),
+    };
+
+    // Like git, only the message of the index commit ends with a newline.
+    let write = |tree: owned::Id, parents: &[owned::Id], message: &BStr| {
+        commit::write(
+            git_dir,
+            &owned::Commit {
+                tree,
+                parents: parents.iter().copied().collect(),
+                author: options.committer.clone(),
+                committer: options.committer.clone(),
+                encoding: None,
+                message: message.to_owned(),
+                extra_headers: Vec::new(),
+            },
+        )
+    };
+    let index = write(index, &[base], index_message.as_ref())?;
+    let worktree = write(worktree, &[base, index], message.as_ref())?;
+    let (_, previous) = update::resolve(git_dir, REF_NAME.into())?;
+    let reflog_options = update::Options {
+        committer: options.committer.clone(),
+        message: message.clone(),
+        dry_run: false,
+        hooks: None,
+    };
+    update::write_ref(
+        git_dir,
+        REF_NAME.into(),
+        previous,
+        worktree,
+        message.as_ref(),
+        &reflog_options,
+    )?;
+    Ok(Stash { worktree, index, base })
+}
+
+/// Return the reflog entries of all stashes in the repository at `git_dir`, newest first.
+pub fn list(git_dir: impl AsRef<Path>) -> Result<Vec<reflog::Entry>, Error> {
+    let mut entries = reflog::read(git_dir, REF_NAME.into())?;
+    entries.reverse();
+    Ok(entries)
+}
+
+/// Return the commits of the `n`th stash in the repository at `git_dir`.
+pub fn get<Find>(git_dir: impl AsRef<Path>, n: usize, mut find: Find) -> Result<Stash, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let worktree = list(git_dir)?.get(n).ok_or(Error::Missing(n))?.new;
+    let mut buf = Vec::new();
+    let parents: Vec<_> = match find(worktree.to_borrowed(), &mut buf).ok_or(Error::Find(worktree))? {
+        borrowed::Object::Commit(commit) => commit.parents().collect(),
+        _ => return Err(Error::NotAStash(worktree)),
+    };
+    match parents.as_slice() {
+        [base, index, ..] => Ok(Stash {
+            worktree,
+            index: *index,
+            base: *base,
+        }),
+        _ => Err(Error::NotAStash(worktree)),
+    }
+}
+
+/// Apply the `n`th stash of the repository at `git_dir` to `tree`, the tree of the current index, by merging the
+/// changes of its worktree and index with the commit `HEAD` pointed to when stashing as merge base.
+///
+/// Like git, conflicts are labeled `Updated upstream` and `Stashed changes`, and are written into the merged trees.
+pub fn apply<Find>(git_dir: impl AsRef<Path>, n: usize, tree: owned::Id, mut find: Find) -> Result<Applied, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let git_dir = git_dir.as_ref();
+    let stash = get(git_dir, n, &mut find)?;
+    let mut buf = Vec::new();
+    let mut tree_of = |id: owned::Id| match find(id.to_borrowed(), &mut buf) {
+        Some(borrowed::Object::Commit(commit)) => Ok(commit.tree()),
+        _ => Err(Error::Find(id)),
+    };
+    let (base, worktree, index) = (tree_of(stash.base)?, tree_of(stash.worktree)?, tree_of(stash.index)?);
+    let db = git_odb::loose::Db::at(linked::common_dir(git_dir)?.join("objects"));
+    let labels = merge::Labels {
+        ours: "Updated upstream".into(),
+        theirs: "Stashed changes".into(),
+    };
+    Ok(Applied {
+        worktree: merge::trees(Some(base), tree, worktree, &mut find, &db, labels)?,
+        index: merge::trees(Some(base), tree, index, &mut find, &db, labels)?,
+    })
+}
+
+/// [Apply][apply()] the `n`th stash of the repository at `git_dir` to `tree` and [drop][drop()] it, unless its worktree
+/// conflicts.
+pub fn pop<Find>(git_dir: impl AsRef<Path>, n: usize, tree: owned::Id, find: Find) -> Result<Applied, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let git_dir = git_dir.as_ref();
+    let applied = apply(git_dir, n, tree, find)?;
+    if applied.worktree.conflicts.is_empty() {
+        drop(git_dir, n)?;
+    }
+    Ok(applied)
+}
+
+/// Remove the `n`th stash from the reflog of `refs/stash` in the repository at `git_dir`, and return its reflog entry.
+///
+/// Like `git stash drop`, the following entry is changed to come from the preceding one, `refs/stash` is updated to
+/// point to the newest remaining stash, and it's deleted along with its reflog once no stash is left.
+pub fn drop(git_dir: impl AsRef<Path>, n: usize) -> Result<reflog::Entry, Error> {
+    let git_dir = git_dir.as_ref();
+    let mut entries = reflog::read(git_dir, REF_NAME.into())?;
+    let index = entries.len().checked_sub(n + 1).ok_or(Error::Missing(n))?;
+    let dropped = entries.remove(index);
+    if let Some(next) = entries.get_mut(index) {
+        next.previous = dropped.previous;
+    }
+
+    let log_path = reflog::path(git_dir, REF_NAME.into())?;
+    let ref_path = linked::ref_dir(git_dir, REF_NAME.into())?.join(REF_NAME);
+    match entries.last() {
+        Some(newest) => {
+            let mut log = Vec::new();
+            for entry in &entries {
+                entry
+                    .write_to(&mut log)
+                    .map_err(|err| Error::Io(err, log_path.clone()))?;
+            }
+            replace(&log_path, &log)?;
+            replace(&ref_path, format!("{}\n", newest.new).as_bytes())?;
+        }
+        None => {
+            for path in &[ref_path, log_path] {
+                match fs::remove_file(path) {
+                    Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(Error::Io(err, path.clone())),
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(dropped)
+}
+
+/// Replace the file at `path` with `content` by renaming a lock file over it.
+fn replace(path: &Path, content: &[u8]) -> Result<(), Error> {
+    let mut lock = lock::File::acquire(path, Default::default())?;
+    lock.write_all(content).map_err(|err| Error::Io(err, path.to_owned()))?;
+    lock.commit()?;
+    Ok(())
+}
@@ -0,0 +1,528 @@
+//! Find the changes between the tree of `HEAD`, the index and the worktree, like `git status` does.
+//!
+//! The tree of `HEAD` is compared to the index to find the changes which are staged for the next commit, and the index
+//! is compared to the worktree to find the changes which aren't staged yet. Files whose metadata matches the one
+//! recorded in the index are considered unchanged without reading them, unless they were changed so shortly before the
+//! index was written that their metadata can't tell, in which case they are hashed like files whose metadata differs.
+//!
+//! Like git, renames are only detected among the staged changes, but only files whose content didn't change are
+//! considered renamed. Files are compared as they are, without applying filters or converting line endings, and
+//! submodules are only checked for existence.
+//!
+//! Untracked and ignored files are found with a [walk of the worktree][crate::dirwalk::walk()] and listed after the
+//! changes of tracked files.
+use crate::{dirwalk, ignore, index, worktree};
+use git_object::{
+    borrowed,
+    bstr::{BStr, BString, ByteSlice},
+    owned, TreeMode,
+};
+use quick_error::quick_error;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Find(id: owned::Id) {
+            display("Object {} could not be found", id)
+        }
+        NotATree(id: owned::Id) {
+            display("Object {} was expected to be a tree", id)
+        }
+        Path(err: git_features::path::Error, path: BString) {
+            display("The path '{}' of the index can't be used in the worktree", path)
+            source(err)
+        }
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not read '{}'", path.display())
+            source(err)
+        }
+        Walk(err: dirwalk::Error) {
+            display("Could not find untracked files")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// Which untracked files to list, like `status.showUntrackedFiles`.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum UntrackedFiles {
+    /// No untracked or ignored files are listed.
+    No,
+    /// Untracked files are listed, but directories without tracked files are listed as a whole.
+    Normal,
+    /// All untracked files are listed, along with all files in ignored directories if ignored files are.
+    All,
+}
+
+impl UntrackedFiles {
+    /// Parse the `name` git uses for the mode, like `normal`, or return `None` if it's unknown.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "no" => UntrackedFiles::No,
+            "normal" => UntrackedFiles::Normal,
+            "all" => UntrackedFiles::All,
+            _ => return None,
+        })
+    }
+}
+
+/// Configure how changes are found.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub struct Options {
+    /// If true, a deleted and an added file with the same content are shown as a single rename, like `status.renames`.
+    pub renames: bool,
+    /// Which untracked files to list.
+    pub untracked_files: UntrackedFiles,
+    /// If true, ignored files are listed as well, like with `git status --ignored`, unless no untracked files are.
+    pub ignored: bool,
+    /// The amount of threads to look for untracked files with, or `None` to use all of them.
+    pub thread_limit: Option<usize>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            renames: true,
+            untracked_files: UntrackedFiles::Normal,
+            ignored: false,
+            thread_limit: None,
+        }
+    }
+}
+
+/// The sides of a conflict which changed the file, as shown by git.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Conflict {
+    /// `DD`: the file was deleted on both sides.
+    BothDeleted,
+    /// `AU`: the file was added by our side only.
+    AddedByUs,
+    /// `UD`: the file was deleted by their side and modified by ours.
+    DeletedByThem,
+    /// `UA`: the file was added by their side only.
+    AddedByThem,
+    /// `DU`: the file was deleted by our side and modified by theirs.
+    DeletedByUs,
+    /// `AA`: the file was added on both sides.
+    BothAdded,
+    /// `UU`: the file was modified on both sides.
+    BothModified,
+}
+
+impl Conflict {
+    /// The two letters `git status --short` shows for the conflict.
+    pub fn short_format(&self) -> [u8; 2] {
+        *match self {
+            Conflict::BothDeleted => b"DD",
+            Conflict::AddedByUs => b"AU",
+            Conflict::DeletedByThem => b"UD",
+            Conflict::AddedByThem => b"UA",
+            Conflict::DeletedByUs => b"DU",
+            Conflict::BothAdded => b"AA",
+            Conflict::BothModified => b"UU",
+        }
+    }
+}
+
+/// How a file changed.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Change {
+    /// The file is new.
+    Added,
+    /// The file was removed.
+    Deleted,
+    /// The content or the executable bit of the file changed.
+    Modified,
+    /// The file became a symbolic link or submodule, or the other way around.
+    TypeChanged,
+    /// The file was moved from `source` without changing its content.
+    Renamed {
+        /// The path the file was moved from.
+        source: BString,
+    },
+    /// The file has a conflict which wasn't resolved yet.
+    Unmerged(Conflict),
+    /// The file isn't tracked, which is only the case for changes of the worktree.
+    Untracked,
+    /// The file isn't tracked and ignored, which is only the case for changes of the worktree.
+    Ignored,
+}
+
+impl Change {
+    fn short_format(&self) -> u8 {
+        match self {
+            Change::Added => b'A',
+            Change::Deleted => b'D',
+            Change::Modified => b'M',
+            Change::TypeChanged => b'T',
+            Change::Renamed { .. } => b'R',
+            Change::Unmerged(_) => b'U',
+            Change::Untracked => b'?',
+            Change::Ignored => b'!',
+        }
+    }
+}
+
+/// A changed file.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    /// The path of the file relative to the root of the worktree, which is the destination of renames, or of an
+    /// untracked or ignored directory with a trailing slash.
+    pub path: BString,
+    /// The change of the index compared to `HEAD`, if any.
+    pub staged: Option<Change>,
+    /// The change of the worktree compared to the index, if any.
+    pub unstaged: Option<Change>,
+}
+
+impl Entry {
+    /// The two letters `git status --short` shows in front of the path, one for the staged and one for the unstaged
+    /// change, with a space for no change.
+    pub fn short_format(&self) -> [u8; 2] {
+        match (&self.staged, &self.unstaged) {
+            (Some(Change::Unmerged(conflict)), _) => return conflict.short_format(),
+            (None, Some(Change::Untracked)) => return *b"??",
+            (None, Some(Change::Ignored)) => return *b"!!",
+            _ => {}
+        }
+        let letter = |change: &Option<Change>| change.as_ref().map_or(b' ', Change::short_format);
+        [letter(&self.staged), letter(&self.unstaged)]
+    }
+}
+
+/// The changes of a worktree.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The changed files, ordered by path, followed by the untracked and the ignored ones.
+    pub entries: Vec<Entry>,
+}
+
+/// Compare the tree `head`, or nothing if `HEAD` is unborn, to `index`, and `index` to the files in `worktree` on a
+/// filesystem with `capabilities`, and list the files which aren't tracked, ignoring those matching `excludes` or
+/// the patterns of `.gitignore` files.
+///
+/// `find` is used to lookup objects, and places the object data into the provided buffer and returns the decoded object.
+pub fn status<Find>(
+    head: Option<owned::Id>,
+    index: &index::File,
+    worktree: impl AsRef<Path>,
+    capabilities: &worktree::Capabilities,
+    excludes: &ignore::Stack,
+    mut find: Find,
+    options: &Options,
+) -> Result<Outcome, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let mut span = git_features::trace::span("status");
+    let mut head_entries = BTreeMap::new();
+    if let Some(tree) = head {
+        let mut buf = Vec::new();
+        flatten(&mut find, &mut buf, tree, BString::default(), &mut head_entries)?;
+    }
+
+    let mut staged_entries = BTreeMap::new();
+    let mut conflicts = BTreeMap::<&BStr, u8>::new();
+    for entry in &index.entries {
+        if entry.stage == 0 {
+            staged_entries.insert(entry.path.as_bstr(), entry);
+        } else {
+            *conflicts.entry(entry.path.as_bstr()).or_default() |= 1 << (entry.stage - 1);
+        }
+    }
+
+    let paths: BTreeSet<&BStr> = head_entries
+        .keys()
+        .map(|path| path.as_bstr())
+        .chain(staged_entries.keys().copied())
+        .chain(conflicts.keys().copied())
+        .collect();
+    let mut entries = Vec::new();
+    for path in paths {
+        if let Some(stages) = conflicts.get(path) {
+            let conflict = Change::Unmerged(conflict(*stages));
+            entries.push(Entry {
+                path: path.to_owned(),
+                staged: Some(conflict.clone()),
+                unstaged: Some(conflict),
+            });
+            continue;
+        }
+        let index_entry = staged_entries.get(path).copied();
+        let head_entry = head_entries.get(path);
+        let staged = match (head_entry, index_entry.filter(|entry| !entry.intent_to_add)) {
+            (None, None) => None,
+            (None, Some(_)) => Some(Change::Added),
+            (Some(_), None) => Some(Change::Deleted),
+            (Some(&(mode, id)), Some(entry)) => change(mode, id, entry.mode, entry.id),
+        };
+        let unstaged = match index_entry {
+            Some(entry) if !entry.skip_worktree && !entry.assume_valid => {
+                worktree_change(worktree.as_ref(), entry, index.mtime, capabilities)?
+            }
+            _ => None,
+        };
+        if staged.is_some() || unstaged.is_some() {
+            entries.push(Entry {
+                path: path.to_owned(),
+                staged,
+                unstaged,
+            });
+        }
+    }
+    if options.renames {
+        detect_renames(&mut entries, &head_entries, &staged_entries);
+    }
+    if options.untracked_files != UntrackedFiles::No {
+        let walk = dirwalk::walk(
+            worktree.as_ref(),
+            index,
+            excludes,
+            capabilities,
+            &dirwalk::Options {
+                tracked: false,
+                ignored: options.ignored,
+                collapse_untracked_directories: options.untracked_files == UntrackedFiles::Normal,
+                thread_limit: options.thread_limit,
+            },
+        )?;
+        let (untracked, ignored): (Vec<_>, Vec<_>) = walk
+            .entries
+            .into_iter()
+            .partition(|entry| entry.status == dirwalk::Status::Untracked);
+        for (entry, change) in untracked
+            .into_iter()
+            .map(|entry| (entry, Change::Untracked))
+            .chain(ignored.into_iter().map(|entry| (entry, Change::Ignored)))
+        {
+            let mut path = entry.path.clone();
+            if entry.is_directory() {
+                path.push(b'/');
+            }
+            entries.push(Entry {
+                path,
+                staged: None,
+                unstaged: Some(change),
+            });
+        }
+    }
+    span.record(format_args!("changes={}", entries.len()));
+    Ok(Outcome { entries })
+}
+
+/// Determine the conflict from the bitmask of `stages` present in the index, with bit 0 for the merge base.
+fn conflict(stages: u8) -> Conflict {
+    match stages {
+        0b001 => Conflict::BothDeleted,
+        0b010 => Conflict::AddedByUs,
+        0b011 => Conflict::DeletedByThem,
+        0b100 => Conflict::AddedByThem,
+        0b101 => Conflict::DeletedByUs,
+        0b110 => Conflict::BothAdded,
+        _ => Conflict::BothModified,
+    }
+}
+
+/// Files, links and submodules are different kinds of entries, whose changes are type changes.
+fn kind(mode: TreeMode) -> u8 {
+    match mode {
+        TreeMode::Link => 1,
+        TreeMode::Commit => 2,
+        _ => 0,
+    }
+}
+
+fn change(old_mode: TreeMode, old_id: owned::Id, new_mode: TreeMode, new_id: owned::Id) -> Option<Change> {
+    if kind(old_mode) != kind(new_mode) {
+        Some(Change::TypeChanged)
+    } else if old_mode != new_mode || old_id != new_id {
+        Some(Change::Modified)
+    } else {
+        None
+    }
+}
+
+/// Compare the file of `entry` in `worktree` to it, hashing the file only if its metadata doesn't tell, which is
+/// the case if it was changed at or after `index_mtime`.
+fn worktree_change(
+    worktree: &Path,
+    entry: &index::Entry,
+    index_mtime: Option<index::Timestamp>,
+    capabilities: &worktree::Capabilities,
+) -> Result<Option<Change>, Error> {
+    let path = worktree.join(
+        git_features::path::to_native(&entry.path, Default::default())
+            .map_err(|err| Error::Path(err, entry.path.clone()))?,
+    );
+    let metadata = match fs::symlink_metadata(&path) {
+        Ok(metadata) => metadata,
+        Err(err) if matches!(err.kind(), io::ErrorKind::NotFound | io::ErrorKind::NotADirectory) => {
+            return Ok(Some(Change::Deleted))
+        }
+        Err(err) => return Err(Error::Io(err, path)),
+    };
+    if entry.intent_to_add {
+        return Ok(Some(Change::Added));
+    }
+    let file_type = metadata.file_type();
+    let mode = if file_type.is_dir() {
+        return Ok(match entry.mode {
+            TreeMode::Commit => None,
+            _ => Some(Change::Deleted),
+        });
+    } else if file_type.is_symlink() || (entry.mode == TreeMode::Link && !capabilities.symlinks) {
+        TreeMode::Link
+    } else if capabilities.file_mode && is_executable(&metadata) {
+        TreeMode::BlobExecutable
+    } else if capabilities.file_mode || entry.mode == TreeMode::Link {
+        TreeMode::Blob
+    } else {
+        match entry.mode {
+            TreeMode::BlobExecutable => TreeMode::BlobExecutable,
+            _ => TreeMode::Blob,
+        }
+    };
+    if kind(mode) != kind(entry.mode) {
+        return Ok(Some(Change::TypeChanged));
+    }
+    let stat = index::Stat::from_metadata(&metadata);
+    // Like git, entries without a size, like those read from a tree, are compared by content.
+    if mode != entry.mode || (stat.size != entry.stat.size && entry.stat.size != 0) {
+        return Ok(Some(Change::Modified));
+    }
+    let is_racy = index_mtime.is_none_or(|index_mtime| entry.stat.mtime >= index_mtime);
+    if stat.matches(&entry.stat) && !is_racy {
+        return Ok(None);
+    }
+    let data = if file_type.is_symlink() {
+        link_target(&path)
+    } else {
+        fs::read(&path)
+    }
+    .map_err(|err| Error::Io(err, path))?;
+    Ok(if blob_id(&data) == entry.id {
+        None
+    } else {
+        Some(Change::Modified)
+    })
+}
+
+/// The id of a blob with `data`.
+fn blob_id(data: &[u8]) -> owned::Id {
+    let mut hasher = git_features::hash::Sha1::default();
+    hasher.update(format!("blob {}\0", data.len()).as_bytes());
+    hasher.update(data);
+    owned::Id::new_sha1(hasher.digest())
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o100 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn link_target(path: &Path) -> io::Result<Vec<u8>> {
+    use std::os::unix::ffi::OsStringExt;
+    fs::read_link(path).map(|target| target.into_os_string().into_vec())
+}
+
+#[cfg(not(unix))]
+fn link_target(path: &Path) -> io::Result<Vec<u8>> {
+    let target = fs::read_link(path)?;
+    target
+        .to_str()
+        .map(|target| target.replace('\\', "/").into_bytes())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "The link target is not valid UTF-8"))
+}
+
+/// Turn pairs of staged deletions and additions of files with the same kind and content into renames, matching
+/// deleted files to added ones in the order of their paths.
+fn detect_renames(
+    entries: &mut Vec<Entry>,
+    head_entries: &BTreeMap<BString, (TreeMode, owned::Id)>,
+    staged_entries: &BTreeMap<&BStr, &index::Entry>,
+) {
+    let mut deleted = HashMap::<_, Vec<usize>>::new();
+    for (pos, entry) in entries.iter().enumerate() {
+        if entry.staged == Some(Change::Deleted) {
+            let (mode, id) = head_entries[&entry.path];
+            if kind(mode) != kind(TreeMode::Commit) {
+                deleted.entry((kind(mode), id)).or_default().push(pos);
+            }
+        }
+    }
+    deleted.values_mut().for_each(|positions| positions.reverse());
+    let mut renamed = Vec::new();
+    for pos in 0..entries.len() {
+        if entries[pos].staged != Some(Change::Added) {
+            continue;
+        }
+        let added = staged_entries[entries[pos].path.as_bstr()];
+        if let Some(source) = deleted
+            .get_mut(&(kind(added.mode), added.id))
+            .and_then(|positions| positions.pop())
+        {
+            entries[pos].staged = Some(Change::Renamed {
+                source: entries[source].path.clone(),
+            });
+            renamed.push(source);
+        }
+    }
+    renamed.sort_unstable();
+    for pos in renamed.into_iter().rev() {
+        entries.remove(pos);
+    }
+}
+
+/// Collect all files of `tree` at `prefix` into `out`, recursively.
+fn flatten<Find>(
+    find: &mut Find,
+    buf: &mut Vec<u8>,
+    tree: owned::Id,
+    prefix: BString,
+    out: &mut BTreeMap<BString, (TreeMode, owned::Id)>,
+) -> Result<(), Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let entries: Vec<_> = match find(tree.to_borrowed(), buf).ok_or(Error::Find(tree))? {
+        borrowed::Object::Tree(tree) => tree
+            .entries
+            .into_iter()
+            .map(|entry| {
+                let mut path = prefix.clone();
+                if !path.is_empty() {
+                    path.push(b'/');
+                }
+                path.extend_from_slice(entry.filename);
+                (path, entry.mode, owned::Id::from_borrowed_sha1(entry.oid.sha1()))
+            })
+            .collect(),
+        _ => return Err(Error::NotATree(tree)),
+    };
+    for (path, mode, id) in entries {
+        if mode == TreeMode::Tree {
+            flatten(find, buf, id, path, out)?;
+        } else {
+            out.insert(path, (mode, id));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,158 @@
+//! Read the submodules configured in `.gitmodules` files and find the gitlinks of trees, the entries with mode `160000`
+//! which record the commit a submodule is checked out at.
+//!
+//! Like git, names and paths which could be used to write outside of the submodule directories are refused.
+use git_object::{
+    borrowed,
+    bstr::{BStr, BString, ByteSlice, ByteVec},
+    owned, TreeMode,
+};
+use quick_error::quick_error;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Config(err: git_config::parse::Error) {
+            display("The .gitmodules file could not be parsed")
+            from()
+            source(err)
+        }
+        UnsafeName(name: BString) {
+            display("Refusing the submodule name '{}' as it could be used to write outside of the git directory", name)
+        }
+        UnsafePath(name: BString, path: BString) {
+            display("Refusing the path '{}' of submodule '{}' as it could be used to write outside of the worktree", path, name)
+        }
+        MissingPath(name: BString) {
+            display("The submodule '{}' has no path", name)
+        }
+        MissingUrl(name: BString) {
+            display("The submodule '{}' has no url", name)
+        }
+        Find(id: owned::Id) {
+            display("Object {} could not be found", id)
+        }
+        NotATree(id: owned::Id) {
+            display("Object {} was expected to be a tree", id)
+        }
+        NotABlob(id: owned::Id) {
+            display("Object {} was expected to be a blob", id)
+        }
+    }
+}
+
+/// A submodule as configured with `submodule.<name>.*` keys.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Submodule {
+    /// The name of the submodule, which is also the name of its git directory below `$GIT_DIR/modules`.
+    pub name: BString,
+    /// The path relative to the root of the worktree at which the submodule is checked out.
+    pub path: BString,
+    /// The url to clone the submodule from, which may be relative to the url of the superproject.
+    pub url: BString,
+    /// The branch to follow when updating the submodule with `--remote`, where `.` means the branch of the superproject.
+    pub branch: Option<BString>,
+}
+
+/// An entry of a tree recording the commit a submodule is checked out at.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Gitlink {
+    /// The path relative to the root of the tree.
+    pub path: BString,
+    /// The commit of the submodule.
+    pub commit: owned::Id,
+}
+
+/// Parse the contents of a `.gitmodules` file into its submodules, in the order of their first appearance.
+pub fn from_bytes(data: &[u8]) -> Result<Vec<Submodule>, Error> {
+    from_config(&git_config::File::from_bytes(data)?)
+}
+
+/// Read all submodules configured in `config`, in the order of their first appearance.
+pub fn from_config(config: &git_config::File) -> Result<Vec<Submodule>, Error> {
+    config
+        .subsections("submodule")
+        .into_iter()
+        .map(|name| {
+            let name_str = match name.to_str() {
+                Ok(name_str) if is_safe(name) => name_str,
+                _ => return Err(Error::UnsafeName(name.to_owned())),
+            };
+            let value = |key| config.value("submodule", Some(name_str), key).map(ToOwned::to_owned);
+            let path = value("path").ok_or_else(|| Error::MissingPath(name.to_owned()))?;
+            let path: BString = path.trim_end_with(|c| c == '/').into();
+            if path.is_empty() || path.starts_with(b"/") || !is_safe(path.as_ref()) {
+                return Err(Error::UnsafePath(name.to_owned(), path));
+            }
+            Ok(Submodule {
+                name: name.to_owned(),
+                path,
+                url: value("url").ok_or_else(|| Error::MissingUrl(name.to_owned()))?,
+                branch: value("branch"),
+            })
+        })
+        .collect()
+}
+
+/// Return true if no component of `path` is `..` or `.git`, ignoring case.
+fn is_safe(path: &BStr) -> bool {
+    !path
+        .split(|b| *b == b'/' || *b == b'\\')
+        .any(|component| component == b".." || component.eq_ignore_ascii_case(b".git"))
+}
+
+/// Read the submodules configured in the `.gitmodules` file at the root of `tree`, which are none if there is no such
+/// file.
+pub fn from_tree<Find>(tree: borrowed::Id<'_>, mut find: Find) -> Result<Vec<Submodule>, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let mut buf = Vec::new();
+    let tree = owned::Id::from_borrowed_sha1(tree.sha1());
+    let gitmodules = match find(tree.to_borrowed(), &mut buf).ok_or(Error::Find(tree))? {
+        borrowed::Object::Tree(tree) => tree
+            .entries
+            .iter()
+            .find(|entry| entry.mode == TreeMode::Blob && entry.filename == ".gitmodules")
+            .map(|entry| owned::Id::from_borrowed_sha1(entry.oid.sha1())),
+        _ => return Err(Error::NotATree(tree)),
+    };
+    match gitmodules {
+        Some(id) => match find(id.to_borrowed(), &mut buf).ok_or(Error::Find(id))? {
+            borrowed::Object::Blob(blob) => from_bytes(blob.data),
+            _ => Err(Error::NotABlob(id)),
+        },
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Return all gitlinks in `tree` and its subtrees, ordered by path.
+pub fn gitlinks<Find>(tree: borrowed::Id<'_>, mut find: Find) -> Result<Vec<Gitlink>, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let mut buf = Vec::new();
+    let mut gitlinks = Vec::new();
+    let mut trees = vec![(owned::Id::from_borrowed_sha1(tree.sha1()), BString::default())];
+    while let Some((id, directory)) = trees.pop() {
+        let tree = match find(id.to_borrowed(), &mut buf).ok_or(Error::Find(id))? {
+            borrowed::Object::Tree(tree) => tree,
+            _ => return Err(Error::NotATree(id)),
+        };
+        for entry in &tree.entries {
+            let mut path = directory.clone();
+            if !path.is_empty() {
+                path.push(b'/');
+            }
+            path.push_str(entry.filename);
+            let id = owned::Id::from_borrowed_sha1(entry.oid.sha1());
+            match entry.mode {
+                TreeMode::Tree => trees.push((id, path)),
+                TreeMode::Commit => gitlinks.push(Gitlink { path, commit: id }),
+                TreeMode::Blob | TreeMode::BlobExecutable | TreeMode::Link => {}
+            }
+        }
+    }
+    gitlinks.sort();
+    Ok(gitlinks)
+}
@@ -0,0 +1,209 @@
+//! Create lightweight and annotated tags, and extract the signature of signed tags for verification.
+//!
+//! The tag ref is written through a lock file, which is only moved into place once the tag object was written, so
+//! other processes either see the previous tag or the new one. Existing tags are only replaced if forced, like
+//! `git tag --force` does.
+//!
+//! Annotated tags are signed by a [`Sign`] function, whose signature is appended to the tag object. Signatures are
+//! verified by programs like `gpg` or `ssh-keygen`, which are given the payload and signature as obtained by
+//! [`extract_signature()`].
+use crate::{commit::Sign, remote::update, worktree::linked};
+use git_features::lock;
+use git_object::{
+    bstr::{BStr, BString, ByteSlice},
+    owned, HashKind,
+};
+use git_odb::{loose, Write as _};
+use quick_error::quick_error;
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        InvalidName(name: BString, err: git_ref::name::Error) {
+            display("'{}' is not a valid tag name", name)
+            source(err)
+        }
+        UnsafeName(name: BString, err: git_features::path::Error) {
+            display("The tag name '{}' can't be stored as file on this platform", name)
+            source(err)
+        }
+        StartsWithDash(name: BString) {
+            display("The tag name '{}' must not start with a dash", name)
+        }
+        Exists(name: BString, id: owned::Id) {
+            display("The tag '{}' exists already and points to {}", name, id)
+        }
+        Locked(path: PathBuf) {
+            display("The lock at '{}' exists, another process might be updating the tag", path.display())
+        }
+        Sign(err: Box<dyn std::error::Error + Send + Sync>) {
+            display("The tag could not be signed")
+            source(&**err)
+        }
+        ReadRef(err: update::Error) {
+            display("The existing tag could not be read")
+            from()
+            source(err)
+        }
+        WriteObject(err: loose::db::write::Error) {
+            display("The tag object could not be written")
+            from()
+            source(err)
+        }
+        Worktree(err: linked::Error) {
+            display("The common git directory of the worktree could not be determined")
+            from()
+            source(err)
+        }
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not write '{}'", path.display())
+            source(err)
+        }
+    }
+}
+
+/// The information stored in the tag object of an annotated tag.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Annotation {
+    /// The kind of object the tag points to, which is usually a commit.
+    pub target_kind: git_object::Kind,
+    /// The identity of the one creating the tag, along with the time of creation.
+    pub tagger: owned::Signature,
+    /// The message of the tag, which is terminated with a newline unless it is empty.
+    pub message: BString,
+}
+
+/// Configure how to create a tag.
+#[derive(Default)]
+pub struct Options<'a> {
+    /// If set, an annotated tag is created by writing a tag object with this information and pointing the tag ref
+    /// to it. Otherwise the tag is lightweight, pointing to the target directly.
+    pub annotation: Option<Annotation>,
+    /// If true, an existing tag of the same name is replaced.
+    pub force: bool,
+    /// If set, the tag object of an annotated tag is signed. It's ignored for lightweight tags.
+    pub sign: Option<Sign<'a>>,
+}
+
+/// The result of [`create()`].
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The object the tag ref points to, which is the tag object for annotated tags or the target otherwise.
+    pub id: owned::Id,
+    /// The object the tag pointed to before it was replaced, or `None` if it didn't exist.
+    pub previous: Option<owned::Id>,
+}
+
+/// Create the tag `name`, like `v1.0`, pointing to `target` in the repository at `git_dir`.
+pub fn create(
+    git_dir: impl AsRef<Path>,
+    name: &BStr,
+    target: owned::Id,
+    options: Options<'_>,
+) -> Result<Outcome, Error> {
+    let git_dir = git_dir.as_ref();
+    let mut ref_name = BString::from("refs/tags/");
+    ref_name.extend_from_slice(name);
+    git_ref::name::validate(ref_name.as_ref()).map_err(|err| Error::InvalidName(name.to_owned(), err))?;
+    if name.starts_with(b"-") {
+        return Err(Error::StartsWithDash(name.to_owned()));
+    }
+
+    let path = git_features::path::long(
+        linked::common_dir(git_dir)?.join(
+            git_features::path::to_native(ref_name.as_ref(), Default::default())
+                .map_err(|err| Error::UnsafeName(name.to_owned(), err))?,
+        ),
+    );
+    let mut lock = lock::File::acquire(path, Default::default()).map_err(lock_error)?;
+    let outcome = write_locked(git_dir, &mut lock, name, ref_name.as_ref(), target, options)?;
+    lock.commit().map_err(lock_error)?;
+    Ok(outcome)
+}
+
+fn lock_error(err: lock::Error) -> Error {
+    match err {
+        lock::Error::Locked(path) => Error::Locked(path),
+        lock::Error::Io(err, path) => Error::Io(err, path),
+    }
+}
+
+fn write_locked(
+    git_dir: &Path,
+    lock: &mut lock::File,
+    name: &BStr,
+    ref_name: &BStr,
+    target: owned::Id,
+    options: Options<'_>,
+) -> Result<Outcome, Error> {
+    let (_, previous) = update::resolve(git_dir, ref_name)?;
+    if let (Some(previous), false) = (previous, options.force) {
+        return Err(Error::Exists(name.to_owned(), previous));
+    }
+    let id = match options.annotation {
+        Some(Annotation {
+            target_kind,
+            tagger,
+            mut message,
+        }) => {
+            if !message.is_empty() && !message.ends_with(b"\n") {
+                message.push(b'\n');
+            }
+            let tag = owned::Tag {
+                target,
+                name: name.to_owned(),
+                target_kind,
+                message,
+                signature: Some(tagger),
+                pgp_signature: None,
+            };
+            let objects = loose::Db::at(linked::common_dir(git_dir)?.join("objects"));
+            match options.sign {
+                Some(mut sign) => {
+                    // The signature follows the message directly, which is how git finds it.
+                    let mut data = Vec::new();
+                    tag.write_to(&mut data).expect("writing to a vector does not fail");
+                    let signature = sign(&data).map_err(Error::Sign)?;
+                    data.extend_from_slice(&signature);
+                    objects.write_buf(git_object::Kind::Tag, &data, HashKind::Sha1)?
+                }
+                None => objects.write(&owned::Object::Tag(tag), HashKind::Sha1)?,
+            }
+        }
+        None => target,
+    };
+    lock.write_all(&id.to_sha1_hex())
+        .and_then(|_| lock.write_all(b"\n"))
+        .map_err(|err| Error::Io(err, git_dir.join(ref_name.to_path_lossy())))?;
+    Ok(Outcome { id, previous })
+}
+
+/// The lines starting the kinds of signatures git knows.
+const SIGNATURE_STARTS: &[&[u8]] = &[
+    b"-----BEGIN PGP SIGNATURE-----",
+    b"-----BEGIN PGP MESSAGE-----",
+    b"-----BEGIN SIGNED MESSAGE-----",
+    b"-----BEGIN SSH SIGNATURE-----",
+];
+
+/// Split the data of a signed tag object into the payload which was signed and the signature, or return `None` if it
+/// isn't signed.
+///
+/// Like git, the signature is assumed to start at the last line that starts a PGP or SSH signature, and to reach to
+/// the end of the object, while the payload is everything before it.
+pub fn extract_signature(data: &[u8]) -> Option<(&BStr, &BStr)> {
+    let mut line_start = 0;
+    let mut signature_start = None;
+    for line in data.lines_with_terminator() {
+        if SIGNATURE_STARTS.iter().any(|start| line.starts_with(start)) {
+            signature_start = Some(line_start);
+        }
+        line_start += line.len();
+    }
+    signature_start.map(|pos| (data[..pos].as_bstr(), data[pos..].as_bstr()))
+}
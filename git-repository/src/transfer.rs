@@ -0,0 +1,60 @@
+//! Settings shared by [`upload_pack()`][crate::upload_pack()] and [`receive_pack()`][crate::receive_pack()] to shape
+//! what they advertise, like `git` does with its `transfer.*` configuration.
+use git_object::bstr::{BStr, BString};
+use git_protocol::handshake::Ref;
+
+/// What to tell clients about ourselves and the refs of the repository.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Advertisement {
+    /// The value of the `agent` capability, which is `git/oxide-<version>` by default.
+    pub agent: String,
+    /// Patterns of refs not to advertise, like `transfer.hideRefs`.
+    ///
+    /// A pattern matches the ref of the same name and all refs below it, and patterns starting with `!` make refs
+    /// visible again. The last matching pattern decides, and refs matching none of them are visible.
+    pub hide_refs: Vec<BString>,
+}
+
+impl Default for Advertisement {
+    fn default() -> Self {
+        Advertisement {
+            agent: format!("git/oxide-{}", env!("CARGO_PKG_VERSION")),
+            hide_refs: Vec::new(),
+        }
+    }
+}
+
+impl Advertisement {
+    /// Returns true if the ref `name` must not be advertised.
+    pub fn is_hidden(&self, name: &BStr) -> bool {
+        self.hide_refs
+            .iter()
+            .rev()
+            .find_map(|pattern| {
+                let (hidden, prefix) = match pattern.strip_prefix(b"!") {
+                    Some(prefix) => (false, prefix),
+                    None => (true, pattern.as_slice()),
+                };
+                let matches = name.starts_with(prefix) && (name.len() == prefix.len() || name[prefix.len()] == b'/');
+                if matches {
+                    Some(hidden)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(false)
+    }
+
+    /// Return the `refs` which may be advertised.
+    pub fn visible<'a>(&'a self, refs: &'a [Ref]) -> impl Iterator<Item = &'a Ref> + 'a {
+        refs.iter().filter(move |r| !self.is_hidden(path(r)))
+    }
+}
+
+fn path(r: &Ref) -> &BStr {
+    match r {
+        Ref::Direct { path, .. } | Ref::Peeled { path, .. } | Ref::Symbolic { path, .. } | Ref::Unborn { path, .. } => {
+            path.as_ref()
+        }
+    }
+}
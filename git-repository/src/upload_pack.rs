@@ -0,0 +1,491 @@
+//! Serve fetches and clones of a repository, answering requests like `git upload-pack` does.
+//!
+//! Only the original protocol is spoken, which all clients understand, except for the V2 [`object_info()`] command
+//! which lets clients learn the sizes of objects without fetching them. The refs to advertise are provided by the
+//! caller along with a function to lookup objects, while the connection to the client is up to the host. It may
+//! be the pipes of a spawned process, a TCP connection accepted on behalf of `git daemon`, or the bodies of
+//! smart http requests in [stateless][Options::stateless] mode.
+//!
+//! Objects are chosen like `git push` does it: all objects reachable from the wanted objects which are not reachable
+//! from the objects the client has in common with us are sent as pack without deltas. If [enabled][Options::filter],
+//! clients may leave out blobs they don't need for now, like partial clones do with `--filter=blob:none`.
+use crate::{push::decoded, transfer};
+use git_features::progress::Progress;
+use git_object::{
+    borrowed,
+    bstr::{BString, ByteSlice},
+    owned,
+};
+use git_odb::{pack, traverse};
+use git_protocol::handshake::Ref;
+use git_transport::{packetline, packetline::PacketLine};
+use quick_error::quick_error;
+use std::{collections::HashSet, io};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred while talking to the client")
+            from()
+            source(err)
+        }
+        PacketLine(err: packetline::decode::Error) {
+            display("A packet line sent by the client could not be decoded")
+            from()
+            source(err)
+        }
+        UnexpectedLine(line: BString) {
+            display("The client sent the unexpected line '{}'", line)
+        }
+        InvalidId(hex: BString) {
+            display("'{}' could not be parsed as an object id", hex)
+        }
+        NotOurRef(id: owned::Id) {
+            display("The client asked for {}, which isn't the object of an advertised ref", id)
+        }
+        UnsupportedFilter(spec: BString) {
+            display("The client asked for the unsupported filter '{}'", spec)
+        }
+        UnexpectedEndOfInput {
+            display("The client disconnected before it was done negotiating")
+        }
+        Traverse(err: traverse::commit_and_objects::Error) {
+            display("The objects to send could not be determined")
+            from()
+            source(err)
+        }
+        WritePack(err: pack::data::write::Error) {
+            display("The pack could not be written")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// The capabilities advertised to clients in addition to `symref`, `agent` and the ones enabled in [`Options`].
+pub const CAPABILITIES: &[&str] = &["side-band", "side-band-64k", "no-progress", "include-tag"];
+
+/// Configure how to talk to the client.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+pub struct Options {
+    /// If true, each request of the client is answered on its own, like the smart http protocol demands.
+    ///
+    /// Refs are not advertised at the beginning, use [`advertise_refs()`] for that, and negotiation ends after
+    /// the first round of `have` lines without a pack unless the client is `done`.
+    pub stateless: bool,
+    /// The agent to advertise and the refs to hide.
+    pub advertisement: transfer::Advertisement,
+    /// If true, clients may ask for the objects of hidden refs as well, like `uploadpack.allowTipSHA1InWant`.
+    pub allow_tip_sha1_in_want: bool,
+    /// If true, clients may ask to leave out all blobs or those larger than a limit, like `uploadpack.allowFilter`.
+    pub filter: bool,
+}
+
+/// The objects the client asked to leave out of the pack.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum Filter {
+    /// Send no blobs, as requested with `blob:none`.
+    BlobNone,
+    /// Send only blobs up to the given size in bytes, as requested with `blob:limit=<n>[kmg]`.
+    BlobLimit(u64),
+}
+
+impl Filter {
+    /// Parse the filter `spec` as sent by the client, or return `None` if it isn't supported.
+    pub fn from_spec(spec: &[u8]) -> Option<Self> {
+        if spec == b"blob:none" {
+            return Some(Filter::BlobNone);
+        }
+        let limit = spec.strip_prefix(b"blob:limit=")?;
+        let (digits, factor) = match limit.last()?.to_ascii_lowercase() {
+            b'k' => (&limit[..limit.len() - 1], 1024),
+            b'm' => (&limit[..limit.len() - 1], 1024 * 1024),
+            b'g' => (&limit[..limit.len() - 1], 1024 * 1024 * 1024),
+            _ => (limit, 1),
+        };
+        let limit: u64 = digits.to_str().ok()?.parse().ok()?;
+        limit.checked_mul(factor).map(Filter::BlobLimit)
+    }
+}
+
+/// The result of [`upload_pack()`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Outcome {
+    /// The objects the client asked for, which is empty if it just wanted to see the refs.
+    pub wants: Vec<owned::Id>,
+    /// The objects the client said it has which we have as well.
+    pub common: Vec<owned::Id>,
+    /// The capabilities the client asked for with its first `want` line.
+    pub capabilities: Vec<BString>,
+    /// The objects the client asked to leave out, if any.
+    pub filter: Option<Filter>,
+    /// Information about the sent pack, or `None` if no pack was sent.
+    pub pack: Option<pack::data::write::Outcome>,
+}
+
+/// Write the V1 advertisement of the `refs` which aren't hidden along with our capabilities as configured in `options`
+/// to `out`, followed by a flush packet.
+pub fn advertise_refs(refs: &[Ref], options: &Options, mut out: impl io::Write) -> io::Result<()> {
+    let refs: Vec<_> = options.advertisement.visible(refs).collect();
+    let mut capabilities: Vec<String> = CAPABILITIES.iter().map(|c| (*c).to_owned()).collect();
+    if options.allow_tip_sha1_in_want {
+        capabilities.push("allow-tip-sha1-in-want".into());
+    }
+    if options.filter {
+        capabilities.push("filter".into());
+    }
+    for r in &refs {
+        if let Ref::Symbolic { path, target, .. } = r {
+            capabilities.push(format!("symref={}:{}", path, target));
+        }
+    }
+    capabilities.push(format!("agent={}", options.advertisement.agent));
+    let capabilities = capabilities.join(" ");
+
+    let mut lines = Vec::new();
+    for r in refs {
+        match r {
+            Ref::Direct { path, object } | Ref::Symbolic { path, object, .. } => {
+                lines.push(format!("{} {}", object, path));
+            }
+            Ref::Peeled { path, tag, object } => {
+                lines.push(format!("{} {}", tag, path));
+                lines.push(format!("{} {}^{{}}", object, path));
+            }
+            Ref::Unborn { .. } => {}
+        }
+    }
+    if lines.is_empty() {
+        lines.push(format!("{} capabilities^{{}}", owned::Id::null_sha1()));
+    }
+    for (index, line) in lines.iter().enumerate() {
+        if index == 0 {
+            packetline::encode::text_to_write(format!("{}\0{}", line, capabilities).as_bytes(), &mut out)?;
+        } else {
+            packetline::encode::text_to_write(line.as_bytes(), &mut out)?;
+        }
+    }
+    packetline::encode::flush_to_write(&mut out)?;
+    out.flush()
+}
+
+/// Answer the fetch request read from `input` by writing to `out`, advertising `refs` first unless
+/// [stateless][Options::stateless].
+///
+/// Clients may only ask for the objects of refs which aren't hidden, unless the [objects of all
+/// refs][Options::allow_tip_sha1_in_want] are allowed.
+///
+/// `find` is used to lookup objects, placing their data into the provided buffer and returning it along with its kind.
+/// Progress of writing the pack is reported to `progress`, and to the client if it asked for it.
+pub fn upload_pack<Find>(
+    input: impl io::Read,
+    mut out: impl io::Write,
+    refs: &[Ref],
+    mut find: Find,
+    progress: impl Progress,
+    options: Options,
+) -> Result<Outcome, Error>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<pack::Object<'a>>,
+{
+    if !options.stateless {
+        advertise_refs(refs, &options, &mut out)?;
+    }
+    let mut outcome = Outcome {
+        wants: Vec::new(),
+        common: Vec::new(),
+        capabilities: Vec::new(),
+        filter: None,
+        pack: None,
+    };
+    let wantable: Vec<_> = if options.allow_tip_sha1_in_want {
+        refs.iter().collect()
+    } else {
+        options.advertisement.visible(refs).collect()
+    };
+    let advertised: HashSet<_> = wantable
+        .into_iter()
+        .flat_map(|r| match r {
+            Ref::Peeled { tag, object, .. } => vec![*tag, *object],
+            Ref::Direct { object, .. } | Ref::Symbolic { object, .. } => vec![*object],
+            Ref::Unborn { .. } => Vec::new(),
+        })
+        .collect();
+
+    let mut lines = packetline::Provider::new(input, &[PacketLine::Flush]);
+    while let Some(line) = lines.next_line() {
+        let line = line??;
+        let text = line.to_text().unwrap_or_default();
+        if let (Some(spec), true) = (text.strip_prefix(b"filter "), options.filter) {
+            match Filter::from_spec(spec) {
+                Some(filter) => outcome.filter = Some(filter),
+                None => {
+                    let message = format!("upload-pack: unsupported filter '{}'", spec.as_bstr());
+                    packetline::encode::error_to_write(message.as_bytes(), &mut out)?;
+                    return Err(Error::UnsupportedFilter(spec.into()));
+                }
+            }
+            continue;
+        }
+        let want = text
+            .strip_prefix(b"want ")
+            .ok_or_else(|| Error::UnexpectedLine(text.to_owned()))?;
+        let (hex, capabilities) = match want.find_byte(b' ') {
+            Some(pos) => (&want[..pos], &want[pos + 1..]),
+            None => (want, &[][..]),
+        };
+        let id = parse_id(hex)?;
+        if !advertised.contains(&id) {
+            packetline::encode::error_to_write(format!("upload-pack: not our ref {}", id).as_bytes(), &mut out)?;
+            return Err(Error::NotOurRef(id));
+        }
+        if outcome.wants.is_empty() {
+            outcome.capabilities = capabilities.fields().map(|c| c.into()).collect();
+        }
+        outcome.wants.push(id);
+    }
+    if outcome.wants.is_empty() {
+        // The client hangs up after seeing the refs, like `git ls-remote` does.
+        return Ok(outcome);
+    }
+
+    let mut buf = Vec::new();
+    loop {
+        lines.reset();
+        let mut done = false;
+        while let Some(line) = lines.next_line() {
+            let line = line??;
+            let text = line.to_text().unwrap_or_default();
+            if text == "done" {
+                done = true;
+                break;
+            }
+            let id = parse_id(
+                text.strip_prefix(b"have ")
+                    .ok_or_else(|| Error::UnexpectedLine(text.to_owned()))?,
+            )?;
+            if find(id.to_borrowed(), &mut buf).is_some() {
+                outcome.common.push(id);
+                if outcome.common.len() == 1 {
+                    packetline::encode::text_to_write(format!("ACK {}", id).as_bytes(), &mut out)?;
+                    out.flush()?;
+                }
+            }
+        }
+        if !done && lines.stopped_at().is_none() {
+            return Err(Error::UnexpectedEndOfInput);
+        }
+        if outcome.common.is_empty() {
+            packetline::encode::text_to_write(b"NAK", &mut out)?;
+            out.flush()?;
+        }
+        if done {
+            break;
+        }
+        if options.stateless {
+            return Ok(outcome);
+        }
+    }
+
+    let has = |name: &str| outcome.capabilities.iter().any(|c| c == name);
+    let max_data_len = if has("side-band-64k") {
+        Some(packetline::MAX_DATA_LEN - 1)
+    } else if has("side-band") {
+        Some(995)
+    } else {
+        None
+    };
+    let send_progress = max_data_len.is_some() && !has("no-progress");
+    let include_tags = has("include-tag");
+
+    let ids = match object_ids(&outcome, refs, include_tags, &mut find) {
+        Ok(ids) => ids,
+        Err(err) => {
+            if max_data_len.is_some() {
+                report_error(&err, &mut out);
+            }
+            return Err(err);
+        }
+    };
+    if send_progress {
+        let message = format!("Enumerating objects: {}, done.\n", ids.len());
+        packetline::encode::band_to_write(packetline::Channel::Progress, message.as_bytes(), &mut out)?;
+    }
+    let pack = match max_data_len {
+        Some(max_data_len) => {
+            let mut writer = io::BufWriter::with_capacity(
+                max_data_len,
+                Sideband {
+                    out: &mut out,
+                    max_data_len,
+                },
+            );
+            let pack = pack::data::File::write_objects_to_stream(&ids, &mut find, progress, &mut writer);
+            let pack = pack.and_then(|pack| io::Write::flush(&mut writer).map(|_| pack).map_err(Into::into));
+            drop(writer);
+            match pack {
+                Ok(pack) => pack,
+                Err(err) => {
+                    let err = Error::from(err);
+                    report_error(&err, &mut out);
+                    return Err(err);
+                }
+            }
+        }
+        None => pack::data::File::write_objects_to_stream(&ids, &mut find, progress, &mut out)?,
+    };
+    if send_progress {
+        let message = format!("Total {} (delta 0), reused 0 (delta 0)\n", pack.num_objects);
+        packetline::encode::band_to_write(packetline::Channel::Progress, message.as_bytes(), &mut out)?;
+    }
+    if max_data_len.is_some() {
+        packetline::encode::flush_to_write(&mut out)?;
+    }
+    out.flush()?;
+    outcome.pack = Some(pack);
+    Ok(outcome)
+}
+
+/// Write the V2 capability advertisement announcing the commands we serve with protocol version 2 to `out`, which
+/// currently is only `object-info`, followed by a flush packet.
+pub fn advertise_v2_capabilities(options: &Options, mut out: impl io::Write) -> io::Result<()> {
+    packetline::encode::text_to_write(b"version 2", &mut out)?;
+    packetline::encode::text_to_write(format!("agent={}", options.advertisement.agent).as_bytes(), &mut out)?;
+    packetline::encode::text_to_write(b"object-info", &mut out)?;
+    packetline::encode::flush_to_write(&mut out)?;
+    out.flush()
+}
+
+/// Answer the V2 `object-info` request read from `input` by writing the requested attributes of each object to `out`,
+/// and return the ids of the objects the client asked about.
+///
+/// The request starts with the `command=object-info` line. `size` is the only attribute, and it is obtained with
+/// `header`, which returns the size of the object's data or `None` if the object doesn't exist, like
+/// [`Find::header()`][git_odb::Find::header()] does. The sizes of missing objects are left empty.
+pub fn object_info(
+    input: impl io::Read,
+    mut out: impl io::Write,
+    mut header: impl FnMut(borrowed::Id<'_>) -> Option<u64>,
+) -> Result<Vec<owned::Id>, Error> {
+    let mut lines = packetline::Provider::new(input, &[PacketLine::Flush]);
+    let mut in_arguments = false;
+    let mut size = false;
+    let mut ids = Vec::new();
+    while let Some(line) = lines.next_line() {
+        let line = line??;
+        if let PacketLine::Delimiter = line {
+            in_arguments = true;
+            continue;
+        }
+        let text = line.to_text().unwrap_or_default();
+        if !in_arguments {
+            let is_known =
+                text == "command=object-info" || text.starts_with(b"agent=") || text.starts_with(b"object-format=");
+            if !is_known {
+                return Err(Error::UnexpectedLine(text.to_owned()));
+            }
+        } else if text == "size" {
+            size = true;
+        } else if let Some(hex) = text.strip_prefix(b"oid ") {
+            ids.push(parse_id(hex)?);
+        } else {
+            return Err(Error::UnexpectedLine(text.to_owned()));
+        }
+    }
+    if lines.stopped_at().is_none() {
+        return Err(Error::UnexpectedEndOfInput);
+    }
+
+    if size {
+        packetline::encode::text_to_write(b"size", &mut out)?;
+    }
+    for id in &ids {
+        let line = if size {
+            let size = header(id.to_borrowed())
+                .map(|size| size.to_string())
+                .unwrap_or_default();
+            format!("{} {}", id, size)
+        } else {
+            id.to_string()
+        };
+        packetline::encode::text_to_write(line.as_bytes(), &mut out)?;
+    }
+    packetline::encode::flush_to_write(&mut out)?;
+    out.flush()?;
+    Ok(ids)
+}
+
+fn parse_id(hex: &[u8]) -> Result<owned::Id, Error> {
+    owned::Id::from_40_bytes_in_hex(hex).map_err(|_| Error::InvalidId(hex.into()))
+}
+
+fn object_ids<Find>(
+    outcome: &Outcome,
+    refs: &[Ref],
+    include_tags: bool,
+    find: &mut Find,
+) -> Result<Vec<owned::Id>, Error>
+where
+    Find: for<'a> FnMut(borrowed::Id<'_>, &'a mut Vec<u8>) -> Option<pack::Object<'a>>,
+{
+    let seen = traverse::commit_and_objects(outcome.common.iter().copied(), |id, buf| decoded(find, id, buf))
+        .map(|entry| entry.map(|e| e.id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let entries = traverse::commit_and_objects(outcome.wants.iter().copied(), |id, buf| decoded(find, id, buf))
+        .with_seen(seen)
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut buf = Vec::new();
+    let mut ids: Vec<_> = entries
+        .into_iter()
+        .filter(|entry| {
+            // Like git, wanted objects are sent even if they are filtered.
+            entry.kind != git_object::Kind::Blob
+                || outcome.wants.contains(&entry.id)
+                || match outcome.filter {
+                    None => true,
+                    Some(Filter::BlobNone) => false,
+                    Some(Filter::BlobLimit(limit)) => {
+                        find(entry.id.to_borrowed(), &mut buf).is_none_or(|object| object.data.len() as u64 <= limit)
+                    }
+                }
+        })
+        .map(|entry| entry.id)
+        .collect();
+    if include_tags {
+        let sent: HashSet<_> = ids.iter().copied().collect();
+        for r in refs {
+            if let Ref::Peeled { tag, object, .. } = r {
+                if sent.contains(object) && !sent.contains(tag) {
+                    ids.push(*tag);
+                }
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// Tell the client why the pack won't arrive, ignoring errors as the connection may be gone already.
+fn report_error(err: &Error, mut out: impl io::Write) {
+    let message = format!("upload-pack: {}\n", err);
+    packetline::encode::band_to_write(packetline::Channel::Error, message.as_bytes(), &mut out).ok();
+    out.flush().ok();
+}
+
+/// Writes all data as side-band lines on the data channel.
+struct Sideband<W> {
+    out: W,
+    max_data_len: usize,
+}
+
+impl<W: io::Write> io::Write for Sideband<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.max_data_len);
+        packetline::encode::band_to_write(packetline::Channel::Data, &buf[..len], &mut self.out)?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,691 @@
+//! Write the files of a tree into a tar, gzip-compressed tar or zip archive, similar to `git archive`.
+//!
+//! Tar archives are written exactly like git writes them, including the pax header with the id of the archived commit.
+//! Zip archives store files uncompressed if deflating them wouldn't make them smaller, and the id of the commit is
+//! stored as the archive comment.
+//!
+//! The `.gitattributes` files in the tree are used like git does: files and directories with the `export-ignore`
+//! attribute are left out, and `$Format:…$` placeholders in files with the `export-subst` attribute are replaced with
+//! information about the commit, if one is archived. The supported placeholders are the ones of
+//! [`pretty::expand()`][crate::pretty::expand()], with dates in the default format and without mailmap.
+use crate::{attributes, date, mailmap, pretty};
+use git_object::{
+    borrowed,
+    bstr::{BStr, BString, ByteSlice},
+    owned, TreeMode,
+};
+use miniz_oxide::deflate::{
+    self,
+    core::{create_comp_flags_from_zip_params, CompressorOxide, TDEFLFlush, TDEFLStatus},
+};
+use quick_error::quick_error;
+use std::{convert::TryFrom, io};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Find(id: owned::Id) {
+            display("Object {} could not be found", id)
+        }
+        NotATree(id: owned::Id) {
+            display("Object {} can't be archived as it doesn't point to a tree", id)
+        }
+        NotABlob(id: owned::Id) {
+            display("Object {} was expected to be a blob", id)
+        }
+        ZipTooLarge {
+            display("Zip archives of more than 4GB or 65535 entries are not supported")
+        }
+        Io(err: io::Error) {
+            display("The archive could not be written")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// The kind of archive to write.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Format {
+    /// An uncompressed tar archive.
+    Tar,
+    /// A tar archive compressed with gzip, also known as `.tgz` or `.tar.gz`.
+    TarGz,
+    /// A zip archive with deflate compression.
+    Zip,
+}
+
+/// The default compression level for [`Options::compression_level`].
+pub const DEFAULT_COMPRESSION_LEVEL: u8 = 6;
+
+/// Configure how to write an archive.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Options {
+    /// The kind of archive to write.
+    pub format: Format,
+    /// A string to prepend to all paths, like `project/` to put all files into the `project` directory.
+    pub prefix: BString,
+    /// The modification time of all entries in seconds since the epoch, or `None` to use the time the archived commit
+    /// was committed, or the current time if a tree is archived.
+    pub mtime: Option<u32>,
+    /// The level of compression from 0 to 9, for compressed formats.
+    pub compression_level: u8,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            format: Format::Tar,
+            prefix: BString::default(),
+            mtime: None,
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+        }
+    }
+}
+
+/// The result of [`archive()`].
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The commit which was archived, or `None` if a tree was archived.
+    pub commit: Option<owned::Id>,
+    /// The amount of files, symbolic links and directories that were written.
+    pub entries: usize,
+}
+
+/// Write the tree of `treeish`, which is a commit, a tag pointing to one or a tree, into `out` as archive.
+///
+/// `find` is used to lookup all objects.
+pub fn archive<Find>(
+    treeish: borrowed::Id<'_>,
+    mut find: Find,
+    out: impl io::Write,
+    options: Options,
+) -> Result<Outcome, Error>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    let mut buf = Vec::new();
+    let mut id = owned::Id::from_borrowed_sha1(treeish.sha1());
+    let mut commit = None;
+    let tree = loop {
+        match find(id.to_borrowed(), &mut buf).ok_or(Error::Find(id))? {
+            borrowed::Object::Tag(tag) => id = tag.target(),
+            borrowed::Object::Commit(c) => {
+                commit = Some(pretty::Commit::from_borrowed(id, &c));
+                break c.tree();
+            }
+            borrowed::Object::Tree(_) => break id,
+            borrowed::Object::Blob(_) => return Err(Error::NotATree(id)),
+        }
+    };
+    let mtime = match (options.mtime, &commit) {
+        (Some(mtime), _) => mtime,
+        (None, Some(commit)) => commit.committer.time.time,
+        (None, None) => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or_default(),
+    };
+
+    let mut walk = Walk {
+        find,
+        buf,
+        attributes: attributes::Stack::default(),
+        commit: commit.as_ref(),
+        entries: 0,
+    };
+    let commit_id = commit.as_ref().map(|c| c.id);
+    let prefix = options.prefix.as_ref();
+    match options.format {
+        Format::Tar => {
+            let mut tar = Tar::new(out, mtime, commit_id)?;
+            walk.root(&mut tar, tree, prefix)?;
+            tar.finish()?;
+        }
+        Format::TarGz => {
+            let mut tar = Tar::new(Gzip::new(out, options.compression_level)?, mtime, commit_id)?;
+            walk.root(&mut tar, tree, prefix)?;
+            tar.finish()?.finish()?;
+        }
+        Format::Zip => {
+            let mut zip = Zip::new(out, mtime, commit_id, options.compression_level);
+            walk.root(&mut zip, tree, prefix)?;
+            zip.finish()?;
+        }
+    }
+    Ok(Outcome {
+        commit: commit_id,
+        entries: walk.entries,
+    })
+}
+
+/// An entry to write into an archive.
+struct Entry<'a> {
+    /// The path including the prefix, with a trailing slash for directories.
+    path: &'a [u8],
+    /// [`TreeMode::Tree`] for all directories, including those of submodules.
+    mode: TreeMode,
+    id: owned::Id,
+    /// The data of files, or the target of symbolic links.
+    data: &'a [u8],
+}
+
+trait Archive {
+    fn add(&mut self, entry: Entry<'_>) -> Result<(), Error>;
+}
+
+struct Walk<'a, Find> {
+    find: Find,
+    buf: Vec<u8>,
+    attributes: attributes::Stack,
+    commit: Option<&'a pretty::Commit>,
+    entries: usize,
+}
+
+impl<'a, Find> Walk<'a, Find>
+where
+    Find: for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>>,
+{
+    fn root(&mut self, archive: &mut impl Archive, tree: owned::Id, prefix: &BStr) -> Result<(), Error> {
+        if prefix.ends_with(b"/") {
+            self.entries += 1;
+            archive.add(Entry {
+                path: prefix,
+                mode: TreeMode::Tree,
+                id: tree,
+                data: &[],
+            })?;
+        }
+        let mut path = prefix.to_owned();
+        self.tree(archive, tree, prefix.len(), &mut path)
+    }
+
+    /// Write the contents of `tree`, whose path is `path`, with `path[..prefix_len]` being the prefix.
+    fn tree(
+        &mut self,
+        archive: &mut impl Archive,
+        tree: owned::Id,
+        prefix_len: usize,
+        path: &mut BString,
+    ) -> Result<(), Error> {
+        let entries: Vec<_> = match (self.find)(tree.to_borrowed(), &mut self.buf).ok_or(Error::Find(tree))? {
+            borrowed::Object::Tree(tree) => tree
+                .entries
+                .iter()
+                .map(|e| {
+                    (
+                        e.mode,
+                        e.filename.to_owned(),
+                        owned::Id::from_borrowed_sha1(e.oid.sha1()),
+                    )
+                })
+                .collect(),
+            _ => return Err(Error::NotATree(tree)),
+        };
+        if let Some((_, _, id)) = entries
+            .iter()
+            .find(|(mode, name, _)| *mode == TreeMode::Blob && name == ".gitattributes")
+        {
+            let directory = path[prefix_len..].trim_end_with(|c| c == '/').as_bstr().to_owned();
+            let data = self.blob(*id)?.to_owned();
+            self.attributes.add(directory.as_ref(), &data);
+        }
+
+        for (mode, name, id) in entries {
+            let path_len = path.len();
+            path.extend_from_slice(&name);
+            if self.attributes.is_set(path[prefix_len..].as_bstr(), "export-ignore") {
+                path.truncate(path_len);
+                continue;
+            }
+            self.entries += 1;
+            match mode {
+                TreeMode::Tree | TreeMode::Commit => {
+                    path.push(b'/');
+                    archive.add(Entry {
+                        path,
+                        mode: TreeMode::Tree,
+                        id,
+                        data: &[],
+                    })?;
+                    if mode == TreeMode::Tree {
+                        self.tree(archive, id, prefix_len, path)?;
+                    }
+                }
+                TreeMode::Blob | TreeMode::BlobExecutable | TreeMode::Link => {
+                    let substitute = mode != TreeMode::Link
+                        && self.commit.is_some()
+                        && self.attributes.is_set(path[prefix_len..].as_bstr(), "export-subst");
+                    let commit = self.commit;
+                    let data = self.blob(id)?;
+                    let substituted;
+                    let data = match commit {
+                        Some(commit) if substitute => {
+                            substituted = substitute_placeholders(data, commit);
+                            &substituted
+                        }
+                        _ => data,
+                    };
+                    archive.add(Entry { path, mode, id, data })?;
+                }
+            }
+            path.truncate(path_len);
+        }
+        Ok(())
+    }
+
+    fn blob(&mut self, id: owned::Id) -> Result<&[u8], Error> {
+        match (self.find)(id.to_borrowed(), &mut self.buf).ok_or(Error::Find(id))? {
+            borrowed::Object::Blob(blob) => Ok(blob.data),
+            _ => Err(Error::NotABlob(id)),
+        }
+    }
+}
+
+/// Replace all `$Format:…$` placeholders in `data` with the information of `commit`.
+fn substitute_placeholders(data: &[u8], commit: &pretty::Commit) -> Vec<u8> {
+    const START: &[u8] = b"$Format:";
+    let mut out = Vec::with_capacity(data.len());
+    let mut rest = data;
+    while let Some(start) = rest.find(START) {
+        let format = &rest[start + START.len()..];
+        let end = match format.find_byte(b'$') {
+            Some(end) => end,
+            None => break,
+        };
+        out.extend_from_slice(&rest[..start]);
+        pretty::expand(
+            &format[..end],
+            commit,
+            &mailmap::Snapshot::default(),
+            date::Format::Default,
+            &mut out,
+        );
+        rest = &format[end + 1..];
+    }
+    out.extend_from_slice(rest);
+    out
+}
+
+const BLOCK_SIZE: usize = 512;
+/// Git pads tar archives to a multiple of this.
+const RECORD_SIZE: usize = BLOCK_SIZE * 20;
+/// The permission bits git removes by default, as configured with `tar.umask`.
+const UMASK: u32 = 0o002;
+
+/// Write tar archives like git does.
+struct Tar<W> {
+    out: W,
+    written: usize,
+    mtime: u32,
+}
+
+impl<W: io::Write> Tar<W> {
+    fn new(out: W, mtime: u32, commit: Option<owned::Id>) -> io::Result<Self> {
+        let mut tar = Tar { out, written: 0, mtime };
+        if let Some(commit) = commit {
+            let mut header = Vec::new();
+            pax_record(&mut header, "comment", &commit.to_sha1_hex());
+            tar.header(b"pax_global_header", &[], 0o666, b'g', header.len(), &[])?;
+            tar.data(&header)?;
+        }
+        Ok(tar)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn header(
+        &mut self,
+        name: &[u8],
+        prefix: &[u8],
+        mode: u32,
+        typeflag: u8,
+        size: usize,
+        linkname: &[u8],
+    ) -> io::Result<()> {
+        let mut header = [0u8; BLOCK_SIZE];
+        let mut field = |range: std::ops::Range<usize>, value: &[u8]| {
+            let len = value.len().min(range.len());
+            header[range.start..range.start + len].copy_from_slice(&value[..len]);
+        };
+        field(0..100, name);
+        field(100..108, format!("{:07o}", mode & 0o7777).as_bytes());
+        field(108..116, b"0000000");
+        field(116..124, b"0000000");
+        field(124..136, format!("{:011o}", size).as_bytes());
+        field(136..148, format!("{:011o}", self.mtime).as_bytes());
+        field(156..157, &[typeflag]);
+        field(157..257, linkname);
+        field(257..263, b"ustar\0");
+        field(263..265, b"00");
+        field(265..297, b"root");
+        field(297..329, b"root");
+        field(329..337, b"0000000");
+        field(337..345, b"0000000");
+        field(345..500, prefix);
+        let checksum: u32 = header[..148]
+            .iter()
+            .chain(&[b' '; 8])
+            .chain(&header[156..])
+            .map(|b| *b as u32)
+            .sum();
+        header[148..156].copy_from_slice(format!("{:07o}\0", checksum).as_bytes());
+        self.write(&header)
+    }
+
+    /// Write `data` padded to a full block.
+    fn data(&mut self, data: &[u8]) -> io::Result<()> {
+        self.write(data)?;
+        let tail = (BLOCK_SIZE - data.len() % BLOCK_SIZE) % BLOCK_SIZE;
+        self.write(&[0; BLOCK_SIZE][..tail])
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.out.write_all(data)?;
+        self.written += data.len();
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        let tail = RECORD_SIZE - self.written % RECORD_SIZE;
+        self.write(&vec![0; tail])?;
+        if tail < 2 * BLOCK_SIZE {
+            self.write(&[0; RECORD_SIZE])?;
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+impl<W: io::Write> Archive for Tar<W> {
+    fn add(&mut self, entry: Entry<'_>) -> Result<(), Error> {
+        let Entry { path, mode, id, data } = entry;
+        let (typeflag, mode) = match mode {
+            TreeMode::Tree | TreeMode::Commit => (b'5', 0o777 & !UMASK),
+            TreeMode::Link => (b'2', 0o777),
+            TreeMode::BlobExecutable => (b'0', 0o777 & !UMASK),
+            TreeMode::Blob => (b'0', 0o666 & !UMASK),
+        };
+        let mut extended = Vec::new();
+        let (mut name, mut prefix): (Vec<u8>, &[u8]) = (path.to_owned(), &[]);
+        if path.len() > 100 {
+            let prefix_len = path_prefix_len(path, 155);
+            let rest = path.len() - prefix_len - 1;
+            if prefix_len > 0 && rest <= 100 {
+                prefix = &path[..prefix_len];
+                name = path[prefix_len + 1..].to_owned();
+            } else {
+                name = format!("{}.data", id).into_bytes();
+                pax_record(&mut extended, "path", path);
+            }
+        }
+        let mut linkname = Vec::new();
+        if typeflag == b'2' {
+            if data.len() > 100 {
+                linkname = format!("see {}.paxheader", id).into_bytes();
+                pax_record(&mut extended, "linkpath", data);
+            } else {
+                linkname = data.to_owned();
+            }
+        }
+        if !extended.is_empty() {
+            self.header(
+                format!("{}.paxheader", id).as_bytes(),
+                &[],
+                0o666,
+                b'x',
+                extended.len(),
+                &[],
+            )?;
+            self.data(&extended)?;
+        }
+        let size = if typeflag == b'0' { data.len() } else { 0 };
+        self.header(&name, prefix, mode, typeflag, size, &linkname)?;
+        if typeflag == b'0' {
+            self.data(data)?;
+        }
+        Ok(())
+    }
+}
+
+/// The length of the longest leading part of `path` ending before a slash, which fits into `max_len` bytes.
+fn path_prefix_len(path: &[u8], max_len: usize) -> usize {
+    let mut i = path.len();
+    if i > 1 && path[i - 1] == b'/' {
+        i -= 1;
+    }
+    i = i.min(max_len);
+    loop {
+        i -= 1;
+        if i == 0 || path[i] == b'/' {
+            return i;
+        }
+    }
+}
+
+/// Append a pax record, which starts with its own length.
+fn pax_record(out: &mut Vec<u8>, key: &str, value: &[u8]) {
+    let mut len = 1 + 1 + key.len() + 1 + value.len() + 1;
+    let mut digits = len;
+    while digits >= 10 {
+        digits /= 10;
+        len += 1;
+    }
+    out.extend_from_slice(format!("{} {}=", len, key).as_bytes());
+    out.extend_from_slice(value);
+    out.push(b'\n');
+}
+
+/// A writer compressing everything in the gzip format.
+struct Gzip<W> {
+    out: W,
+    compressor: Box<CompressorOxide>,
+    buf: Vec<u8>,
+    crc32: u32,
+    size: u32,
+}
+
+impl<W: io::Write> Gzip<W> {
+    fn new(mut out: W, level: u8) -> io::Result<Self> {
+        // Magic, deflate, no flags, no modification time, no extra flags and unix as operating system.
+        out.write_all(&[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3])?;
+        Ok(Gzip {
+            out,
+            compressor: Box::new(CompressorOxide::new(create_comp_flags_from_zip_params(
+                level.min(9) as i32,
+                -15,
+                0,
+            ))),
+            buf: vec![0; 32 * 1024],
+            crc32: 0,
+            size: 0,
+        })
+    }
+
+    fn compress(&mut self, mut input: &[u8], flush: TDEFLFlush) -> io::Result<()> {
+        loop {
+            let (status, consumed, written) =
+                deflate::core::compress(&mut self.compressor, input, &mut self.buf, flush);
+            self.out.write_all(&self.buf[..written])?;
+            input = &input[consumed..];
+            match status {
+                TDEFLStatus::Done => return Ok(()),
+                TDEFLStatus::Okay if input.is_empty() && (flush == TDEFLFlush::None || written < self.buf.len()) => {
+                    return Ok(())
+                }
+                TDEFLStatus::Okay => {}
+                TDEFLStatus::BadParam | TDEFLStatus::PutBufFailed => return Err(io::ErrorKind::InvalidInput.into()),
+            }
+        }
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        self.compress(&[], TDEFLFlush::Finish)?;
+        self.out.write_all(&self.crc32.to_le_bytes())?;
+        self.out.write_all(&self.size.to_le_bytes())?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+impl<W: io::Write> io::Write for Gzip<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.crc32 = git_features::hash::crc32_update(self.crc32, buf);
+        self.size = self.size.wrapping_add(buf.len() as u32);
+        self.compress(buf, TDEFLFlush::None)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Write zip archives, storing the central directory until the end.
+struct Zip<W> {
+    out: W,
+    offset: u64,
+    central_directory: Vec<u8>,
+    num_entries: usize,
+    /// The modification time in MS-DOS format, as well as in seconds since the epoch.
+    dos_time: u16,
+    dos_date: u16,
+    mtime: u32,
+    comment: Vec<u8>,
+    level: u8,
+}
+
+impl<W: io::Write> Zip<W> {
+    fn new(out: W, mtime: u32, commit: Option<owned::Id>, level: u8) -> Self {
+        let (dos_date, dos_time) = dos_date_time(mtime);
+        Zip {
+            out,
+            offset: 0,
+            central_directory: Vec::new(),
+            num_entries: 0,
+            dos_time,
+            dos_date,
+            mtime,
+            comment: commit.map(|id| id.to_sha1_hex().to_vec()).unwrap_or_default(),
+            level: level.min(9),
+        }
+    }
+
+    fn finish(mut self) -> Result<W, Error> {
+        let entries = u16::try_from(self.num_entries).map_err(|_| Error::ZipTooLarge)?;
+        let offset = u32::try_from(self.offset).map_err(|_| Error::ZipTooLarge)?;
+        let size = u32::try_from(self.central_directory.len()).map_err(|_| Error::ZipTooLarge)?;
+        self.out.write_all(&self.central_directory)?;
+        let mut end = Vec::new();
+        end.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        end.extend_from_slice(&[0; 4]);
+        end.extend_from_slice(&entries.to_le_bytes());
+        end.extend_from_slice(&entries.to_le_bytes());
+        end.extend_from_slice(&size.to_le_bytes());
+        end.extend_from_slice(&offset.to_le_bytes());
+        end.extend_from_slice(&(self.comment.len() as u16).to_le_bytes());
+        end.extend_from_slice(&self.comment);
+        self.out.write_all(&end)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+impl<W: io::Write> Archive for Zip<W> {
+    fn add(&mut self, entry: Entry<'_>) -> Result<(), Error> {
+        let Entry { path, mode, data, .. } = entry;
+        let (unix_mode, dos_attributes) = match mode {
+            TreeMode::Tree | TreeMode::Commit => (0o040_755u32, 0x10u32),
+            TreeMode::Link => (0o120_777, 0),
+            TreeMode::BlobExecutable => (0o100_755, 0),
+            TreeMode::Blob => (0o100_644, 0),
+        };
+        let deflated = if mode != TreeMode::Tree && self.level != 0 && !data.is_empty() {
+            Some(deflate::compress_to_vec(data, self.level)).filter(|deflated| deflated.len() < data.len())
+        } else {
+            None
+        };
+        let (method, version, stored): (u16, u16, &[u8]) = match &deflated {
+            Some(deflated) => (8, 20, deflated),
+            None => (0, 10, data),
+        };
+        let crc32 = git_features::hash::crc32(data);
+        let too_large = |_| Error::ZipTooLarge;
+        let (compressed_size, size) = (
+            u32::try_from(stored.len()).map_err(too_large)?,
+            u32::try_from(data.len()).map_err(too_large)?,
+        );
+        let offset = u32::try_from(self.offset).map_err(too_large)?;
+        let name_len = u16::try_from(path.len()).map_err(too_large)?;
+        let flags: u16 = if !path.is_ascii() && path.to_str().is_ok() {
+            0x0800
+        } else {
+            0
+        };
+        // The extended timestamp field with the modification time.
+        let mut extra = Vec::with_capacity(9);
+        extra.extend_from_slice(&0x5455u16.to_le_bytes());
+        extra.extend_from_slice(&5u16.to_le_bytes());
+        extra.push(1);
+        extra.extend_from_slice(&self.mtime.to_le_bytes());
+
+        let mut common = Vec::with_capacity(26);
+        common.extend_from_slice(&version.to_le_bytes());
+        common.extend_from_slice(&flags.to_le_bytes());
+        common.extend_from_slice(&method.to_le_bytes());
+        common.extend_from_slice(&self.dos_time.to_le_bytes());
+        common.extend_from_slice(&self.dos_date.to_le_bytes());
+        common.extend_from_slice(&crc32.to_le_bytes());
+        common.extend_from_slice(&compressed_size.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&name_len.to_le_bytes());
+        common.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+
+        let mut local = Vec::with_capacity(30 + path.len() + extra.len());
+        local.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        local.extend_from_slice(&common);
+        local.extend_from_slice(path);
+        local.extend_from_slice(&extra);
+        self.out.write_all(&local)?;
+        self.out.write_all(stored)?;
+        self.offset += (local.len() + stored.len()) as u64;
+
+        let central = &mut self.central_directory;
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        // Made by unix with version 2.0 of the specification.
+        central.extend_from_slice(&0x0314u16.to_le_bytes());
+        central.extend_from_slice(&common);
+        // No comment, the first disk, no internal attributes.
+        central.extend_from_slice(&[0; 6]);
+        central.extend_from_slice(&(unix_mode << 16 | dos_attributes).to_le_bytes());
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(path);
+        central.extend_from_slice(&extra);
+        self.num_entries += 1;
+        Ok(())
+    }
+}
+
+/// Convert seconds since the epoch into the MS-DOS date and time in UTC, as used by zip files.
+fn dos_date_time(time: u32) -> (u16, u16) {
+    let days = time / 86_400;
+    let seconds = time % 86_400;
+    // Convert days since the epoch into a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    let date = if year < 1980 {
+        (1 << 5) | 1
+    } else {
+        (((year - 1980).min(127) as u16) << 9) | ((month as u16) << 5) | day as u16
+    };
+    let time = (((seconds / 3600) << 11) | ((seconds % 3600 / 60) << 5) | ((seconds % 60) / 2)) as u16;
+    (date, time)
+}
@@ -0,0 +1,128 @@
+//! Find out what the filesystem of a repository supports, like git does when initializing a repository, and remember
+//! it in the configuration so checkouts don't need to probe again.
+use std::{fs, io, path::Path};
+
+/// What the filesystem holding a repository supports, as configured by the `core.*` variables of the same name.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Capabilities {
+    /// If true, the executable bit of files is kept, as set by `core.fileMode`.
+    pub file_mode: bool,
+    /// If true, symbolic links can be created, as set by `core.symlinks`. Otherwise links are checked out as files
+    /// containing their target.
+    pub symlinks: bool,
+    /// If true, file names which differ only in case refer to the same file, as set by `core.ignoreCase`.
+    pub ignore_case: bool,
+    /// If true, file names with precomposed unicode characters can also be accessed in decomposed form, so names
+    /// read from the filesystem should be precomposed, as set by `core.precomposeUnicode`.
+    pub precompose_unicode: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities {
+            file_mode: true,
+            symlinks: true,
+            ignore_case: false,
+            precompose_unicode: false,
+        }
+    }
+}
+
+const PRECOMPOSED: &str = "probe-\u{00C4}";
+const DECOMPOSED: &str = "probe-A\u{0308}";
+
+impl Capabilities {
+    /// Probe the filesystem by creating and removing files in `git_dir`, which must exist.
+    pub fn probe(git_dir: impl AsRef<Path>) -> io::Result<Self> {
+        let git_dir = git_dir.as_ref();
+        Ok(Capabilities {
+            file_mode: probe_file_mode(git_dir)?,
+            symlinks: probe_symlinks(git_dir),
+            ignore_case: {
+                let path = git_dir.join("probe-CaSe");
+                fs::write(&path, b"")?;
+                let res = git_dir.join("probe-case").exists();
+                fs::remove_file(&path)?;
+                res
+            },
+            precompose_unicode: {
+                let path = git_dir.join(PRECOMPOSED);
+                fs::write(&path, b"")?;
+                let res = git_dir.join(DECOMPOSED).exists();
+                fs::remove_file(&path)?;
+                res
+            },
+        })
+    }
+
+    /// Read the capabilities from the `core` section of `config`, using the defaults of git for missing or invalid
+    /// values.
+    pub fn from_config(config: &git_config::File) -> Self {
+        let default = Capabilities::default();
+        let boolean = |key, default| match config.boolean("core", None, key) {
+            Some(Ok(value)) => value,
+            _ => default,
+        };
+        Capabilities {
+            file_mode: boolean("fileMode", default.file_mode),
+            symlinks: boolean("symlinks", default.symlinks),
+            ignore_case: boolean("ignoreCase", default.ignore_case),
+            precompose_unicode: boolean("precomposeUnicode", default.precompose_unicode),
+        }
+    }
+
+    /// Read the capabilities from the configuration of the repository at `git_dir`, or use the defaults if there is
+    /// no readable configuration.
+    pub fn from_git_dir(git_dir: impl AsRef<Path>) -> Self {
+        git_config::File::at(git_dir.as_ref().join("config"))
+            .map(|config| Capabilities::from_config(&config))
+            .unwrap_or_default()
+    }
+
+    /// Return the `core` variables to add to the configuration. Like git, only values differing from the defaults are
+    /// written, except for `fileMode`.
+    pub fn to_config(&self) -> String {
+        let mut config = format!("\tfilemode = {}\n", self.file_mode);
+        if !self.symlinks {
+            config.push_str("\tsymlinks = false\n");
+        }
+        if self.ignore_case {
+            config.push_str("\tignorecase = true\n");
+        }
+        if self.precompose_unicode {
+            config.push_str("\tprecomposeunicode = true\n");
+        }
+        config
+    }
+}
+
+#[cfg(unix)]
+fn probe_file_mode(git_dir: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::PermissionsExt;
+    let path = git_dir.join("probe-filemode");
+    fs::write(&path, b"")?;
+    let mode = fs::metadata(&path)?.permissions().mode();
+    fs::set_permissions(&path, fs::Permissions::from_mode(mode ^ 0o100))?;
+    let res = fs::metadata(&path)?.permissions().mode() != mode;
+    fs::remove_file(&path)?;
+    Ok(res)
+}
+
+#[cfg(not(unix))]
+fn probe_file_mode(_git_dir: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+fn probe_symlinks(git_dir: &Path) -> bool {
+    let path = git_dir.join("probe-symlink");
+    #[cfg(unix)]
+    let res = std::os::unix::fs::symlink("target", &path);
+    #[cfg(windows)]
+    let res = std::os::windows::fs::symlink_file("target", &path);
+    #[cfg(not(any(unix, windows)))]
+    let res: io::Result<()> = Err(io::Error::other("symlinks are unsupported"));
+    let is_link = res.is_ok() && matches!(fs::symlink_metadata(&path), Ok(m) if m.file_type().is_symlink());
+    fs::remove_file(&path).ok();
+    is_link
+}
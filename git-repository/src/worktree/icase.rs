@@ -0,0 +1,71 @@
+//! Look up paths the way a case-insensitive or unicode normalizing filesystem would, to find paths of a tree which
+//! refer to the same file on disk.
+use crate::worktree::Capabilities;
+use git_object::bstr::{BStr, BString, ByteSlice};
+use std::{
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap},
+};
+use unicode_normalization::UnicodeNormalization;
+
+/// Paths keyed by their normalized form, which is the path itself unless [`Capabilities::ignore_case`] or
+/// [`Capabilities::precompose_unicode`] are set.
+pub struct Paths {
+    ignore_case: bool,
+    precompose_unicode: bool,
+    paths: HashMap<BString, BString>,
+}
+
+impl Paths {
+    /// Create an empty lookup normalizing paths as the filesystem with `capabilities` does.
+    pub fn new(capabilities: &Capabilities) -> Self {
+        Paths {
+            ignore_case: capabilities.ignore_case,
+            precompose_unicode: capabilities.precompose_unicode,
+            paths: HashMap::new(),
+        }
+    }
+
+    /// Return the form of `path` which is the same for all paths referring to the same file.
+    ///
+    /// Unicode is composed to NFC, and case is folded to lower case for all characters. Paths which aren't valid
+    /// UTF-8 are only folded for ASCII characters.
+    pub fn normalize<'a>(&self, path: &'a BStr) -> Cow<'a, BStr> {
+        if !self.ignore_case && (!self.precompose_unicode || path.is_ascii()) {
+            return Cow::Borrowed(path);
+        }
+        let path: BString = match path.to_str() {
+            Ok(path) => {
+                let composed: String = if self.precompose_unicode {
+                    path.nfc().collect()
+                } else {
+                    path.to_owned()
+                };
+                if self.ignore_case {
+                    composed.to_lowercase().into()
+                } else {
+                    composed.into()
+                }
+            }
+            Err(_) if self.ignore_case => path.to_ascii_lowercase().into(),
+            Err(_) => path.to_owned(),
+        };
+        Cow::Owned(path)
+    }
+
+    /// Add `path`, and return the path added earlier which refers to the same file, if there is one.
+    pub fn insert(&mut self, path: &BStr) -> Option<&BStr> {
+        match self.paths.entry(self.normalize(path).into_owned()) {
+            Entry::Occupied(earlier) => Some(earlier.into_mut().as_bstr()),
+            Entry::Vacant(entry) => {
+                entry.insert(path.to_owned());
+                None
+            }
+        }
+    }
+
+    /// Return the path which was added and refers to the same file as `path`.
+    pub fn get(&self, path: &BStr) -> Option<&BStr> {
+        self.paths.get(self.normalize(path).as_ref()).map(|path| path.as_bstr())
+    }
+}
@@ -0,0 +1,184 @@
+//! Linked worktrees as created by `git worktree add`, which share all objects and most refs with the main worktree.
+//!
+//! The git directory of a linked worktree is `$GIT_COMMON_DIR/worktrees/<name>`. It only contains the files belonging
+//! to the worktree, like `HEAD`, `refs/bisect/*` and the index, and its `commondir` file points to the main git
+//! directory containing everything else. The `.git` file in the worktree points to this git directory, while
+//! the `gitdir` file in the git directory points back to the `.git` file.
+use git_object::{
+    bstr::{BStr, BString, ByteSlice},
+    owned,
+};
+use quick_error::quick_error;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error, path: PathBuf) {
+            display("Could not access '{}'", path.display())
+            source(err)
+        }
+        InvalidDotGit(path: PathBuf) {
+            display("The file at '{}' does not point to a git directory with a 'gitdir: <path>' line", path.display())
+        }
+    }
+}
+
+/// The directory below the common git directory containing the git directories of all linked worktrees.
+pub const DIRECTORY_NAME: &str = "worktrees";
+
+/// What the `HEAD` of a worktree points to.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Head {
+    /// `HEAD` is a symbolic ref to a branch, like `refs/heads/main`, which may not exist yet.
+    Symbolic(BString),
+    /// `HEAD` points to a commit directly.
+    Detached(owned::Id),
+}
+
+/// A linked worktree as recorded in the common git directory.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Worktree {
+    /// The name of its git directory below [`DIRECTORY_NAME`], which is the name of the worktree directory unless
+    /// that was taken already.
+    pub name: BString,
+    /// The git directory of the worktree.
+    pub git_dir: PathBuf,
+    /// The directory the worktree is checked out in, or `None` if it isn't known.
+    pub path: Option<PathBuf>,
+    /// What `HEAD` points to, or `None` if it can't be read.
+    pub head: Option<Head>,
+    /// The reason the worktree is locked by `git worktree lock`, which is empty if none was given, or `None` if it isn't
+    /// locked.
+    pub locked: Option<BString>,
+}
+
+impl Worktree {
+    /// Return true if the worktree can be pruned as its directory is gone and it isn't locked.
+    pub fn is_prunable(&self) -> bool {
+        self.locked.is_none() && !matches!(&self.path, Some(path) if path.join(".git").is_file())
+    }
+}
+
+/// Return the git directory of the worktree whose `.git` is at `dot_git`, which is `dot_git` itself unless it's a
+/// file pointing to the git directory elsewhere, like the ones of linked worktrees and submodules.
+pub fn git_dir(dot_git: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    let dot_git = dot_git.as_ref();
+    if dot_git.is_dir() {
+        return Ok(dot_git.to_owned());
+    }
+    let content = fs::read(dot_git).map_err(|err| Error::Io(err, dot_git.to_owned()))?;
+    let path = content
+        .trim_end_with(|c| c == '\n' || c == '\r')
+        .strip_prefix(b"gitdir: ")
+        .and_then(|path| path.to_path().ok())
+        .ok_or_else(|| Error::InvalidDotGit(dot_git.to_owned()))?;
+    Ok(match dot_git.parent() {
+        Some(parent) if path.is_relative() => parent.join(path),
+        _ => path.to_owned(),
+    })
+}
+
+/// Return the git directory shared by all worktrees, as stored in the `commondir` file of `git_dir` by linked
+/// worktrees, or `git_dir` itself if there is no such file.
+pub fn common_dir(git_dir: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    let git_dir = git_dir.as_ref();
+    let path = git_dir.join("commondir");
+    let content = match fs::read(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(git_dir.to_owned()),
+        Err(err) => return Err(Error::Io(err, path)),
+    };
+    let common_dir = content
+        .trim_end_with(|c| c == '\n' || c == '\r')
+        .to_path()
+        .map_err(|_| Error::Io(io::ErrorKind::InvalidData.into(), path.clone()))?;
+    Ok(git_dir.join(common_dir))
+}
+
+/// Return true if the ref `name` belongs to a worktree instead of being shared by all of them, like `HEAD`, all
+/// other refs outside of `refs/`, and those in `refs/bisect/`, `refs/worktree/` and `refs/rewritten/`.
+pub fn is_per_worktree_ref(name: &BStr) -> bool {
+    !name.starts_with(b"refs/")
+        || name.starts_with(b"refs/bisect/")
+        || name.starts_with(b"refs/worktree/")
+        || name.starts_with(b"refs/rewritten/")
+}
+
+/// Return the directory the ref `name` of the worktree with `git_dir` is stored in, which is `git_dir` for
+/// [per-worktree refs][is_per_worktree_ref()] and the [common directory][common_dir()] for all others.
+///
+/// This applies to the reflog of `name` in the `logs` directory as well.
+pub fn ref_dir(git_dir: impl AsRef<Path>, name: &BStr) -> Result<PathBuf, Error> {
+    let git_dir = git_dir.as_ref();
+    if is_per_worktree_ref(name) {
+        Ok(git_dir.to_owned())
+    } else {
+        common_dir(git_dir)
+    }
+}
+
+/// Return all linked worktrees of the repository with the git directory `common_dir`, ordered by name.
+pub fn list(common_dir: impl AsRef<Path>) -> Result<Vec<Worktree>, Error> {
+    let directory = common_dir.as_ref().join(DIRECTORY_NAME);
+    let entries = match fs::read_dir(&directory) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(Error::Io(err, directory)),
+    };
+    let mut worktrees = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|err| Error::Io(err, directory.clone()))?;
+        let git_dir = entry.path();
+        if !git_dir.is_dir() {
+            continue;
+        }
+        worktrees.push(Worktree {
+            name: BString::from(entry.file_name().to_string_lossy().as_ref()),
+            path: read_trimmed(&git_dir.join("gitdir"))?
+                .and_then(|path| path.to_path().map(|path| git_dir.join(path)).ok())
+                .and_then(|dot_git| dot_git.parent().map(ToOwned::to_owned)),
+            head: read_trimmed(&git_dir.join("HEAD"))?.and_then(|head| match head.strip_prefix(b"ref: ") {
+                Some(target) => Some(Head::Symbolic(target.into())),
+                None => owned::Id::from_40_bytes_in_hex(&head).ok().map(Head::Detached),
+            }),
+            locked: read_trimmed(&git_dir.join("locked"))?,
+            git_dir,
+        });
+    }
+    worktrees.sort();
+    Ok(worktrees)
+}
+
+/// Remove the git directories of all [prunable][Worktree::is_prunable()] worktrees of the repository with the git
+/// directory `common_dir`, like `git worktree prune` does, and return them.
+///
+/// If `dry_run` is true, nothing is removed.
+pub fn prune(common_dir: impl AsRef<Path>, dry_run: bool) -> Result<Vec<Worktree>, Error> {
+    let common_dir = common_dir.as_ref();
+    let pruned: Vec<_> = list(common_dir)?.into_iter().filter(Worktree::is_prunable).collect();
+    if !dry_run {
+        for worktree in &pruned {
+            fs::remove_dir_all(&worktree.git_dir).map_err(|err| Error::Io(err, worktree.git_dir.clone()))?;
+        }
+        let directory = common_dir.join(DIRECTORY_NAME);
+        if let Ok(true) = fs::read_dir(&directory).map(|mut entries| entries.next().is_none()) {
+            fs::remove_dir(&directory).map_err(|err| Error::Io(err, directory))?;
+        }
+    }
+    Ok(pruned)
+}
+
+/// Read the file at `path` without its trailing newline, or `None` if it doesn't exist.
+fn read_trimmed(path: &Path) -> Result<Option<BString>, Error> {
+    match fs::read(path) {
+        Ok(content) => Ok(Some(content.trim_end_with(|c| c == '\n' || c == '\r').into())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(Error::Io(err, path.to_owned())),
+    }
+}
@@ -0,0 +1,11 @@
+//! Work with the files of a tree as they would appear in a work tree, and with the linked worktrees of a repository.
+pub mod archive;
+#[doc(inline)]
+pub use archive::archive;
+
+pub mod capabilities;
+pub use capabilities::Capabilities;
+
+pub mod icase;
+
+pub mod linked;
@@ -0,0 +1,361 @@
+use crate::{git, git_command, git_output, hex_to_id, run_with_input};
+use git_object::{borrowed, bstr::ByteSlice, owned};
+use git_odb::{compound, loose, pack};
+use git_repository::apply::{self, Line, Options};
+use std::{
+    fs,
+    os::unix::fs::{symlink, PermissionsExt},
+    path::Path,
+};
+
+fn rev(dir: &Path, rev: &str) -> owned::Id {
+    hex_to_id(&git(dir, &["rev-parse", rev]))
+}
+
+fn find(dir: &Path) -> impl for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>> {
+    let db = compound::Db::at(dir.join(".git/objects")).unwrap();
+    move |id, buf| {
+        let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+        borrowed::Object::from_bytes(object.kind, object.data).ok()
+    }
+}
+
+fn init() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "--quiet", "--initial-branch=main"]);
+    dir
+}
+
+fn numbered_lines(range: std::ops::Range<usize>) -> String {
+    range.map(|n| format!("line {}\n", n)).collect()
+}
+
+/// A repository with a `base` commit and a commit on top of it with all kinds of changes, which is checked out.
+fn repository() -> tempfile::TempDir {
+    let dir = init();
+    let path = dir.path();
+    fs::write(path.join("lines"), numbered_lines(1..30)).unwrap();
+    fs::write(path.join("with space"), "one\n").unwrap();
+    fs::write(path.join("script"), "#!/bin/sh\n").unwrap();
+    fs::write(path.join("deleted"), "gone\n").unwrap();
+    fs::write(path.join("renamed"), numbered_lines(1..10)).unwrap();
+    fs::write(path.join("no-newline"), "no newline").unwrap();
+    fs::create_dir(path.join("dir")).unwrap();
+    fs::write(path.join("dir/file"), "in a directory\n").unwrap();
+    symlink("lines", path.join("link")).unwrap();
+    git(path, &["add", "."]);
+    git(path, &["commit", "--quiet", "-m", "base"]);
+    git(path, &["tag", "base"]);
+
+    let mut lines = numbered_lines(1..30).replace("line 3\n", "line three\n");
+    lines = lines.replace("line 20\n", "");
+    lines.push_str("appended\n");
+    fs::write(path.join("lines"), lines).unwrap();
+    fs::write(path.join("with space"), "one\ntwo\n").unwrap();
+    fs::set_permissions(path.join("script"), fs::Permissions::from_mode(0o755)).unwrap();
+    fs::remove_file(path.join("deleted")).unwrap();
+    fs::remove_file(path.join("renamed")).unwrap();
+    fs::write(
+        path.join("new name"),
+        numbered_lines(1..10).replace("line 5\n", "five\n"),
+    )
+    .unwrap();
+    fs::write(path.join("no-newline"), "no newline\nbut now").unwrap();
+    fs::remove_file(path.join("dir/file")).unwrap();
+    fs::write(path.join("dir/new"), "").unwrap();
+    fs::remove_file(path.join("link")).unwrap();
+    symlink("with space", path.join("link")).unwrap();
+    git(path, &["add", "--all"]);
+    git(path, &["commit", "--quiet", "-m", "changes"]);
+    dir
+}
+
+#[test]
+fn patches_written_by_git_diff_apply_to_trees_and_worktrees() {
+    let dir = repository();
+    let dir = dir.path();
+    let diff = git(dir, &["diff", "-M", "base", "HEAD"]);
+    let patches = apply::parse(diff.as_bytes()).unwrap();
+    assert_eq!(patches.len(), 9);
+    let renamed = patches.iter().find(|patch| patch.path() == "new name").unwrap();
+    assert_eq!(renamed.old_path.as_ref().unwrap(), "renamed");
+    assert!(!renamed.copy);
+    assert_eq!(
+        patches.iter().find(|patch| patch.path() == "no-newline").unwrap().hunks[0].lines,
+        vec![
+            Line::Removed("no newline".into()),
+            Line::Added("no newline\n".into()),
+            Line::Added("but now".into())
+        ]
+    );
+
+    let db = loose::Db::at(dir.join(".git/objects"));
+    let outcome = apply::to_tree(rev(dir, "base^{tree}"), &patches, find(dir), &db, &Options::default()).unwrap();
+    assert!(outcome.rejected.is_empty());
+    assert_eq!(outcome.tree, rev(dir, "HEAD^{tree}"));
+
+    git(dir, &["checkout", "--quiet", "base"]);
+    let rejected = apply::to_worktree(dir, &patches, &Options::default()).unwrap();
+    assert!(rejected.is_empty());
+    git(dir, &["add", "--all"]);
+    assert_eq!(
+        rev(dir, git(dir, &["write-tree"]).trim_end()),
+        rev(dir, "HEAD@{1}^{tree}")
+    );
+    assert!(!dir.join("dir/file").exists() && dir.join("dir/new").exists());
+
+    assert!(matches!(
+        apply::to_tree(rev(dir, "base^{tree}"), &patches[..0], find(dir), &db, &Options::default()),
+        Ok(apply::Outcome { tree, .. }) if tree == rev(dir, "base^{tree}")
+    ));
+    assert!(matches!(
+        apply::to_tree(
+            rev(dir, "HEAD@{1}^{tree}"),
+            &patches,
+            find(dir),
+            &db,
+            &Options::default()
+        ),
+        Err(apply::Error::Missing(_))
+    ));
+}
+
+/// Apply `patch` to `content` in a file named `file` with the API and with `git apply` called with `args`, and return
+/// the content of both files along with the amount of rejected hunks and whether git succeeded.
+fn apply_both(content: &str, patch: &str, options: Options, args: &[&str]) -> ((String, usize), (String, bool)) {
+    let (ours, theirs) = (init(), init());
+    let (ours, theirs) = (ours.path(), theirs.path());
+    for dir in &[ours, theirs] {
+        fs::write(dir.join("file"), content).unwrap();
+    }
+    let patches = apply::parse(patch.as_bytes()).unwrap();
+    let rejected = apply::to_worktree(ours, &patches, &options).unwrap();
+    let mut git_args = vec!["apply"];
+    git_args.extend(args);
+    let output = run_with_input(git_command(theirs).args(&git_args), patch.as_bytes());
+    (
+        (
+            fs::read_to_string(ours.join("file")).unwrap(),
+            rejected.iter().map(|file| file.hunks.len()).sum(),
+        ),
+        (
+            fs::read_to_string(theirs.join("file")).unwrap(),
+            output.status.success(),
+        ),
+    )
+}
+
+fn diff(old: &str, new: &str) -> String {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("old"), old).unwrap();
+    fs::write(dir.path().join("new"), new).unwrap();
+    let output = git_output(dir.path(), &["diff", "--no-index", "old", "new"]);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .replace("a/old", "a/file")
+        .replace("b/new", "b/file")
+}
+
+#[test]
+fn hunks_move_with_their_lines_and_may_lose_context_like_with_git() {
+    let old = numbered_lines(1..40);
+    let new = old.replace("line 10\n", "ten\n").replace("line 30\n", "thirty\n");
+    let patch = diff(&old, &new);
+
+    let moved = format!("inserted\ninserted\n{}", old);
+    let ((ours, rejected), (theirs, success)) = apply_both(&moved, &patch, Options::default(), &[]);
+    assert_eq!((rejected, success), (0, true));
+    assert_eq!(ours, theirs);
+    assert!(ours.contains("ten\n"));
+
+    let changed_context = moved.replace("line 8\n", "changed\n").replace("line 32\n", "changed\n");
+    let ((ours, rejected), (theirs, success)) = apply_both(&changed_context, &patch, Options::default(), &[]);
+    assert_eq!((rejected, success), (2, false));
+    assert_eq!(ours, changed_context, "nothing is changed");
+    assert_eq!(ours, theirs);
+
+    let options = Options {
+        min_context: Some(1),
+        ..Default::default()
+    };
+    let ((ours, rejected), (theirs, success)) = apply_both(&changed_context, &patch, options, &["-C1"]);
+    assert_eq!((rejected, success), (0, true));
+    assert_eq!(ours, theirs);
+
+    let one_changed = moved.replace("line 12\n", "changed\n");
+    let options = Options {
+        reject: true,
+        ..Default::default()
+    };
+    let ((ours, rejected), (theirs, success)) = apply_both(&one_changed, &patch, options, &["--reject"]);
+    assert_eq!((rejected, success), (1, false));
+    assert_eq!(ours, theirs);
+    assert!(ours.contains("thirty\n") && !ours.contains("ten\n"));
+}
+
+#[test]
+fn hunks_at_the_start_and_end_stay_there_unless_context_may_be_lost() {
+    let old = numbered_lines(1..10);
+    let patch = diff(&old, &format!("first\n{}last\n", old));
+    for content in &[old.clone(), format!("{}{}", old, old), format!("x\n{}x\n", old)] {
+        for (options, args) in &[
+            (Options::default(), &[][..]),
+            (
+                Options {
+                    min_context: Some(0),
+                    ..Default::default()
+                },
+                &["-C0"][..],
+            ),
+        ] {
+            let ((ours, rejected), (theirs, success)) = apply_both(content, &patch, *options, args);
+            assert_eq!(ours, theirs, "{:?} {:?}", content, args);
+            assert_eq!(rejected == 0, success, "{:?} {:?}", content, args);
+        }
+    }
+}
+
+#[test]
+fn whitespace_changes_in_context_lines_can_be_ignored_like_with_git() {
+    let old = "fn main() {\n    let a = 1;\n    let b = 2;\n    let c = 3;\n}\n";
+    let patch = diff(old, &old.replace("let b = 2;", "let b = 20;"));
+    let reindented = old.replace("    ", "\t").replace("a = 1", "a  =  1");
+    let ((ours, rejected), (theirs, success)) = apply_both(&reindented, &patch, Options::default(), &[]);
+    assert_eq!((rejected, success, ours.as_str()), (1, false, theirs.as_str()));
+
+    let options = Options {
+        ignore_whitespace: true,
+        ..Default::default()
+    };
+    let ((ours, rejected), (theirs, success)) = apply_both(&reindented, &patch, options, &["--ignore-whitespace"]);
+    assert_eq!((rejected, success), (0, true));
+    assert_eq!(ours, theirs);
+    assert!(ours.contains("\tlet a  =  1;"), "context lines are kept as they are");
+
+    let unindented = old.replace("    let a", "let a");
+    let ((_, rejected), (_, success)) = apply_both(&unindented, &patch, options, &["--ignore-whitespace"]);
+    assert_eq!(
+        (rejected, success),
+        (1, false),
+        "whitespace must not disappear entirely"
+    );
+    let trailing_whitespace = old.replace("{\n", "{ \n");
+    let ((_, rejected), (_, success)) = apply_both(&trailing_whitespace, &patch, options, &["--ignore-whitespace"]);
+    assert_eq!((rejected, success), (1, false), "whitespace must not appear at the end");
+}
+
+#[test]
+fn emails_written_by_git_format_patch_are_commits_to_apply_on_top_of_each_other() {
+    let dir = repository();
+    let dir = dir.path();
+    fs::write(dir.join("with space"), "one\ntwo\nthree\n").unwrap();
+    git(dir, &["add", "--all"]);
+    git(
+        dir,
+        &[
+            "-c",
+            "user.name=Jörg Müller",
+            "commit",
+            "--quiet",
+            "-m",
+            "A rather long subject with ümlauts, which needs to be folded because it goes on and on and on",
+            "-m",
+            "The body\n\nwith two paragraphs.",
+        ],
+    );
+    let mbox = git(dir, &["format-patch", "--stdout", "base"]);
+    let mails = apply::mails(mbox.as_bytes()).unwrap();
+    assert_eq!(mails.len(), 2);
+
+    let db = loose::Db::at(dir.join(".git/objects"));
+    let mut tree = rev(dir, "base^{tree}");
+    for (mail, commit) in mails.iter().zip(&["HEAD~1", "HEAD"]) {
+        let raw = git(dir, &["cat-file", "commit", commit]);
+        let (headers, message) = raw.split_at(raw.find("\n\n").unwrap() + 2);
+        assert_eq!(mail.message, message);
+        assert_eq!(mail.subject, message.lines().next().unwrap());
+        let author = headers.lines().find_map(|line| line.strip_prefix("author ")).unwrap();
+        let mut signature = Vec::new();
+        mail.author.write_to(&mut signature).unwrap();
+        assert_eq!(signature.as_bstr(), author);
+
+        let outcome = apply::to_tree(tree, &mail.patches, find(dir), &db, &Options::default()).unwrap();
+        assert!(outcome.rejected.is_empty());
+        assert_eq!(outcome.tree, rev(dir, &format!("{}^{{tree}}", commit)));
+        tree = outcome.tree;
+    }
+}
+
+#[test]
+fn links_and_executable_bits_are_only_written_if_the_filesystem_supports_them() {
+    let dir = repository();
+    let dir = dir.path();
+    let diff = git(dir, &["diff", "base", "HEAD"]);
+    let patches = apply::parse(diff.as_bytes()).unwrap();
+    git(dir, &["checkout", "--quiet", "base"]);
+    git(dir, &["config", "core.symlinks", "false"]);
+    git(dir, &["config", "core.fileMode", "false"]);
+
+    let rejected = apply::to_worktree(dir, &patches, &Options::default()).unwrap();
+    assert!(rejected.is_empty());
+    let link = fs::symlink_metadata(dir.join("link")).unwrap();
+    assert!(!link.file_type().is_symlink());
+    assert_eq!(fs::read_to_string(dir.join("link")).unwrap(), "with space");
+    assert_eq!(
+        fs::metadata(dir.join("script")).unwrap().permissions().mode() & 0o111,
+        0
+    );
+}
+
+#[test]
+fn patches_without_paths_are_rejected() {
+    assert!(matches!(
+        apply::parse(b"--- /dev/null\n+++ /dev/null\n@@ -0,0 +1 @@\n+x\n"),
+        Err(apply::Error::Parse(3, _))
+    ));
+
+    let dir = init();
+    let patch = apply::FilePatch {
+        hunks: apply::parse(b"--- /dev/null\n+++ b/x\n@@ -0,0 +1 @@\n+x\n").unwrap()[0]
+            .hunks
+            .clone(),
+        ..Default::default()
+    };
+    assert!(matches!(
+        apply::to_worktree(dir.path(), &[patch], &Options::default()),
+        Err(apply::Error::UnknownPaths)
+    ));
+}
+
+#[test]
+fn files_beyond_symbolic_links_are_neither_read_nor_written() {
+    let dir = init();
+    let outside = tempfile::tempdir().unwrap();
+    fs::write(outside.path().join("victim"), "x\n").unwrap();
+    symlink(outside.path(), dir.path().join("link")).unwrap();
+
+    for patch in &[
+        "diff --git a/link/evil b/link/evil\nnew file mode 100644\n--- /dev/null\n+++ b/link/evil\n@@ -0,0 +1 @@\n+evil\n",
+        "diff --git a/link/victim b/link/victim\ndeleted file mode 100644\n--- a/link/victim\n+++ /dev/null\n@@ -1 +0,0 @@\n-x\n",
+    ] {
+        let patches = apply::parse(patch.as_bytes()).unwrap();
+        assert!(matches!(
+            apply::to_worktree(dir.path(), &patches, &Options::default()),
+            Err(apply::Error::UnsafePath(path)) if path.starts_with(b"link/")
+        ));
+    }
+    assert!(!outside.path().join("evil").exists());
+    assert_eq!(fs::read_to_string(outside.path().join("victim")).unwrap(), "x\n");
+
+    let through_link_created_by_patch = "diff --git a/link2 b/link2\nnew file mode 120000\n--- /dev/null\n+++ b/link2\n@@ -0,0 +1 @@\n+..\n\\ No newline at end of file\ndiff --git a/link2/evil b/link2/evil\nnew file mode 100644\n--- /dev/null\n+++ b/link2/evil\n@@ -0,0 +1 @@\n+evil\n";
+    let patches = apply::parse(through_link_created_by_patch.as_bytes()).unwrap();
+    assert!(matches!(
+        apply::to_worktree(dir.path(), &patches, &Options::default()),
+        Err(apply::Error::UnsafePath(path)) if path == "link2/evil"
+    ));
+    assert!(fs::symlink_metadata(dir.path().join("link2"))
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert!(!dir.path().parent().unwrap().join("evil").exists());
+}
@@ -0,0 +1,78 @@
+use crate::git;
+use git_object::bstr::ByteSlice;
+use git_repository::attributes::{self, Assignment, Stack, State};
+use std::fs;
+
+const ROOT: &str = "# comment
+*.txt text -diff eol=lf
+/anchored set
+docs/**/*.md doc
+[attr]macro -text
+!negated set
+";
+const NESTED: &str = "*.txt !text diff
+deep/* set=nested
+";
+
+#[test]
+fn parse_reads_patterns_and_assignments() {
+    let lines: Vec<_> = attributes::parse(ROOT.as_bytes()).collect();
+    assert_eq!(lines.len(), 3, "comments, macros and negative patterns are skipped");
+    assert_eq!(lines[0].pattern, "*.txt");
+    assert_eq!(
+        lines[0].assignments,
+        vec![
+            Assignment {
+                name: "text".into(),
+                state: State::Set
+            },
+            Assignment {
+                name: "diff".into(),
+                state: State::Unset
+            },
+            Assignment {
+                name: "eol".into(),
+                state: State::Value("lf".into())
+            },
+        ]
+    );
+}
+
+#[test]
+fn states_are_looked_up_like_git_does() {
+    let mut stack = Stack::default();
+    stack.add(b"".as_bstr(), ROOT.as_bytes());
+    stack.add(b"sub".as_bstr(), NESTED.as_bytes());
+
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "--quiet"]);
+    fs::write(dir.path().join(".gitattributes"), ROOT).unwrap();
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    fs::write(dir.path().join("sub/.gitattributes"), NESTED).unwrap();
+
+    for path in &[
+        "a.txt",
+        "dir/b.txt",
+        "sub/c.txt",
+        "anchored",
+        "dir/anchored",
+        "docs/a.md",
+        "docs/x/y/b.md",
+        "sub/deep/file",
+        "deep/file",
+        "negated",
+    ] {
+        for name in &["text", "diff", "eol", "set", "doc"] {
+            let expected = git(dir.path(), &["check-attr", name, "--", path]);
+            let expected = expected.trim_end().rsplit(": ").next().unwrap().to_owned();
+            let actual = match stack.state(path.as_bytes().as_bstr(), name) {
+                State::Set => "set".to_owned(),
+                State::Unset => "unset".to_owned(),
+                State::Value(value) => value.to_string(),
+                State::Unspecified => "unspecified".to_owned(),
+            };
+            assert_eq!(actual, expected, "{} {}", path, name);
+        }
+    }
+    assert!(stack.is_set(b"anchored".as_bstr(), "set"));
+}
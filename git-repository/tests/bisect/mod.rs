@@ -0,0 +1,176 @@
+use crate::{git, git_output, hex_to_id};
+use git_object::{borrowed, owned};
+use git_odb::{compound, pack};
+use git_repository::bisect::{self, Error, Mark, Next};
+use std::{fs, path::Path};
+
+fn rev(dir: &Path, rev: &str) -> owned::Id {
+    hex_to_id(&git(dir, &["rev-parse", rev]))
+}
+
+fn find(dir: &Path) -> impl for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>> {
+    let db = compound::Db::at(dir.join(".git/objects")).unwrap();
+    move |id, buf| {
+        let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+        borrowed::Object::from_bytes(object.kind, object.data).ok()
+    }
+}
+
+/// Commit a change to the file named like the branch in `message`.
+fn commit(dir: &Path, message: &str) {
+    let file = format!("{}.txt", message.split(' ').next().unwrap());
+    fs::write(dir.join(&file), message).unwrap();
+    git(dir, &["add", &file]);
+    git(dir, &["commit", "--quiet", "-m", message]);
+}
+
+/// Two identical repositories with a side branch merged into `main`.
+fn repositories() -> (tempfile::TempDir, tempfile::TempDir) {
+    let dirs = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    for dir in &[dirs.0.path(), dirs.1.path()] {
+        git(dir, &["init", "--quiet", "--initial-branch=main"]);
+        for n in 1..=3 {
+            commit(dir, &format!("main {}", n));
+        }
+        git(dir, &["checkout", "--quiet", "-b", "side"]);
+        for n in 1..=3 {
+            commit(dir, &format!("side {}", n));
+        }
+        git(dir, &["checkout", "--quiet", "main"]);
+        for n in 4..=5 {
+            commit(dir, &format!("main {}", n));
+        }
+        git(dir, &["merge", "--quiet", "--no-ff", "-m", "merge", "side"]);
+        for n in 6..=9 {
+            commit(dir, &format!("main {}", n));
+        }
+    }
+    dirs
+}
+
+fn mark(ours: &Path, theirs: &Path, mark: Mark, commit: owned::Id) -> (Next, String) {
+    let next = bisect::mark(ours.join(".git"), mark, commit, find(ours)).unwrap();
+    let output = git_output(theirs, &["bisect", mark.as_str(), &commit.to_string()]);
+    (next, String::from_utf8(output.stdout).unwrap())
+}
+
+fn state_file(dir: &Path, name: &str) -> String {
+    fs::read_to_string(dir.join(".git").join(name)).unwrap()
+}
+
+#[test]
+fn the_same_commits_as_git_are_tested_to_find_the_first_bad_one() {
+    let (ours, theirs) = repositories();
+    let (ours, theirs) = (ours.path(), theirs.path());
+    let (head, root) = (rev(ours, "main"), rev(ours, "main~9"));
+    let culprits = git(ours, &["rev-list", "main", "^main~9"]);
+    for culprit in culprits.lines().map(|hex| rev(ours, hex)) {
+        bisect::start(ours.join(".git")).unwrap();
+        git(theirs, &["bisect", "start"]);
+        assert_eq!(mark(ours, theirs, Mark::Bad, head).0, Next::Waiting);
+        let mut next = mark(ours, theirs, Mark::Good, root);
+        loop {
+            match next {
+                (
+                    Next::Test {
+                        commit,
+                        remaining,
+                        steps,
+                    },
+                    output,
+                ) => {
+                    assert_eq!(commit, rev(theirs, "HEAD"), "same commit to test");
+                    assert!(
+                        output.contains(&format!("Bisecting: {} revision", remaining)),
+                        "{}",
+                        output
+                    );
+                    assert!(output.contains(&format!("(roughly {} step", steps)), "{}", output);
+                    let is_bad = git_output(
+                        ours,
+                        &["merge-base", "--is-ancestor", &culprit.to_string(), &commit.to_string()],
+                    )
+                    .status
+                    .success();
+                    next = mark(ours, theirs, if is_bad { Mark::Bad } else { Mark::Good }, commit);
+                }
+                (Next::FirstBad(commit), output) => {
+                    assert_eq!(commit, culprit);
+                    assert!(output.starts_with(&format!("{} is the first bad commit", commit)));
+                    break;
+                }
+                (next, _) => panic!("unexpected {:?}", next),
+            }
+        }
+        for name in &[
+            "BISECT_LOG",
+            "BISECT_START",
+            "BISECT_TERMS",
+            "BISECT_NAMES",
+            "refs/bisect/bad",
+        ] {
+            assert_eq!(state_file(ours, name), state_file(theirs, name), "{}", name);
+        }
+        assert_eq!(bisect::state(ours.join(".git")).unwrap().good.len(), {
+            git(theirs, &["for-each-ref", "refs/bisect/good-*"]).lines().count()
+        });
+
+        assert_eq!(bisect::reset(ours.join(".git")).unwrap(), "main");
+        git(theirs, &["bisect", "reset"]);
+        assert!(!ours.join(".git/refs/bisect").exists());
+        assert!(!ours.join(".git/BISECT_LOG").exists());
+    }
+}
+
+#[test]
+fn skipped_commits_are_avoided_until_only_they_are_left() {
+    let (ours, theirs) = repositories();
+    let (ours, theirs) = (ours.path(), theirs.path());
+    let id = |rev_spec: &str| rev(ours, rev_spec);
+    bisect::start(ours.join(".git")).unwrap();
+    git(theirs, &["bisect", "start"]);
+    assert!(matches!(bisect::start(ours.join(".git")), Err(Error::InProgress(_))));
+    assert_eq!(mark(ours, theirs, Mark::Good, id("main~4")).0, Next::Waiting);
+    assert_eq!(bisect::next(ours.join(".git"), find(ours)).unwrap(), Next::Waiting);
+
+    let test = |commit: owned::Id, remaining: usize| Next::Test {
+        commit,
+        remaining,
+        steps: 1,
+    };
+    assert_eq!(mark(ours, theirs, Mark::Bad, id("main")).0, test(id("main~2"), 1));
+    assert_eq!(mark(ours, theirs, Mark::Skip, id("main~2")).0, test(id("main~3"), 2));
+    assert_eq!(mark(ours, theirs, Mark::Skip, id("main~1")).0, test(id("main~3"), 2));
+    assert_eq!(
+        mark(ours, theirs, Mark::Good, id("main~3")).0,
+        Next::OnlySkipped(vec![id("main"), id("main~1"), id("main~2")])
+    );
+    assert_eq!(state_file(ours, "BISECT_LOG"), state_file(theirs, "BISECT_LOG"));
+    assert_eq!(
+        bisect::state(ours.join(".git")).unwrap(),
+        bisect::State {
+            bad: Some(id("main")),
+            good: {
+                let mut good = vec![id("main~3"), id("main~4")];
+                good.sort();
+                good
+            },
+            skipped: {
+                let mut skipped = vec![id("main~1"), id("main~2")];
+                skipped.sort();
+                skipped
+            },
+        }
+    );
+
+    assert!(matches!(
+        bisect::mark(ours.join(".git"), Mark::Good, id("main"), find(ours)),
+        Err(Error::BothGoodAndBad(_))
+    ));
+    bisect::reset(ours.join(".git")).unwrap();
+    assert!(matches!(bisect::reset(ours.join(".git")), Err(Error::NotInProgress(_))));
+    assert!(matches!(
+        bisect::mark(ours.join(".git"), Mark::Bad, id("main"), find(ours)),
+        Err(Error::NotInProgress(_))
+    ));
+}
@@ -0,0 +1,149 @@
+use crate::{git, git_command, run};
+use git_object::{borrowed, bstr::ByteSlice, owned};
+use git_odb::{compound, pack};
+use git_repository::blame::{self, Error, Hunk, Options};
+use std::{fs, path::Path};
+
+/// Run git with commits dated at `time`.
+fn git_at(dir: &Path, time: u32, args: &[&str]) -> String {
+    let date = format!("{} +0000", time);
+    let output = run(git_command(dir)
+        .args(args)
+        .env("GIT_AUTHOR_DATE", &date)
+        .env("GIT_COMMITTER_DATE", &date));
+    String::from_utf8(output).unwrap()
+}
+
+/// Commit `content` as `dir/file` at `time`, returning the new commit.
+fn commit(dir: &Path, time: u32, content: &str) -> String {
+    fs::create_dir_all(dir.join("dir")).unwrap();
+    fs::write(dir.join("dir/file"), content).unwrap();
+    git(dir, &["add", "dir/file"]);
+    git_at(dir, time, &["commit", "--quiet", "-m", &time.to_string()]);
+    git(dir, &["rev-parse", "HEAD"]).trim_end().to_owned()
+}
+
+/// A history with a merge of two branches, both changing the file.
+fn repository(dir: &Path) -> String {
+    git(dir, &["init", "--quiet"]);
+    commit(dir, 1_000_000_000, "a\nb\nc\nd\n");
+    commit(dir, 1_000_000_100, "a\nB\nc\nd\ne\n");
+    git(dir, &["branch", "side"]);
+    commit(dir, 1_000_000_200, "a\nB\nc\nD\ne\n");
+    git(dir, &["checkout", "--quiet", "side"]);
+    commit(dir, 1_000_000_300, "A\nB\nc\nd\ne\nf\n");
+    git(dir, &["checkout", "--quiet", "-"]);
+    git_at(dir, 1_000_000_400, &["merge", "--quiet", "--no-edit", "side"]);
+    commit(dir, 1_000_000_500, "A\nB\nc\nD\ninserted\ne\nf\n")
+}
+
+/// The commit and zero-based source line of each blamed line, as reported by `git blame`.
+fn git_blame(dir: &Path, args: &[&str]) -> Vec<(String, u32)> {
+    let mut all_args = vec!["blame", "--porcelain"];
+    all_args.extend_from_slice(args);
+    all_args.extend_from_slice(&["HEAD", "--", "dir/file"]);
+    git(dir, &all_args)
+        .lines()
+        .filter(|line| !line.starts_with('\t'))
+        .filter_map(|line| {
+            let mut tokens = line.split(' ');
+            let id = tokens.next()?;
+            if id.len() != 40 || !id.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return None;
+            }
+            Some((id.to_owned(), tokens.next()?.parse::<u32>().ok()? - 1))
+        })
+        .collect()
+}
+
+fn lines_of(hunks: &[Hunk]) -> Vec<(String, u32)> {
+    hunks
+        .iter()
+        .flat_map(|hunk| {
+            hunk.source_lines
+                .clone()
+                .map(move |line| (hunk.commit.to_string(), line))
+        })
+        .collect()
+}
+
+fn blame(dir: &Path, head: &str, options: Options) -> Result<blame::Outcome, Error> {
+    let db = compound::Db::at(dir.join(".git/objects")).unwrap();
+    let head = owned::Id::from_40_bytes_in_hex(head.as_bytes()).unwrap();
+    blame::file(
+        "dir/file".into(),
+        head.to_borrowed(),
+        |id, buf| {
+            let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+            borrowed::Object::from_bytes(object.kind, object.data).ok()
+        },
+        options,
+    )
+}
+
+#[test]
+fn lines_are_attributed_like_git_does() {
+    let dir = tempfile::tempdir().unwrap();
+    let head = repository(dir.path());
+    let outcome = blame(dir.path(), &head, Options::default()).unwrap();
+    assert_eq!(lines_of(&outcome.hunks), git_blame(dir.path(), &[]));
+    assert_eq!(
+        outcome.hunks.iter().map(|h| h.lines.end - h.lines.start).sum::<u32>(),
+        7
+    );
+    assert!(outcome.hunks.windows(2).all(|w| w[0].lines.end == w[1].lines.start));
+    assert_eq!(outcome.commits_seen, 6, "all commits were visited");
+}
+
+#[test]
+fn first_parent_attributes_merged_lines_to_the_merge() {
+    let dir = tempfile::tempdir().unwrap();
+    let head = repository(dir.path());
+    let outcome = blame(
+        dir.path(),
+        &head,
+        Options {
+            first_parent: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(lines_of(&outcome.hunks), git_blame(dir.path(), &["--first-parent"]));
+}
+
+#[test]
+fn ranges_limit_the_blamed_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let head = repository(dir.path());
+    let outcome = blame(
+        dir.path(),
+        &head,
+        Options {
+            range: Some(1..4),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(lines_of(&outcome.hunks), git_blame(dir.path(), &["-L", "2,4"]));
+    assert_eq!(outcome.hunks.first().unwrap().lines.start, 1);
+    assert_eq!(outcome.hunks.last().unwrap().lines.end, 4);
+
+    assert!(matches!(
+        blame(dir.path(), &head, Options { range: Some(5..8), ..Default::default() }),
+        Err(Error::InvalidRange(range, 7)) if range == (5..8)
+    ));
+    let db_head = owned::Id::from_40_bytes_in_hex(head.as_bytes()).unwrap();
+    let db = compound::Db::at(dir.path().join(".git/objects")).unwrap();
+    assert!(matches!(
+        blame::file(
+            b"dir/missing".as_bstr(),
+            db_head.to_borrowed(),
+            |id, buf| {
+                let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+                borrowed::Object::from_bytes(object.kind, object.data).ok()
+            },
+            Options::default()
+        ),
+        Err(Error::NotFound(path, _)) if path == "dir/missing"
+    ));
+}
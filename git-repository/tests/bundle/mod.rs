@@ -0,0 +1,205 @@
+use crate::{git_command, hex_to_id, run};
+use git_features::progress;
+use git_odb::pack;
+use git_repository::bundle::{self, Header, Ref, Version};
+use std::{fs, path::Path};
+
+const MAIN: &str = "877c3ad4e85fa4408811d3fcc38aef2500f04ade";
+const OTHER: &str = "cecbafcde689e1bc457bf13334c3d934200802a1";
+const TAG: &str = "17934c4602e90c793a9660b02d49b6a678772104";
+
+fn fixture_git_dir() -> std::path::PathBuf {
+    std::env::current_dir()
+        .unwrap()
+        .join("tests")
+        .join("fixtures")
+        .join("repos")
+        .join("files.git")
+}
+
+fn source_bundle() -> pack::Bundle {
+    let pack_dir = fixture_git_dir().join("objects").join("pack");
+    let index = fs::read_dir(pack_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension() == Some("idx".as_ref()))
+        .expect("an index file");
+    pack::Bundle::at(index).unwrap()
+}
+
+fn git(args: &[&str]) -> String {
+    let git_dir = fixture_git_dir();
+    String::from_utf8(run(git_command(&git_dir).arg("--git-dir").arg(&git_dir).args(args))).unwrap()
+}
+
+fn write_bundle(path: &Path, version: Version, refs: &[(&str, &str)], prerequisites: &[&str]) -> bundle::Outcome {
+    let source = source_bundle();
+    bundle::write(
+        version,
+        refs.iter()
+            .map(|(name, hex)| Ref {
+                id: hex_to_id(hex),
+                name: (*name).into(),
+            })
+            .collect(),
+        &prerequisites.iter().map(|hex| hex_to_id(hex)).collect::<Vec<_>>(),
+        |id, buf| source.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok(),
+        progress::Discard,
+        fs::File::create(path).unwrap(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn bundles_created_by_git_can_be_read_and_indexed() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    for (version, format) in &[(Version::V2, "2"), (Version::V3, "3")] {
+        let path = dir.path().join(format!("v{}.bundle", format));
+        git(&[
+            "bundle",
+            "create",
+            "--quiet",
+            &format!("--version={}", format),
+            &path.display().to_string(),
+            &format!("{}..main", OTHER),
+        ]);
+
+        let pack_dir = dir.path().join(format!("v{}", format));
+        fs::create_dir(&pack_dir)?;
+        let (header, outcome) = bundle::index_pack(
+            &path,
+            Some(&pack_dir),
+            progress::Discard,
+            pack::bundle::write::Options {
+                thread_limit: None,
+                iteration_mode: pack::data::iter::Mode::Verify,
+                index_kind: pack::index::Kind::default(),
+            },
+        )?;
+        assert_eq!(header.version, *version);
+        assert_eq!(
+            header.refs,
+            vec![Ref {
+                id: hex_to_id(MAIN),
+                name: "refs/heads/main".into()
+            }]
+        );
+        assert_eq!(
+            header.prerequisites.iter().map(|p| p.id).collect::<Vec<_>>(),
+            vec![hex_to_id(OTHER)]
+        );
+        if *version == Version::V3 {
+            assert_eq!(header.capabilities, vec!["object-format=sha1"]);
+        }
+        assert!(outcome.index.num_objects > 0);
+        assert!(outcome
+            .to_bundle()
+            .expect("written to disk")?
+            .index
+            .lookup(hex_to_id(MAIN).to_borrowed())
+            .is_some());
+    }
+    Ok(())
+}
+
+#[test]
+fn headers_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let header = Header {
+        version: Version::V3,
+        capabilities: vec!["object-format=sha1".into(), "filter=blob:none".into()],
+        prerequisites: vec![bundle::Prerequisite {
+            id: hex_to_id(OTHER),
+            comment: "the first commit".into(),
+        }],
+        refs: vec![
+            Ref {
+                id: hex_to_id(MAIN),
+                name: "refs/heads/main".into(),
+            },
+            Ref {
+                id: hex_to_id(TAG),
+                name: "refs/tags/v1.0".into(),
+            },
+        ],
+    };
+    let mut buf = Vec::new();
+    header.write_to(&mut buf)?;
+    buf.extend_from_slice(b"PACK");
+    let mut input = buf.as_slice();
+    assert_eq!(Header::from_read(&mut input)?, header);
+    assert_eq!(input, b"PACK", "the input is positioned at the pack");
+    Ok(())
+}
+
+#[test]
+fn invalid_headers_are_rejected() {
+    let parse = |input: &[u8]| Header::from_read(input);
+    assert!(matches!(
+        parse(b"# v4 git bundle\n\n"),
+        Err(bundle::Error::InvalidSignature(_))
+    ));
+    assert!(matches!(
+        parse(b"# v2 git bundle\n@object-format=sha1\n\n"),
+        Err(bundle::Error::InvalidLine(_))
+    ));
+    assert!(matches!(
+        parse(b"# v3 git bundle\n@object-format=sha256\n\n"),
+        Err(bundle::Error::UnsupportedObjectFormat(_))
+    ));
+    assert!(matches!(
+        parse(format!("# v2 git bundle\n{}\n\n", MAIN).as_bytes()),
+        Err(bundle::Error::InvalidLine(_))
+    ));
+    assert!(matches!(
+        parse(format!("# v2 git bundle\n{} refs/heads/main\n", MAIN).as_bytes()),
+        Err(bundle::Error::UnexpectedEndOfHeader)
+    ));
+}
+
+#[test]
+fn written_bundles_are_accepted_by_git() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    for (version, name) in &[(Version::V2, "v2"), (Version::V3, "v3")] {
+        let path = dir.path().join(format!("{}.bundle", name));
+        let outcome = write_bundle(
+            &path,
+            *version,
+            &[("refs/heads/main", MAIN), ("refs/tags/v1.0", TAG)],
+            &[],
+        );
+        assert_eq!(
+            outcome.pack.num_objects, 12,
+            "everything reachable from main and the tag"
+        );
+        git(&["bundle", "verify", "--quiet", &path.display().to_string()]);
+        assert_eq!(
+            git(&["bundle", "list-heads", &path.display().to_string()]),
+            format!("{} refs/heads/main\n{} refs/tags/v1.0\n", MAIN, TAG)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn incremental_bundles_contain_only_objects_missing_from_the_prerequisites() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("incremental.bundle");
+    let outcome = write_bundle(&path, Version::V2, &[("refs/heads/main", MAIN)], &[OTHER]);
+    assert_eq!(outcome.header.prerequisites.len(), 1);
+    assert!(
+        !outcome.header.prerequisites[0].comment.is_empty(),
+        "the commit subject"
+    );
+    let (header, _pack) = bundle::open(&path)?;
+    assert_eq!(header, outcome.header);
+
+    let full = write_bundle(
+        &dir.path().join("full.bundle"),
+        Version::V2,
+        &[("refs/heads/main", MAIN)],
+        &[],
+    );
+    assert!(outcome.pack.num_objects < full.pack.num_objects);
+    git(&["bundle", "verify", "--quiet", &path.display().to_string()]);
+    Ok(())
+}
@@ -0,0 +1,364 @@
+use crate::git;
+use git_features::progress;
+use git_protocol::fetch;
+use git_repository::clone;
+use git_transport::Protocol;
+use std::{fs, path::Path};
+
+fn fixture_url() -> String {
+    format!(
+        "file://{}",
+        std::env::current_dir()
+            .unwrap()
+            .join("tests")
+            .join("fixtures")
+            .join("repos")
+            .join("files.git")
+            .display()
+    )
+}
+
+fn read(path: impl AsRef<Path>) -> String {
+    fs::read_to_string(path).unwrap()
+}
+
+const MAIN: &str = "877c3ad4e85fa4408811d3fcc38aef2500f04ade\n";
+
+fn assert_cloned(worktree: &Path, outcome: &clone::Outcome) {
+    let git_dir = worktree.join(".git");
+    assert_eq!(outcome.git_dir, git_dir);
+    assert_eq!(
+        outcome.head.as_ref().map(|h| h.to_string()),
+        Some("refs/heads/main".into())
+    );
+    assert_eq!(outcome.pack.as_ref().map(|p| p.index.num_objects), Some(12));
+
+    assert_eq!(read(git_dir.join("HEAD")), "ref: refs/heads/main\n");
+    assert_eq!(read(git_dir.join("refs/heads/main")), MAIN);
+    assert_eq!(read(git_dir.join("refs/remotes/origin/main")), MAIN);
+    assert_eq!(
+        read(git_dir.join("refs/remotes/origin/other")),
+        "cecbafcde689e1bc457bf13334c3d934200802a1\n"
+    );
+    assert_eq!(
+        read(git_dir.join("refs/remotes/origin/HEAD")),
+        "ref: refs/remotes/origin/main\n"
+    );
+    assert_eq!(
+        read(git_dir.join("refs/tags/v1.0")),
+        "17934c4602e90c793a9660b02d49b6a678772104\n",
+        "tags point to the tag object"
+    );
+    let config = read(git_dir.join("config"));
+    assert!(config.contains("[remote \"origin\"]\n\turl = file://"));
+    assert!(config.contains("\tfetch = +refs/heads/*:refs/remotes/origin/*\n"));
+    assert!(config.contains("[branch \"main\"]\n\tremote = origin\n\tmerge = refs/heads/main\n"));
+
+    assert_eq!(outcome.checked_out_files, 4);
+    assert!(outcome.collided_paths.is_empty());
+    assert_eq!(read(worktree.join("a")), "hello\nmore\n");
+    assert_eq!(read(worktree.join("dir/sub/b")), "nested\n");
+    assert_eq!(read(worktree.join("run.sh")), "#!/bin/sh\necho hi\n");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(worktree.join("run.sh")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o111, 0o111, "the executable bit is set");
+        assert_eq!(fs::read_link(worktree.join("link")).unwrap(), Path::new("a"));
+    }
+    for args in &[&["diff-files", "--name-only"][..], &["status", "--porcelain"]] {
+        assert_eq!(
+            git(worktree, args),
+            "",
+            "{:?}: the index records all files along with their metadata",
+            args
+        );
+    }
+}
+
+#[test]
+fn v1_and_v2_clones_are_the_same() -> Result<(), Box<dyn std::error::Error>> {
+    for protocol in &[Protocol::V1, Protocol::V2] {
+        let dir = tempfile::tempdir()?;
+        let worktree = dir.path().join("clone");
+        let outcome = clone(
+            &fixture_url(),
+            &worktree,
+            progress::Discard,
+            clone::Options {
+                protocol: *protocol,
+                ..Default::default()
+            },
+        )
+        .map_err(|e| format!("{:?}: {:?}", protocol, e))?;
+        assert_cloned(&worktree, &outcome);
+    }
+    Ok(())
+}
+
+#[test]
+fn without_checkout_only_the_git_dir_is_written() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let outcome = clone(
+        &fixture_url(),
+        dir.path(),
+        progress::Discard,
+        clone::Options {
+            checkout: false,
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(outcome.checked_out_files, 0);
+    assert_eq!(fs::read_dir(dir.path())?.count(), 1, "only the .git directory");
+    Ok(())
+}
+
+#[test]
+fn non_empty_directories_are_refused() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("file"), b"")?;
+    assert!(matches!(
+        clone(&fixture_url(), dir.path(), progress::Discard, Default::default()),
+        Err(clone::Error::DirectoryNotEmpty(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn filters_are_refused_if_the_remote_does_not_allow_them() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    assert!(matches!(
+        clone(
+            &fixture_url(),
+            dir.path(),
+            progress::Discard,
+            clone::Options {
+                filter: Some(fetch::Filter::BlobNone),
+                ..Default::default()
+            }
+        ),
+        Err(clone::Error::FilterUnsupported(fetch::Filter::BlobNone))
+    ));
+    Ok(())
+}
+
+#[test]
+fn partial_clones_record_the_promisor_remote() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let remote = dir.path().join("remote.git");
+    git(
+        dir.path(),
+        &["clone", "--bare", "--quiet", &fixture_url(), "remote.git"],
+    );
+    git(&remote, &["config", "uploadpack.allowFilter", "true"]);
+    let url = format!("file://{}", remote.display());
+
+    for protocol in &[Protocol::V1, Protocol::V2] {
+        let worktree = dir.path().join(format!("{:?}", protocol));
+        let options = clone::Options {
+            protocol: *protocol,
+            filter: Some(fetch::Filter::BlobNone),
+            checkout: false,
+            ..Default::default()
+        };
+        let outcome = clone(&url, &worktree, progress::Discard, options.clone())?;
+        let pack = outcome.pack.expect("a pack was received");
+        assert_eq!(pack.index.num_objects, 7, "{:?}: all objects but the 5 blobs", protocol);
+        let promisor = read(pack.data_path.expect("written to disk").with_extension("promisor"));
+        assert!(promisor.contains(&format!("{} refs/heads/main\n", MAIN.trim_end())));
+
+        let config = read(outcome.git_dir.join("config"));
+        assert!(config.contains("\trepositoryformatversion = 1\n"));
+        assert!(config.contains("\tpromisor = true\n\tpartialclonefilter = blob:none\n"));
+        assert!(config.contains("[extensions]\n\tpartialclone = origin\n"));
+
+        let worktree = dir.path().join(format!("{:?}-checkout", protocol));
+        assert!(matches!(
+            clone(
+                &url,
+                &worktree,
+                progress::Discard,
+                clone::Options {
+                    checkout: true,
+                    ..options
+                }
+            ),
+            Err(clone::Error::PromisedObjectMissing(_))
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn bare_clones_mirror_branches_without_worktree() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let git_dir = dir.path().join("bare.git");
+    let outcome = clone(
+        &fixture_url(),
+        &git_dir,
+        progress::Discard,
+        clone::Options {
+            bare: true,
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(outcome.git_dir, git_dir);
+    assert_eq!(outcome.checked_out_files, 0);
+    assert_eq!(read(git_dir.join("HEAD")), "ref: refs/heads/main\n");
+    assert_eq!(read(git_dir.join("refs/heads/main")), MAIN);
+    assert_eq!(
+        read(git_dir.join("refs/heads/other")),
+        "cecbafcde689e1bc457bf13334c3d934200802a1\n"
+    );
+    assert!(!git_dir.join("refs/remotes").exists());
+    assert!(!git_dir.join(".git").exists());
+
+    let config = read(git_dir.join("config"));
+    assert!(config.contains("\tbare = true\n"));
+    assert!(config.contains("[remote \"origin\"]\n\turl = file://"));
+    assert!(!config.contains("fetch ="), "bare clones have no fetch refspec");
+    assert!(!config.contains("[branch"));
+    Ok(())
+}
+
+#[test]
+fn shallow_clones_record_their_boundary() -> Result<(), Box<dyn std::error::Error>> {
+    for protocol in &[Protocol::V1, Protocol::V2] {
+        let dir = tempfile::tempdir()?;
+        let outcome = clone(
+            &fixture_url(),
+            dir.path(),
+            progress::Discard,
+            clone::Options {
+                protocol: *protocol,
+                depth: Some(1),
+                ..Default::default()
+            },
+        )?;
+        let shallow = read(outcome.git_dir.join("shallow"));
+        assert_eq!(
+            outcome.shallow_commits.len(),
+            shallow.lines().count(),
+            "{:?}: every boundary commit is written",
+            protocol
+        );
+        assert!(
+            shallow.contains(MAIN),
+            "{:?}: the parent of main is cut off, even though other points to it: {}",
+            protocol,
+            shallow
+        );
+        assert_eq!(outcome.checked_out_files, 4);
+    }
+    Ok(())
+}
+
+fn fixture_git_dir() -> String {
+    fixture_url().trim_start_matches("file://").to_owned()
+}
+
+/// Write a bundle of `revisions` of the fixture to `path` using git.
+fn git_bundle(path: &Path, revisions: &[&str]) {
+    let path = path.display().to_string();
+    let git_dir = fixture_git_dir();
+    let mut args = vec!["--git-dir", &git_dir, "bundle", "create", "--quiet", &path];
+    args.extend_from_slice(revisions);
+    git(Path::new("."), &args);
+}
+
+#[test]
+fn bundles_are_unpacked_and_only_the_missing_objects_are_fetched() -> Result<(), Box<dyn std::error::Error>> {
+    for protocol in &[Protocol::V1, Protocol::V2] {
+        let dir = tempfile::tempdir()?;
+        let bundle = dir.path().join("other.bundle");
+        git_bundle(&bundle, &["other"]);
+        let worktree = dir.path().join("clone");
+        let outcome = clone(
+            &fixture_url(),
+            &worktree,
+            progress::Discard,
+            clone::Options {
+                protocol: *protocol,
+                bundle_uri: Some(bundle.display().to_string()),
+                ..Default::default()
+            },
+        )?;
+        let git_dir = &outcome.git_dir;
+        assert_eq!(outcome.bundles.len(), 1);
+        let unbundled = outcome.bundles[0].index.num_objects;
+        let fetched = outcome.pack.as_ref().map(|p| p.index.num_objects);
+        assert!(unbundled > 0);
+        assert_eq!(
+            fetched,
+            Some(12 - unbundled),
+            "{:?}: the server only sends what the bundle lacks",
+            protocol
+        );
+        assert_eq!(
+            read(git_dir.join("refs/bundles/other")),
+            "cecbafcde689e1bc457bf13334c3d934200802a1\n"
+        );
+        assert_eq!(read(git_dir.join("refs/remotes/origin/main")), MAIN);
+        assert_eq!(outcome.checked_out_files, 4);
+        assert_eq!(read(worktree.join("a")), "hello\nmore\n");
+    }
+    Ok(())
+}
+
+#[test]
+fn bundle_lists_are_applied_in_order_and_broken_bundles_are_skipped() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    git_bundle(&dir.path().join("base.bundle"), &["other"]);
+    git_bundle(&dir.path().join("incremental.bundle"), &["main", "v1.0", "^other"]);
+    fs::write(dir.path().join("broken.bundle"), "# v2 git bundle\n")?;
+    let list = dir.path().join("bundles.list");
+    fs::write(
+        &list,
+        "[bundle]\n\tversion = 1\n\tmode = all\n\
+         [bundle \"incremental\"]\n\turi = incremental.bundle\n\tcreationToken = 2\n\
+         [bundle \"broken\"]\n\turi = broken.bundle\n\tcreationToken = 3\n\
+         [bundle \"base\"]\n\turi = base.bundle\n\tcreationToken = 1\n",
+    )?;
+    let worktree = dir.path().join("clone");
+    let outcome = clone(
+        &fixture_url(),
+        &worktree,
+        progress::Discard,
+        clone::Options {
+            bundle_uri: Some(list.display().to_string()),
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(
+        outcome.bundles.len(),
+        2,
+        "the incremental bundle needs the base bundle first"
+    );
+    assert_eq!(outcome.bundles.iter().map(|b| b.index.num_objects).sum::<u32>(), 12);
+    assert!(outcome.pack.is_none(), "all objects were contained in the bundles");
+    assert_eq!(read(outcome.git_dir.join("refs/bundles/main")), MAIN);
+    assert_eq!(read(outcome.git_dir.join("refs/heads/main")), MAIN);
+    assert_eq!(outcome.checked_out_files, 4);
+    assert_eq!(read(worktree.join("dir/sub/b")), "nested\n");
+    Ok(())
+}
+
+#[test]
+fn bundle_uris_are_refused_for_shallow_clones() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    assert!(matches!(
+        clone(
+            &fixture_url(),
+            dir.path().join("clone"),
+            progress::Discard,
+            clone::Options {
+                depth: Some(1),
+                bundle_uri: Some("bundle".into()),
+                ..Default::default()
+            },
+        ),
+        Err(clone::Error::BundleUriWithDepth)
+    ));
+    assert!(!dir.path().join("clone").exists());
+    Ok(())
+}
@@ -0,0 +1,313 @@
+use crate::{git, hex_to_id};
+use git_object::{owned, Sign, Time};
+use git_repository::commit::{self, Error, Hooks, Options};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const MAIN: &str = "877c3ad4e85fa4408811d3fcc38aef2500f04ade";
+const OTHER: &str = "cecbafcde689e1bc457bf13334c3d934200802a1";
+
+fn repository(dir: &Path) -> PathBuf {
+    let fixture = Path::new("tests/fixtures/repos/files.git").canonicalize().unwrap();
+    git(
+        dir,
+        &[
+            "clone",
+            "--quiet",
+            "--bare",
+            "--no-local",
+            fixture.to_str().unwrap(),
+            "repo.git",
+        ],
+    );
+    dir.join("repo.git")
+}
+
+fn signature() -> owned::Signature {
+    owned::Signature {
+        name: "Bot".into(),
+        email: "bot@example.com".into(),
+        time: Time {
+            time: 1_600_000_000,
+            offset: 0,
+            sign: Sign::Plus,
+        },
+    }
+}
+
+fn tree_of(git_dir: &Path, rev: &str) -> owned::Id {
+    hex_to_id(git(git_dir, &["rev-parse", &format!("{}^{{tree}}", rev)]).trim_end())
+}
+
+fn create(
+    git_dir: &Path,
+    parents: Vec<owned::Id>,
+    message: &str,
+    options: Options<'_>,
+) -> Result<commit::Outcome, Error> {
+    commit::create(
+        git_dir,
+        tree_of(git_dir, OTHER),
+        parents,
+        signature(),
+        signature(),
+        message,
+        options,
+    )
+}
+
+#[test]
+fn commits_move_the_branch_head_points_to_and_are_logged_in_both_reflogs() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = repository(dir.path());
+    let outcome = create(&git_dir, vec![hex_to_id(MAIN)], "subject\n\nbody\n", Options::default()).unwrap();
+    assert_eq!(outcome.reference, "refs/heads/main");
+    assert_eq!(outcome.previous, Some(hex_to_id(MAIN)));
+    assert_eq!(git(&git_dir, &["rev-parse", "HEAD"]).trim_end(), outcome.id.to_string());
+    assert_eq!(
+        git(&git_dir, &["log", "-1", "--format=%s%n%b%P"]),
+        format!("subject\nbody\n{}\n", MAIN)
+    );
+
+    let reflog_line = format!(
+        "{} {} Bot <bot@example.com> 1600000000 +0000\tcommit: subject\n",
+        MAIN, outcome.id
+    );
+    for log in &["logs/HEAD", "logs/refs/heads/main"] {
+        assert_eq!(fs::read_to_string(git_dir.join(log)).unwrap(), reflog_line, "{}", log);
+    }
+    git(&git_dir, &["fsck", "--strict"]);
+}
+
+#[test]
+fn initial_commits_create_the_given_ref() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = repository(dir.path());
+    let outcome = create(
+        &git_dir,
+        Vec::new(),
+        "root",
+        Options {
+            reference: Some("refs/heads/new".into()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(outcome.previous, None);
+    assert_eq!(git(&git_dir, &["rev-parse", "new"]).trim_end(), outcome.id.to_string());
+    assert!(fs::read_to_string(git_dir.join("logs/refs/heads/new"))
+        .unwrap()
+        .ends_with("\tcommit (initial): root\n"));
+    assert!(!git_dir.join("logs/HEAD").exists(), "HEAD wasn't involved");
+}
+
+#[test]
+fn refs_not_pointing_to_the_first_parent_are_left_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = repository(dir.path());
+    assert!(matches!(
+        create(&git_dir, vec![hex_to_id(OTHER)], "stale", Options::default()),
+        Err(Error::RefChanged(name, Some(expected), Some(actual)))
+            if name == "refs/heads/main" && expected == hex_to_id(OTHER) && actual == hex_to_id(MAIN)
+    ));
+    assert_eq!(git(&git_dir, &["rev-parse", "main"]).trim_end(), MAIN);
+}
+
+#[test]
+fn hooks_can_reject_commits_and_change_their_message() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = repository(dir.path());
+    let rejected = create(
+        &git_dir,
+        vec![hex_to_id(MAIN)],
+        "rejected",
+        Options {
+            hooks: Hooks {
+                pre_commit: Some(Box::new(|commit, messages| {
+                    messages.extend_from_slice(b"no ");
+                    messages.extend_from_slice(&commit.message);
+                    false
+                })),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+    assert!(matches!(rejected, Err(Error::Rejected("pre-commit", messages)) if messages == "no rejected"));
+    assert_eq!(git(&git_dir, &["rev-parse", "main"]).trim_end(), MAIN);
+
+    let mut committed = None;
+    let outcome = create(
+        &git_dir,
+        vec![hex_to_id(MAIN)],
+        "message",
+        Options {
+            hooks: Hooks {
+                commit_msg: Some(Box::new(|message, _messages| {
+                    message.extend_from_slice(b"\n\nSigned-off-by: Bot <bot@example.com>\n");
+                    true
+                })),
+                post_commit: Some(Box::new(|id, messages| {
+                    committed = Some(id);
+                    messages.extend_from_slice(b"done");
+                })),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(committed, Some(outcome.id));
+    assert_eq!(outcome.messages, "done");
+    assert_eq!(
+        git(&git_dir, &["log", "-1", "--format=%B"]),
+        "message\n\nSigned-off-by: Bot <bot@example.com>\n\n"
+    );
+}
+
+#[test]
+fn signed_commits_embed_the_signature_and_can_be_split_into_payload_and_signature() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = repository(dir.path());
+    let signature = "-----BEGIN SSH SIGNATURE-----\nU1NIU0lH\n-----END SSH SIGNATURE-----\n";
+    let mut payload = Vec::new();
+    let outcome = create(
+        &git_dir,
+        vec![hex_to_id(MAIN)],
+        "signed\n",
+        Options {
+            sign: Some(Box::new(|data| {
+                payload = data.to_owned();
+                Ok(signature.into())
+            })),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let data = git(&git_dir, &["cat-file", "commit", &outcome.id.to_string()]);
+    assert_eq!(
+        data,
+        format!(
+            "tree {}\nparent {}\nauthor Bot <bot@example.com> 1600000000 +0000\ncommitter Bot <bot@example.com> 1600000000 +0000\ngpgsig -----BEGIN SSH SIGNATURE-----\n U1NIU0lH\n -----END SSH SIGNATURE-----\n\nsigned\n",
+            tree_of(&git_dir, OTHER),
+            MAIN
+        )
+    );
+    assert_eq!(
+        commit::extract_signature(data.as_bytes()),
+        Some((payload.into(), signature.into())),
+        "the payload is what was signed"
+    );
+    assert_eq!(
+        commit::extract_signature(b"tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n\ngpgsig no header\n"),
+        None
+    );
+    git(&git_dir, &["fsck", "--strict"]);
+
+    assert!(matches!(
+        create(
+            &git_dir,
+            vec![outcome.id],
+            "unsigned",
+            Options {
+                sign: Some(Box::new(|_| Err("no key".into()))),
+                ..Default::default()
+            }
+        ),
+        Err(Error::Sign(err)) if err.to_string() == "no key"
+    ));
+    assert_eq!(
+        git(&git_dir, &["rev-parse", "main"]).trim_end(),
+        outcome.id.to_string(),
+        "nothing is committed if signing fails"
+    );
+}
+
+#[test]
+fn the_configured_encoding_is_recorded_unless_it_is_utf8() {
+    let config = |value: &str| {
+        git_config::File::from_bytes(format!("[i18n]\n\tcommitEncoding = {}\n", value).as_bytes()).unwrap()
+    };
+    assert_eq!(commit::encoding_from_config(&config("UTF-8")), None);
+    assert_eq!(commit::encoding_from_config(&config("utf8")), None);
+    assert_eq!(commit::encoding_from_config(&git_config::File::default()), None);
+    let encoding = commit::encoding_from_config(&config("ISO-8859-1"));
+    assert_eq!(encoding, Some("ISO-8859-1".into()));
+
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = repository(dir.path());
+    let outcome = commit::create(
+        &git_dir,
+        tree_of(&git_dir, OTHER),
+        vec![hex_to_id(MAIN)],
+        signature(),
+        signature(),
+        &b"caf\xe9\n"[..],
+        Options {
+            encoding,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(git(&git_dir, &["log", "-1", "--format=%e"]), "ISO-8859-1\n");
+    assert_eq!(
+        git(&git_dir, &["log", "-1", "--format=%B"]),
+        "café\n\n",
+        "git re-encodes the message for display"
+    );
+
+    let outcome = create(
+        &git_dir,
+        vec![outcome.id],
+        "plain",
+        Options {
+            encoding: Some("UTF-8".into()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        git(&git_dir, &["log", "-1", "--format=%e", &outcome.id.to_string()]),
+        "\n",
+        "no header is written"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn executable_hooks_are_run() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = repository(dir.path());
+    for (name, script) in &[
+        ("pre-commit", "echo pre-commit"),
+        ("commit-msg", "echo commit-msg $1; echo 'edited' > \"$1\""),
+        ("post-commit", "echo post-commit"),
+    ] {
+        let path = git_dir.join("hooks").join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let outcome = create(
+        &git_dir,
+        vec![hex_to_id(MAIN)],
+        "original",
+        Options {
+            hooks: Hooks::from_git_dir(&git_dir),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(outcome.messages, "pre-commit\ncommit-msg COMMIT_EDITMSG\npost-commit\n");
+    assert_eq!(git(&git_dir, &["log", "-1", "--format=%B"]), "edited\n\n");
+
+    let path = git_dir.join("hooks/pre-commit");
+    fs::write(&path, "#!/bin/sh\necho nope; exit 1\n").unwrap();
+    assert!(matches!(
+        create(&git_dir, vec![outcome.id], "rejected", Options { hooks: Hooks::from_git_dir(&git_dir), ..Default::default() }),
+        Err(Error::Rejected("pre-commit", messages)) if messages == "nope\n"
+    ));
+}
@@ -0,0 +1,177 @@
+use crate::{git, git_command, run};
+use git_object::{bstr::ByteSlice, Sign, Time};
+use git_repository::date;
+use std::{fs, path::Path};
+
+const NOW: u32 = 1_600_000_000;
+
+/// The time git parses `input` as, at `NOW` in the timezone `tz`.
+fn parsed_by_git(dir: &Path, tz: &str, input: &str) -> u32 {
+    let output = run(git_command(dir)
+        .env("GIT_TEST_DATE_NOW", NOW.to_string())
+        .env("TZ", tz)
+        .args(["rev-parse", &format!("--since={}", input)]));
+    let output = String::from_utf8(output).unwrap();
+    output.trim_end().strip_prefix("--max-age=").unwrap().parse().unwrap()
+}
+
+fn now(offset: i32) -> Time {
+    Time {
+        time: NOW,
+        offset,
+        sign: Sign::Plus,
+    }
+}
+
+#[test]
+fn dates_are_parsed_like_git_does() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("repo")).unwrap();
+    let repo = dir.path().join("repo");
+    git(&repo, &["init", "--quiet"]);
+
+    for input in &[
+        "1234567890",
+        "@1234567890",
+        "1234567890 +0100",
+        "Thu, 07 Apr 2005 22:13:13 +0200",
+        "2005-04-07T22:13:13+02:00",
+        "2005-04-07T22:13:13Z",
+        "2005-04-07 22:13:13",
+        "2005-04-07 22:13",
+        "2005-04-07",
+        "2005.04.07",
+        "Apr 7 2005",
+        "7 Apr 2005 10:00",
+        "10:00",
+        "now",
+        "today",
+        "yesterday",
+        "noon",
+        "midnight",
+        "2 weeks ago",
+        "2.weeks.ago",
+        "3 days 2 hours ago",
+        "last week",
+        "1 month ago",
+        "1 year ago",
+    ] {
+        for (tz, offset) in &[("UTC", 0), ("Europe/Berlin", 2 * 3600)] {
+            assert_eq!(
+                date::parse(input.as_bytes().as_bstr(), now(*offset)).unwrap().time,
+                parsed_by_git(&repo, tz, input),
+                "{} in {}",
+                input,
+                tz
+            );
+        }
+    }
+}
+
+#[test]
+fn timezones_are_kept_and_garbage_is_an_error() {
+    let parse = |input: &str| date::parse(input.as_bytes().as_bstr(), now(3600));
+    let time = parse("Thu, 07 Apr 2005 22:13:13 -0700").unwrap();
+    assert_eq!((time.offset, time.sign), (-7 * 3600, Sign::Minus));
+    assert_eq!(parse("yesterday").unwrap().offset, 3600, "the local timezone is used");
+    assert_eq!(parse("@1234567890").unwrap().offset, 0);
+
+    assert!(matches!(parse("garbage"), Err(date::Error::UnknownWord(..))));
+    assert!(matches!(parse(""), Err(date::Error::Invalid(_))));
+    assert!(matches!(parse("2005-13-07"), Err(date::Error::Invalid(_))));
+    assert!(matches!(parse("60 years ago"), Err(date::Error::OutOfRange(_))));
+}
+
+#[test]
+fn huge_amounts_are_out_of_range_instead_of_overflowing() {
+    let parse = |input: &str| date::parse(input.as_bytes().as_bstr(), now(3600));
+    for input in &[
+        "99999999999999999999 days ago",
+        "9999999999999 years ago",
+        "99999999999999999999 months ago",
+        "9223372036854775807 seconds ago yesterday",
+        "9223372036854775807 seconds ago 1 second ago",
+    ] {
+        assert!(
+            matches!(parse(input), Err(date::Error::OutOfRange(_))),
+            "{}: {:?}",
+            input,
+            parse(input)
+        );
+    }
+}
+
+#[test]
+fn dates_are_formatted_like_git_does() {
+    let dir = tempfile::tempdir().unwrap();
+    let git = |args: &[&str], date: &str| {
+        let output = run(git_command(dir.path())
+            .args(args)
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_DATE", date));
+        String::from_utf8(output).unwrap()
+    };
+    git(&["init", "--quiet"], "");
+    for (date, time) in &[
+        (
+            "1112904793 +0200",
+            Time {
+                time: 1_112_904_793,
+                offset: 7200,
+                sign: Sign::Plus,
+            },
+        ),
+        (
+            "1112904793 -0530",
+            Time {
+                time: 1_112_904_793,
+                offset: -19800,
+                sign: Sign::Minus,
+            },
+        ),
+        (
+            "1600000000 +0000",
+            Time {
+                time: 1_600_000_000,
+                offset: 0,
+                sign: Sign::Plus,
+            },
+        ),
+        (
+            "1600000000 -0000",
+            Time {
+                time: 1_600_000_000,
+                offset: 0,
+                sign: Sign::Minus,
+            },
+        ),
+        (
+            "@86400 +0100",
+            Time {
+                time: 86400,
+                offset: 3600,
+                sign: Sign::Plus,
+            },
+        ),
+    ] {
+        git(&["commit", "--quiet", "--allow-empty", "-m", "commit"], date);
+        for (name, format) in &[
+            ("default", date::Format::Default),
+            ("rfc", date::Format::Rfc2822),
+            ("iso", date::Format::Iso8601),
+            ("iso-strict", date::Format::Iso8601Strict),
+            ("unix", date::Format::Unix),
+        ] {
+            assert_eq!(date::Format::from_name(name), Some(*format));
+            let expected = git(&["log", "-1", &format!("--date={}", name), "--format=%ad"], "");
+            assert_eq!(
+                date::format(*time, *format),
+                expected.trim_end(),
+                "{} in {}",
+                date,
+                name
+            );
+        }
+    }
+    assert_eq!(date::Format::from_name("relative"), None);
+}
@@ -0,0 +1,271 @@
+use git_object::{
+    borrowed,
+    bstr::{BStr, ByteSlice},
+    owned, HashKind, Kind, Sign, Time,
+};
+use git_odb::Write;
+use git_repository::describe;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+/// An in-memory commit graph mirroring what `git` would produce for the same empty commits.
+#[derive(Default)]
+pub(crate) struct Graph {
+    objects: HashMap<owned::Id, Vec<u8>>,
+}
+
+impl Graph {
+    pub(crate) fn commit(&mut self, time: u32, message: &str, parents: &[owned::Id]) -> owned::Id {
+        let signature = owned::Signature {
+            name: "a".into(),
+            email: "a@b".into(),
+            time: Time {
+                time,
+                offset: 0,
+                sign: Sign::Plus,
+            },
+        };
+        let commit = owned::Commit {
+            tree: owned::Id::from_40_bytes_in_hex(b"4b825dc642cb6eb9a060e54bf8d69288fbee4904").unwrap(),
+            parents: parents.iter().cloned().collect(),
+            author: signature.clone(),
+            committer: signature,
+            encoding: None,
+            message: format!("{}\n", message).into(),
+            extra_headers: Vec::new(),
+        };
+        let mut buf = Vec::new();
+        commit.write_to(&mut buf).unwrap();
+        let id = git_odb::sink().write_buf(Kind::Commit, &buf, HashKind::Sha1).unwrap();
+        self.objects.insert(id, buf);
+        id
+    }
+
+    pub(crate) fn find<'a>(&self, id: borrowed::Id<'_>, buf: &'a mut Vec<u8>) -> Option<borrowed::Object<'a>> {
+        let data = self.objects.get(&owned::Id::from_borrowed_sha1(id.sha1()))?;
+        buf.clear();
+        buf.extend_from_slice(data);
+        borrowed::Object::from_bytes(Kind::Commit, buf).ok()
+    }
+}
+
+struct Fixture {
+    graph: Graph,
+    names: HashMap<owned::Id, Cow<'static, BStr>>,
+    head: owned::Id,
+    v1_1: owned::Id,
+    c4: owned::Id,
+}
+
+/// Build the following history, with commit times increasing in order of creation:
+///
+/// ```text
+/// c1 (v1.0) - c2 - c3 (v1.1) - c4 ------------------- c5 - m (HEAD)
+///                                 \                        /
+///                                  b1 - b2 (v2.0-beta) ----
+/// ```
+fn fixture() -> Fixture {
+    let mut graph = Graph::default();
+    let t = |n: u32| 1_000_000_000 + n;
+    let c1 = graph.commit(t(1), "c1", &[]);
+    let c2 = graph.commit(t(2), "c2", &[c1]);
+    let c3 = graph.commit(t(3), "c3", &[c2]);
+    let c4 = graph.commit(t(4), "c4", &[c3]);
+    let b1 = graph.commit(t(5), "b1", &[c4]);
+    let b2 = graph.commit(t(6), "b2", &[b1]);
+    let c5 = graph.commit(t(7), "c5", &[c4]);
+    let m = graph.commit(t(8), "m", &[c5, b2]);
+
+    let mut names = HashMap::new();
+    names.insert(c1, Cow::Borrowed(b"v1.0".as_bstr()));
+    names.insert(c3, Cow::Borrowed(b"v1.1".as_bstr()));
+    names.insert(b2, Cow::Borrowed(b"v2.0-beta".as_bstr()));
+    Fixture {
+        graph,
+        names,
+        head: m,
+        v1_1: c3,
+        c4,
+    }
+}
+
+fn run(fixture: &Fixture, commit: owned::Id, opts: describe::Options<'static>) -> Option<describe::Outcome<'static>> {
+    describe(commit.to_borrowed(), |id, buf| fixture.graph.find(id, buf), opts).unwrap()
+}
+
+fn opts(fixture: &Fixture) -> describe::Options<'static> {
+    describe::Options {
+        name_by_oid: fixture.names.clone(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn closest_tag_across_merges_matches_git() {
+    let f = fixture();
+    let outcome = run(&f, f.head, opts(&f)).expect("found");
+    assert_eq!(outcome.into_format(7).to_string(), "v2.0-beta-2-g24adfb6");
+}
+
+#[test]
+fn first_parent_ignores_merged_branches() {
+    let f = fixture();
+    let outcome = run(
+        &f,
+        f.head,
+        describe::Options {
+            first_parent: true,
+            ..opts(&f)
+        },
+    )
+    .expect("found");
+    assert_eq!(outcome.into_format(7).to_string(), "v1.1-3-g24adfb6");
+}
+
+#[test]
+fn patterns_limit_the_considered_names() {
+    let f = fixture();
+    let outcome = run(
+        &f,
+        f.head,
+        describe::Options {
+            patterns: vec![Cow::Borrowed(b"v1*".as_bstr())],
+            ..opts(&f)
+        },
+    )
+    .expect("found");
+    assert_eq!(outcome.into_format(7).to_string(), "v1.1-5-g24adfb6");
+
+    let outcome = run(
+        &f,
+        f.head,
+        describe::Options {
+            patterns: vec![Cow::Borrowed(b"v1.[!1]".as_bstr())],
+            ..opts(&f)
+        },
+    )
+    .expect("found");
+    assert_eq!(outcome.name.as_deref(), Some(b"v1.0".as_bstr()));
+}
+
+#[test]
+fn a_single_candidate_still_finds_the_closest_name() {
+    let f = fixture();
+    let outcome = run(
+        &f,
+        f.head,
+        describe::Options {
+            max_candidates: 1,
+            ..opts(&f)
+        },
+    )
+    .expect("found");
+    assert_eq!(outcome.into_format(7).to_string(), "v2.0-beta-2-g24adfb6");
+}
+
+#[test]
+fn the_last_path_is_only_skipped_if_it_is_covered_by_the_best_candidates() {
+    // m merges c3 into the newer a, which is tagged but has no history in common with c3:
+    //
+    // c1 - c2 - c3 - m
+    //               /
+    //        a (v1)
+    let mut graph = Graph::default();
+    let t = |n: u32| 1_000_000_000 + n;
+    let c1 = graph.commit(t(1), "c1", &[]);
+    let c2 = graph.commit(t(2), "c2", &[c1]);
+    let c3 = graph.commit(t(3), "c3", &[c2]);
+    let a = graph.commit(t(4), "a", &[]);
+    let m = graph.commit(t(5), "m", &[c3, a]);
+    let mut names = HashMap::new();
+    names.insert(a, Cow::Borrowed(b"v1".as_bstr()));
+
+    let outcome = describe(
+        m.to_borrowed(),
+        |id, buf| graph.find(id, buf),
+        describe::Options {
+            name_by_oid: names,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .expect("found");
+    assert_eq!(
+        outcome.into_format(7).to_string(),
+        "v1-4-g8d0aee7",
+        "c3, c2 and c1 aren't reachable from v1, like git says"
+    );
+}
+
+#[test]
+fn abbreviation_length_and_long_format() {
+    let f = fixture();
+    let outcome = run(&f, f.head, opts(&f)).expect("found");
+    assert_eq!(outcome.clone().into_format(0).to_string(), "v2.0-beta");
+    assert_eq!(
+        outcome.clone().into_format(40).to_string(),
+        "v2.0-beta-2-g24adfb6e5416979f71a187c413d45cd68af9a5b1"
+    );
+    let mut format = outcome.into_format(7);
+    format.dirty_suffix = Some("dirty".into());
+    assert_eq!(format.to_string(), "v2.0-beta-2-g24adfb6-dirty");
+
+    let outcome = run(&f, f.v1_1, opts(&f)).expect("found");
+    let mut format = outcome.into_format(7);
+    assert!(format.is_exact_match());
+    assert_eq!(format.to_string(), "v1.1");
+    format.long = true;
+    assert_eq!(format.to_string(), "v1.1-0-g134a0f8");
+}
+
+#[test]
+fn exact_matches_only_if_there_are_no_candidates() {
+    let f = fixture();
+    let no_candidates = || describe::Options {
+        max_candidates: 0,
+        ..opts(&f)
+    };
+    assert!(run(&f, f.head, no_candidates()).is_none());
+    assert_eq!(
+        run(&f, f.v1_1, no_candidates()).expect("exact match").name.as_deref(),
+        Some(b"v1.1".as_bstr())
+    );
+}
+
+#[test]
+fn fallback_to_oid_if_no_name_is_reachable() {
+    let f = fixture();
+    let outcome = run(
+        &f,
+        f.head,
+        describe::Options {
+            name_by_oid: Default::default(),
+            fallback_to_oid: true,
+            ..Default::default()
+        },
+    )
+    .expect("fallback");
+    assert_eq!(outcome.name, None);
+    assert_eq!(outcome.into_format(7).to_string(), "24adfb6");
+}
+
+#[test]
+fn shallow_commits_hide_their_ancestors() {
+    let f = fixture();
+    let mut shallow = HashSet::new();
+    shallow.insert(f.c4);
+    let outcome = run(
+        &f,
+        f.head,
+        describe::Options {
+            first_parent: true,
+            shallow,
+            fallback_to_oid: true,
+            ..opts(&f)
+        },
+    )
+    .expect("fallback");
+    assert_eq!(outcome.name, None, "v1.1 is behind the shallow boundary");
+}
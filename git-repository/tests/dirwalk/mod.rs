@@ -0,0 +1,151 @@
+use crate::git;
+use git_repository::{
+    dirwalk::{self, Entry, Kind, Options, Status},
+    ignore, index, worktree,
+};
+use std::{fs, path::Path, process::Command};
+
+fn write(dir: &Path, path: &str, content: &str) {
+    let path = dir.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn repository(dir: &Path) {
+    git(dir, &["init", "--quiet"]);
+    write(dir, ".gitignore", "*.o\n");
+    write(dir, "tracked", "");
+    write(dir, "dir/tracked.o", "");
+    write(dir, "dir/sub/tracked", "");
+    git(dir, &["add", ".", "--force"]);
+    write(dir, "untracked", "");
+    write(dir, "dir/x.o", "");
+    write(dir, "dir/sub/untracked", "");
+    write(dir, "new/a", "");
+    write(dir, "new/deeper/b.o", "");
+}
+
+fn walk(dir: &Path, options: Options) -> Vec<(String, Kind, Status)> {
+    let git_dir = dir.join(".git");
+    let index = index::File::at(git_dir.join("index")).unwrap().unwrap_or_default();
+    dirwalk::walk(
+        dir,
+        &index,
+        &ignore::Stack::from_git_dir(&git_dir),
+        &worktree::Capabilities::from_git_dir(&git_dir),
+        &options,
+    )
+    .unwrap()
+    .entries
+    .into_iter()
+    .map(|Entry { path, kind, status }| (path.to_string(), kind, status))
+    .collect()
+}
+
+fn entry(path: &str, kind: Kind, status: Status) -> (String, Kind, Status) {
+    (path.into(), kind, status)
+}
+
+#[test]
+fn entries_are_classified_and_untracked_directories_collapsed() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    let all = Options {
+        tracked: true,
+        ignored: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        walk(dir.path(), all),
+        vec![
+            entry(".gitignore", Kind::File, Status::Tracked),
+            entry("dir/sub/tracked", Kind::File, Status::Tracked),
+            entry("dir/sub/untracked", Kind::File, Status::Untracked),
+            entry("dir/tracked.o", Kind::File, Status::Tracked),
+            entry("dir/x.o", Kind::File, Status::Ignored),
+            entry("new", Kind::Directory, Status::Untracked),
+            entry("new/deeper/b.o", Kind::File, Status::Ignored),
+            entry("tracked", Kind::File, Status::Tracked),
+            entry("untracked", Kind::File, Status::Untracked),
+        ]
+    );
+
+    assert_eq!(
+        walk(
+            dir.path(),
+            Options {
+                collapse_untracked_directories: false,
+                ..Default::default()
+            }
+        ),
+        vec![
+            entry("dir/sub/untracked", Kind::File, Status::Untracked),
+            entry("new/a", Kind::File, Status::Untracked),
+            entry("untracked", Kind::File, Status::Untracked),
+        ]
+    );
+}
+
+#[test]
+fn the_result_does_not_depend_on_the_amount_of_threads() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    for directory in 0..20 {
+        for file in 0..5 {
+            write(dir.path(), &format!("many/{}/{}/file{}", directory, file, file), "");
+        }
+    }
+    for collapse in &[true, false] {
+        let options = |thread_limit| Options {
+            tracked: true,
+            ignored: true,
+            collapse_untracked_directories: *collapse,
+            thread_limit,
+        };
+        assert_eq!(walk(dir.path(), options(Some(1))), walk(dir.path(), options(None)));
+    }
+}
+
+#[test]
+fn repositories_are_not_entered() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    git(&dir.path().join("dir"), &["init", "--quiet", "nested"]);
+    write(dir.path(), "dir/nested/file", "");
+    git(dir.path(), &["init", "--quiet", "submodule"]);
+    git(
+        &dir.path().join("submodule"),
+        &["commit", "--quiet", "--allow-empty", "-m", "initial"],
+    );
+    git(dir.path(), &["add", "submodule"]);
+
+    let entries = walk(
+        dir.path(),
+        Options {
+            tracked: true,
+            collapse_untracked_directories: false,
+            ..Default::default()
+        },
+    );
+    assert!(entries.contains(&entry("dir/nested", Kind::Repository, Status::Untracked)));
+    assert!(entries.contains(&entry("submodule", Kind::Repository, Status::Tracked)));
+    assert!(entries.iter().all(|(path, _, _)| !path.contains(".git/")
+        && !path.starts_with("dir/nested/")
+        && !path.starts_with("submodule/")));
+}
+
+#[cfg(unix)]
+#[test]
+fn links_are_not_followed_and_special_files_are_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    std::os::unix::fs::symlink("dir", dir.path().join("link")).unwrap();
+    let fifo = Command::new("mkfifo").arg(dir.path().join("fifo")).status().unwrap();
+    assert!(fifo.success());
+
+    let entries = walk(dir.path(), Options::default());
+    assert!(entries.contains(&entry("link", Kind::Link, Status::Untracked)));
+    assert!(!entries
+        .iter()
+        .any(|(path, _, _)| path == "fifo" || path.starts_with("link/")));
+}
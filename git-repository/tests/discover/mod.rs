@@ -0,0 +1,153 @@
+use crate::git;
+use git_repository::discover::{self, Environment, Error};
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+fn canonical(path: impl AsRef<Path>) -> PathBuf {
+    path.as_ref().canonicalize().unwrap()
+}
+
+#[test]
+fn environment_is_read_like_git_does() {
+    let vars = |name: &str| -> Option<OsString> {
+        match name {
+            "GIT_DIR" => Some("repo.git".into()),
+            "GIT_WORK_TREE" => Some("".into()),
+            "GIT_ALTERNATE_OBJECT_DIRECTORIES" => Some(std::env::join_paths(["/a", "", "b"]).unwrap()),
+            "GIT_CEILING_DIRECTORIES" => Some(std::env::join_paths(["/ceiling", "relative"]).unwrap()),
+            _ => None,
+        }
+    };
+    assert_eq!(
+        Environment::from_vars(vars),
+        Environment {
+            git_dir: Some("repo.git".into()),
+            alternate_object_dirs: vec!["/a".into(), "b".into()],
+            ceiling_dirs: vec!["/ceiling".into()],
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn repositories_are_found_from_subdirectories_of_the_worktree() {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "--quiet"]);
+    let sub = dir.path().join("a/b");
+    fs::create_dir_all(&sub).unwrap();
+
+    let paths = discover::discover(&sub, &Environment::default()).unwrap();
+    assert_eq!(paths.git_dir, canonical(dir.path().join(".git")));
+    assert_eq!(paths.common_dir, paths.git_dir);
+    assert_eq!(paths.work_tree, Some(canonical(dir.path())));
+    assert_eq!(paths.object_dir, paths.git_dir.join("objects"));
+    assert_eq!(paths.index_file, paths.git_dir.join("index"));
+    assert_eq!(paths.object_dirs().count(), 1);
+}
+
+#[test]
+fn bare_repositories_have_no_worktree() {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "--quiet", "--bare", "repo.git"]);
+    let paths = discover::discover(dir.path().join("repo.git/refs"), &Environment::default()).unwrap();
+    assert_eq!(paths.git_dir, canonical(dir.path().join("repo.git")));
+    assert_eq!(paths.work_tree, None);
+}
+
+#[test]
+fn ceiling_directories_stop_the_search() {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "--quiet"]);
+    let sub = dir.path().join("a/b");
+    fs::create_dir_all(&sub).unwrap();
+
+    let env = Environment {
+        ceiling_dirs: vec![dir.path().join("a")],
+        ..Default::default()
+    };
+    assert!(matches!(discover::discover(&sub, &env), Err(Error::NotFound(_))));
+    assert!(
+        discover::discover(dir.path().join("a"), &env).is_ok(),
+        "ceiling directories themselves are searched"
+    );
+}
+
+#[test]
+fn git_dir_and_overrides_take_precedence() {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "--quiet", "--bare", "repo.git"]);
+    let cwd = dir.path().join("elsewhere");
+    fs::create_dir(&cwd).unwrap();
+
+    let env = Environment {
+        git_dir: Some("../repo.git".into()),
+        ..Default::default()
+    };
+    let paths = discover::discover(&cwd, &env).unwrap();
+    assert_eq!(canonical(&paths.git_dir), canonical(dir.path().join("repo.git")));
+    assert_eq!(paths.work_tree, None, "core.bare is honored");
+
+    let env = Environment {
+        git_dir: Some(dir.path().join("repo.git")),
+        work_tree: Some("tree".into()),
+        object_dir: Some("objects".into()),
+        alternate_object_dirs: vec!["/alternate".into()],
+        index_file: Some("index".into()),
+        ceiling_dirs: Vec::new(),
+    };
+    let paths = discover::discover(&cwd, &env).unwrap();
+    assert_eq!(paths.work_tree, Some(cwd.join("tree")));
+    assert_eq!(paths.object_dir, cwd.join("objects"));
+    assert_eq!(
+        paths.object_dirs().collect::<Vec<_>>(),
+        vec![cwd.join("objects").as_path(), Path::new("/alternate")]
+    );
+    assert_eq!(paths.index_file, cwd.join("index"));
+
+    let env = Environment {
+        git_dir: Some(cwd.clone()),
+        ..Default::default()
+    };
+    assert!(matches!(discover::discover(&cwd, &env), Err(Error::NotAGitDir(_))));
+}
+
+#[test]
+fn git_dir_without_bare_configuration_uses_the_current_directory_as_worktree() {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "--quiet", "repo"]);
+    let paths = discover::open(dir.path().join("repo/.git"), dir.path(), &Environment::default()).unwrap();
+    assert_eq!(paths.work_tree, Some(dir.path().to_owned()));
+}
+
+#[test]
+fn linked_worktrees_are_found_through_their_dot_git_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let main = dir.path().join("main");
+    fs::create_dir(&main).unwrap();
+    git(&main, &["init", "--quiet"]);
+    git(
+        &main,
+        &[
+            "-c",
+            "user.name=a",
+            "-c",
+            "user.email=a@example.com",
+            "commit",
+            "--quiet",
+            "--allow-empty",
+            "-m",
+            "initial",
+        ],
+    );
+    git(&main, &["worktree", "add", "--quiet", "../linked"]);
+
+    let paths = discover::discover(dir.path().join("linked"), &Environment::default()).unwrap();
+    assert_eq!(canonical(&paths.git_dir), canonical(main.join(".git/worktrees/linked")));
+    assert_eq!(canonical(&paths.common_dir), canonical(main.join(".git")));
+    assert_eq!(canonical(&paths.object_dir), canonical(main.join(".git/objects")));
+    assert_eq!(paths.index_file, paths.git_dir.join("index"));
+    assert_eq!(paths.work_tree, Some(canonical(dir.path().join("linked"))));
+}
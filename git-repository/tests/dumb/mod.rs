@@ -0,0 +1,157 @@
+use crate::{git, hex_to_id};
+use git_features::progress;
+use git_protocol::handshake::Ref;
+use git_repository::dumb;
+use std::{fs, io, path::Path};
+
+const MAIN: &str = "877c3ad4e85fa4408811d3fcc38aef2500f04ade";
+const OTHER: &str = "cecbafcde689e1bc457bf13334c3d934200802a1";
+const TAG: &str = "17934c4602e90c793a9660b02d49b6a678772104";
+
+fn refs() -> Vec<Ref> {
+    vec![
+        Ref::Symbolic {
+            path: "HEAD".into(),
+            target: "refs/heads/main".into(),
+            object: hex_to_id(MAIN),
+        },
+        Ref::Direct {
+            path: "refs/heads/main".into(),
+            object: hex_to_id(MAIN),
+        },
+        Ref::Direct {
+            path: "refs/heads/other".into(),
+            object: hex_to_id(OTHER),
+        },
+        Ref::Peeled {
+            path: "refs/tags/v1.0".into(),
+            tag: hex_to_id(TAG),
+            object: hex_to_id(OTHER),
+        },
+    ]
+}
+
+/// A bare copy of the fixture repository which can be served dumbly.
+fn server(dir: &Path) -> std::path::PathBuf {
+    let fixture = Path::new("tests/fixtures/repos/files.git").canonicalize().unwrap();
+    git(
+        dir,
+        &[
+            "clone",
+            "--quiet",
+            "--bare",
+            "--no-local",
+            fixture.to_str().unwrap(),
+            "server.git",
+        ],
+    );
+    let server = dir.join("server.git");
+    dumb::update_server_info(&server, &refs()).unwrap();
+    server
+}
+
+fn remote(git_dir: &Path) -> impl FnMut(&str) -> io::Result<Option<Vec<u8>>> + '_ {
+    move |path| match fs::read(git_dir.join(path)) {
+        Ok(data) => Ok(Some(data)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[test]
+fn server_info_is_the_same_as_the_one_written_by_git() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = server(dir.path());
+    let ours = (
+        fs::read(server.join("info/refs")).unwrap(),
+        fs::read(server.join("objects/info/packs")).unwrap(),
+    );
+    git(&server, &["update-server-info"]);
+    assert_eq!(
+        ours,
+        (
+            fs::read(server.join("info/refs")).unwrap(),
+            fs::read(server.join("objects/info/packs")).unwrap(),
+        )
+    );
+}
+
+#[test]
+fn refs_are_read_from_server_info_and_head() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = server(dir.path());
+    assert_eq!(dumb::refs(&mut remote(&server)).unwrap(), refs());
+    assert!(matches!(
+        dumb::refs(&mut remote(dir.path())),
+        Err(dumb::Error::MissingRefs)
+    ));
+}
+
+#[test]
+fn fetch_receives_loose_objects_and_packs_and_stops_at_objects_we_have() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = server(dir.path());
+    let tree = git(&server, &["rev-parse", "main^{tree}"]);
+    let loose = git(
+        &server,
+        &[
+            "-c",
+            "user.name=Committer",
+            "-c",
+            "user.email=committer@example.com",
+            "commit-tree",
+            tree.trim_end(),
+            "-p",
+            MAIN,
+            "-m",
+            "loose",
+        ],
+    );
+    let loose = hex_to_id(loose.trim_end());
+
+    let git_dir = git_repository::init::repository_at(dir.path().join("client")).unwrap();
+    let objects_dir = git_dir.join("objects");
+    let outcome = dumb::fetch(
+        &mut remote(&server),
+        Some(hex_to_id(OTHER)),
+        &objects_dir,
+        progress::Discard,
+    )
+    .unwrap();
+    assert_eq!(outcome.loose_objects, 0);
+    assert_eq!(outcome.packs.len(), 1, "the only pack contains all other objects");
+
+    let outcome = dumb::fetch(
+        &mut remote(&server),
+        vec![loose, hex_to_id(TAG)],
+        &objects_dir,
+        progress::Discard,
+    )
+    .unwrap();
+    assert_eq!(
+        outcome,
+        dumb::Outcome {
+            loose_objects: 1,
+            packs: Vec::new(),
+        },
+        "all objects but the new commit are in the pack we have already"
+    );
+    let listed = git(&git_dir, &["rev-list", "--objects", &loose.to_string(), TAG]);
+    assert_eq!(
+        listed.lines().count(),
+        13,
+        "the new commit, the objects of both branches and the tag are present"
+    );
+}
+
+#[test]
+fn fetch_fails_if_an_object_is_nowhere_to_be_found() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = server(dir.path());
+    fs::write(server.join("objects/info/packs"), "\n").unwrap();
+    let git_dir = git_repository::init::repository_at(dir.path().join("client")).unwrap();
+    assert!(matches!(
+        dumb::fetch(&mut remote(&server), Some(hex_to_id(MAIN)), git_dir.join("objects"), progress::Discard),
+        Err(dumb::Error::ObjectMissing(missing)) if missing == hex_to_id(MAIN)
+    ));
+}
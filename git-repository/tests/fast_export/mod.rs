@@ -0,0 +1,106 @@
+use crate::{git, hex_to_id};
+use git_object::{borrowed, bstr::BString, owned};
+use git_odb::{compound, pack};
+use git_repository::fast_export;
+use std::{
+    fs,
+    os::unix::fs::{symlink, PermissionsExt},
+    path::Path,
+};
+
+fn rev(dir: &Path, rev: &str) -> owned::Id {
+    hex_to_id(&git(dir, &["rev-parse", rev]))
+}
+
+fn find(dir: &Path) -> impl for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>> {
+    let db = compound::Db::at(dir.join(".git/objects")).unwrap();
+    move |id, buf| {
+        let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+        borrowed::Object::from_bytes(object.kind, object.data).ok()
+    }
+}
+
+/// A repository with a merge, special files and paths, deletions and tags.
+fn repository() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path();
+    git(path, &["init", "--quiet", "--initial-branch=main"]);
+    fs::create_dir(path.join("dir")).unwrap();
+    fs::write(path.join("dir/file"), "1\n").unwrap();
+    fs::write(path.join("a file with spaces"), "spaces\n").unwrap();
+    fs::write(path.join("tab\tand \"quote\""), "quoted\n").unwrap();
+    git(path, &["add", "."]);
+    git(path, &["commit", "--quiet", "-m", "first"]);
+
+    git(path, &["checkout", "--quiet", "-b", "side"]);
+    fs::write(path.join("script"), "#!/bin/sh\n").unwrap();
+    fs::set_permissions(path.join("script"), fs::Permissions::from_mode(0o755)).unwrap();
+    git(path, &["add", "script"]);
+    symlink("dir/file", path.join("link")).unwrap();
+    git(path, &["add", "link"]);
+    git(path, &["commit", "--quiet", "-m", "side"]);
+
+    git(path, &["checkout", "--quiet", "main"]);
+    fs::write(path.join("dir/file"), "2\n").unwrap();
+    git(path, &["rm", "--quiet", "a file with spaces"]);
+    git(path, &["commit", "--quiet", "-am", "second\n\nwith a body"]);
+    git(path, &["merge", "--quiet", "--no-ff", "-m", "merge", "side"]);
+    git(path, &["tag", "-a", "-m", "annotated", "v1"]);
+    fs::write(path.join("dir/nested"), "nested\n").unwrap();
+    git(path, &["add", "."]);
+    git(path, &["commit", "--quiet", "-m", "third"]);
+    git(path, &["tag", "lightweight"]);
+    git(path, &["branch", "same-as-main"]);
+    dir
+}
+
+fn export(dir: &Path, refs: &[&str], excluded: &[&str]) -> (String, usize) {
+    let mut out = Vec::new();
+    let marks = fast_export::write(
+        refs.iter().map(|name| (BString::from(*name), rev(dir, name))),
+        excluded.iter().map(|name| rev(dir, name)),
+        find(dir),
+        &mut out,
+    )
+    .unwrap();
+    (String::from_utf8(out).unwrap(), marks.len())
+}
+
+#[test]
+fn the_stream_is_the_same_as_the_one_git_writes() {
+    let dir = repository();
+    let dir = dir.path();
+    let refs = [
+        "refs/heads/main",
+        "refs/heads/side",
+        "refs/heads/same-as-main",
+        "refs/tags/v1",
+        "refs/tags/lightweight",
+    ];
+    let (stream, marks) = export(dir, &refs, &[]);
+    let mut args = vec!["fast-export", "--reencode=no", "--signed-tags=verbatim"];
+    args.extend(&refs);
+    assert_eq!(stream, git(dir, &args));
+    assert_eq!(marks, stream.matches("\nmark :").count());
+}
+
+#[test]
+fn excluded_commits_are_left_out_and_their_children_contain_all_files() {
+    let dir = repository();
+    let dir = dir.path();
+    let (stream, _) = export(dir, &["refs/heads/main"], &["refs/heads/side"]);
+    assert_eq!(
+        stream,
+        git(
+            dir,
+            &[
+                "fast-export",
+                "--reencode=no",
+                "--signed-tags=verbatim",
+                "refs/heads/main",
+                "^refs/heads/side"
+            ]
+        )
+    );
+    assert!(!stream.contains("\nreset "), "no commit is a root");
+}
@@ -0,0 +1,209 @@
+use crate::{git, git_with_input, hex_to_id};
+use git_object::{borrowed, owned, Sign, Time};
+use git_odb::{compound, pack};
+use git_repository::{
+    fast_import::{self, Error, Options},
+    remote::update::Mode,
+};
+use std::path::Path;
+
+fn rev(dir: &Path, rev: &str) -> owned::Id {
+    hex_to_id(&git(dir, &["rev-parse", rev]))
+}
+
+fn find(dir: &Path) -> impl for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>> {
+    let db = compound::Db::at(dir.join(".git/objects")).unwrap();
+    move |id, buf| {
+        let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+        borrowed::Object::from_bytes(object.kind, object.data).ok()
+    }
+}
+
+fn init() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "--quiet", "--initial-branch=main"]);
+    dir
+}
+
+fn import(dir: &Path, stream: &[u8]) -> Result<fast_import::Outcome, Error> {
+    let options = Options {
+        committer: owned::Signature {
+            name: "a".into(),
+            email: "a@example.com".into(),
+            time: Time {
+                time: 1_600_000_000,
+                offset: 0,
+                sign: Sign::Plus,
+            },
+        },
+        force: false,
+    };
+    fast_import::import(dir.join(".git"), stream, find(dir), options)
+}
+
+const STREAM: &str = r#"feature done
+# a comment
+blob
+mark :1
+data 6
+hello
+
+commit refs/heads/main
+mark :2
+author Author <author@example.com> 1500000000 +0200
+committer Committer <committer@example.com> 1600000000 +0000
+data <<END
+first
+
+with a body
+END
+M 100644 :1 "dir/with \"quotes\"\tand a tab"
+M 100755 inline dir/script
+data 10
+#!/bin/sh
+M 120000 inline link
+data 6
+target
+M 100644 :1 other
+
+commit refs/heads/main
+mark :3
+committer Committer <committer@example.com> 1600000100 +0000
+data 6
+second
+C dir copied
+R other "renamed file"
+D link
+
+reset refs/heads/side
+from :2
+
+commit refs/heads/side
+mark :4
+committer Committer <committer@example.com> 1600000200 +0000
+encoding ISO-8859-1
+data 4
+side
+deleteall
+M 644 :1 only
+M 100644 :1 only/replaced-by-a-directory
+
+commit refs/heads/main
+mark :5
+committer Committer <committer@example.com> 1600000300 +0000
+data 5
+merge
+merge :4
+M 160000 1111111111111111111111111111111111111111 submodule
+
+reset refs/heads/orphan
+
+commit refs/heads/orphan
+committer Committer <committer@example.com> 1600000400 +0000
+data 6
+orphan
+from 0000000000000000000000000000000000000000
+M 644 inline "only"
+data 0
+
+tag v1
+from :5
+tagger Tagger <tagger@example.com> 1600000500 +0000
+data 10
+annotated
+
+progress almost done
+checkpoint
+done
+"#;
+
+#[test]
+fn the_objects_and_refs_are_the_same_git_writes() {
+    let (ours, theirs) = (init(), init());
+    let (ours, theirs) = (ours.path(), theirs.path());
+    git_with_input(theirs, &["fast-import", "--quiet"], STREAM.as_bytes());
+
+    let outcome = import(ours, STREAM.as_bytes()).unwrap();
+    for name in &[
+        "refs/heads/main",
+        "refs/heads/side",
+        "refs/heads/orphan",
+        "refs/tags/v1",
+    ] {
+        assert_eq!(rev(ours, name), rev(theirs, name), "{}", name);
+    }
+    assert_eq!(
+        outcome
+            .updates
+            .iter()
+            .map(|update| (update.name.to_string(), update.mode))
+            .collect::<Vec<_>>(),
+        vec![
+            ("refs/heads/main".into(), Mode::New),
+            ("refs/heads/orphan".into(), Mode::New),
+            ("refs/heads/side".into(), Mode::New),
+            ("refs/tags/v1".into(), Mode::New),
+        ]
+    );
+    assert_eq!(outcome.marks.len(), 5);
+    assert_eq!(outcome.marks[&5], rev(theirs, "refs/heads/main"));
+}
+
+#[test]
+fn streams_written_by_git_fast_export_are_imported_into_the_same_commits() {
+    let (source, ours) = (init(), init());
+    let (source, ours) = (source.path(), ours.path());
+    for n in 0..3 {
+        std::fs::write(source.join("file"), format!("{}\n", n)).unwrap();
+        git(source, &["add", "file"]);
+        git(source, &["commit", "--quiet", "-m", &format!("commit {}", n)]);
+    }
+    git(source, &["tag", "-a", "-m", "tag", "v1", "HEAD~1"]);
+    let stream = git(source, &["fast-export", "--all"]);
+    import(ours, stream.as_bytes()).unwrap();
+    for name in &["refs/heads/main", "refs/tags/v1"] {
+        assert_eq!(rev(ours, name), rev(source, name), "{}", name);
+    }
+}
+
+#[test]
+fn branches_are_only_moved_forward_unless_forced() {
+    let dir = init();
+    let dir = dir.path();
+    let commit = |message: &str, from: &str| {
+        format!(
+            "commit refs/heads/main\ncommitter C <c@example.com> 1600000000 +0000\ndata {}\n{}\n{}",
+            message.len(),
+            message,
+            from
+        )
+    };
+    let first = import(dir, commit("first", "").as_bytes()).unwrap();
+    assert_eq!(first.updates[0].mode, Mode::New);
+    let root = import(dir, commit("new root", "").as_bytes()).unwrap();
+    assert_eq!(
+        root.updates[0].mode,
+        Mode::Rejected,
+        "commits of unknown branches don't have a parent"
+    );
+    let second = import(dir, commit("second", "from refs/heads/main\n").as_bytes()).unwrap();
+    assert_eq!(second.updates[0].mode, Mode::FastForward);
+    assert_eq!(second.updates[0].previous, Some(first.updates[0].new));
+
+    let unrelated = commit("unrelated", "from 0000000000000000000000000000000000000000\n");
+    let rejected = import(dir, unrelated.as_bytes()).unwrap();
+    assert_eq!(rejected.updates[0].mode, Mode::Rejected);
+    assert_eq!(rev(dir, "main"), second.updates[0].new);
+    let forced = import(dir, format!("feature force\n{}", unrelated).as_bytes()).unwrap();
+    assert_eq!(forced.updates[0].mode, Mode::Forced);
+    assert_eq!(rev(dir, "main"), forced.updates[0].new);
+
+    assert!(matches!(
+        import(
+            dir,
+            b"commit refs/heads/x\ncommitter C <c@example.com> 1 +0000\ndata 0\nfrom :1\n"
+        ),
+        Err(Error::UnknownMark(1))
+    ));
+    assert!(matches!(import(dir, b"ls \"path\"\n"), Err(Error::Unsupported(1, _))));
+}
@@ -0,0 +1,230 @@
+use crate::{git_command, git_with_input, hex_to_id, run};
+use git_object::{borrowed, owned};
+use git_odb::{compound, pack};
+use git_repository::format_patch::{self, Options};
+use std::{
+    fs,
+    os::unix::fs::{symlink, PermissionsExt},
+    path::Path,
+};
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = run(git_command(dir).args(args).env("GIT_AUTHOR_DATE", "1600000000 +0230"));
+    String::from_utf8(output).unwrap()
+}
+
+fn commit(dir: &Path, message: &str) {
+    git(dir, &["add", "--all"]);
+    git(
+        dir,
+        &[
+            "commit",
+            "--quiet",
+            "--allow-empty",
+            "--cleanup=verbatim",
+            "-m",
+            message,
+        ],
+    );
+}
+
+fn revs(dir: &Path, range: &str) -> Vec<owned::Id> {
+    git(dir, &["rev-list", "--reverse", range])
+        .lines()
+        .map(hex_to_id)
+        .collect()
+}
+
+fn find(dir: &Path) -> impl for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>> {
+    let db = compound::Db::at(dir.join(".git/objects")).unwrap();
+    move |id, buf| {
+        let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+        borrowed::Object::from_bytes(object.kind, object.data).ok()
+    }
+}
+
+fn init() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "--quiet", "--initial-branch=main"]);
+    dir
+}
+
+fn format_patch(dir: &Path, range: &str, options: &Options) -> String {
+    let mut out = Vec::new();
+    format_patch::write(&revs(dir, range), find(dir), options, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn source_file() -> String {
+    let mut text = String::new();
+    for function in 0..4 {
+        text.push_str(&format!("fn function_{}() {{\n", function));
+        for line in 0..8 {
+            text.push_str(&format!("    let line_{} = {};\n", line, function));
+        }
+        text.push_str("}\n\n");
+    }
+    text
+}
+
+#[test]
+fn emails_are_the_same_git_writes() {
+    let dir = init();
+    let dir = dir.path();
+    fs::create_dir(dir.join("dir")).unwrap();
+    fs::write(dir.join("source.rs"), source_file()).unwrap();
+    fs::write(dir.join("dir/with space"), "a\nb\n").unwrap();
+    fs::write(dir.join("\"quoted\" ümlaut"), "x\n").unwrap();
+    fs::write(dir.join("empty"), "").unwrap();
+    fs::write(dir.join("script"), "#!/bin/sh\n").unwrap();
+    commit(dir, "initial commit\n");
+
+    let source = source_file()
+        .replace("let line_1 = 0;", "let line_1 = 10;")
+        .replace("let line_7 = 1;", "let line_7 = 11;\n    let added = 1;")
+        .replace("let line_5 = 3;\n", "");
+    fs::write(dir.join("source.rs"), source).unwrap();
+    fs::set_permissions(dir.join("script"), fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(dir.join("dir/with space"), "a\nb").unwrap();
+    commit(
+        dir,
+        "A subject which is so long that it has to be wrapped as it doesn't fit into a line of the email\nand continues here\n\n\nThe body with trailing whitespace   \n\n\tand indentation\n\n\n",
+    );
+
+    fs::remove_file(dir.join("empty")).unwrap();
+    fs::remove_file(dir.join("script")).unwrap();
+    symlink("target", dir.join("script")).unwrap();
+    fs::write(dir.join("\"quoted\" ümlaut"), "y\n").unwrap();
+    fs::write(dir.join("many"), "1\n".repeat(100)).unwrap();
+    commit(
+        dir,
+        "Ümlauts and =?encoded?= words in a long subject which has to be folded\n\nand a bödy\n",
+    );
+    git(
+        dir,
+        &[
+            "commit",
+            "--quiet",
+            "--amend",
+            "--no-edit",
+            "--author=Jörg <j@example.com>",
+        ],
+    );
+
+    commit(dir, "empty commit");
+    git(dir, &["checkout", "--quiet", "-b", "side"]);
+    fs::write(dir.join("dir/side"), "side\n").unwrap();
+    commit(dir, "no body");
+    git(
+        dir,
+        &[
+            "commit",
+            "--quiet",
+            "--amend",
+            "--no-edit",
+            "--author=A. U. \"Thor\" <thor@example.com>",
+        ],
+    );
+    git(dir, &["checkout", "--quiet", "main"]);
+    fs::write(dir.join("many"), "1\n".repeat(50)).unwrap();
+    commit(dir, "main");
+    git(dir, &["merge", "--quiet", "--no-ff", "-m", "merge", "side"]);
+
+    let options = Options {
+        signature: Some("signature".into()),
+        ..Default::default()
+    };
+    assert_eq!(
+        format_patch(dir, "HEAD", &options),
+        git(
+            dir,
+            &[
+                "format-patch",
+                "--stdout",
+                "--no-renames",
+                "--signature=signature",
+                "--root",
+                "HEAD"
+            ]
+        )
+    );
+}
+
+#[test]
+fn single_emails_are_only_numbered_if_requested() {
+    let dir = init();
+    let dir = dir.path();
+    fs::write(dir.join("source.rs"), source_file()).unwrap();
+    commit(dir, "first");
+    fs::write(
+        dir.join("source.rs"),
+        source_file().replace("line_4 = 2", "line_4 = 20"),
+    )
+    .unwrap();
+    commit(dir, "second");
+
+    let mut options = Options {
+        subject_prefix: "RFC PATCH".into(),
+        context_lines: 1,
+        ..Default::default()
+    };
+    let args = [
+        "format-patch",
+        "--stdout",
+        "--no-signature",
+        "--subject-prefix=RFC PATCH",
+        "-U1",
+        "-1",
+    ];
+    assert_eq!(format_patch(dir, "HEAD^!", &options), git(dir, &args));
+    options.numbered = Some(true);
+    assert_eq!(
+        format_patch(dir, "HEAD^!", &options),
+        git(dir, &[&args[..], &["-n"]].concat())
+    );
+    options.subject_prefix = "".into();
+    options.numbered = Some(false);
+    let args = [
+        "format-patch",
+        "--stdout",
+        "--no-signature",
+        "--subject-prefix=",
+        "-U1",
+        "-N",
+        "--root",
+        "HEAD",
+    ];
+    assert_eq!(format_patch(dir, "HEAD", &options), git(dir, &args));
+}
+
+#[test]
+fn binary_files_are_applied_by_git_am() {
+    let dir = init();
+    let dir = dir.path();
+    let mut state = 42u32;
+    let random: Vec<u8> = (0..5000)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 16) as u8
+        })
+        .collect();
+    fs::write(dir.join("text"), "text\n").unwrap();
+    commit(dir, "base");
+    fs::write(dir.join("binary"), &random).unwrap();
+    fs::write(dir.join("small"), b"\0\x01\x02").unwrap();
+    commit(dir, "add binaries");
+    let changed = [&random[..1000], b"changed", &random[1100..]].concat();
+    fs::write(dir.join("binary"), &changed).unwrap();
+    fs::write(dir.join("text"), b"now\0binary").unwrap();
+    fs::remove_file(dir.join("small")).unwrap();
+    commit(dir, "change binaries");
+
+    let options = Options::default();
+    let emails = format_patch(dir, "HEAD~2..", &options);
+    assert!(emails.contains("\ndelta "), "the change of the large file is a delta");
+    assert!(emails.contains(" binary | Bin 5000 -> 4907 bytes\n"));
+    let expected = git(dir, &["rev-parse", "HEAD^{tree}"]);
+    git(dir, &["checkout", "--quiet", "--detach", "HEAD~2"]);
+    git_with_input(dir, &["am", "--quiet"], emails.as_bytes());
+    assert_eq!(git(dir, &["rev-parse", "HEAD^{tree}"]), expected);
+}
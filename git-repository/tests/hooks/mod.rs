@@ -0,0 +1,128 @@
+use crate::{git, git_output, hex_to_id};
+use git_object::{owned, Sign, Time};
+use git_repository::{
+    commit::{self, Hooks},
+    hooks::Runner,
+};
+use std::{fs, path::Path};
+
+#[cfg(unix)]
+fn write_hook(directory: &Path, name: &str, script: &str) {
+    use std::os::unix::fs::PermissionsExt;
+    let path = directory.join(name);
+    fs::create_dir_all(directory).unwrap();
+    fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn hooks_are_found_in_the_common_dir_or_the_configured_hooks_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = dir.path().join("repo");
+    fs::create_dir(&repo).unwrap();
+    git(&repo, &["init", "--quiet"]);
+    let git_dir = repo.join(".git");
+
+    let runner = Runner::from_git_dir(&git_dir);
+    assert_eq!(runner.directory, Some(git_dir.join("hooks")));
+    assert_eq!(runner.working_dir, repo, "hooks run in the root of the worktree");
+    assert_eq!(runner.git_path("COMMIT_EDITMSG"), Path::new(".git/COMMIT_EDITMSG"));
+
+    git(&repo, &["config", "core.hooksPath", "custom-hooks"]);
+    assert_eq!(
+        Runner::from_git_dir(&git_dir).directory,
+        Some(repo.join("custom-hooks")),
+        "relative paths are relative to the worktree"
+    );
+    git(&repo, &["config", "core.hooksPath", "/somewhere/else"]);
+    assert_eq!(Runner::from_git_dir(&git_dir).directory, Some("/somewhere/else".into()));
+
+    let bare = dir.path().join("bare.git");
+    git(dir.path(), &["init", "--quiet", "--bare", "bare.git"]);
+    let runner = Runner::from_git_dir(&bare);
+    assert_eq!(runner.directory, Some(bare.join("hooks")));
+    assert_eq!(
+        runner.working_dir, bare,
+        "bare repositories run hooks in the git directory"
+    );
+    assert_eq!(runner.git_path("COMMIT_EDITMSG"), Path::new("COMMIT_EDITMSG"));
+}
+
+#[cfg(unix)]
+#[test]
+fn hooks_receive_the_same_arguments_as_with_git_and_can_be_disabled() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = dir.path().join("repo");
+    fs::create_dir(&repo).unwrap();
+    git(&repo, &["init", "--quiet"]);
+    git(&repo, &["config", "core.hooksPath", "custom-hooks"]);
+    git(&repo, &["commit", "--quiet", "--allow-empty", "-m", "initial"]);
+    write_hook(&repo.join("custom-hooks"), "commit-msg", "echo \"commit-msg $1\" >&2");
+
+    let output = git_output(&repo, &["commit", "--quiet", "--allow-empty", "-m", "by git"]);
+    assert!(output.status.success());
+    let by_git = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(by_git, "commit-msg .git/COMMIT_EDITMSG\n");
+
+    let git_dir = repo.join(".git");
+    let head = hex_to_id(&git(&repo, &["rev-parse", "HEAD"]));
+    let tree = hex_to_id(&git(&repo, &["rev-parse", "HEAD^{tree}"]));
+    let signature = owned::Signature {
+        name: "Bot".into(),
+        email: "bot@example.com".into(),
+        time: Time {
+            time: 1_600_000_000,
+            offset: 0,
+            sign: Sign::Plus,
+        },
+    };
+    let create = |parent: owned::Id, runner: Runner| {
+        commit::create(
+            &git_dir,
+            tree,
+            vec![parent],
+            signature.clone(),
+            signature.clone(),
+            "by us\n",
+            commit::Options {
+                hooks: Hooks::from_runner(runner),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    };
+    let outcome = create(head, Runner::from_git_dir(&git_dir));
+    assert_eq!(outcome.messages, by_git);
+
+    let runner = Runner::disabled();
+    assert_eq!(runner.find("commit-msg"), None);
+    assert_eq!(runner.run("commit-msg", &[], None, &mut Vec::new()), None);
+    assert_eq!(create(outcome.id, runner).messages, "", "no hook is run");
+}
+
+#[cfg(unix)]
+#[test]
+fn hooks_run_in_the_worktree_with_the_git_dir_set() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = dir.path().join("repo");
+    fs::create_dir(&repo).unwrap();
+    git(&repo, &["init", "--quiet"]);
+    let git_dir = repo.join(".git");
+    write_hook(
+        &git_dir.join("hooks"),
+        "post-receive",
+        "echo \"$GIT_DIR $*\"; pwd; cat; exit 3",
+    );
+
+    let runner = Runner::from_git_dir(&git_dir);
+    let mut messages = Vec::new();
+    let args = ["a".to_string(), "b".into()];
+    assert_eq!(
+        runner.run("post-receive", &args, Some(b"input\n"), &mut messages),
+        Some(false)
+    );
+    assert_eq!(
+        String::from_utf8(messages).unwrap(),
+        format!(".git a b\n{}\ninput\n", repo.canonicalize().unwrap().display())
+    );
+}
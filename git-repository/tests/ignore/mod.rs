@@ -0,0 +1,97 @@
+use crate::{git, git_with_input};
+use git_object::bstr::ByteSlice;
+use git_repository::ignore::{self, Pattern, Stack};
+use std::{fs, path::Path};
+
+fn write(dir: &Path, path: &str, content: &str) {
+    let path = dir.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+#[test]
+fn patterns_are_parsed_like_git_does() {
+    let pattern = |text: &str, negative, directory_only| Pattern {
+        text: text.into(),
+        negative,
+        directory_only,
+    };
+    assert_eq!(
+        ignore::parse(b"# comment\n\n*.o\r\n!keep.o\nbuild/\n/anchored\n\\#literal\ntrailing  \nescaped\\ \n")
+            .collect::<Vec<_>>(),
+        vec![
+            pattern("*.o", false, false),
+            pattern("keep.o", true, false),
+            pattern("build", false, true),
+            pattern("/anchored", false, false),
+            pattern("\\#literal", false, false),
+            pattern("trailing", false, false),
+            pattern("escaped\\ ", false, false),
+        ]
+    );
+}
+
+#[test]
+fn paths_are_ignored_like_git_does() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("repo");
+    fs::create_dir(&root).unwrap();
+    git(&root, &["init", "--quiet"]);
+    write(dir.path(), "global-excludes", "*.global\nexcluded-twice\n");
+    git(
+        &root,
+        &[
+            "config",
+            "core.excludesFile",
+            dir.path().join("global-excludes").to_str().unwrap(),
+        ],
+    );
+    write(&root, ".git/info/exclude", "*.exclude\n!excluded-twice\n");
+    write(
+        &root,
+        ".gitignore",
+        "*.o\n!keep.o\n/build/\ndocs/*.html\nlogs/\nspaced\\ \n**/deep/**/x\n[ab].class\n",
+    );
+    write(&root, "sub/.gitignore", "!*.o\nlocal\n/anchored\nlogs/keep\n");
+
+    let paths = [
+        "x.o",
+        "keep.o",
+        "sub/x.o",
+        "build/out",
+        "sub/build/out",
+        "docs/index.html",
+        "docs/guide/index.html",
+        "logs/file",
+        "sub/logs/keep",
+        "spaced ",
+        "a/deep/b/c/x",
+        "deep/x",
+        "a.class",
+        "c.class",
+        "sub/local",
+        "local",
+        "sub/anchored",
+        "sub/other/anchored",
+        "file.global",
+        "file.exclude",
+        "excluded-twice",
+    ];
+    for path in &paths {
+        write(&root, path, "");
+    }
+    let output = git_with_input(&root, &["check-ignore", "--stdin"], paths.join("\n").as_bytes());
+    let expected: Vec<&str> = output.to_str().unwrap().lines().collect();
+
+    let mut stack = Stack::from_git_dir(root.join(".git"));
+    stack.add("".into(), &fs::read(root.join(".gitignore")).unwrap());
+    stack.add("sub/".into(), &fs::read(root.join("sub/.gitignore")).unwrap());
+    let actual: Vec<&str> = paths
+        .iter()
+        .copied()
+        .filter(|path| stack.is_ignored(path.as_bytes().as_bstr(), false))
+        .collect();
+    assert_eq!(actual, expected);
+    assert!(stack.is_ignored("sub/logs".into(), true));
+    assert!(!stack.is_ignored("sub/logs".into(), false), "only directories match");
+}
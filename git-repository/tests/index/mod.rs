@@ -0,0 +1,234 @@
+use crate::{git, git_output};
+use git_object::{bstr::ByteSlice, TreeMode};
+use git_repository::index::{self, Error, File, Stat};
+use std::{fs, path::Path};
+
+fn repository(dir: &Path) {
+    git(dir, &["init", "--quiet"]);
+    for (path, content) in &[
+        ("README", "readme"),
+        ("a/file", "a"),
+        ("a/b/long-file-name", "b"),
+        ("a/b/long-file-name-2", "b2"),
+        ("c", "c"),
+    ] {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("README", dir.join("link")).unwrap();
+    git(dir, &["add", "."]);
+    git(dir, &["update-index", "--chmod=+x", "c"]);
+}
+
+/// Format the entries like `git ls-files --stage` does.
+fn ls_files(file: &File) -> String {
+    file.entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "{:06o} {} {}\t{}\n",
+                entry.mode as u16 as u32, entry.id, entry.stage, entry.path
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn entries_are_read_in_all_supported_versions() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    let expected = git(dir.path(), &["ls-files", "--stage"]);
+    // Version 3 is only written if extended flags are needed, so stays at version 2 here.
+    for version in &["2", "4"] {
+        git(dir.path(), &["update-index", "--index-version", version]);
+        let file = File::at(dir.path().join(".git/index")).unwrap().expect("index exists");
+        assert_eq!(file.version.to_string(), *version);
+        assert_eq!(ls_files(&file), expected, "version {}", version);
+        assert!(file.mtime.is_some(), "the time of the index file is known");
+    }
+}
+
+#[test]
+fn modes_and_extended_flags_are_read() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    fs::write(dir.path().join("new"), "new").unwrap();
+    git(dir.path(), &["add", "--intent-to-add", "new"]);
+    git(dir.path(), &["update-index", "--skip-worktree", "a/file"]);
+    git(dir.path(), &["update-index", "--assume-unchanged", "README"]);
+
+    let file = File::at(dir.path().join(".git/index")).unwrap().expect("index exists");
+    assert_eq!(file.version, 3, "extended flags need version 3");
+    let entry = |path: &str| {
+        file.entries
+            .iter()
+            .find(|entry| entry.path == path.as_bytes().as_bstr())
+            .unwrap()
+    };
+    assert!(entry("new").intent_to_add);
+    assert!(entry("a/file").skip_worktree);
+    assert!(entry("README").assume_valid);
+    assert!(!entry("c").intent_to_add && !entry("c").skip_worktree && !entry("c").assume_valid);
+    assert_eq!(entry("c").mode, TreeMode::BlobExecutable);
+    assert_eq!(entry("a/file").mode, TreeMode::Blob);
+    #[cfg(unix)]
+    assert_eq!(entry("link").mode, TreeMode::Link);
+}
+
+#[test]
+fn written_indices_are_read_by_git() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    let index_path = dir.path().join(".git/index");
+    for version in &["2", "4"] {
+        git(dir.path(), &["update-index", "--index-version", version]);
+        let expected = git(dir.path(), &["ls-files", "--stage"]);
+        let changed_files = git(dir.path(), &["diff-files", "--name-only"]);
+        let file = File::at(&index_path).unwrap().expect("index exists");
+        let mut data = Vec::new();
+        file.write_to(&mut data).unwrap();
+        fs::write(&index_path, &data).unwrap();
+
+        let written = File::from_bytes(&data).unwrap();
+        assert_eq!(written.version, file.version, "the version is kept");
+        assert_eq!(written.entries, file.entries);
+        assert_eq!(
+            git(dir.path(), &["ls-files", "--stage"]),
+            expected,
+            "version {}",
+            version
+        );
+        assert_eq!(
+            git(dir.path(), &["diff-files", "--name-only"]),
+            changed_files,
+            "the metadata is kept, so files remain unchanged"
+        );
+    }
+
+    fs::write(dir.path().join("new"), "new").unwrap();
+    git(dir.path(), &["update-index", "--index-version", "2"]);
+    git(dir.path(), &["add", "--intent-to-add", "new"]);
+    git(dir.path(), &["update-index", "--skip-worktree", "a/file"]);
+    git(dir.path(), &["update-index", "--assume-unchanged", "README"]);
+    let expected = git(dir.path(), &["ls-files", "--stage", "-v"]);
+    let mut file = File::at(&index_path).unwrap().expect("index exists");
+    file.version = 2;
+    let mut data = Vec::new();
+    file.write_to(&mut data).unwrap();
+    fs::write(&index_path, &data).unwrap();
+    assert_eq!(
+        File::from_bytes(&data).unwrap().version,
+        3,
+        "extended flags need version 3"
+    );
+    assert_eq!(git(dir.path(), &["ls-files", "--stage", "-v"]), expected);
+}
+
+#[test]
+fn conflicts_have_one_entry_per_stage() {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "--quiet", "--initial-branch=main"]);
+    fs::write(dir.path().join("file"), "base").unwrap();
+    git(dir.path(), &["add", "file"]);
+    git(dir.path(), &["commit", "--quiet", "-m", "base"]);
+    git(dir.path(), &["checkout", "--quiet", "-b", "other"]);
+    fs::write(dir.path().join("file"), "theirs").unwrap();
+    git(dir.path(), &["commit", "--quiet", "-am", "theirs"]);
+    git(dir.path(), &["checkout", "--quiet", "main"]);
+    fs::write(dir.path().join("file"), "ours").unwrap();
+    git(dir.path(), &["commit", "--quiet", "-am", "ours"]);
+    let status = git_output(dir.path(), &["merge", "other"]);
+    assert!(!status.status.success(), "the merge conflicts");
+
+    let file = File::at(dir.path().join(".git/index")).unwrap().expect("index exists");
+    assert_eq!(file.entries.iter().map(|e| e.stage).collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert_eq!(ls_files(&file), git(dir.path(), &["ls-files", "--stage"]));
+}
+
+#[test]
+fn the_recorded_metadata_matches_the_one_of_unchanged_files() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    let file = File::at(dir.path().join(".git/index")).unwrap().expect("index exists");
+    for entry in file.entries.iter().filter(|entry| entry.mode != TreeMode::Link) {
+        let metadata = fs::symlink_metadata(dir.path().join(entry.path.to_str().unwrap())).unwrap();
+        assert!(Stat::from_metadata(&metadata).matches(&entry.stat), "{}", entry.path);
+    }
+}
+
+#[test]
+fn a_missing_index_is_no_error() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(File::at(dir.path().join("index")).unwrap().is_none());
+}
+
+#[test]
+fn corrupt_indices_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    let mut data = fs::read(dir.path().join(".git/index")).unwrap();
+    data[20] ^= 1;
+    assert!(matches!(File::from_bytes(&data), Err(Error::Checksum)));
+    assert!(matches!(File::from_bytes(b"DIRC"), Err(Error::Corrupt(_))));
+
+    let mut unsupported = b"DIRC\0\0\0\x05\0\0\0\0".to_vec();
+    unsupported.extend_from_slice(&git_features::hash::sha1(&unsupported));
+    assert!(matches!(
+        File::from_bytes(&unsupported),
+        Err(Error::UnsupportedVersion(5))
+    ));
+}
+
+#[test]
+fn the_amount_of_entries_is_not_trusted() {
+    let mut data = b"DIRC\0\0\0\x02\xff\xff\xff\xff".to_vec();
+    data.extend_from_slice(&[0; 72]);
+    data.extend_from_slice(&git_features::hash::sha1(&data));
+    assert!(matches!(File::from_bytes(&data), Err(Error::Corrupt(_))));
+}
+
+#[test]
+fn optional_extensions_are_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    git(dir.path(), &["config", "core.untrackedCache", "true"]);
+    git(dir.path(), &["update-index", "--untracked-cache"]);
+    git(dir.path(), &["status", "--porcelain"]);
+    let data = fs::read(dir.path().join(".git/index")).unwrap();
+    assert!(data.windows(4).any(|w| w == b"UNTR"), "the untracked cache was written");
+    let file = File::from_bytes(&data).unwrap();
+    assert_eq!(ls_files(&file), git(dir.path(), &["ls-files", "--stage"]));
+}
+
+#[test]
+fn extensions_changing_the_meaning_of_entries_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    git(dir.path(), &["update-index", "--split-index"]);
+    assert!(matches!(File::at(dir.path().join(".git/index")), Err(Error::Split)));
+
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    git(dir.path(), &["commit", "--quiet", "-m", "initial"]);
+    git(
+        dir.path(),
+        &["sparse-checkout", "set", "--cone", "--sparse-index", "a/b"],
+    );
+    assert!(matches!(File::at(dir.path().join(".git/index")), Err(Error::Sparse)));
+
+    let mut data = b"DIRC\0\0\0\x02\0\0\0\0abcd\0\0\0\0".to_vec();
+    data.extend_from_slice(&git_features::hash::sha1(&data));
+    assert!(matches!(File::from_bytes(&data), Err(Error::UnknownExtension(signature)) if signature == "abcd"));
+}
+
+#[test]
+fn indices_without_checksum_are_accepted() {
+    let mut data = b"DIRC\0\0\0\x02\0\0\0\0".to_vec();
+    data.extend_from_slice(&[0; 20]);
+    let file = index::File::from_bytes(&data).unwrap();
+    assert_eq!(file.version, 2);
+    assert!(file.entries.is_empty());
+    assert_eq!(file.mtime, None);
+}
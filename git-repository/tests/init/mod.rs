@@ -0,0 +1,73 @@
+use git_repository::init::{self, Error, Options};
+use std::fs;
+
+#[test]
+fn bare_repositories_use_the_directory_itself_and_the_initial_branch() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("bare.git");
+    let git_dir = init::repository_with_options(
+        &target,
+        Options {
+            bare: true,
+            initial_branch: Some("trunk".into()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(git_dir, target);
+    assert_eq!(
+        fs::read_to_string(git_dir.join("HEAD")).unwrap(),
+        "ref: refs/heads/trunk\n"
+    );
+    assert!(fs::read_to_string(git_dir.join("config"))
+        .unwrap()
+        .contains("\tbare = true\n"));
+    assert!(git_dir.join("objects").join("pack").is_dir());
+    assert!(!git_dir.join(".git").exists());
+
+    assert!(
+        matches!(
+            init::repository_with_options(
+                &target,
+                Options {
+                    bare: true,
+                    ..Default::default()
+                }
+            ),
+            Err(Error::DirectoryNotEmpty(_))
+        ),
+        "bare repositories aren't initialized twice"
+    );
+}
+
+#[test]
+fn invalid_targets_and_branch_names_are_refused_before_writing_anything() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("file");
+    fs::write(&file, b"").unwrap();
+    assert!(matches!(
+        init::repository_at(&file),
+        Err(Error::NotADirectory(path)) if path == file
+    ));
+
+    let target = dir.path().join("repo");
+    assert!(matches!(
+        init::repository_with_options(
+            &target,
+            Options {
+                initial_branch: Some("a..b".into()),
+                ..Default::default()
+            }
+        ),
+        Err(Error::InvalidBranchName(name, _)) if name == "a..b"
+    ));
+    assert!(!target.exists());
+
+    let git_dir = init::repository_at(&target).unwrap();
+    assert_eq!(git_dir, target.join(".git"));
+    assert_eq!(
+        fs::read_to_string(git_dir.join("HEAD")).unwrap(),
+        "ref: refs/heads/main\n"
+    );
+    assert!(matches!(init::repository_at(&target), Err(Error::DirectoryExists(_))));
+}
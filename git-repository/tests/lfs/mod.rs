@@ -0,0 +1,128 @@
+use crate::git;
+use git_features::progress;
+use git_repository::{clone, lfs};
+use std::{fs, path::Path};
+
+const OID: &str = "sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393";
+
+fn pointer(size: u64) -> String {
+    format!(
+        "version https://git-lfs.github.com/spec/v1\noid {}\nsize {}\n",
+        OID, size
+    )
+}
+
+#[test]
+fn pointers_are_recognized() {
+    let parsed = lfs::Pointer::from_bytes(pointer(12345).as_bytes()).expect("valid pointer");
+    assert_eq!(
+        parsed,
+        lfs::Pointer {
+            oid: OID.into(),
+            size: 12345
+        }
+    );
+    assert_eq!(parsed.to_bstring(), pointer(12345));
+
+    let with_extension = format!(
+        "version https://git-lfs.github.com/spec/v1\next-0-foo sha256:{}\noid {}\nsize 1\n",
+        "0".repeat(64),
+        OID
+    );
+    assert!(lfs::is_pointer(with_extension.as_bytes()), "unknown keys are skipped");
+
+    for invalid in &[
+        "".to_string(),
+        pointer(1).trim_end().into(),
+        pointer(1).replace("version https://git-lfs.github.com/spec/v1\n", ""),
+        format!("oid {}\nversion https://git-lfs.github.com/spec/v1\nsize 1\n", OID),
+        format!("version https://git-lfs.github.com/spec/v1\nsize 1\noid {}\n", OID),
+        pointer(1).replace("sha256:", "sha1:"),
+        pointer(1).replace("4d7a", "4D7A"),
+        pointer(1).replace("size 1", "size -1"),
+        format!("{}{}", pointer(1), "x".repeat(1024)),
+        "hello\n".into(),
+    ] {
+        assert!(!lfs::is_pointer(invalid.as_bytes()), "{:?}", invalid);
+    }
+}
+
+fn remote(dir: &Path) -> String {
+    fs::create_dir(dir).unwrap();
+    git(dir, &["init", "--quiet"]);
+    for (path, content) in &[
+        (
+            ".gitattributes",
+            "*.bin filter=lfs diff=lfs merge=lfs -text\n".to_string(),
+        ),
+        ("big.bin", pointer(5)),
+        ("dir/nested.bin", pointer(6)),
+        ("not-a-pointer.bin", "plain\n".into()),
+        ("pointer-without-attribute.txt", pointer(7)),
+    ] {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "--quiet", "-m", "add pointers"]);
+    format!("file://{}", dir.display())
+}
+
+fn client(script: &str) -> lfs::Client {
+    lfs::Client {
+        program: "sh".into(),
+        args: vec!["-c".into(), script.into(), "lfs".into()],
+    }
+}
+
+#[test]
+fn clone_smudges_pointers_with_the_lfs_filter_attribute() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let url = remote(&dir.path().join("remote"));
+
+    let worktree = dir.path().join("clone");
+    let outcome = clone(
+        &url,
+        &worktree,
+        progress::Discard,
+        clone::Options {
+            lfs: Some(client(
+                r#"read version; read oid; read size; printf 'content of %s (%s)\n' "$1" "$size""#,
+            )),
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(outcome.checked_out_files, 5);
+    let read = |path: &str| fs::read_to_string(worktree.join(path)).unwrap();
+    assert_eq!(read("big.bin"), "content of big.bin (size 5)\n");
+    assert_eq!(read("dir/nested.bin"), "content of dir/nested.bin (size 6)\n");
+    assert_eq!(read("not-a-pointer.bin"), "plain\n");
+    assert_eq!(read("pointer-without-attribute.txt"), pointer(7));
+
+    let worktree = dir.path().join("without-client");
+    clone(&url, &worktree, progress::Discard, clone::Options::default())?;
+    assert_eq!(
+        fs::read_to_string(worktree.join("big.bin"))?,
+        pointer(5),
+        "pointers are checked out as they are"
+    );
+
+    let worktree = dir.path().join("failing");
+    let err = clone(
+        &url,
+        &worktree,
+        progress::Discard,
+        clone::Options {
+            lfs: Some(client("cat >/dev/null; echo 'no such object' >&2; exit 2")),
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+    assert!(
+        matches!(&err, clone::Error::Lfs(lfs::Error::Failed(_, stderr), path) if stderr == "no such object" && path == "big.bin"),
+        "{:?}",
+        err
+    );
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use crate::git;
+use git_object::{bstr::ByteSlice, owned, Sign, Time};
+use git_repository::mailmap::{self, Entry, Error, Snapshot};
+
+const MAILMAP: &str = "# comments and empty lines are ignored
+
+Proper Name <commit@example.com>
+<proper@example.com> <Old@Example.com>
+Both <both@example.com> <both-old@example.com> # trailing comments, too
+Specific <specific@example.com> Old Name <shared@example.com>
+Everyone <shared@example.com>
+";
+
+fn signature(name: &str, email: &str) -> owned::Signature {
+    owned::Signature {
+        name: name.into(),
+        email: email.into(),
+        time: Time {
+            time: 1_600_000_000,
+            offset: 0,
+            sign: Sign::Plus,
+        },
+    }
+}
+
+#[test]
+fn parse_understands_all_four_forms() {
+    let entries: Vec<_> = mailmap::parse(MAILMAP.as_bytes()).map(Result::unwrap).collect();
+    assert_eq!(
+        entries[..4],
+        [
+            Entry {
+                new_name: Some("Proper Name".into()),
+                new_email: None,
+                old_name: None,
+                old_email: "commit@example.com".into(),
+            },
+            Entry {
+                new_name: None,
+                new_email: Some("proper@example.com".into()),
+                old_name: None,
+                old_email: "Old@Example.com".into(),
+            },
+            Entry {
+                new_name: Some("Both".into()),
+                new_email: Some("both@example.com".into()),
+                old_name: None,
+                old_email: "both-old@example.com".into(),
+            },
+            Entry {
+                new_name: Some("Specific".into()),
+                new_email: Some("specific@example.com".into()),
+                old_name: Some("Old Name".into()),
+                old_email: "shared@example.com".into(),
+            },
+        ]
+    );
+    assert_eq!(entries.len(), 5);
+
+    let mut entries = mailmap::parse(b"good <a@b>\nno email\n");
+    assert!(entries.next().unwrap().is_ok());
+    assert!(matches!(entries.next(), Some(Err(Error::Malformed(2, line))) if line == "no email"));
+    assert!(entries.next().is_none());
+}
+
+#[test]
+fn identities_are_resolved_like_git_does() {
+    let snapshot = Snapshot::from_bytes(MAILMAP.as_bytes());
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mailmap");
+    std::fs::write(&path, MAILMAP).unwrap();
+    git(dir.path(), &["init", "--quiet", "--bare"]);
+    let check_mailmap = |identity: &str| {
+        git(
+            dir.path(),
+            &[
+                "--git-dir=.",
+                "-c",
+                &format!("mailmap.file={}", path.display()),
+                "check-mailmap",
+                identity,
+            ],
+        )
+        .trim_end()
+        .to_owned()
+    };
+
+    for (name, email) in &[
+        ("Someone", "commit@example.com"),
+        ("Someone", "COMMIT@example.com"),
+        ("Someone", "old@example.com"),
+        ("Someone", "both-old@example.com"),
+        ("Old Name", "shared@example.com"),
+        ("old name", "Shared@example.com"),
+        ("Other Name", "shared@example.com"),
+        ("Unknown", "unknown@example.com"),
+    ] {
+        let resolved = snapshot.resolve(&signature(name, email));
+        assert_eq!(
+            format!("{} <{}>", resolved.name, resolved.email),
+            check_mailmap(&format!("{} <{}>", name, email)),
+            "{} <{}>",
+            name,
+            email
+        );
+        assert_eq!(resolved.time, signature(name, email).time, "the time is kept");
+    }
+    assert_eq!(snapshot.try_resolve(&signature("Unknown", "unknown@example.com")), None);
+}
+
+#[test]
+fn later_entries_take_precedence() {
+    let mut snapshot = Snapshot::from_bytes(b"First <first@example.com> <old@example.com>\n");
+    assert!(!snapshot.is_empty());
+    snapshot.merge(mailmap::parse(b"Second <old@example.com>\n").map(Result::unwrap));
+    assert_eq!(
+        snapshot.try_resolve_identity("Name".into(), "old@example.com".into()),
+        Some(("Second".into(), "first@example.com".into())),
+        "only the name is replaced"
+    );
+    assert_eq!(
+        Snapshot::default().try_resolve_identity(b"Name".as_bstr(), b"old@example.com".as_bstr()),
+        None
+    );
+}
@@ -0,0 +1,174 @@
+use crate::{git, git_output, hex_to_id};
+use git_object::borrowed;
+use git_odb::{compound, loose, pack};
+use git_repository::merge::{self, ConflictKind, Labels};
+use std::{fs, path::Path};
+
+/// Write `files`, where `None` deletes a file, and commit the changes.
+fn commit(dir: &Path, message: &str, files: &[(&str, Option<&str>)]) {
+    for (path, content) in files {
+        let path = dir.join(path);
+        match content {
+            Some(content) => {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, content).unwrap();
+            }
+            None => fs::remove_file(path).unwrap(),
+        }
+    }
+    git(dir, &["add", "--all"]);
+    git(dir, &["commit", "--quiet", "-m", message]);
+}
+
+fn merge_file(base: &str, ours: &str, theirs: &str) -> (Vec<u8>, usize) {
+    let dir = tempfile::tempdir().unwrap();
+    for (name, content) in &[("base", base), ("ours", ours), ("theirs", theirs)] {
+        fs::write(dir.path().join(name), content).unwrap();
+    }
+    let output = git_output(
+        dir.path(),
+        &[
+            "merge-file",
+            "-p",
+            "-L",
+            "ours",
+            "-L",
+            "base",
+            "-L",
+            "theirs",
+            "ours",
+            "base",
+            "theirs",
+        ],
+    );
+    (output.stdout, output.status.code().unwrap() as usize)
+}
+
+#[test]
+fn lines_are_merged_like_git_merge_file() {
+    let base = "1\n2\n3\n4\n5\n6\n7\n";
+    for (ours, theirs) in &[
+        ("1\ntwo\n3\n4\n5\n6\n7\n", "1\n2\n3\n4\n5\nsix\n7\n"),
+        ("1\ntwo\n3\n4\n5\n6\n7\n", "1\ntwo\n3\n4\n5\n6\n7\n"),
+        ("1\n2\n3\n4\n5\n6\n7\n", "0\n2\n3\n4\n5\n6\n7\n8\n"),
+        ("1\ntwo\n3\n4\n5\n6\n7\n", "1\nzwei\n3\n4\n5\n6\n7\n"),
+        ("1\nx\ntwo\ny\n4\n5\n6\n7\n", "1\nx\nzwei\ny\n4\n5\n6\nseven\n"),
+        ("1\n2\n3\n4\n5\n6\n7", "1\n2\n3\n4\n5\n6\nseven\n"),
+        ("1\n2\n3\n", "1\n2\nthree\n4\n5\n6\n7\n"),
+        ("", "1\n2\n3\n4\n5\n6\n7\nnew\n"),
+    ] {
+        let labels = Labels {
+            ours: "ours".into(),
+            theirs: "theirs".into(),
+        };
+        let (merged, conflicts) = merge::lines(base.as_bytes(), ours.as_bytes(), theirs.as_bytes(), labels);
+        let (expected, expected_conflicts) = merge_file(base, ours, theirs);
+        assert_eq!(
+            String::from_utf8(merged).unwrap(),
+            String::from_utf8(expected).unwrap(),
+            "{:?} {:?}",
+            ours,
+            theirs
+        );
+        assert_eq!(conflicts, expected_conflicts);
+    }
+}
+
+#[test]
+fn trees_are_merged_like_git_merge_tree() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    git(dir, &["init", "--quiet", "--initial-branch=main"]);
+    commit(
+        dir,
+        "base",
+        &[
+            ("both", Some("1\n2\n3\n4\n5\n")),
+            ("conflict", Some("a\nb\nc\n")),
+            ("deleted-by-ours", Some("unchanged\n")),
+            ("modify-delete", Some("content\n")),
+            ("dir/moved-away", Some("file\n")),
+            ("mode", Some("content\n")),
+        ],
+    );
+    git(dir, &["branch", "theirs"]);
+    git(dir, &["checkout", "--quiet", "-b", "ours"]);
+    commit(
+        dir,
+        "ours",
+        &[
+            ("both", Some("one\n2\n3\n4\n5\n")),
+            ("conflict", Some("a\nours\nc\n")),
+            ("deleted-by-ours", None),
+            ("modify-delete", Some("changed\n")),
+            ("mode", Some("changed content\n")),
+            ("added/by-ours", Some("ours\n")),
+            ("add-add", Some("ours\n")),
+        ],
+    );
+    git(dir, &["checkout", "--quiet", "theirs"]);
+    fs::set_permissions(dir.join("mode"), std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+    commit(
+        dir,
+        "theirs",
+        &[
+            ("both", Some("1\n2\n3\n4\nfive\n")),
+            ("conflict", Some("a\ntheirs\nc\n")),
+            ("modify-delete", None),
+            ("dir/moved-away", None),
+            ("dir/new", Some("file\n")),
+            ("added/by-theirs", Some("theirs\n")),
+            ("add-add", Some("theirs\n")),
+        ],
+    );
+
+    let output = git_output(
+        dir,
+        &[
+            "merge-tree",
+            "--write-tree",
+            "--name-only",
+            "--no-messages",
+            "ours",
+            "theirs",
+        ],
+    );
+    assert_eq!(output.status.code(), Some(1), "the trees conflict");
+    let output = String::from_utf8(output.stdout).unwrap();
+    let mut lines = output.lines();
+    let expected_tree = hex_to_id(lines.next().unwrap());
+    let expected_conflicts: Vec<_> = lines.filter(|line| !line.is_empty()).collect();
+
+    let db = compound::Db::at(dir.join(".git/objects")).unwrap();
+    let tree_of = |rev: &str| hex_to_id(&git(dir, &["rev-parse", &format!("{}^{{tree}}", rev)]));
+    let base = hex_to_id(&git(dir, &["merge-base", "ours", "theirs"]));
+    let outcome = merge::trees(
+        Some(tree_of(&base.to_string())),
+        tree_of("ours"),
+        tree_of("theirs"),
+        |id, buf| {
+            let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+            borrowed::Object::from_bytes(object.kind, object.data).ok()
+        },
+        &loose::Db::at(dir.join(".git/objects")),
+        Labels {
+            ours: "ours".into(),
+            theirs: "theirs".into(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(outcome.tree, expected_tree);
+    assert_eq!(
+        outcome.conflicts.iter().map(|c| c.path.to_string()).collect::<Vec<_>>(),
+        expected_conflicts
+    );
+    assert_eq!(
+        outcome.conflicts.iter().map(|c| c.kind).collect::<Vec<_>>(),
+        vec![ConflictKind::AddAdd, ConflictKind::Content, ConflictKind::ModifyDelete]
+    );
+    assert_eq!(
+        git(dir, &["cat-file", "-p", &format!("{}:conflict", outcome.tree)]),
+        "a\n<<<<<<< ours\nours\n=======\ntheirs\n>>>>>>> theirs\nc\n"
+    );
+}
@@ -0,0 +1,152 @@
+use crate::{git_command, run};
+use git_object::{borrowed, owned};
+use git_repository::{date, mailmap, pretty};
+use std::{fs, path::Path};
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = run(git_command(dir)
+        .args(args)
+        .env("GIT_AUTHOR_DATE", "1600000000 +0200")
+        .env("GIT_COMMITTER_DATE", "1600000100 -0130"));
+    String::from_utf8(output).unwrap()
+}
+
+fn commit(dir: &Path, message: &str) {
+    git(
+        dir,
+        &[
+            "commit",
+            "--quiet",
+            "--allow-empty",
+            "--cleanup=verbatim",
+            "-m",
+            message,
+        ],
+    );
+}
+
+/// Create commits with messages git shows in special ways, ending with a merge.
+fn repository(dir: &Path) {
+    git(dir, &["init", "--quiet", "--initial-branch=main"]);
+    commit(dir, "subject only");
+    commit(dir, "\n\nleading blank lines\n\n\n\tand\ta tabbed body\n\n  \n");
+    commit(dir, "a subject\nspanning lines\n\nbody\nwith lines\n");
+    git(dir, &["checkout", "--quiet", "-b", "other", "HEAD~1"]);
+    commit(dir, "other side");
+    git(dir, &["checkout", "--quiet", "main"]);
+    git(dir, &["merge", "--quiet", "--no-ff", "-m", "merge other", "other"]);
+}
+
+/// The commits reachable from `HEAD`, newest first as `git log` shows them.
+fn commits(dir: &Path) -> Vec<pretty::Commit> {
+    git(dir, &["rev-list", "HEAD"])
+        .lines()
+        .map(|hex| {
+            let id = owned::Id::from_40_bytes_in_hex(hex.as_bytes()).unwrap();
+            let data = git(dir, &["cat-file", "commit", hex]);
+            let commit = borrowed::Commit::from_bytes(data.as_bytes()).unwrap();
+            pretty::Commit::from_borrowed(id, &commit)
+        })
+        .collect()
+}
+
+#[test]
+fn the_builtin_formats_match_the_ones_of_git() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    let mailmap = mailmap::Snapshot::default();
+    let commits = commits(dir.path());
+
+    let mut medium = Vec::new();
+    for (index, commit) in commits.iter().enumerate() {
+        if index > 0 {
+            medium.push(b'\n');
+        }
+        pretty::medium(commit, &mailmap, date::Format::Default, &mut medium);
+    }
+    assert_eq!(String::from_utf8(medium).unwrap(), git(dir.path(), &["log"]));
+
+    let mut oneline = Vec::new();
+    for commit in &commits {
+        pretty::oneline(commit, &mut oneline);
+        oneline.push(b'\n');
+    }
+    assert_eq!(
+        String::from_utf8(oneline).unwrap(),
+        git(dir.path(), &["log", "--oneline", "--abbrev=7"])
+    );
+}
+
+#[test]
+fn placeholders_are_expanded_like_git_does() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    let mailmap = mailmap::Snapshot::default();
+    for format in &[
+        "%H %h %T %t %P %p",
+        "%an <%ae> %ad %aD %ai %aI %at",
+        "%cn <%ce> %cd %cD %ci %cI %ct",
+        "%s%n%b%%%n%B",
+        "unknown %x %aX %",
+    ] {
+        let mut expanded = Vec::new();
+        for commit in commits(dir.path()) {
+            pretty::expand(
+                format.as_bytes(),
+                &commit,
+                &mailmap,
+                date::Format::Iso8601,
+                &mut expanded,
+            );
+            expanded.push(b'\n');
+        }
+        assert_eq!(
+            String::from_utf8(expanded).unwrap(),
+            git(
+                dir.path(),
+                &[
+                    "log",
+                    "--abbrev=7",
+                    "--date=iso",
+                    &format!("--format=tformat:{}", format)
+                ]
+            ),
+            "{}",
+            format
+        );
+    }
+}
+
+#[test]
+fn identities_are_mapped_where_git_maps_them() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    fs::write(
+        dir.path().join(".mailmap"),
+        "Mapped <mapped@example.com> <a@example.com>\n",
+    )
+    .unwrap();
+    let mailmap = mailmap::Snapshot::from_bytes(&fs::read(dir.path().join(".mailmap")).unwrap());
+    let commits = commits(dir.path());
+
+    let mut medium = Vec::new();
+    pretty::medium(&commits[0], &mailmap, date::Format::Rfc2822, &mut medium);
+    assert_eq!(
+        String::from_utf8(medium).unwrap(),
+        git(dir.path(), &["log", "-1", "--date=rfc", "--use-mailmap"])
+    );
+
+    let format = "%an %ae %aN %aE %cN %cE";
+    let mut expanded = Vec::new();
+    pretty::expand(
+        format.as_bytes(),
+        &commits[0],
+        &mailmap,
+        date::Format::Default,
+        &mut expanded,
+    );
+    assert_eq!(
+        String::from_utf8(expanded).unwrap(),
+        git(dir.path(), &["log", "-1", &format!("--format=format:{}", format)])
+    );
+}
@@ -0,0 +1,350 @@
+use crate::{git, hex_to_id};
+use git_features::progress;
+use git_object::owned;
+use git_odb::{compound, pack};
+use git_protocol::push::Status;
+use git_repository::{
+    push::{self, Lease, Rejection, Update},
+    remote::{refspec, RefSpec, Remote},
+};
+use git_transport::{client::file, Protocol};
+use std::{collections::HashSet, fs, path::Path};
+
+const MAIN: &str = "877c3ad4e85fa4408811d3fcc38aef2500f04ade";
+const OTHER: &str = "cecbafcde689e1bc457bf13334c3d934200802a1";
+const TAG: &str = "17934c4602e90c793a9660b02d49b6a678772104";
+
+fn source_bundle() -> pack::Bundle {
+    let pack_dir = Path::new("tests/fixtures/repos/files.git/objects/pack");
+    let index = fs::read_dir(pack_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension() == Some("idx".as_ref()))
+        .expect("an index file");
+    pack::Bundle::at(index).unwrap()
+}
+
+fn update(source: Option<&str>, destination: &str, force: bool) -> Update {
+    Update {
+        source: source.map(hex_to_id),
+        destination: destination.into(),
+        force,
+        lease: None,
+    }
+}
+
+fn push_to(git_dir: &Path, updates: &[Update]) -> push::Outcome {
+    push_with_options(git_dir, updates, push::Options::default()).unwrap()
+}
+
+fn push_with_options(git_dir: &Path, updates: &[Update], options: push::Options) -> Result<push::Outcome, push::Error> {
+    let bundle = source_bundle();
+    let mut transport = file::connect(git_dir.display().to_string(), Protocol::V1);
+    git_repository::push(
+        &mut transport,
+        updates,
+        |id, buf| bundle.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok(),
+        progress::Discard,
+        options,
+    )
+}
+
+fn read_ref(git_dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(git_dir.join(name))
+        .ok()
+        .map(|s| s.trim_end().to_owned())
+}
+
+#[test]
+fn only_objects_missing_on_the_remote_are_sent() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = git_repository::init::repository_at(dir.path()).unwrap();
+
+    let outcome = push_to(
+        &git_dir,
+        &[
+            update(Some(OTHER), "refs/heads/pushed", false),
+            update(Some(TAG), "refs/tags/v1.0", false),
+        ],
+    );
+    assert!(outcome.report.is_success(), "{:?}", outcome.report);
+    assert_eq!(outcome.report.refs.len(), 2);
+    assert_eq!(
+        outcome.pack.expect("sent").num_objects,
+        9,
+        "the tag and 8 objects of the commit"
+    );
+    assert_eq!(read_ref(&git_dir, "refs/heads/pushed"), Some(OTHER.into()));
+    assert_eq!(read_ref(&git_dir, "refs/tags/v1.0"), Some(TAG.into()));
+
+    let outcome = push_to(&git_dir, &[update(Some(MAIN), "refs/heads/pushed", false)]);
+    assert!(outcome.report.is_success());
+    assert_eq!(
+        outcome.pack.expect("sent").num_objects,
+        3,
+        "the new commit, its tree and the changed blob"
+    );
+    assert_eq!(read_ref(&git_dir, "refs/heads/pushed"), Some(MAIN.into()));
+
+    let outcome = push_to(&git_dir, &[update(Some(MAIN), "refs/heads/pushed", false)]);
+    assert_eq!(outcome.up_to_date, vec!["refs/heads/pushed"]);
+    assert!(outcome.commands.is_empty() && outcome.pack.is_none());
+}
+
+#[test]
+fn non_fast_forwards_are_rejected_unless_forced() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = git_repository::init::repository_at(dir.path()).unwrap();
+    push_to(
+        &git_dir,
+        &[
+            update(Some(MAIN), "refs/heads/pushed", false),
+            update(Some(TAG), "refs/tags/v1.0", false),
+        ],
+    );
+
+    let outcome = push_to(
+        &git_dir,
+        &[
+            update(Some(OTHER), "refs/heads/pushed", false),
+            update(Some(MAIN), "refs/tags/v1.0", false),
+        ],
+    );
+    assert_eq!(
+        outcome.rejected,
+        vec![
+            ("refs/heads/pushed".into(), Rejection::NonFastForward),
+            ("refs/tags/v1.0".into(), Rejection::AlreadyExists),
+        ]
+    );
+    assert!(outcome.commands.is_empty());
+    assert_eq!(read_ref(&git_dir, "refs/heads/pushed"), Some(MAIN.into()));
+
+    let outcome = push_to(&git_dir, &[update(Some(OTHER), "refs/heads/pushed", true)]);
+    assert!(outcome.report.is_success());
+    assert_eq!(outcome.pack.expect("sent").num_objects, 0, "the remote has everything");
+    assert_eq!(read_ref(&git_dir, "refs/heads/pushed"), Some(OTHER.into()));
+}
+
+#[test]
+fn refs_can_be_deleted_and_refusals_of_the_server_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = git_repository::init::repository_at(dir.path()).unwrap();
+    push_to(&git_dir, &[update(Some(MAIN), "refs/heads/pushed", false)]);
+
+    let outcome = push_to(
+        &git_dir,
+        &[
+            update(None, "refs/heads/pushed", false),
+            update(None, "refs/heads/missing", false),
+        ],
+    );
+    assert_eq!(
+        outcome.rejected,
+        vec![("refs/heads/missing".into(), Rejection::MissingRemoteRef)]
+    );
+    assert!(outcome.pack.is_none(), "deletions need no pack");
+    assert!(outcome.report.is_success());
+    assert_eq!(read_ref(&git_dir, "refs/heads/pushed"), None);
+
+    let config = git_dir.join("config");
+    let mut content = fs::read_to_string(&config).unwrap();
+    content.push_str("[receive]\n\tdenyNonFastForwards = true\n");
+    fs::write(&config, content).unwrap();
+    push_to(&git_dir, &[update(Some(MAIN), "refs/heads/pushed", false)]);
+    let outcome = push_to(&git_dir, &[update(Some(OTHER), "refs/heads/pushed", true)]);
+    let status = outcome.report.status_of("refs/heads/pushed".into()).expect("reported");
+    assert!(
+        matches!(&status.status, Status::Rejected(reason) if reason == "non-fast-forward"),
+        "{:?}",
+        status
+    );
+    assert!(!outcome.report.is_success());
+    assert_eq!(read_ref(&git_dir, "refs/heads/pushed"), Some(MAIN.into()));
+}
+
+#[test]
+fn atomic_pushes_update_all_refs_or_none() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = git_repository::init::repository_at(dir.path()).unwrap();
+    push_to(&git_dir, &[update(Some(MAIN), "refs/heads/pushed", false)]);
+    let atomic = push::Options {
+        atomic: true,
+        ..Default::default()
+    };
+
+    let outcome = push_with_options(
+        &git_dir,
+        &[
+            update(Some(OTHER), "refs/heads/pushed", false),
+            update(Some(MAIN), "refs/heads/new", false),
+        ],
+        atomic.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        outcome.rejected,
+        vec![
+            ("refs/heads/pushed".into(), Rejection::NonFastForward),
+            ("refs/heads/new".into(), Rejection::AtomicPushFailed),
+        ]
+    );
+    assert!(outcome.commands.is_empty() && outcome.pack.is_none());
+
+    let config = git_dir.join("config");
+    let mut content = fs::read_to_string(&config).unwrap();
+    content.push_str("[receive]\n\tdenyNonFastForwards = true\n");
+    fs::write(&config, content).unwrap();
+    let outcome = push_with_options(
+        &git_dir,
+        &[
+            update(Some(OTHER), "refs/heads/pushed", true),
+            update(Some(MAIN), "refs/heads/new", false),
+        ],
+        atomic,
+    )
+    .unwrap();
+    assert!(!outcome.report.is_success());
+    let status = outcome.report.status_of("refs/heads/new".into()).expect("reported");
+    assert!(
+        matches!(&status.status, Status::Rejected(reason) if reason == "atomic push failure"),
+        "{:?}",
+        status
+    );
+    assert_eq!(read_ref(&git_dir, "refs/heads/pushed"), Some(MAIN.into()));
+    assert_eq!(
+        read_ref(&git_dir, "refs/heads/new"),
+        None,
+        "the valid update isn't applied either"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn push_options_are_passed_to_the_hooks_of_the_server() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = git_repository::init::repository_at(dir.path()).unwrap();
+    let with_options = push::Options {
+        push_options: vec!["ci.skip".into(), "reviewer=someone".into()],
+        ..Default::default()
+    };
+    let updates = [update(Some(MAIN), "refs/heads/pushed", false)];
+    assert!(matches!(
+        push_with_options(&git_dir, &updates, with_options.clone()),
+        Err(push::Error::PushOptionsUnsupported)
+    ));
+
+    let config = git_dir.join("config");
+    let mut content = fs::read_to_string(&config).unwrap();
+    content.push_str("[receive]\n\tadvertisePushOptions = true\n");
+    fs::write(&config, content).unwrap();
+    let hook = git_dir.join("hooks").join("pre-receive");
+    fs::create_dir_all(hook.parent().unwrap()).unwrap();
+    fs::write(
+        &hook,
+        "#!/bin/sh\necho \"$GIT_PUSH_OPTION_COUNT $GIT_PUSH_OPTION_0 $GIT_PUSH_OPTION_1\" > push-options\n",
+    )
+    .unwrap();
+    fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let outcome = push_with_options(&git_dir, &updates, with_options).unwrap();
+    assert!(outcome.report.is_success(), "{:?}", outcome.report);
+    assert_eq!(
+        fs::read_to_string(git_dir.join("push-options")).unwrap(),
+        "2 ci.skip reviewer=someone\n"
+    );
+}
+
+#[test]
+fn leased_updates_are_only_sent_if_the_remote_ref_is_as_expected() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = git_repository::init::repository_at(dir.path().join("remote")).unwrap();
+    push_to(&git_dir, &[update(Some(MAIN), "refs/heads/pushed", false)]);
+    let leased = |expected: &str| Update {
+        lease: Some(Lease {
+            expected: Some(hex_to_id(expected)),
+        }),
+        ..update(Some(OTHER), "refs/heads/pushed", false)
+    };
+
+    let outcome = push_to(&git_dir, &[leased(OTHER)]);
+    assert_eq!(
+        outcome.rejected,
+        vec![("refs/heads/pushed".into(), Rejection::StaleInfo)]
+    );
+    assert_eq!(read_ref(&git_dir, "refs/heads/pushed"), Some(MAIN.into()));
+
+    let outcome = push_to(&git_dir, &[leased(MAIN)]);
+    assert!(outcome.report.is_success(), "no fast-forward is needed");
+    assert_eq!(outcome.commands[0].old, hex_to_id(MAIN), "the expected value is sent");
+    assert_eq!(read_ref(&git_dir, "refs/heads/pushed"), Some(OTHER.into()));
+
+    let local = git_repository::init::repository_at(dir.path().join("local")).unwrap();
+    let remote = Remote {
+        name: "origin".into(),
+        urls: Vec::new(),
+        push_urls: Vec::new(),
+        fetch_specs: vec![
+            RefSpec::parse("+refs/heads/*:refs/remotes/origin/*".into(), refspec::Operation::Fetch).unwrap(),
+        ],
+        push_specs: Vec::new(),
+    };
+    fs::create_dir_all(local.join("refs/remotes/origin")).unwrap();
+    fs::write(local.join("refs/remotes/origin/pushed"), format!("{}\n", OTHER)).unwrap();
+    let lease = |name: &str| Lease::from_tracking_ref(&local, &remote, name.into()).unwrap();
+    assert_eq!(lease("refs/heads/pushed").expected, Some(hex_to_id(OTHER)));
+    assert_eq!(
+        lease("refs/heads/unknown").expected,
+        None,
+        "refs without tracking ref must not exist"
+    );
+}
+
+#[test]
+fn only_the_difference_is_walked_and_sent_as_thin_pack() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("source");
+    fs::create_dir(&source).unwrap();
+    git(&source, &["init", "--quiet"]);
+    let lines: String = (0..1000).map(|n| format!("line {}\n", n)).collect();
+    for n in 0..10 {
+        fs::write(source.join("file"), format!("{}{}\n", lines, n)).unwrap();
+        git(&source, &["add", "file"]);
+        git(&source, &["commit", "--quiet", "-m", &format!("commit {}", n)]);
+    }
+    git(dir.path(), &["init", "--quiet", "--bare", "remote.git"]);
+    let remote = dir.path().join("remote.git");
+    git(
+        &source,
+        &["push", "--quiet", remote.to_str().unwrap(), "HEAD~1:refs/heads/main"],
+    );
+    let root = hex_to_id(&git(&source, &["rev-list", "--max-parents=0", "HEAD"]));
+    let head = git(&source, &["rev-parse", "HEAD"]);
+
+    let db = compound::Db::at(source.join(".git").join("objects")).unwrap();
+    let mut looked_up = HashSet::new();
+    let mut transport = file::connect(remote.display().to_string(), Protocol::V1);
+    let outcome = git_repository::push(
+        &mut transport,
+        &[update(Some(&head), "refs/heads/main", false)],
+        |id, buf| {
+            looked_up.insert(owned::Id::from_borrowed_sha1(id.sha1()));
+            db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()
+        },
+        progress::Discard,
+        push::Options::default(),
+    )
+    .unwrap();
+    assert!(outcome.report.is_success(), "{:?}", outcome.report);
+    let pack = outcome.pack.expect("sent");
+    assert_eq!(pack.num_objects, 3, "the new commit, its tree and the changed file");
+    assert!(
+        pack.bytes_written < 500,
+        "the file is sent as delta against its previous version, the pack has {} bytes",
+        pack.bytes_written
+    );
+    assert!(!looked_up.contains(&root), "the history the remote has isn't walked");
+    assert_eq!(git(&remote, &["rev-parse", "refs/heads/main"]), head);
+    git(&remote, &["fsck", "--strict"]);
+}
@@ -0,0 +1,204 @@
+use crate::{git_command, hex_to_id, run, run_with_input};
+use git_object::{borrowed, owned, Sign, Time};
+use git_odb::{compound, pack};
+use git_repository::rebase::{self, Error, Options, Outcome};
+use std::{fs, path::Path, process::Command};
+
+/// A git command which doesn't wait for the user to edit messages.
+fn git_without_editor(dir: &Path, args: &[&str]) -> Command {
+    let mut cmd = git_command(dir);
+    cmd.args(args).env("GIT_EDITOR", "true");
+    cmd
+}
+
+fn git_with_input(dir: &Path, args: &[&str], input: &str) -> std::process::Output {
+    run_with_input(&mut git_without_editor(dir, args), input.as_bytes())
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    String::from_utf8(run(&mut git_without_editor(dir, args))).unwrap()
+}
+
+fn id(dir: &Path, rev: &str) -> owned::Id {
+    hex_to_id(&git(dir, &["rev-parse", rev]))
+}
+
+fn committer() -> owned::Signature {
+    owned::Signature {
+        name: "a".into(),
+        email: "a@example.com".into(),
+        time: Time {
+            time: 1_600_000_000,
+            offset: 0,
+            sign: Sign::Plus,
+        },
+    }
+}
+
+fn commit(dir: &Path, message: &str, files: &[(&str, &str)]) {
+    for (path, content) in files {
+        fs::write(dir.join(path), content).unwrap();
+    }
+    git(dir, &["add", "--all"]);
+    git(dir, &["commit", "--quiet", "-m", message]);
+}
+
+/// Two identical repositories with `main` and `topic` branches which diverged, with `topic` checked out.
+///
+/// The second commit of `topic` conflicts with `main`, and the third one makes a change `main` made as well.
+fn repositories() -> (tempfile::TempDir, tempfile::TempDir) {
+    let dirs = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    for dir in &[dirs.0.path(), dirs.1.path()] {
+        git(dir, &["init", "--quiet", "--initial-branch=main"]);
+        commit(dir, "base", &[("a", "1\n2\n3\n"), ("b", "1\n2\n3\n"), ("c", "1\n")]);
+        git(dir, &["branch", "old"]);
+        git(dir, &["checkout", "--quiet", "-b", "topic"]);
+        commit(dir, "change a", &[("a", "1\n2\nthree\n")]);
+        commit(dir, "change b", &[("b", "1\ntopic\n3\n")]);
+        commit(dir, "change c", &[("c", "one\n")]);
+        git(dir, &["checkout", "--quiet", "main"]);
+        commit(dir, "main", &[("b", "1\nmain\n3\n"), ("c", "one\n")]);
+        git(dir, &["checkout", "--quiet", "topic"]);
+    }
+    dirs
+}
+
+fn find(dir: &Path) -> impl for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>> {
+    let db = compound::Db::at(dir.join(".git/objects")).unwrap();
+    move |id, buf| {
+        let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+        borrowed::Object::from_bytes(object.kind, object.data).ok()
+    }
+}
+
+fn start(dir: &Path, upstream: &str, onto: Option<&str>) -> Result<Outcome, Error> {
+    rebase::start(
+        dir.join(".git"),
+        "HEAD".into(),
+        id(dir, upstream),
+        find(dir),
+        Options {
+            committer: committer(),
+            onto: onto.map(|rev| id(dir, rev)),
+        },
+    )
+}
+
+/// Write the tree of `rev` with `b` replaced by `content`.
+fn resolved_tree(dir: &Path, rev: &str, content: &str) -> owned::Id {
+    let blob = String::from_utf8(git_with_input(dir, &["hash-object", "-w", "--stdin"], content).stdout).unwrap();
+    let entries: String = git(dir, &["ls-tree", rev])
+        .lines()
+        .map(|line| match line.strip_suffix("\tb") {
+            Some(_) => format!("100644 blob {}\tb\n", blob.trim_end()),
+            None => format!("{}\n", line),
+        })
+        .collect();
+    let tree = String::from_utf8(git_with_input(dir, &["mktree"], &entries).stdout).unwrap();
+    owned::Id::from_40_bytes_in_hex(tree.trim_end().as_bytes()).unwrap()
+}
+
+fn reflog(dir: &Path, name: &str) -> String {
+    git(dir, &["reflog", "-1", "--format=%gs", name])
+}
+
+#[test]
+fn conflicts_stop_the_rebase_until_it_is_resumed_and_the_result_matches_git() {
+    let (ours, theirs) = repositories();
+    let (ours, theirs) = (ours.path(), theirs.path());
+    let orig_head = id(ours, "topic");
+
+    let outcome = start(ours, "main", None).unwrap();
+    assert!(!git_with_input(theirs, &["rebase", "main"], "").status.success());
+    let stopped = match outcome {
+        Outcome::Stopped { commit, merge } => {
+            assert_eq!(merge.conflicts.len(), 1);
+            commit
+        }
+        Outcome::Done { .. } => panic!("expected a conflict"),
+    };
+    assert_eq!(stopped, id(ours, "topic~1"));
+    assert_eq!(id(ours, "topic"), orig_head, "the branch doesn't move while stopped");
+    assert_eq!(id(ours, "ORIG_HEAD"), orig_head);
+    let state = |dir: &Path, name: &str| fs::read_to_string(dir.join(".git").join(name)).unwrap();
+    for name in &[
+        "REBASE_HEAD",
+        "MERGE_MSG",
+        "AUTO_MERGE",
+        "rebase-merge/head-name",
+        "rebase-merge/onto",
+        "rebase-merge/orig-head",
+        "rebase-merge/done",
+        "rebase-merge/msgnum",
+        "rebase-merge/end",
+        "rebase-merge/stopped-sha",
+        "rebase-merge/rewritten-list",
+    ] {
+        assert_eq!(state(ours, name), state(theirs, name), "{}", name);
+    }
+    assert_eq!(
+        state(ours, "rebase-merge/git-rebase-todo"),
+        format!("pick {} change c\n", id(ours, "topic"))
+    );
+    assert!(matches!(start(ours, "main", None), Err(Error::InProgress(_))));
+
+    let tree = resolved_tree(ours, "AUTO_MERGE", "1\nresolved\n3\n");
+    fs::write(theirs.join("b"), "1\nresolved\n3\n").unwrap();
+    git(theirs, &["add", "b"]);
+    git(theirs, &["rebase", "--continue"]);
+    let outcome = rebase::resume(ours.join(".git"), tree, find(ours), committer()).unwrap();
+
+    assert_eq!(
+        outcome,
+        Outcome::Done {
+            previous: orig_head,
+            new: id(theirs, "topic")
+        }
+    );
+    assert_eq!(id(ours, "topic"), id(theirs, "topic"));
+    assert_eq!(
+        id(ours, "topic~2"),
+        id(ours, "main"),
+        "the already applied change was dropped"
+    );
+    assert_eq!(reflog(ours, "topic"), reflog(theirs, "topic"));
+    assert_eq!(reflog(ours, "HEAD"), reflog(theirs, "HEAD"));
+    for name in &["rebase-merge", "REBASE_HEAD", "MERGE_MSG", "AUTO_MERGE"] {
+        assert!(!ours.join(".git").join(name).exists(), "{} was removed", name);
+    }
+}
+
+#[test]
+fn onto_picks_the_commits_onto_another_commit_like_git() {
+    let (ours, theirs) = repositories();
+    let (ours, theirs) = (ours.path(), theirs.path());
+    git(ours, &["checkout", "--quiet", "-b", "rest", "topic"]);
+    git(theirs, &["checkout", "--quiet", "-b", "rest", "topic"]);
+
+    let outcome = start(ours, "topic~2", Some("old")).unwrap();
+    git(theirs, &["rebase", "--onto", "old", "topic~2"]);
+    assert_eq!(
+        outcome,
+        Outcome::Done {
+            previous: id(ours, "topic"),
+            new: id(theirs, "rest")
+        }
+    );
+    assert_eq!(id(ours, "rest"), id(theirs, "rest"));
+    assert_eq!(reflog(ours, "rest"), reflog(theirs, "rest"));
+}
+
+#[test]
+fn abort_removes_the_state_and_leaves_the_branch_untouched() {
+    let (ours, _theirs) = repositories();
+    let ours = ours.path();
+    let orig_head = id(ours, "topic");
+    assert!(matches!(start(ours, "main", None), Ok(Outcome::Stopped { .. })));
+
+    rebase::abort(ours.join(".git")).unwrap();
+    assert!(!ours.join(".git/rebase-merge").exists());
+    assert!(!ours.join(".git/REBASE_HEAD").exists());
+    assert_eq!(id(ours, "topic"), orig_head);
+    assert!(matches!(rebase::abort(ours.join(".git")), Err(Error::NotInProgress(_))));
+    assert!(matches!(start(ours, "old", None), Ok(Outcome::Done { .. })));
+}
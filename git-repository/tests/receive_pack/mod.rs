@@ -0,0 +1,366 @@
+use crate::{git, git_output, hex_to_id};
+use git_features::progress;
+use git_object::{bstr::ByteSlice, owned};
+use git_odb::pack;
+use git_protocol::{
+    handshake::Ref,
+    push::{Command, Report, Status},
+};
+use git_repository::{
+    receive_pack::{self, Hooks, Options},
+    transfer,
+};
+use git_transport::packetline;
+use std::{
+    fs,
+    io::Read,
+    net::TcpListener,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+};
+
+const MAIN: &str = "877c3ad4e85fa4408811d3fcc38aef2500f04ade";
+const OTHER: &str = "cecbafcde689e1bc457bf13334c3d934200802a1";
+
+/// The refs of the repository at `git_dir`, as listed by git.
+fn refs_of(git_dir: &Path) -> Vec<Ref> {
+    git(git_dir, &["for-each-ref", "--format=%(objectname) %(refname)"])
+        .lines()
+        .map(|line| {
+            let mut tokens = line.splitn(2, ' ');
+            let object = hex_to_id(tokens.next().unwrap());
+            Ref::Direct {
+                path: tokens.next().unwrap().into(),
+                object,
+            }
+        })
+        .collect()
+}
+
+fn read_ref(git_dir: &Path, name: &str) -> Option<String> {
+    let output = git_output(git_dir, &["rev-parse", "--verify", "--quiet", name]);
+    if output.status.success() {
+        Some(String::from_utf8(output.stdout).unwrap().trim_end().to_owned())
+    } else {
+        None
+    }
+}
+
+/// Act as `git daemon` for `connections` clients pushing into `git_dir`, using its executable hooks.
+fn daemon(git_dir: PathBuf, connections: usize) -> (u16, JoinHandle<Vec<receive_pack::Outcome>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = std::thread::spawn(move || {
+        let mut outcomes = Vec::new();
+        for _ in 0..connections {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut len = [0u8; 4];
+            stream.read_exact(&mut len).unwrap();
+            let len = usize::from_str_radix(std::str::from_utf8(&len).unwrap(), 16).unwrap();
+            let mut request = vec![0; len - 4];
+            stream.read_exact(&mut request).unwrap();
+            assert!(request.starts_with(b"git-receive-pack /target.git\0"));
+
+            outcomes.push(
+                git_repository::receive_pack(
+                    stream.try_clone().unwrap(),
+                    &stream,
+                    &git_dir,
+                    &refs_of(&git_dir),
+                    Hooks::from_git_dir(&git_dir),
+                    progress::Discard,
+                    Options::default(),
+                )
+                .unwrap(),
+            );
+        }
+        outcomes
+    });
+    (port, handle)
+}
+
+#[cfg(unix)]
+fn write_hook(git_dir: &Path, name: &str, script: &str) {
+    use std::os::unix::fs::PermissionsExt;
+    let path = git_dir.join("hooks").join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, format!("#!/bin/sh\n{}", script)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn git_can_push_and_hooks_are_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = git_repository::init::repository_at(dir.path().join("target")).unwrap();
+    let source = dir.path().join("source");
+    git(
+        dir.path(),
+        &["clone", "--quiet", "--no-local", &fixture_path(), "source"],
+    );
+    let (port, server) = daemon(target.clone(), 4);
+    let url = format!("git://127.0.0.1:{}/target.git", port);
+
+    git(
+        &source,
+        &["push", "--quiet", &url, "origin/other:refs/heads/other", "v1.0"],
+    );
+    git(&source, &["push", "--quiet", &url, "origin/main:refs/heads/main"]);
+    assert_eq!(read_ref(&target, "refs/heads/main"), Some(MAIN.into()));
+    assert_eq!(read_ref(&target, "refs/heads/other"), Some(OTHER.into()));
+    git(&target, &["fsck", "--strict"]);
+
+    write_hook(
+        &target,
+        "pre-receive",
+        "while read old new name; do echo \"pre-receive $name\"; done\n",
+    );
+    write_hook(
+        &target,
+        "update",
+        "if [ \"$1\" = refs/heads/protected ]; then echo \"$1 is protected\" >&2; exit 1; fi\n",
+    );
+    write_hook(&target, "post-receive", "cat > post-receive.log\n");
+    let output = git_output(
+        &source,
+        &["push", &url, "origin/main:refs/heads/protected", ":refs/heads/other"],
+    );
+    assert!(!output.status.success(), "one of the updates was declined");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("remote: pre-receive refs/heads/protected"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("remote: refs/heads/protected is protected"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("hook declined"), "{}", stderr);
+    assert_eq!(read_ref(&target, "refs/heads/other"), None, "deletions are applied");
+    assert_eq!(read_ref(&target, "refs/heads/protected"), None);
+    assert_eq!(
+        fs::read_to_string(target.join("post-receive.log")).unwrap(),
+        format!("{} {} refs/heads/other\n", OTHER, owned::Id::null_sha1()),
+        "only applied updates are passed"
+    );
+
+    let output = git_output(
+        &source,
+        &[
+            "push",
+            "--atomic",
+            &url,
+            "origin/main:refs/heads/protected",
+            "origin/other:refs/heads/other",
+        ],
+    );
+    assert!(!output.status.success());
+    assert_eq!(
+        read_ref(&target, "refs/heads/other"),
+        None,
+        "nothing is applied if one update fails"
+    );
+
+    let outcomes = server.join().unwrap();
+    assert!(outcomes
+        .iter()
+        .all(|o| o.capabilities.iter().any(|c| c == "report-status")));
+    assert_eq!(outcomes[0].pack.as_ref().map(|p| p.index.num_objects), Some(9));
+    assert_eq!(outcomes[2].report.refs.len(), 2);
+    assert!(outcomes[3].capabilities.iter().any(|c| c == "atomic"));
+    assert!(outcomes[3]
+        .report
+        .refs
+        .iter()
+        .any(|r| r.status == Status::Rejected("atomic transaction failed".into())));
+}
+
+fn fixture_path() -> String {
+    std::env::current_dir()
+        .unwrap()
+        .join("tests/fixtures/repos/files.git")
+        .display()
+        .to_string()
+}
+
+fn source_bundle() -> pack::Bundle {
+    let pack_dir = Path::new("tests/fixtures/repos/files.git/objects/pack");
+    let index = fs::read_dir(pack_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension() == Some("idx".as_ref()))
+        .expect("an index file");
+    pack::Bundle::at(index).unwrap()
+}
+
+fn request(commands: &[Command], objects: &[owned::Id]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (index, command) in commands.iter().enumerate() {
+        let mut line = format!("{} {} {}", command.old, command.new, command.name).into_bytes();
+        if index == 0 {
+            line.extend_from_slice(b"\0report-status");
+        }
+        packetline::encode::text_to_write(&line, &mut out).unwrap();
+    }
+    packetline::encode::flush_to_write(&mut out).unwrap();
+    let bundle = source_bundle();
+    pack::data::File::write_objects_to_stream(
+        objects,
+        |id, buf| bundle.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok(),
+        progress::Discard,
+        &mut out,
+    )
+    .unwrap();
+    out
+}
+
+fn command(old: Option<&str>, new: &str, name: &str) -> Command {
+    Command {
+        old: old.map(hex_to_id).unwrap_or_else(owned::Id::null_sha1),
+        new: hex_to_id(new),
+        name: name.into(),
+    }
+}
+
+#[test]
+fn updates_are_refused_if_objects_are_missing_or_refs_changed() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = git_repository::init::repository_at(dir.path()).unwrap();
+    let commands = vec![
+        command(None, OTHER, "refs/heads/broken"),
+        Command {
+            old: hex_to_id(MAIN),
+            new: owned::Id::null_sha1(),
+            name: "refs/heads/stale".into(),
+        },
+        command(None, OTHER, "HEAD"),
+        command(None, OTHER, "refs/hidden/other"),
+    ];
+    let called = Arc::new(Mutex::new(Vec::new()));
+    let hooks = Hooks {
+        pre_receive: Some(Box::new({
+            let called = called.clone();
+            move |commands: &[Command], _: &mut Vec<u8>| {
+                called.lock().unwrap().push(("pre-receive", commands.len()));
+                true
+            }
+        })),
+        update: None,
+        post_receive: Some(Box::new({
+            let called = called.clone();
+            move |commands: &[Command], _: &mut Vec<u8>| called.lock().unwrap().push(("post-receive", commands.len()))
+        })),
+    };
+    let mut out = Vec::new();
+    let outcome = git_repository::receive_pack(
+        std::io::Cursor::new(request(&commands, &[hex_to_id(OTHER)])),
+        &mut out,
+        &git_dir,
+        &[],
+        hooks,
+        progress::Discard,
+        Options {
+            stateless: true,
+            advertisement: transfer::Advertisement {
+                hide_refs: vec!["refs/hidden".into()],
+                ..Default::default()
+            },
+        },
+    )
+    .unwrap();
+
+    let reasons: Vec<_> = outcome
+        .report
+        .refs
+        .iter()
+        .map(|r| match &r.status {
+            Status::Rejected(reason) => reason.to_str_lossy().into_owned(),
+            Status::Ok => "ok".into(),
+        })
+        .collect();
+    assert_eq!(
+        reasons,
+        vec![
+            "missing necessary objects",
+            "stale info",
+            "funny refname",
+            "deny updating a hidden ref"
+        ]
+    );
+    assert_eq!(
+        *called.lock().unwrap(),
+        vec![("pre-receive", 1)],
+        "only commands that may be applied are passed"
+    );
+    assert_eq!(outcome.pack.as_ref().map(|p| p.index.num_objects), Some(1));
+    assert_eq!(
+        outcome.missing,
+        vec![hex_to_id("f64a13251c7d8eb3302688b4f04a60ce87b3e406")],
+        "the tree of the commit wasn't sent"
+    );
+
+    let mut lines = packetline::Provider::new(out.as_slice(), &[packetline::PacketLine::Flush]);
+    assert_eq!(
+        Report::from_lines(&mut lines).unwrap(),
+        outcome.report,
+        "the report is sent to the client"
+    );
+    assert_eq!(read_ref(&git_dir, "refs/heads/broken"), None);
+}
+
+/// The files in the pack directory of `git_dir`, asserting that no quarantine directory was left behind.
+fn kept_packs(git_dir: &Path) -> Vec<PathBuf> {
+    let objects = git_dir.join("objects");
+    assert!(
+        fs::read_dir(&objects).unwrap().all(|entry| !entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .starts_with("incoming-")),
+        "quarantine directories are removed"
+    );
+    fs::read_dir(objects.join("pack"))
+        .map(|entries| entries.map(|entry| entry.unwrap().path()).collect())
+        .unwrap_or_default()
+}
+
+#[test]
+fn packs_are_only_kept_once_the_pre_receive_hook_accepted_them() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = git_repository::init::repository_at(dir.path()).unwrap();
+    let objects: Vec<_> = git(Path::new(&fixture_path()), &["rev-list", "--objects", OTHER])
+        .lines()
+        .map(|line| hex_to_id(&line[..40]))
+        .collect();
+    let commands = vec![command(None, OTHER, "refs/heads/other")];
+    for accept in &[false, true] {
+        let accept = *accept;
+        let hooks = Hooks {
+            pre_receive: Some(Box::new(move |_: &[Command], _: &mut Vec<u8>| accept)),
+            ..Default::default()
+        };
+        let outcome = git_repository::receive_pack(
+            std::io::Cursor::new(request(&commands, &objects)),
+            Vec::new(),
+            &git_dir,
+            &refs_of(&git_dir),
+            hooks,
+            progress::Discard,
+            Options {
+                stateless: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let pack = outcome.pack.expect("a pack was sent");
+        let mut paths: Vec<_> = pack.data_path.into_iter().chain(pack.index_path).collect();
+        paths.sort();
+        assert_eq!(kept_packs(&git_dir), paths);
+        assert_eq!(paths.len(), if accept { 2 } else { 0 });
+    }
+    assert_eq!(read_ref(&git_dir, "refs/heads/other"), Some(OTHER.into()));
+    git(&git_dir, &["fsck", "--strict"]);
+}
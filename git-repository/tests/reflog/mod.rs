@@ -0,0 +1,107 @@
+use crate::{git_command, run};
+use git_object::{bstr::ByteSlice, owned, Sign, Time};
+use git_repository::reflog;
+use std::{fs, path::Path};
+
+const NOW: u32 = 1_600_000_000;
+
+fn git(dir: &Path, env: &[(&str, String)], args: &[&str]) -> String {
+    let output = run(git_command(dir)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .env("GIT_TEST_DATE_NOW", NOW.to_string())
+        .env("TZ", "UTC")
+        .args(args));
+    String::from_utf8(output).unwrap()
+}
+
+/// A repository whose `main` branch got a commit on each of the last `days` days, at noon.
+fn repository(dir: &Path, days: u32) {
+    fs::create_dir(dir).unwrap();
+    git(dir, &[], &["init", "--quiet", "--initial-branch=main"]);
+    for day in (1..=days).rev() {
+        let date = format!("@{} +0000", NOW - day * 86400);
+        git(
+            dir,
+            &[("GIT_COMMITTER_DATE", date.clone()), ("GIT_AUTHOR_DATE", date)],
+            &["commit", "--quiet", "--allow-empty", "-m", &format!("{} days ago", day)],
+        );
+    }
+}
+
+#[test]
+fn reflog_entries_are_parsed() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = dir.path().join("repo");
+    repository(&repo, 2);
+    let entries = reflog::read(repo.join(".git"), "refs/heads/main".into()).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].previous, None);
+    assert_eq!(entries[1].previous, Some(entries[0].new));
+    assert_eq!(
+        entries[1].committer,
+        owned::Signature {
+            name: "a".into(),
+            email: "a@example.com".into(),
+            time: Time {
+                time: NOW - 86400,
+                offset: 0,
+                sign: Sign::Plus,
+            },
+        }
+    );
+    assert_eq!(entries[1].message, "commit: 1 days ago");
+    assert!(reflog::read(repo.join(".git"), "refs/heads/missing".into())
+        .unwrap()
+        .is_empty());
+    assert!(matches!(
+        reflog::from_bytes(b"not a reflog\n", Path::new("log")),
+        Err(reflog::Error::Corrupt(1, _))
+    ));
+}
+
+#[test]
+fn lookups_by_date_and_count_match_git() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = dir.path().join("repo");
+    repository(&repo, 5);
+    let now = Time {
+        time: NOW,
+        offset: 0,
+        sign: Sign::Plus,
+    };
+    for spec in &[
+        "main@{yesterday}",
+        "main@{3 days ago}",
+        "main@{2.days.ago}",
+        "refs/heads/main@{now}",
+        "HEAD@{4 days ago}",
+        "@{1 week ago}",
+        "main@{0}",
+        "main@{2}",
+        "HEAD@{4}",
+    ] {
+        let expected = git(&repo, &[], &["rev-parse", "--quiet", spec]);
+        let id = reflog::lookup(repo.join(".git"), spec.as_bytes().as_bstr(), now)
+            .unwrap()
+            .expect("found");
+        assert_eq!(id.to_string(), expected.trim_end(), "{}", spec);
+    }
+
+    assert_eq!(
+        reflog::lookup(repo.join(".git"), "main@{9}".into(), now).unwrap(),
+        None,
+        "the reflog isn't long enough"
+    );
+    assert_eq!(
+        reflog::lookup(repo.join(".git"), "other@{yesterday}".into(), now).unwrap(),
+        None
+    );
+    assert!(matches!(
+        reflog::lookup(repo.join(".git"), "main".into(), now),
+        Err(reflog::Error::InvalidSpec(_))
+    ));
+    assert!(matches!(
+        reflog::lookup(repo.join(".git"), "main@{whenever}".into(), now),
+        Err(reflog::Error::Date(_))
+    ));
+}
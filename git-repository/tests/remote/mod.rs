@@ -0,0 +1,250 @@
+use git_object::bstr::ByteSlice;
+use git_repository::remote::{self, refspec::Operation, Mapping, Remote};
+use std::path::PathBuf;
+
+mod refspec;
+mod update;
+
+const CONFIG: &[u8] = br#"[remote "origin"]
+	url = https://example.com/repo.git
+	pushurl = git@example.com:repo.git
+	fetch = +refs/heads/*:refs/remotes/origin/*
+	fetch = ^refs/heads/wip/*
+	fetch = refs/tags/v1.0:refs/tags/v1.0
+	push = refs/heads/main
+[remote "fork"]
+	url = ../fork
+[remote "broken"]
+	fetch = refs/heads/*:refs/remotes/broken/main
+"#;
+
+fn config() -> git_config::File {
+    git_config::File::from_bytes(CONFIG).unwrap()
+}
+
+#[test]
+fn names_are_listed_in_order() {
+    assert_eq!(remote::names(&config()), vec!["origin", "fork", "broken"]);
+}
+
+#[test]
+fn remotes_are_read_from_configuration() {
+    let config = config();
+    let origin = Remote::from_config(&config, "origin").unwrap().expect("configured");
+    assert_eq!(origin.urls.len(), 1);
+    assert_eq!(origin.urls[0].to_string(), "https://example.com/repo.git");
+    assert_eq!(origin.push_urls()[0].to_string(), "git@example.com:repo.git");
+    assert_eq!(origin.fetch_specs.len(), 3);
+    assert_eq!(origin.push_specs[0].destination.as_ref().unwrap(), "refs/heads/main");
+
+    let fork = Remote::from_config(&config, "fork").unwrap().expect("configured");
+    assert_eq!(fork.push_urls(), &fork.urls[..], "push urls default to the fetch urls");
+    assert!(fork.fetch_specs.is_empty());
+
+    assert!(Remote::from_config(&config, "missing").unwrap().is_none());
+    assert!(matches!(
+        Remote::from_config(&config, "broken"),
+        Err(remote::Error::RefSpec(_, _))
+    ));
+}
+
+#[test]
+fn fetch_mappings_respect_negative_refspecs() {
+    let origin = Remote::from_config(&config(), "origin").unwrap().unwrap();
+    let refs = [
+        "refs/heads/main",
+        "refs/heads/wip/experiment",
+        "refs/tags/v1.0",
+        "refs/tags/v2.0",
+    ];
+    assert_eq!(
+        origin.fetch_mappings(refs.iter().map(|r| r.as_bytes().as_bstr())),
+        vec![
+            Mapping {
+                remote: "refs/heads/main".into(),
+                local: Some("refs/remotes/origin/main".into()),
+                force: true,
+            },
+            Mapping {
+                remote: "refs/tags/v1.0".into(),
+                local: Some("refs/tags/v1.0".into()),
+                force: false,
+            },
+        ]
+    );
+}
+
+#[test]
+fn credential_helpers_are_read_from_configuration() {
+    use git_credentials::helper::Program;
+    let helpers = git_config::File::from_bytes(
+        b"[credential]\n\thelper = store\n\thelper =\n\thelper = cache --timeout 60\n\thelper = \"!f() { echo; }; f\"\n\tuseHttpPath = true\n",
+    )
+    .unwrap();
+    let cascade = remote::credentials(&helpers);
+    assert_eq!(
+        cascade.programs,
+        vec![
+            Program::Builtin("cache --timeout 60".into()),
+            Program::ExternalShellScript("f() { echo; }; f".into())
+        ],
+        "empty values reset the list"
+    );
+    assert!(cascade.use_http_path);
+    assert!(remote::credentials(&config()).programs.is_empty());
+}
+
+const REWRITES: &[u8] = br#"[url "https://mirror.example.com/"]
+	insteadOf = https://example.com/
+	insteadOf = example:
+[url "https://mirror.example.com/special/"]
+	insteadOf = https://example.com/special
+[url "ssh://git@example.com/"]
+	pushInsteadOf = https://example.com/
+[remote "origin"]
+	url = https://example.com/repo.git
+	url = https://example.com/special-repo.git
+	url = https://other.example.com/repo.git
+[remote "explicit"]
+	url = example:repo.git
+	pushurl = https://example.com/push.git
+"#;
+
+#[test]
+fn urls_are_rewritten_with_the_longest_matching_prefix() {
+    let config = git_config::File::from_bytes(REWRITES).unwrap();
+    let rewrite = |url: &str, operation| remote::rewrite_url(&config, url.into(), operation).to_string();
+    assert_eq!(
+        rewrite("https://example.com/special/repo.git", Operation::Fetch),
+        "https://mirror.example.com/special//repo.git"
+    );
+    assert_eq!(
+        rewrite("example:repo.git", Operation::Fetch),
+        "https://mirror.example.com/repo.git"
+    );
+    assert_eq!(
+        rewrite("https://other.example.com/repo.git", Operation::Fetch),
+        "https://other.example.com/repo.git"
+    );
+    assert_eq!(
+        rewrite("https://example.com/repo.git", Operation::Push),
+        "ssh://git@example.com/repo.git"
+    );
+    assert_eq!(
+        rewrite("example:repo.git", Operation::Push),
+        "https://mirror.example.com/repo.git",
+        "urls to push to fall back to insteadOf"
+    );
+
+    let origin = Remote::from_config(&config, "origin").unwrap().unwrap();
+    let strings = |urls: &[git_url::Url]| urls.iter().map(ToString::to_string).collect::<Vec<_>>();
+    assert_eq!(
+        strings(&origin.urls),
+        vec![
+            "https://mirror.example.com/repo.git",
+            "https://mirror.example.com/special/-repo.git",
+            "https://other.example.com/repo.git"
+        ]
+    );
+    assert_eq!(
+        strings(origin.push_urls()),
+        vec![
+            "ssh://git@example.com/repo.git",
+            "ssh://git@example.com/special-repo.git"
+        ],
+        "only urls rewritten with pushInsteadOf are pushed to"
+    );
+}
+
+#[test]
+fn push_instead_of_does_not_apply_to_configured_push_urls() {
+    let config = git_config::File::from_bytes(REWRITES).unwrap();
+    let explicit = Remote::from_config(&config, "explicit").unwrap().unwrap();
+    assert_eq!(explicit.urls[0].to_string(), "https://mirror.example.com/repo.git");
+    assert_eq!(
+        explicit.push_urls()[0].to_string(),
+        "https://mirror.example.com/push.git",
+        "push urls are only rewritten with insteadOf"
+    );
+}
+
+const HTTP: &[u8] = br#"[http]
+	proxy = http://proxy.example.com:8080
+	extraHeader = X-First: 1
+	sslVerify = true
+	followRedirects = false
+	maxRetries = 3
+[http "https://*.example.com"]
+	proxy = http://wildcard.example.com
+	followRedirects = initial
+	sslVerify = false
+[http "https://git.example.com:443/team/"]
+	sslCAInfo = /etc/team.pem
+	extraHeader =
+	extraHeader = X-Team: 1
+[http "https://user@git.example.com/team/repo.git"]
+	proxy =
+[http]
+	extraHeader = X-Last: 1
+	proxy = http://ignored.example.com
+[http "http://git.example.com"]
+	sslVerify = false
+"#;
+
+#[test]
+fn http_options_are_taken_from_the_most_specific_matching_section() {
+    let config = git_config::File::from_bytes(HTTP).unwrap();
+    let options = |url: &str| remote::http_options(&config, url.into());
+    let no_verify_from_env = std::env::var_os("GIT_SSL_NO_VERIFY").is_some();
+    let ca_info = |configured: Option<&str>| {
+        std::env::var_os("GIT_SSL_CAINFO")
+            .map(PathBuf::from)
+            .or_else(|| configured.map(PathBuf::from))
+    };
+
+    let plain = options("https://example.org/repo.git");
+    assert_eq!(plain.proxy.as_deref(), Some("http://ignored.example.com"));
+    assert_eq!(plain.extra_headers, vec!["X-First: 1", "X-Last: 1"]);
+    assert_eq!(plain.ssl_no_verify, no_verify_from_env);
+    assert_eq!(plain.ssl_ca_info, ca_info(None));
+    assert!(!plain.follow_redirects);
+    assert_eq!(plain.max_retries, 3);
+
+    let team = options("https://Git.Example.com/team/repo.git");
+    assert_eq!(
+        team.proxy.as_deref(),
+        Some("http://wildcard.example.com"),
+        "later plain values don't override more specific ones"
+    );
+    assert!(team.ssl_no_verify, "wildcards match a single label");
+    assert!(team.follow_redirects, "only the initial request is redirected");
+    assert_eq!(team.ssl_ca_info, ca_info(Some("/etc/team.pem")));
+    assert_eq!(
+        team.extra_headers,
+        vec!["X-Team: 1"],
+        "an empty value resets the headers and less specific ones are ignored afterwards"
+    );
+
+    assert_eq!(options("https://user@git.example.com/team/repo.git").proxy, None);
+    assert_eq!(
+        options("https://other@git.example.com/team/repo.git").proxy.as_deref(),
+        Some("http://wildcard.example.com"),
+        "the user has to match"
+    );
+    assert_eq!(
+        options("https://git.example.com/teams/repo.git").ssl_ca_info,
+        ca_info(None),
+        "paths match at component boundaries"
+    );
+    assert_eq!(
+        options("https://example.com/repo.git").ssl_no_verify,
+        no_verify_from_env,
+        "wildcards don't match missing labels"
+    );
+    assert_eq!(
+        options("https://git.example.com:8443/repo.git").ssl_no_verify,
+        no_verify_from_env,
+        "ports have to match"
+    );
+    assert!(options("http://git.example.com:80/repo.git").ssl_no_verify);
+}
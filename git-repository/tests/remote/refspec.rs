@@ -0,0 +1,114 @@
+use git_object::bstr::{BStr, ByteSlice};
+use git_repository::remote::{
+    refspec::{Error, Operation},
+    RefSpec,
+};
+
+fn b(s: &str) -> &BStr {
+    s.as_bytes().as_bstr()
+}
+
+fn fetch(spec: &str) -> Result<RefSpec, Error> {
+    RefSpec::parse(b(spec), Operation::Fetch)
+}
+
+fn push(spec: &str) -> Result<RefSpec, Error> {
+    RefSpec::parse(b(spec), Operation::Push)
+}
+
+#[test]
+fn parse_fetch_specs() {
+    let spec = fetch("+refs/heads/*:refs/remotes/origin/*").unwrap();
+    assert!(spec.force && !spec.negative && spec.is_pattern());
+    assert_eq!(spec.source.as_ref().unwrap(), "refs/heads/*");
+    assert_eq!(spec.destination.as_ref().unwrap(), "refs/remotes/origin/*");
+
+    let spec = fetch("main").unwrap();
+    assert_eq!(spec.destination, None, "only fetched, not stored");
+    let spec = fetch(":refs/heads/head").unwrap();
+    assert_eq!(spec.source.as_ref().unwrap(), "HEAD", "an empty source means HEAD");
+    let spec = fetch("^refs/heads/wip/*").unwrap();
+    assert!(spec.negative);
+    let spec = fetch("49ef5cb06ece2cb5d816cb659526781d156eb05b:refs/heads/fetched").unwrap();
+    assert_eq!(
+        spec.source.as_ref().unwrap(),
+        "49ef5cb06ece2cb5d816cb659526781d156eb05b"
+    );
+}
+
+#[test]
+fn parse_push_specs() {
+    let spec = push("refs/heads/main").unwrap();
+    assert_eq!(spec.destination, spec.source, "the destination defaults to the source");
+    let spec = push(":refs/heads/gone").unwrap();
+    assert_eq!(spec.source, None, "deletes the destination");
+    let spec = push("@:refs/heads/main").unwrap();
+    assert_eq!(spec.source.as_ref().unwrap(), "HEAD");
+    assert!(matches!(push(""), Err(Error::Empty)));
+    assert!(matches!(push("main:"), Err(Error::Empty)));
+}
+
+#[test]
+fn invalid_specs() {
+    assert!(matches!(fetch(""), Err(Error::Empty)));
+    assert!(matches!(
+        fetch("^refs/heads/a:refs/heads/b"),
+        Err(Error::NegativeWithDestination)
+    ));
+    assert!(matches!(fetch("^+refs/heads/a"), Err(Error::NegativeForced)));
+    assert!(matches!(
+        fetch("^49ef5cb06ece2cb5d816cb659526781d156eb05b"),
+        Err(Error::NegativeObjectId)
+    ));
+    assert!(matches!(
+        fetch("refs/heads/*:refs/heads/main"),
+        Err(Error::PatternMismatch)
+    ));
+    assert!(matches!(fetch("refs/*/*:refs/*/*"), Err(Error::MultipleWildcards(_))));
+    assert!(matches!(fetch("refs/heads/a..b"), Err(Error::InvalidName(_, _))));
+}
+
+#[test]
+fn patterns_expand_the_matched_part() {
+    let spec = fetch("+refs/heads/*:refs/remotes/origin/*").unwrap();
+    assert_eq!(
+        spec.expand(b("refs/heads/feature/a")).unwrap(),
+        "refs/remotes/origin/feature/a"
+    );
+    assert_eq!(spec.expand(b("refs/tags/v1.0")), None);
+
+    let spec = fetch("refs/heads/release-*-final:refs/releases/*").unwrap();
+    assert_eq!(
+        spec.expand(b("refs/heads/release-1.0-final")).unwrap(),
+        "refs/releases/1.0"
+    );
+    assert!(!spec.matches(b("refs/heads/release-final")));
+}
+
+#[test]
+fn short_sources_match_the_refs_they_expand_to() {
+    let spec = fetch("main:refs/heads/upstream").unwrap();
+    for name in &[
+        "main",
+        "refs/main",
+        "refs/heads/main",
+        "refs/tags/main",
+        "refs/remotes/main/HEAD",
+    ] {
+        assert!(spec.matches(b(name)), "{}", name);
+    }
+    assert!(!spec.matches(b("refs/heads/main2")));
+    assert_eq!(spec.expand(b("refs/heads/main")).unwrap(), "refs/heads/upstream");
+}
+
+#[test]
+fn specs_serialize_to_their_original_form() {
+    for spec in &[
+        "+refs/heads/*:refs/remotes/origin/*",
+        "^refs/heads/wip/*",
+        "main",
+        "main:refs/heads/b",
+    ] {
+        assert_eq!(fetch(spec).unwrap().to_bstring(), *spec);
+    }
+}
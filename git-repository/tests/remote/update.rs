@@ -0,0 +1,346 @@
+use crate::describe::Graph;
+use git_object::{bstr::ByteSlice, owned, Sign, Time};
+use git_protocol::handshake::Ref;
+use git_repository::remote::{
+    self,
+    refspec::{Operation, RefSpec},
+    update::{self, Mode},
+};
+use std::{fs, path::Path};
+
+struct Fixture {
+    _dir: tempfile::TempDir,
+    git_dir: std::path::PathBuf,
+    graph: Graph,
+    c1: owned::Id,
+    c2: owned::Id,
+    diverged: owned::Id,
+}
+
+/// A repository whose `main` tracking branch can be fast-forwarded to `c2`, while `other` can't be updated to
+/// `diverged` without force.
+fn fixture() -> Fixture {
+    let mut graph = Graph::default();
+    let c1 = graph.commit(1, "c1", &[]);
+    let c2 = graph.commit(2, "c2", &[c1]);
+    let diverged = graph.commit(3, "diverged", &[c1]);
+
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = git_repository::init::repository_at(dir.path()).unwrap();
+    write_loose(&git_dir, "refs/remotes/origin/main", c1);
+    write_loose(&git_dir, "refs/tags/v1.0", c1);
+    fs::write(
+        git_dir.join("packed-refs"),
+        format!(
+            "# pack-refs with: peeled fully-peeled sorted \n{} refs/remotes/origin/other\n",
+            c2.to_sha1_hex_string()
+        ),
+    )
+    .unwrap();
+    Fixture {
+        _dir: dir,
+        git_dir,
+        graph,
+        c1,
+        c2,
+        diverged,
+    }
+}
+
+fn write_loose(git_dir: &Path, name: &str, id: owned::Id) {
+    let path = git_dir.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, format!("{}\n", id.to_sha1_hex_string())).unwrap();
+}
+
+fn remote_refs(f: &Fixture) -> Vec<Ref> {
+    let direct = |path: &str, object| Ref::Direct {
+        path: path.into(),
+        object,
+    };
+    vec![
+        Ref::Symbolic {
+            path: "HEAD".into(),
+            target: "refs/heads/main".into(),
+            object: f.c2,
+        },
+        direct("refs/heads/main", f.c2),
+        direct("refs/heads/other", f.diverged),
+        direct("refs/heads/feature", f.c1),
+        direct("refs/tags/v1.0", f.c2),
+    ]
+}
+
+fn mappings(refs: &[Ref], force: bool) -> Vec<remote::Mapping> {
+    let specs = [
+        format!("{}refs/heads/*:refs/remotes/origin/*", if force { "+" } else { "" }),
+        "refs/tags/*:refs/tags/*".into(),
+    ];
+    let specs: Vec<_> = specs
+        .iter()
+        .map(|s| RefSpec::parse(s.as_bytes().as_bstr(), Operation::Fetch).unwrap())
+        .collect();
+    remote::map(&specs, refs.iter().map(|r| r.unpack().0))
+}
+
+fn options(dry_run: bool) -> update::Options {
+    update::Options {
+        committer: owned::Signature {
+            name: "committer".into(),
+            email: "c@example.com".into(),
+            time: Time {
+                time: 1_000_000_000,
+                offset: 3600,
+                sign: Sign::Plus,
+            },
+        },
+        message: "fetch origin".into(),
+        dry_run,
+        hooks: None,
+    }
+}
+
+fn read_ref(git_dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(git_dir.join(name))
+        .ok()
+        .map(|s| s.trim_end().to_owned())
+}
+
+#[test]
+fn fast_forwards_and_new_refs_are_applied_while_others_are_rejected() {
+    let f = fixture();
+    let refs = remote_refs(&f);
+    let updates = update::apply(
+        &f.git_dir,
+        &mappings(&refs, false),
+        &refs,
+        |id, buf| f.graph.find(id, buf),
+        &options(false),
+    )
+    .unwrap();
+    let modes: Vec<_> = updates.iter().map(|u| (u.local.to_string(), u.mode)).collect();
+    assert_eq!(
+        modes,
+        vec![
+            ("refs/remotes/origin/main".into(), Mode::FastForward),
+            ("refs/remotes/origin/other".into(), Mode::Rejected),
+            ("refs/remotes/origin/feature".into(), Mode::New),
+            ("refs/tags/v1.0".into(), Mode::Rejected),
+        ]
+    );
+    assert_eq!(
+        updates[1].previous,
+        Some(f.c2),
+        "previous values are read from packed refs too"
+    );
+
+    let hex = |id: owned::Id| id.to_sha1_hex_string();
+    assert_eq!(read_ref(&f.git_dir, "refs/remotes/origin/main"), Some(hex(f.c2)));
+    assert_eq!(read_ref(&f.git_dir, "refs/remotes/origin/feature"), Some(hex(f.c1)));
+    assert_eq!(
+        read_ref(&f.git_dir, "refs/remotes/origin/other"),
+        None,
+        "still only packed"
+    );
+    assert_eq!(read_ref(&f.git_dir, "refs/tags/v1.0"), Some(hex(f.c1)));
+
+    assert_eq!(
+        read_ref(&f.git_dir, "logs/refs/remotes/origin/main"),
+        Some(format!(
+            "{} {} committer <c@example.com> 1000000000 +0100\tfetch origin: fast-forward",
+            hex(f.c1),
+            hex(f.c2)
+        ))
+    );
+    assert_eq!(
+        read_ref(&f.git_dir, "logs/refs/remotes/origin/feature"),
+        Some(format!(
+            "{} {} committer <c@example.com> 1000000000 +0100\tfetch origin: storing head",
+            hex(owned::Id::null_sha1()),
+            hex(f.c1)
+        ))
+    );
+    assert!(!f.git_dir.join("logs/refs/tags/v1.0").exists());
+
+    let updates = update::apply(
+        &f.git_dir,
+        &mappings(&refs, false),
+        &refs,
+        |id, buf| f.graph.find(id, buf),
+        &options(false),
+    )
+    .unwrap();
+    assert_eq!(
+        updates[0].mode,
+        Mode::NoChange,
+        "applying the same refs again changes nothing"
+    );
+}
+
+#[test]
+fn forced_mappings_overwrite_diverged_refs() {
+    let f = fixture();
+    let refs = remote_refs(&f);
+    let updates = update::apply(
+        &f.git_dir,
+        &mappings(&refs, true),
+        &refs,
+        |id, buf| f.graph.find(id, buf),
+        &options(false),
+    )
+    .unwrap();
+    assert_eq!(updates[1].mode, Mode::Forced);
+    assert_eq!(
+        read_ref(&f.git_dir, "refs/remotes/origin/other"),
+        Some(f.diverged.to_sha1_hex_string())
+    );
+    assert_eq!(updates[3].mode, Mode::Rejected, "tags need their own forced refspec");
+}
+
+#[test]
+fn dry_runs_leave_refs_untouched() {
+    let f = fixture();
+    let refs = remote_refs(&f);
+    let updates = update::apply(
+        &f.git_dir,
+        &mappings(&refs, true),
+        &refs,
+        |id, buf| f.graph.find(id, buf),
+        &options(true),
+    )
+    .unwrap();
+    assert_eq!(updates.iter().filter(|u| u.mode.is_update()).count(), 3);
+    assert_eq!(
+        read_ref(&f.git_dir, "refs/remotes/origin/main"),
+        Some(f.c1.to_sha1_hex_string())
+    );
+    assert!(!f.git_dir.join("refs/remotes/origin/feature").exists());
+    assert!(!f.git_dir.join("logs").join("refs").exists());
+}
+
+#[test]
+fn locked_refs_are_not_written() {
+    let f = fixture();
+    let refs = remote_refs(&f);
+    fs::write(f.git_dir.join("refs/remotes/origin/main.lock"), b"").unwrap();
+    assert!(matches!(
+        update::apply(
+            &f.git_dir,
+            &mappings(&refs, false),
+            &refs,
+            |id, buf| f.graph.find(id, buf),
+            &options(false),
+        ),
+        Err(update::Error::Locked(_))
+    ));
+    assert_eq!(
+        read_ref(&f.git_dir, "refs/remotes/origin/main"),
+        Some(f.c1.to_sha1_hex_string())
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn the_reference_transaction_hook_sees_all_updates_and_may_reject_them() {
+    use std::os::unix::fs::PermissionsExt;
+    let f = fixture();
+    let refs = remote_refs(&f);
+    let path = f.git_dir.join("hooks/reference-transaction");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(
+        &path,
+        concat!(
+            "#!/bin/sh\n",
+            "echo $1 >> \"$GIT_DIR/transaction.log\"\n",
+            "cat >> \"$GIT_DIR/transaction.log\"\n",
+            "[ -e \"$GIT_DIR/reject\" ] && echo no && exit 1\n",
+            "exit 0\n"
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    let log = f.git_dir.join("transaction.log");
+    let with_hooks = update::Options {
+        hooks: Some(git_repository::hooks::Runner::from_git_dir(&f.git_dir)),
+        ..options(false)
+    };
+
+    fs::write(f.git_dir.join("reject"), b"").unwrap();
+    assert!(matches!(
+        update::apply(&f.git_dir, &mappings(&refs, false), &refs, |id, buf| f.graph.find(id, buf), &with_hooks),
+        Err(update::Error::Rejected(messages)) if messages == "no\n"
+    ));
+    assert_eq!(
+        read_ref(&f.git_dir, "refs/remotes/origin/main"),
+        Some(f.c1.to_sha1_hex_string()),
+        "nothing is written"
+    );
+    let updates = format!(
+        "{} {} refs/remotes/origin/main\n{} {} refs/remotes/origin/feature\n",
+        f.c1,
+        f.c2,
+        owned::Id::null_sha1(),
+        f.c1
+    );
+    assert_eq!(
+        fs::read_to_string(&log).unwrap(),
+        format!("prepared\n{}aborted\n{}", updates, updates)
+    );
+
+    fs::remove_file(f.git_dir.join("reject")).unwrap();
+    fs::remove_file(&log).unwrap();
+    update::apply(
+        &f.git_dir,
+        &mappings(&refs, false),
+        &refs,
+        |id, buf| f.graph.find(id, buf),
+        &with_hooks,
+    )
+    .unwrap();
+    assert_eq!(
+        read_ref(&f.git_dir, "refs/remotes/origin/main"),
+        Some(f.c2.to_sha1_hex_string())
+    );
+    assert_eq!(
+        fs::read_to_string(&log).unwrap(),
+        format!("prepared\n{}committed\n{}", updates, updates)
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn refs_changed_after_they_were_read_are_not_overwritten() {
+    use std::os::unix::fs::PermissionsExt;
+    let f = fixture();
+    let refs = remote_refs(&f);
+    let path = f.git_dir.join("hooks/reference-transaction");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(
+        &path,
+        format!(
+            "#!/bin/sh\n[ $1 = prepared ] && echo {} > \"$GIT_DIR/refs/remotes/origin/main\"\nexit 0\n",
+            f.diverged
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    let with_hooks = update::Options {
+        hooks: Some(git_repository::hooks::Runner::from_git_dir(&f.git_dir)),
+        ..options(false)
+    };
+
+    assert!(matches!(
+        update::apply(&f.git_dir, &mappings(&refs, false), &refs, |id, buf| f.graph.find(id, buf), &with_hooks),
+        Err(update::Error::RefChanged(name, expected, actual))
+            if name == "refs/remotes/origin/main" && expected == Some(f.c1) && actual == Some(f.diverged)
+    ));
+    assert_eq!(
+        read_ref(&f.git_dir, "refs/remotes/origin/main"),
+        Some(f.diverged.to_sha1_hex_string()),
+        "the concurrent change is kept"
+    );
+    assert!(
+        !f.git_dir.join("logs/refs/remotes/origin/main").exists(),
+        "the reflog is only written once the ref was"
+    );
+}
@@ -0,0 +1,114 @@
+use git_object::owned;
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Output, Stdio},
+};
+
+pub fn hex_to_id(hex: &str) -> owned::Id {
+    owned::Id::from_40_bytes_in_hex(hex.trim_end().as_bytes()).expect("40 bytes hex")
+}
+
+/// A git command running in `dir` with a fixed identity and dates, which ignores the system and user configuration.
+pub fn git_command(dir: &Path) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(dir)
+        .args(["-c", "user.name=a", "-c", "user.email=a@example.com"])
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("HOME", env!("CARGO_TARGET_TMPDIR"))
+        .env_remove("XDG_CONFIG_HOME")
+        .env("GIT_AUTHOR_DATE", "1600000000 +0000")
+        .env("GIT_COMMITTER_DATE", "1600000000 +0000");
+    cmd
+}
+
+/// Run `cmd` with `input` on stdin and return its output, whether it succeeded or not.
+pub fn run_with_input(cmd: &mut Command, input: &[u8]) -> Output {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("git can be run");
+    child
+        .stdin
+        .take()
+        .expect("piped")
+        .write_all(input)
+        .expect("input written");
+    child.wait_with_output().expect("git can be waited for")
+}
+
+fn successful(cmd: &Command, output: Output) -> Vec<u8> {
+    assert!(
+        output.status.success(),
+        "{:?}: {}",
+        cmd,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output.stdout
+}
+
+/// Run `cmd` and return its output, panicking with its error output if it failed.
+pub fn run(cmd: &mut Command) -> Vec<u8> {
+    let output = run_with_input(cmd, b"");
+    successful(cmd, output)
+}
+
+/// Run git with `args` in `dir` and return its output, whether it succeeded or not.
+pub fn git_output(dir: &Path, args: &[&str]) -> Output {
+    run_with_input(git_command(dir).args(args), b"")
+}
+
+/// Run git with `args` in `dir` with `input` on stdin and return its output, panicking if it failed.
+pub fn git_with_input(dir: &Path, args: &[&str], input: &[u8]) -> Vec<u8> {
+    let mut cmd = git_command(dir);
+    cmd.args(args);
+    let output = run_with_input(&mut cmd, input);
+    successful(&cmd, output)
+}
+
+/// Run git with `args` in `dir` and return its output, which must be UTF-8, panicking if it failed.
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    String::from_utf8(run(git_command(dir).args(args))).expect("UTF-8 output")
+}
+
+mod apply;
+mod attributes;
+mod bisect;
+mod blame;
+mod bundle;
+mod clone;
+mod commit;
+mod date;
+mod describe;
+mod dirwalk;
+mod discover;
+mod dumb;
+mod fast_export;
+mod fast_import;
+mod format_patch;
+mod hooks;
+mod ignore;
+mod index;
+mod init;
+mod lfs;
+mod mailmap;
+mod merge;
+mod pretty;
+mod push;
+mod rebase;
+mod receive_pack;
+mod reflog;
+mod remote;
+mod revision;
+mod revwalk;
+mod sequencer;
+mod shallow;
+mod sparse;
+mod stash;
+mod status;
+mod submodule;
+mod tag;
+mod upload_pack;
+mod worktree;
@@ -0,0 +1,49 @@
+use crate::hex_to_id;
+use git_object::owned;
+use git_odb::compound;
+use git_repository::revision;
+use std::path::Path;
+
+fn resolve(spec: &str) -> Result<owned::Id, revision::Error> {
+    let git_dir = Path::new("tests/fixtures/repos/files.git");
+    let db = compound::Db::at(git_dir.join("objects")).unwrap();
+    revision::resolve(git_dir, &db, spec.into())
+}
+
+#[test]
+fn refs_are_found_by_their_short_and_full_names() {
+    let main = hex_to_id("877c3ad4e85fa4408811d3fcc38aef2500f04ade");
+    for spec in &["HEAD", "main", "heads/main", "refs/heads/main"] {
+        assert_eq!(resolve(spec).unwrap(), main, "{}", spec);
+    }
+    assert_eq!(
+        resolve("other").unwrap(),
+        hex_to_id("cecbafcde689e1bc457bf13334c3d934200802a1")
+    );
+    assert_eq!(
+        resolve("v1.0").unwrap(),
+        hex_to_id("17934c4602e90c793a9660b02d49b6a678772104"),
+        "tags resolve to the tag object"
+    );
+}
+
+#[test]
+fn object_ids_are_found_by_unique_prefix() {
+    let tree = hex_to_id("341d144518e7805a98735c5e0919c89c622aaba2");
+    assert_eq!(resolve("341d").unwrap(), tree);
+    assert_eq!(resolve("341D1445").unwrap(), tree);
+    assert_eq!(resolve(&tree.to_string()).unwrap(), tree);
+    let missing = "ffffffffffffffffffffffffffffffffffffffff";
+    assert_eq!(
+        resolve(missing).unwrap(),
+        hex_to_id(missing),
+        "full ids don't have to exist"
+    );
+}
+
+#[test]
+fn unknown_revisions_are_not_found() {
+    for spec in &["ffff", "34", "nothing", "main~1"] {
+        assert!(matches!(resolve(spec), Err(revision::Error::NotFound(_))), "{}", spec);
+    }
+}
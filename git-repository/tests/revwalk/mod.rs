@@ -0,0 +1,216 @@
+use crate::{git_command, run};
+use git_object::{borrowed, owned};
+use git_odb::{compound, pack};
+use git_repository::{
+    commit_graph,
+    revwalk::{self, Options, Statistics},
+};
+use std::{fs, path::Path};
+
+/// Run git with commits dated `time` seconds after a fixed point in time.
+fn git_at(dir: &Path, time: u32, args: &[&str]) -> String {
+    let date = format!("{} +0000", 1_000_000_000 + time);
+    let output = run(git_command(dir)
+        .args(args)
+        .env("GIT_AUTHOR_DATE", &date)
+        .env("GIT_COMMITTER_DATE", &date));
+    String::from_utf8(output).unwrap()
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    git_at(dir, 0, args)
+}
+
+/// Write `files` and commit them at `time`.
+fn commit(dir: &Path, time: u32, files: &[(&str, &str)]) {
+    for (path, content) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    git(dir, &["add", "."]);
+    git_at(
+        dir,
+        time,
+        &["commit", "--quiet", "--allow-empty", "-m", &time.to_string()],
+    );
+}
+
+/// A history with merges, where some branches change `src` and others only `docs`.
+fn repository(dir: &Path) {
+    git(dir, &["init", "--quiet", "--initial-branch=main"]);
+    commit(dir, 100, &[("README", "readme")]);
+    commit(dir, 200, &[("src/lib.rs", "1"), ("docs/index.md", "1")]);
+    git(dir, &["branch", "docs"]);
+    git(dir, &["branch", "feature"]);
+    commit(dir, 300, &[("src/lib.rs", "2")]);
+    commit(dir, 400, &[("README", "readme 2")]);
+    git(dir, &["checkout", "--quiet", "docs"]);
+    commit(dir, 500, &[("docs/index.md", "2")]);
+    commit(dir, 600, &[("docs/other.md", "1")]);
+    git(dir, &["checkout", "--quiet", "feature"]);
+    commit(dir, 700, &[("src/deep/mod.rs", "1")]);
+    git(dir, &["checkout", "--quiet", "main"]);
+    git_at(dir, 800, &["merge", "--quiet", "--no-edit", "docs"]);
+    git_at(dir, 900, &["merge", "--quiet", "--no-edit", "feature"]);
+    commit(dir, 1000, &[("src/lib.rs", "3"), ("docs/index.md", "3")]);
+    for n in 0..20 {
+        commit(dir, 1100 + n, &[("README", &format!("readme {}", n))]);
+    }
+}
+
+/// The commits and their parents as listed by `git rev-list --parents`.
+fn rev_list(dir: &Path, paths: &[&str]) -> Vec<Vec<String>> {
+    let mut args = vec!["rev-list", "--parents", "main", "--"];
+    args.extend_from_slice(paths);
+    git(dir, &args)
+        .lines()
+        .map(|line| line.split(' ').map(ToOwned::to_owned).collect())
+        .collect()
+}
+
+fn walk(dir: &Path, paths: &[&str], commit_graph: Option<commit_graph::File>) -> (Vec<Vec<String>>, Statistics) {
+    let db = compound::Db::at(dir.join(".git/objects")).unwrap();
+    let tip = owned::Id::from_40_bytes_in_hex(git(dir, &["rev-parse", "main"]).trim_end().as_bytes()).unwrap();
+    let mut walk = revwalk::walk(
+        Some(tip),
+        |id, buf| {
+            let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+            borrowed::Object::from_bytes(object.kind, object.data).ok()
+        },
+        Options {
+            paths: paths.iter().map(|p| (*p).into()).collect(),
+            commit_graph,
+        },
+    );
+    let commits = walk
+        .by_ref()
+        .map(|commit| {
+            let commit = commit.unwrap();
+            std::iter::once(commit.id)
+                .chain(commit.parents)
+                .map(|id| id.to_string())
+                .collect()
+        })
+        .collect();
+    (commits, walk.statistics())
+}
+
+const PATHS: &[&[&str]] = &[
+    &[],
+    &["src"],
+    &["src/"],
+    &["docs"],
+    &["docs/index.md"],
+    &["src/deep"],
+    &["README"],
+    &["src", "docs/other.md"],
+    &["missing"],
+];
+
+#[test]
+fn history_is_simplified_and_parents_are_rewritten_like_git_does() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    for paths in PATHS {
+        let (commits, statistics) = walk(dir.path(), paths, None);
+        assert_eq!(commits, rev_list(dir.path(), paths), "{:?}", paths);
+        assert_eq!(statistics.bloom_filter_hits, 0);
+    }
+}
+
+#[test]
+fn bloom_filters_of_commit_graphs_avoid_tree_diffs() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    git(dir.path(), &["commit-graph", "write", "--reachable", "--changed-paths"]);
+    let graph = commit_graph::File::at(dir.path().join(".git"))
+        .unwrap()
+        .expect("written");
+
+    let all = git(dir.path(), &["rev-list", "main"]);
+    assert_eq!(graph.num_commits() as usize, all.lines().count());
+    for hex in all.lines() {
+        let id = owned::Id::from_40_bytes_in_hex(hex.as_bytes()).unwrap();
+        let position = graph.lookup(id.to_borrowed()).expect("all commits are in the graph");
+        assert_eq!(graph.id_at(position), id.to_borrowed());
+    }
+    let head =
+        owned::Id::from_40_bytes_in_hex(git(dir.path(), &["rev-parse", "main~20"]).trim_end().as_bytes()).unwrap();
+    let filter = graph.bloom_filter(&head).expect("filters were written");
+    for changed in &["src", "src/lib.rs", "docs", "docs/index.md"] {
+        assert!(
+            filter.may_contain(changed.as_bytes()),
+            "no false negatives for {}",
+            changed
+        );
+    }
+    assert!(!filter.may_contain(b"README"));
+    assert_eq!(graph.lookup(owned::Id::null_sha1().to_borrowed()), None);
+
+    for paths in PATHS {
+        let (without_graph, plain) = walk(dir.path(), paths, None);
+        let (commits, accelerated) = walk(dir.path(), paths, Some(graph.clone()));
+        assert_eq!(commits, without_graph, "{:?}", paths);
+        if !paths.is_empty() {
+            assert!(accelerated.bloom_filter_hits > 0, "{:?}", paths);
+            if !paths.contains(&"README") {
+                assert!(
+                    accelerated.bloom_filter_hits >= 20,
+                    "the commits changing only the README are skipped without diff"
+                );
+            }
+            assert!(accelerated.tree_diffs + accelerated.bloom_filter_hits <= plain.tree_diffs);
+        }
+    }
+}
+
+#[test]
+fn commit_graph_checksums_are_verified_on_request() {
+    use git_odb::open;
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "--quiet"]);
+    git(dir.path(), &["commit", "--quiet", "--allow-empty", "-m", "first"]);
+    git(dir.path(), &["commit-graph", "write", "--reachable"]);
+    let git_dir = dir.path().join(".git");
+    let checksum = open::Options {
+        verify: open::Verify::Checksum,
+    };
+    assert!(commit_graph::File::at_with_options(&git_dir, checksum)
+        .unwrap()
+        .is_some());
+
+    let path = git_dir.join(commit_graph::FILE_NAME);
+    let mut data = fs::read(&path).unwrap();
+    let last_data_byte = data.len() - 21;
+    data[last_data_byte] ^= 0xff;
+    fs::remove_file(&path).unwrap();
+    fs::write(&path, data).unwrap();
+    assert!(commit_graph::File::at(&git_dir).unwrap().is_some());
+    assert!(matches!(
+        commit_graph::File::at_with_options(&git_dir, checksum),
+        Err(commit_graph::Error::ChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn commit_graphs_with_a_corrupt_fanout_are_rejected() {
+    use std::convert::TryInto;
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "--quiet"]);
+    git(dir.path(), &["commit", "--quiet", "--allow-empty", "-m", "first"]);
+    git(dir.path(), &["commit-graph", "write", "--reachable"]);
+    let path = dir.path().join(".git").join(commit_graph::FILE_NAME);
+    let mut data = fs::read(&path).unwrap();
+    let table = &data[8..8 + usize::from(data[6]) * 12];
+    let fanout = table
+        .chunks(12)
+        .find(|entry| &entry[..4] == b"OIDF")
+        .map(|entry| u64::from_be_bytes(entry[4..12].try_into().unwrap()) as usize)
+        .expect("a fanout chunk");
+    data[fanout..fanout + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+    assert!(matches!(
+        commit_graph::File::from_bytes(data),
+        Err(commit_graph::Error::Corrupt("the fanout chunk isn't ascending"))
+    ));
+}
@@ -0,0 +1,166 @@
+use crate::{git, git_output, hex_to_id};
+use git_object::{borrowed, owned, Sign, Time};
+use git_odb::{compound, pack};
+use git_repository::sequencer::{self, Error, Options, Outcome};
+use std::{fs, path::Path};
+
+fn id(dir: &Path, rev: &str) -> owned::Id {
+    hex_to_id(&git(dir, &["rev-parse", rev]))
+}
+
+fn committer() -> owned::Signature {
+    owned::Signature {
+        name: "a".into(),
+        email: "a@example.com".into(),
+        time: Time {
+            time: 1_600_000_000,
+            offset: 0,
+            sign: Sign::Plus,
+        },
+    }
+}
+
+fn commit(dir: &Path, message: &str, files: &[(&str, &str)]) {
+    for (path, content) in files {
+        fs::write(dir.join(path), content).unwrap();
+    }
+    git(dir, &["add", "--all"]);
+    git(dir, &["commit", "--quiet", "-m", message]);
+}
+
+/// Two identical repositories, a branch `topic` with changes to pick and a `main` branch with other changes.
+fn repositories() -> (tempfile::TempDir, tempfile::TempDir) {
+    let dirs = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    for dir in &[dirs.0.path(), dirs.1.path()] {
+        git(dir, &["init", "--quiet", "--initial-branch=main"]);
+        commit(dir, "base", &[("a", "1\n2\n3\n"), ("b", "1\n2\n3\n")]);
+        git(dir, &["checkout", "--quiet", "-b", "topic"]);
+        commit(dir, "change a", &[("a", "1\n2\nthree\n")]);
+        commit(dir, "change b", &[("b", "1\ntopic\n3\n")]);
+        git(dir, &["checkout", "--quiet", "main"]);
+        commit(dir, "main", &[("a", "one\n2\n3\n"), ("b", "1\nmain\n3\n")]);
+    }
+    dirs
+}
+
+fn options(reference: Option<&str>) -> Options {
+    Options {
+        committer: committer(),
+        reference: reference.map(Into::into),
+    }
+}
+
+fn cherry_pick(dir: &Path, rev: &str, onto: &str, options: Options) -> Result<Outcome, Error> {
+    let db = compound::Db::at(dir.join(".git/objects")).unwrap();
+    sequencer::cherry_pick(
+        dir.join(".git"),
+        id(dir, rev),
+        id(dir, onto),
+        |id, buf| {
+            let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+            borrowed::Object::from_bytes(object.kind, object.data).ok()
+        },
+        options,
+    )
+}
+
+fn revert(dir: &Path, rev: &str, options: Options) -> Result<Outcome, Error> {
+    let db = compound::Db::at(dir.join(".git/objects")).unwrap();
+    sequencer::revert(
+        dir.join(".git"),
+        id(dir, rev),
+        |id, buf| {
+            let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+            borrowed::Object::from_bytes(object.kind, object.data).ok()
+        },
+        options,
+    )
+}
+
+fn last_reflog_message(dir: &Path) -> String {
+    git(dir, &["reflog", "-1", "--format=%gs", "main"])
+}
+
+#[test]
+fn cherry_pick_creates_the_same_commit_as_git() {
+    let (ours, theirs) = repositories();
+    let (ours, theirs) = (ours.path(), theirs.path());
+    let outcome = cherry_pick(ours, "topic~1", "main", options(Some("HEAD"))).unwrap();
+    git(theirs, &["cherry-pick", "topic~1"]);
+    assert_eq!(outcome, Outcome::Commit(id(theirs, "main")));
+    assert_eq!(id(ours, "main"), id(theirs, "main"), "HEAD was followed");
+    assert_eq!(last_reflog_message(ours), last_reflog_message(theirs));
+    assert_eq!(
+        git(ours, &["reflog", "-1", "--format=%gs", "HEAD"]),
+        git(theirs, &["reflog", "-1", "--format=%gs", "HEAD"])
+    );
+
+    let tip = id(ours, "main");
+    let outcome = cherry_pick(ours, "topic~1", "main", options(None)).unwrap();
+    assert_eq!(outcome, Outcome::Empty, "the changes are already contained");
+    let outcome = cherry_pick(ours, "topic~1", "main~1", options(None)).unwrap();
+    assert_eq!(outcome, Outcome::Commit(tip), "only the commit is created");
+    assert_eq!(id(ours, "main"), tip);
+
+    assert!(matches!(
+        cherry_pick(ours, "topic~1", "main~1", options(Some("main"))),
+        Err(Error::RefChanged(..))
+    ));
+}
+
+#[test]
+fn conflicts_leave_the_same_state_as_git() {
+    let (ours, theirs) = repositories();
+    let (ours, theirs) = (ours.path(), theirs.path());
+    let outcome = cherry_pick(ours, "topic", "main", options(Some("HEAD"))).unwrap();
+    assert!(!git_output(theirs, &["cherry-pick", "topic"]).status.success());
+    let merged = match outcome {
+        Outcome::Conflict(merged) => merged,
+        _ => panic!("expected a conflict"),
+    };
+    assert_eq!(merged.conflicts.len(), 1);
+    assert_eq!(merged.conflicts[0].path, "b");
+    for file in &["CHERRY_PICK_HEAD", "MERGE_MSG", "AUTO_MERGE"] {
+        assert_eq!(
+            fs::read_to_string(ours.join(".git").join(file)).unwrap(),
+            fs::read_to_string(theirs.join(".git").join(file)).unwrap(),
+            "{}",
+            file
+        );
+    }
+    assert_eq!(
+        git(ours, &["cat-file", "-p", &format!("{}:b", merged.tree)]),
+        fs::read_to_string(theirs.join("b")).unwrap()
+    );
+    assert_eq!(id(ours, "main"), id(theirs, "main"), "HEAD didn't move");
+}
+
+#[test]
+fn revert_creates_the_same_commit_as_git() {
+    let (ours, theirs) = repositories();
+    let (ours, theirs) = (ours.path(), theirs.path());
+    git(ours, &["checkout", "--quiet", "topic"]);
+    git(theirs, &["checkout", "--quiet", "topic"]);
+    let outcome = revert(ours, "topic~1", options(Some("HEAD"))).unwrap();
+    git(theirs, &["revert", "--no-edit", "topic~1"]);
+    assert_eq!(outcome, Outcome::Commit(id(theirs, "topic")));
+    assert_eq!(id(ours, "topic"), id(theirs, "topic"));
+    assert_eq!(
+        git(ours, &["reflog", "-1", "--format=%gs", "topic"]),
+        git(theirs, &["reflog", "-1", "--format=%gs", "topic"])
+    );
+
+    let outcome = revert(ours, "topic~2", options(None)).unwrap();
+    assert_eq!(outcome, Outcome::Empty, "the changes were reverted already");
+    let outcome = revert(ours, "topic~3", options(Some("HEAD"))).unwrap();
+    assert!(!git_output(theirs, &["revert", "--no-edit", "topic~3"]).status.success());
+    assert!(matches!(outcome, Outcome::Conflict(_)));
+    for file in &["REVERT_HEAD", "MERGE_MSG", "AUTO_MERGE"] {
+        assert_eq!(
+            fs::read_to_string(ours.join(".git").join(file)).unwrap(),
+            fs::read_to_string(theirs.join(".git").join(file)).unwrap(),
+            "{}",
+            file
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use crate::hex_to_id;
+use git_repository::shallow::{self, Boundary, Update};
+
+const A: &str = "24adfb6e5416979f71a187c413d45cd68af9a5b1";
+const B: &str = "134a0f80e73cdd97a5c865119e82d2de936ba71e";
+
+#[test]
+fn a_missing_file_means_the_repository_is_not_shallow() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let boundary = Boundary::at(dir.path())?;
+    assert!(!boundary.is_shallow());
+    Ok(())
+}
+
+#[test]
+fn parse_ignores_empty_lines_and_rejects_invalid_ones() -> Result<(), Box<dyn std::error::Error>> {
+    let boundary = Boundary::from_bytes(format!("{}\n\n{}\n", A, B).as_bytes())?;
+    assert_eq!(
+        boundary.commits().collect::<Vec<_>>(),
+        vec![hex_to_id(B), hex_to_id(A)],
+        "sorted"
+    );
+    assert!(boundary.contains(&hex_to_id(A)));
+    assert!(matches!(
+        Boundary::from_bytes(format!("{}\nnot-hex\n", A).as_bytes()),
+        Err(shallow::Error::Parse(2))
+    ));
+    Ok(())
+}
+
+#[test]
+fn deepen_updates_roundtrip_through_the_shallow_file() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let mut boundary = Boundary::default();
+    boundary.apply(vec![Update::Shallow(hex_to_id(A)), Update::Shallow(hex_to_id(B))]);
+    boundary.write_to(dir.path())?;
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join(shallow::FILE_NAME))?,
+        format!("{}\n{}\n", B, A)
+    );
+    assert_eq!(Boundary::at(dir.path())?, boundary);
+    assert!(
+        !dir.path().join("shallow.lock").exists(),
+        "the lock is moved into place"
+    );
+
+    boundary.apply(vec![Update::Unshallow(hex_to_id(A)), Update::Unshallow(hex_to_id(B))]);
+    assert!(!boundary.is_shallow());
+    boundary.write_to(dir.path())?;
+    assert!(
+        !dir.path().join(shallow::FILE_NAME).exists(),
+        "the file is removed once the repository is complete"
+    );
+    Ok(())
+}
+
+#[test]
+fn writing_fails_if_the_lock_is_held() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("shallow.lock"), b"")?;
+    let mut boundary = Boundary::default();
+    boundary.apply(Some(Update::Shallow(hex_to_id(A))));
+    assert!(boundary.write_to(dir.path()).is_err());
+    assert!(dir.path().join("shallow.lock").exists(), "other locks are not removed");
+    Ok(())
+}
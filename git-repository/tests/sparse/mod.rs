@@ -0,0 +1,176 @@
+use crate::git;
+use git_features::progress;
+use git_object::bstr::ByteSlice;
+use git_repository::{clone, sparse::Patterns};
+use std::{collections::BTreeSet, fs, path::Path};
+
+const FILES: &[&str] = &[
+    "README",
+    "a/file",
+    "a/b/file",
+    "a/b/c/deep",
+    "a/other/file",
+    "docs/index.md",
+    "docs/keep/file.md",
+    "src/main.rs",
+    "src/docs/file",
+    "weird[dir]/file",
+];
+
+fn repository(dir: &Path) {
+    fs::create_dir(dir).unwrap();
+    git(dir, &["init", "--quiet"]);
+    for file in FILES {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, *file).unwrap();
+    }
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "--quiet", "-m", "files"]);
+}
+
+/// The files git checked out, which are the ones without skip-worktree bit.
+fn checked_out_by_git(dir: &Path) -> BTreeSet<String> {
+    git(dir, &["ls-files", "-t"])
+        .lines()
+        .filter_map(|line| line.strip_prefix("H "))
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+fn included(patterns: &Patterns) -> BTreeSet<String> {
+    FILES
+        .iter()
+        .filter(|file| patterns.is_included((**file).into()))
+        .map(|file| file.to_string())
+        .collect()
+}
+
+fn files_in(dir: &Path) -> BTreeSet<String> {
+    let mut files = BTreeSet::new();
+    let mut directories = vec![dir.to_owned()];
+    while let Some(directory) = directories.pop() {
+        for entry in fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.ends_with(".git") {
+                continue;
+            }
+            if path.is_dir() {
+                assert!(fs::read_dir(&path).unwrap().next().is_some(), "no empty directories");
+                directories.push(path);
+            } else {
+                files.insert(path.strip_prefix(dir).unwrap().to_str().unwrap().to_owned());
+            }
+        }
+    }
+    files
+}
+
+#[test]
+fn cone_patterns_are_the_same_as_the_ones_of_git() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = dir.path().join("repo");
+    repository(&repo);
+    for directories in &[
+        vec!["a/b"],
+        vec!["a/b/c", "docs"],
+        vec!["a", "a/b"],
+        vec!["weird[dir]"],
+        vec![],
+    ] {
+        let mut args = vec!["sparse-checkout", "set", "--cone", "--skip-checks"];
+        args.extend(directories.iter());
+        git(&repo, &args);
+        let expected = fs::read(repo.join(".git/info/sparse-checkout")).unwrap();
+        let patterns = Patterns::cone(directories.iter().map(|d| d.as_bytes().as_bstr()));
+        assert_eq!(patterns.to_bstring(), expected.as_bstr(), "{:?}", directories);
+        assert_eq!(Patterns::from_bytes(&expected, true).unwrap(), patterns);
+        assert_eq!(included(&patterns), checked_out_by_git(&repo), "{:?}", directories);
+    }
+
+    for not_cone in &["/a/*\n", "docs/\n", "!/a/*/\n"] {
+        assert!(Patterns::from_bytes(not_cone.as_bytes(), true).is_err(), "{}", not_cone);
+    }
+}
+
+#[test]
+fn non_cone_patterns_match_like_in_git() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = dir.path().join("repo");
+    repository(&repo);
+    for patterns in &[
+        vec!["/*", "!/*/"],
+        vec!["*.md"],
+        vec!["docs/", "!keep/"],
+        vec!["/a/", "!/a/b/", "/a/b/c/"],
+        vec!["file", "!a/file"],
+        vec!["/src/*", "README"],
+    ] {
+        let mut args = vec!["sparse-checkout", "set", "--no-cone"];
+        args.extend(patterns.iter());
+        git(&repo, &args);
+        let data = fs::read(repo.join(".git/info/sparse-checkout")).unwrap();
+        let parsed = Patterns::from_bytes(&data, false).unwrap();
+        assert_eq!(parsed.to_bstring(), data.as_bstr());
+        assert_eq!(included(&parsed), checked_out_by_git(&repo), "{:?}", patterns);
+    }
+}
+
+#[test]
+fn sparse_clones_only_check_out_included_files() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let repo = dir.path().join("repo");
+    repository(&repo);
+
+    let worktree = dir.path().join("clone");
+    let patterns = Patterns::cone(vec!["a/b".as_bytes().as_bstr(), b"docs/keep".as_bstr()]);
+    let outcome = clone(
+        &format!("file://{}", repo.display()),
+        &worktree,
+        progress::Discard,
+        clone::Options {
+            sparse: Some(patterns.clone()),
+            ..Default::default()
+        },
+    )?;
+    let expected = included(&patterns);
+    assert_eq!(
+        expected.iter().map(String::as_str).collect::<Vec<_>>(),
+        vec![
+            "README",
+            "a/b/c/deep",
+            "a/b/file",
+            "a/file",
+            "docs/index.md",
+            "docs/keep/file.md"
+        ]
+    );
+    assert_eq!(outcome.checked_out_files, expected.len());
+    assert_eq!(files_in(&worktree), expected);
+
+    assert_eq!(
+        git(&worktree, &["status", "--porcelain"]),
+        "",
+        "excluded files skip the worktree"
+    );
+    let skipped: BTreeSet<String> = git(&worktree, &["ls-files", "-t"])
+        .lines()
+        .filter_map(|line| line.strip_prefix("S "))
+        .map(ToOwned::to_owned)
+        .collect();
+    let all: BTreeSet<String> = git(&worktree, &["ls-tree", "-r", "--name-only", "HEAD"])
+        .lines()
+        .map(ToOwned::to_owned)
+        .collect();
+    assert_eq!(skipped, all.difference(&expected).cloned().collect());
+    assert_eq!(git(&worktree, &["sparse-checkout", "list"]), "a/b\ndocs/keep\n");
+    assert_eq!(git(&worktree, &["config", "core.sparseCheckoutCone"]), "true\n");
+    assert_eq!(
+        Patterns::from_config(
+            worktree.join(".git"),
+            &git_config::File::at(worktree.join(".git/config"))?
+        )?,
+        Some(patterns)
+    );
+    Ok(())
+}
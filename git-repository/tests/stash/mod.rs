@@ -0,0 +1,171 @@
+use crate::{git, git_command, hex_to_id, run_with_input};
+use git_object::{borrowed, owned, Sign, Time};
+use git_odb::{compound, pack};
+use git_repository::stash::{self, Error, Options};
+use std::{fs, path::Path};
+
+fn git_with_env(dir: &Path, args: &[&str], env: &[(&str, &str)]) -> std::process::Output {
+    run_with_input(git_command(dir).args(args).envs(env.iter().copied()), b"")
+}
+
+fn rev(dir: &Path, rev: &str) -> owned::Id {
+    hex_to_id(&git(dir, &["rev-parse", rev]))
+}
+
+fn find(dir: &Path) -> impl for<'b> FnMut(borrowed::Id<'_>, &'b mut Vec<u8>) -> Option<borrowed::Object<'b>> {
+    let db = compound::Db::at(dir.join(".git/objects")).unwrap();
+    move |id, buf| {
+        let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+        borrowed::Object::from_bytes(object.kind, object.data).ok()
+    }
+}
+
+fn options(message: Option<&str>) -> Options {
+    Options {
+        committer: owned::Signature {
+            name: "a".into(),
+            email: "a@example.com".into(),
+            time: Time {
+                time: 1_600_000_000,
+                offset: 0,
+                sign: Sign::Plus,
+            },
+        },
+        message: message.map(Into::into),
+    }
+}
+
+/// The trees of the index and of all tracked files in the worktree.
+fn index_and_worktree_trees(dir: &Path) -> (owned::Id, owned::Id) {
+    let index = hex_to_id(&git(dir, &["write-tree"]));
+    let scratch = dir.join(".git/scratch-index");
+    fs::copy(dir.join(".git/index"), &scratch).unwrap();
+    let env = [("GIT_INDEX_FILE", scratch.to_str().unwrap())];
+    assert!(git_with_env(dir, &["add", "--update"], &env).status.success());
+    let worktree = hex_to_id(&String::from_utf8(git_with_env(dir, &["write-tree"], &env).stdout).unwrap());
+    fs::remove_file(scratch).unwrap();
+    (index, worktree)
+}
+
+/// Two identical repositories with staged and unstaged changes.
+fn repositories() -> (tempfile::TempDir, tempfile::TempDir) {
+    let dirs = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    for dir in &[dirs.0.path(), dirs.1.path()] {
+        git(dir, &["init", "--quiet", "--initial-branch=main"]);
+        fs::write(dir.join("a"), "1\n2\n3\n").unwrap();
+        fs::write(dir.join("b"), "1\n2\n3\n").unwrap();
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "--quiet", "-m", "base"]);
+        fs::write(dir.join("a"), "1\nstaged\n3\n").unwrap();
+        git(dir, &["add", "a"]);
+        fs::write(dir.join("b"), "1\n2\nunstaged\n").unwrap();
+    }
+    dirs
+}
+
+/// Stash the changes in `ours` with the API and in `theirs` with git, and reset `ours` like git does.
+fn stash_both(ours: &Path, theirs: &Path, message: Option<&str>) -> stash::Stash {
+    let (index, worktree) = index_and_worktree_trees(ours);
+    let stash = stash::push(ours.join(".git"), index, worktree, find(ours), options(message)).unwrap();
+    git(ours, &["reset", "--quiet", "--hard"]);
+    match message {
+        Some(message) => git(theirs, &["stash", "push", "--quiet", "-m", message]),
+        None => git(theirs, &["stash", "--quiet"]),
+    };
+    stash
+}
+
+fn log(dir: &Path) -> String {
+    fs::read_to_string(dir.join(".git/logs/refs/stash")).unwrap()
+}
+
+#[test]
+fn stashes_are_the_same_commits_git_creates_and_are_listed_newest_first() {
+    let (ours, theirs) = repositories();
+    let (ours, theirs) = (ours.path(), theirs.path());
+    let first = stash_both(ours, theirs, None);
+    assert_eq!(first.worktree, rev(theirs, "stash@{0}"));
+    assert_eq!(first.index, rev(theirs, "stash@{0}^2"));
+    assert_eq!(first.base, rev(theirs, "main"));
+
+    fs::write(ours.join("b"), "changed again\n").unwrap();
+    fs::write(theirs.join("b"), "changed again\n").unwrap();
+    let second = stash_both(ours, theirs, Some("my message"));
+    assert_eq!(second.worktree, rev(theirs, "stash@{0}"));
+    assert_eq!(log(ours), log(theirs));
+
+    let list = stash::list(ours.join(".git")).unwrap();
+    assert_eq!(
+        list.iter().map(|entry| entry.new).collect::<Vec<_>>(),
+        vec![second.worktree, first.worktree]
+    );
+    assert_eq!(list[0].message, "On main: my message");
+    assert_eq!(stash::get(ours.join(".git"), 1, find(ours)).unwrap(), first);
+    assert!(matches!(
+        stash::get(ours.join(".git"), 2, find(ours)),
+        Err(Error::Missing(2))
+    ));
+}
+
+#[test]
+fn applying_merges_with_the_changes_made_since_like_git() {
+    let (ours, theirs) = repositories();
+    let (ours, theirs) = (ours.path(), theirs.path());
+    stash_both(ours, theirs, None);
+    for dir in &[ours, theirs] {
+        fs::write(dir.join("b"), "one\n2\n3\n").unwrap();
+        git(dir, &["commit", "--quiet", "-am", "upstream"]);
+    }
+
+    let applied = stash::apply(ours.join(".git"), 0, rev(ours, "HEAD^{tree}"), find(ours)).unwrap();
+    git(theirs, &["stash", "apply", "--quiet", "--index"]);
+    assert!(applied.worktree.conflicts.is_empty());
+    assert_eq!(applied.worktree.tree, index_and_worktree_trees(theirs).1);
+    assert_eq!(applied.index.tree, index_and_worktree_trees(theirs).0);
+
+    for dir in &[ours, theirs] {
+        git(dir, &["reset", "--quiet", "--hard"]);
+        fs::write(dir.join("b"), "one\n2\nupstream\n").unwrap();
+        git(dir, &["commit", "--quiet", "-am", "conflict"]);
+    }
+    let applied = stash::pop(ours.join(".git"), 0, rev(ours, "HEAD^{tree}"), find(ours)).unwrap();
+    assert!(!git_with_env(theirs, &["stash", "pop"], &[]).status.success());
+    assert_eq!(applied.worktree.conflicts.len(), 1);
+    assert_eq!(
+        git(ours, &["cat-file", "-p", &format!("{}:b", applied.worktree.tree)]),
+        fs::read_to_string(theirs.join("b")).unwrap()
+    );
+    assert_eq!(
+        stash::list(ours.join(".git")).unwrap().len(),
+        1,
+        "conflicts keep the stash"
+    );
+}
+
+#[test]
+fn dropping_rewrites_the_reflog_like_git() {
+    let (ours, theirs) = repositories();
+    let (ours, theirs) = (ours.path(), theirs.path());
+    for n in 0..3 {
+        for dir in &[ours, theirs] {
+            fs::write(dir.join("b"), format!("change {}\n", n)).unwrap();
+        }
+        stash_both(ours, theirs, Some(&format!("stash {}", n)));
+    }
+
+    let dropped = stash::drop(ours.join(".git"), 1).unwrap();
+    git(theirs, &["stash", "drop", "--quiet", "stash@{1}"]);
+    assert_eq!(dropped.message, "On main: stash 1");
+    assert_eq!(log(ours), log(theirs));
+    assert_eq!(rev(ours, "refs/stash"), rev(theirs, "refs/stash"));
+
+    stash::drop(ours.join(".git"), 0).unwrap();
+    git(theirs, &["stash", "drop", "--quiet"]);
+    assert_eq!(log(ours), log(theirs));
+    assert_eq!(rev(ours, "refs/stash"), rev(theirs, "refs/stash"));
+
+    stash::pop(ours.join(".git"), 0, rev(ours, "HEAD^{tree}"), find(ours)).unwrap();
+    assert!(!ours.join(".git/refs/stash").exists());
+    assert!(!ours.join(".git/logs/refs/stash").exists());
+    assert!(matches!(stash::drop(ours.join(".git"), 0), Err(Error::Missing(0))));
+}
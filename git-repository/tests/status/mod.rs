@@ -0,0 +1,345 @@
+use crate::{git, git_output};
+use git_object::{borrowed, owned};
+use git_odb::{compound, pack};
+use git_repository::{
+    ignore, index,
+    status::{self, Change, Conflict, Options, UntrackedFiles},
+    worktree,
+};
+use std::{fs, path::Path};
+
+fn write(dir: &Path, path: &str, content: &str) {
+    let path = dir.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn repository(dir: &Path) {
+    git(dir, &["init", "--quiet", "--initial-branch=main"]);
+    write(dir, "README", "readme\n");
+    write(dir, "a/file", "a\n");
+    write(dir, "a/b/file", "b\n");
+    write(dir, "c", "c\n");
+    write(dir, "empty", "");
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "--quiet", "-m", "initial"]);
+}
+
+fn status(dir: &Path, options: Options) -> status::Outcome {
+    let git_dir = dir.join(".git");
+    let db = compound::Db::at(git_dir.join("objects")).unwrap();
+    let head = git_output(dir, &["rev-parse", "--verify", "--quiet", "HEAD^{tree}"]);
+    let head = Some(head)
+        .filter(|head| head.status.success())
+        .map(|head| head.stdout)
+        .map(|hex| owned::Id::from_40_bytes_in_hex(&hex[..40]).unwrap());
+    let index = index::File::at(git_dir.join("index")).unwrap().unwrap_or_default();
+    status::status(
+        head,
+        &index,
+        dir,
+        &worktree::Capabilities::from_git_dir(&git_dir),
+        &ignore::Stack::from_git_dir(&git_dir),
+        |id, buf| {
+            let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+            borrowed::Object::from_bytes(object.kind, object.data).ok()
+        },
+        &options,
+    )
+    .unwrap()
+}
+
+/// Format the outcome like `git status --porcelain`.
+fn porcelain(outcome: &status::Outcome) -> String {
+    outcome
+        .entries
+        .iter()
+        .map(|entry| {
+            let source = match &entry.staged {
+                Some(Change::Renamed { source }) => format!("{} -> ", source),
+                _ => String::new(),
+            };
+            format!(
+                "{} {}{}\n",
+                String::from_utf8_lossy(&entry.short_format()),
+                source,
+                entry.path
+            )
+        })
+        .collect()
+}
+
+/// Compare our status to the one of git with various options, which is obtained afterwards as it refreshes the index.
+fn assert_matches_git(dir: &Path) -> status::Outcome {
+    let outcome = status(dir, Options::default());
+    for (options, args) in &[
+        (Options::default(), &[][..]),
+        (
+            Options {
+                renames: false,
+                ..Default::default()
+            },
+            &["--no-renames"][..],
+        ),
+        (
+            Options {
+                untracked_files: UntrackedFiles::No,
+                ignored: true,
+                ..Default::default()
+            },
+            &["--untracked-files=no", "--ignored"][..],
+        ),
+        (
+            Options {
+                untracked_files: UntrackedFiles::All,
+                ..Default::default()
+            },
+            &["--untracked-files=all"][..],
+        ),
+        (
+            Options {
+                untracked_files: UntrackedFiles::All,
+                ignored: true,
+                ..Default::default()
+            },
+            &["--untracked-files=all", "--ignored"][..],
+        ),
+        (
+            Options {
+                ignored: true,
+                thread_limit: Some(1),
+                ..Default::default()
+            },
+            &["--ignored"][..],
+        ),
+    ] {
+        let mut git_args = vec!["status", "--porcelain"];
+        git_args.extend_from_slice(args);
+        assert_eq!(porcelain(&status(dir, *options)), git(dir, &git_args), "{:?}", args);
+    }
+    outcome
+}
+
+#[test]
+fn a_clean_worktree_has_no_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    assert!(assert_matches_git(dir.path()).entries.is_empty());
+
+    // Files whose metadata changed are compared by content.
+    fs::write(dir.path().join("README"), "readme\n").unwrap();
+    fs::remove_file(dir.path().join("a/file")).unwrap();
+    fs::write(dir.path().join("a/file"), "a\n").unwrap();
+    assert!(assert_matches_git(dir.path()).entries.is_empty());
+}
+
+#[test]
+fn staged_and_unstaged_changes_are_told_apart() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    write(dir.path(), "README", "staged\n");
+    git(dir.path(), &["add", "README"]);
+    write(dir.path(), "README", "staged and unstaged\n");
+    write(dir.path(), "a/file", "unstaged\n");
+    write(dir.path(), "new", "new\n");
+    write(dir.path(), "a/b/new", "new\n");
+    git(dir.path(), &["add", "new", "a/b/new"]);
+    write(dir.path(), "new", "new and changed\n");
+    git(dir.path(), &["rm", "--quiet", "a/b/file"]);
+    fs::remove_file(dir.path().join("c")).unwrap();
+
+    let outcome = assert_matches_git(dir.path());
+    let readme = &outcome.entries[0];
+    assert_eq!(readme.path, "README");
+    assert_eq!(readme.staged, Some(Change::Modified));
+    assert_eq!(readme.unstaged, Some(Change::Modified));
+    assert_eq!(porcelain(&outcome).lines().count(), 6);
+}
+
+#[cfg(unix)]
+#[test]
+fn mode_and_type_changes() {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    fs::set_permissions(dir.path().join("c"), fs::Permissions::from_mode(0o755)).unwrap();
+    fs::remove_file(dir.path().join("README")).unwrap();
+    symlink("c", dir.path().join("README")).unwrap();
+    fs::remove_file(dir.path().join("a/b/file")).unwrap();
+    fs::create_dir(dir.path().join("a/b/file")).unwrap();
+
+    let outcome = assert_matches_git(dir.path());
+    assert_eq!(outcome.entries[0].unstaged, Some(Change::TypeChanged));
+
+    git(dir.path(), &["add", "--all"]);
+    let outcome = assert_matches_git(dir.path());
+    assert_eq!(outcome.entries[0].staged, Some(Change::TypeChanged));
+
+    git(dir.path(), &["config", "core.fileMode", "false"]);
+    fs::set_permissions(dir.path().join("c"), fs::Permissions::from_mode(0o644)).unwrap();
+    assert_matches_git(dir.path());
+}
+
+#[test]
+fn staged_renames_are_detected_if_the_content_is_unchanged() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    git(dir.path(), &["mv", "a/file", "moved"]);
+    git(dir.path(), &["mv", "empty", "a/empty"]);
+    git(dir.path(), &["mv", "c", "d"]);
+    write(dir.path(), "d", "changed after the move\n");
+    git(dir.path(), &["mv", "README", "README.md"]);
+    write(dir.path(), "README.md", "changed\n");
+    git(dir.path(), &["add", "README.md"]);
+
+    let outcome = assert_matches_git(dir.path());
+    let renamed: Vec<_> = outcome
+        .entries
+        .iter()
+        .filter_map(|entry| match &entry.staged {
+            Some(Change::Renamed { source }) => Some((source.to_string(), entry.path.to_string())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        renamed,
+        vec![
+            ("empty".to_string(), "a/empty".to_string()),
+            ("c".into(), "d".into()),
+            ("a/file".into(), "moved".into())
+        ]
+    );
+    assert_eq!(
+        outcome.entries.iter().find(|e| e.path == "d").unwrap().unstaged,
+        Some(Change::Modified)
+    );
+}
+
+#[test]
+fn files_added_with_intent_to_add_are_unstaged_additions() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    write(dir.path(), "new", "new\n");
+    git(dir.path(), &["add", "--intent-to-add", "new"]);
+    let outcome = assert_matches_git(dir.path());
+    assert_eq!(outcome.entries[0].staged, None);
+    assert_eq!(outcome.entries[0].unstaged, Some(Change::Added));
+}
+
+#[test]
+fn skipped_files_are_unchanged() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    git(dir.path(), &["update-index", "--skip-worktree", "c"]);
+    fs::remove_file(dir.path().join("c")).unwrap();
+    git(dir.path(), &["update-index", "--assume-unchanged", "README"]);
+    write(dir.path(), "README", "changed\n");
+    assert!(assert_matches_git(dir.path()).entries.is_empty());
+}
+
+#[test]
+fn an_unborn_head_has_only_additions() {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "--quiet"]);
+    assert!(assert_matches_git(dir.path()).entries.is_empty());
+    write(dir.path(), "file", "content\n");
+    write(dir.path(), "dir/file", "content\n");
+    git(dir.path(), &["add", "."]);
+    fs::remove_file(dir.path().join("file")).unwrap();
+    let outcome = assert_matches_git(dir.path());
+    assert!(outcome.entries.iter().all(|e| e.staged == Some(Change::Added)));
+}
+
+#[test]
+fn conflicts_are_classified_by_the_sides_which_changed() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    git(dir.path(), &["checkout", "--quiet", "-b", "other"]);
+    write(dir.path(), "README", "theirs\n");
+    write(dir.path(), "c", "theirs\n");
+    git(dir.path(), &["rm", "--quiet", "a/file"]);
+    write(dir.path(), "both-added", "theirs\n");
+    git(dir.path(), &["add", "."]);
+    git(dir.path(), &["commit", "--quiet", "-m", "theirs"]);
+    git(dir.path(), &["checkout", "--quiet", "main"]);
+    write(dir.path(), "README", "ours\n");
+    write(dir.path(), "a/file", "ours\n");
+    git(dir.path(), &["rm", "--quiet", "c"]);
+    write(dir.path(), "both-added", "ours\n");
+    git(dir.path(), &["add", "."]);
+    git(dir.path(), &["commit", "--quiet", "-m", "ours"]);
+    assert!(!git_output(dir.path(), &["merge", "other"]).status.success());
+
+    let outcome = assert_matches_git(dir.path());
+    let conflicts: Vec<_> = outcome
+        .entries
+        .iter()
+        .map(|entry| match &entry.staged {
+            Some(Change::Unmerged(conflict)) => *conflict,
+            other => panic!("{:?} is no conflict", other),
+        })
+        .collect();
+    assert_eq!(
+        conflicts,
+        vec![
+            Conflict::BothModified,
+            Conflict::DeletedByThem,
+            Conflict::BothAdded,
+            Conflict::DeletedByUs
+        ]
+    );
+}
+
+#[test]
+fn untracked_and_ignored_files_are_listed_like_git_does() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    write(
+        dir.path(),
+        ".gitignore",
+        "# tracked files stay tracked\nc\n*.o\n!keep.o\n/build/\ndocs/*.html\nonly-dir/\ntrailing  \n",
+    );
+    write(dir.path(), "a/.gitignore", "*.tmp\n!important.tmp\n");
+    write(dir.path(), ".git/info/exclude", "excluded\n");
+    for path in &[
+        "new",
+        "x.o",
+        "keep.o",
+        "a/x.tmp",
+        "a/important.tmp",
+        "a/b/x.o",
+        "a/b/new",
+        "build/out",
+        "build/deep/er/out",
+        "sub/build/out",
+        "docs/index.html",
+        "docs/guide/index.html",
+        "untracked/dir/file",
+        "untracked/dir/x.o",
+        "only-ignored/x.o",
+        "only-ignored/deep/y.o",
+        "only-dir/file",
+        "a/only-dir",
+        "excluded",
+        "trailing",
+    ] {
+        write(dir.path(), path, "content\n");
+    }
+    fs::create_dir_all(dir.path().join("no-files/at-all")).unwrap();
+    git(dir.path(), &["init", "--quiet", "nested"]);
+    write(dir.path(), "nested/file", "content\n");
+    git(dir.path(), &["init", "--quiet", "build/nested"]);
+    fs::create_dir_all(dir.path().join("build/no-files")).unwrap();
+
+    let outcome = assert_matches_git(dir.path());
+    assert_eq!(
+        outcome
+            .entries
+            .iter()
+            .find(|e| e.path == "untracked/")
+            .unwrap()
+            .unstaged,
+        Some(Change::Untracked)
+    );
+    assert!(outcome.entries.iter().all(|e| e.unstaged != Some(Change::Ignored)));
+}
@@ -0,0 +1,144 @@
+use crate::{git, hex_to_id};
+use git_features::progress;
+use git_object::{borrowed, owned};
+use git_odb::{compound, pack};
+use git_repository::{clone, submodule};
+use std::{fs, path::Path};
+
+const GITMODULES: &str = r#"[submodule "lib"]
+	path = lib
+	url = https://example.com/lib.git
+[submodule "nested/sub"]
+	path = dir/sub/
+	url = ../sub.git
+	branch = .
+"#;
+
+const LIB: &str = "1111111111111111111111111111111111111111";
+const SUB: &str = "2222222222222222222222222222222222222222";
+
+fn superproject(dir: &Path) -> owned::Id {
+    fs::create_dir(dir).unwrap();
+    git(dir, &["init", "--quiet"]);
+    fs::write(dir.join(".gitmodules"), GITMODULES).unwrap();
+    fs::write(dir.join("file"), "content\n").unwrap();
+    git(dir, &["add", "."]);
+    for (commit, path) in &[(LIB, "lib"), (SUB, "dir/sub")] {
+        git(
+            dir,
+            &[
+                "update-index",
+                "--add",
+                "--cacheinfo",
+                &format!("160000,{},{}", commit, path),
+            ],
+        );
+    }
+    git(dir, &["commit", "--quiet", "-m", "add submodules"]);
+    hex_to_id(&git(dir, &["rev-parse", "HEAD^{tree}"]))
+}
+
+#[test]
+fn gitmodules_are_parsed() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!(
+        submodule::from_bytes(GITMODULES.as_bytes())?,
+        vec![
+            submodule::Submodule {
+                name: "lib".into(),
+                path: "lib".into(),
+                url: "https://example.com/lib.git".into(),
+                branch: None,
+            },
+            submodule::Submodule {
+                name: "nested/sub".into(),
+                path: "dir/sub".into(),
+                url: "../sub.git".into(),
+                branch: Some(".".into()),
+            }
+        ]
+    );
+    assert!(submodule::from_bytes(b"")?.is_empty());
+
+    assert!(matches!(
+        submodule::from_bytes(b"[submodule \"a\"]\n\turl = u\n"),
+        Err(submodule::Error::MissingPath(_))
+    ));
+    assert!(matches!(
+        submodule::from_bytes(b"[submodule \"a\"]\n\tpath = a\n"),
+        Err(submodule::Error::MissingUrl(_))
+    ));
+    assert!(matches!(
+        submodule::from_bytes(b"[submodule \"../../escape\"]\n\tpath = a\n\turl = u\n"),
+        Err(submodule::Error::UnsafeName(_))
+    ));
+    for path in &["../a", "a/.GIT/hooks", "/absolute", ""] {
+        let data = format!("[submodule \"a\"]\n\tpath = {}\n\turl = u\n", path);
+        assert!(
+            matches!(
+                submodule::from_bytes(data.as_bytes()),
+                Err(submodule::Error::UnsafePath(_, _))
+            ),
+            "{}",
+            path
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn gitlinks_and_gitmodules_of_trees() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let repo = dir.path().join("super");
+    let tree = superproject(&repo);
+    let db = compound::Db::at(repo.join(".git/objects"))?;
+    macro_rules! find {
+        () => {
+            |id, buf| {
+                let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+                borrowed::Object::from_bytes(object.kind, object.data).ok()
+            }
+        };
+    }
+
+    assert_eq!(
+        submodule::gitlinks(tree.to_borrowed(), find!())?,
+        vec![
+            submodule::Gitlink {
+                path: "dir/sub".into(),
+                commit: hex_to_id(SUB),
+            },
+            submodule::Gitlink {
+                path: "lib".into(),
+                commit: hex_to_id(LIB),
+            }
+        ]
+    );
+    let submodules = submodule::from_tree(tree.to_borrowed(), find!())?;
+    assert_eq!(
+        submodules.iter().map(|s| s.path.to_string()).collect::<Vec<_>>(),
+        vec!["lib", "dir/sub"]
+    );
+    Ok(())
+}
+
+#[test]
+fn clone_checks_out_gitlinks_as_empty_directories() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let repo = dir.path().join("super");
+    superproject(&repo);
+
+    let worktree = dir.path().join("clone");
+    let outcome = clone(
+        &format!("file://{}", repo.display()),
+        &worktree,
+        progress::Discard,
+        clone::Options::default(),
+    )?;
+    assert_eq!(outcome.checked_out_files, 2, ".gitmodules and file");
+    for path in &["lib", "dir/sub"] {
+        let path = worktree.join(path);
+        assert!(path.is_dir(), "{}", path.display());
+        assert_eq!(fs::read_dir(&path)?.count(), 0, "submodules aren't cloned");
+    }
+    Ok(())
+}
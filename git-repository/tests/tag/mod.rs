@@ -0,0 +1,185 @@
+use crate::{git, hex_to_id};
+use git_object::{bstr::ByteSlice, owned, Sign, Time};
+use git_repository::tag::{self, Annotation, Error, Options};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const MAIN: &str = "877c3ad4e85fa4408811d3fcc38aef2500f04ade";
+const TAG: &str = "17934c4602e90c793a9660b02d49b6a678772104";
+
+fn repository(dir: &Path) -> PathBuf {
+    let fixture = Path::new("tests/fixtures/repos/files.git").canonicalize().unwrap();
+    git(
+        dir,
+        &[
+            "clone",
+            "--quiet",
+            "--bare",
+            "--no-local",
+            fixture.to_str().unwrap(),
+            "repo.git",
+        ],
+    );
+    dir.join("repo.git")
+}
+
+fn annotation(message: &str) -> Annotation {
+    Annotation {
+        target_kind: git_object::Kind::Commit,
+        tagger: owned::Signature {
+            name: "Tagger".into(),
+            email: "tagger@example.com".into(),
+            time: Time {
+                time: 1_600_000_000,
+                offset: 3600,
+                sign: Sign::Plus,
+            },
+        },
+        message: message.into(),
+    }
+}
+
+#[test]
+fn lightweight_and_annotated_tags_are_understood_by_git() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = repository(dir.path());
+
+    let outcome = tag::create(&git_dir, "light".into(), hex_to_id(MAIN), Options::default()).unwrap();
+    assert_eq!(outcome.id, hex_to_id(MAIN));
+    assert_eq!(outcome.previous, None);
+
+    let outcome = tag::create(
+        &git_dir,
+        "releases/annotated".into(),
+        hex_to_id(MAIN),
+        Options {
+            annotation: Some(annotation("the message")),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_ne!(outcome.id, hex_to_id(MAIN), "the ref points to the tag object");
+    assert_eq!(
+        git(
+            &git_dir,
+            &[
+                "rev-parse",
+                "releases/annotated",
+                "releases/annotated^{commit}",
+                "light"
+            ]
+        ),
+        format!("{}\n{}\n{}\n", outcome.id, MAIN, MAIN)
+    );
+    assert_eq!(
+        git(&git_dir, &["cat-file", "tag", "releases/annotated"]),
+        format!(
+            "object {}\ntype commit\ntag releases/annotated\ntagger Tagger <tagger@example.com> 1600000000 +0100\n\nthe message\n",
+            MAIN
+        )
+    );
+    git(&git_dir, &["fsck", "--strict"]);
+}
+
+#[test]
+fn existing_tags_are_only_replaced_if_forced() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = repository(dir.path());
+    assert!(matches!(
+        tag::create(&git_dir, "v1.0".into(), hex_to_id(MAIN), Options::default()),
+        Err(Error::Exists(name, previous)) if name == "v1.0" && previous == hex_to_id(TAG)
+    ));
+    assert!(
+        !git_dir.join("refs/tags/v1.0.lock").exists(),
+        "the lock is removed if the tag isn't written"
+    );
+
+    let outcome = tag::create(
+        &git_dir,
+        "v1.0".into(),
+        hex_to_id(MAIN),
+        Options {
+            force: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        outcome.previous,
+        Some(hex_to_id(TAG)),
+        "the previous value was read from the packed refs"
+    );
+    assert_eq!(git(&git_dir, &["rev-parse", "v1.0"]).trim_end(), MAIN);
+}
+
+#[test]
+fn invalid_names_and_locked_tags_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = repository(dir.path());
+    for name in &["", "a..b", "with space", "-dash"] {
+        assert!(
+            matches!(
+                tag::create(&git_dir, (*name).into(), hex_to_id(MAIN), Options::default()),
+                Err(Error::InvalidName(..)) | Err(Error::StartsWithDash(_))
+            ),
+            "{}",
+            name
+        );
+    }
+
+    fs::create_dir_all(git_dir.join("refs/tags")).unwrap();
+    fs::write(git_dir.join("refs/tags/locked.lock"), "").unwrap();
+    assert!(matches!(
+        tag::create(&git_dir, "locked".into(), hex_to_id(MAIN), Options::default()),
+        Err(Error::Locked(_))
+    ));
+    assert!(!git_dir.join("refs/tags/locked").exists());
+}
+
+#[test]
+fn signed_tags_append_the_signature_to_the_payload() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = repository(dir.path());
+    let signature = "-----BEGIN PGP SIGNATURE-----\n\niQEz\n-----END PGP SIGNATURE-----\n";
+    let mut payload = Vec::new();
+    let outcome = tag::create(
+        &git_dir,
+        "signed".into(),
+        hex_to_id(MAIN),
+        Options {
+            annotation: Some(annotation("the message")),
+            sign: Some(Box::new(|data| {
+                payload = data.to_owned();
+                Ok(signature.into())
+            })),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let data = git(&git_dir, &["cat-file", "tag", &outcome.id.to_string()]);
+    assert_eq!(
+        tag::extract_signature(data.as_bytes()),
+        Some((payload.as_bstr(), signature.as_bytes().as_bstr())),
+        "the payload is what was signed"
+    );
+    assert!(payload.ends_with(b"\n\nthe message\n"));
+    git(&git_dir, &["fsck", "--strict"]);
+}
+
+#[test]
+fn extract_signature_splits_signed_tags_into_payload_and_signature() {
+    let payload = "object 877c3ad4e85fa4408811d3fcc38aef2500f04ade\ntype commit\ntag v1.0\ntagger Tagger <tagger@example.com> 1600000000 +0100\n\nthe message\n-----BEGIN PGP SIGNATURE-----\nnot the signature\n";
+    let signature = "-----BEGIN SSH SIGNATURE-----\nU1NIU0lH\n-----END SSH SIGNATURE-----\n";
+    let data = format!("{}{}", payload, signature);
+    assert_eq!(
+        tag::extract_signature(data.as_bytes()),
+        Some((payload.as_bytes().as_bstr(), signature.as_bytes().as_bstr())),
+        "the last signature start counts"
+    );
+    assert_eq!(
+        tag::extract_signature(&payload.as_bytes()[..payload.find("-----").unwrap()]),
+        None
+    );
+}
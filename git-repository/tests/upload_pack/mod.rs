@@ -0,0 +1,334 @@
+use crate::{git, hex_to_id};
+use git_features::progress;
+use git_object::bstr::ByteSlice;
+use git_odb::{pack, Find};
+use git_protocol::handshake::Ref;
+use git_repository::upload_pack::{self, Options};
+use git_transport::packetline;
+use std::{fs, io::Read, net::TcpListener, path::Path, thread::JoinHandle};
+
+const MAIN: &str = "877c3ad4e85fa4408811d3fcc38aef2500f04ade";
+const OTHER: &str = "cecbafcde689e1bc457bf13334c3d934200802a1";
+const TAG: &str = "17934c4602e90c793a9660b02d49b6a678772104";
+
+fn refs() -> Vec<Ref> {
+    vec![
+        Ref::Symbolic {
+            path: "HEAD".into(),
+            target: "refs/heads/main".into(),
+            object: hex_to_id(MAIN),
+        },
+        Ref::Direct {
+            path: "refs/heads/main".into(),
+            object: hex_to_id(MAIN),
+        },
+        Ref::Direct {
+            path: "refs/heads/other".into(),
+            object: hex_to_id(OTHER),
+        },
+        Ref::Peeled {
+            path: "refs/tags/v1.0".into(),
+            tag: hex_to_id(TAG),
+            object: hex_to_id(OTHER),
+        },
+    ]
+}
+
+fn source_bundle() -> pack::Bundle {
+    let pack_dir = Path::new("tests/fixtures/repos/files.git/objects/pack");
+    let index = fs::read_dir(pack_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension() == Some("idx".as_ref()))
+        .expect("an index file");
+    pack::Bundle::at(index).unwrap()
+}
+
+fn serve(input: &[u8], options: Options) -> (Result<upload_pack::Outcome, upload_pack::Error>, Vec<u8>) {
+    let bundle = source_bundle();
+    let mut out = Vec::new();
+    let res = git_repository::upload_pack(
+        input,
+        &mut out,
+        &refs(),
+        |id, buf| bundle.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok(),
+        progress::Discard,
+        options,
+    );
+    (res, out)
+}
+
+/// Act as `git daemon` for `connections` clients, returning the outcomes of serving them.
+fn daemon(connections: usize) -> (u16, JoinHandle<Vec<upload_pack::Outcome>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = std::thread::spawn(move || {
+        let bundle = source_bundle();
+        let mut outcomes = Vec::new();
+        for _ in 0..connections {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut len = [0u8; 4];
+            stream.read_exact(&mut len).unwrap();
+            let len = usize::from_str_radix(std::str::from_utf8(&len).unwrap(), 16).unwrap();
+            let mut request = vec![0; len - 4];
+            stream.read_exact(&mut request).unwrap();
+            assert!(request.starts_with(b"git-upload-pack /files.git\0"));
+
+            outcomes.push(
+                git_repository::upload_pack(
+                    stream.try_clone().unwrap(),
+                    &stream,
+                    &refs(),
+                    |id, buf| bundle.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok(),
+                    progress::Discard,
+                    Options::default(),
+                )
+                .unwrap(),
+            );
+        }
+        outcomes
+    });
+    (port, handle)
+}
+
+#[test]
+fn git_can_clone_and_fetch_only_what_it_is_missing() {
+    let (port, server) = daemon(2);
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("git://127.0.0.1:{}/files.git", port);
+    git(
+        dir.path(),
+        &[
+            "clone",
+            "--quiet",
+            "--bare",
+            "--single-branch",
+            "--branch",
+            "other",
+            &url,
+            "clone.git",
+        ],
+    );
+    let clone = dir.path().join("clone.git");
+    git(&clone, &["fetch", "--quiet", &url, "refs/heads/main:refs/heads/main"]);
+    assert_eq!(git(&clone, &["rev-parse", "main"]).trim_end(), MAIN);
+    git(&clone, &["fsck", "--strict"]);
+
+    let outcomes = server.join().unwrap();
+    assert!(outcomes[0].capabilities.iter().any(|c| c == "side-band-64k"));
+    assert_eq!(outcomes[0].wants, vec![hex_to_id(OTHER)]);
+    assert!(outcomes[0].common.is_empty());
+    assert_eq!(
+        outcomes[0].pack.as_ref().map(|p| p.num_objects),
+        Some(9),
+        "the commit, its objects and the tag"
+    );
+    assert_eq!(outcomes[1].wants, vec![hex_to_id(MAIN)]);
+    assert!(outcomes[1].common.contains(&hex_to_id(OTHER)));
+    assert_eq!(
+        outcomes[1].pack.as_ref().map(|p| p.num_objects),
+        Some(3),
+        "the new commit, its tree and the changed blob"
+    );
+}
+
+#[test]
+fn clone_from_ourselves() {
+    let (port, server) = daemon(1);
+    let dir = tempfile::tempdir().unwrap();
+    let outcome = git_repository::clone(
+        &format!("git://127.0.0.1:{}/files.git", port),
+        dir.path(),
+        progress::Discard,
+        Default::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        outcome.head.as_ref().map(|h| h.to_string()),
+        Some("refs/heads/main".into())
+    );
+    assert_eq!(outcome.pack.as_ref().map(|p| p.index.num_objects), Some(12));
+    assert_eq!(outcome.checked_out_files, 4);
+    assert_eq!(server.join().unwrap()[0].wants.len(), 3, "HEAD is not asked for twice");
+}
+
+fn request(lines: &[&str], done: bool) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut lines = lines.iter();
+    for line in lines.by_ref() {
+        if line.is_empty() {
+            break;
+        }
+        packetline::encode::text_to_write(line.as_bytes(), &mut out).unwrap();
+    }
+    packetline::encode::flush_to_write(&mut out).unwrap();
+    for line in lines {
+        packetline::encode::text_to_write(line.as_bytes(), &mut out).unwrap();
+    }
+    if done {
+        packetline::encode::text_to_write(b"done", &mut out).unwrap();
+    } else {
+        packetline::encode::flush_to_write(&mut out).unwrap();
+    }
+    out
+}
+
+#[test]
+fn stateless_negotiation_and_pack_without_sideband() {
+    let stateless = Options {
+        stateless: true,
+        ..Default::default()
+    };
+    let want = format!("want {} agent=test", MAIN);
+    let have = format!("have {}", OTHER);
+    let (res, out) = serve(&request(&[&want, "", &have], false), stateless.clone());
+    let outcome = res.unwrap();
+    assert_eq!(outcome.common, vec![hex_to_id(OTHER)]);
+    assert_eq!(outcome.capabilities, vec!["agent=test"]);
+    assert!(outcome.pack.is_none(), "the client isn't done yet");
+    assert_eq!(out, format!("0031ACK {}\n", OTHER).as_bytes());
+
+    let (res, out) = serve(&request(&[&want, "", &have], true), stateless.clone());
+    let outcome = res.unwrap();
+    assert_eq!(outcome.pack.as_ref().map(|p| p.num_objects), Some(3));
+    let pack = &out[format!("0031ACK {}\n", OTHER).len()..];
+    assert!(pack.starts_with(b"PACK"), "the pack follows right away");
+
+    let (res, out) = serve(&request(&[&format!("want {}", OTHER), ""], true), stateless);
+    assert_eq!(res.unwrap().pack.as_ref().map(|p| p.num_objects), Some(8));
+    assert!(out.starts_with(b"0008NAK\n"));
+}
+
+#[test]
+fn refs_are_advertised_and_unadvertised_objects_are_refused() {
+    let blob = "2227cddb7f6318ea735a1c4adb52f5cd36c5783c";
+    let (res, out) = serve(&request(&[&format!("want {}", blob), ""], true), Options::default());
+    assert!(matches!(res, Err(upload_pack::Error::NotOurRef(id)) if id.to_string() == blob));
+
+    let mut lines = packetline::Provider::new(out.as_slice(), &[packetline::PacketLine::Flush]);
+    let outcome = git_protocol::handshake::parse(&mut lines).unwrap();
+    assert_eq!(outcome.refs, Some(refs()));
+    assert!(outcome.capabilities.contains("side-band-64k"));
+    lines.reset();
+    let error = lines.next_line().unwrap().unwrap().unwrap();
+    assert_eq!(
+        error.to_error().map(|e| e.0.as_bstr().to_string()),
+        Some(format!("upload-pack: not our ref {}", blob))
+    );
+
+    let (res, _) = serve(&request(&[""], true), Options::default());
+    assert!(res.unwrap().wants.is_empty(), "clients may just look at the refs");
+}
+
+#[test]
+fn hidden_refs_are_neither_advertised_nor_wanted_unless_allowed() {
+    let options = Options {
+        advertisement: git_repository::transfer::Advertisement {
+            agent: "test/1.0".into(),
+            hide_refs: vec!["refs/tags".into(), "refs/heads".into(), "!refs/heads/main".into()],
+        },
+        ..Default::default()
+    };
+    let want_other = request(&[&format!("want {}", OTHER), ""], true);
+    let (res, out) = serve(&want_other, options.clone());
+    assert!(matches!(res, Err(upload_pack::Error::NotOurRef(_))));
+
+    let mut lines = packetline::Provider::new(out.as_slice(), &[packetline::PacketLine::Flush]);
+    let outcome = git_protocol::handshake::parse(&mut lines).unwrap();
+    assert_eq!(
+        outcome.refs,
+        Some(refs()[..2].to_vec()),
+        "the last matching pattern wins"
+    );
+    assert_eq!(
+        outcome
+            .capabilities
+            .capability("agent")
+            .and_then(|agent| agent.value())
+            .map(|agent| agent.to_string()),
+        Some("test/1.0".into())
+    );
+    assert!(!outcome.capabilities.contains("allow-tip-sha1-in-want"));
+
+    let (res, out) = serve(
+        &want_other,
+        Options {
+            allow_tip_sha1_in_want: true,
+            ..options
+        },
+    );
+    assert_eq!(res.unwrap().pack.as_ref().map(|p| p.num_objects), Some(8));
+    let mut lines = packetline::Provider::new(out.as_slice(), &[packetline::PacketLine::Flush]);
+    let outcome = git_protocol::handshake::parse(&mut lines).unwrap();
+    assert!(outcome.capabilities.contains("allow-tip-sha1-in-want"));
+}
+
+#[test]
+fn blobs_are_filtered_if_the_client_asks_for_it() {
+    let filter = Options {
+        filter: true,
+        ..Default::default()
+    };
+    let want_other = |filter: &str| request(&[&format!("want {}", OTHER), filter, ""], true);
+    for (spec, expected, num_objects) in &[
+        ("filter blob:none", upload_pack::Filter::BlobNone, 4),
+        ("filter blob:limit=6", upload_pack::Filter::BlobLimit(6), 6),
+        ("filter blob:limit=1k", upload_pack::Filter::BlobLimit(1024), 8),
+    ] {
+        let (res, _) = serve(&want_other(spec), filter.clone());
+        let outcome = res.unwrap();
+        assert_eq!(outcome.filter, Some(*expected));
+        assert_eq!(
+            outcome.pack.as_ref().map(|p| p.num_objects),
+            Some(*num_objects),
+            "{}",
+            spec
+        );
+    }
+
+    let (res, _) = serve(&want_other("filter tree:0"), filter);
+    assert!(matches!(res, Err(upload_pack::Error::UnsupportedFilter(spec)) if spec == "tree:0"));
+    let (res, _) = serve(&want_other("filter blob:none"), Options::default());
+    assert!(
+        matches!(res, Err(upload_pack::Error::UnexpectedLine(_))),
+        "filters must be enabled"
+    );
+}
+
+#[test]
+fn object_sizes_are_answered_without_sending_objects() {
+    let missing = "ffffffffffffffffffffffffffffffffffffffff";
+    let mut input = Vec::new();
+    for line in &["command=object-info", "agent=git/2.39.5", "object-format=sha1"] {
+        packetline::encode::text_to_write(line.as_bytes(), &mut input).unwrap();
+    }
+    packetline::encode::delim_to_write(&mut input).unwrap();
+    for line in &["size".into(), format!("oid {}", MAIN), format!("oid {}", missing)] {
+        packetline::encode::text_to_write(line.as_bytes(), &mut input).unwrap();
+    }
+    packetline::encode::flush_to_write(&mut input).unwrap();
+
+    let bundle = source_bundle();
+    let mut out = Vec::new();
+    let ids = upload_pack::object_info(input.as_slice(), &mut out, |id| {
+        bundle.header(id).and_then(Result::ok).map(|(_kind, size)| size)
+    })
+    .unwrap();
+    assert_eq!(ids, vec![hex_to_id(MAIN), hex_to_id(missing)]);
+    let mut lines = packetline::Provider::new(out.as_slice(), &[packetline::PacketLine::Flush]);
+    let mut response = Vec::new();
+    while let Some(line) = lines.next_line() {
+        response.push(line.unwrap().unwrap().to_text().unwrap().to_str().unwrap().to_owned());
+    }
+    assert_eq!(
+        response,
+        vec!["size".into(), format!("{} 169", MAIN), format!("{} ", missing)]
+    );
+
+    let mut out = Vec::new();
+    upload_pack::advertise_v2_capabilities(&Options::default(), &mut out).unwrap();
+    let mut lines = packetline::Provider::new(out.as_slice(), &[packetline::PacketLine::Flush]);
+    let outcome = git_protocol::handshake::parse(&mut lines).unwrap();
+    assert_eq!(outcome.server_protocol_version, git_transport::Protocol::V2);
+    assert!(outcome.capabilities.contains("object-info"));
+}
@@ -0,0 +1,180 @@
+use crate::{git_command, run};
+use git_object::{borrowed, owned};
+use git_odb::{compound, pack};
+use git_repository::worktree::archive::{self, Format, Options};
+use std::{fs, path::Path, process::Command};
+
+fn git(dir: &Path, args: &[&str]) -> Vec<u8> {
+    let date = "1600000000 +0200";
+    run(git_command(dir)
+        .args(args)
+        .env("GIT_AUTHOR_DATE", date)
+        .env("GIT_COMMITTER_DATE", date))
+}
+
+fn repository(dir: &Path) -> owned::Id {
+    git(dir, &["init", "--quiet"]);
+    let long_directory = "a-directory-with-a-rather-long-name-which-is-needed-to-exceed-the-limits-of-tar";
+    let long_file = "and-a-file-whose-name-is-long-enough-to-not-fit-into-the-name-field-of-a-tar-header.txt";
+    for (path, content) in &[
+        ("README", "hello\n".to_string()),
+        ("script.sh", "#!/bin/sh\n".into()),
+        ("dir/nested/file", "nested\n".repeat(100)),
+        (
+            ".gitattributes",
+            "ignored export-ignore\nsecret export-ignore\n*.in export-subst\n".into(),
+        ),
+        ("ignored", "not archived\n".into()),
+        ("secret/file", "not archived either\n".into()),
+        (
+            "dir/subst.in",
+            "$Format:%H %h %T%n%an <%ae> %at %cn <%ce> %ct%n%s|%b|%P|%p|%%|%x$ unfinished $Format:%H".into(),
+        ),
+        (&format!("{}/{}", long_directory, long_file), "long\n".into()),
+        (&format!("{}/short", long_directory), "short\n".into()),
+    ] {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir.join("script.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        std::os::unix::fs::symlink("README", dir.join("link")).unwrap();
+        std::os::unix::fs::symlink("x".repeat(120), dir.join("long-link")).unwrap();
+    }
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "--quiet", "-m", "subject\n\nbody"]);
+    let hex = git(dir, &["rev-parse", "HEAD"]);
+    owned::Id::from_40_bytes_in_hex(&hex[..40]).unwrap()
+}
+
+fn archive(dir: &Path, id: owned::Id, options: Options) -> (Vec<u8>, archive::Outcome) {
+    let db = compound::Db::at(dir.join(".git/objects")).unwrap();
+    let mut out = Vec::new();
+    let outcome = archive::archive(
+        id.to_borrowed(),
+        |id, buf| {
+            let object = db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok()?;
+            borrowed::Object::from_bytes(object.kind, object.data).ok()
+        },
+        &mut out,
+        options,
+    )
+    .unwrap();
+    (out, outcome)
+}
+
+#[test]
+fn tar_archives_are_identical_to_the_ones_of_git() {
+    let dir = tempfile::tempdir().unwrap();
+    let head = repository(dir.path());
+    let (tar, outcome) = archive(dir.path(), head, Options::default());
+    assert_eq!(outcome.commit, Some(head));
+    assert_eq!(tar, git(dir.path(), &["archive", "--format=tar", "HEAD"]));
+
+    let (tar, _) = archive(
+        dir.path(),
+        head,
+        Options {
+            prefix: "project/".into(),
+            ..Default::default()
+        },
+    );
+    assert_eq!(
+        tar,
+        git(dir.path(), &["archive", "--format=tar", "--prefix=project/", "HEAD"])
+    );
+}
+
+#[test]
+fn trees_are_archived_without_commit_information() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    let tree = git(dir.path(), &["rev-parse", "HEAD^{tree}"]);
+    let tree = owned::Id::from_40_bytes_in_hex(&tree[..40]).unwrap();
+    let (tar, outcome) = archive(
+        dir.path(),
+        tree,
+        Options {
+            mtime: Some(1_600_000_000),
+            ..Default::default()
+        },
+    );
+    assert_eq!(outcome.commit, None);
+    let mut expected = git(dir.path(), &["archive", "--format=tar", "HEAD^{tree}"]);
+    // git uses the current time for trees, so the modification times and header checksums are ignored.
+    let mut ours = tar.clone();
+    for block in (0..expected.len().min(ours.len())).step_by(512) {
+        if expected[block + 257..block + 262] == *b"ustar" {
+            expected[block + 136..block + 156].copy_from_slice(&[0; 20]);
+            ours[block + 136..block + 156].copy_from_slice(&[0; 20]);
+        }
+    }
+    assert_eq!(ours, expected);
+    assert_eq!(outcome.entries, 12, "files, links and directories");
+}
+
+#[test]
+fn compressed_archives_contain_the_same_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let head = repository(dir.path());
+    let (tgz, _) = archive(
+        dir.path(),
+        head,
+        Options {
+            format: Format::TarGz,
+            ..Default::default()
+        },
+    );
+    fs::write(dir.path().join("archive.tar.gz"), &tgz).unwrap();
+    let gunzipped = Command::new("gzip")
+        .args(["-dc", "archive.tar.gz"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(gunzipped.status.success());
+    assert_eq!(gunzipped.stdout, git(dir.path(), &["archive", "--format=tar", "HEAD"]));
+
+    let (zip, outcome) = archive(
+        dir.path(),
+        head,
+        Options {
+            format: Format::Zip,
+            prefix: "project/".into(),
+            ..Default::default()
+        },
+    );
+    assert_eq!(outcome.entries, 13, "the prefix is a directory, too");
+    fs::write(dir.path().join("ours.zip"), &zip).unwrap();
+    fs::write(
+        dir.path().join("theirs.zip"),
+        git(dir.path(), &["archive", "--format=zip", "--prefix=project/", "HEAD"]),
+    )
+    .unwrap();
+    let unzip = |args: &[&str]| {
+        let output = Command::new("unzip")
+            .current_dir(dir.path())
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+        output.stdout
+    };
+    unzip(&["-tq", "ours.zip"]);
+    assert_eq!(unzip(&["-Z1", "ours.zip"]), unzip(&["-Z1", "theirs.zip"]));
+    for path in &["project/README", "project/dir/nested/file", "project/dir/subst.in"] {
+        assert_eq!(
+            unzip(&["-p", "ours.zip", path]),
+            unzip(&["-p", "theirs.zip", path]),
+            "{}",
+            path
+        );
+    }
+    assert_eq!(
+        unzip(&["-qz", "ours.zip"]),
+        format!("{}\n", head).into_bytes(),
+        "the commit is the comment"
+    );
+}
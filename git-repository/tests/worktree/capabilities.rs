@@ -0,0 +1,54 @@
+use git_repository::{init, worktree::Capabilities};
+
+#[test]
+#[cfg(target_os = "linux")]
+fn probing_finds_what_linux_filesystems_support() {
+    let dir = tempfile::tempdir().unwrap();
+    let capabilities = Capabilities::probe(dir.path()).unwrap();
+    assert_eq!(capabilities, Capabilities::default());
+    assert_eq!(
+        std::fs::read_dir(dir.path()).unwrap().count(),
+        0,
+        "probes leave nothing behind"
+    );
+}
+
+#[test]
+fn capabilities_are_written_to_and_read_from_the_configuration() {
+    let capabilities = Capabilities {
+        file_mode: false,
+        symlinks: false,
+        ignore_case: true,
+        precompose_unicode: true,
+    };
+    let config = format!("[core]\n{}", capabilities.to_config());
+    assert_eq!(
+        config,
+        "[core]\n\tfilemode = false\n\tsymlinks = false\n\tignorecase = true\n\tprecomposeunicode = true\n"
+    );
+    assert_eq!(
+        Capabilities::from_config(&git_config::File::from_bytes(config.as_bytes()).unwrap()),
+        capabilities
+    );
+    assert_eq!(
+        Capabilities::default().to_config(),
+        "\tfilemode = true\n",
+        "defaults aren't written"
+    );
+    assert_eq!(
+        Capabilities::from_config(&git_config::File::from_bytes(b"[core]\n\tsymlinks = maybe\n").unwrap()),
+        Capabilities::default(),
+        "invalid values fall back to the defaults"
+    );
+}
+
+#[test]
+fn new_repositories_remember_the_probed_capabilities() {
+    let dir = tempfile::tempdir().unwrap();
+    let git_dir = init::repository_at(dir.path()).unwrap();
+    let probed = Capabilities::probe(&git_dir).unwrap();
+    assert_eq!(Capabilities::from_git_dir(&git_dir), probed);
+    assert!(std::fs::read_to_string(git_dir.join("config"))
+        .unwrap()
+        .contains(&format!("\tfilemode = {}\n", probed.file_mode)));
+}
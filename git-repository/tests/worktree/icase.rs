@@ -0,0 +1,63 @@
+use git_object::bstr::ByteSlice;
+use git_repository::worktree::{icase::Paths, Capabilities};
+
+fn paths(ignore_case: bool, precompose_unicode: bool) -> Paths {
+    Paths::new(&Capabilities {
+        ignore_case,
+        precompose_unicode,
+        ..Default::default()
+    })
+}
+
+const COMPOSED: &str = "dir/\u{00C4}pfel";
+const DECOMPOSED: &str = "dir/A\u{0308}pfel";
+
+#[test]
+fn paths_are_only_normalized_as_the_filesystem_demands() {
+    let mut exact = paths(false, false);
+    assert_eq!(exact.insert("README".into()), None);
+    assert_eq!(exact.insert("readme".into()), None);
+    assert_eq!(exact.insert(COMPOSED.into()), None);
+    assert_eq!(exact.insert(DECOMPOSED.into()), None);
+    assert_eq!(exact.get("README".into()), Some("README".as_bytes().as_bstr()));
+}
+
+#[test]
+fn paths_differing_in_case_collide_on_case_insensitive_filesystems() {
+    let mut icase = paths(true, false);
+    assert_eq!(icase.insert("Dir/README".into()), None);
+    assert_eq!(
+        icase.insert("dir/readme".into()),
+        Some("Dir/README".as_bytes().as_bstr())
+    );
+    assert_eq!(icase.get("DIR/ReadMe".into()), Some("Dir/README".as_bytes().as_bstr()));
+    assert_eq!(
+        icase.normalize("Ä/ß".into()).as_ref(),
+        "ä/ß",
+        "case is folded beyond ASCII"
+    );
+    assert_eq!(icase.insert(b"B\xff"[..].as_bstr()), None);
+    assert_eq!(
+        icase.get(b"b\xff"[..].as_bstr()),
+        Some(b"B\xff"[..].as_bstr()),
+        "invalid UTF-8 is folded for ASCII"
+    );
+    assert_eq!(icase.insert(DECOMPOSED.into()), None);
+    assert_eq!(icase.get(COMPOSED.into()), None, "unicode isn't normalized");
+}
+
+#[test]
+fn decomposed_and_composed_paths_collide_on_normalizing_filesystems() {
+    let mut nfc = paths(false, true);
+    assert_eq!(nfc.insert(DECOMPOSED.into()), None);
+    assert_eq!(nfc.insert(COMPOSED.into()), Some(DECOMPOSED.as_bytes().as_bstr()));
+    assert_eq!(nfc.normalize(DECOMPOSED.into()).as_ref(), COMPOSED);
+    assert_eq!(nfc.get("dir/\u{00E4}pfel".into()), None, "case matters");
+
+    let mut both = paths(true, true);
+    assert_eq!(both.insert(COMPOSED.into()), None);
+    assert_eq!(
+        both.insert("DIR/a\u{0308}PFEL".into()),
+        Some(COMPOSED.as_bytes().as_bstr())
+    );
+}
@@ -0,0 +1,179 @@
+use crate::{git, hex_to_id};
+use git_object::{owned, Sign, Time};
+use git_repository::{
+    commit,
+    worktree::linked::{self, Head},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A repository in `dir/main` with the linked worktrees `feature` on a new branch and `detached`.
+fn repository(dir: &Path) -> PathBuf {
+    let main = dir.join("main");
+    fs::create_dir(&main).unwrap();
+    git(&main, &["init", "--quiet"]);
+    fs::write(main.join("file"), "content\n").unwrap();
+    git(&main, &["add", "file"]);
+    git(&main, &["commit", "--quiet", "-m", "initial"]);
+    git(&main, &["worktree", "add", "--quiet", "-b", "feature", "../feature"]);
+    git(&main, &["worktree", "add", "--quiet", "--detach", "../detached"]);
+    main
+}
+
+#[test]
+fn git_and_common_dirs_of_linked_worktrees() {
+    let dir = tempfile::tempdir().unwrap();
+    let main = repository(dir.path());
+    let common_dir = main.join(".git");
+
+    assert_eq!(linked::git_dir(&common_dir).unwrap(), common_dir);
+    assert_eq!(linked::common_dir(&common_dir).unwrap(), common_dir);
+
+    let git_dir = linked::git_dir(dir.path().join("feature/.git")).unwrap();
+    assert_eq!(
+        git_dir.canonicalize().unwrap(),
+        common_dir.join("worktrees/feature").canonicalize().unwrap()
+    );
+    assert_eq!(
+        linked::common_dir(&git_dir).unwrap().canonicalize().unwrap(),
+        common_dir.canonicalize().unwrap()
+    );
+
+    assert!(matches!(
+        linked::git_dir(main.join("file")),
+        Err(linked::Error::InvalidDotGit(_))
+    ));
+
+    for name in &[
+        "HEAD",
+        "ORIG_HEAD",
+        "refs/bisect/bad",
+        "refs/worktree/x",
+        "refs/rewritten/y",
+    ] {
+        assert!(linked::is_per_worktree_ref((*name).into()), "{}", name);
+        assert_eq!(linked::ref_dir(&git_dir, (*name).into()).unwrap(), git_dir);
+    }
+    for name in &["refs/heads/feature", "refs/tags/v1", "refs/remotes/origin/main"] {
+        assert!(!linked::is_per_worktree_ref((*name).into()), "{}", name);
+    }
+}
+
+#[test]
+fn list_and_prune_linked_worktrees() {
+    let dir = tempfile::tempdir().unwrap();
+    let main = repository(dir.path());
+    let common_dir = main.join(".git");
+    let head = hex_to_id(&git(&main, &["rev-parse", "HEAD"]));
+
+    let worktrees = linked::list(&common_dir).unwrap();
+    assert_eq!(
+        worktrees
+            .iter()
+            .map(|w| (
+                w.name.to_string(),
+                w.path.as_ref().map(|p| p.canonicalize().unwrap()),
+                w.head.clone(),
+                w.locked.clone()
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "detached".into(),
+                Some(dir.path().join("detached").canonicalize().unwrap()),
+                Some(Head::Detached(head)),
+                None
+            ),
+            (
+                "feature".into(),
+                Some(dir.path().join("feature").canonicalize().unwrap()),
+                Some(Head::Symbolic("refs/heads/feature".into())),
+                None
+            ),
+        ]
+    );
+    assert!(worktrees.iter().all(|w| !w.is_prunable()));
+    assert!(linked::list(dir.path().join("feature")).unwrap().is_empty());
+
+    git(
+        &main,
+        &["worktree", "lock", "--reason", "on a usb stick", "../detached"],
+    );
+    fs::remove_dir_all(dir.path().join("detached")).unwrap();
+    fs::remove_dir_all(dir.path().join("feature")).unwrap();
+    let worktrees = linked::list(&common_dir).unwrap();
+    assert_eq!(worktrees[0].locked, Some("on a usb stick".into()));
+    assert!(!worktrees[0].is_prunable(), "locked worktrees are kept");
+    assert!(worktrees[1].is_prunable());
+
+    let pruned = linked::prune(&common_dir, true).unwrap();
+    assert_eq!(pruned.len(), 1);
+    assert!(pruned[0].git_dir.is_dir(), "nothing is removed in a dry run");
+
+    let pruned = linked::prune(&common_dir, false).unwrap();
+    assert_eq!(
+        pruned.iter().map(|w| w.name.to_string()).collect::<Vec<_>>(),
+        vec!["feature"]
+    );
+    assert!(!common_dir.join("worktrees/feature").exists());
+    assert_eq!(
+        git(&main, &["worktree", "list", "--porcelain"])
+            .lines()
+            .filter(|l| l.starts_with("worktree "))
+            .count(),
+        2,
+        "git agrees: only the main and the locked worktree are left"
+    );
+}
+
+#[test]
+fn commits_in_linked_worktrees_move_their_own_head() {
+    let dir = tempfile::tempdir().unwrap();
+    let main = repository(dir.path());
+    let feature = dir.path().join("feature");
+    let git_dir = linked::git_dir(feature.join(".git")).unwrap();
+    let head = hex_to_id(&git(&main, &["rev-parse", "HEAD"]));
+    let tree = hex_to_id(&git(&main, &["rev-parse", "HEAD^{tree}"]));
+    let signature = owned::Signature {
+        name: "Bot".into(),
+        email: "bot@example.com".into(),
+        time: Time {
+            time: 1_600_000_000,
+            offset: 0,
+            sign: Sign::Plus,
+        },
+    };
+
+    let outcome = commit::create(
+        &git_dir,
+        tree,
+        vec![head],
+        signature.clone(),
+        signature,
+        "on feature\n",
+        commit::Options::default(),
+    )
+    .unwrap();
+    assert_eq!(outcome.reference, "refs/heads/feature");
+    assert_eq!(hex_to_id(&git(&feature, &["rev-parse", "HEAD"])), outcome.id);
+    assert_eq!(
+        hex_to_id(&git(&main, &["rev-parse", "feature"])),
+        outcome.id,
+        "the branch is shared"
+    );
+    assert_eq!(
+        hex_to_id(&git(&main, &["rev-parse", "HEAD"])),
+        head,
+        "the main worktree is unchanged"
+    );
+    assert_eq!(
+        git(&feature, &["log", "-g", "--format=%gs", "HEAD"]).lines().next(),
+        Some("commit: on feature"),
+        "the reflog of HEAD is the one of the linked worktree"
+    );
+    assert!(git_dir.join("logs/HEAD").is_file());
+    assert!(main.join(".git/logs/refs/heads/feature").is_file());
+    git(&feature, &["fsck", "--no-progress"]);
+}
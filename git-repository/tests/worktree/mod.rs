@@ -0,0 +1,4 @@
+mod archive;
+mod capabilities;
+mod icase;
+mod linked;
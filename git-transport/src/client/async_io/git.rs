@@ -0,0 +1,137 @@
+//! The git protocol over a pair of asynchronous streams, like a TCP connection to a `git daemon` provided by the caller.
+use crate::{
+    client::{
+        async_io::{BoxFuture, Lines, RequestWriter, Transport},
+        git::{message, ConnectMode},
+        Error, MessageKind, WriteMode,
+    },
+    packetline, Protocol, Service,
+};
+use bstr::BString;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_lite::AsyncWriteExt;
+use std::io;
+
+/// A connection to a `git daemon`, or anything else speaking the git protocol over a pair of asynchronous streams.
+pub struct Connection<W> {
+    writer: W,
+    line_provider: Lines,
+    path: BString,
+    virtual_host: Option<(String, Option<u16>)>,
+    desired_version: Protocol,
+    mode: ConnectMode,
+    handshake_sent: bool,
+}
+
+impl<W: AsyncWrite + Unpin + Send> Connection<W> {
+    /// Create a new connection reading server responses from `read` and sending requests to `write`, asking for
+    /// the repository at `path` with the `desired_version`.
+    ///
+    /// The optional `virtual_host` with port is sent along to allow the server to pick the repository by host name.
+    pub fn new(
+        read: impl AsyncRead + Unpin + Send + 'static,
+        write: W,
+        desired_version: Protocol,
+        path: impl Into<BString>,
+        virtual_host: Option<(impl Into<String>, Option<u16>)>,
+    ) -> Self {
+        Connection {
+            writer: write,
+            line_provider: packetline::async_io::Provider::new(Box::new(read), &[packetline::PacketLine::Flush]),
+            path: path.into(),
+            virtual_host: virtual_host.map(|(host, port)| (host.into(), port)),
+            desired_version,
+            mode: ConnectMode::Daemon,
+            handshake_sent: false,
+        }
+    }
+
+    /// Create a new connection to a process serving the repository at `path`, which communicates through `read`
+    /// and `write`.
+    ///
+    /// No request line is sent as the process already knows which service to provide, and the `desired_version`
+    /// must be communicated to it when spawning it.
+    pub fn new_for_spawned_process(
+        read: impl AsyncRead + Unpin + Send + 'static,
+        write: W,
+        desired_version: Protocol,
+        path: impl Into<BString>,
+    ) -> Self {
+        Connection {
+            mode: ConnectMode::Process,
+            ..Self::new(read, write, desired_version, path, None::<(String, Option<u16>)>)
+        }
+    }
+
+    /// Return the repository `path` as sent to the server.
+    pub fn path(&self) -> &BString {
+        &self.path
+    }
+
+    /// Access the writer to which requests are written.
+    pub fn writer_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+}
+
+impl<W: AsyncWrite + Unpin + Send> Transport for Connection<W> {
+    fn handshake(&mut self, service: Service) -> BoxFuture<'_, Result<&mut Lines, Error>> {
+        Box::pin(async move {
+            if self.mode == ConnectMode::Daemon {
+                let line = message(service, self.desired_version, &self.path, self.virtual_host.as_ref());
+                packetline::async_io::encode::data_to_write(&line, &mut self.writer).await?;
+                self.writer.flush().await?;
+            }
+            self.handshake_sent = true;
+            self.line_provider.reset_with(&[packetline::PacketLine::Flush]);
+            Ok(&mut self.line_provider)
+        })
+    }
+
+    fn request(&mut self, write_mode: WriteMode, on_into_read: MessageKind) -> Result<RequestWriter<'_>, Error> {
+        if !self.handshake_sent {
+            return Err(Error::MissingHandshake);
+        }
+        Ok(RequestWriter::new(
+            &mut self.writer,
+            &mut self.line_provider,
+            write_mode,
+            on_into_read,
+        ))
+    }
+
+    fn close(&mut self) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            if self.handshake_sent {
+                self.handshake_sent = false;
+                let res = match packetline::async_io::encode::flush_to_write(&mut self.writer).await {
+                    Ok(_) => self.writer.flush().await,
+                    Err(err) => Err(err),
+                };
+                match res {
+                    // The server already ended the conversation, as it does after sending a pack.
+                    Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {}
+                    res => res?,
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn to_url(&self) -> String {
+        let path = self.path.to_string();
+        match &self.virtual_host {
+            Some((host, Some(port))) => format!("git://{}:{}{}", host, port, path),
+            Some((host, None)) => format!("git://{}{}", host, path),
+            None => path,
+        }
+    }
+
+    fn desired_protocol_version(&self) -> Protocol {
+        self.desired_version
+    }
+
+    fn is_stateful(&self) -> bool {
+        true
+    }
+}
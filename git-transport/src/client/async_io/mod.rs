@@ -0,0 +1,142 @@
+//! Asynchronous variants of the [`Transport`][crate::client::Transport] trait and the [`RequestWriter`]
+//! built on the `AsyncRead` and `AsyncWrite` traits of `futures-io`, available with the `async-client` feature.
+//!
+//! Only connections over streams provided by the caller are supported, which allows servers running on any executor
+//! to talk to remotes without blocking their worker threads.
+use crate::{
+    client::{Error, MessageKind, WriteMode},
+    packetline, Protocol, Service,
+};
+use futures_io::AsyncWrite;
+use futures_lite::AsyncWriteExt;
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+///
+pub mod git;
+
+/// The pkt-lines a server sends as response, delimited by flush packets.
+pub type Lines = packetline::async_io::Provider<Box<dyn futures_io::AsyncRead + Unpin + Send>>;
+
+/// A boxed future as returned by the methods of [`Transport`].
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A single request to the server, writing pkt-lines until [`into_read()`][RequestWriter::into_read()] is called
+/// to obtain the response.
+pub struct RequestWriter<'a> {
+    on_into_read: MessageKind,
+    writer: packetline::async_io::Writer<Box<dyn AsyncWrite + Unpin + Send + 'a>>,
+    reader: &'a mut Lines,
+}
+
+impl<'a> RequestWriter<'a> {
+    /// Create a new instance writing to `writer` in the given `write_mode`, sending `on_into_read` once the request
+    /// is complete and the response should be read from `reader`.
+    pub fn new(
+        writer: impl AsyncWrite + Unpin + Send + 'a,
+        reader: &'a mut Lines,
+        write_mode: WriteMode,
+        on_into_read: MessageKind,
+    ) -> Self {
+        let mut writer = packetline::async_io::Writer::new(Box::new(writer) as Box<dyn AsyncWrite + Unpin + Send>);
+        writer.enable_text_mode(write_mode == WriteMode::OneLfTerminatedLinePerWriteCall);
+        RequestWriter {
+            on_into_read,
+            writer,
+            reader,
+        }
+    }
+
+    /// Write the given `message` right away.
+    pub async fn write_message(&mut self, message: MessageKind) -> io::Result<()> {
+        // Lines written before must go out first.
+        self.writer.flush().await?;
+        let out = self.writer.inner_mut();
+        match message {
+            MessageKind::Flush => packetline::async_io::encode::flush_to_write(out).await,
+            MessageKind::Delimiter => packetline::async_io::encode::delim_to_write(out).await,
+            MessageKind::ResponseEnd => packetline::async_io::encode::response_end_to_write(out).await,
+            MessageKind::Text(text) => packetline::async_io::encode::text_to_write(text, out).await,
+        }
+        .map(|_| ())
+    }
+
+    /// Finish the request by sending the message configured on creation, and return the lines of the response.
+    pub async fn into_read(mut self) -> io::Result<&'a mut Lines> {
+        self.write_message(self.on_into_read).await?;
+        self.writer.flush().await?;
+        drop(self.writer);
+        self.reader.reset();
+        Ok(self.reader)
+    }
+}
+
+impl<'a> AsyncWrite for RequestWriter<'a> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().writer).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().writer).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().writer).poll_close(cx)
+    }
+}
+
+/// All methods provided by an asynchronous transport to communicate with a remote service.
+///
+/// Methods that talk to the server return boxed futures.
+pub trait Transport {
+    /// Initiate a connection to the given `service` and return the lines of its advertisement, up to the first flush.
+    ///
+    /// The desired protocol version is requested from the server, but it may respond with a lower one.
+    fn handshake(&mut self, service: Service) -> BoxFuture<'_, Result<&mut Lines, Error>>;
+
+    /// Start a new request writing lines according to `write_mode`, sending `on_into_read` once the response
+    /// should be read.
+    fn request(&mut self, write_mode: WriteMode, on_into_read: MessageKind) -> Result<RequestWriter<'_>, Error>;
+
+    /// Gracefully end the conversation with the server.
+    fn close(&mut self) -> BoxFuture<'_, Result<(), Error>>;
+
+    /// Returns the URL of the remote, for display purposes.
+    fn to_url(&self) -> String;
+
+    /// Returns the protocol version requested from the server during the handshake.
+    fn desired_protocol_version(&self) -> Protocol;
+
+    /// Returns true if the connection is kept open between requests, allowing the server to retain state.
+    fn is_stateful(&self) -> bool;
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn handshake(&mut self, service: Service) -> BoxFuture<'_, Result<&mut Lines, Error>> {
+        self.as_mut().handshake(service)
+    }
+
+    fn request(&mut self, write_mode: WriteMode, on_into_read: MessageKind) -> Result<RequestWriter<'_>, Error> {
+        self.as_mut().request(write_mode, on_into_read)
+    }
+
+    fn close(&mut self) -> BoxFuture<'_, Result<(), Error>> {
+        self.as_mut().close()
+    }
+
+    fn to_url(&self) -> String {
+        self.as_ref().to_url()
+    }
+
+    fn desired_protocol_version(&self) -> Protocol {
+        self.as_ref().desired_protocol_version()
+    }
+
+    fn is_stateful(&self) -> bool {
+        self.as_ref().is_stateful()
+    }
+}
@@ -0,0 +1,138 @@
+//! The `file://` transport, spawning `git-upload-pack` or `git-receive-pack` locally.
+//!
+//! The [`SpawnProcessOnDemand`][SpawnProcessOnDemand] transport is shared with the `ssh` transport, which spawns
+//! an `ssh` program running the service on the remote host instead.
+use crate::{
+    client::{self, git, ssh, Error, Lines, MessageKind, RequestWriter, WriteMode},
+    Protocol, Service,
+};
+use bstr::{BString, ByteSlice};
+use std::{
+    ffi::OsString,
+    process::{self, Command, Stdio},
+};
+
+/// A transport spawning the service program or `ssh` once the handshake is performed, talking to it through its
+/// standard input and output. Its standard error is inherited.
+pub struct SpawnProcessOnDemand {
+    desired_version: Protocol,
+    path: BString,
+    ssh: Option<ssh::Invocation>,
+    askpass: Option<OsString>,
+    connection: Option<git::Connection<process::ChildStdin>>,
+    child: Option<process::Child>,
+}
+
+impl SpawnProcessOnDemand {
+    pub(crate) fn new_local(path: BString, desired_version: Protocol) -> Self {
+        SpawnProcessOnDemand {
+            desired_version,
+            path,
+            ssh: None,
+            askpass: None,
+            connection: None,
+            child: None,
+        }
+    }
+
+    pub(crate) fn new_ssh(invocation: ssh::Invocation, path: BString, desired_version: Protocol) -> Self {
+        SpawnProcessOnDemand {
+            ssh: Some(invocation),
+            ..Self::new_local(path, desired_version)
+        }
+    }
+
+    fn command(&self, service: Service) -> Result<Command, Error> {
+        let mut cmd = match &self.ssh {
+            Some(invocation) => {
+                let mut cmd = invocation.to_command(service, &self.path, self.desired_version)?;
+                if let Some(askpass) = &self.askpass {
+                    if std::env::var_os("SSH_ASKPASS").is_none() {
+                        cmd.env("SSH_ASKPASS", askpass);
+                    }
+                }
+                cmd
+            }
+            None => {
+                if ssh::looks_like_option(&self.path) {
+                    return Err(Error::StrangeArgument("pathname", self.path.to_string()));
+                }
+                let mut cmd = Command::new(service.as_str());
+                cmd.arg(self.path.to_os_str_lossy());
+                cmd
+            }
+        };
+        if self.desired_version != Protocol::V1 {
+            cmd.env("GIT_PROTOCOL", format!("version={}", self.desired_version as usize));
+        }
+        cmd.stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit());
+        Ok(cmd)
+    }
+
+    fn connection_mut(&mut self) -> Result<&mut git::Connection<process::ChildStdin>, Error> {
+        self.connection.as_mut().ok_or(Error::MissingHandshake)
+    }
+}
+
+impl client::Transport for SpawnProcessOnDemand {
+    fn handshake(&mut self, service: Service) -> Result<&mut Lines, Error> {
+        let mut cmd = self.command(service)?;
+        let mut child = cmd
+            .spawn()
+            .map_err(|err| Error::SpawnCommand(err, format!("{:?}", cmd)))?;
+        let connection = git::Connection::new_for_spawned_process(
+            child.stdout.take().expect("stdout configured"),
+            child.stdin.take().expect("stdin configured"),
+            self.desired_version,
+            self.path.clone(),
+        );
+        self.child = Some(child);
+        self.connection = Some(connection);
+        self.connection_mut()?.handshake(service)
+    }
+
+    fn request(&mut self, write_mode: WriteMode, on_into_read: MessageKind) -> Result<RequestWriter<'_>, Error> {
+        self.connection_mut()?.request(write_mode, on_into_read)
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        if let Some(mut connection) = self.connection.take() {
+            connection.close()?;
+        }
+        if let Some(mut child) = self.child.take() {
+            child.wait()?;
+        }
+        Ok(())
+    }
+
+    fn to_url(&self) -> String {
+        match &self.ssh {
+            Some(invocation) => invocation.to_url(&self.path),
+            None => format!("file://{}", self.path),
+        }
+    }
+
+    fn desired_protocol_version(&self) -> Protocol {
+        self.desired_version
+    }
+
+    fn is_stateful(&self) -> bool {
+        true
+    }
+
+    /// `ssh` authenticates by itself, but is made to use the askpass program of `credentials` to ask for passwords
+    /// unless `SSH_ASKPASS` is set already.
+    fn set_credentials(&mut self, credentials: git_credentials::Cascade) {
+        if self.ssh.is_some() {
+            self.askpass = credentials.prompt.askpass;
+        }
+    }
+}
+
+/// Connect to the repository at `path` on the local file system by spawning `git-upload-pack` or `git-receive-pack`
+/// on handshake, asking it for the `desired_version` of the protocol.
+pub fn connect(path: impl Into<BString>, desired_version: Protocol) -> SpawnProcessOnDemand {
+    SpawnProcessOnDemand::new_local(path.into(), desired_version)
+}
@@ -0,0 +1,217 @@
+//! The `git://` transport, talking to a `git daemon` over TCP.
+use crate::{
+    client::{self, Error, Lines, MessageKind, RequestWriter, WriteMode},
+    packetline, Protocol, Service,
+};
+use bstr::{BString, ByteVec};
+use std::{
+    io,
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// The port `git daemon` listens on by default.
+pub const DEFAULT_PORT: u16 = 9418;
+
+/// Whether the other end of a [`Connection`][Connection] needs to be told which service to run.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum ConnectMode {
+    /// A `git daemon` which expects a request line naming the service and repository.
+    Daemon,
+    /// A spawned process that already is the service, either locally or on the other side of an ssh connection.
+    Process,
+}
+
+/// A connection to a `git daemon`, or anything else speaking the git protocol over a pair of streams.
+pub struct Connection<W> {
+    writer: W,
+    line_provider: Lines,
+    path: BString,
+    virtual_host: Option<(String, Option<u16>)>,
+    desired_version: Protocol,
+    mode: ConnectMode,
+    handshake_sent: bool,
+}
+
+impl<W: io::Write> Connection<W> {
+    /// Create a new connection reading server responses from `read` and sending requests to `write`, asking for
+    /// the repository at `path` with the `desired_version`.
+    ///
+    /// The optional `virtual_host` with port is sent along to allow the server to pick the repository by host name.
+    pub fn new(
+        read: impl io::Read + 'static,
+        write: W,
+        desired_version: Protocol,
+        path: impl Into<BString>,
+        virtual_host: Option<(impl Into<String>, Option<u16>)>,
+    ) -> Self {
+        Connection {
+            writer: write,
+            line_provider: packetline::Provider::new(Box::new(read), &[packetline::PacketLine::Flush]),
+            path: path.into(),
+            virtual_host: virtual_host.map(|(host, port)| (host.into(), port)),
+            desired_version,
+            mode: ConnectMode::Daemon,
+            handshake_sent: false,
+        }
+    }
+
+    /// Create a new connection to a process serving the repository at `path`, which communicates through its
+    /// standard output (`read`) and input (`write`).
+    ///
+    /// No request line is sent as the process already knows which service to provide, and the `desired_version`
+    /// must be communicated to it when spawning it.
+    pub fn new_for_spawned_process(
+        read: impl io::Read + 'static,
+        write: W,
+        desired_version: Protocol,
+        path: impl Into<BString>,
+    ) -> Self {
+        Connection {
+            mode: ConnectMode::Process,
+            ..Self::new(read, write, desired_version, path, None::<(String, Option<u16>)>)
+        }
+    }
+
+    /// Return the repository `path` as sent to the server.
+    pub fn path(&self) -> &BString {
+        &self.path
+    }
+
+    /// Access the writer to which requests are written.
+    pub fn writer_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+}
+
+/// Produce the request line sent as the very first pkt-line, without the pkt-line header.
+///
+/// It looks like `git-upload-pack /path\0host=example.com\0`, followed by `\0version=2\0` if V2 is desired.
+pub fn message(
+    service: Service,
+    desired_version: Protocol,
+    path: &[u8],
+    virtual_host: Option<&(String, Option<u16>)>,
+) -> BString {
+    let mut out = BString::from(service.as_str());
+    out.push(b' ');
+    out.push_str(path);
+    out.push(0);
+    if let Some((host, port)) = virtual_host {
+        out.push_str("host=");
+        out.push_str(host);
+        if let Some(port) = port {
+            out.push_str(format!(":{}", port));
+        }
+        out.push(0);
+    }
+    if desired_version != Protocol::V1 {
+        out.push(0);
+        out.push_str(format!("version={}", desired_version as usize));
+        out.push(0);
+    }
+    out
+}
+
+impl<W: io::Write> client::Transport for Connection<W> {
+    fn handshake(&mut self, service: Service) -> Result<&mut Lines, Error> {
+        if self.mode == ConnectMode::Daemon {
+            let line = message(service, self.desired_version, &self.path, self.virtual_host.as_ref());
+            packetline::encode::data_to_write(&line, &mut self.writer)?;
+            self.writer.flush()?;
+        }
+        self.handshake_sent = true;
+        self.line_provider.reset_with(&[packetline::PacketLine::Flush]);
+        Ok(&mut self.line_provider)
+    }
+
+    fn request(&mut self, write_mode: WriteMode, on_into_read: MessageKind) -> Result<RequestWriter<'_>, Error> {
+        if !self.handshake_sent {
+            return Err(Error::MissingHandshake);
+        }
+        Ok(RequestWriter::new(
+            &mut self.writer,
+            &mut self.line_provider,
+            write_mode,
+            on_into_read,
+        ))
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        if self.handshake_sent {
+            self.handshake_sent = false;
+            match packetline::encode::flush_to_write(&mut self.writer).and_then(|_| self.writer.flush()) {
+                // The server already ended the conversation, as it does after sending a pack.
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {}
+                res => res?,
+            }
+        }
+        Ok(())
+    }
+
+    fn to_url(&self) -> String {
+        let path = self.path.to_string();
+        match &self.virtual_host {
+            Some((host, Some(port))) => format!("git://{}:{}{}", host, port, path),
+            Some((host, None)) => format!("git://{}{}", host, path),
+            None => path,
+        }
+    }
+
+    fn desired_protocol_version(&self) -> Protocol {
+        self.desired_version
+    }
+
+    fn is_stateful(&self) -> bool {
+        true
+    }
+}
+
+/// Options to configure how to [`connect()`][connect()] to a `git daemon`.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default)]
+pub struct Options {
+    /// The port to connect to, or [`DEFAULT_PORT`][DEFAULT_PORT] if unset.
+    pub port: Option<u16>,
+    /// The time to wait for the connection to be established, or the operating system default if unset.
+    pub connect_timeout: Option<Duration>,
+    /// The host name and port to send to the server, instead of the `host` and `port` connected to.
+    pub virtual_host: Option<(String, Option<u16>)>,
+    /// The protocol version to ask the server for.
+    pub version: Protocol,
+}
+
+/// Connect to the `git daemon` at `host` to interact with the repository at `path`, configured by `options`.
+pub fn connect(host: &str, path: impl Into<BString>, options: Options) -> Result<Connection<TcpStream>, Error> {
+    let Options {
+        port: custom_port,
+        connect_timeout,
+        virtual_host,
+        version,
+    } = options;
+    let port = custom_port.unwrap_or(DEFAULT_PORT);
+    let stream = match connect_timeout {
+        Some(timeout) => connect_with_timeout(host, port, timeout)?,
+        None => TcpStream::connect((host, port)).map_err(|err| Error::Connect(host.into(), port, err))?,
+    };
+    let read = stream.try_clone()?;
+    let virtual_host = virtual_host.unwrap_or_else(|| (host.to_owned(), custom_port));
+    Ok(Connection::new(read, stream, version, path, Some(virtual_host)))
+}
+
+fn connect_with_timeout(host: &str, port: u16, timeout: Duration) -> Result<TcpStream, Error> {
+    let mut last_err = None;
+    for addr in (host, port)
+        .to_socket_addrs()
+        .map_err(|err| Error::Connect(host.into(), port, err))?
+    {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(match last_err {
+        Some(err) if err.kind() == io::ErrorKind::TimedOut => Error::ConnectTimeout(host.into(), port),
+        Some(err) => Error::Connect(host.into(), port, err),
+        None => Error::UnresolvedHost(host.into(), port),
+    })
+}
@@ -0,0 +1,181 @@
+//! An [`Http`][crate::client::http::Http] backend using `libcurl`, performing each request on a thread of its own.
+use crate::client::http::{pipe, traits, GetResponse, Http, Options, PostResponse};
+use std::io::{Read, Write};
+
+/// The number of chunks buffered in each pipe before the producing side blocks.
+const PIPE_CAPACITY: usize = 64;
+
+/// A backend using `libcurl`.
+#[derive(Default)]
+pub struct Curl;
+
+struct Request {
+    url: String,
+    headers: Vec<String>,
+    proxy: Option<String>,
+    ssl_no_verify: bool,
+    ssl_ca_info: Option<std::path::PathBuf>,
+    identity: Option<git_credentials::Identity>,
+    post_body: Option<pipe::Reader>,
+}
+
+impl Curl {
+    fn spawn(request: Request) -> (pipe::Reader, pipe::Reader) {
+        let (headers_tx, headers_rx) = pipe::unidirectional(PIPE_CAPACITY);
+        let (body_tx, body_rx) = pipe::unidirectional(PIPE_CAPACITY);
+        std::thread::spawn(move || perform(request, headers_tx, body_tx));
+        (headers_rx, body_rx)
+    }
+}
+
+impl Http for Curl {
+    type Headers = pipe::Reader;
+    type ResponseBody = pipe::Reader;
+    type PostBody = pipe::Writer;
+
+    fn get(
+        &mut self,
+        url: &str,
+        headers: Vec<String>,
+        options: &Options,
+    ) -> Result<GetResponse<Self::Headers, Self::ResponseBody>, traits::Error> {
+        let (headers, body) = Curl::spawn(Request {
+            url: url.to_owned(),
+            headers,
+            proxy: options.proxy.clone(),
+            ssl_no_verify: options.ssl_no_verify,
+            ssl_ca_info: options.ssl_ca_info.clone(),
+            identity: options.identity.clone(),
+            post_body: None,
+        });
+        Ok(GetResponse { headers, body })
+    }
+
+    fn post(
+        &mut self,
+        url: &str,
+        headers: Vec<String>,
+        options: &Options,
+    ) -> Result<PostResponse<Self::Headers, Self::ResponseBody, Self::PostBody>, traits::Error> {
+        let (post_body, post_body_rx) = pipe::unidirectional(PIPE_CAPACITY);
+        let (headers, body) = Curl::spawn(Request {
+            url: url.to_owned(),
+            headers,
+            proxy: options.proxy.clone(),
+            ssl_no_verify: options.ssl_no_verify,
+            ssl_ca_info: options.ssl_ca_info.clone(),
+            identity: options.identity.clone(),
+            post_body: Some(post_body_rx),
+        });
+        Ok(PostResponse {
+            post_body,
+            headers,
+            body,
+        })
+    }
+}
+
+fn perform(request: Request, headers_tx: pipe::Writer, mut body_tx: pipe::Writer) {
+    let Request {
+        url,
+        headers,
+        proxy,
+        ssl_no_verify,
+        ssl_ca_info,
+        identity,
+        mut post_body,
+    } = request;
+    let mut headers_tx = Some(headers_tx);
+    let mut collected_headers = Vec::new();
+    let mut status = 0;
+
+    let mut handle = curl::easy::Easy::new();
+    let res = (|| -> Result<(), curl::Error> {
+        handle.url(&url)?;
+        handle.accept_encoding("gzip")?;
+        handle.follow_location(false)?;
+        if let Some(proxy) = &proxy {
+            handle.proxy(proxy)?;
+        }
+        if ssl_no_verify {
+            handle.ssl_verify_peer(false)?;
+            handle.ssl_verify_host(false)?;
+        }
+        if let Some(path) = &ssl_ca_info {
+            handle.cainfo(path)?;
+        }
+        if let Some(identity) = &identity {
+            handle.username(&identity.username)?;
+            handle.password(&identity.password)?;
+        }
+        let mut list = curl::easy::List::new();
+        for header in &headers {
+            list.append(header)?;
+        }
+        if post_body.is_some() {
+            handle.post(true)?;
+            list.append("Transfer-Encoding: chunked")?;
+            list.append("Expect:")?;
+        }
+        handle.http_headers(list)?;
+
+        let mut transfer = handle.transfer();
+        transfer.header_function(|line| {
+            let line = String::from_utf8_lossy(line);
+            let line = line.trim_end();
+            if line.starts_with("HTTP/") {
+                status = line
+                    .split_whitespace()
+                    .nth(1)
+                    .and_then(|code| code.parse().ok())
+                    .unwrap_or(0);
+                collected_headers.clear();
+            } else if line.is_empty() {
+                if let Some(mut headers_tx) = headers_tx.take() {
+                    if (300..400).contains(&status) {
+                        let location = location(&collected_headers).unwrap_or_default();
+                        headers_tx.send_error(traits::Error::Redirect(status, location).into());
+                        return false;
+                    } else if (200..300).contains(&status) {
+                        headers_tx.write_all(&collected_headers).ok();
+                    } else {
+                        headers_tx.send_error(traits::Error::Status(status, url.clone()).into());
+                        return false;
+                    }
+                }
+            } else {
+                collected_headers.extend_from_slice(line.as_bytes());
+                collected_headers.push(b'\n');
+            }
+            true
+        })?;
+        transfer.write_function(|data| Ok(body_tx.write_all(data).map(|_| data.len()).unwrap_or(0)))?;
+        if let Some(post_body) = post_body.as_mut() {
+            transfer.read_function(move |buf| Ok(post_body.read(buf).unwrap_or(0)))?;
+        }
+        transfer.perform()
+    })();
+
+    if let Err(err) = res {
+        let err = traits::Error::Detail(format!("{}", err)).into();
+        match headers_tx {
+            Some(headers_tx) => headers_tx.send_error(err),
+            None => {
+                if status == 0 || (200..300).contains(&status) {
+                    body_tx.send_error(err)
+                }
+            }
+        }
+    }
+}
+
+/// Find the value of the `Location` header in the collected `headers`, if present.
+fn location(headers: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(headers).lines().find_map(|line| {
+        let mut tokens = line.splitn(2, ':');
+        match (tokens.next(), tokens.next()) {
+            (Some(name), Some(value)) if name.trim().eq_ignore_ascii_case("location") => Some(value.trim().to_owned()),
+            _ => None,
+        }
+    })
+}
@@ -0,0 +1,171 @@
+use crate::client::http::{traits::Error, GetResponse, Http, Options};
+use std::{
+    borrow::Cow,
+    io::{self, Read},
+};
+
+/// The maximum amount of redirects to follow for one request, like `git` does.
+pub const MAX_REDIRECTS: usize = 20;
+
+/// The statuses indicating that the same request may succeed if it is repeated later.
+const TRANSIENT_STATUSES: &[u16] = &[408, 429, 500, 502, 503, 504];
+
+/// The outcome of a [`download()`].
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Download {
+    /// The url the data was received from, which differs from the requested one if the request was redirected.
+    pub url: String,
+    /// The headers of the last response, one `Name: value` per line.
+    pub headers: Vec<u8>,
+}
+
+/// Perform a GET request to `url` with `headers` using `http` configured with `options`, and write the response body
+/// to `out`.
+///
+/// Redirects are followed if [enabled][Options::follow_redirects], except for the ones from `https` to `http`, and the
+/// [identity][Options::identity] isn't sent anymore once a redirect leads to another server. The request is repeated up to
+/// [`max_retries`][Options::max_retries] times with exponential backoff if it failed for reasons which may go away,
+/// like connection failures or the statuses 408, 429, 500, 502, 503 and 504. If the connection broke while receiving
+/// the body, the next attempt asks for the missing part only, which is skipped as well if the server sends the whole
+/// body again.
+pub fn download<H: Http>(
+    http: &mut H,
+    url: &str,
+    headers: Vec<String>,
+    options: &Options,
+    out: &mut dyn io::Write,
+) -> Result<Download, Error> {
+    let mut url = url.to_owned();
+    let mut options = Cow::Borrowed(options);
+    let mut redirects = 0;
+    let mut retries = 0;
+    let mut received = 0;
+    loop {
+        let mut request_headers = headers.clone();
+        if received > 0 {
+            request_headers.push(format!("Range: bytes={}-", received));
+        }
+        let GetResponse {
+            headers: mut response_headers,
+            mut body,
+        } = http.get(&url, request_headers, &options)?;
+        let mut header_lines = Vec::new();
+        let res = response_headers
+            .read_to_end(&mut header_lines)
+            .map_err(into_error)
+            .and_then(|_| {
+                let skip = if has_header(&header_lines, "content-range") {
+                    0
+                } else {
+                    received
+                };
+                copy_skipping(&mut body, out, skip, &mut received)
+            });
+        match res {
+            Ok(()) => {
+                return Ok(Download {
+                    url,
+                    headers: header_lines,
+                })
+            }
+            Err(Error::Redirect(_, location)) if options.follow_redirects => {
+                redirects += 1;
+                if redirects > MAX_REDIRECTS {
+                    return Err(Error::TooManyRedirects(url));
+                }
+                let target = join(&url, &location);
+                let is_downgrade = url.starts_with("https://") && !target.starts_with("https://");
+                if is_downgrade || !(target.starts_with("http://") || target.starts_with("https://")) {
+                    return Err(Error::UnsupportedRedirect(target));
+                }
+                if options.identity.is_some() && !is_same_server(&url, &target) {
+                    options.to_mut().identity = None;
+                }
+                url = target;
+            }
+            Err(err) if is_transient(&err) && retries < options.max_retries => {
+                std::thread::sleep(options.retry_delay * 2u32.saturating_pow(retries as u32));
+                retries += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Copy `body` to `out` while counting the `received` bytes, but don't write the first `skip` bytes as we have them
+/// already.
+fn copy_skipping(
+    mut body: impl io::Read,
+    out: &mut dyn io::Write,
+    mut skip: usize,
+    received: &mut usize,
+) -> Result<(), Error> {
+    let mut buf = [0; 64 * 1024];
+    loop {
+        let len = match body.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(into_error(err)),
+        };
+        let skipped = skip.min(len);
+        skip -= skipped;
+        out.write_all(&buf[skipped..len]).map_err(Error::Write)?;
+        *received += len - skipped;
+    }
+}
+
+fn has_header(headers: &[u8], name: &str) -> bool {
+    headers.split(|b| *b == b'\n').any(|line| {
+        let line = String::from_utf8_lossy(line);
+        let mut tokens = line.splitn(2, ':');
+        matches!(tokens.next(), Some(actual) if actual.trim().eq_ignore_ascii_case(name))
+    })
+}
+
+fn is_transient(err: &Error) -> bool {
+    match err {
+        Error::Status(status, _) => TRANSIENT_STATUSES.contains(status),
+        Error::Detail(_) => true,
+        _ => false,
+    }
+}
+
+pub(crate) fn into_error(err: io::Error) -> Error {
+    Error::from_io(err).unwrap_or_else(|err| Error::Detail(err.to_string()))
+}
+
+/// Return true if `a` and `b` have the same host and port, ignoring the user information.
+pub(crate) fn is_same_server(a: &str, b: &str) -> bool {
+    fn authority(url: &str) -> &str {
+        let rest = url.find("://").map_or(url, |pos| &url[pos + 3..]);
+        let authority = &rest[..rest.find(&['/', '?', '#'][..]).unwrap_or(rest.len())];
+        authority.rsplit('@').next().unwrap_or(authority)
+    }
+    authority(a).eq_ignore_ascii_case(authority(b))
+}
+
+/// Resolve the `location` of a redirect relative to `url`, the url which was redirected.
+fn join(url: &str, location: &str) -> String {
+    if location.contains("://") {
+        return location.to_owned();
+    }
+    let scheme_end = url.find("://").map_or(0, |pos| pos + 3);
+    if let Some(location) = location.strip_prefix("//") {
+        return format!("{}{}", &url[..scheme_end], location);
+    }
+    let path_start = url[scheme_end..].find('/').map_or(url.len(), |pos| scheme_end + pos);
+    if location.starts_with('/') {
+        return format!("{}{}", &url[..path_start], location);
+    }
+    let path = &url[..url[path_start..].find('?').map_or(url.len(), |pos| path_start + pos)];
+    let directory_end = path[path_start..]
+        .rfind('/')
+        .map_or(path.len(), |pos| path_start + pos + 1);
+    let mut joined = path[..directory_end].to_owned();
+    if !joined.ends_with('/') {
+        joined.push('/');
+    }
+    joined.push_str(location);
+    joined
+}
@@ -0,0 +1,305 @@
+//! The smart HTTP(S) transport, which works with any implementation of the [`Http`][Http] trait.
+//!
+//! Backends are provided by the `http-client-curl` and `http-client-reqwest` features, the former taking
+//! precedence if both are enabled. Request bodies are streamed with chunked transfer encoding and gzip
+//! compressed responses are accepted. If the server asks for authentication during the handshake, the credential
+//! helpers configured in the [`Options`] are asked once for the credentials of the url which asked for them, and the
+//! requests are repeated there with basic authentication.
+//!
+//! Like git does by default, only the redirects of the initial request for the advertisement are followed, and if
+//! the advertisement was found elsewhere, all further requests go there as well. This initial request is also
+//! retried and resumed if configured, as it is repeatable unlike the requests sending data to the server.
+use crate::{
+    client::{self, Lines, MessageKind, RequestWriter, WriteMode},
+    packetline, Protocol, Service,
+};
+use std::{
+    io::{self, BufRead},
+    time::Duration,
+};
+
+///
+pub mod pipe;
+mod traits;
+pub use traits::{Error, GetResponse, Http, PostResponse};
+
+mod download;
+pub use download::{download, Download, MAX_REDIRECTS};
+
+///
+#[cfg(feature = "http-client-curl")]
+pub mod curl;
+///
+#[cfg(feature = "http-client-reqwest")]
+pub mod reqwest;
+
+/// The backend used by [`connect()`][connect()].
+#[cfg(feature = "http-client-curl")]
+pub type Impl = curl::Curl;
+/// The backend used by [`connect()`][connect()].
+#[cfg(all(feature = "http-client-reqwest", not(feature = "http-client-curl")))]
+pub type Impl = reqwest::Remote;
+
+/// Configuration applied to all requests.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct Options {
+    /// Headers like `Name: value` to send along with each request.
+    pub extra_headers: Vec<String>,
+    /// The proxy to send all requests through, like `http://proxy.example.com:8080`.
+    pub proxy: Option<String>,
+    /// If true, the certificate of the server isn't verified, like `http.sslVerify = false` does.
+    pub ssl_no_verify: bool,
+    /// A file with the certificates in PEM format to verify the server with instead of the ones of the system.
+    pub ssl_ca_info: Option<std::path::PathBuf>,
+    /// The user name and password to send with each request using basic authentication.
+    pub identity: Option<git_credentials::Identity>,
+    /// The credential helpers to ask for an `identity` if the server requires authentication, which are told
+    /// whether it was accepted.
+    pub credentials: Option<git_credentials::Cascade>,
+    /// If true, which is the default, redirects of requests which can be repeated are followed, like
+    /// `http.followRedirects = initial`.
+    pub follow_redirects: bool,
+    /// The amount of times to repeat requests which can be repeated if they failed for reasons which may go away,
+    /// like `http.maxRetries`. It's 0 by default.
+    pub max_retries: usize,
+    /// The time to wait before the first retry, which is doubled for each retry after it.
+    pub retry_delay: Duration,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            extra_headers: Vec::new(),
+            proxy: None,
+            ssl_no_verify: false,
+            ssl_ca_info: None,
+            identity: None,
+            credentials: None,
+            follow_redirects: true,
+            max_retries: 0,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// A transport talking the smart HTTP protocol, performing one request per round-trip.
+pub struct Transport<H: Http> {
+    url: String,
+    desired_version: Protocol,
+    http: H,
+    options: Options,
+    service: Option<Service>,
+    line_provider: Option<Lines>,
+}
+
+impl<H: Http> Transport<H> {
+    /// Access the configuration applied to all requests.
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.options
+    }
+
+    fn headers(&self, mut headers: Vec<String>) -> Vec<String> {
+        if self.desired_version != Protocol::V1 {
+            headers.push(format!("Git-Protocol: version={}", self.desired_version as usize));
+        }
+        headers.extend(self.options.extra_headers.iter().cloned());
+        headers
+    }
+}
+
+impl<H> client::Transport for Transport<H>
+where
+    H: Http,
+    H::ResponseBody: 'static,
+    H::Headers: 'static,
+{
+    fn handshake(&mut self, service: Service) -> Result<&mut Lines, client::Error> {
+        let path = format!("/info/refs?service={}", service.as_str());
+        let requested = format!("{}{}", self.url, path);
+        let mut url = requested.clone();
+        let mut filled = None;
+        let (download, body) = loop {
+            let mut body = Vec::new();
+            let headers = self.headers(Vec::new());
+            let res = download(&mut self.http, &url, headers, &self.options, &mut body).and_then(|download| {
+                check_content_type(service, "advertisement", download.headers.as_slice())
+                    .map_err(download::into_error)?;
+                Ok(download)
+            });
+            match res {
+                Ok(download) => break (download, body),
+                Err(Error::Status(401, unauthorized)) if filled.is_none() => {
+                    if let Some(credentials) = &self.options.credentials {
+                        // The credentials are for the server asking for them, which may be one we were redirected
+                        // to, so that's where the request is repeated to keep them from being sent anywhere else.
+                        let base = unauthorized
+                            .strip_suffix(&path)
+                            .ok_or_else(|| Error::UnsupportedRedirect(unauthorized.clone()))?;
+                        let outcome = credentials.fill(base)?;
+                        self.options.identity = Some(outcome.identity.clone());
+                        filled = Some(outcome);
+                        url = unauthorized;
+                        continue;
+                    }
+                    return Err(Error::Status(401, unauthorized).into());
+                }
+                Err(err) => {
+                    if let (Some(credentials), Some(outcome)) = (&self.options.credentials, &filled) {
+                        if matches!(err, Error::Status(401, _)) {
+                            self.options.identity = None;
+                            credentials.reject(outcome)?;
+                        }
+                    }
+                    return Err(err.into());
+                }
+            }
+        };
+        if let (Some(credentials), Some(outcome)) = (&self.options.credentials, &filled) {
+            credentials.approve(outcome)?;
+        }
+        if !download::is_same_server(&url, &download.url) {
+            self.options.identity = None;
+        }
+        if download.url != requested {
+            self.url = download
+                .url
+                .strip_suffix(&path)
+                .ok_or_else(|| Error::UnsupportedRedirect(download.url.clone()))?
+                .to_owned();
+        }
+
+        let line_provider = self.line_provider.get_or_insert_with(|| {
+            packetline::Provider::new(
+                Box::new(io::empty()) as Box<dyn io::Read>,
+                &[packetline::PacketLine::Flush],
+            )
+        });
+        line_provider.replace(Box::new(io::Cursor::new(body)));
+        line_provider.reset_with(&[packetline::PacketLine::Flush]);
+
+        // V1 responses are prefixed with the announcement of the service, followed by a flush.
+        let announced = format!("# service={}", service.as_str());
+        let has_announcement = match line_provider.peek_line() {
+            Some(Ok(Ok(line))) => matches!(line.to_text(), Some(text) if text.as_ref() == announced.as_bytes()),
+            Some(Err(err)) => return Err(err.into()),
+            _ => false,
+        };
+        if has_announcement {
+            line_provider.next_line();
+            if line_provider.next_line().is_some() {
+                return Err(Error::MissingServiceAnnouncement(service.as_str()).into());
+            }
+            line_provider.reset();
+        } else if self.desired_version == Protocol::V1 {
+            return Err(Error::MissingServiceAnnouncement(service.as_str()).into());
+        }
+        self.service = Some(service);
+        Ok(line_provider)
+    }
+
+    fn request(
+        &mut self,
+        write_mode: WriteMode,
+        on_into_read: MessageKind,
+    ) -> Result<RequestWriter<'_>, client::Error> {
+        let service = self.service.ok_or(client::Error::MissingHandshake)?;
+        let url = format!("{}/{}", self.url, service.as_str());
+        let headers = self.headers(vec![
+            format!("Content-Type: application/x-{}-request", service.as_str()),
+            format!("Accept: application/x-{}-result", service.as_str()),
+        ]);
+        let PostResponse {
+            post_body,
+            headers,
+            body,
+        } = self.http.post(&url, headers, &self.options)?;
+        let line_provider = self.line_provider.as_mut().ok_or(client::Error::MissingHandshake)?;
+        line_provider.replace(Box::new(HeadersThenBody {
+            service,
+            headers: Some(headers),
+            body,
+        }));
+        Ok(RequestWriter::new(post_body, line_provider, write_mode, on_into_read))
+    }
+
+    fn close(&mut self) -> Result<(), client::Error> {
+        self.service = None;
+        Ok(())
+    }
+
+    fn to_url(&self) -> String {
+        self.url.clone()
+    }
+
+    fn desired_protocol_version(&self) -> Protocol {
+        self.desired_version
+    }
+
+    fn is_stateful(&self) -> bool {
+        false
+    }
+
+    fn set_credentials(&mut self, credentials: git_credentials::Cascade) {
+        self.options.credentials = Some(credentials);
+    }
+
+    fn set_http_options(&mut self, options: Options) {
+        let credentials = self.options.credentials.take();
+        self.options = options;
+        if self.options.credentials.is_none() {
+            self.options.credentials = credentials;
+        }
+    }
+}
+
+/// Read the response body once the headers were checked, which happens after the request body was sent.
+struct HeadersThenBody<H, B> {
+    service: Service,
+    headers: Option<H>,
+    body: B,
+}
+
+impl<H: BufRead, B: BufRead> io::Read for HeadersThenBody<H, B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(headers) = self.headers.take() {
+            check_content_type(self.service, "result", headers)?;
+        }
+        self.body.read(buf)
+    }
+}
+
+fn check_content_type(service: Service, kind: &str, headers: impl BufRead) -> io::Result<()> {
+    let expected = format!("application/x-{}-{}", service.as_str(), kind);
+    let mut actual = None;
+    for line in headers.lines() {
+        let line = line?;
+        let mut tokens = line.splitn(2, ':');
+        if let (Some(name), Some(value)) = (tokens.next(), tokens.next()) {
+            if name.trim().eq_ignore_ascii_case("content-type") {
+                actual = Some(value.trim().to_owned());
+            }
+        }
+    }
+    match actual {
+        Some(actual) if actual == expected => Ok(()),
+        actual => Err(Error::UnexpectedContentType(expected, actual).into()),
+    }
+}
+
+/// Use the given `http` backend to talk to the repository at `url`, asking for the `desired_version` of the protocol.
+pub fn connect_with<H: Http>(http: H, url: &str, desired_version: Protocol, options: Options) -> Transport<H> {
+    Transport {
+        url: url.trim_end_matches('/').to_owned(),
+        desired_version,
+        http,
+        options,
+        service: None,
+        line_provider: None,
+    }
+}
+
+/// Talk to the repository at `url` using the default backend, asking for the `desired_version` of the protocol.
+#[cfg(any(feature = "http-client-curl", feature = "http-client-reqwest"))]
+pub fn connect(url: &str, desired_version: Protocol) -> Transport<Impl> {
+    connect_with(Impl::default(), url, desired_version, Options::default())
+}
@@ -0,0 +1,85 @@
+//! A bounded in-memory pipe to stream bytes between threads, used to connect request and response bodies to
+//! backends performing requests on a thread of their own.
+use std::{
+    io,
+    sync::mpsc::{sync_channel, Receiver, SyncSender},
+};
+
+/// The writing end of a pipe, sending each call to `write()` as one chunk.
+pub struct Writer {
+    channel: SyncSender<io::Result<Vec<u8>>>,
+}
+
+/// The reading end of a pipe, which is at its end once the [`Writer`][Writer] is dropped.
+pub struct Reader {
+    channel: Receiver<io::Result<Vec<u8>>>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+/// Create a new pipe holding up to `capacity` chunks before writes block.
+pub fn unidirectional(capacity: usize) -> (Writer, Reader) {
+    let (tx, rx) = sync_channel(capacity);
+    (
+        Writer { channel: tx },
+        Reader {
+            channel: rx,
+            buf: Vec::new(),
+            pos: 0,
+        },
+    )
+}
+
+impl Writer {
+    /// Make the reader fail with `err` once it reaches the data sent so far.
+    pub fn send_error(&self, err: io::Error) {
+        self.channel.send(Err(err)).ok();
+    }
+}
+
+impl io::Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.channel
+            .send(Ok(buf.to_vec()))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the reading end of the pipe was dropped"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl io::BufRead for Reader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos == self.buf.len() {
+            match self.channel.recv() {
+                Ok(Ok(chunk)) => {
+                    self.buf = chunk;
+                    self.pos = 0;
+                }
+                Ok(Err(err)) => return Err(err),
+                Err(_disconnected) => return Ok(&[]),
+            }
+        }
+        Ok(&self.buf[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buf.len());
+    }
+}
+
+impl io::Read for Reader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        use io::BufRead;
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
@@ -0,0 +1,159 @@
+//! An [`Http`][crate::client::http::Http] backend using the pure Rust `reqwest` crate, performing each request on a
+//! thread of its own.
+use crate::client::http::{pipe, traits, GetResponse, Http, Options, PostResponse};
+use std::{convert::TryFrom, io::Write};
+
+/// The number of chunks buffered in each pipe before the producing side blocks.
+const PIPE_CAPACITY: usize = 64;
+
+/// A backend using a blocking `reqwest` client, which is rebuilt whenever the proxy or TLS configuration changes.
+#[derive(Default)]
+pub struct Remote {
+    client: Option<(ClientOptions, reqwest::blocking::Client)>,
+}
+
+/// The parts of the [`Options`] which are baked into a client.
+#[derive(PartialEq)]
+struct ClientOptions {
+    proxy: Option<String>,
+    ssl_no_verify: bool,
+    ssl_ca_info: Option<std::path::PathBuf>,
+}
+
+impl Remote {
+    fn client(&mut self, options: &Options) -> Result<reqwest::blocking::Client, traits::Error> {
+        let client_options = ClientOptions {
+            proxy: options.proxy.clone(),
+            ssl_no_verify: options.ssl_no_verify,
+            ssl_ca_info: options.ssl_ca_info.clone(),
+        };
+        match &self.client {
+            Some((cached, client)) if cached == &client_options => Ok(client.clone()),
+            _ => {
+                let mut builder = reqwest::blocking::Client::builder().redirect(reqwest::redirect::Policy::none());
+                if let Some(proxy) = &client_options.proxy {
+                    builder = builder.proxy(reqwest::Proxy::all(proxy.as_str()).map_err(detail)?);
+                }
+                if client_options.ssl_no_verify {
+                    builder = builder.danger_accept_invalid_certs(true);
+                }
+                if let Some(path) = &client_options.ssl_ca_info {
+                    let pem = std::fs::read(path).map_err(detail)?;
+                    for certificate in reqwest::Certificate::from_pem_bundle(&pem).map_err(detail)? {
+                        builder = builder.add_root_certificate(certificate);
+                    }
+                }
+                let client = builder.build().map_err(detail)?;
+                self.client = Some((client_options, client.clone()));
+                Ok(client)
+            }
+        }
+    }
+
+    fn spawn(request: reqwest::blocking::RequestBuilder, url: &str) -> (pipe::Reader, pipe::Reader) {
+        let (mut headers_tx, headers_rx) = pipe::unidirectional(PIPE_CAPACITY);
+        let (mut body_tx, body_rx) = pipe::unidirectional(PIPE_CAPACITY);
+        let url = url.to_owned();
+        std::thread::spawn(move || {
+            let mut response = match request.send() {
+                Ok(response) => response,
+                Err(err) => return headers_tx.send_error(detail(err).into()),
+            };
+            let status = response.status();
+            if status.is_redirection() {
+                let location = response
+                    .headers()
+                    .get(reqwest::header::LOCATION)
+                    .and_then(|location| location.to_str().ok())
+                    .unwrap_or_default()
+                    .to_owned();
+                return headers_tx.send_error(traits::Error::Redirect(status.as_u16(), location).into());
+            }
+            if !status.is_success() {
+                return headers_tx.send_error(traits::Error::Status(status.as_u16(), url).into());
+            }
+            let mut headers = Vec::new();
+            for (name, value) in response.headers() {
+                headers.extend_from_slice(name.as_str().as_bytes());
+                headers.extend_from_slice(b": ");
+                headers.extend_from_slice(value.as_bytes());
+                headers.push(b'\n');
+            }
+            if headers_tx.write_all(&headers).is_err() {
+                return;
+            }
+            drop(headers_tx);
+            if let Err(err) = response.copy_to(&mut body_tx) {
+                body_tx.send_error(detail(err).into());
+            }
+        });
+        (headers_rx, body_rx)
+    }
+}
+
+fn detail(err: impl std::fmt::Display) -> traits::Error {
+    traits::Error::Detail(err.to_string())
+}
+
+fn authenticated(request: reqwest::blocking::RequestBuilder, options: &Options) -> reqwest::blocking::RequestBuilder {
+    match &options.identity {
+        Some(identity) => request.basic_auth(&identity.username, Some(&identity.password)),
+        None => request,
+    }
+}
+
+fn to_header_map(headers: Vec<String>) -> Result<reqwest::header::HeaderMap, traits::Error> {
+    let mut map = reqwest::header::HeaderMap::new();
+    for header in headers {
+        let mut tokens = header.splitn(2, ':');
+        match (tokens.next(), tokens.next()) {
+            (Some(name), Some(value)) => {
+                map.append(
+                    reqwest::header::HeaderName::try_from(name.trim()).map_err(detail)?,
+                    reqwest::header::HeaderValue::try_from(value.trim()).map_err(detail)?,
+                );
+            }
+            _ => return Err(traits::Error::Detail(format!("Invalid header line: '{}'", header))),
+        }
+    }
+    Ok(map)
+}
+
+impl Http for Remote {
+    type Headers = pipe::Reader;
+    type ResponseBody = pipe::Reader;
+    type PostBody = pipe::Writer;
+
+    fn get(
+        &mut self,
+        url: &str,
+        headers: Vec<String>,
+        options: &Options,
+    ) -> Result<GetResponse<Self::Headers, Self::ResponseBody>, traits::Error> {
+        let request = authenticated(self.client(options)?.get(url).headers(to_header_map(headers)?), options);
+        let (headers, body) = Remote::spawn(request, url);
+        Ok(GetResponse { headers, body })
+    }
+
+    fn post(
+        &mut self,
+        url: &str,
+        headers: Vec<String>,
+        options: &Options,
+    ) -> Result<PostResponse<Self::Headers, Self::ResponseBody, Self::PostBody>, traits::Error> {
+        let (post_body, post_body_rx) = pipe::unidirectional(PIPE_CAPACITY);
+        let request = authenticated(
+            self.client(options)?
+                .post(url)
+                .headers(to_header_map(headers)?)
+                .body(reqwest::blocking::Body::new(post_body_rx)),
+            options,
+        );
+        let (headers, body) = Remote::spawn(request, url);
+        Ok(PostResponse {
+            post_body,
+            headers,
+            body,
+        })
+    }
+}
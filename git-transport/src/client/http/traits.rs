@@ -0,0 +1,101 @@
+use crate::client::http::Options;
+use quick_error::quick_error;
+use std::io;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Detail(description: String) {
+            display("{}", description)
+        }
+        Status(code: u16, url: String) {
+            display("Received HTTP status {} when requesting '{}'", code, url)
+        }
+        UnexpectedContentType(expected: String, actual: Option<String>) {
+            display("Expected content type '{}', but got '{}'", expected, actual.as_deref().unwrap_or("<none>"))
+        }
+        MissingServiceAnnouncement(service: &'static str) {
+            display("The server response did not start with the smart HTTP announcement of service '{}'", service)
+        }
+        Redirect(code: u16, location: String) {
+            display("Received HTTP status {} redirecting to '{}'", code, location)
+        }
+        TooManyRedirects(url: String) {
+            display("Gave up following redirects at '{}'", url)
+        }
+        UnsupportedRedirect(url: String) {
+            display("Refusing to follow the redirect to '{}'", url)
+        }
+        Write(err: io::Error) {
+            display("The received data could not be written")
+            source(err)
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        io::Error::other(err)
+    }
+}
+
+impl Error {
+    /// Retrieve an `Error` previously converted into an `io::Error`, or `None` if `err` carries something else.
+    pub fn from_io(err: io::Error) -> Result<Self, io::Error> {
+        if matches!(err.get_ref(), Some(inner) if inner.is::<Error>()) {
+            Ok(*err.into_inner().expect("checked").downcast::<Error>().expect("checked"))
+        } else {
+            Err(err)
+        }
+    }
+}
+
+/// The response of a GET request, with headers as `Name: value` lines and the body, which are available once the
+/// server responded.
+pub struct GetResponse<H, B> {
+    /// The response headers, one per line.
+    pub headers: H,
+    /// The response body.
+    pub body: B,
+}
+
+/// The response of a POST request, available once the request body was written to `post_body` and dropped.
+pub struct PostResponse<H, B, PB> {
+    /// The body of the request, which is sent with chunked transfer encoding.
+    pub post_body: PB,
+    /// The response headers, one per line.
+    pub headers: H,
+    /// The response body.
+    pub body: B,
+}
+
+/// A backend performing HTTP requests, as used by the smart HTTP [`Transport`][crate::client::http::Transport].
+///
+/// Errors occurring once the request is in flight are received when reading the headers, and failure statuses
+/// are reported as [`Error::Status`][Error::Status] that way as well. Redirects are not followed but reported as
+/// [`Error::Redirect`][Error::Redirect], leaving it to the caller to decide whether to follow them.
+pub trait Http {
+    /// The type of the header lines.
+    type Headers: io::BufRead;
+    /// The type of the response body.
+    type ResponseBody: io::BufRead;
+    /// The type of the request body.
+    type PostBody: io::Write;
+
+    /// Initiate a GET request to `url` with the given `headers`, configured with `options`.
+    fn get(
+        &mut self,
+        url: &str,
+        headers: Vec<String>,
+        options: &Options,
+    ) -> Result<GetResponse<Self::Headers, Self::ResponseBody>, Error>;
+
+    /// Initiate a POST request to `url` with the given `headers`, configured with `options`.
+    #[allow(clippy::type_complexity)]
+    fn post(
+        &mut self,
+        url: &str,
+        headers: Vec<String>,
+        options: &Options,
+    ) -> Result<PostResponse<Self::Headers, Self::ResponseBody, Self::PostBody>, Error>;
+}
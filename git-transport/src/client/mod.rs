@@ -0,0 +1,304 @@
+//! Connect to a remote to invoke one of its services, exchanging pkt-lines over the connection.
+//!
+//! All transports implement [`Transport`][Transport], which exposes the server response as pkt-lines
+//! to be interpreted by the protocol layer.
+use crate::{packetline, Protocol, Service};
+use bstr::ByteSlice;
+use quick_error::quick_error;
+use std::io::{self, Write};
+
+#[cfg(feature = "async-client")]
+pub mod async_io;
+///
+pub mod file;
+///
+pub mod git;
+///
+pub mod http;
+///
+pub mod ssh;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Io(err: io::Error) {
+            display("An IO error occurred when talking to the server")
+            from()
+            source(err)
+        }
+        Connect(host: String, port: u16, err: io::Error) {
+            display("Could not connect to '{}:{}'", host, port)
+            source(err)
+        }
+        ConnectTimeout(host: String, port: u16) {
+            display("Connecting to '{}:{}' timed out", host, port)
+        }
+        UnresolvedHost(host: String, port: u16) {
+            display("Could not resolve any address for '{}:{}'", host, port)
+        }
+        SpawnCommand(err: io::Error, command: String) {
+            display("Could not spawn '{}'", command)
+            source(err)
+        }
+        SshPortUnsupported(program: String) {
+            display("The ssh program '{}' does not support setting a port", program)
+        }
+        StrangeArgument(kind: &'static str, value: String) {
+            display("strange {} '{}' blocked", kind, value)
+        }
+        Http(err: http::Error) {
+            display("An HTTP request failed")
+            from()
+            source(err)
+        }
+        Url(err: git_url::parse::Error) {
+            display("The URL could not be parsed")
+            from()
+            source(err)
+        }
+        UnsupportedScheme(scheme: git_url::Scheme) {
+            display("The '{}' scheme is not supported by any transport", scheme)
+        }
+        CompiledWithoutHttp(scheme: git_url::Scheme) {
+            display("'{}' is not compiled in, enable one of the http-client-* features", scheme)
+        }
+        MissingHandshake {
+            display("A handshake must be performed before sending requests")
+        }
+        Credentials(err: git_credentials::Error) {
+            display("Could not obtain credentials")
+            from()
+            source(err)
+        }
+    }
+}
+
+/// The pkt-lines a server sends as response, delimited by flush packets.
+pub type Lines = packetline::Provider<Box<dyn io::Read>>;
+
+/// How to interpret each call to `write()` on a [`RequestWriter`][RequestWriter].
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum WriteMode {
+    /// Each `write()` call produces one or more data lines containing the bytes as is, for example pack data.
+    Binary,
+    /// Each `write()` call produces exactly one data line terminated with a newline, the usual way to send commands
+    /// and arguments.
+    OneLfTerminatedLinePerWriteCall,
+}
+
+/// A special line or message to send to the server.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum MessageKind {
+    /// A flush packet, `0000`.
+    Flush,
+    /// A delimiter packet, `0001`, separating the capabilities from the arguments of V2 commands.
+    Delimiter,
+    /// A response-end packet, `0002`.
+    ResponseEnd,
+    /// A newline terminated text line like `done`.
+    Text(&'static [u8]),
+}
+
+/// A single request to the server, writing pkt-lines until [`into_read()`][RequestWriter::into_read()] is called
+/// to obtain the response.
+pub struct RequestWriter<'a> {
+    on_into_read: MessageKind,
+    writer: packetline::Writer<Box<dyn io::Write + 'a>>,
+    reader: &'a mut Lines,
+}
+
+impl<'a> RequestWriter<'a> {
+    /// Create a new instance writing to `writer` in the given `write_mode`, sending `on_into_read` once the request
+    /// is complete and the response should be read from `reader`.
+    pub fn new(
+        writer: impl io::Write + 'a,
+        reader: &'a mut Lines,
+        write_mode: WriteMode,
+        on_into_read: MessageKind,
+    ) -> Self {
+        let mut writer = packetline::Writer::new(Box::new(writer) as Box<dyn io::Write>);
+        writer.enable_text_mode(write_mode == WriteMode::OneLfTerminatedLinePerWriteCall);
+        RequestWriter {
+            on_into_read,
+            writer,
+            reader,
+        }
+    }
+
+    /// Write the given `message` right away.
+    pub fn write_message(&mut self, message: MessageKind) -> io::Result<()> {
+        let out = self.writer.inner_mut();
+        match message {
+            MessageKind::Flush => packetline::encode::flush_to_write(out),
+            MessageKind::Delimiter => packetline::encode::delim_to_write(out),
+            MessageKind::ResponseEnd => packetline::encode::response_end_to_write(out),
+            MessageKind::Text(text) => packetline::encode::text_to_write(text, out),
+        }
+        .map(|_| ())
+    }
+
+    /// Finish the request by sending the message configured on creation, and return the lines of the response.
+    pub fn into_read(self) -> io::Result<&'a mut Lines> {
+        self.into_read_with_raw_data(|_| Ok(()))
+    }
+
+    /// Like [`into_read()`][RequestWriter::into_read()], but call `write_raw` after sending the configured message to
+    /// write data without pkt-line framing, like the pack following the commands of a push.
+    pub fn into_read_with_raw_data(
+        mut self,
+        write_raw: impl FnOnce(&mut dyn io::Write) -> io::Result<()>,
+    ) -> io::Result<&'a mut Lines> {
+        self.write_message(self.on_into_read)?;
+        write_raw(self.writer.inner_mut())?;
+        self.writer.flush()?;
+        drop(self.writer);
+        self.reader.reset();
+        Ok(self.reader)
+    }
+}
+
+impl<'a> io::Write for RequestWriter<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// All methods provided by a transport to communicate with a remote service.
+pub trait Transport {
+    /// Initiate a connection to the given `service` and return the lines of its advertisement, up to the first flush.
+    ///
+    /// The desired protocol version is requested from the server, but it may respond with a lower one.
+    fn handshake(&mut self, service: Service) -> Result<&mut Lines, Error>;
+
+    /// Start a new request writing lines according to `write_mode`, sending `on_into_read` once the response
+    /// should be read.
+    fn request(&mut self, write_mode: WriteMode, on_into_read: MessageKind) -> Result<RequestWriter<'_>, Error>;
+
+    /// Gracefully end the conversation with the server.
+    fn close(&mut self) -> Result<(), Error>;
+
+    /// Returns the URL of the remote, for display purposes.
+    fn to_url(&self) -> String;
+
+    /// Returns the protocol version requested from the server during the handshake.
+    fn desired_protocol_version(&self) -> Protocol;
+
+    /// Returns true if the connection is kept open between requests, allowing the server to retain state.
+    fn is_stateful(&self) -> bool;
+
+    /// Use `credentials` to authenticate with the server if it asks for it.
+    ///
+    /// Transports which don't authenticate or leave it to other programs ignore them.
+    fn set_credentials(&mut self, _credentials: git_credentials::Cascade) {}
+
+    /// Apply `options` like proxies and TLS settings to all further HTTP requests, keeping the credentials
+    /// set previously if `options` has none.
+    ///
+    /// Transports which don't talk HTTP ignore them.
+    fn set_http_options(&mut self, _options: http::Options) {}
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn handshake(&mut self, service: Service) -> Result<&mut Lines, Error> {
+        self.as_mut().handshake(service)
+    }
+
+    fn request(&mut self, write_mode: WriteMode, on_into_read: MessageKind) -> Result<RequestWriter<'_>, Error> {
+        self.as_mut().request(write_mode, on_into_read)
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        self.as_mut().close()
+    }
+
+    fn to_url(&self) -> String {
+        self.as_ref().to_url()
+    }
+
+    fn desired_protocol_version(&self) -> Protocol {
+        self.as_ref().desired_protocol_version()
+    }
+
+    fn is_stateful(&self) -> bool {
+        self.as_ref().is_stateful()
+    }
+
+    fn set_credentials(&mut self, credentials: git_credentials::Cascade) {
+        self.as_mut().set_credentials(credentials)
+    }
+
+    fn set_http_options(&mut self, options: http::Options) {
+        self.as_mut().set_http_options(options)
+    }
+}
+
+/// Parse `url` and return a transport for its scheme, asking for the `desired_version` of the protocol.
+///
+/// No connection is made for transports that connect lazily on handshake, which are all but the `git` transport.
+pub fn connect(url: &[u8], desired_version: Protocol) -> Result<Box<dyn Transport>, Error> {
+    let url = git_url::parse(url)?;
+    Ok(match url.scheme {
+        git_url::Scheme::File => Box::new(file::connect(url.path, desired_version)),
+        git_url::Scheme::Ssh => Box::new(ssh::connect(
+            url.host.as_deref().expect("ssh URLs have a host"),
+            url.path,
+            desired_version,
+            url.user.as_deref(),
+            url.port,
+        )?),
+        git_url::Scheme::Git => Box::new(git::connect(
+            url.host.as_deref().expect("git URLs have a host"),
+            url.path,
+            git::Options {
+                port: url.port,
+                version: desired_version,
+                ..Default::default()
+            },
+        )?),
+        #[cfg(not(any(feature = "http-client-curl", feature = "http-client-reqwest")))]
+        git_url::Scheme::Https | git_url::Scheme::Http => return Err(Error::CompiledWithoutHttp(url.scheme)),
+        #[cfg(any(feature = "http-client-curl", feature = "http-client-reqwest"))]
+        git_url::Scheme::Https | git_url::Scheme::Http => Box::new(http::connect(&url.to_string(), desired_version)),
+        git_url::Scheme::Ftp | git_url::Scheme::Ftps => return Err(Error::UnsupportedScheme(url.scheme)),
+    })
+}
+
+/// Write the file at `url` to `out`, which is a local path or `file://` URL, or fetched with a GET request configured
+/// with `options` for `http(s)` URLs.
+///
+/// This is used for data that doesn't need a git server, like bundles and bundle lists.
+pub fn download(url: &[u8], options: &http::Options, out: &mut dyn Write) -> Result<(), Error> {
+    let parsed = git_url::parse(url)?;
+    match parsed.scheme {
+        git_url::Scheme::File => {
+            let path = parsed
+                .path
+                .to_path()
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            io::copy(&mut std::fs::File::open(path)?, out)?;
+            Ok(())
+        }
+        #[cfg(not(any(feature = "http-client-curl", feature = "http-client-reqwest")))]
+        git_url::Scheme::Https | git_url::Scheme::Http => {
+            let _ = options;
+            Err(Error::CompiledWithoutHttp(parsed.scheme))
+        }
+        #[cfg(any(feature = "http-client-curl", feature = "http-client-reqwest"))]
+        git_url::Scheme::Https | git_url::Scheme::Http => {
+            let url = std::str::from_utf8(url).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            http::download(
+                &mut http::Impl::default(),
+                url,
+                options.extra_headers.clone(),
+                options,
+                out,
+            )?;
+            Ok(())
+        }
+        scheme => Err(Error::UnsupportedScheme(scheme)),
+    }
+}
@@ -0,0 +1,274 @@
+//! The `ssh` transport, running the service on the remote host through an `ssh` program.
+//!
+//! The program is `ssh` by default and may be overridden with `GIT_SSH_COMMAND`, which is run through a shell,
+//! or `GIT_SSH`, which is run as is. `GIT_SSH_VARIANT` overrides the argument style detected from the program name.
+use crate::{
+    client::{file::SpawnProcessOnDemand, Error},
+    Protocol, Service,
+};
+use bstr::{BString, ByteSlice};
+use std::{
+    ffi::{OsStr, OsString},
+    path::Path,
+    process::Command,
+};
+
+/// The kind of `ssh` program, determining which arguments it understands.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum ProgramKind {
+    /// OpenSSH, supporting `-p <port>` and `-o SendEnv=GIT_PROTOCOL` to pass the protocol version.
+    Ssh,
+    /// `plink` or `putty`, supporting `-P <port>`.
+    Plink,
+    /// `tortoiseplink`, supporting `-P <port>` and requiring `-batch`.
+    TortoisePlink,
+    /// A program that only knows the host and the command to run, without support for ports.
+    Simple,
+}
+
+impl ProgramKind {
+    /// Derive the kind from the file name of `program`, assuming OpenSSH if it is unknown.
+    pub fn from_program_name(program: impl AsRef<OsStr>) -> Self {
+        let name = Path::new(program.as_ref())
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_ascii_lowercase());
+        match name.as_deref() {
+            Some("plink") | Some("putty") => ProgramKind::Plink,
+            Some("tortoiseplink") => ProgramKind::TortoisePlink,
+            _ => ProgramKind::Ssh,
+        }
+    }
+
+    /// Parse the value of `GIT_SSH_VARIANT`, returning `None` for unknown values and `auto`.
+    pub fn from_variant(variant: &str) -> Option<Self> {
+        Some(match variant {
+            "ssh" => ProgramKind::Ssh,
+            "plink" | "putty" => ProgramKind::Plink,
+            "tortoiseplink" => ProgramKind::TortoisePlink,
+            "simple" => ProgramKind::Simple,
+            _ => return None,
+        })
+    }
+}
+
+/// The program to run to connect to the remote host.
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+pub struct Program {
+    /// The program to run, or the command line to run through `sh -c` if `use_shell` is true.
+    pub path: OsString,
+    /// If true, `path` is a command line which may contain arguments.
+    pub use_shell: bool,
+    /// The kind of program, determining the arguments passed to it.
+    pub kind: ProgramKind,
+}
+
+impl Default for Program {
+    fn default() -> Self {
+        Program {
+            path: "ssh".into(),
+            use_shell: false,
+            kind: ProgramKind::Ssh,
+        }
+    }
+}
+
+impl Program {
+    /// Determine the program from the `GIT_SSH_COMMAND`, `GIT_SSH` and `GIT_SSH_VARIANT` environment variables.
+    pub fn from_env() -> Self {
+        Self::from_env_values(
+            std::env::var_os("GIT_SSH_COMMAND"),
+            std::env::var_os("GIT_SSH"),
+            std::env::var_os("GIT_SSH_VARIANT"),
+        )
+    }
+
+    /// Like [`from_env()`][Program::from_env()], but with the values of the environment variables passed explicitly.
+    pub fn from_env_values(ssh_command: Option<OsString>, ssh: Option<OsString>, variant: Option<OsString>) -> Self {
+        let mut program = match (ssh_command, ssh) {
+            (Some(command), _) => {
+                let kind = command
+                    .to_string_lossy()
+                    .split_whitespace()
+                    .next()
+                    .map(ProgramKind::from_program_name)
+                    .unwrap_or(ProgramKind::Ssh);
+                Program {
+                    path: command,
+                    use_shell: true,
+                    kind,
+                }
+            }
+            (None, Some(path)) => Program {
+                kind: ProgramKind::from_program_name(&path),
+                path,
+                use_shell: false,
+            },
+            (None, None) => Program::default(),
+        };
+        if let Some(kind) = variant.and_then(|v| ProgramKind::from_variant(&v.to_string_lossy())) {
+            program.kind = kind;
+        }
+        program
+    }
+}
+
+/// Everything needed to run a service on a remote host through an `ssh` program.
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+pub struct Invocation {
+    /// The program to run.
+    pub program: Program,
+    /// The host to connect to.
+    pub host: String,
+    /// The user to log in as, or the default of the `ssh` program if unset.
+    pub user: Option<String>,
+    /// The port to connect to, or the default of the `ssh` program if unset.
+    pub port: Option<u16>,
+}
+
+impl Invocation {
+    /// Return the arguments to pass to the program to run `service` for the repository at `path` on the remote host.
+    ///
+    /// Hosts and users looking like a command line option are rejected, as they would be interpreted as such by the
+    /// program.
+    pub fn arguments(&self, service: Service, path: &[u8], desired_version: Protocol) -> Result<Vec<OsString>, Error> {
+        if let Some(user) = self.user.as_ref().filter(|user| looks_like_option(user.as_bytes())) {
+            return Err(Error::StrangeArgument("username", user.clone()));
+        }
+        if looks_like_option(self.host.as_bytes()) {
+            return Err(Error::StrangeArgument("hostname", self.host.clone()));
+        }
+        let mut args = Vec::<OsString>::new();
+        match self.program.kind {
+            ProgramKind::Ssh => {
+                if desired_version != Protocol::V1 {
+                    args.extend(vec!["-o".into(), "SendEnv=GIT_PROTOCOL".into()]);
+                }
+                if let Some(port) = self.port {
+                    args.extend(vec!["-p".into(), port.to_string().into()]);
+                }
+            }
+            ProgramKind::Plink | ProgramKind::TortoisePlink => {
+                if self.program.kind == ProgramKind::TortoisePlink {
+                    args.push("-batch".into());
+                }
+                if let Some(port) = self.port {
+                    args.extend(vec!["-P".into(), port.to_string().into()]);
+                }
+            }
+            ProgramKind::Simple => {
+                if self.port.is_some() {
+                    return Err(Error::SshPortUnsupported(
+                        self.program.path.to_string_lossy().into_owned(),
+                    ));
+                }
+            }
+        }
+        args.push(
+            match &self.user {
+                Some(user) => format!("{}@{}", user, self.host),
+                None => self.host.clone(),
+            }
+            .into(),
+        );
+        let mut remote_command = BString::from(service.as_str());
+        remote_command.push(b' ');
+        remote_command.extend_from_slice(&quote(path));
+        args.push(remote_command.to_os_str_lossy().into_owned());
+        Ok(args)
+    }
+
+    pub(crate) fn to_command(
+        &self,
+        service: Service,
+        path: &[u8],
+        desired_version: Protocol,
+    ) -> Result<Command, Error> {
+        let args = self.arguments(service, path, desired_version)?;
+        Ok(if self.program.use_shell {
+            let command_line = self.program.path.to_string_lossy();
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c")
+                .arg(format!("{} \"$@\"", command_line))
+                .arg(command_line.as_ref())
+                .args(args);
+            cmd
+        } else {
+            let mut cmd = Command::new(&self.program.path);
+            cmd.args(args);
+            cmd
+        })
+    }
+
+    pub(crate) fn to_url(&self, path: &[u8]) -> String {
+        let mut url = String::from("ssh://");
+        if let Some(user) = &self.user {
+            url.push_str(user);
+            url.push('@');
+        }
+        url.push_str(&self.host);
+        if let Some(port) = self.port {
+            url.push_str(&format!(":{}", port));
+        }
+        if !path.starts_with(b"/") {
+            url.push('/');
+        }
+        url.push_str(&path.to_str_lossy());
+        url
+    }
+}
+
+/// Return true if `arg` would be taken as option instead of positional argument by most programs.
+pub(crate) fn looks_like_option(arg: &[u8]) -> bool {
+    arg.starts_with(b"-")
+}
+
+/// Quote `path` for use as a single argument in a POSIX shell on the remote host.
+fn quote(path: &[u8]) -> BString {
+    let mut out = BString::from("'");
+    for &b in path {
+        match b {
+            b'\'' | b'!' => {
+                out.extend_from_slice(b"'\\");
+                out.push(b);
+                out.push(b'\'');
+            }
+            _ => out.push(b),
+        }
+    }
+    out.push(b'\'');
+    out
+}
+
+/// Connect to `host` as `user` on `port` to run a service for the repository at `path`, asking for the
+/// `desired_version` of the protocol. The `ssh` program is determined by [`Program::from_env()`][Program::from_env()].
+///
+/// The program is spawned once the handshake is performed.
+pub fn connect(
+    host: &str,
+    path: impl Into<BString>,
+    desired_version: Protocol,
+    user: Option<&str>,
+    port: Option<u16>,
+) -> Result<SpawnProcessOnDemand, Error> {
+    connect_with(
+        Invocation {
+            program: Program::from_env(),
+            host: host.into(),
+            user: user.map(Into::into),
+            port,
+        },
+        path,
+        desired_version,
+    )
+}
+
+/// Like [`connect()`][connect()], but with full control over the `invocation` of the `ssh` program.
+pub fn connect_with(
+    invocation: Invocation,
+    path: impl Into<BString>,
+    desired_version: Protocol,
+) -> Result<SpawnProcessOnDemand, Error> {
+    let path = path.into();
+    invocation.arguments(Service::UploadPack, &path, desired_version)?;
+    Ok(SpawnProcessOnDemand::new_ssh(invocation, path, desired_version))
+}
@@ -1,9 +1,36 @@
 #![forbid(unsafe_code)]
 
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
+pub use git_packetline as packetline;
+
+/// The version of the way client and server communicate.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Protocol {
+    V1 = 1,
+    #[default]
+    V2 = 2,
+}
+
+/// The kind of service to invoke on the remote for fetching or pushing data.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Service {
+    /// The service sending packs to the client, used for fetching.
+    UploadPack,
+    /// The service receiving packs from the client, used for pushing.
+    ReceivePack,
+}
+
+impl Service {
+    /// The name of the program implementing the service, as used in the protocol.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Service::ReceivePack => "git-receive-pack",
+            Service::UploadPack => "git-upload-pack",
+        }
     }
 }
+
+pub mod client;
+#[doc(inline)]
+pub use client::Transport;
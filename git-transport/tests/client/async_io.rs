@@ -0,0 +1,57 @@
+use crate::fixture_bytes;
+use bstr::ByteSlice;
+use futures_lite::{future::block_on, io::Cursor, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, StreamExt};
+use git_transport::{
+    client::{
+        self,
+        async_io::{git, Transport},
+        MessageKind, WriteMode,
+    },
+    Protocol, Service,
+};
+
+#[test]
+fn handshake_v1_and_request() -> Result<(), Box<dyn std::error::Error>> {
+    let mut out = Vec::new();
+    block_on(async {
+        let input = Cursor::new(fixture_bytes("v1/clone.response"));
+        let mut c = git::Connection::new(input, &mut out, Protocol::V1, "/foo.git", Some(("example.com", None)));
+        assert_eq!(c.to_url(), "git://example.com/foo.git");
+        assert!(c.is_stateful());
+        assert!(matches!(
+            c.request(WriteMode::Binary, MessageKind::Flush),
+            Err(client::Error::MissingHandshake)
+        ));
+
+        let lines = c.handshake(Service::UploadPack).await?;
+        let mut first = String::new();
+        lines.as_read().read_line(&mut first).await?;
+        assert!(first.starts_with("529d504d0deadca6d9079225c631eea5757a47f4 HEAD\0multi_ack"));
+        let remaining = lines
+            .as_read()
+            .lines()
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(remaining.len(), 5, "it stops at the flush line");
+
+        let mut writer = c.request(WriteMode::OneLfTerminatedLinePerWriteCall, MessageKind::Text(b"done"))?;
+        writer
+            .write_all(b"want 529d504d0deadca6d9079225c631eea5757a47f4")
+            .await?;
+        writer.write_message(MessageKind::Flush).await?;
+        let mut response = Vec::new();
+        writer.into_read().await?.as_read().read_to_end(&mut response).await?;
+        assert!(response.is_empty(), "there is nothing more to read");
+        c.close().await?;
+        Ok::<_, Box<dyn std::error::Error>>(())
+    })?;
+
+    assert_eq!(
+        out.as_bstr(),
+        b"002egit-upload-pack /foo.git\0host=example.com\x000032want 529d504d0deadca6d9079225c631eea5757a47f4\n00000009done\n0000"
+            .as_bstr()
+    );
+    Ok(())
+}
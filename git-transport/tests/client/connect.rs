@@ -0,0 +1,48 @@
+use crate::client::fixture_repo_path;
+use git_transport::{client, Protocol, Service};
+use std::io::BufRead;
+
+#[test]
+fn transport_by_scheme() -> Result<(), Box<dyn std::error::Error>> {
+    let mut c = client::connect(fixture_repo_path().as_bytes(), Protocol::V1)?;
+    assert_eq!(c.to_url(), format!("file://{}", fixture_repo_path()));
+    let refs = c.handshake(Service::UploadPack)?.as_read().lines().count();
+    assert_eq!(refs, 2);
+    c.close()?;
+
+    let c = client::connect(b"git@example.com:repo.git", Protocol::V2)?;
+    assert_eq!(c.to_url(), "ssh://git@example.com/repo.git");
+    assert_eq!(c.desired_protocol_version(), Protocol::V2);
+    let c = client::connect(b"ssh://example.com:2222/~/repo.git", Protocol::V2)?;
+    assert_eq!(c.to_url(), "ssh://example.com:2222/~/repo.git");
+    Ok(())
+}
+
+#[test]
+fn unsupported_schemes() {
+    assert!(matches!(
+        client::connect(b"ftp://example.com/repo.git", Protocol::V2),
+        Err(client::Error::UnsupportedScheme(git_url::Scheme::Ftp))
+    ));
+    assert!(matches!(
+        client::connect(b"foo://example.com/repo.git", Protocol::V2),
+        Err(client::Error::Url(_))
+    ));
+}
+
+#[test]
+#[cfg(not(any(feature = "http-client-curl", feature = "http-client-reqwest")))]
+fn http_requires_a_backend() {
+    assert!(matches!(
+        client::connect(b"https://example.com/repo.git", Protocol::V2),
+        Err(client::Error::CompiledWithoutHttp(git_url::Scheme::Https))
+    ));
+}
+
+#[test]
+#[cfg(any(feature = "http-client-curl", feature = "http-client-reqwest"))]
+fn http_is_connected_lazily() -> Result<(), Box<dyn std::error::Error>> {
+    let c = client::connect(b"https://example.com/repo.git/", Protocol::V2)?;
+    assert_eq!(c.to_url(), "https://example.com/repo.git");
+    Ok(())
+}
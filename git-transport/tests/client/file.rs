@@ -0,0 +1,59 @@
+use crate::client::fixture_repo_path;
+use git_transport::{
+    client::{self, file, MessageKind, Transport, WriteMode},
+    Protocol, Service,
+};
+use std::io::{BufRead, Read, Write};
+
+#[test]
+fn handshake_v1_lists_refs() -> Result<(), Box<dyn std::error::Error>> {
+    let path = fixture_repo_path();
+    let mut c = file::connect(path.as_str(), Protocol::V1);
+    assert_eq!(c.to_url(), format!("file://{}", path));
+    let lines = c.handshake(Service::UploadPack)?;
+    let refs = lines.as_read().lines().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(refs.len(), 2);
+    assert!(refs[0].starts_with("6070b330b610600fd6650c123b6dbadda8b0b14f HEAD\0"));
+    assert_eq!(refs[1], "6070b330b610600fd6650c123b6dbadda8b0b14f refs/heads/main");
+    c.close()?;
+    Ok(())
+}
+
+#[test]
+fn handshake_v2_and_ls_refs() -> Result<(), Box<dyn std::error::Error>> {
+    let mut c = file::connect(fixture_repo_path(), Protocol::V2);
+    let lines = c.handshake(Service::UploadPack)?;
+    let capabilities = lines.as_read().lines().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(capabilities[0], "version 2");
+    assert!(capabilities.iter().any(|c| c.starts_with("ls-refs")));
+
+    let mut writer = c.request(WriteMode::OneLfTerminatedLinePerWriteCall, MessageKind::Flush)?;
+    writer.write_all(b"command=ls-refs")?;
+    writer.write_message(MessageKind::Delimiter)?;
+    writer.write_all(b"symrefs")?;
+    let mut refs = String::new();
+    writer.into_read()?.as_read().read_to_string(&mut refs)?;
+    assert_eq!(
+        refs,
+        "6070b330b610600fd6650c123b6dbadda8b0b14f HEAD symref-target:refs/heads/main\n\
+         6070b330b610600fd6650c123b6dbadda8b0b14f refs/heads/main\n"
+    );
+    c.close()?;
+    Ok(())
+}
+
+#[test]
+fn paths_looking_like_options_are_blocked() {
+    let mut c = file::connect("--upload-pack=touch pwned", Protocol::V2);
+    assert!(matches!(
+        c.handshake(Service::UploadPack),
+        Err(client::Error::StrangeArgument("pathname", _))
+    ));
+}
+
+#[test]
+fn requests_need_a_handshake() {
+    let mut c = file::connect("/does-not-matter", Protocol::V2);
+    assert!(c.request(WriteMode::Binary, MessageKind::Flush).is_err());
+    assert!(c.close().is_ok(), "closing without connection is fine");
+}
@@ -0,0 +1,117 @@
+use crate::fixture_bytes;
+use bstr::ByteSlice;
+use git_transport::{
+    client::{self, git, MessageKind, Transport, WriteMode},
+    Protocol, Service,
+};
+use std::{
+    io::{BufRead, Read, Write},
+    net::TcpListener,
+    time::Duration,
+};
+
+#[test]
+fn request_line_with_and_without_version() {
+    let host = ("example.com".to_string(), Some(2222));
+    assert_eq!(
+        git::message(Service::UploadPack, Protocol::V1, b"/foo.git", Some(&host)),
+        b"git-upload-pack /foo.git\0host=example.com:2222\0".as_bstr()
+    );
+    assert_eq!(
+        git::message(Service::ReceivePack, Protocol::V2, b"/foo.git", None),
+        b"git-receive-pack /foo.git\0\0version=2\0".as_bstr()
+    );
+}
+
+#[test]
+fn handshake_v1_and_request() -> Result<(), Box<dyn std::error::Error>> {
+    let mut out = Vec::new();
+    let input = std::io::Cursor::new(fixture_bytes("v1/clone.response"));
+    let mut c = git::Connection::new(input, &mut out, Protocol::V1, "/foo.git", Some(("example.com", None)));
+    assert_eq!(c.to_url(), "git://example.com/foo.git");
+    assert!(c.is_stateful());
+    assert!(matches!(
+        c.request(WriteMode::Binary, MessageKind::Flush),
+        Err(client::Error::MissingHandshake)
+    ));
+
+    let lines = c.handshake(Service::UploadPack)?;
+    let mut first = String::new();
+    lines.as_read().read_line(&mut first)?;
+    assert!(first.starts_with("529d504d0deadca6d9079225c631eea5757a47f4 HEAD\0multi_ack"));
+    let remaining = lines.as_read().lines().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(remaining.len(), 5, "it stops at the flush line");
+
+    let mut writer = c.request(WriteMode::OneLfTerminatedLinePerWriteCall, MessageKind::Text(b"done"))?;
+    writer.write_all(b"want 529d504d0deadca6d9079225c631eea5757a47f4")?;
+    writer.write_message(MessageKind::Flush)?;
+    let mut response = Vec::new();
+    writer.into_read()?.as_read().read_to_end(&mut response)?;
+    assert!(response.is_empty(), "there is nothing more to read");
+    c.close()?;
+    drop(c);
+
+    assert_eq!(
+        out.as_bstr(),
+        b"002egit-upload-pack /foo.git\0host=example.com\x000032want 529d504d0deadca6d9079225c631eea5757a47f4\n00000009done\n0000"
+            .as_bstr()
+    );
+    Ok(())
+}
+
+#[test]
+fn connect_to_daemon_over_tcp() -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    let server = std::thread::spawn(move || -> std::io::Result<Vec<u8>> {
+        let (mut stream, _) = listener.accept()?;
+        let mut request = vec![0; 46];
+        stream.read_exact(&mut request)?;
+        stream.write_all(&crate::fixture_bytes("v2/clone.response"))?;
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest)?;
+        request.extend(rest);
+        Ok(request)
+    });
+
+    let mut c = git::connect(
+        "127.0.0.1",
+        "/repo.git",
+        git::Options {
+            port: Some(port),
+            connect_timeout: Some(Duration::from_secs(5)),
+            virtual_host: Some(("virtual.example.com".into(), None)),
+            version: Protocol::V2,
+        },
+    )?;
+    assert_eq!(c.desired_protocol_version(), Protocol::V2);
+    let lines = c.handshake(Service::UploadPack)?;
+    let advertisement = lines.as_read().lines().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(advertisement[0], "version 2");
+    c.close()?;
+    drop(c);
+
+    assert_eq!(
+        server.join().expect("no panic")?.as_bstr(),
+        b"0042git-upload-pack /repo.git\0host=virtual.example.com\0\0version=2\x000000".as_bstr()
+    );
+    Ok(())
+}
+
+#[test]
+fn connection_refused() -> Result<(), Box<dyn std::error::Error>> {
+    let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+    for connect_timeout in &[None, Some(Duration::from_secs(1))] {
+        let res = git::connect(
+            "127.0.0.1",
+            "/repo.git",
+            git::Options {
+                port: Some(port),
+                connect_timeout: *connect_timeout,
+                ..Default::default()
+            },
+        );
+        assert!(matches!(res, Err(client::Error::Connect(_, p, _)) if p == port));
+    }
+    Ok(())
+}
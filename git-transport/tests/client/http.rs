@@ -0,0 +1,559 @@
+use crate::fixture_bytes;
+use bstr::ByteSlice;
+use git_transport::{
+    client::{self, http, MessageKind, Transport, WriteMode},
+    Protocol, Service,
+};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    thread::JoinHandle,
+};
+
+struct Request {
+    head: String,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    /// Additional header lines, like `Location: /elsewhere`.
+    headers: Vec<String>,
+    body: Vec<u8>,
+    /// If set, the connection is closed after sending this many bytes of the body.
+    cut_after: Option<usize>,
+}
+
+impl Response {
+    fn new(status: &'static str, content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Response {
+            status,
+            content_type,
+            headers: Vec::new(),
+            body: body.into(),
+            cut_after: None,
+        }
+    }
+}
+
+/// Serve one `Response` per connection, and return all requests received.
+fn serve(responses: Vec<Response>) -> (String, JoinHandle<std::io::Result<Vec<Request>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("free port");
+    let url = format!("http://{}/repo.git", listener.local_addr().expect("bound"));
+    let server = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for response in responses {
+            let (stream, _) = listener.accept()?;
+            requests.push(handle(stream, response)?);
+        }
+        Ok(requests)
+    });
+    (url, server)
+}
+
+fn handle(stream: TcpStream, response: Response) -> std::io::Result<Request> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut head = String::new();
+    loop {
+        let len = reader.read_line(&mut head)?;
+        if len <= 2 {
+            break;
+        }
+    }
+    let mut body = Vec::new();
+    if head.to_ascii_lowercase().contains("transfer-encoding: chunked") {
+        loop {
+            let mut size = String::new();
+            reader.read_line(&mut size)?;
+            let size = usize::from_str_radix(size.trim(), 16).expect("valid chunk size");
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk)?;
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    }
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    )?;
+    for header in &response.headers {
+        write!(stream, "{}\r\n", header)?;
+    }
+    write!(stream, "\r\n")?;
+    stream.write_all(&response.body[..response.cut_after.unwrap_or(response.body.len())])?;
+    Ok(Request { head, body })
+}
+
+fn advertisement(version: Protocol) -> Response {
+    let body = match version {
+        Protocol::V1 => {
+            let mut body = b"001e# service=git-upload-pack\n0000".to_vec();
+            body.extend(fixture_bytes("v1/clone.response"));
+            body
+        }
+        Protocol::V2 => fixture_bytes("v2/clone.response"),
+    };
+    Response::new("200 OK", "application/x-git-upload-pack-advertisement", body)
+}
+
+fn nak() -> Response {
+    Response::new("200 OK", "application/x-git-upload-pack-result", &b"0008NAK\n"[..])
+}
+
+fn handshake_and_request(http: impl http::Http + 'static, version: Protocol) -> Result<(), Box<dyn std::error::Error>> {
+    let (url, server) = serve(vec![advertisement(version), nak()]);
+    let mut options = http::Options::default();
+    options.extra_headers.push("X-Custom: value".into());
+    let mut c = http::connect_with(http, &format!("{}/", url), version, options);
+    assert_eq!(c.to_url(), url, "trailing slashes are removed");
+    assert!(!c.is_stateful());
+
+    let first_line = c
+        .handshake(Service::UploadPack)?
+        .as_read()
+        .lines()
+        .next()
+        .expect("a line")?;
+    match version {
+        Protocol::V1 => assert!(first_line.starts_with("529d504d0deadca6d9079225c631eea5757a47f4 HEAD\0")),
+        Protocol::V2 => assert_eq!(first_line, "version 2"),
+    }
+
+    let mut writer = c.request(WriteMode::OneLfTerminatedLinePerWriteCall, MessageKind::Text(b"done"))?;
+    writer.write_all(b"want 529d504d0deadca6d9079225c631eea5757a47f4")?;
+    writer.write_message(MessageKind::Flush)?;
+    let mut response = String::new();
+    writer.into_read()?.as_read().read_to_string(&mut response)?;
+    assert_eq!(response, "NAK\n");
+
+    let requests = server.join().expect("no panic")?;
+    let get = &requests[0];
+    assert!(get
+        .head
+        .starts_with("GET /repo.git/info/refs?service=git-upload-pack HTTP/1.1\r\n"));
+    assert!(get.head.contains("X-Custom: value") || get.head.contains("x-custom: value"));
+    assert_eq!(
+        get.head.to_ascii_lowercase().contains("git-protocol: version=2"),
+        version == Protocol::V2
+    );
+    let post = &requests[1];
+    assert!(post.head.starts_with("POST /repo.git/git-upload-pack HTTP/1.1\r\n"));
+    assert!(post
+        .head
+        .to_ascii_lowercase()
+        .contains("content-type: application/x-git-upload-pack-request"));
+    assert_eq!(
+        post.body.as_bstr(),
+        b"0032want 529d504d0deadca6d9079225c631eea5757a47f4\n00000009done\n".as_bstr()
+    );
+    Ok(())
+}
+
+fn failures<H: http::Http + 'static>(new_http: impl Fn() -> H) {
+    let (url, server) = serve(vec![Response::new("404 Not Found", "text/plain", &b"not found"[..])]);
+    assert!(matches!(
+        handshake(new_http(), &url),
+        Err(client::Error::Http(http::Error::Status(404, _)))
+    ));
+    server.join().expect("no panic").expect("served");
+
+    let (url, server) = serve(vec![Response::new(
+        "200 OK",
+        "text/plain",
+        &b"529d504d0deadca6d9079225c631eea5757a47f4 HEAD\n"[..],
+    )]);
+    assert!(
+        matches!(
+            handshake(new_http(), &url),
+            Err(client::Error::Http(http::Error::UnexpectedContentType(_, Some(actual)))) if actual == "text/plain"
+        ),
+        "dumb servers are not supported"
+    );
+    server.join().expect("no panic").expect("served");
+}
+
+fn unauthorized() -> Response {
+    Response::new("401 Unauthorized", "text/plain", &b"authentication required"[..])
+}
+
+fn authentication<H: http::Http + 'static>(new_http: impl Fn() -> H) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("log");
+    let credentials = git_credentials::Cascade {
+        programs: vec![git_credentials::helper::Program::ExternalShellScript(format!(
+            "f() {{ cat > /dev/null; echo \"$1\" >> '{}'; test \"$1\" = get && printf 'username=alice\\npassword=secret\\n'; true; }}; f",
+            log.display()
+        ))],
+        ..Default::default()
+    };
+
+    let (url, server) = serve(vec![unauthorized(), advertisement(Protocol::V1)]);
+    let mut c = http::connect_with(new_http(), &url, Protocol::V1, Default::default());
+    c.set_credentials(credentials.clone());
+    c.set_http_options(http::Options {
+        extra_headers: vec!["X-Later: value".into()],
+        ..Default::default()
+    });
+    c.handshake(Service::UploadPack)?;
+    let requests = server.join().expect("no panic")?;
+    assert!(!requests[0].head.to_ascii_lowercase().contains("authorization:"));
+    assert!(
+        requests[0].head.to_ascii_lowercase().contains("x-later: value"),
+        "options set later apply while the credentials are kept"
+    );
+    assert!(
+        requests[1].head.contains("Basic YWxpY2U6c2VjcmV0"),
+        "the filled credentials are sent: {}",
+        requests[1].head
+    );
+    assert_eq!(std::fs::read_to_string(&log)?, "get\nstore\n");
+
+    std::fs::remove_file(&log)?;
+    let (url, server) = serve(vec![unauthorized(), unauthorized()]);
+    let mut c = http::connect_with(new_http(), &url, Protocol::V1, Default::default());
+    c.set_credentials(credentials);
+    assert!(matches!(
+        c.handshake(Service::UploadPack),
+        Err(client::Error::Http(http::Error::Status(401, _)))
+    ));
+    server.join().expect("no panic")?;
+    assert_eq!(
+        std::fs::read_to_string(&log)?,
+        "get\nerase\n",
+        "rejected credentials are erased"
+    );
+
+    let (url, server) = serve(vec![unauthorized()]);
+    assert!(
+        matches!(
+            handshake(new_http(), &url),
+            Err(client::Error::Http(http::Error::Status(401, _)))
+        ),
+        "without credentials there is only one attempt"
+    );
+    server.join().expect("no panic")?;
+    Ok(())
+}
+
+fn redirect(location: &str) -> Response {
+    Response {
+        headers: vec![format!("Location: {}", location)],
+        ..Response::new("302 Found", "text/plain", &b"moved"[..])
+    }
+}
+
+fn redirects<H: http::Http + 'static>(new_http: impl Fn() -> H) -> Result<(), Box<dyn std::error::Error>> {
+    let (url, server) = serve(vec![
+        redirect("/moved.git/info/refs?service=git-upload-pack"),
+        advertisement(Protocol::V1),
+        nak(),
+    ]);
+    let mut c = http::connect_with(new_http(), &url, Protocol::V1, Default::default());
+    c.handshake(Service::UploadPack)?;
+    assert_eq!(
+        c.to_url(),
+        url.replace("/repo.git", "/moved.git"),
+        "the base url follows the redirect"
+    );
+    let mut writer = c.request(WriteMode::OneLfTerminatedLinePerWriteCall, MessageKind::Text(b"done"))?;
+    writer.write_all(b"want 529d504d0deadca6d9079225c631eea5757a47f4")?;
+    writer.into_read()?.as_read().read_to_end(&mut Vec::new())?;
+    let requests = server.join().expect("no panic")?;
+    assert!(requests[1]
+        .head
+        .starts_with("GET /moved.git/info/refs?service=git-upload-pack HTTP/1.1\r\n"));
+    assert!(requests[2]
+        .head
+        .starts_with("POST /moved.git/git-upload-pack HTTP/1.1\r\n"));
+
+    let (url, server) = serve(vec![redirect("/elsewhere"), advertisement(Protocol::V1)]);
+    assert!(
+        matches!(
+            handshake(new_http(), &url),
+            Err(client::Error::Http(http::Error::UnsupportedRedirect(target))) if target.ends_with("/elsewhere")
+        ),
+        "the base url can't be determined if the path isn't kept"
+    );
+    server.join().expect("no panic")?;
+
+    let (url, server) = serve(vec![redirect("/moved.git/info/refs?service=git-upload-pack")]);
+    let mut c = http::connect_with(
+        new_http(),
+        &url,
+        Protocol::V1,
+        http::Options {
+            follow_redirects: false,
+            ..Default::default()
+        },
+    );
+    assert!(matches!(
+        c.handshake(Service::UploadPack),
+        Err(client::Error::Http(http::Error::Redirect(302, _)))
+    ));
+    server.join().expect("no panic")?;
+    Ok(())
+}
+
+fn redirects_to_other_servers<H: http::Http + 'static>(
+    new_http: impl Fn() -> H,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("log");
+    let credentials = git_credentials::Cascade {
+        programs: vec![git_credentials::helper::Program::ExternalShellScript(format!(
+            "f() {{ grep host= >> '{}'; test \"$1\" = get && printf 'username=alice\\npassword=secret\\n'; true; }}; f",
+            log.display()
+        ))],
+        ..Default::default()
+    };
+    let (other_url, other_server) = serve(vec![unauthorized(), advertisement(Protocol::V1)]);
+    let other_url = other_url.replace("127.0.0.1", "localhost");
+    let (url, server) = serve(vec![redirect(&format!(
+        "{}/info/refs?service=git-upload-pack",
+        other_url
+    ))]);
+    let mut c = http::connect_with(new_http(), &url, Protocol::V1, Default::default());
+    c.set_credentials(credentials);
+    c.handshake(Service::UploadPack)?;
+    assert_eq!(c.to_url(), other_url);
+    server.join().expect("no panic")?;
+    let requests = other_server.join().expect("no panic")?;
+    assert!(
+        requests[1].head.contains("Basic YWxpY2U6c2VjcmV0"),
+        "the request is repeated where authentication is required"
+    );
+    let host = other_url.trim_start_matches("http://").trim_end_matches("/repo.git");
+    assert_eq!(
+        std::fs::read_to_string(&log)?,
+        format!("host={}\nhost={}\n", host, host),
+        "the credentials of the server asking for them are used"
+    );
+
+    let (other_url, other_server) = serve(vec![advertisement(Protocol::V1)]);
+    let (url, server) = serve(vec![redirect(&format!(
+        "{}/info/refs?service=git-upload-pack",
+        other_url.replace("127.0.0.1", "localhost")
+    ))]);
+    let mut c = http::connect_with(
+        new_http(),
+        &url,
+        Protocol::V1,
+        http::Options {
+            identity: Some(git_credentials::Identity {
+                username: "alice".into(),
+                password: "secret".into(),
+            }),
+            ..Default::default()
+        },
+    );
+    c.handshake(Service::UploadPack)?;
+    let requests = server.join().expect("no panic")?;
+    assert!(requests[0].head.contains("Basic YWxpY2U6c2VjcmV0"));
+    let requests = other_server.join().expect("no panic")?;
+    assert!(
+        !requests[0].head.to_ascii_lowercase().contains("authorization:"),
+        "the identity isn't sent to other servers"
+    );
+    Ok(())
+}
+
+/// A backend redirecting all GET requests to a location, reported when reading the headers like the real ones do.
+struct RedirectingTo(&'static str);
+
+impl http::Http for RedirectingTo {
+    type Headers = RedirectingTo;
+    type ResponseBody = std::io::Cursor<Vec<u8>>;
+    type PostBody = std::io::Sink;
+
+    fn get(
+        &mut self,
+        _url: &str,
+        _headers: Vec<String>,
+        _options: &http::Options,
+    ) -> Result<http::GetResponse<Self::Headers, Self::ResponseBody>, http::Error> {
+        Ok(http::GetResponse {
+            headers: RedirectingTo(self.0),
+            body: Default::default(),
+        })
+    }
+
+    fn post(
+        &mut self,
+        url: &str,
+        _headers: Vec<String>,
+        _options: &http::Options,
+    ) -> Result<http::PostResponse<Self::Headers, Self::ResponseBody, Self::PostBody>, http::Error> {
+        Err(http::Error::Detail(format!("unexpected POST to '{}'", url)))
+    }
+}
+
+impl Read for RedirectingTo {
+    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        Err(http::Error::Redirect(302, self.0.into()).into())
+    }
+}
+
+impl BufRead for RedirectingTo {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        Err(http::Error::Redirect(302, self.0.into()).into())
+    }
+
+    fn consume(&mut self, _amt: usize) {}
+}
+
+#[test]
+fn redirects_from_https_to_http_are_refused() {
+    let res = http::download(
+        &mut RedirectingTo("http://example.com/repo.git"),
+        "https://example.com/repo.git",
+        Vec::new(),
+        &Default::default(),
+        &mut Vec::new(),
+    );
+    assert!(matches!(res, Err(http::Error::UnsupportedRedirect(url)) if url == "http://example.com/repo.git"));
+}
+
+fn retries_and_resumption<H: http::Http + 'static>(new_http: impl Fn() -> H) -> Result<(), Box<dyn std::error::Error>> {
+    let retrying = || http::Options {
+        max_retries: 1,
+        retry_delay: std::time::Duration::from_millis(1),
+        ..Default::default()
+    };
+    let unavailable = || Response::new("503 Service Unavailable", "text/plain", &b"try again"[..]);
+    let (url, server) = serve(vec![unavailable(), advertisement(Protocol::V1)]);
+    http::connect_with(new_http(), &url, Protocol::V1, retrying()).handshake(Service::UploadPack)?;
+    server.join().expect("no panic")?;
+
+    let (url, server) = serve(vec![unavailable()]);
+    assert!(
+        matches!(
+            handshake(new_http(), &url),
+            Err(client::Error::Http(http::Error::Status(503, _)))
+        ),
+        "there are no retries by default"
+    );
+    server.join().expect("no panic")?;
+
+    let full = advertisement(Protocol::V1).body;
+    let interrupted = || Response {
+        cut_after: Some(20),
+        ..advertisement(Protocol::V1)
+    };
+    let rest = Response {
+        headers: vec![format!("Content-Range: bytes 20-{}/{}", full.len() - 1, full.len())],
+        ..Response::new(
+            "206 Partial Content",
+            "application/x-git-upload-pack-advertisement",
+            &full[20..],
+        )
+    };
+    for second in [rest, advertisement(Protocol::V1)] {
+        let (url, server) = serve(vec![interrupted(), second]);
+        let mut c = http::connect_with(new_http(), &url, Protocol::V1, retrying());
+        let first_line = c
+            .handshake(Service::UploadPack)?
+            .as_read()
+            .lines()
+            .next()
+            .expect("a line")?;
+        assert!(first_line.starts_with("529d504d0deadca6d9079225c631eea5757a47f4 HEAD\0"));
+        let requests = server.join().expect("no panic")?;
+        assert!(
+            requests[1].head.to_ascii_lowercase().contains("range: bytes=20-"),
+            "only the missing part is asked for"
+        );
+    }
+    Ok(())
+}
+
+fn handshake<H: http::Http + 'static>(http: H, url: &str) -> Result<(), client::Error> {
+    let mut c = http::connect_with(http, url, Protocol::V1, Default::default());
+    c.handshake(Service::UploadPack).map(|_| ())
+}
+
+#[cfg(feature = "http-client-curl")]
+mod curl {
+    use git_transport::{client::http, Protocol};
+
+    #[test]
+    fn handshake_and_request_v1() -> Result<(), Box<dyn std::error::Error>> {
+        super::handshake_and_request(http::curl::Curl, Protocol::V1)
+    }
+
+    #[test]
+    fn handshake_and_request_v2() -> Result<(), Box<dyn std::error::Error>> {
+        super::handshake_and_request(http::curl::Curl, Protocol::V2)
+    }
+
+    #[test]
+    fn failures() {
+        super::failures(|| http::curl::Curl)
+    }
+
+    #[test]
+    fn authentication() -> Result<(), Box<dyn std::error::Error>> {
+        super::authentication(|| http::curl::Curl)
+    }
+
+    #[test]
+    fn redirects() -> Result<(), Box<dyn std::error::Error>> {
+        super::redirects(|| http::curl::Curl)
+    }
+
+    #[test]
+    fn redirects_to_other_servers() -> Result<(), Box<dyn std::error::Error>> {
+        super::redirects_to_other_servers(|| http::curl::Curl)
+    }
+
+    #[test]
+    fn retries_and_resumption() -> Result<(), Box<dyn std::error::Error>> {
+        super::retries_and_resumption(|| http::curl::Curl)
+    }
+}
+
+#[cfg(feature = "http-client-reqwest")]
+mod reqwest {
+    use git_transport::{client::http, Protocol};
+
+    #[test]
+    fn handshake_and_request_v1() -> Result<(), Box<dyn std::error::Error>> {
+        super::handshake_and_request(http::reqwest::Remote::default(), Protocol::V1)
+    }
+
+    #[test]
+    fn handshake_and_request_v2() -> Result<(), Box<dyn std::error::Error>> {
+        super::handshake_and_request(http::reqwest::Remote::default(), Protocol::V2)
+    }
+
+    #[test]
+    fn failures() {
+        super::failures(http::reqwest::Remote::default)
+    }
+
+    #[test]
+    fn authentication() -> Result<(), Box<dyn std::error::Error>> {
+        super::authentication(http::reqwest::Remote::default)
+    }
+
+    #[test]
+    fn redirects() -> Result<(), Box<dyn std::error::Error>> {
+        super::redirects(http::reqwest::Remote::default)
+    }
+
+    #[test]
+    fn redirects_to_other_servers() -> Result<(), Box<dyn std::error::Error>> {
+        super::redirects_to_other_servers(http::reqwest::Remote::default)
+    }
+
+    #[test]
+    fn retries_and_resumption() -> Result<(), Box<dyn std::error::Error>> {
+        super::retries_and_resumption(http::reqwest::Remote::default)
+    }
+}
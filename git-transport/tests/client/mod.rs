@@ -0,0 +1,16 @@
+#[cfg(feature = "async-client")]
+mod async_io;
+mod connect;
+mod file;
+mod git;
+#[cfg(any(feature = "http-client-curl", feature = "http-client-reqwest"))]
+mod http;
+mod ssh;
+
+pub fn fixture_repo_path() -> String {
+    std::env::current_dir()
+        .expect("valid working directory")
+        .join("tests/fixtures/repos/repo.git")
+        .to_string_lossy()
+        .into_owned()
+}
@@ -0,0 +1,177 @@
+use crate::client::fixture_repo_path;
+use git_transport::{
+    client::{
+        self,
+        ssh::{self, Invocation, Program, ProgramKind},
+        Transport,
+    },
+    Protocol, Service,
+};
+use std::{ffi::OsString, io::BufRead};
+
+fn invocation(kind: ProgramKind, user: Option<&str>, port: Option<u16>) -> Invocation {
+    Invocation {
+        program: Program {
+            kind,
+            ..Default::default()
+        },
+        host: "example.com".into(),
+        user: user.map(Into::into),
+        port,
+    }
+}
+
+fn args(invocation: &Invocation, version: Protocol) -> Vec<String> {
+    invocation
+        .arguments(Service::UploadPack, b"/repo's.git", version)
+        .expect("valid")
+        .into_iter()
+        .map(|a| a.into_string().expect("utf8"))
+        .collect()
+}
+
+#[test]
+fn program_from_environment() {
+    assert_eq!(Program::from_env_values(None, None, None), Program::default());
+    assert_eq!(
+        Program::from_env_values(Some("plink.exe -v".into()), Some("ssh".into()), None),
+        Program {
+            path: "plink.exe -v".into(),
+            use_shell: true,
+            kind: ProgramKind::Plink
+        }
+    );
+    assert_eq!(
+        Program::from_env_values(None, Some("/usr/bin/TortoisePlink.exe".into()), None),
+        Program {
+            path: "/usr/bin/TortoisePlink.exe".into(),
+            use_shell: false,
+            kind: ProgramKind::TortoisePlink
+        }
+    );
+    assert_eq!(
+        Program::from_env_values(None, Some(OsString::from("my-ssh")), Some("simple".into())).kind,
+        ProgramKind::Simple
+    );
+}
+
+#[test]
+fn arguments_per_program_kind() {
+    assert_eq!(
+        args(&invocation(ProgramKind::Ssh, Some("git"), Some(2222)), Protocol::V2),
+        vec![
+            "-o",
+            "SendEnv=GIT_PROTOCOL",
+            "-p",
+            "2222",
+            "git@example.com",
+            "git-upload-pack '/repo'\\''s.git'"
+        ]
+    );
+    assert_eq!(
+        args(&invocation(ProgramKind::Ssh, None, None), Protocol::V1),
+        vec!["example.com", "git-upload-pack '/repo'\\''s.git'"]
+    );
+    assert_eq!(
+        args(&invocation(ProgramKind::TortoisePlink, None, Some(22)), Protocol::V2),
+        vec!["-batch", "-P", "22", "example.com", "git-upload-pack '/repo'\\''s.git'"]
+    );
+    assert!(matches!(
+        invocation(ProgramKind::Simple, None, Some(22)).arguments(Service::UploadPack, b"/r", Protocol::V1),
+        Err(client::Error::SshPortUnsupported(_))
+    ));
+    assert!(ssh::connect_with(invocation(ProgramKind::Simple, None, Some(22)), "/r", Protocol::V1).is_err());
+}
+
+#[test]
+fn hosts_and_users_looking_like_options_are_blocked() {
+    for url in &[
+        "ssh://-oProxyCommand=touch%20pwned/x",
+        "ssh://-oProxyCommand=touch%20pwned@example.com/x",
+        "-oProxyCommand=touch pwned:x",
+        "-oProxyCommand=touch pwned@example.com:x",
+    ] {
+        assert!(
+            matches!(
+                client::connect(url.as_bytes(), Protocol::V2),
+                Err(client::Error::StrangeArgument(_, value)) if value == "-oProxyCommand=touch pwned"
+            ),
+            "{} is blocked",
+            url
+        );
+    }
+}
+
+#[test]
+fn url_for_display() -> Result<(), Box<dyn std::error::Error>> {
+    let c = ssh::connect_with(
+        invocation(ProgramKind::Ssh, Some("git"), Some(2222)),
+        "/repo.git",
+        Protocol::V2,
+    )?;
+    assert_eq!(c.to_url(), "ssh://git@example.com:2222/repo.git");
+    let c = ssh::connect_with(invocation(ProgramKind::Ssh, None, None), "repo.git", Protocol::V2)?;
+    assert_eq!(c.to_url(), "ssh://example.com/repo.git");
+    Ok(())
+}
+
+#[test]
+fn handshake_through_shell_command() -> Result<(), Box<dyn std::error::Error>> {
+    // A stand-in for `ssh` which runs the remote command locally.
+    let fake_ssh = Program {
+        path: "fake_ssh() { eval \"$2\"; }; fake_ssh".into(),
+        use_shell: true,
+        kind: ProgramKind::Simple,
+    };
+    let mut c = ssh::connect_with(
+        Invocation {
+            program: fake_ssh,
+            host: "localhost".into(),
+            user: None,
+            port: None,
+        },
+        fixture_repo_path(),
+        Protocol::V2,
+    )?;
+    let lines = c.handshake(Service::UploadPack)?;
+    let capabilities = lines.as_read().lines().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        capabilities[0], "version 2",
+        "the protocol version is passed through the environment"
+    );
+    c.close()?;
+    Ok(())
+}
+
+#[test]
+fn askpass_of_credentials_is_passed_to_ssh() -> Result<(), Box<dyn std::error::Error>> {
+    if std::env::var_os("SSH_ASKPASS").is_some() {
+        return Ok(());
+    }
+    let fake_ssh = Program {
+        path: "fake_ssh() { test \"$SSH_ASKPASS\" = my-askpass && eval \"$2\"; }; fake_ssh".into(),
+        use_shell: true,
+        kind: ProgramKind::Simple,
+    };
+    let mut c = ssh::connect_with(
+        Invocation {
+            program: fake_ssh,
+            host: "localhost".into(),
+            user: None,
+            port: None,
+        },
+        fixture_repo_path(),
+        Protocol::V2,
+    )?;
+    c.set_credentials(git_credentials::Cascade {
+        prompt: git_credentials::prompt::Options {
+            askpass: Some("my-askpass".into()),
+            terminal: false,
+        },
+        ..Default::default()
+    });
+    let lines = c.handshake(Service::UploadPack)?;
+    assert_eq!(lines.as_read().lines().next().expect("a line")?, "version 2");
+    c.close()?;
+    Ok(())
+}
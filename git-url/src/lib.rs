@@ -1,9 +1,155 @@
+//! Parse and serialize the URLs git uses to refer to remote repositories.
+//!
+//! Besides proper URLs like `ssh://user@host:22/path`, git accepts the scp-like syntax `user@host:path` as well as
+//! paths on the local file system, all of which are represented by a [`Url`][Url].
 #![forbid(unsafe_code)]
 
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
+use bstr::BString;
+use std::{fmt, io};
+
+pub mod parse;
+#[doc(inline)]
+pub use parse::parse;
+
+/// The scheme of a URL, determining the transport to use.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum Scheme {
+    File,
+    Git,
+    Ssh,
+    Http,
+    Https,
+    Ftp,
+    Ftps,
+}
+
+impl Scheme {
+    /// Return the scheme as used in URLs, like `https`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scheme::File => "file",
+            Scheme::Git => "git",
+            Scheme::Ssh => "ssh",
+            Scheme::Http => "http",
+            Scheme::Https => "https",
+            Scheme::Ftp => "ftp",
+            Scheme::Ftps => "ftps",
+        }
+    }
+}
+
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A URL with all of its components percent-decoded.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Url {
+    /// The scheme, which is `File` for local paths and `Ssh` for the scp-like syntax.
+    pub scheme: Scheme,
+    /// The user to log in as.
+    pub user: Option<String>,
+    /// The password of the user, only used with `http(s)` and `ftp(s)`.
+    pub password: Option<String>,
+    /// The host to connect to, without brackets for IPv6 addresses. It's `None` for `File` URLs.
+    pub host: Option<String>,
+    /// The port to connect to, if it differs from the default of the scheme.
+    pub port: Option<u16>,
+    /// The path to the repository, which may start with `~` to refer to a home directory with `ssh` and `git` URLs.
+    pub path: BString,
+}
+
+impl Url {
+    /// Write the canonical form of this URL to `out`, which can be parsed back into an equal `Url`.
+    ///
+    /// `ssh` URLs with a path relative to the home directory of the user are written in the scp-like form
+    /// `user@host:path`, unless they have a port.
+    pub fn write_to(&self, mut out: impl io::Write) -> io::Result<()> {
+        let is_relative = !self.path.starts_with(b"/") && !self.path.starts_with(b"~");
+        if self.scheme == Scheme::Ssh && is_relative && self.port.is_none() {
+            if let Some(user) = &self.user {
+                write!(out, "{}@", user)?;
+            }
+            if let Some(host) = &self.host {
+                self.write_host(host, &mut out)?;
+            }
+            out.write_all(b":")?;
+            return out.write_all(&self.path);
+        }
+
+        out.write_all(self.scheme.as_str().as_bytes())?;
+        out.write_all(b"://")?;
+        if self.scheme == Scheme::File {
+            return percent_encode(&self.path, is_allowed_in_path, out);
+        }
+        if let Some(user) = &self.user {
+            percent_encode(user.as_bytes(), is_allowed_in_user_info, &mut out)?;
+            if let Some(password) = &self.password {
+                out.write_all(b":")?;
+                percent_encode(password.as_bytes(), is_allowed_in_user_info, &mut out)?;
+            }
+            out.write_all(b"@")?;
+        }
+        if let Some(host) = &self.host {
+            self.write_host(host, &mut out)?;
+        }
+        if let Some(port) = self.port {
+            write!(out, ":{}", port)?;
+        }
+        if !self.path.starts_with(b"/") {
+            out.write_all(b"/")?;
+            if is_relative {
+                out.write_all(b"~/")?;
+            }
+        }
+        percent_encode(&self.path, is_allowed_in_path, out)
+    }
+
+    fn write_host(&self, host: &str, mut out: impl io::Write) -> io::Result<()> {
+        if host.contains(':') {
+            write!(out, "[{}]", host)
+        } else {
+            out.write_all(host.as_bytes())
+        }
+    }
+
+    /// Return the canonical form of this URL.
+    pub fn to_bstring(&self) -> BString {
+        let mut buf = Vec::with_capacity(self.path.len() + 16);
+        self.write_to(&mut buf).expect("io cannot fail in memory");
+        buf.into()
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.to_bstring()))
+    }
+}
+
+fn is_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"-._~".contains(&b)
+}
+
+fn is_allowed_in_user_info(b: u8) -> bool {
+    is_unreserved(b) || b"!$&'()*+,;=".contains(&b)
+}
+
+fn is_allowed_in_path(b: u8) -> bool {
+    is_allowed_in_user_info(b) || b"/:@".contains(&b)
+}
+
+fn percent_encode(input: &[u8], is_allowed: fn(u8) -> bool, mut out: impl io::Write) -> io::Result<()> {
+    for &b in input {
+        if is_allowed(b) {
+            out.write_all(&[b])?;
+        } else {
+            write!(out, "%{:02X}", b)?;
+        }
     }
+    Ok(())
 }
@@ -0,0 +1,250 @@
+//! Parse all URL syntaxes git understands into a [`Url`][crate::Url].
+use crate::{Scheme, Url};
+use bstr::{BStr, BString, ByteSlice};
+use quick_error::quick_error;
+use std::borrow::Cow;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        Utf8(err: bstr::Utf8Error) {
+            display("User names, passwords and hosts must be valid UTF-8")
+            from()
+            source(err)
+        }
+        UnsupportedScheme(scheme: BString) {
+            display("The scheme '{}' is not supported", scheme)
+        }
+        MissingHost(url: BString) {
+            display("The URL '{}' does not name a host", url)
+        }
+        EmptyPath(url: BString) {
+            display("The URL '{}' does not contain a path to a repository", url)
+        }
+        InvalidPort(url: BString) {
+            display("The port of URL '{}' is not a number between 0 and 65535", url)
+        }
+        MissingClosingBracket(url: BString) {
+            display("The IPv6 address in URL '{}' lacks the closing bracket", url)
+        }
+    }
+}
+
+/// Parse `input` as URL, scp-like `[user@]host:path` or local path, in this order.
+///
+/// Components of URLs are percent-decoded, whereas the scp-like syntax and paths are taken as is.
+pub fn parse(input: &[u8]) -> Result<Url, Error> {
+    match input.find(b"://") {
+        Some(pos) => parse_url(input, &input[..pos], &input[pos + 3..]),
+        None => match scp_like_host_end(input) {
+            Some(colon) => parse_scp_like(input, &input[..colon], &input[colon + 1..]),
+            None => parse_local(input),
+        },
+    }
+}
+
+fn parse_scheme(input: &[u8], scheme: &[u8]) -> Result<Scheme, Error> {
+    Ok(match scheme {
+        b"file" => Scheme::File,
+        b"git" => Scheme::Git,
+        b"ssh" | b"git+ssh" | b"ssh+git" => Scheme::Ssh,
+        b"http" => Scheme::Http,
+        b"https" => Scheme::Https,
+        b"ftp" => Scheme::Ftp,
+        b"ftps" => Scheme::Ftps,
+        _ if scheme.is_empty() => return Err(Error::UnsupportedScheme(input.into())),
+        _ => return Err(Error::UnsupportedScheme(scheme.into())),
+    })
+}
+
+fn parse_url(input: &[u8], scheme: &[u8], rest: &[u8]) -> Result<Url, Error> {
+    let scheme = parse_scheme(input, scheme)?;
+    if scheme == Scheme::File {
+        if rest.is_empty() {
+            return Err(Error::EmptyPath(input.into()));
+        }
+        return Ok(Url {
+            scheme,
+            user: None,
+            password: None,
+            host: None,
+            port: None,
+            path: percent_decode(rest).into_owned(),
+        });
+    }
+
+    let (authority, path) = match rest.find_byte(b'/') {
+        Some(pos) => rest.split_at(pos),
+        None => (rest, &b""[..]),
+    };
+    let (user_info, host_and_port) = match authority.rfind_byte(b'@') {
+        Some(pos) => (Some(&authority[..pos]), &authority[pos + 1..]),
+        None => (None, authority),
+    };
+    let (user, password) = match user_info {
+        Some(user_info) => {
+            let (user, password) = match user_info.find_byte(b':') {
+                Some(pos) => (&user_info[..pos], Some(&user_info[pos + 1..])),
+                None => (user_info, None),
+            };
+            (
+                Some(decode_to_string(user)?),
+                password.map(decode_to_string).transpose()?,
+            )
+        }
+        None => (None, None),
+    };
+    let (host, port) = split_host_and_port(input, host_and_port)?;
+    if host.is_empty() {
+        return Err(Error::MissingHost(input.into()));
+    }
+
+    let mut path = percent_decode(path).into_owned();
+    if matches!(scheme, Scheme::Ssh | Scheme::Git) && path.starts_with(b"/~") {
+        path.remove(0);
+    }
+    if path.is_empty() {
+        match scheme {
+            Scheme::Http | Scheme::Https | Scheme::Ftp | Scheme::Ftps => path.push(b'/'),
+            _ => return Err(Error::EmptyPath(input.into())),
+        }
+    }
+    Ok(Url {
+        scheme,
+        user,
+        password,
+        host: Some(host),
+        port,
+        path,
+    })
+}
+
+fn split_host_and_port(input: &[u8], host_and_port: &[u8]) -> Result<(String, Option<u16>), Error> {
+    let (host, port) = if host_and_port.starts_with(b"[") {
+        let end = host_and_port
+            .find_byte(b']')
+            .ok_or_else(|| Error::MissingClosingBracket(input.into()))?;
+        let port = &host_and_port[end + 1..];
+        let port = match port.strip_prefix(b":") {
+            Some(port) => Some(port),
+            None if port.is_empty() => None,
+            None => return Err(Error::InvalidPort(input.into())),
+        };
+        (&host_and_port[1..end], port)
+    } else {
+        match host_and_port.find_byte(b':') {
+            Some(pos) => (&host_and_port[..pos], Some(&host_and_port[pos + 1..])),
+            None => (host_and_port, None),
+        }
+    };
+    let port = match port.filter(|port| !port.is_empty()) {
+        Some(port) => Some(
+            port.to_str()
+                .ok()
+                .and_then(|port| port.parse().ok())
+                .ok_or_else(|| Error::InvalidPort(input.into()))?,
+        ),
+        None => None,
+    };
+    Ok((decode_to_string(host)?, port))
+}
+
+/// Return the position of the colon separating host and path if `input` uses the scp-like syntax.
+///
+/// That's the case if there is a colon before the first slash, and the part before it isn't a drive letter on Windows.
+fn scp_like_host_end(input: &[u8]) -> Option<usize> {
+    let search_start = if input.starts_with(b"[") || input.find(b"@[").is_some() {
+        input.find_byte(b']')?
+    } else {
+        0
+    };
+    let colon = search_start + input[search_start..].find_byte(b':')?;
+    if input[..colon].contains(&b'/') {
+        return None;
+    }
+    if cfg!(windows) && colon == 1 && input[0].is_ascii_alphabetic() {
+        return None;
+    }
+    Some(colon)
+}
+
+fn parse_scp_like(input: &[u8], user_and_host: &[u8], path: &[u8]) -> Result<Url, Error> {
+    if path.is_empty() {
+        return Err(Error::EmptyPath(input.into()));
+    }
+    let (user, host) = match user_and_host.rfind_byte(b'@') {
+        Some(pos) => (
+            Some(user_and_host[..pos].to_str()?.to_owned()),
+            &user_and_host[pos + 1..],
+        ),
+        None => (None, user_and_host),
+    };
+    let host = host
+        .strip_prefix(b"[")
+        .and_then(|h| h.strip_suffix(b"]"))
+        .unwrap_or(host);
+    if host.is_empty() {
+        return Err(Error::MissingHost(input.into()));
+    }
+    Ok(Url {
+        scheme: Scheme::Ssh,
+        user,
+        password: None,
+        host: Some(host.to_str()?.to_owned()),
+        port: None,
+        path: path.into(),
+    })
+}
+
+fn parse_local(input: &[u8]) -> Result<Url, Error> {
+    if input.is_empty() {
+        return Err(Error::EmptyPath(input.into()));
+    }
+    Ok(Url {
+        scheme: Scheme::File,
+        user: None,
+        password: None,
+        host: None,
+        port: None,
+        path: input.into(),
+    })
+}
+
+fn decode_to_string(input: &[u8]) -> Result<String, Error> {
+    Ok(percent_decode(input).to_str()?.to_owned())
+}
+
+/// Decode all `%XX` sequences in `input`, leaving malformed ones as they are.
+fn percent_decode(input: &[u8]) -> Cow<'_, BStr> {
+    if !input.contains(&b'%') {
+        return Cow::Borrowed(input.as_bstr());
+    }
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        let decoded = match input.get(i..i + 3) {
+            Some([b'%', hi, lo]) => hex_value(*hi).and_then(|hi| hex_value(*lo).map(|lo| hi << 4 | lo)),
+            _ => None,
+        };
+        match decoded {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(input[i]);
+                i += 1;
+            }
+        }
+    }
+    Cow::Owned(out.into())
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
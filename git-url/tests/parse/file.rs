@@ -0,0 +1,39 @@
+use crate::parse::{assert_url_and_canonical, assert_url_roundtrip, url};
+use git_url::Scheme;
+
+#[test]
+fn file_urls() -> Result<(), git_url::parse::Error> {
+    assert_url_roundtrip(
+        "file:///path/to/repo.git",
+        url(Scheme::File, None, None, None, b"/path/to/repo.git"),
+    )?;
+    assert_url_and_canonical(
+        "file:///path/with%20space",
+        url(Scheme::File, None, None, None, b"/path/with space"),
+        "file:///path/with%20space",
+    )
+}
+
+#[test]
+fn local_paths() -> Result<(), git_url::parse::Error> {
+    assert_url_and_canonical(
+        "/absolute/repo.git",
+        url(Scheme::File, None, None, None, b"/absolute/repo.git"),
+        "file:///absolute/repo.git",
+    )?;
+    assert_url_and_canonical(
+        "relative/repo",
+        url(Scheme::File, None, None, None, b"relative/repo"),
+        "file://relative/repo",
+    )?;
+    assert_url_and_canonical(
+        "./with:colon",
+        url(Scheme::File, None, None, None, b"./with:colon"),
+        "file://./with:colon",
+    )?;
+    assert_url_and_canonical(
+        "~/repo%20without%20decoding",
+        url(Scheme::File, None, None, None, b"~/repo%20without%20decoding"),
+        "file://~/repo%2520without%2520decoding",
+    )
+}
@@ -0,0 +1,27 @@
+use crate::parse::{assert_url_and_canonical, assert_url_roundtrip, url};
+use git_url::Scheme;
+
+#[test]
+fn git_urls() -> Result<(), git_url::parse::Error> {
+    assert_url_roundtrip(
+        "git://example.com/repo.git",
+        url(Scheme::Git, None, "example.com", None, b"/repo.git"),
+    )?;
+    assert_url_roundtrip(
+        "git://example.com:9419/repo.git",
+        url(Scheme::Git, None, "example.com", 9419, b"/repo.git"),
+    )?;
+    assert_url_roundtrip(
+        "git://example.com/~byron/repo",
+        url(Scheme::Git, None, "example.com", None, b"~byron/repo"),
+    )
+}
+
+#[test]
+fn empty_port_is_ignored() -> Result<(), git_url::parse::Error> {
+    assert_url_and_canonical(
+        "git://example.com:/repo",
+        url(Scheme::Git, None, "example.com", None, b"/repo"),
+        "git://example.com/repo",
+    )
+}
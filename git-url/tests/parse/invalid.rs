@@ -0,0 +1,41 @@
+use git_url::parse::Error;
+
+#[test]
+fn unsupported_scheme() {
+    assert!(matches!(git_url::parse(b"foo://host/repo"), Err(Error::UnsupportedScheme(s)) if s == "foo"));
+    assert!(matches!(
+        git_url::parse(b"://host/repo"),
+        Err(Error::UnsupportedScheme(_))
+    ));
+}
+
+#[test]
+fn missing_parts() {
+    assert!(matches!(git_url::parse(b""), Err(Error::EmptyPath(_))));
+    assert!(matches!(git_url::parse(b"file://"), Err(Error::EmptyPath(_))));
+    assert!(matches!(git_url::parse(b"ssh://host"), Err(Error::EmptyPath(_))));
+    assert!(matches!(git_url::parse(b"host:"), Err(Error::EmptyPath(_))));
+    assert!(matches!(git_url::parse(b"ssh:///repo"), Err(Error::MissingHost(_))));
+    assert!(matches!(git_url::parse(b"user@:repo"), Err(Error::MissingHost(_))));
+}
+
+#[test]
+fn invalid_ports_and_hosts() {
+    assert!(matches!(
+        git_url::parse(b"ssh://host:port/repo"),
+        Err(Error::InvalidPort(_))
+    ));
+    assert!(matches!(
+        git_url::parse(b"ssh://host:65536/repo"),
+        Err(Error::InvalidPort(_))
+    ));
+    assert!(matches!(
+        git_url::parse(b"ssh://[::1]x/repo"),
+        Err(Error::InvalidPort(_))
+    ));
+    assert!(matches!(
+        git_url::parse(b"ssh://[::1/repo"),
+        Err(Error::MissingClosingBracket(_))
+    ));
+    assert!(matches!(git_url::parse(b"ssh://h%FFst/repo"), Err(Error::Utf8(_))));
+}
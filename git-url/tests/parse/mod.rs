@@ -0,0 +1,43 @@
+use bstr::ByteSlice;
+use git_url::{Scheme, Url};
+
+fn url(
+    scheme: Scheme,
+    user: impl Into<Option<&'static str>>,
+    host: impl Into<Option<&'static str>>,
+    port: impl Into<Option<u16>>,
+    path: &'static [u8],
+) -> Url {
+    Url {
+        scheme,
+        user: user.into().map(Into::into),
+        password: None,
+        host: host.into().map(Into::into),
+        port: port.into(),
+        path: path.into(),
+    }
+}
+
+/// Parse `input` into `expected`, and assert its canonical form is `canonical` and parses into the same URL.
+fn assert_url_and_canonical(input: &str, expected: Url, canonical: &str) -> Result<(), git_url::parse::Error> {
+    let actual = git_url::parse(input.as_bytes())?;
+    assert_eq!(actual, expected, "parsing {}", input);
+    assert_eq!(actual.to_bstring(), canonical.as_bytes().as_bstr());
+    assert_eq!(
+        git_url::parse(&actual.to_bstring())?,
+        expected,
+        "round trip of {}",
+        canonical
+    );
+    Ok(())
+}
+
+fn assert_url_roundtrip(input: &str, expected: Url) -> Result<(), git_url::parse::Error> {
+    assert_url_and_canonical(input, expected, input)
+}
+
+mod file;
+mod git;
+mod http;
+mod invalid;
+mod ssh;
@@ -0,0 +1,70 @@
+use crate::parse::{assert_url_and_canonical, assert_url_roundtrip, url};
+use git_url::Scheme;
+
+#[test]
+fn ssh_urls() -> Result<(), git_url::parse::Error> {
+    assert_url_roundtrip(
+        "ssh://git@github.com/Byron/gitoxide.git",
+        url(Scheme::Ssh, "git", "github.com", None, b"/Byron/gitoxide.git"),
+    )?;
+    assert_url_roundtrip(
+        "ssh://user@host.xz:2222/path/repo.git",
+        url(Scheme::Ssh, "user", "host.xz", 2222, b"/path/repo.git"),
+    )?;
+    assert_url_roundtrip(
+        "ssh://host.xz/~/repo.git",
+        url(Scheme::Ssh, None, "host.xz", None, b"~/repo.git"),
+    )?;
+    assert_url_roundtrip(
+        "ssh://[::1]:22/repo.git",
+        url(Scheme::Ssh, None, "::1", 22, b"/repo.git"),
+    )
+}
+
+#[test]
+fn alternative_scheme_names() -> Result<(), git_url::parse::Error> {
+    for scheme in &["git+ssh", "ssh+git"] {
+        assert_url_and_canonical(
+            &format!("{}://host/repo", scheme),
+            url(Scheme::Ssh, None, "host", None, b"/repo"),
+            "ssh://host/repo",
+        )?;
+    }
+    Ok(())
+}
+
+#[test]
+fn scp_like() -> Result<(), git_url::parse::Error> {
+    assert_url_roundtrip(
+        "git@github.com:Byron/gitoxide.git",
+        url(Scheme::Ssh, "git", "github.com", None, b"Byron/gitoxide.git"),
+    )?;
+    assert_url_and_canonical(
+        "host.xz:/absolute/repo",
+        url(Scheme::Ssh, None, "host.xz", None, b"/absolute/repo"),
+        "ssh://host.xz/absolute/repo",
+    )?;
+    assert_url_and_canonical(
+        "host.xz:~user/repo%20as-is",
+        url(Scheme::Ssh, None, "host.xz", None, b"~user/repo%20as-is"),
+        "ssh://host.xz/~user/repo%2520as-is",
+    )
+}
+
+#[test]
+fn relative_paths_with_port_are_relative_to_home() {
+    let mut url = url(Scheme::Ssh, None, "host", 22, b"repo");
+    assert_eq!(url.to_bstring(), "ssh://host:22/~/repo");
+    url.port = None;
+    assert_eq!(url.to_string(), "host:repo");
+}
+
+#[test]
+fn scp_like_with_ipv6_host() -> Result<(), git_url::parse::Error> {
+    assert_url_roundtrip("user@[::1]:repo", url(Scheme::Ssh, "user", "::1", None, b"repo"))?;
+    assert_url_and_canonical(
+        "[fe80::1]:/repo",
+        url(Scheme::Ssh, None, "fe80::1", None, b"/repo"),
+        "ssh://[fe80::1]/repo",
+    )
+}
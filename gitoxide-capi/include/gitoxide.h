@@ -0,0 +1,63 @@
+/*
+ * A C interface to open object stores, look up objects in them and verify packs with gitoxide.
+ *
+ * All functions report failures through their return value, along with a message returned by gix_last_error(),
+ * which is kept per thread. Stores must only be used by one thread at a time, so each thread should open its own.
+ *
+ * The declarations mirror the exported functions in src/lib.rs, which the tests of this crate check.
+ */
+#ifndef GITOXIDE_H
+#define GITOXIDE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Return values */
+#define GIX_OK 0                   /* The operation succeeded. */
+#define GIX_ERR_NOT_FOUND 1        /* The object doesn't exist. */
+#define GIX_ERR_BUFFER_TOO_SMALL 2 /* The buffer is too small for the object, whose size was stored regardless. */
+#define GIX_ERR_INVALID_ARGUMENT 3 /* A pointer was null or a path wasn't valid. */
+#define GIX_ERR_FAILED 4           /* The operation failed as described by gix_last_error(). */
+
+/* Object kinds, using the type numbers of git */
+#define GIX_OBJECT_COMMIT 1
+#define GIX_OBJECT_TREE 2
+#define GIX_OBJECT_BLOB 3
+#define GIX_OBJECT_TAG 4
+
+/* An object store, opened with gix_store_open() or gix_repository_open() and freed with gix_store_free(). */
+typedef struct gix_store gix_store;
+
+/* Return the message describing the last failure on this thread, or NULL if there was none. It remains valid until
+ * the next call failing on this thread. */
+const char *gix_last_error(void);
+
+/* Open the object store at objects_dir, like ".git/objects", or return NULL on failure. */
+gix_store *gix_store_open(const char *objects_dir);
+
+/* Open the object store of the repository path is in, honoring GIT_DIR and the other variables git reads to find
+ * repositories, or return NULL on failure. */
+gix_store *gix_repository_open(const char *path);
+
+/* Free store, which may be NULL. */
+void gix_store_free(gix_store *store);
+
+/* Copy the data of the object with the 20 byte id into buf of buf_len bytes, and store its size in out_size and its
+ * kind in out_kind. If buf is too small, GIX_ERR_BUFFER_TOO_SMALL is returned after storing the size, so the call can
+ * be repeated with a larger buffer. buf may be NULL if buf_len is 0, and out_kind may be NULL. */
+int gix_store_locate(gix_store *store, const uint8_t *id, uint8_t *buf, size_t buf_len, size_t *out_size,
+                     int *out_kind);
+
+/* Verify the checksum of the pack at path if it ends in ".pack", or if it ends in ".idx", verify the index and all
+ * objects in the pack next to it. */
+int gix_pack_verify(const char *path);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* GITOXIDE_H */
@@ -0,0 +1,242 @@
+//! A C interface to open object stores, look up objects in them and verify packs, declared in `include/gitoxide.h`.
+//!
+//! All functions catch panics and report failures through their return value, along with a message for
+//! [`gix_last_error()`] which is kept per thread. Stores must only be used by one thread at a time, so each thread
+//! should open its own.
+use git_features::progress;
+use git_object::borrowed;
+use git_odb::compound;
+use git_repository::discover;
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int},
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    ptr, slice,
+};
+
+/// The operation succeeded.
+pub const GIX_OK: c_int = 0;
+/// The object doesn't exist.
+pub const GIX_ERR_NOT_FOUND: c_int = 1;
+/// The buffer is too small for the object, whose size was stored regardless.
+pub const GIX_ERR_BUFFER_TOO_SMALL: c_int = 2;
+/// A pointer was null or a path wasn't valid.
+pub const GIX_ERR_INVALID_ARGUMENT: c_int = 3;
+/// The operation failed as described by [`gix_last_error()`].
+pub const GIX_ERR_FAILED: c_int = 4;
+
+/// The kind of a commit, using the type numbers of git.
+pub const GIX_OBJECT_COMMIT: c_int = 1;
+/// The kind of a tree.
+pub const GIX_OBJECT_TREE: c_int = 2;
+/// The kind of a blob.
+pub const GIX_OBJECT_BLOB: c_int = 3;
+/// The kind of an annotated tag.
+pub const GIX_OBJECT_TAG: c_int = 4;
+
+/// An object store along with the buffer objects are decoded into before they are copied to the caller.
+#[allow(non_camel_case_types)]
+pub struct gix_store {
+    handle: compound::Handle,
+    buf: Vec<u8>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl ToString) {
+    let message = message.to_string().replace('\0', " ");
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+}
+
+/// Run `f`, turning panics into [`GIX_ERR_FAILED`].
+fn guard(f: impl FnOnce() -> c_int) -> c_int {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|_| {
+        set_last_error("gitoxide panicked");
+        GIX_ERR_FAILED
+    })
+}
+
+unsafe fn to_path(path: *const c_char) -> Option<PathBuf> {
+    if path.is_null() {
+        set_last_error("the path must not be null");
+        return None;
+    }
+    let path = CStr::from_ptr(path).to_bytes();
+    #[cfg(unix)]
+    let path = {
+        use std::os::unix::ffi::OsStrExt;
+        Some(PathBuf::from(std::ffi::OsStr::from_bytes(path)))
+    };
+    #[cfg(not(unix))]
+    let path = std::str::from_utf8(path).ok().map(PathBuf::from);
+    if path.is_none() {
+        set_last_error("the path must be valid UTF-8");
+    }
+    path
+}
+
+fn to_store(store: Result<std::sync::Arc<compound::Store>, compound::init::Error>) -> *mut gix_store {
+    match store {
+        Ok(store) => Box::into_raw(Box::new(gix_store {
+            handle: store.to_handle(),
+            buf: Vec::new(),
+        })),
+        Err(err) => {
+            set_last_error(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Return the message describing the last failure on this thread, or null if there was none. It remains valid until
+/// the next call failing on this thread.
+#[no_mangle]
+pub extern "C" fn gix_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Open the object store at `objects_dir`, like `.git/objects`, or return null on failure.
+///
+/// # Safety
+///
+/// `objects_dir` must be null or a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn gix_store_open(objects_dir: *const c_char) -> *mut gix_store {
+    let mut store = ptr::null_mut();
+    guard(|| {
+        if let Some(objects_dir) = to_path(objects_dir) {
+            store = to_store(compound::Store::at(objects_dir));
+        }
+        GIX_OK
+    });
+    store
+}
+
+/// Open the object store of the repository `path` is in, honoring `GIT_DIR` and the other variables git reads to find
+/// repositories, or return null on failure.
+///
+/// # Safety
+///
+/// `path` must be null or a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn gix_repository_open(path: *const c_char) -> *mut gix_store {
+    let mut store = ptr::null_mut();
+    guard(|| {
+        if let Some(path) = to_path(path) {
+            match discover::discover(path, &discover::Environment::from_env()) {
+                Ok(paths) => store = to_store(compound::Store::at(paths.object_dir)),
+                Err(err) => set_last_error(err),
+            }
+        }
+        GIX_OK
+    });
+    store
+}
+
+/// Free `store`, which may be null.
+///
+/// # Safety
+///
+/// `store` must be null or returned by one of the functions opening stores, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn gix_store_free(store: *mut gix_store) {
+    if !store.is_null() {
+        guard(|| {
+            drop(Box::from_raw(store));
+            GIX_OK
+        });
+    }
+}
+
+/// Copy the data of the object with the 20 byte `id` into `buf` of `buf_len` bytes, and store its size in `out_size`
+/// and its kind in `out_kind`.
+///
+/// If `buf` is too small, [`GIX_ERR_BUFFER_TOO_SMALL`] is returned after storing the size, so the call can be
+/// repeated with a larger buffer. `buf` may be null if `buf_len` is 0, and `out_kind` may be null.
+///
+/// # Safety
+///
+/// `store` must be valid, `id` must point to 20 bytes, `buf` to `buf_len` writable bytes and `out_size` and
+/// `out_kind` to writable values.
+#[no_mangle]
+pub unsafe extern "C" fn gix_store_locate(
+    store: *mut gix_store,
+    id: *const u8,
+    buf: *mut u8,
+    buf_len: usize,
+    out_size: *mut usize,
+    out_kind: *mut c_int,
+) -> c_int {
+    if store.is_null() || id.is_null() || out_size.is_null() || (buf.is_null() && buf_len != 0) {
+        set_last_error("store, id and out_size must not be null, and buf must not be null if buf_len isn't 0");
+        return GIX_ERR_INVALID_ARGUMENT;
+    }
+    guard(|| {
+        let gix_store { handle, buf: data } = &mut *store;
+        let id = borrowed::Id::from(&*(id as *const [u8; 20]));
+        let object = match handle.locate(id, data) {
+            Some(Ok(object)) => object,
+            Some(Err(err)) => {
+                set_last_error(err);
+                return GIX_ERR_FAILED;
+            }
+            None => return GIX_ERR_NOT_FOUND,
+        };
+        *out_size = object.data.len();
+        if !out_kind.is_null() {
+            *out_kind = match object.kind {
+                git_object::Kind::Commit => GIX_OBJECT_COMMIT,
+                git_object::Kind::Tree => GIX_OBJECT_TREE,
+                git_object::Kind::Blob => GIX_OBJECT_BLOB,
+                git_object::Kind::Tag => GIX_OBJECT_TAG,
+            };
+        }
+        if object.data.len() > buf_len {
+            return GIX_ERR_BUFFER_TOO_SMALL;
+        }
+        if !object.data.is_empty() {
+            slice::from_raw_parts_mut(buf, object.data.len()).copy_from_slice(object.data);
+        }
+        GIX_OK
+    })
+}
+
+/// Verify the checksum of the pack at `path` if it ends in `.pack`, or if it ends in `.idx`, verify the index and all
+/// objects in the pack next to it.
+///
+/// # Safety
+///
+/// `path` must be null or a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn gix_pack_verify(path: *const c_char) -> c_int {
+    let path = match to_path(path) {
+        Some(path) => path,
+        None => return GIX_ERR_INVALID_ARGUMENT,
+    };
+    guard(|| {
+        use gitoxide_core::pack::verify;
+        let res = verify::open(&path).and_then(|target| {
+            if let verify::Target::Index { pack: Err(err), .. } = &target {
+                return Err(anyhow::anyhow!("Could not open the pack next to the index: {}", err));
+            }
+            target.verify(
+                None::<progress::Discard>,
+                verify::Options {
+                    mode: verify::Mode::Sha1CRC32Decode,
+                    ..Default::default()
+                },
+            )
+        });
+        match res {
+            Ok(_) => GIX_OK,
+            Err(err) => {
+                set_last_error(format!("{:#}", err));
+                GIX_ERR_FAILED
+            }
+        }
+    })
+}
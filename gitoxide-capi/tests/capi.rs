@@ -0,0 +1,226 @@
+use gitoxide_capi::*;
+use std::{
+    ffi::{CStr, CString},
+    fs,
+    os::raw::c_int,
+    path::Path,
+    process::Command,
+    ptr,
+};
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(["-c", "user.name=a", "-c", "user.email=a@example.com"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap().trim_end().to_owned()
+}
+
+fn c_path(path: &Path) -> CString {
+    CString::new(path.to_str().unwrap()).unwrap()
+}
+
+fn id(hex: &str) -> [u8; 20] {
+    let mut id = [0; 20];
+    id.copy_from_slice(
+        git_object::owned::Id::from_40_bytes_in_hex(hex.as_bytes())
+            .unwrap()
+            .as_slice(),
+    );
+    id
+}
+
+fn last_error() -> String {
+    let message = gix_last_error();
+    assert!(!message.is_null());
+    unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned()
+}
+
+/// A repository with a commit of a file containing `content`, returning the ids of the blob and the commit.
+fn repository(dir: &Path) -> (String, String) {
+    git(dir, &["init", "--quiet"]);
+    fs::write(dir.join("file"), "content\n").unwrap();
+    git(dir, &["add", "file"]);
+    git(dir, &["commit", "--quiet", "-m", "initial"]);
+    (git(dir, &["rev-parse", "HEAD:file"]), git(dir, &["rev-parse", "HEAD"]))
+}
+
+#[test]
+fn objects_are_copied_into_buffers_which_are_large_enough() {
+    let dir = tempfile::tempdir().unwrap();
+    let (blob, commit) = repository(dir.path());
+    git(dir.path(), &["repack", "--quiet", "-a", "-d"]);
+    fs::write(dir.path().join("loose"), "loose\n").unwrap();
+    let loose = git(dir.path(), &["hash-object", "-w", "loose"]);
+
+    let store = unsafe { gix_store_open(c_path(&dir.path().join(".git/objects")).as_ptr()) };
+    assert!(!store.is_null());
+    let (mut size, mut kind) = (0, 0 as c_int);
+    let mut buf = [0u8; 64];
+    for (id_hex, expected) in &[(blob, &b"content\n"[..]), (loose, &b"loose\n"[..])] {
+        let res = unsafe {
+            gix_store_locate(
+                store,
+                id(id_hex).as_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut size,
+                &mut kind,
+            )
+        };
+        assert_eq!(res, GIX_OK);
+        assert_eq!(kind, GIX_OBJECT_BLOB);
+        assert_eq!(&buf[..size], *expected);
+    }
+
+    let res = unsafe {
+        gix_store_locate(
+            store,
+            id(&commit).as_ptr(),
+            ptr::null_mut(),
+            0,
+            &mut size,
+            ptr::null_mut(),
+        )
+    };
+    assert_eq!(res, GIX_ERR_BUFFER_TOO_SMALL);
+    let mut commit_buf = vec![0; size];
+    let res = unsafe {
+        gix_store_locate(
+            store,
+            id(&commit).as_ptr(),
+            commit_buf.as_mut_ptr(),
+            commit_buf.len(),
+            &mut size,
+            &mut kind,
+        )
+    };
+    assert_eq!(res, GIX_OK);
+    assert_eq!(kind, GIX_OBJECT_COMMIT);
+    assert!(commit_buf.starts_with(b"tree "));
+
+    let missing = [0xffu8; 20];
+    let res = unsafe {
+        gix_store_locate(
+            store,
+            missing.as_ptr(),
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut size,
+            &mut kind,
+        )
+    };
+    assert_eq!(res, GIX_ERR_NOT_FOUND);
+    unsafe { gix_store_free(store) };
+}
+
+#[test]
+fn repositories_are_opened_from_subdirectories() {
+    let dir = tempfile::tempdir().unwrap();
+    let (blob, _) = repository(dir.path());
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    let store = unsafe { gix_repository_open(c_path(&dir.path().join("sub")).as_ptr()) };
+    assert!(!store.is_null());
+    let mut size = 0;
+    let res = unsafe {
+        gix_store_locate(
+            store,
+            id(&blob).as_ptr(),
+            ptr::null_mut(),
+            0,
+            &mut size,
+            ptr::null_mut(),
+        )
+    };
+    assert_eq!(res, GIX_ERR_BUFFER_TOO_SMALL);
+    assert_eq!(size, b"content\n".len());
+    unsafe { gix_store_free(store) };
+
+    let store = unsafe { gix_repository_open(c_path(&dir.path().join("missing")).as_ptr()) };
+    assert!(store.is_null());
+    assert!(last_error().contains("missing"));
+}
+
+#[test]
+fn invalid_arguments_are_rejected() {
+    assert!(unsafe { gix_store_open(ptr::null()) }.is_null());
+    assert!(last_error().contains("null"));
+    let mut size = 0;
+    let res = unsafe {
+        gix_store_locate(
+            ptr::null_mut(),
+            ptr::null(),
+            ptr::null_mut(),
+            0,
+            &mut size,
+            ptr::null_mut(),
+        )
+    };
+    assert_eq!(res, GIX_ERR_INVALID_ARGUMENT);
+    assert_eq!(unsafe { gix_pack_verify(ptr::null()) }, GIX_ERR_INVALID_ARGUMENT);
+    unsafe { gix_store_free(ptr::null_mut()) };
+}
+
+#[test]
+fn packs_and_indices_are_verified() {
+    let dir = tempfile::tempdir().unwrap();
+    repository(dir.path());
+    git(dir.path(), &["repack", "--quiet", "-a", "-d"]);
+    let pack_dir = dir.path().join(".git/objects/pack");
+    let index = fs::read_dir(&pack_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "idx"))
+        .unwrap();
+    assert_eq!(unsafe { gix_pack_verify(c_path(&index).as_ptr()) }, GIX_OK);
+    assert_eq!(
+        unsafe { gix_pack_verify(c_path(&index.with_extension("pack")).as_ptr()) },
+        GIX_OK
+    );
+
+    let pack = index.with_extension("pack");
+    let mut data = fs::read(&pack).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 0xff;
+    let mut permissions = fs::metadata(&pack).unwrap().permissions();
+    #[cfg(unix)]
+    std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o644);
+    // Elsewhere the read-only flag is all there is to allow writing the pack.
+    #[cfg(not(unix))]
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    fs::set_permissions(&pack, permissions).unwrap();
+    fs::write(&pack, data).unwrap();
+    assert_eq!(unsafe { gix_pack_verify(c_path(&pack).as_ptr()) }, GIX_ERR_FAILED);
+    assert!(!last_error().is_empty());
+}
+
+#[test]
+fn the_header_declares_all_exported_functions_and_constants() {
+    let header = include_str!("../include/gitoxide.h");
+    let source = include_str!("../src/lib.rs");
+    let mut functions = 0;
+    for line in source.lines() {
+        if let Some(name) = line
+            .strip_prefix("pub unsafe extern \"C\" fn ")
+            .or_else(|| line.strip_prefix("pub extern \"C\" fn "))
+        {
+            let name = &name[..name.find('(').unwrap()];
+            assert!(
+                header.contains(&format!(" {}(", name)) || header.contains(&format!("*{}(", name)),
+                "{}",
+                name
+            );
+            functions += 1;
+        }
+        if let Some(constant) = line.strip_prefix("pub const ") {
+            let name = &constant[..constant.find(':').unwrap()];
+            let value = constant[constant.find("= ").unwrap() + 2..].trim_end_matches(';');
+            assert!(header.contains(&format!("#define {} {}", name, value)), "{}", name);
+        }
+    }
+    assert_eq!(functions, 6);
+}
@@ -1,3 +1,9 @@
+//! The operations backing the gitoxide CLI, usable by other programs as well.
+//!
+//! Besides functions writing human or JSON output like the CLI does, operations like
+//! [verifying][pack::verify::open()], [indexing][pack::index::write()], [exploding][pack::explode::objects()] and
+//! [receiving][pack::receive::fetch()] packs are available as functions taking typed options and returning typed
+//! outcomes.
 #![forbid(unsafe_code)]
 
 use std::str::FromStr;
@@ -33,5 +39,70 @@ impl FromStr for OutputFormat {
     }
 }
 
+/// An error along with the chain of its causes, for use by tools consuming the output of failed commands.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize))]
+pub struct ErrorSummary {
+    /// The message of the outermost error.
+    pub error: String,
+    /// The messages of all errors that caused it, the innermost one last.
+    pub causes: Vec<String>,
+}
+
+impl From<&anyhow::Error> for ErrorSummary {
+    fn from(err: &anyhow::Error) -> Self {
+        ErrorSummary {
+            error: err.to_string(),
+            causes: err.chain().skip(1).map(ToString::to_string).collect(),
+        }
+    }
+}
+
+impl ErrorSummary {
+    /// Write the summary as JSON object to `out`.
+    #[cfg(feature = "serde1")]
+    pub fn write_json(&self, mut out: impl std::io::Write) -> anyhow::Result<()> {
+        serde_json::to_writer_pretty(&mut out, self)?;
+        writeln!(out)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Default)]
+pub enum Protocol {
+    V1,
+    #[default]
+    V2,
+}
+
+impl Protocol {
+    pub fn variants() -> &'static [&'static str] {
+        &["1", "2"]
+    }
+}
+
+impl FromStr for Protocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "1" => Protocol::V1,
+            "2" => Protocol::V2,
+            _ => return Err(format!("Unsupported protocol version '{}', choose '1' or '2'", s)),
+        })
+    }
+}
+
+impl From<Protocol> for git_transport::Protocol {
+    fn from(v: Protocol) -> Self {
+        match v {
+            Protocol::V1 => git_transport::Protocol::V1,
+            Protocol::V2 => git_transport::Protocol::V2,
+        }
+    }
+}
+
+pub mod object;
 pub mod pack;
+pub mod remote;
 pub mod repository;
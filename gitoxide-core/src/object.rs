@@ -0,0 +1,151 @@
+use crate::OutputFormat;
+use anyhow::{Context as AnyhowContext, Result};
+use git_object::{borrowed, bstr::ByteSlice, owned, TreeMode};
+use git_odb::{compound, pack, prefix, Find};
+use git_repository::{discover, revision};
+use std::{io, path::PathBuf};
+
+pub struct Context<W: io::Write> {
+    pub format: OutputFormat,
+    pub out: W,
+}
+
+/// An object along with its id and the size of its data.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize))]
+pub struct Outcome {
+    pub id: owned::Id,
+    pub kind: git_object::Kind,
+    pub size: usize,
+    pub object: owned::Object,
+}
+
+/// Find the object `revision` names in the repository `repository` is in, or the current directory is in if unset,
+/// and write its kind, size and content.
+///
+/// Like `git cat-file -p`, trees are listed one entry per line, and the data of other objects is written as is.
+pub fn cat<W: io::Write>(repository: Option<PathBuf>, revision: &str, mut ctx: Context<W>) -> Result<Outcome> {
+    let (git_dir, db) = open(repository)?;
+    let id = revision::resolve(&git_dir, &db, revision.into())
+        .with_context(|| format!("Could not resolve '{}'", revision))?;
+    let mut buf = Vec::new();
+    let object = db
+        .locate(id.to_borrowed(), &mut buf, &mut pack::cache::DecodeEntryNoop)
+        .ok_or_else(|| anyhow::anyhow!("The object {} does not exist", id))?
+        .with_context(|| format!("Could not read object {}", id))?;
+    let decoded = borrowed::Object::from_bytes(object.kind, object.data)
+        .with_context(|| format!("Could not decode {} object {}", object.kind, id))?;
+    let outcome = Outcome {
+        id,
+        kind: object.kind,
+        size: object.data.len(),
+        object: decoded.into(),
+    };
+    match ctx.format {
+        OutputFormat::Human => {
+            writeln!(ctx.out, "{} {} {}", outcome.id, outcome.kind, outcome.size)?;
+            match &outcome.object {
+                owned::Object::Tree(tree) => write_tree(&mut ctx.out, tree)?,
+                _ => ctx.out.write_all(object.data)?,
+            }
+        }
+        #[cfg(feature = "serde1")]
+        OutputFormat::Json => serde_json::to_writer_pretty(&mut ctx.out, &outcome)?,
+    };
+    Ok(outcome)
+}
+
+/// What to write for each object requested in a batch.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+pub enum Batch {
+    /// Write `<id> <kind> <size>` lines without reading the data of objects, like `git cat-file --batch-check`.
+    Headers,
+    /// Write the header line followed by the data of the object and a newline, like `git cat-file --batch`.
+    Contents,
+}
+
+/// Answer the requests for objects read line by line from `input` until it ends, writing the responses to `out` in the
+/// format of `git cat-file --batch` or `--batch-check` depending on `mode`.
+///
+/// Each line is a revision as accepted by [`cat()`], and revisions which don't name an object are answered with
+/// `<revision> missing` or `<revision> ambiguous`. The object database and its caches are opened once and used for all
+/// requests, and each response is flushed right away for the benefit of programs waiting for it. Returns the amount
+/// of answered requests.
+pub fn batch(
+    repository: Option<PathBuf>,
+    input: impl io::BufRead,
+    mut out: impl io::Write,
+    mode: Batch,
+) -> Result<usize> {
+    let (git_dir, db) = open(repository)?;
+    let mut cache = pack::cache::DecodeEntryLRU::default();
+    let mut buf = Vec::new();
+    let mut num_requests = 0;
+    for line in input.split(b'\n') {
+        let mut line = line?;
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        num_requests += 1;
+        let id = match revision::resolve(&git_dir, &db, line.as_slice().into()) {
+            Ok(id) => id,
+            Err(revision::Error::NotFound(_)) => {
+                writeln!(out, "{} missing", line.as_bstr())?;
+                out.flush()?;
+                continue;
+            }
+            Err(revision::Error::Prefix(prefix::Error::Ambiguous { .. })) => {
+                writeln!(out, "{} ambiguous", line.as_bstr())?;
+                out.flush()?;
+                continue;
+            }
+            Err(err) => return Err(err).with_context(|| format!("Could not resolve '{}'", line.as_bstr())),
+        };
+        match mode {
+            Batch::Headers => match db.header(id.to_borrowed()) {
+                Some(header) => {
+                    let (kind, size) = header.with_context(|| format!("Could not read object {}", id))?;
+                    writeln!(out, "{} {} {}", id, kind, size)?;
+                }
+                None => writeln!(out, "{} missing", line.as_bstr())?,
+            },
+            Batch::Contents => match db.locate(id.to_borrowed(), &mut buf, &mut cache) {
+                Some(object) => {
+                    let object = object.with_context(|| format!("Could not read object {}", id))?;
+                    writeln!(out, "{} {} {}", id, object.kind, object.data.len())?;
+                    out.write_all(object.data)?;
+                    writeln!(out)?;
+                }
+                None => writeln!(out, "{} missing", line.as_bstr())?,
+            },
+        }
+        out.flush()?;
+    }
+    Ok(num_requests)
+}
+
+/// Find the repository `repository` is in, or the current directory is in if unset, and open its objects.
+fn open(repository: Option<PathBuf>) -> Result<(PathBuf, compound::Db)> {
+    let paths = discover::discover(
+        repository.unwrap_or_else(|| ".".into()),
+        &discover::Environment::from_env(),
+    )
+    .with_context(|| "Could not find the repository")?;
+    let db = compound::Db::at(&paths.object_dir).with_context(|| "Could not open the object database")?;
+    Ok((paths.git_dir, db))
+}
+
+/// Write one line per entry of `tree`, like `git ls-tree` does.
+fn write_tree(mut out: impl io::Write, tree: &owned::Tree) -> io::Result<()> {
+    for entry in &tree.entries {
+        let kind = match entry.mode {
+            TreeMode::Tree => git_object::Kind::Tree,
+            TreeMode::Commit => git_object::Kind::Commit,
+            TreeMode::Blob | TreeMode::BlobExecutable | TreeMode::Link => git_object::Kind::Blob,
+        };
+        write!(out, "{:06o} {} {}\t", entry.mode as u16, kind, entry.oid)?;
+        out.write_all(&entry.filename)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
@@ -1,8 +1,13 @@
+use crate::OutputFormat;
 use anyhow::{anyhow, Result};
 use git_features::progress::{self, Progress};
 use git_object::{owned, HashKind};
 use git_odb::{loose, pack, Write};
-use std::{fs, io::Read, path::Path};
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
 
 #[derive(PartialEq, Debug)]
 pub enum SafetyCheck {
@@ -135,26 +140,46 @@ impl OutputWriter {
     }
 }
 
-#[derive(Default)]
-pub struct Context {
+pub struct Context<W: io::Write> {
     pub thread_limit: Option<usize>,
     pub delete_pack: bool,
     pub sink_compress: bool,
     pub verify: bool,
+    pub output_statistics: Option<OutputFormat>,
+    pub out: W,
 }
 
-pub fn pack_or_pack_index<P>(
+/// How to explode a pack into loose objects.
+#[derive(PartialEq, Debug, Default)]
+pub struct Options {
+    /// If set, don't use more than this amount of threads.
+    pub thread_limit: Option<usize>,
+    /// How much to verify while decoding objects.
+    pub check: SafetyCheck,
+    /// If true, delete the pack and its index once all objects were written.
+    pub delete_pack: bool,
+    /// If true, compress objects even if they are written into a sink as no object directory is given.
+    pub sink_compress: bool,
+    /// If true, read back and verify every written object.
+    pub verify: bool,
+}
+
+/// Write all objects of the pack or index at `pack_path` as loose objects into `object_path`, or into a sink if unset,
+/// and return statistics about the objects in the pack.
+///
+/// If only a pack is given, a temporary index is created for it.
+pub fn objects<P>(
     pack_path: impl AsRef<Path>,
     object_path: Option<impl AsRef<Path>>,
-    check: SafetyCheck,
     progress: Option<P>,
-    Context {
+    Options {
         thread_limit,
+        check,
         delete_pack,
         sink_compress,
         verify,
-    }: Context,
-) -> Result<()>
+    }: Options,
+) -> Result<pack::index::traverse::Outcome>
 where
     P: Progress + Send,
     <P as Progress>::SubProgress: Send,
@@ -163,12 +188,31 @@ where
     use anyhow::Context;
 
     let path = pack_path.as_ref();
-    let bundle = pack::Bundle::at(path).with_context(|| {
-        format!(
-            "Could not find .idx or .pack file from given file at '{}'",
-            path.display()
-        )
-    })?;
+    let mut progress = progress;
+    let (bundle, temporary_index_directory) =
+        if path.extension().map(|ext| ext == "pack").unwrap_or(false) && !path.with_extension("idx").is_file() {
+            if let Some(progress) = progress.as_mut() {
+                progress.info(format!(
+                    "No index found next to '{}' - creating a temporary one from the pack",
+                    path.display()
+                ));
+            }
+            let (bundle, index_directory) = bundle_with_temporary_index(path, thread_limit).with_context(|| {
+                format!(
+                    "Could not create a temporary index for the pack at '{}'",
+                    path.display()
+                )
+            })?;
+            (bundle, Some(index_directory))
+        } else {
+            let bundle = pack::Bundle::at(path).with_context(|| {
+                format!(
+                    "Could not find .idx or .pack file from given file at '{}'",
+                    path.display()
+                )
+            })?;
+            (bundle, None)
+        };
 
     if !object_path.as_ref().map(|p| p.as_ref().is_dir()).unwrap_or(true) {
         return Err(anyhow!(
@@ -190,7 +234,7 @@ where
                 pack::index::traverse::Algorithm::DeltaTreeLookup
             }
         });
-    let mut progress = bundle.index.traverse(
+    let (statistics, mut progress) = bundle.index.traverse(
         &bundle.pack,
         progress,
         {
@@ -229,26 +273,102 @@ where
             thread_limit,
             check: check.into(),
         },
-    ).map(|(_,_,c)|progress::DoOrDiscard::from(c)).with_context(|| "Failed to explode the entire pack - some loose objects may have been created nonetheless")?;
+    ).map(|(_, statistics, c)| (statistics, progress::DoOrDiscard::from(c))).with_context(|| "Failed to explode the entire pack - some loose objects may have been created nonetheless")?;
 
     let (index_path, data_path) = (bundle.index.path().to_owned(), bundle.pack.path().to_owned());
     drop(bundle);
+    // A temporary index was created next to a copy of the pack, which go away with their directory.
+    let (index_path, data_path) = match temporary_index_directory {
+        Some(_) => (None, path.to_owned()),
+        None => (Some(index_path), data_path),
+    };
 
     if delete_pack {
-        fs::remove_file(&index_path)
-            .and_then(|_| fs::remove_file(&data_path))
-            .with_context(|| {
-                format!(
-                    "Failed to delete pack index file at '{} or data file at '{}'",
+        match index_path {
+            Some(index_path) => {
+                fs::remove_file(&index_path)
+                    .and_then(|_| fs::remove_file(&data_path))
+                    .with_context(|| {
+                        format!(
+                            "Failed to delete pack index file at '{} or data file at '{}'",
+                            index_path.display(),
+                            data_path.display()
+                        )
+                    })?;
+                progress.info(format!(
+                    "Removed '{}' and '{}'",
                     index_path.display(),
                     data_path.display()
-                )
-            })?;
-        progress.info(format!(
-            "Removed '{}' and '{}'",
-            index_path.display(),
-            data_path.display()
-        ));
+                ));
+            }
+            None => {
+                fs::remove_file(&data_path)
+                    .with_context(|| format!("Failed to delete pack data file at '{}'", data_path.display()))?;
+                progress.info(format!("Removed '{}'", data_path.display()));
+            }
+        }
     }
+
+    Ok(statistics)
+}
+
+pub fn pack_or_pack_index<P, W: io::Write>(
+    pack_path: impl AsRef<Path>,
+    object_path: Option<impl AsRef<Path>>,
+    check: SafetyCheck,
+    progress: Option<P>,
+    Context {
+        thread_limit,
+        delete_pack,
+        sink_compress,
+        verify,
+        output_statistics,
+        mut out,
+    }: Context<W>,
+) -> Result<()>
+where
+    P: Progress + Send,
+    <P as Progress>::SubProgress: Send,
+    <<P as Progress>::SubProgress as Progress>::SubProgress: Send,
+{
+    let statistics = objects(
+        pack_path,
+        object_path,
+        progress,
+        Options {
+            thread_limit,
+            check,
+            delete_pack,
+            sink_compress,
+            verify,
+        },
+    )?;
+    match output_statistics {
+        Some(OutputFormat::Human) => drop(super::verify::print_statistics(&mut out, &statistics)),
+        #[cfg(feature = "serde1")]
+        Some(OutputFormat::Json) => serde_json::to_writer_pretty(out, &statistics)?,
+        None => {}
+    };
     Ok(())
 }
+
+/// Index the pack at `path` into a temporary directory, which must be kept alive as long as the returned bundle is used.
+fn bundle_with_temporary_index(path: &Path, thread_limit: Option<usize>) -> Result<(pack::Bundle, tempfile::TempDir)> {
+    let directory = tempfile::tempdir()?;
+    let pack_len = path.metadata()?.len();
+    let outcome = pack::Bundle::write_to_directory(
+        fs::File::open(path)?,
+        Some(pack_len),
+        Some(directory.path()),
+        progress::Discard,
+        pack::bundle::write::Options {
+            thread_limit,
+            iteration_mode: pack::data::iter::Mode::Verify,
+            index_kind: pack::index::Kind::default(),
+        },
+    )?;
+    let index_path = outcome
+        .index_path
+        .expect("an index path if an output directory is given");
+    Ok((pack::Bundle::at(index_path)?, directory))
+}
@@ -1,7 +1,7 @@
 use crate::OutputFormat;
 use git_features::progress::Progress;
-use git_odb::pack;
-use std::{fs, io, path::PathBuf, str::FromStr};
+use git_odb::{compound, pack};
+use std::{fs, io, io::Read, path::PathBuf, str::FromStr};
 
 #[derive(PartialEq, Debug)]
 pub enum IterationMode {
@@ -51,6 +51,7 @@ impl From<IterationMode> for pack::data::iter::Mode {
 pub struct Context<W: io::Write> {
     pub thread_limit: Option<usize>,
     pub iteration_mode: IterationMode,
+    pub object_dir: Option<PathBuf>,
     pub format: OutputFormat,
     pub out: W,
 }
@@ -67,12 +68,32 @@ pub fn stream_len(mut s: impl io::Seek) -> io::Result<u64> {
 
 pub const PROGRESS_RANGE: std::ops::RangeInclusive<u8> = 2..=3;
 
-pub fn from_pack<P, W: io::Write>(
+/// What to do while writing the index of a pack.
+#[derive(PartialEq, Debug, Default)]
+pub struct Options {
+    /// If set, don't use more than this amount of threads.
+    pub thread_limit: Option<usize>,
+    /// How to handle entries of the pack which are corrupt.
+    pub iteration_mode: IterationMode,
+    /// If set, the objects directory to look up the bases of thin packs in, which are added to the pack before indexing it.
+    pub object_dir: Option<PathBuf>,
+}
+
+/// Read the pack at `pack`, or from stdin if unset, and write it along with its index into `directory`, or only
+/// compute the index if unset.
+///
+/// Thin packs can only be indexed with an `object_dir` containing the objects they refer to, as these are added to
+/// the pack beforehand. This requires reading the whole pack into memory.
+pub fn write<P>(
     pack: Option<PathBuf>,
     directory: Option<PathBuf>,
     progress: P,
-    ctx: Context<W>,
-) -> anyhow::Result<()>
+    Options {
+        thread_limit,
+        iteration_mode,
+        object_dir,
+    }: Options,
+) -> anyhow::Result<pack::bundle::write::Outcome>
 where
     P: Progress,
     <P as Progress>::SubProgress: Send + 'static,
@@ -80,13 +101,30 @@ where
 {
     use anyhow::Context;
     let options = pack::bundle::write::Options {
-        thread_limit: ctx.thread_limit,
-        iteration_mode: ctx.iteration_mode.into(),
+        thread_limit,
+        iteration_mode: iteration_mode.into(),
         index_kind: pack::index::Kind::default(),
     };
-    let out = ctx.out;
-    let format = ctx.format;
-    let res = match pack {
+    if let Some(object_dir) = object_dir {
+        let mut data = Vec::new();
+        match pack {
+            Some(pack) => fs::File::open(pack)?.read_to_end(&mut data)?,
+            None => io::stdin().read_to_end(&mut data)?,
+        };
+        let db = compound::Db::at(object_dir).with_context(|| "Could not open the object database")?;
+        let thin = pack::data::File::from_data(data)?;
+        let mut completed = Vec::new();
+        thin.write_completed_to_stream(
+            |id, buf| db.locate(id, buf, &mut pack::cache::DecodeEntryNoop)?.ok(),
+            git_features::progress::Discard,
+            &mut completed,
+        )
+        .with_context(|| "Failed to add the objects the pack refers to")?;
+        let len = completed.len() as u64;
+        return pack::Bundle::write_to_directory(io::Cursor::new(completed), Some(len), directory, progress, options)
+            .with_context(|| "Failed to write pack and index");
+    }
+    match pack {
         Some(pack) => {
             let pack_len = pack.metadata()?.len();
             let pack_file = fs::File::open(pack)?;
@@ -97,11 +135,46 @@ where
             pack::Bundle::write_to_directory(stdin, None, directory, progress, options)
         }
     }
-    .with_context(|| "Failed to write pack and index")?;
-    match format {
-        OutputFormat::Human => drop(human_output(out, res)),
+    .map_err(|err| {
+        let is_thin = matches!(
+            err,
+            pack::bundle::write::Error::IndexWrite(pack::index::write::Error::IteratorInvariantNoRefDelta)
+        );
+        let err = anyhow::Error::from(err);
+        if is_thin {
+            err.context("Thin packs refer to objects outside of the pack and are not supported - use a pack created without '--thin' or pass the repository objects with '--object-dir'")
+        } else {
+            err
+        }
+    })
+    .with_context(|| "Failed to write pack and index")
+}
+
+pub fn from_pack<P, W: io::Write>(
+    pack: Option<PathBuf>,
+    directory: Option<PathBuf>,
+    progress: P,
+    ctx: Context<W>,
+) -> anyhow::Result<()>
+where
+    P: Progress,
+    <P as Progress>::SubProgress: Send + 'static,
+    <<P as Progress>::SubProgress as Progress>::SubProgress: Send,
+{
+    let res = write(
+        pack,
+        directory,
+        progress,
+        Options {
+            thread_limit: ctx.thread_limit,
+            iteration_mode: ctx.iteration_mode,
+            object_dir: ctx.object_dir,
+        },
+    )?;
+    match ctx.format {
+        OutputFormat::Human => drop(human_output(ctx.out, res)),
         #[cfg(feature = "serde1")]
-        OutputFormat::Json => serde_json::to_writer_pretty(out, &res)?,
+        OutputFormat::Json => serde_json::to_writer_pretty(ctx.out, &res)?,
     };
     Ok(())
 }
@@ -1,3 +1,4 @@
 pub mod explode;
 pub mod index;
+pub mod receive;
 pub mod verify;
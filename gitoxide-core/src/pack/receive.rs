@@ -0,0 +1,186 @@
+use crate::{OutputFormat, Protocol};
+use anyhow::{Context as AnyhowContext, Result};
+use git_features::progress::Progress;
+use git_object::bstr::{BString, ByteSlice};
+use git_odb::pack;
+use git_protocol::{fetch, handshake, handshake::Ref, ls_refs, RemoteProgress};
+use git_transport::{
+    client::{self, http::pipe, Transport},
+    Service,
+};
+use std::{fs, io, path::PathBuf};
+
+pub const PROGRESS_RANGE: std::ops::RangeInclusive<u8> = 1..=3;
+
+pub struct Context<W: io::Write> {
+    pub thread_limit: Option<usize>,
+    pub format: OutputFormat,
+    pub out: W,
+}
+
+/// What to receive and how to index it.
+#[derive(PartialEq, Debug, Default)]
+pub struct Options {
+    /// The protocol version to use, or the default one if unset.
+    pub protocol: Option<Protocol>,
+    /// Only receive objects of refs starting with one of these prefixes, or of all refs if empty.
+    pub ref_prefixes: Vec<BString>,
+    /// If set, don't use more than this amount of threads.
+    pub thread_limit: Option<usize>,
+}
+
+/// The pack and refs received from a remote.
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The pack along with its index, or `None` if the remote had no objects to send.
+    pub pack: Option<pack::bundle::write::Outcome>,
+    /// The refs advertised by the remote, limited to the [prefixes][Options::ref_prefixes].
+    pub refs: Vec<Ref>,
+}
+
+/// Connect to the remote at `url`, receive a pack with all objects of the refs matching `options`, and write it along
+/// with its index into `directory`, or into a sink if unset.
+pub fn fetch<P>(
+    url: &str,
+    directory: Option<PathBuf>,
+    mut progress: P,
+    Options {
+        protocol,
+        ref_prefixes,
+        thread_limit,
+    }: Options,
+) -> Result<Outcome>
+where
+    P: Progress,
+    <P as Progress>::SubProgress: Send + 'static,
+    <<P as Progress>::SubProgress as Progress>::SubProgress: Send + 'static,
+    <<<P as Progress>::SubProgress as Progress>::SubProgress as Progress>::SubProgress: Send,
+{
+    let mut transport = client::connect(url.as_bytes(), protocol.unwrap_or_default().into())
+        .with_context(|| format!("Could not connect to '{}'", url))?;
+    let outcome = handshake::parse(transport.handshake(Service::UploadPack)?)?;
+    let refs = match outcome.refs.clone() {
+        Some(refs) => refs
+            .into_iter()
+            .filter(|r| {
+                let (path, _) = r.unpack();
+                ref_prefixes.is_empty() || ref_prefixes.iter().any(|p| path.starts_with(p.as_bytes()))
+            })
+            .collect(),
+        None => git_protocol::ls_refs(
+            &mut transport,
+            &outcome.capabilities,
+            &ls_refs::Options {
+                prefixes: ref_prefixes,
+                ..Default::default()
+            },
+        )?,
+    };
+
+    let mut arguments = fetch::Arguments::new(&outcome);
+    for r in &refs {
+        let id = match r {
+            Ref::Peeled { tag, .. } => *tag,
+            Ref::Direct { object, .. } | Ref::Symbolic { object, .. } => *object,
+            Ref::Unborn { .. } => continue,
+        };
+        if !arguments.wants().contains(&id) {
+            arguments.want(id);
+        }
+    }
+
+    let pack = if arguments.is_empty() {
+        None
+    } else {
+        let write_progress = progress.add_child("pack");
+        let mut remote_progress = progress.add_child("remote");
+        let options = pack::bundle::write::Options {
+            thread_limit,
+            iteration_mode: pack::data::iter::Mode::Verify,
+            index_kind: pack::index::Kind::default(),
+        };
+        let mut written = None;
+        fetch::fetch(
+            &mut transport,
+            &arguments,
+            &mut fetch::negotiate::Noop,
+            |is_err, text| RemoteProgress::translate_to_progress(is_err, text, &mut remote_progress),
+            |pack, _response| {
+                let (mut writer, reader) = pipe::unidirectional(64);
+                let indexer = std::thread::spawn(move || {
+                    pack::Bundle::write_to_directory(reader, None, directory, write_progress, options)
+                });
+                if let Err(err) = io::copy(pack, &mut writer) {
+                    writer.send_error(io::Error::new(err.kind(), err.to_string()));
+                }
+                drop(writer);
+                written = Some(indexer.join().expect("no panic in indexer thread"));
+                Ok(())
+            },
+        )?;
+        Some(
+            written
+                .expect("pack to be received")
+                .with_context(|| "Failed to write pack and index")?,
+        )
+    };
+    transport.close()?;
+    Ok(Outcome { pack, refs })
+}
+
+/// Connect to the remote at `url`, receive a pack with all objects of the refs starting with one of `ref_prefixes`,
+/// or of all refs if there is none, and write it along with its index into `directory`, or into a sink if unset.
+///
+/// The refs received from the remote are written to `refs_file` if set.
+pub fn receive<P, W: io::Write>(
+    protocol: Option<Protocol>,
+    url: &str,
+    directory: Option<PathBuf>,
+    refs_file: Option<PathBuf>,
+    ref_prefixes: Vec<BString>,
+    progress: P,
+    ctx: Context<W>,
+) -> Result<()>
+where
+    P: Progress,
+    <P as Progress>::SubProgress: Send + 'static,
+    <<P as Progress>::SubProgress as Progress>::SubProgress: Send + 'static,
+    <<<P as Progress>::SubProgress as Progress>::SubProgress as Progress>::SubProgress: Send,
+{
+    let Outcome { pack, refs } = fetch(
+        url,
+        directory,
+        progress,
+        Options {
+            protocol,
+            ref_prefixes,
+            thread_limit: ctx.thread_limit,
+        },
+    )?;
+    if let Some(path) = refs_file {
+        let mut buf = Vec::new();
+        crate::remote::refs::print(&mut buf, &refs)?;
+        fs::write(&path, buf).with_context(|| format!("Could not write refs to '{}'", path.display()))?;
+    }
+    match ctx.format {
+        OutputFormat::Human => drop(human_output(ctx.out, pack, &refs)),
+        #[cfg(feature = "serde1")]
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(ctx.out, &serde_json::json!({ "pack": pack, "refs": refs }))?
+        }
+    };
+    Ok(())
+}
+
+fn human_output(mut out: impl io::Write, pack: Option<pack::bundle::write::Outcome>, refs: &[Ref]) -> io::Result<()> {
+    match pack {
+        Some(pack) => {
+            writeln!(&mut out, "index: {}", pack.index.index_hash)?;
+            writeln!(&mut out, "pack: {}", pack.index.data_hash)?;
+        }
+        None => writeln!(&mut out, "The remote has no objects to send")?,
+    }
+    writeln!(&mut out)?;
+    crate::remote::refs::print(out, refs)
+}
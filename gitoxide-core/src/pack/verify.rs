@@ -91,6 +91,122 @@ impl pack::cache::DecodeEntry for EitherCache {
             EitherCache::Right(v) => v.get(offset, out),
         }
     }
+
+    fn metrics(&self) -> pack::cache::Metrics {
+        match self {
+            EitherCache::Left(v) => v.metrics(),
+            EitherCache::Right(v) => v.metrics(),
+        }
+    }
+}
+
+/// How to verify an index or pack.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub struct Options {
+    /// If set, don't use more than this amount of threads.
+    pub thread_limit: Option<usize>,
+    /// What to verify of each object.
+    pub mode: index::verify::Mode,
+    /// How to traverse the pack.
+    pub algorithm: Algorithm,
+    /// If true, decode all objects without a cache to see the entire delta chains, so statistics are exact.
+    pub exact_statistics: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            thread_limit: None,
+            mode: index::verify::Mode::Sha1CRC32,
+            algorithm: Algorithm::LessMemory,
+            exact_statistics: false,
+        }
+    }
+}
+
+/// The result of a verification.
+#[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The checksum of the verified pack or index.
+    pub checksum: owned::Id,
+    /// Statistics about the objects in the pack, if an index was verified along with its pack.
+    pub statistics: Option<index::traverse::Outcome>,
+}
+
+/// A pack, or an index along with its pack if that could be opened, to [verify][Target::verify()].
+pub enum Target {
+    /// A pack whose checksum is verified.
+    Pack(pack::data::File),
+    /// An index, and its pack to verify all objects of the index in.
+    Index {
+        /// The index, boxed as it's much larger than a pack.
+        index: Box<index::File>,
+        /// The pack next to the index, or the error opening it, in which case only the index is verified.
+        pack: Result<pack::data::File, pack::data::parse::Error>,
+    },
+}
+
+/// Open the pack or index at `path`, depending on its extension.
+pub fn open(path: impl AsRef<Path>) -> Result<Target> {
+    let path = path.as_ref();
+    let ext = path.extension().and_then(|ext| ext.to_str()).ok_or_else(|| {
+        anyhow!(
+            "Cannot determine data type on path without extension '{}', expecting default extensions 'idx' and 'pack'",
+            path.display()
+        )
+    })?;
+    Ok(match ext {
+        "pack" => Target::Pack(git_odb::pack::data::File::at(path).with_context(|| "Could not open pack file")?),
+        "idx" => Target::Index {
+            index: Box::new(git_odb::pack::index::File::at(path).with_context(|| "Could not open pack index file")?),
+            pack: git_odb::pack::data::File::at(path.with_extension("pack")),
+        },
+        ext => return Err(anyhow!("Unknown extension {:?}, expecting 'idx' or 'pack'", ext)),
+    })
+}
+
+impl Target {
+    /// Verify the pack or index with `options`, reporting progress to `progress`.
+    pub fn verify<P>(self, progress: Option<P>, options: Options) -> Result<Outcome>
+    where
+        P: Progress + Send,
+        <P as Progress>::SubProgress: Send,
+        <<P as Progress>::SubProgress as Progress>::SubProgress: Send,
+    {
+        let Options {
+            thread_limit,
+            mode,
+            algorithm,
+            exact_statistics,
+        } = options;
+        let (checksum, statistics) = match self {
+            Target::Pack(pack) => pack
+                .verify_checksum(progress::DoOrDiscard::from(progress).add_child("Sha1 of pack"))
+                .map(|id| (id, None))?,
+            Target::Index { index, pack } => {
+                let cache = || -> EitherCache {
+                    if exact_statistics {
+                        // turn off acceleration as we need to see entire chains all the time
+                        EitherCache::Left(pack::cache::DecodeEntryNoop)
+                    } else {
+                        EitherCache::Right(pack::cache::DecodeEntryLRU::default())
+                    }
+                };
+
+                index
+                    .verify_integrity(
+                        pack.as_ref().ok().map(|p| (p, mode, algorithm.into())),
+                        thread_limit,
+                        progress,
+                        cache,
+                    )
+                    .map(|(a, b, _)| (a, b))
+                    .with_context(|| "Verification failure")?
+            }
+        };
+        Ok(Outcome { checksum, statistics })
+    }
 }
 
 pub fn pack_or_pack_index<P, W1, W2>(
@@ -113,54 +229,26 @@ where
     <<P as Progress>::SubProgress as Progress>::SubProgress: Send,
 {
     let path = path.as_ref();
-    let ext = path.extension().and_then(|ext| ext.to_str()).ok_or_else(|| {
-        anyhow!(
-            "Cannot determine data type on path without extension '{}', expecting default extensions 'idx' and 'pack'",
-            path.display()
+    let target = open(path)?;
+    if let Target::Index { pack: Err(e), .. } = &target {
+        writeln!(
+            err,
+            "Could not find matching pack file at '{}' - only index file will be verified, error was: {}",
+            path.with_extension("pack").display(),
+            e
         )
-    })?;
-    let res = match ext {
-        "pack" => {
-            let pack = git_odb::pack::data::File::at(path).with_context(|| "Could not open pack file")?;
-            pack.verify_checksum(progress::DoOrDiscard::from(progress).add_child("Sha1 of pack"))
-                .map(|id| (id, None))?
-        }
-        "idx" => {
-            let idx = git_odb::pack::index::File::at(path).with_context(|| "Could not open pack index file")?;
-            let packfile_path = path.with_extension("pack");
-            let pack = git_odb::pack::data::File::at(&packfile_path)
-                .map_err(|e| {
-                    writeln!(
-                        err,
-                        "Could not find matching pack file at '{}' - only index file will be verified, error was: {}",
-                        packfile_path.display(),
-                        e
-                    )
-                    .ok();
-                    e
-                })
-                .ok();
-            let cache = || -> EitherCache {
-                if output_statistics.is_some() {
-                    // turn off acceleration as we need to see entire chains all the time
-                    EitherCache::Left(pack::cache::DecodeEntryNoop)
-                } else {
-                    EitherCache::Right(pack::cache::DecodeEntryLRU::default())
-                }
-            };
-
-            idx.verify_integrity(
-                pack.as_ref().map(|p| (p, mode, algorithm.into())),
-                thread_limit,
-                progress,
-                cache,
-            )
-            .map(|(a, b, _)| (a, b))
-            .with_context(|| "Verification failure")?
-        }
-        ext => return Err(anyhow!("Unknown extension {:?}, expecting 'idx' or 'pack'", ext)),
-    };
-    if let Some(stats) = res.1.as_ref() {
+        .ok();
+    }
+    let Outcome { checksum, statistics } = target.verify(
+        progress,
+        Options {
+            thread_limit,
+            mode,
+            algorithm,
+            exact_statistics: output_statistics.is_some(),
+        },
+    )?;
+    if let Some(stats) = statistics.as_ref() {
         match output_statistics {
             Some(OutputFormat::Human) => drop(print_statistics(&mut out, stats)),
             #[cfg(feature = "serde1")]
@@ -168,10 +256,10 @@ where
             _ => {}
         };
     }
-    Ok(res)
+    Ok((checksum, statistics))
 }
 
-fn print_statistics(out: &mut impl io::Write, stats: &index::traverse::Outcome) -> io::Result<()> {
+pub(crate) fn print_statistics(out: &mut impl io::Write, stats: &index::traverse::Outcome) -> io::Result<()> {
     writeln!(out, "objects per delta chain length")?;
     let mut chain_length_to_object: Vec<_> = stats.objects_per_chain_length.iter().map(|(a, b)| (*a, *b)).collect();
     chain_length_to_object.sort_by_key(|e| e.0);
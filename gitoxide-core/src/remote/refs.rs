@@ -0,0 +1,51 @@
+use crate::{OutputFormat, Protocol};
+use anyhow::{Context as AnyhowContext, Result};
+use git_object::bstr::BString;
+use git_protocol::{handshake::Ref, ls_refs};
+use git_transport::client;
+use std::io;
+
+pub struct Context<W: io::Write> {
+    pub format: OutputFormat,
+    pub out: W,
+}
+
+/// List the refs of the remote at `url` starting with one of `ref_prefixes`, or all of them if there is none.
+pub fn list<W: io::Write>(
+    protocol: Option<Protocol>,
+    url: &str,
+    ref_prefixes: Vec<BString>,
+    ctx: Context<W>,
+) -> Result<()> {
+    let mut transport = client::connect(url.as_bytes(), protocol.unwrap_or_default().into())
+        .with_context(|| format!("Could not connect to '{}'", url))?;
+    let refs = git_protocol::ls_remote(
+        &mut transport,
+        &ls_refs::Options {
+            prefixes: ref_prefixes,
+            ..Default::default()
+        },
+    )
+    .with_context(|| "Could not list the refs of the remote")?;
+    match ctx.format {
+        OutputFormat::Human => drop(print(ctx.out, &refs)),
+        #[cfg(feature = "serde1")]
+        OutputFormat::Json => serde_json::to_writer_pretty(ctx.out, &refs)?,
+    };
+    Ok(())
+}
+
+/// Write one line per ref to `out`, naming the tag object and symbolic ref targets as well.
+pub(crate) fn print(mut out: impl io::Write, refs: &[Ref]) -> io::Result<()> {
+    for r in refs {
+        match r {
+            Ref::Direct { path, object } => writeln!(&mut out, "{} {}", object, path)?,
+            Ref::Peeled { path, tag, object } => writeln!(&mut out, "{} {} object:{}", tag, path, object)?,
+            Ref::Symbolic { path, target, object } => {
+                writeln!(&mut out, "{} {} symref-target:{}", object, path, target)?
+            }
+            Ref::Unborn { path, target } => writeln!(&mut out, "unborn {} symref-target:{}", path, target)?,
+        }
+    }
+    Ok(())
+}
@@ -1,5 +1,426 @@
-use anyhow::{Context as AnyhowContext, Result};
+use anyhow::{anyhow, Context as AnyhowContext, Result};
+use git_features::progress::Progress;
+use git_object::{
+    borrowed,
+    bstr::{BString, ByteSlice},
+    Sign, Time,
+};
+use git_odb::{compound, pack};
+use git_repository::{commit_graph, date, discover, ignore, index, mailmap, pretty, revision, revwalk, worktree};
+use std::{fs, io, path::PathBuf, str::FromStr};
 
-pub fn init() -> Result<()> {
-    git_repository::init::repository().with_context(|| "Repository initialization failed")
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Default)]
+pub enum ObjectFormat {
+    #[default]
+    Sha1,
+}
+
+impl ObjectFormat {
+    pub fn variants() -> &'static [&'static str] {
+        &["sha1"]
+    }
+}
+
+impl FromStr for ObjectFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s_lc = s.to_ascii_lowercase();
+        Ok(match s_lc.as_str() {
+            "sha1" => ObjectFormat::Sha1,
+            "sha256" => return Err("The object format 'sha256' is not supported yet, use 'sha1'".into()),
+            _ => return Err(format!("Unknown object format: '{}'", s)),
+        })
+    }
+}
+
+impl From<ObjectFormat> for git_object::HashKind {
+    fn from(v: ObjectFormat) -> Self {
+        match v {
+            ObjectFormat::Sha1 => git_object::HashKind::Sha1,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct InitOptions {
+    pub bare: bool,
+    pub initial_branch: Option<String>,
+    pub object_format: ObjectFormat,
+}
+
+/// Initialize a repository in `directory`, or in the current directory if unset.
+pub fn init(
+    directory: Option<PathBuf>,
+    InitOptions {
+        bare,
+        initial_branch,
+        object_format,
+    }: InitOptions,
+) -> Result<()> {
+    git_repository::init::repository_with_options(
+        directory.unwrap_or_default(),
+        git_repository::init::Options {
+            bare,
+            initial_branch,
+            object_hash: object_format.into(),
+        },
+    )
+    .map(|_| ())
+    .with_context(|| "Repository initialization failed")
+}
+
+pub const CLONE_PROGRESS_RANGE: std::ops::RangeInclusive<u8> = 1..=3;
+
+pub struct CloneOptions {
+    pub bare: bool,
+    pub bundle_uri: Option<String>,
+    pub checkout: bool,
+    pub depth: Option<usize>,
+    pub filter: Option<String>,
+    pub protocol: Option<crate::Protocol>,
+    pub thread_limit: Option<usize>,
+}
+
+impl Default for CloneOptions {
+    fn default() -> Self {
+        CloneOptions {
+            bare: false,
+            bundle_uri: None,
+            checkout: true,
+            depth: None,
+            filter: None,
+            protocol: None,
+            thread_limit: None,
+        }
+    }
+}
+
+/// Clone the repository at `url` into `directory`, or into a directory named after the last component of `url`.
+pub fn clone<P>(
+    url: &str,
+    directory: Option<PathBuf>,
+    progress: P,
+    CloneOptions {
+        bare,
+        bundle_uri,
+        checkout,
+        depth,
+        filter,
+        protocol,
+        thread_limit,
+    }: CloneOptions,
+) -> Result<()>
+where
+    P: Progress,
+    <P as Progress>::SubProgress: Send + 'static,
+    <<P as Progress>::SubProgress as Progress>::SubProgress: Send + 'static,
+    <<<P as Progress>::SubProgress as Progress>::SubProgress as Progress>::SubProgress: Send,
+{
+    let filter = filter
+        .map(|spec| spec.parse::<git_protocol::fetch::Filter>())
+        .transpose()
+        .with_context(|| "Invalid filter specification")?;
+    if depth == Some(0) {
+        return Err(anyhow!("The depth of a shallow clone must be at least 1"));
+    }
+    let directory = match directory {
+        Some(directory) => directory,
+        None => directory_from_url(url, bare)
+            .ok_or_else(|| anyhow!("Could not derive a directory name from '{}', please specify one", url))?,
+    };
+    git_repository::clone(
+        url,
+        &directory,
+        progress,
+        git_repository::clone::Options {
+            protocol: protocol.unwrap_or_default().into(),
+            checkout,
+            thread_limit,
+            filter,
+            depth,
+            bare,
+            bundle_uri,
+            ..Default::default()
+        },
+    )
+    .map(|_| ())
+    .with_context(|| format!("Could not clone '{}' into '{}'", url, directory.display()))
+}
+
+pub struct StatusOptions {
+    pub renames: bool,
+    pub untracked_files: Option<String>,
+    pub ignored: bool,
+    pub thread_limit: Option<usize>,
+}
+
+impl Default for StatusOptions {
+    fn default() -> Self {
+        StatusOptions {
+            renames: true,
+            untracked_files: None,
+            ignored: false,
+            thread_limit: None,
+        }
+    }
+}
+
+/// Write the changes of the worktree containing `directory`, or the current directory if unset, to `out` in the short
+/// format of `git status`, followed by the untracked and ignored files.
+pub fn status(
+    directory: Option<PathBuf>,
+    mut out: impl io::Write,
+    StatusOptions {
+        renames,
+        untracked_files,
+        ignored,
+        thread_limit,
+    }: StatusOptions,
+) -> Result<()> {
+    let untracked_files = match untracked_files {
+        Some(name) => git_repository::status::UntrackedFiles::from_name(&name)
+            .ok_or_else(|| anyhow!("Unknown mode for untracked files: '{}'", name))?,
+        None => git_repository::status::UntrackedFiles::Normal,
+    };
+    let paths = discover::discover(
+        directory.unwrap_or_else(|| ".".into()),
+        &discover::Environment::from_env(),
+    )
+    .with_context(|| "Could not find a repository")?;
+    let work_tree = paths
+        .work_tree
+        .as_ref()
+        .ok_or_else(|| anyhow!("The repository at '{}' has no worktree", paths.git_dir.display()))?;
+    let db = compound::Db::at(&paths.object_dir)?;
+    let mut cache = pack::cache::DecodeEntryLRU::default();
+    let head = match revision::resolve(&paths.git_dir, &db, "HEAD".into()) {
+        Ok(id) => {
+            let mut buf = Vec::new();
+            let tree = match find(&db, id.to_borrowed(), &mut buf, &mut cache) {
+                Some(borrowed::Object::Commit(commit)) => commit.tree(),
+                _ => return Err(anyhow!("HEAD does not point to a commit")),
+            };
+            Some(tree)
+        }
+        Err(revision::Error::NotFound(_)) => None,
+        Err(err) => return Err(err.into()),
+    };
+    let index = index::File::at(&paths.index_file)?.unwrap_or_default();
+    let outcome = git_repository::status(
+        head,
+        &index,
+        work_tree,
+        &worktree::Capabilities::from_git_dir(&paths.common_dir),
+        &ignore::Stack::from_git_dir(&paths.git_dir),
+        |id, buf| find(&db, id, buf, &mut cache),
+        &git_repository::status::Options {
+            renames,
+            untracked_files,
+            ignored,
+            thread_limit,
+        },
+    )?;
+    for entry in outcome.entries {
+        out.write_all(&entry.short_format())?;
+        out.write_all(b" ")?;
+        if let Some(git_repository::status::Change::Renamed { source }) = &entry.staged {
+            out.write_all(source.as_bytes())?;
+            out.write_all(b" -> ")?;
+        }
+        out.write_all(entry.path.as_bytes())?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct LogOptions {
+    pub revisions: Vec<String>,
+    pub paths: Vec<String>,
+    pub oneline: bool,
+    pub format: Option<String>,
+    pub date: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub max_count: Option<usize>,
+}
+
+/// How each commit is shown, and whether commits are separated or terminated by newlines.
+enum LogFormat {
+    OneLine,
+    Medium,
+    Template { template: String, separated: bool },
+}
+
+/// Write the commits reachable from the given revisions, or from `HEAD`, of the repository containing `directory`, or
+/// the current directory if unset, to `out` like `git log` does.
+pub fn log(
+    directory: Option<PathBuf>,
+    mut out: impl io::Write,
+    LogOptions {
+        revisions,
+        paths,
+        oneline,
+        format,
+        date,
+        since,
+        until,
+        max_count,
+    }: LogOptions,
+) -> Result<()> {
+    let format = match (oneline, format.as_deref()) {
+        (true, _) | (false, Some("oneline")) => LogFormat::OneLine,
+        (false, None) | (false, Some("medium")) => LogFormat::Medium,
+        (false, Some(format)) => match format.strip_prefix("format:") {
+            Some(template) => LogFormat::Template {
+                template: template.into(),
+                separated: true,
+            },
+            None => match format.strip_prefix("tformat:") {
+                Some(template) => LogFormat::Template {
+                    template: template.into(),
+                    separated: false,
+                },
+                None if format.contains('%') => LogFormat::Template {
+                    template: format.into(),
+                    separated: false,
+                },
+                None => return Err(anyhow!("Invalid pretty format: '{}'", format)),
+            },
+        },
+    };
+    let date = match date {
+        Some(name) => date::Format::from_name(&name).ok_or_else(|| anyhow!("Unknown date format: '{}'", name))?,
+        None => date::Format::Default,
+    };
+    let now = Time {
+        time: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or_default(),
+        offset: 0,
+        sign: Sign::Plus,
+    };
+    let parse_date = |input: Option<String>| {
+        input
+            .map(|input| date::parse(input.as_str().into(), now).map(|time| time.time))
+            .transpose()
+    };
+    let (since, until) = (parse_date(since)?, parse_date(until)?);
+
+    let repo = discover::discover(
+        directory.unwrap_or_else(|| ".".into()),
+        &discover::Environment::from_env(),
+    )
+    .with_context(|| "Could not find a repository")?;
+    let db = compound::Db::at(&repo.object_dir)?;
+    let mailmap = match &repo.work_tree {
+        Some(work_tree) => match fs::read(work_tree.join(".mailmap")) {
+            Ok(data) => mailmap::Snapshot::from_bytes(&data),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => mailmap::Snapshot::default(),
+            Err(err) => return Err(err.into()),
+        },
+        None => mailmap::Snapshot::default(),
+    };
+
+    let revisions = if revisions.is_empty() {
+        vec!["HEAD".to_string()]
+    } else {
+        revisions
+    };
+    let mut cache = pack::cache::DecodeEntryLRU::default();
+    let mut buf = Vec::new();
+    let mut tips = Vec::new();
+    for revision in &revisions {
+        let mut id = revision::resolve(&repo.git_dir, &db, revision.as_str().into())
+            .with_context(|| format!("Could not resolve '{}'", revision))?;
+        loop {
+            match find(&db, id.to_borrowed(), &mut buf, &mut cache) {
+                Some(borrowed::Object::Tag(tag)) => id = tag.target(),
+                Some(borrowed::Object::Commit(_)) => break,
+                _ => return Err(anyhow!("'{}' does not point to a commit", revision)),
+            }
+        }
+        tips.push(id);
+    }
+
+    let walk = revwalk::walk(
+        tips,
+        |id, buf| find(&db, id, buf, &mut cache),
+        revwalk::Options {
+            paths: paths.iter().map(|path| BString::from(path.as_str())).collect(),
+            commit_graph: commit_graph::File::at(&repo.common_dir).ok().flatten(),
+        },
+    );
+    let mut commit_buf = Vec::new();
+    let mut commit_cache = pack::cache::DecodeEntryLRU::default();
+    let mut line = Vec::new();
+    let mut shown = 0;
+    for commit in walk {
+        let id = commit?.id;
+        let commit = match find(&db, id.to_borrowed(), &mut commit_buf, &mut commit_cache) {
+            Some(borrowed::Object::Commit(commit)) => pretty::Commit::from_borrowed(id, &commit),
+            _ => return Err(anyhow!("Commit {} could not be found", id)),
+        };
+        let time = commit.committer.time.time;
+        // Commits are walked newest first, so all remaining ones are too old.
+        if since.is_some_and(|since| time < since) {
+            break;
+        }
+        if until.is_some_and(|until| time > until) {
+            continue;
+        }
+        if max_count.is_some_and(|max_count| shown == max_count) {
+            break;
+        }
+        line.clear();
+        match &format {
+            LogFormat::OneLine => {
+                pretty::oneline(&commit, &mut line);
+                line.push(b'\n');
+            }
+            LogFormat::Medium => {
+                if shown > 0 {
+                    out.write_all(b"\n")?;
+                }
+                pretty::medium(&commit, &mailmap, date, &mut line);
+            }
+            LogFormat::Template { template, separated } => {
+                if *separated && shown > 0 {
+                    out.write_all(b"\n")?;
+                }
+                pretty::expand(template.as_bytes(), &commit, &mailmap, date, &mut line);
+                if !separated {
+                    line.push(b'\n');
+                }
+            }
+        }
+        out.write_all(&line)?;
+        shown += 1;
+    }
+    Ok(())
+}
+
+fn find<'a>(
+    db: &compound::Db,
+    id: borrowed::Id<'_>,
+    buf: &'a mut Vec<u8>,
+    cache: &mut pack::cache::DecodeEntryLRU,
+) -> Option<borrowed::Object<'a>> {
+    let object = db.locate(id, buf, cache)?.ok()?;
+    borrowed::Object::from_bytes(object.kind, object.data).ok()
+}
+
+/// Name the directory to clone into after the last path component of `url`, without `.git` unless it's `bare`.
+fn directory_from_url(url: &str, bare: bool) -> Option<PathBuf> {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()?
+        .trim_end_matches(".git");
+    if name.is_empty() {
+        return None;
+    }
+    Some(if bare { format!("{}.git", name) } else { name.to_owned() }.into())
 }
@@ -28,10 +28,65 @@ mod options {
     #[derive(FromArgs, PartialEq, Debug)]
     #[argh(subcommand)]
     pub enum SubCommands {
+        PackReceive(PackReceive),
+        RemoteRefList(RemoteRefList),
         PackVerify(PackVerify),
         PackExplode(PackExplode),
         IndexFromPack(IndexFromPack),
+        CatFile(CatFile),
     }
+    /// Receive a pack from a remote identified by URL.
+    ///
+    /// This is the plumbing equivalent of `git clone` and `git fetch`.
+    #[derive(FromArgs, PartialEq, Debug)]
+    #[argh(subcommand, name = "pack-receive")]
+    pub struct PackReceive {
+        /// the protocol version to use. Valid values are 1 and 2
+        #[argh(option, short = 'p')]
+        pub protocol: Option<core::Protocol>,
+
+        /// the file into which to write the received refs, one per line. Existing files will be overwritten.
+        #[argh(option, short = 'r')]
+        pub refs_file: Option<PathBuf>,
+
+        /// only receive the refs starting with this prefix, like 'refs/heads/'. Can be given multiple times.
+        ///
+        /// If unset, all refs are received.
+        #[argh(option, short = 'R')]
+        pub ref_prefix: Vec<String>,
+
+        /// the URL of the remote to connect to, like `https://github.com/byron/gitoxide`.
+        #[argh(positional)]
+        pub url: String,
+
+        /// the directory into which to write the received pack and index.
+        ///
+        /// If unset, they will be discarded.
+        #[argh(positional)]
+        pub directory: Option<PathBuf>,
+    }
+
+    /// List remote references from a remote identified by a url.
+    ///
+    /// This is the plumbing equivalent of `git ls-remote`.
+    #[derive(FromArgs, PartialEq, Debug)]
+    #[argh(subcommand, name = "remote-ref-list")]
+    pub struct RemoteRefList {
+        /// the protocol version to use. Valid values are 1 and 2
+        #[argh(option, short = 'p')]
+        pub protocol: Option<core::Protocol>,
+
+        /// only list the refs starting with this prefix, like 'refs/heads/'. Can be given multiple times.
+        ///
+        /// If unset, all refs are listed.
+        #[argh(option, short = 'R')]
+        pub ref_prefix: Vec<String>,
+
+        /// the URL of the remote to connect to, like `https://github.com/byron/gitoxide`.
+        #[argh(positional)]
+        pub url: String,
+    }
+
     /// Create an index from a packfile.
     ///
     /// This command can also be used to stream packs to standard input or to repair partial packs.
@@ -48,6 +103,12 @@ mod options {
         #[argh(option, short = 'i')]
         pub iteration_mode: Option<core::pack::index::IterationMode>,
 
+        /// the objects directory of a repository, like '.git/objects', to look up objects thin packs refer to.
+        ///
+        /// These objects are added to the pack so that it can be indexed.
+        #[argh(option)]
+        pub object_dir: Option<PathBuf>,
+
         /// path to the pack file to read (with .pack extension).
         ///
         /// If unset, the pack file is expected on stdin.
@@ -67,6 +128,10 @@ mod options {
     #[derive(FromArgs, PartialEq, Debug)]
     #[argh(subcommand, name = "pack-explode")]
     pub struct PackExplode {
+        /// output statistical information about the exploded pack
+        #[argh(switch, short = 's')]
+        pub statistics: bool,
+
         #[argh(switch)]
         /// read written objects back and assert they match their source. Fail the operation otherwise.
         ///
@@ -96,6 +161,8 @@ mod options {
         pub check: Option<core::pack::explode::SafetyCheck>,
 
         /// the '.pack' or '.idx' file to explode into loose objects
+        ///
+        /// If a '.pack' file has no index next to it, a temporary one is created.
         #[argh(positional)]
         pub pack_path: PathBuf,
 
@@ -137,18 +204,49 @@ mod options {
         #[argh(positional)]
         pub path: PathBuf,
     }
+
+    /// Print the kind, size and content of an object.
+    ///
+    /// This is the plumbing equivalent of `git cat-file -p`.
+    #[derive(FromArgs, PartialEq, Debug)]
+    #[argh(subcommand, name = "cat-file")]
+    pub struct CatFile {
+        /// the repository to read the object from.
+        ///
+        /// If unset, it's the one the current directory is in.
+        #[argh(option, short = 'r')]
+        pub repository: Option<PathBuf>,
+
+        /// read one revision per line from stdin and print the kind, size and data of each object, until stdin ends.
+        #[argh(switch)]
+        pub batch: bool,
+
+        /// like --batch, but only print the kind and size of each object.
+        #[argh(switch)]
+        pub batch_check: bool,
+
+        /// the object to print, as full id, ref name or unique prefix of an id.
+        ///
+        /// It must be set unless --batch or --batch-check is.
+        #[argh(positional)]
+        pub revision: Option<String>,
+    }
 }
 
 use crate::shared::ProgressRange;
 use anyhow::Result;
 use git_features::progress;
 use gitoxide_core::{self as core, OutputFormat};
-use std::io::{self, stderr, stdout};
+use std::io::{self, stderr, stdin, stdout};
 
 #[cfg(not(any(feature = "prodash-render-line-crossterm", feature = "prodash-render-line-termion")))]
-fn prepare(verbose: bool, name: &str, _: impl Into<Option<ProgressRange>>) -> ((), Option<prodash::progress::Log>) {
-    super::init_env_logger(verbose);
-    ((), Some(prodash::progress::Log::new(name, Some(1))))
+fn prepare(
+    verbose: bool,
+    name: &str,
+    _: impl Into<Option<ProgressRange>>,
+) -> ((), Option<progress::Rate<prodash::progress::Log>>) {
+    crate::shared::init_env_logger(verbose);
+    ((), Some(crate::shared::log_progress(name)))
 }
 
 #[cfg(any(feature = "prodash-render-line-crossterm", feature = "prodash-render-line-termion"))]
@@ -158,7 +256,7 @@ fn prepare(
     range: impl Into<Option<ProgressRange>>,
 ) -> (Option<prodash::render::line::JoinHandle>, Option<prodash::tree::Item>) {
     use crate::shared::{self, STANDARD_RANGE};
-    super::init_env_logger(false);
+    crate::shared::init_env_logger(false);
 
     if verbose {
         let progress = prodash::Tree::new();
@@ -177,8 +275,47 @@ pub fn main() -> Result<()> {
     let thread_limit = cli.threads;
     let verbose = cli.verbose;
     match cli.subcommand {
+        SubCommands::PackReceive(PackReceive {
+            protocol,
+            refs_file,
+            ref_prefix,
+            url,
+            directory,
+        }) => {
+            let (_handle, progress) = prepare(verbose, "pack-receive", core::pack::receive::PROGRESS_RANGE);
+            core::pack::receive::receive(
+                protocol,
+                &url,
+                directory,
+                refs_file,
+                ref_prefix.into_iter().map(Into::into).collect(),
+                progress::DoOrDiscard::from(progress),
+                core::pack::receive::Context {
+                    thread_limit,
+                    format: OutputFormat::Human,
+                    out: io::stdout(),
+                },
+            )
+        }
+        SubCommands::RemoteRefList(RemoteRefList {
+            protocol,
+            ref_prefix,
+            url,
+        }) => {
+            let (_handle, _progress) = prepare(verbose, "remote-ref-list", None);
+            core::remote::refs::list(
+                protocol,
+                &url,
+                ref_prefix.into_iter().map(Into::into).collect(),
+                core::remote::refs::Context {
+                    format: OutputFormat::Human,
+                    out: io::stdout(),
+                },
+            )
+        }
         SubCommands::IndexFromPack(IndexFromPack {
             iteration_mode,
+            object_dir,
             pack_path,
             directory,
         }) => {
@@ -190,12 +327,14 @@ pub fn main() -> Result<()> {
                 core::pack::index::Context {
                     thread_limit,
                     iteration_mode: iteration_mode.unwrap_or_default(),
+                    object_dir,
                     format: OutputFormat::Human,
                     out: io::stdout(),
                 },
             )
         }
         SubCommands::PackExplode(PackExplode {
+            statistics,
             pack_path,
             sink_compress,
             object_path,
@@ -214,6 +353,8 @@ pub fn main() -> Result<()> {
                     delete_pack,
                     sink_compress,
                     verify,
+                    output_statistics: if statistics { Some(OutputFormat::Human) } else { None },
+                    out: io::stdout(),
                 },
             )
         }
@@ -248,5 +389,30 @@ pub fn main() -> Result<()> {
             )
             .map(|_| ())
         }
+        SubCommands::CatFile(CatFile {
+            repository,
+            batch,
+            batch_check,
+            revision,
+        }) => match (revision, batch, batch_check) {
+            (None, true, _) => {
+                core::object::batch(repository, stdin().lock(), stdout(), core::object::Batch::Contents).map(|_| ())
+            }
+            (None, false, true) => {
+                core::object::batch(repository, stdin().lock(), stdout(), core::object::Batch::Headers).map(|_| ())
+            }
+            (Some(revision), false, false) => core::object::cat(
+                repository,
+                &revision,
+                core::object::Context {
+                    format: OutputFormat::Human,
+                    out: stdout(),
+                },
+            )
+            .map(|_| ()),
+            _ => Err(anyhow::anyhow!(
+                "Either a revision or one of --batch and --batch-check must be given"
+            )),
+        },
     }
 }
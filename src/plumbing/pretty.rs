@@ -1,7 +1,8 @@
-use anyhow::{anyhow, Result};
+use crate::shared::pretty::prepare_and_run;
+use anyhow::Result;
 use clap::Clap;
 use gitoxide_core as core;
-use std::io::{stderr, stdout, Write};
+use std::io::stdout;
 
 use gitoxide_core::pack::verify;
 use options::*;
@@ -26,7 +27,9 @@ mod options {
         #[clap(long, short = "v")]
         pub verbose: bool,
 
-        /// Bring up a terminal user interface displaying progress visually
+        /// Bring up a terminal user interface displaying progress visually.
+        ///
+        /// If standard output isn't a terminal, progress is displayed line by line as with '--verbose'.
         #[clap(long, conflicts_with("verbose"))]
         pub progress: bool,
 
@@ -36,7 +39,7 @@ mod options {
         #[clap(long, conflicts_with("verbose"), requires("progress"))]
         pub progress_keep_open: bool,
 
-        /// Determine the format to use when outputting statistics.
+        /// Determine the format to use when outputting statistics and other results, as well as errors.
         #[clap(
             long,
             short = "f",
@@ -51,9 +54,58 @@ mod options {
 
     #[derive(Debug, Clap)]
     pub enum Subcommands {
+        /// Receive a pack from a remote identified by URL.
+        ///
+        /// This is the plumbing equivalent of `git clone` and `git fetch`.
+        #[clap(setting = AppSettings::ColoredHelp)]
+        #[clap(setting = AppSettings::DisableVersion)]
+        PackReceive {
+            /// The protocol version to use. Valid values are 1 and 2
+            #[clap(long, short = "p", possible_values(core::Protocol::variants()))]
+            protocol: Option<core::Protocol>,
+
+            /// The file into which to write the received refs, one per line. Existing files will be overwritten.
+            #[clap(long, short = "r", parse(from_os_str))]
+            refs_file: Option<PathBuf>,
+
+            /// Only receive the refs starting with this prefix, like 'refs/heads/'. Can be given multiple times.
+            ///
+            /// If unset, all refs are received.
+            #[clap(long = "ref-prefix", short = "R", number_of_values = 1)]
+            ref_prefixes: Vec<String>,
+
+            /// The URL of the remote to connect to, like `https://github.com/byron/gitoxide`.
+            url: String,
+
+            /// The directory into which to write the received pack and index.
+            ///
+            /// If unset, they will be discarded.
+            #[clap(parse(from_os_str))]
+            directory: Option<PathBuf>,
+        },
+        /// List remote references from a remote identified by a url.
+        ///
+        /// This is the plumbing equivalent of `git ls-remote`.
+        #[clap(setting = AppSettings::ColoredHelp)]
+        #[clap(setting = AppSettings::DisableVersion)]
+        RemoteRefList {
+            /// The protocol version to use. Valid values are 1 and 2
+            #[clap(long, short = "p", possible_values(core::Protocol::variants()))]
+            protocol: Option<core::Protocol>,
+
+            /// Only list the refs starting with this prefix, like 'refs/heads/'. Can be given multiple times.
+            ///
+            /// If unset, all refs are listed.
+            #[clap(long = "ref-prefix", short = "R", number_of_values = 1)]
+            ref_prefixes: Vec<String>,
+
+            /// The URL of the remote to connect to, like `https://github.com/byron/gitoxide`.
+            url: String,
+        },
         /// Create an index from a packfile.
         ///
         /// This command can also be used to stream packs to standard input or to repair partial packs.
+        #[clap(alias = "pack-index-from-data")]
         #[clap(setting = AppSettings::ColoredHelp)]
         #[clap(setting = AppSettings::DisableVersion)]
         IndexFromPack {
@@ -72,6 +124,12 @@ mod options {
             )]
             iteration_mode: core::pack::index::IterationMode,
 
+            /// The objects directory of a repository, like '.git/objects', to look up objects thin packs refer to.
+            ///
+            /// These objects are added to the pack so that it can be indexed.
+            #[clap(long, parse(from_os_str))]
+            object_dir: Option<PathBuf>,
+
             /// Path to the pack file to read (with .pack extension).
             ///
             /// If unset, the pack file is expected on stdin.
@@ -84,10 +142,17 @@ mod options {
             #[clap(parse(from_os_str))]
             directory: Option<PathBuf>,
         },
-        /// Verify the integrity of a pack or index file
+        /// Explode a pack into loose objects.
+        ///
+        /// This can be useful in case of partially invalidated packs to extract as much information as possible,
+        /// or because working with loose objects is easier with custom tooling.
         #[clap(setting = AppSettings::ColoredHelp)]
         #[clap(setting = AppSettings::DisableVersion)]
         PackExplode {
+            /// Output statistical information about the exploded pack
+            #[clap(long, short = "s")]
+            statistics: bool,
+
             #[clap(long)]
             /// Read written objects back and assert they match their source. Fail the operation otherwise.
             ///
@@ -116,6 +181,8 @@ mod options {
             sink_compress: bool,
 
             /// The '.pack' or '.idx' file to explode into loose objects
+            ///
+            /// If a '.pack' file has no index next to it, a temporary one is created.
             #[clap(parse(from_os_str))]
             pack_path: PathBuf,
 
@@ -159,112 +226,31 @@ mod options {
             #[clap(parse(from_os_str))]
             path: PathBuf,
         },
-    }
-}
+        /// Print the kind, size and content of an object.
+        ///
+        /// This is the plumbing equivalent of `git cat-file -p`.
+        #[clap(setting = AppSettings::ColoredHelp)]
+        #[clap(setting = AppSettings::DisableVersion)]
+        CatFile {
+            /// The repository to read the object from.
+            ///
+            /// If unset, it's the one the current directory is in.
+            #[clap(long, short = "r", parse(from_os_str))]
+            repository: Option<PathBuf>,
 
-use crate::shared::ProgressRange;
-
-fn prepare_and_run<T: Send + 'static>(
-    name: &str,
-    verbose: bool,
-    progress: bool,
-    progress_keep_open: bool,
-    range: impl Into<Option<ProgressRange>>,
-    run: impl FnOnce(Option<prodash::tree::Item>, &mut dyn std::io::Write, &mut dyn std::io::Write) -> Result<T>
-        + Send
-        + 'static,
-) -> Result<T> {
-    use crate::shared::{self, STANDARD_RANGE};
-    super::init_env_logger(false);
-    use git_features::interruptible::{interrupt, is_interrupted};
-    match (verbose, progress) {
-        (false, false) => run(None, &mut stdout(), &mut stderr()),
-        (true, false) => {
-            enum Event<T> {
-                UIDone,
-                ComputationDone(Result<T>),
-            };
-            let progress = prodash::Tree::new();
-            let sub_progress = progress.add_child(name);
-            let (tx, rx) = std::sync::mpsc::sync_channel::<Event<T>>(1);
-            let ui_handle = shared::setup_line_renderer_range(progress, range.into().unwrap_or(STANDARD_RANGE), true);
-            std::thread::spawn({
-                let tx = tx.clone();
-                move || loop {
-                    std::thread::sleep(std::time::Duration::from_millis(500));
-                    if is_interrupted() {
-                        tx.send(Event::UIDone).ok();
-                        break;
-                    }
-                }
-            });
-            std::thread::spawn(move || {
-                let res = run(Some(sub_progress), &mut stdout(), &mut stderr());
-                tx.send(Event::ComputationDone(res)).ok();
-            });
-            match rx.recv()? {
-                Event::UIDone => {
-                    ui_handle.shutdown_and_wait();
-                    Err(anyhow!("Operation cancelled by user"))
-                }
-                Event::ComputationDone(res) => {
-                    ui_handle.shutdown_and_wait();
-                    res
-                }
-            }
-        }
-        (true, true) | (false, true) => {
-            enum Event<T> {
-                UIDone,
-                ComputationDone(Result<T>, Vec<u8>, Vec<u8>),
-            };
-            let progress = prodash::Tree::new();
-            let sub_progress = progress.add_child(name);
-            let render_tui = prodash::render::tui(
-                stdout(),
-                progress,
-                prodash::render::tui::Options {
-                    title: "gitoxide".into(),
-                    frames_per_second: crate::shared::DEFAULT_FRAME_RATE,
-                    stop_if_empty_progress: !progress_keep_open,
-                    throughput: true,
-                    ..Default::default()
-                },
-            )
-            .expect("tui to come up without io error");
-            let (tx, rx) = std::sync::mpsc::sync_channel::<Event<T>>(1);
-            let ui_handle = std::thread::spawn({
-                let tx = tx.clone();
-                move || {
-                    futures_lite::future::block_on(render_tui);
-                    tx.send(Event::UIDone).ok();
-                }
-            });
-            std::thread::spawn(move || {
-                // We might have something interesting to show, which would be hidden by the alternate screen if there is a progress TUI
-                // We know that the printing happens at the end, so this is fine.
-                let mut out = Vec::new();
-                let mut err = Vec::new();
-                let res = run(Some(sub_progress), &mut out, &mut err);
-                tx.send(Event::ComputationDone(res, out, err)).ok();
-            });
-            loop {
-                match rx.recv()? {
-                    Event::UIDone => {
-                        // We don't know why the UI is done, usually it's the user aborting.
-                        // We need the computation to stop as well so let's wait for that to happen
-                        interrupt();
-                        continue;
-                    }
-                    Event::ComputationDone(res, out, err) => {
-                        ui_handle.join().ok();
-                        stdout().write_all(&out)?;
-                        stderr().write_all(&err)?;
-                        break res;
-                    }
-                }
-            }
-        }
+            /// Read one revision per line from stdin and print the kind, size and data of each object, until stdin
+            /// ends.
+            #[clap(long, conflicts_with_all(&["batch-check", "revision"]))]
+            batch: bool,
+
+            /// Like --batch, but only print the kind and size of each object.
+            #[clap(long, conflicts_with("revision"))]
+            batch_check: bool,
+
+            /// The object to print, as full id, ref name or unique prefix of an id.
+            #[clap(required_unless_one(&["batch", "batch-check"]))]
+            revision: Option<String>,
+        },
     }
 }
 
@@ -279,9 +265,57 @@ pub fn main() -> Result<()> {
     } = Args::parse();
     git_features::interruptible::init_interrupt_handler(std::io::stderr());
 
-    match cmd {
+    let res = match cmd {
+        Subcommands::PackReceive {
+            protocol,
+            refs_file,
+            ref_prefixes,
+            url,
+            directory,
+        } => prepare_and_run(
+            "pack-receive",
+            verbose,
+            progress,
+            progress_keep_open,
+            core::pack::receive::PROGRESS_RANGE,
+            move |progress, out, _err| {
+                core::pack::receive::receive(
+                    protocol,
+                    &url,
+                    directory,
+                    refs_file,
+                    ref_prefixes.into_iter().map(Into::into).collect(),
+                    git_features::progress::DoOrDiscard::from(progress),
+                    core::pack::receive::Context {
+                        thread_limit,
+                        format,
+                        out,
+                    },
+                )
+            },
+        ),
+        Subcommands::RemoteRefList {
+            protocol,
+            ref_prefixes,
+            url,
+        } => prepare_and_run(
+            "remote-ref-list",
+            verbose,
+            progress,
+            progress_keep_open,
+            None,
+            move |_progress, out, _err| {
+                core::remote::refs::list(
+                    protocol,
+                    &url,
+                    ref_prefixes.into_iter().map(Into::into).collect(),
+                    core::remote::refs::Context { format, out },
+                )
+            },
+        ),
         Subcommands::IndexFromPack {
             iteration_mode,
+            object_dir,
             pack_path,
             directory,
         } => prepare_and_run(
@@ -298,6 +332,7 @@ pub fn main() -> Result<()> {
                     core::pack::index::Context {
                         thread_limit,
                         iteration_mode,
+                        object_dir,
                         format,
                         out,
                     },
@@ -305,6 +340,7 @@ pub fn main() -> Result<()> {
             },
         ),
         Subcommands::PackExplode {
+            statistics,
             check,
             sink_compress,
             delete_pack,
@@ -317,7 +353,7 @@ pub fn main() -> Result<()> {
             progress,
             progress_keep_open,
             None,
-            move |progress, _out, _err| {
+            move |progress, out, _err| {
                 core::pack::explode::pack_or_pack_index(
                     pack_path,
                     object_path,
@@ -328,6 +364,8 @@ pub fn main() -> Result<()> {
                         delete_pack,
                         sink_compress,
                         verify,
+                        output_statistics: if statistics { Some(format) } else { None },
+                        out,
                     },
                 )
             },
@@ -366,6 +404,34 @@ pub fn main() -> Result<()> {
             },
         )
         .map(|_| ()),
-    }?;
-    Ok(())
+        Subcommands::CatFile {
+            repository,
+            batch,
+            batch_check,
+            revision,
+        } => prepare_and_run(
+            "cat-file",
+            verbose,
+            progress,
+            progress_keep_open,
+            None,
+            move |_progress, out, _err| match revision {
+                Some(revision) => {
+                    core::object::cat(repository, &revision, core::object::Context { format, out }).map(|_| ())
+                }
+                None => {
+                    let mode = if batch && !batch_check {
+                        core::object::Batch::Contents
+                    } else {
+                        core::object::Batch::Headers
+                    };
+                    core::object::batch(repository, std::io::stdin().lock(), out, mode).map(|_| ())
+                }
+            },
+        ),
+    };
+    if let (Err(err), core::OutputFormat::Json) = (&res, format) {
+        core::ErrorSummary::from(err).write_json(stdout())?;
+    }
+    res
 }
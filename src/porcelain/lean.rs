@@ -1,5 +1,7 @@
 mod options {
     use argh::FromArgs;
+    use gitoxide_core as core;
+    use std::path::PathBuf;
 
     #[derive(FromArgs)]
     /// The lean git
@@ -8,6 +10,16 @@ mod options {
         /// print the program version.
         pub version: bool,
 
+        #[argh(switch, short = 'v')]
+        /// display verbose messages and progress information
+        pub verbose: bool,
+
+        #[argh(option, short = 't')]
+        /// the amount of threads to use for some operations.
+        ///
+        /// If unset, or the value is 0, there is no limit and all logical cores can be used.
+        pub threads: Option<usize>,
+
         #[argh(subcommand)]
         pub subcommand: SubCommands,
     }
@@ -16,23 +28,236 @@ mod options {
     #[argh(subcommand)]
     pub enum SubCommands {
         Init(Init),
+        Clone(Clone),
+        Status(Status),
+        Log(Log),
     }
 
-    /// Initialize the repository in the current directory.
+    /// Initialize the repository in the current directory, or in the given one.
     #[derive(FromArgs, PartialEq, Debug)]
     #[argh(subcommand, name = "init")]
-    pub struct Init {}
+    pub struct Init {
+        /// create a bare repository without worktree, placing the git directory into the target directory itself.
+        ///
+        /// The target directory must be empty in this case.
+        #[argh(switch)]
+        pub bare: bool,
+
+        /// the name of the branch `HEAD` points to. Defaults to 'main'.
+        #[argh(option, short = 'b')]
+        pub initial_branch: Option<String>,
+
+        /// the kind of hash used to name objects. Defaults to 'sha1'.
+        #[argh(option)]
+        pub object_format: Option<core::repository::ObjectFormat>,
+
+        /// the directory in which to initialize the repository, which is created if it doesn't exist.
+        ///
+        /// If unset, the current directory is used.
+        #[argh(positional)]
+        pub directory: Option<PathBuf>,
+    }
+
+    /// Clone the repository at the given url into a new directory, and checkout the branch its `HEAD` points to.
+    #[derive(FromArgs, PartialEq, Debug)]
+    #[argh(subcommand, name = "clone")]
+    pub struct Clone {
+        /// create a bare repository without worktree, mirroring the branches of the remote.
+        #[argh(switch)]
+        pub bare: bool,
+
+        /// download and unpack the bundle or bundle list at the given uri before fetching the objects it lacks.
+        #[argh(option)]
+        pub bundle_uri: Option<String>,
+
+        /// create a shallow clone with a history truncated to the given amount of commits.
+        #[argh(option)]
+        pub depth: Option<usize>,
+
+        /// don't checkout the branch `HEAD` points to after the clone.
+        #[argh(switch)]
+        pub no_checkout: bool,
+
+        /// ask the remote to omit objects matching the given filter specification, like 'blob:none'.
+        #[argh(option)]
+        pub filter: Option<String>,
+
+        /// the protocol version to use. Valid values are 1 and 2
+        #[argh(option, short = 'p')]
+        pub protocol: Option<core::Protocol>,
+
+        /// the url of the repository to clone, like 'https://github.com/Byron/gitoxide' or 'file:///path/to/repo'.
+        #[argh(positional)]
+        pub url: String,
+
+        /// the directory to clone into, which is created if it doesn't exist.
+        ///
+        /// If unset, it's named after the last component of the url.
+        #[argh(positional)]
+        pub directory: Option<PathBuf>,
+    }
+
+    /// Show the changes staged for the next commit and the ones which aren't yet, in the short format of `git status`.
+    #[derive(FromArgs, PartialEq, Debug)]
+    #[argh(subcommand, name = "status")]
+    pub struct Status {
+        /// show renamed files as deleted and added files.
+        #[argh(switch)]
+        pub no_renames: bool,
+
+        /// which untracked files to show: 'no', 'normal' to show untracked directories as a whole, or 'all'.
+        #[argh(option, short = 'u')]
+        pub untracked_files: Option<String>,
+
+        /// show ignored files as well.
+        #[argh(switch)]
+        pub ignored: bool,
+    }
+
+    /// Show the commits reachable from the given revisions, or from HEAD, newest first.
+    #[derive(FromArgs, PartialEq, Debug)]
+    #[argh(subcommand, name = "log")]
+    pub struct Log {
+        /// show each commit on a single line with its abbreviated id and subject.
+        #[argh(switch)]
+        pub oneline: bool,
+
+        /// show commits in the 'oneline' or 'medium' format, or with a template like 'format:%h %an %s' or
+        /// 'tformat:%H', which separate or terminate commits with a newline.
+        #[argh(option)]
+        pub format: Option<String>,
+
+        /// the format of dates: 'default', 'rfc', 'iso', 'iso-strict' or 'unix'.
+        #[argh(option)]
+        pub date: Option<String>,
+
+        /// only show commits more recent than the given date, like '2 weeks ago' or '2020-01-01'.
+        #[argh(option)]
+        pub since: Option<String>,
+
+        /// only show commits older than the given date.
+        #[argh(option)]
+        pub until: Option<String>,
+
+        /// show at most the given amount of commits.
+        #[argh(option, short = 'n')]
+        pub max_count: Option<usize>,
+
+        /// only show commits changing the given file or directory. Can be given multiple times.
+        #[argh(option)]
+        pub path: Vec<String>,
+
+        /// the revisions to start from, like 'main' or a commit id.
+        #[argh(positional)]
+        pub revisions: Vec<String>,
+    }
 }
 
 use anyhow::Result;
+use git_features::progress;
 use gitoxide_core as core;
 
+#[cfg(not(any(feature = "prodash-render-line-crossterm", feature = "prodash-render-line-termion")))]
+fn prepare(verbose: bool, name: &str) -> ((), Option<progress::Rate<prodash::progress::Log>>) {
+    crate::shared::init_env_logger(verbose);
+    ((), Some(crate::shared::log_progress(name)))
+}
+
+#[cfg(any(feature = "prodash-render-line-crossterm", feature = "prodash-render-line-termion"))]
+fn prepare(verbose: bool, name: &str) -> (Option<prodash::render::line::JoinHandle>, Option<prodash::tree::Item>) {
+    crate::shared::init_env_logger(false);
+
+    if verbose {
+        let progress = prodash::Tree::new();
+        let sub_progress = progress.add_child(name);
+        let handle = crate::shared::setup_line_renderer_range(progress, core::repository::CLONE_PROGRESS_RANGE, true);
+        (Some(handle), Some(sub_progress))
+    } else {
+        (None, None)
+    }
+}
+
 pub fn main() -> Result<()> {
     pub use options::*;
     let cli: Args = crate::shared::from_env();
     git_features::interruptible::init_interrupt_handler(std::io::stderr());
 
     match cli.subcommand {
-        SubCommands::Init(_) => core::repository::init(),
+        SubCommands::Init(Init {
+            bare,
+            initial_branch,
+            object_format,
+            directory,
+        }) => core::repository::init(
+            directory,
+            core::repository::InitOptions {
+                bare,
+                initial_branch,
+                object_format: object_format.unwrap_or_default(),
+            },
+        ),
+        SubCommands::Clone(Clone {
+            bare,
+            bundle_uri,
+            depth,
+            no_checkout,
+            filter,
+            protocol,
+            url,
+            directory,
+        }) => {
+            let (_handle, progress) = prepare(cli.verbose, "clone");
+            core::repository::clone(
+                &url,
+                directory,
+                progress::DoOrDiscard::from(progress),
+                core::repository::CloneOptions {
+                    bare,
+                    bundle_uri,
+                    checkout: !no_checkout,
+                    depth,
+                    filter,
+                    protocol,
+                    thread_limit: cli.threads,
+                },
+            )
+        }
+        SubCommands::Status(Status {
+            no_renames,
+            untracked_files,
+            ignored,
+        }) => core::repository::status(
+            None,
+            std::io::stdout(),
+            core::repository::StatusOptions {
+                renames: !no_renames,
+                untracked_files,
+                ignored,
+                thread_limit: cli.threads,
+            },
+        ),
+        SubCommands::Log(Log {
+            oneline,
+            format,
+            date,
+            since,
+            until,
+            max_count,
+            path,
+            revisions,
+        }) => core::repository::log(
+            None,
+            std::io::stdout(),
+            core::repository::LogOptions {
+                revisions,
+                paths: path,
+                oneline,
+                format,
+                date,
+                since,
+                until,
+                max_count,
+            },
+        ),
     }
 }
@@ -1,26 +1,164 @@
+use crate::shared::pretty::prepare_and_run;
 use anyhow::Result;
 use clap::Clap;
 use gitoxide_core as core;
 
 mod options {
     use clap::{AppSettings, Clap};
+    use gitoxide_core as core;
+    use std::path::PathBuf;
 
     #[derive(Debug, Clap)]
     #[clap(about = "The rusty git", version = clap::crate_version!())]
     #[clap(setting = AppSettings::SubcommandRequired)]
     #[clap(setting = AppSettings::ColoredHelp)]
     pub struct Args {
+        /// Display verbose messages and progress information
+        #[clap(long, short = "v")]
+        pub verbose: bool,
+
+        /// Bring up a terminal user interface displaying progress visually.
+        ///
+        /// If standard output isn't a terminal, progress is displayed line by line as with '--verbose'.
+        #[clap(long, conflicts_with("verbose"))]
+        pub progress: bool,
+
+        /// The progress TUI will stay up even though the work is already completed.
+        ///
+        /// Use this to be able to read progress messages or additional information visible in the TUI log pane.
+        #[clap(long, conflicts_with("verbose"), requires("progress"))]
+        pub progress_keep_open: bool,
+
+        /// The amount of threads to use for some operations.
+        ///
+        /// If unset, or the value is 0, there is no limit and all logical cores can be used.
+        #[clap(long, short = "t")]
+        pub threads: Option<usize>,
+
         #[clap(subcommand)]
         pub cmd: Subcommands,
     }
 
     #[derive(Debug, Clap)]
     pub enum Subcommands {
-        /// Initialize the repository in the current directory.
+        /// Initialize the repository in the current directory, or in the given one.
         #[clap(alias = "initialize")]
         #[clap(setting = AppSettings::ColoredHelp)]
         #[clap(setting = AppSettings::DisableVersion)]
-        Init,
+        Init {
+            /// Create a bare repository without worktree, placing the git directory into the target directory itself.
+            ///
+            /// The target directory must be empty in this case.
+            #[clap(long)]
+            bare: bool,
+
+            /// The name of the branch `HEAD` points to. Defaults to 'main'.
+            #[clap(long, short = "b")]
+            initial_branch: Option<String>,
+
+            /// The kind of hash used to name objects
+            #[clap(
+                long,
+                default_value = "sha1",
+                possible_values(core::repository::ObjectFormat::variants())
+            )]
+            object_format: core::repository::ObjectFormat,
+
+            /// The directory in which to initialize the repository, which is created if it doesn't exist.
+            ///
+            /// If unset, the current directory is used.
+            #[clap(parse(from_os_str))]
+            directory: Option<PathBuf>,
+        },
+        /// Clone the repository at the given url into a new directory, and checkout the branch its `HEAD` points to.
+        #[clap(setting = AppSettings::ColoredHelp)]
+        #[clap(setting = AppSettings::DisableVersion)]
+        Clone {
+            /// Create a bare repository without worktree, mirroring the branches of the remote.
+            #[clap(long)]
+            bare: bool,
+
+            /// Download and unpack the bundle or bundle list at the given uri before fetching the objects it lacks.
+            #[clap(long)]
+            bundle_uri: Option<String>,
+
+            /// Create a shallow clone with a history truncated to the given amount of commits.
+            #[clap(long)]
+            depth: Option<usize>,
+
+            /// Don't checkout the branch `HEAD` points to after the clone.
+            #[clap(long)]
+            no_checkout: bool,
+
+            /// Ask the remote to omit objects matching the given filter specification, like 'blob:none'.
+            #[clap(long)]
+            filter: Option<String>,
+
+            /// The protocol version to use. Valid values are 1 and 2
+            #[clap(long, short = "p", possible_values(core::Protocol::variants()))]
+            protocol: Option<core::Protocol>,
+
+            /// The url of the repository to clone, like 'https://github.com/Byron/gitoxide' or 'file:///path/to/repo'.
+            url: String,
+
+            /// The directory to clone into, which is created if it doesn't exist.
+            ///
+            /// If unset, it's named after the last component of the url.
+            #[clap(parse(from_os_str))]
+            directory: Option<PathBuf>,
+        },
+        /// Show the changes staged for the next commit and the ones which aren't yet, in the short format of `git status`.
+        #[clap(setting = AppSettings::ColoredHelp)]
+        #[clap(setting = AppSettings::DisableVersion)]
+        Status {
+            /// Show renamed files as deleted and added files.
+            #[clap(long)]
+            no_renames: bool,
+
+            /// Which untracked files to show: 'no', 'normal' to show untracked directories as a whole, or 'all'.
+            #[clap(long, short = "u")]
+            untracked_files: Option<String>,
+
+            /// Show ignored files as well.
+            #[clap(long)]
+            ignored: bool,
+        },
+        /// Show the commits reachable from the given revisions, or from HEAD, newest first.
+        #[clap(setting = AppSettings::ColoredHelp)]
+        #[clap(setting = AppSettings::DisableVersion)]
+        Log {
+            /// Show each commit on a single line with its abbreviated id and subject.
+            #[clap(long)]
+            oneline: bool,
+
+            /// Show commits in the 'oneline' or 'medium' format, or with a template like 'format:%h %an %s' or
+            /// 'tformat:%H', which separate or terminate commits with a newline.
+            #[clap(long, alias = "pretty")]
+            format: Option<String>,
+
+            /// The format of dates: 'default', 'rfc', 'iso', 'iso-strict' or 'unix'.
+            #[clap(long)]
+            date: Option<String>,
+
+            /// Only show commits more recent than the given date, like '2 weeks ago' or '2020-01-01'.
+            #[clap(long, alias = "after")]
+            since: Option<String>,
+
+            /// Only show commits older than the given date.
+            #[clap(long, alias = "before")]
+            until: Option<String>,
+
+            /// Show at most the given amount of commits.
+            #[clap(long, short = "n")]
+            max_count: Option<usize>,
+
+            /// Only show commits changing the given file or directory. Can be given multiple times.
+            #[clap(long = "path", number_of_values = 1)]
+            paths: Vec<String>,
+
+            /// The revisions to start from, like 'main' or a commit id.
+            revisions: Vec<String>,
+        },
     }
 }
 
@@ -29,7 +167,91 @@ pub fn main() -> Result<()> {
     let args = Args::parse();
     git_features::interruptible::init_interrupt_handler(std::io::stderr());
     match args.cmd {
-        Subcommands::Init => core::repository::init(),
+        Subcommands::Init {
+            bare,
+            initial_branch,
+            object_format,
+            directory,
+        } => core::repository::init(
+            directory,
+            core::repository::InitOptions {
+                bare,
+                initial_branch,
+                object_format,
+            },
+        ),
+        Subcommands::Clone {
+            bare,
+            bundle_uri,
+            depth,
+            no_checkout,
+            filter,
+            protocol,
+            url,
+            directory,
+        } => {
+            let thread_limit = args.threads;
+            prepare_and_run(
+                "clone",
+                args.verbose,
+                args.progress,
+                args.progress_keep_open,
+                core::repository::CLONE_PROGRESS_RANGE,
+                move |progress, _out, _err| {
+                    core::repository::clone(
+                        &url,
+                        directory,
+                        git_features::progress::DoOrDiscard::from(progress),
+                        core::repository::CloneOptions {
+                            bare,
+                            bundle_uri,
+                            checkout: !no_checkout,
+                            depth,
+                            filter,
+                            protocol,
+                            thread_limit,
+                        },
+                    )
+                },
+            )
+        }
+        Subcommands::Status {
+            no_renames,
+            untracked_files,
+            ignored,
+        } => core::repository::status(
+            None,
+            std::io::stdout(),
+            core::repository::StatusOptions {
+                renames: !no_renames,
+                untracked_files,
+                ignored,
+                thread_limit: args.threads,
+            },
+        ),
+        Subcommands::Log {
+            oneline,
+            format,
+            date,
+            since,
+            until,
+            max_count,
+            paths,
+            revisions,
+        } => core::repository::log(
+            None,
+            std::io::stdout(),
+            core::repository::LogOptions {
+                revisions,
+                paths,
+                oneline,
+                format,
+                date,
+                since,
+                until,
+                max_count,
+            },
+        ),
     }?;
     Ok(())
 }
@@ -31,6 +31,29 @@ pub fn setup_line_renderer_range(
     )
 }
 
+/// A progress logging the steps made along with the throughput and the estimated time until completion, which the log
+/// doesn't show by itself.
+#[allow(unused)]
+#[cfg(feature = "lean-cli")]
+pub fn log_progress(name: &str) -> git_features::progress::Rate<prodash::progress::Log> {
+    git_features::progress::Rate::new(prodash::progress::Log::new(name, Some(1)))
+        .reporting_every(std::time::Duration::from_secs(1), 1)
+}
+
+/// If verbose is true, the env logger will be forcibly set to 'info' logging level. Otherwise env logging facilities
+/// will just be initialized.
+#[allow(unused)]
+#[cfg(feature = "env_logger")]
+pub fn init_env_logger(verbose: bool) {
+    if verbose {
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+            .format_module_path(false)
+            .init();
+    } else {
+        env_logger::init();
+    }
+}
+
 #[cfg(all(feature = "lean-cli", not(feature = "pretty-cli")))]
 pub fn from_env<T: argh::TopLevelCommand>() -> T {
     static VERSION: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
@@ -52,3 +75,122 @@ pub fn from_env<T: argh::TopLevelCommand>() -> T {
         })
     })
 }
+
+#[cfg(feature = "pretty-cli")]
+pub mod pretty {
+    use crate::shared::ProgressRange;
+    use anyhow::{anyhow, Result};
+    use std::io::{stderr, stdout, Write};
+
+    pub fn prepare_and_run<T: Send + 'static>(
+        name: &str,
+        verbose: bool,
+        progress: bool,
+        progress_keep_open: bool,
+        range: impl Into<Option<ProgressRange>>,
+        run: impl FnOnce(Option<prodash::tree::Item>, &mut dyn std::io::Write, &mut dyn std::io::Write) -> Result<T>
+            + Send
+            + 'static,
+    ) -> Result<T> {
+        crate::shared::init_env_logger(false);
+        use git_features::interruptible::{interrupt, is_interrupted};
+        // The TUI needs a terminal to draw into, so show the progress line by line instead if there is none.
+        let (verbose, progress) = if progress && !atty::is(atty::Stream::Stdout) {
+            (true, false)
+        } else {
+            (verbose, progress)
+        };
+        match (verbose, progress) {
+            (false, false) => run(None, &mut stdout(), &mut stderr()),
+            (true, false) => {
+                enum Event<T> {
+                    UIDone,
+                    ComputationDone(Result<T>),
+                }
+                let progress = prodash::Tree::new();
+                let sub_progress = progress.add_child(name);
+                let (tx, rx) = std::sync::mpsc::sync_channel::<Event<T>>(1);
+                let ui_handle =
+                    super::setup_line_renderer_range(progress, range.into().unwrap_or(super::STANDARD_RANGE), true);
+                std::thread::spawn({
+                    let tx = tx.clone();
+                    move || loop {
+                        std::thread::sleep(std::time::Duration::from_millis(500));
+                        if is_interrupted() {
+                            tx.send(Event::UIDone).ok();
+                            break;
+                        }
+                    }
+                });
+                std::thread::spawn(move || {
+                    let res = run(Some(sub_progress), &mut stdout(), &mut stderr());
+                    tx.send(Event::ComputationDone(res)).ok();
+                });
+                match rx.recv()? {
+                    Event::UIDone => {
+                        ui_handle.shutdown_and_wait();
+                        // The computation is still running, but won't get to remove its temporary files before we exit.
+                        git_features::tempfile::cleanup();
+                        Err(anyhow!("Operation cancelled by user"))
+                    }
+                    Event::ComputationDone(res) => {
+                        ui_handle.shutdown_and_wait();
+                        res
+                    }
+                }
+            }
+            (true, true) | (false, true) => {
+                enum Event<T> {
+                    UIDone,
+                    ComputationDone(Result<T>, Vec<u8>, Vec<u8>),
+                }
+                let progress = prodash::Tree::new();
+                let sub_progress = progress.add_child(name);
+                let render_tui = prodash::render::tui(
+                    stdout(),
+                    progress,
+                    prodash::render::tui::Options {
+                        title: "gitoxide".into(),
+                        frames_per_second: super::DEFAULT_FRAME_RATE,
+                        stop_if_empty_progress: !progress_keep_open,
+                        throughput: true,
+                        ..Default::default()
+                    },
+                )
+                .expect("tui to come up without io error");
+                let (tx, rx) = std::sync::mpsc::sync_channel::<Event<T>>(1);
+                let ui_handle = std::thread::spawn({
+                    let tx = tx.clone();
+                    move || {
+                        futures_lite::future::block_on(render_tui);
+                        tx.send(Event::UIDone).ok();
+                    }
+                });
+                std::thread::spawn(move || {
+                    // We might have something interesting to show, which would be hidden by the alternate screen if there is a progress TUI
+                    // We know that the printing happens at the end, so this is fine.
+                    let mut out = Vec::new();
+                    let mut err = Vec::new();
+                    let res = run(Some(sub_progress), &mut out, &mut err);
+                    tx.send(Event::ComputationDone(res, out, err)).ok();
+                });
+                loop {
+                    match rx.recv()? {
+                        Event::UIDone => {
+                            // We don't know why the UI is done, usually it's the user aborting.
+                            // We need the computation to stop as well so let's wait for that to happen
+                            interrupt();
+                            continue;
+                        }
+                        Event::ComputationDone(res, out, err) => {
+                            ui_handle.join().ok();
+                            stdout().write_all(&out)?;
+                            stderr().write_all(&err)?;
+                            break res;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
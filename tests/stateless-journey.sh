@@ -46,6 +46,167 @@ title "CLI ${kind}"
       )
     )
   )
+  (with "the --bare flag, the --initial-branch option and a target directory"
+    (sandbox
+      it "succeeds" && {
+        WITH_SNAPSHOT="$snapshot/success" \
+        expect_run $SUCCESSFULLY "$exe" init --bare --initial-branch trunk bare.git
+      }
+
+      it "points HEAD to the initial branch" && {
+        WITH_SNAPSHOT="$snapshot/bare-head" \
+        expect_run $SUCCESSFULLY cat bare.git/HEAD
+      }
+
+      it "configures the repository as bare" && {
+        WITH_SNAPSHOT="$snapshot/bare-config" \
+        expect_run $SUCCESSFULLY cat bare.git/config
+      }
+
+      (when "trying to initialize the same directory again"
+        it "fails" && {
+          WITH_SNAPSHOT="$snapshot/bare-fail" \
+          expect_run $WITH_FAILURE "$exe" init --bare bare.git
+        }
+      )
+    )
+  )
+  (with "an invalid initial branch name"
+    (sandbox
+      it "fails with a helpful error message" && {
+        WITH_SNAPSHOT="$snapshot/invalid-initial-branch-fail" \
+        expect_run $WITH_FAILURE "$exe" init -b "a..b"
+      }
+    )
+  )
+)
+(when "cloning a repository"
+  snapshot="$snapshot/clone"
+  REPO_URL="file://$root/../git-repository/tests/fixtures/repos/files.git"
+  (with_program git
+    (with "no target directory"
+      (sandbox
+        it "succeeds" && {
+          WITH_SNAPSHOT="$snapshot/success" \
+          expect_run $SUCCESSFULLY "$exe" clone "$REPO_URL"
+        }
+        it "checks out the branch HEAD points to into a directory named after the url" && {
+          WITH_SNAPSHOT="$snapshot/success-worktree" \
+          expect_run $SUCCESSFULLY ls -A files
+        }
+        it "records all checked out files in the index, unchanged and tracked" && {
+          WITH_SNAPSHOT="$snapshot/success-status" \
+          expect_run $SUCCESSFULLY git -C files status --porcelain
+        }
+      )
+    )
+    (with "the --bare flag"
+      (sandbox
+        it "succeeds" && {
+          WITH_SNAPSHOT="$snapshot/success" \
+          expect_run $SUCCESSFULLY "$exe" clone --bare "$REPO_URL"
+        }
+        it "mirrors the branches of the remote into a directory ending in .git" && {
+          WITH_SNAPSHOT="$snapshot/bare-branches" \
+          expect_run $SUCCESSFULLY ls files.git/refs/heads
+        }
+      )
+    )
+    (with "the --depth option, the --no-checkout flag and a target directory"
+      (sandbox
+        it "succeeds" && {
+          WITH_SNAPSHOT="$snapshot/success" \
+          expect_run $SUCCESSFULLY "$exe" clone --depth 1 --no-checkout "$REPO_URL" shallow
+        }
+        it "records the shallow boundary" && {
+          WITH_SNAPSHOT="$snapshot/shallow-boundary" \
+          expect_run $SUCCESSFULLY cat shallow/.git/shallow
+        }
+        it "doesn't checkout any files" && {
+          WITH_SNAPSHOT="$snapshot/no-checkout-worktree" \
+          expect_run $SUCCESSFULLY ls -A shallow
+        }
+      )
+    )
+    (with "an invalid filter specification"
+      (sandbox
+        it "fails with a helpful error message" && {
+          WITH_SNAPSHOT="$snapshot/invalid-filter-fail" \
+          expect_run $WITH_FAILURE "$exe" clone --filter blob:some "$REPO_URL"
+        }
+      )
+    )
+  )
+)
+(when "showing the status of a worktree"
+  snapshot="$snapshot/status"
+  (with_program git
+    (sandbox
+      git clone --quiet "file://$root/../git-repository/tests/fixtures/repos/files.git" files
+      cd files
+      echo changed > a && rm dir/sub/b && echo new > new && git add new && git mv run.sh run2.sh
+      (with "no flags"
+        it "shows the staged and unstaged changes of tracked files" && {
+          WITH_SNAPSHOT="$snapshot/success" \
+          expect_run $SUCCESSFULLY "$exe" status
+        }
+      )
+      (with "the --no-renames flag"
+        it "shows renamed files as deleted and added" && {
+          WITH_SNAPSHOT="$snapshot/no-renames" \
+          expect_run $SUCCESSFULLY "$exe" status --no-renames
+        }
+      )
+      (with "untracked and ignored files"
+        echo '*.o' > .gitignore && mkdir -p build/sub && touch untracked x.o build/sub/file build/sub/y.o
+        it "shows untracked files and directories after the changes of tracked files" && {
+          WITH_SNAPSHOT="$snapshot/untracked" \
+          expect_run $SUCCESSFULLY "$exe" status
+        }
+        it "shows all untracked files and the ignored ones with --untracked-files=all and --ignored" && {
+          WITH_SNAPSHOT="$snapshot/untracked-all-ignored" \
+          expect_run $SUCCESSFULLY "$exe" status --untracked-files all --ignored
+        }
+        it "fails with a helpful error message for an unknown mode" && {
+          WITH_SNAPSHOT="$snapshot/untracked-unknown-mode-fail" \
+          expect_run $WITH_FAILURE "$exe" status -u some
+        }
+      )
+    )
+  )
+)
+(when "showing the history of a repository"
+  snapshot="$snapshot/log"
+  (with_program git
+    (sandbox
+      git clone --quiet "file://$root/../git-repository/tests/fixtures/repos/files.git" files
+      cd files
+      (with "no flags"
+        it "shows all commits reachable from HEAD in the medium format" && {
+          WITH_SNAPSHOT="$snapshot/success" \
+          expect_run $SUCCESSFULLY "$exe" log
+        }
+      )
+      (with "the --oneline flag and a path"
+        it "shows the commits changing the path on one line each" && {
+          WITH_SNAPSHOT="$snapshot/oneline-path" \
+          expect_run $SUCCESSFULLY "$exe" log --oneline --path dir
+        }
+      )
+      (with "a custom format, a date format and a maximum count"
+        it "shows the expanded placeholders of the newest commit" && {
+          WITH_SNAPSHOT="$snapshot/format-date-max-count" \
+          expect_run $SUCCESSFULLY "$exe" log --format "%h %an %ad %s" --date iso -n 1
+        }
+      )
+      (with "an unknown format"
+        it "fails with a helpful error message" && {
+          WITH_SNAPSHOT="$snapshot/unknown-format-fail" \
+          expect_run $WITH_FAILURE "$exe" log --format fuller
+        }
+      )
+    )
+  )
 )
 
 title plumbing
@@ -89,6 +250,26 @@ snapshot="$snapshot/plumbing"
       )
     )
   )
+  (with "a thin pack referring to objects outside of it"
+    it "fails with a helpful error message" && {
+      WITH_SNAPSHOT="$snapshot/thin-pack-failure" \
+      expect_run $WITH_FAILURE "$exe_plumbing" index-from-pack -p "$fixtures/packs/thin.pack"
+    }
+    (with "the objects directory containing the objects it refers to"
+      it "adds these objects to the pack and generates an index" && {
+        WITH_SNAPSHOT="$snapshot/thin-pack-with-object-dir-success" \
+        expect_run $SUCCESSFULLY "$exe_plumbing" index-from-pack --object-dir "$fixtures/thin-pack-bases" -p "$fixtures/packs/thin-with-bases.pack"
+      }
+    )
+  )
+  if test "$kind" = "max"; then
+  (with "the 'pack-index-from-data' alias"
+    it "generates an index into a sink and outputs pack and index information" && {
+      WITH_SNAPSHOT="$snapshot/no-output-dir-success" \
+      expect_run $SUCCESSFULLY "$exe_plumbing" pack-index-from-data -p "$PACK_FILE"
+    }
+  )
+  fi
   (with "'restore' iteration mode"
     (sandbox
       cp "${PACK_FILE}" .
@@ -112,6 +293,68 @@ snapshot="$snapshot/plumbing"
     )
   )
 )
+(when "running 'pack-receive'"
+  snapshot="$snapshot/pack-receive"
+  REPO_URL="file://$root/../git-repository/tests/fixtures/repos/files.git"
+  (with_program git
+    (with "protocol V1"
+      it "receives all refs and the pack into a sink and outputs pack and ref information" && {
+        WITH_SNAPSHOT="$snapshot/file-v1-to-sink-success" \
+        expect_run $SUCCESSFULLY "$exe_plumbing" pack-receive -p 1 "$REPO_URL"
+      }
+    )
+    (with "protocol V2"
+      (with "a ref prefix, refs file and output directory"
+        (sandbox
+          it "receives only the matching refs and writes the pack, index and refs" && {
+            WITH_SNAPSHOT="$snapshot/file-v2-prefix-to-dir-success" \
+            expect_run $SUCCESSFULLY "$exe_plumbing" pack-receive -p 2 -R refs/heads/ -r refs "$REPO_URL" .
+          }
+          it "writes the received refs into the refs file" && {
+            WITH_SNAPSHOT="$snapshot/file-v2-prefix-to-dir-refs" \
+            expect_run $SUCCESSFULLY cat refs
+          }
+          it "writes the pack and index into the directory" && {
+            WITH_SNAPSHOT="$snapshot/file-v2-prefix-to-dir-content" \
+            expect_run $SUCCESSFULLY ls
+          }
+        )
+      )
+    )
+  )
+)
+(when "running 'remote-ref-list'"
+  snapshot="$snapshot/remote-ref-list"
+  REPO_URL="file://$root/../git-repository/tests/fixtures/repos/files.git"
+  (with_program git
+    (with "protocol V1"
+      it "lists all refs of the remote" && {
+        WITH_SNAPSHOT="$snapshot/file-v1-success" \
+        expect_run $SUCCESSFULLY "$exe_plumbing" remote-ref-list -p 1 "$REPO_URL"
+      }
+    )
+    (with "protocol V2"
+      it "lists all refs of the remote just like V1" && {
+        WITH_SNAPSHOT="$snapshot/file-v1-success" \
+        expect_run $SUCCESSFULLY "$exe_plumbing" remote-ref-list -p 2 "$REPO_URL"
+      }
+      (with "ref prefixes"
+        it "lists only the matching refs" && {
+          WITH_SNAPSHOT="$snapshot/file-v2-prefix-success" \
+          expect_run $SUCCESSFULLY "$exe_plumbing" remote-ref-list -R refs/tags/ -R HEAD "$REPO_URL"
+        }
+      )
+      if test "$kind" = "max"; then
+      (with "--format json"
+        it "lists the refs as JSON" && {
+          WITH_SNAPSHOT="$snapshot/file-v2-prefix-as-json-success" \
+          expect_run $SUCCESSFULLY "$exe_plumbing" --format json remote-ref-list -R refs/heads/main "$REPO_URL"
+        }
+      )
+      fi
+    )
+  )
+)
 (when "running 'pack-explode"
   snapshot="$snapshot/pack-explode"
   PACK_FILE="$fixtures/packs/pack-11fdfa9e156ab73caae3b6da867192221f2089c2"
@@ -121,6 +364,21 @@ snapshot="$snapshot/plumbing"
       expect_run $SUCCESSFULLY "$exe_plumbing" pack-explode "${PACK_FILE}.idx"
     }
 
+    (with "statistics"
+      it "explodes the pack successfully and outputs statistics" && {
+        WITH_SNAPSHOT="$snapshot/to-sink-statistics-success" \
+        expect_run $SUCCESSFULLY "$exe_plumbing" pack-explode --statistics "${PACK_FILE}.idx"
+      }
+      if test "$kind" = "max"; then
+      (with "--format json"
+        it "explodes the pack successfully and outputs statistics as JSON" && {
+          WITH_SNAPSHOT="$snapshot/to-sink-statistics-json-success" \
+          expect_run $SUCCESSFULLY "$exe_plumbing" --format json pack-explode --statistics "${PACK_FILE}.idx"
+        }
+      )
+      fi
+    )
+
     (when "using the --delete-pack flag"
       (sandbox
         (with "a valid pack"
@@ -146,6 +404,15 @@ snapshot="$snapshot/plumbing"
               expect_run $WITH_FAILURE "$exe_plumbing" pack-explode --sink-compress --check all --delete-pack "${PACK_FILE}.pack"
             }
 
+            if test "$kind" = "max"; then
+            (with "--format json"
+              it "does not explode the file at all and summarizes the error as JSON" && {
+                WITH_SNAPSHOT="$snapshot/broken-delete-pack-to-sink-json-failure" \
+                expect_run $WITH_FAILURE "$exe_plumbing" --format json pack-explode --sink-compress --check all --delete-pack "${PACK_FILE}.pack"
+              }
+            )
+            fi
+
             it "did not touch index or pack file" && {
               expect_exists "${PACK_FILE}".pack
               expect_exists "${PACK_FILE}".idx
@@ -175,6 +442,27 @@ snapshot="$snapshot/plumbing"
       )
     )
   )
+  (with "a pack file without an index"
+    (sandbox
+      cp "${PACK_FILE}".pack .
+      PACK_FILE="${PACK_FILE##*/}"
+      it "creates a temporary index and explodes the pack into the given directory" && {
+        WITH_SNAPSHOT="$snapshot/without-index-with-objects-dir-success" \
+        expect_run $SUCCESSFULLY "$exe_plumbing" pack-explode --verify --delete-pack "${PACK_FILE}.pack" .
+      }
+
+      it "removes the original pack" && {
+        expect_run $WITH_FAILURE test -e "${PACK_FILE}".pack
+      }
+
+      (with_program tree
+        it "creates all pack objects" && {
+          WITH_SNAPSHOT="$snapshot/with-objects-dir-success-tree" \
+          expect_run $SUCCESSFULLY tree
+        }
+      )
+    )
+  )
   (with "a non-existing directory specified"
     it "fails with a helpful error message" && {
       WITH_SNAPSHOT="$snapshot/missing-objects-dir-fail" \
@@ -222,6 +510,13 @@ snapshot="$snapshot/plumbing"
         expect_run $SUCCESSFULLY "$exe_plumbing" pack-verify --statistics "$PACK_INDEX_FILE"
       }
     )
+    if test "$kind" = "max"; then
+    (with "the progress TUI"
+      it "falls back to line-based progress as there is no terminal and verifies the pack index successfully" && {
+        expect_run $SUCCESSFULLY "$exe_plumbing" --progress pack-verify "$PACK_INDEX_FILE"
+      }
+    )
+    fi
     (with "decode"
       it "verifies the pack index successfully and with desired output, and decodes all objects" && {
         WITH_SNAPSHOT="$snapshot/index-success" \
@@ -243,6 +538,18 @@ snapshot="$snapshot/plumbing"
     )
     fi
   )
+  (sandbox
+    (with "a pack file that is invalid somewhere"
+      PACK_FILE="$fixtures/packs/pack-11fdfa9e156ab73caae3b6da867192221f2089c2"
+      cp ${PACK_FILE}.idx ${PACK_FILE}.pack .
+      PACK_FILE="${PACK_FILE##*/}"
+      "$jtt" mess-in-the-middle "${PACK_FILE}".pack
+      it "fails to verify the pack index and names the checksum that doesn't match" && {
+        WITH_SNAPSHOT="$snapshot/broken-pack-index-failure" \
+        expect_run $WITH_FAILURE "$exe_plumbing" pack-verify --statistics "${PACK_FILE}.idx"
+      }
+    )
+  )
   (sandbox
     (with "an INvalid pack INDEX file"
       PACK_INDEX_FILE="$fixtures/packs/pack-11fdfa9e156ab73caae3b6da867192221f2089c2.idx"
@@ -255,3 +562,52 @@ snapshot="$snapshot/plumbing"
     )
   )
 )
+
+(when "running 'cat-file'"
+  snapshot="$snapshot/cat-file"
+  REPO="$root/../git-repository/tests/fixtures/repos/files.git"
+  (with "a branch name"
+    it "prints the commit it points to" && {
+      WITH_SNAPSHOT="$snapshot/branch-commit-success" \
+      expect_run $SUCCESSFULLY "$exe_plumbing" cat-file -r "$REPO" main
+    }
+  )
+  (with "a tag name"
+    it "prints the tag object" && {
+      WITH_SNAPSHOT="$snapshot/tag-success" \
+      expect_run $SUCCESSFULLY "$exe_plumbing" cat-file -r "$REPO" v1.0
+    }
+  )
+  (with "the prefix of a tree"
+    it "lists the entries of the tree" && {
+      WITH_SNAPSHOT="$snapshot/tree-prefix-success" \
+      expect_run $SUCCESSFULLY "$exe_plumbing" cat-file -r "$REPO" 341d
+    }
+  )
+  (with "a revision that doesn't exist"
+    it "fails with a helpful error message" && {
+      WITH_SNAPSHOT="$snapshot/unknown-revision-failure" \
+      expect_run $WITH_FAILURE "$exe_plumbing" cat-file -r "$REPO" does-not-exist
+    }
+  )
+  (with "--batch-check and revisions on stdin"
+    it "prints the header of each object or why it can't be found" && {
+      WITH_SNAPSHOT="$snapshot/batch-check-success" \
+      expect_run $SUCCESSFULLY "$exe_plumbing" cat-file -r "$REPO" --batch-check < <(printf 'main\n341d\ndoes-not-exist\nv1.0\n')
+    }
+  )
+  (with "--batch and revisions on stdin"
+    it "prints the header and data of each object" && {
+      WITH_SNAPSHOT="$snapshot/batch-success" \
+      expect_run $SUCCESSFULLY "$exe_plumbing" cat-file -r "$REPO" --batch < <(printf 'main\n2227cd\ndoes-not-exist\n')
+    }
+  )
+  if test "$kind" = "max"; then
+  (with "--format json and the prefix of a blob"
+    it "prints the object as JSON" && {
+      WITH_SNAPSHOT="$snapshot/blob-prefix-as-json-success" \
+      expect_run $SUCCESSFULLY "$exe_plumbing" --format json cat-file -r "$REPO" 2227cd
+    }
+  )
+  fi
+)